@@ -0,0 +1,153 @@
+//! Peak heap and time for parsing a large friends payload: the old way
+//! (whole body into a String, then `from_str`) against `get_friends`, which
+//! parses while the bounded reader streams the body.
+//!
+//! `cargo bench --bench body_parse`
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use openkakao_rs::model::{Friend, KakaoCredentials};
+use openkakao_rs::rest::KakaoRestClient;
+use serde_json::{json, Value};
+
+/// System allocator that tracks the bytes in use and their high-water mark.
+struct Counting;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            let now = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(now, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+const FRIENDS: usize = 60_000;
+const ROUNDS: u32 = 5;
+
+fn fixture() -> Vec<u8> {
+    let friends: Vec<Value> = (0..FRIENDS)
+        .map(|i| {
+            json!({
+                "userId": i,
+                "nickName": format!("friend-{i}"),
+                "friendNickName": "",
+                "phoneNumber": format!("010-0000-{:04}", i % 10_000),
+                "statusMessage": "a status message long enough to matter",
+                "profileImageUrl": format!("https://p.kakaocdn.net/th/talkp/{i}/profile.jpg"),
+                "favorite": i % 7 == 0,
+                "hidden": false,
+            })
+        })
+        .collect();
+    serde_json::to_vec(&json!({"status": 0, "friends": friends})).unwrap()
+}
+
+/// Local server answering every request with `body`; returns its base URL.
+fn serve(body: Vec<u8>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let head = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut buf = [0u8; 4096];
+            let _ = stream.read(&mut buf);
+            let _ = stream.write_all(head.as_bytes());
+            let _ = stream.write_all(&body);
+        }
+    });
+    base
+}
+
+/// Best time over `ROUNDS` runs of `run`, and the most heap any run added
+/// on top of what was in use before it.
+fn measure(run: impl Fn() -> usize) -> (Duration, usize, usize) {
+    let mut best = Duration::MAX;
+    let mut peak = 0;
+    let mut friends = 0;
+    for _ in 0..ROUNDS {
+        let before = CURRENT.load(Ordering::Relaxed);
+        PEAK.store(before, Ordering::Relaxed);
+        let started = Instant::now();
+        friends = run();
+        best = best.min(started.elapsed());
+        peak = peak.max(PEAK.load(Ordering::Relaxed) - before);
+    }
+    (best, peak, friends)
+}
+
+fn main() {
+    let body = fixture();
+    let size = body.len();
+    let base = serve(body);
+    let url = format!("{base}/mac/friends/update.json");
+
+    let http = reqwest::blocking::Client::new();
+    let buffered = measure(|| {
+        let text = http
+            .post(&url)
+            .body("since=0")
+            .send()
+            .unwrap()
+            .text()
+            .unwrap();
+        let value: Value = serde_json::from_str(&text).unwrap();
+        let friends: Vec<Friend> = value["friends"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(Friend::from_json)
+            .collect();
+        friends.len()
+    });
+
+    let client = KakaoRestClient::builder(KakaoCredentials::new(
+        "token".into(),
+        1,
+        "uuid".into(),
+        "3.7.0".into(),
+        "agent".into(),
+        "mac/3.7.0/ko".into(),
+    ))
+    .base_url(base)
+    .build()
+    .unwrap();
+    let streamed = measure(|| client.get_friends().unwrap().len());
+
+    println!(
+        "friends payload: {} friends, {:.1} MiB",
+        FRIENDS,
+        size as f64 / 1048576.0
+    );
+    for (label, (time, peak, friends)) in [
+        ("buffered (text + from_str)", buffered),
+        ("streamed (get_friends)", streamed),
+    ] {
+        assert_eq!(friends, FRIENDS);
+        println!(
+            "  {label:<28} peak heap {:>7.1} MiB  best of {ROUNDS} {:>8.1?}",
+            peak as f64 / 1048576.0,
+            time
+        );
+    }
+}
@@ -0,0 +1,242 @@
+//! Saved credentials and KakaoTalk logged in as different accounts.
+//!
+//! After KakaoTalk is switched to another account, the saved credentials
+//! file still holds the old one. Which of the two a command runs as must
+//! not depend on which token happens to verify, so [`settle`] decides from
+//! `--prefer-saved`/`--prefer-extracted`, an answer remembered in the state
+//! file, or a prompt showing both accounts. Without any of those it keeps
+//! the saved account and warns.
+
+use std::sync::OnceLock;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::credentials::save_credentials;
+use crate::model::KakaoCredentials;
+use crate::prompt;
+use crate::rest::KakaoRestClient;
+use crate::warnings::{warn, WarningCode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AccountChoice {
+    /// The account in the saved credentials file.
+    Saved,
+    /// The account KakaoTalk is logged in as.
+    Extracted,
+}
+
+/// Two credentials for different accounts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountConflict {
+    pub saved_user_id: i64,
+    pub extracted_user_id: i64,
+}
+
+impl AccountConflict {
+    /// The state file key an answer for this pair is remembered under.
+    pub fn key(&self) -> String {
+        format!("{}:{}", self.saved_user_id, self.extracted_user_id)
+    }
+}
+
+/// `None` when both credentials are for the same account, or either does
+/// not know its user id.
+pub fn detect(saved: &KakaoCredentials, extracted: &KakaoCredentials) -> Option<AccountConflict> {
+    (saved.user_id != 0 && extracted.user_id != 0 && saved.user_id != extracted.user_id).then_some(
+        AccountConflict {
+            saved_user_id: saved.user_id,
+            extracted_user_id: extracted.user_id,
+        },
+    )
+}
+
+/// Where the answer for a conflict comes from, first match wins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    /// `--prefer-saved` or `--prefer-extracted`.
+    Flag(AccountChoice),
+    /// Answered at an earlier prompt.
+    Remembered(AccountChoice),
+    /// Nothing decided yet, and the terminal can be asked.
+    Ask,
+    /// Nothing decided and no one to ask: keep the saved account.
+    Undecided,
+}
+
+pub fn decide(
+    flag: Option<AccountChoice>,
+    remembered: Option<AccountChoice>,
+    can_ask: bool,
+) -> Decision {
+    match (flag, remembered) {
+        (Some(choice), _) => Decision::Flag(choice),
+        (None, Some(choice)) => Decision::Remembered(choice),
+        (None, None) if can_ask => Decision::Ask,
+        (None, None) => Decision::Undecided,
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Preference {
+    choice: Option<AccountChoice>,
+    overwrite: bool,
+}
+
+static PREFERENCE: OnceLock<Preference> = OnceLock::new();
+
+/// Set from `--prefer-saved`/`--prefer-extracted` and `--overwrite-saved`.
+pub fn set_preference(choice: Option<AccountChoice>, overwrite: bool) {
+    let _ = PREFERENCE.set(Preference { choice, overwrite });
+}
+
+fn preference() -> Preference {
+    PREFERENCE.get().copied().unwrap_or_default()
+}
+
+/// Which of `saved` and `extracted` (for the accounts in `conflict`) to
+/// run as. Choosing the extracted account may overwrite the saved file
+/// with it, when `--overwrite-saved` or the prompt asks for that.
+pub fn settle(
+    conflict: AccountConflict,
+    saved: &KakaoCredentials,
+    extracted: &KakaoCredentials,
+) -> Result<AccountChoice> {
+    let preference = preference();
+    let remembered =
+        crate::state::remembered_account_choice(&conflict.key()).unwrap_or_else(|err| {
+            tracing::debug!("account choice not read from the state file: {err:#}");
+            None
+        });
+    let (choice, overwrite) = match decide(
+        preference.choice,
+        remembered,
+        prompt::non_interactive_reason().is_none(),
+    ) {
+        Decision::Flag(choice) => (choice, preference.overwrite),
+        Decision::Remembered(choice) => (choice, false),
+        Decision::Ask => {
+            let (choice, overwrite) = ask(conflict, saved, extracted)?;
+            if let Err(err) = crate::state::remember_account_choice(&conflict.key(), choice) {
+                warn(
+                    WarningCode::StateSaveFailed,
+                    format!("Could not remember the account choice: {err:#}"),
+                );
+            }
+            (choice, overwrite)
+        }
+        Decision::Undecided => {
+            warn(
+                WarningCode::AccountMismatch,
+                format!(
+                    "Saved credentials are for user {} but KakaoTalk is logged in as user {}; using the saved account. Pass --prefer-saved or --prefer-extracted to choose.",
+                    conflict.saved_user_id, conflict.extracted_user_id
+                ),
+            );
+            (AccountChoice::Saved, false)
+        }
+    };
+    if choice == AccountChoice::Extracted && overwrite {
+        let location = save_credentials(extracted)?;
+        eprintln!(
+            "[auth] Saved user {}'s credentials to {location}.",
+            conflict.extracted_user_id
+        );
+    }
+    Ok(choice)
+}
+
+fn ask(
+    conflict: AccountConflict,
+    saved: &KakaoCredentials,
+    extracted: &KakaoCredentials,
+) -> Result<(AccountChoice, bool)> {
+    eprintln!("The saved credentials and KakaoTalk are logged in as different accounts:");
+    eprintln!(
+        "  1) saved file       user {} ({})",
+        conflict.saved_user_id,
+        nickname(saved)
+    );
+    eprintln!(
+        "  2) KakaoTalk        user {} ({})",
+        conflict.extracted_user_id,
+        nickname(extracted)
+    );
+    eprintln!("  3) KakaoTalk, and overwrite the saved file with it");
+    loop {
+        match prompt::line("Use which account? [1/2/3]: ")?.as_str() {
+            "1" => return Ok((AccountChoice::Saved, false)),
+            "2" => return Ok((AccountChoice::Extracted, false)),
+            "3" => return Ok((AccountChoice::Extracted, true)),
+            _ => eprintln!("Answer 1, 2 or 3."),
+        }
+    }
+}
+
+/// The account's nickname from profile3/me, or why it is not known.
+fn nickname(creds: &KakaoCredentials) -> String {
+    match KakaoRestClient::new(creds.clone()).and_then(|client| client.get_my_profile()) {
+        Ok(profile) if !profile.nickname.is_empty() => profile.nickname,
+        Ok(_) => "no nickname".to_string(),
+        Err(err) => format!("profile unavailable: {err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn creds(token: &str, user_id: i64) -> KakaoCredentials {
+        KakaoCredentials::new(
+            token.into(),
+            user_id,
+            "device".into(),
+            "26.1.0".into(),
+            String::new(),
+            String::new(),
+        )
+    }
+
+    #[test]
+    fn only_two_known_and_different_user_ids_conflict() {
+        let conflict = detect(&creds("old", 111), &creds("new", 222)).unwrap();
+        assert_eq!(conflict.saved_user_id, 111);
+        assert_eq!(conflict.extracted_user_id, 222);
+        assert_eq!(conflict.key(), "111:222");
+
+        // A refreshed token for the same account is no conflict.
+        assert!(detect(&creds("old", 111), &creds("new", 111)).is_none());
+        // Nor are credentials typed in without a user id.
+        assert!(detect(&creds("old", 0), &creds("new", 222)).is_none());
+        assert!(detect(&creds("old", 111), &creds("new", 0)).is_none());
+    }
+
+    #[test]
+    fn flags_win_over_a_remembered_answer_and_the_prompt() {
+        use AccountChoice::*;
+        assert_eq!(
+            decide(Some(Saved), Some(Extracted), true),
+            Decision::Flag(Saved)
+        );
+        assert_eq!(
+            decide(Some(Extracted), None, false),
+            Decision::Flag(Extracted)
+        );
+        assert_eq!(
+            decide(None, Some(Extracted), true),
+            Decision::Remembered(Extracted)
+        );
+        assert_eq!(decide(None, None, true), Decision::Ask);
+        // Scripts without a flag keep running as the saved account.
+        assert_eq!(decide(None, None, false), Decision::Undecided);
+    }
+
+    #[test]
+    fn choices_are_stored_by_name() {
+        let json = serde_json::to_string(&AccountChoice::Extracted).unwrap();
+        assert_eq!(json, "\"extracted\"");
+        let back: AccountChoice = serde_json::from_str("\"saved\"").unwrap();
+        assert_eq!(back, AccountChoice::Saved);
+    }
+}
@@ -0,0 +1,280 @@
+//! User-defined command aliases from `[alias]` in config.toml, e.g.
+//! `unreads = "chats --unread --type group"`. An alias in the subcommand
+//! position is replaced by its words before clap parses the arguments, so
+//! flags given before and after it still apply. The short forms (`ls`, `f`,
+//! `r`, `m`) are clap aliases and never come through here.
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+
+use anyhow::{bail, Result};
+
+/// How many aliases may expand into one another before giving up.
+pub const MAX_DEPTH: usize = 8;
+
+/// The words of an alias definition, split the way a POSIX shell would:
+/// whitespace separates words; single quotes keep everything up to the next
+/// single quote; double quotes keep everything but allow `\"` and `\\`;
+/// a backslash outside quotes takes the next character as is. Quotes may
+/// sit inside a word (`--search="a b"`) and `""` is an empty word.
+pub fn split_words(line: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let current = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => bail!("Unterminated ' in alias: {line}"),
+                    }
+                }
+            }
+            '"' => {
+                let current = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => current.push(c),
+                            Some(c) => {
+                                current.push('\\');
+                                current.push(c);
+                            }
+                            None => bail!("Unterminated \" in alias: {line}"),
+                        },
+                        Some(c) => current.push(c),
+                        None => bail!("Unterminated \" in alias: {line}"),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some(c) => word.get_or_insert_with(String::new).push(c),
+                None => bail!("Trailing backslash in alias: {line}"),
+            },
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// `args` as one shell line, quoting words that need it, for
+/// `--print-expansion`.
+pub fn quote_words(args: &[OsString]) -> String {
+    args.iter()
+        .map(|arg| {
+            let text = arg.to_string_lossy();
+            let plain = !text.is_empty()
+                && text
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || "-_=.,:/@%+".contains(c));
+            if plain {
+                text.into_owned()
+            } else {
+                format!("'{}'", text.replace('\'', r"'\''"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Index of the subcommand in `args` (program name first), skipping global
+/// flags and their values; `None` when there is none before `--`.
+fn command_position(args: &[OsString], takes_value: &impl Fn(&str) -> bool) -> Option<usize> {
+    let mut idx = 1;
+    while let Some(arg) = args.get(idx) {
+        let text = arg.to_string_lossy();
+        if text == "--" {
+            return None;
+        }
+        if let Some(long) = text.strip_prefix("--") {
+            idx += if !long.contains('=') && takes_value(long) {
+                2
+            } else {
+                1
+            };
+        } else if text.starts_with('-') && text.len() > 1 {
+            idx += 1;
+        } else {
+            return Some(idx);
+        }
+    }
+    None
+}
+
+/// `args` (program name first) with an alias in the subcommand position
+/// replaced by its words, again and again while the result starts with
+/// another alias. Built-in subcommands always win over an alias of the same
+/// name. `takes_value` says which global long flags take a separate value.
+/// Returns `None` when no alias was used; fails on a loop or on more than
+/// [`MAX_DEPTH`] expansions.
+pub fn expand(
+    args: &[OsString],
+    aliases: &BTreeMap<String, String>,
+    is_builtin: impl Fn(&str) -> bool,
+    takes_value: impl Fn(&str) -> bool,
+) -> Result<Option<Vec<OsString>>> {
+    let mut args = args.to_vec();
+    let mut chain: Vec<String> = Vec::new();
+    while let Some(idx) = command_position(&args, &takes_value) {
+        let name = args[idx].to_string_lossy().into_owned();
+        if is_builtin(&name) {
+            break;
+        }
+        let Some(definition) = aliases.get(&name) else {
+            break;
+        };
+        if chain.contains(&name) {
+            chain.push(name);
+            bail!("Alias loop: {}", chain.join(" -> "));
+        }
+        if chain.len() == MAX_DEPTH {
+            bail!(
+                "Aliases nest more than {MAX_DEPTH} deep: {} -> {name}",
+                chain.join(" -> ")
+            );
+        }
+        let words = split_words(definition)?;
+        if words.is_empty() {
+            bail!("Alias '{name}' is empty");
+        }
+        args.splice(idx..=idx, words.into_iter().map(OsString::from));
+        chain.push(name);
+    }
+    Ok((!chain.is_empty()).then_some(args))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn os(words: &[&str]) -> Vec<OsString> {
+        words.iter().map(OsString::from).collect()
+    }
+
+    fn aliases(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, line)| (name.to_string(), line.to_string()))
+            .collect()
+    }
+
+    /// [`expand`] with `chats`, `read` and `friends` built in and `--out`
+    /// taking a value.
+    fn run(args: &[&str], defined: &[(&str, &str)]) -> Result<Option<Vec<OsString>>> {
+        expand(
+            &os(args),
+            &aliases(defined),
+            |name| matches!(name, "chats" | "read" | "friends"),
+            |flag| flag == "out",
+        )
+    }
+
+    #[test]
+    fn words_follow_shell_quoting() {
+        assert_eq!(
+            split_words(r#"friends --favorites  --search "김 'quoted' \"x\"""#).unwrap(),
+            ["friends", "--favorites", "--search", r#"김 'quoted' "x""#]
+        );
+        assert_eq!(
+            split_words(r#"read 'say "hi" \now' --out=a\ b.txt"#).unwrap(),
+            ["read", r#"say "hi" \now"#, "--out=a b.txt"]
+        );
+        assert_eq!(
+            split_words(r#"x --search="" "a\qb""#).unwrap(),
+            ["x", "--search=", r"a\qb"]
+        );
+        assert_eq!(split_words("chats '' ").unwrap(), ["chats", ""]);
+        assert!(split_words("chats 'open").is_err());
+        assert!(split_words(r#"chats "open\""#).is_err());
+        assert!(split_words(r"chats \").is_err());
+    }
+
+    #[test]
+    fn flags_before_and_after_an_alias_both_apply() {
+        let unreads = [("unreads", "chats --unread --type group")];
+        assert_eq!(
+            run(
+                &["ok", "--json", "--out", "a.json", "unreads", "--no-color"],
+                &unreads
+            )
+            .unwrap()
+            .unwrap(),
+            os(&[
+                "ok",
+                "--json",
+                "--out",
+                "a.json",
+                "chats",
+                "--unread",
+                "--type",
+                "group",
+                "--no-color",
+            ])
+        );
+        // A flag value that happens to be an alias name is not expanded.
+        assert_eq!(
+            run(&["ok", "--out", "unreads", "chats"], &unreads).unwrap(),
+            None
+        );
+        assert_eq!(run(&["ok", "--", "unreads"], &unreads).unwrap(), None);
+    }
+
+    #[test]
+    fn aliases_nest_and_builtins_win() {
+        let defined = [
+            ("fav", "friends --favorites"),
+            ("kim", "--json fav --search '김 민수'"),
+            ("chats", "friends"),
+        ];
+        assert_eq!(
+            run(&["ok", "kim"], &defined).unwrap().unwrap(),
+            os(&[
+                "ok",
+                "--json",
+                "friends",
+                "--favorites",
+                "--search",
+                "김 민수"
+            ])
+        );
+        assert_eq!(run(&["ok", "chats"], &defined).unwrap(), None);
+        assert_eq!(run(&["ok", "unknown"], &defined).unwrap(), None);
+    }
+
+    #[test]
+    fn loops_and_deep_chains_are_refused() {
+        let looped = [("a", "b --json"), ("b", "c"), ("c", "a")];
+        let err = run(&["ok", "a"], &looped).unwrap_err().to_string();
+        assert_eq!(err, "Alias loop: a -> b -> c -> a");
+
+        let deep: Vec<(String, String)> = (0..=MAX_DEPTH)
+            .map(|i| (format!("a{i}"), format!("a{}", i + 1)))
+            .collect();
+        let deep: Vec<(&str, &str)> = deep.iter().map(|(a, b)| (a.as_str(), b.as_str())).collect();
+        let err = run(&["ok", "a0"], &deep).unwrap_err().to_string();
+        assert!(err.starts_with("Aliases nest more than 8 deep"), "{err}");
+
+        assert!(run(&["ok", "empty"], &[("empty", "  ")]).is_err());
+    }
+
+    #[test]
+    fn expansions_print_as_shell_lines() {
+        assert_eq!(
+            quote_words(&os(&[
+                "openkakao-rs",
+                "friends",
+                "--search",
+                "김 민수",
+                "it's",
+                ""
+            ])),
+            r"openkakao-rs friends --search '김 민수' 'it'\''s' ''"
+        );
+    }
+}
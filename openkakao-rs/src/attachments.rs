@@ -0,0 +1,631 @@
+use std::collections::HashSet;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+
+use crate::media::{parse_attachment_url, sanitize_filename};
+use crate::model::{ChatMessage, MessageContent};
+use crate::urlnorm::extract_urls;
+
+/// Attachment types listed by `files`; links are URLs found in text messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttachmentKind {
+    Photo,
+    Video,
+    File,
+    Link,
+}
+
+impl AttachmentKind {
+    pub fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "photo" | "photos" => Ok(Self::Photo),
+            "video" | "videos" => Ok(Self::Video),
+            "file" | "files" => Ok(Self::File),
+            "link" | "links" => Ok(Self::Link),
+            _ => Err(anyhow!(
+                "Unknown attachment type '{}'. Use: photo, video, file, link",
+                s
+            )),
+        }
+    }
+
+    /// Photo (2) and multi-photo (27), video (3) and file (26) messages.
+    /// Links depend on the text, not the type.
+    pub fn of_message_type(message_type: i64) -> Option<Self> {
+        match message_type {
+            2 | 27 => Some(Self::Photo),
+            3 => Some(Self::Video),
+            26 => Some(Self::File),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Photo => "photo",
+            Self::Video => "video",
+            Self::File => "file",
+            Self::Link => "link",
+        }
+    }
+}
+
+/// One attachment message, from the local cache or a network page.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AttachmentEntry {
+    pub log_id: i64,
+    pub author_id: i64,
+    pub send_at: i64,
+    pub kind: AttachmentKind,
+    /// File name, or the normalized URL for links.
+    pub filename: String,
+    /// Bytes, when the attachment says (`s` / `size`).
+    pub size: Option<u64>,
+}
+
+impl AttachmentEntry {
+    /// `None` for messages that are not photo, video or file attachments and
+    /// text messages (1) without a link. A message with several links is
+    /// listed under its first one.
+    pub fn from_message(
+        log_id: i64,
+        author_id: i64,
+        message_type: i64,
+        message: &str,
+        attachment: &str,
+        send_at: i64,
+    ) -> Option<Self> {
+        let Some(kind) = AttachmentKind::of_message_type(message_type) else {
+            if message_type != 1 {
+                return None;
+            }
+            let url = extract_urls(message, crate::urlnorm::strip_tracking())
+                .into_iter()
+                .next()?;
+            return Some(Self {
+                log_id,
+                author_id,
+                send_at,
+                kind: AttachmentKind::Link,
+                filename: url,
+                size: None,
+            });
+        };
+        let parsed: serde_json::Value = serde_json::from_str(attachment).unwrap_or_default();
+        let filename = parse_attachment_url(attachment, message_type as i32)
+            .map(|(_, name)| name)
+            .or_else(|| {
+                parsed
+                    .get("name")
+                    .and_then(|v| v.as_str())
+                    .filter(|n| !n.is_empty())
+                    .map(str::to_string)
+            })
+            .or_else(
+                || match MessageContent::parse(message_type, message, attachment) {
+                    MessageContent::MultiPhoto { photos } => Some(album_label(photos.len())),
+                    _ => None,
+                },
+            )
+            .unwrap_or_default();
+        let size = attachment_size(&parsed);
+        Some(Self {
+            log_id,
+            author_id,
+            send_at,
+            kind,
+            filename,
+            size,
+        })
+    }
+}
+
+/// How an album of `count` photos is shown where one message is one line:
+/// `(album: 4 photos)`.
+pub fn album_label(count: usize) -> String {
+    format!("(album: {count} photos)")
+}
+
+/// One file `download <chat_id>` saves: a photo, video or file message, or
+/// one photo of a multi-photo.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MediaTarget {
+    pub log_id: i64,
+    pub kind: AttachmentKind,
+    pub url: String,
+    /// `<send_at>_<log_id>_<original name>`, with the photo's position
+    /// after the log id for multi-photos, so reruns pick the same names.
+    pub filename: String,
+}
+
+impl MediaTarget {
+    /// What `msg` has to download; empty for other message types and for
+    /// attachments without a URL.
+    pub fn from_message(msg: &ChatMessage) -> Vec<Self> {
+        let Some(kind) = AttachmentKind::of_message_type(msg.message_type) else {
+            return Vec::new();
+        };
+        let prefix = format!("{}_{}", msg.send_at, msg.log_id);
+        if let MessageContent::MultiPhoto { photos } = &msg.content {
+            return photos
+                .iter()
+                .enumerate()
+                .map(|(index, photo)| {
+                    let name = photo.url.rsplit('/').next().unwrap_or_default();
+                    Self {
+                        log_id: msg.log_id,
+                        kind,
+                        url: photo.url.clone(),
+                        filename: format!("{}_{}_{}", prefix, index + 1, sanitize_filename(name)),
+                    }
+                })
+                .collect();
+        }
+        parse_attachment_url(&msg.attachment, msg.message_type as i32)
+            .map(|(url, name)| Self {
+                log_id: msg.log_id,
+                kind,
+                url,
+                filename: format!("{}_{}", prefix, sanitize_filename(&name)),
+            })
+            .into_iter()
+            .collect()
+    }
+}
+
+/// Bytes an attachment says it takes: `s` / `size`, or for a multi-photo
+/// the sum of its `sl` list. `None` when the size is not given (or not all
+/// of a multi-photo's sizes are), which is unknown rather than empty.
+pub fn attachment_size(attachment: &serde_json::Value) -> Option<u64> {
+    if let Some(size) = ["s", "size"]
+        .iter()
+        .find_map(|key| attachment.get(*key).and_then(|v| v.as_u64()))
+    {
+        return Some(size);
+    }
+    let sizes = attachment.get("sl")?.as_array()?;
+    if sizes.is_empty() {
+        return None;
+    }
+    sizes.iter().map(|v| v.as_u64()).sum()
+}
+
+/// Message groups `stats --media` counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageCategory {
+    Text,
+    Photo,
+    Video,
+    File,
+    Emoticon,
+    Other,
+}
+
+impl MessageCategory {
+    pub const ALL: [Self; 6] = [
+        Self::Text,
+        Self::Photo,
+        Self::Video,
+        Self::File,
+        Self::Emoticon,
+        Self::Other,
+    ];
+
+    pub fn of_message_type(message_type: i64) -> Self {
+        match message_type {
+            1 => Self::Text,
+            2 | 27 => Self::Photo,
+            3 => Self::Video,
+            26 => Self::File,
+            14 | 20 | 25 => Self::Emoticon,
+            _ => Self::Other,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Photo => "photo",
+            Self::Video => "video",
+            Self::File => "file",
+            Self::Emoticon => "emoticon",
+            Self::Other => "other",
+        }
+    }
+
+    /// Photos, videos and files: what `download` fetches.
+    pub fn is_downloadable(&self) -> bool {
+        matches!(self, Self::Photo | Self::Video | Self::File)
+    }
+}
+
+/// Attachment bytes of a group of messages. Messages whose attachment does
+/// not give a size are counted in `unknown`, never as zero bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct SizeTotal {
+    pub known_bytes: u64,
+    pub sized: usize,
+    pub unknown: usize,
+}
+
+impl SizeTotal {
+    fn add(&mut self, size: Option<u64>) {
+        match size {
+            Some(bytes) => {
+                self.known_bytes += bytes;
+                self.sized += 1;
+            }
+            None => self.unknown += 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CategoryUsage {
+    pub category: MessageCategory,
+    pub messages: usize,
+    /// Only for downloadable categories.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<SizeTotal>,
+}
+
+/// Per-category message counts and attachment sizes for `stats --media`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MediaUsage {
+    pub total_messages: usize,
+    pub categories: Vec<CategoryUsage>,
+}
+
+impl MediaUsage {
+    /// From `(message_type, attachment)` pairs.
+    pub fn from_messages<'a>(messages: impl IntoIterator<Item = (i64, &'a str)>) -> Self {
+        let mut categories: Vec<CategoryUsage> = MessageCategory::ALL
+            .iter()
+            .map(|&category| CategoryUsage {
+                category,
+                messages: 0,
+                size: category.is_downloadable().then(SizeTotal::default),
+            })
+            .collect();
+        let mut total_messages = 0;
+        for (message_type, attachment) in messages {
+            total_messages += 1;
+            let category = MessageCategory::of_message_type(message_type);
+            let Some(usage) = categories.iter_mut().find(|u| u.category == category) else {
+                continue;
+            };
+            usage.messages += 1;
+            if let Some(size) = &mut usage.size {
+                let parsed: serde_json::Value =
+                    serde_json::from_str(attachment).unwrap_or_default();
+                size.add(attachment_size(&parsed));
+            }
+        }
+        Self {
+            total_messages,
+            categories,
+        }
+    }
+
+    /// What downloading every photo, video and file would take.
+    pub fn download_estimate(&self) -> SizeTotal {
+        self.categories
+            .iter()
+            .filter_map(|u| u.size)
+            .fold(SizeTotal::default(), |total, size| SizeTotal {
+                known_bytes: total.known_bytes + size.known_bytes,
+                sized: total.sized + size.sized,
+                unknown: total.unknown + size.unknown,
+            })
+    }
+}
+
+/// Keep only the first (newest, given newest-first input) entry of each
+/// normalized link; other attachments pass through.
+pub fn dedupe_links(entries: Vec<AttachmentEntry>) -> Vec<AttachmentEntry> {
+    let mut seen = HashSet::new();
+    entries
+        .into_iter()
+        .filter(|entry| entry.kind != AttachmentKind::Link || seen.insert(entry.filename.clone()))
+        .collect()
+}
+
+/// `files --type` / `--grep`.
+#[derive(Debug, Clone, Default)]
+pub struct AttachmentFilter {
+    pub kind: Option<AttachmentKind>,
+    /// Case-insensitive substring of the filename.
+    pub grep: Option<String>,
+    /// Compare `grep` and filenames in [`crate::normalize`] form.
+    pub normalize: bool,
+}
+
+impl AttachmentFilter {
+    pub fn keeps(&self, entry: &AttachmentEntry) -> bool {
+        if self.kind.is_some_and(|kind| kind != entry.kind) {
+            return false;
+        }
+        match &self.grep {
+            Some(needle) if self.normalize => {
+                crate::normalize::contains_normalized(&entry.filename, needle)
+            }
+            Some(needle) => entry
+                .filename
+                .to_lowercase()
+                .contains(&needle.to_lowercase()),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixtures() -> Vec<AttachmentEntry> {
+        [
+            (1, 1, r#"{"msg":"hello"}"#),
+            (
+                2,
+                26,
+                r#"{"name":"Q3 Report.pdf","s":1572864,"url":"https://dn.example/f/1"}"#,
+            ),
+            (
+                3,
+                2,
+                r#"{"k":"abc/photo123.jpg","s":204800,"w":800,"h":600}"#,
+            ),
+            (4, 3, r#"{"k":"def/clip","size":10485760}"#),
+            (
+                5,
+                26,
+                r#"{"name":"notes.txt","url":"https://dn.example/f/2"}"#,
+            ),
+            (6, 14, r#"{"name":"(Emoticons)"}"#),
+        ]
+        .iter()
+        .filter_map(|(log_id, kind, att)| {
+            AttachmentEntry::from_message(*log_id, 7, *kind, "", att, 1_700_000_000)
+        })
+        .collect()
+    }
+
+    #[test]
+    fn only_photo_video_and_file_messages_are_listed() {
+        let entries = fixtures();
+        let ids: Vec<i64> = entries.iter().map(|e| e.log_id).collect();
+        assert_eq!(ids, [2, 3, 4, 5]);
+        assert_eq!(entries[0].filename, "Q3 Report.pdf");
+        assert_eq!(entries[0].size, Some(1_572_864));
+        assert_eq!(entries[1].filename, "photo123.jpg");
+        assert_eq!(entries[2].filename, "clip.mp4");
+        assert_eq!(entries[2].size, Some(10_485_760));
+        assert_eq!(entries[3].size, None);
+
+        let album = AttachmentEntry::from_message(
+            8,
+            7,
+            27,
+            "",
+            r#"{"kl":["a.jpg","b.jpg"],"sl":[100,200]}"#,
+            1_700_000_000,
+        )
+        .unwrap();
+        assert_eq!(album.filename, "(album: 2 photos)");
+        assert_eq!(album.size, Some(300));
+    }
+
+    #[test]
+    fn filters_by_type_and_filename() {
+        let entries = fixtures();
+        let keep = |filter: AttachmentFilter| -> Vec<i64> {
+            entries
+                .iter()
+                .filter(|e| filter.keeps(e))
+                .map(|e| e.log_id)
+                .collect()
+        };
+        assert_eq!(keep(AttachmentFilter::default()), [2, 3, 4, 5]);
+        assert_eq!(
+            keep(AttachmentFilter {
+                kind: Some(AttachmentKind::File),
+                grep: None,
+                ..Default::default()
+            }),
+            [2, 5]
+        );
+        assert_eq!(
+            keep(AttachmentFilter {
+                kind: Some(AttachmentKind::File),
+                grep: Some("REPORT".into()),
+                ..Default::default()
+            }),
+            [2]
+        );
+        assert_eq!(
+            keep(AttachmentFilter {
+                kind: Some(AttachmentKind::Photo),
+                grep: Some("pdf".into()),
+                ..Default::default()
+            }),
+            Vec::<i64>::new()
+        );
+        assert!(AttachmentKind::from_str("audio").is_err());
+    }
+
+    #[test]
+    fn normalized_grep_matches_decomposed_and_full_width_names() {
+        // macOS sends decomposed jamo: 회의록 as NFD.
+        let nfd = "\u{1112}\u{116C}\u{110B}\u{1174}\u{1105}\u{1169}\u{11A8}_ＦＩＮＡＬ.pdf";
+        let entry = AttachmentEntry::from_message(
+            1,
+            7,
+            26,
+            "",
+            &serde_json::json!({"name": nfd, "url": "https://dn.example/f/3"}).to_string(),
+            1_700_000_000,
+        )
+        .unwrap();
+        let filter = |normalize| AttachmentFilter {
+            grep: Some("회의록_final".into()),
+            normalize,
+            ..Default::default()
+        };
+        assert!(!filter(false).keeps(&entry));
+        assert!(filter(true).keeps(&entry));
+    }
+
+    #[test]
+    fn media_usage_counts_types_and_keeps_unknown_sizes_apart() {
+        let messages = [
+            (1, ""),
+            (1, ""),
+            (2, r#"{"k":"a.jpg","s":1000}"#),
+            (2, r#"{"k":"b.jpg"}"#),
+            (27, r#"{"kl":["c","d"],"sl":[300,700]}"#),
+            // One size missing from the list: the whole message is unknown.
+            (27, r#"{"kl":["e","f"],"sl":[300,null]}"#),
+            (3, r#"{"k":"clip","size":5000}"#),
+            (26, r#"{"name":"a.pdf","s":0}"#),
+            (26, "not json"),
+            (14, r#"{"name":"(Emoticons)","s":99}"#),
+            (25, "{}"),
+            (0, r#"{"feedType":4}"#),
+            (71, "{}"),
+        ];
+        let usage = MediaUsage::from_messages(messages.iter().map(|(t, a)| (*t, *a)));
+        assert_eq!(usage.total_messages, 13);
+        let counts: Vec<(&str, usize)> = usage
+            .categories
+            .iter()
+            .map(|u| (u.category.label(), u.messages))
+            .collect();
+        assert_eq!(
+            counts,
+            [
+                ("text", 2),
+                ("photo", 4),
+                ("video", 1),
+                ("file", 2),
+                ("emoticon", 2),
+                ("other", 2)
+            ]
+        );
+        let photo = usage.categories[1].size.unwrap();
+        assert_eq!(
+            photo,
+            SizeTotal {
+                known_bytes: 2000,
+                sized: 2,
+                unknown: 2,
+            }
+        );
+        // A zero-byte file is a known size; unreadable metadata is not.
+        let file = usage.categories[3].size.unwrap();
+        assert_eq!((file.known_bytes, file.sized, file.unknown), (0, 1, 1));
+        // Text and emoticons are not downloaded, so carry no size at all.
+        assert!(usage.categories[0].size.is_none());
+        assert!(usage.categories[4].size.is_none());
+
+        let estimate = usage.download_estimate();
+        assert_eq!(
+            estimate,
+            SizeTotal {
+                known_bytes: 7000,
+                sized: 4,
+                unknown: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn empty_usage_has_no_estimate() {
+        let usage = MediaUsage::from_messages(std::iter::empty());
+        assert_eq!(usage.total_messages, 0);
+        assert_eq!(usage.download_estimate(), SizeTotal::default());
+    }
+
+    #[test]
+    fn identical_links_are_listed_once() {
+        let messages = [
+            (12, 1, "again https://Example.com/post/#comments"),
+            (11, 1, "see https://example.com/post"),
+            (10, 1, "no link"),
+            (9, 1, "other www.naver.com"),
+        ];
+        let entries: Vec<AttachmentEntry> = messages
+            .iter()
+            .filter_map(|(log_id, kind, text)| {
+                AttachmentEntry::from_message(*log_id, 7, *kind, text, "", 1_700_000_000)
+            })
+            .collect();
+        assert_eq!(entries.len(), 3);
+        assert!(entries.iter().all(|e| e.kind == AttachmentKind::Link));
+
+        let deduped = dedupe_links(entries);
+        let listed: Vec<(i64, &str)> = deduped
+            .iter()
+            .map(|e| (e.log_id, e.filename.as_str()))
+            .collect();
+        assert_eq!(
+            listed,
+            [
+                (12, "https://example.com/post"),
+                (9, "https://www.naver.com")
+            ]
+        );
+    }
+
+    #[test]
+    fn media_targets_are_named_by_time_and_log_id() {
+        let message = |log_id: i64, kind: i64, attachment: &str| {
+            ChatMessage::from_json(&serde_json::json!({
+                "logId": log_id,
+                "authorId": 7,
+                "type": kind,
+                "message": "",
+                "attachment": attachment,
+                "sendAt": 1_700_000_000,
+            }))
+        };
+        let photo = MediaTarget::from_message(&message(
+            5,
+            2,
+            r#"{"url":"https://dn/talkm/a/b.jpg","k":"a/b.jpg"}"#,
+        ));
+        assert_eq!(photo.len(), 1);
+        assert_eq!(photo[0].kind, AttachmentKind::Photo);
+        assert_eq!(photo[0].filename, "1700000000_5_b.jpg");
+
+        let file = MediaTarget::from_message(&message(
+            6,
+            26,
+            r#"{"url":"https://dn/f","name":"../report.pdf"}"#,
+        ));
+        assert_eq!(file[0].filename, "1700000000_6_report.pdf");
+
+        let album = MediaTarget::from_message(&message(7, 27, r#"{"kl":["x/1.jpg","x/2.jpg"]}"#));
+        let names: Vec<&str> = album.iter().map(|t| t.filename.as_str()).collect();
+        assert_eq!(names, ["1700000000_7_1_1.jpg", "1700000000_7_2_2.jpg"]);
+
+        // A 30-photo album with a broken entry still downloads the other 29,
+        // numbered without a gap.
+        let keys: Vec<serde_json::Value> = (1..=30)
+            .map(|i| match i {
+                12 => serde_json::Value::Null,
+                i => format!("y/{i}.jpg").into(),
+            })
+            .collect();
+        let attachment = serde_json::json!({ "kl": keys }).to_string();
+        let album = MediaTarget::from_message(&message(10, 27, &attachment));
+        assert_eq!(album.len(), 29);
+        assert_eq!(album[11].filename, "1700000000_10_12_13.jpg");
+        assert_eq!(album[28].filename, "1700000000_10_29_30.jpg");
+
+        assert!(MediaTarget::from_message(&message(8, 1, "")).is_empty());
+        assert!(MediaTarget::from_message(&message(9, 2, "{}")).is_empty());
+    }
+}
@@ -1,15 +1,19 @@
 use std::cmp::Ordering;
 use std::collections::HashSet;
 use std::fs;
-use std::io::{self, Cursor, Write};
+use std::io::Cursor;
 use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
 use plist::Value as PlistValue;
+use rusqlite::types::ValueRef;
 use rusqlite::Connection;
 use tempfile::tempdir;
 
+use crate::error::OpenKakaoError;
 use crate::model::KakaoCredentials;
+use crate::timestamp::normalize_timestamp;
 
 struct ExtractedCredential {
     creds: KakaoCredentials,
@@ -19,81 +23,123 @@ struct ExtractedCredential {
 }
 
 pub fn get_credential_candidates(max_candidates: usize) -> Result<Vec<KakaoCredentials>> {
-    let extracted = extract_candidates_from_cache_db(300)?;
+    let mut extracted = Vec::new();
+    for store in credential_store_paths() {
+        if store.is_dir() {
+            extracted.extend(extract_candidates_from_header_dumps(&store));
+        } else if store.is_file() {
+            extracted.extend(extract_candidates_from_cache_db(&store, 300)?);
+        }
+    }
 
     if extracted.is_empty() {
         return Ok(Vec::new());
     }
+    let extracted = rank_candidates(extracted);
 
     let mut out = Vec::new();
-    let debug = std::env::var("OPENKAKAO_RS_DEBUG").is_ok();
     for candidate in extracted.into_iter().take(max_candidates.max(1)) {
-        if debug {
-            eprintln!(
-                "[auth] candidate: ts={:.3}, priority={}, url={}",
-                candidate.timestamp, candidate.priority, candidate.source_url
-            );
-        }
+        tracing::debug!(
+            "candidate: token={}, ts={:.3}, priority={}, url={}",
+            crate::trace::redact_secret(&candidate.creds.oauth_token),
+            candidate.timestamp,
+            candidate.priority,
+            candidate.source_url
+        );
         out.push(candidate.creds);
     }
 
     Ok(out)
 }
 
-pub fn get_credentials_interactive() -> Result<KakaoCredentials> {
-    eprintln!("Could not auto-extract KakaoTalk credentials.");
-    eprintln!("Please provide credentials manually.");
-
-    let oauth_token = prompt("OAuth Token (Authorization header value): ")?;
-    let user_id_raw = prompt("User ID (numeric, from talk-user-id header): ")?;
+/// Best first: higher URL priority, then newer. Each token is kept once,
+/// at its best position.
+fn rank_candidates(mut candidates: Vec<ExtractedCredential>) -> Vec<ExtractedCredential> {
+    candidates.sort_by(|a, b| {
+        b.priority.cmp(&a.priority).then_with(|| {
+            b.timestamp
+                .partial_cmp(&a.timestamp)
+                .unwrap_or(Ordering::Equal)
+        })
+    });
+    let mut seen_tokens = HashSet::new();
+    candidates.retain(|c| {
+        let first = seen_tokens.insert(c.creds.oauth_token.clone());
+        if !first {
+            tracing::debug!(
+                "rejected {}: same token as a better-ranked request",
+                c.source_url
+            );
+        }
+        first
+    });
+    candidates
+}
 
-    let user_id = user_id_raw.trim().parse::<i64>().unwrap_or(0);
-    let device_uuid = oauth_token
-        .split_once('-')
-        .map(|(_, suffix)| suffix.to_string())
-        .unwrap_or_default();
-
-    Ok(KakaoCredentials::new(
-        oauth_token,
-        user_id,
-        device_uuid,
-        "3.7.0".to_string(),
-        String::new(),
-        String::new(),
-    ))
+/// Where the KakaoTalk client on this platform keeps the requests it sent:
+/// the Mac app's `Cache.db`.
+#[cfg(not(windows))]
+pub fn credential_store_paths() -> Vec<PathBuf> {
+    kakao_container_path()
+        .map(|container| {
+            vec![container
+                .join("Data")
+                .join("Library")
+                .join("Caches")
+                .join("Cache.db")]
+        })
+        .unwrap_or_default()
 }
 
-fn prompt(label: &str) -> Result<String> {
-    print!("{}", label);
-    io::stdout().flush().context("Failed to flush stdout")?;
-    let mut input = String::new();
-    io::stdin()
-        .read_line(&mut input)
-        .context("Failed to read stdin")?;
-    Ok(input.trim().to_string())
+/// Where the KakaoTalk client on this platform keeps the requests it sent:
+/// the PC client's data directories under `%LOCALAPPDATA%` and `%APPDATA%`.
+#[cfg(windows)]
+pub fn credential_store_paths() -> Vec<PathBuf> {
+    [dirs::data_local_dir(), dirs::data_dir()]
+        .into_iter()
+        .flatten()
+        .map(|base| base.join("Kakao").join("KakaoTalk"))
+        .collect()
 }
 
-fn extract_candidates_from_cache_db(max_rows: usize) -> Result<Vec<ExtractedCredential>> {
+/// Sandbox container the KakaoTalk Mac app creates on first launch.
+pub fn kakao_container_path() -> Result<PathBuf> {
     let home = dirs::home_dir().context("Could not resolve home directory")?;
-    let cache_db = home
+    Ok(home
         .join("Library")
         .join("Containers")
-        .join("com.kakao.KakaoTalkMac")
-        .join("Data")
-        .join("Library")
-        .join("Caches")
-        .join("Cache.db");
+        .join("com.kakao.KakaoTalkMac"))
+}
 
-    if !cache_db.exists() {
-        return Ok(Vec::new());
+/// The KakaoTalk container path, or [`OpenKakaoError::ContainerNotFound`]
+/// when the app is not installed (or has never been launched).
+pub fn find_kakao_container() -> Result<PathBuf> {
+    let path = kakao_container_path()?;
+    if path.is_dir() {
+        Ok(path)
+    } else {
+        Err(OpenKakaoError::ContainerNotFound(path).into())
     }
+}
 
+/// OAuth tokens end in `-<device uuid>`.
+pub fn device_uuid_from_token(oauth_token: &str) -> String {
+    oauth_token
+        .split_once('-')
+        .map(|(_, suffix)| suffix.to_string())
+        .unwrap_or_default()
+}
+
+fn extract_candidates_from_cache_db(
+    cache_db: &Path,
+    max_rows: usize,
+) -> Result<Vec<ExtractedCredential>> {
     let temp_dir = tempdir().context("Failed to create temporary directory")?;
     let tmp_db = temp_dir.path().join("Cache.db");
-    copy_with_timeout(&cache_db, &tmp_db, 5)?;
+    copy_with_timeout(cache_db, &tmp_db, 5)?;
 
-    copy_companion_file(&cache_db, &tmp_db, "-wal")?;
-    copy_companion_file(&cache_db, &tmp_db, "-shm")?;
+    copy_companion_file(cache_db, &tmp_db, "-wal")?;
+    copy_companion_file(cache_db, &tmp_db, "-shm")?;
 
     let conn = Connection::open(&tmp_db)
         .with_context(|| format!("Failed to open {}", tmp_db.display()))?;
@@ -111,37 +157,48 @@ fn extract_candidates_from_cache_db(max_rows: usize) -> Result<Vec<ExtractedCred
     )?;
 
     let mut rows = stmt.query([max_rows as i64])?;
+    tracing::debug!(
+        "reading up to {max_rows} kakao requests from {}",
+        cache_db.display()
+    );
 
     let mut candidates = Vec::new();
-    let mut seen_tokens = HashSet::new();
 
     while let Some(row) = rows.next()? {
         let request_object: Vec<u8> = row.get(0)?;
         let request_key: String = row.get::<_, String>(1).unwrap_or_default();
+        // `time_stamp` may be CFAbsoluteTime; compare as Unix seconds.
         let timestamp = row
             .get::<_, f64>(2)
             .or_else(|_| row.get::<_, i64>(2).map(|v| v as f64))
-            .unwrap_or(0.0);
+            .ok()
+            .and_then(crate::timestamp::normalize_timestamp)
+            .map_or(0.0, |dt| dt.timestamp_millis() as f64 / 1000.0);
 
         let plist = match PlistValue::from_reader(Cursor::new(request_object)) {
             Ok(v) => v,
-            Err(_) => continue,
+            Err(err) => {
+                tracing::debug!("rejected {request_key}: request is not a plist ({err})");
+                continue;
+            }
         };
 
         let headers = match find_headers_map(&plist) {
             Some(h) => h,
-            None => continue,
+            None => {
+                tracing::debug!("rejected {request_key}: no header map in the request");
+                continue;
+            }
         };
 
         let auth_token = match value_as_string(headers.get("Authorization")) {
             Some(token) if !token.is_empty() => token,
-            _ => continue,
+            _ => {
+                tracing::debug!("rejected {request_key}: no Authorization header");
+                continue;
+            }
         };
 
-        if !seen_tokens.insert(auth_token.clone()) {
-            continue;
-        }
-
         let user_id = value_as_string(headers.get("talk-user-id"))
             .and_then(|s| s.parse::<i64>().ok())
             .unwrap_or(0);
@@ -156,6 +213,10 @@ fn extract_candidates_from_cache_db(max_rows: usize) -> Result<Vec<ExtractedCred
             .unwrap_or_default();
 
         let priority = url_priority(&request_key);
+        tracing::debug!(
+            "considered {request_key}: token={}, user_id={user_id}, ts={timestamp:.3}, priority={priority}",
+            crate::trace::redact_secret(&auth_token)
+        );
 
         candidates.push(ExtractedCredential {
             creds: KakaoCredentials::new(
@@ -172,21 +233,210 @@ fn extract_candidates_from_cache_db(max_rows: usize) -> Result<Vec<ExtractedCred
         });
     }
 
-    candidates.sort_by(|a, b| {
-        b.priority.cmp(&a.priority).then_with(|| {
-            b.timestamp
-                .partial_cmp(&a.timestamp)
-                .unwrap_or(Ordering::Equal)
-        })
-    });
-
     Ok(candidates)
 }
 
+/// Past this, the requests in `Cache.db` are too old to count on for
+/// extraction: KakaoTalk has not been used recently.
+pub const CACHE_STALE_AFTER_SECS: i64 = 24 * 60 * 60;
+
+/// How recently the Mac app wrote to its `Cache.db`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheFreshness {
+    /// Newest modification time of `Cache.db` and its write-ahead log.
+    pub modified: Option<DateTime<Utc>>,
+    /// Time of the newest cached kakao.com request.
+    pub newest_request: Option<DateTime<Utc>>,
+}
+
+impl CacheFreshness {
+    /// The newest kakao.com request, or the last write to the file when no
+    /// request has a readable time.
+    pub fn last_updated(&self) -> Option<DateTime<Utc>> {
+        self.newest_request.or(self.modified)
+    }
+
+    /// Seconds since [`Self::last_updated`].
+    pub fn age_secs(&self, now: DateTime<Utc>) -> Option<i64> {
+        self.last_updated()
+            .map(|at| (now - at).num_seconds().max(0))
+    }
+
+    /// Older than `threshold_secs`, or of unknown age.
+    pub fn is_stale(&self, now: DateTime<Utc>, threshold_secs: i64) -> bool {
+        self.age_secs(now).is_none_or(|age| age > threshold_secs)
+    }
+}
+
+/// Freshness of this platform's `Cache.db`; `None` when there is none
+/// (other platforms, or KakaoTalk never used here).
+pub fn cache_freshness() -> Result<Option<CacheFreshness>> {
+    match credential_store_paths()
+        .into_iter()
+        .find(|path| path.is_file())
+    {
+        Some(cache_db) => cache_freshness_at(&cache_db).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Freshness of the `Cache.db` at `cache_db`, read from a copy so the
+/// running app's lock is never waited on.
+pub fn cache_freshness_at(cache_db: &Path) -> Result<CacheFreshness> {
+    let modified = [
+        cache_db.to_path_buf(),
+        PathBuf::from(format!("{}-wal", cache_db.display())),
+    ]
+    .iter()
+    .filter_map(|path| fs::metadata(path).ok()?.modified().ok())
+    .max()
+    .map(DateTime::<Utc>::from);
+
+    let temp_dir = tempdir().context("Failed to create temporary directory")?;
+    let tmp_db = temp_dir.path().join("Cache.db");
+    copy_with_timeout(cache_db, &tmp_db, 5)?;
+    copy_companion_file(cache_db, &tmp_db, "-wal")?;
+    copy_companion_file(cache_db, &tmp_db, "-shm")?;
+    let conn = Connection::open(&tmp_db)
+        .with_context(|| format!("Failed to open {}", tmp_db.display()))?;
+
+    let mut stmt = conn.prepare(
+        "SELECT time_stamp FROM cfurl_cache_response WHERE request_key LIKE '%kakao.com%'",
+    )?;
+    let mut rows = stmt.query([])?;
+    let mut newest_request = None;
+    while let Some(row) = rows.next()? {
+        newest_request = newest_request.max(cache_time(row.get_ref(0)?));
+    }
+    Ok(CacheFreshness {
+        modified,
+        newest_request,
+    })
+}
+
+/// A `time_stamp` value: SQLite's `CURRENT_TIMESTAMP` text (UTC), or a
+/// number in whichever epoch it was written (CFAbsoluteTime included).
+fn cache_time(value: ValueRef<'_>) -> Option<DateTime<Utc>> {
+    match value {
+        ValueRef::Integer(n) => normalize_timestamp(n as f64),
+        ValueRef::Real(n) => normalize_timestamp(n),
+        ValueRef::Text(text) => {
+            let text = std::str::from_utf8(text).ok()?.trim();
+            match NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S") {
+                Ok(at) => Some(at.and_utc()),
+                Err(_) => normalize_timestamp(text.parse().ok()?),
+            }
+        }
+        ValueRef::Null | ValueRef::Blob(_) => None,
+    }
+}
+
+/// Files larger than this are not request logs worth scanning.
+const MAX_HEADER_DUMP_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Scan the files under `dir` (the PC client's data directory) for
+/// recorded request headers. Unreadable files are skipped: the client
+/// keeps some of them locked while it runs.
+fn extract_candidates_from_header_dumps(dir: &Path) -> Vec<ExtractedCredential> {
+    let mut candidates = Vec::new();
+    let mut pending = vec![(dir.to_path_buf(), 0)];
+    while let Some((dir, depth)) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            if meta.is_dir() {
+                if depth < 4 {
+                    pending.push((path, depth + 1));
+                }
+                continue;
+            }
+            if meta.len() > MAX_HEADER_DUMP_BYTES {
+                continue;
+            }
+            let Ok(bytes) = fs::read(&path) else {
+                continue;
+            };
+            let timestamp = meta
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map_or(0.0, |d| d.as_secs_f64());
+            candidates.extend(candidates_from_header_text(
+                &String::from_utf8_lossy(&bytes),
+                timestamp,
+                &path.display().to_string(),
+            ));
+        }
+    }
+    candidates
+}
+
+/// Requests recorded as `Name: value` header lines, one blank-line
+/// separated block per request, with the request line (if any) first.
+/// Blocks carrying an `Authorization` header become candidates, read the
+/// same way as the Mac cache.
+fn candidates_from_header_text(
+    text: &str,
+    timestamp: f64,
+    source: &str,
+) -> Vec<ExtractedCredential> {
+    let text = text.replace("\r\n", "\n");
+    let mut candidates = Vec::new();
+    for block in text.split("\n\n") {
+        let mut request_line = None;
+        let mut header = std::collections::HashMap::new();
+        for line in block.lines() {
+            match line.split_once(':') {
+                Some((name, value)) if !name.is_empty() && !name.contains(char::is_whitespace) => {
+                    header.insert(name.to_ascii_lowercase(), value.trim().to_string());
+                }
+                _ if request_line.is_none() && !line.trim().is_empty() => {
+                    request_line = Some(line.trim());
+                }
+                _ => {}
+            }
+        }
+        let auth_token = match header.remove("authorization") {
+            Some(token) if !token.is_empty() => token,
+            _ => continue,
+        };
+        let user_id = header
+            .get("talk-user-id")
+            .and_then(|s| s.parse::<i64>().ok())
+            .unwrap_or(0);
+        let user_agent = header.remove("user-agent").unwrap_or_default();
+        let a_header = header.remove("a").unwrap_or_default();
+        let app_version = a_header.split('/').nth(1).unwrap_or("3.7.0").to_string();
+        let device_uuid = device_uuid_from_token(&auth_token);
+        let source_url = request_line.unwrap_or(source).to_string();
+
+        candidates.push(ExtractedCredential {
+            creds: KakaoCredentials::new(
+                auth_token,
+                user_id,
+                device_uuid,
+                app_version,
+                user_agent,
+                a_header,
+            ),
+            timestamp,
+            priority: url_priority(&source_url),
+            source_url,
+        });
+    }
+    candidates
+}
+
 fn url_priority(url: &str) -> u8 {
-    if url.contains("/mac/account/more_settings.json") {
+    // The Mac app calls `/mac/...`, the PC client `/win32/...`.
+    if url.contains("/account/more_settings.json") {
         3
-    } else if url.contains("/messaging/chats") || url.contains("/mac/profile3/me.json") {
+    } else if url.contains("/messaging/chats") || url.contains("/profile3/me.json") {
         2
     } else {
         1
@@ -239,19 +489,39 @@ pub fn extract_rest_token_from_cache_db() -> Result<Option<String>> {
 
         let plist = match PlistValue::from_reader(Cursor::new(request_object)) {
             Ok(v) => v,
-            Err(_) => continue,
+            Err(err) => {
+                tracing::debug!("rest token: rejected a row, request is not a plist ({err})");
+                continue;
+            }
         };
 
         let headers = match find_headers_map(&plist) {
             Some(h) => h,
-            None => continue,
+            None => {
+                tracing::debug!("rest token: rejected a row without a header map");
+                continue;
+            }
         };
 
         let auth_token = match value_as_string(headers.get("Authorization")) {
             Some(token) if token.len() > 100 => token,
-            _ => continue,
+            Some(token) => {
+                tracing::debug!(
+                    "rest token: rejected {}, too short for a bearer token",
+                    crate::trace::redact_secret(&token)
+                );
+                continue;
+            }
+            None => {
+                tracing::debug!("rest token: rejected a row without an Authorization header");
+                continue;
+            }
         };
 
+        tracing::debug!(
+            "rest token: using {}",
+            crate::trace::redact_secret(&auth_token)
+        );
         return Ok(Some(auth_token));
     }
 
@@ -259,7 +529,7 @@ pub fn extract_rest_token_from_cache_db() -> Result<Option<String>> {
 }
 
 /// Extract refresh_token from Cache.db by looking at renew_token.json POST body.
-/// The POST body is stored as <data> inside the request_object plist.
+/// The POST body is stored as `<data>` inside the `request_object` plist.
 pub fn extract_refresh_token() -> Result<Option<String>> {
     let home = dirs::home_dir().context("Could not resolve home directory")?;
     let cache_db = home
@@ -533,6 +803,48 @@ fn value_as_string(value: Option<&PlistValue>) -> Option<String> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_container_not_found_names_path() {
+        let err = OpenKakaoError::ContainerNotFound(PathBuf::from("/nope/com.kakao.KakaoTalkMac"));
+        assert_eq!(
+            err.to_string(),
+            "KakaoTalk container not found at /nope/com.kakao.KakaoTalkMac"
+        );
+    }
+
+    #[test]
+    fn header_dumps_yield_one_candidate_per_authorized_request() {
+        let text = "GET https://katalk.kakao.com/win32/account/more_settings.json\r\n\
+                    Authorization: tok-dev-1\r\n\
+                    talk-user-id: 42\r\n\
+                    A: win32/25.1.0/ko\r\n\
+                    User-Agent: KT/25.1.0 Wd/10.0 ko\r\n\r\n\
+                    GET https://katalk.kakao.com/win32/emoticons.json\r\n\
+                    Content-Type: application/json\r\n";
+        let found = candidates_from_header_text(text, 10.0, "log.txt");
+        assert_eq!(found.len(), 1);
+        let creds = &found[0].creds;
+        assert_eq!(creds.oauth_token, "tok-dev-1");
+        assert_eq!(creds.user_id, 42);
+        assert_eq!(creds.device_uuid, "dev-1");
+        assert_eq!(creds.app_version, "25.1.0");
+        assert_eq!(found[0].priority, 3);
+    }
+
+    #[test]
+    fn ranking_is_shared_across_sources_and_drops_repeated_tokens() {
+        let older =
+            candidates_from_header_text("Authorization: a-1\n\nAuthorization: b-1", 1.0, "x");
+        let newer = candidates_from_header_text("Authorization: a-1", 5.0, "y");
+        let ranked = rank_candidates(older.into_iter().chain(newer).collect());
+        let tokens: Vec<&str> = ranked
+            .iter()
+            .map(|c| c.creds.oauth_token.as_str())
+            .collect();
+        assert_eq!(tokens, ["a-1", "b-1"]);
+        assert_eq!(ranked[0].timestamp, 5.0);
+    }
+
     #[test]
     fn test_url_priority_more_settings() {
         assert_eq!(
@@ -615,4 +927,55 @@ mod tests {
         assert_eq!(params.device_name, "KakaoTalk");
         assert_eq!(params.x_vc, "test-xvc");
     }
+
+    #[test]
+    fn cache_freshness_reads_the_newest_kakao_request() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_db = dir.path().join("Cache.db");
+        let conn = Connection::open(&cache_db).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE cfurl_cache_response (
+                 entry_ID INTEGER PRIMARY KEY, request_key TEXT, time_stamp
+             );",
+        )
+        .unwrap();
+        let cf_2023_11_14 = (1_700_000_000 - crate::timestamp::CF_ABSOLUTE_EPOCH_OFFSET) as f64;
+        let rows: [(&str, rusqlite::types::Value); 4] = [
+            ("https://katalk.kakao.com/mac/chats", cf_2023_11_14.into()),
+            (
+                "https://talk-pilsner.kakao.com/messaging/chats",
+                "2023-11-13 08:00:00".to_string().into(),
+            ),
+            // Newer, but not a kakao.com request.
+            ("https://example.com/", 1_800_000_000_i64.into()),
+            (
+                "https://katalk.kakao.com/mac/profile",
+                rusqlite::types::Value::Null,
+            ),
+        ];
+        for (key, stamp) in rows {
+            conn.execute(
+                "INSERT INTO cfurl_cache_response (request_key, time_stamp) VALUES (?1, ?2)",
+                rusqlite::params![key, stamp],
+            )
+            .unwrap();
+        }
+        drop(conn);
+
+        let freshness = cache_freshness_at(&cache_db).unwrap();
+        let newest = freshness.newest_request.unwrap();
+        assert_eq!(newest.to_rfc3339(), "2023-11-14T22:13:20+00:00");
+        assert!(freshness.modified.is_some());
+
+        let later = newest + chrono::Duration::hours(26);
+        assert_eq!(freshness.age_secs(later), Some(26 * 3600));
+        assert!(freshness.is_stale(later, CACHE_STALE_AFTER_SECS));
+        assert!(!freshness.is_stale(newest, CACHE_STALE_AFTER_SECS));
+        // Neither a request time nor a file time: assume the worst.
+        let unknown = CacheFreshness {
+            modified: None,
+            newest_request: None,
+        };
+        assert!(unknown.is_stale(later, CACHE_STALE_AFTER_SECS));
+    }
 }
@@ -1,25 +1,127 @@
+use std::collections::HashMap;
 use std::process::Command;
-use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock, RwLock};
 
 use anyhow::{anyhow, Result};
 use bson::Document;
 use serde_json::Value;
+use sha2::{Digest, Sha256};
 use tokio::task;
 
+use crate::account_conflict::{self, AccountChoice};
 use crate::auth::{
-    extract_login_params, extract_refresh_token, get_credential_candidates,
-    get_credentials_interactive,
+    credential_store_paths, device_uuid_from_token, extract_login_params, extract_refresh_token,
+    get_credential_candidates,
 };
 use crate::config::AuthConfig;
+use crate::credential_provider::{CredentialProvider, ProviderChain};
+use crate::credential_watch::{fingerprint, CredentialWatch, Reload};
 use crate::credentials::{load_credentials, save_credentials};
+use crate::error::OpenKakaoError;
 use crate::loco::client::LocoClient;
-use crate::model::KakaoCredentials;
-use crate::rest::KakaoRestClient;
+use crate::model::{CredentialGap, KakaoCredentials};
+use crate::prompt;
+use crate::rest::{KakaoRestClient, TokenScope, TokenStatus};
 use crate::state::{
     auth_cooldown_remaining_secs, enter_auth_cooldown, mark_relogin_attempt, mark_renew_attempt,
     record_failure, record_success, recovery_state_summary, relogin_cooldown_remaining_secs_with,
     renew_cooldown_remaining_secs,
 };
+use crate::verify_cache::VerifiedTokens;
+use crate::warnings::{warn, WarningCode};
+
+/// The KakaoTalk credential stores looked at, for messages that explain
+/// why nothing was extracted.
+fn checked_stores() -> String {
+    let paths = credential_store_paths();
+    if paths.is_empty() {
+        return "no KakaoTalk data location for this platform".to_string();
+    }
+    paths
+        .iter()
+        .map(|path| {
+            let state = if path.exists() { "" } else { " (missing)" };
+            format!("{}{}", path.display(), state)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+pub fn get_credentials_interactive() -> Result<KakaoCredentials> {
+    if let Some(reason) = prompt::non_interactive_reason() {
+        let reason = format!("{reason} (checked {})", checked_stores());
+        return Err(OpenKakaoError::NoCredentials { reason }.into());
+    }
+
+    eprintln!(
+        "Could not auto-extract KakaoTalk credentials (checked {}).",
+        checked_stores()
+    );
+    eprintln!("Please provide credentials manually.");
+
+    let oauth_token = prompt::line("OAuth Token (Authorization header value): ")?;
+    let user_id_raw = prompt::line("User ID (numeric, from talk-user-id header): ")?;
+
+    let user_id = user_id_raw.trim().parse::<i64>().unwrap_or(0);
+    let device_uuid = device_uuid_from_token(&oauth_token);
+
+    Ok(KakaoCredentials::new(
+        oauth_token,
+        user_id,
+        device_uuid,
+        "3.7.0".to_string(),
+        String::new(),
+        String::new(),
+    ))
+}
+
+pub const TOKEN_ENV: &str = "OPENKAKAO_TOKEN";
+pub const USER_ID_ENV: &str = "OPENKAKAO_USER_ID";
+pub const A_HEADER_ENV: &str = "OPENKAKAO_A_HEADER";
+pub const USER_AGENT_ENV: &str = "OPENKAKAO_USER_AGENT";
+
+/// Credentials from `OPENKAKAO_TOKEN` and friends, for hosts with neither
+/// the KakaoTalk cache nor a saved credentials file.
+pub fn credentials_from_env() -> Option<KakaoCredentials> {
+    credentials_from_vars(|key| std::env::var(key).ok())
+}
+
+fn credentials_from_vars(var: impl Fn(&str) -> Option<String>) -> Option<KakaoCredentials> {
+    let non_empty = |key: &str| {
+        var(key)
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+
+    let oauth_token = non_empty(TOKEN_ENV)?;
+    let user_id = match non_empty(USER_ID_ENV) {
+        Some(raw) => raw.parse::<i64>().unwrap_or_else(|_| {
+            warn(
+                WarningCode::InvalidEnvironment,
+                format!("Ignoring non-numeric {USER_ID_ENV}={raw}; using user_id=0."),
+            );
+            0
+        }),
+        None => {
+            warn(
+                WarningCode::InvalidEnvironment,
+                format!("{TOKEN_ENV} is set without {USER_ID_ENV}; commands that need your user id may fail."),
+            );
+            0
+        }
+    };
+    let device_uuid = device_uuid_from_token(&oauth_token);
+
+    Some(KakaoCredentials::new(
+        oauth_token,
+        user_id,
+        device_uuid,
+        "3.7.0".to_string(),
+        non_empty(USER_AGENT_ENV).unwrap_or_default(),
+        non_empty(A_HEADER_ENV).unwrap_or_default(),
+    ))
+}
 
 static AUTH_POLICY: OnceLock<AuthPolicy> = OnceLock::new();
 
@@ -99,8 +201,310 @@ pub(crate) enum RecoveryAttempt {
     },
 }
 
+/// Where [`resolve_base_credentials`] will take credentials from.
+pub fn credential_source() -> &'static str {
+    if crate::credentials::explicit_profile().is_some() {
+        "saved profile"
+    } else if std::env::var(TOKEN_ENV).is_ok_and(|v| !v.trim().is_empty()) {
+        "environment"
+    } else if crate::credentials::credentials_path().is_ok_and(|p| p.exists()) {
+        "saved file"
+    } else {
+        "KakaoTalk cache"
+    }
+}
+
 pub fn resolve_base_credentials() -> Result<KakaoCredentials> {
-    if let Some(mut saved) = load_credentials()? {
+    if let Some(pinned) = pinned_credentials() {
+        return Ok(pinned);
+    }
+    resolve_chain_credentials()
+}
+
+fn resolve_chain_credentials() -> Result<KakaoCredentials> {
+    let creds = settle_saved_account(credential_chain().resolve()?)?;
+    Ok(complete_partial_credentials(creds))
+}
+
+/// This run's answer for a saved token: `Some` KakaoTalk credentials when
+/// those were chosen over it.
+type SettledAccount = (String, Option<KakaoCredentials>);
+
+static ACCOUNT_SETTLED: Mutex<Option<SettledAccount>> = Mutex::new(None);
+
+/// `creds` from the saved file, unless KakaoTalk is logged in as another
+/// account and [`account_conflict::settle`] picks that one instead. Settled
+/// once per run.
+fn settle_saved_account(creds: KakaoCredentials) -> Result<KakaoCredentials> {
+    if credential_source() != "saved file"
+        || !credential_store_paths().iter().any(|path| path.exists())
+    {
+        return Ok(creds);
+    }
+    let mut settled = ACCOUNT_SETTLED.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((token, replacement)) = settled.as_ref() {
+        if *token == creds.oauth_token {
+            return Ok(replacement.clone().unwrap_or(creds));
+        }
+    }
+    // The candidate the chain would fall back to; when the chain already
+    // did, this is `creds` itself and nothing conflicts.
+    let extracted = get_credential_candidates(8)
+        .unwrap_or_else(|err| {
+            tracing::debug!("KakaoTalk credentials not extracted for the account check: {err:#}");
+            Vec::new()
+        })
+        .into_iter()
+        .find(|c| c.user_id != 0 && !credential_chain().is_rejected(&c.oauth_token));
+    let mut replacement = None;
+    if let Some(extracted) = extracted {
+        if let Some(conflict) = account_conflict::detect(&creds, &extracted) {
+            if account_conflict::settle(conflict, &creds, &extracted)? == AccountChoice::Extracted {
+                replacement = Some(extracted);
+            }
+        }
+    }
+    *settled = Some((creds.oauth_token.clone(), replacement.clone()));
+    Ok(replacement.unwrap_or(creds))
+}
+
+/// Narrow KakaoTalk candidates replacing rejected `creds` to the same
+/// account when it is among them, so recovery does not switch accounts
+/// just because another account's token verifies.
+fn prefer_same_account(creds: &KakaoCredentials, fresh: &mut Vec<KakaoCredentials>) {
+    if creds.user_id != 0 && fresh.iter().any(|c| c.user_id == creds.user_id) {
+        fresh.retain(|c| c.user_id == creds.user_id);
+    }
+}
+
+/// Refuse to replace rejected `creds` with `replacement` for another
+/// account unless [`account_conflict::settle`] chooses it.
+fn confirm_account_switch(creds: &KakaoCredentials, replacement: &KakaoCredentials) -> Result<()> {
+    let Some(conflict) = account_conflict::detect(creds, replacement) else {
+        return Ok(());
+    };
+    match account_conflict::settle(conflict, creds, replacement)? {
+        AccountChoice::Extracted => Ok(()),
+        AccountChoice::Saved => Err(anyhow!(
+            "The token for user {} was rejected and KakaoTalk is logged in as user {}; log KakaoTalk in as user {} again, or pass --prefer-extracted to switch accounts.",
+            conflict.saved_user_id,
+            conflict.extracted_user_id,
+            conflict.saved_user_id
+        )),
+    }
+}
+
+/// Credentials a long-running command settled on, answered by
+/// [`resolve_base_credentials`] instead of the chain so a saved token that
+/// fails verification is not picked up by the next task.
+static PINNED: RwLock<Option<KakaoCredentials>> = RwLock::new(None);
+
+/// Use `creds` for the rest of this run, until the server rejects them.
+pub fn pin_credentials(creds: KakaoCredentials) {
+    *PINNED.write().unwrap_or_else(|e| e.into_inner()) = Some(creds);
+}
+
+fn pinned_credentials() -> Option<KakaoCredentials> {
+    let pinned = PINNED.read().unwrap_or_else(|e| e.into_inner()).clone()?;
+    (!credential_chain().is_rejected(&pinned.oauth_token)).then_some(pinned)
+}
+
+/// Watch the active profile's credentials file for a token saved by another
+/// run (`auth refresh`, the daemon's renew task). Credentials kept in the
+/// keychain are not watched.
+pub fn watch_credentials(current: KakaoCredentials) -> Result<CredentialWatch> {
+    Ok(CredentialWatch::new(
+        current,
+        [crate::credentials::credentials_path()?],
+    ))
+}
+
+/// [`CredentialWatch::poll`] against the credential sources, verifying a new
+/// token with a client of its own, and say on stderr what happened under
+/// `label`. The caller swaps in [`Reload::Rotated`] credentials.
+pub fn reload_credentials(watch: &mut CredentialWatch, label: &str) -> Option<Reload> {
+    let reload = watch.poll(
+        resolve_chain_credentials,
+        |creds| match KakaoRestClient::new(creds.clone()) {
+            Ok(client) => client
+                .with_retry_policy(crate::util::retry_policy())
+                .check_token(),
+            Err(err) => TokenStatus::NetworkError {
+                message: err.to_string(),
+            },
+        },
+    )?;
+    match &reload {
+        Reload::Unchanged => {}
+        Reload::Rotated { previous, current } => {
+            pin_credentials(KakaoCredentials::clone(current));
+            eprintln!(
+                "[{label}] Credentials changed on disk; now using token {} (was {}).",
+                fingerprint(current),
+                fingerprint(previous)
+            );
+        }
+        Reload::Rejected { reason } => warn(
+            WarningCode::StaleToken,
+            format!("reloaded credentials not used ({reason}); keeping the current token"),
+        ),
+        Reload::Deferred { reason } => eprintln!(
+            "[{label}] Credentials changed on disk but could not be verified ({reason}); retrying next cycle."
+        ),
+    }
+    Some(reload)
+}
+
+static SAVE_BACKFILL: AtomicBool = AtomicBool::new(false);
+
+/// Set from `--save-backfill`: also save a user id fetched for credentials
+/// that had none.
+pub fn set_save_backfill(save: bool) {
+    SAVE_BACKFILL.store(save, Ordering::Relaxed);
+}
+
+/// User ids settled for incomplete credentials this run, by token hash
+/// (0 when the fetch failed), so each token is filled in and reported once.
+static PARTIAL_HANDLED: OnceLock<Mutex<HashMap<String, i64>>> = OnceLock::new();
+
+/// [`fill_credential_gaps`] once per token per run, raising its warnings
+/// and, with `--save-backfill`, saving the fetched user id.
+fn complete_partial_credentials(mut creds: KakaoCredentials) -> KakaoCredentials {
+    if creds.gaps().is_empty() {
+        return creds;
+    }
+    let key = hex::encode(Sha256::digest(creds.oauth_token.as_bytes()));
+    // Held across the fetch so concurrent lookups wait for it instead of
+    // repeating it.
+    let mut handled = PARTIAL_HANDLED
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    if let Some(&user_id) = handled.get(&key) {
+        if creds.user_id == 0 {
+            creds.user_id = user_id;
+        }
+        return creds;
+    }
+    let had_user_id = creds.user_id != 0;
+    let notes = fill_credential_gaps(&mut creds, |creds| {
+        Ok(KakaoRestClient::new(creds.clone())?
+            .with_retry_policy(crate::util::retry_policy())
+            .backfill_user_id()?)
+    });
+    for note in notes {
+        warn(WarningCode::PartialCredentials, note);
+    }
+    let backfilled = !had_user_id && creds.user_id != 0;
+    if backfilled {
+        eprintln!(
+            "[auth] Credentials had no user id; using {} from profile3/me.",
+            creds.user_id
+        );
+    }
+    if backfilled && SAVE_BACKFILL.load(Ordering::Relaxed) {
+        match save_credentials(&creds) {
+            Ok(location) => eprintln!("[auth] Saved the user id to {location}."),
+            Err(err) => warn(
+                WarningCode::StateSaveFailed,
+                format!("Could not save the fetched user id: {err:#}"),
+            ),
+        }
+    }
+    handled.insert(key, creds.user_id);
+    creds
+}
+
+/// The degradation matrix for credentials typed in by hand or taken from
+/// the environment: a missing user id is fetched with `fetch_user_id`;
+/// a missing `A` header is derived (by the REST client) and reported as
+/// approximate; a missing device UUID is reported with the features that
+/// refuse to run without it. Returns the warnings to raise.
+fn fill_credential_gaps(
+    creds: &mut KakaoCredentials,
+    fetch_user_id: impl FnOnce(&KakaoCredentials) -> Result<i64>,
+) -> Vec<String> {
+    let mut notes = Vec::new();
+    let gaps = creds.gaps();
+    if gaps.contains(&CredentialGap::UserId) {
+        match fetch_user_id(creds) {
+            Ok(user_id) if user_id != 0 => creds.user_id = user_id,
+            Ok(_) => notes.push(
+                "Credentials have no user_id and profile3/me did not name one; your own messages and membership are not recognized".to_string(),
+            ),
+            Err(err) => notes.push(format!(
+                "Credentials have no user_id and fetching it from profile3/me failed ({err:#}); your own messages and membership are not recognized"
+            )),
+        }
+    }
+    for gap in gaps {
+        match gap {
+            CredentialGap::UserId => {}
+            CredentialGap::AHeader => notes.push(format!(
+                "Credentials have no A header; sending mac/{}/ko derived from app version {}, so requests are approximate (set OPENKAKAO_A_HEADER or run 'login --save')",
+                creds.app_version, creds.app_version
+            )),
+            CredentialGap::DeviceUuid => notes.push(format!(
+                "Credentials have no {}: {}",
+                gap.label(),
+                gap.consequence()
+            )),
+        }
+    }
+    notes
+}
+
+static CREDENTIAL_CHAIN: OnceLock<ProviderChain> = OnceLock::new();
+
+/// Sources [`resolve_base_credentials`] asks, set up on first use.
+pub fn credential_chain() -> &'static ProviderChain {
+    CREDENTIAL_CHAIN.get_or_init(default_credential_chain)
+}
+
+/// The CLI's sources: `OPENKAKAO_RS_TOKEN`, the saved credentials file,
+/// the newest KakaoTalk cache candidate, then an interactive prompt. With
+/// `--profile` only that profile's saved file counts.
+pub fn default_credential_chain() -> ProviderChain {
+    if crate::credentials::explicit_profile().is_some() {
+        return ProviderChain::new().with(SavedFileProvider);
+    }
+    ProviderChain::new()
+        .with(EnvironmentProvider)
+        .with(SavedFileProvider)
+        .with(KakaoCacheProvider)
+        .with(InteractiveProvider)
+}
+
+pub struct EnvironmentProvider;
+
+impl CredentialProvider for EnvironmentProvider {
+    fn name(&self) -> &str {
+        "environment"
+    }
+
+    fn provide(&self) -> Result<Vec<KakaoCredentials>> {
+        Ok(credentials_from_env().into_iter().collect())
+    }
+}
+
+pub struct SavedFileProvider;
+
+impl CredentialProvider for SavedFileProvider {
+    fn name(&self) -> &str {
+        "saved file"
+    }
+
+    fn provide(&self) -> Result<Vec<KakaoCredentials>> {
+        let Some(mut saved) = load_credentials()? else {
+            // An explicitly chosen profile never falls through to whichever
+            // account KakaoTalk happens to be logged in as.
+            if let Some(profile) = crate::credentials::explicit_profile() {
+                return Err(anyhow!(
+                    "No saved credentials for profile '{profile}'. Run: openkakao-rs --profile {profile} login --save"
+                ));
+            }
+            return Ok(Vec::new());
+        };
         // Best-effort: populate rest_token from Cache.db if not already set
         if saved.rest_token.is_none() {
             match crate::auth::extract_rest_token_from_cache_db() {
@@ -110,22 +514,44 @@ pub fn resolve_base_credentials() -> Result<KakaoCredentials> {
                     let _ = save_credentials(&saved);
                 }
                 Ok(None) => {}
-                Err(e) => {
-                    if std::env::var("OPENKAKAO_RS_DEBUG").is_ok() {
-                        eprintln!("[auth] Cache.db rest_token extraction failed: {}", e);
-                    }
-                }
+                Err(e) => tracing::debug!("Cache.db rest_token extraction failed: {e:#}"),
             }
         }
-        return Ok(saved);
+        Ok(vec![saved])
     }
+}
 
-    let candidates = get_credential_candidates(8)?;
-    if !candidates.is_empty() {
-        return select_best_credential(candidates);
+pub struct KakaoCacheProvider;
+
+impl CredentialProvider for KakaoCacheProvider {
+    fn name(&self) -> &str {
+        "KakaoTalk cache"
+    }
+
+    fn provide(&self) -> Result<Vec<KakaoCredentials>> {
+        if !credential_store_paths().iter().any(|path| path.exists()) {
+            eprintln!(
+                "[auth] No KakaoTalk credential store at {}",
+                checked_stores()
+            );
+            return Ok(Vec::new());
+        }
+        // Candidates come newest first. The chain takes the top one
+        // unverified; REST and LOCO both recover if the server rejects it.
+        get_credential_candidates(8)
+    }
+}
+
+pub struct InteractiveProvider;
+
+impl CredentialProvider for InteractiveProvider {
+    fn name(&self) -> &str {
+        "interactive prompt"
     }
 
-    get_credentials_interactive()
+    fn provide(&self) -> Result<Vec<KakaoCredentials>> {
+        get_credentials_interactive().map(|creds| vec![creds])
+    }
 }
 
 /// Attempt to refresh the REST bearer token from Cache.db.
@@ -141,7 +567,10 @@ pub fn refresh_rest_token(creds: &mut KakaoCredentials) -> Result<bool> {
         }
         Ok(None) => Ok(false),
         Err(e) => {
-            eprintln!("[auth] Cache.db rest_token refresh failed: {}", e);
+            warn(
+                WarningCode::RestTokenRefresh,
+                format!("Cache.db rest_token refresh failed: {e}"),
+            );
             Ok(false)
         }
     }
@@ -155,6 +584,19 @@ pub fn get_auth_policy() -> AuthPolicy {
     AUTH_POLICY.get().cloned().unwrap_or_default()
 }
 
+/// Pause before re-checking a candidate whose check hit a network error.
+const CHECK_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
+
+static VERIFY_TOKENS: AtomicBool = AtomicBool::new(true);
+
+/// Set from `--no-verify`: take the newest candidate without checking it.
+pub fn set_verify_tokens(verify: bool) {
+    VERIFY_TOKENS.store(verify, Ordering::Relaxed);
+}
+
+/// Pick the credential to use from `candidates` (newest first). A token the
+/// server accepted within the verification cache TTL is trusted as is;
+/// otherwise the remaining candidates are checked concurrently.
 pub fn select_best_credential(candidates: Vec<KakaoCredentials>) -> Result<KakaoCredentials> {
     let mut unique = Vec::new();
     let mut seen = std::collections::HashSet::new();
@@ -163,62 +605,238 @@ pub fn select_best_credential(candidates: Vec<KakaoCredentials>) -> Result<Kakao
             unique.push(c);
         }
     }
+    if unique.is_empty() {
+        return Err(anyhow!("No credentials candidate"));
+    }
+    if !VERIFY_TOKENS.load(Ordering::Relaxed) {
+        return Ok(unique.swap_remove(0));
+    }
 
-    let first = unique
-        .first()
-        .cloned()
-        .ok_or_else(|| anyhow!("No credentials candidate"))?;
+    let ttl = crate::verify_cache::ttl_secs();
+    let now = chrono::Utc::now().timestamp();
+    let mut cache = VerifiedTokens::load();
+    let cached: Vec<Option<TokenScope>> = unique
+        .iter()
+        .map(|creds| {
+            cache
+                .fresh(&creds.oauth_token, now, ttl)
+                .map(|entry| entry.scope())
+        })
+        .collect();
+    if let Some(i) = cached
+        .iter()
+        .position(|scope| scope.as_ref().is_some_and(TokenScope::is_complete))
+    {
+        return Ok(unique.swap_remove(i));
+    }
 
-    for creds in unique {
-        let client = match KakaoRestClient::new(creds.clone()) {
-            Ok(client) => client,
-            Err(_) => continue,
-        };
+    let results: Vec<(TokenStatus, TokenScope)> = std::thread::scope(|scope| {
+        let checks: Vec<_> = unique
+            .iter()
+            .zip(&cached)
+            .map(|(creds, cached)| {
+                scope.spawn(move || match cached {
+                    Some(known) => (TokenStatus::Valid, *known),
+                    None => check_credential(creds),
+                })
+            })
+            .collect();
+        checks
+            .into_iter()
+            .map(|check| {
+                check.join().unwrap_or_else(|_| {
+                    (
+                        TokenStatus::NetworkError {
+                            message: "token check failed".into(),
+                        },
+                        TokenScope::default(),
+                    )
+                })
+            })
+            .collect()
+    });
 
-        match client.verify_token() {
-            Ok(true) => return Ok(creds),
-            Ok(false) => continue,
-            Err(_) => continue,
+    for (creds, (status, scope)) in unique.iter().zip(&results) {
+        match status {
+            TokenStatus::Valid => cache.record(&creds.oauth_token, scope, now, ttl),
+            TokenStatus::Invalid { .. } => {
+                cache.forget(&creds.oauth_token);
+            }
+            _ => {}
+        }
+    }
+    if ttl > 0 {
+        if let Err(err) = cache.save() {
+            warn(
+                WarningCode::CacheUnavailable,
+                format!("Could not update the token verification cache: {err:#}"),
+            );
         }
     }
 
-    eprintln!("[auth] No valid token candidate found; using newest cached token.");
-    Ok(first)
+    let choice = choose_credential((0..unique.len()).collect(), |&i| results[i].clone())
+        .map(|i| unique[i].clone());
+    match choice {
+        CredentialChoice::Verified(creds) => Ok(creds),
+        CredentialChoice::Unverified(creds, status) => {
+            warn(
+                WarningCode::StaleToken,
+                format!(
+                    "Token check inconclusive ({}); using the newest token not known to be rejected.",
+                    status.reason()
+                ),
+            );
+            Ok(creds)
+        }
+        CredentialChoice::Fallback(creds) => {
+            warn(
+                WarningCode::StaleToken,
+                "No valid token candidate found; using newest cached token.",
+            );
+            Ok(creds)
+        }
+    }
 }
 
-pub fn get_rest_ready_client() -> Result<KakaoRestClient> {
-    let creds = resolve_base_credentials()?;
-    let stable = stabilize_rest_credentials(creds)?;
-    KakaoRestClient::new(stable)
+/// Ask the server whether `creds` is accepted, retrying once after a
+/// network error, and which endpoint families take it.
+fn check_credential(creds: &KakaoCredentials) -> (TokenStatus, TokenScope) {
+    let Ok(client) = KakaoRestClient::new(creds.clone()) else {
+        return (
+            TokenStatus::NetworkError {
+                message: "could not build an HTTP client".into(),
+            },
+            TokenScope::default(),
+        );
+    };
+    let mut status = client.check_token();
+    if matches!(status, TokenStatus::NetworkError { .. }) {
+        std::thread::sleep(CHECK_RETRY_DELAY);
+        status = client.check_token();
+    }
+    let scope = if status == TokenStatus::Valid {
+        client.probe_scope()
+    } else {
+        client.known_scope()
+    };
+    (status, scope)
 }
 
-pub fn stabilize_rest_credentials(creds: KakaoCredentials) -> Result<KakaoCredentials> {
-    let policy = get_auth_policy();
-    let client = KakaoRestClient::new(creds.clone())?;
+/// Outcome of [`choose_credential`].
+#[derive(Debug, PartialEq)]
+enum CredentialChoice<T> {
+    /// Katalk accepted it (pilsner too, if any candidate had both).
+    Verified(T),
+    /// Checking was cut short by rate limiting or the network; this is the
+    /// newest candidate the server has not rejected.
+    Unverified(T, TokenStatus),
+    /// Every candidate was rejected; the newest one.
+    Fallback(T),
+}
 
-    match client.verify_token() {
-        Ok(true) => {
-            record_success("rest", Some("saved credentials"))?;
-            eprintln!("[auth/rest] State: {}", recovery_state_summary()?);
-            return Ok(creds);
+impl<T> CredentialChoice<T> {
+    fn map<U>(self, f: impl FnOnce(T) -> U) -> CredentialChoice<U> {
+        match self {
+            CredentialChoice::Verified(t) => CredentialChoice::Verified(f(t)),
+            CredentialChoice::Unverified(t, status) => CredentialChoice::Unverified(f(t), status),
+            CredentialChoice::Fallback(t) => CredentialChoice::Fallback(f(t)),
         }
-        Ok(false) => {
-            record_failure("auth_expired")?;
-            if let Some(remaining) = auth_cooldown_remaining_secs()? {
-                eprintln!("[auth/rest] State: {}", recovery_state_summary()?);
-                anyhow::bail!(
-                    "REST auth recovery cooling down for {}s; retry later or relogin manually",
-                    remaining
-                );
+    }
+}
+
+/// Pick a credential from `candidates` (newest first). Prefer one both
+/// katalk and pilsner accept, then one katalk accepts. Only a real rejection
+/// rules a candidate out: a rate-limited or unreachable check keeps that
+/// candidate in play, ahead of anything older.
+fn choose_credential<T: Clone>(
+    candidates: Vec<T>,
+    mut check: impl FnMut(&T) -> (TokenStatus, TokenScope),
+) -> CredentialChoice<T> {
+    let first = candidates[0].clone();
+    let mut katalk_only = None;
+    for creds in candidates {
+        let (status, scope) = check(&creds);
+        match status {
+            TokenStatus::Valid if scope.is_complete() => return CredentialChoice::Verified(creds),
+            TokenStatus::Valid => {
+                katalk_only.get_or_insert(creds);
+            }
+            TokenStatus::Invalid { .. } => {}
+            inconclusive @ (TokenStatus::RateLimited | TokenStatus::NetworkError { .. }) => {
+                return match katalk_only {
+                    Some(creds) => CredentialChoice::Verified(creds),
+                    None => CredentialChoice::Unverified(creds, inconclusive),
+                };
             }
-            eprintln!(
-                "[auth/rest] Token invalid. Recovery order: {}",
-                Transport::Rest.recovery_order(&policy).join(" -> ")
-            );
         }
-        Err(_) => return Ok(creds),
     }
+    match katalk_only {
+        Some(creds) => CredentialChoice::Verified(creds),
+        None => CredentialChoice::Fallback(first),
+    }
+}
+
+/// REST client on the resolved credentials without checking them first;
+/// the first request doubles as the check and recovery runs only if it is
+/// rejected (see [`KakaoRestClient::with_reauth`]).
+pub fn get_rest_ready_client() -> Result<KakaoRestClient> {
+    let creds = resolve_base_credentials()?;
+    Ok(KakaoRestClient::new(creds.clone())?
+        .with_retry_policy(crate::util::retry_policy())
+        .with_reauth(Box::new(RestReauth { creds })))
+}
+
+struct RestReauth {
+    creds: KakaoCredentials,
+}
+
+impl crate::rest::Reauth for RestReauth {
+    fn confirmed(&self) {
+        let _ = record_success("rest", Some("saved credentials"));
+    }
+
+    fn recover(self: Box<Self>) -> Result<KakaoCredentials> {
+        reject_token(&self.creds.oauth_token);
+        begin_rest_recovery()?;
+        recover_rest_credentials(self.creds)
+    }
+}
+
+/// Tell the credential sources the server rejected `oauth_token`, so
+/// later lookups in this run skip it.
+fn reject_token(oauth_token: &str) {
+    crate::verify_cache::forget_token(oauth_token);
+    if let Err(err) = credential_chain().invalidate(oauth_token) {
+        warn(
+            WarningCode::PartialData,
+            format!("credential source not updated after token rejection: {err:#}"),
+        );
+    }
+}
+
+/// Record the rejected token and refuse while auth recovery is cooling down.
+fn begin_rest_recovery() -> Result<()> {
+    record_failure("auth_expired")?;
+    if let Some(remaining) = auth_cooldown_remaining_secs()? {
+        eprintln!("[auth/rest] State: {}", recovery_state_summary()?);
+        anyhow::bail!(
+            "REST auth recovery cooling down for {}s; retry later or relogin manually",
+            remaining
+        );
+    }
+    eprintln!(
+        "[auth/rest] Token invalid. Recovery order: {}",
+        Transport::Rest
+            .recovery_order(&get_auth_policy())
+            .join(" -> ")
+    );
+    Ok(())
+}
 
+/// Replacement for rejected `creds`: the configured recovery steps, then
+/// the verified best Cache.db candidate.
+fn recover_rest_credentials(creds: KakaoCredentials) -> Result<KakaoCredentials> {
+    let policy = get_auth_policy();
     for step in recovery_steps(&policy) {
         match run_recovery_step_sync(step, &creds)? {
             RecoveryAttempt::Unavailable { source, reason } => {
@@ -241,9 +859,12 @@ pub fn stabilize_rest_credentials(creds: KakaoCredentials) -> Result<KakaoCreden
         }
     }
 
-    let fresh = get_credential_candidates(8)?;
+    let mut fresh = get_credential_candidates(8)?;
+    fresh.retain(|c| !credential_chain().is_rejected(&c.oauth_token));
+    prefer_same_account(&creds, &mut fresh);
     if !fresh.is_empty() {
         let new_creds = select_best_credential(fresh)?;
+        confirm_account_switch(&creds, &new_creds)?;
         save_credentials(&new_creds)?;
         eprintln!("[auth/rest] Recovered via Cache.db extraction.");
         record_success("rest", Some("Cache.db extraction"))?;
@@ -252,6 +873,7 @@ pub fn stabilize_rest_credentials(creds: KakaoCredentials) -> Result<KakaoCreden
     }
 
     record_failure("auth_recovery_exhausted")?;
+    crate::health::observe_tokens_invalidated("rest");
     let cooldown = enter_auth_cooldown()?;
     eprintln!("[auth/rest] State: {}", recovery_state_summary()?);
     anyhow::bail!(
@@ -586,7 +1208,8 @@ pub(crate) fn attempt_renew(creds: &KakaoCredentials) -> Result<RecoveryAttempt>
 
 pub async fn connect_loco_with_reauth(client: &mut LocoClient) -> Result<Document> {
     let policy = get_auth_policy();
-    let login_data = client.full_connect_with_retry(3).await?;
+    client.retry_policy = crate::util::retry_policy();
+    let login_data = client.full_connect_with_retry().await?;
     let status = login_status(&login_data);
 
     if status == 0 {
@@ -596,9 +1219,11 @@ pub async fn connect_loco_with_reauth(client: &mut LocoClient) -> Result<Documen
     }
 
     if status != -950 {
+        crate::health::observe_status("LOGINLIST", status);
         anyhow::bail!("LOCO login failed (status={})", status);
     }
 
+    reject_token(&client.credentials.oauth_token);
     record_failure("auth_expired")?;
 
     if let Some(remaining) = auth_cooldown_remaining_secs()? {
@@ -629,13 +1254,20 @@ pub async fn connect_loco_with_reauth(client: &mut LocoClient) -> Result<Documen
         }
     }
 
-    let fresh = get_credential_candidates_async(8).await?;
+    let mut fresh = get_credential_candidates_async(8).await?;
+    fresh.retain(|c| !credential_chain().is_rejected(&c.oauth_token));
+    prefer_same_account(&client.credentials, &mut fresh);
     if !fresh.is_empty() {
         let new_creds = select_best_credential_async(fresh).await?;
+        let (rejected, replacement) = (client.credentials.clone(), new_creds.clone());
+        task::spawn_blocking(move || confirm_account_switch(&rejected, &replacement))
+            .await
+            .map_err(|err| anyhow!("account check task join failed: {}", err))??;
         return reconnect_loco_with_credentials(client, new_creds, "Cache.db extraction").await;
     }
 
     record_failure("auth_recovery_exhausted")?;
+    crate::health::observe_tokens_invalidated("loco");
     let cooldown = enter_auth_cooldown()?;
     eprintln!("[auth/loco] State: {}", recovery_state_summary()?);
     anyhow::bail!(
@@ -824,7 +1456,8 @@ async fn reconnect_loco_with_credentials(
     client.credentials = new_creds;
     client.disconnect();
 
-    let login_data = client.full_connect_with_retry(3).await?;
+    client.retry_policy = crate::util::retry_policy();
+    let login_data = client.full_connect_with_retry().await?;
     let status = login_status(&login_data);
     if status != 0 {
         record_failure("auth_relogin_needed")?;
@@ -845,6 +1478,225 @@ async fn reconnect_loco_with_credentials(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_credentials_from_vars() {
+        let vars = |key: &str| match key {
+            "OPENKAKAO_TOKEN" => Some("abc123-device-uuid".to_string()),
+            "OPENKAKAO_USER_ID" => Some("42".to_string()),
+            "OPENKAKAO_A_HEADER" => Some("mac/3.7.0/ko".to_string()),
+            _ => None,
+        };
+        let creds = credentials_from_vars(vars).unwrap();
+        assert_eq!(creds.oauth_token, "abc123-device-uuid");
+        assert_eq!(creds.user_id, 42);
+        assert_eq!(creds.device_uuid, "device-uuid");
+        assert_eq!(creds.a_header, "mac/3.7.0/ko");
+        assert_eq!(creds.user_agent, "");
+    }
+
+    #[test]
+    fn test_credentials_from_vars_requires_token() {
+        assert!(credentials_from_vars(|_| None).is_none());
+        assert!(
+            credentials_from_vars(|key| (key == "OPENKAKAO_TOKEN").then(|| " ".into())).is_none()
+        );
+
+        let creds =
+            credentials_from_vars(|key| (key == "OPENKAKAO_TOKEN").then(|| "tok-uuid".into()))
+                .unwrap();
+        assert_eq!(creds.user_id, 0);
+    }
+
+    /// Credentials as `OPENKAKAO_TOKEN` alone would give them, with the
+    /// other variables set as named.
+    fn partial(vars: &[(&str, &str)]) -> KakaoCredentials {
+        credentials_from_vars(|key| {
+            vars.iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| value.to_string())
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn complete_credentials_need_no_degradation() {
+        let mut creds = partial(&[
+            (TOKEN_ENV, "tok-device-uuid"),
+            (USER_ID_ENV, "42"),
+            (A_HEADER_ENV, "mac/26.1.0/ko"),
+        ]);
+        let notes = fill_credential_gaps(&mut creds, |_| panic!("user id is known"));
+        assert!(notes.is_empty(), "{notes:?}");
+    }
+
+    #[test]
+    fn missing_user_id_is_backfilled_from_the_profile() {
+        let mut creds = partial(&[
+            (TOKEN_ENV, "tok-device-uuid"),
+            (A_HEADER_ENV, "mac/26.1.0/ko"),
+        ]);
+        assert_eq!(creds.gaps(), [CredentialGap::UserId]);
+        let notes = fill_credential_gaps(&mut creds, |creds| {
+            assert_eq!(creds.oauth_token, "tok-device-uuid");
+            Ok(77)
+        });
+        assert!(notes.is_empty(), "{notes:?}");
+        assert_eq!(creds.user_id, 77);
+        assert!(creds.gaps().is_empty());
+
+        let mut unlucky = partial(&[
+            (TOKEN_ENV, "tok-device-uuid"),
+            (A_HEADER_ENV, "mac/26.1.0/ko"),
+        ]);
+        let notes = fill_credential_gaps(&mut unlucky, |_| Err(anyhow!("connection refused")));
+        assert_eq!(unlucky.user_id, 0);
+        assert_eq!(notes.len(), 1);
+        assert!(notes[0].contains("user_id") && notes[0].contains("connection refused"));
+    }
+
+    #[test]
+    fn missing_a_header_is_derived_and_flagged_approximate() {
+        let mut creds = partial(&[(TOKEN_ENV, "tok-device-uuid"), (USER_ID_ENV, "42")]);
+        assert!(creds.is_approximate());
+        let notes = fill_credential_gaps(&mut creds, |_| panic!("user id is known"));
+        assert_eq!(notes.len(), 1);
+        assert!(notes[0].contains("no A header"), "{}", notes[0]);
+        assert!(notes[0].contains("mac/3.7.0/ko") && notes[0].contains("approximate"));
+    }
+
+    #[test]
+    fn missing_device_uuid_names_the_skipped_features() {
+        let mut creds = partial(&[
+            (TOKEN_ENV, "tok"),
+            (USER_ID_ENV, "42"),
+            (A_HEADER_ENV, "mac/26.1.0/ko"),
+        ]);
+        creds.device_uuid.clear();
+        let notes = fill_credential_gaps(&mut creds, |_| panic!("user id is known"));
+        assert_eq!(notes.len(), 1);
+        assert!(notes[0].contains("device_uuid") && notes[0].contains("LOCO"));
+        let err = creds.require_device_uuid("LOCO login").unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("LOCO login needs a device UUID"));
+    }
+
+    fn complete() -> (TokenStatus, TokenScope) {
+        (
+            TokenStatus::Valid,
+            TokenScope {
+                katalk: Some(true),
+                pilsner: Some(true),
+            },
+        )
+    }
+
+    fn katalk_only() -> (TokenStatus, TokenScope) {
+        (
+            TokenStatus::Valid,
+            TokenScope {
+                katalk: Some(true),
+                pilsner: Some(false),
+            },
+        )
+    }
+
+    fn invalid() -> (TokenStatus, TokenScope) {
+        (
+            TokenStatus::Invalid {
+                kakao_status: -950,
+                message: String::new(),
+            },
+            TokenScope::default(),
+        )
+    }
+
+    fn network() -> (TokenStatus, TokenScope) {
+        (
+            TokenStatus::NetworkError {
+                message: "timed out".into(),
+            },
+            TokenScope::default(),
+        )
+    }
+
+    /// Run [`choose_credential`] over candidates named by their check result.
+    fn choose(results: Vec<(TokenStatus, TokenScope)>) -> (CredentialChoice<usize>, Vec<usize>) {
+        let mut checked = Vec::new();
+        let choice = choose_credential((0..results.len()).collect(), |&i| {
+            checked.push(i);
+            results[i].clone()
+        });
+        (choice, checked)
+    }
+
+    #[test]
+    fn credential_choice_prefers_complete_scope() {
+        let (choice, _) = choose(vec![invalid(), katalk_only(), complete()]);
+        assert_eq!(choice, CredentialChoice::Verified(2));
+        let (choice, _) = choose(vec![katalk_only(), invalid()]);
+        assert_eq!(choice, CredentialChoice::Verified(0));
+        let (choice, _) = choose(vec![invalid(), invalid()]);
+        assert_eq!(choice, CredentialChoice::Fallback(0));
+    }
+
+    #[test]
+    fn transient_failures_do_not_discard_candidates() {
+        // The newest token times out: it is kept, and nothing older is
+        // probed into the outage.
+        let (choice, checked) = choose(vec![network(), complete()]);
+        assert!(matches!(
+            choice,
+            CredentialChoice::Unverified(0, TokenStatus::NetworkError { .. })
+        ));
+        assert_eq!(checked, [0]);
+
+        // Rate limited after a rejected token: the next one is kept unchecked.
+        let (choice, checked) = choose(vec![
+            invalid(),
+            (TokenStatus::RateLimited, TokenScope::default()),
+            complete(),
+        ]);
+        assert_eq!(
+            choice,
+            CredentialChoice::Unverified(1, TokenStatus::RateLimited)
+        );
+        assert_eq!(checked, [0, 1]);
+
+        // A token already known good wins over an unchecked one.
+        let (choice, _) = choose(vec![katalk_only(), network()]);
+        assert_eq!(choice, CredentialChoice::Verified(0));
+    }
+
+    #[test]
+    fn recovery_keeps_to_the_rejected_account_when_it_can() {
+        let creds = |token: &str, user_id| {
+            KakaoCredentials::new(
+                token.into(),
+                user_id,
+                "device".into(),
+                "26.1.0".into(),
+                String::new(),
+                String::new(),
+            )
+        };
+        let tokens = |fresh: &[KakaoCredentials]| -> Vec<String> {
+            fresh.iter().map(|c| c.oauth_token.clone()).collect()
+        };
+        let rejected = creds("expired", 111);
+
+        // The other account is newer, but the same account's token is kept.
+        let mut fresh = vec![creds("other", 222), creds("renewed", 111)];
+        prefer_same_account(&rejected, &mut fresh);
+        assert_eq!(tokens(&fresh), ["renewed"]);
+
+        // With only the other account left, it reaches the account check.
+        let mut fresh = vec![creds("other", 222)];
+        prefer_same_account(&rejected, &mut fresh);
+        assert_eq!(tokens(&fresh), ["other"]);
+        assert!(account_conflict::detect(&rejected, &fresh[0]).is_some());
+    }
+
     #[test]
     fn transport_recovery_order_is_defined() {
         assert!(Transport::Rest.recovery_order(&AuthPolicy::default()).len() >= 3);
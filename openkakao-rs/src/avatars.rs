@@ -0,0 +1,208 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::output::write_atomic;
+
+/// Kept in the avatar directory; records what each file was fetched from.
+pub const MANIFEST_FILE: &str = ".openkakao-avatars.json";
+
+/// Longest name part of an avatar file name, in characters, so long
+/// nicknames stay well under filesystem name limits.
+const MAX_NAME_CHARS: usize = 60;
+
+/// `<display_name>_<user_id>.jpg` with characters that are unsafe in file
+/// names on macOS, Windows or Linux replaced by `_`.
+pub fn avatar_filename(display_name: &str, user_id: i64) -> String {
+    let mut name: String = display_name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .take(MAX_NAME_CHARS)
+        .collect();
+    // Leading dots would hide the file; trailing dots and spaces are
+    // dropped by Windows.
+    name = name
+        .trim_matches(|c: char| c == '.' || c.is_whitespace())
+        .to_string();
+    if name.is_empty() {
+        name = "friend".to_string();
+    }
+    format!("{name}_{user_id}.jpg")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AvatarEntry {
+    pub file: String,
+    pub url: String,
+    pub sha256: String,
+}
+
+/// What [`AvatarManifest::store`] did with a downloaded image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoreOutcome {
+    /// Same bytes as last time; at most the file was renamed.
+    Unchanged,
+    /// New or changed image written.
+    Written,
+}
+
+/// Per-friend record of the last synced avatar, so a re-run only downloads
+/// images whose URL changed and only rewrites files whose bytes changed.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AvatarManifest {
+    #[serde(default)]
+    pub avatars: BTreeMap<i64, AvatarEntry>,
+}
+
+impl AvatarManifest {
+    /// The manifest in `dir`; empty when there is none yet.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let path = dir.join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let data = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&data).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let data = serde_json::to_string_pretty(self)?;
+        write_atomic(&dir.join(MANIFEST_FILE), |w| {
+            Ok(w.write_all(data.as_bytes())?)
+        })
+    }
+
+    /// Whether `user_id`'s avatar from `url` is already on disk as `file`,
+    /// so it can be skipped without a request.
+    pub fn is_current(&self, dir: &Path, user_id: i64, url: &str, file: &str) -> bool {
+        self.avatars
+            .get(&user_id)
+            .is_some_and(|e| e.url == url && e.file == file && dir.join(file).is_file())
+    }
+
+    /// Save a freshly downloaded image as `file`. Identical bytes are not
+    /// rewritten (a renamed friend's file is moved instead); a file left
+    /// behind under an old name is removed.
+    pub fn store(
+        &mut self,
+        dir: &Path,
+        user_id: i64,
+        url: &str,
+        file: &str,
+        bytes: &[u8],
+    ) -> Result<StoreOutcome> {
+        let sha256 = hex::encode(Sha256::digest(bytes));
+        let previous = self.avatars.get(&user_id).cloned();
+        let target = dir.join(file);
+
+        let outcome = match &previous {
+            Some(old) if old.sha256 == sha256 && dir.join(&old.file).is_file() => {
+                if old.file != file {
+                    fs::rename(dir.join(&old.file), &target).with_context(|| {
+                        format!("Failed to rename {} to {}", old.file, target.display())
+                    })?;
+                }
+                StoreOutcome::Unchanged
+            }
+            _ => {
+                write_atomic(&target, |w| Ok(w.write_all(bytes)?))?;
+                if let Some(old) = previous.as_ref().filter(|old| old.file != file) {
+                    let _ = fs::remove_file(dir.join(&old.file));
+                }
+                StoreOutcome::Written
+            }
+        };
+        self.avatars.insert(
+            user_id,
+            AvatarEntry {
+                file: file.to_string(),
+                url: url.to_string(),
+                sha256,
+            },
+        );
+        Ok(outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filenames_are_safe_everywhere() {
+        assert_eq!(avatar_filename("김민수", 42), "김민수_42.jpg");
+        assert_eq!(avatar_filename("a/b\\c:d*e?", 1), "a_b_c_d_e__1.jpg");
+        assert_eq!(avatar_filename("<\"x\">|", 1), "__x____1.jpg");
+        assert_eq!(avatar_filename("tab\there\n", 1), "tab_here__1.jpg");
+        assert_eq!(avatar_filename("..hidden. ", 1), "hidden_1.jpg");
+        assert_eq!(avatar_filename("../../etc", 7), "_.._etc_7.jpg");
+        assert_eq!(avatar_filename("", 9), "friend_9.jpg");
+        assert_eq!(avatar_filename(" . ", 9), "friend_9.jpg");
+        let long = avatar_filename(&"가".repeat(200), 1);
+        assert_eq!(long.chars().count(), MAX_NAME_CHARS + "_1.jpg".len());
+    }
+
+    #[test]
+    fn manifest_skips_current_images_and_survives_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manifest = AvatarManifest::load(dir.path()).unwrap();
+        assert!(manifest.avatars.is_empty());
+
+        let outcome = manifest
+            .store(dir.path(), 1, "https://p/1.jpg", "Kim_1.jpg", b"one")
+            .unwrap();
+        assert_eq!(outcome, StoreOutcome::Written);
+        manifest.save(dir.path()).unwrap();
+
+        let manifest = AvatarManifest::load(dir.path()).unwrap();
+        assert!(manifest.is_current(dir.path(), 1, "https://p/1.jpg", "Kim_1.jpg"));
+        // A new URL, a new name or a deleted file all mean fetching again.
+        assert!(!manifest.is_current(dir.path(), 1, "https://p/1b.jpg", "Kim_1.jpg"));
+        assert!(!manifest.is_current(dir.path(), 1, "https://p/1.jpg", "Lee_1.jpg"));
+        assert!(!manifest.is_current(dir.path(), 2, "https://p/1.jpg", "Kim_1.jpg"));
+        fs::remove_file(dir.path().join("Kim_1.jpg")).unwrap();
+        assert!(!manifest.is_current(dir.path(), 1, "https://p/1.jpg", "Kim_1.jpg"));
+    }
+
+    #[test]
+    fn identical_bytes_are_not_rewritten_but_renames_follow() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manifest = AvatarManifest::default();
+        manifest
+            .store(dir.path(), 1, "https://p/a.jpg", "Kim_1.jpg", b"same")
+            .unwrap();
+
+        // New URL, same bytes, friend renamed: file moves, nothing rewritten.
+        let outcome = manifest
+            .store(dir.path(), 1, "https://p/b.jpg", "Minsu_1.jpg", b"same")
+            .unwrap();
+        assert_eq!(outcome, StoreOutcome::Unchanged);
+        assert!(!dir.path().join("Kim_1.jpg").exists());
+        assert_eq!(fs::read(dir.path().join("Minsu_1.jpg")).unwrap(), b"same");
+        assert_eq!(manifest.avatars[&1].url, "https://p/b.jpg");
+
+        // Changed bytes under a new name: written, old file cleaned up.
+        let outcome = manifest
+            .store(dir.path(), 1, "https://p/c.jpg", "Min_1.jpg", b"new")
+            .unwrap();
+        assert_eq!(outcome, StoreOutcome::Written);
+        assert!(!dir.path().join("Minsu_1.jpg").exists());
+        assert_eq!(fs::read(dir.path().join("Min_1.jpg")).unwrap(), b"new");
+    }
+
+    #[test]
+    fn corrupt_manifest_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(MANIFEST_FILE), "not json").unwrap();
+        assert!(AvatarManifest::load(dir.path()).is_err());
+    }
+}
@@ -0,0 +1,197 @@
+//! Bytes received over HTTP during a run, and the `--max-bytes` cap on
+//! them for metered connections.
+//!
+//! Every REST response and download body is read through a
+//! [`CountingReader`] before any decompression, so a gzip answer counts at
+//! its size on the wire. Requests check the cap before they are sent and
+//! downloads check the announced `Content-Length` before the body is
+//! streamed; a body that crosses the cap mid-stream stops there. Either way
+//! the request fails with [`OpenKakaoError::ByteCapReached`], which bulk
+//! fetches treat as the end of the data rather than a failure of what was
+//! already fetched. LOCO traffic is not counted.
+
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+
+use crate::error::OpenKakaoError;
+
+/// Bytes received so far and the cap on them, shared by every client that
+/// holds it.
+#[derive(Debug)]
+pub struct ByteMeter {
+    received: AtomicU64,
+    /// `u64::MAX` when there is no cap.
+    max: AtomicU64,
+}
+
+impl Default for ByteMeter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ByteMeter {
+    pub const fn new() -> Self {
+        Self {
+            received: AtomicU64::new(0),
+            max: AtomicU64::new(u64::MAX),
+        }
+    }
+
+    /// A meter that stops at `max` bytes.
+    pub fn with_max(max: u64) -> Self {
+        let meter = Self::new();
+        meter.set_max(Some(max));
+        meter
+    }
+
+    pub fn set_max(&self, max: Option<u64>) {
+        self.max.store(max.unwrap_or(u64::MAX), Ordering::Relaxed);
+    }
+
+    pub fn max(&self) -> Option<u64> {
+        match self.max.load(Ordering::Relaxed) {
+            u64::MAX => None,
+            max => Some(max),
+        }
+    }
+
+    pub fn received(&self) -> u64 {
+        self.received.load(Ordering::Relaxed)
+    }
+
+    pub fn record(&self, bytes: u64) {
+        self.received.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Whether more than the cap has been received.
+    pub fn exceeded(&self) -> bool {
+        self.max().is_some_and(|max| self.received() > max)
+    }
+
+    /// Fail when `expected` more bytes would not fit under the cap. With
+    /// `expected == 0` (size unknown) this fails once the cap is used up.
+    pub fn check(&self, expected: u64) -> Result<(), OpenKakaoError> {
+        let Some(max) = self.max() else {
+            return Ok(());
+        };
+        let received = self.received();
+        let over = if expected == 0 {
+            received >= max
+        } else {
+            received.saturating_add(expected) > max
+        };
+        if over {
+            return Err(OpenKakaoError::ByteCapReached { received, max });
+        }
+        Ok(())
+    }
+
+    /// The error a request stopped by the cap fails with.
+    pub fn cap_error(&self) -> OpenKakaoError {
+        OpenKakaoError::ByteCapReached {
+            received: self.received(),
+            max: self.max().unwrap_or(u64::MAX),
+        }
+    }
+}
+
+static GLOBAL: OnceLock<Arc<ByteMeter>> = OnceLock::new();
+
+/// The process-wide meter that clients use unless given their own.
+pub fn global() -> Arc<ByteMeter> {
+    GLOBAL.get_or_init(|| Arc::new(ByteMeter::new())).clone()
+}
+
+/// Reader that records every byte it passes on in a [`ByteMeter`] and fails
+/// once the meter is past its cap.
+pub struct CountingReader<R> {
+    inner: R,
+    meter: Arc<ByteMeter>,
+}
+
+impl<R: Read> CountingReader<R> {
+    pub fn new(inner: R, meter: Arc<ByteMeter>) -> Self {
+        Self { inner, meter }
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.meter.record(n as u64);
+        if self.meter.exceeded() {
+            return Err(io::Error::other(self.meter.cap_error()));
+        }
+        Ok(n)
+    }
+}
+
+/// `--max-bytes` value: a byte count with an optional `K`, `M` or `G`
+/// suffix (powers of 1024, `B`/`iB` accepted after it).
+pub fn parse_byte_size(input: &str) -> Result<u64, String> {
+    let trimmed = input.trim();
+    let upper = trimmed.to_ascii_uppercase();
+    let number = upper
+        .trim_end_matches("IB")
+        .trim_end_matches('B')
+        .trim_end();
+    let (digits, unit) = match number.char_indices().last() {
+        Some((idx, 'K')) => (&number[..idx], 1u64 << 10),
+        Some((idx, 'M')) => (&number[..idx], 1 << 20),
+        Some((idx, 'G')) => (&number[..idx], 1 << 30),
+        _ => (number, 1),
+    };
+    let value: u64 = digits
+        .trim()
+        .parse()
+        .map_err(|_| format!("'{trimmed}' is not a size like 500000, 800K or 50M"))?;
+    if value == 0 {
+        return Err("the cap must be more than 0 bytes".to_string());
+    }
+    value
+        .checked_mul(unit)
+        .ok_or_else(|| format!("'{trimmed}' is too large"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_parse_with_binary_suffixes() {
+        assert_eq!(parse_byte_size("500000"), Ok(500_000));
+        assert_eq!(parse_byte_size("800K"), Ok(800 * 1024));
+        assert_eq!(parse_byte_size("50mb"), Ok(50 * 1024 * 1024));
+        assert_eq!(parse_byte_size("2GiB"), Ok(2 << 30));
+        assert!(parse_byte_size("0").is_err());
+        assert!(parse_byte_size("lots").is_err());
+        assert!(parse_byte_size("99999999999G").is_err());
+    }
+
+    #[test]
+    fn cap_is_checked_before_and_while_reading() {
+        let meter = Arc::new(ByteMeter::with_max(10));
+        assert!(meter.check(10).is_ok());
+        assert!(meter.check(11).is_err());
+
+        let mut out = Vec::new();
+        CountingReader::new(&b"123456"[..], meter.clone())
+            .read_to_end(&mut out)
+            .unwrap();
+        assert_eq!(meter.received(), 6);
+        assert!(meter.check(0).is_ok());
+        assert!(meter.check(5).is_err());
+
+        let err = CountingReader::new(&b"123456"[..], meter.clone())
+            .read_to_end(&mut Vec::new())
+            .unwrap_err();
+        assert!(err.to_string().contains("--max-bytes"), "{err}");
+        assert!(meter.exceeded());
+        assert!(matches!(
+            meter.check(0),
+            Err(OpenKakaoError::ByteCapReached { max: 10, .. })
+        ));
+    }
+}
@@ -0,0 +1,118 @@
+//! Optional parts of the binary and whether this build has them, for
+//! `version --features`. Every entry is a compile-time `cfg!`, so the list
+//! cannot disagree with what was built. Commands that need a missing one
+//! stay in `--help` and fail through [`require`] with a "not compiled in"
+//! error instead.
+
+use anyhow::Result;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+pub struct Capability {
+    pub name: &'static str,
+    pub compiled: bool,
+    pub description: &'static str,
+    /// How to get a build that has it.
+    #[serde(skip)]
+    pub rebuild: &'static str,
+}
+
+/// Reading the macOS KakaoTalk database and the credentials it caches.
+pub const EXTRACT: Capability = Capability {
+    name: "extract",
+    compiled: cfg!(feature = "extract"),
+    description: "read credentials and chats from the KakaoTalk app's cache",
+    rebuild: "rebuild with --features extract",
+};
+
+/// The local SQLite message archive (SQLite is bundled).
+pub const ARCHIVE: Capability = Capability {
+    name: "archive",
+    compiled: cfg!(feature = "archive"),
+    description: "local message archive (cache, archive, search over synced chats)",
+    rebuild: "rebuild with --features archive",
+};
+
+/// LOCO push (`watch`, LOCO `read`/`chats`). The LOCO client is part of the
+/// library core, so no build leaves it out; it is listed for scripts that
+/// probe every capability the same way.
+pub const REALTIME: Capability = Capability {
+    name: "realtime",
+    compiled: true,
+    description: "LOCO connection for push watching and LOCO reads",
+    rebuild: "",
+};
+
+/// The login keychain as a credential store; macOS only.
+pub const KEYRING: Capability = Capability {
+    name: "keyring",
+    compiled: cfg!(all(feature = "keyring", target_os = "macos")),
+    description: "macOS keychain credential store",
+    rebuild: "build for macOS with --features keyring",
+};
+
+/// The `tui` chat browser.
+pub const TUI: Capability = Capability {
+    name: "tui",
+    compiled: cfg!(feature = "tui"),
+    description: "terminal chat browser (tui)",
+    rebuild: "rebuild with --features tui",
+};
+
+/// Korean public holidays by name in `--since`/`--until`.
+pub const HOLIDAYS: Capability = Capability {
+    name: "holidays",
+    compiled: cfg!(feature = "holidays"),
+    description: "Korean holiday names in --since/--until (설날, 추석, ...)",
+    rebuild: "rebuild with --features holidays",
+};
+
+pub const ALL: [Capability; 6] = [EXTRACT, ARCHIVE, REALTIME, KEYRING, TUI, HOLIDAYS];
+
+/// Fail with what to rebuild when `capability` is not in this binary.
+pub fn require(capability: &Capability) -> Result<()> {
+    if capability.compiled {
+        return Ok(());
+    }
+    anyhow::bail!(
+        "This command needs {}, which is not compiled into this binary ({}). \
+         `openkakao-rs version --features` lists what is.",
+        capability.name,
+        capability.rebuild
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_follow_the_build_flags() {
+        assert_eq!(EXTRACT.compiled, cfg!(feature = "extract"));
+        assert_eq!(ARCHIVE.compiled, cfg!(feature = "archive"));
+        assert_eq!(TUI.compiled, cfg!(feature = "tui"));
+        assert_eq!(HOLIDAYS.compiled, cfg!(feature = "holidays"));
+        assert_eq!(
+            KEYRING.compiled,
+            cfg!(all(feature = "keyring", target_os = "macos"))
+        );
+
+        let names: Vec<_> = ALL.iter().map(|c| c.name).collect();
+        assert_eq!(
+            names,
+            ["extract", "archive", "realtime", "keyring", "tui", "holidays"]
+        );
+    }
+
+    #[test]
+    fn missing_capabilities_say_how_to_rebuild() {
+        let missing = Capability {
+            compiled: false,
+            ..TUI
+        };
+        let err = require(&missing).unwrap_err().to_string();
+        assert!(err.contains("not compiled into this binary"), "{err}");
+        assert!(err.contains("--features tui"), "{err}");
+        assert!(require(&REALTIME).is_ok());
+    }
+}
@@ -0,0 +1,472 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Map, Value};
+
+/// Bumped when the report layout changes.
+pub const REPORT_VERSION: u32 = 1;
+
+static RECORDER: OnceLock<Mutex<Recorder>> = OnceLock::new();
+
+/// Transport, method and endpoint of the latest request, kept whether or not
+/// capture is active so a crash report can tell what was in flight.
+static LAST_REQUEST: Mutex<Option<(&'static str, String, String)>> = Mutex::new(None);
+
+/// One request/response exchange, as recorded (unscrubbed, in memory only).
+#[derive(Debug, Clone, Default)]
+pub struct Exchange {
+    /// "rest" or "loco".
+    pub transport: &'static str,
+    /// HTTP verb or LOCO method.
+    pub method: String,
+    /// URL for REST, empty for LOCO.
+    pub endpoint: String,
+    pub status: Option<i64>,
+    pub latency: Duration,
+    pub error: Option<String>,
+    pub request: Option<Value>,
+    pub response: Option<Value>,
+}
+
+/// Collects exchanges for `--capture-report`.
+#[derive(Debug)]
+pub struct Recorder {
+    include_bodies: bool,
+    started: Instant,
+    exchanges: Vec<Exchange>,
+}
+
+impl Recorder {
+    pub fn new(include_bodies: bool) -> Self {
+        Self {
+            include_bodies,
+            started: Instant::now(),
+            exchanges: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, mut exchange: Exchange) {
+        if !self.include_bodies {
+            exchange.request = None;
+            exchange.response = None;
+        }
+        self.exchanges.push(exchange);
+    }
+
+    /// Sanitized report. `args` is the raw command line, `config` the
+    /// effective config, `errors` the final error chain (outermost first).
+    pub fn report(&self, args: &[String], config: &Value, errors: &[String]) -> Value {
+        let mut scrubber = Scrubber::default();
+        let requests: Vec<Value> = self
+            .exchanges
+            .iter()
+            .map(|e| {
+                let mut entry = json!({
+                    "transport": e.transport,
+                    "method": e.method,
+                    "endpoint": scrub_endpoint(&e.endpoint),
+                    "status": e.status,
+                    "latency_ms": e.latency.as_millis() as u64,
+                });
+                if let Some(error) = &e.error {
+                    entry["error"] = json!(scrubber.scrub_text(error));
+                }
+                if let Some(request) = &e.request {
+                    entry["request_body"] = scrubber.scrub_value(request);
+                }
+                if let Some(response) = &e.response {
+                    entry["response_body"] = scrubber.scrub_value(response);
+                }
+                entry
+            })
+            .collect();
+
+        json!({
+            "report_version": REPORT_VERSION,
+            "crate_version": env!("CARGO_PKG_VERSION"),
+            "os": std::env::consts::OS,
+            "arch": std::env::consts::ARCH,
+            "command": scrub_args(args),
+            "config": scrubber.scrub_value(config),
+            "bodies_included": self.include_bodies,
+            "duration_ms": self.started.elapsed().as_millis() as u64,
+            "requests": requests,
+            "error_chain": errors.iter().map(|e| scrubber.scrub_text(e)).collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Start recording for this process. Later calls are ignored.
+pub fn start(include_bodies: bool) {
+    let _ = RECORDER.set(Mutex::new(Recorder::new(include_bodies)));
+}
+
+pub fn is_active() -> bool {
+    RECORDER.get().is_some()
+}
+
+/// Whether request/response bodies should be handed to [`record`].
+pub fn wants_bodies() -> bool {
+    RECORDER
+        .get()
+        .and_then(|r| r.lock().ok().map(|r| r.include_bodies))
+        .unwrap_or(false)
+}
+
+/// Record an exchange if capture is active; a no-op otherwise.
+pub fn record(exchange: Exchange) {
+    if let Some(recorder) = RECORDER.get() {
+        if let Ok(mut recorder) = recorder.lock() {
+            recorder.record(exchange);
+        }
+    }
+}
+
+/// Note a request about to be sent; only the latest one is kept.
+pub fn note_request(transport: &'static str, method: &str, endpoint: &str) {
+    if let Ok(mut last) = LAST_REQUEST.lock() {
+        *last = Some((transport, method.to_string(), endpoint.to_string()));
+    }
+}
+
+/// The latest request noted by [`note_request`], endpoint scrubbed. Never
+/// blocks, so a panic while the slot is held still gets a report.
+pub fn last_request() -> Option<Value> {
+    let last = LAST_REQUEST.try_lock().ok()?;
+    let (transport, method, endpoint) = last.as_ref()?;
+    Some(json!({
+        "transport": transport,
+        "method": method,
+        "endpoint": scrub_endpoint(endpoint),
+    }))
+}
+
+/// Build the report from the global recorder, if capture is active.
+pub fn finish(args: &[String], config: &Value, errors: &[String]) -> Option<Value> {
+    let recorder = RECORDER.get()?.lock().ok()?;
+    Some(recorder.report(args, config, errors))
+}
+
+/// Keep flags, the subcommand and numbers; hide other free-form arguments
+/// (message text, search queries, paths).
+pub fn scrub_args(args: &[String]) -> Vec<String> {
+    let mut seen_subcommand = false;
+    args.iter()
+        .skip(1)
+        .map(|arg| {
+            if arg.starts_with('-') {
+                // `--flag=value` keeps the flag only.
+                arg.split('=').next().unwrap_or(arg).to_string()
+            } else if !seen_subcommand && arg.chars().all(|c| c.is_ascii_lowercase() || c == '-') {
+                seen_subcommand = true;
+                arg.clone()
+            } else {
+                "<arg>".to_string()
+            }
+        })
+        .collect()
+}
+
+/// Drop query strings and replace numeric path segments.
+fn scrub_endpoint(endpoint: &str) -> String {
+    let path = endpoint.split(['?', '#']).next().unwrap_or("");
+    path.split('/')
+        .map(|seg| {
+            if !seg.is_empty() && seg.chars().all(|c| c.is_ascii_digit()) {
+                ":id"
+            } else {
+                seg
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FieldKind {
+    Secret,
+    Identifier,
+    Text,
+    Other,
+}
+
+fn classify_key(key: &str) -> FieldKind {
+    let k: String = key
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    const SECRET: &[&str] = &[
+        "token",
+        "password",
+        "secret",
+        "authorization",
+        "aheader",
+        "cookie",
+        "uuid",
+        "email",
+        "phone",
+        "cmd",
+        "xvc",
+        "key",
+    ];
+    const TEXT: &[&str] = &[
+        "message",
+        "msg",
+        "attachment",
+        "text",
+        "nickname",
+        "name",
+        "title",
+        "status",
+        "url",
+    ];
+    if SECRET.iter().any(|s| k.contains(s)) {
+        FieldKind::Secret
+    } else if k.ends_with("id") || k.ends_with("ids") {
+        FieldKind::Identifier
+    } else if TEXT.iter().any(|s| k.contains(s)) && k != "status" {
+        FieldKind::Text
+    } else {
+        FieldKind::Other
+    }
+}
+
+/// Replaces secrets, personal text and ids. Ids map to stable pseudonyms
+/// (`id-1`, `id-2`, ...) so requests can still be correlated.
+#[derive(Debug, Default)]
+pub struct Scrubber {
+    pseudonyms: HashMap<i64, String>,
+}
+
+impl Scrubber {
+    pub fn pseudonym(&mut self, id: i64) -> String {
+        let next = self.pseudonyms.len() + 1;
+        self.pseudonyms
+            .entry(id)
+            .or_insert_with(|| format!("id-{next}"))
+            .clone()
+    }
+
+    pub fn scrub_value(&mut self, value: &Value) -> Value {
+        self.scrub_field(FieldKind::Other, value)
+    }
+
+    fn scrub_field(&mut self, kind: FieldKind, value: &Value) -> Value {
+        match (kind, value) {
+            (_, Value::Null) | (_, Value::Bool(_)) => value.clone(),
+            (FieldKind::Secret, _) => json!("<redacted>"),
+            (_, Value::Object(map)) => {
+                let mut out = Map::new();
+                for (k, v) in map {
+                    out.insert(k.clone(), self.scrub_field(classify_key(k), v));
+                }
+                Value::Object(out)
+            }
+            (_, Value::Array(items)) => {
+                Value::Array(items.iter().map(|v| self.scrub_field(kind, v)).collect())
+            }
+            (FieldKind::Identifier, Value::Number(n)) => match n.as_i64() {
+                Some(0) | None => value.clone(),
+                Some(id) => json!(self.pseudonym(id)),
+            },
+            (FieldKind::Identifier, Value::String(s)) => match s.parse::<i64>() {
+                Ok(id) if id != 0 => json!(self.pseudonym(id)),
+                _ => json!(format!("<{} chars>", s.chars().count())),
+            },
+            (FieldKind::Text, Value::String(s)) => json!(format!("<{} chars>", s.chars().count())),
+            (_, Value::String(s)) => json!(self.scrub_text(s)),
+            (_, Value::Number(_)) => value.clone(),
+        }
+    }
+
+    /// Scrub free text such as error messages: long digit runs become
+    /// pseudonyms, phone-like numbers and token-like words are removed.
+    pub fn scrub_text(&mut self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut word = String::new();
+        for c in text.chars() {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '+' | '.' | '=' | '/') {
+                word.push(c);
+            } else {
+                out.push_str(&self.scrub_word(&word));
+                word.clear();
+                out.push(c);
+            }
+        }
+        out.push_str(&self.scrub_word(&word));
+        out
+    }
+
+    fn scrub_word(&mut self, word: &str) -> String {
+        if word.is_empty() {
+            return String::new();
+        }
+        let digits = word.chars().filter(char::is_ascii_digit).count();
+        let letters = word.chars().filter(char::is_ascii_alphabetic).count();
+        if letters == 0 && digits >= 9 && word.contains(['-', '+']) && !word.starts_with('-') {
+            return "<phone>".to_string();
+        }
+        if letters == 0 && digits >= 6 {
+            if let Ok(id) = word.trim_start_matches('-').parse::<i64>() {
+                let sign = if word.starts_with('-') { "-" } else { "" };
+                return format!("{sign}{}", self.pseudonym(id));
+            }
+            return "<number>".to_string();
+        }
+        // Paths keep their shape; tokens are long mixed alphanumerics.
+        if word.len() >= 24 && digits > 0 && letters > 0 && !word.contains('/') {
+            return "<token>".to_string();
+        }
+        word.to_string()
+    }
+}
+
+/// Parse an `a=b&c=d` form body into a JSON object for scrubbing.
+pub fn form_to_json(body: &str) -> Value {
+    let mut map = Map::new();
+    for pair in body.split('&').filter(|p| !p.is_empty()) {
+        let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+        let v = urlencoding::decode(v)
+            .map(|v| v.into_owned())
+            .unwrap_or_default();
+        map.insert(k.to_string(), Value::String(v));
+    }
+    Value::Object(map)
+}
+
+/// Error chain of an `anyhow::Error`, outermost first.
+pub fn error_chain(err: &anyhow::Error) -> Vec<String> {
+    err.chain().map(|e| e.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "a1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d6-deadbeef";
+    const PHONE: &str = "010-1234-5678";
+    const USER_ID: i64 = 405_979_308;
+    const TEXT: &str = "meet me at the station";
+
+    fn fake_session() -> Recorder {
+        let mut recorder = Recorder::new(true);
+        recorder.record(Exchange {
+            transport: "rest",
+            method: "POST".into(),
+            endpoint: format!("https://katalk.kakao.com/mac/profile3/friend.json?id={USER_ID}"),
+            status: Some(0),
+            latency: Duration::from_millis(42),
+            error: None,
+            request: Some(form_to_json(&format!("id={USER_ID}&access_token={TOKEN}"))),
+            response: Some(json!({
+                "status": 0,
+                "friends": [{
+                    "userId": USER_ID,
+                    "nickName": "Alice",
+                    "phoneNumber": PHONE,
+                    "statusMessage": TEXT,
+                }],
+            })),
+        });
+        recorder.record(Exchange {
+            transport: "loco",
+            method: "SYNCMSG".into(),
+            status: Some(-950),
+            latency: Duration::from_millis(7),
+            error: Some(format!(
+                "LOCO login failed for user {USER_ID} with token {TOKEN}, call {PHONE}"
+            )),
+            request: Some(json!({"chatId": 900000000000003_i64, "cur": 0})),
+            response: Some(json!({
+                "chatLogs": [{"authorId": USER_ID, "message": TEXT, "attachment": "{}"}],
+            })),
+            ..Exchange::default()
+        });
+        recorder
+    }
+
+    #[test]
+    fn nothing_sensitive_survives_the_report() {
+        let recorder = fake_session();
+        let config = json!({
+            "auth": {"password_cmd": "pass show kakao", "email_cmd": "echo me@example.com"},
+            "safety": {"allow_loco_write": false},
+        });
+        let args: Vec<String> = ["openkakao-rs", "send", "123", TEXT, "--yes"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let errors = vec![
+            format!("REST API error for {USER_ID}: bearer {TOKEN}"),
+            "HTTP request failed: POST https://katalk.kakao.com/mac/profile3/friend.json".into(),
+        ];
+        let report = recorder.report(&args, &config, &errors);
+        let text = serde_json::to_string(&report).unwrap();
+
+        for secret in [
+            TOKEN,
+            PHONE,
+            TEXT,
+            "Alice",
+            "405979308",
+            "900000000000003",
+            "pass show kakao",
+            "me@example.com",
+        ] {
+            assert!(!text.contains(secret), "{secret} leaked: {text}");
+        }
+        assert_eq!(
+            report["command"],
+            json!(["send", "<arg>", "<arg>", "--yes"])
+        );
+        assert_eq!(
+            report["requests"][0]["endpoint"],
+            "https://katalk.kakao.com/mac/profile3/friend.json"
+        );
+        assert_eq!(report["requests"][1]["status"], -950);
+        assert!(report["error_chain"][1]
+            .as_str()
+            .unwrap()
+            .ends_with("/mac/profile3/friend.json"));
+        assert_eq!(report["config"]["safety"]["allow_loco_write"], false);
+    }
+
+    #[test]
+    fn user_ids_get_stable_pseudonyms() {
+        let report = fake_session().report(&[], &Value::Null, &[]);
+        let friend = &report["requests"][0]["response_body"]["friends"][0]["userId"];
+        let author = &report["requests"][1]["response_body"]["chatLogs"][0]["authorId"];
+        assert_eq!(friend, author);
+        assert!(friend.as_str().unwrap().starts_with("id-"));
+    }
+
+    #[test]
+    fn bodies_are_dropped_unless_opted_in() {
+        let mut recorder = Recorder::new(false);
+        recorder.record(Exchange {
+            transport: "rest",
+            method: "GET".into(),
+            response: Some(json!({"message": TEXT})),
+            ..Exchange::default()
+        });
+        let report = recorder.report(&[], &Value::Null, &[]);
+        assert!(report["requests"][0].get("response_body").is_none());
+        assert_eq!(report["bodies_included"], false);
+    }
+
+    #[test]
+    fn last_request_keeps_only_the_scrubbed_endpoint() {
+        note_request(
+            "rest",
+            "GET",
+            "https://katalk.kakao.com/chats/405979308?token=x",
+        );
+        let last = last_request().unwrap();
+        assert_eq!(
+            last,
+            json!({"transport": "rest", "method": "GET", "endpoint": "https://katalk.kakao.com/chats/:id"})
+        );
+    }
+}
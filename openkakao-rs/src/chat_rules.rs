@@ -0,0 +1,411 @@
+//! Include/exclude rules that pick chats for `cache --all`, the daemon's
+//! `sync` task and `watch --include/--exclude`.
+//!
+//! A rule is one of:
+//!
+//! | rule               | matches                                          |
+//! |--------------------|--------------------------------------------------|
+//! | `chat:<id>`        | that chat id                                     |
+//! | `link:<id>`        | the open chat with that open link id             |
+//! | `title:<glob>`     | titles matching the glob (`*`, `?`, any case)    |
+//! | `<type>:<glob>`    | chats of that type whose title matches the glob  |
+//! | `type:<type>`      | every chat of that type                          |
+//! | `*`                | every chat                                       |
+//!
+//! Types are the raw kinds (`OpenMultiChat`), their labels (`OpenGroup`) or
+//! the aliases `dm`, `group`, `memo` and `open`, in any case.
+//!
+//! The most specific matching rule decides: an id beats a title glob, which
+//! beats a type, which beats `*`. When an include and an exclude rule are
+//! equally specific, the exclude wins. A chat no rule matches is included
+//! only if there are no include rules.
+
+use anyhow::Result;
+
+use crate::model::ChatRoom;
+use crate::util::type_label;
+
+/// What the rules look at in a chat.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChatSubject {
+    pub chat_id: i64,
+    /// Raw kind, e.g. `OpenMultiChat`; empty when unknown.
+    pub kind: String,
+    pub title: String,
+    pub link_id: Option<i64>,
+}
+
+impl ChatSubject {
+    pub fn from_room(room: &ChatRoom) -> Self {
+        Self {
+            chat_id: room.chat_id,
+            kind: room.kind.clone(),
+            title: room.display_title(),
+            link_id: room.settings.open_link.as_ref().map(|link| link.link_id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Matcher {
+    Chat(i64),
+    Link(i64),
+    Title(String),
+    TypeTitle(Vec<&'static str>, String),
+    Type(Vec<&'static str>),
+    All,
+}
+
+impl Matcher {
+    /// Higher wins.
+    fn specificity(&self) -> u8 {
+        match self {
+            Matcher::Chat(_) | Matcher::Link(_) => 3,
+            Matcher::Title(_) | Matcher::TypeTitle(..) => 2,
+            Matcher::Type(_) => 1,
+            Matcher::All => 0,
+        }
+    }
+
+    fn matches(&self, chat: &ChatSubject) -> bool {
+        match self {
+            Matcher::Chat(id) => chat.chat_id == *id,
+            Matcher::Link(id) => chat.link_id == Some(*id),
+            Matcher::Title(glob) => glob_matches(glob, &chat.title),
+            Matcher::TypeTitle(kinds, glob) => {
+                kinds.contains(&chat.kind.as_str()) && glob_matches(glob, &chat.title)
+            }
+            Matcher::Type(kinds) => kinds.contains(&chat.kind.as_str()),
+            Matcher::All => true,
+        }
+    }
+}
+
+const KINDS: &[&str] = &[
+    "DirectChat",
+    "MultiChat",
+    "MemoChat",
+    "OpenDirectChat",
+    "OpenMultiChat",
+];
+
+/// Raw kinds a type name stands for.
+fn parse_kinds(name: &str) -> Option<Vec<&'static str>> {
+    let alias: &[&'static str] = match name.to_ascii_lowercase().as_str() {
+        "dm" => &["DirectChat"],
+        "group" => &["MultiChat"],
+        "memo" => &["MemoChat"],
+        "open" => &["OpenDirectChat", "OpenMultiChat"],
+        _ => &[],
+    };
+    if !alias.is_empty() {
+        return Some(alias.to_vec());
+    }
+    KINDS
+        .iter()
+        .find(|kind| kind.eq_ignore_ascii_case(name) || type_label(kind).eq_ignore_ascii_case(name))
+        .map(|kind| vec![*kind])
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    text: String,
+    matcher: Matcher,
+}
+
+impl Rule {
+    fn parse(text: &str) -> Result<Self> {
+        let text = text.trim();
+        let parse_id = |value: &str| {
+            value
+                .trim()
+                .parse::<i64>()
+                .map_err(|_| anyhow::anyhow!("invalid chat rule '{}': expected a number", text))
+        };
+        let matcher = match text.split_once(':') {
+            _ if text == "*" => Matcher::All,
+            Some(("chat", id)) => Matcher::Chat(parse_id(id)?),
+            Some(("link", id)) => Matcher::Link(parse_id(id)?),
+            Some(("title", glob)) if !glob.is_empty() => Matcher::Title(glob.to_string()),
+            Some(("type", name)) => Matcher::Type(
+                parse_kinds(name)
+                    .ok_or_else(|| anyhow::anyhow!("invalid chat rule '{}': unknown type", text))?,
+            ),
+            Some((name, glob)) if !glob.is_empty() => match parse_kinds(name) {
+                Some(kinds) => Matcher::TypeTitle(kinds, glob.to_string()),
+                None => anyhow::bail!(
+                    "invalid chat rule '{}': expected chat:, link:, title:, type: or a chat type",
+                    text
+                ),
+            },
+            _ => anyhow::bail!(
+                "invalid chat rule '{}': expected chat:<id>, link:<id>, title:<glob>, type:<type>, <type>:<glob> or *",
+                text
+            ),
+        };
+        Ok(Self {
+            text: text.to_string(),
+            matcher,
+        })
+    }
+}
+
+/// Whether a chat is in, and the rule that said so.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decision {
+    pub included: bool,
+    /// `include <rule>` / `exclude <rule>`, or `None` when no rule matched.
+    pub rule: Option<String>,
+}
+
+impl Decision {
+    pub fn reason(&self) -> String {
+        match (&self.rule, self.included) {
+            (Some(rule), _) => rule.clone(),
+            (None, true) => "no exclude rule matched".to_string(),
+            (None, false) => "no include rule matched".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChatRules {
+    include: Vec<Rule>,
+    exclude: Vec<Rule>,
+}
+
+impl ChatRules {
+    pub fn parse(include: &[String], exclude: &[String]) -> Result<Self> {
+        let parse_all = |rules: &[String]| {
+            rules
+                .iter()
+                .map(|r| Rule::parse(r))
+                .collect::<Result<Vec<_>>>()
+        };
+        Ok(Self {
+            include: parse_all(include)?,
+            exclude: parse_all(exclude)?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    pub fn decide(&self, chat: &ChatSubject) -> Decision {
+        match (
+            best_match(&self.include, chat),
+            best_match(&self.exclude, chat),
+        ) {
+            (Some((inc, rule)), exc) if exc.is_none_or(|(exc, _)| inc > exc) => Decision {
+                included: true,
+                rule: Some(format!("include {}", rule)),
+            },
+            (_, Some((_, rule))) => Decision {
+                included: false,
+                rule: Some(format!("exclude {}", rule)),
+            },
+            (_, None) => Decision {
+                included: self.include.is_empty(),
+                rule: None,
+            },
+        }
+    }
+
+    pub fn includes(&self, chat: &ChatSubject) -> bool {
+        self.decide(chat).included
+    }
+}
+
+/// The most specific rule matching `chat`, as (specificity, rule text).
+fn best_match<'a>(rules: &'a [Rule], chat: &ChatSubject) -> Option<(u8, &'a str)> {
+    rules
+        .iter()
+        .filter(|rule| rule.matcher.matches(chat))
+        .max_by_key(|rule| rule.matcher.specificity())
+        .map(|rule| (rule.matcher.specificity(), rule.text.as_str()))
+}
+
+/// `*` is any run of characters, `?` any one; ASCII case is ignored.
+fn glob_matches(glob: &str, text: &str) -> bool {
+    let glob: Vec<char> = glob.chars().map(|c| c.to_ascii_lowercase()).collect();
+    let text: Vec<char> = text.chars().map(|c| c.to_ascii_lowercase()).collect();
+    let (mut g, mut t) = (0, 0);
+    // Where the last `*` was, and the text position it has swallowed up to.
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match glob.get(g) {
+            Some('*') => {
+                star = Some((g, t));
+                g += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                g += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_g, star_t)) => {
+                    g = star_g + 1;
+                    t = star_t + 1;
+                    star = Some((star_g, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    glob[g..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chat(chat_id: i64, kind: &str, title: &str) -> ChatSubject {
+        ChatSubject {
+            chat_id,
+            kind: kind.to_string(),
+            title: title.to_string(),
+            link_id: None,
+        }
+    }
+
+    fn rules(include: &[&str], exclude: &[&str]) -> ChatRules {
+        let owned = |rules: &[&str]| rules.iter().map(|r| r.to_string()).collect::<Vec<_>>();
+        ChatRules::parse(&owned(include), &owned(exclude)).unwrap()
+    }
+
+    #[test]
+    fn globs() {
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("*", "아무거나"));
+        assert!(glob_matches("Rust*", "rust 스터디"));
+        assert!(glob_matches("*스터디", "Rust 스터디"));
+        assert!(glob_matches("*a*b*", "xxaxxbxx"));
+        assert!(glob_matches("팀?", "팀A"));
+        assert!(!glob_matches("팀?", "팀"));
+        assert!(!glob_matches("Rust", "Rust 스터디"));
+        assert!(!glob_matches("*a*b", "xxaxxbxc"));
+    }
+
+    #[test]
+    fn parses_every_rule_form() {
+        let parse = |text: &str| Rule::parse(text).unwrap().matcher;
+        assert_eq!(parse("chat:18231"), Matcher::Chat(18231));
+        assert_eq!(parse("link: 77"), Matcher::Link(77));
+        assert_eq!(parse("title:가족*"), Matcher::Title("가족*".into()));
+        assert_eq!(parse("type:dm"), Matcher::Type(vec!["DirectChat"]));
+        assert_eq!(
+            parse("type:OPENGROUP"),
+            Matcher::Type(vec!["OpenMultiChat"])
+        );
+        assert_eq!(parse("type:MultiChat"), Matcher::Type(vec!["MultiChat"]));
+        assert_eq!(
+            parse("type:open"),
+            Matcher::Type(vec!["OpenDirectChat", "OpenMultiChat"])
+        );
+        assert_eq!(
+            parse("OpenGroup:*"),
+            Matcher::TypeTitle(vec!["OpenMultiChat"], "*".into())
+        );
+        assert_eq!(parse(" * "), Matcher::All);
+
+        for bad in [
+            "chat:abc",
+            "type:channel",
+            "title:",
+            "Planet:*",
+            "18231",
+            "",
+        ] {
+            assert!(Rule::parse(bad).is_err(), "{bad:?} should not parse");
+        }
+    }
+
+    #[test]
+    fn no_rules_include_everything() {
+        let decision = rules(&[], &[]).decide(&chat(1, "DirectChat", "a"));
+        assert!(decision.included);
+        assert_eq!(decision.rule, None);
+    }
+
+    #[test]
+    fn include_rules_make_the_rest_excluded() {
+        let rules = rules(&["type:group"], &[]);
+        assert!(rules.includes(&chat(1, "MultiChat", "a")));
+        let other = rules.decide(&chat(2, "DirectChat", "b"));
+        assert!(!other.included);
+        assert_eq!(other.reason(), "no include rule matched");
+    }
+
+    #[test]
+    fn exclude_only_keeps_the_rest() {
+        let rules = rules(&[], &["type:dm"]);
+        assert!(rules.includes(&chat(1, "MultiChat", "a")));
+        let dm = rules.decide(&chat(2, "DirectChat", "b"));
+        assert!(!dm.included);
+        assert_eq!(dm.reason(), "exclude type:dm");
+    }
+
+    #[test]
+    fn explicit_id_beats_title_beats_type() {
+        let rules = rules(
+            &["chat:3", "title:공지*", "type:open"],
+            &["type:group", "title:*잡담*", "chat:4"],
+        );
+        // An included id wins over an excluded title and type.
+        let c = chat(3, "MultiChat", "잡담방");
+        assert_eq!(rules.decide(&c).reason(), "include chat:3");
+        // An excluded id wins over an included title.
+        let c = chat(4, "OpenMultiChat", "공지");
+        assert_eq!(rules.decide(&c).reason(), "exclude chat:4");
+        // A title include beats a type exclude.
+        let c = chat(5, "MultiChat", "공지사항");
+        assert_eq!(rules.decide(&c).reason(), "include title:공지*");
+        // A title exclude beats a type include.
+        let c = chat(6, "OpenMultiChat", "오픈 잡담");
+        assert_eq!(rules.decide(&c).reason(), "exclude title:*잡담*");
+        // Only the include type matches.
+        let c = chat(7, "OpenDirectChat", "x");
+        assert_eq!(rules.decide(&c).reason(), "include type:open");
+    }
+
+    #[test]
+    fn exclude_wins_ties() {
+        let rules = rules(
+            &["chat:1", "type:dm", "title:a*"],
+            &["chat:1", "type:dm", "title:*b"],
+        );
+        assert_eq!(
+            rules.decide(&chat(1, "MultiChat", "x")).reason(),
+            "exclude chat:1"
+        );
+        assert_eq!(
+            rules.decide(&chat(2, "DirectChat", "x")).reason(),
+            "exclude type:dm"
+        );
+        assert_eq!(
+            rules.decide(&chat(3, "MultiChat", "ab")).reason(),
+            "exclude title:*b"
+        );
+        let all = self::rules(&["*"], &["*"]);
+        assert!(!all.includes(&chat(4, "MultiChat", "x")));
+    }
+
+    #[test]
+    fn link_ids_and_typed_titles() {
+        let rules = rules(&["OpenGroup:*"], &["link:77"]);
+        let mut open = chat(1, "OpenMultiChat", "Rust 스터디");
+        assert_eq!(rules.decide(&open).reason(), "include OpenGroup:*");
+        open.link_id = Some(77);
+        assert_eq!(rules.decide(&open).reason(), "exclude link:77");
+        // The typed title only applies to its type.
+        assert!(!rules.includes(&chat(2, "MultiChat", "Rust 스터디")));
+    }
+
+    #[test]
+    fn unknown_kind_only_matches_ids_titles_and_star() {
+        let rules = rules(&["type:group", "title:팀*"], &[]);
+        assert!(!rules.includes(&chat(1, "", "x")));
+        assert!(rules.includes(&chat(1, "", "팀 채팅")));
+    }
+}
@@ -1,13 +1,28 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use anyhow::Result;
+use serde::Serialize;
 
+use crate::attachments::MediaUsage;
+use crate::chat_rules::{ChatRules, ChatSubject, Decision};
+use crate::commands::read::{link_suffix, print_context, with_link};
+use crate::cursor::MessageCursor;
+use crate::humanize;
 use crate::loco_helpers::loco_connect_with_auto_refresh;
+use crate::media_cache::MediaCache;
 use crate::message_db;
+use crate::model::MessageLink;
+use crate::progress::StderrProgress;
+use crate::report::{ActivityReport, ReportInput, ReportPeriod};
 use crate::util::{
-    extract_chat_type, format_time, get_bson_i32, get_bson_i64, get_bson_str, get_creds,
-    message_type_label, parse_since_date, print_section_title, print_table, truncate, type_label,
+    display_safe, extract_chat_type, format_bytes, format_time, get_bson_i32, get_bson_i64,
+    get_bson_str, get_bson_timestamp, get_creds, get_rest_client, message_type_label,
+    parse_date_range, parse_since_date, percent_change, print_section_title, print_table, truncate,
+    type_label,
 };
+use crate::warnings::{warn, WarningCode};
+
+const COMPARE_TOP_AUTHORS: usize = 5;
 
 pub fn cmd_stats(
     chat_id: i64,
@@ -112,7 +127,7 @@ pub fn cmd_stats(
                 let log_id = get_bson_i64(doc, &["logId"]);
                 let author_id = get_bson_i64(doc, &["authorId"]);
                 let msg_type = get_bson_i32(doc, &["type"]);
-                let send_at = get_bson_i64(doc, &["sendAt"]);
+                let send_at = get_bson_timestamp(doc, &["sendAt"]);
 
                 // Apply since filter
                 if let Some(min_ts) = since_ts {
@@ -175,13 +190,13 @@ pub fn cmd_stats(
                 (uid, name, count)
             })
             .collect();
-        author_stats.sort_by(|a, b| b.2.cmp(&a.2));
+        author_stats.sort_by_key(|b| std::cmp::Reverse(b.2));
 
         let mut type_stats: Vec<(i32, &str, usize)> = type_counts
             .iter()
             .map(|(&t, &count)| (t, message_type_label(t), count))
             .collect();
-        type_stats.sort_by(|a, b| b.2.cmp(&a.2));
+        type_stats.sort_by_key(|b| std::cmp::Reverse(b.2));
 
         // Find peak hour
         let peak_hour = hourly_counts
@@ -233,7 +248,7 @@ pub fn cmd_stats(
                 "avg_daily_messages": format!("{:.1}", avg_daily),
                 "peak_hour": peak_hour,
             });
-            println!("{}", serde_json::to_string_pretty(&output)?);
+            crate::util::output_json(&output)?;
             return Ok(());
         }
 
@@ -305,9 +320,511 @@ pub fn cmd_stats(
     })
 }
 
-pub fn cmd_cache(chat_id: i64, limit: Option<usize>, json: bool) -> Result<()> {
+/// `stats --media`: messages per type and attachment sizes, from the local
+/// message cache plus up to `pages` REST pages of recent messages, with an
+/// estimate of what downloading every photo, video and file would take.
+pub fn cmd_stats_media(chat_id: i64, pages: usize, json: bool) -> Result<()> {
+    let mut messages: BTreeMap<i64, (i64, String)> = BTreeMap::new();
+    match message_db::MessageDb::open().and_then(|db| db.get_messages(chat_id, 0)) {
+        Ok(cached) => {
+            for m in cached {
+                messages.insert(m.log_id, (i64::from(m.message_type), m.attachment));
+            }
+        }
+        Err(err) => warn(
+            WarningCode::CacheUnavailable,
+            format!("local message cache not read: {}", err),
+        ),
+    }
+    let cached = messages.len();
+
+    if pages > 0 {
+        let client = get_rest_client()?;
+        let progress = StderrProgress::new("stats");
+        match client.get_all_messages_with_progress(chat_id, pages, &progress) {
+            Ok(fetched) => {
+                for m in fetched {
+                    messages.insert(m.log_id, (m.message_type, m.attachment));
+                }
+            }
+            Err(err) => warn(
+                WarningCode::PartialData,
+                format!("network pages skipped, counting cached messages only: {err}"),
+            ),
+        }
+    }
+
+    let usage = MediaUsage::from_messages(
+        messages
+            .values()
+            .map(|(message_type, attachment)| (*message_type, attachment.as_str())),
+    );
+    let estimate = usage.download_estimate();
+
+    if json {
+        let output = serde_json::json!({
+            "chat_id": chat_id,
+            "cached_messages": cached,
+            "network_messages": usage.total_messages - cached,
+            "usage": usage,
+            "download_estimate": estimate,
+        });
+        crate::util::output_json(&output)?;
+        return Ok(());
+    }
+
+    if usage.total_messages == 0 {
+        println!(
+            "No messages found ({} cached, {} network pages scanned).",
+            cached, pages
+        );
+        return Ok(());
+    }
+
+    print_section_title(&format!(
+        "Media Usage: chat {} ({} messages, {} cached)",
+        chat_id, usage.total_messages, cached
+    ));
+    let rows: Vec<Vec<String>> = usage
+        .categories
+        .iter()
+        .map(|u| {
+            let pct = u.messages as f64 / usage.total_messages as f64 * 100.0;
+            let (size, unknown) = match u.size {
+                Some(size) => (format_bytes(size.known_bytes), size.unknown.to_string()),
+                None => ("-".to_string(), "-".to_string()),
+            };
+            vec![
+                u.category.label().to_string(),
+                u.messages.to_string(),
+                format!("{:.1}%", pct),
+                size,
+                unknown,
+            ]
+        })
+        .collect();
+    print_table(&["Type", "Count", "%", "Size", "Size unknown"], rows);
+    println!();
+    let mut line = format!(
+        "Download estimate: {} for {} files",
+        format_bytes(estimate.known_bytes),
+        estimate.sized
+    );
+    if estimate.unknown > 0 {
+        line.push_str(&format!(", plus {} of unknown size", estimate.unknown));
+    }
+    println!("{line}");
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+struct AuthorCount {
+    user_id: i64,
+    name: String,
+    count: usize,
+}
+
+/// Aggregates for one `stats --compare` window.
+#[derive(Debug, Serialize)]
+struct WindowStats {
+    range: String,
+    start: i64,
+    end: i64,
+    messages: usize,
+    messages_per_day: f64,
+    active_members: usize,
+    top_authors: Vec<AuthorCount>,
+    /// "full", "partial" (data starts after the window opens) or "none".
+    coverage: &'static str,
+}
+
+fn window_stats(
+    range: &str,
+    (start, end): (i64, i64),
+    samples: &[(i64, i64)],
+    names: &HashMap<i64, String>,
+    data_start: Option<i64>,
+) -> WindowStats {
+    let mut counts: HashMap<i64, usize> = HashMap::new();
+    for &(author_id, send_at) in samples {
+        if send_at >= start && send_at < end {
+            *counts.entry(author_id).or_insert(0) += 1;
+        }
+    }
+    let messages: usize = counts.values().sum();
+    let days = ((end - start) as f64 / 86_400.0).max(1.0);
+
+    let mut top_authors: Vec<AuthorCount> = counts
+        .iter()
+        .map(|(&user_id, &count)| AuthorCount {
+            user_id,
+            name: names
+                .get(&user_id)
+                .cloned()
+                .unwrap_or_else(|| format!("User#{}", user_id)),
+            count,
+        })
+        .collect();
+    top_authors.sort_by(|a, b| b.count.cmp(&a.count).then(a.user_id.cmp(&b.user_id)));
+    top_authors.truncate(COMPARE_TOP_AUTHORS);
+
+    let coverage = if messages == 0 {
+        "none"
+    } else if data_start.is_some_and(|first| first > start) {
+        "partial"
+    } else {
+        "full"
+    };
+
+    WindowStats {
+        range: range.to_string(),
+        start,
+        end,
+        messages,
+        messages_per_day: messages as f64 / days,
+        active_members: counts.len(),
+        top_authors,
+        coverage,
+    }
+}
+
+fn format_delta(delta: Option<f64>) -> String {
+    match delta {
+        Some(d) => format!("{:+.1}%", d),
+        None => "n/a".to_string(),
+    }
+}
+
+/// Compare message volume and participation between two date ranges.
+/// Uses the local message cache when it has this chat, otherwise scans the
+/// chat history over LOCO.
+pub fn cmd_stats_compare(chat_id: i64, range1: &str, range2: &str, json: bool) -> Result<()> {
+    let windows = [
+        (range1, parse_date_range(range1)?),
+        (range2, parse_date_range(range2)?),
+    ];
+    let scan_start = windows.iter().map(|(_, (s, _))| *s).min().unwrap_or(0);
+    let scan_end = windows.iter().map(|(_, (_, e))| *e).max().unwrap_or(0);
+
+    let db = message_db::MessageDb::open()?;
+    let mut samples: Vec<(i64, i64)> = Vec::new();
+    let mut names: HashMap<i64, String> = HashMap::new();
+
+    let (source, data_start) = if let Some((first, _)) = db.time_bounds(chat_id)? {
+        for m in db.get_messages_between(chat_id, scan_start, scan_end)? {
+            if !m.author_name.is_empty() {
+                names.insert(m.author_id, m.author_name.clone());
+            }
+            samples.push((m.author_id, m.send_at));
+        }
+        ("cache", Some(first))
+    } else {
+        eprintln!("[stats] No cached messages for this chat; scanning via LOCO...");
+        let mut messages = Vec::new();
+        let first = scan_loco_history(chat_id, scan_start, scan_end, &mut messages, &mut names)?;
+        samples.extend(messages.iter().map(|m| (m.author_id, m.send_at)));
+        ("loco", first)
+    };
+
+    let stats: Vec<WindowStats> = windows
+        .iter()
+        .map(|(range, bounds)| window_stats(range, *bounds, &samples, &names, data_start))
+        .collect();
+
+    for w in &stats {
+        match w.coverage {
+            "none" => warn(
+                WarningCode::PartialData,
+                format!(
+                    "No messages found for {} — shown as zeros; the {} may not cover it.",
+                    w.range, source
+                ),
+            ),
+            "partial" => warn(
+                WarningCode::PartialData,
+                format!(
+                    "{} data starts at {}, after {} begins — figures are partial.",
+                    source,
+                    format_time(data_start.unwrap_or(0)),
+                    w.range
+                ),
+            ),
+            _ => {}
+        }
+    }
+
+    let (a, b) = (&stats[0], &stats[1]);
+    let deltas = [
+        (
+            "messages",
+            percent_change(a.messages as f64, b.messages as f64),
+        ),
+        (
+            "messages_per_day",
+            percent_change(a.messages_per_day, b.messages_per_day),
+        ),
+        (
+            "active_members",
+            percent_change(a.active_members as f64, b.active_members as f64),
+        ),
+    ];
+
+    if json {
+        let output = serde_json::json!({
+            "chat_id": chat_id,
+            "source": source,
+            "windows": stats,
+            "deltas": deltas
+                .iter()
+                .map(|(k, v)| (k.to_string(), serde_json::json!(v)))
+                .collect::<serde_json::Map<_, _>>(),
+        });
+        crate::util::output_json(&output)?;
+        return Ok(());
+    }
+
+    print_section_title(&format!("Chat Stats Compare: {} ({})", chat_id, source));
+    println!();
+    let rows = vec![
+        vec![
+            "Messages".to_string(),
+            a.messages.to_string(),
+            b.messages.to_string(),
+            format_delta(deltas[0].1),
+        ],
+        vec![
+            "Msgs/day".to_string(),
+            format!("{:.1}", a.messages_per_day),
+            format!("{:.1}", b.messages_per_day),
+            format_delta(deltas[1].1),
+        ],
+        vec![
+            "Active members".to_string(),
+            a.active_members.to_string(),
+            b.active_members.to_string(),
+            format_delta(deltas[2].1),
+        ],
+    ];
+    print_table(&["", &a.range, &b.range, "Change"], rows);
+    println!();
+
+    print_section_title("Top Authors");
+    let author_cell = |w: &WindowStats, i: usize| {
+        w.top_authors
+            .get(i)
+            .map(|c| format!("{} ({})", c.name, c.count))
+            .unwrap_or_default()
+    };
+    let top_rows: Vec<Vec<String>> = (0..a.top_authors.len().max(b.top_authors.len()))
+        .map(|i| vec![format!("#{}", i + 1), author_cell(a, i), author_cell(b, i)])
+        .collect();
+    print_table(&["", &a.range, &b.range], top_rows);
+
+    Ok(())
+}
+
+/// Collect messages in `[start, end)` via SYNCMSG. Returns the earliest
+/// timestamp seen, which bounds how far back the scan could reach.
+fn scan_loco_history(
+    chat_id: i64,
+    start: i64,
+    end: i64,
+    samples: &mut Vec<message_db::CachedMessage>,
+    names: &mut HashMap<i64, String>,
+) -> Result<Option<i64>> {
+    let creds = get_creds()?;
+    let rt = tokio::runtime::Runtime::new()?;
+    rt.block_on(async {
+        let mut client = crate::loco::client::LocoClient::new(creds);
+        loco_connect_with_auto_refresh(&mut client).await?;
+
+        let room_info = client
+            .send_command("CHATONROOM", bson::doc! { "chatId": chat_id })
+            .await?;
+        if room_info.status() != 0 {
+            anyhow::bail!("CHATONROOM failed (status={})", room_info.status());
+        }
+        if let Ok(members) = room_info.body.get_array("m") {
+            names.extend(crate::util::build_member_name_map_from_bson(members));
+        }
+
+        let last_log_id = room_info.body.get_i64("l").unwrap_or(0);
+        let mut cur = 0i64;
+        let mut earliest: Option<i64> = None;
+        let mut scanned = 0usize;
+        if last_log_id == 0 {
+            return Ok(None);
+        }
+
+        loop {
+            let response = client
+                .send_command(
+                    "SYNCMSG",
+                    bson::doc! {
+                        "chatId": chat_id,
+                        "cur": cur,
+                        "cnt": 100_i32,
+                        "max": last_log_id,
+                    },
+                )
+                .await?;
+            if response.status() != 0 {
+                break;
+            }
+            let msgs = match response.body.get_array("chatLogs") {
+                Ok(msgs) => msgs.clone(),
+                Err(_) => break,
+            };
+            if msgs.is_empty() {
+                break;
+            }
+
+            let mut past_end = false;
+            for msg in &msgs {
+                let Some(doc) = msg.as_document() else {
+                    continue;
+                };
+                let send_at = get_bson_timestamp(doc, &["sendAt"]);
+                cur = get_bson_i64(doc, &["logId"]);
+                scanned += 1;
+                if send_at <= 0 {
+                    continue;
+                }
+                earliest = Some(earliest.map_or(send_at, |e| e.min(send_at)));
+                if send_at >= end {
+                    past_end = true;
+                    break;
+                }
+                if send_at >= start {
+                    samples.push(message_db::CachedMessage {
+                        chat_id,
+                        log_id: cur,
+                        author_id: get_bson_i64(doc, &["authorId"]),
+                        author_name: String::new(),
+                        message_type: get_bson_i32(doc, &["type"]),
+                        message: get_bson_str(doc, &["message"]),
+                        attachment: get_bson_str(doc, &["attachment"]),
+                        send_at,
+                    });
+                }
+            }
+
+            if past_end || response.body.get_bool("isOK").unwrap_or(true) {
+                break;
+            }
+            if scanned.is_multiple_of(500) {
+                eprintln!("[stats] {} messages scanned...", scanned);
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+
+        Ok(earliest)
+    })
+}
+
+/// Aggregate a `report` period and the one before it, from the message
+/// cache when it has this chat, otherwise from a LOCO history scan.
+pub fn build_report(chat_id: i64, period: ReportPeriod) -> Result<ActivityReport> {
+    let now = chrono::Utc::now().timestamp();
+    let (previous_start, _, end) = period.windows(now);
+
+    let db = message_db::MessageDb::open()?;
+    let mut names: HashMap<i64, String> = HashMap::new();
+    let messages = if db.time_bounds(chat_id)?.is_some() {
+        db.get_messages_between(chat_id, previous_start, end)?
+    } else {
+        eprintln!("[report] No cached messages for this chat; scanning via LOCO...");
+        let mut messages = Vec::new();
+        scan_loco_history(chat_id, previous_start, end, &mut messages, &mut names)?;
+        messages
+    };
+
+    let chat_title = get_rest_client()
+        .and_then(|client| Ok(client.get_all_chats()?))
+        .ok()
+        .and_then(|rooms| rooms.into_iter().find(|room| room.chat_id == chat_id))
+        .map(|room| room.display_title())
+        .unwrap_or_else(|| format!("Chat {}", chat_id));
+
+    Ok(ActivityReport::build(
+        &ReportInput {
+            chat_id,
+            chat_title: &chat_title,
+            period,
+            now,
+            messages: &messages,
+            names: &names,
+        },
+        &chrono::Local,
+    ))
+}
+
+/// The content mode `chat_id` is synced in: `--no-content`, else
+/// `[archive] content`, else whatever the chat was archived in. A mode
+/// other than the recorded one is refused unless `convert`, which switches
+/// the cached rows over (see [`message_db::MessageDb::set_content_mode`]).
+fn resolve_content_mode(
+    db: &message_db::MessageDb,
+    chat_id: i64,
+    no_content: bool,
+    convert: bool,
+) -> Result<message_db::ContentMode> {
+    use message_db::ContentMode;
+    let requested = if no_content {
+        Some(ContentMode::Hash)
+    } else {
+        crate::config::load_config()?.archive.content
+    };
+    let current = db.content_mode(chat_id)?;
+    let wanted = requested
+        .or(if convert { None } else { current })
+        .unwrap_or(ContentMode::Text);
+    match current {
+        Some(current) if current == wanted => {}
+        Some(current) if !convert => {
+            let conversion = match wanted {
+                ContentMode::Hash => "hashes the cached messages in place",
+                ContentMode::Text => {
+                    "drops the hashed messages and fetches them again with their text"
+                }
+            };
+            anyhow::bail!(
+                "Chat {chat_id} is archived with content = \"{current}\", but this sync asks \
+                 for \"{wanted}\". Pass --convert to switch it ({conversion}), or sync it as \
+                 \"{current}\"{}.",
+                match current {
+                    ContentMode::Hash => " with --no-content",
+                    ContentMode::Text => " (no --no-content, no [archive] content = \"hash\")",
+                }
+            );
+        }
+        Some(current) => {
+            let changed = db.set_content_mode(chat_id, wanted)?;
+            eprintln!(
+                "[cache] Chat {chat_id}: content {current} -> {wanted} ({changed} messages {})",
+                match wanted {
+                    ContentMode::Hash => "hashed",
+                    ContentMode::Text => "dropped for refetch",
+                }
+            );
+        }
+        None => {
+            db.set_content_mode(chat_id, wanted)?;
+        }
+    }
+    Ok(wanted)
+}
+
+pub fn cmd_cache(
+    chat_id: i64,
+    limit: Option<usize>,
+    no_content: bool,
+    convert: bool,
+    json: bool,
+) -> Result<()> {
     let creds = get_creds()?;
     let db = message_db::MessageDb::open()?;
+    let content = resolve_content_mode(&db, chat_id, no_content, convert)?;
 
     let existing_cursor = db.get_sync_cursor(chat_id)?.unwrap_or(0);
 
@@ -395,7 +912,7 @@ pub fn cmd_cache(chat_id: i64, limit: Option<usize>, json: bool) -> Result<()> {
                 let log_id = get_bson_i64(doc, &["logId"]);
                 let author_id = get_bson_i64(doc, &["authorId"]);
                 let msg_type = get_bson_i32(doc, &["type"]);
-                let send_at = get_bson_i64(doc, &["sendAt"]);
+                let send_at = get_bson_timestamp(doc, &["sendAt"]);
                 let message = doc.get_str("msg").unwrap_or("").to_string();
                 let attachment = doc.get_str("attachment").unwrap_or("").to_string();
                 let author_name = member_names.get(&author_id).cloned().unwrap_or_default();
@@ -436,25 +953,207 @@ pub fn cmd_cache(chat_id: i64, limit: Option<usize>, json: bool) -> Result<()> {
                 "chat_id": chat_id,
                 "synced": synced,
                 "cursor": cur,
+                "content": content,
                 "total_cached": db.total_count()?,
             });
-            println!("{}", serde_json::to_string_pretty(&output)?);
+            crate::util::output_json(&output)?;
         } else {
             eprintln!("[cache] Synced {} new messages (cursor={})", synced, cur);
-            eprintln!("[cache] Total cached: {} messages", db.total_count()?);
+            eprintln!(
+                "[cache] Total cached: {} messages",
+                humanize::count(db.total_count()?.max(0) as u64, humanize::lang())
+            );
         }
 
         Ok(())
     })
 }
 
-pub fn cmd_cache_search(query: &str, chat_id: Option<i64>, count: usize, json: bool) -> Result<()> {
+/// The chat list as a sync sees it.
+pub struct SyncPlan {
+    /// Every chat in the list, with the `[sync]` rules' verdict on it.
+    pub decisions: Vec<(ChatSubject, Decision)>,
+    /// Archived chats that left the list or came back, when recorded.
+    pub changes: message_db::ChatListChanges,
+}
+
+/// Every chat in the chat list, with the `[sync]` rules' verdict on it.
+/// With `record`, named rooms' titles go into the archive's title history
+/// and archived chats missing from the list are marked departed (rejoined
+/// ones unmarked), as every sync does.
+pub fn sync_decisions(record: bool) -> Result<SyncPlan> {
+    let config = crate::config::load_config()?;
+    let rules = ChatRules::parse(&config.sync.include, &config.sync.exclude)?;
+    let rooms = get_rest_client()?.get_all_chats()?;
+    let mut changes = message_db::ChatListChanges::default();
+    if record {
+        let db = message_db::MessageDb::open()?;
+        let now = chrono::Utc::now().timestamp();
+        let renames = db.record_titles(
+            rooms.iter().map(|room| (room.chat_id, room.title.as_str())),
+            now,
+        )?;
+        for rename in renames {
+            eprintln!(
+                "[cache] Chat {} renamed from \"{}\" to \"{}\"",
+                rename.chat_id,
+                display_safe(&rename.from),
+                display_safe(&rename.to)
+            );
+        }
+        changes = db.record_chat_list(rooms.iter().map(|room| room.chat_id), now)?;
+        for chat_id in &changes.departed {
+            let title = db
+                .title_history(*chat_id)?
+                .pop()
+                .map(|entry| format!(" \"{}\"", display_safe(&entry.title)))
+                .unwrap_or_default();
+            eprintln!(
+                "[cache] Chat {}{} is no longer in the chat list (left or deleted); its archive is kept",
+                chat_id, title
+            );
+        }
+        for chat_id in &changes.returned {
+            eprintln!("[cache] Chat {} is back in the chat list", chat_id);
+        }
+    }
+    let decisions = rooms
+        .iter()
+        .map(|room| {
+            let chat = ChatSubject::from_room(room);
+            let decision = rules.decide(&chat);
+            (chat, decision)
+        })
+        .collect();
+    Ok(SyncPlan { decisions, changes })
+}
+
+/// Chat ids the `[sync]` rules select.
+pub fn sync_chat_ids() -> Result<Vec<i64>> {
+    Ok(sync_decisions(true)?
+        .decisions
+        .into_iter()
+        .filter(|(_, decision)| decision.included)
+        .map(|(chat, _)| chat.chat_id)
+        .collect())
+}
+
+pub fn cmd_cache_all(
+    limit: Option<usize>,
+    no_content: bool,
+    convert: bool,
+    explain: bool,
+    json: bool,
+) -> Result<()> {
+    let SyncPlan { decisions, changes } = sync_decisions(!explain)?;
+    if explain {
+        if json {
+            let rows: Vec<serde_json::Value> = decisions
+                .iter()
+                .map(|(chat, decision)| {
+                    serde_json::json!({
+                        "chat_id": chat.chat_id,
+                        "type": chat.kind,
+                        "title": chat.title,
+                        "link_id": chat.link_id,
+                        "included": decision.included,
+                        "rule": decision.rule,
+                    })
+                })
+                .collect();
+            crate::util::output_json(&rows)?;
+        } else {
+            let rows = decisions
+                .iter()
+                .map(|(chat, decision)| {
+                    vec![
+                        chat.chat_id.to_string(),
+                        type_label(&chat.kind).to_string(),
+                        truncate(&display_safe(&chat.title), 30),
+                        if decision.included { "sync" } else { "skip" }.to_string(),
+                        decision.reason(),
+                    ]
+                })
+                .collect();
+            print_table(&["Chat ID", "Type", "Title", "", "Rule"], rows);
+        }
+        return Ok(());
+    }
+
+    let chats: Vec<i64> = decisions
+        .iter()
+        .filter(|(_, decision)| decision.included)
+        .map(|(chat, _)| chat.chat_id)
+        .collect();
+    if chats.is_empty() {
+        anyhow::bail!(
+            "The [sync] rules select none of {} chats; `cache --all --explain` shows why.",
+            decisions.len()
+        );
+    }
+    let mut failed = Vec::new();
+    for (i, chat_id) in chats.iter().enumerate() {
+        eprintln!("[cache] Chat {} ({}/{})", chat_id, i + 1, chats.len());
+        if let Err(err) = cmd_cache(*chat_id, limit, no_content, convert, false) {
+            eprintln!("[cache] Chat {} failed: {:#}", chat_id, err);
+            failed.push((*chat_id, format!("{err:#}")));
+        }
+    }
+    if json {
+        crate::util::output_json(&serde_json::json!({
+            "chats": chats.len(),
+            "skipped": decisions.len() - chats.len(),
+            "departed": changes.departed,
+            "returned": changes.returned,
+            "failed": failed
+                .iter()
+                .map(|(chat_id, error)| serde_json::json!({"chat_id": chat_id, "error": error}))
+                .collect::<Vec<_>>(),
+        }))?;
+    } else {
+        eprintln!(
+            "[cache] Synced {} of {} chats ({} skipped by rules)",
+            chats.len() - failed.len(),
+            chats.len(),
+            decisions.len() - chats.len()
+        );
+        if !changes.departed.is_empty() {
+            let ids: Vec<String> = changes.departed.iter().map(i64::to_string).collect();
+            eprintln!(
+                "[cache] {} archived chat(s) left the chat list: {} (hidden from cache-search and \
+                 archive read unless --include-departed)",
+                ids.len(),
+                ids.join(", ")
+            );
+        }
+    }
+    if !failed.is_empty() {
+        anyhow::bail!("{} of {} chats failed to sync", failed.len(), chats.len());
+    }
+    Ok(())
+}
+
+pub fn cmd_cache_search(
+    query: &str,
+    chat_id: Option<i64>,
+    count: usize,
+    json: bool,
+    links: bool,
+    normalize: bool,
+    include_departed: bool,
+) -> Result<()> {
     let db = message_db::MessageDb::open()?;
+    if normalize && !db.is_normalized()? {
+        anyhow::bail!(
+            "The cache search index is not normalized. Run 'openkakao-rs cache-reindex --normalize' first."
+        );
+    }
 
     let results = if let Some(cid) = chat_id {
+        refuse_departed(&db, cid, include_departed)?;
         db.search(cid, query, count)?
     } else {
-        db.search_all(query, count)?
+        db.search_all(query, count, include_departed)?
     };
 
     if json {
@@ -472,8 +1171,12 @@ pub fn cmd_cache_search(query: &str, chat_id: Option<i64>, count: usize, json: b
                     "time": format_time(m.send_at),
                 })
             })
+            .map(|v| {
+                let chat_id = v["chat_id"].as_i64().unwrap_or(0);
+                with_link(v, chat_id, links)
+            })
             .collect();
-        println!("{}", serde_json::to_string_pretty(&output)?);
+        crate::util::output_json(&output)?;
         return Ok(());
     }
 
@@ -503,15 +1206,89 @@ pub fn cmd_cache_search(query: &str, chat_id: Option<i64>, count: usize, json: b
         };
 
         println!(
-            "  {} [chat:{}] {}{}: {}",
+            "  {} [chat:{}] {}{}: {}{}",
             time,
             m.chat_id,
-            author,
+            display_safe(&author),
             type_tag,
-            truncate(&m.message, 100)
+            display_safe(&truncate(&m.message, 100)),
+            link_suffix(m.chat_id, m.log_id, links)
+        );
+    }
+
+    Ok(())
+}
+
+/// Archived chats that left the chat list are kept out of cache reads
+/// unless asked for.
+fn refuse_departed(db: &message_db::MessageDb, chat_id: i64, include_departed: bool) -> Result<()> {
+    match db.departed_at(chat_id)? {
+        Some(departed_at) if !include_departed => anyhow::bail!(
+            "Chat {} has been missing from the chat list since {} (left or deleted). \
+             Its archive is kept; pass --include-departed to use it.",
+            chat_id,
+            format_time(departed_at)
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Messages shown on each side of the target by `open`.
+const OPEN_CONTEXT_RADIUS: usize = 5;
+
+pub fn cmd_link(chat_id: i64, log_id: i64, open: bool, json: bool) -> Result<()> {
+    let link = MessageLink::new(chat_id, log_id);
+    if open {
+        return cmd_open(&link.to_string(), json);
+    }
+    if json {
+        crate::util::output_json(&serde_json::json!({
+            "chat_id": chat_id,
+            "log_id": log_id,
+            "link": link.to_string(),
+        }))?;
+    } else {
+        println!("{}", link);
+    }
+    Ok(())
+}
+
+pub fn cmd_open(reference: &str, json: bool) -> Result<()> {
+    let link: MessageLink = reference.parse().map_err(anyhow::Error::msg)?;
+    let db = message_db::MessageDb::open()?;
+    let context = db.get_context(link.chat_id, link.log_id, OPEN_CONTEXT_RADIUS)?;
+    if !context.iter().any(|m| m.log_id == link.log_id) {
+        anyhow::bail!(
+            "Message {} is not in the local cache. Run 'openkakao-rs cache {}' or 'openkakao-rs read {}' first.",
+            link.log_id,
+            link.chat_id,
+            link.chat_id
         );
     }
 
+    print_context(link, &context, json)
+}
+
+/// Rebuild the cache search index in the requested form.
+pub fn cmd_cache_reindex(normalize: bool, json: bool) -> Result<()> {
+    let db = message_db::MessageDb::open()?;
+    let was_normalized = db.is_normalized()?;
+    let indexed = db.reindex(normalize)?;
+
+    if json {
+        crate::util::output_json(&serde_json::json!({
+            "status": "ok",
+            "normalized": normalize,
+            "was_normalized": was_normalized,
+            "messages": indexed,
+        }))?;
+    } else {
+        println!(
+            "Reindexed {} cached messages ({})",
+            indexed,
+            if normalize { "normalized" } else { "as sent" }
+        );
+    }
     Ok(())
 }
 
@@ -519,27 +1296,32 @@ pub fn cmd_cache_stats(json: bool) -> Result<()> {
     let db = message_db::MessageDb::open()?;
     let total = db.total_count()?;
     let chat_stats = db.chat_stats()?;
+    let departed = db.departed_chats()?;
 
     if json {
         let output = serde_json::json!({
+            "schema_version": db.schema_version()?,
             "total_messages": total,
             "chats": chat_stats.iter().map(|(cid, count, last_ts)| {
                 serde_json::json!({
                     "chat_id": cid,
                     "message_count": count,
                     "last_message": format_time(*last_ts),
+                    "departed_at": departed.get(cid),
                 })
             }).collect::<Vec<_>>(),
         });
-        println!("{}", serde_json::to_string_pretty(&output)?);
+        crate::util::output_json(&output)?;
         return Ok(());
     }
 
     print_section_title("Local Message Cache");
+    let lang = humanize::lang();
     println!(
-        "  Total: {} messages across {} chats",
-        total,
-        chat_stats.len()
+        "  Total: {} messages across {} chats (schema v{})",
+        humanize::count(total.max(0) as u64, lang),
+        chat_stats.len(),
+        db.schema_version()?
     );
     println!();
 
@@ -547,11 +1329,441 @@ pub fn cmd_cache_stats(json: bool) -> Result<()> {
         let rows: Vec<Vec<String>> = chat_stats
             .iter()
             .map(|(cid, count, last_ts)| {
-                vec![cid.to_string(), count.to_string(), format_time(*last_ts)]
+                vec![
+                    cid.to_string(),
+                    count.to_string(),
+                    format_time(*last_ts),
+                    departed
+                        .get(cid)
+                        .map(|at| format_time(*at))
+                        .unwrap_or_default(),
+                ]
             })
             .collect();
-        print_table(&["Chat ID", "Messages", "Last Msg"], rows);
+        print_table(&["Chat ID", "Messages", "Last Msg", "Departed"], rows);
     }
 
     Ok(())
 }
+
+/// `archive titles <chat_id>`: every title a sync has seen, oldest first.
+pub fn cmd_archive_titles(chat_id: i64, json: bool) -> Result<()> {
+    let titles = crate::commands::chats::title_history(chat_id)?;
+    if json {
+        return crate::util::output_json(&serde_json::json!({
+            "chat_id": chat_id,
+            "titles": titles,
+        }));
+    }
+    if titles.is_empty() {
+        println!(
+            "No titles recorded for chat {}. `openkakao-rs cache --all` records them.",
+            chat_id
+        );
+        return Ok(());
+    }
+    let rows: Vec<Vec<String>> = titles
+        .iter()
+        .map(|entry| {
+            vec![
+                format_time(entry.observed_at),
+                display_safe(&entry.title).into_owned(),
+            ]
+        })
+        .collect();
+    print_table(&["Seen since", "Title"], rows);
+    Ok(())
+}
+
+/// `archive info`: where the archive lives, how much it holds and the size
+/// of the media cache next to it.
+pub fn cmd_archive_info(json: bool) -> Result<()> {
+    let db = message_db::MessageDb::open()?;
+    let path = message_db::db_path()?;
+    let db_bytes = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    let total = db.total_count()?;
+    let chats = db.chat_stats()?.len();
+    let media = MediaCache::open()?;
+
+    if json {
+        return crate::util::output_json(&serde_json::json!({
+            "path": path.display().to_string(),
+            "bytes": db_bytes,
+            "schema_version": db.schema_version()?,
+            "total_messages": total,
+            "chats": chats,
+            "media_cache": {
+                "files": media.len(),
+                "bytes": media.total_bytes(),
+                "max_bytes": media.max_bytes(),
+            },
+        }));
+    }
+
+    print_section_title("Archive");
+    let lang = humanize::lang();
+    println!("  Path: {}", path.display());
+    println!(
+        "  Size: {} (schema v{})",
+        format_bytes(db_bytes),
+        db.schema_version()?
+    );
+    println!(
+        "  Total: {} messages across {} chats",
+        humanize::count(total.max(0) as u64, lang),
+        chats
+    );
+    println!(
+        "  Media cache: {} files, {} (cap {})",
+        humanize::count(media.len() as u64, lang),
+        format_bytes(media.total_bytes()),
+        format_bytes(media.max_bytes())
+    );
+    Ok(())
+}
+
+/// `archive stats <chat_id>`: per-author activity from the archive alone.
+pub fn cmd_archive_stats(chat_id: i64, json: bool) -> Result<()> {
+    let db = message_db::MessageDb::open()?;
+    let authors = db.author_activity(chat_id)?;
+    let content = db.content_mode(chat_id)?;
+    if json {
+        return crate::util::output_json(&serde_json::json!({
+            "chat_id": chat_id,
+            "content": content,
+            "authors": authors,
+        }));
+    }
+    if authors.is_empty() {
+        println!(
+            "No cached messages for chat {}. Run 'openkakao-rs cache {}' first.",
+            chat_id, chat_id
+        );
+        return Ok(());
+    }
+    let rows: Vec<Vec<String>> = authors
+        .iter()
+        .map(|author| {
+            let name = if author.author_name.is_empty() {
+                format!("User#{}", author.author_id)
+            } else {
+                display_safe(&author.author_name).into_owned()
+            };
+            vec![
+                name,
+                author.messages.to_string(),
+                author.characters.to_string(),
+                format_time(author.first_at),
+                format_time(author.last_at),
+            ]
+        })
+        .collect();
+    print_table(&["Author", "Messages", "Chars", "First", "Last"], rows);
+    Ok(())
+}
+
+/// `archive read <chat_id>`: the newest `count` cached messages. Messages
+/// deleted for everyone show as the placeholder unless `view` is
+/// [`message_db::DeletionView::Show`], which prints the archived original
+/// tagged `[deleted]`.
+/// In a chat archived with `content = "hash"` each line shows the stored
+/// length in place of the text.
+pub fn cmd_archive_read(
+    chat_id: i64,
+    count: usize,
+    view: message_db::DeletionView,
+    include_departed: bool,
+    json: bool,
+) -> Result<()> {
+    let db = message_db::MessageDb::open()?;
+    refuse_departed(&db, chat_id, include_departed)?;
+    let rows = db.get_messages_before(chat_id, i64::MAX, count)?;
+    if rows.is_empty() && !json {
+        println!(
+            "No cached messages for chat {}. Run 'openkakao-rs cache {}' first.",
+            chat_id, chat_id
+        );
+        return Ok(());
+    }
+    let rows = message_db::apply_deletions(rows, &db.deleted_log_ids(chat_id)?, view);
+    let digests = db.content_digests(chat_id)?;
+    if json {
+        let output: Vec<serde_json::Value> = rows
+            .iter()
+            .map(|(m, deleted)| {
+                let mut row = serde_json::json!({
+                    "chat_id": m.chat_id,
+                    "log_id": m.log_id,
+                    "author_id": m.author_id,
+                    "author_name": m.author_name,
+                    "message_type": m.message_type,
+                    "message": m.message,
+                    "send_at": m.send_at,
+                    "deleted": deleted,
+                    "content_stored": !digests.contains_key(&m.log_id),
+                });
+                if let Some(digest) = digests.get(&m.log_id) {
+                    row["content_hash"] = digest.hash.clone().into();
+                    row["length"] = digest.length.into();
+                }
+                row
+            })
+            .collect();
+        return crate::util::output_json(&output);
+    }
+    if db.content_mode(chat_id)? == Some(message_db::ContentMode::Hash) {
+        println!(
+            "Chat {} is archived without message content (content = \"hash\"): only authors, \
+             times and lengths were stored.",
+            chat_id
+        );
+    }
+    for (m, deleted) in &rows {
+        let author = if m.author_name.is_empty() {
+            format!("User#{}", m.author_id)
+        } else {
+            m.author_name.clone()
+        };
+        let tag = if *deleted && view == message_db::DeletionView::Show {
+            " [deleted]"
+        } else {
+            ""
+        };
+        let message = match digests.get(&m.log_id) {
+            Some(digest) if !*deleted || view == message_db::DeletionView::Show => {
+                format!("[content not stored, {} chars]", digest.length)
+            }
+            _ => display_safe(&m.message).into_owned(),
+        };
+        println!(
+            "{} {}: {}{}",
+            format_time(m.send_at),
+            display_safe(&author),
+            message,
+            tag
+        );
+    }
+    Ok(())
+}
+
+/// `archive gaps <chat_id>`: jumps in the archived log ids that look like
+/// missing messages rather than a quiet chat (see [`crate::gaps`]).
+pub fn cmd_archive_gaps(chat_id: i64, json: bool) -> Result<()> {
+    let db = message_db::MessageDb::open()?;
+    let messages = db.log_times(chat_id)?;
+    let gaps = crate::gaps::find_gaps(&messages);
+    if json {
+        return crate::util::output_json(&serde_json::json!({
+            "chat_id": chat_id,
+            "messages": messages.len(),
+            "gaps": gaps,
+        }));
+    }
+    if messages.is_empty() {
+        println!(
+            "No cached messages for chat {}. Run 'openkakao-rs cache {}' first.",
+            chat_id, chat_id
+        );
+        return Ok(());
+    }
+    if gaps.is_empty() {
+        if messages.len() < crate::gaps::MIN_MESSAGES {
+            println!(
+                "Chat {} has {} cached messages, too few to look for gaps.",
+                chat_id,
+                messages.len()
+            );
+        } else {
+            println!(
+                "No gaps in the {} cached messages of chat {}.",
+                messages.len(),
+                chat_id
+            );
+        }
+        return Ok(());
+    }
+    let rows: Vec<Vec<String>> = gaps
+        .iter()
+        .map(|gap| {
+            vec![
+                format!("{} {}", gap.after_log_id, format_time(gap.after_at)),
+                format!("{} {}", gap.before_log_id, format_time(gap.before_at)),
+                humanize::duration(
+                    gap.before_at.saturating_sub(gap.after_at).max(0) as u64,
+                    humanize::lang(),
+                ),
+                format!("{}x", gap.jump / gap.typical.max(1)),
+            ]
+        })
+        .collect();
+    print_table(&["After", "Before", "Silence", "Jump/typical"], rows);
+    println!(
+        "\n{} possible gap(s). Run 'openkakao-rs archive repair {}' to fetch them again.",
+        gaps.len(),
+        chat_id
+    );
+    Ok(())
+}
+
+/// Pages fetched per gap by `archive repair` unless `--pages` says otherwise.
+pub const REPAIR_PAGES: usize = 10;
+
+/// `archive repair <chat_id>`: page the REST history back from the newer
+/// end of each gap until it reaches the older end, merging what was
+/// missing. The server only serves recent history, so what it no longer
+/// has is reported as unrecoverable.
+pub fn cmd_archive_repair(chat_id: i64, pages: usize, json: bool) -> Result<()> {
+    use crate::gaps::RepairOutcome;
+
+    let db = message_db::MessageDb::open()?;
+    let gaps = crate::gaps::find_gaps(&db.log_times(chat_id)?);
+    if gaps.is_empty() {
+        if json {
+            return crate::util::output_json(&serde_json::json!({
+                "chat_id": chat_id,
+                "gaps": [],
+            }));
+        }
+        println!("No gaps to repair in chat {}.", chat_id);
+        return Ok(());
+    }
+    let client = get_rest_client()?;
+    let names = db.member_names(chat_id)?;
+    let mut results = Vec::new();
+    for gap in &gaps {
+        let mut cursor = Some(MessageCursor::new(gap.before_log_id));
+        let mut recovered = Vec::new();
+        let mut reached_start = false;
+        let mut history_ended = false;
+        for _ in 0..pages {
+            let Some(page_cursor) = cursor else {
+                break;
+            };
+            let (page, next) = client.get_messages(chat_id, Some(page_cursor))?;
+            for msg in &page {
+                if msg.log_id <= gap.after_log_id {
+                    reached_start = true;
+                } else if msg.log_id < gap.before_log_id {
+                    let name = names
+                        .get(&msg.author_id)
+                        .cloned()
+                        .or_else(|| msg.author_nickname.clone())
+                        .unwrap_or_default();
+                    recovered.push(message_db::CachedMessage::from_message(chat_id, msg, name));
+                }
+            }
+            cursor = next;
+            if page.is_empty() || cursor.is_none() {
+                history_ended = true;
+            }
+            if reached_start || history_ended {
+                break;
+            }
+        }
+        db.upsert_messages(&recovered)?;
+        let outcome = crate::gaps::repair_outcome(recovered.len(), reached_start, history_ended);
+        results.push((gap, outcome));
+    }
+
+    if json {
+        let output: Vec<serde_json::Value> = results
+            .iter()
+            .map(|(gap, outcome)| serde_json::json!({"gap": gap, "outcome": outcome}))
+            .collect();
+        return crate::util::output_json(&serde_json::json!({
+            "chat_id": chat_id,
+            "gaps": output,
+        }));
+    }
+    let rows: Vec<Vec<String>> = results
+        .iter()
+        .map(|(gap, outcome)| {
+            let result = match outcome {
+                RepairOutcome::Repaired { recovered } => format!("recovered {recovered}"),
+                RepairOutcome::NothingMissing => "nothing missing".to_string(),
+                RepairOutcome::Unrecoverable { recovered } => {
+                    format!("recovered {recovered}; the rest is no longer served")
+                }
+                RepairOutcome::Incomplete { recovered } => {
+                    format!("recovered {recovered}; page limit reached")
+                }
+            };
+            vec![
+                format!("{} {}", gap.after_log_id, format_time(gap.after_at)),
+                format!("{} {}", gap.before_log_id, format_time(gap.before_at)),
+                result,
+            ]
+        })
+        .collect();
+    print_table(&["After", "Before", "Result"], rows);
+    if results
+        .iter()
+        .any(|(_, outcome)| matches!(outcome, RepairOutcome::Incomplete { .. }))
+    {
+        println!("\nSome gaps need more pages: pass --pages {}.", pages * 2);
+    }
+    Ok(())
+}
+
+pub fn cmd_cache_clear(scrap: bool, json: bool) -> Result<()> {
+    if scrap {
+        let removed = crate::scrap_cache::ScrapCache::open()?.clear()?;
+        if json {
+            crate::util::output_json(&serde_json::json!({
+                "status": "ok",
+                "removed_entries": removed,
+            }))?;
+        } else {
+            eprintln!("[cache] Removed {} cached link previews", removed);
+        }
+        return Ok(());
+    }
+
+    let mut media = MediaCache::open()?;
+    let bytes = media.total_bytes();
+    let removed = media.clear()?;
+
+    if json {
+        crate::util::output_json(&serde_json::json!({
+            "status": "ok",
+            "removed_files": removed,
+            "freed_bytes": bytes,
+        }))?;
+    } else {
+        eprintln!("[cache] Removed {} media files ({} bytes)", removed, bytes);
+    }
+    Ok(())
+}
+
+/// `cache clear [chat_id]`: drop cached messages, member names and title
+/// history for one chat, or for every chat.
+pub fn cmd_cache_clear_messages(chat_id: Option<i64>, yes: bool, json: bool) -> Result<()> {
+    let db = message_db::MessageDb::open()?;
+    let (scope, cached) = match chat_id {
+        Some(chat_id) => (format!("chat {chat_id}"), db.message_count(chat_id)?),
+        None => ("every chat".to_string(), db.total_count()?),
+    };
+    if !yes
+        && !crate::prompt::confirm(&format!(
+            "Delete {cached} cached messages of {scope}? Older ones may no longer be on the server. [y/N] "
+        ))?
+    {
+        eprintln!("Cancelled.");
+        return Ok(());
+    }
+    let removed = match chat_id {
+        Some(chat_id) => db.clear_chat(chat_id)?,
+        None => db.clear_all()?,
+    };
+
+    if json {
+        crate::util::output_json(&serde_json::json!({
+            "status": "ok",
+            "chat_id": chat_id,
+            "removed_messages": removed,
+        }))?;
+    } else {
+        eprintln!("[cache] Removed {} cached messages of {}", removed, scope);
+    }
+    Ok(())
+}
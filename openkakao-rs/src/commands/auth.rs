@@ -1,54 +1,103 @@
-use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+
+use anyhow::{Context, Result};
 use owo_colors::OwoColorize;
 use serde_json::Value;
 
 use crate::auth::{extract_refresh_token, get_credential_candidates};
 use crate::auth_flow::{attempt_relogin, attempt_renew, select_best_credential, RecoveryAttempt};
-use crate::credentials::save_credentials;
+use crate::credential_lint::{self, LintIssue, Severity};
+use crate::credentials::{
+    active_profile, list_stored_credentials, load_credentials_located, migrate_to_keychain,
+    plan_prune, restrict_permissions, save_credentials, save_credentials_to, shred_file,
+    CredentialLocation, CredentialStore, Keychain, PruneAction, SecretStore, StoredCredential,
+    TokenCheck,
+};
+use crate::humanize;
 use crate::loco;
 use crate::loco_helpers::try_renew_token;
-use crate::rest::KakaoRestClient;
+use crate::rest::{KakaoRestClient, TokenStatus};
 use crate::state::recovery_snapshot;
-use crate::util::{color_enabled, get_creds, mask_token, print_loco_error_hint};
+use crate::theme::theme;
+use crate::util::{
+    color_enabled, format_relative_age, get_creds, mask_token, print_loco_error_hint, print_table,
+    stale_cache_note,
+};
+use crate::warnings::{warn, WarningCode};
 
 pub fn cmd_auth(json: bool) -> Result<()> {
+    let source = crate::auth_flow::credential_source();
     let creds = get_creds()?;
     let client = KakaoRestClient::new(creds.clone())?;
-    let valid = client.verify_token()?;
+    let status = client.check_token();
+    let valid = status == TokenStatus::Valid;
+    let scope = client.probe_scope();
+    let freshness = crate::auth::cache_freshness().ok().flatten();
+    let now = chrono::Utc::now();
+    let cache_age_secs = freshness.as_ref().and_then(|f| f.age_secs(now));
+    if let Some(note) = freshness.as_ref().and_then(stale_cache_note) {
+        warn(WarningCode::StaleCache, note);
+    }
 
     if json {
         let out = serde_json::json!({
+            "source": source,
             "user_id": creds.user_id,
             "token_prefix": creds.oauth_token.chars().take(8).collect::<String>(),
             "app_version": creds.app_version,
             "valid": valid,
+            "token_status": status,
+            "scope": scope,
+            "cache_age_secs": cache_age_secs,
         });
-        println!("{}", serde_json::to_string_pretty(&out)?);
+        crate::util::output_json(&out)?;
         return Ok(());
     }
 
+    println!("  Source:  {}", source);
     println!("  User ID: {}", creds.user_id);
     println!(
         "  Token:   {}...",
         creds.oauth_token.chars().take(8).collect::<String>()
     );
     println!("  Version: {}", creds.app_version);
+    println!("  Scope:   {}", scope.summary());
+    if let Some(at) = freshness.as_ref().and_then(|f| f.last_updated()) {
+        println!(
+            "  Cache:   updated {}",
+            format_relative_age(at.timestamp(), now.timestamp())
+        );
+    }
 
     if valid {
         if color_enabled() {
-            println!("  {}", "Token is valid!".green());
+            println!("  {}", "Token is valid!".style(theme().ok));
         } else {
             println!("  Token is valid!");
         }
     } else {
+        let line = format!("Token {}.", status.reason());
         if color_enabled() {
-            println!("  {}", "Token is invalid or expired.".red());
+            let style = match status {
+                TokenStatus::Invalid { .. } => theme().fail,
+                _ => theme().warn,
+            };
+            println!("  {}", line.style(style));
         } else {
-            println!("  Token is invalid or expired.");
+            println!("  {}", line);
+        }
+        if matches!(status, TokenStatus::Invalid { .. }) {
+            println!(
+                "  Hint: {}",
+                crate::status::KakaoStatus::HttpUnauthorized.suggestion()
+            );
+        }
+    }
+    if valid {
+        for family in scope.rejected() {
+            println!("  Note: {}.", family.unavailable_hint());
         }
-        println!(
-            "  Hint: open KakaoTalk, open chat list once, then run 'openkakao-rs login --save'."
-        );
     }
 
     Ok(())
@@ -71,7 +120,7 @@ pub fn cmd_auth_status(json: bool) -> Result<()> {
             "relogin_available_in_secs": snapshot.relogin_available_in_secs,
             "renew_available_in_secs": snapshot.renew_available_in_secs,
         });
-        println!("{}", serde_json::to_string_pretty(&out)?);
+        crate::util::output_json(&out)?;
         return Ok(());
     }
 
@@ -121,20 +170,24 @@ pub fn cmd_auth_status(json: bool) -> Result<()> {
 
 pub fn format_simple_remaining(value: Option<u64>) -> String {
     match value {
-        Some(secs) => format!("{}s", secs),
+        Some(secs) => humanize::duration(secs, humanize::lang()),
         None => "now".to_string(),
     }
 }
 
 pub fn format_remaining(remaining_secs: Option<u64>, until: Option<&str>) -> String {
     match (remaining_secs, until) {
-        (Some(secs), Some(until)) => format!("{}s (until {})", secs, until),
-        (Some(secs), None) => format!("{}s", secs),
+        (Some(secs), Some(until)) => format!(
+            "{} (until {})",
+            humanize::duration(secs, humanize::lang()),
+            until
+        ),
+        (Some(secs), None) => humanize::duration(secs, humanize::lang()),
         _ => "none".to_string(),
     }
 }
 
-pub fn cmd_login(save: bool) -> Result<()> {
+pub fn cmd_login(save: bool, store: Option<CredentialStore>) -> Result<()> {
     let candidates = get_credential_candidates(8)?;
     let Some(_) = candidates.first() else {
         println!("Could not extract credentials. Is KakaoTalk running?");
@@ -150,17 +203,441 @@ pub fn cmd_login(save: bool) -> Result<()> {
     );
 
     let client = KakaoRestClient::new(creds.clone())?;
-    if client.verify_token()? {
-        println!("  Token verified OK");
-    } else {
-        println!("  Token may be expired for some operations");
+    match client.check_token() {
+        TokenStatus::Valid => println!("  Token verified OK"),
+        status => println!("  Token not verified: {}", status.reason()),
     }
 
     if save {
-        let path = save_credentials(&creds)?;
-        println!("Credentials saved to {}", path.display());
+        let location = save_credentials_to(&creds, store)?;
+        println!("Credentials saved to {location}");
+    }
+
+    Ok(())
+}
+
+/// Pause between token checks so checking many files is not a request burst.
+const VERIFY_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Verify each stored file's token. Each distinct token is checked once;
+/// duplicates share the result.
+fn verify_stored(stored: &[StoredCredential]) -> Vec<TokenCheck> {
+    let mut verified: HashMap<String, TokenCheck> = HashMap::new();
+    let mut checks = Vec::with_capacity(stored.len());
+    for entry in stored {
+        let check = match &entry.creds {
+            None => TokenCheck::Unreadable,
+            Some(creds) => match verified.get(&creds.oauth_token) {
+                Some(check) => *check,
+                None => {
+                    if !verified.is_empty() {
+                        std::thread::sleep(VERIFY_DELAY);
+                    }
+                    let check = match KakaoRestClient::new(creds.clone())
+                        .and_then(|client| client.verify_token())
+                    {
+                        Ok(true) => TokenCheck::Valid,
+                        Ok(false) => TokenCheck::Expired,
+                        Err(_) => TokenCheck::Unverified,
+                    };
+                    verified.insert(creds.oauth_token.clone(), check);
+                    check
+                }
+            },
+        };
+        checks.push(check);
+    }
+    checks
+}
+
+fn check_label(check: TokenCheck) -> &'static str {
+    match check {
+        TokenCheck::Valid => "valid",
+        TokenCheck::Expired => "expired",
+        TokenCheck::Unverified => "unverified",
+        TokenCheck::Unreadable => "unreadable",
+    }
+}
+
+/// `credentials profiles`: every saved profile with its user id and whether
+/// its token verifies right now.
+pub fn cmd_credentials_profiles(json: bool) -> Result<()> {
+    let stored = list_stored_credentials()?;
+    let checks = verify_stored(&stored);
+    let active = active_profile();
+
+    if json {
+        let entries: Vec<Value> = stored
+            .iter()
+            .zip(&checks)
+            .map(|(entry, check)| {
+                serde_json::json!({
+                    "name": entry.name,
+                    "path": entry.path,
+                    "active": entry.name == active,
+                    "user_id": entry.creds.as_ref().map(|c| c.user_id),
+                    "token": entry.creds.as_ref().map(|c| mask_token(&c.oauth_token)),
+                    "status": check,
+                })
+            })
+            .collect();
+        let out = serde_json::json!({ "active": active, "profiles": entries });
+        crate::util::output_json(&out)?;
+        return Ok(());
+    }
+
+    if stored.is_empty() {
+        println!("No saved profiles. Run: openkakao-rs login --save [--profile NAME]");
+        return Ok(());
+    }
+    let rows = stored
+        .iter()
+        .zip(&checks)
+        .map(|(entry, check)| {
+            let marker = if entry.name == active { "*" } else { "" };
+            vec![
+                format!("{}{}", entry.name, marker),
+                entry
+                    .creds
+                    .as_ref()
+                    .map(|c| c.user_id.to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+                entry
+                    .creds
+                    .as_ref()
+                    .map(|c| mask_token(&c.oauth_token))
+                    .unwrap_or_else(|| "-".to_string()),
+                check_label(*check).to_string(),
+            ]
+        })
+        .collect();
+    print_table(&["Profile", "User ID", "Token", "Status"], rows);
+    println!();
+    println!("* active profile; select another with --profile NAME.");
+    Ok(())
+}
+
+/// `credentials profiles rm <name>`: the profile's credentials file and
+/// keychain entry, and its whole state tree (archive, caches, state files).
+pub fn cmd_credentials_profiles_rm(name: &str, yes: bool, json: bool) -> Result<()> {
+    let file = crate::credentials::profile_path(name)?;
+    let state = crate::paths::profile_dir_of(name)?;
+    let on_disk: Vec<&std::path::Path> = [file.as_path(), state.as_path()]
+        .into_iter()
+        .filter(|path| path.exists())
+        .collect();
+    if on_disk.is_empty() && !Keychain::available() {
+        anyhow::bail!(
+            "No profile '{name}': neither {} nor {} exists.",
+            file.display(),
+            state.display()
+        );
+    }
+    if !yes {
+        eprintln!("Deleting profile '{name}' removes:");
+        for path in &on_disk {
+            eprintln!("  {}", path.display());
+        }
+        if Keychain::available() {
+            eprintln!("  its keychain entry, if any");
+        }
+        if !crate::prompt::confirm(
+            "Archived messages no longer on the server cannot be fetched again. Delete? [y/N] ",
+        )? {
+            eprintln!("Cancelled.");
+            return Ok(());
+        }
+    }
+
+    let mut removed = Vec::new();
+    if file.exists() {
+        shred_file(&file)?;
+        removed.push(file.display().to_string());
+    }
+    let keychain = Keychain.delete(name)?;
+    if state.exists() {
+        std::fs::remove_dir_all(&state)
+            .with_context(|| format!("Failed to delete {}", state.display()))?;
+        removed.push(state.display().to_string());
+    }
+    if removed.is_empty() && !keychain {
+        anyhow::bail!("No profile '{name}' to delete.");
+    }
+
+    if json {
+        return crate::util::output_json(&serde_json::json!({
+            "status": "ok",
+            "profile": name,
+            "removed": removed,
+            "keychain": keychain,
+        }));
+    }
+    for path in &removed {
+        eprintln!("[auth] Removed {path}");
+    }
+    if keychain {
+        eprintln!("[auth] Removed the keychain entry of profile '{name}'");
+    }
+    if name == active_profile() {
+        eprintln!("[auth] '{name}' was this run's profile; it starts empty if used again.");
+    }
+    Ok(())
+}
+
+/// `credentials lint`: every saved credential checked for values that
+/// cannot work as written, without a network call. The exit code follows
+/// the worst problem found.
+pub fn cmd_credentials_lint(json: bool) -> Result<()> {
+    let mut checked: Vec<(String, String, Vec<LintIssue>)> = Vec::new();
+    if let Ok(Some(secret)) = Keychain.get(active_profile()) {
+        let location = CredentialLocation::Keychain {
+            profile: active_profile().to_string(),
+        };
+        checked.push((
+            active_profile().to_string(),
+            location.to_string(),
+            credential_lint::lint_saved(&secret),
+        ));
+    }
+    for entry in list_stored_credentials()? {
+        let mut issues = match std::fs::read_to_string(&entry.path) {
+            Ok(text) => credential_lint::lint_saved(&text),
+            Err(err) => anyhow::bail!("Failed to read {}: {err}", entry.path.display()),
+        };
+        issues.extend(
+            entry
+                .mode
+                .and_then(|mode| credential_lint::file_mode(mode, &entry.path)),
+        );
+        checked.push((entry.name, entry.path.display().to_string(), issues));
+    }
+
+    let all = || checked.iter().flat_map(|(_, _, issues)| issues);
+    let count = |severity: Severity| all().filter(|i| i.severity == severity).count();
+    crate::util::EXIT_CODE.store(credential_lint::exit_code(all()), Ordering::Relaxed);
+
+    if json {
+        let entries: Vec<Value> = checked
+            .iter()
+            .map(|(name, location, issues)| {
+                serde_json::json!({ "name": name, "location": location, "issues": issues })
+            })
+            .collect();
+        return crate::util::output_json(&serde_json::json!({
+            "credentials": entries,
+            "errors": count(Severity::Error),
+            "warnings": count(Severity::Warning),
+        }));
     }
 
+    if checked.is_empty() {
+        println!("No saved credentials. Run: openkakao-rs login --save [--profile NAME]");
+        return Ok(());
+    }
+    for (name, location, issues) in &checked {
+        if issues.is_empty() {
+            println!("{name} ({location}): ok");
+            continue;
+        }
+        println!("{name} ({location}):");
+        for issue in issues {
+            let label = match issue.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            };
+            let label = if color_enabled() {
+                let style = match issue.severity {
+                    Severity::Error => theme().fail,
+                    Severity::Warning => theme().warn,
+                };
+                format!("{}", label.style(style))
+            } else {
+                label.to_string()
+            };
+            println!("  {label}: {} {}", issue.field, issue.problem);
+            println!("    fix: {}", issue.fix);
+        }
+    }
+    println!();
+    println!(
+        "{} error(s), {} warning(s) in {} credential(s).",
+        count(Severity::Error),
+        count(Severity::Warning),
+        checked.len()
+    );
+    Ok(())
+}
+
+/// `credentials migrate`: move the active profile's file into the keychain.
+pub fn cmd_credentials_migrate(json: bool) -> Result<()> {
+    let moved = migrate_to_keychain()?;
+    if json {
+        let out = serde_json::json!({
+            "profile": active_profile(),
+            "migrated": moved.is_some(),
+            "shredded": moved,
+        });
+        return crate::util::output_json(&out);
+    }
+    match moved {
+        Some(path) => println!(
+            "Moved profile '{}' into the keychain; {} was overwritten and deleted.",
+            active_profile(),
+            path.display()
+        ),
+        None => println!(
+            "No credentials file for profile '{}'; nothing to migrate.",
+            active_profile()
+        ),
+    }
+    Ok(())
+}
+
+/// `credentials show`: the active profile's saved credentials, tokens masked
+/// unless `reveal`.
+pub fn cmd_credentials_show(reveal: bool, json: bool) -> Result<()> {
+    let Some((creds, location)) = load_credentials_located()? else {
+        anyhow::bail!(
+            "No saved credentials for profile '{}'. Run: openkakao-rs login --save",
+            active_profile()
+        );
+    };
+    let token = |token: &str| {
+        if reveal {
+            token.to_string()
+        } else {
+            mask_token(token)
+        }
+    };
+    if json {
+        let out = serde_json::json!({
+            "profile": active_profile(),
+            "store": location.store(),
+            "location": location.to_string(),
+            "user_id": creds.user_id,
+            "oauth_token": token(&creds.oauth_token),
+            "refresh_token": creds.refresh_token.as_deref().map(token),
+            "device_uuid": creds.device_uuid,
+        });
+        return crate::util::output_json(&out);
+    }
+    println!("Profile:       {}", active_profile());
+    println!("Stored in:     {location}");
+    println!("User ID:       {}", creds.user_id);
+    println!("Access token:  {}", token(&creds.oauth_token));
+    if let Some(refresh) = creds.refresh_token.as_deref() {
+        println!("Refresh token: {}", token(refresh));
+    }
+    println!("Device UUID:   {}", creds.device_uuid);
+    Ok(())
+}
+
+pub fn cmd_credentials_prune(apply: bool, json: bool) -> Result<()> {
+    let stored = list_stored_credentials()?;
+    let checks = verify_stored(&stored);
+    let actions = plan_prune(&stored, &checks, active_profile());
+
+    let mut permissions_fixed = vec![false; stored.len()];
+    if apply {
+        for (idx, entry) in stored.iter().enumerate() {
+            if actions[idx] == PruneAction::Keep {
+                permissions_fixed[idx] = restrict_permissions(&entry.path)?.is_some();
+            } else {
+                shred_file(&entry.path)?;
+            }
+        }
+    }
+
+    if json {
+        let entries: Vec<Value> = stored
+            .iter()
+            .enumerate()
+            .map(|(idx, entry)| {
+                serde_json::json!({
+                    "name": entry.name,
+                    "path": entry.path,
+                    "user_id": entry.creds.as_ref().map(|c| c.user_id),
+                    "token": entry.creds.as_ref().map(|c| mask_token(&c.oauth_token)),
+                    "status": checks[idx],
+                    "plan": actions[idx],
+                    "mode": entry.mode.map(|m| format!("{m:o}")),
+                    "permissions_fixed": permissions_fixed[idx],
+                })
+            })
+            .collect();
+        let out = serde_json::json!({ "applied": apply, "credentials": entries });
+        crate::util::output_json(&out)?;
+        return Ok(());
+    }
+
+    if stored.is_empty() {
+        println!("No saved credentials.");
+        return Ok(());
+    }
+
+    let rows = stored
+        .iter()
+        .enumerate()
+        .map(|(idx, entry)| {
+            let status = check_label(checks[idx]);
+            let action = match &actions[idx] {
+                PruneAction::Keep => "keep".to_string(),
+                PruneAction::DeleteExpired => "delete".to_string(),
+                PruneAction::DeleteDuplicate { kept } => format!("delete (duplicate of {kept})"),
+            };
+            let mode = match entry.mode {
+                Some(0o600) | None => "ok".to_string(),
+                Some(m) if permissions_fixed[idx] => format!("{m:o} -> 600"),
+                Some(m) if actions[idx] == PruneAction::Keep => format!("{m:o} (fix to 600)"),
+                Some(m) => format!("{m:o}"),
+            };
+            vec![
+                entry.name.clone(),
+                entry
+                    .creds
+                    .as_ref()
+                    .map(|c| c.user_id.to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+                entry
+                    .creds
+                    .as_ref()
+                    .map(|c| mask_token(&c.oauth_token))
+                    .unwrap_or_else(|| "-".to_string()),
+                status.to_string(),
+                action,
+                mode,
+            ]
+        })
+        .collect();
+    print_table(
+        &["Name", "User ID", "Token", "Status", "Action", "Mode"],
+        rows,
+    );
+
+    let deletions = actions.iter().filter(|a| **a != PruneAction::Keep).count();
+    let fixes = if apply {
+        permissions_fixed.iter().filter(|f| **f).count()
+    } else {
+        stored
+            .iter()
+            .zip(&actions)
+            .filter(|(e, a)| **a == PruneAction::Keep && e.mode.is_some_and(|m| m != 0o600))
+            .count()
+    };
+    println!();
+    if apply {
+        println!(
+            "Deleted {} file(s), reset permissions on {} file(s).",
+            deletions, fixes
+        );
+    } else if deletions + fixes == 0 {
+        println!("Nothing to prune.");
+    } else {
+        println!(
+            "Dry run: would delete {} file(s) and reset permissions on {} file(s). Re-run with --apply.",
+            deletions, fixes
+        );
+    }
     Ok(())
 }
 
@@ -179,15 +656,12 @@ pub fn cmd_renew(json: bool) -> Result<()> {
             response,
         } => {
             if json {
-                println!(
-                    "{}",
-                    serde_json::to_string_pretty(&serde_json::json!({
-                        "outcome": "failed",
-                        "source": source,
-                        "detail": detail,
-                        "response": response,
-                    }))?
-                );
+                crate::util::output_json(&serde_json::json!({
+                    "outcome": "failed",
+                    "source": source,
+                    "detail": detail,
+                    "response": response,
+                }))?;
             } else {
                 eprintln!("Token renewal failed via {}.", source);
                 eprintln!("Detail: {}", detail);
@@ -211,14 +685,11 @@ pub fn cmd_renew(json: bool) -> Result<()> {
 
 pub fn print_renew_result(json: bool, source: &str, response: &Value) -> Result<()> {
     if json {
-        println!(
-            "{}",
-            serde_json::to_string_pretty(&serde_json::json!({
-                "outcome": "recovered",
-                "source": source,
-                "response": response,
-            }))?
-        );
+        crate::util::output_json(&serde_json::json!({
+            "outcome": "recovered",
+            "source": source,
+            "response": response,
+        }))?;
     } else {
         eprintln!("  Token renewed successfully via {}!", source);
         if let Some(access) = response.get("access_token").and_then(Value::as_str) {
@@ -262,15 +733,12 @@ pub fn cmd_relogin(
             response,
         } => {
             if json {
-                println!(
-                    "{}",
-                    serde_json::to_string_pretty(&serde_json::json!({
-                        "outcome": "failed",
-                        "source": source,
-                        "detail": detail,
-                        "response": response,
-                    }))?
-                );
+                crate::util::output_json(&serde_json::json!({
+                    "outcome": "failed",
+                    "source": source,
+                    "detail": detail,
+                    "response": response,
+                }))?;
                 return Ok(());
             }
 
@@ -295,14 +763,11 @@ pub fn cmd_relogin(
         } => {
             save_credentials(&credentials)?;
             if json {
-                println!(
-                    "{}",
-                    serde_json::to_string_pretty(&serde_json::json!({
-                        "outcome": "recovered",
-                        "source": source,
-                        "response": response,
-                    }))?
-                );
+                crate::util::output_json(&serde_json::json!({
+                    "outcome": "recovered",
+                    "source": source,
+                    "response": response,
+                }))?;
                 return Ok(());
             }
 
@@ -332,8 +797,9 @@ pub fn cmd_loco_test() -> Result<()> {
 
     let rt = tokio::runtime::Runtime::new()?;
     rt.block_on(async {
-        let mut client = loco::client::LocoClient::new(creds.clone());
-        let login_data = client.full_connect_with_retry(3).await?;
+        let mut client = loco::client::LocoClient::new(creds.clone())
+            .with_retry_policy(crate::util::retry_policy());
+        let login_data = client.full_connect_with_retry().await?;
 
         let status = login_data
             .get_i64("status")
@@ -416,7 +882,7 @@ pub fn cmd_watch_cache(interval: u64) -> Result<()> {
         if let Ok(Some(rt)) = extract_refresh_token() {
             if rt != last_token {
                 if color_enabled() {
-                    eprintln!("{}", "NEW refresh_token detected!".green().bold());
+                    eprintln!("{}", "NEW refresh_token detected!".style(theme().ok.bold()));
                 } else {
                     eprintln!("NEW refresh_token detected!");
                 }
@@ -428,7 +894,10 @@ pub fn cmd_watch_cache(interval: u64) -> Result<()> {
                     match try_renew_token(&creds, &rt) {
                         Ok(Some(new_token)) => {
                             if color_enabled() {
-                                eprintln!("{}", "Token renewal SUCCEEDED!".green().bold());
+                                eprintln!(
+                                    "{}",
+                                    "Token renewal SUCCEEDED!".style(theme().ok.bold())
+                                );
                             } else {
                                 eprintln!("Token renewal SUCCEEDED!");
                             }
@@ -440,8 +909,8 @@ pub fn cmd_watch_cache(interval: u64) -> Result<()> {
                             let mut new_creds = creds.clone();
                             new_creds.oauth_token = new_token;
                             new_creds.refresh_token = Some(rt);
-                            if let Ok(path) = save_credentials(&new_creds) {
-                                eprintln!("  Saved to {}", path.display());
+                            if let Ok(location) = save_credentials(&new_creds) {
+                                eprintln!("  Saved to {location}");
                             }
                         }
                         Ok(None) => eprintln!("  Renewal returned no access_token."),
@@ -456,7 +925,7 @@ pub fn cmd_watch_cache(interval: u64) -> Result<()> {
             if let Some(cand) = candidates.first() {
                 if cand.oauth_token != last_oauth {
                     if color_enabled() {
-                        eprintln!("{}", "NEW oauth_token detected!".green().bold());
+                        eprintln!("{}", "NEW oauth_token detected!".style(theme().ok.bold()));
                     } else {
                         eprintln!("NEW oauth_token detected!");
                     }
@@ -1,15 +1,25 @@
-use anyhow::Result;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::atomic::Ordering;
+
+use anyhow::{anyhow, Result};
 use serde::Serialize;
 use tokio::runtime::Runtime;
 
 use crate::loco;
-use crate::loco_helpers::loco_connect_with_auto_refresh;
+use crate::loco_helpers::{check_loco_status, loco_connect_with_auto_refresh};
+use crate::message_db::{MessageDb, TitleEntry};
+use crate::model::{BoardPost, ChatRoom, Notice};
+use crate::names::NameResolver;
+use crate::state::{since_state_exit_code, update_unread_marks, UnreadMark};
 use crate::util::{
-    get_bson_i32, get_bson_i64, get_bson_str, get_bson_str_array, get_creds, get_rest_client,
-    print_section_title, print_table, type_label,
+    display_safe, format_relative_age, format_time, get_bson_i32, get_bson_i64, get_bson_str,
+    get_bson_str_array, get_bson_timestamp, get_creds, get_rest_client, name_resolver,
+    parse_duration_secs, print_section_title, print_table, retain_name_matches, truncate,
+    type_label, NameQuery,
 };
+use crate::warnings::{warn, WarningCode};
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, schemars::JsonSchema)]
 pub struct ChatListing {
     pub chat_id: i64,
     pub kind: String,
@@ -19,15 +29,134 @@ pub struct ChatListing {
     pub active_members: Option<i32>,
     pub last_log_id: Option<i64>,
     pub last_seen_log_id: Option<i64>,
+    pub last_active_at: Option<i64>,
+    /// The room's own name; `None` when `title` is made of member names.
+    #[serde(skip)]
+    pub name: Option<String>,
+    /// `--since-state`: the title last recorded, when the room was renamed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub renamed_from: Option<String>,
+}
+
+/// `chats --sort`: list order instead of the server's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ChatSort {
+    /// Most recently active first.
+    Recent,
+    /// Most unread first.
+    Unread,
+    /// By title.
+    Name,
+}
+
+/// Order `chats` by `sort`. Rooms without a time or unread count go last
+/// rather than being read as ancient or empty; ties keep the server order.
+fn sort_listings(chats: &mut [ChatListing], sort: ChatSort) {
+    match sort {
+        ChatSort::Recent => chats.sort_by_key(|chat| std::cmp::Reverse(chat.last_active_at)),
+        ChatSort::Unread => chats.sort_by_key(|chat| {
+            std::cmp::Reverse((chat.has_unread, chat.unread_count.unwrap_or(0)))
+        }),
+        ChatSort::Name => chats.sort_by_cached_key(|chat| chat.title.to_lowercase()),
+    }
+}
+
+/// Keep rooms idle for at least `stale_secs` and order them oldest-first.
+/// Rooms without a timestamp are kept but sorted last, since their age is
+/// unknown rather than ancient.
+fn retain_stale(chats: &mut Vec<ChatListing>, stale_secs: i64, now: i64) {
+    let cutoff = now - stale_secs;
+    chats.retain(|chat| chat.last_active_at.is_none_or(|ts| ts <= cutoff));
+    chats.sort_by_key(|chat| (chat.last_active_at.is_none(), chat.last_active_at));
+}
+
+/// `--since-state`: save `user_id`'s unread mark of every fetched room
+/// (before any filter, so differently filtered runs share one baseline) and
+/// return the rooms that are newly unread or gained unread messages.
+fn unread_changes(
+    since_state: bool,
+    user_id: i64,
+    marks: impl IntoIterator<Item = (i64, UnreadMark)>,
+) -> Result<Option<BTreeSet<i64>>> {
+    if !since_state {
+        return Ok(None);
+    }
+    let marks: BTreeMap<i64, UnreadMark> = marks.into_iter().collect();
+    update_unread_marks(user_id, &marks).map(Some)
+}
+
+/// `--since-state`: record every named room's title in the archive's title
+/// history and mark the rooms renamed since the last recorded title as
+/// changed too.
+fn title_changes(chats: &mut [ChatListing], changed: Option<&mut BTreeSet<i64>>) -> Result<()> {
+    let Some(changed) = changed else {
+        return Ok(());
+    };
+    let renames = MessageDb::open()?.record_titles(
+        chats
+            .iter()
+            .filter_map(|chat| Some((chat.chat_id, chat.name.as_deref()?))),
+        chrono::Utc::now().timestamp(),
+    )?;
+    for rename in renames {
+        changed.insert(rename.chat_id);
+        if let Some(chat) = chats.iter_mut().find(|chat| chat.chat_id == rename.chat_id) {
+            chat.renamed_from = Some(rename.from);
+        }
+    }
+    Ok(())
+}
+
+/// The "renamed from X to Y" lines printed under a `--since-state` table.
+fn print_renames(chats: &[ChatListing]) {
+    for chat in chats {
+        if let Some(from) = &chat.renamed_from {
+            println!(
+                "Chat {}: renamed from \"{}\" to \"{}\"",
+                chat.chat_id,
+                display_safe(from),
+                display_safe(&chat.title)
+            );
+        }
+    }
 }
 
+/// Keep only the changed rooms and exit 0 if any are left, 5 otherwise.
+fn retain_changed(chats: &mut Vec<ChatListing>, changed: Option<&BTreeSet<i64>>) {
+    if let Some(changed) = changed {
+        chats.retain(|chat| changed.contains(&chat.chat_id));
+        crate::util::EXIT_CODE.store(since_state_exit_code(chats.len()), Ordering::Relaxed);
+    }
+}
+
+fn last_active_label(chat: &ChatListing, now: i64) -> String {
+    chat.last_active_at
+        .map(|ts| format_relative_age(ts, now))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// `--read-status`: how far behind my read watermark the room is.
+fn read_status_label(chat: &ChatListing) -> String {
+    match chat.unread_count {
+        Some(count) if count > 0 => format!("{count} behind"),
+        _ if chat.has_unread => "behind".to_string(),
+        _ => "up to date".to_string(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn cmd_chats_rest(
     show_all: bool,
     unread: bool,
-    search: Option<String>,
+    search: Option<NameQuery>,
     chat_type: Option<String>,
+    stale: Option<&str>,
+    sort: Option<ChatSort>,
+    since_state: bool,
+    read_status: bool,
     json: bool,
 ) -> Result<()> {
+    let stale_secs = stale.map(parse_duration_secs).transpose()?;
     let client = get_rest_client()?;
 
     let mut chats = if show_all {
@@ -35,14 +164,29 @@ pub fn cmd_chats_rest(
     } else {
         client.get_chats(None)?.0
     };
+    let mut changed = unread_changes(
+        since_state,
+        client.user_id(),
+        chats.iter().map(|c| {
+            let mark = UnreadMark {
+                unread: c.unread_count,
+                last_log_id: c.settings.last_log_id,
+            };
+            (c.chat_id, mark)
+        }),
+    )?;
 
     if unread {
         chats.retain(|c| c.unread_count > 0);
     }
 
     if let Some(ref query) = search {
-        let q = query.to_lowercase();
-        chats.retain(|c| c.display_title().to_lowercase().contains(&q));
+        retain_name_matches(
+            &mut chats,
+            query,
+            |c| c.chat_id,
+            |c| vec![c.display_title()],
+        );
     }
 
     if let Some(ref t) = chat_type {
@@ -58,7 +202,7 @@ pub fn cmd_chats_rest(
         chats.retain(|c| c.kind == kind);
     }
 
-    let listings = chats
+    let mut listings = chats
         .into_iter()
         .map(|chat| {
             let title = chat.display_title();
@@ -70,19 +214,32 @@ pub fn cmd_chats_rest(
                 has_unread: chat.unread_count > 0,
                 unread_count: Some(chat.unread_count),
                 active_members: Some(active_members),
-                last_log_id: None,
-                last_seen_log_id: None,
+                last_log_id: chat.settings.last_log_id,
+                last_seen_log_id: chat.settings.watermark,
+                last_active_at: chat.last_active_at,
+                name: Some(chat.title).filter(|name| !name.is_empty()),
+                renamed_from: None,
             }
         })
         .collect::<Vec<_>>();
+    title_changes(&mut listings, changed.as_mut())?;
+
+    let now = chrono::Utc::now().timestamp();
+    if let Some(secs) = stale_secs {
+        retain_stale(&mut listings, secs, now);
+    }
+    retain_changed(&mut listings, changed.as_ref());
+    if let Some(sort) = sort {
+        sort_listings(&mut listings, sort);
+    }
 
     if json {
-        println!("{}", serde_json::to_string_pretty(&listings)?);
+        crate::util::output_json(&listings)?;
         return Ok(());
     }
 
     let mut rows = Vec::new();
-    for c in listings {
+    for c in &listings {
         let kind = type_label(&c.kind);
         let unread_str = if c.has_unread {
             c.unread_count.unwrap_or(1).to_string()
@@ -90,39 +247,86 @@ pub fn cmd_chats_rest(
             String::new()
         };
 
-        rows.push(vec![
-            kind.to_string(),
-            c.title,
-            unread_str,
-            c.chat_id.to_string(),
-        ]);
+        let mut row = vec![kind.to_string(), c.title.clone(), unread_str];
+        if read_status {
+            row.push(read_status_label(c));
+        }
+        if stale_secs.is_some() {
+            row.push(last_active_label(c, now));
+        }
+        row.push(c.chat_id.to_string());
+        rows.push(row);
     }
 
+    let mut headers = vec!["Type", "Name", "Unread"];
+    if read_status {
+        headers.push("Read status");
+    }
+    if stale_secs.is_some() {
+        headers.push("Last active");
+    }
+    headers.push("Chat ID");
     print_section_title(&format!("Chats ({})", rows.len()));
-    print_table(&["Type", "Name", "Unread", "Chat ID"], rows);
+    print_table(&headers, rows);
+    print_renames(&listings);
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn cmd_chats(
     show_all: bool,
     unread: bool,
-    search: Option<String>,
+    search: Option<NameQuery>,
     chat_type: Option<String>,
+    stale: Option<&str>,
+    sort: Option<ChatSort>,
     rest: bool,
+    since_state: bool,
+    read_status: bool,
     json: bool,
 ) -> Result<()> {
-    if rest {
-        return cmd_chats_rest(show_all, unread, search, chat_type, json);
+    // Only the REST chat list carries unread counts and read watermarks.
+    if rest || read_status {
+        return cmd_chats_rest(
+            show_all,
+            unread,
+            search,
+            chat_type,
+            stale,
+            sort,
+            since_state,
+            read_status,
+            json,
+        );
     }
 
-    match cmd_loco_chats(show_all, unread, search.clone(), chat_type.clone(), json) {
+    match cmd_loco_chats(
+        show_all,
+        unread,
+        search.clone(),
+        chat_type.clone(),
+        stale,
+        sort,
+        since_state,
+        json,
+    ) {
         Ok(()) => Ok(()),
         Err(err) => {
             eprintln!(
                 "[chats] LOCO chat list failed: {}. Falling back to REST recent chat list.",
                 err
             );
-            cmd_chats_rest(show_all, unread, search, chat_type, json)
+            cmd_chats_rest(
+                show_all,
+                unread,
+                search,
+                chat_type,
+                stale,
+                sort,
+                since_state,
+                false,
+                json,
+            )
         }
     }
 }
@@ -166,18 +370,25 @@ pub async fn fetch_loco_chat_listings_with_client(
             let last_seen = get_bson_i64(doc, &["ll", "lastSeenLogId"]);
             let has_unread = last_log_id > last_seen;
             let active_member_count = get_bson_i32(doc, &["a", "activeMembersCount"]);
+            // `o` is the room's last update time; fall back to the embedded last chat log.
+            let last_active_at = Some(get_bson_i64(doc, &["o", "lastUpdatedAt"]))
+                .filter(|ts| *ts > 0)
+                .or_else(|| {
+                    doc.get_document("l")
+                        .ok()
+                        .map(|log| get_bson_timestamp(log, &["sendAt"]))
+                        .filter(|ts| *ts > 0)
+                });
 
-            let title = doc
+            let name = doc
                 .get_document("chatInfo")
                 .ok()
                 .and_then(|ci| ci.get_str("name").ok())
-                .map(String::from)
-                .unwrap_or_default();
-            let title = if title.is_empty() {
-                get_bson_str_array(doc, &["k"]).join(", ")
-            } else {
-                title
-            };
+                .filter(|name| !name.is_empty())
+                .map(String::from);
+            let title = name
+                .clone()
+                .unwrap_or_else(|| get_bson_str_array(doc, &["k"]).join(", "));
 
             if !show_all && !has_unread && title.is_empty() {
                 continue;
@@ -192,6 +403,9 @@ pub async fn fetch_loco_chat_listings_with_client(
                 active_members: Some(active_member_count),
                 last_log_id: Some(last_log_id),
                 last_seen_log_id: Some(last_seen),
+                last_active_at,
+                name,
+                renamed_from: None,
             });
         }
     }
@@ -199,14 +413,20 @@ pub async fn fetch_loco_chat_listings_with_client(
     Ok(chats)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn cmd_loco_chats(
     show_all: bool,
     unread: bool,
-    search: Option<String>,
+    search: Option<NameQuery>,
     chat_type: Option<String>,
+    stale: Option<&str>,
+    sort: Option<ChatSort>,
+    since_state: bool,
     json: bool,
 ) -> Result<()> {
+    let stale_secs = stale.map(parse_duration_secs).transpose()?;
     let creds = get_creds()?;
+    let user_id = creds.user_id;
 
     let rt = Runtime::new()?;
     rt.block_on(async {
@@ -214,14 +434,32 @@ pub fn cmd_loco_chats(
         let login_data = loco_connect_with_auto_refresh(&mut client).await?;
         let mut chats =
             fetch_loco_chat_listings_with_client(&mut client, &login_data, show_all).await?;
+        let mut changed = unread_changes(
+            since_state,
+            user_id,
+            chats.iter().map(|chat| {
+                // LOCO only knows whether a room is unread; a newer
+                // last_log_id is what says more arrived.
+                let mark = UnreadMark {
+                    unread: chat.has_unread as i64,
+                    last_log_id: chat.last_log_id,
+                };
+                (chat.chat_id, mark)
+            }),
+        )?;
+        title_changes(&mut chats, changed.as_mut())?;
 
         if unread {
             chats.retain(|chat| chat.has_unread);
         }
 
         if let Some(ref query) = search {
-            let q = query.to_lowercase();
-            chats.retain(|chat| chat.title.to_lowercase().contains(&q));
+            retain_name_matches(
+                &mut chats,
+                query,
+                |chat| chat.chat_id,
+                |chat| vec![chat.title.clone()],
+            );
         }
 
         if let Some(ref t) = chat_type {
@@ -237,15 +475,24 @@ pub fn cmd_loco_chats(
             chats.retain(|chat| chat.kind == expected);
         }
 
+        let now = chrono::Utc::now().timestamp();
+        if let Some(secs) = stale_secs {
+            retain_stale(&mut chats, secs, now);
+        }
+        retain_changed(&mut chats, changed.as_ref());
+        if let Some(sort) = sort {
+            sort_listings(&mut chats, sort);
+        }
+
         if json {
-            println!("{}", serde_json::to_string_pretty(&chats)?);
+            crate::util::output_json(&chats)?;
             return Ok(());
         }
 
         let rows = chats
             .iter()
             .map(|chat| {
-                vec![
+                let mut row = vec![
                     type_label(&chat.kind).to_string(),
                     chat.title.clone(),
                     if chat.has_unread {
@@ -253,14 +500,602 @@ pub fn cmd_loco_chats(
                     } else {
                         String::new()
                     },
-                    chat.chat_id.to_string(),
-                ]
+                ];
+                if stale_secs.is_some() {
+                    row.push(last_active_label(chat, now));
+                }
+                row.push(chat.chat_id.to_string());
+                row
             })
             .collect::<Vec<_>>();
 
         print_section_title(&format!("Chats ({})", rows.len()));
-        print_table(&["Type", "Name", "Unread", "Chat ID"], rows);
+        if stale_secs.is_some() {
+            print_table(&["Type", "Name", "Unread", "Last active", "Chat ID"], rows);
+        } else {
+            print_table(&["Type", "Name", "Unread", "Chat ID"], rows);
+        }
+        print_renames(&chats);
 
         Ok(())
     })
 }
+
+/// Cached-message coverage of one chat in the local message cache.
+#[derive(Debug, Clone, Serialize)]
+struct ArchiveCoverage {
+    messages: i64,
+    first_at: i64,
+    last_at: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct ChatDetail {
+    #[serde(flatten)]
+    room: ChatRoom,
+    display_title: String,
+    /// Members returned by the member list; `None` when that call failed.
+    members: Option<usize>,
+    archive: Option<ArchiveCoverage>,
+    /// Titles recorded by syncs, oldest first.
+    titles: Vec<TitleEntry>,
+}
+
+fn archive_coverage(chat_id: i64) -> Result<Option<ArchiveCoverage>> {
+    // Only look at an existing cache; opening one would create it.
+    if !crate::message_db::db_path()?.exists() {
+        return Ok(None);
+    }
+    let db = MessageDb::open()?;
+    Ok(db
+        .time_bounds(chat_id)?
+        .map(|(first_at, last_at)| ArchiveCoverage {
+            messages: db.message_count(chat_id).unwrap_or(0),
+            first_at,
+            last_at,
+        }))
+}
+
+/// Recorded titles for `chat_id`; empty when there is no message cache yet.
+pub(crate) fn title_history(chat_id: i64) -> Result<Vec<TitleEntry>> {
+    if !crate::message_db::db_path()?.exists() {
+        return Ok(Vec::new());
+    }
+    MessageDb::open()?.title_history(chat_id)
+}
+
+/// `chat <chat_id>` / `chats info <chat_id>`: everything known about one
+/// room — the chat list entry, one member list call and the local message
+/// cache. Fields the server did not send are left out rather than shown as
+/// zero.
+pub fn cmd_chat(chat_id: i64, json: bool) -> Result<()> {
+    let client = get_rest_client()?;
+    let room = client
+        .get_all_chats()?
+        .into_iter()
+        .find(|room| room.chat_id == chat_id)
+        .ok_or_else(|| anyhow!("Chat {chat_id} is not in your chat list"))?;
+
+    let members = match client.get_chat_members(chat_id) {
+        Ok(members) => Some(members.len()),
+        Err(err) => {
+            warn(
+                WarningCode::PartialData,
+                format!("member list unavailable: {err:#}"),
+            );
+            None
+        }
+    };
+    let archive = archive_coverage(chat_id).unwrap_or_else(|err| {
+        warn(
+            WarningCode::PartialData,
+            format!("message cache unavailable: {err:#}"),
+        );
+        None
+    });
+    let titles = title_history(chat_id).unwrap_or_else(|err| {
+        warn(
+            WarningCode::PartialData,
+            format!("title history unavailable: {err:#}"),
+        );
+        Vec::new()
+    });
+
+    let detail = ChatDetail {
+        display_title: room.display_title(),
+        room,
+        members,
+        archive,
+        titles,
+    };
+    if json {
+        return crate::util::output_json(&detail);
+    }
+
+    let room = &detail.room;
+    let settings = &room.settings;
+    let now = chrono::Utc::now().timestamp();
+    let mut rows = vec![
+        (
+            "Type",
+            format!("{} ({})", type_label(&room.kind), room.kind),
+        ),
+        ("Chat ID", room.chat_id.to_string()),
+    ];
+    if let Some(current) = detail.titles.last() {
+        rows.push((
+            "Title",
+            format!(
+                "{} (seen since {})",
+                display_safe(&current.title),
+                format_time(current.observed_at)
+            ),
+        ));
+    }
+    if detail.titles.len() > 1 {
+        let previous = detail
+            .titles
+            .windows(2)
+            .rev()
+            .map(|pair| {
+                format!(
+                    "{} (until {})",
+                    display_safe(&pair[0].title),
+                    format_time(pair[1].observed_at)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("; ");
+        rows.push(("Previous titles", previous));
+    }
+    if let Some(count) = detail.members.map(|n| n as i64).or(settings.member_count) {
+        rows.push(("Members", count.to_string()));
+    }
+    let member_names = room.display_member_names();
+    if !member_names.is_empty() {
+        let names = member_names
+            .iter()
+            .map(|(_, name)| display_safe(name).into_owned())
+            .collect::<Vec<_>>()
+            .join(", ");
+        rows.push(("Display members", names));
+    }
+    if let Some(link) = &settings.open_link {
+        let mut value = format!("{} (link {})", display_safe(&link.name), link.link_id);
+        if !link.url.is_empty() {
+            value.push_str(&format!(" {}", link.url));
+        }
+        rows.push(("Open link", value));
+    }
+    rows.push(("Pinned", yes_no(settings.pinned).to_string()));
+    rows.push(("Muted", yes_no(settings.muted).to_string()));
+    rows.push(("Unread", room.unread_count.to_string()));
+    if let Some(watermark) = settings.watermark {
+        let mut value = format!("log {watermark}");
+        if let Some(last) = settings.last_log_id {
+            value.push_str(&format!(" (latest {last})"));
+        }
+        rows.push(("Read up to", value));
+    }
+    if let Some(ts) = room.last_active_at {
+        rows.push((
+            "Last active",
+            format!("{} ({})", format_time(ts), format_relative_age(ts, now)),
+        ));
+    }
+    if let Some(ts) = room.last_updated_at {
+        rows.push(("Last updated", format_time(ts)));
+    }
+    if let Some(msg) = &room.last_message {
+        let author = member_names
+            .iter()
+            .find(|(user_id, _)| *user_id == msg.author_id)
+            .map(|(_, name)| name.clone())
+            .or_else(|| msg.author_nickname.clone().filter(|n| !n.is_empty()))
+            .unwrap_or_else(|| format!("User#{}", msg.author_id));
+        rows.push((
+            "Last message",
+            format!(
+                "{} {}: {}",
+                format_time(msg.send_at),
+                display_safe(&author),
+                display_safe(&truncate(&crate::commands::read::message_body(msg), 60))
+            ),
+        ));
+    }
+    rows.push((
+        "Cached",
+        match &detail.archive {
+            Some(archive) => format!(
+                "{} messages, {} → {}",
+                archive.messages,
+                format_time(archive.first_at),
+                format_time(archive.last_at)
+            ),
+            None => "nothing cached locally".to_string(),
+        },
+    ));
+
+    print_section_title(&display_safe(&detail.display_title));
+    let width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0) + 1;
+    for (label, value) in rows {
+        println!("  {:<width$} {}", format!("{label}:"), value);
+    }
+    Ok(())
+}
+
+fn yes_no(flag: bool) -> &'static str {
+    if flag {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+/// A friend and our 1:1 chat with them, for `dm <friend>`.
+#[derive(Debug, Clone, Serialize)]
+pub struct DmTarget {
+    pub user_id: i64,
+    pub name: String,
+    pub chat_id: i64,
+    /// `friends` when the friends list named the chat, `chats` when it was
+    /// found by scanning the chat list.
+    pub source: &'static str,
+}
+
+/// Resolve `friend` (a user id or a name, 초성 allowed) to our direct chat.
+/// Fails when the name is unknown or ambiguous, or no direct chat exists.
+pub fn resolve_dm(friend: &str) -> Result<DmTarget> {
+    let client = get_rest_client()?;
+    let mut resolver = name_resolver(get_creds()?.user_id);
+
+    let (user_id, name) = match friend.trim().parse::<i64>() {
+        Ok(user_id) => {
+            let names = resolver.friend_names(|| Ok(client.get_friends()?))?;
+            let name = names
+                .get(&user_id)
+                .cloned()
+                .unwrap_or_else(|| user_id.to_string());
+            (user_id, name)
+        }
+        Err(_) => find_one_friend(&mut resolver, friend, || Ok(client.get_friends()?))?,
+    };
+
+    let chat = resolver
+        .direct_chat(user_id, || Ok(client.get_all_chats()?))?
+        .ok_or_else(|| {
+            anyhow!(
+                "No direct chat with {} ({user_id}) yet. Start one in KakaoTalk first.",
+                display_safe(&name)
+            )
+        })?;
+    Ok(DmTarget {
+        user_id,
+        name,
+        chat_id: chat.chat_id,
+        source: if chat.from_friends_list {
+            "friends"
+        } else {
+            "chats"
+        },
+    })
+}
+
+/// The one friend whose name matches `query` (초성 allowed), as
+/// `(user_id, name)`. Fails when none matches, or lists the candidates when
+/// several do.
+pub fn find_one_friend<F>(
+    resolver: &mut NameResolver,
+    query: &str,
+    fetch: F,
+) -> Result<(i64, String)>
+where
+    F: FnOnce() -> Result<Vec<crate::model::Friend>>,
+{
+    let mut found = resolver.find_friends(query, fetch)?;
+    match found.len() {
+        0 => Err(anyhow!("No friend matches '{}'", display_safe(query))),
+        1 => Ok(found.remove(0)),
+        n => {
+            let listed: Vec<String> = found
+                .iter()
+                .take(10)
+                .map(|(id, name)| format!("{} ({id})", display_safe(name)))
+                .collect();
+            Err(anyhow!(
+                "'{}' matches {n} friends: {}{}. Use the user id instead.",
+                display_safe(query),
+                listed.join(", "),
+                if n > listed.len() { ", ..." } else { "" }
+            ))
+        }
+    }
+}
+
+/// `dm <friend>` without `--read`/`--send`: print the chat id.
+pub fn cmd_dm(target: &DmTarget, json: bool) -> Result<()> {
+    if json {
+        return crate::util::output_json(target);
+    }
+    eprintln!(
+        "Direct chat with {} ({})",
+        display_safe(&target.name),
+        target.user_id
+    );
+    println!("{}", target.chat_id);
+    Ok(())
+}
+
+/// `notice <chat_id>`: the room's pinned notice from LOCO `CHATINFO`, kept
+/// in the message cache so `--history` can list the earlier ones this
+/// command has seen. A refused `CHATINFO` fails with the server's status.
+pub fn cmd_notice(chat_id: i64, history: bool, json: bool) -> Result<()> {
+    let creds = get_creds()?;
+    let my_user_id = creds.user_id;
+    let rt = Runtime::new()?;
+    let info = rt.block_on(async {
+        let mut client = loco::client::LocoClient::new(creds);
+        loco_connect_with_auto_refresh(&mut client).await?;
+        let response = client
+            .send_command("CHATINFO", bson::doc! { "chatId": chat_id })
+            .await?;
+        check_loco_status("CHATINFO", &response)?;
+        Ok::<_, anyhow::Error>(bson::from_document::<serde_json::Value>(response.body)?)
+    })?;
+    let notice = Notice::from_chat_info(&info);
+
+    let db = MessageDb::open();
+    if let (Ok(db), Some(notice)) = (&db, &notice) {
+        if let Err(err) = db.record_notice(chat_id, notice) {
+            warn(
+                WarningCode::CacheUnavailable,
+                format!("Could not record the notice: {err:#}"),
+            );
+        }
+    }
+    let earlier: Vec<Notice> = if history {
+        match &db {
+            Ok(db) => db
+                .notice_history(chat_id)?
+                .into_iter()
+                .filter(|old| Some(old) != notice.as_ref())
+                .collect(),
+            Err(err) => {
+                warn(
+                    WarningCode::CacheUnavailable,
+                    format!("No notice history without the message cache: {err:#}"),
+                );
+                Vec::new()
+            }
+        }
+    } else {
+        Vec::new()
+    };
+
+    // Best effort: authors who left or a chat the REST server has not
+    // cached fall back to their user id.
+    let mut resolver = name_resolver(my_user_id);
+    if notice.is_some() || !earlier.is_empty() {
+        if let Ok(client) = get_rest_client() {
+            let _ = resolver.member_names(chat_id, || Ok(client.get_chat_members(chat_id)?));
+        }
+    }
+    let author_of = |notice: &Notice| {
+        resolver
+            .resolve(chat_id, notice.author_id)
+            .unwrap_or_else(|| notice.author_id.to_string())
+    };
+
+    if json {
+        let with_author = |notice: &Notice| -> Result<serde_json::Value> {
+            let mut value = serde_json::to_value(notice)?;
+            value["author_name"] = author_of(notice).into();
+            Ok(value)
+        };
+        let mut output = serde_json::json!({
+            "chat_id": chat_id,
+            "notice": notice.as_ref().map(with_author).transpose()?,
+        });
+        if history {
+            output["history"] = earlier
+                .iter()
+                .map(with_author)
+                .collect::<Result<Vec<_>>>()?
+                .into();
+        }
+        crate::util::output_json(&output)?;
+        return Ok(());
+    }
+
+    let print_notice = |notice: &Notice| {
+        println!(
+            "{} · {}",
+            display_safe(&author_of(notice)),
+            format_time(notice.updated_at)
+        );
+        println!("{}", display_safe(&notice.content));
+    };
+    match &notice {
+        Some(notice) => {
+            print_section_title(&format!("Notice in chat {chat_id}"));
+            print_notice(notice);
+        }
+        None => println!("Chat {chat_id} has no notice."),
+    }
+    if history {
+        println!();
+        if earlier.is_empty() {
+            println!(
+                "No earlier notices recorded. The server only returns the current one; \
+                 each run of `notice` keeps what it sees."
+            );
+        } else {
+            print_section_title(&format!("Earlier notices ({})", earlier.len()));
+            for (i, old) in earlier.iter().enumerate() {
+                if i > 0 {
+                    println!();
+                }
+                print_notice(old);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Most board pages `board` fetches to collect `count` posts.
+const BOARD_MAX_PAGES: usize = 20;
+
+/// `board <chat_id>`: the newest posts on an open chat community's board,
+/// from `cursor` on when given. Whole pages are shown, so the last one may
+/// take the listing past `count`; the printed cursor continues after it.
+pub fn cmd_board(chat_id: i64, count: usize, cursor: Option<&str>, json: bool) -> Result<()> {
+    let client = get_rest_client()?;
+    let mut posts: Vec<BoardPost> = Vec::new();
+    let mut cursor = cursor.map(str::to_string);
+    for _ in 0..BOARD_MAX_PAGES {
+        let page = client.get_board_posts(chat_id, cursor.as_deref())?;
+        let empty = page.items.is_empty();
+        posts.extend(page.items);
+        cursor = page.next_cursor;
+        if empty || cursor.is_none() || posts.len() >= count {
+            break;
+        }
+    }
+    let mut resolver = name_resolver(client.user_id());
+    if !posts.is_empty() {
+        let _ = resolver.member_names(chat_id, || Ok(client.get_chat_members(chat_id)?));
+    }
+    let author_of = |author_id: i64| {
+        resolver
+            .resolve(chat_id, author_id)
+            .unwrap_or_else(|| author_id.to_string())
+    };
+
+    if json {
+        let items = posts
+            .iter()
+            .map(|post| {
+                let mut value = serde_json::to_value(post)?;
+                value["author_name"] = author_of(post.author_id).into();
+                Ok(value)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        crate::util::output_json(&serde_json::json!({
+            "chat_id": chat_id,
+            "posts": items,
+            "next_cursor": cursor,
+        }))?;
+        return Ok(());
+    }
+
+    if posts.is_empty() {
+        println!("No board posts in chat {chat_id}.");
+        return Ok(());
+    }
+    print_table(
+        &["Type", "Author", "Date", "Title", "Comments", "Post ID"],
+        posts
+            .iter()
+            .map(|post| {
+                let kind = if post.notice && post.kind != "notice" {
+                    format!("{} (notice)", post.kind)
+                } else {
+                    post.kind.clone()
+                };
+                vec![
+                    kind,
+                    display_safe(&author_of(post.author_id)).into_owned(),
+                    format_time(post.created_at),
+                    display_safe(&truncate(post.preview(), 50)).into_owned(),
+                    post.comment_count.to_string(),
+                    post.post_id.clone(),
+                ]
+            })
+            .collect(),
+    );
+    if let Some(cursor) = cursor {
+        println!("\nOlder posts: openkakao-rs board {chat_id} --cursor {cursor}");
+    }
+    println!("Full post with comments: openkakao-rs board show <post_id> --chat {chat_id}");
+    Ok(())
+}
+
+/// `board show <post_id>`: one post in full, then every comment under it.
+/// `chat_id` is only used to name the authors.
+pub fn cmd_board_show(post_id: &str, chat_id: Option<i64>, json: bool) -> Result<()> {
+    let client = get_rest_client()?;
+    let mut post = client.get_board_post(post_id)?;
+    let mut cursor: Option<String> = None;
+    for _ in 0..BOARD_MAX_PAGES {
+        let page = client.get_board_comments(post_id, cursor.as_deref())?;
+        let empty = page.items.is_empty();
+        post.comments.extend(page.items);
+        cursor = page.next_cursor;
+        if empty || cursor.is_none() {
+            break;
+        }
+    }
+    if cursor.is_some() {
+        warn(
+            WarningCode::PartialData,
+            format!(
+                "Showing the first {} comments; the post has more",
+                post.comments.len()
+            ),
+        );
+    }
+
+    let mut resolver = name_resolver(client.user_id());
+    if let Some(chat_id) = chat_id {
+        let _ = resolver.member_names(chat_id, || Ok(client.get_chat_members(chat_id)?));
+    }
+    let author_of = |author_id: i64| {
+        chat_id
+            .and_then(|chat_id| resolver.resolve(chat_id, author_id))
+            .unwrap_or_else(|| author_id.to_string())
+    };
+
+    if json {
+        let mut value = serde_json::to_value(&post)?;
+        value["author_name"] = author_of(post.author_id).into();
+        if let Some(comments) = value.get_mut("comments").and_then(|c| c.as_array_mut()) {
+            for (comment, parsed) in comments.iter_mut().zip(&post.comments) {
+                comment["author_name"] = author_of(parsed.author_id).into();
+            }
+        }
+        crate::util::output_json(&value)?;
+        return Ok(());
+    }
+
+    let title = if post.title.is_empty() {
+        format!("Board post {}", post.post_id)
+    } else {
+        display_safe(&post.title).into_owned()
+    };
+    print_section_title(&title);
+    println!(
+        "{} · {} · {}{}",
+        post.kind,
+        display_safe(&author_of(post.author_id)),
+        format_time(post.created_at),
+        if post.notice { " · notice" } else { "" }
+    );
+    if !post.content.is_empty() {
+        println!("\n{}", display_safe(&post.content));
+    }
+    println!();
+    if post.comments.is_empty() {
+        println!("No comments.");
+        return Ok(());
+    }
+    print_section_title(&format!("Comments ({})", post.comments.len()));
+    for comment in &post.comments {
+        println!(
+            "{} · {}",
+            display_safe(&author_of(comment.author_id)),
+            format_time(comment.created_at)
+        );
+        println!("  {}", display_safe(&comment.content).replace('\n', "\n  "));
+    }
+    Ok(())
+}
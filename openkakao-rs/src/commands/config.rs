@@ -0,0 +1,87 @@
+use anyhow::Result;
+
+use crate::commands::watch::watch_state_path;
+use crate::config::config_path;
+use crate::credentials::{credentials_path, profiles_dir};
+use crate::media_cache::cache_dir;
+use crate::message_db::db_path;
+use crate::outbox::outbox_path;
+use crate::paths;
+use crate::state::state_path;
+use crate::util::{print_section_title, print_table};
+
+pub fn cmd_config_path(json: bool) -> Result<()> {
+    let scope = paths::scope();
+    let credentials_scope = if paths::credentials_are_local() {
+        "local"
+    } else {
+        "global"
+    };
+    let files = [
+        ("config", config_path()?),
+        ("profile_state", paths::profile_dir()?),
+        ("state", state_path()?),
+        ("watch_state", watch_state_path()?),
+        ("outbox", outbox_path()?),
+        ("ignore_list", crate::ignore::ignore_path()?),
+        ("message_cache", db_path()?),
+        ("media_cache", cache_dir()?),
+        ("credentials", credentials_path()?),
+        ("credential_profiles", profiles_dir()?),
+    ];
+
+    if json {
+        let paths: serde_json::Map<String, serde_json::Value> = files
+            .iter()
+            .map(|(name, path)| (name.to_string(), path.display().to_string().into()))
+            .collect();
+        let out = serde_json::json!({
+            "scope": scope,
+            "profile": paths::profile(),
+            "credentials_scope": credentials_scope,
+            "paths": paths,
+        });
+        crate::util::output_json(&out)?;
+        return Ok(());
+    }
+
+    print_section_title(&format!("Scope: {}", scope.describe()));
+    println!("Credentials: {}", credentials_scope);
+    println!("Profile:     {}", paths::profile());
+    println!();
+    let rows = files
+        .iter()
+        .map(|(name, path)| vec![name.to_string(), path.display().to_string()])
+        .collect();
+    print_table(&["File", "Path"], rows);
+    Ok(())
+}
+
+/// `version`: the version, and with `--features` which optional parts this
+/// binary has, so scripts can check before calling a command that needs one.
+pub fn cmd_version(features: bool, json: bool) -> Result<()> {
+    if json {
+        let mut out = serde_json::json!({ "version": crate::util::VERSION });
+        if features {
+            out["features"] = serde_json::to_value(crate::capabilities::ALL)?;
+        }
+        return crate::util::output_json(&out);
+    }
+
+    println!("openkakao-rs {}", crate::util::VERSION);
+    if features {
+        println!();
+        let rows = crate::capabilities::ALL
+            .iter()
+            .map(|capability| {
+                vec![
+                    capability.name.to_string(),
+                    if capability.compiled { "yes" } else { "no" }.to_string(),
+                    capability.description.to_string(),
+                ]
+            })
+            .collect();
+        print_table(&["Feature", "Compiled", "Provides"], rows);
+    }
+    Ok(())
+}
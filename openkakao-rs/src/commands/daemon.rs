@@ -0,0 +1,486 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::auth_flow::{
+    attempt_renew, pin_credentials, reload_credentials, watch_credentials, RecoveryAttempt,
+};
+use crate::config::{config_path, load_config, DaemonConfig};
+use crate::credential_watch::{fingerprint, CredentialWatch, FileWatch, Reload};
+use crate::credentials::save_credentials;
+use crate::logs::{daemon_log_path, rotate_if_due, Level, RotationPolicy};
+use crate::output::write_atomic;
+use crate::scheduler::{plan_tasks, Clock, DaemonTask, Scheduler, SystemClock, TaskKind};
+use crate::util::{get_creds, print_section_title, print_table};
+
+pub const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Longest sleep between checks for a shutdown signal.
+const SHUTDOWN_POLL: Duration = Duration::from_secs(1);
+
+/// Written by a running daemon after every task, read by `daemon status`.
+#[derive(Debug, Serialize, Deserialize)]
+struct DaemonStatus {
+    pid: u32,
+    started_at: String,
+    updated_at: String,
+    /// Set when the daemon shut down cleanly.
+    stopped_at: Option<String>,
+    tasks: Vec<TaskStatusEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TaskStatusEntry {
+    name: String,
+    kind: String,
+    every_secs: i64,
+    last_run: Option<String>,
+    last_duration_secs: Option<i64>,
+    next_run: String,
+    last_error: Option<String>,
+    running: bool,
+    runs: u64,
+    failures: u64,
+}
+
+fn status_path() -> Result<PathBuf> {
+    Ok(crate::paths::profile_dir()?.join("daemon-status.json"))
+}
+
+fn format_at(at: NaiveDateTime) -> String {
+    at.format(TIME_FORMAT).to_string()
+}
+
+fn write_status(
+    scheduler: &Scheduler,
+    tasks: &[DaemonTask],
+    started_at: NaiveDateTime,
+    now: NaiveDateTime,
+    stopped: bool,
+) -> Result<()> {
+    let status = DaemonStatus {
+        pid: std::process::id(),
+        started_at: format_at(started_at),
+        updated_at: format_at(now),
+        stopped_at: stopped.then(|| format_at(now)),
+        tasks: scheduler
+            .tasks()
+            .zip(tasks)
+            .map(|((plan, status), task)| TaskStatusEntry {
+                name: plan.name.clone(),
+                kind: task.kind.label().to_string(),
+                every_secs: plan.every.num_seconds(),
+                last_run: status.last_run.map(format_at),
+                last_duration_secs: status.last_duration.map(|d| d.num_seconds()),
+                next_run: format_at(status.next_run),
+                last_error: status.last_error.clone(),
+                running: status.running,
+                runs: status.runs,
+                failures: status.failures,
+            })
+            .collect(),
+    };
+    let data = serde_json::to_string_pretty(&status)?;
+    let path = status_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    write_atomic(&path, |w| Ok(w.write_all(data.as_bytes())?))
+}
+
+/// Appends one JSON object per line, rotating the file as configured.
+struct DaemonLog {
+    path: PathBuf,
+    policy: RotationPolicy,
+}
+
+impl DaemonLog {
+    fn new(config: &DaemonConfig) -> Result<Self> {
+        Ok(Self {
+            path: daemon_log_path(config.log_file.as_deref())?,
+            policy: RotationPolicy::from_config(config)?,
+        })
+    }
+
+    fn event(&self, now: NaiveDateTime, task: &str, event: &str, extra: serde_json::Value) {
+        if let Err(err) = rotate_if_due(&self.path, &self.policy, now) {
+            crate::warnings::warn(
+                crate::warnings::WarningCode::StateSaveFailed,
+                format!("daemon log {} not rotated: {:#}", self.path.display(), err),
+            );
+        }
+        let level = match event {
+            "error" => Level::Error,
+            "credentials_rejected" | "credentials_unverified" | "config_rejected" => Level::Warn,
+            _ => Level::Info,
+        };
+        let mut line = serde_json::json!({
+            "ts": format_at(now),
+            "level": level.label(),
+            "task": task,
+            "event": event,
+        });
+        if let (Some(line), serde_json::Value::Object(extra)) = (line.as_object_mut(), extra) {
+            line.extend(extra);
+        }
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| writeln!(file, "{line}"));
+        if let Err(err) = written {
+            crate::warnings::warn(
+                crate::warnings::WarningCode::StateSaveFailed,
+                format!("daemon log {} not written: {}", self.path.display(), err),
+            );
+        }
+    }
+}
+
+/// Set on SIGINT or SIGTERM. The task in progress finishes first; a second
+/// signal exits at once.
+fn shutdown_on_signal() -> Arc<AtomicBool> {
+    let flag = Arc::new(AtomicBool::new(false));
+    let set = flag.clone();
+    std::thread::spawn(move || {
+        let Ok(rt) = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+        else {
+            return;
+        };
+        rt.block_on(async move {
+            loop {
+                wait_for_signal().await;
+                if set.swap(true, Ordering::SeqCst) {
+                    eprintln!("[daemon] Second signal, exiting now.");
+                    std::process::exit(130);
+                }
+                eprintln!(
+                    "[daemon] Shutting down after the current task (signal again to exit now)..."
+                );
+            }
+        });
+    });
+    flag
+}
+
+async fn wait_for_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        if let Ok(mut term) = signal(SignalKind::terminate()) {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = term.recv() => {}
+            }
+            return;
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+/// Run one task through the same code as its command.
+fn run_task(task: &DaemonTask) -> Result<()> {
+    match task.kind {
+        TaskKind::Sync => {
+            let chats = if task.chats.is_empty() {
+                crate::commands::analytics::sync_chat_ids()?
+            } else {
+                task.chats.clone()
+            };
+            let mut failed = Vec::new();
+            for chat_id in &chats {
+                if let Err(err) =
+                    crate::commands::analytics::cmd_cache(*chat_id, None, false, false, false)
+                {
+                    failed.push(format!("{}: {}", chat_id, err));
+                }
+            }
+            if !failed.is_empty() {
+                anyhow::bail!(
+                    "{} of {} chats failed to sync ({})",
+                    failed.len(),
+                    chats.len(),
+                    failed.join("; ")
+                );
+            }
+            Ok(())
+        }
+        TaskKind::Renew => match attempt_renew(&get_creds()?)? {
+            RecoveryAttempt::Recovered { credentials, .. } => {
+                save_credentials(&credentials)?;
+                Ok(())
+            }
+            RecoveryAttempt::Failed { source, detail, .. } => {
+                anyhow::bail!("{} failed: {}", source, detail)
+            }
+            RecoveryAttempt::Unavailable { source, reason } => {
+                anyhow::bail!("{} unavailable: {}", source, reason)
+            }
+        },
+        TaskKind::Unread => {
+            let result = crate::commands::chats::cmd_chats_rest(
+                true, false, None, None, None, None, true, false, true,
+            );
+            // `chats --since-state` reports "nothing changed" as its exit
+            // code; that is not the daemon's.
+            crate::util::EXIT_CODE.store(0, Ordering::Relaxed);
+            result
+        }
+    }
+}
+
+/// Re-plan the tasks when the config file changed. A config that no longer
+/// parses, or has no tasks, is reported and the running plan stays.
+fn reload_config(
+    watch: &mut FileWatch,
+    tasks: &mut Vec<DaemonTask>,
+    scheduler: &mut Scheduler,
+    log: &DaemonLog,
+    now: NaiveDateTime,
+) {
+    if watch.changed().is_empty() {
+        return;
+    }
+    let planned = load_config().and_then(|config| plan_tasks(&config.daemon));
+    match planned {
+        Ok(planned) if !planned.is_empty() => {
+            scheduler.replan(planned.iter().map(|task| task.plan.clone()).collect(), now);
+            *tasks = planned;
+            eprintln!(
+                "[daemon] Config changed; now running {} tasks.",
+                tasks.len()
+            );
+            log.event(
+                now,
+                "daemon",
+                "config_reloaded",
+                serde_json::json!({"tasks": tasks.len()}),
+            );
+        }
+        planned => {
+            let reason = match planned {
+                Err(err) => format!("{err:#}"),
+                Ok(_) => "no tasks configured".to_string(),
+            };
+            crate::warnings::warn(
+                crate::warnings::WarningCode::PartialData,
+                format!("changed config not applied ({reason}); keeping the running tasks"),
+            );
+            log.event(
+                now,
+                "daemon",
+                "config_rejected",
+                serde_json::json!({"reason": reason}),
+            );
+        }
+    }
+}
+
+/// Take up credentials another run saved, logging the rotation.
+fn reload_daemon_credentials(watch: &mut CredentialWatch, log: &DaemonLog, now: NaiveDateTime) {
+    match reload_credentials(watch, "daemon") {
+        None | Some(Reload::Unchanged) => {}
+        Some(Reload::Rotated { previous, current }) => log.event(
+            now,
+            "daemon",
+            "credentials_rotated",
+            serde_json::json!({"token": fingerprint(&current), "previous": fingerprint(&previous)}),
+        ),
+        Some(Reload::Rejected { reason }) => log.event(
+            now,
+            "daemon",
+            "credentials_rejected",
+            serde_json::json!({"reason": reason, "token": fingerprint(watch.current())}),
+        ),
+        Some(Reload::Deferred { reason }) => log.event(
+            now,
+            "daemon",
+            "credentials_unverified",
+            serde_json::json!({"reason": reason, "token": fingerprint(watch.current())}),
+        ),
+    }
+}
+
+pub fn cmd_daemon_run(json: bool) -> Result<()> {
+    let config = load_config()?;
+    let mut tasks = plan_tasks(&config.daemon)?;
+    if tasks.is_empty() {
+        anyhow::bail!(
+            "No daemon tasks configured. Add [[daemon.tasks]] entries to {}",
+            config_path()?.display()
+        );
+    }
+    let log = DaemonLog::new(&config.daemon)?;
+    let shutdown = shutdown_on_signal();
+    let clock = SystemClock;
+    let started_at = clock.now();
+    let mut scheduler = Scheduler::new(
+        tasks.iter().map(|task| task.plan.clone()).collect(),
+        started_at,
+    );
+
+    log.event(
+        started_at,
+        "daemon",
+        "start",
+        serde_json::json!({"pid": std::process::id(), "tasks": tasks.len()}),
+    );
+    eprintln!(
+        "[daemon] Running {} tasks. Log: {}; `openkakao-rs daemon status` shows progress.",
+        tasks.len(),
+        log.path.display()
+    );
+    write_status(&scheduler, &tasks, started_at, started_at, false)?;
+
+    // Tasks resolve credentials as they run; pinning them means a token
+    // saved by another run is only used once it has been verified.
+    let mut config_watch = FileWatch::new([config_path()?]);
+    let mut credential_watch = match get_creds() {
+        Ok(creds) => {
+            pin_credentials(creds.clone());
+            Some(watch_credentials(creds)?)
+        }
+        Err(err) => {
+            eprintln!(
+                "[daemon] No credentials yet ({err:#}); tasks will look for them as they run."
+            );
+            None
+        }
+    };
+
+    while !shutdown.load(Ordering::SeqCst) {
+        reload_config(
+            &mut config_watch,
+            &mut tasks,
+            &mut scheduler,
+            &log,
+            clock.now(),
+        );
+        if let Some(watch) = credential_watch.as_mut() {
+            reload_daemon_credentials(watch, &log, clock.now());
+        }
+        if let Some(name) = scheduler.next_due(clock.now()) {
+            let task = tasks
+                .iter()
+                .find(|task| task.plan.name == name)
+                .expect("scheduler only knows configured tasks");
+            let started = clock.now();
+            log.event(started, &name, "start", serde_json::json!({}));
+            let result = run_task(task).map_err(|err| format!("{err:#}"));
+            let finished = clock.now();
+            let duration_secs = (finished - started).num_seconds();
+            match &result {
+                Ok(()) => log.event(
+                    finished,
+                    &name,
+                    "ok",
+                    serde_json::json!({"duration_secs": duration_secs}),
+                ),
+                Err(err) => {
+                    eprintln!("[daemon] {} failed: {}", name, err);
+                    log.event(
+                        finished,
+                        &name,
+                        "error",
+                        serde_json::json!({"duration_secs": duration_secs, "error": err}),
+                    );
+                }
+            }
+            scheduler.finish(&name, started, finished, result);
+            write_status(&scheduler, &tasks, started_at, finished, false)?;
+            continue;
+        }
+        let now = clock.now();
+        let wait = scheduler
+            .next_wakeup()
+            .and_then(|next| (next - now).to_std().ok())
+            .unwrap_or(SHUTDOWN_POLL);
+        std::thread::sleep(wait.min(SHUTDOWN_POLL));
+    }
+
+    let stopped = clock.now();
+    log.event(stopped, "daemon", "stop", serde_json::json!({}));
+    write_status(&scheduler, &tasks, started_at, stopped, true)?;
+    if json {
+        crate::util::output_json(&serde_json::json!({
+            "status": "stopped",
+            "started_at": format_at(started_at),
+            "stopped_at": format_at(stopped),
+        }))?;
+    } else {
+        eprintln!("[daemon] Stopped.");
+    }
+    Ok(())
+}
+
+pub fn cmd_daemon_status(json: bool) -> Result<()> {
+    let path = status_path()?;
+    if !path.exists() {
+        anyhow::bail!("No daemon status yet. Start it with 'openkakao-rs daemon'.");
+    }
+    let data = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let status: DaemonStatus = serde_json::from_str(&data)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+    if json {
+        crate::util::output_json(&status)?;
+        return Ok(());
+    }
+
+    print_section_title("Daemon");
+    println!("  pid {}, started {}", status.pid, status.started_at);
+    match &status.stopped_at {
+        Some(at) => println!("  stopped {}", at),
+        None => println!("  last update {}", status.updated_at),
+    }
+    println!();
+    let rows = status
+        .tasks
+        .iter()
+        .map(|task| {
+            vec![
+                task.name.clone(),
+                task.kind.clone(),
+                format_every(task.every_secs),
+                task.last_run.clone().unwrap_or_else(|| "-".into()),
+                if task.running {
+                    "running".into()
+                } else {
+                    task.next_run.clone()
+                },
+                task.last_error.clone().unwrap_or_default(),
+            ]
+        })
+        .collect();
+    print_table(
+        &[
+            "Task",
+            "Kind",
+            "Every",
+            "Last run",
+            "Next run",
+            "Last error",
+        ],
+        rows,
+    );
+    Ok(())
+}
+
+fn format_every(secs: i64) -> String {
+    match secs {
+        s if s % 86_400 == 0 => format!("{}d", s / 86_400),
+        s if s % 3600 == 0 => format!("{}h", s / 3600),
+        s if s % 60 == 0 => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
@@ -6,10 +6,13 @@ use owo_colors::OwoColorize;
 use crate::auth::get_credential_candidates;
 use crate::config::OpenKakaoConfig;
 use crate::credentials::load_credentials;
+use crate::humanize;
 use crate::model::KakaoCredentials;
+use crate::paths;
 use crate::rest::KakaoRestClient;
 use crate::state::{recovery_snapshot, safety_snapshot};
-use crate::util::{color_enabled, VERSION};
+use crate::theme::theme;
+use crate::util::{color_enabled, format_relative_age, nudge_kakaotalk, stale_cache_note, VERSION};
 
 struct Check {
     name: String,
@@ -25,13 +28,26 @@ enum CheckStatus {
 
 fn format_remaining(remaining_secs: Option<u64>, until: Option<&str>) -> String {
     match (remaining_secs, until) {
-        (Some(secs), Some(until)) => format!("{}s (until {})", secs, until),
-        (Some(secs), None) => format!("{}s", secs),
+        (Some(secs), Some(until)) => format!(
+            "{} (until {})",
+            humanize::duration(secs, humanize::lang()),
+            until
+        ),
+        (Some(secs), None) => humanize::duration(secs, humanize::lang()),
         _ => "none".to_string(),
     }
 }
 
-pub fn cmd_doctor(json: bool, test_loco: bool, config: &OpenKakaoConfig) -> Result<()> {
+/// How long `doctor --nudge` gives KakaoTalk to make requests before the
+/// cache is checked again.
+const NUDGE_WAIT: std::time::Duration = std::time::Duration::from_secs(20);
+
+pub fn cmd_doctor(
+    json: bool,
+    test_loco: bool,
+    nudge: bool,
+    config: &OpenKakaoConfig,
+) -> Result<()> {
     let mut checks: Vec<Check> = Vec::new();
     let mut installed_version: Option<String> = None;
     let mut saved_app_version: Option<String> = None;
@@ -45,6 +61,20 @@ pub fn cmd_doctor(json: bool, test_loco: bool, config: &OpenKakaoConfig) -> Resu
         config.safety.min_webhook_interval_secs.unwrap_or(2),
     )?;
 
+    let scope = paths::scope();
+    checks.push(Check {
+        name: "State scope".into(),
+        status: CheckStatus::Ok,
+        detail: format!(
+            "{}; credentials {}",
+            scope.describe(),
+            if paths::credentials_are_local() {
+                "local"
+            } else {
+                "global"
+            }
+        ),
+    });
     checks.push(Check {
         name: "State file".into(),
         status: CheckStatus::Ok,
@@ -129,6 +159,19 @@ pub fn cmd_doctor(json: bool, test_loco: bool, config: &OpenKakaoConfig) -> Resu
         });
     }
 
+    match crate::auth::find_kakao_container() {
+        Ok(path) => checks.push(Check {
+            name: "KakaoTalk container".into(),
+            status: CheckStatus::Ok,
+            detail: path.display().to_string(),
+        }),
+        Err(e) => checks.push(Check {
+            name: "KakaoTalk container".into(),
+            status: CheckStatus::Fail,
+            detail: format!("{e}. Install and log in to KakaoTalk for Mac."),
+        }),
+    }
+
     // 2. KakaoTalk process running
     let pgrep_output = std::process::Command::new("pgrep")
         .args(["-x", "KakaoTalk"])
@@ -156,39 +199,45 @@ pub fn cmd_doctor(json: bool, test_loco: bool, config: &OpenKakaoConfig) -> Resu
     let cache_db =
         home.join("Library/Containers/com.kakao.KakaoTalkMac/Data/Library/Caches/Cache.db");
     if cache_db.exists() {
-        match std::fs::metadata(&cache_db) {
-            Ok(meta) => {
-                let modified = meta
-                    .modified()
-                    .ok()
-                    .and_then(|t| t.elapsed().ok())
-                    .map(|d| {
-                        if d.as_secs() < 60 {
-                            format!("{}s ago", d.as_secs())
-                        } else if d.as_secs() < 3600 {
-                            format!("{}m ago", d.as_secs() / 60)
-                        } else if d.as_secs() < 86400 {
-                            format!("{}h ago", d.as_secs() / 3600)
-                        } else {
-                            format!("{}d ago", d.as_secs() / 86400)
-                        }
+        let mut freshness = crate::auth::cache_freshness_at(&cache_db);
+        let stale = freshness
+            .as_ref()
+            .is_ok_and(|f| stale_cache_note(f).is_some());
+        if nudge && stale {
+            nudge_kakaotalk()?;
+            eprintln!(
+                "Opened KakaoTalk; re-checking its cache in {}s...",
+                NUDGE_WAIT.as_secs()
+            );
+            std::thread::sleep(NUDGE_WAIT);
+            freshness = crate::auth::cache_freshness_at(&cache_db);
+        }
+        let size_kb = std::fs::metadata(&cache_db).map_or(0, |meta| meta.len() / 1024);
+        match freshness {
+            Ok(freshness) => {
+                let now = chrono::Utc::now().timestamp();
+                let age = |at: Option<chrono::DateTime<chrono::Utc>>| {
+                    at.map_or("unknown".into(), |at| {
+                        format_relative_age(at.timestamp(), now)
                     })
-                    .unwrap_or_else(|| "unknown".into());
-                let size_kb = meta.len() / 1024;
-                let status = if meta
-                    .modified()
-                    .ok()
-                    .and_then(|t| t.elapsed().ok())
-                    .is_some_and(|d| d.as_secs() > 86400)
-                {
-                    CheckStatus::Warn
-                } else {
-                    CheckStatus::Ok
+                };
+                let mut detail = format!(
+                    "{}KB, modified {}, newest kakao.com request {}",
+                    size_kb,
+                    age(freshness.modified),
+                    age(freshness.newest_request)
+                );
+                let status = match stale_cache_note(&freshness) {
+                    Some(note) => {
+                        detail = format!("{detail}. {note}");
+                        CheckStatus::Warn
+                    }
+                    None => CheckStatus::Ok,
                 };
                 checks.push(Check {
                     name: "Cache.db".into(),
                     status,
-                    detail: format!("{}KB, modified {}", size_kb, modified),
+                    detail,
                 });
             }
             Err(e) => {
@@ -207,40 +256,24 @@ pub fn cmd_doctor(json: bool, test_loco: bool, config: &OpenKakaoConfig) -> Resu
         });
     }
 
-    // 4. Saved credentials file
+    // 4. Saved credentials (keychain, then file)
     match crate::credentials::credentials_path() {
-        Ok(path) => {
-            if path.exists() {
-                match load_credentials() {
-                    Ok(Some(creds)) => {
-                        saved_app_version = Some(creds.app_version.clone());
-                        checks.push(Check {
-                            name: "Saved credentials".into(),
-                            status: CheckStatus::Ok,
-                            detail: format!(
-                                "user_id={}, version={}, token={}...",
-                                creds.user_id,
-                                creds.app_version,
-                                creds.oauth_token.chars().take(8).collect::<String>()
-                            ),
-                        });
-                    }
-                    Ok(None) => {
-                        checks.push(Check {
-                            name: "Saved credentials".into(),
-                            status: CheckStatus::Warn,
-                            detail: "File exists but empty/invalid".into(),
-                        });
-                    }
-                    Err(e) => {
-                        checks.push(Check {
-                            name: "Saved credentials".into(),
-                            status: CheckStatus::Warn,
-                            detail: format!("Parse error: {}", e),
-                        });
-                    }
-                }
-            } else {
+        Ok(path) => match crate::credentials::load_credentials_located() {
+            Ok(Some((creds, location))) => {
+                saved_app_version = Some(creds.app_version.clone());
+                checks.push(Check {
+                    name: "Saved credentials".into(),
+                    status: CheckStatus::Ok,
+                    detail: format!(
+                        "user_id={}, version={}, token={}... in {}",
+                        creds.user_id,
+                        creds.app_version,
+                        creds.oauth_token.chars().take(8).collect::<String>(),
+                        location
+                    ),
+                });
+            }
+            Ok(None) => {
                 checks.push(Check {
                     name: "Saved credentials".into(),
                     status: CheckStatus::Warn,
@@ -250,7 +283,14 @@ pub fn cmd_doctor(json: bool, test_loco: bool, config: &OpenKakaoConfig) -> Resu
                     ),
                 });
             }
-        }
+            Err(e) => {
+                checks.push(Check {
+                    name: "Saved credentials".into(),
+                    status: CheckStatus::Warn,
+                    detail: format!("Parse error: {}", e),
+                });
+            }
+        },
         Err(e) => {
             checks.push(Check {
                 name: "Saved credentials".into(),
@@ -301,6 +341,22 @@ pub fn cmd_doctor(json: bool, test_loco: bool, config: &OpenKakaoConfig) -> Resu
                         status: CheckStatus::Ok,
                         detail: format!("Valid (user_id={})", creds.user_id),
                     });
+                    let scope = client.probe_scope();
+                    let rejected = scope.rejected();
+                    checks.push(Check {
+                        name: "Token scope".into(),
+                        status: if rejected.is_empty() {
+                            CheckStatus::Ok
+                        } else {
+                            CheckStatus::Warn
+                        },
+                        detail: match rejected.first() {
+                            Some(family) => {
+                                format!("{} ({})", scope.summary(), family.unavailable_hint())
+                            }
+                            None => scope.summary(),
+                        },
+                    });
                 }
                 Ok(false) => {
                     checks.push(Check {
@@ -489,10 +545,11 @@ pub fn cmd_doctor(json: bool, test_loco: bool, config: &OpenKakaoConfig) -> Resu
             .collect();
         let out = serde_json::json!({
             "checks": items,
+            "scope": scope,
             "recovery_state": recovery,
             "safety_state": safety,
         });
-        println!("{}", serde_json::to_string_pretty(&out)?);
+        crate::util::output_json(&out)?;
     } else {
         println!("openkakao-rs doctor (v{})", VERSION);
         println!();
@@ -500,21 +557,21 @@ pub fn cmd_doctor(json: bool, test_loco: bool, config: &OpenKakaoConfig) -> Resu
             let (icon, color_fn): (&str, fn(&str) -> String) = match c.status {
                 CheckStatus::Ok => {
                     if color_enabled() {
-                        ("OK", |s: &str| format!("{}", s.green()))
+                        ("OK", |s: &str| format!("{}", s.style(theme().ok)))
                     } else {
                         ("OK", |s: &str| s.to_string())
                     }
                 }
                 CheckStatus::Warn => {
                     if color_enabled() {
-                        ("WARN", |s: &str| format!("{}", s.yellow()))
+                        ("WARN", |s: &str| format!("{}", s.style(theme().warn)))
                     } else {
                         ("WARN", |s: &str| s.to_string())
                     }
                 }
                 CheckStatus::Fail => {
                     if color_enabled() {
-                        ("FAIL", |s: &str| format!("{}", s.red()))
+                        ("FAIL", |s: &str| format!("{}", s.style(theme().fail)))
                     } else {
                         ("FAIL", |s: &str| s.to_string())
                     }
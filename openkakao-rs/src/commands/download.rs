@@ -1,17 +1,429 @@
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
+use std::time::Duration;
 
 use anyhow::Result;
+use chrono::TimeZone;
 
+use crate::attachments::{
+    dedupe_links, AttachmentEntry, AttachmentFilter, AttachmentKind, MediaTarget,
+};
+use crate::download_pool::{self, DownloadJob, DownloadResult, Outcome, PoolOptions};
+use crate::links::{markdown_by_month, shared_links, urls_in_message, LinksFormat, SharedLink};
 use crate::loco_helpers::{check_loco_status, loco_connect_with_auto_refresh};
-use crate::media::{download_media_file, parse_attachment_url, sanitize_filename};
-use crate::util::{get_bson_i32, get_bson_i64, get_bson_str, get_creds, truncate};
+use crate::media::{
+    download_media_file, download_media_file_cached, parse_attachment_url, sanitize_filename,
+};
+use crate::message_db::MessageDb;
+use crate::model::json_string;
+use crate::progress::{ProgressEvent, ProgressSink, StderrProgress};
+use crate::rest::KakaoRestClient;
+use crate::scrap_cache::{ScrapCache, DEFAULT_TTL_SECS};
+use crate::util::{
+    display_safe, format_bytes, get_bson_i32, get_bson_i64, get_bson_str, get_creds,
+    get_rest_client, name_resolver, print_section_title, print_table, truncate,
+};
+use crate::warnings::{warn, WarningCode};
 
-pub fn cmd_download(chat_id: i64, log_id: i64, output_dir: Option<&str>, json: bool) -> Result<()> {
+/// List photo, video, file and link attachments in a chat: the local
+/// message cache first, then up to `pages` REST pages of recent messages.
+/// The same link shared repeatedly is listed once, at its newest message.
+pub fn cmd_files(chat_id: i64, pages: usize, filter: &AttachmentFilter, json: bool) -> Result<()> {
+    let mut entries: BTreeMap<i64, AttachmentEntry> = BTreeMap::new();
+    let mut names: HashMap<i64, String> = HashMap::new();
+
+    match MessageDb::open().and_then(|db| db.get_messages(chat_id, 0)) {
+        Ok(cached) => {
+            for m in cached {
+                let entry = AttachmentEntry::from_message(
+                    m.log_id,
+                    m.author_id,
+                    i64::from(m.message_type),
+                    &m.message,
+                    &m.attachment,
+                    m.send_at,
+                );
+                if let Some(entry) = entry {
+                    if !m.author_name.is_empty() {
+                        names.insert(m.author_id, m.author_name);
+                    }
+                    entries.insert(entry.log_id, entry);
+                }
+            }
+        }
+        Err(err) => warn(
+            WarningCode::CacheUnavailable,
+            format!("local message cache not read: {}", err),
+        ),
+    }
+    let archived = entries.len();
+
+    let client = if pages > 0 {
+        Some(get_rest_client()?)
+    } else {
+        None
+    };
+    if let Some(client) = &client {
+        let progress = StderrProgress::new("files");
+        match client.get_all_messages_with_progress(chat_id, pages, &progress) {
+            Ok(messages) => {
+                for m in messages {
+                    let entry = AttachmentEntry::from_message(
+                        m.log_id,
+                        m.author_id,
+                        m.message_type,
+                        &m.message,
+                        &m.attachment,
+                        m.send_at,
+                    );
+                    if let Some(entry) = entry {
+                        if let Some(name) = m.author_nickname {
+                            names.entry(m.author_id).or_insert(name);
+                        }
+                        entries.insert(entry.log_id, entry);
+                    }
+                }
+            }
+            Err(err) => warn(
+                WarningCode::PartialData,
+                format!(
+                    "network pages skipped, showing cached attachments only: {}",
+                    err
+                ),
+            ),
+        }
+    }
+
+    let matched: Vec<AttachmentEntry> = dedupe_links(
+        entries
+            .into_values()
+            .rev()
+            .filter(|entry| filter.keeps(entry))
+            .collect(),
+    );
+
+    if let Some(client) = &client {
+        resolve_author_names(
+            client,
+            chat_id,
+            matched.iter().map(|e| e.author_id),
+            &mut names,
+        )?;
+    }
+    let author = |user_id: i64| {
+        names
+            .get(&user_id)
+            .cloned()
+            .unwrap_or_else(|| user_id.to_string())
+    };
+
+    if json {
+        let out: Vec<serde_json::Value> = matched
+            .iter()
+            .map(|entry| {
+                let mut value = serde_json::to_value(entry)?;
+                value["author"] = author(entry.author_id).into();
+                Ok(value)
+            })
+            .collect::<Result<_>>()?;
+        crate::util::output_json(&out)?;
+        return Ok(());
+    }
+
+    if matched.is_empty() {
+        println!(
+            "No matching attachments ({} cached, {} network pages scanned).",
+            archived, pages
+        );
+        return Ok(());
+    }
+
+    print_section_title(&format!(
+        "Attachments in chat {} ({})",
+        chat_id,
+        matched.len()
+    ));
+    let rows = matched
+        .iter()
+        .map(|entry| {
+            vec![
+                chrono::Local
+                    .timestamp_opt(entry.send_at, 0)
+                    .single()
+                    .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_default(),
+                display_safe(&author(entry.author_id)).into_owned(),
+                entry.kind.label().to_string(),
+                display_safe(&entry.filename).into_owned(),
+                entry.size.map(format_bytes).unwrap_or_default(),
+                entry.log_id.to_string(),
+            ]
+        })
+        .collect();
+    print_table(
+        &["Date", "Author", "Type", "Filename", "Size", "Log ID"],
+        rows,
+    );
+    println!(
+        "\nDownload one: openkakao-rs download {} --log-id <LOG_ID>",
+        chat_id
+    );
+    Ok(())
+}
+
+/// Fill in `names` for the `authors` not named yet from the chat's members.
+fn resolve_author_names(
+    client: &KakaoRestClient,
+    chat_id: i64,
+    authors: impl IntoIterator<Item = i64>,
+    names: &mut HashMap<i64, String>,
+) -> Result<()> {
+    let missing: Vec<i64> = authors
+        .into_iter()
+        .filter(|id| !names.contains_key(id))
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    let mut resolver = name_resolver(get_creds()?.user_id);
+    if resolver
+        .member_names(chat_id, || Ok(client.get_chat_members(chat_id)?))
+        .is_ok()
+    {
+        for author_id in missing {
+            if let Some(name) = resolver.resolve(chat_id, author_id) {
+                names.entry(author_id).or_insert(name);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Options of `links`.
+#[derive(Debug, Clone, Copy)]
+pub struct LinksOptions {
+    /// REST message pages to scan after the local cache.
+    pub pages: usize,
+    /// Fetch previews of links the preview cache has no fresh title for.
+    pub enrich: bool,
+    /// Neither read nor write the preview cache.
+    pub no_cache: bool,
+    pub format: LinksFormat,
+}
+
+/// Space between link preview fetches, on top of the client's pacing: each
+/// one makes Kakao fetch a third-party page.
+const PREVIEW_GAP: Duration = Duration::from_secs(1);
+
+/// Every link shared in a chat, once each at its first share: the local
+/// message cache first, then up to `pages` REST pages of recent messages.
+/// Titles come from the `scrap` preview cache, and with `enrich` from the
+/// preview service for links it has none for.
+pub fn cmd_links(chat_id: i64, options: &LinksOptions, json: bool) -> Result<()> {
+    let strip = crate::urlnorm::strip_tracking();
+    let mut messages: BTreeMap<i64, (i64, i64, Vec<String>)> = BTreeMap::new();
+    let mut names: HashMap<i64, String> = HashMap::new();
+
+    match MessageDb::open().and_then(|db| db.get_messages(chat_id, 0)) {
+        Ok(cached) => {
+            for m in cached {
+                let urls = urls_in_message(&m.message, &m.attachment, strip);
+                if urls.is_empty() {
+                    continue;
+                }
+                if !m.author_name.is_empty() {
+                    names.insert(m.author_id, m.author_name);
+                }
+                messages.insert(m.log_id, (m.author_id, m.send_at, urls));
+            }
+        }
+        Err(err) => warn(
+            WarningCode::CacheUnavailable,
+            format!("local message cache not read: {}", err),
+        ),
+    }
+
+    let client = if options.pages > 0 || options.enrich {
+        Some(get_rest_client()?)
+    } else {
+        None
+    };
+    if let (Some(client), true) = (&client, options.pages > 0) {
+        let progress = StderrProgress::new("links");
+        match client.get_all_messages_with_progress(chat_id, options.pages, &progress) {
+            Ok(page) => {
+                for m in page {
+                    let urls = urls_in_message(&m.message, &m.attachment, strip);
+                    if urls.is_empty() {
+                        continue;
+                    }
+                    if let Some(name) = m.author_nickname {
+                        names.entry(m.author_id).or_insert(name);
+                    }
+                    messages
+                        .entry(m.log_id)
+                        .or_insert((m.author_id, m.send_at, urls));
+                }
+            }
+            Err(err) => warn(
+                WarningCode::PartialData,
+                format!("network pages skipped, showing cached links only: {}", err),
+            ),
+        }
+    }
+
+    let mut links = shared_links(
+        messages
+            .into_iter()
+            .map(|(log_id, (author_id, send_at, urls))| (log_id, author_id, send_at, urls)),
+    );
+    add_titles(
+        &mut links,
+        client.as_ref().filter(|_| options.enrich),
+        options.no_cache,
+    );
+
+    if let Some(client) = &client {
+        resolve_author_names(
+            client,
+            chat_id,
+            links.iter().map(|l| l.author_id),
+            &mut names,
+        )?;
+    }
+    let author = |user_id: i64| {
+        names
+            .get(&user_id)
+            .cloned()
+            .unwrap_or_else(|| user_id.to_string())
+    };
+
+    if json {
+        let out: Vec<serde_json::Value> = links
+            .iter()
+            .map(|link| {
+                let mut value = serde_json::to_value(link)?;
+                value["author"] = author(link.author_id).into();
+                Ok(value)
+            })
+            .collect::<Result<_>>()?;
+        crate::util::output_json(&out)?;
+        return Ok(());
+    }
+
+    if options.format == LinksFormat::Markdown {
+        let title = format!("Links in chat {chat_id}");
+        print!(
+            "{}",
+            markdown_by_month(&title, &links, author, &chrono::Local)
+        );
+        return Ok(());
+    }
+
+    if links.is_empty() {
+        println!(
+            "No links found (local cache and {} network pages scanned).",
+            options.pages
+        );
+        return Ok(());
+    }
+    print_section_title(&format!("Links in chat {} ({})", chat_id, links.len()));
+    let rows = links
+        .iter()
+        .map(|link| {
+            vec![
+                chrono::Local
+                    .timestamp_opt(link.send_at, 0)
+                    .single()
+                    .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_default(),
+                display_safe(&author(link.author_id)).into_owned(),
+                truncate(&display_safe(link.title.as_deref().unwrap_or("")), 40),
+                display_safe(&link.url).into_owned(),
+                link.times_shared.to_string(),
+            ]
+        })
+        .collect();
+    print_table(&["Shared", "By", "Title", "URL", "Times"], rows);
+    if !options.enrich && links.iter().any(|link| link.title.is_none()) {
+        println!("\nAdd titles from link previews: openkakao-rs links {chat_id} --enrich");
+    }
+    Ok(())
+}
+
+/// Titles from the preview cache, fetching missing or stale previews with
+/// `client` when given. Failed fetches are counted and reported once.
+fn add_titles(links: &mut [SharedLink], client: Option<&KakaoRestClient>, no_cache: bool) {
+    let cache = if no_cache {
+        None
+    } else {
+        match ScrapCache::open() {
+            Ok(cache) => Some(cache),
+            Err(err) => {
+                warn(
+                    WarningCode::CacheUnavailable,
+                    format!("link preview cache not read: {err}"),
+                );
+                None
+            }
+        }
+    };
+    let now = chrono::Utc::now().timestamp();
+    let mut fetched = 0usize;
+    let mut failed = 0usize;
+    for link in links.iter_mut() {
+        let cached = cache.as_ref().and_then(|cache| cache.get(&link.url));
+        let fresh = cached
+            .as_ref()
+            .is_some_and(|entry| entry.is_fresh(now, DEFAULT_TTL_SECS));
+        let preview = match client {
+            Some(client) if !fresh && crate::urlnorm::is_fetchable(&link.url) => {
+                if fetched > 0 {
+                    std::thread::sleep(PREVIEW_GAP);
+                }
+                fetched += 1;
+                match client.get_scrap_preview(&link.url) {
+                    Ok(preview) => {
+                        if let Some(cache) = &cache {
+                            if let Err(err) = cache.put(&link.url, &preview, now) {
+                                tracing::debug!("preview of {} not cached: {err:#}", link.url);
+                            }
+                        }
+                        Some(preview)
+                    }
+                    Err(err) => {
+                        tracing::debug!("preview of {} failed: {err}", link.url);
+                        failed += 1;
+                        cached.map(|entry| entry.preview)
+                    }
+                }
+            }
+            _ => cached.map(|entry| entry.preview),
+        };
+        link.title = preview
+            .map(|preview| json_string(&preview, "title"))
+            .filter(|title| !title.is_empty());
+    }
+    if failed > 0 {
+        warn(
+            WarningCode::PartialData,
+            format!("{failed} of {fetched} link previews could not be fetched"),
+        );
+    }
+}
+
+pub fn cmd_download(
+    chat_id: i64,
+    log_id: i64,
+    output_dir: Option<&str>,
+    cache: bool,
+    json: bool,
+) -> Result<()> {
     let creds = get_creds()?;
     let out_dir = output_dir.unwrap_or("downloads");
 
     let rt = tokio::runtime::Runtime::new()?;
-    rt.block_on(async {
+    let (msg_type, url, save_path) = rt.block_on(async {
         let mut client = crate::loco::client::LocoClient::new(creds.clone());
         eprintln!("Connecting via LOCO...");
         loco_connect_with_auto_refresh(&mut client).await?;
@@ -99,20 +511,7 @@ pub fn cmd_download(chat_id: i64, log_id: i64, output_dir: Option<&str>, json: b
             Some((url, filename)) => {
                 let dir = Path::new(out_dir).join(chat_id.to_string());
                 let save_name = format!("{}_{}", log_id, sanitize_filename(&filename));
-                let save_path = dir.join(&save_name);
-
-                eprintln!("Downloading: {}", url);
-                let bytes = download_media_file(&creds, &url, &save_path)?;
-                if json {
-                    crate::util::output_json(&serde_json::json!({
-                        "status": "ok",
-                        "path": save_path.display().to_string(),
-                        "media_type": crate::util::message_type_label(msg_type),
-                        "size": bytes,
-                    }))?;
-                } else {
-                    println!("Saved: {} ({} bytes)", save_path.display(), bytes);
-                }
+                Ok((msg_type, url, dir.join(save_name)))
             }
             None => {
                 anyhow::bail!(
@@ -122,7 +521,144 @@ pub fn cmd_download(chat_id: i64, log_id: i64, output_dir: Option<&str>, json: b
                 );
             }
         }
+    })?;
+
+    // The blocking REST client stays outside the LOCO runtime.
+    let client = KakaoRestClient::new(creds)?;
+    eprintln!("Downloading: {}", url);
+    let bytes = if cache {
+        download_media_file_cached(&client, &url, &save_path)?
+    } else {
+        download_media_file(&client, &url, &save_path)?
+    };
+    if json {
+        crate::util::output_json(&serde_json::json!({
+            "status": "ok",
+            "path": save_path.display().to_string(),
+            "media_type": crate::util::message_type_label(msg_type),
+            "size": bytes,
+        }))?;
+    } else {
+        println!("Saved: {} ({} bytes)", save_path.display(), bytes);
+    }
+    Ok(())
+}
+
+/// `download <chat_id>` without a log id.
+#[derive(Debug, Clone)]
+pub struct BulkDownloadOptions {
+    /// Empty means photos, videos and files.
+    pub kinds: Vec<AttachmentKind>,
+    pub out_dir: String,
+    /// Epoch seconds; older messages are not downloaded and end the walk.
+    pub since: Option<i64>,
+    pub concurrency: usize,
+}
+
+/// Save every photo, video and file attachment in a chat (newest first,
+/// back to `--since`) into `<out>/<chat_id>/`. Files already there are
+/// skipped, so an interrupted run can simply be repeated.
+pub fn cmd_download_chat(chat_id: i64, opts: &BulkDownloadOptions, json: bool) -> Result<()> {
+    if opts.kinds.contains(&AttachmentKind::Link) {
+        anyhow::bail!("Links are not downloadable. Use --types photo,video,file");
+    }
+    let client = get_rest_client()?;
+    let dir = Path::new(&opts.out_dir).join(chat_id.to_string());
+
+    let progress = StderrProgress::new("download");
+    let mut messages = Vec::new();
+    let mut cursor = None;
+    for page in 1.. {
+        let (batch, next_cursor) = client.get_messages(chat_id, cursor)?;
+        if batch.is_empty() {
+            break;
+        }
+        let items = batch.len();
+        let reached_since = opts
+            .since
+            .is_some_and(|since| batch.iter().any(|m| m.send_at > 0 && m.send_at < since));
+        messages.extend(batch);
+        progress.emit(&ProgressEvent::PageFetched {
+            page,
+            items,
+            total: messages.len(),
+        });
+        if reached_since || next_cursor.is_none() {
+            break;
+        }
+        cursor = next_cursor;
+    }
+    let messages = crate::model::select_window(messages, usize::MAX, None);
+    progress.emit(&ProgressEvent::Done {
+        total: messages.len(),
+    });
 
-        Ok(())
-    })
+    let mut results = Vec::new();
+    let mut queue = Vec::new();
+    for msg in messages.iter().rev() {
+        if opts.since.is_some_and(|since| msg.send_at < since) {
+            continue;
+        }
+        for target in MediaTarget::from_message(msg) {
+            if !opts.kinds.is_empty() && !opts.kinds.contains(&target.kind) {
+                continue;
+            }
+            let path = dir.join(&target.filename);
+            if path.exists() {
+                results.push(DownloadResult {
+                    label: target.filename,
+                    path,
+                    outcome: Outcome::Skipped {
+                        reason: "already downloaded".to_string(),
+                    },
+                });
+            } else {
+                queue.push(DownloadJob {
+                    label: target.filename,
+                    url: target.url,
+                    path,
+                });
+            }
+        }
+    }
+
+    let options = PoolOptions {
+        jobs: opts.concurrency,
+        retry: crate::util::retry_policy(),
+    };
+    results.extend(download_pool::run(&client, queue, options));
+
+    let counts = download_pool::counts(&results);
+    if json {
+        crate::util::output_json(&serde_json::json!({
+            "chat_id": chat_id,
+            "dir": dir.display().to_string(),
+            "messages": messages.len(),
+            "counts": counts,
+            "results": results,
+        }))?;
+    } else {
+        println!(
+            "Attachments in {}: {} downloaded, {} skipped, {} failed",
+            dir.display(),
+            counts.succeeded,
+            counts.skipped,
+            counts.failed
+        );
+        let rows: Vec<Vec<String>> = download_pool::summary_rows(&results)
+            .into_iter()
+            .map(|[status, item, reason]| vec![status, display_safe(&item).into_owned(), reason])
+            .collect();
+        if !rows.is_empty() {
+            println!();
+            print_table(&["Result", "File", "Reason"], rows);
+        }
+    }
+    if counts.failed > 0 {
+        warn(
+            WarningCode::DownloadFailed,
+            format!("{} attachment downloads failed", counts.failed),
+        );
+    }
+    Ok(())
 }
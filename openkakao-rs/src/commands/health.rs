@@ -0,0 +1,75 @@
+use anyhow::Result;
+use owo_colors::OwoColorize;
+
+use crate::health::{assess, HealthLevel};
+use crate::state::load_state;
+use crate::theme::theme;
+use crate::util::{color_enabled, get_rest_client, print_section_title, print_table};
+
+const BEST_EFFORT_NOTE: &str = "Best-effort heuristics from what openkakao-rs has seen on this \
+    machine; not an official Kakao signal. An empty history does not mean the account is safe.";
+
+/// Restriction early-warning history and assessment. `check` first fetches
+/// the account settings once so a flipped restriction field is noticed now.
+pub fn cmd_health(check: bool, json: bool) -> Result<()> {
+    if check {
+        let settings = get_rest_client()?.get_settings()?;
+        crate::health::observe_settings(&settings);
+    }
+
+    let state = load_state()?;
+    let now = chrono::Utc::now();
+    let assessment = assess(&state.health_observations, now);
+
+    if json {
+        crate::util::output_json(&serde_json::json!({
+            "best_effort": true,
+            "note": BEST_EFFORT_NOTE,
+            "window_days": crate::health::ASSESSMENT_DAYS,
+            "assessment": assessment,
+            "observations": state.health_observations,
+        }))?;
+        return Ok(());
+    }
+
+    print_section_title("Account health (best-effort)");
+    let (mark, style) = match assessment.level {
+        HealthLevel::Ok => ("OK", theme().ok),
+        HealthLevel::Caution => ("CAUTION", theme().warn),
+        HealthLevel::Warning => ("WARNING", theme().fail),
+    };
+    if color_enabled() {
+        println!("  [{}] {}", mark.style(style), assessment.message);
+    } else {
+        println!("  [{}] {}", mark, assessment.message);
+    }
+    println!("  {}", BEST_EFFORT_NOTE);
+
+    if state.health_observations.is_empty() {
+        println!("\n  No observations recorded.");
+        return Ok(());
+    }
+    println!();
+    let rows = state
+        .health_observations
+        .iter()
+        .rev()
+        .map(|o| {
+            let at = chrono::DateTime::parse_from_rfc3339(&o.at)
+                .map(|dt| {
+                    dt.with_timezone(&chrono::Local)
+                        .format("%Y-%m-%d %H:%M")
+                        .to_string()
+                })
+                .unwrap_or_else(|_| o.at.clone());
+            vec![
+                at,
+                o.kind.label().to_string(),
+                o.detail.clone(),
+                o.count.to_string(),
+            ]
+        })
+        .collect();
+    print_table(&["When", "Sign", "Detail", "Count"], rows);
+    Ok(())
+}
@@ -0,0 +1,192 @@
+use anyhow::{anyhow, Result};
+
+use crate::commands::chats::find_one_friend;
+use crate::hangul::name_matches;
+use crate::ignore::{IgnoreEntry, IgnoreList};
+use crate::util::{display_safe, format_time, get_creds, get_rest_client, name_resolver};
+
+/// `who` as `(user_id, name)`: a user id as is (named from the chat's
+/// members or friends when they know it), else a name among the members of
+/// `chat_id`, or among friends without a chat.
+fn resolve_author(who: &str, chat_id: Option<i64>) -> Result<(i64, String)> {
+    let client = get_rest_client()?;
+    let my_user_id = get_creds()?.user_id;
+    let mut resolver = name_resolver(my_user_id);
+
+    if let Ok(user_id) = who.trim().parse::<i64>() {
+        if user_id == my_user_id {
+            anyhow::bail!("That is your own user id.");
+        }
+        let name = match chat_id {
+            Some(chat_id) => resolver
+                .member_names(chat_id, || Ok(client.get_chat_members(chat_id)?))
+                .ok()
+                .and_then(|names| names.get(&user_id).cloned()),
+            None => resolver
+                .friend_names(|| Ok(client.get_friends()?))
+                .ok()
+                .and_then(|names| names.get(&user_id).cloned()),
+        };
+        return Ok((user_id, name.unwrap_or_default()));
+    }
+
+    let Some(chat_id) = chat_id else {
+        return find_one_friend(&mut resolver, who, || Ok(client.get_friends()?)).map_err(|err| {
+            anyhow!("{err}. For someone who is not a friend, add --chat <chat_id>.")
+        });
+    };
+    let names = resolver.member_names(chat_id, || Ok(client.get_chat_members(chat_id)?))?;
+    let wanted = who.trim().to_lowercase();
+    let mut found: Vec<(i64, String)> = names
+        .iter()
+        .filter(|(id, _)| **id != my_user_id)
+        .filter(|(_, name)| name.to_lowercase() == wanted)
+        .map(|(id, name)| (*id, name.clone()))
+        .collect();
+    if found.is_empty() {
+        found = names
+            .iter()
+            .filter(|(id, name)| **id != my_user_id && name_matches(name, who))
+            .map(|(id, name)| (*id, name.clone()))
+            .collect();
+    }
+    found.sort_by(|a, b| a.1.cmp(&b.1).then(a.0.cmp(&b.0)));
+    match found.len() {
+        0 => Err(anyhow!(
+            "No member of chat {chat_id} matches '{}'",
+            display_safe(who)
+        )),
+        1 => Ok(found.remove(0)),
+        n => Err(anyhow!(
+            "'{}' matches {n} members: {}. Use the user id instead.",
+            display_safe(who),
+            found
+                .iter()
+                .take(10)
+                .map(|(id, name)| format!("{} ({id})", display_safe(name)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+fn scope_label(chat_id: Option<i64>) -> String {
+    chat_id.map_or_else(|| "everywhere".to_string(), |id| format!("in chat {id}"))
+}
+
+/// `ignore add <who> [--chat <id>] [--allow]`.
+pub fn cmd_ignore_add(who: &str, chat_id: Option<i64>, allow: bool, json: bool) -> Result<()> {
+    if allow && chat_id.is_none() {
+        anyhow::bail!("--allow makes an exception for one chat; add --chat <chat_id>.");
+    }
+    let (user_id, name) = resolve_author(who, chat_id)?;
+    let mut list = IgnoreList::load()?;
+    let changed = list.set(IgnoreEntry {
+        user_id,
+        name: name.clone(),
+        chat_id,
+        hide: !allow,
+        added_at: chrono::Utc::now().timestamp(),
+    });
+    if changed {
+        list.save()?;
+    }
+    if json {
+        return crate::util::output_json(&serde_json::json!({
+            "user_id": user_id,
+            "name": name,
+            "chat_id": chat_id,
+            "hide": !allow,
+            "changed": changed,
+        }));
+    }
+    let who = if name.is_empty() {
+        user_id.to_string()
+    } else {
+        format!("{} ({user_id})", display_safe(&name))
+    };
+    let verb = if allow { "Showing" } else { "Ignoring" };
+    if changed {
+        println!("{verb} {who} {}.", scope_label(chat_id));
+    } else {
+        println!(
+            "Already {} {who} {}.",
+            verb.to_lowercase(),
+            scope_label(chat_id)
+        );
+    }
+    Ok(())
+}
+
+/// `ignore rm <who> [--chat <id>]`: `who` is matched against the list
+/// itself (user id or recorded name), so it works offline.
+pub fn cmd_ignore_rm(who: &str, chat_id: Option<i64>, json: bool) -> Result<()> {
+    let mut list = IgnoreList::load()?;
+    let mut ids: Vec<i64> = match who.trim().parse::<i64>() {
+        Ok(user_id) => vec![user_id],
+        Err(_) => list
+            .entries
+            .iter()
+            .filter(|e| e.chat_id == chat_id && name_matches(&e.name, who))
+            .map(|e| e.user_id)
+            .collect(),
+    };
+    ids.dedup();
+    let user_id = match ids.as_slice() {
+        [user_id] => *user_id,
+        [] => anyhow::bail!(
+            "No ignore entry {} matches '{}'. See `openkakao-rs ignore list`.",
+            scope_label(chat_id),
+            display_safe(who)
+        ),
+        _ => anyhow::bail!(
+            "'{}' matches several ignore entries; use the user id.",
+            display_safe(who)
+        ),
+    };
+    let removed = list.remove(user_id, chat_id);
+    if removed {
+        list.save()?;
+    }
+    if json {
+        return crate::util::output_json(&serde_json::json!({
+            "user_id": user_id,
+            "chat_id": chat_id,
+            "removed": removed,
+        }));
+    }
+    if removed {
+        println!("Removed {user_id} {}.", scope_label(chat_id));
+    } else {
+        println!("No entry for {user_id} {}.", scope_label(chat_id));
+    }
+    Ok(())
+}
+
+/// `ignore list`.
+pub fn cmd_ignore_list(json: bool) -> Result<()> {
+    let list = IgnoreList::load()?;
+    if json {
+        return crate::util::output_json(&list.entries);
+    }
+    if list.entries.is_empty() {
+        println!("Nobody is ignored. Add someone with `openkakao-rs ignore add <user_id|name>`.");
+        return Ok(());
+    }
+    let rows = list
+        .entries
+        .iter()
+        .map(|e| {
+            vec![
+                e.user_id.to_string(),
+                e.name.clone(),
+                e.chat_id
+                    .map_or_else(|| "all".to_string(), |id| id.to_string()),
+                if e.hide { "hide" } else { "show" }.to_string(),
+                format_time(e.added_at),
+            ]
+        })
+        .collect();
+    crate::util::print_table(&["User ID", "Name", "Chat", "Action", "Added"], rows);
+    Ok(())
+}
@@ -0,0 +1,271 @@
+use anyhow::Result;
+
+use crate::auth::get_credential_candidates;
+use crate::auth_flow::{credentials_from_env, get_credentials_interactive, select_best_credential};
+use crate::config::{config_path, set_config_values, OpenKakaoConfig};
+use crate::credentials::{load_credentials, save_credentials, save_target, CredentialStore};
+use crate::model::KakaoCredentials;
+use crate::onboarding::{
+    parse_interval_answer, parse_output_answer, suggested_first_command, Answers, Outcome,
+    OutputFormat, Step,
+};
+use crate::prompt;
+use crate::rest::{KakaoRestClient, TokenStatus};
+use crate::util::{mask_token, print_section_title};
+
+struct Wizard<'a> {
+    /// `-y`: take every default without asking.
+    yes: bool,
+    config: &'a OpenKakaoConfig,
+    creds: Option<KakaoCredentials>,
+    /// Where `creds` came from, for the summary.
+    source: &'static str,
+    saved: Option<KakaoCredentials>,
+    verified: Option<bool>,
+    answers: Answers,
+    notes: Vec<String>,
+}
+
+/// `init`: find, verify and save credentials, then write output and send
+/// throttle preferences to config.toml. Re-running shows the current values
+/// as defaults and changes nothing unless an answer differs.
+pub fn cmd_init(yes: bool, config: &OpenKakaoConfig) -> Result<()> {
+    let mut wizard = Wizard {
+        yes,
+        config,
+        creds: None,
+        source: "none",
+        saved: load_credentials().ok().flatten(),
+        verified: None,
+        answers: Answers::default(),
+        notes: Vec::new(),
+    };
+    let mut step = Step::FIRST;
+    while step != Step::Done {
+        print_section_title(&format!("[init] {}", step.title()));
+        let outcome = wizard.run(step)?;
+        println!();
+        step = step.next(outcome);
+    }
+    Ok(())
+}
+
+impl Wizard<'_> {
+    fn run(&mut self, step: Step) -> Result<Outcome> {
+        match step {
+            Step::Credentials => self.credentials(),
+            Step::Verify => self.verify(),
+            Step::Save => self.save(),
+            Step::OutputFormat => self.output_format(),
+            Step::Throttle => self.throttle(),
+            Step::WriteConfig => self.write_config(),
+            Step::Summary => self.summary(),
+            Step::Done => Ok(Outcome::Done),
+        }
+    }
+
+    fn credentials(&mut self) -> Result<Outcome> {
+        let found = match (self.saved.clone(), credentials_from_env()) {
+            (Some(saved), _) => Some((saved, "saved credentials")),
+            (None, Some(env)) => Some((env, "environment")),
+            (None, None) => {
+                println!("Looking for KakaoTalk's cached credentials...");
+                get_credential_candidates(8)
+                    .ok()
+                    .filter(|candidates| !candidates.is_empty())
+                    .and_then(|candidates| select_best_credential(candidates).ok())
+                    .map(|creds| (creds, "KakaoTalk cache"))
+            }
+        };
+        if let Some((creds, source)) = found {
+            println!("Found credentials in the {source}:");
+            println!("  User ID: {}", creds.user_id);
+            println!("  Token:   {}", mask_token(&creds.oauth_token));
+            if !self.yes_no("Use them?", true)? {
+                return Ok(Outcome::Skipped);
+            }
+            self.creds = Some(creds);
+            self.source = source;
+            return Ok(Outcome::Done);
+        }
+        if self.yes {
+            println!("No credentials found; skipped. Run KakaoTalk and log in, then re-run init.");
+            return Ok(Outcome::Skipped);
+        }
+        if !self.yes_no("Enter a token by hand?", false)? {
+            return Ok(Outcome::Skipped);
+        }
+        let creds = get_credentials_interactive()?;
+        if creds.oauth_token.is_empty() {
+            return Ok(Outcome::Skipped);
+        }
+        self.creds = Some(creds);
+        self.source = "manual entry";
+        Ok(Outcome::Done)
+    }
+
+    fn verify(&mut self) -> Result<Outcome> {
+        let Some(creds) = &self.creds else {
+            return Ok(Outcome::Skipped);
+        };
+        if !self.yes_no("Check the token with Kakao now?", true)? {
+            return Ok(Outcome::Skipped);
+        }
+        let status = KakaoRestClient::new(creds.clone())?.check_token();
+        match status {
+            TokenStatus::Valid => {
+                println!("Token verified OK.");
+                self.verified = Some(true);
+                Ok(Outcome::Done)
+            }
+            TokenStatus::Invalid { .. } => {
+                println!("Kakao refused the token: {}", status.reason());
+                println!("Open KakaoTalk to refresh it, then re-run init.");
+                self.verified = Some(false);
+                self.notes
+                    .push("The token was refused; it was not saved.".to_string());
+                Ok(Outcome::Failed)
+            }
+            other => {
+                println!("Could not verify the token: {}", other.reason());
+                Ok(Outcome::Skipped)
+            }
+        }
+    }
+
+    fn save(&mut self) -> Result<Outcome> {
+        let Some(creds) = &self.creds else {
+            return Ok(Outcome::Skipped);
+        };
+        let already_saved = self
+            .saved
+            .as_ref()
+            .is_some_and(|saved| saved.oauth_token == creds.oauth_token);
+        if already_saved {
+            println!("These credentials are already saved; nothing to do.");
+            return Ok(Outcome::Skipped);
+        }
+        let target = save_target()?;
+        if target.store() == CredentialStore::File {
+            println!("Credentials are stored as a plain JSON file readable only by you.");
+        }
+        if !self.yes_no(&format!("Save them to {target}?"), true)? {
+            return Ok(Outcome::Skipped);
+        }
+        let location = save_credentials(creds)?;
+        println!("Credentials saved to {location}");
+        self.saved = Some(creds.clone());
+        Ok(Outcome::Done)
+    }
+
+    fn output_format(&mut self) -> Result<Outcome> {
+        let current = OutputFormat::of(self.config);
+        let output = self.ask_until_valid(
+            &format!(
+                "Default output format, text or json [{}]: ",
+                current.as_str()
+            ),
+            |answer| parse_output_answer(answer, current),
+        )?;
+        self.answers.output = Some(output);
+        Ok(Outcome::Done)
+    }
+
+    fn throttle(&mut self) -> Result<Outcome> {
+        let current = self
+            .config
+            .safety
+            .min_unattended_send_interval_secs
+            .unwrap_or(10);
+        let secs = self.ask_until_valid(
+            &format!("Minimum seconds between unattended sends [{current}]: "),
+            |answer| parse_interval_answer(answer, current),
+        )?;
+        self.answers.send_interval_secs = Some(secs);
+        Ok(Outcome::Done)
+    }
+
+    fn write_config(&mut self) -> Result<Outcome> {
+        let edits = self.answers.config_edits(self.config);
+        let path = config_path()?;
+        if edits.is_empty() {
+            println!("{} already matches; nothing to write.", path.display());
+            return Ok(Outcome::Skipped);
+        }
+        for edit in &edits {
+            println!("  [{}] {} = {}", edit.section, edit.key, edit.value);
+        }
+        if path.exists() {
+            println!("Other keys are kept, but comments in the file are not.");
+        }
+        if !self.yes_no(&format!("Write these to {}?", path.display()), true)? {
+            return Ok(Outcome::Skipped);
+        }
+        let path = set_config_values(
+            edits
+                .into_iter()
+                .map(|edit| (edit.section, edit.key, edit.value)),
+        )?;
+        println!("Config written to {}", path.display());
+        Ok(Outcome::Done)
+    }
+
+    fn summary(&mut self) -> Result<Outcome> {
+        let verified = match self.verified {
+            Some(true) => "verified",
+            Some(false) => "refused by Kakao",
+            None => "not verified",
+        };
+        match &self.creds {
+            Some(creds) => println!(
+                "Credentials: user {} from {} ({verified})",
+                creds.user_id, self.source
+            ),
+            None => println!("Credentials: none"),
+        }
+        println!(
+            "Saved:       {}",
+            if self.saved.is_some() { "yes" } else { "no" }
+        );
+        println!("Config:      {}", config_path()?.display());
+        for note in &self.notes {
+            println!("Note: {note}");
+        }
+        let usable = self.creds.is_some() && self.verified != Some(false);
+        println!();
+        println!("Try next: {}", suggested_first_command(usable));
+        Ok(Outcome::Done)
+    }
+
+    /// `question [Y/n]`; an empty answer (or `-y`) takes `default`.
+    fn yes_no(&self, question: &str, default: bool) -> Result<bool> {
+        let hint = if default { "[Y/n]" } else { "[y/N]" };
+        if self.yes {
+            println!("{question} {hint} {}", if default { "y" } else { "n" });
+            return Ok(default);
+        }
+        let answer = prompt::line(&format!("{question} {hint} "))?;
+        Ok(match answer.to_ascii_lowercase().as_str() {
+            "" => default,
+            answer => answer.starts_with('y'),
+        })
+    }
+
+    /// Ask `label` until `parse` accepts the answer; `-y` answers empty.
+    fn ask_until_valid<T>(
+        &self,
+        label: &str,
+        parse: impl Fn(&str) -> Result<T, String>,
+    ) -> Result<T> {
+        if self.yes {
+            println!("{label}");
+            return parse("").map_err(anyhow::Error::msg);
+        }
+        loop {
+            match parse(&prompt::line(label)?) {
+                Ok(value) => return Ok(value),
+                Err(reason) => println!("  {reason}; try again (empty keeps the current value)."),
+            }
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::Result;
+use owo_colors::OwoColorize;
+use serde_json::Value;
+
+use crate::config::load_config;
+use crate::logs::{
+    at_least, daemon_log_path, rotate, rotated_files, Follower, Level, RotationPolicy,
+};
+use crate::theme::theme;
+use crate::util::color_enabled;
+
+/// How often `logs tail -f` looks for new lines.
+const FOLLOW_POLL: Duration = Duration::from_millis(500);
+
+fn log_path() -> Result<PathBuf> {
+    daemon_log_path(load_config()?.daemon.log_file.as_deref())
+}
+
+/// `ts LEVEL task: event key=value ...`; lines that are not JSON as they are.
+fn format_line(line: &str) -> String {
+    let Ok(Value::Object(entry)) = serde_json::from_str::<Value>(line) else {
+        return line.to_string();
+    };
+    let field = |key: &str| entry.get(key).and_then(Value::as_str).unwrap_or("");
+    let level = Level::of(&Value::Object(entry.clone()));
+    let extra: Vec<String> = entry
+        .iter()
+        .filter(|(key, _)| !matches!(key.as_str(), "ts" | "level" | "task" | "event"))
+        .map(|(key, value)| match value {
+            Value::String(text) => format!("{key}={text}"),
+            other => format!("{key}={other}"),
+        })
+        .collect();
+    let label = format!("{:<5}", level.label().to_uppercase());
+    let label = match (color_enabled(), level) {
+        (true, Level::Error) => label.style(theme().fail).to_string(),
+        (true, Level::Warn) => label.style(theme().warn).to_string(),
+        _ => label,
+    };
+    let ts = if color_enabled() {
+        field("ts").style(theme().timestamp).to_string()
+    } else {
+        field("ts").to_string()
+    };
+    let mut out = format!("{ts} {label} {}: {}", field("task"), field("event"));
+    if !extra.is_empty() {
+        out.push(' ');
+        out.push_str(&extra.join(" "));
+    }
+    out
+}
+
+fn print_line(line: &str, json: bool) {
+    if json {
+        println!("{line}");
+    } else {
+        println!("{}", format_line(line));
+    }
+}
+
+pub fn cmd_logs_tail(lines: usize, follow: bool, level: Level, json: bool) -> Result<()> {
+    let path = log_path()?;
+    let mut follower = Follower::new(&path);
+    let existing: Vec<String> = follower
+        .poll()?
+        .into_iter()
+        .filter(|line| at_least(line, level))
+        .collect();
+    if existing.is_empty() && !path.exists() && !follow {
+        eprintln!(
+            "No log at {} yet; the daemon creates it on start.",
+            path.display()
+        );
+        return Ok(());
+    }
+    for line in &existing[existing.len().saturating_sub(lines)..] {
+        print_line(line, json);
+    }
+    if !follow {
+        return Ok(());
+    }
+    loop {
+        std::thread::sleep(FOLLOW_POLL);
+        for line in follower.poll()? {
+            if at_least(&line, level) {
+                print_line(&line, json);
+            }
+        }
+        std::io::stdout().flush()?;
+    }
+}
+
+pub fn cmd_logs_path(json: bool) -> Result<()> {
+    let path = log_path()?;
+    let rotated = rotated_files(&path)?;
+    if json {
+        return crate::util::output_json(&serde_json::json!({
+            "current": path,
+            "exists": path.exists(),
+            "rotated": rotated,
+        }));
+    }
+    println!("{}", path.display());
+    for file in rotated.iter().rev() {
+        println!("{}", file.display());
+    }
+    Ok(())
+}
+
+pub fn cmd_logs_rotate(json: bool) -> Result<()> {
+    let config = load_config()?;
+    let path = daemon_log_path(config.daemon.log_file.as_deref())?;
+    let policy = RotationPolicy::from_config(&config.daemon)?;
+    let rotated = rotate(&path, &policy, chrono::Local::now().naive_local())?;
+    if json {
+        return crate::util::output_json(&serde_json::json!({
+            "current": path,
+            "rotated_to": rotated,
+        }));
+    }
+    match rotated {
+        Some(file) => println!("Rotated {} to {}", path.display(), file.display()),
+        None => println!("Nothing to rotate: {} does not exist.", path.display()),
+    }
+    Ok(())
+}
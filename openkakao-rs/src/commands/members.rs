@@ -1,18 +1,28 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use chrono::TimeZone;
 use owo_colors::OwoColorize;
 use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::Path;
 use std::time::Duration;
 
 use crate::loco;
 use crate::loco_helpers::{
     loco_connect_with_auto_refresh, reconnect_loco_probe_client, should_retry_loco_probe_error,
 };
+use crate::message_db::MessageDb;
 use crate::model::ChatMember;
+use crate::output::write_atomic;
+use crate::progress::{ProgressEvent, ProgressSink, StderrProgress};
+use crate::roster::{self, RosterRow};
+use crate::theme::theme;
 use crate::util::{
-    color_enabled, get_bson_bool, get_bson_i32, get_bson_i32_array, get_bson_i64,
+    color_enabled, display_safe, get_bson_bool, get_bson_i32, get_bson_i32_array, get_bson_i64,
     get_bson_i64_array, get_bson_str, get_creds, get_rest_client, print_section_title, print_table,
     truncate,
 };
+use crate::warnings::{warn, WarningCode};
 
 #[derive(Debug, Clone, Serialize, PartialEq, Eq)]
 pub struct LocoMemberProfile {
@@ -59,12 +69,9 @@ impl LocoMemberProfile {
     }
 
     pub fn as_chat_member(&self) -> ChatMember {
-        ChatMember {
-            user_id: self.user_id,
-            nickname: self.nickname.clone(),
-            friend_nickname: String::new(),
-            country_iso: self.country_iso.clone(),
-        }
+        let mut member = ChatMember::new(self.user_id, self.nickname.clone());
+        member.country_iso = self.country_iso.clone();
+        member
     }
 }
 
@@ -99,6 +106,10 @@ pub async fn fetch_loco_member_profiles_with_client(
         {
             Ok(response) => response,
             Err(error) if should_retry_loco_probe_error(&error) && attempt < 2 => {
+                StderrProgress::new("members").emit(&ProgressEvent::Retrying {
+                    attempt: attempt + 1,
+                    reason: format!("GETMEM: {error}"),
+                });
                 last_error = Some(error);
                 reconnect_loco_probe_client(client).await?;
                 continue;
@@ -243,18 +254,23 @@ pub async fn fetch_loco_blocked_snapshot(
     })
 }
 
-pub fn cmd_loco_members(chat_id: i64, full: bool, json: bool) -> Result<()> {
-    let profiles = fetch_loco_member_profiles(chat_id)?;
+pub fn cmd_loco_members(chat_id: i64, full: bool, limit: Option<usize>, json: bool) -> Result<()> {
+    // GETMEM answers with the whole room in one packet, so `--limit` only
+    // trims the output here; the REST path stops paging at the limit.
+    let mut profiles = fetch_loco_member_profiles(chat_id)?;
+    if let Some(limit) = limit {
+        profiles.truncate(limit);
+    }
 
     if json {
         if full {
-            println!("{}", serde_json::to_string_pretty(&profiles)?);
+            crate::util::output_json(&profiles)?;
         } else {
             let members = profiles
                 .iter()
                 .map(LocoMemberProfile::as_chat_member)
                 .collect::<Vec<_>>();
-            println!("{}", serde_json::to_string_pretty(&members)?);
+            crate::util::output_json(&members)?;
         }
         return Ok(());
     }
@@ -294,50 +310,367 @@ pub fn cmd_loco_members(chat_id: i64, full: bool, json: bool) -> Result<()> {
         if color_enabled() {
             println!(
                 "  {} {}",
-                format!("{}", profile.user_id).dimmed(),
-                profile.nickname.bold()
+                format!("{}", profile.user_id).style(theme().muted),
+                profile.nickname.style(theme().author)
             );
         } else {
-            println!("  {} {}", profile.user_id, profile.nickname);
+            println!("  {} {}", profile.user_id, display_safe(&profile.nickname));
         }
     }
 
     Ok(())
 }
 
-pub fn cmd_members_rest(chat_id: i64, json: bool) -> Result<()> {
+pub fn cmd_members_rest(chat_id: i64, limit: Option<usize>, json: bool) -> Result<()> {
     let client = get_rest_client()?;
-    let members = client.get_chat_members(chat_id)?;
+    let (members, total) =
+        client.get_chat_members_limited(chat_id, limit, &StderrProgress::new("members"))?;
 
     if json {
-        println!("{}", serde_json::to_string_pretty(&members)?);
+        crate::util::output_json(&members)?;
         return Ok(());
     }
 
+    let fetched = members.len();
     let mut rows = Vec::new();
     for m in members {
         rows.push(vec![m.display_name(), m.user_id.to_string(), m.country_iso]);
     }
 
-    print_section_title(&format!("Members ({})", rows.len()));
+    print_section_title(&members_title(fetched, total));
     print_table(&["Name", "User ID", "Country"], rows);
     Ok(())
 }
 
-pub fn cmd_members(chat_id: i64, rest: bool, full: bool, json: bool) -> Result<()> {
+fn members_title(fetched: usize, total: Option<i64>) -> String {
+    match total {
+        Some(total) if total as usize != fetched => format!("Members ({}/{})", fetched, total),
+        _ => format!("Members ({})", fetched),
+    }
+}
+
+pub fn cmd_members(
+    chat_id: i64,
+    rest: bool,
+    full: bool,
+    limit: Option<usize>,
+    json: bool,
+) -> Result<()> {
     if rest {
-        return cmd_members_rest(chat_id, json);
+        return cmd_members_rest(chat_id, limit, json);
     }
 
-    match cmd_loco_members(chat_id, full, json) {
+    match cmd_loco_members(chat_id, full, limit, json) {
         Ok(()) => Ok(()),
         Err(err) => {
             eprintln!(
                 "[members] LOCO member list failed: {err:#}. Falling back to REST member list."
             );
-            cmd_members_rest(chat_id, json)
+            cmd_members_rest(chat_id, limit, json)
+        }
+    }
+}
+
+/// Where `members --export-csv` / `--merge` puts the roster.
+pub enum RosterTarget {
+    /// Write a fresh roster to this path (`-` for stdout).
+    Fresh(String),
+    /// Update this existing roster in place.
+    Merge(String),
+}
+
+/// Current members of `chat_id` as roster rows: LOCO profiles (REST when
+/// `rest` is set or LOCO fails), friend status from the friend list and the
+/// latest join date seen in the local message cache.
+fn roster_rows(chat_id: i64, rest: bool) -> Result<Vec<RosterRow>> {
+    let client = get_rest_client()?;
+    let profiles = if rest {
+        None
+    } else {
+        match fetch_loco_member_profiles(chat_id) {
+            Ok(profiles) => Some(profiles),
+            Err(err) => {
+                eprintln!(
+                    "[members] LOCO member list failed: {err:#}. Falling back to REST member list."
+                );
+                None
+            }
+        }
+    };
+    let members: Vec<(ChatMember, i32, String)> = match profiles {
+        Some(profiles) => profiles
+            .into_iter()
+            .map(|p| (p.as_chat_member(), p.member_type, p.profile_image_url))
+            .collect(),
+        None => client
+            .get_chat_members(chat_id)?
+            .into_iter()
+            .map(|m| (m, 0, String::new()))
+            .collect(),
+    };
+
+    let friends: Option<HashMap<i64, String>> = match client.get_friends() {
+        Ok(friends) => Some(
+            friends
+                .into_iter()
+                .map(|f| (f.user_id, f.friend_nickname))
+                .collect(),
+        ),
+        Err(err) => {
+            warn(
+                WarningCode::PartialData,
+                format!("friend list unavailable, leaving the friend column blank: {err}"),
+            );
+            None
+        }
+    };
+    let joined = MessageDb::open()
+        .and_then(|db| db.get_messages(chat_id, 0))
+        .map(|messages| roster::join_times(&messages))
+        .unwrap_or_default();
+
+    Ok(members
+        .into_iter()
+        .map(|(mut member, member_type, profile_image_url)| {
+            let friend_nickname = friends.as_ref().and_then(|f| f.get(&member.user_id));
+            if member.friend_nickname.is_empty() {
+                if let Some(name) = friend_nickname {
+                    member.friend_nickname = name.clone();
+                }
+            }
+            RosterRow {
+                user_id: member.user_id,
+                display_name: member.display_name(),
+                role: roster::open_role(member_type),
+                country: member.country_iso.clone(),
+                joined_at: joined
+                    .get(&member.user_id)
+                    .and_then(|&at| chrono::Local.timestamp_opt(at, 0).single())
+                    .map(|dt| dt.format("%Y-%m-%d").to_string())
+                    .unwrap_or_default(),
+                friend: match &friends {
+                    Some(_) if friend_nickname.is_some() => "yes".into(),
+                    Some(_) => "no".into(),
+                    None => String::new(),
+                },
+                profile_image_url,
+            }
+        })
+        .collect())
+}
+
+/// `members --export-csv` / `--merge`: one CSV row per member for keeping a
+/// roster in a spreadsheet.
+pub fn cmd_members_roster(
+    chat_id: i64,
+    rest: bool,
+    target: RosterTarget,
+    json: bool,
+) -> Result<()> {
+    let rows = roster_rows(chat_id, rest)?;
+    match target {
+        RosterTarget::Fresh(path) => {
+            let content = roster::write_roster(&rows)?;
+            if path == "-" {
+                io::stdout().write_all(content.as_bytes())?;
+                return Ok(());
+            }
+            write_atomic(Path::new(&path), |w| Ok(w.write_all(content.as_bytes())?))?;
+            if json {
+                crate::util::output_json(&serde_json::json!({
+                    "path": path,
+                    "members": rows.len(),
+                }))?;
+            } else {
+                eprintln!("Wrote {} members to {}", rows.len(), path);
+            }
+        }
+        RosterTarget::Merge(path) => {
+            let existing = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read roster {path}"))?;
+            let (content, summary) = roster::merge_roster(&existing, &rows)
+                .with_context(|| format!("Failed to merge roster {path}"))?;
+            write_atomic(Path::new(&path), |w| Ok(w.write_all(content.as_bytes())?))?;
+            if json {
+                crate::util::output_json(&serde_json::json!({
+                    "path": path,
+                    "members": rows.len(),
+                    "summary": summary,
+                }))?;
+            } else {
+                eprintln!(
+                    "Merged {} members into {}: {} updated, {} added, {} departed, {} returned",
+                    rows.len(),
+                    path,
+                    summary.updated,
+                    summary.added,
+                    summary.departed,
+                    summary.returned
+                );
+            }
         }
     }
+    Ok(())
+}
+
+/// History pages `members --activity` fetches when the chat has nothing in
+/// the local archive.
+const ACTIVITY_MAX_PAGES: usize = 50;
+
+/// Below this many days of history, `members --activity` without
+/// `--inactive-days` warns that silence may predate it.
+const ACTIVITY_SHORT_HISTORY_DAYS: i64 = 30;
+
+fn format_day(at: i64) -> String {
+    chrono::Local
+        .timestamp_opt(at, 0)
+        .single()
+        .map(|dt| dt.format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+
+/// `members --activity`: each member's message count and last message from
+/// the archive (or, when it has nothing for the chat, the server's recent
+/// history), longest inactive first, optionally only those inactive for
+/// `inactive_days`.
+pub fn cmd_members_activity(
+    chat_id: i64,
+    rest: bool,
+    inactive_days: Option<i64>,
+    export_csv: Option<&str>,
+    json: bool,
+) -> Result<()> {
+    let members = roster_rows(chat_id, rest)?;
+    let archived = MessageDb::open()
+        .and_then(|db| db.get_messages(chat_id, 0))
+        .unwrap_or_default();
+    let (messages, source) = if archived.is_empty() {
+        let client = get_rest_client()?;
+        let fetched = client.get_all_messages_with_progress(
+            chat_id,
+            ACTIVITY_MAX_PAGES,
+            &StderrProgress::new("members"),
+        )?;
+        let names = members
+            .iter()
+            .map(|m| (m.user_id, m.display_name.clone()))
+            .collect();
+        (
+            crate::commands::read::to_cache_rows(chat_id, &fetched, &names),
+            "server",
+        )
+    } else {
+        (archived, "archive")
+    };
+    let now = chrono::Utc::now().timestamp();
+    let (activity, coverage) = roster::member_activity(&members, &messages, now, source);
+
+    let threshold = inactive_days.unwrap_or(ACTIVITY_SHORT_HISTORY_DAYS);
+    if coverage.messages == 0 {
+        warn(
+            WarningCode::PartialData,
+            format!(
+                "no message history for chat {chat_id}; nobody can be judged inactive. Sync it with `openkakao-rs cache {chat_id}` first"
+            ),
+        );
+    } else if coverage.days < threshold {
+        warn(
+            WarningCode::PartialData,
+            format!(
+                "the {source} history covers only {} day(s), since {}; members silent through it may have written before. Sync more with `openkakao-rs cache {chat_id}` before removing anyone",
+                coverage.days,
+                coverage.from.map(format_day).unwrap_or_default()
+            ),
+        );
+    }
+
+    let (shown, undetermined): (Vec<_>, usize) = match inactive_days {
+        Some(days) => (
+            activity
+                .iter()
+                .filter(|a| a.inactive_for(days))
+                .cloned()
+                .collect(),
+            activity
+                .iter()
+                .filter(|a| !a.inactive_for(days) && a.before_history)
+                .count(),
+        ),
+        None => (activity, 0),
+    };
+
+    if let Some(path) = export_csv {
+        let content = roster::write_activity(&shown, format_day)?;
+        if path == "-" {
+            io::stdout().write_all(content.as_bytes())?;
+            return Ok(());
+        }
+        write_atomic(Path::new(path), |w| Ok(w.write_all(content.as_bytes())?))?;
+        if !json {
+            eprintln!("Wrote {} members to {}", shown.len(), path);
+        }
+    }
+    if json {
+        return crate::util::output_json(&serde_json::json!({
+            "chat_id": chat_id,
+            "coverage": coverage,
+            "inactive_days": inactive_days,
+            "undetermined": undetermined,
+            "members": shown,
+        }));
+    }
+    if export_csv.is_some() {
+        return Ok(());
+    }
+
+    println!(
+        "History: {} message(s) from the {}, {} to {} ({} day(s))",
+        coverage.messages,
+        coverage.source,
+        coverage.from.map(format_day).unwrap_or_else(|| "-".into()),
+        coverage.to.map(format_day).unwrap_or_else(|| "-".into()),
+        coverage.days
+    );
+    let rows = shown
+        .iter()
+        .map(|a| {
+            vec![
+                truncate(&display_safe(&a.display_name), 24),
+                a.user_id.to_string(),
+                a.role.clone(),
+                a.message_count.to_string(),
+                a.last_message_at
+                    .map(format_day)
+                    .unwrap_or_else(|| "-".into()),
+                a.joined_at.map(format_day).unwrap_or_else(|| "-".into()),
+                match (a.inactive_days, a.before_history) {
+                    (Some(days), true) => format!(">={days}d"),
+                    (Some(days), false) => format!("{days}d"),
+                    (None, _) => "-".into(),
+                },
+            ]
+        })
+        .collect();
+    print_table(
+        &[
+            "Name",
+            "User ID",
+            "Role",
+            "Messages",
+            "Last message",
+            "Joined",
+            "Inactive",
+        ],
+        rows,
+    );
+    if undetermined > 0 {
+        println!(
+            "{undetermined} member(s) silent through all {} day(s) of history cannot be shown inactive for {} days.",
+            coverage.days,
+            inactive_days.unwrap_or_default()
+        );
+    }
+    Ok(())
 }
 
 pub fn cmd_loco_blocked(json: bool) -> Result<()> {
@@ -351,7 +684,7 @@ pub fn cmd_loco_blocked(json: bool) -> Result<()> {
         let snapshot = fetch_loco_blocked_snapshot(&mut client).await?;
 
         if json {
-            println!("{}", serde_json::to_string_pretty(&snapshot)?);
+            crate::util::output_json(&snapshot)?;
             return Ok(());
         }
 
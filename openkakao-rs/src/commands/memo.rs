@@ -0,0 +1,73 @@
+use anyhow::{anyhow, Result};
+
+use crate::commands::read::{cmd_read, ReadCommandOptions};
+use crate::local_db::LocalDbReader;
+use crate::state::{cached_memo_chat_id, record_memo_chat_id};
+use crate::util::{get_creds, get_rest_client};
+use crate::warnings::{warn, WarningCode};
+
+const MEMO_NOT_FOUND: &str = "No memo chat (나와의 채팅) found for this account.\n  \
+    KakaoTalk creates it the first time it is opened: in the Friends tab, tap your own \
+    profile and choose '나와의 채팅', send anything, then retry.\n  \
+    `openkakao-rs chatinfo 0` can also create one over LOCO.";
+
+/// The account's MemoChat id: remembered in the state file after the first
+/// lookup, otherwise found in the chat list or the local KakaoTalk database.
+pub fn memo_chat_id() -> Result<i64> {
+    let creds = get_creds()?;
+    if let Some(chat_id) = cached_memo_chat_id(creds.user_id)? {
+        return Ok(chat_id);
+    }
+    let chat_id = find_memo_chat_id()?.ok_or_else(|| anyhow!(MEMO_NOT_FOUND))?;
+    if let Err(err) = record_memo_chat_id(creds.user_id, chat_id) {
+        warn(
+            WarningCode::StateSaveFailed,
+            format!("memo chat id not remembered: {}", err),
+        );
+    }
+    Ok(chat_id)
+}
+
+fn find_memo_chat_id() -> Result<Option<i64>> {
+    match get_rest_client().and_then(|client| Ok(client.get_all_chats()?)) {
+        Ok(rooms) => {
+            if let Some(room) = rooms.iter().find(|room| room.kind == "MemoChat") {
+                return Ok(Some(room.chat_id));
+            }
+        }
+        Err(err) => eprintln!("[memo] Chat list unavailable ({}); checking local DB", err),
+    }
+    match LocalDbReader::open() {
+        Ok(reader) => reader.find_memo_chat_id(),
+        Err(_) => Ok(None),
+    }
+}
+
+pub fn cmd_chats_memo(json: bool) -> Result<()> {
+    let chat_id = memo_chat_id()?;
+    if json {
+        crate::util::output_json(&serde_json::json!({ "chat_id": chat_id, "kind": "MemoChat" }))?;
+    } else {
+        println!("Memo chat (나와의 채팅): {}", chat_id);
+    }
+    Ok(())
+}
+
+pub fn cmd_memo_read(options: ReadCommandOptions) -> Result<()> {
+    cmd_read(memo_chat_id()?, options)
+}
+
+pub fn cmd_memo_search(query: &str, json: bool, links: bool) -> Result<()> {
+    crate::commands::rest::cmd_search(
+        crate::commands::rest::SearchOptions {
+            query,
+            chat_id: Some(memo_chat_id()?),
+            regex: false,
+            case_sensitive: false,
+            normalize: false,
+            max_pages: None,
+            links,
+        },
+        json,
+    )
+}
@@ -1,12 +1,26 @@
 pub mod analytics;
 pub mod auth;
 pub mod chats;
+pub mod config;
+pub mod daemon;
 pub mod doctor;
 pub mod download;
+pub mod health;
+pub mod ignore;
+pub mod init;
+pub mod logs;
 pub mod members;
+pub mod memo;
+pub mod openchat;
+pub mod outbox;
 pub mod probe;
 pub mod profile;
 pub mod read;
 pub mod rest;
+pub mod schema;
 pub mod send;
+pub mod tail;
+pub mod takeout;
+#[cfg(feature = "tui")]
+pub mod tui;
 pub mod watch;
@@ -0,0 +1,131 @@
+use anyhow::Result;
+
+use crate::error::OpenKakaoError;
+use crate::model::{OpenLink, OpenLinkRef};
+use crate::prompt::confirm;
+use crate::util::{display_safe, get_rest_client};
+
+fn members_label(link: &OpenLink) -> String {
+    match (link.member_count, link.member_limit) {
+        (Some(count), Some(limit)) => format!("{count}/{limit}"),
+        (Some(count), None) => count.to_string(),
+        (None, Some(limit)) => format!("?/{limit}"),
+        (None, None) => "unknown".to_string(),
+    }
+}
+
+/// A refused join or leave with the server's own words when it gave any.
+fn refusal(action: &str, err: OpenKakaoError) -> anyhow::Error {
+    match err {
+        OpenKakaoError::RestApi { status, message } if !message.trim().is_empty() => {
+            anyhow::anyhow!(
+                "{action} refused by Kakao: {} (status {status})",
+                message.trim()
+            )
+        }
+        err => err.into(),
+    }
+}
+
+/// `openchat info <link>`: what a link leads to, without joining.
+pub fn cmd_openchat_info(link: &OpenLinkRef, json: bool) -> Result<()> {
+    let info = get_rest_client()?.get_open_link(link)?;
+    if json {
+        return crate::util::output_json(&info);
+    }
+    println!("{}", display_safe(&info.name));
+    if !info.description.is_empty() {
+        println!("  {}", display_safe(&info.description));
+    }
+    println!("  Link ID:   {}", info.link_id);
+    if !info.url.is_empty() {
+        println!("  URL:       {}", info.url);
+    }
+    println!("  Type:      {}", info.kind);
+    if !info.host_nickname.is_empty() {
+        println!("  Host:      {}", display_safe(&info.host_nickname));
+    }
+    let full = if info.is_full() { " (full)" } else { "" };
+    println!("  Members:   {}{full}", members_label(&info));
+    println!(
+        "  Passcode:  {}",
+        if info.passcode_required {
+            "required"
+        } else {
+            "no"
+        }
+    );
+    if let Some(chat_id) = info.chat_id {
+        println!("  Joined:    yes (chat {chat_id})");
+    }
+    Ok(())
+}
+
+/// `openchat join <link>`: join and print the new chat id on stdout, so it
+/// can go straight into `read`.
+pub fn cmd_openchat_join(
+    link: &OpenLinkRef,
+    passcode: Option<&str>,
+    nickname: Option<&str>,
+    yes: bool,
+    json: bool,
+) -> Result<()> {
+    let client = get_rest_client()?;
+    let info = client.get_open_link(link)?;
+    let name = display_safe(&info.name).into_owned();
+    let chat_id = match info.chat_id {
+        Some(chat_id) => {
+            eprintln!("Already in {name}.");
+            chat_id
+        }
+        None => {
+            if info.passcode_required && passcode.is_none() {
+                anyhow::bail!("{name} needs a passcode; pass it with --passcode.");
+            }
+            let prompt = format!(
+                "Join {name} ({} members) as {}? [y/N] ",
+                members_label(&info),
+                nickname.map_or("your default profile".to_string(), |n| format!("\"{n}\""))
+            );
+            if !yes && !confirm(&prompt)? {
+                println!("Cancelled.");
+                return Ok(());
+            }
+            let chat_id = client
+                .join_open_link(info.link_id, passcode, nickname)
+                .map_err(|err| refusal(&format!("Joining {name}"), err))?;
+            eprintln!("Joined {name}.");
+            chat_id
+        }
+    };
+    if json {
+        return crate::util::output_json(&serde_json::json!({
+            "status": "ok",
+            "action": "join",
+            "link_id": info.link_id,
+            "chat_id": chat_id,
+        }));
+    }
+    println!("{chat_id}");
+    Ok(())
+}
+
+pub fn cmd_openchat_leave(chat_id: i64, yes: bool, json: bool) -> Result<()> {
+    if !yes && !confirm(&format!("Leave open chat {chat_id}? [y/N] "))? {
+        println!("Cancelled.");
+        return Ok(());
+    }
+    get_rest_client()?
+        .leave_open_chat(chat_id)
+        .map_err(|err| refusal("Leaving the chat", err))?;
+    if json {
+        crate::util::output_json(&serde_json::json!({
+            "status": "ok",
+            "action": "leave",
+            "chat_id": chat_id,
+        }))?;
+    } else {
+        println!("Left chat {chat_id}.");
+    }
+    Ok(())
+}
@@ -0,0 +1,442 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::commands::send::{outgoing_parts, prepare_file, ship_file, write_text, PreparedFile};
+use crate::error::OpenKakaoError;
+use crate::loco::client::LocoClient;
+use crate::loco_helpers::loco_connect_with_auto_refresh;
+use crate::model::KakaoCredentials;
+use crate::outbox::{NewItem, Outbox, OutboxItem};
+use crate::prompt::confirm;
+use crate::state::{mark_unattended_send_attempt, record_guard, unattended_send_remaining_secs};
+use crate::util::{
+    extract_chat_type, format_relative_age, is_open_chat, require_permission, retry_policy,
+    truncate, type_label,
+};
+
+pub struct QueueOptions {
+    pub chat_id: i64,
+    /// Already carrying the outgoing prefix.
+    pub message: Option<String>,
+    pub file: Option<String>,
+    pub split: bool,
+    pub force: bool,
+    pub json: bool,
+}
+
+pub struct FlushOptions {
+    /// Also send items older than the TTL.
+    pub force: bool,
+    pub ttl_secs: u64,
+    pub skip_confirm: bool,
+    pub unattended: bool,
+    pub allow_non_interactive: bool,
+    pub min_interval_secs: u64,
+    pub json: bool,
+}
+
+fn now_secs() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+/// `send --queue` / `send-file --queue`: check what can be checked offline,
+/// then append to the outbox.
+pub fn cmd_outbox_queue(opts: QueueOptions) -> Result<()> {
+    let QueueOptions {
+        chat_id,
+        message,
+        file,
+        split,
+        force,
+        json,
+    } = opts;
+    if let Some(message) = &message {
+        outgoing_parts(message, split)?;
+    }
+    let file = match file {
+        Some(file) => {
+            let path = PathBuf::from(&file);
+            prepare_file(&path)?;
+            Some(std::fs::canonicalize(&path)?)
+        }
+        None => None,
+    };
+
+    let mut outbox = Outbox::open()?;
+    let item = outbox.push(
+        NewItem {
+            chat_id,
+            text: message,
+            file,
+            split,
+            force,
+        },
+        now_secs(),
+    )?;
+
+    if json {
+        crate::util::output_json(&serde_json::json!({
+            "status": "queued",
+            "id": item.id,
+            "chat_id": chat_id,
+            "pending": outbox.items().len(),
+        }))?;
+    } else {
+        println!(
+            "Queued #{} for chat {} ({} pending). Send with `openkakao-rs outbox flush`.",
+            item.id,
+            chat_id,
+            outbox.items().len()
+        );
+    }
+    Ok(())
+}
+
+pub fn cmd_outbox_list(ttl_secs: u64, json: bool) -> Result<()> {
+    let outbox = Outbox::open()?;
+    let now = now_secs();
+
+    if json {
+        let items: Vec<serde_json::Value> = outbox
+            .items()
+            .iter()
+            .map(|item| {
+                let mut value = serde_json::to_value(item).unwrap_or_default();
+                value["stale"] = item.is_stale(now, ttl_secs).into();
+                value
+            })
+            .collect();
+        return crate::util::output_json(&serde_json::json!({
+            "path": outbox.path(),
+            "ttl_secs": ttl_secs,
+            "items": items,
+        }));
+    }
+
+    if outbox.is_empty() {
+        println!("Outbox is empty.");
+        return Ok(());
+    }
+    for item in outbox.items() {
+        let state = if item.is_stale(now, ttl_secs) {
+            "stale"
+        } else if item.last_error.is_some() {
+            "failed"
+        } else {
+            "pending"
+        };
+        println!(
+            "#{:<4} chat {:<20} {:<8} queued {}  {}",
+            item.id,
+            item.chat_id,
+            state,
+            format_relative_age(item.queued_at, now),
+            describe(item)
+        );
+        if let Some(error) = &item.last_error {
+            println!("      attempt {} failed: {}", item.attempts, error);
+        }
+    }
+    Ok(())
+}
+
+fn describe(item: &OutboxItem) -> String {
+    let mut parts = Vec::new();
+    if let Some(text) = &item.text {
+        parts.push(format!("\"{}\"", truncate(text, 50)));
+    }
+    if let Some(file) = &item.file {
+        parts.push(format!("file {}", file.display()));
+    }
+    parts.join(" + ")
+}
+
+#[derive(Serialize)]
+struct FlushResult {
+    id: u64,
+    chat_id: i64,
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// What an item sends, read before connecting so a missing file is a plain
+/// failure rather than a retry.
+struct Payload {
+    parts: Vec<String>,
+    file: Option<PreparedFile>,
+}
+
+impl Payload {
+    fn load(item: &OutboxItem) -> Result<Self> {
+        let parts = match &item.text {
+            Some(text) => outgoing_parts(text, item.split)?,
+            None => Vec::new(),
+        };
+        let file = item.file.as_deref().map(prepare_file).transpose()?;
+        Ok(Self { parts, file })
+    }
+}
+
+struct Session {
+    client: LocoClient,
+    creds: KakaoCredentials,
+    connected: bool,
+    chat_types: HashMap<i64, String>,
+}
+
+impl Session {
+    /// One delivery attempt for `item`, resuming after `parts_sent` parts.
+    async fn deliver(
+        &mut self,
+        item: &OutboxItem,
+        payload: &Payload,
+        parts_sent: &mut usize,
+    ) -> Result<()> {
+        if !self.connected {
+            loco_connect_with_auto_refresh(&mut self.client).await?;
+            self.connected = true;
+        }
+        let chat_type = match self.chat_types.get(&item.chat_id) {
+            Some(chat_type) => chat_type.clone(),
+            None => {
+                let room_info = self
+                    .client
+                    .send_command("CHATONROOM", bson::doc! { "chatId": item.chat_id })
+                    .await?;
+                let chat_type = extract_chat_type(&room_info.body);
+                self.chat_types.insert(item.chat_id, chat_type.clone());
+                chat_type
+            }
+        };
+        if is_open_chat(&chat_type) && !item.force {
+            return Err(OpenKakaoError::SafetyBlock(format!(
+                "chat {} is {} (open chat); queue it again with --force to send",
+                item.chat_id,
+                type_label(&chat_type)
+            ))
+            .into());
+        }
+        let start = (*parts_sent).min(payload.parts.len());
+        for part in &payload.parts[start..] {
+            write_text(&mut self.client, item.chat_id, part, self.creds.user_id).await?;
+            *parts_sent += 1;
+        }
+        if let Some(file) = &payload.file {
+            ship_file(&mut self.client, &self.creds, item.chat_id, file).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Send queued items in order, each with the global retry policy. An item
+/// leaves the outbox only once the server accepted it; a failure holds the
+/// later items for the same chat so they never overtake it, and a network
+/// failure stops the flush.
+pub fn cmd_outbox_flush(opts: FlushOptions) -> Result<()> {
+    let FlushOptions {
+        force,
+        ttl_secs,
+        skip_confirm,
+        unattended,
+        allow_non_interactive: allow_non_interactive_send,
+        min_interval_secs: min_unattended_send_interval_secs,
+        json,
+    } = opts;
+    let mut outbox = Outbox::open()?;
+    let pending = outbox.items().to_vec();
+    let started = now_secs();
+    let stale = pending
+        .iter()
+        .filter(|item| item.is_stale(started, ttl_secs))
+        .count();
+    let deliverable = if force {
+        pending.len()
+    } else {
+        pending.len() - stale
+    };
+
+    if deliverable > 0 {
+        if skip_confirm {
+            require_permission(
+                unattended && allow_non_interactive_send,
+                "non-interactive outbox flush (-y/--yes)",
+                "Re-run with --unattended --allow-non-interactive-send, or set both in ~/.config/openkakao/config.toml.",
+            )?;
+            if let Some(remaining) =
+                unattended_send_remaining_secs(min_unattended_send_interval_secs)?
+            {
+                record_guard("unattended_send_rate_limited")?;
+                anyhow::bail!(
+                    "unattended send is rate-limited for {}s; wait or raise safety.min_unattended_send_interval_secs",
+                    remaining
+                );
+            }
+            mark_unattended_send_attempt()?;
+        } else {
+            if !confirm(&format!("Send {} queued message(s)?\n[y/N] ", deliverable))? {
+                println!("Cancelled.");
+                return Ok(());
+            }
+        }
+    }
+
+    let mut results = Vec::with_capacity(pending.len());
+    if deliverable > 0 {
+        let creds = crate::util::get_creds()?;
+        let mut session = Session {
+            client: LocoClient::new(creds.clone()),
+            creds,
+            connected: false,
+            chat_types: HashMap::new(),
+        };
+        eprintln!("Connecting via LOCO...");
+        let rt = tokio::runtime::Runtime::new()?;
+        rt.block_on(flush_items(
+            &mut session,
+            &mut outbox,
+            &pending,
+            force,
+            ttl_secs,
+            started,
+            &mut results,
+        ))?;
+    } else {
+        for item in &pending {
+            results.push(result_for(item, "stale"));
+        }
+    }
+    outbox.compact()?;
+
+    let failed = results.iter().filter(|r| r.status == "failed").count();
+    if json {
+        crate::util::output_json(&serde_json::json!({
+            "results": results,
+            "remaining": outbox.items().len(),
+        }))?;
+    } else {
+        if results.is_empty() {
+            println!("Outbox is empty.");
+        }
+        for result in &results {
+            let note = match result.status {
+                "stale" => format!(
+                    "older than {}s; use `outbox flush --force` to send it",
+                    ttl_secs
+                ),
+                "held" => "waiting on an earlier message to this chat".to_string(),
+                "not_attempted" => "flush stopped before this message".to_string(),
+                _ => result.error.clone().unwrap_or_default(),
+            };
+            if note.is_empty() {
+                println!("#{} chat {}: {}", result.id, result.chat_id, result.status);
+            } else {
+                println!(
+                    "#{} chat {}: {} ({})",
+                    result.id,
+                    result.chat_id,
+                    result.status.replace('_', " "),
+                    note
+                );
+            }
+        }
+    }
+    if failed > 0 {
+        anyhow::bail!(
+            "{} queued message(s) failed and stay in the outbox; see `openkakao-rs outbox list`",
+            failed
+        );
+    }
+    Ok(())
+}
+
+fn result_for(item: &OutboxItem, status: &'static str) -> FlushResult {
+    FlushResult {
+        id: item.id,
+        chat_id: item.chat_id,
+        status,
+        error: None,
+    }
+}
+
+async fn flush_items(
+    session: &mut Session,
+    outbox: &mut Outbox,
+    pending: &[OutboxItem],
+    force: bool,
+    ttl_secs: u64,
+    started: i64,
+    results: &mut Vec<FlushResult>,
+) -> Result<()> {
+    let policy = retry_policy();
+    let mut held = HashSet::new();
+    let mut stopped = false;
+
+    for item in pending {
+        if stopped {
+            results.push(result_for(item, "not_attempted"));
+            continue;
+        }
+        if held.contains(&item.chat_id) {
+            results.push(result_for(item, "held"));
+            continue;
+        }
+        if item.is_stale(started, ttl_secs) && !force {
+            held.insert(item.chat_id);
+            results.push(result_for(item, "stale"));
+            continue;
+        }
+
+        let mut parts_sent = item.parts_sent;
+        let outcome = match Payload::load(item) {
+            Ok(payload) => {
+                let mut attempt = 1;
+                loop {
+                    match session.deliver(item, &payload, &mut parts_sent).await {
+                        Ok(()) => break Ok(()),
+                        Err(err) if policy.should_retry(&err, attempt) => {
+                            let delay = policy.delay(attempt);
+                            eprintln!(
+                                "[outbox] #{} attempt {}/{} failed: {}. Retrying in {:?}...",
+                                item.id, attempt, policy.max_attempts, err, delay
+                            );
+                            tokio::time::sleep(delay).await;
+                            session.client.disconnect();
+                            session.connected = false;
+                            attempt += 1;
+                        }
+                        Err(err) => {
+                            // Out of retries on a connection problem: the
+                            // rest would only fail the same way.
+                            stopped = !session.connected || policy.is_retryable_error(&err);
+                            break Err(err);
+                        }
+                    }
+                }
+            }
+            Err(err) => Err(err),
+        };
+
+        match outcome {
+            Ok(()) => {
+                outbox.record_sent(item.id)?;
+                results.push(result_for(item, "sent"));
+            }
+            Err(err) => {
+                let error = format!("{err:#}");
+                outbox.record_failure(item.id, &error, parts_sent, now_secs())?;
+                held.insert(item.chat_id);
+                results.push(FlushResult {
+                    id: item.id,
+                    chat_id: item.chat_id,
+                    status: "failed",
+                    error: Some(error),
+                });
+            }
+        }
+    }
+    Ok(())
+}
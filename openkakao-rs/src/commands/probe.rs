@@ -115,7 +115,7 @@ pub fn cmd_loco_probe(
         });
 
         if json {
-            println!("{}", serde_json::to_string_pretty(&payload)?);
+            crate::util::output_json(&payload)?;
         } else {
             print_section_title(&format!("LOCO probe: {}", method));
             if let Some(response) = &result.response {
@@ -150,7 +150,7 @@ pub fn cmd_loco_chatinfo(chat_id: i64, json: bool) -> Result<()> {
             eprintln!("Finding MemoChat (나와의 채팅)...");
 
             // First scan LOGINLIST chatDatas for existing MemoChat
-            let login_data = client.full_connect_with_retry(3).await?;
+            let login_data = client.full_connect_with_retry().await?;
             if let Ok(chat_datas) = login_data.get_array("chatDatas") {
                 for cd in chat_datas {
                     if let Some(doc) = cd.as_document() {
@@ -205,7 +205,7 @@ pub fn cmd_loco_chatinfo(chat_id: i64, json: bool) -> Result<()> {
         if json {
             // Convert BSON body to JSON
             let json_val: serde_json::Value = bson::from_document(response.body.clone())?;
-            println!("{}", serde_json::to_string_pretty(&json_val)?);
+            crate::util::output_json(&json_val)?;
         } else {
             print_section_title(&format!("Chat info: {}", chat_id));
             for (k, v) in response.body.iter() {
@@ -11,6 +11,7 @@ use crate::commands::members::{fetch_loco_member_profiles_with_client, LocoBlock
 use crate::loco;
 use crate::loco_helpers::loco_connect_with_auto_refresh;
 use crate::util::get_creds;
+use crate::warnings::{warn, WarningCode};
 
 pub fn merge_unique_string(values: &mut Vec<String>, candidate: &str) {
     if candidate.is_empty() || values.iter().any(|value| value == candidate) {
@@ -119,7 +120,10 @@ pub async fn build_local_friend_graph_with_client(
                 }
             }
             Err(err) => {
-                eprintln!("[friends/local] GETMEM {} failed: {}", chat.chat_id, err);
+                warn(
+                    WarningCode::MemberFetchFailed,
+                    format!("GETMEM {} failed: {}", chat.chat_id, err),
+                );
                 failed_chat_ids.push(chat.chat_id);
             }
         }
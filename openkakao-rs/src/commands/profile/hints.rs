@@ -97,7 +97,7 @@ pub fn cmd_profile_hints(
     };
 
     if json {
-        println!("{}", serde_json::to_string_pretty(&snapshot)?);
+        crate::util::output_json(&snapshot)?;
         return Ok(());
     }
 
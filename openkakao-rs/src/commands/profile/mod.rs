@@ -11,15 +11,18 @@ pub use probe::*;
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
 
+use crate::commands::chats::find_one_friend;
 use crate::commands::members::fetch_loco_member_profiles;
 use crate::commands::probe::MethodProbeResult;
 use crate::commands::rest::filter_friend_search;
+use crate::error::OpenKakaoError;
 use crate::loco;
 use crate::loco_helpers::loco_connect_with_auto_refresh;
-use crate::model::json_string;
-use crate::util::{get_creds, get_rest_client, print_section_title, print_table, truncate};
+use crate::util::{
+    display_safe, get_creds, get_rest_client, name_resolver, print_section_title, print_table,
+    truncate, NameQuery,
+};
 
 // ---------------------------------------------------------------------------
 // Types
@@ -174,7 +177,7 @@ pub struct KakaoAppStateDiffEntry {
 pub fn cmd_friends_local(
     favorites: bool,
     hidden: bool,
-    search: Option<String>,
+    search: Option<NameQuery>,
     chat_id: Option<i64>,
     user_id: Option<i64>,
     json: bool,
@@ -207,12 +210,15 @@ pub fn cmd_friends_local(
     if hidden {
         snapshot.entries.retain(|entry| entry.hidden_like);
     }
-    filter_friend_search(&mut snapshot.entries, search, |entry| {
-        (entry.nickname.clone(), entry.status_message.clone())
-    });
+    filter_friend_search(
+        &mut snapshot.entries,
+        search,
+        |entry| entry.user_id,
+        |entry| vec![entry.nickname.clone(), entry.status_message.clone()],
+    );
 
     if json {
-        println!("{}", serde_json::to_string_pretty(&snapshot.entries)?);
+        crate::util::output_json(&snapshot.entries)?;
         return Ok(());
     }
 
@@ -258,23 +264,39 @@ pub fn cmd_friends_local(
 
 pub fn cmd_profile_rest(user_id: i64, json: bool) -> Result<()> {
     let client = get_rest_client()?;
-    let data = client.get_friend_profile(user_id)?;
+    let profile = client.get_member_profile(user_id)?;
 
     if json {
-        println!("{}", serde_json::to_string_pretty(&data)?);
+        crate::util::output_json(&profile)?;
         return Ok(());
     }
 
-    let profile = data.get("profile").cloned().unwrap_or(Value::Null);
     print_section_title("Friend Profile");
-    println!("  Nickname: {}", json_string(&profile, "nickname"));
-    let status = json_string(&profile, "statusMessage");
-    if !status.is_empty() {
-        println!("  Status:   {}", status);
+    println!("  Source:   REST");
+    println!("  Nickname: {}", display_safe(&profile.nickname));
+    if !profile.status_message.is_empty() {
+        println!("  Status:   {}", display_safe(&profile.status_message));
+    }
+    println!("  User ID:  {}", profile.user_id);
+    if !profile.account_type.is_empty() {
+        println!("  Type:     {}", profile.account_type);
     }
-    let image = json_string(&profile, "fullProfileImageUrl");
-    if !image.is_empty() {
-        println!("  Image:    {}", image);
+    if profile.channel {
+        println!("  Channel:  yes");
+    }
+    if profile.birthday {
+        println!("  Birthday: today");
+    }
+    for (label, url) in [
+        ("Image:   ", &profile.full_profile_image_url),
+        ("Thumb:   ", &profile.profile_image_url),
+        ("Original:", &profile.original_profile_image_url),
+        ("Backdrop:", &profile.background_image_url),
+        ("Bg orig: ", &profile.original_background_image_url),
+    ] {
+        if !url.is_empty() {
+            println!("  {label} {url}");
+        }
     }
 
     Ok(())
@@ -288,7 +310,7 @@ pub fn cmd_profile_loco(chat_id: i64, user_id: i64, json: bool) -> Result<()> {
         .ok_or_else(|| anyhow::anyhow!("user {} not found in chat {}", user_id, chat_id))?;
 
     if json {
-        println!("{}", serde_json::to_string_pretty(&profile)?);
+        crate::util::output_json(&profile)?;
         return Ok(());
     }
 
@@ -297,9 +319,9 @@ pub fn cmd_profile_loco(chat_id: i64, user_id: i64, json: bool) -> Result<()> {
     println!("  Chat ID:  {}", chat_id);
     println!("  User ID:  {}", profile.user_id);
     println!("  Account:  {}", profile.account_id);
-    println!("  Nickname: {}", profile.nickname);
+    println!("  Nickname: {}", display_safe(&profile.nickname));
     if !profile.status_message.is_empty() {
-        println!("  Status:   {}", profile.status_message);
+        println!("  Status:   {}", display_safe(&profile.status_message));
     }
     if !profile.country_iso.is_empty() {
         println!("  Country:  {}", profile.country_iso);
@@ -336,7 +358,7 @@ pub fn cmd_profile_local(user_id: i64, json: bool) -> Result<()> {
         .ok_or_else(|| anyhow::anyhow!("user {} not found in local LOCO friend graph", user_id))?;
 
     if json {
-        println!("{}", serde_json::to_string_pretty(&profile)?);
+        crate::util::output_json(&profile)?;
         return Ok(());
     }
 
@@ -344,9 +366,9 @@ pub fn cmd_profile_local(user_id: i64, json: bool) -> Result<()> {
     println!("  Source:   local LOCO friend graph");
     println!("  User ID:  {}", profile.user_id);
     println!("  Account:  {}", profile.account_id);
-    println!("  Nickname: {}", profile.nickname);
+    println!("  Nickname: {}", display_safe(&profile.nickname));
     if !profile.status_message.is_empty() {
-        println!("  Status:   {}", profile.status_message);
+        println!("  Status:   {}", display_safe(&profile.status_message));
     }
     if !profile.country_iso.is_empty() {
         println!("  Country:  {}", profile.country_iso);
@@ -374,7 +396,25 @@ pub fn cmd_profile_local(user_id: i64, json: bool) -> Result<()> {
     Ok(())
 }
 
-pub fn cmd_profile(user_id: i64, chat_id: Option<i64>, local: bool, json: bool) -> Result<()> {
+/// `profile <user_id>` or `profile --name <query>`, the name resolved
+/// against the friends list.
+pub fn cmd_profile(
+    user_id: Option<i64>,
+    name: Option<&str>,
+    chat_id: Option<i64>,
+    local: bool,
+    json: bool,
+) -> Result<()> {
+    let user_id = match (user_id, name) {
+        (Some(user_id), _) => user_id,
+        (None, Some(name)) => {
+            let client = get_rest_client()?;
+            let mut resolver = name_resolver(get_creds()?.user_id);
+            find_one_friend(&mut resolver, name, || Ok(client.get_friends()?))?.0
+        }
+        (None, None) => anyhow::bail!("Give a user id or --name"),
+    };
+
     if let Some(chat_id) = chat_id {
         match cmd_profile_loco(chat_id, user_id, json) {
             Ok(()) => return Ok(()),
@@ -399,6 +439,15 @@ pub fn cmd_profile(user_id: i64, chat_id: Option<i64>, local: bool, json: bool)
 
     match cmd_profile_rest(user_id, json) {
         Ok(()) => Ok(()),
+        // Not a failure to work around: there is no profile to show.
+        Err(err)
+            if matches!(
+                err.downcast_ref::<OpenKakaoError>(),
+                Some(OpenKakaoError::ProfileUnavailable { .. })
+            ) =>
+        {
+            Err(err)
+        }
         Err(rest_err) => {
             eprintln!(
                 "[profile] REST profile failed: {rest_err:#}. Trying local LOCO friend graph."
@@ -1,73 +1,591 @@
+use std::cell::Cell;
 use std::collections::HashMap;
 
 use anyhow::Result;
 use owo_colors::OwoColorize;
 
+use crate::attachments::album_label;
+use crate::cursor::MessageCursor;
 use crate::error::OpenKakaoError;
+use crate::ignore::{self, IgnoreList, Shown};
 use crate::loco;
 use crate::loco_helpers::loco_connect_with_auto_refresh;
+use crate::message_db::{CachedMessage, MessageDb};
+use crate::model::{
+    select_context_by, select_window, select_window_by, ChatMessage, MessageContent, MessageLink,
+    ReadWatermarks, DELETED_MESSAGE_TEXT,
+};
+use crate::progress::{ProgressEvent, ProgressSink, StderrProgress};
 use crate::rest::KakaoRestClient;
+use crate::spam::{self, Post, Signal, SpamDisplay, SpamRules};
+use crate::state::ReadPaging;
+use crate::strict::{check_chat_log, check_message};
+use crate::theme::theme;
+use crate::threads::{thread_order, ThreadEntry, ThreadKey};
 use crate::util::{
-    build_member_name_map_from_bson, color_enabled, extract_chat_type, format_time, get_bson_i32,
-    get_bson_i64, get_bson_str, get_creds, is_open_chat, member_name_map, parse_since_date,
-    type_label,
+    build_member_name_map_from_bson, color_enabled, display_safe, extract_chat_type, format_bytes,
+    format_time, get_bson_i32, get_bson_i64, get_bson_str, get_bson_timestamp, get_creds,
+    is_open_chat, name_resolver, print_section_title, truncate, type_label, DaySeparator,
+    TimeWindow,
 };
+use crate::warnings::{warn, WarningCode};
+use crate::wrap::{display_width, wrap_body};
 
 #[derive(Debug, Clone)]
 pub struct ReadCommandOptions {
     pub count: usize,
-    pub cursor: Option<i64>,
+    /// `--cursor`/`--before`: where REST pages back from; LOCO takes it
+    /// as the log id SYNCMSG starts at.
+    pub cursor: Option<MessageCursor>,
+    /// `--since`/`--until` as given; see [`TimeWindow`].
     pub since: Option<String>,
+    pub until: Option<String>,
     pub all: bool,
     pub delay_ms: u64,
     pub force: bool,
     pub rest: bool,
     pub json: bool,
+    /// Append an `openkakao://chat/<chat_id>/<log_id>` reference to each message.
+    pub links: bool,
+    /// Show how many members have not read each message yet.
+    pub receipts: bool,
+    /// Show the messages around this log id instead of the newest ones.
+    pub context: Option<i64>,
+    /// With `context`, read only the local message cache.
+    pub from_archive: bool,
+    /// Page back from where the previous `--continue` read stopped (REST).
+    pub continue_paging: bool,
+    /// With `continue_paging`, start over from the newest messages.
+    pub restart: bool,
+    /// Soft-wrap message bodies to this many terminal columns; `None`
+    /// prints them as they are.
+    pub wrap: Option<usize>,
+    /// Group replies under their sources (`--threads`).
+    pub threads: bool,
+    /// `--flag-spam` / `--hide-spam`.
+    pub spam: SpamDisplay,
+    /// `--collapse-ignored`: one line per run of messages from ignored
+    /// authors instead of none.
+    pub collapse_ignored: bool,
+    /// `--images`: what `read` shows of an album's photos.
+    pub images: ImageDisplay,
 }
 
-pub fn cmd_read_rest(
-    chat_id: i64,
-    count: usize,
-    cursor: Option<i64>,
-    since: Option<&str>,
-    all: bool,
-    json: bool,
-) -> Result<()> {
-    let since_ts = parse_since_date(since)?;
+/// `read --images`: how much of an album `read` shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ImageDisplay {
+    /// Only `(album: 4 photos)`.
+    #[default]
+    Count,
+    /// A line per photo under the message, with its size and URL.
+    Url,
+}
+
+/// REST pages `read --context` walks back through before giving up.
+const CONTEXT_MAX_PAGES: usize = 50;
+
+/// REST pages `read -n` follows to collect `count` messages, so a huge
+/// count cannot page forever.
+const READ_MAX_PAGES: usize = 50;
+
+/// Add a `link` field to a rendered message object when `--links` is on.
+pub fn with_link(mut msg: serde_json::Value, chat_id: i64, links: bool) -> serde_json::Value {
+    if links {
+        let log_id = msg.get("log_id").and_then(|v| v.as_i64()).unwrap_or(0);
+        if let Some(obj) = msg.as_object_mut() {
+            obj.insert(
+                "link".to_string(),
+                MessageLink::new(chat_id, log_id).to_string().into(),
+            );
+        }
+    }
+    msg
+}
+
+/// Add an `unread_by` field when read receipts are available.
+pub fn with_unread(
+    mut msg: serde_json::Value,
+    receipts: Option<&ReadWatermarks>,
+) -> serde_json::Value {
+    if let Some(receipts) = receipts {
+        let log_id = msg.get("log_id").and_then(|v| v.as_i64()).unwrap_or(0);
+        let author_id = msg.get("author_id").and_then(|v| v.as_i64()).unwrap_or(0);
+        if let Some(obj) = msg.as_object_mut() {
+            obj.insert(
+                "unread_by".to_string(),
+                receipts.unread_by(log_id, author_id).into(),
+            );
+        }
+    }
+    msg
+}
+
+/// Text-mode `· N` suffix for `--receipts`, omitted once everyone has read.
+fn unread_suffix(log_id: i64, author_id: i64, receipts: Option<&ReadWatermarks>) -> String {
+    match receipts.map(|r| r.unread_by(log_id, author_id)) {
+        Some(n) if n > 0 => format!(" · {}", n),
+        _ => String::new(),
+    }
+}
+
+/// Watermarks from a CHATONROOM response (`a` user ids, `w` watermarks).
+fn room_watermarks(viewer_id: i64, room_info: &bson::Document) -> Option<ReadWatermarks> {
+    let ids = |key: &str| -> Vec<i64> {
+        room_info
+            .get_array(key)
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|b| b.as_i64().or_else(|| b.as_i32().map(i64::from)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    ReadWatermarks::from_parallel(viewer_id, &ids("a"), &ids("w"))
+}
+
+fn note_receipts_unavailable() {
+    eprintln!(
+        "[read] Read receipts are not available for this chat; showing messages without them."
+    );
+}
+
+/// Text-mode suffix for `--links`.
+/// `--flag-spam`/`--hide-spam`: the signals of each of `items` that is
+/// flagged (empty for the rest), with flagged items dropped from `items`
+/// under [`SpamDisplay::Hide`]. How many each author had flagged goes to
+/// stderr; nothing leaves this machine.
+fn spam_pass<T>(
+    items: &mut Vec<T>,
+    display: SpamDisplay,
+    post: impl Fn(&T) -> Post<'_>,
+    name: impl Fn(i64) -> String,
+) -> Result<Vec<Vec<Signal>>> {
+    if display == SpamDisplay::Off {
+        return Ok(vec![Vec::new(); items.len()]);
+    }
+    let rules = SpamRules::from_config(&crate::config::load_config()?.spam);
+    let (mut signals, flagged, summary) = {
+        let posts: Vec<Post> = items.iter().map(&post).collect();
+        let signals = spam::score(&posts, &rules);
+        let flagged: Vec<bool> = signals
+            .iter()
+            .map(|signals| spam::is_flagged(signals, &rules))
+            .collect();
+        let summary = spam::flagged_by_author(&posts, &flagged);
+        (signals, flagged, summary)
+    };
+    for (signals, flagged) in signals.iter_mut().zip(&flagged) {
+        if !flagged {
+            signals.clear();
+        }
+    }
+    let total: usize = summary.iter().map(|(_, count)| count).sum();
+    if total == 0 {
+        eprintln!("[spam] Nothing flagged in {} messages.", items.len());
+    } else {
+        eprintln!(
+            "[spam] {} of {} messages {}: {}",
+            total,
+            items.len(),
+            if display == SpamDisplay::Hide {
+                "hidden"
+            } else {
+                "flagged"
+            },
+            summary
+                .iter()
+                .map(|(author_id, count)| format!("{} ×{}", display_safe(&name(*author_id)), count))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    if display == SpamDisplay::Hide {
+        let mut keep = flagged.iter().map(|flagged| !flagged);
+        items.retain(|_| keep.next().unwrap_or(true));
+        signals.retain(|signals| signals.is_empty());
+    }
+    Ok(signals)
+}
+
+/// Add `spam` and `spam_signals` fields when spam scoring is on.
+fn with_spam(
+    mut msg: serde_json::Value,
+    signals: &[Signal],
+    display: SpamDisplay,
+) -> serde_json::Value {
+    if let (false, Some(obj)) = (display == SpamDisplay::Off, msg.as_object_mut()) {
+        obj.insert("spam".to_string(), (!signals.is_empty()).into());
+        obj.insert(
+            "spam_signals".to_string(),
+            signals
+                .iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<_>>()
+                .into(),
+        );
+    }
+    msg
+}
+
+/// `body` with the `[spam?]` tag when `signals` flagged it.
+fn spam_tagged(body: String, signals: &[Signal]) -> String {
+    if signals.is_empty() {
+        body
+    } else {
+        format!("[spam?] {body}")
+    }
+}
+
+/// `body` soft-wrapped to `wrap` columns with continuation lines aligned
+/// under the end of `prefix`.
+fn wrapped(body: &str, prefix: &str, wrap: Option<usize>) -> String {
+    match wrap {
+        Some(width) => wrap_body(body, display_width(prefix), width),
+        None => body.to_string(),
+    }
+}
+
+pub fn link_suffix(chat_id: i64, log_id: i64, links: bool) -> String {
+    if !links {
+        return String::new();
+    }
+    let link = MessageLink::new(chat_id, log_id).to_string();
+    if color_enabled() {
+        format!("  {}", link.style(theme().muted))
+    } else {
+        format!("  {}", link)
+    }
+}
+
+/// The text `read` shows for content parsed from the attachment; `None`
+/// for plain text and for anything that fell back to `Other`, which keep
+/// each path's own placeholders.
+fn rich_body(content: &MessageContent) -> Option<String> {
+    match content {
+        MessageContent::Photo { url, width, height } if *width > 0 && *height > 0 => {
+            Some(format!("(photo {}x{}) {}", width, height, url))
+        }
+        MessageContent::Photo { url, .. } => Some(format!("(photo) {}", url)),
+        MessageContent::MultiPhoto { photos } => Some(album_label(photos.len())),
+        MessageContent::Video { url, duration_secs } if *duration_secs > 0 => Some(format!(
+            "(video {}:{:02}) {}",
+            duration_secs / 60,
+            duration_secs % 60,
+            url
+        )),
+        MessageContent::Video { url, .. } => Some(format!("(video) {}", url)),
+        MessageContent::File { name, size, .. } => {
+            Some(format!("(file) {} ({})", name, format_bytes(*size)))
+        }
+        MessageContent::Reply { text, .. } => Some(text.clone()),
+        MessageContent::Emoticon { .. } => content.emoticon_text(),
+        MessageContent::Deleted { .. } => Some(DELETED_MESSAGE_TEXT.to_string()),
+        _ => None,
+    }
+}
+
+/// The `↳ quoted text` line `read` prints above a reply.
+pub fn reply_quote(content: &MessageContent) -> Option<String> {
+    match content {
+        MessageContent::Reply { src_message, .. } if !src_message.is_empty() => {
+            let quoted = src_message.split_whitespace().collect::<Vec<_>>().join(" ");
+            Some(format!("↳ {}", truncate(&quoted, 60)))
+        }
+        _ => None,
+    }
+}
+
+/// What `--threads` adds to the quote line of a reply whose source is not
+/// shown.
+const ORPHAN_NOTE: &str = "(not in this window)";
+
+/// Messages in display order: threaded with `--threads`, else as given.
+fn display_order<T>(items: &[T], threads: bool, key: impl Fn(&T) -> ThreadKey) -> Vec<ThreadEntry> {
+    if threads {
+        thread_order(items, key)
+    } else {
+        (0..items.len()).map(ThreadEntry::flat).collect()
+    }
+}
+
+/// The quote line above a message at `entry`: nested replies sit under
+/// their source and need none, and orphans say the source is not shown.
+fn thread_quote(content: &MessageContent, entry: &ThreadEntry) -> Option<String> {
+    if entry.depth > 0 {
+        return None;
+    }
+    let quote = reply_quote(content);
+    if !entry.orphan {
+        return quote;
+    }
+    Some(match quote {
+        Some(quote) => format!("{quote}  {ORPHAN_NOTE}"),
+        None => format!("↳ {ORPHAN_NOTE}"),
+    })
+}
+
+/// Add a `thread` object (root log id, depth, orphan) with `--threads`.
+fn with_thread(
+    mut msg: serde_json::Value,
+    entry: &ThreadEntry,
+    root_log_id: i64,
+    threads: bool,
+) -> serde_json::Value {
+    if let (true, Some(obj)) = (threads, msg.as_object_mut()) {
+        obj.insert(
+            "thread".into(),
+            serde_json::json!({
+                "root": root_log_id,
+                "depth": entry.depth,
+                "orphan": entry.orphan,
+            }),
+        );
+    }
+    msg
+}
+
+/// Print `quote` above a message line whose body starts after `prefix`.
+fn print_quote(quote: Option<String>, prefix: &str) {
+    let Some(quote) = quote else {
+        return;
+    };
+    let line = format!(
+        "{}{}",
+        " ".repeat(display_width(prefix)),
+        display_safe(&quote)
+    );
+    if color_enabled() {
+        println!("{}", line.style(theme().muted));
+    } else {
+        println!("{}", line);
+    }
+}
+
+/// With `--images url`, an album's photos one per line below the message,
+/// aligned with its body.
+fn print_album_photos(content: &MessageContent, prefix: &str, images: ImageDisplay) {
+    let MessageContent::MultiPhoto { photos } = content else {
+        return;
+    };
+    if images != ImageDisplay::Url {
+        return;
+    }
+    let pad = " ".repeat(display_width(prefix));
+    for (index, photo) in photos.iter().enumerate() {
+        let size = if photo.width > 0 && photo.height > 0 {
+            format!(" {}x{}", photo.width, photo.height)
+        } else {
+            String::new()
+        };
+        let line = format!(
+            "{pad}{}/{}{} {}",
+            index + 1,
+            photos.len(),
+            size,
+            display_safe(&photo.url)
+        );
+        if color_enabled() {
+            println!("{}", line.style(theme().muted));
+        } else {
+            println!("{}", line);
+        }
+    }
+}
+
+/// The text `read` shows for a message, with placeholders for media.
+pub fn message_body(msg: &ChatMessage) -> String {
+    if let Some(body) = rich_body(&msg.content) {
+        return body;
+    }
+    match msg.message_type {
+        1 => msg.message.clone(),
+        2 => "(photo)".to_string(),
+        71 => "(emoticon)".to_string(),
+        _ => {
+            if msg.message.is_empty() {
+                format!("(type={})", msg.message_type)
+            } else {
+                msg.message.clone()
+            }
+        }
+    }
+}
+
+pub fn cmd_read_rest(chat_id: i64, opts: &ReadCommandOptions) -> Result<()> {
+    let count = opts.count;
+    let cursor = opts.cursor;
+    let all = opts.all;
+    let json = opts.json;
+    let links = opts.links;
+    let window = TimeWindow::parse(opts.since.as_deref(), opts.until.as_deref())?;
 
     let creds = get_creds()?;
     let client = KakaoRestClient::new(creds.clone())?;
 
-    let mut messages = if all {
-        client.get_all_messages(chat_id, 100)?
+    // Oldest → newest; `--cursor` pages backwards, so it bounds the window
+    // from above. `next_cursor` is where the following page starts.
+    let next_cursor = Cell::new(None);
+    let fetch_page = |cursor: Option<MessageCursor>| -> Result<Vec<ChatMessage>> {
+        if all {
+            let fetched = client.get_all_messages_with_progress(
+                chat_id,
+                100,
+                &StderrProgress::new("read"),
+            )?;
+            return Ok(select_window(fetched, usize::MAX, None));
+        }
+        if window.is_bounded() {
+            let (fetched, next) = fetch_window(&client, chat_id, cursor, window, count)?;
+            next_cursor.set(next);
+            return Ok(fetched);
+        }
+        let (fetched, next) = client.get_recent_messages(chat_id, cursor, count, READ_MAX_PAGES)?;
+        if fetched.len() < count && next.is_some() {
+            warn(
+                WarningCode::PartialData,
+                format!(
+                    "stopped after {READ_MAX_PAGES} pages with {} of {count} messages",
+                    fetched.len()
+                ),
+            );
+        }
+        next_cursor.set(next);
+        Ok(fetched)
+    };
+    let mut messages = if opts.continue_paging {
+        let paging = ReadPaging::new(chat_id)?;
+        if opts.restart {
+            paging.restart()?;
+        }
+        let page = paging.next_page(fetch_page, |m| m.log_id)?;
+        if page.reset {
+            eprintln!(
+                "[read] The saved --continue cursor was rejected; the chat history shifted. \
+                 Starting again from the newest messages."
+            );
+        } else if page.cursor.is_some() && page.messages.is_empty() {
+            eprintln!(
+                "[read] No older messages. Use --restart to page back from the newest again."
+            );
+        }
+        page.messages
     } else {
-        let (msgs, _next_cursor) = client.get_messages(chat_id, cursor)?;
-        msgs
+        fetch_page(cursor)?
     };
+    for msg in &messages {
+        check_message(chat_id, &serde_json::to_value(msg)?)?;
+    }
 
-    // Apply --since filter
-    if let Some(ts) = since_ts {
-        messages.retain(|m| m.send_at >= ts);
+    // The server drops older history; a `--before` page it no longer has
+    // may still be in the local cache from an earlier read.
+    let cache = crate::util::message_cache();
+    let mut from_cache = false;
+    if let (true, Some(before), Some(db)) = (messages.is_empty() && !all, cursor, &cache) {
+        let cached = db.get_messages_before(chat_id, before.get(), count)?;
+        if !cached.is_empty() {
+            eprintln!(
+                "[read] The server returned nothing before {}; showing {} messages from the local cache.",
+                before,
+                cached.len()
+            );
+            messages = cached.iter().map(CachedMessage::to_message).collect();
+            from_cache = true;
+        }
     }
 
-    let member_map = match client.get_chat_members(chat_id) {
-        Ok(members) => member_name_map(&members, creds.user_id),
+    messages.retain(|m| window.contains(m.send_at));
+
+    let member_map = match name_resolver(creds.user_id)
+        .member_names(chat_id, || Ok(client.get_chat_members(chat_id)?))
+    {
+        Ok(names) => {
+            if let Some(db) = &cache {
+                let _ = db.store_member_names(chat_id, names);
+            }
+            names.clone()
+        }
         Err(_) => {
-            let mut fallback = HashMap::new();
+            let mut fallback = cache
+                .as_ref()
+                .and_then(|db| db.member_names(chat_id).ok())
+                .unwrap_or_default();
             fallback.insert(creds.user_id, "Me".to_string());
             fallback
         }
     };
 
-    if !all {
-        if messages.len() > count {
-            messages.truncate(count);
+    let receipts = if opts.receipts {
+        let receipts = client
+            .get_chat_members(chat_id)
+            .ok()
+            .and_then(|members| ReadWatermarks::from_members(creds.user_id, &members));
+        if receipts.is_none() {
+            note_receipts_unavailable();
+        }
+        receipts
+    } else {
+        None
+    };
+
+    // Merge the page into the cache (off with --no-cache), so a later
+    // `--before` the server no longer serves can still be answered.
+    if let (Some(db), false) = (&cache, from_cache) {
+        if let Err(err) = db.upsert_messages(&to_cache_rows(chat_id, &messages, &member_map)) {
+            warn(
+                WarningCode::CacheUnavailable,
+                format!("Could not save messages to the cache: {err:#}"),
+            );
         }
-        messages.reverse();
     }
 
+    let spam_signals = spam_pass(
+        &mut messages,
+        opts.spam,
+        |m| Post::new(m.author_id, m.send_at, m.message_type, &m.message),
+        |author_id| {
+            member_map
+                .get(&author_id)
+                .cloned()
+                .unwrap_or_else(|| format!("User#{author_id}"))
+        },
+    )?;
+
+    let order = display_order(&messages, opts.threads, |m| ThreadKey::from(m));
+    let ignored = IgnoreList::load()?;
+    let collapse = opts.collapse_ignored && !json;
+    let shown = ignore::apply(&order, collapse, |entry| {
+        let m = &messages[entry.index];
+        ignored
+            .ignores(chat_id, m.author_id)
+            .then(|| (m.author_id, m.author_display_name(&member_map)))
+    });
+    if !collapse {
+        ignore::note_dropped(order.len() - shown.len());
+    }
     if json {
-        println!("{}", serde_json::to_string_pretty(&messages)?);
+        let output = shown
+            .iter()
+            .filter_map(|shown| match shown {
+                Shown::Message(entry) => Some(entry),
+                Shown::Hidden(_) => None,
+            })
+            .map(|entry| {
+                let m = &messages[entry.index];
+                let msg = with_spam(
+                    with_unread(serde_json::to_value(m)?, receipts.as_ref()),
+                    &spam_signals[entry.index],
+                    opts.spam,
+                );
+                let root = messages[entry.root].log_id;
+                Ok(with_thread(
+                    with_link(msg, chat_id, links),
+                    entry,
+                    root,
+                    opts.threads,
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        crate::util::output_json(&output)?;
         return Ok(());
     }
 
@@ -76,40 +594,74 @@ pub fn cmd_read_rest(
         return Ok(());
     }
 
-    for msg in &messages {
-        let name = member_map
-            .get(&msg.author_id)
-            .cloned()
-            .unwrap_or_else(|| msg.author_id.to_string());
-        let time_str = format_time(msg.send_at);
-
-        let body = match msg.message_type {
-            1 => msg.message.clone(),
-            2 => "(photo)".to_string(),
-            71 => "(emoticon)".to_string(),
-            _ => {
-                if msg.message.is_empty() {
-                    format!("(type={})", msg.message_type)
-                } else {
-                    msg.message.clone()
-                }
+    let mut days = DaySeparator::default();
+    for shown in shown {
+        let entry = match shown {
+            Shown::Message(entry) => entry,
+            Shown::Hidden(line) => {
+                print_hidden(&line);
+                continue;
             }
         };
+        let msg = &messages[entry.index];
+        if entry.depth == 0 {
+            print_day_separator(&mut days, msg.send_at);
+        }
+        let name = msg.author_display_name(&member_map);
+        let time_str = format_time(msg.send_at);
 
+        let body = spam_tagged(message_body(msg), &spam_signals[entry.index]);
+
+        let name = display_safe(&name);
+        let indent = entry.indent();
+        let prefix = format!("{}{} [{}]: ", indent, time_str, name);
+        print_quote(thread_quote(&msg.content, entry), &prefix);
+        let body = wrapped(&display_safe(&body), &prefix, opts.wrap);
+        let unread = unread_suffix(msg.log_id, msg.author_id, receipts.as_ref());
+        let link = link_suffix(chat_id, msg.log_id, links);
         if color_enabled() {
-            println!("{} [{}]: {}", time_str.dimmed(), name.bold(), body);
+            println!(
+                "{}{} [{}]: {}{}{}",
+                indent,
+                time_str.style(theme().timestamp),
+                name.style(theme().author),
+                body,
+                unread.style(theme().muted),
+                link
+            );
         } else {
-            println!("{} [{}]: {}", time_str, name, body);
+            println!(
+                "{}{} [{}]: {}{}{}",
+                indent, time_str, name, body, unread, link
+            );
         }
+        print_album_photos(&msg.content, &prefix, opts.images);
     }
 
-    if !all {
-        if let Some(oldest) = messages.first().map(|m| m.log_id) {
+    if opts.continue_paging {
+        println!(
+            "\nShowing {} messages. For older: openkakao-rs read {} --continue",
+            messages.len(),
+            chat_id
+        );
+    } else if !all {
+        // Cached messages are paged by log id; server pages by its cursor.
+        let next = if from_cache {
+            messages.first().map(|m| MessageCursor::new(m.log_id))
+        } else {
+            next_cursor.get()
+        };
+        if let Some(next) = next {
             println!(
                 "\nShowing {} messages. For older: openkakao-rs read {} --cursor {}",
                 messages.len(),
                 chat_id,
-                oldest
+                next
+            );
+        } else {
+            println!(
+                "\nShowing {} messages; nothing older on the server.",
+                messages.len()
             );
         }
     } else {
@@ -119,17 +671,100 @@ pub fn cmd_read_rest(
     Ok(())
 }
 
-pub fn cmd_read(chat_id: i64, options: ReadCommandOptions) -> Result<()> {
-    if options.rest {
-        return cmd_read_rest(
-            chat_id,
-            options.count,
-            options.cursor,
-            options.since.as_deref(),
-            options.all,
-            options.json,
+/// Page back from `cursor` until the pages cover `window`: past its start
+/// with `--since`, else until `count` messages fall before its end. Returns
+/// the messages in the window, oldest first, and the cursor of the page
+/// after the last one read (`None` when the server has nothing older).
+fn fetch_window(
+    client: &KakaoRestClient,
+    chat_id: i64,
+    cursor: Option<MessageCursor>,
+    window: TimeWindow,
+    count: usize,
+) -> Result<(Vec<ChatMessage>, Option<MessageCursor>)> {
+    let mut all: Vec<ChatMessage> = Vec::new();
+    let mut page_cursor = cursor;
+    let mut next = None;
+    let mut covered = false;
+    for _ in 0..READ_MAX_PAGES {
+        let (page, next_cursor) = client.get_messages(chat_id, page_cursor)?;
+        let before = all.len();
+        all = select_window(
+            all.into_iter().chain(page).collect(),
+            usize::MAX,
+            cursor.map(MessageCursor::get),
+        );
+        next = next_cursor;
+        covered = match window.since {
+            Some(since) => all.first().is_some_and(|oldest| oldest.send_at < since),
+            None => all.iter().filter(|m| window.contains(m.send_at)).count() >= count,
+        };
+        if covered || all.len() == before || next.is_none() || page_cursor == next {
+            break;
+        }
+        page_cursor = next;
+    }
+    if !covered && next.is_some() {
+        warn(
+            WarningCode::PartialData,
+            format!("stopped after {READ_MAX_PAGES} pages before reaching the start of the window"),
         );
     }
+    all.retain(|m| window.contains(m.send_at));
+    if window.since.is_none() && all.len() > count {
+        all = select_window(all, count, None);
+        next = all
+            .first()
+            .map_or(next, |m| Some(MessageCursor::new(m.log_id)));
+    }
+    Ok((all, next))
+}
+
+/// A `--collapse-ignored` line, dimmed like other notes.
+pub fn print_hidden(line: &str) {
+    let line = display_safe(line);
+    if color_enabled() {
+        println!("{}", line.style(theme().muted));
+    } else {
+        println!("{line}");
+    }
+}
+
+fn print_day_separator(days: &mut DaySeparator, send_at: i64) {
+    if let Some(line) = days.next(send_at) {
+        if color_enabled() {
+            println!("{}", line.style(theme().separator));
+        } else {
+            println!("{line}");
+        }
+    }
+}
+
+/// Cache rows for `messages`, named from `member_map` or the payload.
+pub fn to_cache_rows(
+    chat_id: i64,
+    messages: &[ChatMessage],
+    member_map: &HashMap<i64, String>,
+) -> Vec<CachedMessage> {
+    messages
+        .iter()
+        .filter(|m| m.log_id != 0)
+        .map(|m| {
+            // The archive keeps an empty name for an unknown author.
+            let author_name = m.author_name(member_map).unwrap_or_default();
+            CachedMessage::from_message(chat_id, m, author_name)
+        })
+        .collect()
+}
+
+pub fn cmd_read(chat_id: i64, options: ReadCommandOptions) -> Result<()> {
+    if let Some(target) = options.context {
+        return cmd_read_context(chat_id, target, &options);
+    }
+    // Backward cursors are a REST notion; LOCO SYNCMSG pages forward.
+    if options.rest || options.continue_paging {
+        return cmd_read_rest(chat_id, &options);
+    }
 
     match cmd_loco_read(chat_id, &options) {
         Ok(()) => Ok(()),
@@ -148,27 +783,171 @@ pub fn cmd_read(chat_id: i64, options: ReadCommandOptions) -> Result<()> {
                     "[read] Note: --delay-ms only applies to LOCO and is ignored for REST fallback."
                 );
             }
-            cmd_read_rest(
-                chat_id,
-                options.count,
-                options.cursor,
-                options.since.as_deref(),
-                options.all,
-                options.json,
-            )
+            cmd_read_rest(chat_id, &options)
         }
     }
 }
 
+/// `read --context`: `count / 2` messages either side of `target`, from the
+/// local cache with `--from-archive`, otherwise by paging REST history back
+/// until the target and the messages before it are loaded.
+fn cmd_read_context(chat_id: i64, target: i64, opts: &ReadCommandOptions) -> Result<()> {
+    let messages = if opts.from_archive {
+        MessageDb::open()?.get_context(chat_id, target, opts.count)?
+    } else {
+        match fetch_context_pages(chat_id, target, opts.count / 2) {
+            Ok(messages) if !messages.is_empty() => messages,
+            Ok(_) => {
+                eprintln!("[read] No REST history for this chat; using the local cache");
+                MessageDb::open()?.get_context(chat_id, target, opts.count)?
+            }
+            Err(err) => {
+                eprintln!(
+                    "[read] REST history failed ({}); using the local cache",
+                    err
+                );
+                MessageDb::open()?.get_context(chat_id, target, opts.count)?
+            }
+        }
+    };
+
+    let window = select_context_by(messages, target, opts.count, |m| m.log_id);
+    if window.items.is_empty() {
+        anyhow::bail!(
+            "No messages available around {} in chat {}. Run 'openkakao-rs cache {}' first.",
+            target,
+            chat_id,
+            chat_id
+        );
+    }
+    if !window.found {
+        let first = window.items.first().map_or(0, |m| m.log_id);
+        let last = window.items.last().map_or(0, |m| m.log_id);
+        warn(
+            WarningCode::PartialData,
+            format!(
+                "Message {} is not in the available history; showing the nearest {} messages ({}..{}).",
+                target,
+                window.items.len(),
+                first,
+                last
+            ),
+        );
+    }
+    print_context(MessageLink::new(chat_id, target), &window.items, opts.json)
+}
+
+/// Page REST history newest → oldest until `before` messages older than
+/// `target` are loaded or the history ends. Pages are cached with names.
+fn fetch_context_pages(chat_id: i64, target: i64, before: usize) -> Result<Vec<CachedMessage>> {
+    let creds = get_creds()?;
+    let client = KakaoRestClient::new(creds.clone())?;
+    let mut fetched: Vec<ChatMessage> = Vec::new();
+    let mut cursor = None;
+    for page in 1..=CONTEXT_MAX_PAGES {
+        let (messages, next_cursor) = client.get_messages(chat_id, cursor)?;
+        if messages.is_empty() {
+            break;
+        }
+        fetched.extend(messages);
+        let older = fetched
+            .iter()
+            .filter(|m| m.log_id != 0 && m.log_id < target)
+            .count();
+        if older > before || next_cursor.is_none() {
+            break;
+        }
+        eprintln!(
+            "[read] Page {}: {} messages loaded, looking for {}...",
+            page,
+            fetched.len(),
+            target
+        );
+        cursor = next_cursor;
+    }
+
+    let names = name_resolver(creds.user_id)
+        .member_names(chat_id, || Ok(client.get_chat_members(chat_id)?))
+        .cloned()
+        .unwrap_or_default();
+    let messages: Vec<CachedMessage> = select_window(fetched, usize::MAX, None)
+        .into_iter()
+        .filter(|m| m.log_id != 0)
+        .map(|m| CachedMessage {
+            chat_id,
+            log_id: m.log_id,
+            author_id: m.author_id,
+            author_name: m.author_display_name(&names),
+            message_type: m.message_type as i32,
+            message: m.message,
+            attachment: m.attachment,
+            send_at: m.send_at,
+        })
+        .collect();
+    if let Some(db) = crate::util::message_cache() {
+        let _ = db.upsert_messages(&messages);
+    }
+    Ok(messages)
+}
+
+/// Print cached messages around `link.log_id`, marking the target with `>`.
+pub fn print_context(link: MessageLink, context: &[CachedMessage], json: bool) -> Result<()> {
+    if json {
+        let output: Vec<serde_json::Value> = context
+            .iter()
+            .map(|m| {
+                serde_json::json!({
+                    "chat_id": m.chat_id,
+                    "log_id": m.log_id,
+                    "author_id": m.author_id,
+                    "author_name": m.author_name,
+                    "message_type": m.message_type,
+                    "message": m.message,
+                    "send_at": m.send_at,
+                    "time": format_time(m.send_at),
+                    "link": MessageLink::new(m.chat_id, m.log_id).to_string(),
+                    "target": m.log_id == link.log_id,
+                })
+            })
+            .collect();
+        crate::util::output_json(&output)?;
+        return Ok(());
+    }
+
+    print_section_title(&format!("{}", link));
+    for m in context {
+        let author = if m.author_name.is_empty() {
+            format!("User#{}", m.author_id)
+        } else {
+            m.author_name.clone()
+        };
+        let line = format!(
+            "{} {}: {}",
+            format_time(m.send_at),
+            display_safe(&author),
+            display_safe(&m.message)
+        );
+        if m.log_id != link.log_id {
+            println!("  {}", line);
+        } else if color_enabled() {
+            println!("> {}", line.style(theme().author));
+        } else {
+            println!("> {}", line);
+        }
+    }
+
+    Ok(())
+}
+
 fn extract_loginlist_messages(
     login_data: &bson::Document,
     chat_id: i64,
-    since_ts: Option<i64>,
+    window: TimeWindow,
     member_names: &mut HashMap<i64, String>,
-) -> Vec<serde_json::Value> {
+) -> Result<Vec<serde_json::Value>> {
     let mut messages = Vec::new();
     let Ok(chat_datas) = login_data.get_array("chatDatas") else {
-        return messages;
+        return Ok(messages);
     };
     for cd in chat_datas {
         let Some(doc) = cd.as_document() else {
@@ -205,16 +984,15 @@ fn extract_loginlist_messages(
             if log_id == 0 {
                 continue;
             }
+            check_chat_log(chat_id, log_doc)?;
             let author_id = get_bson_i64(log_doc, &["authorId"]);
             let msg_type = get_bson_i32(log_doc, &["type"]);
             let message = get_bson_str(log_doc, &["message"]);
-            let send_at = get_bson_i64(log_doc, &["sendAt"]);
+            let send_at = get_bson_timestamp(log_doc, &["sendAt"]);
             let author_nick = get_bson_str(log_doc, &["authorNickname"]);
             let attachment = get_bson_str(log_doc, &["attachment"]);
-            if let Some(ts) = since_ts {
-                if send_at < ts {
-                    continue;
-                }
+            if !window.contains(send_at) {
+                continue;
             }
             messages.push(serde_json::json!({
                 "log_id": log_id,
@@ -234,17 +1012,19 @@ fn extract_loginlist_messages(
         }
         break;
     }
-    messages
+    Ok(messages)
 }
 
 struct SyncmsgParams<'a> {
     chat_id: i64,
     max_log: i64,
     cursor: Option<i64>,
-    since_ts: Option<i64>,
+    window: TimeWindow,
     effective_delay: u64,
     has_existing_messages: bool,
     existing_ids: &'a std::collections::HashSet<i64>,
+    links: bool,
+    receipts: Option<&'a ReadWatermarks>,
 }
 
 async fn fetch_syncmsg_pages(
@@ -255,19 +1035,21 @@ async fn fetch_syncmsg_pages(
         chat_id,
         max_log,
         cursor,
-        since_ts,
+        window,
         effective_delay,
         has_existing_messages,
         existing_ids,
+        ..
     } = params;
     let chat_id = *chat_id;
     let max_log = *max_log;
-    let since_ts = *since_ts;
+    let window = *window;
     let effective_delay = *effective_delay;
     let has_existing_messages = *has_existing_messages;
     let mut messages = Vec::new();
     let mut cur = cursor.unwrap_or(0);
-    let mut batch_num = 0u32;
+    let mut batch_num = 0usize;
+    let progress = StderrProgress::new("loco-read");
     loop {
         let response = match client
             .send_command(
@@ -330,18 +1112,17 @@ async fn fetch_syncmsg_pages(
                 if existing_ids.contains(&log_id) {
                     continue;
                 }
+                check_chat_log(chat_id, doc)?;
 
                 let author_id = get_bson_i64(doc, &["authorId"]);
                 let msg_type = get_bson_i32(doc, &["type"]);
                 let message = get_bson_str(doc, &["message"]);
-                let send_at = get_bson_i64(doc, &["sendAt"]);
+                let send_at = get_bson_timestamp(doc, &["sendAt"]);
                 let author_nick = get_bson_str(doc, &["authorNickname"]);
                 let attachment = get_bson_str(doc, &["attachment"]);
 
-                if let Some(ts) = since_ts {
-                    if send_at < ts {
-                        continue;
-                    }
+                if !window.contains(send_at) {
+                    continue;
                 }
 
                 messages.push(serde_json::json!({
@@ -357,13 +1138,11 @@ async fn fetch_syncmsg_pages(
         }
 
         batch_num += 1;
-        eprintln!(
-            "[loco-read] Batch {}: {} msgs (total: {}, cursor: {})",
-            batch_num,
-            batch_count,
-            messages.len(),
-            max_log_in_batch
-        );
+        progress.emit(&ProgressEvent::PageFetched {
+            page: batch_num,
+            items: batch_count,
+            total: messages.len(),
+        });
 
         if is_ok || max_log_in_batch == 0 {
             break;
@@ -371,9 +1150,14 @@ async fn fetch_syncmsg_pages(
         cur = max_log_in_batch;
 
         if effective_delay > 0 && !is_ok {
-            tokio::time::sleep(std::time::Duration::from_millis(effective_delay)).await;
+            let wait = std::time::Duration::from_millis(effective_delay);
+            progress.emit(&ProgressEvent::Throttled { wait });
+            tokio::time::sleep(wait).await;
         }
     }
+    progress.emit(&ProgressEvent::Done {
+        total: messages.len(),
+    });
     Ok(messages)
 }
 
@@ -387,19 +1171,21 @@ async fn stream_syncmsg_pages(
         chat_id,
         max_log,
         cursor,
-        since_ts,
+        window,
         effective_delay,
         has_existing_messages,
         existing_ids,
+        ..
     } = params;
     let chat_id = *chat_id;
     let max_log = *max_log;
-    let since_ts = *since_ts;
+    let window = *window;
     let effective_delay = *effective_delay;
     let has_existing_messages = *has_existing_messages;
     let mut total_streamed = 0usize;
     let mut cur = cursor.unwrap_or(0);
-    let mut batch_num = 0u32;
+    let mut batch_num = 0usize;
+    let progress = StderrProgress::new("loco-read");
     loop {
         let response = match client
             .send_command(
@@ -462,18 +1248,17 @@ async fn stream_syncmsg_pages(
                 if existing_ids.contains(&log_id) {
                     continue;
                 }
+                check_chat_log(chat_id, doc)?;
 
                 let author_id = get_bson_i64(doc, &["authorId"]);
                 let msg_type = get_bson_i32(doc, &["type"]);
                 let message = get_bson_str(doc, &["message"]);
-                let send_at = get_bson_i64(doc, &["sendAt"]);
+                let send_at = get_bson_timestamp(doc, &["sendAt"]);
                 let author_nick = get_bson_str(doc, &["authorNickname"]);
                 let attachment = get_bson_str(doc, &["attachment"]);
 
-                if let Some(ts) = since_ts {
-                    if send_at < ts {
-                        continue;
-                    }
+                if !window.contains(send_at) {
+                    continue;
                 }
 
                 let msg = serde_json::json!({
@@ -485,16 +1270,18 @@ async fn stream_syncmsg_pages(
                     "attachment": attachment,
                     "send_at": send_at,
                 });
+                let msg = with_link(with_unread(msg, params.receipts), chat_id, params.links);
                 println!("{}", serde_json::to_string(&msg).unwrap_or_default());
                 total_streamed += 1;
             }
         }
 
         batch_num += 1;
-        eprintln!(
-            "[loco-read] Batch {}: {} msgs (streamed: {}, cursor: {})",
-            batch_num, batch_count, total_streamed, max_log_in_batch
-        );
+        progress.emit(&ProgressEvent::PageFetched {
+            page: batch_num,
+            items: batch_count,
+            total: total_streamed,
+        });
 
         if is_ok || max_log_in_batch == 0 {
             break;
@@ -502,27 +1289,138 @@ async fn stream_syncmsg_pages(
         cur = max_log_in_batch;
 
         if effective_delay > 0 && !is_ok {
-            tokio::time::sleep(std::time::Duration::from_millis(effective_delay)).await;
+            let wait = std::time::Duration::from_millis(effective_delay);
+            progress.emit(&ProgressEvent::Throttled { wait });
+            tokio::time::sleep(wait).await;
         }
     }
+    progress.emit(&ProgressEvent::Done {
+        total: total_streamed,
+    });
     Ok(total_streamed)
 }
 
+/// A LOCO message value with the `content` the REST path's
+/// [`ChatMessage`] serializes.
+fn with_content(msg: &serde_json::Value) -> serde_json::Value {
+    let text = |key: &str| msg.get(key).and_then(|v| v.as_str()).unwrap_or("");
+    let message_type = msg
+        .get("message_type")
+        .and_then(|v| v.as_i64())
+        .unwrap_or(0);
+    let content = MessageContent::parse(message_type, text("message"), text("attachment"));
+    let mut msg = msg.clone();
+    if let Some(obj) = msg.as_object_mut() {
+        obj.insert(
+            "content".into(),
+            serde_json::to_value(content).unwrap_or_default(),
+        );
+    }
+    msg
+}
+
 fn format_and_output_messages(
+    chat_id: i64,
     messages: &[serde_json::Value],
     member_names: &HashMap<i64, String>,
-    json: bool,
-) {
-    if json {
-        println!(
-            "{}",
-            serde_json::to_string_pretty(messages).unwrap_or_default()
-        );
-        return;
+    receipts: Option<&ReadWatermarks>,
+    opts: &ReadCommandOptions,
+) -> Result<()> {
+    let links = opts.links;
+    let int =
+        |msg: &serde_json::Value, key: &str| msg.get(key).and_then(|v| v.as_i64()).unwrap_or(0);
+    let mut messages = messages.to_vec();
+    let spam_signals = spam_pass(
+        &mut messages,
+        opts.spam,
+        |msg| {
+            Post::new(
+                int(msg, "author_id"),
+                int(msg, "send_at"),
+                int(msg, "message_type"),
+                msg.get("message").and_then(|v| v.as_str()).unwrap_or(""),
+            )
+        },
+        |author_id| {
+            member_names
+                .get(&author_id)
+                .cloned()
+                .unwrap_or_else(|| author_id.to_string())
+        },
+    )?;
+    let messages = messages.as_slice();
+    let order = display_order(messages, opts.threads, |msg| {
+        let text = |key: &str| msg.get(key).and_then(|v| v.as_str()).unwrap_or("");
+        ThreadKey {
+            log_id: int(msg, "log_id"),
+            send_at: int(msg, "send_at"),
+            reply_to: MessageContent::parse(
+                int(msg, "message_type"),
+                text("message"),
+                text("attachment"),
+            )
+            .reply_to(),
+        }
+    });
+    let ignored = IgnoreList::load()?;
+    let collapse = opts.collapse_ignored && !opts.json;
+    let shown = ignore::apply(&order, collapse, |entry| {
+        let msg = &messages[entry.index];
+        let author_id = int(msg, "author_id");
+        ignored.ignores(chat_id, author_id).then(|| {
+            let nick = msg
+                .get("author_nickname")
+                .and_then(|v| v.as_str())
+                .filter(|nick| !nick.is_empty());
+            let name = nick
+                .map(str::to_string)
+                .or_else(|| member_names.get(&author_id).cloned())
+                .unwrap_or_else(|| author_id.to_string());
+            (author_id, name)
+        })
+    });
+    if !collapse {
+        ignore::note_dropped(order.len() - shown.len());
+    }
+    if opts.json {
+        let output: Vec<serde_json::Value> = shown
+            .iter()
+            .filter_map(|shown| match shown {
+                Shown::Message(entry) => Some(entry),
+                Shown::Hidden(_) => None,
+            })
+            .map(|entry| {
+                let m = &messages[entry.index];
+                let msg = with_link(
+                    with_spam(
+                        with_unread(with_content(m), receipts),
+                        &spam_signals[entry.index],
+                        opts.spam,
+                    ),
+                    chat_id,
+                    links,
+                );
+                let root = int(&messages[entry.root], "log_id");
+                with_thread(msg, entry, root, opts.threads)
+            })
+            .collect();
+        return crate::util::output_json(&output);
     }
 
-    for msg in messages {
+    let mut days = DaySeparator::default();
+    for shown in shown {
+        let entry = match shown {
+            Shown::Message(entry) => entry,
+            Shown::Hidden(line) => {
+                print_hidden(&line);
+                continue;
+            }
+        };
+        let msg = &messages[entry.index];
         let send_at = msg.get("send_at").and_then(|v| v.as_i64()).unwrap_or(0);
+        if entry.depth == 0 {
+            print_day_separator(&mut days, send_at);
+        }
         let time_str = format_time(send_at);
         let nick = msg
             .get("author_nickname")
@@ -543,7 +1441,10 @@ fn format_and_output_messages(
             format!("{}", author_id)
         };
 
-        let content = match msg_type {
+        let attachment = msg.get("attachment").and_then(|v| v.as_str()).unwrap_or("");
+        let parsed = MessageContent::parse(msg_type, message, attachment);
+
+        let content = rich_body(&parsed).unwrap_or_else(|| match msg_type {
             1 => message.to_string(),
             2 => "[사진]".to_string(),
             3 => "[동영상]".to_string(),
@@ -560,13 +1461,34 @@ fn format_and_output_messages(
                     message.to_string()
                 }
             }
-        };
-
+        });
+
+        let content = spam_tagged(content, &spam_signals[entry.index]);
+        let display_nick = display_safe(&display_nick);
+        let indent = entry.indent();
+        let prefix = format!("{}{} {}: ", indent, time_str, display_nick);
+        print_quote(thread_quote(&parsed, entry), &prefix);
+        let content = wrapped(&display_safe(&content), &prefix, opts.wrap);
+        let log_id = msg.get("log_id").and_then(|v| v.as_i64()).unwrap_or(0);
+        let unread = unread_suffix(log_id, author_id, receipts);
+        let link = link_suffix(chat_id, log_id, links);
         if color_enabled() {
-            println!("{} {}: {}", time_str.dimmed(), display_nick.bold(), content);
+            println!(
+                "{}{} {}: {}{}{}",
+                indent,
+                time_str.style(theme().timestamp),
+                display_nick.style(theme().author),
+                content,
+                unread.style(theme().muted),
+                link
+            );
         } else {
-            println!("{} {}: {}", time_str, display_nick, content);
+            println!(
+                "{}{} {}: {}{}{}",
+                indent, time_str, display_nick, content, unread, link
+            );
         }
+        print_album_photos(&parsed, &prefix, opts.images);
     }
 
     let last_cursor = messages
@@ -574,17 +1496,19 @@ fn format_and_output_messages(
         .and_then(|m| m.get("log_id").and_then(|v| v.as_i64()))
         .unwrap_or(0);
     eprintln!("({} messages, last_cursor={})", messages.len(), last_cursor);
+    Ok(())
 }
 
 pub fn cmd_loco_read(chat_id: i64, opts: &ReadCommandOptions) -> Result<()> {
-    let since_ts = parse_since_date(opts.since.as_deref())?;
+    let window = TimeWindow::parse(opts.since.as_deref(), opts.until.as_deref())?;
     let count = opts.count as i32;
-    let cursor = opts.cursor;
+    let cursor = opts.cursor.map(MessageCursor::get);
     let fetch_all = opts.all;
     let delay_ms = opts.delay_ms;
     let force = opts.force;
     let json = opts.json;
     let creds = get_creds()?;
+    let viewer_id = creds.user_id;
 
     let rt = tokio::runtime::Runtime::new()?;
     rt.block_on(async {
@@ -617,9 +1541,12 @@ pub fn cmd_loco_read(chat_id: i64, opts: &ReadCommandOptions) -> Result<()> {
                 )
                 .into());
             }
-            eprintln!(
-                "Warning: reading from {} (open chat). Using conservative rate limiting.",
-                type_label(&chat_type)
+            warn(
+                WarningCode::OpenChat,
+                format!(
+                    "Reading from {} (open chat). Using conservative rate limiting.",
+                    type_label(&chat_type)
+                ),
             );
         }
 
@@ -638,6 +1565,16 @@ pub fn cmd_loco_read(chat_id: i64, opts: &ReadCommandOptions) -> Result<()> {
             anyhow::bail!("No messages in this chat");
         }
 
+        let receipts = if opts.receipts {
+            let receipts = room_watermarks(viewer_id, &room_info.body);
+            if receipts.is_none() {
+                note_receipts_unavailable();
+            }
+            receipts
+        } else {
+            None
+        };
+
         let mut member_names: HashMap<i64, String> =
             if let Ok(members) = room_info.body.get_array("m") {
                 build_member_name_map_from_bson(members)
@@ -655,10 +1592,16 @@ pub fn cmd_loco_read(chat_id: i64, opts: &ReadCommandOptions) -> Result<()> {
         // Streaming path: when json && fetch_all, print each batch as NDJSON
         // instead of buffering the entire history into memory.
         if json && fetch_all {
+            crate::warnings::stream_immediately(true);
             let loginlist_messages =
-                extract_loginlist_messages(&login_data, chat_id, since_ts, &mut member_names);
+                extract_loginlist_messages(&login_data, chat_id, window, &mut member_names)?;
             for msg in &loginlist_messages {
-                println!("{}", serde_json::to_string(msg).unwrap_or_default());
+                let msg = with_link(
+                    with_unread(msg.clone(), receipts.as_ref()),
+                    chat_id,
+                    opts.links,
+                );
+                println!("{}", serde_json::to_string(&msg).unwrap_or_default());
             }
 
             let existing_ids: std::collections::HashSet<i64> = loginlist_messages
@@ -672,10 +1615,12 @@ pub fn cmd_loco_read(chat_id: i64, opts: &ReadCommandOptions) -> Result<()> {
                     chat_id,
                     max_log: last_log_id,
                     cursor,
-                    since_ts,
+                    window,
                     effective_delay,
                     has_existing_messages: !loginlist_messages.is_empty(),
                     existing_ids: &existing_ids,
+                    links: opts.links,
+                    receipts: receipts.as_ref(),
                 },
             )
             .await?;
@@ -686,7 +1631,7 @@ pub fn cmd_loco_read(chat_id: i64, opts: &ReadCommandOptions) -> Result<()> {
         }
 
         let mut all_messages =
-            extract_loginlist_messages(&login_data, chat_id, since_ts, &mut member_names);
+            extract_loginlist_messages(&login_data, chat_id, window, &mut member_names)?;
 
         let existing_ids: std::collections::HashSet<i64> = all_messages
             .iter()
@@ -698,17 +1643,19 @@ pub fn cmd_loco_read(chat_id: i64, opts: &ReadCommandOptions) -> Result<()> {
                 chat_id,
                 max_log: last_log_id,
                 cursor,
-                since_ts,
+                window,
                 effective_delay,
                 has_existing_messages: !all_messages.is_empty(),
                 existing_ids: &existing_ids,
+                links: opts.links,
+                receipts: receipts.as_ref(),
             },
         )
         .await?;
         all_messages.extend(syncmsg_messages);
 
         // Merge with local SQLite cache (populated by `watch`)
-        if let Ok(db) = crate::message_db::MessageDb::open() {
+        if let Some(db) = crate::util::message_cache() {
             let cached = db.get_messages(chat_id, 0).unwrap_or_default();
             if !cached.is_empty() {
                 let loco_ids: std::collections::HashSet<i64> = all_messages
@@ -720,10 +1667,8 @@ pub fn cmd_loco_read(chat_id: i64, opts: &ReadCommandOptions) -> Result<()> {
                     if loco_ids.contains(&msg.log_id) {
                         continue;
                     }
-                    if let Some(ts) = since_ts {
-                        if msg.send_at < ts {
-                            continue;
-                        }
+                    if !window.contains(msg.send_at) {
+                        continue;
                     }
                     all_messages.push(serde_json::json!({
                         "log_id": msg.log_id,
@@ -779,15 +1724,22 @@ pub fn cmd_loco_read(chat_id: i64, opts: &ReadCommandOptions) -> Result<()> {
             }
         }
 
-        if !fetch_all && all_messages.len() > count as usize {
-            let skip = all_messages.len() - count as usize;
-            all_messages = all_messages.split_off(skip);
-        }
-
-        all_messages.sort_by_key(|m| m.get("send_at").and_then(|v| v.as_i64()).unwrap_or(0));
-
-        format_and_output_messages(&all_messages, &member_names, json);
+        // --since shows its whole window; -n caps the rest.
+        let shown = if fetch_all || window.since.is_some() {
+            usize::MAX
+        } else {
+            count as usize
+        };
+        let all_messages = select_window_by(all_messages, shown, None, |m| {
+            m.get("log_id").and_then(|v| v.as_i64()).unwrap_or(0)
+        });
 
-        Ok(())
+        format_and_output_messages(
+            chat_id,
+            &all_messages,
+            &member_names,
+            receipts.as_ref(),
+            opts,
+        )
     })
 }
@@ -1,16 +1,74 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use owo_colors::OwoColorize;
 use serde_json::Value;
 
+use crate::attachments::AttachmentKind;
+use crate::avatars::{avatar_filename, AvatarManifest, StoreOutcome};
+use crate::commands::read::{link_suffix, with_link};
+use crate::contacts::{write_csv, write_vcards, ContactFormat};
+use crate::download_pool::{self, DownloadJob, DownloadResult, Outcome, PoolOptions};
+use crate::error::OpenKakaoError;
 use crate::export::ExportFormat;
-use crate::model::{json_i64, json_string};
+use crate::export_manifest::{self, ExportManifest, ExportParameters, LogIdRange};
+use crate::mbox::MailAttachment;
+use crate::media_cache::MediaCache;
+use crate::message_db;
+use crate::model::{
+    json_i64, json_string, ChatCounts, ChatMessage, Friend, FriendCounts, HiddenFilter, Identity,
+};
+use crate::normalize::format_phone;
+use crate::progress::StderrProgress;
+use crate::prompt::confirm;
 use crate::rest::KakaoRestClient;
+use crate::scrap_cache::ScrapCache;
+use crate::search::{merge_messages, MatchedLine, Matcher, ScanStats};
+use crate::settings_diff::{self, SettingsSnapshot};
+use crate::theme::theme;
 use crate::util::{
-    color_enabled, confirm, format_time, get_creds, get_rest_client, member_name_map,
-    print_section_title, print_table, truncate, type_label,
+    color_enabled, display_safe, format_relative_age, format_time, get_creds, get_rest_client,
+    name_resolver, print_section_title, print_table, retain_name_matches, truncate, type_label,
+    NameQuery,
 };
+use crate::warnings::{warn, WarningCode};
+
+pub fn cmd_whoami(offline: bool, json: bool) -> Result<()> {
+    let identity = if offline {
+        // --profile names a saved file; the environment does not override it.
+        let from_env = crate::credentials::explicit_profile()
+            .is_none()
+            .then(crate::auth_flow::credentials_from_env)
+            .flatten();
+        let creds = match from_env {
+            Some(creds) => creds,
+            None => crate::credentials::load_credentials()?.ok_or_else(|| {
+                anyhow::anyhow!("No saved credentials. Run 'openkakao-rs login --save' first.")
+            })?,
+        };
+        Identity {
+            user_id: creds.user_id,
+            nickname: None,
+        }
+    } else {
+        get_rest_client()?.get_identity()?
+    };
+
+    if json {
+        crate::util::output_json(&identity)?;
+        return Ok(());
+    }
+
+    println!("  User ID:  {}", identity.user_id);
+    match &identity.nickname {
+        Some(nickname) => println!("  Nickname: {}", display_safe(nickname)),
+        None => println!("  Nickname: (not available offline)"),
+    }
+    Ok(())
+}
 
 pub fn cmd_me(json: bool) -> Result<()> {
     let rest_result = (|| -> Result<()> {
@@ -18,15 +76,15 @@ pub fn cmd_me(json: bool) -> Result<()> {
         let profile = client.get_my_profile()?;
 
         if json {
-            println!("{}", serde_json::to_string_pretty(&profile)?);
+            crate::util::output_json(&profile)?;
             return Ok(());
         }
 
         print_section_title("My Profile");
         println!("  Source:   REST");
-        println!("  Nickname: {}", profile.nickname);
+        println!("  Nickname: {}", display_safe(&profile.nickname));
         if !profile.status_message.is_empty() {
-            println!("  Status:   {}", profile.status_message);
+            println!("  Status:   {}", display_safe(&profile.status_message));
         }
         println!("  Email:    {}", profile.email);
         println!("  Account:  {}", profile.account_id);
@@ -40,7 +98,10 @@ pub fn cmd_me(json: bool) -> Result<()> {
     match rest_result {
         Ok(()) => Ok(()),
         Err(rest_err) => {
-            eprintln!("[me] REST profile failed: {rest_err:#}. Trying local LOCO friend graph.");
+            warn(
+                WarningCode::RestFallback,
+                format!("REST profile failed: {rest_err:#}. Using the local LOCO friend graph."),
+            );
             let creds = get_creds()?;
             let snapshot = super::profile::build_local_friend_graph().map_err(|local_err| {
                 anyhow::anyhow!(
@@ -58,15 +119,15 @@ pub fn cmd_me(json: bool) -> Result<()> {
                 })?;
 
             if json {
-                println!("{}", serde_json::to_string_pretty(&profile)?);
+                crate::util::output_json(&profile)?;
                 return Ok(());
             }
 
             print_section_title("My Profile");
             println!("  Source:   local LOCO friend graph");
-            println!("  Nickname: {}", profile.nickname);
+            println!("  Nickname: {}", display_safe(&profile.nickname));
             if !profile.status_message.is_empty() {
-                println!("  Status:   {}", profile.status_message);
+                println!("  Status:   {}", display_safe(&profile.status_message));
             }
             println!("  Account:  {}", profile.account_id);
             println!("  User ID:  {}", profile.user_id);
@@ -95,31 +156,102 @@ pub fn cmd_me(json: bool) -> Result<()> {
     }
 }
 
-pub fn filter_friend_search<T, F>(items: &mut Vec<T>, search: Option<String>, key: F)
+/// `me --set-nickname/--set-status`: one profile update, then the profile
+/// as the server now has it, so the change can be seen to have taken.
+pub fn cmd_me_update(nickname: Option<&str>, status: Option<&str>, json: bool) -> Result<()> {
+    let client = get_rest_client()?;
+    client
+        .update_profile(nickname, status)
+        .map_err(|err| match err {
+            OpenKakaoError::RestApi { status, message } if !message.trim().is_empty() => {
+                anyhow::anyhow!(
+                    "Profile update refused by Kakao: {} (status {status})",
+                    message.trim()
+                )
+            }
+            err => err.into(),
+        })?;
+    let profile = client.get_my_profile()?;
+    if json {
+        return crate::util::output_json(&profile);
+    }
+    print_section_title("Profile updated");
+    println!("  Nickname: {}", display_safe(&profile.nickname));
+    if profile.status_message.is_empty() {
+        println!("  Status:   (none)");
+    } else {
+        println!("  Status:   {}", display_safe(&profile.status_message));
+    }
+    let stale = nickname.is_some_and(|nickname| nickname != profile.nickname)
+        || status.is_some_and(|status| status != profile.status_message);
+    if stale {
+        eprintln!("Kakao accepted the update but still reports the old values; it may take a moment to show.");
+    }
+    Ok(())
+}
+
+pub fn filter_friend_search<T, I, F>(items: &mut Vec<T>, search: Option<NameQuery>, id: I, keys: F)
 where
-    F: Fn(&T) -> (String, String),
+    I: Fn(&T) -> i64,
+    F: Fn(&T) -> Vec<String>,
 {
     if let Some(query) = search {
-        let q = query.to_lowercase();
-        items.retain(|item| {
-            let (primary, secondary) = key(item);
-            primary.to_lowercase().contains(&q) || secondary.to_lowercase().contains(&q)
-        });
+        retain_name_matches(items, &query, id, keys);
+    }
+}
+
+/// The friend list narrowed by the `friends` filters.
+fn filtered_friends(
+    client: &KakaoRestClient,
+    favorites: bool,
+    hidden: HiddenFilter,
+    search: Option<NameQuery>,
+) -> Result<Vec<Friend>> {
+    let mut friends = client.get_friends()?;
+
+    if favorites {
+        friends.retain(|f| f.favorite);
     }
+
+    friends.retain(|f| hidden.keeps(f));
+
+    filter_friend_search(
+        &mut friends,
+        search,
+        |friend| friend.user_id,
+        |friend| {
+            vec![
+                friend.display_name(),
+                friend.phone_number.clone(),
+                friend.uuid.clone(),
+            ]
+        },
+    );
+    Ok(friends)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn cmd_friends(
     favorites: bool,
-    hidden: bool,
-    search: Option<String>,
+    hidden: HiddenFilter,
+    search: Option<NameQuery>,
     local: bool,
     chat_id: Option<i64>,
     user_id: Option<i64>,
+    detail: bool,
+    raw_phones: bool,
     json: bool,
 ) -> Result<()> {
     if local {
+        // The local graph only knows "hidden-like" users, so --hidden and
+        // --hidden-only both select them.
         return super::profile::cmd_friends_local(
-            favorites, hidden, search, chat_id, user_id, json,
+            favorites,
+            hidden != HiddenFilter::Exclude,
+            search,
+            chat_id,
+            user_id,
+            json,
         );
     }
 
@@ -128,46 +260,230 @@ pub fn cmd_friends(
     }
 
     let client = get_rest_client()?;
-    let mut friends = client.get_friends()?;
+    let friends = filtered_friends(&client, favorites, hidden, search)?;
 
-    if favorites {
-        friends.retain(|f| f.favorite);
+    if json {
+        crate::util::output_json(&friends)?;
+        return Ok(());
     }
 
-    if !hidden {
-        friends.retain(|f| !f.hidden);
+    let mut rows = Vec::new();
+    for mut f in friends {
+        if !raw_phones {
+            f.phone_number = format_phone(&f.phone_number, &f.country_iso);
+        }
+        let mut name = f.display_name();
+        if f.favorite {
+            name.push_str(" *");
+        }
+        if detail {
+            rows.push(vec![
+                name,
+                f.uuid,
+                f.status_message,
+                f.phone_number,
+                if f.hidden { "yes" } else { "" }.to_string(),
+                f.user_id.to_string(),
+            ]);
+        } else {
+            let status = truncate(&f.status_message, 30);
+            rows.push(vec![name, status, f.phone_number, f.user_id.to_string()]);
+        }
     }
 
-    filter_friend_search(&mut friends, search, |friend| {
-        (friend.display_name(), friend.phone_number.clone())
-    });
+    let title = if hidden == HiddenFilter::Only {
+        format!("Hidden friends ({})", rows.len())
+    } else {
+        format!("Friends ({})", rows.len())
+    };
+    print_section_title(&title);
+    if detail {
+        print_table(
+            &["Name", "Kakao ID", "Status", "Phone", "Hidden", "User ID"],
+            rows,
+        );
+    } else {
+        print_table(&["Name", "Status", "Phone", "User ID"], rows);
+    }
+    Ok(())
+}
+
+/// `friends --export`: the filtered friend list as CSV or vCard on stdout.
+/// CSV phone numbers follow `--raw-phones`; vCards always carry the
+/// international form.
+pub fn cmd_friends_export(
+    favorites: bool,
+    hidden: HiddenFilter,
+    search: Option<NameQuery>,
+    format: ContactFormat,
+    raw_phones: bool,
+) -> Result<()> {
+    let client = get_rest_client()?;
+    let mut friends = filtered_friends(&client, favorites, hidden, search)?;
+    let content = match format {
+        ContactFormat::Csv => {
+            if !raw_phones {
+                for f in &mut friends {
+                    f.phone_number = format_phone(&f.phone_number, &f.country_iso);
+                }
+            }
+            write_csv(&friends)?
+        }
+        ContactFormat::Vcf => write_vcards(&friends),
+    };
+    std::io::stdout().write_all(content.as_bytes())?;
+    eprintln!("Exported {} friends.", friends.len());
+    Ok(())
+}
+
+/// Look up a user by Kakao ID, marking whether they are already a friend.
+pub fn cmd_friend_find_by_id(kakao_id: &str, json: bool) -> Result<()> {
+    let client = get_rest_client()?;
+    let Some(mut found) = client.find_by_kakao_id(kakao_id)? else {
+        anyhow::bail!("No KakaoTalk user has the Kakao ID '{kakao_id}'.");
+    };
+    if !found.friend {
+        found.friend = client
+            .get_friends()?
+            .iter()
+            .any(|f| f.user_id == found.user_id);
+    }
 
     if json {
-        println!("{}", serde_json::to_string_pretty(&friends)?);
+        crate::util::output_json(&found)?;
         return Ok(());
     }
 
-    let mut rows = Vec::new();
-    for f in friends {
-        let mut name = f.display_name();
-        if f.favorite {
-            name.push_str(" *");
+    print_section_title(&format!("Kakao ID '{}'", found.uuid));
+    println!("  Nickname: {}", display_safe(&found.nickname));
+    println!("  User ID:  {}", found.user_id);
+    println!(
+        "  Relation: {}",
+        if found.friend {
+            "friend"
+        } else {
+            "not a friend"
         }
-        let status = truncate(&f.status_message, 30);
-        rows.push(vec![name, status, f.phone_number, f.user_id.to_string()]);
+    );
+    if !found.status_message.is_empty() {
+        println!("  Status:   {}", display_safe(&found.status_message));
+    }
+    if !found.profile_image_url.is_empty() {
+        println!("  Image:    {}", found.profile_image_url);
+    }
+    Ok(())
+}
+
+/// One-shot account overview: profile, friend and chat totals, unread count.
+/// Succeeding doubles as a REST token health check.
+pub fn cmd_summary(exact: bool, json: bool) -> Result<()> {
+    let client = get_rest_client()?;
+    let profile = client.get_my_profile()?;
+    let friends = FriendCounts::from_friends(&client.get_friends()?);
+    let rooms = if exact {
+        client.get_all_chats()?
+    } else {
+        client.get_chats(None)?.0
+    };
+    let chats = ChatCounts::from_rooms(&rooms);
+
+    let now = chrono::Utc::now().timestamp();
+    let source = crate::auth_flow::credential_source();
+    let token_updated_at = token_updated_at(source);
+    if json {
+        let output = serde_json::json!({
+            "nickname": profile.nickname,
+            "user_id": profile.user_id,
+            "friends": friends,
+            "chats": chats,
+            "chats_exact": exact,
+            "token": {
+                "source": source,
+                "updated_at": token_updated_at,
+                "age_secs": token_updated_at.map(|t| (now - t).max(0)),
+            },
+        });
+        crate::util::output_json(&output)?;
+        return Ok(());
     }
 
-    print_section_title(&format!("Friends ({})", rows.len()));
-    print_table(&["Name", "Status", "Phone", "User ID"], rows);
+    print_section_title(&format!("Summary: {}", profile.nickname));
+    println!(
+        "  Friends: {} ({} favorites, {} hidden, {} channels)",
+        friends.total, friends.favorites, friends.hidden, friends.channels
+    );
+    if friends.hidden > 0 {
+        println!(
+            "           review hidden ones with 'friends --hidden-only'; 'unhide --all-hidden' restores them"
+        );
+    }
+    let by_type = chats
+        .by_type
+        .iter()
+        .map(|(kind, n)| format!("{} {}", n, type_label(kind)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    println!(
+        "  Chats:   {}{} ({})",
+        chats.total,
+        if exact { "" } else { "+" },
+        by_type
+    );
+    if !exact {
+        println!("           first page only; use --exact for a full count");
+    }
+    println!(
+        "  Unread:  {} messages in {} chats",
+        chats.unread_messages, chats.unread_chats
+    );
+    match token_updated_at {
+        Some(at) => println!(
+            "  Token:   from {}, updated {}",
+            source,
+            format_relative_age(at, now)
+        ),
+        None => println!("  Token:   from {}", source),
+    }
     Ok(())
 }
 
-pub fn cmd_settings(json: bool) -> Result<()> {
+/// When the token `source` (an [`crate::auth_flow::credential_source`]
+/// label) was last written, as Unix seconds: the saved file's mtime, or the
+/// newest KakaoTalk cache entry. `None` for a token from the environment.
+fn token_updated_at(source: &str) -> Option<i64> {
+    match source {
+        "saved file" | "saved profile" => crate::credentials::credentials_path()
+            .ok()
+            .and_then(|p| std::fs::metadata(p).ok())
+            .and_then(|m| m.modified().ok())
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs() as i64),
+        "KakaoTalk cache" => crate::auth::cache_freshness()
+            .ok()
+            .flatten()
+            .and_then(|f| f.last_updated())
+            .map(|at| at.timestamp()),
+        _ => None,
+    }
+}
+
+pub fn cmd_settings(track: bool, changes: bool, json: bool) -> Result<()> {
     let client = get_rest_client()?;
     let settings = client.get_settings()?;
+    crate::health::observe_settings(&settings);
+
+    if changes {
+        return settings_changes(&settings, track, json);
+    }
+    if track {
+        let snapshot = SettingsSnapshot::new(&settings, snapshot_time());
+        let path = settings_diff::history_path()?;
+        settings_diff::record_snapshot(&path, snapshot)?;
+        eprintln!("Settings snapshot saved to {}", path.display());
+    }
 
     if json {
-        println!("{}", serde_json::to_string_pretty(&settings)?);
+        crate::util::output_json(&settings)?;
         return Ok(());
     }
 
@@ -181,24 +497,142 @@ pub fn cmd_settings(json: bool) -> Result<()> {
 
     let profile = settings.get("profile").cloned().unwrap_or(Value::Null);
     if !profile.is_null() {
-        println!("\n  Nickname:  {}", json_string(&profile, "nickname"));
-        println!("  Status:    {}", json_string(&profile, "statusMessage"));
+        println!(
+            "\n  Nickname:  {}",
+            display_safe(&json_string(&profile, "nickname"))
+        );
+        println!(
+            "  Status:    {}",
+            display_safe(&json_string(&profile, "statusMessage"))
+        );
     }
 
     Ok(())
 }
 
-pub fn cmd_scrap(url: &str, json: bool) -> Result<()> {
-    let client = get_rest_client()?;
-    let data = client.get_scrap_preview(url)?;
+fn snapshot_time() -> String {
+    chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
+
+/// `settings --changes`: diff against the last tracked snapshot; with
+/// `--track` this snapshot then becomes the next baseline.
+fn settings_changes(settings: &Value, track: bool, json: bool) -> Result<()> {
+    let path = settings_diff::history_path()?;
+    let current = SettingsSnapshot::new(settings, snapshot_time());
+    let Some(previous) = settings_diff::load_history(&path)?.pop() else {
+        if !track {
+            anyhow::bail!("No settings snapshot yet. Run 'openkakao-rs settings --track' first.");
+        }
+        settings_diff::record_snapshot(&path, current)?;
+        eprintln!("No earlier snapshot; saved this one as the baseline.");
+        return Ok(());
+    };
 
+    let changes = settings_diff::diff(&previous, &current);
+    let new_devices = changes.iter().filter(|c| c.is_new_device()).count();
     if json {
-        println!("{}", serde_json::to_string_pretty(&data)?);
+        crate::util::output_json(&serde_json::json!({
+            "previous_at": previous.taken_at,
+            "current_at": current.taken_at,
+            "new_devices": new_devices,
+            "changes": changes,
+        }))?;
+    } else {
+        print!(
+            "{}",
+            settings_diff::render_changes(&previous, &current, &changes)
+        );
+    }
+    if new_devices > 0 {
+        let alert = format!(
+            "{new_devices} new device(s) on this account since {}. If this was not you, change your password.",
+            previous.taken_at
+        );
+        if color_enabled() {
+            eprintln!("{}", alert.style(theme().fail));
+        } else {
+            eprintln!("{alert}");
+        }
+    }
+
+    if track {
+        settings_diff::record_snapshot(&path, current)?;
+    }
+    Ok(())
+}
+
+/// How `scrap` uses the link preview cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScrapOptions {
+    /// Neither read nor write the cache.
+    pub no_cache: bool,
+    /// Fetch even when a fresh entry exists, then update it.
+    pub refresh: bool,
+    /// Answer from the cache only, however old, and never go online.
+    pub cached_only: bool,
+    pub ttl_secs: u64,
+}
+
+pub fn cmd_scrap(url: &str, options: ScrapOptions, json: bool) -> Result<()> {
+    if !crate::urlnorm::is_fetchable(url) {
+        anyhow::bail!(
+            "'{}' is not a public http(s) link, so it cannot be previewed.",
+            url
+        );
+    }
+    let url = &crate::urlnorm::normalize(url, crate::urlnorm::strip_tracking())?;
+    let cache = if options.no_cache {
+        None
+    } else {
+        Some(ScrapCache::open()?)
+    };
+    let now = chrono::Utc::now().timestamp();
+    let cached = match &cache {
+        Some(cache) if !options.refresh => cache.get(url),
+        _ => None,
+    };
+
+    let data = match cached {
+        Some(entry) if options.cached_only || entry.is_fresh(now, options.ttl_secs) => {
+            let stale = if entry.is_fresh(now, options.ttl_secs) {
+                ""
+            } else {
+                ", stale"
+            };
+            eprintln!(
+                "[scrap] From cache (fetched {}{})",
+                format_relative_age(entry.fetched_at, now),
+                stale
+            );
+            entry.preview
+        }
+        _ if options.cached_only => {
+            anyhow::bail!(
+                "No cached preview for {}. Run scrap without --cached-only while online to cache it.",
+                url
+            );
+        }
+        _ => {
+            let data = get_rest_client()?.get_scrap_preview(url)?;
+            if let Some(cache) = &cache {
+                if let Err(e) = cache.put(url, &data, now) {
+                    warn(
+                        WarningCode::CacheUnavailable,
+                        format!("Could not cache the link preview: {e}"),
+                    );
+                }
+            }
+            data
+        }
+    };
+
+    if json {
+        crate::util::output_json(&data)?;
         return Ok(());
     }
 
     print_section_title("Link Preview");
-    println!("  Title: {}", json_string(&data, "title"));
+    println!("  Title: {}", display_safe(&json_string(&data, "title")));
 
     let description = json_string(&data, "description");
     if !description.is_empty() {
@@ -224,9 +658,8 @@ pub fn cmd_chatinfo(chat_id: i64, json: bool) -> Result<()> {
     super::probe::cmd_loco_chatinfo(chat_id, json)
 }
 
-pub fn cmd_favorite(user_id: i64, json: bool) -> Result<()> {
-    eprint!("Add user {} to favorites? [y/N] ", user_id);
-    if !confirm()? {
+pub fn cmd_favorite(user_id: i64, yes: bool, json: bool) -> Result<()> {
+    if !yes && !confirm(&format!("Add user {} to favorites? [y/N] ", user_id))? {
         println!("Cancelled.");
         return Ok(());
     }
@@ -244,9 +677,8 @@ pub fn cmd_favorite(user_id: i64, json: bool) -> Result<()> {
     Ok(())
 }
 
-pub fn cmd_unfavorite(user_id: i64, json: bool) -> Result<()> {
-    eprint!("Remove user {} from favorites? [y/N] ", user_id);
-    if !confirm()? {
+pub fn cmd_unfavorite(user_id: i64, yes: bool, json: bool) -> Result<()> {
+    if !yes && !confirm(&format!("Remove user {} from favorites? [y/N] ", user_id))? {
         println!("Cancelled.");
         return Ok(());
     }
@@ -264,9 +696,8 @@ pub fn cmd_unfavorite(user_id: i64, json: bool) -> Result<()> {
     Ok(())
 }
 
-pub fn cmd_hide(user_id: i64, json: bool) -> Result<()> {
-    eprint!("Hide user {}? [y/N] ", user_id);
-    if !confirm()? {
+pub fn cmd_hide(user_id: i64, yes: bool, json: bool) -> Result<()> {
+    if !yes && !confirm(&format!("Hide user {}? [y/N] ", user_id))? {
         println!("Cancelled.");
         return Ok(());
     }
@@ -284,9 +715,238 @@ pub fn cmd_hide(user_id: i64, json: bool) -> Result<()> {
     Ok(())
 }
 
-pub fn cmd_unhide(user_id: i64, json: bool) -> Result<()> {
-    eprint!("Unhide user {}? [y/N] ", user_id);
-    if !confirm()? {
+/// Spacing between request starts during `--download-avatars`, shared by
+/// all workers and the friends list call.
+const AVATAR_REQUEST_INTERVAL: Duration = Duration::from_millis(300);
+
+/// Where `--download-avatars` stages images before the manifest decides
+/// whether they replace the saved file.
+const AVATAR_STAGING_DIR: &str = ".openkakao-staging";
+
+/// `friends --download-avatars`: fetch the (filtered) friends' profile
+/// images into `dir` with up to `jobs` parallel downloads. Images whose URL
+/// is unchanged since the last run are skipped without a request.
+pub fn cmd_friends_avatars(
+    favorites: bool,
+    hidden: HiddenFilter,
+    search: Option<NameQuery>,
+    dir: &Path,
+    jobs: usize,
+    json: bool,
+) -> Result<()> {
+    crate::rest::set_request_interval(AVATAR_REQUEST_INTERVAL);
+    let client = get_rest_client()?;
+    let friends = filtered_friends(&client, favorites, hidden, search)?;
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let mut manifest = AvatarManifest::load(dir)?;
+    let staging = dir.join(AVATAR_STAGING_DIR);
+
+    let mut results = Vec::new();
+    let mut queue = Vec::new();
+    let mut targets = HashMap::new();
+    for friend in &friends {
+        let name = friend.display_name();
+        let file = avatar_filename(&name, friend.user_id);
+        let url = friend.profile_image_url.trim();
+        let skip = if url.is_empty() {
+            Some("no profile image")
+        } else if manifest.is_current(dir, friend.user_id, url, &file) {
+            Some("unchanged")
+        } else {
+            None
+        };
+        match skip {
+            Some(reason) => results.push(DownloadResult {
+                label: name,
+                path: dir.join(&file),
+                outcome: Outcome::Skipped {
+                    reason: reason.to_string(),
+                },
+            }),
+            None => {
+                let path = staging.join(&file);
+                targets.insert(path.clone(), (friend.user_id, url.to_string(), file));
+                queue.push(DownloadJob {
+                    label: name,
+                    url: url.to_string(),
+                    path,
+                });
+            }
+        }
+    }
+
+    let options = PoolOptions {
+        jobs,
+        retry: crate::util::retry_policy(),
+    };
+    for mut result in download_pool::run(&client, queue, options) {
+        let (user_id, url, file) = &targets[&result.path];
+        if matches!(result.outcome, Outcome::Succeeded { .. }) {
+            let stored = std::fs::read(&result.path)
+                .map_err(anyhow::Error::from)
+                .and_then(|bytes| manifest.store(dir, *user_id, url, file, &bytes));
+            let _ = std::fs::remove_file(&result.path);
+            match stored {
+                Ok(StoreOutcome::Written) => {}
+                Ok(StoreOutcome::Unchanged) => {
+                    result.outcome = Outcome::Skipped {
+                        reason: "unchanged".to_string(),
+                    }
+                }
+                Err(err) => {
+                    result.outcome = Outcome::Failed {
+                        reason: format!("{err:#}"),
+                        attempts: 1,
+                    }
+                }
+            }
+        }
+        result.path = dir.join(file);
+        results.push(result);
+    }
+    // Left behind only by failed downloads, for the next run to resume.
+    let _ = std::fs::remove_dir(&staging);
+    manifest.save(dir)?;
+
+    let counts = download_pool::counts(&results);
+    if json {
+        crate::util::output_json(&serde_json::json!({
+            "dir": dir.display().to_string(),
+            "friends": friends.len(),
+            "counts": counts,
+            "results": results,
+        }))?;
+    } else {
+        println!(
+            "Avatars in {}: {} downloaded, {} skipped, {} failed",
+            dir.display(),
+            counts.succeeded,
+            counts.skipped,
+            counts.failed
+        );
+        let rows: Vec<Vec<String>> = download_pool::summary_rows(&results)
+            .into_iter()
+            .map(|[status, item, reason]| vec![status, display_safe(&item).into_owned(), reason])
+            .collect();
+        if !rows.is_empty() {
+            println!();
+            print_table(&["Result", "Friend", "Reason"], rows);
+        }
+    }
+    if counts.failed > 0 {
+        warn(
+            WarningCode::DownloadFailed,
+            format!("{} avatar downloads failed", counts.failed),
+        );
+    }
+    Ok(())
+}
+
+/// Pause between unhide requests so a batch is not a request burst.
+const UNHIDE_BATCH_DELAY: std::time::Duration = std::time::Duration::from_millis(300);
+
+/// Unhide every hidden friend: show the plan, confirm (unless `yes`), then
+/// unhide one by one and report each result.
+pub fn cmd_unhide_all(yes: bool, dry_run: bool, json: bool) -> Result<()> {
+    let client = get_rest_client()?;
+    let mut hidden = client.get_friends()?;
+    hidden.retain(|f| HiddenFilter::Only.keeps(f));
+
+    if hidden.is_empty() {
+        if json {
+            crate::util::output_json(&serde_json::json!({
+                "action": "unhide",
+                "dry_run": dry_run,
+                "results": [],
+            }))?;
+        } else {
+            println!("No hidden friends.");
+        }
+        return Ok(());
+    }
+
+    let rows = hidden
+        .iter()
+        .map(|f| vec![f.display_name(), f.user_id.to_string()])
+        .collect();
+    if json {
+        eprintln!("Plan: unhide {} friends.", hidden.len());
+    } else {
+        print_section_title(&format!("Plan: unhide {} friends", hidden.len()));
+        print_table(&["Name", "User ID"], rows);
+    }
+
+    if dry_run {
+        eprintln!("[dry-run] Would unhide {} friends.", hidden.len());
+        if json {
+            let planned: Vec<Value> = hidden
+                .iter()
+                .map(|f| serde_json::json!({"user_id": f.user_id, "name": f.display_name()}))
+                .collect();
+            crate::util::output_json(&serde_json::json!({
+                "action": "unhide",
+                "dry_run": true,
+                "planned": planned,
+            }))?;
+        }
+        return Ok(());
+    }
+
+    if !yes
+        && !confirm(&format!(
+            "Unhide all {} hidden friends? [y/N] ",
+            hidden.len()
+        ))?
+    {
+        println!("Cancelled.");
+        return Ok(());
+    }
+
+    let mut results = Vec::new();
+    let mut failed = 0usize;
+    for (idx, friend) in hidden.iter().enumerate() {
+        if idx > 0 {
+            std::thread::sleep(UNHIDE_BATCH_DELAY);
+        }
+        let error = client.unhide_friend(friend.user_id).err();
+        if let Some(err) = &error {
+            failed += 1;
+            eprintln!(
+                "Failed to unhide {} ({}): {}",
+                display_safe(&friend.display_name()),
+                friend.user_id,
+                err
+            );
+        }
+        results.push(serde_json::json!({
+            "user_id": friend.user_id,
+            "name": friend.display_name(),
+            "status": if error.is_none() { "ok" } else { "error" },
+            "error": error.map(|e| e.to_string()),
+        }));
+    }
+
+    if json {
+        crate::util::output_json(&serde_json::json!({
+            "action": "unhide",
+            "dry_run": false,
+            "results": results,
+        }))?;
+    } else {
+        println!(
+            "Unhidden {} of {} friends.",
+            hidden.len() - failed,
+            hidden.len()
+        );
+    }
+    if failed > 0 {
+        anyhow::bail!("{failed} of {} unhide requests failed", hidden.len());
+    }
+    Ok(())
+}
+
+pub fn cmd_unhide(user_id: i64, yes: bool, json: bool) -> Result<()> {
+    if !yes && !confirm(&format!("Unhide user {}? [y/N] ", user_id))? {
         println!("Cancelled.");
         return Ok(());
     }
@@ -304,12 +964,24 @@ pub fn cmd_unhide(user_id: i64, json: bool) -> Result<()> {
     Ok(())
 }
 
+/// The endpoint catalog is for tools, so it is always JSON.
+pub fn cmd_endpoints() -> Result<()> {
+    let catalog: Vec<Value> = crate::rest::ENDPOINT_CATALOG
+        .iter()
+        .map(|spec| spec.to_json())
+        .collect();
+    crate::util::output_json(&serde_json::json!({
+        "version": crate::util::VERSION,
+        "endpoints": catalog,
+    }))
+}
+
 pub fn cmd_profiles(json: bool) -> Result<()> {
     let client = get_rest_client()?;
     let data = client.get_profiles()?;
 
     if json {
-        println!("{}", serde_json::to_string_pretty(&data)?);
+        crate::util::output_json(&data)?;
         return Ok(());
     }
 
@@ -336,7 +1008,7 @@ pub fn cmd_keywords(json: bool) -> Result<()> {
     let data = client.get_alarm_keywords()?;
 
     if json {
-        println!("{}", serde_json::to_string_pretty(&data)?);
+        crate::util::output_json(&data)?;
         return Ok(());
     }
 
@@ -365,7 +1037,7 @@ pub fn cmd_unread(json: bool) -> Result<()> {
     let unread: Vec<_> = chats.into_iter().filter(|c| c.unread_count > 0).collect();
 
     if json {
-        println!("{}", serde_json::to_string_pretty(&unread)?);
+        crate::util::output_json(&unread)?;
         return Ok(());
     }
 
@@ -394,86 +1066,673 @@ pub fn cmd_unread(json: bool) -> Result<()> {
     Ok(())
 }
 
-pub fn cmd_export(chat_id: i64, format: &str, output: Option<&str>, json: bool) -> Result<()> {
+pub struct ExportOptions<'a> {
+    pub format: &'a str,
+    pub output: Option<&'a str>,
+    pub privacy: crate::export::PrivacyFilter,
+    /// How many `--redact` patterns `privacy` holds, for the manifest.
+    pub redactions: usize,
+    pub embed_media: bool,
+    pub key_file: Option<&'a str>,
+    /// Upper bound on history pages fetched.
+    pub max_pages: usize,
+    /// Add only messages not yet in `output` to its end.
+    pub append: bool,
+    /// Mark each message with the chat title in effect when it was sent.
+    pub annotate_titles: bool,
+    /// Group replies under the messages they answer (txt).
+    pub threads: bool,
+    /// Write an integrity manifest beside the output file.
+    pub manifest: bool,
+}
+
+pub fn cmd_export(chat_id: i64, opts: ExportOptions, json: bool) -> Result<()> {
+    let ExportOptions {
+        format,
+        output,
+        privacy,
+        redactions,
+        embed_media,
+        key_file,
+        max_pages,
+        append,
+        annotate_titles,
+        threads,
+        manifest,
+    } = opts;
     let fmt = ExportFormat::from_str(format)?;
+    let is_mbox = matches!(fmt, ExportFormat::Mbox);
+    if embed_media && !is_mbox {
+        anyhow::bail!("--embed-media only applies to --format mbox");
+    }
+    let append_to = match output {
+        Some(path) if append && path != "-" => Some(Path::new(path)),
+        _ if append => anyhow::bail!("--append needs an output file (-o/--out)"),
+        _ => None,
+    };
+    if append && !fmt.appendable() {
+        anyhow::bail!("--append only works with --format jsonl or csv");
+    }
+    if annotate_titles
+        && !matches!(
+            fmt,
+            ExportFormat::Txt | ExportFormat::Json | ExportFormat::Jsonl
+        )
+    {
+        anyhow::bail!("--annotate-titles only works with --format txt, json or jsonl");
+    }
+    if threads && !matches!(fmt, ExportFormat::Txt) {
+        anyhow::bail!("--threads only works with --format txt (mbox threads replies already)");
+    }
+    // Read before fetching so a malformed archive fails fast.
+    let existing = match append_to {
+        Some(path) => crate::export::existing_log_ids(path, &fmt)?,
+        None => HashSet::new(),
+    };
     let creds = get_creds()?;
     let my_user_id = creds.user_id;
     let client = KakaoRestClient::new(creds)?;
 
     eprintln!("Fetching all messages for chat {}...", chat_id);
-    let messages = client.get_all_messages(chat_id, 100)?;
+    let mut messages = client.get_all_messages_with_progress(
+        chat_id,
+        max_pages,
+        &StderrProgress::new("export"),
+    )?;
+    let fetched = messages.len();
+    let covered = LogIdRange::of(messages.iter().map(|m| m.log_id));
     let members = client.get_chat_members(chat_id).unwrap_or_default();
+    if let Some(db) = crate::util::message_cache() {
+        let names: HashMap<i64, String> = members
+            .iter()
+            .map(|m| (m.user_id, m.display_name()))
+            .collect();
+        if !names.is_empty() {
+            let _ = db.store_member_names(chat_id, &names);
+        }
+        let _ = db.upsert_messages(&crate::commands::read::to_cache_rows(
+            chat_id, &messages, &names,
+        ));
+    }
 
     if messages.is_empty() {
         eprintln!("No messages found. The pilsner server only caches recently opened chats.");
         return Ok(());
     }
+    if append_to.is_some() {
+        messages.retain(|m| !existing.contains(&m.log_id));
+        eprintln!(
+            "{} of {} messages are already in the archive.",
+            fetched - messages.len(),
+            fetched
+        );
+    }
 
+    let ignored = crate::ignore::IgnoreList::load()?;
+    if !ignored.is_empty() {
+        let before = messages.len();
+        messages.retain(|m| !ignored.ignores(chat_id, m.author_id));
+        crate::ignore::note_dropped(before - messages.len());
+    }
+
+    let mut chat = crate::export::ExportChat {
+        chat_id,
+        anonymized: privacy.anonymize,
+        threads,
+        ..Default::default()
+    };
+    if is_mbox {
+        chat.title = client
+            .get_all_chats()
+            .ok()
+            .and_then(|rooms| rooms.into_iter().find(|r| r.chat_id == chat_id))
+            .map(|room| room.display_title())
+            .unwrap_or_else(|| format!("KakaoTalk chat {}", chat_id));
+    }
+    if embed_media {
+        chat.attachments = fetch_mail_attachments(&client, &messages)?;
+    }
+    if annotate_titles {
+        chat.titles = crate::commands::chats::title_history(chat_id)?;
+        if chat.titles.is_empty() {
+            warn(
+                WarningCode::PartialData,
+                format!(
+                    "no title history for chat {chat_id}; run `openkakao-rs cache --all` first"
+                ),
+            );
+        }
+    }
+
+    let (messages, members, key) = privacy.apply(&messages, &members, my_user_id);
     eprintln!("Exporting {} messages...", messages.len());
-    crate::export::export_messages(&messages, &members, my_user_id, &fmt, output)?;
+    match append_to {
+        Some(path) => crate::export::append_messages(
+            &messages,
+            &members,
+            my_user_id,
+            &fmt,
+            &chat.titles,
+            path,
+        )?,
+        None => {
+            crate::export::export_messages(&messages, &members, my_user_id, &fmt, &chat, output)?
+        }
+    }
+    if let Some(path) = key_file {
+        crate::export::write_key_file(std::path::Path::new(path), &key)?;
+        eprintln!("Pseudonym key written to {} (keep it private)", path);
+    }
+    let manifest_path = match output.filter(|path| manifest && *path != "-") {
+        Some(path) => Some(write_export_manifest(
+            chat_id,
+            Path::new(path),
+            ExportParameters {
+                format: format.to_string(),
+                max_pages,
+                append,
+                anonymized: privacy.anonymize,
+                no_media: privacy.no_media,
+                redactions,
+                embed_media,
+                annotate_titles,
+                threads,
+            },
+            messages.len(),
+            covered,
+        )?),
+        None => None,
+    };
 
+    // Paging stops at --max-bytes and keeps what it had; the export above
+    // is complete for those messages, but the run still fails.
+    let meter = crate::bandwidth::global();
+    let capped = meter.check(0).is_err();
     if json {
         crate::util::output_json(&serde_json::json!({
-            "status": "ok",
+            "status": if capped { "partial" } else { "ok" },
             "chat_id": chat_id,
             "format": format,
             "message_count": messages.len(),
+            "fetched": fetched,
+            "appended": append,
             "output": output.unwrap_or("-"),
+            "anonymized": privacy.anonymize,
+            "key_file": key_file,
+            "embedded_files": chat.attachments.values().map(Vec::len).sum::<usize>(),
+            "manifest": manifest_path,
         }))?;
     } else if let Some(path) = output {
         eprintln!("Exported to {}", path);
     }
+    if capped {
+        eprintln!(
+            "[export] Stopped at --max-bytes; {} messages were exported.",
+            messages.len()
+        );
+        return Err(meter.cap_error().into());
+    }
 
     Ok(())
 }
 
-pub fn cmd_search(chat_id: i64, query: &str, json: bool) -> Result<()> {
+/// Record `output` as just written in the next manifest of `chat_id` in its
+/// directory.
+fn write_export_manifest(
+    chat_id: i64,
+    output: &Path,
+    parameters: ExportParameters,
+    message_count: usize,
+    covered: Option<LogIdRange>,
+) -> Result<std::path::PathBuf> {
+    let dir = output
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let name = output
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .with_context(|| format!("Not a file name: {}", output.display()))?;
+    let bytes =
+        std::fs::read(output).with_context(|| format!("Failed to read {}", output.display()))?;
+    let previous = export_manifest::latest(dir, chat_id)?;
+    let manifest = ExportManifest::next(
+        previous.as_ref().map(|(m, bytes)| (m, bytes.as_slice())),
+        chat_id,
+        chrono::Utc::now().timestamp(),
+        parameters,
+        message_count,
+        covered,
+        vec![export_manifest::describe_file(&name, &bytes)],
+    );
+    export_manifest::write(dir, &manifest)
+}
+
+/// `export verify <dir>`: recompute every manifest's file hashes and check
+/// each chat's chain. Fails when anything is wrong.
+pub fn cmd_export_verify(dir: &Path, json: bool) -> Result<()> {
+    let manifests = export_manifest::read_manifests(dir)?;
+    if manifests.is_empty() {
+        anyhow::bail!(
+            "No export manifests in {}; they are written by `export -o <file>`.",
+            dir.display()
+        );
+    }
+    let report = export_manifest::verify(&manifests, |name| std::fs::read(dir.join(name)).ok());
+    if json {
+        crate::util::output_json(&serde_json::json!({
+            "ok": report.ok(),
+            "dir": dir,
+            "manifests": report.manifests,
+            "files": report.files,
+            "chats": report.chats,
+            "problems": report.problems,
+        }))?;
+    } else {
+        for problem in &report.problems {
+            println!("{problem}");
+        }
+        println!(
+            "{} manifest(s), {} file(s), {} chat(s): {}",
+            report.manifests,
+            report.files,
+            report.chats,
+            if report.ok() {
+                "all intact".to_string()
+            } else {
+                format!("{} problem(s)", report.problems.len())
+            }
+        );
+    }
+    if !report.ok() {
+        anyhow::bail!("export verify found {} problem(s)", report.problems.len());
+    }
+    Ok(())
+}
+
+/// Photos and files of `messages`, downloaded through the media cache, by
+/// log id. An album gives one attachment per photo, in order, all on its
+/// one mail. A failed download is a warning and leaves that mail without
+/// that attachment.
+fn fetch_mail_attachments(
+    client: &KakaoRestClient,
+    messages: &[crate::model::ChatMessage],
+) -> Result<HashMap<i64, Vec<MailAttachment>>> {
+    let mut cache = MediaCache::open()?;
+    let mut attachments = HashMap::new();
+    let media: Vec<_> = messages
+        .iter()
+        .filter(|msg| {
+            matches!(
+                AttachmentKind::of_message_type(msg.message_type),
+                Some(AttachmentKind::Photo | AttachmentKind::File)
+            )
+        })
+        .flat_map(|msg| {
+            if let crate::model::MessageContent::MultiPhoto { photos } = &msg.content {
+                return photos
+                    .iter()
+                    .enumerate()
+                    .map(|(index, photo)| {
+                        let name = photo.url.rsplit('/').next().unwrap_or_default();
+                        let filename =
+                            format!("{}_{}", index + 1, crate::media::sanitize_filename(name));
+                        (msg.log_id, photo.url.clone(), filename)
+                    })
+                    .collect();
+            }
+            crate::media::parse_attachment_url(&msg.attachment, msg.message_type as i32)
+                .map(|(url, filename)| {
+                    (msg.log_id, url, crate::media::sanitize_filename(&filename))
+                })
+                .into_iter()
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    for (idx, (log_id, url, filename)) in media.iter().enumerate() {
+        eprintln!("[export] media {}/{}: {}", idx + 1, media.len(), filename);
+        let data = cache
+            .download(client, url)
+            .and_then(|path| Ok(std::fs::read(path)?));
+        match data {
+            Ok(data) => attachments
+                .entry(*log_id)
+                .or_insert_with(Vec::new)
+                .push(MailAttachment::new(filename.clone(), data)),
+            Err(err) => warn(
+                WarningCode::DownloadFailed,
+                format!("{} (log {}) not embedded: {}", filename, log_id, err),
+            ),
+        }
+    }
+    Ok(attachments)
+}
+
+/// Pause between member fetches so `--chats-members all` is not a burst.
+const EXPORT_MEMBER_DELAY: std::time::Duration = std::time::Duration::from_millis(300);
+
+/// Write profile, friends, chats and (per `chats_members`) chat members into
+/// an SQLite file for ad-hoc queries.
+pub fn cmd_export_db(path: &str, chats_members: &str, json: bool) -> Result<()> {
+    let fanout = crate::export_db::MemberFanout::from_str(chats_members)?;
+    let client = get_rest_client()?;
+    let db = crate::export_db::ExportDb::open(std::path::Path::new(path))?;
+
+    let profile = client.get_my_profile()?;
+    db.upsert_profile(&profile)?;
+    let friends = db.upsert_friends(&client.get_friends()?)?;
+    eprintln!("[export-db] {} friends", friends);
+    let rooms = client.get_all_chats()?;
+    let chats = db.upsert_chats(&rooms)?;
+    eprintln!("[export-db] {} chats", chats);
+
+    let selected: Vec<_> = rooms.iter().filter(|r| fanout.includes(r)).collect();
+    let mut member_rows = 0usize;
+    let mut failed_chats = Vec::new();
+    for (idx, room) in selected.iter().enumerate() {
+        if idx > 0 {
+            std::thread::sleep(EXPORT_MEMBER_DELAY);
+        }
+        eprintln!(
+            "[export-db] members {}/{}: {}",
+            idx + 1,
+            selected.len(),
+            display_safe(&room.display_title())
+        );
+        match client.get_chat_members(room.chat_id) {
+            Ok(members) => member_rows += db.replace_chat_members(room.chat_id, &members)?,
+            Err(err) => {
+                warn(
+                    WarningCode::MemberFetchFailed,
+                    format!("members of chat {} not exported: {}", room.chat_id, err),
+                );
+                failed_chats.push(room.chat_id);
+            }
+        }
+    }
+
+    if json {
+        crate::util::output_json(&serde_json::json!({
+            "status": "ok",
+            "path": path,
+            "schema_version": crate::export_db::SCHEMA_VERSION,
+            "friends": friends,
+            "chats": chats,
+            "member_chats": selected.len() - failed_chats.len(),
+            "member_rows": member_rows,
+            "failed_member_chats": failed_chats,
+        }))?;
+    } else {
+        println!(
+            "Exported {} friends, {} chats and {} members of {} chats to {}",
+            friends,
+            chats,
+            member_rows,
+            selected.len() - failed_chats.len(),
+            path
+        );
+    }
+    Ok(())
+}
+
+/// `search` options.
+pub struct SearchOptions<'a> {
+    pub query: &'a str,
+    /// One chat; every chat in the chat list when `None`.
+    pub chat_id: Option<i64>,
+    pub regex: bool,
+    pub case_sensitive: bool,
+    pub normalize: bool,
+    /// REST pages per chat (default: 100 for one chat, 5 for all).
+    pub max_pages: Option<usize>,
+    pub links: bool,
+}
+
+const SEARCH_CHAT_PAGES: usize = 100;
+const SEARCH_ALL_PAGES: usize = 5;
+
+/// A message with matching lines.
+struct SearchHit {
+    chat_id: i64,
+    message: ChatMessage,
+    lines: Vec<MatchedLine>,
+}
+
+/// `search`: chats the local cache has synced and that have no unread
+/// messages are searched there; the others are paged over REST (down to the
+/// cached ones, if any). Chats the pilsner server has nothing for are
+/// skipped and counted.
+pub fn cmd_search(opts: SearchOptions<'_>, json: bool) -> Result<()> {
+    let matcher = Matcher::new(opts.query, opts.regex, opts.case_sensitive, opts.normalize)?;
     let creds = get_creds()?;
     let client = KakaoRestClient::new(creds.clone())?;
+    let max_pages = opts.max_pages.unwrap_or(match opts.chat_id {
+        Some(_) => SEARCH_CHAT_PAGES,
+        None => SEARCH_ALL_PAGES,
+    });
 
-    eprintln!("Fetching messages for chat {}...", chat_id);
-    eprintln!("Note: pilsner server only caches messages from recently opened chats.");
+    // Titles and unread counts; a single chat is searched even when the
+    // list is unavailable or does not have it.
+    let rooms = match opts.chat_id {
+        Some(_) => client.get_all_chats().unwrap_or_default(),
+        None => client.get_all_chats()?,
+    };
+    let targets: Vec<(i64, i64)> = match opts.chat_id {
+        Some(chat_id) => {
+            let unread = rooms
+                .iter()
+                .find(|room| room.chat_id == chat_id)
+                .map_or(1, |room| room.unread_count);
+            vec![(chat_id, unread)]
+        }
+        None => rooms
+            .iter()
+            .map(|room| (room.chat_id, room.unread_count))
+            .collect(),
+    };
+    let titles: HashMap<i64, String> = rooms
+        .iter()
+        .map(|room| (room.chat_id, room.title.clone()))
+        .collect();
+    let db = message_db::db_path()
+        .ok()
+        .filter(|path| path.exists())
+        .and_then(|_| message_db::MessageDb::open().ok());
 
-    let messages = client.get_all_messages(chat_id, 100)?;
+    if opts.chat_id.is_some() {
+        eprintln!("Note: pilsner server only caches messages from recently opened chats.");
+    } else {
+        eprintln!("Searching {} chats...", targets.len());
+    }
 
-    let q = query.to_lowercase();
-    let matched: Vec<_> = messages
-        .into_iter()
-        .filter(|m| m.message.to_lowercase().contains(&q))
-        .collect();
+    let mut stats = ScanStats::default();
+    let mut hits: Vec<SearchHit> = Vec::new();
+    for (chat_id, unread) in targets {
+        let synced = db
+            .as_ref()
+            .and_then(|db| db.get_sync_cursor(chat_id).ok().flatten());
+        let cached: Vec<ChatMessage> = match (&db, synced) {
+            (Some(db), Some(_)) => db
+                .get_messages(chat_id, 0)?
+                .iter()
+                .map(|m| m.to_message())
+                .collect(),
+            _ => Vec::new(),
+        };
+        let mut messages = cached;
+        if synced.is_none() || unread > 0 {
+            match search_pages(&client, chat_id, max_pages, synced, &mut stats) {
+                Ok(fetched) => messages = merge_messages(messages, fetched),
+                Err(err) if opts.chat_id.is_none() => {
+                    warn(
+                        WarningCode::PartialData,
+                        format!("Skipped chat {chat_id}: {err}"),
+                    );
+                    continue;
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+        if messages.is_empty() {
+            stats.skipped_chats += 1;
+            continue;
+        }
+        stats.chats += 1;
+        if synced.is_some() {
+            stats.cached_chats += 1;
+        }
+        stats.messages += messages.len();
+        hits.extend(messages.into_iter().filter_map(|message| {
+            let lines = matcher.matching_lines(&message.message);
+            (!lines.is_empty()).then_some(SearchHit {
+                chat_id,
+                message,
+                lines,
+            })
+        }));
+    }
 
-    let member_map = match client.get_chat_members(chat_id) {
-        Ok(members) => member_name_map(&members, creds.user_id),
-        Err(_) => HashMap::new(),
+    // One member list per chat with matches; authors who have since left
+    // fall back to the friends list, loaded only when needed.
+    let mut resolver = name_resolver(creds.user_id);
+    let mut seen_chats = HashSet::new();
+    for hit in &hits {
+        if seen_chats.insert(hit.chat_id) {
+            let _ =
+                resolver.member_names(hit.chat_id, || Ok(client.get_chat_members(hit.chat_id)?));
+        }
+    }
+    if hits.iter().any(|hit| {
+        hit.message.author_nickname.is_none()
+            && resolver
+                .resolve(hit.chat_id, hit.message.author_id)
+                .is_none()
+    }) {
+        let _ = resolver.friend_names(|| Ok(client.get_friends()?));
+    }
+    let author_of = |hit: &SearchHit| {
+        resolver
+            .resolve(hit.chat_id, hit.message.author_id)
+            .or_else(|| hit.message.author_nickname.clone())
+            .unwrap_or_else(|| hit.message.author_id.to_string())
+    };
+    let title_of = |chat_id: i64| {
+        titles
+            .get(&chat_id)
+            .filter(|title| !title.is_empty())
+            .cloned()
+            .unwrap_or_else(|| format!("Chat {chat_id}"))
     };
+    let matches: usize = hits.iter().map(|hit| hit.lines.len()).sum();
+    let summary = stats.summary(matches);
 
     if json {
-        println!("{}", serde_json::to_string_pretty(&matched)?);
+        let output = hits
+            .iter()
+            .map(|hit| {
+                let mut value = serde_json::to_value(&hit.message)?;
+                if let Some(object) = value.as_object_mut() {
+                    object.insert("chat_id".into(), hit.chat_id.into());
+                    object.insert("chat_title".into(), title_of(hit.chat_id).into());
+                    object.insert("author_name".into(), author_of(hit).into());
+                    object.insert(
+                        "matched_lines".into(),
+                        hit.lines.iter().map(|l| l.line.clone()).collect(),
+                    );
+                }
+                Ok(with_link(value, hit.chat_id, opts.links))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        crate::util::output_json(&output)?;
+        eprintln!("{summary}");
         return Ok(());
     }
 
-    if matched.is_empty() {
-        println!("No messages matching '{}'.", query);
+    if hits.is_empty() {
+        println!("No messages matching '{}'.", opts.query);
+        eprintln!("{summary}");
         return Ok(());
     }
 
-    print_section_title(&format!(
-        "Search results for '{}' ({} matches)",
-        query,
-        matched.len()
-    ));
-    for msg in &matched {
-        let name = member_map
-            .get(&msg.author_id)
-            .cloned()
-            .unwrap_or_else(|| msg.author_id.to_string());
-        let time_str = format_time(msg.send_at);
-        if color_enabled() {
-            println!("{} [{}]: {}", time_str.dimmed(), name.bold(), msg.message);
-        } else {
-            println!("{} [{}]: {}", time_str, name, msg.message);
+    let mut current_chat = None;
+    for hit in &hits {
+        if current_chat != Some(hit.chat_id) {
+            current_chat = Some(hit.chat_id);
+            let in_chat = hits
+                .iter()
+                .filter(|other| other.chat_id == hit.chat_id)
+                .map(|other| other.lines.len())
+                .sum::<usize>();
+            print_section_title(&format!(
+                "{} ({} match{})",
+                title_of(hit.chat_id),
+                in_chat,
+                if in_chat == 1 { "" } else { "es" }
+            ));
+        }
+        let time_str = format_time(hit.message.send_at);
+        let name = author_of(hit);
+        let name = display_safe(&name);
+        let link = link_suffix(hit.chat_id, hit.message.log_id, opts.links);
+        for line in &hit.lines {
+            let text: String = line
+                .segments()
+                .into_iter()
+                .map(|(part, matched)| {
+                    let part = display_safe(part);
+                    if matched && color_enabled() {
+                        part.style(theme().highlight).to_string()
+                    } else {
+                        part.into_owned()
+                    }
+                })
+                .collect();
+            if color_enabled() {
+                println!(
+                    "{} [{}]: {}{}",
+                    time_str.style(theme().timestamp),
+                    name.style(theme().author),
+                    text,
+                    link
+                );
+            } else {
+                println!("{} [{}]: {}{}", time_str, name, text, link);
+            }
         }
     }
+    eprintln!("{summary}");
 
     Ok(())
 }
+
+/// Up to `max_pages` REST pages of `chat_id`, newest first, stopping at the
+/// page that reaches `stop_at` (the newest cached log id).
+fn search_pages(
+    client: &KakaoRestClient,
+    chat_id: i64,
+    max_pages: usize,
+    stop_at: Option<i64>,
+    stats: &mut ScanStats,
+) -> Result<Vec<ChatMessage>, OpenKakaoError> {
+    let mut all = Vec::new();
+    let mut cursor = None;
+    for _ in 0..max_pages {
+        let (page, next_cursor) = match client.get_messages(chat_id, cursor) {
+            Ok(page) => page,
+            // The cap ends the search here; the pages so far are whole.
+            Err(OpenKakaoError::ByteCapReached { .. }) if !all.is_empty() => break,
+            Err(err) => return Err(err),
+        };
+        if page.is_empty() {
+            break;
+        }
+        stats.pages += 1;
+        let reached = stop_at.is_some_and(|last| page.iter().any(|m| m.log_id <= last));
+        all.extend(page);
+        if reached || next_cursor.is_none() {
+            break;
+        }
+        cursor = next_cursor;
+    }
+    Ok(all)
+}
@@ -0,0 +1,67 @@
+use anyhow::Result;
+
+use crate::schema::{OutputSchema, SCHEMAS};
+use crate::util::print_table;
+
+/// `schema [COMMAND]`: the JSON Schema document of one command's `--json`
+/// output (by command or schema id), every document keyed by id with
+/// `--all`, or else the list of published schemas.
+pub fn cmd_schema(command: Option<&str>, all: bool, json: bool) -> Result<()> {
+    if let Some(command) = command {
+        let wanted = command.replace(' ', ".");
+        let schema = SCHEMAS
+            .iter()
+            .find(|schema| schema.command == wanted || schema.id() == wanted)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "No published schema for '{}'. Published: {}",
+                    command,
+                    SCHEMAS
+                        .iter()
+                        .map(|schema| schema.command)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })?;
+        println!("{}", serde_json::to_string_pretty(&schema.document())?);
+        return Ok(());
+    }
+
+    if all {
+        let documents = SCHEMAS
+            .iter()
+            .map(|schema| Ok((schema.id(), serde_json::to_value(schema.document())?)))
+            .collect::<Result<serde_json::Map<_, _>>>()?;
+        println!("{}", serde_json::to_string_pretty(&documents)?);
+        return Ok(());
+    }
+
+    if json {
+        let items: Vec<serde_json::Value> = SCHEMAS.iter().map(summary).collect();
+        return crate::util::output_json(&items);
+    }
+    print_table(
+        &["Schema", "Command", "Output"],
+        SCHEMAS
+            .iter()
+            .map(|schema| {
+                vec![
+                    schema.id(),
+                    schema.command.to_string(),
+                    schema.about.to_string(),
+                ]
+            })
+            .collect(),
+    );
+    println!("\nEvery --json output is wrapped as {{\"schema\", \"generated_at\", \"data\"}}; `schema <command>` prints its JSON Schema.");
+    Ok(())
+}
+
+fn summary(schema: &OutputSchema) -> serde_json::Value {
+    serde_json::json!({
+        "id": schema.id(),
+        "command": schema.command,
+        "version": schema.version,
+        "about": schema.about,
+    })
+}
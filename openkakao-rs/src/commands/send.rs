@@ -5,15 +5,22 @@ use anyhow::Result;
 use crate::error::OpenKakaoError;
 use crate::loco_helpers::{check_loco_status, loco_connect_with_auto_refresh};
 use crate::media::{detect_media_type, jpeg_dimensions, png_dimensions};
+use crate::model::{ChatMessage, KakaoCredentials};
+use crate::prompt::confirm;
+use crate::rest::KakaoRestClient;
 use crate::state::{mark_unattended_send_attempt, record_guard, unattended_send_remaining_secs};
 use crate::util::{
-    confirm, extract_chat_type, get_creds, is_open_chat, require_permission, truncate, type_label,
-    validate_outbound_message,
+    extract_chat_type, format_time, get_bson_i64, get_creds, get_rest_client, is_open_chat,
+    require_permission, split_message, truncate, type_label, validate_outbound_message,
+    MAX_MESSAGE_CHARS,
 };
+use crate::warnings::{warn, WarningCode};
 
 pub struct SendOptions {
     pub chat_id: i64,
     pub message: String,
+    /// Send a message over [`MAX_MESSAGE_CHARS`] as several messages.
+    pub split: bool,
     pub force: bool,
     pub skip_confirm: bool,
     pub unattended: bool,
@@ -34,8 +41,13 @@ pub struct DeleteOptions {
 }
 
 pub struct MarkReadOptions {
-    pub chat_id: i64,
-    pub log_id: i64,
+    /// `None` with `all`.
+    pub chat_id: Option<i64>,
+    /// Newest message of the chat when `None`.
+    pub log_id: Option<i64>,
+    /// Every chat the chat list shows with unread messages.
+    pub all: bool,
+    pub yes: bool,
     pub json: bool,
 }
 
@@ -69,10 +81,57 @@ pub struct SendFileOptions {
     pub json: bool,
 }
 
+/// The text of each message `send` writes, in order.
+pub fn outgoing_parts(message: &str, split: bool) -> Result<Vec<String>> {
+    let parts = if split {
+        split_message(message, MAX_MESSAGE_CHARS)
+    } else {
+        vec![message.to_string()]
+    };
+    for part in &parts {
+        validate_outbound_message(part)?;
+    }
+    Ok(parts)
+}
+
+/// The LOCO WRITE body for one text message.
+pub fn write_body(chat_id: i64, message: &str) -> bson::Document {
+    bson::doc! {
+        "chatId": chat_id,
+        "msg": message,
+        "type": 1_i32,
+        "noSeen": false,
+    }
+}
+
+/// The message as the server recorded it: the `chatLog` echoed in the WRITE
+/// response when there is one, else the sent text with the returned log id
+/// and time.
+fn sent_message(response: &bson::Document, message: &str, user_id: i64) -> ChatMessage {
+    if let Ok(chat_log) = response.get_document("chatLog") {
+        return ChatMessage::from_json(
+            &bson::Bson::Document(chat_log.clone()).into_relaxed_extjson(),
+        );
+    }
+    let send_at = response
+        .get("sendAt")
+        .cloned()
+        .map(bson::Bson::into_relaxed_extjson)
+        .unwrap_or_default();
+    ChatMessage::from_json(&serde_json::json!({
+        "logId": get_bson_i64(response, &["logId"]),
+        "authorId": user_id,
+        "type": 1,
+        "message": message,
+        "sendAt": send_at,
+    }))
+}
+
 pub fn cmd_send(opts: SendOptions) -> Result<()> {
     let SendOptions {
         chat_id,
         ref message,
+        split,
         force,
         skip_confirm,
         unattended,
@@ -80,7 +139,7 @@ pub fn cmd_send(opts: SendOptions) -> Result<()> {
         min_interval_secs: min_unattended_send_interval_secs,
         json,
     } = opts;
-    validate_outbound_message(message)?;
+    let parts = outgoing_parts(message, split)?;
     if skip_confirm {
         require_permission(
             unattended && allow_non_interactive_send,
@@ -98,6 +157,7 @@ pub fn cmd_send(opts: SendOptions) -> Result<()> {
         mark_unattended_send_attempt()?;
     }
     let creds = get_creds()?;
+    let user_id = creds.user_id;
 
     let rt = tokio::runtime::Runtime::new()?;
     rt.block_on(async {
@@ -124,84 +184,82 @@ pub fn cmd_send(opts: SendOptions) -> Result<()> {
         }
 
         if is_open_chat(&chat_type) {
-            eprintln!(
-                "Warning: sending to {} (open chat). Proceed with caution.",
-                label
+            warn(
+                WarningCode::OpenChat,
+                format!("Sending to {} (open chat). Proceed with caution.", label),
             );
         }
 
         if !skip_confirm {
-            eprint!(
-                "Send to {} chat {}? Message: \"{}\"\n[y/N] ",
+            let count = if parts.len() > 1 {
+                format!(" as {} messages", parts.len())
+            } else {
+                String::new()
+            };
+            if !confirm(&format!(
+                "Send to {} chat {}{}? Message: \"{}\"\n[y/N] ",
                 label,
                 chat_id,
+                count,
                 truncate(message, 50)
-            );
-            if !confirm()? {
+            ))? {
                 println!("Cancelled.");
                 return Ok(());
             }
         }
 
-        let response = client
-            .send_command(
-                "WRITE",
-                bson::doc! {
-                    "chatId": chat_id,
-                    "msg": message,
-                    "type": 1_i32,
-                    "noSeen": false,
-                },
-            )
-            .await?;
-
-        check_loco_status("WRITE", &response)?;
+        let mut sent = Vec::with_capacity(parts.len());
+        for (i, part) in parts.iter().enumerate() {
+            match write_text(&mut client, chat_id, part, user_id).await {
+                Ok(msg) => sent.push(msg),
+                Err(err) => {
+                    if !sent.is_empty() {
+                        eprintln!("Sent {} of {} parts before the error.", i, parts.len());
+                    }
+                    return Err(err);
+                }
+            }
+        }
 
-        let log_id = response.body.get_i64("logId").unwrap_or(0);
         if json {
             crate::util::output_json(&serde_json::json!({
                 "chat_id": chat_id,
-                "log_id": log_id,
+                "log_id": sent.first().map_or(0, |m| m.log_id),
                 "status": "sent",
+                "messages": sent,
             }))?;
         } else {
-            println!("Message sent!");
+            for (msg, part) in sent.iter().zip(&parts) {
+                let at = match format_time(msg.send_at) {
+                    at if at.is_empty() => String::new(),
+                    at => format!(" at {}", at),
+                };
+                println!("Message sent: log {}{}", msg.log_id, at);
+                if msg.message != *part {
+                    println!("  Stored as: \"{}\"", truncate(&msg.message, 80));
+                }
+            }
         }
 
         Ok(())
     })
 }
 
-pub fn cmd_send_file(opts: SendFileOptions) -> Result<()> {
-    let SendFileOptions {
-        chat_id,
-        ref file_path,
-        force,
-        skip_confirm,
-        unattended,
-        allow_non_interactive: allow_non_interactive_send,
-        min_interval_secs: min_unattended_send_interval_secs,
-        json,
-    } = opts;
-    if skip_confirm {
-        require_permission(
-            unattended && allow_non_interactive_send,
-            "non-interactive file send (-y/--yes)",
-            "Re-run with --unattended --allow-non-interactive-send, or set both in ~/.config/openkakao/config.toml.",
-        )?;
-        if let Some(remaining) = unattended_send_remaining_secs(min_unattended_send_interval_secs)?
-        {
-            record_guard("unattended_send_rate_limited")?;
-            anyhow::bail!(
-                "unattended send is rate-limited for {}s; wait or raise safety.min_unattended_send_interval_secs",
-                remaining
-            );
-        }
-        mark_unattended_send_attempt()?;
-    }
-    let path = Path::new(file_path);
+/// A file read and classified for upload.
+pub struct PreparedFile {
+    pub data: Vec<u8>,
+    pub msg_type: i32,
+    pub ext: String,
+    pub width: i32,
+    pub height: i32,
+    pub file_name: String,
+    pub type_label: &'static str,
+}
+
+/// Read `path` and work out the message type and image size to announce.
+pub fn prepare_file(path: &Path) -> Result<PreparedFile> {
     if !path.exists() {
-        anyhow::bail!("File not found: {}", file_path);
+        anyhow::bail!("File not found: {}", path.display());
     }
 
     let data = std::fs::read(path)?;
@@ -214,10 +272,9 @@ pub fn cmd_send_file(opts: SendFileOptions) -> Result<()> {
         .map(|e| e.to_string_lossy().to_lowercase())
         .unwrap_or_default();
 
-    let (msg_type, ext_str) = detect_media_type(&data, &file_ext);
-    let ext = ext_str.as_str();
+    let (msg_type, ext) = detect_media_type(&data, &file_ext);
 
-    let type_label_str = match msg_type {
+    let type_label = match msg_type {
         2 => "photo",
         3 => "video",
         14 => "gif",
@@ -225,7 +282,7 @@ pub fn cmd_send_file(opts: SendFileOptions) -> Result<()> {
         _ => "file",
     };
 
-    let (width, height) = match (msg_type, ext) {
+    let (width, height) = match (msg_type, ext.as_str()) {
         (2, "jpg") => jpeg_dimensions(&data).unwrap_or((0, 0)),
         (2, "png") => png_dimensions(&data).unwrap_or((0, 0)),
         _ => (0, 0),
@@ -236,14 +293,133 @@ pub fn cmd_send_file(opts: SendFileOptions) -> Result<()> {
         .map(|n| n.to_string_lossy().to_string())
         .unwrap_or_else(|| format!("upload.{}", ext));
 
+    Ok(PreparedFile {
+        data,
+        msg_type,
+        ext,
+        width,
+        height,
+        file_name,
+        type_label,
+    })
+}
+
+/// Announce `file` with SHIP on a connected client, then upload it.
+pub async fn ship_file(
+    client: &mut crate::loco::client::LocoClient,
+    creds: &KakaoCredentials,
+    chat_id: i64,
+    file: &PreparedFile,
+) -> Result<()> {
+    let checksum = {
+        use sha1::Digest;
+        let hash = sha1::Sha1::digest(&file.data);
+        hex::encode(hash)
+    };
+
+    let ship_resp = client
+        .send_command(
+            "SHIP",
+            bson::doc! {
+                "c": chat_id,
+                "s": file.data.len() as i64,
+                "t": file.msg_type,
+                "cs": &checksum,
+                "e": &file.ext,
+                "ex": "{}",
+            },
+        )
+        .await?;
+
+    check_loco_status("SHIP", &ship_resp)?;
+
+    let upload_key = ship_resp
+        .body
+        .get_str("k")
+        .map_err(|_| anyhow::anyhow!("No key in SHIP response"))?
+        .to_string();
+    let vhost = ship_resp
+        .body
+        .get_str("vh")
+        .map_err(|_| anyhow::anyhow!("No vhost in SHIP response"))?
+        .to_string();
+    let upload_port = ship_resp.body.get_i32("p").map(|p| p as u16).unwrap_or(443);
+
+    eprintln!(
+        "[ship] Upload server: {}:{}, key: {}",
+        vhost, upload_port, upload_key
+    );
+
+    crate::loco::client::loco_upload(
+        &vhost,
+        upload_port,
+        creds.user_id,
+        &upload_key,
+        chat_id,
+        &file.data,
+        file.msg_type,
+        file.width,
+        file.height,
+        &creds.app_version,
+    )
+    .await
+}
+
+/// WRITE one text part on a connected client and return it as stored.
+pub async fn write_text(
+    client: &mut crate::loco::client::LocoClient,
+    chat_id: i64,
+    text: &str,
+    user_id: i64,
+) -> Result<ChatMessage> {
+    let response = client
+        .send_command("WRITE", write_body(chat_id, text))
+        .await?;
+    check_loco_status("WRITE", &response)?;
+    Ok(sent_message(&response.body, text, user_id))
+}
+
+pub fn cmd_send_file(opts: SendFileOptions) -> Result<()> {
+    let SendFileOptions {
+        chat_id,
+        ref file_path,
+        force,
+        skip_confirm,
+        unattended,
+        allow_non_interactive: allow_non_interactive_send,
+        min_interval_secs: min_unattended_send_interval_secs,
+        json,
+    } = opts;
+    if skip_confirm {
+        require_permission(
+            unattended && allow_non_interactive_send,
+            "non-interactive file send (-y/--yes)",
+            "Re-run with --unattended --allow-non-interactive-send, or set both in ~/.config/openkakao/config.toml.",
+        )?;
+        if let Some(remaining) = unattended_send_remaining_secs(min_unattended_send_interval_secs)?
+        {
+            record_guard("unattended_send_rate_limited")?;
+            anyhow::bail!(
+                "unattended send is rate-limited for {}s; wait or raise safety.min_unattended_send_interval_secs",
+                remaining
+            );
+        }
+        mark_unattended_send_attempt()?;
+    }
+    let file = prepare_file(Path::new(file_path))?;
+    let PreparedFile {
+        ref file_name,
+        type_label: type_label_str,
+        ..
+    } = file;
     eprintln!(
         "{}: {} ({} bytes, {}x{}, type={})",
         type_label_str,
         file_name,
-        data.len(),
-        width,
-        height,
-        msg_type
+        file.data.len(),
+        file.width,
+        file.height,
+        file.msg_type
     );
 
     let creds = get_creds()?;
@@ -268,69 +444,17 @@ pub fn cmd_send_file(opts: SendFileOptions) -> Result<()> {
             .into());
         }
 
-        if !skip_confirm {
-            eprint!(
+        if !skip_confirm
+            && !confirm(&format!(
                 "Send {} ({}) to {} chat {}?\n[y/N] ",
                 file_name, type_label_str, label, chat_id
-            );
-            if !confirm()? {
-                println!("Cancelled.");
-                return Ok(());
-            }
+            ))?
+        {
+            println!("Cancelled.");
+            return Ok(());
         }
 
-        let checksum = {
-            use sha1::Digest;
-            let hash = sha1::Sha1::digest(&data);
-            hex::encode(hash)
-        };
-
-        let ship_resp = client
-            .send_command(
-                "SHIP",
-                bson::doc! {
-                    "c": chat_id,
-                    "s": data.len() as i64,
-                    "t": msg_type,
-                    "cs": &checksum,
-                    "e": ext,
-                    "ex": "{}",
-                },
-            )
-            .await?;
-
-        check_loco_status("SHIP", &ship_resp)?;
-
-        let upload_key = ship_resp
-            .body
-            .get_str("k")
-            .map_err(|_| anyhow::anyhow!("No key in SHIP response"))?
-            .to_string();
-        let vhost = ship_resp
-            .body
-            .get_str("vh")
-            .map_err(|_| anyhow::anyhow!("No vhost in SHIP response"))?
-            .to_string();
-        let upload_port = ship_resp.body.get_i32("p").map(|p| p as u16).unwrap_or(443);
-
-        eprintln!(
-            "[ship] Upload server: {}:{}, key: {}",
-            vhost, upload_port, upload_key
-        );
-
-        crate::loco::client::loco_upload(
-            &vhost,
-            upload_port,
-            creds.user_id,
-            &upload_key,
-            chat_id,
-            &data,
-            msg_type,
-            width,
-            height,
-            &creds.app_version,
-        )
-        .await?;
+        ship_file(&mut client, &creds, chat_id, &file).await?;
 
         if json {
             crate::util::output_json(&serde_json::json!({
@@ -403,21 +527,20 @@ pub fn cmd_delete(opts: DeleteOptions) -> Result<()> {
         }
 
         if is_open_chat(&chat_type) {
-            eprintln!(
-                "Warning: deleting in {} (open chat). Proceed with caution.",
-                label
+            warn(
+                WarningCode::OpenChat,
+                format!("Deleting in {} (open chat). Proceed with caution.", label),
             );
         }
 
-        if !skip_confirm {
-            eprint!(
+        if !skip_confirm
+            && !confirm(&format!(
                 "Delete message {} from {} chat {}?\n[y/N] ",
                 log_id, label, chat_id
-            );
-            if !confirm()? {
-                println!("Cancelled.");
-                return Ok(());
-            }
+            ))?
+        {
+            println!("Cancelled.");
+            return Ok(());
         }
 
         let response = client
@@ -547,18 +670,17 @@ pub fn cmd_edit(opts: EditOptions) -> Result<()> {
             .into());
         }
 
-        if !skip_confirm {
-            eprint!(
+        if !skip_confirm
+            && !confirm(&format!(
                 "Edit message {} in {} chat {}? New text: \"{}\"\n[y/N] ",
                 log_id,
                 label,
                 chat_id,
                 truncate(message, 50)
-            );
-            if !confirm()? {
-                println!("Cancelled.");
-                return Ok(());
-            }
+            ))?
+        {
+            println!("Cancelled.");
+            return Ok(());
         }
 
         let response = client
@@ -589,40 +711,139 @@ pub fn cmd_edit(opts: EditOptions) -> Result<()> {
     })
 }
 
-pub fn cmd_mark_read(opts: MarkReadOptions) -> Result<()> {
-    let MarkReadOptions {
-        chat_id,
-        log_id,
-        json,
-    } = opts;
+/// Send a NOTIREAD for `chat_id` up to `log_id`, without printing.
+pub fn mark_read(chat_id: i64, log_id: i64) -> Result<()> {
+    mark_read_many(&[(chat_id, log_id)])
+}
+
+/// Move the read watermark of each `(chat_id, log_id)` over one LOCO
+/// connection.
+pub fn mark_read_many(watermarks: &[(i64, i64)]) -> Result<()> {
     let creds = get_creds()?;
 
     let rt = tokio::runtime::Runtime::new()?;
     rt.block_on(async {
         let mut client = crate::loco::client::LocoClient::new(creds);
-        eprintln!("Connecting via LOCO...");
         loco_connect_with_auto_refresh(&mut client).await?;
 
-        let _ = client
-            .send_packet(
-                "NOTIREAD",
-                bson::doc! {
-                    "chatId": chat_id,
-                    "watermark": log_id,
-                },
-            )
-            .await;
+        for &(chat_id, log_id) in watermarks {
+            let _ = client
+                .send_packet(
+                    "NOTIREAD",
+                    bson::doc! {
+                        "chatId": chat_id,
+                        "watermark": log_id,
+                    },
+                )
+                .await;
+        }
+        Ok(())
+    })
+}
+
+/// The newest log id of `chat_id`, from its first REST message page.
+fn newest_log_id(client: &KakaoRestClient, chat_id: i64) -> Result<i64> {
+    let (messages, _) = client.get_messages(chat_id, None)?;
+    messages.iter().map(|m| m.log_id).max().ok_or_else(|| {
+        anyhow::anyhow!(
+            "No messages of chat {chat_id} on the server to mark read up to; pass --up-to <log_id>"
+        )
+    })
+}
 
+pub fn cmd_mark_read(opts: MarkReadOptions) -> Result<()> {
+    let MarkReadOptions {
+        chat_id,
+        log_id,
+        all,
+        yes,
+        json,
+    } = opts;
+    if all {
+        return cmd_mark_all_read(yes, json);
+    }
+    let chat_id = chat_id.ok_or_else(|| anyhow::anyhow!("Give a chat id or --all"))?;
+    let log_id = match log_id {
+        Some(log_id) => log_id,
+        None => newest_log_id(&get_rest_client()?, chat_id)?,
+    };
+    eprintln!("Connecting via LOCO...");
+    mark_read(chat_id, log_id)?;
+
+    if json {
+        crate::util::output_json(&serde_json::json!({
+            "chat_id": chat_id,
+            "watermark": log_id,
+            "status": "marked_read",
+        }))?;
+    } else {
+        println!("Marked as read up to message {}.", log_id);
+    }
+
+    Ok(())
+}
+
+/// `mark-read --all`: every room with `unread_count > 0`, each up to its
+/// last log id from the chat list (or its newest message).
+fn cmd_mark_all_read(yes: bool, json: bool) -> Result<()> {
+    let client = get_rest_client()?;
+    let unread: Vec<_> = client
+        .get_all_chats()?
+        .into_iter()
+        .filter(|room| room.unread_count > 0)
+        .collect();
+    if unread.is_empty() {
         if json {
-            crate::util::output_json(&serde_json::json!({
-                "chat_id": chat_id,
-                "watermark": log_id,
-                "status": "marked_read",
-            }))?;
+            crate::util::output_json(
+                &serde_json::json!({ "marked": [], "status": "marked_read" }),
+            )?;
         } else {
-            println!("Marked as read up to message {}.", log_id);
+            println!("No unread chats.");
         }
+        return Ok(());
+    }
+    let total_unread: i64 = unread.iter().map(|room| room.unread_count).sum();
+    if !yes
+        && !confirm(&format!(
+            "Mark {} chats ({} unread messages) as read? [y/N] ",
+            unread.len(),
+            total_unread
+        ))?
+    {
+        println!("Cancelled.");
+        return Ok(());
+    }
 
-        Ok(())
-    })
+    let mut watermarks = Vec::new();
+    for room in &unread {
+        let log_id = match room.settings.last_log_id {
+            Some(log_id) => log_id,
+            None => match newest_log_id(&client, room.chat_id) {
+                Ok(log_id) => log_id,
+                Err(err) => {
+                    warn(
+                        WarningCode::PartialData,
+                        format!("Left chat {} unread: {err:#}", room.chat_id),
+                    );
+                    continue;
+                }
+            },
+        };
+        watermarks.push((room.chat_id, log_id));
+    }
+    eprintln!("Connecting via LOCO...");
+    mark_read_many(&watermarks)?;
+
+    if json {
+        let marked: Vec<_> = watermarks
+            .iter()
+            .map(|(chat_id, log_id)| serde_json::json!({ "chat_id": chat_id, "watermark": log_id }))
+            .collect();
+        crate::util::output_json(
+            &serde_json::json!({ "marked": marked, "status": "marked_read" }),
+        )?;
+    } else {
+        println!("Marked {} chats as read.", watermarks.len());
+    }
+    Ok(())
 }
@@ -0,0 +1,321 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use owo_colors::OwoColorize;
+
+use crate::auth_flow::{reload_credentials, watch_credentials};
+use crate::commands::read::{message_body, print_hidden as print_hidden_line};
+use crate::credential_watch::Reload;
+use crate::error::OpenKakaoError;
+use crate::ignore::{HiddenRuns, IgnoreList};
+use crate::model::{ChatMessage, ChatRoom};
+use crate::rest::{KakaoRestClient, RequestOptions};
+use crate::tail::{UnreadTracker, Watermarks};
+use crate::theme::theme;
+use crate::util::{
+    color_enabled, display_safe, format_time, get_creds, get_rest_client, name_resolver,
+    require_permission,
+};
+use crate::warnings::{warn, WarningCode};
+
+/// Older pages fetched in one poll when more new messages arrived than the
+/// newest page holds.
+const CATCH_UP_PAGES: usize = 5;
+
+#[derive(Debug, Clone)]
+pub struct TailOptions {
+    /// One chat to follow; `None` follows every room with new unread messages.
+    pub chat_id: Option<i64>,
+    pub interval: Duration,
+    /// Shell command run per new message with the message text on stdin.
+    pub exec: Option<String>,
+    pub exec_timeout: Duration,
+    pub unattended: bool,
+    pub allow_side_effects: bool,
+    pub json: bool,
+    /// `--collapse-ignored`.
+    pub collapse_ignored: bool,
+}
+
+struct Tail<'a> {
+    client: &'a KakaoRestClient,
+    my_user_id: i64,
+    opts: &'a TailOptions,
+    marks: Watermarks,
+    unread: UnreadTracker,
+    ignored: IgnoreList,
+    hidden_runs: HiddenRuns,
+}
+
+/// `watch <chat_id>` / `watch --all-chats`: poll over REST and print new
+/// messages until Ctrl-C.
+pub fn cmd_watch_poll(opts: TailOptions) -> Result<()> {
+    if opts.exec.is_some() {
+        require_permission(
+            opts.unattended && opts.allow_side_effects,
+            "watch --exec",
+            "Re-run with --unattended --allow-watch-side-effects, or set both in ~/.config/openkakao/config.toml.",
+        )?;
+    }
+    let client = get_rest_client()?;
+    let mut credentials = watch_credentials(get_creds()?)?;
+    let my_user_id = credentials.current().user_id;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let rt = tokio::runtime::Runtime::new()?;
+    let flag = stop.clone();
+    rt.spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            flag.store(true, Ordering::Relaxed);
+        }
+    });
+
+    match opts.chat_id {
+        Some(chat_id) => eprintln!(
+            "[watch] Polling chat {} every {}s over REST (Ctrl-C to stop).",
+            chat_id,
+            opts.interval.as_secs()
+        ),
+        None => eprintln!(
+            "[watch] Polling the chat list every {}s over REST (Ctrl-C to stop).",
+            opts.interval.as_secs()
+        ),
+    }
+    crate::warnings::stream_immediately(opts.json);
+
+    let mut tail = Tail {
+        client: &client,
+        my_user_id,
+        opts: &opts,
+        marks: Watermarks::new(),
+        unread: UnreadTracker::new(),
+        ignored: IgnoreList::load()?,
+        hidden_runs: HiddenRuns::default(),
+    };
+    while !stop.load(Ordering::Relaxed) {
+        if let Some(Reload::Rotated { current, .. }) = reload_credentials(&mut credentials, "watch")
+        {
+            client.replace_credentials(*current);
+        }
+        let polled = match opts.chat_id {
+            Some(chat_id) => tail.poll_chat(chat_id),
+            None => tail.poll_all_chats(),
+        };
+        if let Err(err) = polled {
+            let err = OpenKakaoError::from(err);
+            if !err.is_retryable() {
+                return Err(err.into());
+            }
+            eprintln!(
+                "[watch] Poll failed: {}; retrying in {}s.",
+                err,
+                opts.interval.as_secs()
+            );
+        }
+        sleep_unless_stopped(opts.interval, &stop);
+    }
+    eprintln!("[watch] Stopped.");
+    Ok(())
+}
+
+/// Sleep `interval` in short steps so Ctrl-C ends the wait promptly.
+fn sleep_unless_stopped(interval: Duration, stop: &AtomicBool) {
+    let until = Instant::now() + interval;
+    while !stop.load(Ordering::Relaxed) {
+        let left = until.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            break;
+        }
+        std::thread::sleep(left.min(Duration::from_millis(100)));
+    }
+}
+
+impl Tail<'_> {
+    /// Print what arrived in `chat_id` since the last poll. The first poll
+    /// only sets the mark.
+    fn poll_chat(&mut self, chat_id: i64) -> Result<()> {
+        let page = self.newest_messages(chat_id)?;
+        let fresh = self.marks.take_new(chat_id, page, 0);
+        self.emit(chat_id, None, &fresh)
+    }
+
+    /// Print new messages of every room whose unread count rose since the
+    /// last poll.
+    fn poll_all_chats(&mut self) -> Result<()> {
+        let rooms = self.client.get_all_chats()?;
+        for room in &rooms {
+            if self.marks.get(room.chat_id).is_none() {
+                if let Some(last) = room.settings.last_log_id {
+                    self.marks.raise(room.chat_id, last);
+                }
+            }
+        }
+        let titles: HashMap<i64, &ChatRoom> = rooms.iter().map(|r| (r.chat_id, r)).collect();
+        for (chat_id, increase) in self.unread.grown(&rooms) {
+            let page = self.newest_messages(chat_id)?;
+            let first = usize::try_from(increase).unwrap_or(0);
+            let fresh = self.marks.take_new(chat_id, page, first);
+            let title = titles.get(&chat_id).map(|room| room.display_title());
+            self.emit(chat_id, title.as_deref(), &fresh)?;
+        }
+        Ok(())
+    }
+
+    /// The newest page of `chat_id`, plus older pages while it does not
+    /// reach the chat's mark.
+    fn newest_messages(&self, chat_id: i64) -> Result<Vec<ChatMessage>> {
+        let options = RequestOptions::polling();
+        let (mut messages, mut cursor) = self
+            .client
+            .get_messages_with_options(chat_id, None, options)?;
+        let mut pages = 1;
+        while cursor.is_some() && pages <= CATCH_UP_PAGES && self.marks.has_gap(chat_id, &messages)
+        {
+            let (older, next) = self
+                .client
+                .get_messages_with_options(chat_id, cursor, options)?;
+            if older.is_empty() {
+                break;
+            }
+            messages.extend(older);
+            cursor = next;
+            pages += 1;
+        }
+        Ok(messages)
+    }
+
+    /// Print `messages`, leaving out ignored authors; with
+    /// `--collapse-ignored` a run of them is counted until the next shown
+    /// message of the chat, even across polls.
+    fn emit(&mut self, chat_id: i64, title: Option<&str>, messages: &[ChatMessage]) -> Result<()> {
+        if messages.is_empty() {
+            return Ok(());
+        }
+        let names = name_resolver(self.my_user_id)
+            .member_names(chat_id, || Ok(self.client.get_chat_members(chat_id)?))
+            .cloned()
+            .unwrap_or_default();
+        let collapse = self.opts.collapse_ignored && !self.opts.json;
+        for msg in messages {
+            let name = msg.author_display_name(&names);
+            if self.ignored.ignores(chat_id, msg.author_id) {
+                if collapse {
+                    if let Some(line) = self.hidden_runs.hide(chat_id, msg.author_id, &name) {
+                        print_hidden(title, &line);
+                    }
+                }
+                continue;
+            }
+            if collapse {
+                if let Some(line) = self.hidden_runs.finish(chat_id) {
+                    print_hidden(title, &line);
+                }
+            }
+            let body = message_body(msg);
+            if self.opts.json {
+                let mut value = serde_json::to_value(msg)?;
+                value["chat_id"] = chat_id.into();
+                if let Some(title) = title {
+                    value["chat_title"] = title.into();
+                }
+                value["author_name"] = name.clone().into();
+                println!("{}", serde_json::to_string(&value)?);
+            } else {
+                print_line(title, msg, &name, &body);
+            }
+            std::io::stdout().flush()?;
+            if let Some(command) = &self.opts.exec {
+                if let Err(err) = run_exec(command, chat_id, title, msg, &name, &body, self.opts) {
+                    warn(WarningCode::HookFailed, format!("--exec failed: {err}"));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One message as `read` prints it, after `[chat title] ` with --all-chats.
+fn print_line(title: Option<&str>, msg: &ChatMessage, name: &str, body: &str) {
+    let room = title
+        .map(|t| format!("[{}] ", display_safe(t)))
+        .unwrap_or_default();
+    let time_str = format_time(msg.send_at);
+    let name = display_safe(name);
+    let body = display_safe(body);
+    if color_enabled() {
+        println!(
+            "{}{} [{}]: {}",
+            room.style(theme().muted),
+            time_str.style(theme().timestamp),
+            name.style(theme().author),
+            body
+        );
+    } else {
+        println!("{}{} [{}]: {}", room, time_str, name, body);
+    }
+}
+
+/// A `--collapse-ignored` line, after `[chat title] ` with --all-chats.
+fn print_hidden(title: Option<&str>, line: &str) {
+    match title {
+        Some(title) => print_hidden_line(&format!("[{}] {line}", display_safe(title))),
+        None => print_hidden_line(line),
+    }
+}
+
+/// Run `command` through `sh -c` with the message text on stdin and its
+/// details in the same `OPENKAKAO_*` variables `--hook-cmd` gets.
+fn run_exec(
+    command: &str,
+    chat_id: i64,
+    title: Option<&str>,
+    msg: &ChatMessage,
+    name: &str,
+    body: &str,
+    opts: &TailOptions,
+) -> Result<()> {
+    let mut child = Command::new("/bin/sh")
+        .arg("-c")
+        .arg(command)
+        .env("OPENKAKAO_EVENT_TYPE", "message")
+        .env("OPENKAKAO_CHAT_ID", chat_id.to_string())
+        .env("OPENKAKAO_CHAT_NAME", title.unwrap_or_default())
+        .env("OPENKAKAO_LOG_ID", msg.log_id.to_string())
+        .env("OPENKAKAO_AUTHOR_ID", msg.author_id.to_string())
+        .env("OPENKAKAO_AUTHOR_NICKNAME", name)
+        .env("OPENKAKAO_MESSAGE_TYPE", msg.message_type.to_string())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A command that ignores stdin closes the pipe early; that is fine.
+        let _ = stdin.write_all(body.as_bytes());
+    }
+    let deadline = Instant::now() + opts.exec_timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            if status.success() {
+                return Ok(());
+            }
+            anyhow::bail!(
+                "command exited with status {}",
+                status
+                    .code()
+                    .map(|c| c.to_string())
+                    .unwrap_or_else(|| "terminated by signal".to_string())
+            );
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            anyhow::bail!("command timed out after {}s", opts.exec_timeout.as_secs());
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,96 @@
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Result;
+
+use crate::chat_rules::{ChatRules, ChatSubject};
+use crate::model::ChatRoom;
+use crate::takeout::{self, Section, SectionState, TakeoutOptions};
+use crate::util::{display_safe, get_rest_client};
+
+/// Spacing between request starts during a takeout; one account's whole
+/// history is a lot of pages.
+const TAKEOUT_REQUEST_INTERVAL: Duration = Duration::from_millis(300);
+
+fn state_label(state: SectionState) -> &'static str {
+    match state {
+        SectionState::Done => "done",
+        SectionState::Failed => "failed",
+        SectionState::Unavailable => "unavailable",
+    }
+}
+
+/// `takeout <dir>`: everything the REST API hands over, as JSON files with
+/// an `index.json` manifest. Chats are picked by `--include`/`--exclude`,
+/// or by the `[sync]` rules when neither is given. Running it again on the
+/// same directory fetches only what is not done yet.
+pub fn cmd_takeout(
+    dir: &Path,
+    include: &[String],
+    exclude: &[String],
+    max_pages: usize,
+    json: bool,
+) -> Result<()> {
+    let rules = if include.is_empty() && exclude.is_empty() {
+        let config = crate::config::load_config()?;
+        ChatRules::parse(&config.sync.include, &config.sync.exclude)?
+    } else {
+        ChatRules::parse(include, exclude)?
+    };
+    crate::rest::set_request_interval(TAKEOUT_REQUEST_INTERVAL);
+    let client = get_rest_client()?;
+
+    let skip = |room: &ChatRoom| {
+        let decision = rules.decide(&ChatSubject::from_room(room));
+        (!decision.included).then(|| decision.reason())
+    };
+    let report = |key: &str, section: &Section| match (section.count, &section.note) {
+        (Some(count), _) => eprintln!("[takeout] {key}: {} ({count})", state_label(section.state)),
+        (None, Some(note)) => eprintln!("[takeout] {key}: {} ({note})", state_label(section.state)),
+        (None, None) => eprintln!("[takeout] {key}: {}", state_label(section.state)),
+    };
+    let manifest = takeout::run(&client, dir, TakeoutOptions { max_pages }, &skip, &report)?;
+
+    if json {
+        return crate::util::output_json(&manifest);
+    }
+    let entries = manifest.entries();
+    let count = |state: SectionState| {
+        entries
+            .iter()
+            .filter(|(_, section)| section.state == state)
+            .count()
+    };
+    println!(
+        "Takeout in {}: {} sections done, {} failed, {} unavailable, {} not tried",
+        dir.display(),
+        count(SectionState::Done),
+        count(SectionState::Failed),
+        count(SectionState::Unavailable),
+        manifest.pending()
+    );
+    println!(
+        "{} chats exported, {} left out by rules",
+        manifest.chats.len(),
+        manifest.skipped_chats.len()
+    );
+    for (chat_id, chat) in &manifest.chats {
+        let note = chat.messages.as_ref().and_then(|s| s.note.as_deref());
+        if let Some(note) = note {
+            println!("  {} ({chat_id}): {note}", display_safe(&chat.title));
+        }
+    }
+    if count(SectionState::Unavailable) > 0 {
+        println!(
+            "Pilsner turned some sections down for this token; run 'openkakao-rs auth' to check its scope."
+        );
+    }
+    match &manifest.stopped {
+        Some(reason) => println!("Stopped early: {reason}\nRun the same command again to resume."),
+        None if !manifest.is_complete() => {
+            println!("Some sections failed; run the same command again to retry them.")
+        }
+        None => {}
+    }
+    Ok(())
+}
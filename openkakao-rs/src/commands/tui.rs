@@ -0,0 +1,351 @@
+use std::collections::HashMap;
+use std::io::IsTerminal;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Direction, Layout as Split, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::commands::read::message_body;
+use crate::export::{export_messages, ExportChat, ExportFormat};
+use crate::model::ChatMessage;
+use crate::rest::{KakaoRestClient, RequestOptions};
+use crate::tui::{layout_for, App, Effect, Focus, Key, Layout, MIN_HEIGHT, MIN_WIDTH};
+use crate::util::{display_safe, format_time, get_creds, get_rest_client, name_resolver};
+use crate::wrap::{display_width, wrap_body};
+
+/// How often the chat list and the open chat are re-fetched.
+const POLL_EVERY: Duration = Duration::from_secs(10);
+
+/// Width of the chat list in the split layout.
+const CHAT_LIST_WIDTH: u16 = 34;
+
+pub struct TuiOptions {
+    /// False under `--read-only`.
+    pub allow_mark_read: bool,
+}
+
+/// Restores the screen however `cmd_tui` returns; `ratatui::try_init`
+/// installs a panic hook that does the same on panic.
+struct RestoreTerminal;
+
+impl Drop for RestoreTerminal {
+    fn drop(&mut self) {
+        ratatui::restore();
+    }
+}
+
+pub fn cmd_tui(opts: TuiOptions) -> Result<()> {
+    if crate::prompt::interactive_disabled()
+        || !std::io::stdin().is_terminal()
+        || !std::io::stdout().is_terminal()
+    {
+        anyhow::bail!("tui needs an interactive terminal. Use 'chats' and 'read' in scripts.");
+    }
+    let my_user_id = get_creds()?.user_id;
+    let client = get_rest_client()?;
+    let mut app = App::new(opts.allow_mark_read);
+
+    let mut terminal = ratatui::try_init()?;
+    let _restore = RestoreTerminal;
+    run(&mut terminal, &client, my_user_id, &mut app)
+}
+
+fn run(
+    terminal: &mut DefaultTerminal,
+    client: &KakaoRestClient,
+    my_user_id: i64,
+    app: &mut App,
+) -> Result<()> {
+    let mut pending = vec![Effect::LoadChats];
+    let mut last_poll = Instant::now();
+    loop {
+        for effect in std::mem::take(&mut pending) {
+            if effect == Effect::Quit {
+                return Ok(());
+            }
+            app.status = Some("Loading...".into());
+            terminal.draw(|frame| draw(frame, app))?;
+            app.status = None;
+            if let Err(err) = run_effect(client, my_user_id, app, &effect) {
+                app.load_failed(format!("{err:#}"));
+            }
+        }
+        terminal.draw(|frame| draw(frame, app))?;
+
+        let wait = POLL_EVERY.saturating_sub(last_poll.elapsed());
+        if event::poll(wait)? {
+            if let Event::Key(key) = event::read()? {
+                if let Some(key) = map_key(key) {
+                    pending = app.handle_key(key);
+                }
+            }
+        } else {
+            pending = app.poll();
+            last_poll = Instant::now();
+        }
+    }
+}
+
+fn map_key(key: KeyEvent) -> Option<Key> {
+    if key.kind != KeyEventKind::Press {
+        return None;
+    }
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        return (key.code == KeyCode::Char('c')).then_some(Key::Interrupt);
+    }
+    Some(match key.code {
+        KeyCode::Up => Key::Up,
+        KeyCode::Down => Key::Down,
+        KeyCode::PageUp => Key::PageUp,
+        KeyCode::PageDown => Key::PageDown,
+        KeyCode::Home => Key::Home,
+        KeyCode::End => Key::End,
+        KeyCode::Left => Key::Left,
+        KeyCode::Right => Key::Right,
+        KeyCode::Tab | KeyCode::BackTab => Key::Tab,
+        KeyCode::Enter => Key::Enter,
+        KeyCode::Esc => Key::Esc,
+        KeyCode::Backspace => Key::Backspace,
+        KeyCode::Char(c) => Key::Char(c),
+        _ => return None,
+    })
+}
+
+fn run_effect(
+    client: &KakaoRestClient,
+    my_user_id: i64,
+    app: &mut App,
+    effect: &Effect,
+) -> Result<()> {
+    match *effect {
+        Effect::LoadChats => app.chats_loaded(client.get_all_chats()?),
+        Effect::LoadMessages { chat_id } => {
+            let (page, next_cursor) =
+                client.get_messages_with_options(chat_id, None, RequestOptions::polling())?;
+            let names = member_names(client, my_user_id, chat_id);
+            app.messages_loaded(chat_id, page, next_cursor, false, names);
+        }
+        Effect::LoadOlder { chat_id, cursor } => {
+            let (page, next_cursor) = client.get_messages(chat_id, Some(cursor))?;
+            app.messages_loaded(chat_id, page, next_cursor, true, HashMap::new());
+        }
+        Effect::MarkRead { chat_id, log_id } => {
+            crate::commands::send::mark_read(chat_id, log_id)?;
+            app.marked_read(chat_id);
+            app.status = Some(format!("Marked read up to {log_id}."));
+        }
+        Effect::OpenInApp { chat_id } => {
+            app.status = Some(open_in_app(chat_id)?);
+        }
+        Effect::Export { chat_id } => {
+            let messages: Vec<ChatMessage> = match &app.open {
+                Some(open) if open.chat_id == chat_id => open.messages.clone(),
+                _ => {
+                    let (mut page, _) = client.get_messages(chat_id, None)?;
+                    page.sort_by_key(|m| m.log_id);
+                    page
+                }
+            };
+            let members = client.get_chat_members(chat_id).unwrap_or_default();
+            let path = format!(
+                "openkakao-{}-{}.txt",
+                chat_id,
+                chrono::Local::now().format("%Y%m%d-%H%M%S")
+            );
+            let chat = ExportChat {
+                chat_id,
+                ..Default::default()
+            };
+            export_messages(
+                &messages,
+                &members,
+                my_user_id,
+                &ExportFormat::Txt,
+                &chat,
+                Some(&path),
+            )?;
+            app.status = Some(format!("Exported {} messages to {}", messages.len(), path));
+        }
+        Effect::Quit => {}
+    }
+    Ok(())
+}
+
+fn member_names(client: &KakaoRestClient, my_user_id: i64, chat_id: i64) -> HashMap<i64, String> {
+    match name_resolver(my_user_id).member_names(chat_id, || Ok(client.get_chat_members(chat_id)?))
+    {
+        Ok(names) => names.clone(),
+        Err(_) => HashMap::from([(my_user_id, "Me".to_string())]),
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn open_in_app(_chat_id: i64) -> Result<String> {
+    // KakaoTalk has no URL scheme for a specific chat; bring the app up.
+    let status = std::process::Command::new("open")
+        .args(["-a", "KakaoTalk"])
+        .status()?;
+    if !status.success() {
+        anyhow::bail!("Could not open KakaoTalk ({status}).");
+    }
+    Ok("Opened KakaoTalk.".into())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn open_in_app(_chat_id: i64) -> Result<String> {
+    Ok("Opening the KakaoTalk app is only supported on macOS.".into())
+}
+
+fn draw(frame: &mut Frame, app: &App) {
+    let area = frame.area();
+    let layout = layout_for(area.width, area.height);
+    if layout == Layout::TooSmall {
+        frame.render_widget(
+            Paragraph::new(format!(
+                "Terminal too small (need {MIN_WIDTH}x{MIN_HEIGHT})."
+            )),
+            area,
+        );
+        return;
+    }
+
+    let rows = Split::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(1)])
+        .split(area);
+    match layout {
+        Layout::Split => {
+            let panes = Split::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Length(CHAT_LIST_WIDTH), Constraint::Min(1)])
+                .split(rows[0]);
+            draw_chats(frame, app, panes[0]);
+            draw_messages(frame, app, panes[1]);
+        }
+        _ => match app.focus {
+            Focus::Chats => draw_chats(frame, app, rows[0]),
+            Focus::Messages => draw_messages(frame, app, rows[0]),
+        },
+    }
+    frame.render_widget(Paragraph::new(status_line(app)), rows[1]);
+}
+
+fn status_line(app: &App) -> Line<'static> {
+    if app.searching {
+        return Line::from(format!("/{}", app.search));
+    }
+    if let Some(status) = &app.status {
+        return Line::from(status.clone());
+    }
+    let help = match app.focus {
+        Focus::Chats => "enter open  / search  r refresh  m read  o app  e export  q quit",
+        Focus::Messages => "↑↓ scroll  esc back  r refresh  m read  o app  e export  q quit",
+    };
+    Line::styled(help, Style::default().fg(Color::DarkGray))
+}
+
+fn pane_block(title: String, focused: bool) -> Block<'static> {
+    let style = if focused {
+        Style::default().fg(Color::Yellow)
+    } else {
+        Style::default()
+    };
+    Block::default()
+        .borders(Borders::ALL)
+        .border_style(style)
+        .title(title)
+}
+
+fn draw_chats(frame: &mut Frame, app: &App, area: Rect) {
+    let items: Vec<ListItem> = app
+        .visible_chats()
+        .into_iter()
+        .map(|room| {
+            let mut spans = vec![Span::raw(display_safe(&room.display_title()).into_owned())];
+            if room.unread_count > 0 {
+                spans.push(Span::raw(" "));
+                spans.push(Span::styled(
+                    format!(" {} ", room.unread_count),
+                    Style::default().fg(Color::Black).bg(Color::Yellow),
+                ));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let title = if app.search.is_empty() {
+        "Chats".to_string()
+    } else {
+        format!("Chats /{}", app.search)
+    };
+    let list = List::new(items)
+        .block(pane_block(title, app.focus == Focus::Chats))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default().with_selected(Some(app.selected));
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+fn draw_messages(frame: &mut Frame, app: &App, area: Rect) {
+    let focused = app.focus == Focus::Messages;
+    let Some(open) = &app.open else {
+        let block = pane_block("Messages".into(), focused);
+        frame.render_widget(Paragraph::new("Select a chat.").block(block), area);
+        return;
+    };
+    let title = app
+        .chats
+        .iter()
+        .find(|room| room.chat_id == open.chat_id)
+        .map(|room| display_safe(&room.display_title()).into_owned())
+        .unwrap_or_else(|| open.chat_id.to_string());
+    let block = pane_block(title, focused);
+    let inner = block.inner(area);
+    let width = inner.width as usize;
+    let height = inner.height as usize;
+
+    // Render from the newest visible message upwards until the pane is full.
+    let end = open.messages.len().saturating_sub(open.scroll);
+    let mut lines: Vec<Line> = Vec::new();
+    for msg in open.messages[..end].iter().rev() {
+        if lines.len() >= height {
+            break;
+        }
+        lines.splice(0..0, message_lines(msg, &open.names, width));
+    }
+    if open.loading_older && lines.len() < height {
+        lines.insert(
+            0,
+            Line::styled("Loading older...", Style::default().fg(Color::DarkGray)),
+        );
+    }
+    let skip = lines.len().saturating_sub(height);
+    let lines: Vec<Line> = lines.into_iter().skip(skip).collect();
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+/// One message as `read` prints it, wrapped to the pane.
+fn message_lines(
+    msg: &ChatMessage,
+    names: &HashMap<i64, String>,
+    width: usize,
+) -> Vec<Line<'static>> {
+    let time = format_time(msg.send_at);
+    let name = display_safe(&msg.author_display_name(names)).into_owned();
+    let prefix_width = display_width(&format!("{} [{}]: ", time, name));
+    let body = wrap_body(&display_safe(&message_body(msg)), prefix_width, width);
+    let mut body_lines = body.split('\n');
+    let first = vec![
+        Span::styled(time, Style::default().fg(Color::DarkGray)),
+        Span::raw(" ["),
+        Span::styled(name, Style::default().fg(Color::Cyan)),
+        Span::raw("]: "),
+        Span::raw(body_lines.next().unwrap_or_default().to_string()),
+    ];
+    std::iter::once(Line::from(first))
+        .chain(body_lines.map(|line| Line::from(line.to_string())))
+        .collect()
+}
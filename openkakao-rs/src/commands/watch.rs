@@ -1,5 +1,7 @@
 use std::collections::HashMap;
+use std::io::IsTerminal;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::Result;
@@ -9,17 +11,31 @@ use rand::Rng;
 use serde_json::Value;
 use sha2::Sha256;
 
+use crate::auth_flow::{reload_credentials, watch_credentials};
+use crate::chat_rules::{ChatRules, ChatSubject};
+use crate::credential_watch::{CredentialWatch, Reload};
+use crate::dedup::DedupWindow;
 use crate::error::OpenKakaoError;
+use crate::ignore::{HiddenRuns, IgnoreList};
 use crate::loco_helpers::loco_connect_with_auto_refresh;
 use crate::media::{download_media_file, parse_attachment_url, sanitize_filename};
+use crate::model::KakaoCredentials;
+use crate::notify::{
+    DesktopNotifier, MacosNotifier, Notification, Notifier, NotifyBackend, StdoutNotifier,
+    TelegramNotifier,
+};
+use crate::rest::KakaoRestClient;
 use crate::state::{
     auth_cooldown_remaining_secs, hook_remaining_secs, mark_hook_attempt, mark_webhook_attempt,
     record_failure, record_guard, record_transport_success, webhook_remaining_secs,
 };
+use crate::theme::theme;
 use crate::util::{
-    color_enabled, get_bson_i64, get_bson_str_array, message_type_label, render_message_content,
-    require_permission,
+    color_enabled, display_safe, extract_chat_type, get_bson_i64, get_bson_str_array,
+    get_bson_timestamp, message_type_label, render_message_content, require_permission,
 };
+use crate::warnings::{warn, WarningCode};
+use crate::watch_status::WatchStatus;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum WebhookFormat {
@@ -64,6 +80,8 @@ pub struct WatchOptions {
     pub unattended: bool,
     pub allow_side_effects: bool,
     pub filter_chat_id: Option<i64>,
+    /// `--include` / `--exclude` rules on top of `filter_chat_id`.
+    pub scope: ChatRules,
     pub raw: bool,
     pub read_receipt: bool,
     pub max_reconnect: u32,
@@ -85,9 +103,18 @@ pub struct WatchOptions {
     pub webhook_timeout_secs: u64,
     pub allow_insecure_webhooks: bool,
     pub webhook_format: WebhookFormat,
+    pub notify_backends: Vec<NotifyBackend>,
+    pub notify_config: crate::config::NotifyConfig,
     pub resume: bool,
     pub json: bool,
     pub capture: bool,
+    pub bell: bool,
+    pub title: bool,
+    /// Log ids remembered per chat to drop repeats; 0 disables.
+    pub dedup_window: usize,
+    /// `--collapse-ignored`: a line per run of messages from ignored
+    /// authors instead of nothing.
+    pub collapse_ignored: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -118,6 +145,10 @@ impl WatchMessageEvent {
             "author_nickname": self.author_nickname,
             "message_type": self.message_type,
             "message": self.message,
+            "urls": crate::urlnorm::extract_urls(
+                &self.message,
+                crate::urlnorm::strip_tracking()
+            ),
             "attachment": self.attachment,
         })
     }
@@ -378,12 +409,8 @@ fn reconnect_delay(attempt: u32, initial_secs: u64, max_secs: u64) -> Duration {
     Duration::from_secs(base_secs + jitter)
 }
 
-fn watch_state_path() -> Result<PathBuf> {
-    let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("cannot resolve home directory"))?;
-    Ok(home
-        .join(".config")
-        .join("openkakao")
-        .join("watch_state.json"))
+pub fn watch_state_path() -> Result<PathBuf> {
+    Ok(crate::paths::profile_dir()?.join("watch_state.json"))
 }
 
 fn save_watch_state(last_log_ids: &HashMap<i64, i64>) -> Result<()> {
@@ -408,10 +435,101 @@ fn load_watch_state() -> Result<HashMap<i64, i64>> {
 
 struct WatchContext<'a> {
     chat_names: &'a HashMap<i64, String>,
+    chat_subjects: &'a HashMap<i64, ChatSubject>,
     options: &'a WatchOptions,
     hook_config: &'a Option<WatchHookConfig>,
     last_log_ids: &'a mut HashMap<i64, i64>,
     message_db: Option<&'a crate::message_db::MessageDb>,
+    notifiers: &'a [Arc<dyn Notifier>],
+    status: Option<&'a mut WatchStatus>,
+    dedup: &'a mut DedupWindow,
+    ignored: &'a IgnoreList,
+    hidden_runs: &'a mut HiddenRuns,
+    /// Shared by every `--download-media` download; set with that flag.
+    media_client: Option<&'a Arc<KakaoRestClient>>,
+}
+
+impl WatchContext<'_> {
+    /// Whether `--chat-id` and `--include/--exclude` let this chat through.
+    /// Chats missing from the login chat list match only id rules.
+    fn in_scope(&self, chat_id: i64) -> bool {
+        if self
+            .options
+            .filter_chat_id
+            .is_some_and(|filter| filter != chat_id)
+        {
+            return false;
+        }
+        if self.options.scope.is_empty() {
+            return true;
+        }
+        match self.chat_subjects.get(&chat_id) {
+            Some(chat) => self.options.scope.includes(chat),
+            None => self.options.scope.includes(&ChatSubject {
+                chat_id,
+                ..ChatSubject::default()
+            }),
+        }
+    }
+}
+
+/// Instantiate the `--notify` backends, checking their config up front.
+fn build_notifiers(
+    backends: &[NotifyBackend],
+    config: &crate::config::NotifyConfig,
+) -> Result<Vec<Arc<dyn Notifier>>> {
+    backends
+        .iter()
+        .map(|backend| -> Result<Arc<dyn Notifier>> {
+            Ok(match backend {
+                NotifyBackend::Macos => Arc::new(MacosNotifier),
+                NotifyBackend::Desktop => Arc::new(DesktopNotifier),
+                NotifyBackend::Stdout => Arc::new(StdoutNotifier::new()),
+                NotifyBackend::Telegram => {
+                    match (&config.telegram_bot_token, &config.telegram_chat_id) {
+                        (Some(token), Some(chat_id)) => {
+                            Arc::new(TelegramNotifier::new(token.as_str(), chat_id.as_str()))
+                        }
+                        _ => anyhow::bail!(
+                            "--notify telegram needs [notify] telegram_bot_token and telegram_chat_id in config.toml"
+                        ),
+                    }
+                }
+            })
+        })
+        .collect()
+}
+
+/// Hand a message to every `--notify` backend. Failures become warnings;
+/// the watch keeps going.
+async fn notify_all(notifiers: &[Arc<dyn Notifier>], event: &WatchMessageEvent) {
+    if notifiers.is_empty() {
+        return;
+    }
+    let notification = Notification::new(
+        event.chat_id,
+        event.log_id,
+        &event.chat_name,
+        &event.author_nickname,
+        &event.message,
+    );
+    for notifier in notifiers {
+        let result = tokio::task::spawn_blocking({
+            let notifier = notifier.clone();
+            let notification = notification.clone();
+            move || notifier.notify(&notification)
+        })
+        .await;
+        let error = match result {
+            Ok(Ok(())) => continue,
+            Ok(Err(e)) => e,
+            Err(e) => anyhow::anyhow!("notify task join error: {}", e),
+        };
+        warn(
+            WarningCode::HookFailed,
+            format!("Notify ({}) failed: {error}", notifier.name()),
+        );
+    }
 }
 
 async fn handle_msg_packet(
@@ -425,10 +543,8 @@ async fn handle_msg_packet(
         .or_else(|_| packet.body.get_i32("chatId").map(|v| v as i64))
         .unwrap_or(0);
 
-    if let Some(filter) = ctx.options.filter_chat_id {
-        if chat_id != filter {
-            return Ok(());
-        }
+    if !ctx.in_scope(chat_id) {
+        return Ok(());
     }
 
     let chat_label = ctx
@@ -463,6 +579,10 @@ async fn handle_msg_packet(
         .get_i64("authorId")
         .or_else(|_| packet.body.get_i32("authorId").map(|v| v as i64))
         .unwrap_or(0);
+    // Reconnects can replay messages that were already printed.
+    if log_id > 0 && !ctx.dedup.first_seen(chat_id, log_id) {
+        return Ok(());
+    }
     let attachment = packet.body.get_str("attachment").unwrap_or("").to_string();
     let event = WatchMessageEvent {
         event_type: "message",
@@ -478,19 +598,71 @@ async fn handle_msg_packet(
         attachment: attachment.clone(),
     };
 
-    if ctx.options.json {
+    let from_me = author_id == client.credentials.user_id;
+    // Ignored authors are still cached and still move the resume point, but
+    // reach neither the screen nor hooks, webhooks and notifiers.
+    let hidden = ctx.ignored.ignores(chat_id, author_id);
+    let collapse = ctx.options.collapse_ignored && !ctx.options.json;
+    if let (false, Some(status)) = (hidden, ctx.status.as_deref_mut()) {
+        if from_me {
+            status.mark_read(chat_id);
+        } else {
+            let now = chrono::Local::now().format("%H:%M:%S").to_string();
+            status.incoming(chat_id, &chat_label, &now);
+        }
+    }
+    let ended_run = match (hidden, collapse) {
+        (true, true) => {
+            let name = match ctx.ignored.name_of(author_id) {
+                Some(name) if nick == "???" => name,
+                _ => nick.as_str(),
+            };
+            ctx.hidden_runs.hide(chat_id, author_id, name)
+        }
+        (false, true) => ctx.hidden_runs.finish(chat_id),
+        (_, false) => None,
+    };
+    if let Some(line) = ended_run {
+        let now = chrono::Local::now().format("%H:%M:%S");
+        let line = format!(
+            "[{}] [{}] {}",
+            now,
+            display_safe(&chat_label),
+            display_safe(&line)
+        );
+        if color_enabled() {
+            println!("{}", line.style(theme().muted));
+        } else {
+            println!("{line}");
+        }
+    }
+
+    if hidden {
+        // Counted into the run above, or dropped.
+    } else if ctx.options.json {
         println!(
             "{}",
             serde_json::to_string(&event.as_json()).unwrap_or_default()
         );
+    } else if ctx
+        .status
+        .as_deref()
+        .is_some_and(WatchStatus::replaces_lines)
+    {
+        // The status line redrawn after this packet stands in for the line.
     } else {
         let now = chrono::Local::now().format("%H:%M:%S");
+        let (chat_label, nick, content) = (
+            display_safe(&chat_label),
+            display_safe(&nick),
+            display_safe(&content),
+        );
         if color_enabled() {
             println!(
                 "{} {} {}: {}",
-                format!("[{}]", now).dimmed(),
-                format!("[{}]", chat_label).cyan(),
-                nick.bold(),
+                format!("[{}]", now).style(theme().timestamp),
+                format!("[{}]", chat_label).style(theme().chat),
+                nick.style(theme().author),
                 content
             );
         } else {
@@ -505,11 +677,7 @@ async fn handle_msg_packet(
     // Cache message to local SQLite DB
     if let Some(db) = &ctx.message_db {
         if log_id > 0 {
-            let send_at = packet
-                .body
-                .get_i64("sendAt")
-                .or_else(|_| packet.body.get_i32("sendAt").map(|v| v as i64))
-                .unwrap_or(0);
+            let send_at = get_bson_timestamp(&packet.body, &["sendAt"]);
             let cached = crate::message_db::CachedMessage {
                 chat_id,
                 log_id,
@@ -521,18 +689,21 @@ async fn handle_msg_packet(
                 send_at,
             };
             if let Err(e) = db.upsert_messages(std::slice::from_ref(&cached)) {
-                eprintln!("[watch] Cache write failed: {}", e);
+                warn(
+                    WarningCode::CacheUnavailable,
+                    format!("Cache write failed: {e}"),
+                );
             }
         }
     }
 
-    if let Some(config) = ctx.hook_config {
+    if let (false, Some(config)) = (hidden, ctx.hook_config) {
         if watch_hook_matches(config, &event) {
             if config.command.is_some() {
                 match run_watch_command_hook_async(config, &event).await {
                     Ok(()) => {}
                     Err(e) => {
-                        eprintln!("[watch] Hook failed: {}", e);
+                        warn(WarningCode::HookFailed, format!("Hook failed: {e}"));
                         if config.fail_fast {
                             return Err(e);
                         }
@@ -549,14 +720,14 @@ async fn handle_msg_packet(
                 {
                     Ok(Ok(())) => {}
                     Ok(Err(e)) => {
-                        eprintln!("[watch] Webhook failed: {}", e);
+                        warn(WarningCode::HookFailed, format!("Webhook failed: {e}"));
                         if config.fail_fast {
                             return Err(e);
                         }
                     }
                     Err(e) => {
                         let err = anyhow::anyhow!("webhook task join error: {}", e);
-                        eprintln!("[watch] Webhook failed: {}", err);
+                        warn(WarningCode::HookFailed, format!("Webhook failed: {err}"));
                         if config.fail_fast {
                             return Err(err);
                         }
@@ -566,7 +737,14 @@ async fn handle_msg_packet(
         }
     }
 
+    if !hidden {
+        notify_all(ctx.notifiers, &event).await;
+    }
+
     if ctx.options.read_receipt && log_id > 0 {
+        if let Some(status) = ctx.status.as_deref_mut() {
+            status.mark_read(chat_id);
+        }
         let _ = client
             .send_packet(
                 "NOTIREAD",
@@ -578,18 +756,19 @@ async fn handle_msg_packet(
             .await;
     }
 
-    if ctx.options.download_media
-        && matches!(msg_type, 2 | 3 | 12 | 14 | 26 | 27)
-        && !attachment.is_empty()
-    {
-        let dl_creds = client.credentials.clone();
+    if let Some(media_client) = ctx.media_client.filter(|_| {
+        !hidden && matches!(msg_type, 2 | 3 | 12 | 14 | 26 | 27) && !attachment.is_empty()
+    }) {
+        // Follow the token the LOCO session is running on.
+        media_client.replace_credentials(client.credentials.clone());
+        let media_client = Arc::clone(media_client);
         let dl_dir = ctx.options.download_dir.clone();
         tokio::task::spawn_blocking(move || {
             if let Some((url, filename)) = parse_attachment_url(&attachment, msg_type) {
                 let dir = Path::new(&dl_dir).join(chat_id.to_string());
                 let save_name = format!("{}_{}", log_id, sanitize_filename(&filename));
                 let save_path = dir.join(&save_name);
-                match download_media_file(&dl_creds, &url, &save_path) {
+                match download_media_file(&media_client, &url, &save_path) {
                     Ok(bytes) => {
                         eprintln!(
                             "[watch] Downloaded {} ({} bytes)",
@@ -598,7 +777,10 @@ async fn handle_msg_packet(
                         );
                     }
                     Err(e) => {
-                        eprintln!("[watch] Download failed for {}: {}", save_name, e);
+                        warn(
+                            WarningCode::DownloadFailed,
+                            format!("Download failed for {save_name}: {e}"),
+                        );
                     }
                 }
             }
@@ -613,10 +795,8 @@ async fn handle_syncmsg_packet(
     ctx: &mut WatchContext<'_>,
 ) -> Result<()> {
     let chat_id = get_bson_i64(&packet.body, &["chatId"]);
-    if let Some(filter) = ctx.options.filter_chat_id {
-        if chat_id != filter {
-            return Ok(());
-        }
+    if !ctx.in_scope(chat_id) {
+        return Ok(());
     }
     let chat_label = ctx
         .chat_names
@@ -624,6 +804,9 @@ async fn handle_syncmsg_packet(
         .cloned()
         .unwrap_or_else(|| format!("{}", chat_id));
     let log_id = get_bson_i64(&packet.body, &["logId"]);
+    if log_id > 0 && !ctx.dedup.first_seen(chat_id, log_id) {
+        return Ok(());
+    }
     let msg_type = packet.body.get_i32("type").unwrap_or(0);
     let content = render_message_content(&packet.body, msg_type);
     let nick = packet
@@ -631,6 +814,10 @@ async fn handle_syncmsg_packet(
         .get_str("authorNickname")
         .map(String::from)
         .unwrap_or_else(|_| "???".to_string());
+    // You wrote from another device, so you have seen this chat.
+    if let Some(status) = ctx.status.as_deref_mut() {
+        status.mark_read(chat_id);
+    }
 
     if ctx.options.json {
         let sync_event = serde_json::json!({
@@ -647,13 +834,18 @@ async fn handle_syncmsg_packet(
         println!("{}", serde_json::to_string(&sync_event).unwrap_or_default());
     } else {
         let now = chrono::Local::now().format("%H:%M:%S");
+        let (chat_label, nick, content) = (
+            display_safe(&chat_label),
+            display_safe(&nick),
+            display_safe(&content),
+        );
         if color_enabled() {
             println!(
                 "{} {} {} {}: {}",
-                format!("[{}]", now).dimmed(),
-                "[sync]".dimmed(),
-                format!("[{}]", chat_label).cyan(),
-                nick.bold(),
+                format!("[{}]", now).style(theme().timestamp),
+                "[sync]".style(theme().muted),
+                format!("[{}]", chat_label).style(theme().chat),
+                nick.style(theme().author),
                 content
             );
         } else {
@@ -669,7 +861,7 @@ async fn handle_syncmsg_packet(
     if let Some(db) = &ctx.message_db {
         if log_id > 0 {
             let author_id = get_bson_i64(&packet.body, &["authorId"]);
-            let send_at = get_bson_i64(&packet.body, &["sendAt"]);
+            let send_at = get_bson_timestamp(&packet.body, &["sendAt"]);
             let attachment = packet.body.get_str("attachment").unwrap_or("").to_string();
             let cached = crate::message_db::CachedMessage {
                 chat_id,
@@ -682,7 +874,10 @@ async fn handle_syncmsg_packet(
                 send_at,
             };
             if let Err(e) = db.upsert_messages(std::slice::from_ref(&cached)) {
-                eprintln!("[watch] Cache write failed: {}", e);
+                warn(
+                    WarningCode::CacheUnavailable,
+                    format!("Cache write failed: {e}"),
+                );
             }
         }
     }
@@ -695,10 +890,8 @@ async fn handle_syncdlmsg_packet(
     ctx: &mut WatchContext<'_>,
 ) -> Result<()> {
     let chat_id = get_bson_i64(&packet.body, &["chatId"]);
-    if let Some(filter) = ctx.options.filter_chat_id {
-        if chat_id != filter {
-            return Ok(());
-        }
+    if !ctx.in_scope(chat_id) {
+        return Ok(());
     }
     let chat_label = ctx
         .chat_names
@@ -738,9 +931,9 @@ async fn handle_syncdlmsg_packet(
         if color_enabled() {
             println!(
                 "{} {} Chat {}: message {} deleted",
-                format!("[{}]", now).dimmed(),
-                "[deleted]".dimmed(),
-                chat_label.cyan(),
+                format!("[{}]", now).style(theme().timestamp),
+                "[deleted]".style(theme().muted),
+                chat_label.style(theme().chat),
                 log_id
             );
         } else {
@@ -757,7 +950,7 @@ async fn handle_syncdlmsg_packet(
                 match run_watch_command_hook_async(config, &event).await {
                     Ok(()) => {}
                     Err(e) => {
-                        eprintln!("[watch] Hook failed: {}", e);
+                        warn(WarningCode::HookFailed, format!("Hook failed: {e}"));
                         if config.fail_fast {
                             return Err(e);
                         }
@@ -774,14 +967,14 @@ async fn handle_syncdlmsg_packet(
                 {
                     Ok(Ok(())) => {}
                     Ok(Err(e)) => {
-                        eprintln!("[watch] Webhook failed: {}", e);
+                        warn(WarningCode::HookFailed, format!("Webhook failed: {e}"));
                         if config.fail_fast {
                             return Err(e);
                         }
                     }
                     Err(e) => {
                         let err = anyhow::anyhow!("webhook task join error: {}", e);
-                        eprintln!("[watch] Webhook failed: {}", err);
+                        warn(WarningCode::HookFailed, format!("Webhook failed: {err}"));
                         if config.fail_fast {
                             return Err(err);
                         }
@@ -799,10 +992,8 @@ async fn handle_syncaction_packet(
     ctx: &mut WatchContext<'_>,
 ) -> Result<()> {
     let chat_id = get_bson_i64(&packet.body, &["chatId"]);
-    if let Some(filter) = ctx.options.filter_chat_id {
-        if chat_id != filter {
-            return Ok(());
-        }
+    if !ctx.in_scope(chat_id) {
+        return Ok(());
     }
     let chat_label = ctx
         .chat_names
@@ -832,9 +1023,9 @@ async fn handle_syncaction_packet(
         if color_enabled() {
             println!(
                 "{} {} Chat {}: user {} reacted (type={}) to message {}",
-                format!("[{}]", now).dimmed(),
-                "[reaction]".dimmed(),
-                chat_label.cyan(),
+                format!("[{}]", now).style(theme().timestamp),
+                "[reaction]".style(theme().muted),
+                chat_label.style(theme().chat),
                 user_id,
                 action_type,
                 log_id
@@ -855,10 +1046,8 @@ async fn handle_syncrewr_packet(
     ctx: &mut WatchContext<'_>,
 ) -> Result<()> {
     let chat_id = get_bson_i64(&packet.body, &["chatId"]);
-    if let Some(filter) = ctx.options.filter_chat_id {
-        if chat_id != filter {
-            return Ok(());
-        }
+    if !ctx.in_scope(chat_id) {
+        return Ok(());
     }
     let chat_label = ctx
         .chat_names
@@ -881,9 +1070,9 @@ async fn handle_syncrewr_packet(
         if color_enabled() {
             println!(
                 "{} {} Chat {}: message {} edited",
-                format!("[{}]", now).dimmed(),
-                "[edited]".dimmed(),
-                chat_label.cyan(),
+                format!("[{}]", now).style(theme().timestamp),
+                "[edited]".style(theme().muted),
+                chat_label.style(theme().chat),
                 log_id
             );
         } else {
@@ -918,9 +1107,9 @@ fn handle_unknown_push_packet(packet: &crate::loco::packet::LocoPacket, options:
         if color_enabled() {
             println!(
                 "{} {} {} (status={}) body: {}",
-                format!("[{}]", now).dimmed(),
-                "[capture]".dimmed(),
-                packet.method.bold(),
+                format!("[{}]", now).style(theme().timestamp),
+                "[capture]".style(theme().muted),
+                packet.method.style(theme().author),
                 packet.status(),
                 body_json
             );
@@ -936,11 +1125,34 @@ fn handle_unknown_push_packet(packet: &crate::loco::packet::LocoPacket, options:
     }
 }
 
+/// New credentials saved by another run, verified, if there are any.
+/// Verifying is a blocking REST request, so it runs off the runtime.
+async fn rotated_credentials(
+    watch: &Arc<std::sync::Mutex<CredentialWatch>>,
+) -> Option<KakaoCredentials> {
+    let watch = Arc::clone(watch);
+    let reload = tokio::task::spawn_blocking(move || {
+        let mut watch = watch.lock().unwrap_or_else(|e| e.into_inner());
+        reload_credentials(&mut watch, "watch")
+    })
+    .await
+    .ok()??;
+    match reload {
+        Reload::Rotated { current, .. } => Some(*current),
+        _ => None,
+    }
+}
+
 pub fn cmd_watch(options: WatchOptions) -> Result<()> {
-    if options.read_receipt || options.hook_cmd.is_some() || options.webhook_url.is_some() {
+    let sends_off_machine = options.notify_backends.contains(&NotifyBackend::Telegram);
+    if options.read_receipt
+        || options.hook_cmd.is_some()
+        || options.webhook_url.is_some()
+        || sends_off_machine
+    {
         require_permission(
             options.unattended && options.allow_side_effects,
-            "watch side effects (read receipts, hooks, or webhooks)",
+            "watch side effects (read receipts, hooks, webhooks, or telegram notifications)",
             "Re-run with --unattended --allow-watch-side-effects, or set both in ~/.config/openkakao/config.toml.",
         )?;
     }
@@ -949,7 +1161,20 @@ pub fn cmd_watch(options: WatchOptions) -> Result<()> {
         validate_webhook_url(webhook_url, options.allow_insecure_webhooks)?;
     }
 
+    let notifiers = build_notifiers(&options.notify_backends, &options.notify_config)?;
+    let mut status = if (options.bell || options.title) && !options.json && !options.raw {
+        if std::io::stdout().is_terminal() {
+            Some(WatchStatus::new(options.bell, options.title))
+        } else {
+            eprintln!("[watch] stdout is not a terminal; --bell/--title fall back to line output.");
+            None
+        }
+    } else {
+        None
+    };
+
     let creds = crate::util::get_creds()?;
+    let credentials = Arc::new(std::sync::Mutex::new(watch_credentials(creds.clone())?));
     let parsed_webhook_headers = options
         .webhook_headers
         .iter()
@@ -991,21 +1216,30 @@ pub fn cmd_watch(options: WatchOptions) -> Result<()> {
         HashMap::new()
     };
 
+    let ignored = IgnoreList::load()?;
+    crate::warnings::stream_immediately(options.json);
+    // Built outside the runtime: the blocking REST client must not be
+    // dropped inside it.
+    let media_client = if options.download_media {
+        Some(Arc::new(KakaoRestClient::new(creds.clone())?))
+    } else {
+        None
+    };
     let rt = tokio::runtime::Runtime::new()?;
     rt.block_on(async {
         let mut client = crate::loco::client::LocoClient::new(creds);
         let mut reconnect_count: u32 = 0;
+        // Kept across reconnects, which is when repeats show up.
+        let mut dedup = DedupWindow::new(options.dedup_window);
+        let mut hidden_runs = HiddenRuns::default();
 
         // Open local message cache for persisting watched messages
-        let watch_message_db = match crate::message_db::MessageDb::open() {
-            Ok(db) => Some(db),
-            Err(e) => {
-                eprintln!("[watch] Warning: could not open message cache: {}", e);
-                None
-            }
-        };
+        let watch_message_db = crate::util::message_cache();
 
         'reconnect: loop {
+            if let Some(current) = rotated_credentials(&credentials).await {
+                client.credentials = current;
+            }
             let login_data = match loco_connect_with_auto_refresh(&mut client).await {
                 Ok(data) => data,
                 Err(e) => {
@@ -1053,6 +1287,7 @@ pub fn cmd_watch(options: WatchOptions) -> Result<()> {
 
             // Build chat_id → name map from LOGINLIST chatDatas
             let mut chat_names: HashMap<i64, String> = HashMap::new();
+            let mut chat_subjects: HashMap<i64, ChatSubject> = HashMap::new();
             if let Ok(chat_datas) = login_data.get_array("chatDatas") {
                 for cd in chat_datas {
                     if let Some(doc) = cd.as_document() {
@@ -1069,6 +1304,16 @@ pub fn cmd_watch(options: WatchOptions) -> Result<()> {
                             } else {
                                 name
                             };
+                            let link_id = get_bson_i64(doc, &["li"]);
+                            chat_subjects.insert(
+                                cid,
+                                ChatSubject {
+                                    chat_id: cid,
+                                    kind: extract_chat_type(doc),
+                                    title: name.clone(),
+                                    link_id: (link_id > 0).then_some(link_id),
+                                },
+                            );
                             if !name.is_empty() {
                                 chat_names.insert(cid, name);
                             }
@@ -1101,6 +1346,9 @@ pub fn cmd_watch(options: WatchOptions) -> Result<()> {
                 }
                 eprintln!("[watch] Press Ctrl-C to stop.");
             }
+            if let Some(status) = status.as_mut() {
+                let _ = status.draw(&mut std::io::stdout(), false);
+            }
             reconnect_count = 0;
             record_transport_success("watch")?;
 
@@ -1127,12 +1375,24 @@ pub fn cmd_watch(options: WatchOptions) -> Result<()> {
                                     continue;
                                 }
 
+                                // Other output must not land on the status line.
+                                let unread_before = status.as_ref().map(WatchStatus::total_unread);
+                                if let Some(status) = status.as_mut() {
+                                    let _ = status.clear(&mut std::io::stdout());
+                                }
                                 let mut watch_ctx = WatchContext {
                                     chat_names: &chat_names,
+                                    chat_subjects: &chat_subjects,
                                     options: &options,
                                     hook_config: &hook_config,
                                     last_log_ids: &mut last_log_ids,
                                     message_db: watch_message_db.as_ref(),
+                                    notifiers: &notifiers,
+                                    status: status.as_mut(),
+                                    dedup: &mut dedup,
+                                    ignored: &ignored,
+                                    hidden_runs: &mut hidden_runs,
+                                    media_client: media_client.as_ref(),
                                 };
                                 match method.as_str() {
                                     "MSG" => {
@@ -1161,8 +1421,15 @@ pub fn cmd_watch(options: WatchOptions) -> Result<()> {
                                         }
                                     }
                                 }
+                                if let Some(status) = status.as_mut() {
+                                    let ring = unread_before.is_some_and(|n| status.total_unread() > n);
+                                    let _ = status.draw(&mut std::io::stdout(), ring);
+                                }
                             }
                             Err(e) => {
+                                if let Some(status) = status.as_mut() {
+                                    let _ = status.clear(&mut std::io::stdout());
+                                }
                                 let err_msg = e.to_string();
                                 let is_auth = err_msg.contains("-950") || err_msg.contains("-999");
                                 if is_auth {
@@ -1214,7 +1481,15 @@ pub fn cmd_watch(options: WatchOptions) -> Result<()> {
                         }
                     }
                     _ = ping_interval.tick() => {
+                        // The session stays logged in; a rotated token is
+                        // what the next connect uses.
+                        if let Some(current) = rotated_credentials(&credentials).await {
+                            client.credentials = current;
+                        }
                         if let Err(e) = client.send_packet("PING", bson::doc! {}).await {
+                            if let Some(status) = status.as_mut() {
+                                let _ = status.clear(&mut std::io::stdout());
+                            }
                             record_failure("network")?;
                             eprintln!("[watch] PING failed: {}", e);
                             if options.max_reconnect == 0 {
@@ -1241,12 +1516,18 @@ pub fn cmd_watch(options: WatchOptions) -> Result<()> {
                         }
                     }
                     _ = tokio::signal::ctrl_c() => {
+                        if let Some(status) = status.as_mut() {
+                            let _ = status.finish(&mut std::io::stdout());
+                        }
                         eprintln!("\n[watch] Shutting down...");
                         client.disconnect_graceful().await;
                         // Persist last_log_ids for resume
                         if !last_log_ids.is_empty() {
                             if let Err(e) = save_watch_state(&last_log_ids) {
-                                eprintln!("[watch] Failed to save resume state: {}", e);
+                                warn(
+                                    WarningCode::StateSaveFailed,
+                                    format!("Failed to save resume state: {e}"),
+                                );
                             } else {
                                 eprintln!("[watch] Saved resume state ({} chats). Use --resume to continue.", last_log_ids.len());
                             }
@@ -1,10 +1,11 @@
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::PathBuf;
 
 use anyhow::{Context, Result};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Default, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct OpenKakaoConfig {
     #[serde(default)]
     pub mode: ModeConfig,
@@ -16,29 +17,51 @@ pub struct OpenKakaoConfig {
     pub auth: AuthConfig,
     #[serde(default)]
     pub safety: SafetyConfig,
+    #[serde(default)]
+    pub http: HttpConfig,
+    #[serde(default)]
+    pub names: NamesConfig,
+    #[serde(default)]
+    pub ui: UiConfig,
+    #[serde(default)]
+    pub notify: NotifyConfig,
+    #[serde(default)]
+    pub daemon: DaemonConfig,
+    #[serde(default)]
+    pub sync: SyncConfig,
+    #[serde(default)]
+    pub archive: ArchiveConfig,
+    #[serde(default)]
+    pub spam: SpamConfig,
+    /// `[alias]`: command lines that stand in for a subcommand name.
+    #[serde(default)]
+    pub alias: BTreeMap<String, String>,
 }
 
-#[derive(Debug, Clone, Default, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct ModeConfig {
     #[serde(default)]
     pub unattended: bool,
 }
 
-#[derive(Debug, Clone, Default, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct SendConfig {
     #[serde(default)]
     pub allow_non_interactive: bool,
     pub default_prefix: Option<bool>,
+    /// Age in seconds after which `outbox flush` skips a queued message
+    /// unless `--force` (default: one day).
+    pub outbox_ttl_secs: Option<u64>,
 }
 
-#[derive(Debug, Clone, Default, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct WatchConfig {
     #[serde(default)]
     pub allow_side_effects: bool,
     pub default_max_reconnect: Option<u32>,
 }
 
-#[derive(Debug, Clone, Default, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct AuthConfig {
     pub prefer_relogin: Option<bool>,
     pub auto_renew: Option<bool>,
@@ -46,7 +69,117 @@ pub struct AuthConfig {
     pub email_cmd: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct HttpConfig {
+    /// Maximum REST response body size in bytes (default: 4 MiB; friends,
+    /// message and member listings get 8x this).
+    pub max_body_bytes: Option<usize>,
+    /// katalk mirrors; the fastest is used and connect failures or timeouts
+    /// fall over to the next (default: just https://katalk.kakao.com).
+    pub base_urls: Option<Vec<String>>,
+    /// Cache answers of read-only endpoints for a short while: "off",
+    /// "memory" (this run only) or "disk" (shared by runs, under the state
+    /// directory). Default: off.
+    pub response_cache: Option<String>,
+}
+
+/// Limits for the in-memory member/friend name cache.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct NamesConfig {
+    /// Chats whose member lists are kept at once (default: 256).
+    pub max_cached_chats: Option<usize>,
+    /// Seconds before a cached name list is refetched (default: 1800).
+    pub ttl_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct UiConfig {
+    /// Color theme: "dark", "light" or "auto" (default: auto).
+    pub theme: Option<String>,
+    /// Default output format: "text" or "json" (default: text). "json"
+    /// acts as if every command got --json.
+    pub output: Option<String>,
+    /// Language of relative times, durations, sizes and counts: "en" or
+    /// "ko" (default: en).
+    pub lang: Option<String>,
+}
+
+/// Settings for `watch --notify` backends that need them.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct NotifyConfig {
+    /// Bot token from @BotFather for `--notify telegram`.
+    pub telegram_bot_token: Option<String>,
+    /// Target chat: a numeric id as a string ("-1001234") or "@channel".
+    pub telegram_chat_id: Option<String>,
+}
+
+/// Which chats `cache --all` and the daemon's `sync` task cover; see
+/// `chat_rules` for the rule syntax.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct SyncConfig {
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+/// What `cache` keeps of the messages it archives.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ArchiveConfig {
+    /// `"text"` or `"hash"` (a salted digest and the length instead of the
+    /// words) for chats synced from now on; `cache --no-content` overrides.
+    pub content: Option<crate::message_db::ContentMode>,
+}
+
+/// Heuristics of `read --flag-spam`; see `spam` for the defaults.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct SpamConfig {
+    /// Phrases that count as a signal, added to the built-in list.
+    #[serde(default)]
+    pub phrases: Vec<String>,
+    /// Signals a message needs to be flagged (default 1).
+    pub threshold: Option<usize>,
+    /// Distinct links in one message that count (default 3).
+    pub max_urls: Option<usize>,
+    /// Identical messages by one author within `repeat_window_secs` that
+    /// count (default 3 within 600).
+    pub repeat_count: Option<usize>,
+    pub repeat_window_secs: Option<i64>,
+    /// A link this many seconds after its author joined counts (default 600).
+    pub new_member_secs: Option<i64>,
+}
+
+/// Tasks `daemon` runs, in the order they are listed.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DaemonConfig {
+    #[serde(default)]
+    pub tasks: Vec<DaemonTaskConfig>,
+    /// JSON-lines log (default: daemon.log in the state directory).
+    pub log_file: Option<String>,
+    /// Rotate the log at this size, e.g. "10M" (default 10M).
+    pub log_max_size: Option<String>,
+    /// Rotate the log once its first entry is this old, e.g. "7d" (default 7d).
+    pub log_max_age: Option<String>,
+    /// Rotated (gzipped) logs kept (default 5).
+    pub log_keep: Option<usize>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DaemonTaskConfig {
+    /// `sync`, `renew` or `unread`.
+    pub kind: String,
+    /// Unique name in logs and `daemon status` (default: the kind).
+    pub name: Option<String>,
+    /// Pause after a run ends, e.g. "15m" or "6h".
+    pub every: String,
+    /// Local-time window with no runs, e.g. "23:00-07:00".
+    pub quiet_hours: Option<String>,
+    /// Chats for `sync` (default: every chat in the chat list).
+    #[serde(default)]
+    pub chats: Vec<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct SafetyConfig {
     pub min_unattended_send_interval_secs: Option<u64>,
     pub min_hook_interval_secs: Option<u64>,
@@ -76,8 +209,7 @@ impl Default for SafetyConfig {
 }
 
 pub fn config_path() -> Result<PathBuf> {
-    let home = dirs::home_dir().context("Could not resolve home directory")?;
-    Ok(home.join(".config").join("openkakao").join("config.toml"))
+    Ok(crate::paths::state_dir()?.join("config.toml"))
 }
 
 pub fn load_config() -> Result<OpenKakaoConfig> {
@@ -93,6 +225,61 @@ pub fn load_config() -> Result<OpenKakaoConfig> {
     Ok(config)
 }
 
+/// `[alias]` from the config.toml this run will use, read before the
+/// arguments are parsed (and so before the scope is set): the nearest
+/// workspace's, else the global one. A missing or unreadable file gives no
+/// aliases; `load_config` reports the error once the command runs.
+pub fn load_aliases() -> BTreeMap<String, String> {
+    let dir = std::env::current_dir()
+        .ok()
+        .and_then(|cwd| crate::paths::find_workspace(&cwd))
+        .or_else(|| crate::paths::global_dir().ok());
+    dir.and_then(|dir| fs::read_to_string(dir.join("config.toml")).ok())
+        .and_then(|data| toml::from_str::<OpenKakaoConfig>(&data).ok())
+        .map(|config| config.alias)
+        .unwrap_or_default()
+}
+
+/// Set `[section] key = value` for each edit in config.toml, keeping every
+/// other key. The file is rewritten as a whole, so comments are not kept.
+pub fn set_config_values<'a>(
+    edits: impl IntoIterator<Item = (&'a str, &'a str, toml::Value)>,
+) -> Result<PathBuf> {
+    let path = config_path()?;
+    let mut table = if path.exists() {
+        let data = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        data.parse::<toml::Table>()
+            .with_context(|| format!("Failed to parse {}", path.display()))?
+    } else {
+        toml::Table::new()
+    };
+    apply_edits(&mut table, edits)?;
+    let data = toml::to_string_pretty(&table).context("Failed to serialize config")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    crate::output::write_atomic(&path, |w| Ok(w.write_all(data.as_bytes())?))?;
+    Ok(path)
+}
+
+fn apply_edits<'a>(
+    table: &mut toml::Table,
+    edits: impl IntoIterator<Item = (&'a str, &'a str, toml::Value)>,
+) -> Result<()> {
+    for (section, key, value) in edits {
+        let entry = table
+            .entry(section)
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        let Some(section_table) = entry.as_table_mut() else {
+            anyhow::bail!("config key '{section}' is not a [{section}] table");
+        };
+        section_table.insert(key.to_string(), value);
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -112,5 +299,74 @@ mod tests {
         assert_eq!(config.safety.webhook_timeout_secs, Some(10));
         assert!(!config.safety.allow_insecure_webhooks);
         assert!(!config.safety.allow_loco_write);
+        assert!(config.http.max_body_bytes.is_none());
+    }
+
+    #[test]
+    fn daemon_tasks_parse_in_order() {
+        let config: OpenKakaoConfig = toml::from_str(
+            r#"
+[[daemon.tasks]]
+kind = "sync"
+every = "15m"
+quiet_hours = "23:00-07:00"
+chats = [1, 2]
+
+[[daemon.tasks]]
+kind = "renew"
+name = "token"
+every = "6h"
+"#,
+        )
+        .unwrap();
+        let tasks = &config.daemon.tasks;
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].kind, "sync");
+        assert_eq!(tasks[0].chats, [1, 2]);
+        assert_eq!(tasks[0].quiet_hours.as_deref(), Some("23:00-07:00"));
+        assert_eq!(tasks[1].name.as_deref(), Some("token"));
+        assert!(tasks[1].chats.is_empty());
+        assert!(config.daemon.log_file.is_none());
+    }
+
+    #[test]
+    fn sync_rules_parse() {
+        let config: OpenKakaoConfig = toml::from_str(
+            "[sync]\ninclude = [\"OpenGroup:*\", \"chat:18231\"]\nexclude = [\"type:dm\"]\n",
+        )
+        .unwrap();
+        assert_eq!(config.sync.include, ["OpenGroup:*", "chat:18231"]);
+        assert_eq!(config.sync.exclude, ["type:dm"]);
+        let config: OpenKakaoConfig = toml::from_str("").unwrap();
+        assert!(config.sync.include.is_empty() && config.sync.exclude.is_empty());
+    }
+
+    #[test]
+    fn http_section_parses_max_body_bytes() {
+        let config: OpenKakaoConfig = toml::from_str("[http]\nmax_body_bytes = 1048576\n").unwrap();
+        assert_eq!(config.http.max_body_bytes, Some(1_048_576));
+    }
+
+    #[test]
+    fn edits_keep_other_keys() {
+        let mut table: toml::Table = "[ui]\ntheme = \"dark\"\n\n[mode]\nunattended = true\n"
+            .parse()
+            .unwrap();
+        apply_edits(
+            &mut table,
+            [
+                ("ui", "output", "json".into()),
+                ("safety", "min_unattended_send_interval_secs", 30.into()),
+            ],
+        )
+        .unwrap();
+        let config: OpenKakaoConfig = toml::from_str(&toml::to_string(&table).unwrap()).unwrap();
+        assert_eq!(config.ui.theme.as_deref(), Some("dark"));
+        assert_eq!(config.ui.output.as_deref(), Some("json"));
+        assert!(config.mode.unattended);
+        assert_eq!(config.safety.min_unattended_send_interval_secs, Some(30));
+
+        let mut table: toml::Table = "ui = 1\n".parse().unwrap();
+        assert!(apply_edits(&mut table, [("ui", "output", "json".into())]).is_err());
     }
 }
@@ -0,0 +1,226 @@
+//! `friends --export`: the friend list as CSV or vCard 3.0, for backing up
+//! contacts before moving to a new phone.
+
+use anyhow::Result;
+use openkakao_rs::model::Friend;
+use openkakao_rs::normalize::format_phone;
+
+/// What `friends --export` writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ContactFormat {
+    Csv,
+    /// vCard 3.0, one card per friend.
+    Vcf,
+}
+
+/// Columns of the CSV export, in order.
+pub const CSV_COLUMNS: &[&str] = &[
+    "user_id",
+    "display_name",
+    "nickname",
+    "friend_nickname",
+    "phone_number",
+    "status_message",
+    "favorite",
+    "hidden",
+];
+
+fn yes_no(flag: bool) -> &'static str {
+    if flag {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+/// One row per friend; the csv writer quotes fields with commas, quotes or
+/// line breaks. Phone numbers are written as given.
+pub fn write_csv(friends: &[Friend]) -> Result<String> {
+    let mut out = csv::Writer::from_writer(Vec::new());
+    out.write_record(CSV_COLUMNS)?;
+    for f in friends {
+        out.write_record([
+            f.user_id.to_string(),
+            f.display_name(),
+            f.nickname.clone(),
+            f.friend_nickname.clone(),
+            f.phone_number.clone(),
+            f.status_message.clone(),
+            yes_no(f.favorite).to_string(),
+            yes_no(f.hidden).to_string(),
+        ])?;
+    }
+    Ok(String::from_utf8(out.into_inner()?)?)
+}
+
+/// Longest line of a vCard in octets, before folding (RFC 2425).
+const MAX_LINE_OCTETS: usize = 75;
+
+/// One `BEGIN:VCARD` ... `END:VCARD` entry per friend: `FN` and `N` from
+/// the display name, `NICKNAME` when the friend's own name differs, `TEL` in
+/// international form (`+82 10-...` for Korean mobile numbers) and the
+/// status message as `NOTE`. Lines end in CRLF and are folded at 75 octets.
+pub fn write_vcards(friends: &[Friend]) -> String {
+    let mut out = String::new();
+    for f in friends {
+        let name = escape(&f.display_name());
+        let mut lines = vec![
+            "BEGIN:VCARD".to_string(),
+            "VERSION:3.0".to_string(),
+            format!("FN:{name}"),
+            format!("N:;{name};;;"),
+        ];
+        if !f.nickname.is_empty() && f.nickname != f.display_name() {
+            lines.push(format!("NICKNAME:{}", escape(&f.nickname)));
+        }
+        let phone = f.phone_number.trim();
+        if !phone.is_empty() {
+            lines.push(format!(
+                "TEL;TYPE=CELL:{}",
+                format_phone(phone, &f.country_iso)
+            ));
+        }
+        if !f.status_message.is_empty() {
+            lines.push(format!("NOTE:{}", escape(&f.status_message)));
+        }
+        lines.push(format!("X-KAKAOTALK-USER-ID:{}", f.user_id));
+        lines.push("END:VCARD".to_string());
+        for line in lines {
+            out.push_str(&fold(&line));
+        }
+    }
+    out
+}
+
+/// A vCard text value with `\`, `,`, `;` and line breaks escaped.
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            ',' => out.push_str("\\,"),
+            ';' => out.push_str("\\;"),
+            '\r' => {
+                chars.next_if_eq(&'\n');
+                out.push_str("\\n");
+            }
+            '\n' => out.push_str("\\n"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// `line` with CRLF, split into continuation lines (leading space) so none
+/// is longer than [`MAX_LINE_OCTETS`], never inside a character.
+fn fold(line: &str) -> String {
+    let mut out = String::with_capacity(line.len() + 8);
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > MAX_LINE_OCTETS {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn friend(user_id: i64, nickname: &str, friend_nickname: &str, phone: &str) -> Friend {
+        Friend::from_json(&serde_json::json!({
+            "userId": user_id,
+            "nickName": nickname,
+            "friendNickName": friend_nickname,
+            "phoneNumber": phone,
+        }))
+    }
+
+    #[test]
+    fn csv_round_trips_awkward_names() {
+        let mut tricky = friend(1, "Kim, \"Minsu\"", "민수\n(회사)", "010-1234-5678");
+        tricky.status_message = "bye; see you, \"soon\"\r\nnew line".into();
+        tricky.favorite = true;
+        let plain = friend(2, "Dana", "", "");
+        let text = write_csv(&[tricky.clone(), plain]).unwrap();
+
+        let mut reader = csv::Reader::from_reader(text.as_bytes());
+        assert_eq!(reader.headers().unwrap(), CSV_COLUMNS);
+        let rows: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(&rows[0][0], "1");
+        assert_eq!(&rows[0][1], tricky.display_name());
+        assert_eq!(&rows[0][2], "Kim, \"Minsu\"");
+        assert_eq!(&rows[0][3], "민수\n(회사)");
+        assert_eq!(&rows[0][4], "010-1234-5678");
+        assert_eq!(&rows[0][5], tricky.status_message);
+        assert_eq!((&rows[0][6], &rows[0][7]), ("yes", "no"));
+        assert_eq!(&rows[1][1], "Dana");
+    }
+
+    /// Cards of `text` as unfolded lines, checking the framing on the way.
+    fn parse_cards(text: &str) -> Vec<Vec<String>> {
+        assert!(text.ends_with("\r\n"));
+        let physical: Vec<&str> = text.trim_end_matches("\r\n").split("\r\n").collect();
+        for line in &physical {
+            assert!(line.len() <= MAX_LINE_OCTETS, "line too long: {line}");
+            assert!(!line.contains('\n') && !line.contains('\r'));
+        }
+        let mut unfolded: Vec<String> = Vec::new();
+        for line in physical {
+            match line.strip_prefix(' ') {
+                Some(rest) => unfolded.last_mut().unwrap().push_str(rest),
+                None => unfolded.push(line.to_string()),
+            }
+        }
+        let mut cards = Vec::new();
+        let mut current: Option<Vec<String>> = None;
+        for line in unfolded {
+            match line.as_str() {
+                "BEGIN:VCARD" => {
+                    assert!(current.is_none(), "nested BEGIN");
+                    current = Some(Vec::new());
+                }
+                "END:VCARD" => cards.push(current.take().expect("END without BEGIN")),
+                _ => current
+                    .as_mut()
+                    .expect("property outside a card")
+                    .push(line),
+            }
+        }
+        assert!(current.is_none(), "unterminated card");
+        cards
+    }
+
+    #[test]
+    fn vcards_are_well_formed_with_international_numbers() {
+        let mut minsu = friend(1, "김민수", "민수 (회사, 팀장)", "010-1234-5678");
+        minsu.status_message = format!("{};\nline two\\", "여행 중 ".repeat(12));
+        let dana = friend(2, "Dana", "", "");
+        let cards = parse_cards(&write_vcards(&[minsu, dana]));
+        assert_eq!(cards.len(), 2);
+
+        let first = &cards[0];
+        assert_eq!(first[0], "VERSION:3.0");
+        assert!(first.contains(&"FN:민수 (회사\\, 팀장)".to_string()));
+        assert!(first.contains(&"N:;민수 (회사\\, 팀장);;;".to_string()));
+        assert!(first.contains(&"NICKNAME:김민수".to_string()));
+        assert!(first.contains(&"TEL;TYPE=CELL:+82 10-1234-5678".to_string()));
+        let note = first.iter().find(|l| l.starts_with("NOTE:")).unwrap();
+        assert!(note.ends_with("\\;\\nline two\\\\"), "{note}");
+        assert!(first.contains(&"X-KAKAOTALK-USER-ID:1".to_string()));
+
+        let second = &cards[1];
+        assert!(second.contains(&"FN:Dana".to_string()));
+        assert!(!second
+            .iter()
+            .any(|l| l.starts_with("TEL") || l.starts_with("NOTE")));
+        assert!(!second.iter().any(|l| l.starts_with("NICKNAME")));
+    }
+}
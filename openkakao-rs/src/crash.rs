@@ -0,0 +1,173 @@
+//! The panic hook: a short message instead of a raw backtrace, and a crash
+//! report in the state directory to attach to an issue.
+//!
+//! The report goes through the same scrubbing as `--capture-report`: the
+//! command line keeps flags and the subcommand only, quoted text in the panic
+//! message is replaced by its length, and ids, phone numbers and token-like
+//! words in the message and backtrace are removed. Of the request in flight
+//! only the transport, method and scrubbed endpoint are kept.
+
+use std::backtrace::Backtrace;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use openkakao_rs::capture::{self, Scrubber};
+use serde_json::{json, Value};
+
+pub const ISSUES_URL: &str = "https://github.com/JungHoonGhae/openkakao/issues";
+
+/// Bumped when the report layout changes.
+pub const CRASH_REPORT_VERSION: u32 = 1;
+
+/// Replace the default panic output for the rest of the process.
+pub fn install() {
+    std::panic::set_hook(Box::new(|info| {
+        let message = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "<non-string panic payload>".to_string());
+        let location = info
+            .location()
+            .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()));
+        let args: Vec<String> = std::env::args().collect();
+        let report = crash_report(
+            &message,
+            location.as_deref(),
+            &Backtrace::force_capture().to_string(),
+            &args,
+            capture::last_request(),
+        );
+        eprintln!();
+        eprintln!("openkakao-rs crashed. This is a bug, not something you did.");
+        match write_report(&report) {
+            Ok(path) => {
+                eprintln!("A crash report without tokens or message text was written to:");
+                eprintln!("  {}", path.display());
+                eprintln!("Please attach it to an issue at {ISSUES_URL}");
+            }
+            Err(err) => {
+                eprintln!("Could not write a crash report ({err:#}).");
+                eprintln!(
+                    "Please open an issue at {ISSUES_URL} with what you ran and: {}",
+                    report["panic"]["message"]
+                );
+            }
+        }
+    }));
+}
+
+/// The sanitized report of a panic with `message` at `location`.
+pub fn crash_report(
+    message: &str,
+    location: Option<&str>,
+    backtrace: &str,
+    args: &[String],
+    last_request: Option<Value>,
+) -> Value {
+    let mut scrubber = Scrubber::default();
+    let frames: Vec<String> = backtrace
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| scrubber.scrub_text(line))
+        .collect();
+    json!({
+        "report_version": CRASH_REPORT_VERSION,
+        "crate_version": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "command": capture::scrub_args(args),
+        "panic": {
+            "message": scrubber.scrub_text(&unquote(message)),
+            "location": location,
+        },
+        "last_request": last_request,
+        "backtrace": frames,
+    })
+}
+
+/// Panic messages quote the values involved (`` `text` ``, `"text"`,
+/// `'c'`), which may be message contents; keep only their length.
+fn unquote(message: &str) -> String {
+    let mut out = String::with_capacity(message.len());
+    let mut chars = message.char_indices().peekable();
+    let mut prev: Option<char> = None;
+    while let Some((start, c)) = chars.next() {
+        let opens =
+            matches!(c, '`' | '"') || (c == '\'' && !prev.is_some_and(|p| p.is_alphanumeric()));
+        // Quotes are ASCII, so the quoted text starts one byte on.
+        let close = opens.then(|| message[start + 1..].find(c)).flatten();
+        match close {
+            Some(len) => {
+                let end = start + 1 + len;
+                let inner = &message[start + 1..end];
+                out.push_str(&format!("{c}<{} chars>{c}", inner.chars().count()));
+                while chars.next_if(|&(i, _)| i <= end).is_some() {}
+            }
+            None => out.push(c),
+        }
+        prev = Some(c);
+    }
+    out
+}
+
+fn write_report(report: &Value) -> Result<PathBuf> {
+    let dir = openkakao_rs::paths::state_dir()?.join("crash-reports");
+    std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let path = dir.join(format!(
+        "crash-{}-{}.json",
+        chrono::Local::now().format("%Y%m%d-%H%M%S"),
+        std::process::id()
+    ));
+    let mut data = serde_json::to_vec_pretty(report)?;
+    data.push(b'\n');
+    std::fs::write(&path, data).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "a1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d6-deadbeef";
+
+    #[test]
+    fn quoted_values_are_reduced_to_their_length() {
+        assert_eq!(
+            unquote("byte index 1 is not a char boundary; it is inside '가' of `가나다`"),
+            "byte index 1 is not a char boundary; it is inside '<1 chars>' of `<3 chars>`"
+        );
+        assert_eq!(
+            unquote(r#"parse "see you" failed"#),
+            r#"parse "<7 chars>" failed"#
+        );
+        // Apostrophes and unbalanced quotes stay.
+        assert_eq!(unquote("can't `open"), "can't `open");
+    }
+
+    #[test]
+    fn report_keeps_no_tokens_text_or_ids() {
+        let args: Vec<String> = ["openkakao-rs", "send", "405979308", "meet at 6", "-y"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let report = crash_report(
+            &format!("unexpected field in `meet at 6` with token {TOKEN} for 405979308"),
+            Some("src/model.rs:10:5"),
+            &format!("   0: openkakao_rs::model::parse\n             at ./src/model.rs:10:5\n   1: token {TOKEN}\n"),
+            &args,
+            Some(json!({"transport": "rest", "method": "GET", "endpoint": "/chats/:id"})),
+        );
+        let text = report.to_string();
+        for secret in [TOKEN, "meet at 6", "405979308"] {
+            assert!(!text.contains(secret), "{secret} leaked: {text}");
+        }
+        assert_eq!(report["command"], json!(["send", "<arg>", "<arg>", "-y"]));
+        assert_eq!(report["panic"]["location"], "src/model.rs:10:5");
+        assert_eq!(report["last_request"]["endpoint"], "/chats/:id");
+        assert_eq!(report["backtrace"][0], "   0: openkakao_rs::model::parse");
+        assert_eq!(report["crate_version"], env!("CARGO_PKG_VERSION"));
+    }
+}
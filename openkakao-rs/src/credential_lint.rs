@@ -0,0 +1,416 @@
+//! Checks for hand-edited credentials, so a stray newline in a token is
+//! named with its field and fix before a header built from it fails.
+//!
+//! Every rule is a pure function of one field. [`lint`] runs them all for
+//! `credentials lint`; [`load_issues`] leaves out the gaps that
+//! [`KakaoCredentials::gaps`] already handles, for the warnings raised when
+//! credentials are loaded.
+
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::model::{CredentialGap, KakaoCredentials};
+
+/// Shortest and longest oauth token seen in practice, with room to spare:
+/// LOCO tokens are 65 characters, REST bearer tokens about 138.
+const TOKEN_LENGTH: std::ops::RangeInclusive<usize> = 32..=512;
+
+/// Exit code of `credentials lint` when only warnings were found.
+pub const LINT_WARNING_EXIT_CODE: i32 = 1;
+/// Exit code of `credentials lint` when a credential cannot work as saved.
+pub const LINT_ERROR_EXIT_CODE: i32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Requests go out, but may be refused or only approximate the app's.
+    Warning,
+    /// Requests cannot be built or will be refused.
+    Error,
+}
+
+/// One problem with one field.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LintIssue {
+    pub field: &'static str,
+    pub severity: Severity,
+    pub problem: String,
+    pub fix: String,
+}
+
+impl LintIssue {
+    fn new(
+        field: &'static str,
+        severity: Severity,
+        problem: impl Into<String>,
+        fix: impl Into<String>,
+    ) -> Self {
+        Self {
+            field,
+            severity,
+            problem: problem.into(),
+            fix: fix.into(),
+        }
+    }
+}
+
+/// The first character an HTTP header value cannot carry, with its
+/// 1-based position. Spaces are allowed only when `spaces` is set.
+fn bad_char(value: &str, spaces: bool) -> Option<(usize, char)> {
+    value
+        .chars()
+        .enumerate()
+        .find(|(_, c)| !(c.is_ascii_graphic() || (spaces && *c == ' ')))
+        .map(|(i, c)| (i + 1, c))
+}
+
+/// A token must be printable ASCII with no whitespace: it is sent as is in
+/// the `Authorization` header.
+pub fn token_characters(field: &'static str, token: &str) -> Option<LintIssue> {
+    let (position, c) = bad_char(token, false)?;
+    Some(LintIssue::new(
+        field,
+        Severity::Error,
+        format!("character {position} is {c:?}, which an Authorization header cannot carry"),
+        "remove it; a trailing newline or space from copy-paste is the usual cause",
+    ))
+}
+
+pub fn token_length(token: &str) -> Option<LintIssue> {
+    if token.is_empty() {
+        return Some(LintIssue::new(
+            "oauth_token",
+            Severity::Error,
+            "is empty",
+            "run 'openkakao-rs login --save' with KakaoTalk running",
+        ));
+    }
+    let len = token.chars().count();
+    (!TOKEN_LENGTH.contains(&len)).then(|| {
+        LintIssue::new(
+            "oauth_token",
+            Severity::Warning,
+            format!(
+                "is {len} characters; tokens are {} to {}",
+                TOKEN_LENGTH.start(),
+                TOKEN_LENGTH.end()
+            ),
+            "check that the whole Authorization value was copied, and nothing more",
+        )
+    })
+}
+
+/// The token ends in `-<device uuid>`, which should agree with
+/// `device_uuid` when both are there.
+pub fn token_separator(token: &str, device_uuid: &str) -> Option<LintIssue> {
+    if token.is_empty() {
+        return None;
+    }
+    let Some((_, suffix)) = token.split_once('-') else {
+        return Some(LintIssue::new(
+            "oauth_token",
+            Severity::Warning,
+            "has no '-' separating the token from the device UUID",
+            "copy the whole Authorization value, including its '-<device uuid>' suffix",
+        ));
+    };
+    let (suffix, device_uuid) = (suffix.trim(), device_uuid.trim());
+    (!device_uuid.is_empty() && suffix != device_uuid).then(|| {
+        LintIssue::new(
+            "device_uuid",
+            Severity::Warning,
+            "differs from the device UUID the token ends in",
+            format!("set device_uuid to {suffix:?}"),
+        )
+    })
+}
+
+pub fn user_id(user_id: i64) -> Option<LintIssue> {
+    (user_id < 0).then(|| {
+        LintIssue::new(
+            "user_id",
+            Severity::Error,
+            format!("is negative ({user_id})"),
+            "set it to your numeric user id, or to 0 to have it fetched",
+        )
+    })
+}
+
+/// `A` is `<platform>/<x.y.z>/<lang>`, e.g. `mac/26.1.0/ko`.
+pub fn a_header(a_header: &str, app_version: &str) -> Option<LintIssue> {
+    if a_header.is_empty() {
+        return None;
+    }
+    if let Some((position, c)) = bad_char(a_header, false) {
+        return Some(LintIssue::new(
+            "a_header",
+            Severity::Error,
+            format!("character {position} is {c:?}, which a header cannot carry"),
+            "remove it",
+        ));
+    }
+    let parts: Vec<&str> = a_header.split('/').collect();
+    let well_formed = match parts.as_slice() {
+        [platform, version, lang] => {
+            !platform.is_empty()
+                && platform
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+                && version.split('.').count() >= 2
+                && version
+                    .split('.')
+                    .all(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+                && lang.len() == 2
+                && lang.chars().all(|c| c.is_ascii_lowercase())
+        }
+        _ => false,
+    };
+    (!well_formed).then(|| {
+        LintIssue::new(
+            "a_header",
+            Severity::Warning,
+            format!("\"{a_header}\" is not in the form mac/<x.y.z>/<lang>"),
+            format!("set it to \"mac/{app_version}/ko\", or empty to derive it"),
+        )
+    })
+}
+
+pub fn user_agent(user_agent: &str) -> Option<LintIssue> {
+    if user_agent.is_empty() {
+        return None;
+    }
+    if let Some((position, c)) = bad_char(user_agent, true) {
+        return Some(LintIssue::new(
+            "user_agent",
+            Severity::Error,
+            format!("character {position} is {c:?}, which a header cannot carry"),
+            "remove it",
+        ));
+    }
+    (!user_agent.starts_with("KT/")).then(|| {
+        LintIssue::new(
+            "user_agent",
+            Severity::Warning,
+            format!("\"{user_agent}\" does not look like KakaoTalk's (KT/<version> ...)"),
+            "copy the one the app sends, e.g. \"KT/26.1.0 Mc/26.1.0 ko\", or empty for the default",
+        )
+    })
+}
+
+/// Missing values the client copes with (see [`KakaoCredentials::gaps`]),
+/// reported by `credentials lint` so they can be filled in.
+pub fn gaps(creds: &KakaoCredentials) -> Vec<LintIssue> {
+    let login = "run 'openkakao-rs login --save' with KakaoTalk running";
+    creds
+        .gaps()
+        .into_iter()
+        .map(|gap| {
+            let (field, fix) = match gap {
+                CredentialGap::UserId => (
+                    "user_id",
+                    "run any command with --save-backfill, or 'openkakao-rs login --save'",
+                ),
+                CredentialGap::AHeader => ("a_header", login),
+                CredentialGap::DeviceUuid => ("device_uuid", login),
+            };
+            LintIssue::new(
+                field,
+                Severity::Warning,
+                format!("is missing: {}", gap.consequence()),
+                fix,
+            )
+        })
+        .collect()
+}
+
+/// Problems with what is there, leaving out missing values.
+pub fn load_issues(creds: &KakaoCredentials) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    issues.extend(token_characters("oauth_token", &creds.oauth_token));
+    issues.extend(token_length(&creds.oauth_token));
+    issues.extend(token_separator(&creds.oauth_token, &creds.device_uuid));
+    if let Some(rest_token) = &creds.rest_token {
+        issues.extend(token_characters("rest_token", rest_token));
+    }
+    issues.extend(user_id(creds.user_id));
+    issues.extend(a_header(&creds.a_header, &creds.app_version));
+    issues.extend(user_agent(&creds.user_agent));
+    issues
+}
+
+/// Every rule, gaps included.
+pub fn lint(creds: &KakaoCredentials) -> Vec<LintIssue> {
+    let mut issues = load_issues(creds);
+    issues.extend(gaps(creds));
+    issues
+}
+
+/// [`lint`] for a saved file's contents; text that does not parse as
+/// credentials is a single error.
+pub fn lint_saved(text: &str) -> Vec<LintIssue> {
+    match serde_json::from_str::<KakaoCredentials>(text) {
+        Ok(creds) => lint(&creds),
+        Err(err) => vec![LintIssue::new(
+            "file",
+            Severity::Error,
+            format!("is not valid credentials JSON: {err}"),
+            "fix it by hand, or run 'openkakao-rs login --save' to write it again",
+        )],
+    }
+}
+
+/// A credentials file should be readable by its owner only.
+pub fn file_mode(mode: u32, path: &Path) -> Option<LintIssue> {
+    (mode != 0o600).then(|| {
+        LintIssue::new(
+            "file",
+            Severity::Warning,
+            format!("has permissions {mode:o}, expected 600"),
+            format!("chmod 600 {}", path.display()),
+        )
+    })
+}
+
+/// Exit code for a set of issues: 0 when clean.
+pub fn exit_code<'a>(issues: impl IntoIterator<Item = &'a LintIssue>) -> i32 {
+    match issues.into_iter().map(|issue| issue.severity).max() {
+        Some(Severity::Error) => LINT_ERROR_EXIT_CODE,
+        Some(Severity::Warning) => LINT_WARNING_EXIT_CODE,
+        None => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "a1b2c3d4e5f6a1b2c3d4e5f6a1b2c3d4e5f6a1b2c3d4e5f6a1b2c3d4-device-uuid";
+
+    fn creds() -> KakaoCredentials {
+        KakaoCredentials::new(
+            TOKEN.into(),
+            42,
+            "device-uuid".into(),
+            "26.1.0".into(),
+            "KT/26.1.0 Mc/26.1.0 ko".into(),
+            "mac/26.1.0/ko".into(),
+        )
+    }
+
+    #[test]
+    fn well_formed_credentials_pass() {
+        assert_eq!(lint(&creds()), []);
+        assert_eq!(exit_code(&lint(&creds())), 0);
+    }
+
+    #[test]
+    fn token_characters_catch_a_pasted_newline() {
+        let issue = token_characters("oauth_token", &format!("{TOKEN}\n")).unwrap();
+        assert_eq!(issue.severity, Severity::Error);
+        assert!(issue
+            .problem
+            .contains(&format!("character {}", TOKEN.len() + 1)));
+        assert!(issue.problem.contains("'\\n'"), "{}", issue.problem);
+        assert!(token_characters("rest_token", "tok en").is_some());
+        assert!(token_characters("rest_token", "토큰").is_some());
+        assert_eq!(token_characters("oauth_token", TOKEN), None);
+    }
+
+    #[test]
+    fn token_length_flags_empty_and_implausible_tokens() {
+        assert_eq!(token_length("").unwrap().severity, Severity::Error);
+        let short = token_length("abc-def").unwrap();
+        assert_eq!(short.severity, Severity::Warning);
+        assert!(short.problem.contains("7 characters"));
+        assert!(token_length(&"a".repeat(600)).is_some());
+        assert_eq!(token_length(TOKEN), None);
+    }
+
+    #[test]
+    fn token_separator_wants_a_matching_device_uuid_suffix() {
+        let missing = token_separator(&"a".repeat(64), "").unwrap();
+        assert_eq!(missing.field, "oauth_token");
+        let other = token_separator(TOKEN, "another-uuid").unwrap();
+        assert_eq!(other.field, "device_uuid");
+        assert!(other.fix.contains("\"device-uuid\""));
+        assert_eq!(token_separator(TOKEN, "device-uuid"), None);
+        assert_eq!(token_separator(TOKEN, ""), None);
+    }
+
+    #[test]
+    fn user_id_must_not_be_negative() {
+        assert_eq!(user_id(-5).unwrap().severity, Severity::Error);
+        assert_eq!(user_id(0), None);
+        assert_eq!(user_id(42), None);
+    }
+
+    #[test]
+    fn a_header_follows_platform_version_lang() {
+        assert_eq!(a_header("mac/26.1.0/ko", "26.1.0"), None);
+        assert_eq!(a_header("win32/4.2.1/en", "4.2.1"), None);
+        assert_eq!(a_header("", "26.1.0"), None);
+        let loose = a_header("Mac 26.1.0", "26.1.0").unwrap();
+        assert_eq!(loose.severity, Severity::Error);
+        let shape = a_header("mac/26/korean", "26.1.0").unwrap();
+        assert_eq!(shape.severity, Severity::Warning);
+        assert!(shape.fix.contains("mac/26.1.0/ko"));
+    }
+
+    #[test]
+    fn user_agent_looks_like_kakaotalks() {
+        assert_eq!(user_agent("KT/26.1.0 Mc/26.1.0 ko"), None);
+        assert_eq!(user_agent(""), None);
+        assert_eq!(
+            user_agent("Mozilla/5.0").unwrap().severity,
+            Severity::Warning
+        );
+        assert_eq!(
+            user_agent("KT/26.1.0\tko").unwrap().severity,
+            Severity::Error
+        );
+    }
+
+    #[test]
+    fn gaps_are_linted_but_not_warned_at_load() {
+        let mut partial = creds();
+        partial.user_id = 0;
+        partial.a_header.clear();
+        let fields: Vec<_> = lint(&partial).iter().map(|issue| issue.field).collect();
+        assert_eq!(fields, ["user_id", "a_header"]);
+        assert_eq!(load_issues(&partial), []);
+    }
+
+    #[test]
+    fn saved_text_that_is_not_credentials_is_one_error() {
+        let issues = lint_saved(r#"{"oauth_token": "abc"}"#);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].field, "file");
+        assert_eq!(issues[0].severity, Severity::Error);
+        assert!(
+            issues[0].problem.contains("missing field"),
+            "{:?}",
+            issues[0]
+        );
+
+        let text = serde_json::to_string(&creds()).unwrap();
+        assert_eq!(lint_saved(&text), []);
+    }
+
+    #[test]
+    fn file_mode_wants_owner_only() {
+        let path = Path::new("/tmp/default.json");
+        let issue = file_mode(0o644, path).unwrap();
+        assert!(issue.problem.contains("644"));
+        assert_eq!(issue.fix, "chmod 600 /tmp/default.json");
+        assert_eq!(file_mode(0o600, path), None);
+    }
+
+    #[test]
+    fn exit_code_follows_the_worst_issue() {
+        let warning = token_length("abc-def").unwrap();
+        let error = token_length("").unwrap();
+        assert_eq!(exit_code([&warning]), LINT_WARNING_EXIT_CODE);
+        assert_eq!(exit_code([&warning, &error]), LINT_ERROR_EXIT_CODE);
+    }
+}
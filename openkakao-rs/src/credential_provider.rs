@@ -0,0 +1,432 @@
+//! Where credentials come from, as an ordered chain of sources.
+//!
+//! The CLI asks its chain for credentials whenever a command needs them:
+//! the environment, the saved credentials file, the KakaoTalk cache and
+//! finally an interactive prompt. Embedders can build their own
+//! [`ProviderChain`] from any [`CredentialProvider`], e.g. one backed by a
+//! secrets vault, in front of or instead of those sources.
+//!
+//! ```
+//! use anyhow::Result;
+//! use openkakao_rs::credential_provider::{CredentialProvider, ProviderChain};
+//! use openkakao_rs::model::KakaoCredentials;
+//!
+//! struct Vault;
+//!
+//! impl CredentialProvider for Vault {
+//!     fn name(&self) -> &str {
+//!         "vault"
+//!     }
+//!
+//!     fn provide(&self) -> Result<Vec<KakaoCredentials>> {
+//!         Ok(vec![KakaoCredentials::new(
+//!             "token".into(),
+//!             42,
+//!             "device".into(),
+//!             "26.1.0".into(),
+//!             "KT/26.1.0 Mc/10.15.7 ko".into(),
+//!             "ko".into(),
+//!         )])
+//!     }
+//! }
+//!
+//! let chain = ProviderChain::new().with(Vault);
+//! assert_eq!(chain.resolve().unwrap().user_id, 42);
+//! ```
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Result};
+
+use crate::model::KakaoCredentials;
+
+/// A source of credentials.
+pub trait CredentialProvider: Send + Sync {
+    /// Short name for messages, e.g. `environment` or `saved file`.
+    fn name(&self) -> &str;
+
+    /// Credentials this source has, best first; empty when it has none, so
+    /// the chain moves on to the next source. An error stops the chain.
+    fn provide(&self) -> Result<Vec<KakaoCredentials>>;
+
+    /// The server rejected `oauth_token`. A source that stores tokens can
+    /// drop or refresh it here; by default nothing happens.
+    fn invalidate(&self, _oauth_token: &str) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Credential sources asked in order; the first usable credential wins.
+#[derive(Default)]
+pub struct ProviderChain {
+    providers: Vec<Box<dyn CredentialProvider>>,
+    /// Tokens reported by [`Self::invalidate`], skipped from then on.
+    rejected: Mutex<HashSet<String>>,
+}
+
+impl std::fmt::Debug for ProviderChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProviderChain")
+            .field("providers", &self.names())
+            .finish()
+    }
+}
+
+impl ProviderChain {
+    /// An empty chain; add sources with [`Self::with`].
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use openkakao_rs::credential_provider::ProviderChain;
+    ///
+    /// let chain = ProviderChain::new();
+    /// assert!(chain.resolve().is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append `provider`, asked after the ones already in the chain.
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use openkakao_rs::credential_provider::ProviderChain;
+    /// use openkakao_rs::testing::{self, StaticProvider};
+    ///
+    /// let chain = ProviderChain::new()
+    ///     .with(StaticProvider::new("environment", vec![]))
+    ///     .with(StaticProvider::new("saved file", vec![testing::credentials()]));
+    /// // The environment has nothing, so the saved file answers.
+    /// assert_eq!(chain.resolve()?.user_id, testing::USER_ID);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with(mut self, provider: impl CredentialProvider + 'static) -> Self {
+        self.providers.push(Box::new(provider));
+        self
+    }
+
+    /// The sources' names, in the order they are asked.
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use openkakao_rs::credential_provider::ProviderChain;
+    /// use openkakao_rs::testing::StaticProvider;
+    ///
+    /// let chain = ProviderChain::new()
+    ///     .with(StaticProvider::new("vault", vec![]))
+    ///     .with(StaticProvider::new("saved file", vec![]));
+    /// assert_eq!(chain.names(), ["vault", "saved file"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn names(&self) -> Vec<&str> {
+        self.providers.iter().map(|p| p.name()).collect()
+    }
+
+    /// The first credential, in chain order, whose token was not rejected.
+    /// Later sources are not asked once one has a usable credential.
+    ///
+    /// Together with [`Self::invalidate`], this moves on to the next
+    /// credential when the server refuses one:
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use openkakao_rs::credential_provider::ProviderChain;
+    /// use openkakao_rs::rest::TokenStatus;
+    /// use openkakao_rs::testing::{self, Canned, StaticProvider};
+    /// use serde_json::json;
+    ///
+    /// let mut stale = testing::credentials();
+    /// stale.oauth_token = "stale-token".into();
+    /// let chain = ProviderChain::new()
+    ///     .with(StaticProvider::new("saved file", vec![stale]))
+    ///     .with(StaticProvider::new("KakaoTalk cache", vec![testing::credentials()]));
+    /// // A server that has logged the stale token out.
+    /// let server = || {
+    ///     Canned::new().route("/more_settings.json", |request| {
+    ///         if request.authorization.starts_with("stale-token") {
+    ///             json!({"status": -950})
+    ///         } else {
+    ///             json!({"status": 0})
+    ///         }
+    ///     })
+    /// };
+    ///
+    /// let mut creds = chain.resolve()?;
+    /// loop {
+    ///     let client = testing::client_with(server());
+    ///     client.replace_credentials(creds.clone());
+    ///     match client.check_token() {
+    ///         TokenStatus::Valid => break,
+    ///         TokenStatus::Invalid { .. } => {
+    ///             chain.invalidate(&creds.oauth_token)?;
+    ///             creds = chain.resolve()?;
+    ///         }
+    ///         other => anyhow::bail!("cannot tell yet: {}", other.reason()),
+    ///     }
+    /// }
+    /// assert_eq!(creds.oauth_token, testing::TOKEN);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resolve(&self) -> Result<KakaoCredentials> {
+        for provider in &self.providers {
+            if let Some(creds) = provider
+                .provide()?
+                .into_iter()
+                .find(|creds| !self.is_rejected(&creds.oauth_token))
+            {
+                return Ok(creds);
+            }
+        }
+        Err(anyhow!(
+            "No credentials from any source ({})",
+            self.names().join(", ")
+        ))
+    }
+
+    /// Every usable credential from every source, in chain order, each
+    /// token once (where it first appears). Unlike [`Self::resolve`] this
+    /// asks all sources.
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use openkakao_rs::credential_provider::ProviderChain;
+    /// use openkakao_rs::testing::{self, StaticProvider};
+    ///
+    /// let saved = testing::credentials();
+    /// let mut other_device = testing::credentials();
+    /// other_device.oauth_token = "other-device-token".into();
+    /// let chain = ProviderChain::new()
+    ///     .with(StaticProvider::new("saved file", vec![saved.clone()]))
+    ///     .with(StaticProvider::new("KakaoTalk cache", vec![other_device, saved]));
+    /// let tokens: Vec<String> = chain
+    ///     .candidates()?
+    ///     .iter()
+    ///     .map(|c| c.oauth_token.clone())
+    ///     .collect();
+    /// assert_eq!(tokens, [testing::TOKEN, "other-device-token"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn candidates(&self) -> Result<Vec<KakaoCredentials>> {
+        let mut seen = HashSet::new();
+        let mut out = Vec::new();
+        for provider in &self.providers {
+            for creds in provider.provide()? {
+                if !self.is_rejected(&creds.oauth_token) && seen.insert(creds.oauth_token.clone()) {
+                    out.push(creds);
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// Record that the server rejected `oauth_token` and tell every source.
+    /// The token is skipped by later calls on this chain even when a source
+    /// keeps returning it. Every source is told even if one fails; the first
+    /// failure is returned.
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use openkakao_rs::credential_provider::ProviderChain;
+    /// use openkakao_rs::testing::{self, StaticProvider};
+    ///
+    /// let chain = ProviderChain::new()
+    ///     .with(StaticProvider::new("saved file", vec![testing::credentials()]));
+    /// chain.invalidate(testing::TOKEN)?;
+    /// assert!(chain.resolve().is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn invalidate(&self, oauth_token: &str) -> Result<()> {
+        self.rejected
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(oauth_token.to_string());
+        let mut first_err = None;
+        for provider in &self.providers {
+            if let Err(err) = provider.invalidate(oauth_token) {
+                first_err.get_or_insert(err);
+            }
+        }
+        first_err.map_or(Ok(()), Err)
+    }
+
+    /// Whether [`Self::invalidate`] was called for `oauth_token`.
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use openkakao_rs::credential_provider::ProviderChain;
+    ///
+    /// let chain = ProviderChain::new();
+    /// chain.invalidate("expired-token")?;
+    /// assert!(chain.is_rejected("expired-token"));
+    /// assert!(!chain.is_rejected("fresh-token"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn is_rejected(&self, oauth_token: &str) -> bool {
+        self.rejected
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains(oauth_token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn creds(token: &str, user_id: i64) -> KakaoCredentials {
+        KakaoCredentials::new(
+            token.into(),
+            user_id,
+            "device".into(),
+            "26.1.0".into(),
+            "KT/26.1.0".into(),
+            "ko".into(),
+        )
+    }
+
+    /// A source with fixed tokens that counts how often it is asked and
+    /// forgets tokens it is told are rejected.
+    struct Fake {
+        name: &'static str,
+        tokens: Arc<Mutex<Vec<&'static str>>>,
+        calls: Arc<AtomicUsize>,
+        forgets: bool,
+    }
+
+    impl Fake {
+        fn new(name: &'static str, tokens: &[&'static str]) -> Self {
+            Self {
+                name,
+                tokens: Arc::new(Mutex::new(tokens.to_vec())),
+                calls: Arc::default(),
+                forgets: false,
+            }
+        }
+    }
+
+    impl CredentialProvider for Fake {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn provide(&self) -> Result<Vec<KakaoCredentials>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(self
+                .tokens
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|t| creds(t, 1))
+                .collect())
+        }
+
+        fn invalidate(&self, oauth_token: &str) -> Result<()> {
+            if self.forgets {
+                self.tokens.lock().unwrap().retain(|t| *t != oauth_token);
+            }
+            Ok(())
+        }
+    }
+
+    struct Broken;
+
+    impl CredentialProvider for Broken {
+        fn name(&self) -> &str {
+            "broken"
+        }
+
+        fn provide(&self) -> Result<Vec<KakaoCredentials>> {
+            Err(anyhow!("vault sealed"))
+        }
+
+        fn invalidate(&self, _oauth_token: &str) -> Result<()> {
+            Err(anyhow!("vault sealed"))
+        }
+    }
+
+    #[test]
+    fn first_source_with_credentials_wins_and_later_ones_are_not_asked() {
+        let empty = Fake::new("empty", &[]);
+        let vault = Fake::new("vault", &["v1", "v2"]);
+        let prompt = Fake::new("prompt", &["p1"]);
+        let prompt_calls = prompt.calls.clone();
+        let chain = ProviderChain::new().with(empty).with(vault).with(prompt);
+
+        assert_eq!(chain.names(), ["empty", "vault", "prompt"]);
+        assert_eq!(chain.resolve().unwrap().oauth_token, "v1");
+        assert_eq!(prompt_calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn candidates_keep_chain_order_without_duplicates() {
+        let chain = ProviderChain::new()
+            .with(Fake::new("env", &["a"]))
+            .with(Fake::new("file", &["b", "a"]))
+            .with(Fake::new("cache", &["c", "b", "d"]));
+        let tokens: Vec<String> = chain
+            .candidates()
+            .unwrap()
+            .into_iter()
+            .map(|c| c.oauth_token.clone())
+            .collect();
+        assert_eq!(tokens, ["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn invalidated_tokens_are_skipped_and_sources_are_told() {
+        let mut file = Fake::new("file", &["old"]);
+        file.forgets = true;
+        let chain = ProviderChain::new()
+            .with(Fake::new("env", &["stale"]))
+            .with(file)
+            .with(Fake::new("cache", &["stale", "fresh"]));
+
+        assert_eq!(chain.resolve().unwrap().oauth_token, "stale");
+        chain.invalidate("stale").unwrap();
+        // The environment keeps returning it, but the chain moves past it.
+        assert!(chain.is_rejected("stale"));
+        assert_eq!(chain.resolve().unwrap().oauth_token, "old");
+
+        chain.invalidate("old").unwrap();
+        assert_eq!(chain.resolve().unwrap().oauth_token, "fresh");
+        let tokens: Vec<String> = chain
+            .candidates()
+            .unwrap()
+            .into_iter()
+            .map(|c| c.oauth_token.clone())
+            .collect();
+        assert_eq!(tokens, ["fresh"]);
+
+        chain.invalidate("fresh").unwrap();
+        let err = chain.resolve().unwrap_err().to_string();
+        assert!(err.contains("env, file, cache"), "{err}");
+    }
+
+    #[test]
+    fn source_errors_stop_resolution_but_not_invalidation() {
+        let chain = ProviderChain::new()
+            .with(Fake::new("empty", &[]))
+            .with(Broken)
+            .with(Fake::new("prompt", &["p1"]));
+        assert!(chain.resolve().unwrap_err().to_string().contains("sealed"));
+
+        let mut file = Fake::new("file", &["t"]);
+        file.forgets = true;
+        let tokens = file.tokens.clone();
+        let chain = ProviderChain::new().with(Broken).with(file);
+        // The broken source fails, the one after it still forgets the token.
+        assert!(chain.invalidate("t").is_err());
+        assert!(tokens.lock().unwrap().is_empty());
+    }
+}
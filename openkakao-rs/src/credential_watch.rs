@@ -0,0 +1,265 @@
+//! Picking up credentials another process changed while a long-running
+//! command keeps going.
+//!
+//! A daemon or `watch` that runs for weeks outlives its token: an
+//! `auth refresh` in another terminal, or the daemon's own renew task,
+//! writes a new one to the credentials file. [`CredentialWatch`] notices
+//! the change by size and modification time (cheap enough to check every
+//! cycle), loads and verifies the new credentials, and hands them back to
+//! be swapped into the running client with
+//! [`KakaoRestClient::replace_credentials`](crate::rest::KakaoRestClient::replace_credentials).
+//! Credentials that fail verification are reported and the old ones stay.
+
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+
+use crate::model::KakaoCredentials;
+use crate::rest::TokenStatus;
+
+/// What identifies one version of a file: its size and modification time,
+/// or nothing while it does not exist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Stamp {
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+fn stamp(path: &Path) -> Option<Stamp> {
+    let meta = std::fs::metadata(path).ok()?;
+    Some(Stamp {
+        len: meta.len(),
+        modified: meta.modified().ok(),
+    })
+}
+
+/// Files compared against how they looked at the last check. Creating,
+/// rewriting and removing a file all count as a change.
+#[derive(Debug)]
+pub struct FileWatch {
+    files: Vec<(PathBuf, Option<Stamp>)>,
+}
+
+impl FileWatch {
+    /// Watch `paths` from how they look now.
+    pub fn new(paths: impl IntoIterator<Item = PathBuf>) -> Self {
+        Self {
+            files: paths
+                .into_iter()
+                .map(|path| {
+                    let stamp = stamp(&path);
+                    (path, stamp)
+                })
+                .collect(),
+        }
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(|(path, _)| path.as_path())
+    }
+
+    /// The files that changed since the last call (or [`Self::new`]).
+    pub fn changed(&mut self) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
+        for (path, seen) in &mut self.files {
+            let now = stamp(path);
+            if now != *seen {
+                *seen = now;
+                changed.push(path.clone());
+            }
+        }
+        changed
+    }
+}
+
+/// The outcome of a [`CredentialWatch::poll`] that found a changed file.
+#[derive(Debug)]
+pub enum Reload {
+    /// The file changed but still holds the token in use.
+    Unchanged,
+    /// New credentials, verified and now [`CredentialWatch::current`].
+    Rotated {
+        previous: Box<KakaoCredentials>,
+        current: Box<KakaoCredentials>,
+    },
+    /// The new credentials could not be loaded or the server refused them;
+    /// the old ones stay in use.
+    Rejected { reason: String },
+    /// Verification said nothing about the token (rate limited, network).
+    /// The old credentials stay and the next poll tries again.
+    Deferred { reason: String },
+}
+
+/// The credentials a long-running command uses and the files they were
+/// loaded from.
+#[derive(Debug)]
+pub struct CredentialWatch {
+    files: FileWatch,
+    current: KakaoCredentials,
+    /// A change seen but not settled yet, retried on the next poll.
+    pending: bool,
+}
+
+impl CredentialWatch {
+    /// Watch `paths` for credentials replacing `current`.
+    pub fn new(current: KakaoCredentials, paths: impl IntoIterator<Item = PathBuf>) -> Self {
+        Self {
+            files: FileWatch::new(paths),
+            current,
+            pending: false,
+        }
+    }
+
+    pub fn current(&self) -> &KakaoCredentials {
+        &self.current
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.paths()
+    }
+
+    /// `None` unless a watched file changed since the last poll. Then
+    /// `load` reads the credentials again and, when the token differs,
+    /// `verify` checks it before it becomes [`Self::current`].
+    pub fn poll(
+        &mut self,
+        load: impl FnOnce() -> Result<KakaoCredentials>,
+        verify: impl FnOnce(&KakaoCredentials) -> TokenStatus,
+    ) -> Option<Reload> {
+        let changed = !self.files.changed().is_empty();
+        if !changed && !self.pending {
+            return None;
+        }
+        self.pending = false;
+        let loaded = match load() {
+            Ok(loaded) => loaded,
+            Err(err) => {
+                return Some(Reload::Rejected {
+                    reason: format!("{err:#}"),
+                })
+            }
+        };
+        if loaded.oauth_token == self.current.oauth_token
+            && loaded.rest_token == self.current.rest_token
+        {
+            return Some(Reload::Unchanged);
+        }
+        Some(match verify(&loaded) {
+            TokenStatus::Valid => Reload::Rotated {
+                previous: Box::new(std::mem::replace(&mut self.current, loaded.clone())),
+                current: Box::new(loaded),
+            },
+            invalid @ TokenStatus::Invalid { .. } => Reload::Rejected {
+                reason: invalid.reason(),
+            },
+            inconclusive => {
+                self.pending = true;
+                Reload::Deferred {
+                    reason: inconclusive.reason(),
+                }
+            }
+        })
+    }
+}
+
+/// A short, stable name for `creds`' token in logs; never the token.
+pub fn fingerprint(creds: &KakaoCredentials) -> String {
+    hex::encode(&Sha256::digest(creds.oauth_token.as_bytes())[..6])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn creds(token: &str) -> KakaoCredentials {
+        KakaoCredentials::new(
+            token.into(),
+            42,
+            "device".into(),
+            "26.1.0".into(),
+            String::new(),
+            String::new(),
+        )
+    }
+
+    fn valid(_: &KakaoCredentials) -> TokenStatus {
+        TokenStatus::Valid
+    }
+
+    #[test]
+    fn file_watch_sees_creation_rewrite_and_removal() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("default.json");
+        let mut watch = FileWatch::new([path.clone()]);
+        assert!(watch.changed().is_empty());
+
+        std::fs::write(&path, "one").unwrap();
+        assert_eq!(watch.changed(), vec![path.clone()]);
+        assert!(watch.changed().is_empty());
+
+        std::fs::write(&path, "three").unwrap();
+        assert_eq!(watch.changed(), vec![path.clone()]);
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(watch.changed(), vec![path]);
+    }
+
+    #[test]
+    fn poll_rotates_only_verified_new_tokens() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("default.json");
+        let mut watch = CredentialWatch::new(creds("old"), [path.clone()]);
+        assert!(watch.poll(|| unreachable!(), valid).is_none());
+
+        std::fs::write(&path, "same").unwrap();
+        let reload = watch.poll(|| Ok(creds("old")), |_| unreachable!());
+        assert!(matches!(reload, Some(Reload::Unchanged)));
+
+        std::fs::write(&path, "refused").unwrap();
+        let reload = watch.poll(
+            || Ok(creds("bad")),
+            |_| TokenStatus::Invalid {
+                kakao_status: -950,
+                message: "expired".into(),
+            },
+        );
+        assert!(matches!(reload, Some(Reload::Rejected { .. })));
+        assert_eq!(watch.current().oauth_token, "old");
+
+        std::fs::write(&path, "refreshed").unwrap();
+        match watch.poll(|| Ok(creds("new")), valid) {
+            Some(Reload::Rotated { previous, current }) => {
+                assert_eq!(previous.oauth_token, "old");
+                assert_eq!(current.oauth_token, "new");
+            }
+            other => panic!("expected a rotation, got {other:?}"),
+        }
+        assert_eq!(watch.current().oauth_token, "new");
+    }
+
+    #[test]
+    fn inconclusive_verification_is_retried_next_poll() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("default.json");
+        let mut watch = CredentialWatch::new(creds("old"), [path.clone()]);
+        std::fs::write(&path, "refreshed").unwrap();
+
+        let reload = watch.poll(|| Ok(creds("new")), |_| TokenStatus::RateLimited);
+        assert!(matches!(reload, Some(Reload::Deferred { .. })));
+        assert_eq!(watch.current().oauth_token, "old");
+
+        let reload = watch.poll(|| Ok(creds("new")), valid);
+        assert!(matches!(reload, Some(Reload::Rotated { .. })));
+        assert!(watch.poll(|| unreachable!(), valid).is_none());
+    }
+
+    #[test]
+    fn fingerprint_does_not_reveal_the_token() {
+        let print = fingerprint(&creds("secret-token"));
+        assert_eq!(print.len(), 12);
+        assert!(!print.contains("secret"));
+        assert_eq!(print, fingerprint(&creds("secret-token")));
+    }
+}
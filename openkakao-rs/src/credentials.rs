@@ -1,39 +1,270 @@
 use std::fs;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::SystemTime;
 
 use anyhow::{Context, Result};
+use serde::Serialize;
 
 use crate::model::KakaoCredentials;
+use crate::warnings::{warn, WarningCode};
 
+/// Profile used when `--profile` is not given.
+pub const DEFAULT_PROFILE: &str = crate::paths::DEFAULT_PROFILE;
+
+static SELECTED_PROFILE: OnceLock<String> = OnceLock::new();
+
+/// Use profile `name` for every credential read and write in this run,
+/// and its own state tree ([`crate::paths::profile_dir`]).
+pub fn select_profile(name: &str) -> Result<()> {
+    validate_profile_name(name)?;
+    let _ = SELECTED_PROFILE.set(name.to_string());
+    crate::paths::set_profile(name);
+    Ok(())
+}
+
+/// The profile chosen with `--profile`, if any.
+pub fn explicit_profile() -> Option<&'static str> {
+    SELECTED_PROFILE.get().map(String::as_str)
+}
+
+/// The profile this run reads and saves credentials under.
+pub fn active_profile() -> &'static str {
+    explicit_profile().unwrap_or(DEFAULT_PROFILE)
+}
+
+fn validate_profile_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        anyhow::bail!("Invalid profile name '{name}': use letters, digits, '-' and '_' only.");
+    }
+    Ok(())
+}
+
+/// The credentials file of the active profile.
 pub fn credentials_path() -> Result<PathBuf> {
-    let home = dirs::home_dir().context("Could not resolve home directory")?;
-    Ok(home
-        .join(".config")
-        .join("openkakao")
-        .join("credentials.json"))
+    profile_path(active_profile())
+}
+
+/// `credentials/<name>.json`.
+pub fn profile_path(name: &str) -> Result<PathBuf> {
+    validate_profile_name(name)?;
+    Ok(profiles_dir()?.join(format!("{name}.json")))
+}
+
+/// The single `credentials.json` used before profiles existed.
+pub fn legacy_credentials_path() -> Result<PathBuf> {
+    Ok(crate::paths::credentials_dir()?.join("credentials.json"))
+}
+
+/// Move a pre-profile `credentials.json` to the default profile. Returns
+/// the new path when a file was moved; a default profile that already
+/// exists is never overwritten.
+pub fn migrate_legacy_credentials() -> Result<Option<PathBuf>> {
+    migrate_legacy_credentials_in(&legacy_credentials_path()?, &profiles_dir()?)
+}
+
+fn migrate_legacy_credentials_in(legacy: &Path, profiles: &Path) -> Result<Option<PathBuf>> {
+    let target = profiles.join(format!("{DEFAULT_PROFILE}.json"));
+    if !legacy.exists() || target.exists() {
+        return Ok(None);
+    }
+    fs::create_dir_all(profiles)
+        .with_context(|| format!("Failed to create {}", profiles.display()))?;
+    fs::rename(legacy, &target).with_context(|| {
+        format!(
+            "Failed to move {} to {}",
+            legacy.display(),
+            target.display()
+        )
+    })?;
+    Ok(Some(target))
+}
+
+/// Keychain service credentials are stored under; the account is the
+/// profile name.
+pub const KEYCHAIN_SERVICE: &str = "openkakao";
+
+/// Where a profile's credentials are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialStore {
+    /// `credentials/<profile>.json`, readable only by the owner.
+    File,
+    /// The OS keychain (macOS).
+    Keychain,
+}
+
+/// Where credentials were found or saved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CredentialLocation {
+    File(PathBuf),
+    Keychain { profile: String },
+}
+
+impl CredentialLocation {
+    pub fn store(&self) -> CredentialStore {
+        match self {
+            CredentialLocation::File(_) => CredentialStore::File,
+            CredentialLocation::Keychain { .. } => CredentialStore::Keychain,
+        }
+    }
+}
+
+impl std::fmt::Display for CredentialLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CredentialLocation::File(path) => write!(f, "{}", path.display()),
+            CredentialLocation::Keychain { profile } => {
+                write!(f, "the keychain ({KEYCHAIN_SERVICE}/{profile})")
+            }
+        }
+    }
+}
+
+/// Serialized credentials by profile name, outside the credentials files.
+pub trait SecretStore {
+    fn get(&self, profile: &str) -> Result<Option<String>>;
+    fn set(&self, profile: &str, secret: &str) -> Result<()>;
+    /// Whether there was an entry to remove.
+    fn delete(&self, profile: &str) -> Result<bool>;
+}
+
+/// The login keychain on macOS. Other platforms, and builds without the
+/// `keyring` feature, have none: nothing is found there and saving to it
+/// fails.
+pub struct Keychain;
+
+impl Keychain {
+    pub fn available() -> bool {
+        crate::capabilities::KEYRING.compiled
+    }
+}
+
+#[cfg(all(target_os = "macos", feature = "keyring"))]
+impl SecretStore for Keychain {
+    fn get(&self, profile: &str) -> Result<Option<String>> {
+        match keyring::Entry::new(KEYCHAIN_SERVICE, profile)?.get_password() {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(err) => Err(err).context("Failed to read the keychain"),
+        }
+    }
+
+    fn set(&self, profile: &str, secret: &str) -> Result<()> {
+        keyring::Entry::new(KEYCHAIN_SERVICE, profile)?
+            .set_password(secret)
+            .context("Failed to write to the keychain")
+    }
+
+    fn delete(&self, profile: &str) -> Result<bool> {
+        match keyring::Entry::new(KEYCHAIN_SERVICE, profile)?.delete_credential() {
+            Ok(()) => Ok(true),
+            Err(keyring::Error::NoEntry) => Ok(false),
+            Err(err) => Err(err).context("Failed to delete from the keychain"),
+        }
+    }
+}
+
+#[cfg(not(all(target_os = "macos", feature = "keyring")))]
+impl SecretStore for Keychain {
+    fn get(&self, _profile: &str) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn set(&self, _profile: &str, _secret: &str) -> Result<()> {
+        anyhow::bail!(
+            "No keychain in this build ({}); save with --store file.",
+            crate::capabilities::KEYRING.rebuild
+        )
+    }
+
+    fn delete(&self, _profile: &str) -> Result<bool> {
+        Ok(false)
+    }
 }
 
 pub fn load_credentials() -> Result<Option<KakaoCredentials>> {
-    let path = credentials_path()?;
+    Ok(load_credentials_located()?.map(|(creds, _)| creds))
+}
+
+/// The active profile's credentials and where they were found: the keychain
+/// first, then the JSON file.
+pub fn load_credentials_located() -> Result<Option<(KakaoCredentials, CredentialLocation)>> {
+    load_from(&Keychain, active_profile(), &credentials_path()?)
+}
+
+fn load_from(
+    keychain: &dyn SecretStore,
+    profile: &str,
+    path: &Path,
+) -> Result<Option<(KakaoCredentials, CredentialLocation)>> {
+    match keychain.get(profile) {
+        Ok(Some(secret)) => {
+            let creds = serde_json::from_str(&secret).with_context(|| {
+                format!("Failed to parse the keychain entry {KEYCHAIN_SERVICE}/{profile}")
+            })?;
+            let location = CredentialLocation::Keychain {
+                profile: profile.to_string(),
+            };
+            warn_on_lint(&creds, &location);
+            return Ok(Some((creds, location)));
+        }
+        Ok(None) => {}
+        Err(err) => warn(
+            WarningCode::CredentialPermissions,
+            format!("{err:#}; falling back to {}", path.display()),
+        ),
+    }
+    let Some(creds) = load_file(path)? else {
+        return Ok(None);
+    };
+    let location = CredentialLocation::File(path.to_path_buf());
+    warn_on_lint(&creds, &location);
+    Ok(Some((creds, location)))
+}
+
+/// One warning per problem with the loaded values, so a hand-edited file is
+/// named before a request is built from it. Missing values are left to
+/// `W015_PARTIAL_CREDENTIALS`.
+fn warn_on_lint(creds: &KakaoCredentials, location: &CredentialLocation) {
+    for issue in crate::credential_lint::load_issues(creds) {
+        warn(
+            WarningCode::CredentialFormat,
+            format!(
+                "{location}: {} {}; {}. Run 'openkakao-rs credentials lint' to check every profile",
+                issue.field, issue.problem, issue.fix
+            ),
+        );
+    }
+}
+
+fn load_file(path: &Path) -> Result<Option<KakaoCredentials>> {
     if !path.exists() {
         return Ok(None);
     }
 
     let data =
-        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
 
     #[cfg(unix)]
     {
         use std::os::unix::fs::MetadataExt;
-        let metadata = std::fs::metadata(&path)?;
+        let metadata = std::fs::metadata(path)?;
         let mode = metadata.mode() & 0o777;
         if mode != 0o600 {
-            eprintln!(
-                "WARNING: {} has permissions {:o}, expected 600. Run: chmod 600 {}",
-                path.display(),
-                mode,
-                path.display()
+            warn(
+                WarningCode::CredentialPermissions,
+                format!(
+                    "{} has permissions {:o}, expected 600. Run: chmod 600 {} (or 'openkakao-rs credentials prune --apply')",
+                    path.display(),
+                    mode,
+                    path.display()
+                ),
             );
         }
     }
@@ -44,9 +275,81 @@ pub fn load_credentials() -> Result<Option<KakaoCredentials>> {
     Ok(Some(creds))
 }
 
-/// Save credentials to the default path. Returns the path written to.
-pub fn save_credentials(creds: &KakaoCredentials) -> Result<PathBuf> {
+/// Save credentials to the active profile where it is kept now; a new
+/// profile goes to the keychain when there is one. Returns where they went.
+pub fn save_credentials(creds: &KakaoCredentials) -> Result<CredentialLocation> {
+    save_credentials_to(creds, None)
+}
+
+/// [`save_credentials`] to `store` when given. Saving to one store removes
+/// the profile from the other, so a stale copy never shadows the new one.
+pub fn save_credentials_to(
+    creds: &KakaoCredentials,
+    store: Option<CredentialStore>,
+) -> Result<CredentialLocation> {
+    let path = credentials_path()?;
+    let store = store.unwrap_or_else(|| {
+        current_store(&Keychain, Keychain::available(), active_profile(), &path)
+    });
+    save_in(&Keychain, active_profile(), &path, creds, store)
+}
+
+/// Where [`save_credentials`] would write the active profile.
+pub fn save_target() -> Result<CredentialLocation> {
     let path = credentials_path()?;
+    Ok(
+        match current_store(&Keychain, Keychain::available(), active_profile(), &path) {
+            CredentialStore::File => CredentialLocation::File(path),
+            CredentialStore::Keychain => CredentialLocation::Keychain {
+                profile: active_profile().to_string(),
+            },
+        },
+    )
+}
+
+/// The store holding `profile` now, else the keychain when there is one.
+fn current_store(
+    keychain: &dyn SecretStore,
+    keychain_available: bool,
+    profile: &str,
+    path: &Path,
+) -> CredentialStore {
+    if matches!(keychain.get(profile), Ok(Some(_))) {
+        CredentialStore::Keychain
+    } else if path.exists() || !keychain_available {
+        CredentialStore::File
+    } else {
+        CredentialStore::Keychain
+    }
+}
+
+fn save_in(
+    keychain: &dyn SecretStore,
+    profile: &str,
+    path: &Path,
+    creds: &KakaoCredentials,
+    store: CredentialStore,
+) -> Result<CredentialLocation> {
+    match store {
+        CredentialStore::Keychain => {
+            let data = serde_json::to_string(creds).context("Failed to serialize credentials")?;
+            keychain.set(profile, &data)?;
+            if path.exists() {
+                shred_file(path)?;
+            }
+            Ok(CredentialLocation::Keychain {
+                profile: profile.to_string(),
+            })
+        }
+        CredentialStore::File => {
+            write_file(path, creds)?;
+            keychain.delete(profile)?;
+            Ok(CredentialLocation::File(path.to_path_buf()))
+        }
+    }
+}
+
+fn write_file(path: &Path, creds: &KakaoCredentials) -> Result<()> {
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)
             .with_context(|| format!("Failed to create {}", parent.display()))?;
@@ -64,12 +367,12 @@ pub fn save_credentials(creds: &KakaoCredentials) -> Result<PathBuf> {
             .create(true)
             .truncate(true)
             .mode(0o600)
-            .open(&path)
+            .open(path)
             .with_context(|| format!("Failed to create {}", path.display()))?
     };
     #[cfg(not(unix))]
     let mut file =
-        fs::File::create(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+        fs::File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
 
     file.write_all(data.as_bytes())
         .with_context(|| format!("Failed to write {}", path.display()))?;
@@ -77,11 +380,231 @@ pub fn save_credentials(creds: &KakaoCredentials) -> Result<PathBuf> {
     #[cfg(unix)]
     {
         use std::os::unix::fs::PermissionsExt;
-        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
             .with_context(|| format!("Failed to set permissions on {}", path.display()))?;
     }
 
-    Ok(path)
+    Ok(())
+}
+
+/// Overwrite `path` with zeros, flush that to disk and delete it. Best
+/// effort: copy-on-write filesystems and backups already taken keep the
+/// old blocks.
+pub fn shred_file(path: &Path) -> Result<()> {
+    let len = fs::metadata(path)
+        .with_context(|| format!("Failed to stat {}", path.display()))?
+        .len();
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    file.write_all(&vec![0u8; len as usize])
+        .and_then(|()| file.sync_all())
+        .with_context(|| format!("Failed to overwrite {}", path.display()))?;
+    drop(file);
+    fs::remove_file(path).with_context(|| format!("Failed to delete {}", path.display()))
+}
+
+/// Move the active profile's JSON file into the keychain, check that it
+/// reads back, then shred the file. `None` when there is no file.
+pub fn migrate_to_keychain() -> Result<Option<PathBuf>> {
+    if !Keychain::available() {
+        anyhow::bail!("No keychain on this platform; the credentials stay in the file.");
+    }
+    migrate_in(&Keychain, active_profile(), &credentials_path()?)
+}
+
+fn migrate_in(keychain: &dyn SecretStore, profile: &str, path: &Path) -> Result<Option<PathBuf>> {
+    let Some(creds) = load_file(path)? else {
+        return Ok(None);
+    };
+    let data = serde_json::to_string(&creds).context("Failed to serialize credentials")?;
+    if let Some(existing) = keychain.get(profile)? {
+        if existing != data {
+            anyhow::bail!(
+                "The keychain already holds different credentials for profile '{profile}'. \
+                 Delete {} to keep the keychain entry, or re-save with 'login --save --store keychain'.",
+                path.display()
+            );
+        }
+    } else {
+        keychain.set(profile, &data)?;
+        if keychain.get(profile)?.as_deref() != Some(data.as_str()) {
+            anyhow::bail!(
+                "The keychain entry did not read back; {} was kept.",
+                path.display()
+            );
+        }
+    }
+    shred_file(path)?;
+    Ok(Some(path.to_path_buf()))
+}
+
+/// Directory holding one `<profile>.json` per saved account.
+pub fn profiles_dir() -> Result<PathBuf> {
+    Ok(crate::paths::credentials_dir()?.join("credentials"))
+}
+
+/// One credential file on disk, as found by [`list_stored_credentials`].
+#[derive(Debug, Clone)]
+pub struct StoredCredential {
+    /// The profile name (file stem), or `legacy` for a `credentials.json`
+    /// that could not be migrated.
+    pub name: String,
+    pub path: PathBuf,
+    /// `None` when the file could not be parsed.
+    pub creds: Option<KakaoCredentials>,
+    pub modified: Option<SystemTime>,
+    /// Unix permission bits, if available.
+    pub mode: Option<u32>,
+}
+
+/// Every profile in [`profiles_dir`], plus a leftover legacy
+/// `credentials.json`.
+pub fn list_stored_credentials() -> Result<Vec<StoredCredential>> {
+    list_stored_credentials_in(&legacy_credentials_path()?, &profiles_dir()?)
+}
+
+fn list_stored_credentials_in(legacy: &Path, profiles: &Path) -> Result<Vec<StoredCredential>> {
+    let mut paths = Vec::new();
+    if legacy.exists() {
+        paths.push(("legacy".to_string(), legacy.to_path_buf()));
+    }
+    if profiles.is_dir() {
+        let mut named = Vec::new();
+        for entry in fs::read_dir(profiles)
+            .with_context(|| format!("Failed to read {}", profiles.display()))?
+        {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                if let Some(stem) = path.file_stem() {
+                    named.push((stem.to_string_lossy().into_owned(), path));
+                }
+            }
+        }
+        named.sort();
+        paths.extend(named);
+    }
+
+    Ok(paths
+        .into_iter()
+        .map(|(name, path)| {
+            let metadata = fs::metadata(&path).ok();
+            let creds = fs::read_to_string(&path)
+                .ok()
+                .and_then(|data| serde_json::from_str(&data).ok());
+            StoredCredential {
+                name,
+                creds,
+                modified: metadata.as_ref().and_then(|m| m.modified().ok()),
+                mode: metadata.as_ref().and_then(file_mode),
+                path,
+            }
+        })
+        .collect())
+}
+
+#[cfg(unix)]
+fn file_mode(metadata: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.mode() & 0o777)
+}
+
+#[cfg(not(unix))]
+fn file_mode(_metadata: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+/// Reset a credential file to 0600. Returns the previous mode when it changed.
+pub fn restrict_permissions(path: &Path) -> Result<Option<u32>> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let metadata =
+            fs::metadata(path).with_context(|| format!("Failed to stat {}", path.display()))?;
+        let mode = metadata.permissions().mode() & 0o777;
+        if mode != 0o600 {
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+                .with_context(|| format!("Failed to set permissions on {}", path.display()))?;
+            return Ok(Some(mode));
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(None)
+}
+
+/// Result of verifying a stored token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenCheck {
+    Valid,
+    Expired,
+    /// The check itself failed (network, rate limit); never pruned.
+    Unverified,
+    /// The file is not valid credentials JSON; reported, never pruned.
+    Unreadable,
+}
+
+/// What `credentials prune` does with one file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum PruneAction {
+    Keep,
+    DeleteExpired,
+    /// Same token as the newer file `kept`.
+    DeleteDuplicate {
+        kept: String,
+    },
+}
+
+/// Decide an action per file. `checks[i]` is the verification result for
+/// `stored[i]`. Expired tokens are deleted; among files sharing a live
+/// token only one is kept: the `active` profile, `default` or the legacy
+/// file if one of them holds it (these are never deleted as duplicates),
+/// otherwise the most recently modified.
+pub fn plan_prune(
+    stored: &[StoredCredential],
+    checks: &[TokenCheck],
+    active: &str,
+) -> Vec<PruneAction> {
+    let protected = |entry: &StoredCredential| {
+        [active, DEFAULT_PROFILE, "legacy"].contains(&entry.name.as_str())
+    };
+    let mut actions: Vec<PruneAction> = checks
+        .iter()
+        .map(|check| match check {
+            TokenCheck::Expired => PruneAction::DeleteExpired,
+            _ => PruneAction::Keep,
+        })
+        .collect();
+
+    for (idx, entry) in stored.iter().enumerate() {
+        let (Some(creds), PruneAction::Keep, false) =
+            (&entry.creds, &actions[idx], protected(entry))
+        else {
+            continue;
+        };
+        let kept = stored
+            .iter()
+            .enumerate()
+            .filter(|(j, other)| {
+                actions[*j] != PruneAction::DeleteExpired
+                    && other
+                        .creds
+                        .as_ref()
+                        .is_some_and(|c| c.oauth_token == creds.oauth_token)
+            })
+            .max_by_key(|(j, other)| (protected(other), other.modified, std::cmp::Reverse(*j)))
+            .map(|(j, _)| j)
+            .unwrap_or(idx);
+        if kept != idx {
+            actions[idx] = PruneAction::DeleteDuplicate {
+                kept: stored[kept].name.clone(),
+            };
+        }
+    }
+    actions
 }
 
 #[cfg(test)]
@@ -116,11 +639,158 @@ mod tests {
         assert_eq!(loaded.device_name, "openkakao-rs");
     }
 
+    fn stored(name: &str, token: &str, age_secs: u64) -> StoredCredential {
+        let creds = KakaoCredentials::new(
+            token.to_string(),
+            1,
+            "u".to_string(),
+            "3.7.0".to_string(),
+            "agent".to_string(),
+            "a".to_string(),
+        );
+        StoredCredential {
+            name: name.to_string(),
+            path: PathBuf::from(format!("{name}.json")),
+            creds: Some(creds),
+            modified: Some(
+                SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1000 - age_secs),
+            ),
+            mode: Some(0o600),
+        }
+    }
+
+    #[test]
+    fn prune_plan_deletes_expired_and_older_duplicates() {
+        let files = vec![
+            stored("home", "tok-a", 50),
+            stored("work", "tok-a", 10),
+            stored("old", "tok-b", 500),
+            stored("flaky", "tok-c", 5),
+        ];
+        let checks = [
+            TokenCheck::Valid,
+            TokenCheck::Valid,
+            TokenCheck::Expired,
+            TokenCheck::Unverified,
+        ];
+        assert_eq!(
+            plan_prune(&files, &checks, DEFAULT_PROFILE),
+            vec![
+                PruneAction::DeleteDuplicate {
+                    kept: "work".to_string()
+                },
+                PruneAction::Keep,
+                PruneAction::DeleteExpired,
+                PruneAction::Keep,
+            ]
+        );
+    }
+
+    #[test]
+    fn prune_plan_keeps_default_and_active_over_newer_duplicates() {
+        let files = vec![
+            stored("default", "tok-a", 50),
+            stored("work", "tok-a", 10),
+            stored("legacy", "tok-b", 500),
+            stored("home", "tok-b", 5),
+            stored("phone", "tok-c", 40),
+            stored("spare", "tok-c", 1),
+        ];
+        let checks = [TokenCheck::Valid; 6];
+        let duplicate_of = |kept: &str| PruneAction::DeleteDuplicate {
+            kept: kept.to_string(),
+        };
+        assert_eq!(
+            plan_prune(&files, &checks, "phone"),
+            vec![
+                PruneAction::Keep,
+                duplicate_of("default"),
+                PruneAction::Keep,
+                duplicate_of("legacy"),
+                PruneAction::Keep,
+                duplicate_of("phone"),
+            ]
+        );
+    }
+
+    #[test]
+    fn prune_plan_keeps_unreadable_files() {
+        let mut broken = stored("broken", "", 0);
+        broken.creds = None;
+        let files = vec![broken, stored("default", "tok", 0)];
+        let plan = plan_prune(
+            &files,
+            &[TokenCheck::Unreadable, TokenCheck::Valid],
+            DEFAULT_PROFILE,
+        );
+        assert_eq!(plan, vec![PruneAction::Keep, PruneAction::Keep]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn lists_legacy_and_profiles_and_fixes_modes() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let default = dir.path().join("credentials.json");
+        let profiles = dir.path().join("credentials");
+        fs::create_dir_all(&profiles).unwrap();
+        let data = serde_json::to_string(&stored("x", "tok", 0).creds).unwrap();
+        fs::write(&default, &data).unwrap();
+        fs::write(profiles.join("work.json"), &data).unwrap();
+        fs::write(profiles.join("notes.txt"), "ignored").unwrap();
+        fs::set_permissions(&default, fs::Permissions::from_mode(0o644)).unwrap();
+
+        let found = list_stored_credentials_in(&default, &profiles).unwrap();
+        let names: Vec<_> = found.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["legacy", "work"]);
+        assert_eq!(found[0].mode, Some(0o644));
+        assert!(found[1].creds.is_some());
+
+        assert_eq!(restrict_permissions(&default).unwrap(), Some(0o644));
+        assert_eq!(restrict_permissions(&default).unwrap(), None);
+    }
+
     #[test]
     fn test_credentials_path_not_empty() {
         let path = credentials_path().unwrap();
         assert!(path.to_string_lossy().contains("openkakao"));
-        assert!(path.to_string_lossy().ends_with("credentials.json"));
+        assert!(path.ends_with("credentials/default.json"));
+    }
+
+    #[test]
+    fn legacy_file_moves_to_the_default_profile_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let legacy = dir.path().join("credentials.json");
+        let profiles = dir.path().join("credentials");
+        assert_eq!(
+            migrate_legacy_credentials_in(&legacy, &profiles).unwrap(),
+            None
+        );
+
+        fs::write(&legacy, "old").unwrap();
+        let moved = migrate_legacy_credentials_in(&legacy, &profiles)
+            .unwrap()
+            .unwrap();
+        assert_eq!(moved, profiles.join("default.json"));
+        assert_eq!(fs::read_to_string(&moved).unwrap(), "old");
+        assert!(!legacy.exists());
+
+        // A second legacy file never replaces the migrated profile.
+        fs::write(&legacy, "newer").unwrap();
+        assert_eq!(
+            migrate_legacy_credentials_in(&legacy, &profiles).unwrap(),
+            None
+        );
+        assert_eq!(fs::read_to_string(&moved).unwrap(), "old");
+    }
+
+    #[test]
+    fn profile_names_stay_inside_the_profiles_dir() {
+        assert!(validate_profile_name("work").is_ok());
+        assert!(validate_profile_name("work_2-b").is_ok());
+        assert!(validate_profile_name("").is_err());
+        assert!(validate_profile_name("../x").is_err());
+        assert!(validate_profile_name("a.b").is_err());
     }
 
     #[cfg(unix)]
@@ -155,4 +825,140 @@ mod tests {
         let meta = std::fs::metadata(&path).unwrap();
         assert_eq!(meta.mode() & 0o777, 0o600);
     }
+
+    #[derive(Default)]
+    struct MemoryStore(std::sync::Mutex<std::collections::HashMap<String, String>>);
+
+    impl SecretStore for MemoryStore {
+        fn get(&self, profile: &str) -> Result<Option<String>> {
+            Ok(self.0.lock().unwrap().get(profile).cloned())
+        }
+
+        fn set(&self, profile: &str, secret: &str) -> Result<()> {
+            self.0
+                .lock()
+                .unwrap()
+                .insert(profile.to_string(), secret.to_string());
+            Ok(())
+        }
+
+        fn delete(&self, profile: &str) -> Result<bool> {
+            Ok(self.0.lock().unwrap().remove(profile).is_some())
+        }
+    }
+
+    fn creds(token: &str) -> KakaoCredentials {
+        KakaoCredentials::new(
+            token.to_string(),
+            7,
+            "u".to_string(),
+            "3.7.0".to_string(),
+            "agent".to_string(),
+            "a".to_string(),
+        )
+    }
+
+    fn token_at(store: &MemoryStore, path: &Path) -> Option<(String, CredentialStore)> {
+        load_from(store, "default", path)
+            .unwrap()
+            .map(|(creds, location)| (creds.oauth_token.clone(), location.store()))
+    }
+
+    #[test]
+    fn keychain_is_preferred_and_saving_moves_between_stores() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("default.json");
+        let store = MemoryStore::default();
+        assert!(token_at(&store, &path).is_none());
+        assert_eq!(
+            current_store(&store, true, "default", &path),
+            CredentialStore::Keychain
+        );
+        assert_eq!(
+            current_store(&store, false, "default", &path),
+            CredentialStore::File
+        );
+
+        save_in(
+            &store,
+            "default",
+            &path,
+            &creds("file-token"),
+            CredentialStore::File,
+        )
+        .unwrap();
+        assert_eq!(
+            token_at(&store, &path),
+            Some(("file-token".into(), CredentialStore::File))
+        );
+        // An existing file keeps later saves there.
+        assert_eq!(
+            current_store(&store, true, "default", &path),
+            CredentialStore::File
+        );
+
+        let location = save_in(
+            &store,
+            "default",
+            &path,
+            &creds("kc-token"),
+            CredentialStore::Keychain,
+        )
+        .unwrap();
+        assert_eq!(location.to_string(), "the keychain (openkakao/default)");
+        assert!(!path.exists(), "the file copy is shredded");
+        assert_eq!(
+            token_at(&store, &path),
+            Some(("kc-token".into(), CredentialStore::Keychain))
+        );
+
+        save_in(
+            &store,
+            "default",
+            &path,
+            &creds("back"),
+            CredentialStore::File,
+        )
+        .unwrap();
+        assert!(store.get("default").unwrap().is_none());
+        assert_eq!(
+            token_at(&store, &path),
+            Some(("back".into(), CredentialStore::File))
+        );
+    }
+
+    #[test]
+    fn migrate_moves_the_file_into_the_keychain_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("default.json");
+        let store = MemoryStore::default();
+        assert_eq!(migrate_in(&store, "default", &path).unwrap(), None);
+
+        write_file(&path, &creds("tok")).unwrap();
+        assert_eq!(
+            migrate_in(&store, "default", &path).unwrap(),
+            Some(path.clone())
+        );
+        assert!(!path.exists());
+        assert_eq!(
+            token_at(&store, &path),
+            Some(("tok".into(), CredentialStore::Keychain))
+        );
+
+        // A file that disagrees with the keychain is left alone.
+        write_file(&path, &creds("other")).unwrap();
+        let err = migrate_in(&store, "default", &path).unwrap_err();
+        assert!(err.to_string().contains("different credentials"), "{err}");
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn shred_overwrites_before_deleting() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secret.json");
+        fs::write(&path, b"token").unwrap();
+        shred_file(&path).unwrap();
+        assert!(!path.exists());
+        assert!(shred_file(&path).is_err());
+    }
 }
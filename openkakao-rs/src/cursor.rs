@@ -0,0 +1,182 @@
+//! Pagination cursors of the pilsner listings.
+//!
+//! `messaging/chats` and `messaging/chats/{id}/messages` both page with a
+//! bare number, but one listing's cursor means nothing to the other: the
+//! server answers a chat list cursor passed to a message listing with
+//! unrelated messages, not an error. [`ChatListCursor`] and
+//! [`MessageCursor`] keep them apart in the types, and a persisted cursor
+//! carries its kind, `{"kind": "messages", "cursor": 123}`, so a saved one
+//! is never resumed by the other listing.
+//!
+//! ```
+//! use openkakao_rs::cursor::{ChatListCursor, MessageCursor};
+//!
+//! let saved = serde_json::to_string(&MessageCursor::new(123)).unwrap();
+//! assert_eq!(saved, r#"{"kind":"messages","cursor":123}"#);
+//! assert_eq!(serde_json::from_str::<MessageCursor>(&saved).unwrap().get(), 123);
+//! assert!(serde_json::from_str::<ChatListCursor>(&saved).is_err());
+//! ```
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// A cursor of either listing, as persisted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Cursor {
+    ChatList { cursor: i64 },
+    Messages { cursor: i64 },
+}
+
+impl Cursor {
+    fn kind(&self) -> &'static str {
+        match self {
+            Cursor::ChatList { .. } => "chat list",
+            Cursor::Messages { .. } => "message",
+        }
+    }
+}
+
+/// A saved cursor of the other listing.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("expected a {expected} cursor, found a {found} cursor")]
+pub struct CursorKindMismatch {
+    pub expected: &'static str,
+    pub found: &'static str,
+}
+
+/// Where the next page of `messaging/chats` starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(into = "Cursor", try_from = "Cursor")]
+pub struct ChatListCursor(i64);
+
+impl ChatListCursor {
+    /// Wrap a number known to come from the chat list, e.g. typed in.
+    pub const fn new(raw: i64) -> Self {
+        Self(raw)
+    }
+
+    pub const fn get(self) -> i64 {
+        self.0
+    }
+}
+
+impl From<ChatListCursor> for Cursor {
+    fn from(cursor: ChatListCursor) -> Self {
+        Cursor::ChatList { cursor: cursor.0 }
+    }
+}
+
+impl TryFrom<Cursor> for ChatListCursor {
+    type Error = CursorKindMismatch;
+
+    fn try_from(cursor: Cursor) -> Result<Self, Self::Error> {
+        match cursor {
+            Cursor::ChatList { cursor } => Ok(Self(cursor)),
+            other => Err(CursorKindMismatch {
+                expected: "chat list",
+                found: other.kind(),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for ChatListCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Where the next, older page of one chat's messages starts: the log id
+/// the page ends before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(into = "Cursor", try_from = "Cursor")]
+pub struct MessageCursor(i64);
+
+impl MessageCursor {
+    /// Wrap a number known to be a message cursor or log id, e.g. `--before`.
+    pub const fn new(raw: i64) -> Self {
+        Self(raw)
+    }
+
+    pub const fn get(self) -> i64 {
+        self.0
+    }
+}
+
+impl From<MessageCursor> for Cursor {
+    fn from(cursor: MessageCursor) -> Self {
+        Cursor::Messages { cursor: cursor.0 }
+    }
+}
+
+impl TryFrom<Cursor> for MessageCursor {
+    type Error = CursorKindMismatch;
+
+    fn try_from(cursor: Cursor) -> Result<Self, Self::Error> {
+        match cursor {
+            Cursor::Messages { cursor } => Ok(Self(cursor)),
+            other => Err(CursorKindMismatch {
+                expected: "message",
+                found: other.kind(),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for MessageCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursors_round_trip_with_their_kind() {
+        let chats = serde_json::to_value(ChatListCursor::new(7)).unwrap();
+        assert_eq!(chats, serde_json::json!({"kind": "chat_list", "cursor": 7}));
+        assert_eq!(
+            serde_json::from_value::<ChatListCursor>(chats).unwrap(),
+            ChatListCursor::new(7)
+        );
+
+        let messages = serde_json::to_value(MessageCursor::new(-3)).unwrap();
+        assert_eq!(
+            serde_json::from_value::<MessageCursor>(messages.clone()).unwrap(),
+            MessageCursor::new(-3)
+        );
+        assert_eq!(
+            serde_json::from_value::<Cursor>(messages).unwrap(),
+            Cursor::Messages { cursor: -3 }
+        );
+    }
+
+    #[test]
+    fn the_other_kind_and_bare_numbers_are_refused() {
+        let chats = serde_json::to_string(&ChatListCursor::new(7)).unwrap();
+        let err = serde_json::from_str::<MessageCursor>(&chats).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("expected a message cursor, found a chat list cursor"),
+            "{err}"
+        );
+        assert!(serde_json::from_str::<MessageCursor>("123").is_err());
+        assert_eq!(
+            ChatListCursor::try_from(Cursor::from(MessageCursor::new(1))),
+            Err(CursorKindMismatch {
+                expected: "chat list",
+                found: "message",
+            })
+        );
+    }
+
+    #[test]
+    fn cursors_display_as_the_number_the_server_sent() {
+        assert_eq!(MessageCursor::new(4567).to_string(), "4567");
+        assert_eq!(ChatListCursor::new(2).to_string(), "2");
+    }
+}
@@ -0,0 +1,220 @@
+//! Day names for `--since`/`--until`: `오늘`, `어제`, `지난주`, `3일전` and
+//! their English forms, plus Korean public holidays by name when the
+//! `holidays` feature is built in.
+//!
+//! A day name always covers whole local calendar days, never an instant:
+//! as `--since` it starts at midnight of its first day, as `--until` it
+//! runs to the end of its last one. So `--since 3일전` starts at 00:00 three
+//! days ago, while the age `--since 3d` is exactly 72 hours before now.
+//! Weeks run Monday to Sunday. A holiday means its latest occurrence that
+//! has begun by today: this year's, or last year's while this year's is
+//! still ahead.
+
+use anyhow::Result;
+use chrono::{Datelike, Days, Months, NaiveDate};
+
+/// Whole days `[first, end)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DaySpan {
+    pub first: NaiveDate,
+    /// Exclusive.
+    pub end: NaiveDate,
+}
+
+impl DaySpan {
+    pub fn days(first: NaiveDate, count: u64) -> Option<Self> {
+        Some(Self {
+            first,
+            end: first.checked_add_days(Days::new(count))?,
+        })
+    }
+}
+
+/// The relative day names, Korean then English, for help and errors.
+pub const RELATIVE: &[&str] = &[
+    "오늘",
+    "어제",
+    "그제",
+    "N일전",
+    "이번주",
+    "지난주",
+    "이번달",
+    "지난달",
+    "today",
+    "yesterday",
+    "N days ago",
+    "this week",
+    "last week",
+    "this month",
+    "last month",
+];
+
+/// Every day name this build understands, for an error about one it does
+/// not.
+pub fn supported() -> String {
+    format!("{}{}", RELATIVE.join(", "), holiday_names())
+}
+
+#[cfg(feature = "holidays")]
+fn holiday_names() -> String {
+    let names: Vec<_> = crate::holidays::names().collect();
+    format!("; holidays: {}", names.join(", "))
+}
+
+#[cfg(not(feature = "holidays"))]
+fn holiday_names() -> String {
+    String::new()
+}
+
+/// Case, spaces, `-`, `_` and `'` do not matter: `Last-Week`, `last week`
+/// and `lastweek` are the same name, as are `3일 전` and `3일전`.
+pub fn normalize(input: &str) -> String {
+    input
+        .chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, '-' | '_' | '\''))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// The days `input` names as of `today`, `None` when it is no day name.
+/// A holiday outside the compiled-in table is an error.
+pub fn parse(input: &str, today: NaiveDate) -> Result<Option<DaySpan>> {
+    let key = normalize(input);
+    if let Some(span) = relative(&key, today) {
+        return Ok(Some(span));
+    }
+    #[cfg(feature = "holidays")]
+    if let Some(span) = crate::holidays::latest(&key, today) {
+        return span.map(Some);
+    }
+    Ok(None)
+}
+
+fn relative(key: &str, today: NaiveDate) -> Option<DaySpan> {
+    let days_ago = |days: u64| DaySpan::days(today.checked_sub_days(Days::new(days))?, 1);
+    let monday =
+        today.checked_sub_days(Days::new(u64::from(today.weekday().num_days_from_monday())))?;
+    let month = today.with_day(1)?;
+    match key {
+        "오늘" | "today" => days_ago(0),
+        "어제" | "yesterday" => days_ago(1),
+        "그제" | "그저께" => days_ago(2),
+        "이번주" | "thisweek" => DaySpan::days(monday, 7),
+        "지난주" | "lastweek" => DaySpan::days(monday.checked_sub_days(Days::new(7))?, 7),
+        "이번달" | "thismonth" => Some(DaySpan {
+            first: month,
+            end: month.checked_add_months(Months::new(1))?,
+        }),
+        "지난달" | "lastmonth" => Some(DaySpan {
+            first: month.checked_sub_months(Months::new(1))?,
+            end: month,
+        }),
+        _ => {
+            let count = key
+                .strip_suffix("일전")
+                .or_else(|| key.strip_suffix("daysago"))
+                .or_else(|| key.strip_suffix("dayago"))?;
+            if count.is_empty() || !count.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            days_ago(count.parse().ok()?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn span(first: &str, end: &str) -> Option<DaySpan> {
+        Some(DaySpan {
+            first: day(first),
+            end: day(end),
+        })
+    }
+
+    /// A Wednesday.
+    fn today() -> NaiveDate {
+        day("2024-05-15")
+    }
+
+    #[test]
+    fn korean_and_english_names_are_the_same_days() {
+        let cases = [
+            (["오늘", "today"], span("2024-05-15", "2024-05-16")),
+            (["어제", "Yesterday"], span("2024-05-14", "2024-05-15")),
+            (["그제", "2 days ago"], span("2024-05-13", "2024-05-14")),
+            (["3일전", "3-days-ago"], span("2024-05-12", "2024-05-13")),
+            (["1일 전", "1 day ago"], span("2024-05-14", "2024-05-15")),
+            (["이번주", "this week"], span("2024-05-13", "2024-05-20")),
+            (["지난주", "LAST_WEEK"], span("2024-05-06", "2024-05-13")),
+            (["이번달", "this month"], span("2024-05-01", "2024-06-01")),
+            (["지난달", "last month"], span("2024-04-01", "2024-05-01")),
+        ];
+        for (names, expected) in cases {
+            for name in names {
+                assert_eq!(parse(name, today()).unwrap(), expected, "{name}");
+            }
+        }
+        assert_eq!(
+            parse("그저께", today()).unwrap(),
+            parse("그제", today()).unwrap()
+        );
+    }
+
+    #[test]
+    fn weeks_start_on_monday_and_months_cross_years() {
+        // On a Monday this week starts today; on a Sunday it started six
+        // days ago.
+        assert_eq!(
+            parse("이번주", day("2024-05-13")).unwrap(),
+            span("2024-05-13", "2024-05-20")
+        );
+        assert_eq!(
+            parse("지난주", day("2024-05-19")).unwrap(),
+            span("2024-05-06", "2024-05-13")
+        );
+        assert_eq!(
+            parse("지난달", day("2024-01-31")).unwrap(),
+            span("2023-12-01", "2024-01-01")
+        );
+        assert_eq!(
+            parse("0일전", today()).unwrap(),
+            parse("오늘", today()).unwrap()
+        );
+    }
+
+    #[test]
+    fn unknown_names_are_not_day_names() {
+        for input in [
+            "",
+            "내일",
+            "tomorrow",
+            "일전",
+            "+3일전",
+            "3.5일전",
+            "x일전",
+            "days ago",
+            "3 weeks ago",
+            "99999999999999999999일전",
+            "2024-05-12",
+            "2d",
+        ] {
+            assert_eq!(parse(input, today()).unwrap(), None, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn the_supported_list_names_every_relative_literal() {
+        let list = supported();
+        for name in RELATIVE {
+            assert!(list.contains(name), "{name}");
+        }
+        #[cfg(feature = "holidays")]
+        assert!(list.contains("설날"), "{list}");
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Log ids remembered per chat by `watch` unless `--dedup-window` says
+/// otherwise.
+pub const DEFAULT_WINDOW: usize = 1000;
+
+/// At-most-once filter for streamed messages. Remembers the last `capacity`
+/// log ids of each chat; a log id seen again while still remembered is a
+/// duplicate. Once `capacity` newer ids of that chat have passed it is
+/// forgotten and would be let through again, so the guarantee only holds
+/// within the window. A capacity of 0 lets everything through.
+#[derive(Debug)]
+pub struct DedupWindow {
+    capacity: usize,
+    chats: HashMap<i64, ChatWindow>,
+}
+
+#[derive(Debug, Default)]
+struct ChatWindow {
+    order: VecDeque<i64>,
+    seen: HashSet<i64>,
+}
+
+impl DedupWindow {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            chats: HashMap::new(),
+        }
+    }
+
+    /// True the first time `log_id` shows up in `chat_id` within the window;
+    /// false for a repeat, which should be dropped.
+    pub fn first_seen(&mut self, chat_id: i64, log_id: i64) -> bool {
+        if self.capacity == 0 {
+            return true;
+        }
+        let window = self.chats.entry(chat_id).or_default();
+        if !window.seen.insert(log_id) {
+            return false;
+        }
+        window.order.push_back(log_id);
+        if window.order.len() > self.capacity {
+            if let Some(evicted) = window.order.pop_front() {
+                window.seen.remove(&evicted);
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeats_within_the_window_are_dropped() {
+        let mut window = DedupWindow::new(3);
+        assert!(window.first_seen(1, 100));
+        assert!(window.first_seen(1, 101));
+        assert!(!window.first_seen(1, 100));
+        assert!(!window.first_seen(1, 101));
+        // Same log id in another chat is a different message.
+        assert!(window.first_seen(2, 100));
+    }
+
+    #[test]
+    fn evicted_ids_may_reappear() {
+        let mut window = DedupWindow::new(2);
+        assert!(window.first_seen(1, 1));
+        assert!(window.first_seen(1, 2));
+        assert!(window.first_seen(1, 3));
+        // 1 fell out of the window; 2 and 3 are still remembered.
+        assert!(window.first_seen(1, 1));
+        assert!(!window.first_seen(1, 3));
+        // Re-admitting 1 pushed out 2.
+        assert!(window.first_seen(1, 2));
+        // Other chats have their own window.
+        assert!(window.first_seen(9, 1));
+        assert!(!window.first_seen(1, 1));
+    }
+
+    #[test]
+    fn zero_capacity_disables_dedup() {
+        let mut window = DedupWindow::new(0);
+        assert!(window.first_seen(1, 1));
+        assert!(window.first_seen(1, 1));
+    }
+}
@@ -0,0 +1,658 @@
+//! Bounded parallel downloads with retry, resume and a per-item report.
+//!
+//! Batch commands hand [`run`] a list of [`DownloadJob`]s and something that
+//! implements [`Fetch`] (the CLI's REST client; tests use an in-memory fake).
+//! At most [`PoolOptions::jobs`] files are in flight at once. A failed
+//! attempt keeps what it wrote in `<path>.part` and the next attempt asks the
+//! fetcher to continue from there, so a flaky connection does not restart a
+//! large file. One item failing never stops the others; every item ends up
+//! in the returned results as succeeded or failed, with the reason.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::retry::RetryPolicy;
+
+/// Files downloaded at once unless `--jobs` says otherwise.
+pub const DEFAULT_JOBS: usize = 4;
+
+/// Spacing between request starts, shared by every thread holding it, so
+/// parallel workers (and API calls made alongside them) together stay under
+/// one request rate.
+#[derive(Debug)]
+pub struct Throttle {
+    interval: Duration,
+    next: Mutex<Option<Instant>>,
+}
+
+impl Throttle {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            next: Mutex::new(None),
+        }
+    }
+
+    /// Block until this caller's slot. Slots are handed out in call order,
+    /// `interval` apart; the first one is immediate.
+    pub fn wait(&self) {
+        self.wait_for(self.interval);
+    }
+
+    /// [`Self::wait`] with the next slot `interval` after this one, for a
+    /// caller whose spacing changes between requests.
+    pub fn wait_for(&self, interval: Duration) {
+        if interval.is_zero() {
+            return;
+        }
+        let slot = {
+            let mut next = self.next.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            let slot = next.map_or(now, |next| next.max(now));
+            *next = Some(slot + interval);
+            slot
+        };
+        std::thread::sleep(slot.saturating_duration_since(Instant::now()));
+    }
+}
+
+/// A download in progress, kept as `<path>.part` until it completes. Bytes
+/// from an earlier attempt (or an earlier run) are kept and appended to.
+#[derive(Debug)]
+pub struct PartialFile {
+    path: PathBuf,
+    file: File,
+    offset: u64,
+    restarted: bool,
+}
+
+impl PartialFile {
+    pub fn open(target: &Path) -> Result<Self> {
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let mut name = target.file_name().unwrap_or_default().to_os_string();
+        name.push(".part");
+        let path = target.with_file_name(name);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        let offset = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file,
+            offset,
+            restarted: false,
+        })
+    }
+
+    /// Bytes already on disk; a fetch should ask for the rest from here.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Drop what was written so far, for a server that answered a range
+    /// request with the whole file.
+    pub fn restart(&mut self) -> io::Result<()> {
+        self.file.set_len(0)?;
+        self.file.seek(SeekFrom::Start(0))?;
+        self.offset = 0;
+        self.restarted = true;
+        Ok(())
+    }
+
+    fn finish(mut self, target: &Path) -> Result<()> {
+        self.file.flush()?;
+        fs::rename(&self.path, target)
+            .with_context(|| format!("Failed to move download to {}", target.display()))
+    }
+
+    fn discard_if_empty(&self) {
+        if self.offset == 0 {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+impl Write for PartialFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.file.write(buf)?;
+        self.offset += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// The transport [`run`] downloads through.
+pub trait Fetch: Sync {
+    /// Write `url`'s body into `out`. When `out.offset()` is non-zero, ask
+    /// only for the bytes after it (an HTTP `Range` request); if the server
+    /// sends the whole file instead, call [`PartialFile::restart`] before
+    /// writing. Errors the retry policy calls transient are retried.
+    fn fetch(&self, url: &str, out: &mut PartialFile) -> Result<()>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadJob {
+    /// What the report calls this item (a name, a log id).
+    pub label: String,
+    pub url: String,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Outcome {
+    Succeeded {
+        bytes: u64,
+        attempts: u32,
+        /// Finished from bytes an earlier attempt or run left behind.
+        resumed: bool,
+    },
+    Skipped {
+        reason: String,
+    },
+    Failed {
+        reason: String,
+        attempts: u32,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DownloadResult {
+    pub label: String,
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PoolOptions {
+    /// Downloads in flight at once; at least 1.
+    pub jobs: usize,
+    pub retry: RetryPolicy,
+}
+
+impl Default for PoolOptions {
+    fn default() -> Self {
+        Self {
+            jobs: DEFAULT_JOBS,
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+/// Download every job with at most `options.jobs` in flight. Results come
+/// back in job order.
+pub fn run<F: Fetch + ?Sized>(
+    fetcher: &F,
+    jobs: Vec<DownloadJob>,
+    options: PoolOptions,
+) -> Vec<DownloadResult> {
+    let total = jobs.len();
+    let queue = Mutex::new(jobs.into_iter().enumerate());
+    let results = Mutex::new(vec![None; total]);
+    std::thread::scope(|scope| {
+        for _ in 0..options.jobs.clamp(1, total.max(1)) {
+            scope.spawn(|| loop {
+                let next = queue.lock().unwrap_or_else(|e| e.into_inner()).next();
+                let Some((index, job)) = next else {
+                    break;
+                };
+                let outcome = download_one(fetcher, &job, &options.retry);
+                results.lock().unwrap_or_else(|e| e.into_inner())[index] = Some(DownloadResult {
+                    label: job.label,
+                    path: job.path,
+                    outcome,
+                });
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner())
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+fn download_one<F: Fetch + ?Sized>(fetcher: &F, job: &DownloadJob, retry: &RetryPolicy) -> Outcome {
+    let mut part = match PartialFile::open(&job.path) {
+        Ok(part) => part,
+        Err(err) => {
+            return Outcome::Failed {
+                reason: format!("{err:#}"),
+                attempts: 0,
+            }
+        }
+    };
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let start = part.offset();
+        part.restarted = false;
+        match fetcher.fetch(&job.url, &mut part) {
+            Ok(()) => {
+                let bytes = part.offset();
+                let resumed = start > 0 && !part.restarted;
+                return match part.finish(&job.path) {
+                    Ok(()) => Outcome::Succeeded {
+                        bytes,
+                        attempts,
+                        resumed,
+                    },
+                    Err(err) => Outcome::Failed {
+                        reason: format!("{err:#}"),
+                        attempts,
+                    },
+                };
+            }
+            Err(err) if is_byte_cap(&err) => {
+                part.discard_if_empty();
+                return Outcome::Skipped {
+                    reason: "--max-bytes reached".to_string(),
+                };
+            }
+            Err(err) if attempts < retry.max_attempts && retry.is_retryable_error(&err) => {
+                std::thread::sleep(retry.delay(attempts));
+            }
+            Err(err) => {
+                part.discard_if_empty();
+                return Outcome::Failed {
+                    reason: format!("{err:#}"),
+                    attempts,
+                };
+            }
+        }
+    }
+}
+
+/// Whether `err` is the byte cap, which no retry gets past.
+fn is_byte_cap(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<crate::error::OpenKakaoError>(),
+        Some(crate::error::OpenKakaoError::ByteCapReached { .. })
+    )
+}
+
+/// Succeeded / skipped / failed tallies of a batch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ReportCounts {
+    pub succeeded: usize,
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+pub fn counts(results: &[DownloadResult]) -> ReportCounts {
+    let mut counts = ReportCounts::default();
+    for result in results {
+        match result.outcome {
+            Outcome::Succeeded { .. } => counts.succeeded += 1,
+            Outcome::Skipped { .. } => counts.skipped += 1,
+            Outcome::Failed { .. } => counts.failed += 1,
+        }
+    }
+    counts
+}
+
+/// `[status, item, reason]` rows summarizing what did not simply succeed:
+/// each failure on its own row, skips grouped by reason.
+pub fn summary_rows(results: &[DownloadResult]) -> Vec<[String; 3]> {
+    let mut rows = Vec::new();
+    let mut skipped: Vec<(&str, usize)> = Vec::new();
+    for result in results {
+        match &result.outcome {
+            Outcome::Failed { reason, attempts } => rows.push([
+                "failed".to_string(),
+                result.label.clone(),
+                format!("{reason} (after {attempts} attempt(s))"),
+            ]),
+            Outcome::Skipped { reason } => match skipped.iter_mut().find(|(r, _)| r == reason) {
+                Some((_, count)) => *count += 1,
+                None => skipped.push((reason, 1)),
+            },
+            Outcome::Succeeded { .. } => {}
+        }
+    }
+    rows.extend(skipped.into_iter().map(|(reason, count)| {
+        [
+            "skipped".to_string(),
+            format!("{count} item(s)"),
+            reason.to_string(),
+        ]
+    }));
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::error::OpenKakaoError;
+
+    fn quick_retry(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            backoff_base: Duration::from_millis(1),
+            jitter: 0.0,
+            ..RetryPolicy::default()
+        }
+    }
+
+    fn job(dir: &Path, name: &str) -> DownloadJob {
+        DownloadJob {
+            label: name.to_string(),
+            url: format!("https://talk.kakaocdn.net/{name}"),
+            path: dir.join(name),
+        }
+    }
+
+    /// In-memory server: each URL's body, plus scripted trouble.
+    #[derive(Default)]
+    struct MockTransport {
+        bodies: HashMap<String, Vec<u8>>,
+        /// Attempts per URL that die after writing this many bytes.
+        drop_after: HashMap<String, (usize, u32)>,
+        /// URLs whose server ignores Range and always sends everything.
+        no_ranges: bool,
+        calls: Mutex<HashMap<String, Vec<u64>>>,
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+        delay: Duration,
+        /// Checked against each body's length before it is sent, like the
+        /// client checks `Content-Length`.
+        meter: Option<std::sync::Arc<crate::bandwidth::ByteMeter>>,
+    }
+
+    impl MockTransport {
+        fn with(bodies: &[(&str, &[u8])]) -> Self {
+            Self {
+                bodies: bodies
+                    .iter()
+                    .map(|(name, body)| {
+                        (format!("https://talk.kakaocdn.net/{name}"), body.to_vec())
+                    })
+                    .collect(),
+                ..Self::default()
+            }
+        }
+
+        fn offsets(&self, name: &str) -> Vec<u64> {
+            self.calls.lock().unwrap()[&format!("https://talk.kakaocdn.net/{name}")].clone()
+        }
+    }
+
+    impl Fetch for MockTransport {
+        fn fetch(&self, url: &str, out: &mut PartialFile) -> Result<()> {
+            let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(self.delay);
+            let result = (|| {
+                let attempt = {
+                    let mut calls = self.calls.lock().unwrap();
+                    let offsets = calls.entry(url.to_string()).or_default();
+                    offsets.push(out.offset());
+                    offsets.len() as u32
+                };
+                let Some(body) = self.bodies.get(url) else {
+                    return Err(OpenKakaoError::Network {
+                        message: format!("HTTP 404: {url}"),
+                        is_transient: false,
+                    }
+                    .into());
+                };
+                if self.no_ranges {
+                    out.restart()?;
+                }
+                let rest = &body[out.offset() as usize..];
+                if let Some(meter) = &self.meter {
+                    meter.check(rest.len() as u64)?;
+                    meter.record(rest.len() as u64);
+                }
+                match self.drop_after.get(url) {
+                    Some((cut, failing)) if attempt <= *failing => {
+                        let cut = (*cut).min(rest.len());
+                        out.write_all(&rest[..cut])?;
+                        anyhow::bail!("connection reset")
+                    }
+                    _ => Ok(out.write_all(rest)?),
+                }
+            })();
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            result
+        }
+    }
+
+    #[test]
+    fn transient_failures_are_retried_and_resumed() {
+        let dir = tempfile::tempdir().unwrap();
+        let body = b"0123456789abcdef";
+        let mut transport = MockTransport::with(&[("a.jpg", body)]);
+        let url = "https://talk.kakaocdn.net/a.jpg".to_string();
+        transport.drop_after.insert(url, (5, 2));
+
+        let results = run(
+            &transport,
+            vec![job(dir.path(), "a.jpg")],
+            PoolOptions {
+                jobs: 2,
+                retry: quick_retry(4),
+            },
+        );
+        assert_eq!(
+            results[0].outcome,
+            Outcome::Succeeded {
+                bytes: 16,
+                attempts: 3,
+                resumed: true,
+            }
+        );
+        // Each retry asked for the bytes after what was already written.
+        assert_eq!(transport.offsets("a.jpg"), [0, 5, 10]);
+        assert_eq!(fs::read(dir.path().join("a.jpg")).unwrap(), body);
+        assert!(!dir.path().join("a.jpg.part").exists());
+    }
+
+    #[test]
+    fn servers_without_ranges_restart_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let body = b"0123456789";
+        let mut transport = MockTransport::with(&[("b.jpg", body)]);
+        transport.no_ranges = true;
+        transport
+            .drop_after
+            .insert("https://talk.kakaocdn.net/b.jpg".into(), (4, 1));
+
+        let results = run(
+            &transport,
+            vec![job(dir.path(), "b.jpg")],
+            PoolOptions {
+                jobs: 1,
+                retry: quick_retry(3),
+            },
+        );
+        assert!(matches!(
+            results[0].outcome,
+            Outcome::Succeeded {
+                bytes: 10,
+                resumed: false,
+                ..
+            }
+        ));
+        assert_eq!(fs::read(dir.path().join("b.jpg")).unwrap(), body);
+    }
+
+    #[test]
+    fn a_leftover_part_file_is_resumed() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("c.jpg.part"), b"hello ").unwrap();
+        let transport = MockTransport::with(&[("c.jpg", b"hello world")]);
+        let results = run(
+            &transport,
+            vec![job(dir.path(), "c.jpg")],
+            PoolOptions::default(),
+        );
+        assert!(matches!(
+            results[0].outcome,
+            Outcome::Succeeded { resumed: true, .. }
+        ));
+        assert_eq!(transport.offsets("c.jpg"), [6]);
+        assert_eq!(fs::read(dir.path().join("c.jpg")).unwrap(), b"hello world");
+    }
+
+    #[test]
+    fn failures_do_not_stop_the_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut transport = MockTransport::with(&[("ok1", b"1"), ("flaky", b"22"), ("ok2", b"3")]);
+        transport
+            .drop_after
+            .insert("https://talk.kakaocdn.net/flaky".into(), (1, 99));
+
+        let jobs = ["ok1", "missing", "flaky", "ok2"]
+            .iter()
+            .map(|name| job(dir.path(), name))
+            .collect();
+        let results = run(
+            &transport,
+            jobs,
+            PoolOptions {
+                jobs: 2,
+                retry: quick_retry(3),
+            },
+        );
+        let labels: Vec<&str> = results.iter().map(|r| r.label.as_str()).collect();
+        assert_eq!(labels, ["ok1", "missing", "flaky", "ok2"]);
+        // A permanent error is not retried; a transient one runs out of tries.
+        assert!(matches!(
+            &results[1].outcome,
+            Outcome::Failed { attempts: 1, reason } if reason.contains("404")
+        ));
+        assert!(matches!(
+            &results[2].outcome,
+            Outcome::Failed { attempts: 3, reason } if reason.contains("connection reset")
+        ));
+        assert_eq!(
+            counts(&results),
+            ReportCounts {
+                succeeded: 2,
+                skipped: 0,
+                failed: 2,
+            }
+        );
+        // The half-written file stays for a later resume; the empty one goes.
+        assert!(dir.path().join("flaky.part").exists());
+        assert!(!dir.path().join("missing.part").exists());
+
+        let mut results = results;
+        for label in ["a", "b"] {
+            results.push(DownloadResult {
+                label: label.into(),
+                path: dir.path().join(label),
+                outcome: Outcome::Skipped {
+                    reason: "unchanged".into(),
+                },
+            });
+        }
+        let rows = summary_rows(&results);
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0][1], "missing");
+        assert!(rows[1][2].ends_with("(after 3 attempt(s))"));
+        assert_eq!(rows[2], ["skipped", "2 item(s)", "unchanged"]);
+    }
+
+    #[test]
+    fn files_past_the_byte_cap_are_skipped_not_failed() {
+        let dir = tempfile::tempdir().unwrap();
+        let meter = std::sync::Arc::new(crate::bandwidth::ByteMeter::with_max(25));
+        let mut transport = MockTransport::with(&[
+            ("a.jpg", &[1; 10]),
+            ("b.jpg", &[2; 10]),
+            ("c.jpg", &[3; 10]),
+            ("d.jpg", &[4; 5]),
+        ]);
+        transport.meter = Some(meter.clone());
+
+        let jobs = ["a.jpg", "b.jpg", "c.jpg", "d.jpg"]
+            .iter()
+            .map(|name| job(dir.path(), name))
+            .collect();
+        let results = run(
+            &transport,
+            jobs,
+            PoolOptions {
+                jobs: 1,
+                retry: quick_retry(3),
+            },
+        );
+        let statuses: Vec<_> = results
+            .iter()
+            .map(|r| matches!(r.outcome, Outcome::Succeeded { .. }))
+            .collect();
+        // c does not fit in what is left; the smaller d still does.
+        assert_eq!(statuses, [true, true, false, true]);
+        assert_eq!(
+            results[2].outcome,
+            Outcome::Skipped {
+                reason: "--max-bytes reached".into()
+            }
+        );
+        assert_eq!(transport.offsets("c.jpg"), [0]);
+        assert!(!dir.path().join("c.jpg.part").exists());
+        assert_eq!(meter.received(), 25);
+    }
+
+    #[test]
+    fn concurrency_is_capped() {
+        let dir = tempfile::tempdir().unwrap();
+        let names: Vec<String> = (0..12).map(|i| format!("f{i}")).collect();
+        let bodies: Vec<(&str, &[u8])> = names.iter().map(|n| (n.as_str(), &b"x"[..])).collect();
+        let mut transport = MockTransport::with(&bodies);
+        transport.delay = Duration::from_millis(20);
+
+        let jobs = names.iter().map(|name| job(dir.path(), name)).collect();
+        let results = run(
+            &transport,
+            jobs,
+            PoolOptions {
+                jobs: 3,
+                retry: quick_retry(1),
+            },
+        );
+        assert_eq!(counts(&results).succeeded, 12);
+        let peak = transport.max_in_flight.load(Ordering::SeqCst);
+        assert!((1..=3).contains(&peak), "peak {peak}");
+    }
+
+    #[test]
+    fn throttle_spaces_out_request_starts() {
+        let throttle = Throttle::new(Duration::from_millis(15));
+        let started = Instant::now();
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| throttle.wait());
+            }
+        });
+        assert!(started.elapsed() >= Duration::from_millis(45));
+
+        let unthrottled = Throttle::new(Duration::ZERO);
+        let started = Instant::now();
+        for _ in 0..100 {
+            unthrottled.wait();
+        }
+        assert!(started.elapsed() < Duration::from_millis(15));
+    }
+}
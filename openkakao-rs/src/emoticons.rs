@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Labels for the default emoticons most chats use, keyed by resource id:
+/// the attachment `path` without its extension (`2202001.emot_001.webp` →
+/// `2202001.emot_001`). Add new entries anywhere; the order does not matter.
+const KNOWN: &[(&str, &str)] = &[
+    // 카카오프렌즈 기본 이모티콘
+    ("2202001.emot_001", "라이언 하트"),
+    ("2202001.emot_002", "라이언 엄지척"),
+    ("2202001.emot_003", "라이언 인사"),
+    ("2202001.emot_004", "어피치 하트"),
+    ("2202001.emot_005", "어피치 웃음"),
+    ("2202001.emot_006", "무지 눈물"),
+    ("2202001.emot_007", "무지 놀람"),
+    ("2202001.emot_008", "프로도 축하"),
+    ("2202001.emot_009", "네오 윙크"),
+    ("2202001.emot_010", "튜브 화남"),
+    ("2202001.emot_011", "제이지 감사"),
+    ("2202001.emot_012", "콘 박수"),
+    // 춘식이 기본 이모티콘
+    ("2202002.emot_001", "춘식이 하트"),
+    ("2202002.emot_002", "춘식이 인사"),
+    ("2202002.emot_003", "춘식이 졸림"),
+    ("2202002.emot_004", "춘식이 감사"),
+];
+
+/// Names the app puts in `name` for every emoticon; they say nothing about
+/// which one was sent.
+const GENERIC_NAMES: &[&str] = &["(Emoticons)", "(이모티콘)", "(emoticon)"];
+
+const IMAGE_EXTENSIONS: &[&str] = &["png", "gif", "webp", "jpg", "jpeg"];
+
+fn labels() -> &'static HashMap<&'static str, &'static str> {
+    static LABELS: OnceLock<HashMap<&'static str, &'static str>> = OnceLock::new();
+    LABELS.get_or_init(|| KNOWN.iter().copied().collect())
+}
+
+/// The resource id of an emoticon attachment `path`: its file name without
+/// the extension. Empty when `path` is.
+pub fn resource_id(path: &str) -> &str {
+    let file = path.rsplit('/').next().unwrap_or(path);
+    match file.rsplit_once('.') {
+        Some((stem, ext)) if IMAGE_EXTENSIONS.contains(&ext) => stem,
+        _ => file,
+    }
+}
+
+/// Human label for a known resource id.
+pub fn label(resource: &str) -> Option<&'static str> {
+    labels().get(resource).copied()
+}
+
+/// `(이모티콘: 라이언 하트)` for a known resource, otherwise the sender's alt
+/// text, otherwise the raw resource id.
+pub fn render(resource: &str, name: &str) -> String {
+    let text = label(resource)
+        .map(String::from)
+        .or_else(|| {
+            let name = name.trim();
+            (!name.is_empty() && !GENERIC_NAMES.contains(&name)).then(|| name.to_string())
+        })
+        .or_else(|| (!resource.is_empty()).then(|| resource.to_string()));
+    match text {
+        Some(text) => format!("(이모티콘: {})", text),
+        None => "(이모티콘)".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resource_id_strips_directory_and_extension() {
+        assert_eq!(resource_id("2202001.emot_001.webp"), "2202001.emot_001");
+        assert_eq!(resource_id("dw/2202001.emot_001.png"), "2202001.emot_001");
+        assert_eq!(resource_id("2202001.emot_001"), "2202001.emot_001");
+        assert_eq!(resource_id(""), "");
+    }
+
+    #[test]
+    fn known_ids_render_their_label() {
+        assert_eq!(label("2202001.emot_001"), Some("라이언 하트"));
+        assert_eq!(
+            render("2202001.emot_001", "(Emoticons)"),
+            "(이모티콘: 라이언 하트)"
+        );
+    }
+
+    #[test]
+    fn unknown_ids_fall_back_without_panicking() {
+        assert_eq!(label("9999999.emot_042"), None);
+        assert_eq!(
+            render("9999999.emot_042", "(이모티콘)"),
+            "(이모티콘: 9999999.emot_042)"
+        );
+        assert_eq!(
+            render("9999999.emot_042", "춘식이 하이"),
+            "(이모티콘: 춘식이 하이)"
+        );
+        assert_eq!(render("", ""), "(이모티콘)");
+        assert_eq!(render("..", "   "), "(이모티콘: ..)");
+    }
+
+    #[test]
+    fn table_has_no_duplicate_ids() {
+        assert_eq!(labels().len(), KNOWN.len());
+    }
+}
@@ -0,0 +1,181 @@
+use std::time::Duration;
+
+/// One configured katalk base URL and what this run has seen of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoint {
+    pub base: String,
+    /// Probe round-trip on first use; `None` when unreachable or not probed.
+    pub latency: Option<Duration>,
+    pub failures: u32,
+}
+
+/// Mirrors from `[http] base_urls`, kept for the process lifetime. The
+/// first katalk request probes each one; after that the fastest is used
+/// and connect errors or timeouts move on to the next in latency order.
+#[derive(Debug)]
+pub struct EndpointPool {
+    endpoints: Vec<Endpoint>,
+    /// Indexes into `endpoints`, most preferred first.
+    order: Vec<usize>,
+    active: usize,
+    probed: bool,
+    failovers: u32,
+}
+
+impl EndpointPool {
+    /// Trailing slashes are dropped and duplicates ignored; config order is
+    /// the preference until latencies are known.
+    pub fn new(bases: &[String]) -> Self {
+        let mut endpoints: Vec<Endpoint> = Vec::new();
+        for base in bases {
+            let base = base.trim_end_matches('/').to_string();
+            if !base.is_empty() && !endpoints.iter().any(|e| e.base == base) {
+                endpoints.push(Endpoint {
+                    base,
+                    latency: None,
+                    failures: 0,
+                });
+            }
+        }
+        Self {
+            order: (0..endpoints.len()).collect(),
+            endpoints,
+            active: 0,
+            probed: false,
+            failovers: 0,
+        }
+    }
+
+    /// A single mirror is used as-is, without probing.
+    pub fn needs_probe(&self) -> bool {
+        !self.probed && self.endpoints.len() > 1
+    }
+
+    pub fn bases(&self) -> Vec<String> {
+        self.endpoints.iter().map(|e| e.base.clone()).collect()
+    }
+
+    /// Store probe results (same order as [`bases`](Self::bases)) and prefer
+    /// the fastest reachable mirror. Unreachable ones go last, in config order.
+    pub fn record_probe(&mut self, latencies: &[Option<Duration>]) {
+        for (endpoint, latency) in self.endpoints.iter_mut().zip(latencies) {
+            endpoint.latency = *latency;
+        }
+        let endpoints = &self.endpoints;
+        self.order
+            .sort_by_key(|&idx| (endpoints[idx].latency.is_none(), endpoints[idx].latency));
+        self.active = self.order.first().copied().unwrap_or(0);
+        self.probed = true;
+    }
+
+    /// Bases to try for one request: the active mirror, then the rest.
+    pub fn candidates(&self) -> Vec<String> {
+        std::iter::once(self.active)
+            .chain(self.order.iter().copied().filter(|&idx| idx != self.active))
+            .filter_map(|idx| self.endpoints.get(idx))
+            .map(|e| e.base.clone())
+            .collect()
+    }
+
+    /// `base` answered; keep using it.
+    pub fn succeeded(&mut self, base: &str) {
+        if let Some(idx) = self.endpoints.iter().position(|e| e.base == base) {
+            self.active = idx;
+        }
+    }
+
+    /// `base` failed to connect or timed out.
+    pub fn failed(&mut self, base: &str) {
+        if let Some(endpoint) = self.endpoints.iter_mut().find(|e| e.base == base) {
+            endpoint.failures += 1;
+            self.failovers += 1;
+        }
+    }
+
+    /// One line for `--stats`.
+    pub fn summary(&self) -> String {
+        let endpoints = self
+            .order
+            .iter()
+            .map(|&idx| {
+                let e = &self.endpoints[idx];
+                let latency = match e.latency {
+                    Some(latency) => format!("{}ms", latency.as_millis()),
+                    None if self.probed => "unreachable".to_string(),
+                    None => "not probed".to_string(),
+                };
+                let active = if idx == self.active { ", active" } else { "" };
+                let failures = if e.failures > 0 {
+                    format!(", {} failed", e.failures)
+                } else {
+                    String::new()
+                };
+                format!("{} ({}{}{})", e.base, latency, active, failures)
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("{}; failovers={}", endpoints, self.failovers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(bases: &[&str]) -> EndpointPool {
+        EndpointPool::new(&bases.iter().map(|b| b.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn fastest_reachable_mirror_is_preferred() {
+        let mut pool = pool(&[
+            "https://a.kakao.com/",
+            "https://b.kakao.com",
+            "https://c.kakao.com",
+        ]);
+        assert!(pool.needs_probe());
+        pool.record_probe(&[
+            Some(Duration::from_millis(900)),
+            None,
+            Some(Duration::from_millis(40)),
+        ]);
+        assert!(!pool.needs_probe());
+        assert_eq!(
+            pool.candidates(),
+            [
+                "https://c.kakao.com",
+                "https://a.kakao.com",
+                "https://b.kakao.com"
+            ]
+        );
+        assert_eq!(
+            pool.summary(),
+            "https://c.kakao.com (40ms, active), https://a.kakao.com (900ms), \
+             https://b.kakao.com (unreachable); failovers=0"
+        );
+    }
+
+    #[test]
+    fn failover_moves_the_active_mirror() {
+        let mut pool = pool(&["https://a.kakao.com", "https://b.kakao.com"]);
+        pool.record_probe(&[
+            Some(Duration::from_millis(10)),
+            Some(Duration::from_millis(20)),
+        ]);
+        pool.failed("https://a.kakao.com");
+        pool.succeeded("https://b.kakao.com");
+        assert_eq!(
+            pool.candidates(),
+            ["https://b.kakao.com", "https://a.kakao.com"]
+        );
+        assert!(pool.summary().ends_with("failovers=1"));
+        assert!(pool.summary().contains("(10ms, 1 failed)"));
+    }
+
+    #[test]
+    fn single_mirror_is_never_probed() {
+        let pool = pool(&["https://a.kakao.com", "https://a.kakao.com/"]);
+        assert!(!pool.needs_probe());
+        assert_eq!(pool.candidates(), ["https://a.kakao.com"]);
+    }
+}
@@ -1,9 +1,12 @@
 use thiserror::Error;
 
+use crate::status::KakaoStatus;
+
 /// Primary error type for openkakao-rs operations.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum OpenKakaoError {
-    #[error("LOCO command {command} failed (status={status})")]
+    #[error("LOCO command {command} failed (status={status})\n  {}", KakaoStatus::from_code(*status))]
     LocoStatus {
         command: String,
         status: i64,
@@ -16,27 +19,225 @@ pub enum OpenKakaoError {
     #[error("Network error: {message}")]
     Network { message: String, is_transient: bool },
 
-    #[error("REST API error (status={status}): {message}")]
+    #[error("REST API error (status={status}): {message}\n  {}", KakaoStatus::from_code(*status))]
     RestApi { status: i64, message: String },
 
+    /// HTTP 401, or pilsner's `UNAUTHENTICATED`: the token is expired or
+    /// not one this endpoint takes. `message` is the server's, or a short
+    /// description of the body when it was not JSON.
+    #[error("{} ({message})\n  {}", KakaoStatus::HttpUnauthorized.explanation(), KakaoStatus::HttpUnauthorized.suggestion())]
+    Unauthorized { message: String },
+
+    /// HTTP 403: the token is not allowed this endpoint, or the account is
+    /// blocked from it.
+    #[error("{} ({message})\n  {}", KakaoStatus::HttpForbidden.explanation(), KakaoStatus::HttpForbidden.suggestion())]
+    Forbidden { message: String },
+
+    /// HTTP 429. The request was not processed and can be sent again.
+    #[error("Rate limited by Kakao{}", retry_after.map(|wait| format!("; retry after {}s", wait.as_secs())).unwrap_or_default())]
+    RateLimited {
+        /// From a `Retry-After` header given in seconds.
+        retry_after: Option<std::time::Duration>,
+    },
+
+    /// Any other non-success HTTP status whose body names no Kakao status.
+    #[error("HTTP {status}: {body}")]
+    Http {
+        status: u16,
+        /// What the body was, shortened: an HTML error page becomes its
+        /// title and an empty body says so.
+        body: String,
+        /// From a `Retry-After` header given in seconds (503).
+        retry_after: Option<std::time::Duration>,
+    },
+
+    /// The response was not the JSON expected, or was cut off at the size
+    /// limit.
+    #[error("{message}")]
+    Parse { message: String },
+
+    /// The next request or download would pass `--max-bytes`. What was
+    /// fetched before it is complete and usable.
+    #[error("Byte cap reached: {received} bytes received, --max-bytes is {max}")]
+    ByteCapReached { received: u64, max: u64 },
+
+    /// Kakao has no profile to show for this user: the account was deleted
+    /// or its owner blocked us.
+    #[error(
+        "Profile of user {user_id} is unavailable: the account was deleted or has blocked you"
+    )]
+    ProfileUnavailable { user_id: i64 },
+
     #[error("Safety block: {0}")]
     SafetyBlock(String),
 
-    #[error("{0}")]
-    Other(#[from] anyhow::Error),
+    #[error("Schema drift in chat {chat_id} at logId {log_id}: {issue}\n  payload (redacted): {snippet}")]
+    SchemaDrift {
+        chat_id: i64,
+        log_id: i64,
+        issue: String,
+        snippet: String,
+    },
+
+    #[error("KakaoTalk container not found at {}", .0.display())]
+    ContainerNotFound(std::path::PathBuf),
+
+    #[error("no credentials available and {reason}; run `openkakao-rs login --save` with KakaoTalk running, or set OPENKAKAO_TOKEN")]
+    NoCredentials { reason: String },
+
+    #[error(transparent)]
+    Other(anyhow::Error),
 }
 
+/// Result of the library's public API.
+pub type Result<T, E = OpenKakaoError> = std::result::Result<T, E>;
+
 impl OpenKakaoError {
     /// Whether this error is transient and the operation should be retried.
+    ///
+    /// An expired token counts as retryable because the same request
+    /// succeeds once credentials are refreshed; check [`is_auth_failure`]
+    /// first when the loop cannot refresh them.
+    ///
+    /// ```
+    /// use openkakao_rs::error::OpenKakaoError;
+    ///
+    /// assert!(OpenKakaoError::loco("SYNCMSG", -300).is_retryable());
+    /// assert!(!OpenKakaoError::loco("WRITE", -203).is_retryable());
+    /// assert!(OpenKakaoError::loco("LOGINLIST", -950).is_retryable());
+    /// let blip = OpenKakaoError::Http { status: 503, body: String::new(), retry_after: None };
+    /// assert!(blip.is_retryable());
+    /// ```
+    ///
+    /// [`is_auth_failure`]: Self::is_auth_failure
     pub fn is_retryable(&self) -> bool {
         match self {
             Self::LocoStatus { status, .. } => matches!(status, -300 | -500),
-            Self::TokenExpired => true,
+            Self::TokenExpired | Self::RateLimited { .. } => true,
             Self::Network { is_transient, .. } => *is_transient,
+            Self::Http { status, .. } => *status == 429 || *status >= 500,
             _ => false,
         }
     }
 
+    /// Whether the server rejected the credentials (expired token, login
+    /// required, wrong password, unverified device) or none were available.
+    /// Retrying without new credentials will not help.
+    ///
+    /// ```
+    /// use openkakao_rs::error::OpenKakaoError;
+    ///
+    /// assert!(OpenKakaoError::TokenExpired.is_auth_failure());
+    /// let rest = OpenKakaoError::RestApi { status: -500, message: String::new() };
+    /// assert!(!rest.is_auth_failure());
+    /// ```
+    pub fn is_auth_failure(&self) -> bool {
+        match self {
+            Self::NoCredentials { .. } => true,
+            _ => matches!(
+                self.kakao_status(),
+                Some(
+                    KakaoStatus::AuthRejected
+                        | KakaoStatus::AuthRequired
+                        | KakaoStatus::WrongPassword
+                        | KakaoStatus::VerificationRequired
+                        | KakaoStatus::DeviceNotRegistered
+                )
+            ),
+        }
+    }
+
+    /// Raw Kakao status code behind this error (`-950` for an expired
+    /// token), or `None` for network, safety and local errors.
+    ///
+    /// ```
+    /// use openkakao_rs::error::OpenKakaoError;
+    ///
+    /// assert_eq!(OpenKakaoError::loco("WRITE", -203).status_code(), Some(-203));
+    /// assert_eq!(OpenKakaoError::TokenExpired.status_code(), Some(-950));
+    /// assert_eq!(OpenKakaoError::SafetyBlock("open chat".into()).status_code(), None);
+    /// ```
+    pub fn status_code(&self) -> Option<i64> {
+        match self {
+            Self::LocoStatus { status, .. } | Self::RestApi { status, .. } => Some(*status),
+            Self::TokenExpired => Some(-950),
+            Self::Unauthorized { .. } => Some(-401),
+            Self::Forbidden { .. } => Some(-403),
+            _ => None,
+        }
+    }
+
+    /// Classification of the server status behind this error, if any.
+    pub fn kakao_status(&self) -> Option<KakaoStatus> {
+        match self {
+            Self::LocoStatus { status, .. } | Self::RestApi { status, .. } => {
+                Some(KakaoStatus::from_code(*status))
+            }
+            Self::TokenExpired => Some(KakaoStatus::AuthRejected),
+            Self::Unauthorized { .. } => Some(KakaoStatus::HttpUnauthorized),
+            Self::Forbidden { .. } => Some(KakaoStatus::HttpForbidden),
+            _ => None,
+        }
+    }
+
+    /// The error for a non-success HTTP answer, from its status and body:
+    /// 401 (or pilsner's `UNAUTHENTICATED`) is [`Self::Unauthorized`], 403
+    /// [`Self::Forbidden`] and 429 [`Self::RateLimited`]. A 4xx whose JSON
+    /// body carries a non-zero `status` is [`Self::RestApi`] with it; the
+    /// rest are [`Self::Http`].
+    ///
+    /// ```
+    /// use openkakao_rs::error::OpenKakaoError;
+    ///
+    /// let page = b"<html><head><title>401 Authorization Required</title></head></html>";
+    /// let err = OpenKakaoError::from_http(401, page, None);
+    /// assert!(matches!(&err, OpenKakaoError::Unauthorized { message }
+    ///     if message == "HTML page \"401 Authorization Required\""));
+    /// let err = OpenKakaoError::from_http(400, br#"{"status":-805,"message":"slow down"}"#, None);
+    /// assert_eq!(err.status_code(), Some(-805));
+    /// assert!(matches!(
+    ///     OpenKakaoError::from_http(502, b"", None),
+    ///     OpenKakaoError::Http { status: 502, body, .. } if body == "empty body"
+    /// ));
+    /// ```
+    pub fn from_http(status: u16, body: &[u8], retry_after: Option<std::time::Duration>) -> Self {
+        let json = serde_json::from_slice::<serde_json::Value>(body).ok();
+        let field = |key: &str| json.as_ref().and_then(|v| v.get(key));
+        let message = || {
+            field("message")
+                .or_else(|| field("msg"))
+                .and_then(serde_json::Value::as_str)
+                .filter(|text| !text.is_empty())
+                .map_or_else(|| body_snippet(body), str::to_string)
+        };
+        if field("reason").and_then(serde_json::Value::as_str) == Some("UNAUTHENTICATED") {
+            return Self::Unauthorized {
+                message: "UNAUTHENTICATED: pilsner requires Cache.db bearer token".into(),
+            };
+        }
+        match status {
+            401 => Self::Unauthorized { message: message() },
+            403 => Self::Forbidden { message: message() },
+            429 => Self::RateLimited { retry_after },
+            400..=499 => match field("status").and_then(serde_json::Value::as_i64) {
+                Some(code) if code != 0 => Self::RestApi {
+                    status: code,
+                    message: message(),
+                },
+                _ => Self::Http {
+                    status,
+                    body: body_snippet(body),
+                    retry_after,
+                },
+            },
+            _ => Self::Http {
+                status,
+                body: body_snippet(body),
+                retry_after,
+            },
+        }
+    }
+
     /// Create a LOCO status error from a command name and response status.
     pub fn loco(command: impl Into<String>, status: i64) -> Self {
         if status == -950 {
@@ -64,6 +265,62 @@ impl OpenKakaoError {
     }
 }
 
+/// Longest body text kept in an error message.
+const SNIPPET_CHARS: usize = 200;
+
+/// A response body for an error message: `empty body`, `HTML page "<title>"`
+/// for an error page, otherwise its text with whitespace runs collapsed,
+/// cut at [`SNIPPET_CHARS`].
+pub fn body_snippet(body: &[u8]) -> String {
+    let text = String::from_utf8_lossy(body);
+    let text = text.trim();
+    if text.is_empty() {
+        return "empty body".into();
+    }
+    let lower = text.to_ascii_lowercase();
+    if lower.starts_with("<!doctype html") || lower.starts_with("<html") {
+        let title = lower
+            .find("<title>")
+            .map(|start| start + "<title>".len())
+            .and_then(|start| Some((start, start + lower[start..].find("</title>")?)))
+            .map(|(start, end)| {
+                text[start..end]
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .filter(|title| !title.is_empty());
+        return match title {
+            Some(title) => format!("HTML page \"{title}\""),
+            None => "HTML page".into(),
+        };
+    }
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match collapsed.char_indices().nth(SNIPPET_CHARS) {
+        Some((cut, _)) => format!("{}...", &collapsed[..cut]),
+        None => collapsed,
+    }
+}
+
+impl From<anyhow::Error> for OpenKakaoError {
+    /// Keeps a typed error that travelled inside an `anyhow::Error`.
+    ///
+    /// ```
+    /// use openkakao_rs::error::OpenKakaoError;
+    ///
+    /// let wrapped = anyhow::Error::from(OpenKakaoError::TokenExpired);
+    /// assert!(OpenKakaoError::from(wrapped).is_auth_failure());
+    /// let other = OpenKakaoError::from(anyhow::anyhow!("disk full"));
+    /// assert!(matches!(other, OpenKakaoError::Other(_)));
+    /// ```
+    fn from(err: anyhow::Error) -> Self {
+        match err.downcast::<OpenKakaoError>() {
+            Ok(typed) => typed,
+            Err(err) => Self::Other(err),
+        }
+    }
+}
+
 impl From<reqwest::Error> for OpenKakaoError {
     fn from(e: reqwest::Error) -> Self {
         let is_transient = e.is_timeout() || e.is_connect();
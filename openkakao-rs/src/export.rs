@@ -1,47 +1,91 @@
-use std::fs::File;
+use std::collections::{HashMap, HashSet};
+use std::fs::OpenOptions;
 use std::io::{self, Write};
+use std::path::Path;
+use std::sync::OnceLock;
 
 use anyhow::{anyhow, Result};
-use chrono::{Local, TimeZone};
+use chrono::{DateTime, Local, TimeZone};
+use regex::Regex;
+use serde::Serialize;
 
-use crate::model::{ChatMember, ChatMessage};
+use crate::attachments::AttachmentKind;
+use crate::mbox::{self, MailAttachment, MailMessage};
+use crate::message_db::{title_at, TitleEntry};
+use crate::model::{ChatMember, ChatMessage, MessageContent, DELETED_MESSAGE_TEXT};
+use crate::output::write_atomic;
+use crate::threads::{thread_order, ThreadEntry, ThreadKey};
 
 pub enum ExportFormat {
     Json,
+    /// One [`ArchiveRecord`] per line; can be extended with `--append`.
+    Jsonl,
     Csv,
     Txt,
+    /// One RFC 5322 mail per message in an mbox file.
+    Mbox,
 }
 
 impl ExportFormat {
     pub fn from_str(s: &str) -> Result<Self> {
         match s.to_lowercase().as_str() {
             "json" => Ok(Self::Json),
+            "jsonl" => Ok(Self::Jsonl),
             "csv" => Ok(Self::Csv),
             "txt" => Ok(Self::Txt),
-            _ => Err(anyhow!("Unknown format '{}'. Use: json, csv, txt", s)),
+            "mbox" => Ok(Self::Mbox),
+            _ => Err(anyhow!(
+                "Unknown format '{}'. Use: json, jsonl, csv, txt, mbox",
+                s
+            )),
         }
     }
+
+    /// Formats with one record per line that `--append` can extend.
+    pub fn appendable(&self) -> bool {
+        matches!(self, Self::Jsonl | Self::Csv)
+    }
+}
+
+/// The chat being exported, for formats that carry more than the messages.
+#[derive(Debug, Default)]
+pub struct ExportChat {
+    pub chat_id: i64,
+    /// Mail subject in mbox exports.
+    pub title: String,
+    /// Files to attach in mbox exports, by log id (`--embed-media`).
+    pub attachments: HashMap<i64, Vec<MailAttachment>>,
+    /// Authors are pseudonyms, so mail addresses must not carry user ids.
+    pub anonymized: bool,
+    /// `--annotate-titles`: the archive's title history, oldest first.
+    pub titles: Vec<TitleEntry>,
+    /// `--threads`: group replies under their sources (txt).
+    pub threads: bool,
 }
 
+/// Write `messages` in the given order; callers pass them ascending by
+/// log_id (see [`crate::model::select_window`]).
 pub fn export_messages(
     messages: &[ChatMessage],
     members: &[ChatMember],
     my_user_id: i64,
     format: &ExportFormat,
+    chat: &ExportChat,
     output: Option<&str>,
 ) -> Result<()> {
     let content = match format {
-        ExportFormat::Json => format_json(messages, members, my_user_id)?,
-        ExportFormat::Csv => format_csv(messages, members, my_user_id)?,
-        ExportFormat::Txt => format_txt(messages, members, my_user_id),
+        ExportFormat::Json => format_json(messages, members, my_user_id, &chat.titles)?,
+        ExportFormat::Jsonl => format_jsonl(messages, members, my_user_id, &chat.titles)?,
+        ExportFormat::Csv => format_csv(messages, members, my_user_id, true)?,
+        ExportFormat::Txt => format_txt(messages, members, my_user_id, &chat.titles, chat.threads),
+        ExportFormat::Mbox => format_mbox(messages, members, my_user_id, chat),
     };
 
     match output {
-        Some(path) => {
-            let mut file = File::create(path)?;
-            file.write_all(content.as_bytes())?;
+        Some(path) if path != "-" => {
+            write_atomic(Path::new(path), |w| Ok(w.write_all(content.as_bytes())?))?;
         }
-        None => {
+        _ => {
             io::stdout().write_all(content.as_bytes())?;
         }
     }
@@ -49,7 +93,216 @@ pub fn export_messages(
     Ok(())
 }
 
-fn resolve_author(author_id: i64, members: &[ChatMember], my_user_id: i64) -> String {
+/// Log ids already in an earlier `jsonl` or `csv` export at `path`, so
+/// `--append` can skip them. A missing file has none.
+pub fn existing_log_ids(path: &Path, format: &ExportFormat) -> Result<HashSet<i64>> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(HashSet::new()),
+        Err(err) => return Err(anyhow!("Failed to read {}: {}", path.display(), err)),
+    };
+    match format {
+        ExportFormat::Jsonl => content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .enumerate()
+            .map(|(idx, line)| {
+                serde_json::from_str::<serde_json::Value>(line)
+                    .ok()
+                    .and_then(|record| record.get("log_id")?.as_i64())
+                    .ok_or_else(|| anyhow!("{} line {} has no log_id", path.display(), idx + 1))
+            })
+            .collect(),
+        ExportFormat::Csv => {
+            let mut rdr = csv::Reader::from_reader(content.as_bytes());
+            let column = rdr
+                .headers()?
+                .iter()
+                .position(|h| h == "log_id")
+                .ok_or_else(|| anyhow!("{} has no log_id column", path.display()))?;
+            rdr.records()
+                .map(|record| {
+                    let record = record?;
+                    record
+                        .get(column)
+                        .and_then(|id| id.parse().ok())
+                        .ok_or_else(|| anyhow!("{} has a row without a log_id", path.display()))
+                })
+                .collect()
+        }
+        _ => Err(anyhow!("--append only works with --format jsonl or csv")),
+    }
+}
+
+/// Add `messages` to the end of an earlier export at `path` (created when
+/// missing). CSV gets its header only when the file is new or empty.
+pub fn append_messages(
+    messages: &[ChatMessage],
+    members: &[ChatMember],
+    my_user_id: i64,
+    format: &ExportFormat,
+    titles: &[TitleEntry],
+    path: &Path,
+) -> Result<()> {
+    let is_new = std::fs::metadata(path).map_or(true, |meta| meta.len() == 0);
+    let content = match format {
+        ExportFormat::Jsonl => format_jsonl(messages, members, my_user_id, titles)?,
+        ExportFormat::Csv => format_csv(messages, members, my_user_id, is_new)?,
+        _ => return Err(anyhow!("--append only works with --format jsonl or csv")),
+    };
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(content.as_bytes())?;
+    Ok(())
+}
+
+/// `export` privacy options, applied before any format is rendered so
+/// every format gets the same filtered data.
+#[derive(Debug, Default)]
+pub struct PrivacyFilter {
+    /// Replace authors with `Member A`, `Member B`, ... and mask phone
+    /// numbers and email addresses.
+    pub anonymize: bool,
+    /// Drop photo, video and file messages and every attachment payload.
+    pub no_media: bool,
+    /// Extra patterns masked as `[redacted]`.
+    pub redact: Vec<Regex>,
+}
+
+impl PrivacyFilter {
+    pub fn new(anonymize: bool, no_media: bool, redact: &[String]) -> Result<Self> {
+        let redact = redact
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern)
+                    .map_err(|e| anyhow!("invalid --redact pattern '{}': {}", pattern, e))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            anonymize,
+            no_media,
+            redact,
+        })
+    }
+
+    /// Mask PII (with `anonymize`) and the `--redact` patterns in `text`.
+    pub fn scrub(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        if self.anonymize {
+            text = email_pattern().replace_all(&text, "[email]").into_owned();
+            text = phone_pattern().replace_all(&text, "[phone]").into_owned();
+        }
+        for pattern in &self.redact {
+            text = pattern.replace_all(&text, "[redacted]").into_owned();
+        }
+        text
+    }
+
+    /// Filtered copies of `messages` and `members`, plus the pseudonym key
+    /// when anonymizing. Pseudonyms follow first appearance, so they are
+    /// stable within one export; your own messages stay `Me`.
+    pub fn apply(
+        &self,
+        messages: &[ChatMessage],
+        members: &[ChatMember],
+        my_user_id: i64,
+    ) -> (Vec<ChatMessage>, Vec<ChatMember>, Vec<PseudonymEntry>) {
+        let mut key: Vec<PseudonymEntry> = Vec::new();
+        let mut aliases: HashMap<i64, String> = HashMap::new();
+        let mut filtered = Vec::with_capacity(messages.len());
+        for msg in messages {
+            if self.no_media && AttachmentKind::of_message_type(msg.message_type).is_some() {
+                continue;
+            }
+            let mut msg = msg.clone();
+            msg.message = self.scrub(&msg.message);
+            msg.attachment = if self.no_media {
+                String::new()
+            } else {
+                self.scrub(&msg.attachment)
+            };
+            if self.anonymize && msg.author_id != my_user_id {
+                let alias = aliases.entry(msg.author_id).or_insert_with(|| {
+                    let alias = pseudonym(key.len());
+                    key.push(PseudonymEntry {
+                        alias: alias.clone(),
+                        user_id: msg.author_id,
+                        name: resolve_author(
+                            msg.author_id,
+                            msg.author_nickname.as_deref(),
+                            members,
+                            my_user_id,
+                        ),
+                    });
+                    alias
+                });
+                msg.author_nickname = Some(alias.clone());
+            }
+            filtered.push(msg);
+        }
+
+        if !self.anonymize {
+            return (filtered, members.to_vec(), key);
+        }
+        let members = key
+            .iter()
+            .map(|entry| ChatMember::new(entry.user_id, entry.alias.clone()))
+            .collect();
+        (filtered, members, key)
+    }
+}
+
+/// One line of the `--key-file` written next to an anonymized export.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PseudonymEntry {
+    pub alias: String,
+    pub user_id: i64,
+    pub name: String,
+}
+
+/// `Member A` ... `Member Z`, then `Member AA`, `Member AB`, ...
+fn pseudonym(index: usize) -> String {
+    let mut letters = Vec::new();
+    let mut n = index + 1;
+    while n > 0 {
+        n -= 1;
+        letters.push(char::from(b'A' + (n % 26) as u8));
+        n /= 26;
+    }
+    format!("Member {}", letters.iter().rev().collect::<String>())
+}
+
+fn email_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}").unwrap()
+    })
+}
+
+/// Korean mobile and landline numbers (`010-1234-5678`, `01012345678`,
+/// `02 123 4567`) and the same with a country code (`+82 10-1234-5678`).
+fn phone_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"(?:\+\d{1,3}[\s.-]?0?|\b0)\d{1,2}(?:[\s.-]?|\)\s?)\d{3,4}[\s.-]?\d{4}\b")
+            .unwrap()
+    })
+}
+
+/// Write the pseudonym key for an anonymized export as JSON.
+pub fn write_key_file(path: &Path, key: &[PseudonymEntry]) -> Result<()> {
+    write_atomic(path, |w| {
+        serde_json::to_writer_pretty(&mut *w, key)?;
+        w.write_all(b"\n")?;
+        Ok(())
+    })
+}
+
+fn resolve_author(
+    author_id: i64,
+    author_nickname: Option<&str>,
+    members: &[ChatMember],
+    my_user_id: i64,
+) -> String {
     if author_id == my_user_id {
         return "Me".to_string();
     }
@@ -57,51 +310,147 @@ fn resolve_author(author_id: i64, members: &[ChatMember], my_user_id: i64) -> St
         .iter()
         .find(|m| m.user_id == author_id)
         .map(|m| m.display_name())
+        .or_else(|| author_nickname.map(str::to_string))
         .unwrap_or_else(|| author_id.to_string())
 }
 
+/// One message in a `jsonl` export.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ArchiveRecord {
+    pub log_id: i64,
+    pub author_id: i64,
+    pub author_name: String,
+    #[serde(rename = "type")]
+    pub message_type: i64,
+    pub text: String,
+    /// ISO-8601 in UTC; empty when the send time is unknown.
+    pub timestamp: String,
+    /// `--annotate-titles`: the chat title in effect when it was sent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chat_title: Option<String>,
+}
+
+impl ArchiveRecord {
+    fn new(msg: &ChatMessage, members: &[ChatMember], my_user_id: i64) -> Self {
+        Self {
+            log_id: msg.log_id,
+            author_id: msg.author_id,
+            author_name: resolve_author(
+                msg.author_id,
+                msg.author_nickname.as_deref(),
+                members,
+                my_user_id,
+            ),
+            message_type: msg.message_type,
+            text: message_text(msg),
+            timestamp: iso_timestamp(msg.send_at),
+            chat_title: None,
+        }
+    }
+}
+
+/// The text an export records for `msg`: a delete marker's feed JSON
+/// becomes the same placeholder `read` shows.
+fn message_text(msg: &ChatMessage) -> String {
+    match msg.content.deleted_log_id() {
+        Some(_) => DELETED_MESSAGE_TEXT.to_string(),
+        None => msg.message.clone(),
+    }
+}
+
+fn iso_timestamp(send_at: i64) -> String {
+    if send_at <= 0 {
+        return String::new();
+    }
+    DateTime::from_timestamp(send_at, 0)
+        .map(|dt| dt.to_rfc3339())
+        .unwrap_or_default()
+}
+
+fn format_jsonl(
+    messages: &[ChatMessage],
+    members: &[ChatMember],
+    my_user_id: i64,
+    titles: &[TitleEntry],
+) -> Result<String> {
+    let mut out = String::new();
+    for msg in messages {
+        let mut record = ArchiveRecord::new(msg, members, my_user_id);
+        record.chat_title = title_at(titles, msg.send_at).map(String::from);
+        out.push_str(&serde_json::to_string(&record)?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
 fn format_json(
     messages: &[ChatMessage],
     members: &[ChatMember],
     my_user_id: i64,
+    titles: &[TitleEntry],
 ) -> Result<String> {
     let entries: Vec<serde_json::Value> = messages
         .iter()
         .map(|msg| {
-            serde_json::json!({
+            let mut entry = serde_json::json!({
                 "log_id": msg.log_id,
-                "author": resolve_author(msg.author_id, members, my_user_id),
+                "author": resolve_author(
+                msg.author_id,
+                msg.author_nickname.as_deref(),
+                members,
+                my_user_id,
+            ),
                 "message_type": msg.message_type,
-                "message": msg.message,
+                "message": message_text(msg),
                 "attachment": msg.attachment,
                 "send_at": msg.send_at,
-            })
+            });
+            if let Some(title) = title_at(titles, msg.send_at) {
+                entry["chat_title"] = title.into();
+            }
+            entry
         })
         .collect();
 
     Ok(serde_json::to_string_pretty(&entries)?)
 }
 
-fn format_csv(messages: &[ChatMessage], members: &[ChatMember], my_user_id: i64) -> Result<String> {
+fn format_csv(
+    messages: &[ChatMessage],
+    members: &[ChatMember],
+    my_user_id: i64,
+    header: bool,
+) -> Result<String> {
     let mut buf = Vec::new();
     {
         let mut wtr = csv::Writer::from_writer(&mut buf);
-        wtr.write_record([
-            "log_id",
-            "author",
-            "message_type",
-            "message",
-            "attachment",
-            "send_at",
-        ])?;
+        if header {
+            wtr.write_record([
+                "log_id",
+                "author",
+                "message_type",
+                "message",
+                "attachment",
+                "send_at",
+                "author_id",
+                "timestamp",
+            ])?;
+        }
         for msg in messages {
             wtr.write_record(&[
                 msg.log_id.to_string(),
-                resolve_author(msg.author_id, members, my_user_id),
+                resolve_author(
+                    msg.author_id,
+                    msg.author_nickname.as_deref(),
+                    members,
+                    my_user_id,
+                ),
                 msg.message_type.to_string(),
-                msg.message.clone(),
+                message_text(msg),
                 msg.attachment.clone(),
                 msg.send_at.to_string(),
+                msg.author_id.to_string(),
+                iso_timestamp(msg.send_at),
             ])?;
         }
         wtr.flush()?;
@@ -109,16 +458,57 @@ fn format_csv(messages: &[ChatMessage], members: &[ChatMember], my_user_id: i64)
     Ok(String::from_utf8(buf)?)
 }
 
-fn format_txt(messages: &[ChatMessage], members: &[ChatMember], my_user_id: i64) -> String {
+/// Marks a reply whose source is not in a `--threads` txt export.
+const ORPHAN_MARKER: &str = "(reply to a message not in this export) ";
+
+/// One line per message. With a title history, a `=== title ===` line
+/// opens the export and marks every point where the title in effect changes.
+/// With `threads`, replies follow their sources, indented.
+fn format_txt(
+    messages: &[ChatMessage],
+    members: &[ChatMember],
+    my_user_id: i64,
+    titles: &[TitleEntry],
+    threads: bool,
+) -> String {
+    let order = if threads {
+        thread_order(messages, |m| ThreadKey::from(m))
+    } else {
+        (0..messages.len()).map(ThreadEntry::flat).collect()
+    };
     let mut lines = Vec::new();
-    for msg in messages {
-        let author = resolve_author(msg.author_id, members, my_user_id);
+    let mut current_title = None;
+    for entry in &order {
+        let msg = &messages[entry.index];
+        let title = title_at(titles, msg.send_at);
+        if entry.depth == 0 && title.is_some() && title != current_title {
+            lines.push(format!("=== {} ===", title.unwrap_or_default()));
+            current_title = title;
+        }
+        let author = resolve_author(
+            msg.author_id,
+            msg.author_nickname.as_deref(),
+            members,
+            my_user_id,
+        );
         let time_str = Local
             .timestamp_opt(msg.send_at, 0)
             .single()
             .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
             .unwrap_or_else(|| msg.send_at.to_string());
-        lines.push(format!("[{}] {}: {}", time_str, author, msg.message));
+        let text = match msg.content.emoticon_text() {
+            Some(text) if msg.message.trim().is_empty() => text,
+            _ => message_text(msg),
+        };
+        let marker = if entry.orphan { ORPHAN_MARKER } else { "" };
+        lines.push(format!(
+            "{}[{}] {}: {}{}",
+            entry.indent(),
+            time_str,
+            author,
+            marker,
+            text
+        ));
     }
     let mut result = lines.join("\n");
     if !result.is_empty() {
@@ -127,29 +517,83 @@ fn format_txt(messages: &[ChatMessage], members: &[ChatMember], my_user_id: i64)
     result
 }
 
+/// One mail per message, threaded by the log ids replies quote. Messages
+/// without text (photos, files, ...) get a `[photo]`-style body.
+fn format_mbox(
+    messages: &[ChatMessage],
+    members: &[ChatMember],
+    my_user_id: i64,
+    chat: &ExportChat,
+) -> String {
+    let parents: HashMap<i64, i64> = messages
+        .iter()
+        .filter_map(|msg| Some((msg.log_id, mbox::replied_log_id(&msg.attachment)?)))
+        .collect();
+    let mut out = String::new();
+    for msg in messages {
+        let author = resolve_author(
+            msg.author_id,
+            msg.author_nickname.as_deref(),
+            members,
+            my_user_id,
+        );
+        let author_local = if chat.anonymized {
+            author.to_lowercase().replace(' ', "-")
+        } else {
+            msg.author_id.to_string()
+        };
+        let placeholder;
+        let body = if msg.content.deleted_log_id().is_some() {
+            DELETED_MESSAGE_TEXT
+        } else if msg.message.trim().is_empty() {
+            placeholder = match &msg.content {
+                MessageContent::MultiPhoto { photos } => {
+                    format!("[album: {} photos]", photos.len())
+                }
+                content => content.emoticon_text().unwrap_or_else(|| {
+                    format!(
+                        "[{}]",
+                        AttachmentKind::of_message_type(msg.message_type)
+                            .map_or("message", |kind| kind.label())
+                    )
+                }),
+            };
+            &placeholder
+        } else {
+            &msg.message
+        };
+        out.push_str(&mbox::render_message(&MailMessage {
+            chat_id: chat.chat_id,
+            log_id: msg.log_id,
+            author_local,
+            author_name: &author,
+            send_at: msg.send_at,
+            subject: &chat.title,
+            body,
+            references: mbox::reference_chain(msg.log_id, &parents),
+            attachments: chat.attachments.get(&msg.log_id).map_or(&[], Vec::as_slice),
+        }));
+    }
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::model::{ChatMember, ChatMessage};
+    use crate::model::{ChatMember, ChatMessage, DELETED_MESSAGE_TEXT};
 
     fn make_msg(log_id: i64, author_id: i64, text: &str) -> ChatMessage {
-        ChatMessage {
-            log_id,
-            author_id,
-            message_type: 1,
-            message: text.to_string(),
-            attachment: String::new(),
-            send_at: 1_700_000_000,
-        }
+        ChatMessage::from_json(&serde_json::json!({
+            "logId": log_id,
+            "authorId": author_id,
+            "type": 1,
+            "message": text,
+            "sendAt": 1_700_000_000,
+        }))
     }
 
     fn make_member(user_id: i64, nickname: &str) -> ChatMember {
-        ChatMember {
-            user_id,
-            nickname: nickname.to_string(),
-            friend_nickname: String::new(),
-            country_iso: String::new(),
-        }
+        ChatMember::new(user_id, nickname)
     }
 
     // ── ExportFormat::from_str ─────────────────────────────────────────────
@@ -172,6 +616,14 @@ mod tests {
             ExportFormat::from_str("txt"),
             Ok(ExportFormat::Txt)
         ));
+        assert!(matches!(
+            ExportFormat::from_str("mbox"),
+            Ok(ExportFormat::Mbox)
+        ));
+        assert!(matches!(
+            ExportFormat::from_str("jsonl"),
+            Ok(ExportFormat::Jsonl)
+        ));
     }
 
     #[test]
@@ -180,31 +632,154 @@ mod tests {
         assert!(ExportFormat::from_str("").is_err());
     }
 
+    // ── PrivacyFilter ──────────────────────────────────────────────────────
+
+    const PLANTED: &str = "call 010-1234-5678 or +82 10 9876 5432, mail kim.cs@example.co.kr, office (02)123-4567, project BLUEBIRD-7";
+
+    fn planted_chat() -> (Vec<ChatMessage>, Vec<ChatMember>) {
+        let mut photo = make_msg(3, 42, "사진");
+        photo.message_type = 2;
+        photo.attachment = r#"{"url":"https://talk.kakaocdn.net/a.jpg"}"#.into();
+        let mut departed = make_msg(4, 77, "bye 01098765432");
+        departed.author_nickname = Some("Park Jisoo".into());
+        let msgs = vec![
+            make_msg(1, 42, PLANTED),
+            make_msg(2, 1, "my own email me@example.com"),
+            photo,
+            departed,
+            make_msg(5, 42, "again"),
+        ];
+        (msgs, vec![make_member(42, "Kim Minsu")])
+    }
+
+    fn render_all(messages: &[ChatMessage], members: &[ChatMember]) -> Vec<String> {
+        let chat = ExportChat {
+            chat_id: 9,
+            title: "Team".into(),
+            anonymized: messages
+                .iter()
+                .any(|m| m.author_nickname.as_deref() == Some("Member A")),
+            ..Default::default()
+        };
+        vec![
+            format_json(messages, members, 1, &[]).unwrap(),
+            format_csv(messages, members, 1, true).unwrap(),
+            format_txt(messages, members, 1, &[], false),
+            format_mbox(messages, members, 1, &chat),
+        ]
+    }
+
+    #[test]
+    fn anonymize_removes_planted_pii_from_every_format() {
+        let (msgs, members) = planted_chat();
+        let filter = PrivacyFilter::new(true, false, &[]).unwrap();
+        let (msgs, members, key) = filter.apply(&msgs, &members, 1);
+        for out in render_all(&msgs, &members) {
+            for pii in [
+                "Kim Minsu",
+                "Park Jisoo",
+                "010-1234-5678",
+                "9876 5432",
+                "01098765432",
+                "kim.cs@example.co.kr",
+                "me@example.com",
+                "123-4567",
+            ] {
+                assert!(!out.contains(pii), "{pii} leaked:\n{out}");
+            }
+            assert!(out.contains("Member A") && out.contains("Member B"));
+            assert!(out.contains("[phone]") && out.contains("[email]"));
+            assert!(out.contains("BLUEBIRD-7"));
+            // mbox addresses would otherwise carry the user ids.
+            assert!(!out.contains("42@kakao.invalid"));
+        }
+        assert_eq!(
+            key,
+            [
+                PseudonymEntry {
+                    alias: "Member A".into(),
+                    user_id: 42,
+                    name: "Kim Minsu".into(),
+                },
+                PseudonymEntry {
+                    alias: "Member B".into(),
+                    user_id: 77,
+                    name: "Park Jisoo".into(),
+                },
+            ]
+        );
+        assert_eq!(msgs[1].author_nickname, None);
+        // Same author, same pseudonym throughout.
+        assert_eq!(msgs[4].author_nickname.as_deref(), Some("Member A"));
+    }
+
+    #[test]
+    fn redact_and_no_media_apply_without_anonymize() {
+        let (msgs, members) = planted_chat();
+        let filter =
+            PrivacyFilter::new(false, true, &[r"BLUEBIRD-\d+".into(), "(?i)office".into()])
+                .unwrap();
+        let (msgs, members, key) = filter.apply(&msgs, &members, 1);
+        assert!(key.is_empty());
+        assert_eq!(msgs.len(), 4);
+        assert!(msgs.iter().all(|m| m.attachment.is_empty()));
+        for out in render_all(&msgs, &members) {
+            assert!(!out.contains("BLUEBIRD") && !out.contains("office"));
+            assert!(!out.contains("kakaocdn"));
+            assert!(out.contains("[redacted]"));
+            // Names and contact details are untouched without --anonymize.
+            assert!(out.contains("Kim Minsu") && out.contains("010-1234-5678"));
+        }
+        assert!(PrivacyFilter::new(false, false, &["(unclosed".into()]).is_err());
+    }
+
+    #[test]
+    fn phone_pattern_leaves_dates_and_amounts_alone() {
+        let filter = PrivacyFilter::new(true, false, &[]).unwrap();
+        let text = "2024-01-15 회의, 12,000,000원, 주문번호 20240115123456";
+        assert_eq!(filter.scrub(text), text);
+        assert_eq!(filter.scrub("031.123.4567"), "[phone]");
+    }
+
+    #[test]
+    fn pseudonyms_continue_past_z() {
+        assert_eq!(pseudonym(0), "Member A");
+        assert_eq!(pseudonym(25), "Member Z");
+        assert_eq!(pseudonym(26), "Member AA");
+        assert_eq!(pseudonym(27), "Member AB");
+    }
+
     // ── resolve_author ─────────────────────────────────────────────────────
 
     #[test]
     fn resolve_author_self_returns_me() {
         let members = vec![make_member(42, "Alice")];
-        assert_eq!(resolve_author(1, &members, 1), "Me");
+        assert_eq!(resolve_author(1, None, &members, 1), "Me");
     }
 
     #[test]
     fn resolve_author_known_member_returns_display_name() {
         let members = vec![make_member(42, "Alice")];
-        assert_eq!(resolve_author(42, &members, 1), "Alice");
+        assert_eq!(resolve_author(42, None, &members, 1), "Alice");
     }
 
     #[test]
     fn resolve_author_unknown_returns_id_string() {
         let members: Vec<ChatMember> = vec![];
-        assert_eq!(resolve_author(999, &members, 1), "999");
+        assert_eq!(resolve_author(999, None, &members, 1), "999");
+    }
+
+    #[test]
+    fn resolve_author_departed_member_uses_payload_nickname() {
+        let members = vec![make_member(42, "Alice")];
+        assert_eq!(resolve_author(999, Some("Gone"), &members, 1), "Gone");
     }
 
     // ── format_json ────────────────────────────────────────────────────────
 
     #[test]
     fn format_json_empty_messages_returns_empty_array() {
-        let result = format_json(&[], &[], 1).unwrap();
+        let result = format_json(&[], &[], 1, &[]).unwrap();
         let parsed: serde_json::Value = serde_json::from_str(&result).unwrap();
         assert!(parsed.is_array());
         assert_eq!(parsed.as_array().unwrap().len(), 0);
@@ -213,7 +788,7 @@ mod tests {
     #[test]
     fn format_json_includes_expected_fields() {
         let msgs = vec![make_msg(101, 1, "hello")];
-        let result = format_json(&msgs, &[], 1).unwrap();
+        let result = format_json(&msgs, &[], 1, &[]).unwrap();
         let parsed: serde_json::Value = serde_json::from_str(&result).unwrap();
         let entry = &parsed[0];
         assert_eq!(entry["log_id"], 101);
@@ -225,7 +800,7 @@ mod tests {
 
     #[test]
     fn format_csv_empty_messages_has_header_only() {
-        let result = format_csv(&[], &[], 1).unwrap();
+        let result = format_csv(&[], &[], 1, true).unwrap();
         let first_line = result.lines().next().unwrap_or("");
         assert!(first_line.contains("log_id"));
         assert!(first_line.contains("author"));
@@ -235,25 +810,301 @@ mod tests {
     #[test]
     fn format_csv_with_message_has_data_row() {
         let msgs = vec![make_msg(55, 1, "test msg")];
-        let result = format_csv(&msgs, &[], 1).unwrap();
+        let result = format_csv(&msgs, &[], 1, true).unwrap();
         assert!(result.contains("55"));
         assert!(result.contains("Me"));
         assert!(result.contains("test msg"));
     }
 
+    #[test]
+    fn format_csv_without_header_for_appends() {
+        let msgs = vec![make_msg(55, 42, "test msg")];
+        let result = format_csv(&msgs, &[make_member(42, "Alice")], 1, false).unwrap();
+        assert_eq!(
+            result,
+            "55,Alice,1,test msg,,1700000000,42,2023-11-14T22:13:20+00:00\n"
+        );
+    }
+
+    // ── format_jsonl ───────────────────────────────────────────────────────
+
+    #[test]
+    fn format_jsonl_writes_one_archive_record_per_line() {
+        let msgs = vec![make_msg(7, 42, "hi"), make_msg(8, 1, "yo")];
+        let result = format_jsonl(&msgs, &[make_member(42, "Alice")], 1, &[]).unwrap();
+        let lines: Vec<serde_json::Value> = result
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0],
+            serde_json::json!({
+                "log_id": 7,
+                "author_id": 42,
+                "author_name": "Alice",
+                "type": 1,
+                "text": "hi",
+                "timestamp": "2023-11-14T22:13:20+00:00",
+            })
+        );
+        assert_eq!(lines[1]["author_name"], "Me");
+    }
+
+    #[test]
+    fn format_txt_labels_known_emoticons() {
+        let sticker = ChatMessage::from_json(&serde_json::json!({
+            "logId": 5,
+            "authorId": 1,
+            "type": 12,
+            "message": "",
+            "attachment": r#"{"path":"2202001.emot_001.webp","name":"(Emoticons)"}"#,
+            "sendAt": 1_700_000_000,
+        }));
+        let out = format_txt(&[sticker], &[make_member(1, "Alice")], 0, &[], false);
+        assert!(
+            out.trim_end().ends_with("Alice: (이모티콘: 라이언 하트)"),
+            "{out}"
+        );
+    }
+
+    #[test]
+    fn delete_markers_export_as_a_placeholder_in_every_format() {
+        let marker = ChatMessage::from_json(&serde_json::json!({
+            "logId": 6,
+            "authorId": 42,
+            "type": 0,
+            "message": r#"{"feedType":14,"logId":5,"hidden":true}"#,
+            "sendAt": 1_700_000_060,
+        }));
+        let msgs = [make_msg(5, 42, "wrong room"), marker];
+        let mut outputs = render_all(&msgs, &[]);
+        outputs.push(format_jsonl(&msgs, &[], 1, &[]).unwrap());
+        for out in outputs {
+            assert!(out.contains(DELETED_MESSAGE_TEXT), "{out}");
+            assert!(!out.contains("feedType"), "{out}");
+        }
+    }
+
+    #[test]
+    fn unknown_send_time_has_empty_timestamp() {
+        assert_eq!(iso_timestamp(0), "");
+    }
+
+    // ── --append ───────────────────────────────────────────────────────────
+
+    #[test]
+    fn appended_records_are_read_back_as_existing_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, format) in [
+            ("a.jsonl", ExportFormat::Jsonl),
+            ("a.csv", ExportFormat::Csv),
+        ] {
+            let path = dir.path().join(name);
+            assert!(existing_log_ids(&path, &format).unwrap().is_empty());
+
+            append_messages(
+                &[make_msg(1, 1, "a"), make_msg(2, 1, "b")],
+                &[],
+                1,
+                &format,
+                &[],
+                &path,
+            )
+            .unwrap();
+            append_messages(&[make_msg(3, 1, "c, d")], &[], 1, &format, &[], &path).unwrap();
+
+            let ids = existing_log_ids(&path, &format).unwrap();
+            assert_eq!(ids, HashSet::from([1, 2, 3]), "{name}");
+            let content = std::fs::read_to_string(&path).unwrap();
+            assert_eq!(
+                content.lines().count(),
+                3 + format_header_lines(&format),
+                "{name}"
+            );
+        }
+    }
+
+    /// CSV keeps its single header line across appends.
+    fn format_header_lines(format: &ExportFormat) -> usize {
+        usize::from(matches!(format, ExportFormat::Csv))
+    }
+
+    #[test]
+    fn existing_ids_reject_unreadable_archives() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("x.jsonl");
+        std::fs::write(&path, "{\"log_id\":1}\nnot json\n").unwrap();
+        let err = existing_log_ids(&path, &ExportFormat::Jsonl).unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+        assert!(existing_log_ids(&path, &ExportFormat::Txt).is_err());
+    }
+
     // ── format_txt ─────────────────────────────────────────────────────────
 
     #[test]
     fn format_txt_empty_messages_returns_empty_string() {
-        let result = format_txt(&[], &[], 1);
+        let result = format_txt(&[], &[], 1, &[], false);
         assert!(result.is_empty());
     }
 
     #[test]
     fn format_txt_includes_author_and_message() {
         let msgs = vec![make_msg(1, 1, "world")];
-        let result = format_txt(&msgs, &[], 1);
+        let result = format_txt(&msgs, &[], 1, &[], false);
         assert!(result.contains("Me"));
         assert!(result.contains("world"));
     }
+
+    fn renamed_titles() -> Vec<TitleEntry> {
+        vec![
+            TitleEntry {
+                title: "Study".into(),
+                observed_at: 1_700_000_000,
+            },
+            TitleEntry {
+                title: "Study 2024".into(),
+                observed_at: 1_700_000_100,
+            },
+        ]
+    }
+
+    #[test]
+    fn format_txt_marks_title_changes() {
+        let mut msgs = vec![
+            make_msg(1, 1, "a"),
+            make_msg(2, 1, "b"),
+            make_msg(3, 1, "c"),
+        ];
+        msgs[2].send_at = 1_700_000_200;
+        let result = format_txt(&msgs, &[], 1, &renamed_titles(), false);
+        let lines: Vec<&str> = result.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], "=== Study ===");
+        assert!(lines[2].ends_with("b"));
+        assert_eq!(lines[3], "=== Study 2024 ===");
+        assert!(lines[4].ends_with("c"));
+    }
+
+    #[test]
+    fn format_txt_threads_nest_replies_under_their_sources() {
+        let reply = |log_id: i64, src: i64, text: &str| {
+            ChatMessage::from_json(&serde_json::json!({
+                "logId": log_id,
+                "authorId": 1,
+                "type": 26,
+                "message": text,
+                "attachment": {"src_logId": src},
+                "sendAt": 1_700_000_000 + log_id,
+            }))
+        };
+        let mut msgs = vec![
+            make_msg(1, 1, "lunch?"),
+            make_msg(2, 1, "meeting at 3"),
+            reply(3, 1, "sure"),
+            reply(4, 99, "as I said"),
+            reply(5, 3, "where?"),
+        ];
+        msgs[1].send_at = 1_700_000_002;
+        let lines: Vec<String> = format_txt(&msgs, &[], 1, &[], true)
+            .lines()
+            .map(|line| line.split_once("] ").unwrap().1.to_string())
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "Me: lunch?",
+                "Me: sure",
+                "Me: where?",
+                "Me: meeting at 3",
+                "Me: (reply to a message not in this export) as I said",
+            ]
+        );
+        let threaded = format_txt(&msgs, &[], 1, &[], true);
+        let indents: Vec<usize> = threaded
+            .lines()
+            .map(|line| line.len() - line.trim_start().len())
+            .collect();
+        assert_eq!(indents, [0, 2, 4, 0, 0]);
+    }
+
+    #[test]
+    fn format_jsonl_adds_chat_title_only_when_annotating() {
+        let mut msgs = vec![make_msg(1, 1, "a")];
+        msgs[0].send_at = 1_700_000_200;
+        let plain = format_jsonl(&msgs, &[], 1, &[]).unwrap();
+        assert!(!plain.contains("chat_title"));
+        let annotated = format_jsonl(&msgs, &[], 1, &renamed_titles()).unwrap();
+        let record: serde_json::Value = serde_json::from_str(annotated.trim()).unwrap();
+        assert_eq!(record["chat_title"], "Study 2024");
+    }
+
+    // ── format_mbox ────────────────────────────────────────────────────────
+
+    #[test]
+    fn format_mbox_threads_replies_and_attaches_files() {
+        let mut photo = make_msg(2, 42, "");
+        photo.message_type = 2;
+        let mut reply = make_msg(3, 1, "좋아요");
+        reply.attachment = r#"{"src_logId":2,"src_message":""}"#.into();
+        let mut nested = make_msg(4, 42, "thanks");
+        nested.attachment = r#"{"src_logId":3}"#.into();
+        let chat = ExportChat {
+            chat_id: 9,
+            title: "가족".into(),
+            attachments: HashMap::from([(
+                2,
+                vec![MailAttachment::new("a.jpg".into(), vec![1, 2, 3])],
+            )]),
+            anonymized: false,
+            ..Default::default()
+        };
+        let members = [make_member(42, "김민수")];
+        let out = format_mbox(&[photo, reply, nested], &members, 1, &chat);
+
+        let mails: Vec<&str> = out.split("\nFrom ").collect();
+        assert_eq!(mails.len(), 3);
+        assert!(mails[0].starts_with("From 42@kakao.invalid "));
+        assert!(mails[0].contains("From: =?UTF-8?B?6rmA66+87IiY?= <42@kakao.invalid>"));
+        assert!(mails[0].contains("multipart/mixed"));
+        assert!(mails[0].contains("\n[photo]\n"));
+        assert!(!mails[0].contains("In-Reply-To"));
+
+        assert!(mails[1].contains("From: Me <1@kakao.invalid>"));
+        assert!(mails[1].contains("In-Reply-To: <2.9@kakao.invalid>"));
+        assert!(mails[1].contains("\n좋아요\n"));
+        assert!(mails[2].contains("In-Reply-To: <3.9@kakao.invalid>"));
+        assert!(mails[2].contains("References: <2.9@kakao.invalid>\n <3.9@kakao.invalid>"));
+    }
+
+    #[test]
+    fn format_mbox_keeps_an_album_on_one_mail() {
+        let album = ChatMessage::from_json(&serde_json::json!({
+            "logId": 5,
+            "authorId": 42,
+            "type": 27,
+            "message": "",
+            "attachment": r#"{"kl":["a/1.jpg",null,"a/3.jpg"]}"#,
+            "sendAt": 1_700_000_000,
+        }));
+        let chat = ExportChat {
+            chat_id: 9,
+            title: "가족".into(),
+            attachments: HashMap::from([(
+                5,
+                vec![
+                    MailAttachment::new("1_1.jpg".into(), vec![1]),
+                    MailAttachment::new("2_3.jpg".into(), vec![2]),
+                ],
+            )]),
+            ..Default::default()
+        };
+        let out = format_mbox(&[album], &[], 1, &chat);
+
+        assert!(!out.contains("\nFrom "), "one mail for the whole album");
+        assert!(out.contains("\n[album: 2 photos]\n"));
+        let first = out.find("filename=\"1_1.jpg\"").unwrap();
+        let second = out.find("filename=\"2_3.jpg\"").unwrap();
+        assert!(first < second);
+    }
 }
@@ -0,0 +1,370 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use rusqlite::{params, Connection};
+
+use crate::model::{ChatMember, ChatRoom, Friend, MyProfile};
+
+/// Bumped whenever a table or column changes; re-exporting into a file with
+/// a different version is refused rather than silently mixing layouts.
+pub const SCHEMA_VERSION: i64 = 1;
+
+/// Which chats get their member lists fetched for `chat_members`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemberFanout {
+    All,
+    None,
+    Unread,
+}
+
+impl MemberFanout {
+    pub fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "all" => Ok(Self::All),
+            "none" => Ok(Self::None),
+            "unread" => Ok(Self::Unread),
+            _ => Err(anyhow!(
+                "Unknown member mode '{}'. Use: all, none, unread",
+                s
+            )),
+        }
+    }
+
+    pub fn includes(&self, room: &ChatRoom) -> bool {
+        match self {
+            Self::All => true,
+            Self::None => false,
+            Self::Unread => room.unread_count > 0,
+        }
+    }
+}
+
+/// Normalized SQLite snapshot of the account: profile, friends, chats and
+/// chat members, for ad-hoc SQL. Every write is an upsert, so exporting into
+/// the same file again refreshes it in place.
+pub struct ExportDb {
+    conn: Connection,
+}
+
+impl ExportDb {
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn =
+            Connection::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let db = Self { conn };
+        db.init_schema()?;
+        Ok(db)
+    }
+
+    fn init_schema(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS schema_version (
+                version INTEGER NOT NULL
+            );",
+        )?;
+        let existing: Option<i64> =
+            self.conn
+                .query_row("SELECT MAX(version) FROM schema_version", [], |row| {
+                    row.get(0)
+                })?;
+        match existing {
+            Some(version) if version != SCHEMA_VERSION => {
+                anyhow::bail!(
+                    "Export database has schema version {version}, this build writes {SCHEMA_VERSION}; export to a new file"
+                );
+            }
+            Some(_) => {}
+            None => {
+                self.conn.execute(
+                    "INSERT INTO schema_version (version) VALUES (?1)",
+                    params![SCHEMA_VERSION],
+                )?;
+            }
+        }
+
+        self.conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS profile (
+                user_id           INTEGER PRIMARY KEY,
+                nickname          TEXT NOT NULL DEFAULT '',
+                status_message    TEXT NOT NULL DEFAULT '',
+                account_id        INTEGER NOT NULL DEFAULT 0,
+                email             TEXT NOT NULL DEFAULT '',
+                profile_image_url TEXT NOT NULL DEFAULT '',
+                exported_at       INTEGER NOT NULL DEFAULT 0
+            );
+
+            CREATE TABLE IF NOT EXISTS friends (
+                user_id         INTEGER PRIMARY KEY,
+                nickname        TEXT NOT NULL DEFAULT '',
+                friend_nickname TEXT NOT NULL DEFAULT '',
+                display_name    TEXT NOT NULL DEFAULT '',
+                phone_number    TEXT NOT NULL DEFAULT '',
+                status_message  TEXT NOT NULL DEFAULT '',
+                favorite        INTEGER NOT NULL DEFAULT 0,
+                hidden          INTEGER NOT NULL DEFAULT 0,
+                channel         INTEGER NOT NULL DEFAULT 0,
+                uuid            TEXT NOT NULL DEFAULT ''
+            );
+
+            CREATE TABLE IF NOT EXISTS chats (
+                chat_id        INTEGER PRIMARY KEY,
+                kind           TEXT NOT NULL DEFAULT '',
+                title          TEXT NOT NULL DEFAULT '',
+                display_title  TEXT NOT NULL DEFAULT '',
+                unread_count   INTEGER NOT NULL DEFAULT 0,
+                last_active_at INTEGER
+            );
+
+            CREATE TABLE IF NOT EXISTS chat_members (
+                chat_id         INTEGER NOT NULL,
+                user_id         INTEGER NOT NULL,
+                nickname        TEXT NOT NULL DEFAULT '',
+                friend_nickname TEXT NOT NULL DEFAULT '',
+                country_iso     TEXT NOT NULL DEFAULT '',
+                watermark       INTEGER,
+                PRIMARY KEY (chat_id, user_id)
+            );
+            CREATE INDEX IF NOT EXISTS idx_chat_members_user
+                ON chat_members(user_id);",
+        )?;
+        Ok(())
+    }
+
+    pub fn upsert_profile(&self, profile: &MyProfile) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO profile
+             (user_id, nickname, status_message, account_id, email, profile_image_url, exported_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT(user_id) DO UPDATE SET
+                nickname = excluded.nickname,
+                status_message = excluded.status_message,
+                account_id = excluded.account_id,
+                email = excluded.email,
+                profile_image_url = excluded.profile_image_url,
+                exported_at = excluded.exported_at",
+            params![
+                profile.user_id,
+                profile.nickname,
+                profile.status_message,
+                profile.account_id,
+                profile.email,
+                profile.profile_image_url,
+                chrono::Utc::now().timestamp(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn upsert_friends(&self, friends: &[Friend]) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut stmt = tx.prepare_cached(
+            "INSERT INTO friends
+             (user_id, nickname, friend_nickname, display_name, phone_number, status_message,
+              favorite, hidden, channel, uuid)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+             ON CONFLICT(user_id) DO UPDATE SET
+                nickname = excluded.nickname,
+                friend_nickname = excluded.friend_nickname,
+                display_name = excluded.display_name,
+                phone_number = excluded.phone_number,
+                status_message = excluded.status_message,
+                favorite = excluded.favorite,
+                hidden = excluded.hidden,
+                channel = excluded.channel,
+                uuid = excluded.uuid",
+        )?;
+        for f in friends {
+            stmt.execute(params![
+                f.user_id,
+                f.nickname,
+                f.friend_nickname,
+                f.display_name(),
+                f.phone_number,
+                f.status_message,
+                f.favorite,
+                f.hidden,
+                f.channel,
+                f.uuid,
+            ])?;
+        }
+        drop(stmt);
+        tx.commit()?;
+        Ok(friends.len())
+    }
+
+    pub fn upsert_chats(&self, rooms: &[ChatRoom]) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut stmt = tx.prepare_cached(
+            "INSERT INTO chats
+             (chat_id, kind, title, display_title, unread_count, last_active_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(chat_id) DO UPDATE SET
+                kind = excluded.kind,
+                title = excluded.title,
+                display_title = excluded.display_title,
+                unread_count = excluded.unread_count,
+                last_active_at = COALESCE(excluded.last_active_at, chats.last_active_at)",
+        )?;
+        for room in rooms {
+            stmt.execute(params![
+                room.chat_id,
+                room.kind,
+                room.title,
+                room.display_title(),
+                room.unread_count,
+                room.last_active_at,
+            ])?;
+        }
+        drop(stmt);
+        tx.commit()?;
+        Ok(rooms.len())
+    }
+
+    /// Replace one chat's member rows, so members who left disappear on
+    /// re-export.
+    pub fn replace_chat_members(&self, chat_id: i64, members: &[ChatMember]) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "DELETE FROM chat_members WHERE chat_id = ?1",
+            params![chat_id],
+        )?;
+        let mut stmt = tx.prepare_cached(
+            "INSERT OR REPLACE INTO chat_members
+             (chat_id, user_id, nickname, friend_nickname, country_iso, watermark)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )?;
+        for m in members {
+            stmt.execute(params![
+                chat_id,
+                m.user_id,
+                m.nickname,
+                m.friend_nickname,
+                m.country_iso,
+                m.watermark,
+            ])?;
+        }
+        drop(stmt);
+        tx.commit()?;
+        Ok(members.len())
+    }
+
+    #[cfg(test)]
+    fn count(&self, table: &str) -> i64 {
+        self.conn
+            .query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+                row.get(0)
+            })
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn friend(user_id: i64, nickname: &str) -> Friend {
+        Friend::from_json(&serde_json::json!({"userId": user_id, "nickName": nickname}))
+    }
+
+    fn room(chat_id: i64, kind: &str, unread_count: i64) -> ChatRoom {
+        ChatRoom::from_json(&serde_json::json!({
+            "chatId": chat_id,
+            "type": kind,
+            "title": format!("chat {chat_id}"),
+            "unreadCount": unread_count,
+        }))
+    }
+
+    fn member(user_id: i64) -> ChatMember {
+        let mut member = ChatMember::new(user_id, format!("user {user_id}"));
+        member.country_iso = "KR".to_string();
+        member
+    }
+
+    #[test]
+    fn re_export_updates_rows_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("account.sqlite");
+
+        let db = ExportDb::open(&path).unwrap();
+        db.upsert_friends(&[friend(2, "Alice"), friend(3, "Bob")])
+            .unwrap();
+        db.upsert_chats(&[room(10, "MultiChat", 0)]).unwrap();
+        db.replace_chat_members(10, &[member(2), member(3)])
+            .unwrap();
+        drop(db);
+
+        let db = ExportDb::open(&path).unwrap();
+        db.upsert_friends(&[friend(2, "Alice (new)")]).unwrap();
+        db.replace_chat_members(10, &[member(2)]).unwrap();
+        assert_eq!(db.count("schema_version"), 1);
+        assert_eq!(db.count("friends"), 2);
+        assert_eq!(db.count("chats"), 1);
+        assert_eq!(db.count("chat_members"), 1);
+        let nickname: String = db
+            .conn
+            .query_row("SELECT nickname FROM friends WHERE user_id = 2", [], |r| {
+                r.get(0)
+            })
+            .unwrap();
+        assert_eq!(nickname, "Alice (new)");
+    }
+
+    #[test]
+    fn other_schema_versions_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("account.sqlite");
+        drop(ExportDb::open(&path).unwrap());
+        Connection::open(&path)
+            .unwrap()
+            .execute("UPDATE schema_version SET version = 99", [])
+            .unwrap();
+        assert!(ExportDb::open(&path).is_err());
+    }
+
+    #[test]
+    fn friends_without_shared_group_chat_query() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = ExportDb::open(&dir.path().join("account.sqlite")).unwrap();
+        db.upsert_friends(&[friend(2, "Alice"), friend(3, "Bob")])
+            .unwrap();
+        db.upsert_chats(&[room(10, "MultiChat", 1), room(11, "DirectChat", 0)])
+            .unwrap();
+        db.replace_chat_members(10, &[member(1), member(2)])
+            .unwrap();
+        db.replace_chat_members(11, &[member(1), member(3)])
+            .unwrap();
+
+        let mut stmt = db
+            .conn
+            .prepare(
+                "SELECT f.display_name FROM friends f
+                 WHERE NOT EXISTS (
+                   SELECT 1 FROM chat_members m JOIN chats c USING (chat_id)
+                   WHERE m.user_id = f.user_id AND c.kind = 'MultiChat')",
+            )
+            .unwrap();
+        let names: Vec<String> = stmt
+            .query_map([], |r| r.get(0))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(names, vec!["Bob"]);
+    }
+
+    #[test]
+    fn member_fanout_modes() {
+        assert!(MemberFanout::from_str("ALL")
+            .unwrap()
+            .includes(&room(1, "MultiChat", 0)));
+        assert!(!MemberFanout::from_str("none")
+            .unwrap()
+            .includes(&room(1, "MultiChat", 5)));
+        let unread = MemberFanout::from_str("unread").unwrap();
+        assert!(unread.includes(&room(1, "MultiChat", 5)));
+        assert!(!unread.includes(&room(1, "MultiChat", 0)));
+        assert!(MemberFanout::from_str("some").is_err());
+    }
+}
@@ -0,0 +1,611 @@
+//! Integrity manifests for `export -o`, checked by `export verify`.
+//!
+//! Every export to a file writes `manifest-<chat_id>-<sequence>.json` beside
+//! it: the output's size and sha256, the message count and the log_id
+//! window the run fetched, the export parameters and the crate version.
+//! Each manifest names the sha256 of the previous one for the same chat in
+//! that directory, so a series of incremental exports is a hash chain.
+//!
+//! A file is checked against the first `size` bytes it has now, so a later
+//! `--append` to it does not break an earlier manifest but an edit to what
+//! it recorded does. Consecutive fetch windows must overlap; one that
+//! starts after the previous one ended may have skipped messages.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+pub const MANIFEST_VERSION: u32 = 1;
+
+const PREFIX: &str = "manifest-";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestFile {
+    /// File name, relative to the manifest's directory.
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// Oldest and newest log_id of a fetch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogIdRange {
+    pub from: i64,
+    pub to: i64,
+}
+
+impl LogIdRange {
+    pub fn of(log_ids: impl IntoIterator<Item = i64>) -> Option<Self> {
+        log_ids.into_iter().fold(None, |range, id| {
+            Some(match range {
+                None => Self { from: id, to: id },
+                Some(Self { from, to }) => Self {
+                    from: from.min(id),
+                    to: to.max(id),
+                },
+            })
+        })
+    }
+}
+
+/// What the export was asked for, so a reader knows what the file holds.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportParameters {
+    pub format: String,
+    pub max_pages: usize,
+    pub append: bool,
+    pub anonymized: bool,
+    pub no_media: bool,
+    /// How many `--redact` patterns applied; the patterns themselves may
+    /// be what was meant to stay private.
+    pub redactions: usize,
+    pub embed_media: bool,
+    pub annotate_titles: bool,
+    pub threads: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportManifest {
+    pub version: u32,
+    pub crate_version: String,
+    pub chat_id: i64,
+    /// 1 for the first export of the chat into this directory.
+    pub sequence: u32,
+    pub created_at: i64,
+    pub parameters: ExportParameters,
+    /// Messages written by this run.
+    pub message_count: usize,
+    /// The log_ids fetched by this run, written or already in the file.
+    pub covered: Option<LogIdRange>,
+    pub files: Vec<ManifestFile>,
+    /// sha256 of the previous manifest's bytes; `None` for sequence 1.
+    pub previous: Option<String>,
+}
+
+impl ExportManifest {
+    /// The manifest after `previous` (its bytes on disk) for one export.
+    pub fn next(
+        previous: Option<(&ExportManifest, &[u8])>,
+        chat_id: i64,
+        created_at: i64,
+        parameters: ExportParameters,
+        message_count: usize,
+        covered: Option<LogIdRange>,
+        files: Vec<ManifestFile>,
+    ) -> Self {
+        Self {
+            version: MANIFEST_VERSION,
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            chat_id,
+            sequence: previous.map_or(1, |(manifest, _)| manifest.sequence + 1),
+            created_at,
+            parameters,
+            message_count,
+            covered,
+            files,
+            previous: previous.map(|(_, bytes)| sha256(bytes)),
+        }
+    }
+
+    pub fn file_name(&self) -> String {
+        format!("{PREFIX}{}-{:04}.json", self.chat_id, self.sequence)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = serde_json::to_vec_pretty(self)?;
+        bytes.push(b'\n');
+        Ok(bytes)
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let manifest: Self = serde_json::from_slice(bytes)?;
+        if manifest.version > MANIFEST_VERSION {
+            anyhow::bail!(
+                "manifest version {} is newer than this build reads ({MANIFEST_VERSION})",
+                manifest.version
+            );
+        }
+        Ok(manifest)
+    }
+}
+
+pub fn sha256(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+pub fn describe_file(path: &str, bytes: &[u8]) -> ManifestFile {
+    ManifestFile {
+        path: path.to_string(),
+        size: bytes.len() as u64,
+        sha256: sha256(bytes),
+    }
+}
+
+/// Something `export verify` found wrong.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Problem {
+    /// Not JSON, or not a manifest this build reads.
+    Unreadable {
+        manifest: String,
+        error: String,
+    },
+    MissingFile {
+        manifest: String,
+        path: String,
+    },
+    /// Shorter than when the manifest was written.
+    Truncated {
+        manifest: String,
+        path: String,
+    },
+    /// The recorded bytes were changed.
+    Modified {
+        manifest: String,
+        path: String,
+    },
+    /// The manifest before `manifest` is not the one it names.
+    ChainBroken {
+        manifest: String,
+    },
+    /// Manifests between these two sequence numbers are gone.
+    MissingManifests {
+        chat_id: i64,
+        after: u32,
+        next: u32,
+    },
+    /// `manifest`'s fetch began after the previous one ended.
+    CoverageGap {
+        manifest: String,
+        previous_to: i64,
+        from: i64,
+    },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Unreadable { manifest, error } => write!(f, "{manifest}: unreadable: {error}"),
+            Problem::MissingFile { manifest, path } => write!(f, "{manifest}: {path} is missing"),
+            Problem::Truncated { manifest, path } => {
+                write!(f, "{manifest}: {path} is shorter than recorded")
+            }
+            Problem::Modified { manifest, path } => {
+                write!(f, "{manifest}: {path} was modified (sha256 differs)")
+            }
+            Problem::ChainBroken { manifest } => write!(
+                f,
+                "{manifest}: previous manifest hash does not match; it was edited or replaced"
+            ),
+            Problem::MissingManifests {
+                chat_id,
+                after,
+                next,
+            } => {
+                if *after == 0 {
+                    write!(f, "chat {chat_id}: manifests before sequence {next} are missing")
+                } else {
+                    write!(
+                        f,
+                        "chat {chat_id}: manifests between sequence {after} and {next} are missing"
+                    )
+                }
+            }
+            Problem::CoverageGap {
+                manifest,
+                previous_to,
+                from,
+            } => write!(
+                f,
+                "{manifest}: fetch starts at log_id {from}, after the previous export ended at {previous_to}; messages in between may be missing"
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct VerifyReport {
+    pub manifests: usize,
+    pub files: usize,
+    pub chats: usize,
+    pub problems: Vec<Problem>,
+}
+
+impl VerifyReport {
+    pub fn ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Check `manifests` (file name and bytes) against the files `read` returns
+/// by name, and each chat's chain of manifests.
+pub fn verify(
+    manifests: &[(String, Vec<u8>)],
+    read: impl Fn(&str) -> Option<Vec<u8>>,
+) -> VerifyReport {
+    let mut report = VerifyReport {
+        manifests: manifests.len(),
+        ..VerifyReport::default()
+    };
+    // Parsed manifests by chat, with their index in `manifests`.
+    let mut chains: BTreeMap<i64, Vec<(usize, ExportManifest)>> = BTreeMap::new();
+    for (idx, (name, bytes)) in manifests.iter().enumerate() {
+        match ExportManifest::parse(bytes) {
+            Ok(manifest) => chains
+                .entry(manifest.chat_id)
+                .or_default()
+                .push((idx, manifest)),
+            Err(err) => report.problems.push(Problem::Unreadable {
+                manifest: name.clone(),
+                error: format!("{err:#}"),
+            }),
+        }
+    }
+    report.chats = chains.len();
+
+    for (chat_id, mut chain) in chains {
+        chain.sort_by_key(|(_, manifest)| manifest.sequence);
+        let mut previous: Option<(&[u8], &ExportManifest)> = None;
+        for (idx, manifest) in &chain {
+            let (name, bytes) = &manifests[*idx];
+            for file in &manifest.files {
+                report.files += 1;
+                if let Some(problem) = check_file(name, file, &read) {
+                    report.problems.push(problem);
+                }
+            }
+            let after = previous.map_or(0, |(_, prev)| prev.sequence);
+            if manifest.sequence != after + 1 {
+                report.problems.push(Problem::MissingManifests {
+                    chat_id,
+                    after,
+                    next: manifest.sequence,
+                });
+            } else if manifest.previous.as_deref()
+                != previous.map(|(bytes, _)| sha256(bytes)).as_deref()
+            {
+                report.problems.push(Problem::ChainBroken {
+                    manifest: name.to_string(),
+                });
+            }
+            if let (Some((_, prev)), Some(covered)) = (previous, manifest.covered) {
+                if let Some(prev_covered) = prev.covered {
+                    if covered.from > prev_covered.to {
+                        report.problems.push(Problem::CoverageGap {
+                            manifest: name.to_string(),
+                            previous_to: prev_covered.to,
+                            from: covered.from,
+                        });
+                    }
+                }
+            }
+            previous = Some((bytes, manifest));
+        }
+    }
+    report
+}
+
+fn check_file(
+    manifest: &str,
+    file: &ManifestFile,
+    read: &impl Fn(&str) -> Option<Vec<u8>>,
+) -> Option<Problem> {
+    let problem =
+        |make: fn(String, String) -> Problem| Some(make(manifest.to_string(), file.path.clone()));
+    // Names are bare file names; anything else did not come from here.
+    let plain = Path::new(&file.path)
+        .file_name()
+        .is_some_and(|name| name == file.path.as_str());
+    let Some(bytes) = read(&file.path).filter(|_| plain) else {
+        return problem(|manifest, path| Problem::MissingFile { manifest, path });
+    };
+    let Some(recorded) = usize::try_from(file.size)
+        .ok()
+        .and_then(|size| bytes.get(..size))
+    else {
+        return problem(|manifest, path| Problem::Truncated { manifest, path });
+    };
+    if sha256(recorded) != file.sha256 {
+        return problem(|manifest, path| Problem::Modified { manifest, path });
+    }
+    None
+}
+
+/// Manifests in `dir` as (file name, bytes), by name.
+pub fn read_manifests(dir: &Path) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut manifests = Vec::new();
+    let entries = fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with(PREFIX) && name.ends_with(".json") {
+            let bytes = fs::read(entry.path())
+                .with_context(|| format!("Failed to read {}", entry.path().display()))?;
+            manifests.push((name, bytes));
+        }
+    }
+    manifests.sort();
+    Ok(manifests)
+}
+
+/// The newest readable manifest for `chat_id` in `dir`, with its bytes.
+pub fn latest(dir: &Path, chat_id: i64) -> Result<Option<(ExportManifest, Vec<u8>)>> {
+    if !dir.exists() {
+        return Ok(None);
+    }
+    Ok(read_manifests(dir)?
+        .into_iter()
+        .filter_map(|(_, bytes)| Some((ExportManifest::parse(&bytes).ok()?, bytes)))
+        .filter(|(manifest, _)| manifest.chat_id == chat_id)
+        .max_by_key(|(manifest, _)| manifest.sequence))
+}
+
+/// Write `manifest` into `dir` and return its path.
+pub fn write(dir: &Path, manifest: &ExportManifest) -> Result<PathBuf> {
+    let path = dir.join(manifest.file_name());
+    let bytes = manifest.to_bytes()?;
+    crate::output::write_atomic(&path, |out| Ok(out.write_all(&bytes)?))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    /// A directory of exports in memory: file name to bytes.
+    #[derive(Default)]
+    struct Dir(HashMap<String, Vec<u8>>);
+
+    impl Dir {
+        /// Write `content` to `file` (appending when it exists) and a
+        /// manifest for the run covering `covered`.
+        fn export(&mut self, chat_id: i64, file: &str, content: &str, covered: (i64, i64)) {
+            let previous = self
+                .manifests()
+                .into_iter()
+                .filter_map(|(_, bytes)| Some((ExportManifest::parse(&bytes).ok()?, bytes)))
+                .filter(|(m, _)| m.chat_id == chat_id)
+                .max_by_key(|(m, _)| m.sequence);
+            let data = self.0.entry(file.to_string()).or_default();
+            data.extend_from_slice(content.as_bytes());
+            let manifest = ExportManifest::next(
+                previous.as_ref().map(|(m, b)| (m, b.as_slice())),
+                chat_id,
+                0,
+                ExportParameters {
+                    format: "jsonl".into(),
+                    ..ExportParameters::default()
+                },
+                content.lines().count(),
+                Some(LogIdRange {
+                    from: covered.0,
+                    to: covered.1,
+                }),
+                vec![describe_file(file, data)],
+            );
+            self.0
+                .insert(manifest.file_name(), manifest.to_bytes().unwrap());
+        }
+
+        fn manifests(&self) -> Vec<(String, Vec<u8>)> {
+            let mut manifests: Vec<_> = self
+                .0
+                .iter()
+                .filter(|(name, _)| name.starts_with(PREFIX))
+                .map(|(name, bytes)| (name.clone(), bytes.clone()))
+                .collect();
+            manifests.sort();
+            manifests
+        }
+
+        fn verify(&self) -> VerifyReport {
+            verify(&self.manifests(), |name| self.0.get(name).cloned())
+        }
+    }
+
+    fn series() -> Dir {
+        let mut dir = Dir::default();
+        dir.export(
+            7,
+            "chat.jsonl",
+            "{\"log_id\":10}\n{\"log_id\":20}\n",
+            (10, 20),
+        );
+        dir.export(7, "chat.jsonl", "{\"log_id\":30}\n", (20, 30));
+        dir.export(7, "chat.jsonl", "{\"log_id\":40}\n", (25, 40));
+        dir
+    }
+
+    #[test]
+    fn manifests_round_trip_and_chain() {
+        let dir = series();
+        let names: Vec<_> = dir.manifests().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(
+            names,
+            [
+                "manifest-7-0001.json",
+                "manifest-7-0002.json",
+                "manifest-7-0003.json"
+            ]
+        );
+        let first = ExportManifest::parse(&dir.0["manifest-7-0001.json"]).unwrap();
+        let second = ExportManifest::parse(&dir.0["manifest-7-0002.json"]).unwrap();
+        assert_eq!(first.previous, None);
+        assert_eq!(first.message_count, 2);
+        assert_eq!(first.crate_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(
+            second.previous.as_deref(),
+            Some(sha256(&dir.0["manifest-7-0001.json"]).as_str())
+        );
+        assert_eq!(
+            ExportManifest::parse(&second.to_bytes().unwrap()).unwrap(),
+            second
+        );
+    }
+
+    #[test]
+    fn an_intact_series_verifies_even_after_appends() {
+        let report = series().verify();
+        assert_eq!(report.problems, []);
+        assert_eq!((report.manifests, report.files, report.chats), (3, 3, 1));
+    }
+
+    #[test]
+    fn an_edited_message_file_is_detected() {
+        let mut dir = series();
+        let data = dir.0.get_mut("chat.jsonl").unwrap();
+        data[11] = b'9'; // {"log_id":10} -> {"log_id":19}
+        let problems = dir.verify().problems;
+        assert_eq!(problems.len(), 3, "every manifest recorded that byte");
+        assert!(problems
+            .iter()
+            .all(|p| matches!(p, Problem::Modified { .. })));
+
+        let mut dir = series();
+        dir.0.get_mut("chat.jsonl").unwrap().truncate(5);
+        assert!(matches!(
+            dir.verify().problems[0],
+            Problem::Truncated { .. }
+        ));
+
+        let mut dir = series();
+        dir.0.remove("chat.jsonl");
+        assert_eq!(dir.verify().problems.len(), 3);
+        assert!(matches!(
+            dir.verify().problems[0],
+            Problem::MissingFile { .. }
+        ));
+    }
+
+    #[test]
+    fn a_missing_period_in_a_split_export_is_detected() {
+        let mut dir = Dir::default();
+        dir.export(7, "2026-01.jsonl", "a\n", (100, 200));
+        dir.export(7, "2026-02.jsonl", "b\n", (200, 300));
+        dir.export(7, "2026-03.jsonl", "c\n", (300, 400));
+        assert!(dir.verify().ok());
+
+        // The February export and its manifest are gone: both the chain and
+        // the log_ids show the hole.
+        dir.0.remove("2026-02.jsonl");
+        dir.0.remove("manifest-7-0002.json");
+        assert_eq!(
+            dir.verify().problems,
+            [
+                Problem::MissingManifests {
+                    chat_id: 7,
+                    after: 1,
+                    next: 3
+                },
+                Problem::CoverageGap {
+                    manifest: "manifest-7-0003.json".into(),
+                    previous_to: 200,
+                    from: 300,
+                }
+            ]
+        );
+
+        // A run that did not fetch far enough back leaves a hole too.
+        let mut dir = Dir::default();
+        dir.export(7, "chat.jsonl", "a\n", (100, 200));
+        dir.export(7, "chat.jsonl", "b\n", (250, 300));
+        assert_eq!(
+            dir.verify().problems,
+            [Problem::CoverageGap {
+                manifest: "manifest-7-0002.json".into(),
+                previous_to: 200,
+                from: 250,
+            }]
+        );
+    }
+
+    #[test]
+    fn edited_or_replaced_manifests_break_the_chain() {
+        let mut dir = series();
+        let first = dir.0.get_mut("manifest-7-0001.json").unwrap();
+        let text = String::from_utf8(first.clone()).unwrap();
+        *first = text
+            .replace("\"message_count\": 2", "\"message_count\": 5")
+            .into_bytes();
+        assert_eq!(
+            dir.verify().problems,
+            [Problem::ChainBroken {
+                manifest: "manifest-7-0002.json".into()
+            }]
+        );
+
+        let mut dir = series();
+        dir.0.remove("manifest-7-0001.json");
+        assert_eq!(
+            dir.verify().problems,
+            [Problem::MissingManifests {
+                chat_id: 7,
+                after: 0,
+                next: 2
+            }]
+        );
+
+        let mut dir = series();
+        dir.0
+            .insert("manifest-7-0004.json".into(), b"{not json".to_vec());
+        assert!(matches!(
+            dir.verify().problems[..],
+            [Problem::Unreadable { .. }]
+        ));
+    }
+
+    #[test]
+    fn chats_chain_separately_and_paths_stay_in_the_directory() {
+        let mut dir = series();
+        dir.export(8, "other.jsonl", "x\n", (1, 2));
+        let report = dir.verify();
+        assert!(report.ok(), "{:?}", report.problems);
+        assert_eq!(report.chats, 2);
+
+        let file = ManifestFile {
+            path: "../chat.jsonl".into(),
+            ..describe_file("chat.jsonl", &dir.0["chat.jsonl"])
+        };
+        let read = |name: &str| dir.0.get(name.trim_start_matches("../")).cloned();
+        assert!(matches!(
+            check_file("m", &file, &read),
+            Some(Problem::MissingFile { .. })
+        ));
+    }
+
+    #[test]
+    fn log_id_ranges_span_their_ids() {
+        assert_eq!(
+            LogIdRange::of([5, 3, 9]),
+            Some(LogIdRange { from: 3, to: 9 })
+        );
+        assert_eq!(LogIdRange::of([]), None);
+    }
+}
@@ -0,0 +1,240 @@
+//! Scripted latency and failure injection for resilience tests.
+//!
+//! [`KakaoRestClient::with_transport`](crate::rest::KakaoRestClient::with_transport)
+//! replaces the HTTP call at the bottom of every REST request with a
+//! [`Transport`]. [`FaultyTransport`] wraps another transport (usually
+//! [`Canned`] responses) and, request by request, follows a [`Scenario`]:
+//! pass the request through, delay it, or fail it the way the real server
+//! and network do, with the same [`OpenKakaoError`] the HTTP path returns.
+//! Everything above the transport (status checks, re-auth, pagination,
+//! retries) runs unchanged.
+//!
+//! Only built with the `fault-injection` feature.
+//!
+//! ```
+//! use openkakao_rs::fault::{Canned, Fault, FaultyTransport, Scenario};
+//! use serde_json::json;
+//!
+//! let server = Canned::new().route("/messaging/chats", |_| json!({"chats": [], "last": true}));
+//! let scenario = Scenario::new().burst(Fault::Status(429), 2);
+//! let transport = FaultyTransport::new(server, scenario);
+//! assert_eq!(transport.remaining(), 2);
+//! ```
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use crate::error::OpenKakaoError;
+
+/// One REST request as the client would send it.
+#[derive(Debug, Clone, Copy)]
+pub struct Request<'a> {
+    pub method: &'a str,
+    pub url: &'a str,
+    pub body: Option<&'a str>,
+    /// The per-request timeout, when it overrides the client's 15s.
+    pub timeout: Option<Duration>,
+    /// The `Authorization` header the request carries.
+    pub authorization: &'a str,
+}
+
+/// Where a REST request goes instead of the network.
+pub trait Transport: Send + Sync {
+    /// The parsed JSON body, or the error the HTTP path would return.
+    fn send(&self, request: &Request<'_>) -> Result<Value>;
+}
+
+impl<T: Transport + ?Sized> Transport for std::sync::Arc<T> {
+    fn send(&self, request: &Request<'_>) -> Result<Value> {
+        (**self).send(request)
+    }
+}
+
+/// Client-wide timeout [`Fault::Latency`] is measured against when the
+/// request has none of its own.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// What happens to one request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    /// Answer after this delay. A delay at or past the request's timeout
+    /// waits out the timeout and fails like one instead.
+    Latency(Duration),
+    /// The connection drops before any answer (transient network error).
+    Drop,
+    /// An HTTP error status with a plain-text body, e.g. 429 or 500, typed
+    /// the way the client types a real one.
+    Status(u16),
+    /// HTTP 429 with a `Retry-After` of this long.
+    RateLimited(Duration),
+    /// HTTP 200 with a body that is not JSON.
+    MalformedJson,
+    /// `{"status": -950}`: the server no longer accepts the token.
+    TokenExpired,
+}
+
+/// The faults to apply to the next requests, in order; `None` entries
+/// pass a request through untouched. Once the script runs out every
+/// request passes.
+#[derive(Debug, Default)]
+pub struct Scenario {
+    steps: Mutex<VecDeque<Option<Fault>>>,
+}
+
+impl Scenario {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The next request gets `fault`.
+    pub fn then(self, fault: Fault) -> Self {
+        self.push(Some(fault), 1)
+    }
+
+    /// The next `count` requests get `fault`.
+    pub fn burst(self, fault: Fault, count: usize) -> Self {
+        self.push(Some(fault), count)
+    }
+
+    /// The next `count` requests go through untouched.
+    pub fn pass(self, count: usize) -> Self {
+        self.push(None, count)
+    }
+
+    fn push(self, step: Option<Fault>, count: usize) -> Self {
+        self.lock().extend(std::iter::repeat_n(step, count));
+        self
+    }
+
+    fn next(&self) -> Option<Fault> {
+        self.lock().pop_front().flatten()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<Option<Fault>>> {
+        self.steps.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A [`Transport`] applying a [`Scenario`] in front of `inner`, recording
+/// every URL it was asked for.
+pub struct FaultyTransport<T> {
+    inner: T,
+    scenario: Scenario,
+    calls: Mutex<Vec<String>>,
+}
+
+impl<T: Transport> FaultyTransport<T> {
+    pub fn new(inner: T, scenario: Scenario) -> Self {
+        Self {
+            inner,
+            scenario,
+            calls: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// URLs requested so far, faulted or not, in order.
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Scripted steps not used yet.
+    pub fn remaining(&self) -> usize {
+        self.scenario.lock().len()
+    }
+}
+
+impl<T: Transport> Transport for FaultyTransport<T> {
+    fn send(&self, request: &Request<'_>) -> Result<Value> {
+        self.calls
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(request.url.to_string());
+        let Some(fault) = self.scenario.next() else {
+            return self.inner.send(request);
+        };
+        let (method, url) = (request.method, request.url);
+        let error = match fault {
+            Fault::Latency(delay) => {
+                let timeout = request.timeout.unwrap_or(DEFAULT_TIMEOUT);
+                if delay < timeout {
+                    std::thread::sleep(delay);
+                    return self.inner.send(request);
+                }
+                std::thread::sleep(timeout);
+                OpenKakaoError::Network {
+                    message: format!("HTTP request failed: {method} {url}: operation timed out"),
+                    is_transient: true,
+                }
+            }
+            Fault::Drop => OpenKakaoError::Network {
+                message: format!("HTTP request failed: {method} {url}: connection closed"),
+                is_transient: true,
+            },
+            Fault::Status(status) => OpenKakaoError::from_http(status, b"injected failure", None),
+            Fault::RateLimited(retry_after) => OpenKakaoError::RateLimited {
+                retry_after: Some(retry_after),
+            },
+            Fault::MalformedJson => OpenKakaoError::Parse {
+                message: format!(
+                    "Expected JSON from {method} {url} (HTTP 200 OK), got HTML page: expected value"
+                ),
+            },
+            Fault::TokenExpired => return Ok(json!({"status": -950})),
+        };
+        Err(error.into())
+    }
+}
+
+type Handler = Box<dyn Fn(&Request<'_>) -> Value + Send + Sync>;
+
+/// A fake server: the first route whose pattern occurs in the URL answers.
+/// Unrouted URLs get HTTP 404.
+#[derive(Default)]
+pub struct Canned {
+    routes: Vec<(String, Handler)>,
+}
+
+impl Canned {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer URLs containing `pattern` with `handler`'s JSON.
+    pub fn route(
+        mut self,
+        pattern: &str,
+        handler: impl Fn(&Request<'_>) -> Value + Send + Sync + 'static,
+    ) -> Self {
+        self.routes.push((pattern.to_string(), Box::new(handler)));
+        self
+    }
+}
+
+impl Transport for Canned {
+    fn send(&self, request: &Request<'_>) -> Result<Value> {
+        match self
+            .routes
+            .iter()
+            .find(|(pattern, _)| request.url.contains(pattern.as_str()))
+        {
+            Some((_, handler)) => Ok(handler(request)),
+            None => Err(OpenKakaoError::Http {
+                status: 404,
+                body: format!("no canned route for {}", request.url),
+                retry_after: None,
+            }
+            .into()),
+        }
+    }
+}
+
+/// The `cursor` query parameter of `url`, if any.
+pub fn cursor_of(url: &str) -> Option<i64> {
+    url.split(['?', '&'])
+        .find_map(|part| part.strip_prefix("cursor="))
+        .and_then(|c| c.parse().ok())
+}
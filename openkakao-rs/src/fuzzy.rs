@@ -0,0 +1,173 @@
+use unicode_normalization::UnicodeNormalization;
+
+use crate::hangul::{initials, is_compatibility_jamo};
+
+/// Lowest similarity worth suggesting at all.
+pub const MIN_SUGGESTION_SCORE: f64 = 0.3;
+/// A best match must score at least this to be picked automatically...
+pub const DOMINANT_MIN_SCORE: f64 = 0.6;
+/// ...and lead the runner-up by this much.
+pub const DOMINANT_MARGIN: f64 = 0.15;
+
+/// Optimal string alignment distance: insertions, deletions, substitutions
+/// and swaps of adjacent characters each cost 1.
+fn osa_distance(a: &[char], b: &[char]) -> usize {
+    let width = b.len() + 1;
+    let mut d = vec![0usize; (a.len() + 1) * width];
+    for i in 0..=a.len() {
+        d[i * width] = i;
+    }
+    for (j, cell) in d.iter_mut().enumerate().take(width) {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (d[(i - 1) * width + j] + 1)
+                .min(d[i * width + j - 1] + 1)
+                .min(d[(i - 1) * width + j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(d[(i - 2) * width + j - 2] + 1);
+            }
+            d[i * width + j] = best;
+        }
+    }
+    d[a.len() * width + b.len()]
+}
+
+fn normalized_similarity(a: &[char], b: &[char]) -> f64 {
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - osa_distance(a, b) as f64 / longest as f64
+}
+
+/// Similarity in `0.0..=1.0` between a candidate name and a query.
+///
+/// Both sides are NFC-normalized and lowercased, then compared by syllable
+/// and by jamo (so a dropped final consonant costs less than a wrong
+/// syllable). A query of only compatibility jamo is compared against the
+/// name's initial consonants instead. Each whitespace-separated part of the
+/// name is also tried on its own, so "김주하" still ranks "김준하 팀장" high.
+pub fn similarity(name: &str, query: &str) -> f64 {
+    let query: String = query.nfc().collect::<String>().to_lowercase();
+    let query = query.trim();
+    let name: String = name.nfc().collect::<String>().to_lowercase();
+
+    let mut parts: Vec<&str> = name.split_whitespace().collect();
+    if parts.len() > 1 {
+        parts.push(name.trim());
+    }
+    if query.chars().all(is_compatibility_jamo) {
+        let q: Vec<char> = query.chars().filter(|c| !c.is_whitespace()).collect();
+        return parts
+            .iter()
+            .map(|part| normalized_similarity(&initials(part).chars().collect::<Vec<_>>(), &q))
+            .fold(0.0, f64::max);
+    }
+
+    let q_chars: Vec<char> = query.chars().collect();
+    let q_jamo: Vec<char> = query.nfd().collect();
+    parts
+        .iter()
+        .map(|part| {
+            let by_char = normalized_similarity(&part.chars().collect::<Vec<_>>(), &q_chars);
+            let by_jamo = normalized_similarity(&part.nfd().collect::<Vec<_>>(), &q_jamo);
+            by_char.max(by_jamo)
+        })
+        .fold(0.0, f64::max)
+}
+
+/// Score every candidate (each may have several names; the best counts) and
+/// return `(index, score)` pairs above [`MIN_SUGGESTION_SCORE`], best first.
+/// Ties keep input order, so the ranking is deterministic.
+pub fn rank<S: AsRef<str>>(candidates: &[Vec<S>], query: &str) -> Vec<(usize, f64)> {
+    let mut ranked: Vec<(usize, f64)> = candidates
+        .iter()
+        .enumerate()
+        .map(|(idx, names)| {
+            let score = names
+                .iter()
+                .map(|name| similarity(name.as_ref(), query))
+                .fold(0.0, f64::max);
+            (idx, score)
+        })
+        .filter(|(_, score)| *score >= MIN_SUGGESTION_SCORE)
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    ranked
+}
+
+/// The best entry of a [`rank`] result when it clearly stands out.
+pub fn dominant(ranked: &[(usize, f64)]) -> Option<usize> {
+    let (best, score) = *ranked.first()?;
+    let runner_up = ranked.get(1).map_or(0.0, |(_, s)| *s);
+    (score >= DOMINANT_MIN_SCORE && score - runner_up >= DOMINANT_MARGIN).then_some(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(list: &[&str]) -> Vec<Vec<String>> {
+        list.iter().map(|n| vec![n.to_string()]).collect()
+    }
+
+    #[test]
+    fn missing_final_consonant_scores_high() {
+        let score = similarity("김준하", "김주하");
+        assert!(score > 0.8, "{score}");
+        assert!(score < 1.0);
+        assert_eq!(similarity("김준하", "김준하"), 1.0);
+    }
+
+    #[test]
+    fn swapped_syllables_count_as_one_edit() {
+        let swapped = similarity("김준하", "김하준");
+        let unrelated = similarity("김준하", "박서연");
+        assert!(swapped > 0.6, "{swapped}");
+        assert!(unrelated < MIN_SUGGESTION_SCORE, "{unrelated}");
+    }
+
+    #[test]
+    fn decomposed_input_is_normalized() {
+        let decomposed: String = "김준하".nfd().collect();
+        assert_eq!(similarity("김준하", &decomposed), 1.0);
+    }
+
+    #[test]
+    fn choseong_queries_rank_by_initials() {
+        assert!(similarity("김준하", "ㄱㅈㅊ") > 0.6);
+        assert_eq!(similarity("김준하 팀장", "ㄱㅈㅎ"), 1.0);
+    }
+
+    #[test]
+    fn ranking_is_deterministic_and_picks_dominant_match() {
+        let list = names(&["박서연", "김준하", "김준호", "Team Rust"]);
+        let ranked = rank(&list, "김주하");
+        assert_eq!(ranked[0].0, 1);
+        assert_eq!(ranked[1].0, 2);
+        assert_eq!(rank(&list, "김주하"), ranked);
+        // 김준호 is only one jamo further away: too close to call.
+        assert_eq!(dominant(&ranked), None);
+
+        let list = names(&["박서연", "김준하", "Team Rust"]);
+        assert_eq!(dominant(&rank(&list, "김주하")), Some(1));
+
+        // Two equally close candidates: nothing is auto-selected, and the
+        // tie keeps input order.
+        let tied = names(&["김준하", "김준하"]);
+        let ranked = rank(&tied, "김준아");
+        assert_eq!(ranked.iter().map(|r| r.0).collect::<Vec<_>>(), [0, 1]);
+        assert_eq!(dominant(&ranked), None);
+    }
+
+    #[test]
+    fn latin_typos_are_ranked_too() {
+        let list = names(&["Rust Study", "Go Study", "Family"]);
+        let ranked = rank(&list, "rsut");
+        assert_eq!(ranked[0].0, 0);
+        assert!(dominant(&ranked).is_some());
+    }
+}
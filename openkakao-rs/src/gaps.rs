@@ -0,0 +1,193 @@
+//! Holes in a chat's archive, for `archive gaps` and `archive repair`.
+//!
+//! A sync that failed halfway, or a cache built from a few REST pages,
+//! leaves stretches of a chat missing with nothing in the archive to say
+//! so. What gives them away is the jump in log ids across the hole. Chats
+//! pause too, though: overnight, between bursts, for weeks in a memo chat.
+//! A pause has company, other jumps of about its size elsewhere in the
+//! chat; a hole left by a failed sync usually does not. [`find_gaps`]
+//! flags a jump only when fewer than [`COMPANY`] other jumps come within
+//! a factor of [`FACTOR`] of it.
+
+use serde::Serialize;
+
+/// Archives with fewer messages are too small to tell a pause from a hole.
+pub const MIN_MESSAGES: usize = 20;
+/// How much longer than the chat's other long jumps a gap must be.
+pub const FACTOR: u64 = 10;
+/// A jump with this many others at least `1/FACTOR` its size is a pause.
+pub const COMPANY: usize = 3;
+
+/// A suspicious jump between two neighbouring archived messages.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Gap {
+    /// The newest archived message before the gap.
+    pub after_log_id: i64,
+    pub after_at: i64,
+    /// The oldest archived message after it.
+    pub before_log_id: i64,
+    pub before_at: i64,
+    /// `before_log_id - after_log_id`.
+    pub jump: u64,
+    /// The chat's median jump, for scale.
+    pub typical: u64,
+}
+
+/// The gaps in a chat's archive, oldest first. `messages` are
+/// `(log_id, send_at)` in log id order.
+pub fn find_gaps(messages: &[(i64, i64)]) -> Vec<Gap> {
+    if messages.len() < MIN_MESSAGES {
+        return Vec::new();
+    }
+    let jumps: Vec<u64> = messages
+        .windows(2)
+        .map(|pair| pair[1].0.abs_diff(pair[0].0))
+        .collect();
+    let mut sorted = jumps.clone();
+    sorted.sort_unstable();
+    let typical = sorted[sorted.len() / 2];
+    jumps
+        .iter()
+        .enumerate()
+        .filter(|(_, &jump)| {
+            let floor = jump.div_ceil(FACTOR).max(1);
+            // Jumps at least `floor`, this one among them.
+            let comparable = sorted.len() - sorted.partition_point(|&other| other < floor);
+            jump > typical && comparable <= COMPANY
+        })
+        .map(|(i, &jump)| Gap {
+            after_log_id: messages[i].0,
+            after_at: messages[i].1,
+            before_log_id: messages[i + 1].0,
+            before_at: messages[i + 1].1,
+            jump,
+            typical,
+        })
+        .collect()
+}
+
+/// What re-fetching the pages around a gap found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum RepairOutcome {
+    /// The pages reached back past the gap; `recovered` messages were in it.
+    Repaired { recovered: usize },
+    /// The pages reached back past the gap and nothing was missing: the
+    /// chat was quiet.
+    NothingMissing,
+    /// The server's history ends inside the gap: what it no longer serves
+    /// is lost. `recovered` messages from its newer end were saved.
+    Unrecoverable { recovered: usize },
+    /// The page limit ran out inside the gap.
+    Incomplete { recovered: usize },
+}
+
+/// The outcome of paging back from a gap's newer end: whether a page
+/// reached its older end, and whether the server had no more pages.
+pub fn repair_outcome(recovered: usize, reached_start: bool, history_ended: bool) -> RepairOutcome {
+    match (reached_start, history_ended) {
+        (true, _) if recovered == 0 => RepairOutcome::NothingMissing,
+        (true, _) => RepairOutcome::Repaired { recovered },
+        (false, true) => RepairOutcome::Unrecoverable { recovered },
+        (false, false) => RepairOutcome::Incomplete { recovered },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Messages at the given log ids, a minute apart per log id step.
+    fn chat(log_ids: impl IntoIterator<Item = i64>) -> Vec<(i64, i64)> {
+        log_ids
+            .into_iter()
+            .map(|log_id| (log_id, 1_700_000_000 + 60 * log_id))
+            .collect()
+    }
+
+    /// `count` log ids starting at `start`, `step` apart with a little
+    /// jitter.
+    fn steady(start: i64, count: i64, step: i64) -> Vec<i64> {
+        (0..count).map(|i| start + i * step + i % 3).collect()
+    }
+
+    #[test]
+    fn a_hole_in_a_steady_chat_is_flagged() {
+        let mut log_ids = steady(1_000, 150, 10);
+        // Nightly pauses are not gaps: every night has one.
+        for night in 1..6 {
+            log_ids.extend(steady(1_000 + night * 3_000, 150, 10));
+        }
+        let hole_end = *log_ids.last().unwrap() + 60_000;
+        log_ids.extend(steady(hole_end, 150, 10));
+        log_ids.sort_unstable();
+
+        let gaps = find_gaps(&chat(log_ids.iter().copied()));
+        assert_eq!(gaps.len(), 1, "{gaps:?}");
+        let gap = &gaps[0];
+        assert_eq!(gap.before_log_id, hole_end);
+        assert!(gap.after_log_id < hole_end && gap.jump == (hole_end - gap.after_log_id) as u64);
+        assert_eq!(gap.after_at, 1_700_000_000 + 60 * gap.after_log_id);
+        assert!(gap.typical <= 12, "{}", gap.typical);
+    }
+
+    #[test]
+    fn pauses_between_dense_bursts_are_not_gaps() {
+        // Thirty bursts of back-to-back messages, pauses of 200 to 2000
+        // log ids between them.
+        let mut log_ids = Vec::new();
+        let mut next = 1;
+        for burst in 0..30 {
+            for _ in 0..20 {
+                log_ids.push(next);
+                next += 1 + burst % 3;
+            }
+            next += 200 + (burst * 397) % 1_800;
+        }
+        assert!(find_gaps(&chat(log_ids)).is_empty());
+    }
+
+    #[test]
+    fn memo_chats_with_long_silences_are_not_gaps() {
+        // A note to self now and then: an hour, days, two months apart.
+        let spacing = [
+            60, 1_440, 10_080, 43_200, 300, 4_320, 86_400, 20_160, 7_200, 2_880,
+        ];
+        let mut log_ids = vec![0];
+        for i in 0..40 {
+            log_ids.push(log_ids[i] + spacing[i % spacing.len()]);
+        }
+        assert!(find_gaps(&chat(log_ids)).is_empty());
+
+        // A few memos are never enough to judge.
+        assert!(find_gaps(&chat([1, 2, 3, 1_000_000])).is_empty());
+    }
+
+    #[test]
+    fn a_few_holes_are_each_flagged() {
+        let mut log_ids = steady(0, 100, 5);
+        log_ids.extend(steady(100_000, 100, 5));
+        log_ids.extend(steady(200_000, 100, 5));
+        assert_eq!(find_gaps(&chat(log_ids)).len(), 2);
+    }
+
+    #[test]
+    fn repairs_are_judged_by_how_far_back_the_pages_reached() {
+        assert_eq!(
+            repair_outcome(4, true, true),
+            RepairOutcome::Repaired { recovered: 4 }
+        );
+        assert_eq!(
+            repair_outcome(0, true, false),
+            RepairOutcome::NothingMissing
+        );
+        assert_eq!(
+            repair_outcome(2, false, true),
+            RepairOutcome::Unrecoverable { recovered: 2 }
+        );
+        assert_eq!(
+            repair_outcome(0, false, false),
+            RepairOutcome::Incomplete { recovered: 0 }
+        );
+    }
+}
@@ -0,0 +1,79 @@
+/// Initial consonants (choseong) in syllable order, as compatibility jamo.
+const CHOSEONG: [char; 19] = [
+    'ㄱ', 'ㄲ', 'ㄴ', 'ㄷ', 'ㄸ', 'ㄹ', 'ㅁ', 'ㅂ', 'ㅃ', 'ㅅ', 'ㅆ', 'ㅇ', 'ㅈ', 'ㅉ', 'ㅊ', 'ㅋ',
+    'ㅌ', 'ㅍ', 'ㅎ',
+];
+
+const SYLLABLE_FIRST: u32 = 0xAC00;
+const SYLLABLE_LAST: u32 = 0xD7A3;
+/// Syllables per initial consonant: 21 vowels × 28 finals.
+const SYLLABLES_PER_INITIAL: u32 = 21 * 28;
+
+/// Initial consonant of a precomposed Hangul syllable (`김` → `ㄱ`).
+pub fn initial_consonant(c: char) -> Option<char> {
+    let code = c as u32;
+    if !(SYLLABLE_FIRST..=SYLLABLE_LAST).contains(&code) {
+        return None;
+    }
+    Some(CHOSEONG[((code - SYLLABLE_FIRST) / SYLLABLES_PER_INITIAL) as usize])
+}
+
+/// Hangul compatibility jamo (ㄱ..ㅣ), the characters a 초성 query is typed with.
+pub fn is_compatibility_jamo(c: char) -> bool {
+    ('\u{3131}'..='\u{3163}').contains(&c)
+}
+
+/// Replace every syllable with its initial consonant, keeping other characters.
+pub fn initials(s: &str) -> String {
+    s.chars()
+        .map(|c| initial_consonant(c).unwrap_or(c))
+        .collect()
+}
+
+/// Name search used by `chats --search` and `friends --search`. A query made
+/// only of compatibility jamo (`ㄱㅈㅎ`) matches against the initial consonants
+/// of `name`; anything else is a case-insensitive substring match.
+pub fn name_matches(name: &str, query: &str) -> bool {
+    let query: String = query.chars().filter(|c| !c.is_whitespace()).collect();
+    if !query.is_empty() && query.chars().all(is_compatibility_jamo) {
+        let name: String = name.chars().filter(|c| !c.is_whitespace()).collect();
+        return initials(&name).contains(&query);
+    }
+    name.to_lowercase().contains(&query.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decomposes_composed_syllables() {
+        assert_eq!(initial_consonant('김'), Some('ㄱ'));
+        assert_eq!(initial_consonant('가'), Some('ㄱ'));
+        assert_eq!(initial_consonant('힣'), Some('ㅎ'));
+        assert_eq!(initial_consonant('뽀'), Some('ㅃ'));
+        assert_eq!(initial_consonant('a'), None);
+        assert_eq!(initial_consonant('ㄱ'), None);
+        assert_eq!(initials("김준하"), "ㄱㅈㅎ");
+    }
+
+    #[test]
+    fn choseong_queries_match_initials() {
+        assert!(name_matches("김준하", "ㄱㅈㅎ"));
+        assert!(name_matches("김준하", "ㅈㅎ"));
+        assert!(!name_matches("김준하", "ㄱㅎ"));
+        assert!(name_matches("개발 모임", "ㄱㅂㅁ"));
+        assert!(name_matches("ㅋㅋ 모임", "ㅋㅋ"));
+    }
+
+    #[test]
+    fn mixed_and_plain_queries_use_substring_matching() {
+        assert!(name_matches("Rust 스터디", "rust"));
+        assert!(name_matches("Rust 스터디", "스터"));
+        assert!(!name_matches("Rust 스터디", "ㅅㅌㄷ x"));
+        assert!(!name_matches("Team 김준하", "ㅌㄱㅈ"));
+        assert!(name_matches("Team 김준하", "ㄱㅈㅎ"));
+        assert!(!name_matches("김준하", "ㄱ준"));
+        assert!(name_matches("김준하", "준"));
+    }
+}
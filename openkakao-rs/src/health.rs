@@ -0,0 +1,413 @@
+//! Best-effort early warning for account restrictions. Commands report what
+//! they see (restriction status codes, every saved token being rejected,
+//! restriction-like settings fields changing) and `health` summarizes it.
+//! None of this is an official Kakao signal.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::status::KakaoStatus;
+
+/// Observations kept in the state file, oldest dropped first.
+pub const MAX_OBSERVATIONS: usize = 50;
+/// Observations older than this are dropped when a new one is recorded.
+pub const RETENTION_DAYS: i64 = 90;
+/// Window `health` assesses.
+pub const ASSESSMENT_DAYS: i64 = 30;
+/// A repeat of the same observation within this window bumps its count
+/// instead of adding a row, so a retry loop cannot flood the history.
+const COALESCE_MINUTES: i64 = 10;
+
+/// Settings keys whose values are tracked, matched case-insensitively as
+/// substrings. Only boolean and numeric values are kept.
+const SETTINGS_FLAG_HINTS: &[&str] = &[
+    "restrict", "suspend", "banned", "dormant", "penalty", "sanction", "abuse",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ObservationKind {
+    /// A restriction-related status code (-301, -805, -997).
+    RestrictionStatus,
+    /// The spam limit (-303).
+    SpamLimit,
+    /// The saved token was rejected and no recovery path produced a new one.
+    TokensInvalidated,
+    /// A restriction-like field in the account settings changed value.
+    SettingsChanged,
+}
+
+impl ObservationKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::RestrictionStatus => "restriction status",
+            Self::SpamLimit => "spam limit",
+            Self::TokensInvalidated => "all tokens rejected",
+            Self::SettingsChanged => "settings changed",
+        }
+    }
+
+    /// Kinds that point at a restriction rather than just a warning sign.
+    fn is_restriction(&self) -> bool {
+        matches!(self, Self::RestrictionStatus | Self::SettingsChanged)
+    }
+}
+
+/// One entry in the health history. `detail` only ever holds a method or
+/// redacted endpoint name, a status code or a settings key and its old and
+/// new value: never message text, chat ids, names or tokens.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthObservation {
+    /// RFC 3339 time of the latest occurrence.
+    pub at: String,
+    pub kind: ObservationKind,
+    pub detail: String,
+    #[serde(default = "one")]
+    pub count: u32,
+}
+
+fn one() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthLevel {
+    Ok,
+    Caution,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HealthAssessment {
+    pub level: HealthLevel,
+    pub message: String,
+    /// Observations inside the assessment window.
+    pub recent: usize,
+}
+
+fn parse_at(at: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(at)
+        .ok()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+/// Add an observation to `history`, coalescing repeats and enforcing the
+/// retention window and size cap.
+pub fn push_observation(
+    history: &mut Vec<HealthObservation>,
+    kind: ObservationKind,
+    detail: &str,
+    now: DateTime<Utc>,
+) {
+    let cutoff = now - Duration::days(RETENTION_DAYS);
+    history.retain(|o| parse_at(&o.at).is_some_and(|at| at >= cutoff));
+
+    let recent = history.iter_mut().rev().find(|o| {
+        o.kind == kind
+            && o.detail == detail
+            && parse_at(&o.at).is_some_and(|at| now - at < Duration::minutes(COALESCE_MINUTES))
+    });
+    if let Some(existing) = recent {
+        existing.count = existing.count.saturating_add(1);
+        existing.at = now.to_rfc3339();
+    } else {
+        history.push(HealthObservation {
+            at: now.to_rfc3339(),
+            kind,
+            detail: detail.to_string(),
+            count: 1,
+        });
+    }
+    if history.len() > MAX_OBSERVATIONS {
+        let excess = history.len() - MAX_OBSERVATIONS;
+        history.drain(..excess);
+    }
+}
+
+pub fn assess(history: &[HealthObservation], now: DateTime<Utc>) -> HealthAssessment {
+    let cutoff = now - Duration::days(ASSESSMENT_DAYS);
+    let recent: Vec<&HealthObservation> = history
+        .iter()
+        .filter(|o| parse_at(&o.at).is_some_and(|at| at >= cutoff))
+        .collect();
+    let restrictions = recent.iter().filter(|o| o.kind.is_restriction()).count();
+
+    let (level, message) = if restrictions > 0 {
+        (
+            HealthLevel::Warning,
+            format!(
+                "Restriction indicators observed in the last {ASSESSMENT_DAYS} days ({restrictions}). \
+                 Stop automated traffic and check the KakaoTalk app for a notice."
+            ),
+        )
+    } else if !recent.is_empty() {
+        (
+            HealthLevel::Caution,
+            format!(
+                "Warning signs observed in the last {ASSESSMENT_DAYS} days ({}), but no restriction \
+                 indicators. Slow down automated sends and keep an eye on this.",
+                recent.len()
+            ),
+        )
+    } else {
+        (
+            HealthLevel::Ok,
+            format!("No restriction indicators observed in the last {ASSESSMENT_DAYS} days."),
+        )
+    };
+    HealthAssessment {
+        level,
+        message,
+        recent: recent.len(),
+    }
+}
+
+/// Observation for a non-zero status, if it is one of the warning signs.
+pub fn classify_status(status: i64) -> Option<ObservationKind> {
+    match KakaoStatus::from_code(status) {
+        KakaoStatus::AccountRestricted
+        | KakaoStatus::TemporarilyRestricted
+        | KakaoStatus::AccountBlocked => Some(ObservationKind::RestrictionStatus),
+        KakaoStatus::SpamLimited => Some(ObservationKind::SpamLimit),
+        _ => None,
+    }
+}
+
+/// Boolean and numeric values of restriction-like keys in a settings
+/// payload (top level and one object deep), keyed by their path.
+pub fn settings_flags(settings: &Value) -> BTreeMap<String, String> {
+    fn collect(prefix: &str, value: &Value, depth: usize, out: &mut BTreeMap<String, String>) {
+        let Some(object) = value.as_object() else {
+            return;
+        };
+        for (key, value) in object {
+            let path = if prefix.is_empty() {
+                key.clone()
+            } else {
+                format!("{prefix}.{key}")
+            };
+            let lower = key.to_lowercase();
+            let tracked = SETTINGS_FLAG_HINTS.iter().any(|hint| lower.contains(hint));
+            match value {
+                Value::Bool(_) | Value::Number(_) if tracked => {
+                    out.insert(path, value.to_string());
+                }
+                Value::Object(_) if depth == 0 => collect(&path, value, depth + 1, out),
+                _ => {}
+            }
+        }
+    }
+    let mut flags = BTreeMap::new();
+    collect("", settings, 0, &mut flags);
+    flags
+}
+
+/// `key: old -> new` for each tracked field whose value changed. Fields seen
+/// for the first time form the baseline and are not reported.
+pub fn settings_changes(
+    previous: &BTreeMap<String, String>,
+    current: &BTreeMap<String, String>,
+) -> Vec<String> {
+    current
+        .iter()
+        .filter_map(|(key, value)| {
+            let old = previous.get(key)?;
+            (old != value).then(|| format!("{key}: {old} -> {value}"))
+        })
+        .collect()
+}
+
+/// Record observations in the state file. Failures are ignored: health
+/// tracking must never break the command that noticed something.
+fn record(observations: &[(ObservationKind, String)]) {
+    if observations.is_empty() {
+        return;
+    }
+    let now = Utc::now();
+    let _ = crate::state::update_health(|history, _| {
+        for (kind, detail) in observations {
+            push_observation(history, *kind, detail, now);
+        }
+    });
+}
+
+/// Note a non-zero LOCO or REST status from `source` (a LOCO method or a
+/// [`crate::rest::redact_endpoint`] path) if it is a warning sign.
+pub fn observe_status(source: &str, status: i64) {
+    if let Some(kind) = classify_status(status) {
+        record(&[(kind, format!("{source} returned {status}"))]);
+    }
+}
+
+/// The saved token was rejected on `transport` and recovery found nothing.
+pub fn observe_tokens_invalidated(transport: &str) {
+    record(&[(
+        ObservationKind::TokensInvalidated,
+        format!("{transport}: saved token rejected, every recovery path failed"),
+    )]);
+}
+
+/// Compare restriction-like settings fields with the last snapshot.
+pub fn observe_settings(settings: &Value) {
+    let current = settings_flags(settings);
+    let now = Utc::now();
+    let _ = crate::state::update_health(|history, snapshot| {
+        for change in settings_changes(snapshot, &current) {
+            push_observation(history, ObservationKind::SettingsChanged, &change, now);
+        }
+        snapshot.extend(current);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_792_108_800 + secs, 0).unwrap()
+    }
+
+    #[test]
+    fn repeats_coalesce_and_history_is_bounded() {
+        let mut history = Vec::new();
+        push_observation(
+            &mut history,
+            ObservationKind::SpamLimit,
+            "WRITE returned -303",
+            at(0),
+        );
+        push_observation(
+            &mut history,
+            ObservationKind::SpamLimit,
+            "WRITE returned -303",
+            at(60),
+        );
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].count, 2);
+
+        push_observation(
+            &mut history,
+            ObservationKind::SpamLimit,
+            "WRITE returned -303",
+            at(3600),
+        );
+        assert_eq!(history.len(), 2);
+
+        for i in 0..(MAX_OBSERVATIONS as i64 + 5) {
+            push_observation(
+                &mut history,
+                ObservationKind::SpamLimit,
+                &format!("x{i}"),
+                at(7200 + i),
+            );
+        }
+        assert_eq!(history.len(), MAX_OBSERVATIONS);
+        assert_eq!(
+            history.last().unwrap().detail,
+            format!("x{}", MAX_OBSERVATIONS + 4)
+        );
+    }
+
+    #[test]
+    fn old_observations_expire() {
+        let mut history = Vec::new();
+        push_observation(&mut history, ObservationKind::SpamLimit, "a", at(0));
+        push_observation(
+            &mut history,
+            ObservationKind::SpamLimit,
+            "b",
+            at(0) + Duration::days(RETENTION_DAYS + 1),
+        );
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].detail, "b");
+    }
+
+    #[test]
+    fn assessment_levels() {
+        let now = at(0);
+        assert_eq!(assess(&[], now).level, HealthLevel::Ok);
+        assert_eq!(
+            assess(&[], now).message,
+            "No restriction indicators observed in the last 30 days."
+        );
+
+        let mut history = Vec::new();
+        push_observation(
+            &mut history,
+            ObservationKind::TokensInvalidated,
+            "rest",
+            now - Duration::days(2),
+        );
+        assert_eq!(assess(&history, now).level, HealthLevel::Caution);
+
+        push_observation(
+            &mut history,
+            ObservationKind::RestrictionStatus,
+            "WRITE returned -301",
+            now - Duration::days(1),
+        );
+        let assessment = assess(&history, now);
+        assert_eq!(assessment.level, HealthLevel::Warning);
+        assert_eq!(assessment.recent, 2);
+
+        // Outside the window only.
+        assert_eq!(
+            assess(&history, now + Duration::days(40)).level,
+            HealthLevel::Ok
+        );
+    }
+
+    #[test]
+    fn only_warning_statuses_are_classified() {
+        assert_eq!(
+            classify_status(-301),
+            Some(ObservationKind::RestrictionStatus)
+        );
+        assert_eq!(
+            classify_status(-997),
+            Some(ObservationKind::RestrictionStatus)
+        );
+        assert_eq!(
+            classify_status(-805),
+            Some(ObservationKind::RestrictionStatus)
+        );
+        assert_eq!(classify_status(-303), Some(ObservationKind::SpamLimit));
+        assert_eq!(classify_status(-500), None);
+        assert_eq!(classify_status(-950), None);
+    }
+
+    #[test]
+    fn settings_flag_changes_are_detected_after_a_baseline() {
+        let before = settings_flags(&json!({
+            "status": 0,
+            "emailAddress": "me@example.com",
+            "isRestricted": false,
+            "account": {"suspendedUntil": 0, "nickname": "Me"},
+            "restrictionNotice": "text is ignored",
+            "bannerCount": 3,
+        }));
+        assert_eq!(
+            before.keys().collect::<Vec<_>>(),
+            ["account.suspendedUntil", "isRestricted"]
+        );
+
+        let after = settings_flags(&json!({
+            "isRestricted": true,
+            "account": {"suspendedUntil": 1_800_000_000},
+            "dormantAccount": false,
+        }));
+        assert_eq!(
+            settings_changes(&before, &after),
+            [
+                "account.suspendedUntil: 0 -> 1800000000",
+                "isRestricted: false -> true"
+            ]
+        );
+        assert!(settings_changes(&BTreeMap::new(), &after).is_empty());
+    }
+}
@@ -0,0 +1,250 @@
+//! Korean public holidays by name, for `--since 설날` and friends. Solar
+//! holidays fall on the same date every year; the lunar ones (설날, 추석,
+//! 부처님오신날) come from a table of the years it covers. 설날 and 추석 are
+//! the three-day holidays, from the eve to the day after. Substitute
+//! holidays are not included.
+//!
+//! Built with the `holidays` feature.
+
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Days, NaiveDate};
+
+use crate::date_literal::DaySpan;
+
+/// Solar dates of lunar holidays as `(year, month, day)`, per the Korea
+/// Astronomy and Space Science Institute calendar.
+const SEOLLAL: &[(i32, u32, u32)] = &[
+    (2020, 1, 25),
+    (2021, 2, 12),
+    (2022, 2, 1),
+    (2023, 1, 22),
+    (2024, 2, 10),
+    (2025, 1, 29),
+    (2026, 2, 17),
+    (2027, 2, 7),
+    (2028, 1, 27),
+    (2029, 2, 13),
+    (2030, 2, 3),
+];
+
+const BUDDHAS_BIRTHDAY: &[(i32, u32, u32)] = &[
+    (2020, 4, 30),
+    (2021, 5, 19),
+    (2022, 5, 8),
+    (2023, 5, 27),
+    (2024, 5, 15),
+    (2025, 5, 5),
+    (2026, 5, 24),
+    (2027, 5, 13),
+    (2028, 5, 2),
+    (2029, 5, 20),
+    (2030, 5, 9),
+];
+
+const CHUSEOK: &[(i32, u32, u32)] = &[
+    (2020, 10, 1),
+    (2021, 9, 21),
+    (2022, 9, 10),
+    (2023, 9, 29),
+    (2024, 9, 17),
+    (2025, 10, 6),
+    (2026, 9, 25),
+    (2027, 9, 15),
+    (2028, 10, 3),
+    (2029, 9, 22),
+    (2030, 9, 12),
+];
+
+#[derive(Debug, Clone, Copy)]
+enum When {
+    Solar(u32, u32),
+    Lunar(&'static [(i32, u32, u32)]),
+}
+
+#[derive(Debug)]
+struct Holiday {
+    /// Korean first, then other names, already
+    /// [`normalize`](crate::date_literal::normalize)d.
+    names: &'static [&'static str],
+    when: When,
+    /// The eve and the day after count too.
+    three_days: bool,
+}
+
+const HOLIDAYS: &[Holiday] = &[
+    Holiday {
+        names: &["신정", "newyearsday", "newyear"],
+        when: When::Solar(1, 1),
+        three_days: false,
+    },
+    Holiday {
+        names: &["설날", "설", "seollal", "lunarnewyear"],
+        when: When::Lunar(SEOLLAL),
+        three_days: true,
+    },
+    Holiday {
+        names: &["삼일절", "31절", "independencemovementday"],
+        when: When::Solar(3, 1),
+        three_days: false,
+    },
+    Holiday {
+        names: &["어린이날", "childrensday"],
+        when: When::Solar(5, 5),
+        three_days: false,
+    },
+    Holiday {
+        names: &["부처님오신날", "석가탄신일", "buddhasbirthday"],
+        when: When::Lunar(BUDDHAS_BIRTHDAY),
+        three_days: false,
+    },
+    Holiday {
+        names: &["현충일", "memorialday"],
+        when: When::Solar(6, 6),
+        three_days: false,
+    },
+    Holiday {
+        names: &["광복절", "liberationday"],
+        when: When::Solar(8, 15),
+        three_days: false,
+    },
+    Holiday {
+        names: &["추석", "chuseok"],
+        when: When::Lunar(CHUSEOK),
+        three_days: true,
+    },
+    Holiday {
+        names: &["개천절", "nationalfoundationday"],
+        when: When::Solar(10, 3),
+        three_days: false,
+    },
+    Holiday {
+        names: &["한글날", "hangulday"],
+        when: When::Solar(10, 9),
+        three_days: false,
+    },
+    Holiday {
+        names: &["성탄절", "크리스마스", "christmas"],
+        when: When::Solar(12, 25),
+        three_days: false,
+    },
+];
+
+/// The Korean name of every holiday, in calendar order.
+pub fn names() -> impl Iterator<Item = &'static str> {
+    HOLIDAYS.iter().map(|holiday| holiday.names[0])
+}
+
+impl Holiday {
+    /// Its days in `year`; `None` when the table does not cover the year.
+    fn in_year(&self, year: i32) -> Option<DaySpan> {
+        let day = match self.when {
+            When::Solar(month, day) => NaiveDate::from_ymd_opt(year, month, day)?,
+            When::Lunar(table) => table
+                .iter()
+                .find(|(y, _, _)| *y == year)
+                .and_then(|&(y, month, day)| NaiveDate::from_ymd_opt(y, month, day))?,
+        };
+        if self.three_days {
+            DaySpan::days(day.checked_sub_days(Days::new(1))?, 3)
+        } else {
+            DaySpan::days(day, 1)
+        }
+    }
+}
+
+/// The latest occurrence of the holiday called `key` (normalized) that has
+/// begun by `today`; `None` when `key` is no holiday.
+pub fn latest(key: &str, today: NaiveDate) -> Option<Result<DaySpan>> {
+    let holiday = HOLIDAYS
+        .iter()
+        .find(|holiday| holiday.names.contains(&key))?;
+    for year in [today.year(), today.year() - 1] {
+        match holiday.in_year(year) {
+            Some(span) if span.first <= today => return Some(Ok(span)),
+            Some(_) => continue,
+            None => break,
+        }
+    }
+    let (first, last) = match holiday.when {
+        When::Lunar(table) => (table[0].0, table[table.len() - 1].0),
+        When::Solar(..) => unreachable!("solar holidays fall in every year"),
+    };
+    Some(Err(anyhow!(
+        "{} is only known for {first}-{last}; use a date instead.",
+        holiday.names[0]
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::date_literal::normalize;
+
+    fn day(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn span(key: &str, today: &str) -> (NaiveDate, NaiveDate) {
+        let span = latest(&normalize(key), day(today)).unwrap().unwrap();
+        (span.first, span.end)
+    }
+
+    #[test]
+    fn a_holiday_is_this_years_once_it_has_begun() {
+        assert_eq!(
+            span("어린이날", "2024-05-05"),
+            (day("2024-05-05"), day("2024-05-06"))
+        );
+        assert_eq!(
+            span("Children's Day", "2024-05-04"),
+            (day("2023-05-05"), day("2023-05-06"))
+        );
+        // 설날 2024 is Feb 9-11; on its eve it already counts.
+        assert_eq!(
+            span("설날", "2024-02-09"),
+            (day("2024-02-09"), day("2024-02-12"))
+        );
+        assert_eq!(
+            span("seollal", "2024-02-08"),
+            (day("2023-01-21"), day("2023-01-24"))
+        );
+        assert_eq!(
+            span("추석", "2025-12-31"),
+            (day("2025-10-05"), day("2025-10-08"))
+        );
+        assert_eq!(
+            span("크리스마스", "2024-01-10"),
+            span("성탄절", "2024-01-10")
+        );
+    }
+
+    #[test]
+    fn lunar_holidays_outside_the_table_are_errors() {
+        let err = latest("설날", day("2031-03-01")).unwrap().unwrap_err();
+        assert!(err.to_string().contains("2020-2030"), "{err}");
+        // Solar ones need no table.
+        assert_eq!(
+            span("광복절", "2040-09-01"),
+            (day("2040-08-15"), day("2040-08-16"))
+        );
+        assert!(latest("내일", day("2024-01-01")).is_none());
+    }
+
+    #[test]
+    fn every_lunar_table_covers_the_same_years_in_order() {
+        for table in [SEOLLAL, BUDDHAS_BIRTHDAY, CHUSEOK] {
+            let years: Vec<i32> = table.iter().map(|(year, _, _)| *year).collect();
+            assert_eq!(years, (2020..=2030).collect::<Vec<_>>());
+            for &(year, month, day) in table {
+                assert!(NaiveDate::from_ymd_opt(year, month, day).is_some());
+            }
+        }
+        let mut seen = std::collections::HashSet::new();
+        for holiday in HOLIDAYS {
+            for name in holiday.names {
+                assert_eq!(normalize(name), *name, "{name} is not normalized");
+                assert!(seen.insert(*name), "{name} names two holidays");
+            }
+        }
+    }
+}
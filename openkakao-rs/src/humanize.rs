@@ -0,0 +1,264 @@
+//! Durations, ages, byte sizes and counts for people to read, in English
+//! or Korean. Every function takes the language explicitly; the CLI passes
+//! [`lang`], set once from `--lang` / `[ui] lang`.
+//!
+//! ```
+//! use openkakao_rs::humanize::{self, Lang};
+//!
+//! assert_eq!(humanize::ago(180, Lang::Ko), "3분 전");
+//! assert_eq!(humanize::ago(180, Lang::En), "3m ago");
+//! assert_eq!(humanize::bytes(1536, Lang::En), "1.5KiB");
+//! assert_eq!(humanize::count(1_234, Lang::Ko), "1.2천");
+//! assert_eq!(humanize::count(1_234, Lang::En), "1.2k");
+//! ```
+
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, Result};
+
+/// The language humanized values are written in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Lang {
+    #[default]
+    En,
+    Ko,
+}
+
+impl std::str::FromStr for Lang {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "en" | "english" => Ok(Self::En),
+            "ko" | "kr" | "korean" | "한국어" => Ok(Self::Ko),
+            _ => Err(anyhow!("Unknown language '{}'. Use: en, ko", s)),
+        }
+    }
+}
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+/// Set once from `--lang` or `[ui] lang`.
+pub fn set_lang(lang: Lang) {
+    let _ = LANG.set(lang);
+}
+
+/// The language set with [`set_lang`], English before that.
+pub fn lang() -> Lang {
+    LANG.get().copied().unwrap_or_default()
+}
+
+const MINUTE: u64 = 60;
+const HOUR: u64 = 60 * MINUTE;
+const DAY: u64 = 24 * HOUR;
+
+/// `(seconds per unit, English suffix, Korean suffix)`, largest first.
+const TIME_UNITS: [(u64, &str, &str); 4] = [
+    (DAY, "d", "일"),
+    (HOUR, "h", "시간"),
+    (MINUTE, "m", "분"),
+    (1, "s", "초"),
+];
+
+fn time_unit(secs: u64) -> (u64, &'static str, &'static str) {
+    TIME_UNITS
+        .into_iter()
+        .find(|(size, _, _)| secs >= *size)
+        .unwrap_or(TIME_UNITS[3])
+}
+
+fn time_part(value: u64, (_, en, ko): (u64, &str, &str), lang: Lang) -> String {
+    match lang {
+        Lang::En => format!("{value}{en}"),
+        Lang::Ko => format!("{value}{ko}"),
+    }
+}
+
+/// How long ago something `secs_ago` seconds old happened, in its largest
+/// whole unit: `59s ago`, `1m ago`, `23h ago`, `3d ago` (`3분 전`). A
+/// negative age is in the future: `in 3m` (`3분 후`).
+pub fn ago(secs_ago: i64, lang: Lang) -> String {
+    let secs = secs_ago.unsigned_abs();
+    let unit = time_unit(secs);
+    let part = time_part(secs / unit.0, unit, lang);
+    match (lang, secs_ago < 0) {
+        (Lang::En, false) => format!("{part} ago"),
+        (Lang::En, true) => format!("in {part}"),
+        (Lang::Ko, false) => format!("{part} 전"),
+        (Lang::Ko, true) => format!("{part} 후"),
+    }
+}
+
+/// A length of time in its two largest units, the second left out when
+/// zero: `45s`, `1m 5s`, `2h`, `3d 4h` (`1분 5초`).
+pub fn duration(secs: u64, lang: Lang) -> String {
+    let unit = time_unit(secs);
+    let mut out = time_part(secs / unit.0, unit, lang);
+    let rest = secs % unit.0;
+    if let Some(next) = TIME_UNITS
+        .into_iter()
+        .find(|(size, _, _)| *size < unit.0)
+        .filter(|(size, _, _)| rest / size > 0)
+    {
+        out.push(' ');
+        out.push_str(&time_part(rest / next.0, next, lang));
+    }
+    out
+}
+
+/// A size in binary units with one decimal: `1023B` (`1023바이트`),
+/// `1.0KiB`, `1.5MiB`, `2.0GiB`, `1.0TiB`.
+pub fn bytes(n: u64, lang: Lang) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if n < 1024 {
+        return match lang {
+            Lang::En => format!("{n}B"),
+            Lang::Ko => format!("{n}바이트"),
+        };
+    }
+    let mut value = n as f64 / 1024.0;
+    let mut unit = 0;
+    // Promote before rounding would print 1024.0 of the smaller unit.
+    while value >= 1023.95 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1}{}", UNITS[unit])
+}
+
+/// A count shortened past a thousand, rounded down to one decimal below
+/// ten of a unit: `999`, `1.2k`, `12k`, `1.2M` in English; Korean groups by
+/// 천, 만 and 억: `1.2천`, `1.2만`, `120만`, `1.2억`. A `.0` is dropped.
+pub fn count(n: u64, lang: Lang) -> String {
+    let units: &[(u64, &str)] = match lang {
+        Lang::En => &[
+            (1_000_000_000_000, "T"),
+            (1_000_000_000, "B"),
+            (1_000_000, "M"),
+            (1_000, "k"),
+        ],
+        Lang::Ko => &[
+            (1_000_000_000_000, "조"),
+            (100_000_000, "억"),
+            (10_000, "만"),
+            (1_000, "천"),
+        ],
+    };
+    let Some(&(size, suffix)) = units.iter().find(|(size, _)| n >= *size) else {
+        return n.to_string();
+    };
+    let whole = n / size;
+    let tenths = n % size / (size / 10);
+    if whole < 10 && tenths > 0 {
+        format!("{whole}.{tenths}{suffix}")
+    } else {
+        format!("{whole}{suffix}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ages_switch_units_at_each_boundary() {
+        let cases: &[(i64, &str, &str)] = &[
+            (0, "0s ago", "0초 전"),
+            (1, "1s ago", "1초 전"),
+            (59, "59s ago", "59초 전"),
+            (60, "1m ago", "1분 전"),
+            (119, "1m ago", "1분 전"),
+            (3_599, "59m ago", "59분 전"),
+            (3_600, "1h ago", "1시간 전"),
+            (86_399, "23h ago", "23시간 전"),
+            (86_400, "1d ago", "1일 전"),
+            (400 * 86_400, "400d ago", "400일 전"),
+            (-180, "in 3m", "3분 후"),
+            (-59, "in 59s", "59초 후"),
+            (i64::MIN, "in 106751991167300d", "106751991167300일 후"),
+        ];
+        for &(secs, en, ko) in cases {
+            assert_eq!(ago(secs, Lang::En), en, "{secs}");
+            assert_eq!(ago(secs, Lang::Ko), ko, "{secs}");
+        }
+    }
+
+    #[test]
+    fn durations_show_two_units_and_drop_a_zero_second() {
+        let cases: &[(u64, &str, &str)] = &[
+            (0, "0s", "0초"),
+            (59, "59s", "59초"),
+            (60, "1m", "1분"),
+            (65, "1m 5s", "1분 5초"),
+            (3_599, "59m 59s", "59분 59초"),
+            (3_600, "1h", "1시간"),
+            (3_661, "1h 1m", "1시간 1분"),
+            (7_259, "2h", "2시간"),
+            (86_400, "1d", "1일"),
+            (97_200, "1d 3h", "1일 3시간"),
+            (86_459, "1d", "1일"),
+        ];
+        for &(secs, en, ko) in cases {
+            assert_eq!(duration(secs, Lang::En), en, "{secs}");
+            assert_eq!(duration(secs, Lang::Ko), ko, "{secs}");
+        }
+    }
+
+    #[test]
+    fn sizes_use_binary_units_with_one_decimal() {
+        let cases: &[(u64, &str)] = &[
+            (1_024, "1.0KiB"),
+            (1_536, "1.5KiB"),
+            (1_048_575, "1.0MiB"),
+            (1_048_576, "1.0MiB"),
+            (10 * 1_048_576, "10.0MiB"),
+            (1_073_741_823, "1.0GiB"),
+            (3 * 1_073_741_824 / 2, "1.5GiB"),
+            (1_099_511_627_776, "1.0TiB"),
+            (2_048 * 1_099_511_627_776, "2048.0TiB"),
+        ];
+        for &(n, expected) in cases {
+            assert_eq!(bytes(n, Lang::En), expected, "{n}");
+            assert_eq!(bytes(n, Lang::Ko), expected, "{n}");
+        }
+        assert_eq!(bytes(0, Lang::En), "0B");
+        assert_eq!(bytes(1_023, Lang::En), "1023B");
+        assert_eq!(bytes(1_023, Lang::Ko), "1023바이트");
+    }
+
+    #[test]
+    fn counts_shorten_past_a_thousand_and_round_down() {
+        let cases: &[(u64, &str, &str)] = &[
+            (0, "0", "0"),
+            (999, "999", "999"),
+            (1_000, "1k", "1천"),
+            (1_099, "1k", "1천"),
+            (1_234, "1.2k", "1.2천"),
+            (1_999, "1.9k", "1.9천"),
+            (9_999, "9.9k", "9.9천"),
+            (10_000, "10k", "1만"),
+            (12_345, "12k", "1.2만"),
+            (99_999, "99k", "9.9만"),
+            (999_999, "999k", "99만"),
+            (1_000_000, "1M", "100만"),
+            (1_250_000, "1.2M", "125만"),
+            (99_999_999, "99M", "9999만"),
+            (100_000_000, "100M", "1억"),
+            (123_456_789, "123M", "1.2억"),
+            (1_500_000_000, "1.5B", "15억"),
+            (2_000_000_000_000, "2T", "2조"),
+        ];
+        for &(n, en, ko) in cases {
+            assert_eq!(count(n, Lang::En), en, "{n}");
+            assert_eq!(count(n, Lang::Ko), ko, "{n}");
+        }
+    }
+
+    #[test]
+    fn languages_parse_by_code_and_name() {
+        assert_eq!("KO".parse::<Lang>().unwrap(), Lang::Ko);
+        assert_eq!("한국어".parse::<Lang>().unwrap(), Lang::Ko);
+        assert_eq!("en".parse::<Lang>().unwrap(), Lang::En);
+        assert!("ja".parse::<Lang>().is_err());
+    }
+}
@@ -0,0 +1,334 @@
+//! Authors whose messages `read`, `watch` and `export` leave out, managed
+//! by `ignore add|list|rm` and kept in `ignore.json` beside config.toml.
+//!
+//! An entry without a chat applies everywhere; one with a chat applies to
+//! that chat only and overrides a global entry for the same author, so
+//! `ignore add 철수 --chat 5 --allow` shows 철수 in chat 5 while hiding
+//! them elsewhere. Hidden messages are dropped, or with `--collapse-ignored`
+//! each run of them becomes one `(n messages from 철수 hidden)` line.
+//! Nothing is removed from the message cache.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IgnoreEntry {
+    pub user_id: i64,
+    /// The name the author had when added, for `ignore list` and the
+    /// collapsed line when the renderer knows no better one.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    /// `None` for everywhere.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chat_id: Option<i64>,
+    /// `false` for a per-chat exception to a global entry (`--allow`).
+    #[serde(default = "default_hide")]
+    pub hide: bool,
+    pub added_at: i64,
+}
+
+fn default_hide() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IgnoreList {
+    #[serde(default)]
+    pub entries: Vec<IgnoreEntry>,
+}
+
+impl IgnoreList {
+    /// Whether `user_id`'s messages in `chat_id` are hidden: the entry for
+    /// this chat when there is one, else the global entry.
+    pub fn ignores(&self, chat_id: i64, user_id: i64) -> bool {
+        self.entry_for(chat_id, user_id)
+            .is_some_and(|entry| entry.hide)
+    }
+
+    fn entry_for(&self, chat_id: i64, user_id: i64) -> Option<&IgnoreEntry> {
+        let mut global = None;
+        for entry in self.entries.iter().filter(|e| e.user_id == user_id) {
+            match entry.chat_id {
+                Some(id) if id == chat_id => return Some(entry),
+                None => global = Some(entry),
+                Some(_) => {}
+            }
+        }
+        global
+    }
+
+    /// Name recorded for `user_id`, if any entry has one.
+    pub fn name_of(&self, user_id: i64) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|e| e.user_id == user_id && !e.name.is_empty())
+            .map(|e| e.name.as_str())
+    }
+
+    /// Add or replace the entry for `(user_id, chat_id)`. Returns whether
+    /// the list changed.
+    pub fn set(&mut self, entry: IgnoreEntry) -> bool {
+        match self
+            .entries
+            .iter_mut()
+            .find(|e| e.user_id == entry.user_id && e.chat_id == entry.chat_id)
+        {
+            Some(existing) if existing.hide == entry.hide => false,
+            Some(existing) => {
+                *existing = entry;
+                true
+            }
+            None => {
+                self.entries.push(entry);
+                true
+            }
+        }
+    }
+
+    /// Remove the entry for `(user_id, chat_id)`. Returns whether there was one.
+    pub fn remove(&mut self, user_id: i64, chat_id: Option<i64>) -> bool {
+        let before = self.entries.len();
+        self.entries
+            .retain(|e| !(e.user_id == user_id && e.chat_id == chat_id));
+        self.entries.len() != before
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.entries.iter().any(|e| e.hide)
+    }
+
+    pub fn load() -> Result<Self> {
+        load_from(&ignore_path()?)
+    }
+
+    pub fn save(&self) -> Result<PathBuf> {
+        let path = ignore_path()?;
+        save_to(&path, self)?;
+        Ok(path)
+    }
+}
+
+pub fn ignore_path() -> Result<PathBuf> {
+    Ok(crate::paths::profile_dir()?.join("ignore.json"))
+}
+
+fn load_from(path: &Path) -> Result<IgnoreList> {
+    if !path.exists() {
+        return Ok(IgnoreList::default());
+    }
+    let data =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&data).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+fn save_to(path: &Path, list: &IgnoreList) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let data = serde_json::to_string_pretty(list)?;
+    crate::output::write_atomic(path, |out| Ok(out.write_all(data.as_bytes())?))
+}
+
+/// `(3 messages from 철수 hidden)`.
+pub fn hidden_line(name: &str, count: usize) -> String {
+    format!(
+        "({count} message{} from {name} hidden)",
+        if count == 1 { "" } else { "s" }
+    )
+}
+
+/// Tell stderr how many messages were left out, so a short page is not
+/// mistaken for a quiet chat.
+pub fn note_dropped(count: usize) {
+    if count > 0 {
+        eprintln!(
+            "[ignore] {count} message{} from ignored authors left out.",
+            if count == 1 { "" } else { "s" }
+        );
+    }
+}
+
+/// The run of hidden messages in progress, for `--collapse-ignored`.
+/// Renderers call [`hide`](Self::hide) for each hidden message and
+/// [`finish`](Self::finish) before the next shown one and at the end;
+/// both return the collapsed line of a run that just ended.
+#[derive(Debug, Default)]
+pub struct HiddenRun {
+    current: Option<(i64, String, usize)>,
+}
+
+impl HiddenRun {
+    pub fn hide(&mut self, author_id: i64, name: &str) -> Option<String> {
+        if let Some((id, _, count)) = &mut self.current {
+            if *id == author_id {
+                *count += 1;
+                return None;
+            }
+        }
+        let ended = self.finish();
+        self.current = Some((author_id, name.to_string(), 1));
+        ended
+    }
+
+    pub fn finish(&mut self) -> Option<String> {
+        self.current
+            .take()
+            .map(|(_, name, count)| hidden_line(&name, count))
+    }
+}
+
+/// One [`HiddenRun`] per chat, for streams that interleave chats.
+#[derive(Debug, Default)]
+pub struct HiddenRuns(HashMap<i64, HiddenRun>);
+
+impl HiddenRuns {
+    pub fn hide(&mut self, chat_id: i64, author_id: i64, name: &str) -> Option<String> {
+        self.0.entry(chat_id).or_default().hide(author_id, name)
+    }
+
+    pub fn finish(&mut self, chat_id: i64) -> Option<String> {
+        self.0.get_mut(&chat_id).and_then(HiddenRun::finish)
+    }
+}
+
+/// What a renderer prints for a list of messages: the shown ones, and with
+/// `collapse` a line per run of hidden ones.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Shown<T> {
+    Message(T),
+    Hidden(String),
+}
+
+/// `items` with hidden ones dropped, or collapsed into one line per run.
+/// `hidden` gives the author id and name of an item to hide.
+pub fn apply<T>(
+    items: impl IntoIterator<Item = T>,
+    collapse: bool,
+    hidden: impl Fn(&T) -> Option<(i64, String)>,
+) -> Vec<Shown<T>> {
+    let mut out = Vec::new();
+    let mut run = HiddenRun::default();
+    for item in items {
+        match hidden(&item) {
+            Some((author_id, name)) => {
+                if collapse {
+                    out.extend(run.hide(author_id, &name).map(Shown::Hidden));
+                }
+            }
+            None => {
+                out.extend(run.finish().map(Shown::Hidden));
+                out.push(Shown::Message(item));
+            }
+        }
+    }
+    out.extend(run.finish().map(Shown::Hidden));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(user_id: i64, chat_id: Option<i64>, hide: bool) -> IgnoreEntry {
+        IgnoreEntry {
+            user_id,
+            name: String::new(),
+            chat_id,
+            hide,
+            added_at: 0,
+        }
+    }
+
+    #[test]
+    fn chat_entries_override_global_ones() {
+        let list = IgnoreList {
+            entries: vec![
+                entry(1, None, true),
+                entry(1, Some(10), false),
+                entry(2, Some(10), true),
+            ],
+        };
+        assert!(list.ignores(20, 1), "global entry applies elsewhere");
+        assert!(!list.ignores(10, 1), "--allow in chat 10 wins");
+        assert!(list.ignores(10, 2));
+        assert!(!list.ignores(20, 2), "chat entry stays in its chat");
+        assert!(!list.ignores(10, 3));
+    }
+
+    #[test]
+    fn set_replaces_and_remove_is_scoped() {
+        let mut list = IgnoreList::default();
+        assert!(list.set(entry(1, None, true)));
+        assert!(!list.set(entry(1, None, true)), "already there");
+        assert!(list.set(entry(1, Some(10), true)));
+        assert!(list.set(entry(1, Some(10), false)), "flipped to --allow");
+        assert_eq!(list.entries.len(), 2);
+
+        assert!(!list.remove(1, Some(20)));
+        assert!(list.remove(1, None));
+        assert_eq!(list.entries, vec![entry(1, Some(10), false)]);
+        assert!(list.is_empty(), "an exception alone hides nothing");
+    }
+
+    #[test]
+    fn list_round_trips_through_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ignore.json");
+        assert_eq!(load_from(&path).unwrap(), IgnoreList::default());
+
+        let list = IgnoreList {
+            entries: vec![IgnoreEntry {
+                name: "철수".into(),
+                ..entry(7, Some(3), true)
+            }],
+        };
+        save_to(&path, &list).unwrap();
+        assert_eq!(load_from(&path).unwrap(), list);
+        assert_eq!(list.name_of(7), Some("철수"));
+    }
+
+    #[test]
+    fn runs_collapse_per_author_or_drop() {
+        // (author, text); author 1 is ignored.
+        let items = [(2, "a"), (1, "x"), (1, "y"), (1, "z"), (2, "b"), (1, "w")];
+        let hidden = |item: &(i64, &str)| (item.0 == 1).then(|| (1, "철수".to_string()));
+
+        assert_eq!(
+            apply(items, true, hidden),
+            vec![
+                Shown::Message((2, "a")),
+                Shown::Hidden("(3 messages from 철수 hidden)".into()),
+                Shown::Message((2, "b")),
+                Shown::Hidden("(1 message from 철수 hidden)".into()),
+            ]
+        );
+        assert_eq!(
+            apply(items, false, hidden),
+            vec![Shown::Message((2, "a")), Shown::Message((2, "b"))]
+        );
+    }
+
+    #[test]
+    fn a_new_author_ends_the_run() {
+        let mut run = HiddenRun::default();
+        assert_eq!(run.hide(1, "철수"), None);
+        assert_eq!(run.hide(1, "철수"), None);
+        assert_eq!(
+            run.hide(3, "영희"),
+            Some("(2 messages from 철수 hidden)".into())
+        );
+        assert_eq!(run.finish(), Some("(1 message from 영희 hidden)".into()));
+        assert_eq!(run.finish(), None);
+
+        let mut runs = HiddenRuns::default();
+        assert_eq!(runs.hide(10, 1, "철수"), None);
+        assert_eq!(runs.hide(20, 1, "철수"), None);
+        assert_eq!(runs.finish(10), Some("(1 message from 철수 hidden)".into()));
+        assert_eq!(runs.finish(10), None);
+    }
+}
@@ -1,5 +1,41 @@
+#[cfg(feature = "extract")]
+pub mod auth;
+pub mod bandwidth;
+pub mod capture;
+pub mod credential_provider;
+pub mod credential_watch;
+pub mod cursor;
+pub mod download_pool;
+pub mod emoticons;
+mod endpoints;
 pub mod error;
+#[cfg(feature = "fault-injection")]
+pub mod fault;
+pub mod fuzzy;
+pub mod hangul;
+pub mod humanize;
+#[cfg(feature = "extract")]
 pub mod local_db;
 pub mod loco;
+#[cfg(feature = "archive")]
 pub mod message_db;
 pub mod model;
+pub mod names;
+pub mod normalize;
+pub mod notify;
+#[cfg(feature = "store")]
+pub mod paths;
+pub mod prelude;
+pub mod progress;
+pub mod rate_limit;
+pub mod response_cache;
+pub mod rest;
+pub mod rest_async;
+pub mod retry;
+pub mod status;
+pub mod takeout;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod threads;
+pub mod timestamp;
+pub mod trace;
@@ -0,0 +1,224 @@
+//! Links shared in a chat, for `links`: every URL in message text and in
+//! link attachments, normalized so the same page shared twice is one entry,
+//! and a Markdown rendering grouped by month.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use chrono::TimeZone;
+use serde::Serialize;
+
+use crate::urlnorm::{extract_urls, normalize};
+
+/// `links --format` value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinksFormat {
+    Table,
+    Markdown,
+}
+
+impl LinksFormat {
+    pub fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "table" => Ok(Self::Table),
+            "md" | "markdown" => Ok(Self::Markdown),
+            _ => Err(anyhow!("Unknown links format '{}'. Use: table, md", s)),
+        }
+    }
+}
+
+/// The links in one message: those in its text, then those of its link
+/// preview (the attachment's `urls`), normalized and without duplicates.
+/// Photo, video and file URLs are not links.
+pub fn urls_in_message(message: &str, attachment: &str, strip_tracking: bool) -> Vec<String> {
+    let mut urls = extract_urls(message, strip_tracking);
+    let parsed: serde_json::Value = serde_json::from_str(attachment).unwrap_or_default();
+    let previews = parsed
+        .get("urls")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|v| v.as_str());
+    for url in previews {
+        if let Ok(url) = normalize(url, strip_tracking) {
+            if !urls.contains(&url) {
+                urls.push(url);
+            }
+        }
+    }
+    urls
+}
+
+/// One link, as first shared.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SharedLink {
+    pub url: String,
+    /// From the link preview, when known.
+    pub title: Option<String>,
+    pub author_id: i64,
+    pub log_id: i64,
+    pub send_at: i64,
+    /// Messages the link appears in, the first included.
+    pub times_shared: usize,
+}
+
+/// One entry per link, at the message that shared it first, newest first.
+/// `messages` are `(log_id, author_id, send_at, urls)` in any order.
+pub fn shared_links(
+    messages: impl IntoIterator<Item = (i64, i64, i64, Vec<String>)>,
+) -> Vec<SharedLink> {
+    let mut links: HashMap<String, SharedLink> = HashMap::new();
+    for (log_id, author_id, send_at, urls) in messages {
+        for url in urls {
+            let link = links.entry(url.clone()).or_insert_with(|| SharedLink {
+                url,
+                title: None,
+                author_id,
+                log_id,
+                send_at,
+                times_shared: 0,
+            });
+            link.times_shared += 1;
+            if log_id < link.log_id {
+                link.author_id = author_id;
+                link.log_id = log_id;
+                link.send_at = send_at;
+            }
+        }
+    }
+    let mut links: Vec<SharedLink> = links.into_values().collect();
+    links.sort_by_key(|link| std::cmp::Reverse(link.log_id));
+    links
+}
+
+/// A Markdown list per month (`## 2024-05`), in the order of `links`.
+/// Items are `[title](url)`, or `<url>` without a title, followed by who
+/// shared them and on which day in `tz`.
+pub fn markdown_by_month<Tz: TimeZone>(
+    title: &str,
+    links: &[SharedLink],
+    author: impl Fn(i64) -> String,
+    tz: &Tz,
+) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let mut out = format!("# {}\n", escape_markdown(title));
+    let mut month = String::new();
+    for link in links {
+        let shared = tz.timestamp_opt(link.send_at, 0).single();
+        let this_month = shared
+            .as_ref()
+            .map(|dt| dt.format("%Y-%m").to_string())
+            .unwrap_or_else(|| "Unknown date".to_string());
+        if this_month != month {
+            out.push_str(&format!("\n## {this_month}\n\n"));
+            month = this_month;
+        }
+        let target = match &link.title {
+            Some(title) if !title.trim().is_empty() => {
+                format!("[{}]({})", escape_markdown(title.trim()), link.url)
+            }
+            _ => format!("<{}>", link.url),
+        };
+        let day = shared
+            .map(|dt| format!(", {}", dt.format("%Y-%m-%d")))
+            .unwrap_or_default();
+        out.push_str(&format!(
+            "- {target} — {}{day}\n",
+            escape_markdown(&author(link.author_id))
+        ));
+    }
+    out
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '[' | ']' | '*' | '_' | '`' | '<' | '>') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(url: &str, log_id: i64, send_at: i64) -> SharedLink {
+        SharedLink {
+            url: url.into(),
+            title: None,
+            author_id: 7,
+            log_id,
+            send_at,
+            times_shared: 1,
+        }
+    }
+
+    #[test]
+    fn preview_urls_join_the_links_in_the_text() {
+        let urls = urls_in_message(
+            "이거 봐 https://example.com/a?utm_source=kakao",
+            r#"{"urls":["https://example.com/a","https://news.kakao.com/v/1/"]}"#,
+            true,
+        );
+        assert_eq!(
+            urls,
+            ["https://example.com/a", "https://news.kakao.com/v/1"]
+        );
+        // A photo's own URL is not a shared link.
+        assert!(urls_in_message("", r#"{"url":"https://dn.kakao.com/p.jpg"}"#, false).is_empty());
+        assert!(urls_in_message("no links", "not json", false).is_empty());
+    }
+
+    #[test]
+    fn each_link_is_listed_once_at_its_first_share() {
+        let links = shared_links([
+            (30, 2, 3_000, vec!["https://a.com".to_string()]),
+            (10, 1, 1_000, vec!["https://a.com".to_string()]),
+            (
+                20,
+                3,
+                2_000,
+                vec!["https://b.com".to_string(), "https://a.com".to_string()],
+            ),
+        ]);
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[0].url, "https://b.com");
+        assert_eq!(links[1].url, "https://a.com");
+        assert_eq!((links[1].log_id, links[1].author_id), (10, 1));
+        assert_eq!(links[1].times_shared, 3);
+    }
+
+    #[test]
+    fn markdown_groups_links_by_month_in_the_given_zone() {
+        let kst = chrono::FixedOffset::east_opt(9 * 3600).unwrap();
+        let mut titled = link("https://a.com", 3, 1_717_167_600); // 2024-06-01 00:00 KST
+        titled.title = Some("Rust [KR] 모임".into());
+        let links = [
+            titled,
+            link("https://b.com", 2, 1_717_164_000), // 2024-05-31 23:00 KST
+            link("https://c.com", 1, 1_714_521_600),
+        ];
+        let md = markdown_by_month("가족", &links, |_| "지영".to_string(), &kst);
+        assert_eq!(
+            md,
+            "# 가족\n\
+             \n## 2024-06\n\n\
+             - [Rust \\[KR\\] 모임](https://a.com) — 지영, 2024-06-01\n\
+             \n## 2024-05\n\n\
+             - <https://b.com> — 지영, 2024-05-31\n\
+             - <https://c.com> — 지영, 2024-05-01\n"
+        );
+    }
+
+    #[test]
+    fn formats_parse_by_name() {
+        assert_eq!(LinksFormat::from_str("MD").unwrap(), LinksFormat::Markdown);
+        assert_eq!(LinksFormat::from_str("table").unwrap(), LinksFormat::Table);
+        assert!(LinksFormat::from_str("csv").is_err());
+    }
+}
@@ -12,6 +12,7 @@ use sha2::Digest;
 // ---------------------------------------------------------------------------
 
 #[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
 pub struct LocalChat {
     pub chat_id: i64,
     pub chat_type: i32,
@@ -24,6 +25,7 @@ pub struct LocalChat {
 }
 
 #[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
 pub struct LocalMessage {
     pub log_id: i64,
     pub chat_id: i64,
@@ -524,6 +526,7 @@ impl LocalDbReader {
 }
 
 #[derive(Debug, Serialize)]
+#[non_exhaustive]
 pub struct LocalDbStatus {
     pub uuid_available: bool,
     pub user_id_available: bool,
@@ -11,7 +11,10 @@ use tokio_rustls::client::TlsStream;
 use tokio_rustls::rustls::{ClientConfig, RootCertStore};
 use tokio_rustls::TlsConnector;
 
+use crate::capture;
 use crate::model::KakaoCredentials;
+use crate::response_cache;
+use crate::retry::RetryPolicy;
 
 use super::crypto::LocoEncryptor;
 use super::packet::{LocoPacket, PacketBuilder, HEADER_SIZE};
@@ -222,15 +225,26 @@ pub struct LocoClient {
     /// Optional (chatId, maxId) pairs to include in LOGINLIST for message sync.
     /// When set, the server returns chatLog data for these chats.
     pub sync_chat_ids: Vec<(i64, i64)>,
+    /// Attempts and backoff for [`full_connect_with_retry`](Self::full_connect_with_retry).
+    pub retry_policy: RetryPolicy,
     is_dirty: bool,
 }
 
 #[derive(Debug, Default)]
+#[non_exhaustive]
 pub struct ProbeCommandResult {
     pub response: Option<LocoPacket>,
     pub pushes: Vec<LocoPacket>,
 }
 
+/// The chat a LOCO write changes, whose cached REST answers it makes stale.
+fn written_chat(method: &str, body: &Document) -> Option<i64> {
+    response_cache::LOCO_WRITES
+        .contains(&method)
+        .then(|| body.get_i64("chatId").ok())
+        .flatten()
+}
+
 impl LocoClient {
     pub fn new(credentials: KakaoCredentials) -> Self {
         Self {
@@ -238,10 +252,17 @@ impl LocoClient {
             packet_builder: PacketBuilder::new(),
             stream: None,
             sync_chat_ids: Vec::new(),
+            retry_policy: RetryPolicy::default(),
             is_dirty: false,
         }
     }
 
+    /// Use `policy` instead of [`RetryPolicy::default`] when connecting.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
     /// Phase 1: Booking — get configuration and checkin server info.
     pub async fn booking(&self) -> Result<Document> {
         let builder = PacketBuilder::new();
@@ -341,15 +362,15 @@ impl LocoClient {
             let mut tcp = TcpStream::connect((host, port)).await?;
             let enc = LocoEncryptor::new();
             let handshake = enc.build_handshake_packet()?;
-            if std::env::var("OPENKAKAO_RS_DEBUG").is_ok() && handshake.len() >= 12 {
+            if handshake.len() >= 12 && tracing::enabled!(tracing::Level::DEBUG) {
                 let key_size =
                     u32::from_le_bytes([handshake[0], handshake[1], handshake[2], handshake[3]]);
                 let key_type =
                     u32::from_le_bytes([handshake[4], handshake[5], handshake[6], handshake[7]]);
                 let enc_type =
                     u32::from_le_bytes([handshake[8], handshake[9], handshake[10], handshake[11]]);
-                eprintln!(
-                    "[handshake] key_size={}, key_type={}, encrypt_type={}, total_len={}",
+                tracing::debug!(
+                    "handshake: key_size={}, key_type={}, encrypt_type={}, total_len={}",
                     key_size,
                     key_type,
                     enc_type,
@@ -372,6 +393,46 @@ impl LocoClient {
     /// Send a command and wait for the matching response (by packet_id).
     /// Skips any server push packets received before the response.
     pub async fn send_command(&mut self, method: &str, body: Document) -> Result<LocoPacket> {
+        let written_chat = written_chat(method, &body);
+        let result = self.send_command_captured(method, body).await;
+        if let (Ok(_), Some(chat_id)) = (&result, written_chat) {
+            response_cache::invalidate_chat(chat_id);
+        }
+        result
+    }
+
+    async fn send_command_captured(&mut self, method: &str, body: Document) -> Result<LocoPacket> {
+        capture::note_request("loco", method, "");
+        if !capture::is_active() {
+            return self.send_command_inner(method, body).await;
+        }
+        let started = std::time::Instant::now();
+        let request = capture::wants_bodies()
+            .then(|| bson::Bson::Document(body.clone()).into_relaxed_extjson());
+        let result = self.send_command_inner(method, body).await;
+        let response = result.as_ref().ok();
+        capture::record(capture::Exchange {
+            transport: "loco",
+            method: method.to_string(),
+            status: response.map(|p| {
+                p.body
+                    .get_i32("status")
+                    .map(i64::from)
+                    .or_else(|_| p.body.get_i64("status"))
+                    .unwrap_or(p.status_code as i64)
+            }),
+            latency: started.elapsed(),
+            error: result.as_ref().err().map(|e| format!("{e:#}")),
+            request,
+            response: response
+                .filter(|_| capture::wants_bodies())
+                .map(|p| bson::Bson::Document(p.body.clone()).into_relaxed_extjson()),
+            ..Default::default()
+        });
+        result
+    }
+
+    async fn send_command_inner(&mut self, method: &str, body: Document) -> Result<LocoPacket> {
         if self.is_dirty {
             eprintln!("[loco] Connection dirty, disconnecting for fresh reconnect");
             self.disconnect();
@@ -460,12 +521,17 @@ impl LocoClient {
 
     /// Send a raw packet without waiting for response (for PING keepalive).
     pub async fn send_packet(&mut self, method: &str, body: Document) -> Result<()> {
+        let written_chat = written_chat(method, &body);
         let packet = self.packet_builder.build(method, body);
         let stream = self
             .stream
             .as_mut()
             .ok_or_else(|| anyhow!("Not connected"))?;
-        stream.send_packet(&packet).await
+        stream.send_packet(&packet).await?;
+        if let Some(chat_id) = written_chat {
+            response_cache::invalidate_chat(chat_id);
+        }
+        Ok(())
     }
 
     /// Receive a single packet from the stream.
@@ -481,6 +547,7 @@ impl LocoClient {
     /// When `sync_chat_ids` is set, includes those chatIds/maxIds so the server
     /// returns chatLog data with recent messages for those chats.
     pub async fn login(&mut self) -> Result<Document> {
+        self.credentials.require_device_uuid("LOCO login")?;
         let (chat_ids_bson, max_ids_bson) = if self.sync_chat_ids.is_empty() {
             (bson::Bson::Array(vec![]), bson::Bson::Array(vec![]))
         } else {
@@ -519,13 +586,11 @@ impl LocoClient {
             "bg": false,
         };
 
-        if std::env::var("OPENKAKAO_RS_DEBUG").is_ok() {
-            eprintln!(
-                "[login] LOGINLIST: appVer={}, os=mac, token_len={}",
-                self.credentials.app_version,
-                self.credentials.oauth_token.len(),
-            );
-        }
+        tracing::debug!(
+            "LOGINLIST: appVer={}, os=mac, token={}",
+            self.credentials.app_version,
+            crate::trace::redact_secret(&self.credentials.oauth_token),
+        );
 
         let response = self.send_command("LOGINLIST", login_body).await?;
 
@@ -542,6 +607,9 @@ impl LocoClient {
 
     /// Execute the full connection flow: booking -> checkin -> connect -> login.
     pub async fn full_connect(&mut self) -> Result<Document> {
+        // Refused before booking rather than by the server after checkin.
+        self.credentials.require_device_uuid("LOCO login")?;
+
         // Phase 1: Booking
         let config = self.booking().await?;
 
@@ -621,41 +689,36 @@ impl LocoClient {
         self.is_dirty = false;
     }
 
-    /// Execute full_connect with exponential backoff retry.
-    /// Retries on transient errors (connection refused, timeout, TLS errors).
-    /// Does NOT retry on auth errors (-950, -999) as those need different fixes.
-    pub async fn full_connect_with_retry(&mut self, max_retries: u32) -> Result<Document> {
-        let mut attempt = 0;
+    /// Execute full_connect, retrying per [`retry_policy`](Self::retry_policy).
+    /// Transient errors (connection refused, timeout, TLS errors) are retried;
+    /// auth errors (-950, -999) are not, as those need different fixes.
+    pub async fn full_connect_with_retry(&mut self) -> Result<Document> {
+        let policy = self.retry_policy;
+        let mut attempt = 1;
         loop {
             match self.full_connect().await {
                 Ok(data) => return Ok(data),
                 Err(e) => {
-                    let err_msg = e.to_string();
-                    // Don't retry on auth/protocol errors
-                    if err_msg.contains("-950")
-                        || err_msg.contains("-999")
-                        || err_msg.contains("-400")
-                    {
+                    if !policy.is_retryable_error(&e) {
                         return Err(e);
                     }
-
-                    attempt += 1;
-                    if attempt > max_retries {
+                    if attempt >= policy.max_attempts {
                         return Err(anyhow!(
                             "Failed after {} attempts. Last error: {}",
-                            max_retries,
+                            attempt,
                             e
                         ));
                     }
 
-                    let delay = std::time::Duration::from_millis(500 * 2u64.pow(attempt - 1));
+                    let delay = policy.delay(attempt);
                     eprintln!(
                         "[loco] Attempt {}/{} failed: {}. Retrying in {:?}...",
-                        attempt, max_retries, e, delay
+                        attempt, policy.max_attempts, e, delay
                     );
                     tokio::time::sleep(delay).await;
                     // Reset stream for fresh connection
                     self.disconnect();
+                    attempt += 1;
                 }
             }
         }
@@ -108,6 +108,7 @@ pub fn check_loco_status(command: &str, response: &crate::loco::packet::LocoPack
     if status == 0 {
         Ok(())
     } else {
+        crate::health::observe_status(command, status);
         Err(OpenKakaoError::loco_with_body(command, status, response.body.clone()).into())
     }
 }
@@ -0,0 +1,464 @@
+//! Rotation and following of the JSON-lines log the daemon writes.
+//!
+//! The current file is renamed aside once it reaches a size or its first
+//! entry gets too old; the next write starts a new one. Writers open the
+//! file in append mode for every line, so a rename never loses a line: it
+//! lands in whichever file the name pointed at when it was opened. When the
+//! daemon and an ad-hoc `logs rotate` rotate at once, only one rename
+//! succeeds and the other finds nothing to do.
+//!
+//! Rotated files are gzipped one rotation later, so a writer that opened the
+//! file just before the rename never appends to a file already compressed.
+//! Only the newest `keep` rotated files are kept.
+
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::NaiveDateTime;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde_json::Value;
+
+use crate::bandwidth::parse_byte_size;
+use crate::commands::daemon::TIME_FORMAT;
+use crate::config::DaemonConfig;
+use crate::output::write_atomic;
+use crate::util::parse_duration_secs;
+
+pub const DEFAULT_MAX_SIZE: &str = "10M";
+pub const DEFAULT_MAX_AGE: &str = "7d";
+pub const DEFAULT_KEEP: usize = 5;
+
+/// `daemon.log` in the state directory unless `[daemon] log_file` says
+/// otherwise.
+pub fn daemon_log_path(configured: Option<&str>) -> Result<PathBuf> {
+    match configured {
+        Some(path) => Ok(PathBuf::from(path)),
+        None => Ok(crate::paths::state_dir()?.join("daemon.log")),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RotationPolicy {
+    pub max_bytes: u64,
+    pub max_age_secs: i64,
+    /// Rotated files kept; older ones are deleted.
+    pub keep: usize,
+}
+
+impl RotationPolicy {
+    /// `log_max_size`, `log_max_age` and `log_keep` from `[daemon]`.
+    pub fn from_config(config: &DaemonConfig) -> Result<Self> {
+        let size = config.log_max_size.as_deref().unwrap_or(DEFAULT_MAX_SIZE);
+        let age = config.log_max_age.as_deref().unwrap_or(DEFAULT_MAX_AGE);
+        Ok(Self {
+            max_bytes: parse_byte_size(size)
+                .map_err(|err| anyhow::anyhow!("[daemon] log_max_size: {err}"))?,
+            max_age_secs: parse_duration_secs(age).context("[daemon] log_max_age")?,
+            keep: config.log_keep.unwrap_or(DEFAULT_KEEP),
+        })
+    }
+
+    /// Whether a log of `size` bytes whose first entry was written at
+    /// `first_entry` should be rotated at `now`.
+    pub fn due(&self, size: u64, first_entry: Option<NaiveDateTime>, now: NaiveDateTime) -> bool {
+        size >= self.max_bytes
+            || first_entry.is_some_and(|at| (now - at).num_seconds() >= self.max_age_secs)
+    }
+}
+
+/// The `ts` of the first line of `path`, when it has one.
+pub fn first_entry_at(path: &Path) -> Option<NaiveDateTime> {
+    let mut line = String::new();
+    BufReader::new(File::open(path).ok()?)
+        .read_line(&mut line)
+        .ok()?;
+    let entry: Value = serde_json::from_str(&line).ok()?;
+    NaiveDateTime::parse_from_str(entry.get("ts")?.as_str()?, TIME_FORMAT).ok()
+}
+
+/// Rotate `path` if `policy` says it is due. Returns the rotated file.
+pub fn rotate_if_due(
+    path: &Path,
+    policy: &RotationPolicy,
+    now: NaiveDateTime,
+) -> Result<Option<PathBuf>> {
+    let size = match fs::metadata(path) {
+        Ok(meta) => meta.len(),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => {
+            return Err(err).with_context(|| format!("Failed to inspect {}", path.display()))
+        }
+    };
+    if size == 0 || !policy.due(size, first_entry_at(path), now) {
+        return Ok(None);
+    }
+    rotate(path, policy, now)
+}
+
+/// Rename `path` aside, compress the files earlier rotations left and
+/// delete all but the newest `policy.keep`. Returns the rotated file, or
+/// `None` when there was no file (or another process renamed it first).
+pub fn rotate(path: &Path, policy: &RotationPolicy, now: NaiveDateTime) -> Result<Option<PathBuf>> {
+    let target = rotated_name(path, now);
+    let rotated = match fs::rename(path, &target) {
+        Ok(()) => Some(target),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+        Err(err) => {
+            return Err(err).with_context(|| format!("Failed to rotate {}", path.display()))
+        }
+    };
+    for file in rotated_files(path)? {
+        if Some(&file) != rotated.as_ref() && !is_compressed(&file) {
+            compress(&file)?;
+        }
+    }
+    let files = rotated_files(path)?;
+    for old in &files[..files.len().saturating_sub(policy.keep)] {
+        match fs::remove_file(old) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                return Err(err).with_context(|| format!("Failed to delete {}", old.display()))
+            }
+            _ => {}
+        }
+    }
+    Ok(rotated)
+}
+
+/// `<name>.<YYYYmmdd-HHMMSS>-<pid>`, with a counter when this process
+/// already rotated within the same second.
+fn rotated_name(path: &Path, now: NaiveDateTime) -> PathBuf {
+    let base = format!(
+        "{}.{}-{}",
+        file_name(path),
+        now.format("%Y%m%d-%H%M%S"),
+        std::process::id()
+    );
+    let taken = |name: &str| {
+        let candidate = path.with_file_name(name);
+        candidate.exists() || path.with_file_name(format!("{name}.gz")).exists()
+    };
+    let mut name = base.clone();
+    let mut n = 1;
+    while taken(&name) {
+        name = format!("{base}-{n}");
+        n += 1;
+    }
+    path.with_file_name(name)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn is_compressed(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "gz")
+}
+
+/// Rotated files of the log at `path`, oldest first.
+pub fn rotated_files(path: &Path) -> Result<Vec<PathBuf>> {
+    let prefix = format!("{}.", file_name(path));
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).with_context(|| format!("Failed to list {}", dir.display())),
+    };
+    let mut files: Vec<(String, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let stamp = name.strip_prefix(&prefix)?;
+            let stamp = stamp.strip_suffix(".gz").unwrap_or(stamp);
+            let digits = stamp.as_bytes();
+            let looks_rotated = digits.len() > 16
+                && digits[..8].iter().all(u8::is_ascii_digit)
+                && digits[8] == b'-'
+                && digits[9..15].iter().all(u8::is_ascii_digit)
+                && digits[15] == b'-';
+            looks_rotated.then(|| (stamp.to_string(), entry.path()))
+        })
+        .collect();
+    files.sort();
+    Ok(files.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Replace `path` with `path.gz`.
+fn compress(path: &Path) -> Result<()> {
+    let mut source = match File::open(path) {
+        Ok(file) => file,
+        // Another process compressed it already.
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let target = path.with_file_name(format!("{}.gz", file_name(path)));
+    write_atomic(&target, |out| {
+        let mut encoder = GzEncoder::new(out, Compression::default());
+        io::copy(&mut source, &mut encoder)?;
+        encoder.finish()?;
+        Ok(())
+    })?;
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => {
+            Err(err).with_context(|| format!("Failed to delete {}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// How serious an entry is, for `logs tail --level`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum Level {
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Warn => "warn",
+            Self::Error => "error",
+        }
+    }
+
+    /// The entry's `level`; entries written before it existed count as
+    /// `error` for an `error` event and `info` otherwise.
+    pub fn of(entry: &Value) -> Self {
+        let field = |key: &str| entry.get(key).and_then(Value::as_str);
+        match field("level") {
+            Some("error") => Self::Error,
+            Some("warn") => Self::Warn,
+            Some(_) => Self::Info,
+            None if field("event") == Some("error") => Self::Error,
+            None => Self::Info,
+        }
+    }
+}
+
+/// Whether the log line `line` is at least `min`. Lines that are not JSON
+/// are always shown.
+pub fn at_least(line: &str, min: Level) -> bool {
+    serde_json::from_str::<Value>(line).map_or(true, |entry| Level::of(&entry) >= min)
+}
+
+/// Reads what is appended to a log, across rotations: when the name points
+/// at a new file, the rest of the old one is read before switching, and a
+/// file that shrank is read again from the start.
+pub struct Follower {
+    path: PathBuf,
+    file: Option<File>,
+    pos: u64,
+    partial: Vec<u8>,
+}
+
+impl Follower {
+    /// Starts at the beginning of the file.
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            file: None,
+            pos: 0,
+            partial: Vec::new(),
+        }
+    }
+
+    /// Complete lines written since the last call.
+    pub fn poll(&mut self) -> io::Result<Vec<String>> {
+        let mut lines = Vec::new();
+        if self.file.is_none() {
+            match File::open(&self.path) {
+                Ok(file) => self.file = Some(file),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(lines),
+                Err(err) => return Err(err),
+            }
+        }
+        self.read_available(&mut lines)?;
+        let current = match fs::metadata(&self.path) {
+            Ok(meta) => meta,
+            // Renamed aside and not recreated yet: keep reading the old file.
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(lines),
+            Err(err) => return Err(err),
+        };
+        let open = self.file.as_ref().map(File::metadata).transpose()?;
+        if open.is_some_and(|open| !same_file(&open, &current)) {
+            // Lines a writer added to the old file after the last read.
+            self.read_available(&mut lines)?;
+            if !self.partial.is_empty() {
+                lines.push(String::from_utf8_lossy(&self.partial).into_owned());
+                self.partial.clear();
+            }
+            self.file = None;
+            self.pos = 0;
+            lines.extend(self.poll()?);
+        } else if current.len() < self.pos {
+            self.pos = 0;
+            self.partial.clear();
+            if let Some(file) = &mut self.file {
+                file.seek(SeekFrom::Start(0))?;
+            }
+            self.read_available(&mut lines)?;
+        }
+        Ok(lines)
+    }
+
+    fn read_available(&mut self, lines: &mut Vec<String>) -> io::Result<()> {
+        let Some(file) = &mut self.file else {
+            return Ok(());
+        };
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf)?;
+        self.pos += buf.len() as u64;
+        self.partial.extend_from_slice(&buf);
+        while let Some(end) = self.partial.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.partial.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line[..end]);
+            lines.push(line.trim_end_matches('\r').to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn same_file(a: &fs::Metadata, b: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    (a.dev(), a.ino()) == (b.dev(), b.ino())
+}
+
+/// Without inode numbers a rotation shows up as the file shrinking.
+#[cfg(not(unix))]
+fn same_file(_: &fs::Metadata, _: &fs::Metadata) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn at(time: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(&format!("2024-05-12 {time}"), TIME_FORMAT).unwrap()
+    }
+
+    fn entry(time: &str, event: &str) -> String {
+        format!("{{\"ts\":\"2024-05-12 {time}\",\"task\":\"sync\",\"event\":\"{event}\"}}\n")
+    }
+
+    fn append(path: &Path, text: &str) {
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap()
+            .write_all(text.as_bytes())
+            .unwrap();
+    }
+
+    #[test]
+    fn rotation_is_due_by_size_or_age() {
+        let policy = RotationPolicy {
+            max_bytes: 100,
+            max_age_secs: 3600,
+            keep: 2,
+        };
+        assert!(!policy.due(99, Some(at("10:00:00")), at("10:59:59")));
+        assert!(policy.due(100, None, at("10:00:00")));
+        assert!(policy.due(1, Some(at("10:00:00")), at("11:00:00")));
+
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("daemon.log");
+        append(&log, &entry("10:00:00", "start"));
+        assert_eq!(first_entry_at(&log), Some(at("10:00:00")));
+        assert_eq!(rotate_if_due(&log, &policy, at("10:30:00")).unwrap(), None);
+        let rotated = rotate_if_due(&log, &policy, at("11:00:00"))
+            .unwrap()
+            .unwrap();
+        assert!(!log.exists());
+        assert_eq!(
+            fs::read_to_string(rotated).unwrap(),
+            entry("10:00:00", "start")
+        );
+        // Nothing to rotate until the next write creates the file again.
+        assert_eq!(rotate_if_due(&log, &policy, at("12:00:00")).unwrap(), None);
+    }
+
+    #[test]
+    fn older_rotations_are_compressed_and_pruned() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("daemon.log");
+        let policy = RotationPolicy {
+            max_bytes: 1,
+            max_age_secs: 3600,
+            keep: 2,
+        };
+        for (n, time) in ["10:00:00", "10:00:00", "10:05:00", "10:10:00"]
+            .iter()
+            .enumerate()
+        {
+            append(&log, &entry(time, &format!("run{n}")));
+            rotate(&log, &policy, at(time)).unwrap();
+        }
+        // A rename that loses the race finds nothing to do.
+        assert_eq!(rotate(&log, &policy, at("10:15:00")).unwrap(), None);
+
+        let files = rotated_files(&log).unwrap();
+        let names: Vec<String> = files.iter().map(|f| file_name(f)).collect();
+        assert_eq!(names.len(), 2, "{names:?}");
+        assert!(names[0].starts_with("daemon.log.20240512-100500-"));
+        assert!(names.iter().all(|name| name.ends_with(".gz")));
+
+        let mut text = String::new();
+        flate2::read::GzDecoder::new(File::open(&files[1]).unwrap())
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, entry("10:10:00", "run3"));
+        // Files that only share the prefix are left alone.
+        fs::write(dir.path().join("daemon.log.bak"), "x").unwrap();
+        assert_eq!(rotated_files(&log).unwrap(), files);
+    }
+
+    #[test]
+    fn follower_reads_across_rotation_and_truncation() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("daemon.log");
+        let mut follower = Follower::new(&log);
+        assert!(follower.poll().unwrap().is_empty());
+
+        append(&log, "one\ntw");
+        assert_eq!(follower.poll().unwrap(), ["one"]);
+        append(&log, "o\n");
+        assert_eq!(follower.poll().unwrap(), ["two"]);
+
+        // Renamed aside; a writer that opened it before still appends.
+        let policy = RotationPolicy {
+            max_bytes: 1,
+            max_age_secs: 3600,
+            keep: 5,
+        };
+        let rotated = rotate(&log, &policy, at("10:00:00")).unwrap().unwrap();
+        append(&rotated, "three\n");
+        assert_eq!(follower.poll().unwrap(), ["three"]);
+        append(&rotated, "four\n");
+        append(&log, "five\n");
+        assert_eq!(follower.poll().unwrap(), ["four", "five"]);
+
+        fs::write(&log, "six\n").unwrap();
+        assert_eq!(follower.poll().unwrap(), ["six"]);
+    }
+
+    #[test]
+    fn levels_fall_back_to_the_event() {
+        assert!(at_least(&entry("10:00:00", "error"), Level::Error));
+        assert!(!at_least(&entry("10:00:00", "ok"), Level::Warn));
+        assert!(at_least(r#"{"level":"warn","event":"ok"}"#, Level::Warn));
+        assert!(at_least("not json", Level::Error));
+    }
+}
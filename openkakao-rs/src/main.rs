@@ -1,33 +1,78 @@
-mod auth;
+mod account_conflict;
+mod alias;
+mod attachments;
 mod auth_flow;
+mod avatars;
+mod capabilities;
+mod chat_rules;
 mod commands;
 mod config;
+mod contacts;
+mod crash;
+mod credential_lint;
 mod credentials;
-mod error;
+mod date_literal;
+mod dedup;
 mod export;
-mod local_db;
-mod loco;
+mod export_db;
+mod export_manifest;
+mod gaps;
+mod health;
+#[cfg(feature = "holidays")]
+mod holidays;
+mod ignore;
+mod links;
 mod loco_helpers;
+mod logs;
+mod mbox;
 mod media;
-mod message_db;
-mod model;
-mod rest;
+mod media_cache;
+mod onboarding;
+mod outbox;
+mod output;
+mod prompt;
+mod report;
+mod roster;
+mod scheduler;
+mod schema;
+mod scrap_cache;
+mod search;
+mod settings_diff;
+mod spam;
 mod state;
+mod strict;
+mod tail;
+mod theme;
+#[cfg(feature = "tui")]
+mod tui;
+mod urlnorm;
 mod util;
+mod verify_cache;
+mod warnings;
+mod watch_status;
+mod wrap;
 
+use std::ffi::OsString;
 use std::io;
 use std::sync::atomic::Ordering;
 
-use anyhow::{Context, Result};
+use anyhow::Result;
 use chrono::TimeZone;
 use clap::{CommandFactory, Parser, Subcommand};
 use clap_complete::{generate, Shell};
 
+use openkakao_rs::{
+    auth, bandwidth, capture, credential_provider, credential_watch, cursor, download_pool, error,
+    fuzzy, hangul, humanize, local_db, loco, message_db, model, names, normalize, notify, paths,
+    progress, response_cache, rest, retry, status, takeout, threads, timestamp,
+};
+use response_cache::ResponseCache;
+
 use crate::auth_flow::{set_auth_policy, AuthPolicy};
 use crate::commands::read::ReadCommandOptions;
 use crate::commands::watch::{WatchOptions, WebhookFormat};
 use crate::config::load_config;
-use crate::util::{format_outgoing_message, NO_COLOR, VERSION};
+use crate::util::{format_outgoing_message, NameQuery, NO_COLOR, SHOW_BIDI, VERSION};
 
 #[derive(Parser, Debug)]
 #[command(name = "openkakao-rs")]
@@ -36,8 +81,49 @@ use crate::util::{format_outgoing_message, NO_COLOR, VERSION};
 struct Cli {
     #[arg(long, global = true, help = "Output as JSON")]
     json: bool,
+    /// With --json: keep only these comma-separated fields (dots for nested ones)
+    #[arg(long, global = true, value_name = "a,b.c", requires = "json")]
+    fields: Option<String>,
     #[arg(long, global = true, help = "Disable colored output")]
     no_color: bool,
+    #[arg(
+        long,
+        global = true,
+        value_name = "N",
+        help = "Retries for LOCO connects and REST 429/5xx answers (default: 3)"
+    )]
+    retries: Option<u32>,
+    #[arg(
+        long,
+        global = true,
+        help = "Color theme: dark, light, auto (COLORFGBG, else dark) [config: ui.theme]"
+    )]
+    theme: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        value_name = "LANG",
+        help = "Language of relative times, durations, sizes and counts: en, ko [config: ui.lang]"
+    )]
+    lang: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        help = "Show bidi control characters in messages as <U+XXXX> instead of dropping them"
+    )]
+    show_bidi: bool,
+    #[arg(
+        long,
+        global = true,
+        help = "Exit nonzero if the command raised any warnings"
+    )]
+    fail_on_warnings: bool,
+    #[arg(
+        long,
+        global = true,
+        help = "Never wait for stdin: confirmations without -y/--yes and credential prompts fail (exit 6) instead of asking"
+    )]
+    no_input: bool,
     #[arg(
         long,
         global = true,
@@ -53,7 +139,7 @@ struct Cli {
     #[arg(
         long,
         global = true,
-        help = "Allow watch read receipts, hooks, and webhooks when combined with --unattended"
+        help = "Allow watch read receipts, hooks, webhooks, and telegram notifications when combined with --unattended"
     )]
     allow_watch_side_effects: bool,
     #[arg(
@@ -68,10 +154,178 @@ struct Cli {
         help = "Print [DONE] to stdout after command completes successfully"
     )]
     completion_promise: bool,
+    #[arg(
+        long,
+        global = true,
+        help = "Print name and response cache counters and bytes received to stderr after the command"
+    )]
+    stats: bool,
+    #[arg(
+        long,
+        global = true,
+        value_name = "SIZE",
+        value_parser = bandwidth::parse_byte_size,
+        help = "Stop once this much has been received over HTTP (e.g. 50M), keeping what was fetched"
+    )]
+    max_bytes: Option<u64>,
+    #[arg(
+        long,
+        global = true,
+        value_name = "URL",
+        help = "Send HTTP requests through this http(s):// or socks5(h):// proxy (also OPENKAKAO_PROXY)"
+    )]
+    proxy: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        value_name = "SECS",
+        value_parser = rest::parse_timeout_secs,
+        help = "REST request timeout in seconds (default: 15; also OPENKAKAO_TIMEOUT)"
+    )]
+    timeout: Option<std::time::Duration>,
+    #[arg(
+        long,
+        global = true,
+        value_name = "URL",
+        help = "Send katalk requests here instead, e.g. a local mock server (also OPENKAKAO_BASE_URL)"
+    )]
+    base_url: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        value_name = "URL",
+        help = "Send pilsner (messages, members) requests here instead (also OPENKAKAO_PILSNER_URL)"
+    )]
+    pilsner_url: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        help = "Skip the response cache configured in [http] response_cache"
+    )]
+    fresh: bool,
+    #[arg(
+        long,
+        global = true,
+        help = "Fail on unknown message types or payload drift (also OPENKAKAO_STRICT=1)"
+    )]
+    strict: bool,
+    #[arg(
+        long,
+        global = true,
+        value_name = "DIR",
+        conflicts_with = "strict",
+        help = "Save raw unknown message objects to DIR instead of failing"
+    )]
+    collect_unknown: Option<std::path::PathBuf>,
+    #[arg(
+        long,
+        global = true,
+        help = "Refuse commands that change server state or are visible to other users"
+    )]
+    read_only: bool,
+    #[arg(
+        short = 'v',
+        long,
+        global = true,
+        action = clap::ArgAction::Count,
+        help = "Log each HTTP request and credential lookup to stderr, with raw payloads behind status errors; repeat (-vv) to add response bodies"
+    )]
+    verbose: u8,
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATH",
+        help = "Write the command's output to PATH atomically ('-' for stdout)"
+    )]
+    out: Option<std::path::PathBuf>,
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATH",
+        help = "Write a sanitized JSON transcript of this run (requests, timings, errors) for bug reports"
+    )]
+    capture_report: Option<std::path::PathBuf>,
+    #[arg(
+        long,
+        global = true,
+        requires = "capture_report",
+        help = "Include scrubbed request/response bodies in the capture report"
+    )]
+    capture_bodies: bool,
+    #[arg(
+        long,
+        global = true,
+        help = "Drop utm_* and known ad/click tracking parameters from links (scrap, files, watch output)"
+    )]
+    strip_tracking: bool,
+    #[arg(
+        long,
+        help = "Keep config, caches and state in ./.openkakao (created if missing); \
+                a .openkakao directory in or above the working directory is used automatically"
+    )]
+    local: bool,
+    #[arg(
+        long,
+        help = "In workspace scope, also keep credentials in .openkakao instead of ~/.config/openkakao"
+    )]
+    local_credentials: bool,
+    #[arg(
+        long,
+        global = true,
+        help = "Write nothing to the local caches: read, export and watch skip the message cache, scrap the preview cache"
+    )]
+    no_cache: bool,
+    #[arg(
+        long,
+        global = true,
+        value_name = "NAME",
+        help = "Use the saved credentials of profile NAME (credentials/NAME.json) instead of the default"
+    )]
+    profile: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        help = "Use the newest KakaoTalk token without checking candidates with the server (login, init, auth recovery)"
+    )]
+    no_verify: bool,
+    #[arg(
+        long,
+        global = true,
+        help = "When the credentials have no user id, save the one fetched from profile3/me"
+    )]
+    save_backfill: bool,
+    #[arg(
+        long,
+        global = true,
+        conflicts_with = "prefer_extracted",
+        help = "When the saved credentials and KakaoTalk are different accounts, use the saved one without asking"
+    )]
+    prefer_saved: bool,
+    #[arg(
+        long,
+        global = true,
+        help = "When the saved credentials and KakaoTalk are different accounts, use KakaoTalk's without asking"
+    )]
+    prefer_extracted: bool,
+    #[arg(
+        long,
+        global = true,
+        requires = "prefer_extracted",
+        help = "With --prefer-extracted, also overwrite the saved credentials with KakaoTalk's account"
+    )]
+    overwrite_saved: bool,
+    #[arg(
+        long,
+        global = true,
+        help = "Print the command line after [alias] expansion to stderr before running it"
+    )]
+    print_expansion: bool,
     #[command(subcommand)]
     command: Commands,
 }
 
+// Parsed once per run; `watch` carries most of the flags.
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Verify token validity
@@ -82,17 +336,57 @@ enum Commands {
     Login {
         #[arg(long)]
         save: bool,
+        /// Where --save puts them; defaults to where the profile is now, else
+        /// the keychain when there is one
+        #[arg(long, value_enum, requires = "save")]
+        store: Option<credentials::CredentialStore>,
+    },
+    /// First-run setup: find, verify and save credentials, then pick output and send throttle defaults
+    Init {
+        #[arg(short = 'y', long, help = "Take every default without asking")]
+        yes: bool,
+    },
+    /// Manage saved credentials
+    Credentials {
+        #[command(subcommand)]
+        action: CredentialsCommand,
+    },
+    /// Inspect configuration and where state is stored
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+    /// Show own profile, or change its status message and nickname
+    Me {
+        /// New status message; "" clears it
+        #[arg(long, value_name = "TEXT")]
+        set_status: Option<String>,
+        /// New nickname
+        #[arg(long, value_name = "NAME")]
+        set_nickname: Option<String>,
+    },
+    /// Print your user id and nickname (one request; the cheap liveness check)
+    Whoami {
+        #[arg(long, help = "Answer from saved credentials only, without any network")]
+        offline: bool,
     },
-    /// Show own profile
-    Me,
     /// List friends
+    #[command(visible_alias = "f")]
     Friends {
         #[arg(short = 'f', long)]
         favorites: bool,
-        #[arg(long)]
+        #[arg(long, help = "Include hidden friends")]
         hidden: bool,
+        #[arg(long, help = "List only hidden friends")]
+        hidden_only: bool,
         #[arg(short = 's', long)]
         search: Option<String>,
+        #[arg(
+            long,
+            requires = "search",
+            help = "With no exact --search hit, use the closest name if it clearly stands out"
+        )]
+        fuzzy: bool,
         #[arg(
             long,
             help = "Build a local friend graph from LOCO GETMEM across known chats"
@@ -105,21 +399,160 @@ enum Commands {
         chat_id: Option<i64>,
         #[arg(long, help = "When used with --local, only include this user")]
         user_id: Option<i64>,
+        #[arg(
+            long,
+            visible_alias = "uuid",
+            help = "Show Kakao IDs and full status messages"
+        )]
+        detail: bool,
+        #[arg(
+            long,
+            help = "Print phone numbers as stored instead of in international form"
+        )]
+        raw_phones: bool,
+        #[arg(
+            long,
+            value_name = "DIR",
+            conflicts_with_all = ["local", "detail"],
+            help = "Save the listed friends' profile images into DIR, skipping unchanged ones"
+        )]
+        download_avatars: Option<std::path::PathBuf>,
+        #[arg(
+            long,
+            default_value_t = download_pool::DEFAULT_JOBS as u8,
+            requires = "download_avatars",
+            value_parser = clap::value_parser!(u8).range(1..=8),
+            help = "Parallel downloads with --download-avatars"
+        )]
+        jobs: u8,
+        /// Write the listed friends as CSV or vCard 3.0 instead of the table
+        /// (to a file with --out)
+        #[arg(
+            long,
+            value_enum,
+            value_name = "FORMAT",
+            conflicts_with_all = ["local", "detail", "download_avatars"]
+        )]
+        export: Option<contacts::ContactFormat>,
+    },
+    /// Friend lookups beyond your own friends list
+    Friend {
+        #[command(subcommand)]
+        action: FriendCommand,
+    },
+    /// Show everything known about one chat room: settings, members, cached range
+    Chat { chat_id: i64 },
+    /// Find the 1:1 chat with a friend (name, 초성 or user id) and print its id
+    Dm {
+        friend: String,
+        #[arg(
+            long,
+            conflicts_with = "send",
+            help = "Read the chat instead of printing its id"
+        )]
+        read: bool,
+        #[arg(
+            short = 'n',
+            long,
+            default_value_t = 30,
+            requires = "read",
+            help = "Messages to show with --read"
+        )]
+        count: usize,
+        #[arg(long, value_name = "TEXT", help = "Send TEXT to the chat (asks first)")]
+        send: Option<String>,
+        #[arg(
+            short = 'y',
+            long,
+            requires = "send",
+            help = "Skip the send confirmation"
+        )]
+        yes: bool,
     },
     /// List chat rooms
+    #[command(
+        visible_alias = "ls",
+        args_conflicts_with_subcommands = true,
+        subcommand_negates_reqs = true
+    )]
     Chats {
+        #[command(subcommand)]
+        action: Option<ChatsCommand>,
         #[arg(short = 'a', long = "all")]
         show_all: bool,
         #[arg(short = 'u', long)]
         unread: bool,
         #[arg(long, help = "Search chat rooms by title")]
         search: Option<String>,
+        #[arg(
+            long,
+            requires = "search",
+            help = "With no exact --search hit, use the closest title if it clearly stands out"
+        )]
+        fuzzy: bool,
         #[arg(long = "type", help = "Filter by type: dm, group, memo, open")]
         chat_type: Option<String>,
+        #[arg(
+            long,
+            value_name = "DURATION",
+            help = "Only rooms idle for at least this long (e.g. 90d, 2w), oldest first"
+        )]
+        stale: Option<String>,
+        #[arg(
+            long,
+            value_enum,
+            conflicts_with_all = ["memo", "stale"],
+            help = "Order rooms by last activity, unread count or title"
+        )]
+        sort: Option<commands::chats::ChatSort>,
         #[arg(long, help = "Force REST chat list path instead of LOCO")]
         rest: bool,
+        #[arg(long, help = "Only print the memo chat (나와의 채팅) id")]
+        memo: bool,
+        #[arg(
+            long,
+            conflicts_with = "memo",
+            visible_alias = "changed",
+            help = "Only rooms newly unread, with more unread or renamed since the last --since-state run; exit 5 when there are none. LOCO lists no unread counts, so there an already unread room counts as changed only when a newer message arrived"
+        )]
+        since_state: bool,
+        #[arg(
+            long,
+            conflicts_with = "memo",
+            help = "Add a column with how many messages each room is past my read watermark (REST chat list)"
+        )]
+        read_status: bool,
+    },
+    /// Show a group chat's pinned notice (공지): author, date and full text
+    Notice {
+        chat_id: i64,
+        #[arg(long, help = "Also list earlier notices recorded by previous runs")]
+        history: bool,
+    },
+    /// List the board posts (게시판) of an open chat community: type, author, date, title, comments
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    Board {
+        #[command(subcommand)]
+        action: Option<BoardCommand>,
+        #[arg(required = true)]
+        chat_id: Option<i64>,
+        #[arg(
+            short = 'n',
+            long,
+            default_value_t = 20,
+            help = "Fetch pages until at least this many posts"
+        )]
+        count: usize,
+        #[arg(long, help = "Continue from the cursor printed by a previous listing")]
+        cursor: Option<String>,
+    },
+    /// Read or search your memo chat (나와의 채팅) without looking up its id
+    Memo {
+        #[command(subcommand)]
+        action: MemoCommand,
     },
     /// Read messages from a chat room
+    #[command(visible_alias = "r")]
     Read {
         chat_id: i64,
         #[arg(short = 'n', long, default_value_t = 30)]
@@ -128,8 +561,19 @@ enum Commands {
         before: Option<i64>,
         #[arg(long, help = "Resume from cursor (logId from previous run)")]
         cursor: Option<i64>,
-        #[arg(long, help = "Filter messages after this date (YYYY-MM-DD)")]
+        /// Only messages from this time on, all of them (-n is ignored):
+        /// YYYY-MM-DD, YYYY-MM-DDTHH:MM, an age like 2d or 12h, or a day name
+        /// (오늘, 어제, 그제, 3일전, 지난주, 지난달, a holiday like 설날, or
+        /// today, yesterday, 3 days ago, last week...), in local time. A day
+        /// name covers whole days, so it starts at midnight: 3일전 is 00:00
+        /// three days ago, 3d is 72 hours before now
+        #[arg(long, value_name = "WHEN")]
         since: Option<String>,
+        /// Only messages before the end of this time (a date or day name
+        /// includes its whole last day: --until 지난주 runs through Sunday);
+        /// same forms as --since
+        #[arg(long, value_name = "WHEN")]
+        until: Option<String>,
         #[arg(long, help = "Fetch all available messages")]
         all: bool,
         #[arg(
@@ -142,24 +586,153 @@ enum Commands {
         force: bool,
         #[arg(long, help = "Force REST read path instead of LOCO")]
         rest: bool,
+        #[arg(long, help = "Append an openkakao:// message link to each message")]
+        links: bool,
+        #[arg(
+            long,
+            help = "Show how many members have not read each message (· N), when the room exposes it"
+        )]
+        receipts: bool,
+        /// Show -n messages centered on this logId, paging back until it is found
+        #[arg(long, value_name = "LOG_ID", conflicts_with_all = ["before", "cursor", "since", "until", "all"])]
+        context: Option<i64>,
+        /// With --context, read only the local message cache
+        #[arg(long, requires = "context")]
+        from_archive: bool,
+        #[arg(
+            long,
+            value_name = "COLS",
+            help = "Wrap message bodies at this many columns, continuation lines aligned under the body (default: terminal width; 0 disables)"
+        )]
+        wrap: Option<usize>,
+        #[arg(
+            long,
+            conflicts_with = "wrap",
+            help = "Print message bodies with their original line breaks only"
+        )]
+        no_wrap: bool,
+        /// Page back from where the last --continue read of this chat stopped (REST)
+        #[arg(
+            long = "continue",
+            conflicts_with_all = ["before", "cursor", "since", "until", "all", "context"]
+        )]
+        continue_paging: bool,
+        /// Forget the saved --continue cursor and start from the newest messages
+        #[arg(long, conflicts_with_all = ["before", "cursor", "since", "until", "all", "context"])]
+        restart: bool,
+        /// Group replies under the messages they answer, indented; replies to
+        /// messages outside the window are marked and shown at the top level
+        #[arg(long, conflicts_with = "context")]
+        threads: bool,
+        /// Mark messages that local heuristics (link dumps, repeats, [spam]
+        /// phrases, links right after joining) score as spam with [spam?]
+        #[arg(long, conflicts_with = "context")]
+        flag_spam: bool,
+        /// Leave out messages --flag-spam would mark
+        #[arg(long, conflicts_with = "context")]
+        hide_spam: bool,
+        /// Replace each run of messages from ignored authors with one
+        /// "(n messages from X hidden)" line instead of leaving them out
+        #[arg(long, conflicts_with = "context")]
+        collapse_ignored: bool,
+        /// Albums show as "(album: 4 photos)"; url lists each photo's size
+        /// and URL below it
+        #[arg(long, value_enum, value_name = "SHOW", default_value = "count")]
+        images: commands::read::ImageDisplay,
+    },
+    /// Hide an author's messages from read, watch and export, everywhere or in one chat
+    Ignore {
+        #[command(subcommand)]
+        action: IgnoreCommand,
     },
+    /// Browse chats and messages in a two-pane terminal UI
+    ///
+    /// Chat list with unread badges and 초성 search on the left, messages on
+    /// the right; scrolling up loads older pages. Keys: enter open, / search,
+    /// r refresh, m mark read, o open in KakaoTalk, e export to .txt, q quit.
+    Tui,
     /// List members of a chat room
+    #[command(visible_alias = "m")]
     Members {
         chat_id: i64,
         #[arg(long, help = "Force REST member list path instead of LOCO")]
         rest: bool,
         #[arg(long, help = "Show richer LOCO member profile fields")]
         full: bool,
+        #[arg(
+            long,
+            help = "Show only this many members; with --rest paging stops there, LOCO still fetches the whole list"
+        )]
+        limit: Option<usize>,
+        #[arg(
+            long,
+            value_name = "PATH",
+            conflicts_with_all = ["merge", "full", "limit"],
+            help = "Write a roster CSV (user id, name, role, country, join date, friend, profile image); - for stdout"
+        )]
+        export_csv: Option<String>,
+        #[arg(
+            long,
+            value_name = "ROSTER",
+            conflicts_with_all = ["full", "limit"],
+            help = "Update an existing roster CSV in place, matching rows on user_id and keeping extra columns"
+        )]
+        merge: Option<String>,
+        /// Each member's message count and last message from the archive (or recent server history), longest inactive first
+        #[arg(long, conflicts_with_all = ["merge", "full", "limit"])]
+        activity: bool,
+        /// With --activity: only members known to be silent for at least this many days
+        #[arg(long, value_name = "N", requires = "activity")]
+        inactive_days: Option<u32>,
     },
     /// Get detailed information about a chat room
     Chatinfo { chat_id: i64 },
+    /// Look up, join or leave open chats by their open.kakao.com link
+    Openchat {
+        #[command(subcommand)]
+        action: OpenchatCommand,
+    },
     /// Show account settings
-    Settings,
+    Settings {
+        #[arg(
+            long,
+            help = "Save a normalized snapshot of the settings for later --changes"
+        )]
+        track: bool,
+        #[arg(
+            long,
+            help = "Show field-level changes since the last tracked snapshot (new devices called out)"
+        )]
+        changes: bool,
+    },
     /// Get link preview (OG tags) for a URL
-    Scrap { url: String },
-    /// Show a friend's profile
+    Scrap {
+        url: String,
+        #[arg(
+            long,
+            conflicts_with = "no_cache",
+            help = "Fetch even if a fresh preview is cached, then update the cache"
+        )]
+        refresh: bool,
+        #[arg(
+            long,
+            conflicts_with_all = ["refresh", "no_cache"],
+            help = "Answer from the preview cache only (offline); stale entries are shown"
+        )]
+        cached_only: bool,
+        #[arg(long, value_name = "SECS", default_value_t = scrap_cache::DEFAULT_TTL_SECS, help = "Serve cached previews younger than this")]
+        ttl: u64,
+    },
+    /// Show a friend's profile: name, status, images, birthday and account type
     Profile {
-        user_id: i64,
+        #[arg(required_unless_present = "name")]
+        user_id: Option<i64>,
+        #[arg(
+            long,
+            conflicts_with = "user_id",
+            help = "Look the user up by friend name (초성 allowed) instead of id"
+        )]
+        name: Option<String>,
         #[arg(long, help = "Use chat-scoped LOCO member profile for this chat")]
         #[arg(conflicts_with = "local")]
         chat_id: Option<i64>,
@@ -170,29 +743,175 @@ enum Commands {
         local: bool,
     },
     /// Add a friend to favorites
-    Favorite { user_id: i64 },
+    Favorite {
+        user_id: i64,
+        #[arg(long, short = 'y', help = "Skip confirmation prompt")]
+        yes: bool,
+    },
     /// Remove a friend from favorites
-    Unfavorite { user_id: i64 },
+    Unfavorite {
+        user_id: i64,
+        #[arg(long, short = 'y', help = "Skip confirmation prompt")]
+        yes: bool,
+    },
     /// Hide a friend
-    Hide { user_id: i64 },
-    /// Unhide a friend
-    Unhide { user_id: i64 },
+    Hide {
+        user_id: i64,
+        #[arg(long, short = 'y', help = "Skip confirmation prompt")]
+        yes: bool,
+    },
+    /// Unhide a friend, or every hidden friend with --all-hidden
+    Unhide {
+        #[arg(required_unless_present = "all_hidden")]
+        user_id: Option<i64>,
+        #[arg(
+            long,
+            conflicts_with = "user_id",
+            help = "Unhide every hidden friend (shows the plan and asks first)"
+        )]
+        all_hidden: bool,
+        #[arg(long, short = 'y', help = "Skip confirmation prompt")]
+        yes: bool,
+        #[arg(
+            long,
+            conflicts_with = "user_id",
+            help = "Preview the action without executing"
+        )]
+        dry_run: bool,
+    },
     /// List profile cards (multi-profile)
     Profiles,
     /// Show notification alarm keywords
     Keywords,
+    /// Print the catalog of REST endpoints this build calls, as JSON
+    Endpoints,
     /// Show unread chat summary
     Unread,
+    /// Account overview: friends, chats by type, unread total, token health
+    Summary {
+        /// Page through every chat instead of counting the first page only
+        #[arg(long)]
+        exact: bool,
+    },
     /// Export chat messages
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
     Export {
-        chat_id: i64,
-        #[arg(long, default_value = "txt", help = "Output format: json, csv, txt")]
-        format: String,
-        #[arg(short = 'o', long, help = "Output file (default: stdout)")]
+        #[command(subcommand)]
+        action: Option<ExportCommand>,
+        #[arg(required = true)]
+        chat_id: Option<i64>,
+        #[arg(
+            long,
+            default_value = "txt",
+            help = "Output format: json, jsonl, csv, txt, mbox"
+        )]
+        format: String,
+        #[arg(short = 'o', long, help = "Output file (default: stdout)")]
         output: Option<String>,
+        /// Stop after this many history pages
+        #[arg(long, default_value = "100")]
+        max_pages: usize,
+        /// Add only messages whose log_id is not in the output file yet (jsonl, csv)
+        #[arg(long, conflicts_with = "anonymize")]
+        append: bool,
+        /// Replace authors with Member A, B, ... and mask phone numbers and emails
+        #[arg(long)]
+        anonymize: bool,
+        /// Drop photo, video and file messages and all attachment data
+        #[arg(long)]
+        no_media: bool,
+        /// Download photos and files and attach them to the mails (mbox only)
+        #[arg(long, conflicts_with = "no_media")]
+        embed_media: bool,
+        /// Mask text matching this regex as [redacted] (repeatable)
+        #[arg(long, value_name = "REGEX")]
+        redact: Vec<String>,
+        /// Write the pseudonym-to-member mapping to this JSON file
+        #[arg(long, requires = "anonymize")]
+        key_file: Option<String>,
+        /// Mark where the chat title changed, from `cache` syncs (txt, json, jsonl)
+        #[arg(long)]
+        annotate_titles: bool,
+        /// Group replies under the messages they answer, indented (txt)
+        #[arg(long)]
+        threads: bool,
+        /// Skip the integrity manifest written beside an output file
+        #[arg(long)]
+        no_manifest: bool,
+    },
+    /// Export friends, chats, chat members and your profile to SQLite.
+    ///
+    /// Writes the tables profile, friends, chats and chat_members (plus
+    /// schema_version). Re-exporting into the same file updates rows in place.
+    ///
+    /// Example queries:
+    ///
+    ///   -- friends I share no group chat with
+    ///   SELECT f.display_name FROM friends f WHERE NOT EXISTS (
+    ///     SELECT 1 FROM chat_members m JOIN chats c USING (chat_id)
+    ///     WHERE m.user_id = f.user_id AND c.kind = 'MultiChat');
+    ///
+    ///   -- group chats ranked by how many of my friends are in them
+    ///   SELECT c.display_title, COUNT(*) AS friends FROM chat_members m
+    ///     JOIN chats c USING (chat_id) JOIN friends f USING (user_id)
+    ///     GROUP BY c.chat_id ORDER BY friends DESC;
+    #[command(verbatim_doc_comment)]
+    ExportDb {
+        #[arg(help = "SQLite file to create or update")]
+        path: String,
+        #[arg(
+            long,
+            default_value = "none",
+            help = "Which chats get their members fetched: all, none, unread"
+        )]
+        chats_members: String,
+    },
+    /// Export everything the REST API hands over into a directory, for a
+    /// personal archive
+    ///
+    /// Writes profile.json, settings.json, friends.json, chats.json and
+    /// chats/<chat_id>/{members,messages}.json with an index.json manifest.
+    /// Only reads; requests are spaced out and --max-bytes applies. Run it
+    /// again on the same directory to resume after an interruption.
+    Takeout {
+        /// Directory to export into (empty, new, or an earlier takeout)
+        dir: std::path::PathBuf,
+        /// Only chats matching this rule (repeatable; default: the [sync] rules)
+        #[arg(long, value_name = "RULE")]
+        include: Vec<String>,
+        /// Skip chats matching this rule (repeatable; default: the [sync] rules)
+        #[arg(long, value_name = "RULE")]
+        exclude: Vec<String>,
+        /// Stop after this many history pages per chat
+        #[arg(long, default_value_t = takeout::DEFAULT_MAX_PAGES)]
+        max_pages: usize,
+    },
+    /// Search message text in one chat or every chat
+    ///
+    /// Chats the local cache has synced are searched there unless they have
+    /// unread messages; the rest are paged over REST. The older form
+    /// `search <chat_id> <query>` still works.
+    Search {
+        #[arg(required = true, num_args = 1..=2, value_name = "QUERY")]
+        terms: Vec<String>,
+        #[arg(long = "chat", help = "Only search this chat")]
+        chat_id: Option<i64>,
+        #[arg(long, help = "Treat the query as a regular expression")]
+        regex: bool,
+        #[arg(long, help = "Match case exactly")]
+        case_sensitive: bool,
+        #[arg(
+            long,
+            value_name = "N",
+            help = "REST pages to read per chat (default: 100 with --chat, 5 otherwise)"
+        )]
+        max_pages: Option<usize>,
+        #[arg(long, help = "Append an openkakao:// message link to each match")]
+        links: bool,
+        /// Match ㅋㅋ against ㅋㅋㅋㅋ, full-width against ASCII, any Latin case
+        #[arg(long, conflicts_with_all = ["regex", "case_sensitive"])]
+        normalize: bool,
     },
-    /// Search messages in a chat room
-    Search { chat_id: i64, query: String },
     /// Show chat statistics (message counts, activity, top participants)
     Stats {
         chat_id: i64,
@@ -201,8 +920,49 @@ enum Commands {
             help = "Number of recent messages to analyze (default: all available)"
         )]
         limit: Option<usize>,
-        #[arg(long, help = "Only count messages after this date (YYYY-MM-DD)")]
+        #[arg(
+            long,
+            value_name = "WHEN",
+            help = "Only count messages from this time on (YYYY-MM-DD, 2d, or a day name like 어제 or 지난주; see read --help)"
+        )]
         since: Option<String>,
+        /// Compare two date ranges side by side (e.g. 2024-01..2024-02 2024-03..2024-04)
+        #[arg(
+            long,
+            num_args = 2,
+            value_names = ["RANGE1", "RANGE2"],
+            conflicts_with_all = ["limit", "since"]
+        )]
+        compare: Option<Vec<String>>,
+        #[arg(
+            long,
+            conflicts_with_all = ["limit", "since", "compare"],
+            help = "Count messages per type and sum attachment sizes, with a download size estimate"
+        )]
+        media: bool,
+        #[arg(
+            long,
+            default_value_t = 5,
+            requires = "media",
+            help = "REST message pages to scan after the local cache with --media (0 = cache only)"
+        )]
+        pages: usize,
+    },
+    /// Weekly or monthly activity report, ready to post into a chat or memo
+    Report {
+        #[arg(long = "chat")]
+        chat_id: i64,
+        /// week or month, each ending now and compared with the one before
+        #[arg(long, default_value = "week")]
+        period: String,
+        /// text or md
+        #[arg(long, default_value = "text")]
+        format: String,
+        /// Send the rendered report to this chat via LOCO instead of printing it
+        #[arg(long)]
+        send_to: Option<i64>,
+        #[arg(long, short = 'y', help = "Skip confirmation prompt")]
+        yes: bool,
     },
     /// Generate shell completions
     Completions {
@@ -211,6 +971,24 @@ enum Commands {
     },
     /// Attempt to renew OAuth token using cached refresh_token
     Renew,
+    /// Run the [[daemon.tasks]] schedule from the config file until stopped
+    ///
+    /// Tasks (sync, renew, unread) run in-process one at a time, each again
+    /// `every` after it finished and never inside its `quiet_hours`. Events go
+    /// to a JSON-lines log; SIGINT/SIGTERM stop after the current task.
+    Daemon {
+        #[command(subcommand)]
+        action: Option<DaemonCommand>,
+    },
+    /// Show, follow and rotate the daemon log
+    ///
+    /// The log is rotated at `log_max_size` or once its first entry is
+    /// `log_max_age` old ([daemon] in the config file); rotated files are
+    /// gzipped and the newest `log_keep` kept.
+    Logs {
+        #[command(subcommand)]
+        action: LogsCommand,
+    },
     /// Re-login via login.json to obtain LOCO access_token
     Relogin {
         /// Generate fresh X-VC values instead of using cached one
@@ -230,12 +1008,20 @@ enum Commands {
     Send {
         chat_id: i64,
         message: String,
+        #[arg(
+            long,
+            help = "Send a message over the length limit as several messages, split at line breaks"
+        )]
+        split: bool,
         #[arg(long, help = "Allow sending to open chats (higher ban risk)")]
         force: bool,
         #[arg(long, short = 'y', help = "Skip confirmation prompt")]
         yes: bool,
         #[arg(long, help = "Preview the action without executing")]
         dry_run: bool,
+        /// Store the message in the outbox instead of sending it now
+        #[arg(long, conflicts_with_all = ["yes", "dry_run"])]
+        queue: bool,
     },
     /// Send a message to memo chat (나와의 채팅) via LOCO protocol
     SendMe {
@@ -245,10 +1031,28 @@ enum Commands {
         #[arg(long, help = "Preview the action without executing")]
         dry_run: bool,
     },
-    /// Watch real-time messages via LOCO protocol
+    /// Watch real-time messages via LOCO protocol, or poll one chat (or all) over REST like `tail -f`
     Watch {
         #[arg(long, help = "Filter by chat ID")]
         chat_id: Option<i64>,
+        /// Poll this chat over REST and print only new messages, without a LOCO connection
+        #[arg(value_name = "CHAT_ID", conflicts_with_all = ["chat_id", "all_chats"])]
+        poll_chat_id: Option<i64>,
+        /// Poll the chat list over REST and print new messages of rooms whose unread count rose
+        #[arg(long)]
+        all_chats: bool,
+        /// Seconds between polls with CHAT_ID or --all-chats
+        #[arg(long, value_name = "SECS", default_value_t = 5, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+        /// With CHAT_ID or --all-chats, run CMD for each new message with its text on stdin
+        #[arg(long, value_name = "CMD")]
+        exec: Option<String>,
+        /// Only chats matching this rule (repeatable; same syntax as [sync] include)
+        #[arg(long, value_name = "RULE")]
+        include: Vec<String>,
+        /// Skip chats matching this rule (repeatable; same syntax as [sync] exclude)
+        #[arg(long, value_name = "RULE")]
+        exclude: Vec<String>,
         #[arg(long, help = "Show raw BSON body")]
         raw: bool,
         #[arg(long, help = "Send read receipts (NOTIREAD) for incoming messages")]
@@ -312,6 +1116,9 @@ enum Commands {
         webhook_format: Option<String>,
         #[arg(long, help = "Stop watch when a hook command fails")]
         hook_fail_fast: bool,
+        /// Notify about each new message: macos, desktop (notify-send), telegram or stdout; repeatable
+        #[arg(long, value_name = "BACKEND")]
+        notify: Vec<String>,
         #[arg(long, help = "Resume from last saved watch state (last-seen log IDs)")]
         resume: bool,
         #[arg(
@@ -319,6 +1126,19 @@ enum Commands {
             help = "Capture unknown push packets as JSON for protocol reverse engineering"
         )]
         capture: bool,
+        /// Ring the terminal bell per message and keep a one-line unread summary instead of scrolling
+        #[arg(long)]
+        bell: bool,
+        /// Show the unread count in the terminal window title
+        #[arg(long)]
+        title: bool,
+        /// Recent log ids remembered per chat so replayed messages print once (0 = off)
+        #[arg(long, value_name = "N", default_value_t = dedup::DEFAULT_WINDOW)]
+        dedup_window: usize,
+        /// Print one "(n messages from X hidden)" line per run of messages
+        /// from ignored authors instead of nothing
+        #[arg(long)]
+        collapse_ignored: bool,
     },
     /// Send a photo via LOCO protocol (alias for send-file)
     SendPhoto {
@@ -343,6 +1163,14 @@ enum Commands {
         yes: bool,
         #[arg(long, help = "Preview the action without executing")]
         dry_run: bool,
+        /// Store the file reference in the outbox instead of sending it now
+        #[arg(long, conflicts_with_all = ["yes", "dry_run"])]
+        queue: bool,
+    },
+    /// Messages stored by `send --queue` / `send-file --queue`
+    Outbox {
+        #[command(subcommand)]
+        action: OutboxCommand,
     },
     /// Delete a message via LOCO protocol
     Delete {
@@ -355,8 +1183,21 @@ enum Commands {
         #[arg(long, help = "Preview the action without executing")]
         dry_run: bool,
     },
-    /// Mark messages as read up to a specific message via LOCO protocol
-    MarkRead { chat_id: i64, log_id: i64 },
+    /// Mark messages as read via LOCO, up to the newest one or a given log id
+    #[command(visible_alias = "markread")]
+    MarkRead {
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        chat_id: Option<i64>,
+        /// Last read message (default: the newest one in the chat)
+        #[arg(conflicts_with = "up_to")]
+        log_id: Option<i64>,
+        #[arg(long, value_name = "LOG_ID", help = "Mark read up to this message")]
+        up_to: Option<i64>,
+        #[arg(long, help = "Mark every chat with unread messages as read")]
+        all: bool,
+        #[arg(long, short = 'y', help = "Skip confirmation prompt for --all")]
+        yes: bool,
+    },
     /// Add a reaction to a message via LOCO ACTION
     React {
         chat_id: i64,
@@ -379,18 +1220,103 @@ enum Commands {
         #[arg(long, help = "Preview the action without executing")]
         dry_run: bool,
     },
-    /// Download media attachment from a specific message
+    /// Download one message's attachment, or without a log id every photo and file in a chat
     Download {
         chat_id: i64,
-        log_id: i64,
+        log_id: Option<i64>,
+        #[arg(
+            long = "log-id",
+            id = "log_id_flag",
+            value_name = "LOG_ID",
+            conflicts_with = "log_id",
+            help = "Message to download (same as the positional LOG_ID; see `files`)"
+        )]
+        log_id_flag: Option<i64>,
         #[arg(short = 'o', long, help = "Output directory (default: downloads)")]
         output_dir: Option<String>,
+        #[arg(
+            long,
+            value_delimiter = ',',
+            value_name = "TYPES",
+            conflicts_with_all = ["log_id", "log_id_flag"],
+            help = "Whole chat: only these types (photo, video, file; default: all)"
+        )]
+        types: Vec<String>,
+        #[arg(
+            long,
+            value_name = "WHEN",
+            conflicts_with_all = ["log_id", "log_id_flag"],
+            help = "Whole chat: only messages sent from this time on (YYYY-MM-DD, 2d, or a day name like 어제; see read --help)"
+        )]
+        since: Option<String>,
+        #[arg(
+            long,
+            visible_alias = "jobs",
+            default_value_t = download_pool::DEFAULT_JOBS as u8,
+            value_parser = clap::value_parser!(u8).range(1..=8),
+            help = "Whole chat: parallel downloads"
+        )]
+        concurrency: u8,
+        /// Single message: keep a copy in the media cache, and reuse one
+        /// already there instead of fetching again
+        #[arg(long, conflicts_with_all = ["types", "since"])]
+        cache: bool,
+    },
+    /// List photo, video and file attachments in a chat (local cache, then recent pages)
+    Files {
+        chat_id: i64,
+        #[arg(
+            long,
+            default_value_t = 5,
+            help = "REST message pages to scan after the local cache (0 = cache only)"
+        )]
+        pages: usize,
+        #[arg(long = "type", help = "Only this type: photo, video, file, link")]
+        attachment_type: Option<String>,
+        #[arg(long, help = "Only filenames containing this text (case-insensitive)")]
+        grep: Option<String>,
+        /// Compare --grep and filenames normalized (NFC, full-width, ㅋㅋㅋ runs)
+        #[arg(long, requires = "grep")]
+        normalize: bool,
+    },
+    /// List every link shared in a chat, once each (local cache, then recent pages)
+    Links {
+        chat_id: i64,
+        #[arg(
+            long,
+            default_value_t = 5,
+            help = "REST message pages to scan after the local cache (0 = cache only)"
+        )]
+        pages: usize,
+        /// Fetch link previews for titles the preview cache does not have (throttled)
+        #[arg(long)]
+        enrich: bool,
+        /// table or md (a Markdown list per month)
+        #[arg(long, default_value = "table")]
+        format: String,
     },
     /// Sync messages to local SQLite cache for offline search
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
     Cache {
-        chat_id: i64,
+        #[command(subcommand)]
+        action: Option<CacheCommand>,
+        #[arg(required_unless_present = "all")]
+        chat_id: Option<i64>,
         #[arg(long, help = "Max messages to sync (default: all)")]
         limit: Option<usize>,
+        /// Every chat the [sync] include/exclude rules in the config select
+        #[arg(long, conflicts_with = "chat_id")]
+        all: bool,
+        /// With --all: list each chat and the rule that included or excluded it, without syncing
+        #[arg(long, requires = "all", conflicts_with = "chat_id")]
+        explain: bool,
+        /// Keep a salted hash and the length of each message instead of its text
+        /// (overrides [archive] content)
+        #[arg(long)]
+        no_content: bool,
+        /// Switch a chat already archived in the other content mode instead of refusing
+        #[arg(long)]
+        convert: bool,
     },
     /// Search locally cached messages
     CacheSearch {
@@ -399,9 +1325,44 @@ enum Commands {
         chat_id: Option<i64>,
         #[arg(short = 'n', long, default_value_t = 30)]
         count: usize,
+        #[arg(long, help = "Append an openkakao:// message link to each match")]
+        links: bool,
+        /// Require a normalized index (see cache-reindex); queries against
+        /// one are always normalized
+        #[arg(long)]
+        normalize: bool,
+        /// Also search chats that are no longer in the chat list (left or deleted)
+        #[arg(long)]
+        include_departed: bool,
+    },
+    /// Rebuild the local cache search index, with or without normalization
+    ///
+    /// With --normalize, messages are indexed (and cache-search queries
+    /// matched) after NFC, full-width folding, Latin case folding and
+    /// collapsing ㅋㅋㅋ/ㅠㅠㅠ runs. The choice is kept until the next reindex.
+    CacheReindex {
+        #[arg(long)]
+        normalize: bool,
     },
     /// Show local cache statistics
     CacheStats,
+    /// History kept in the local message cache
+    Archive {
+        #[command(subcommand)]
+        action: ArchiveCommand,
+    },
+    /// Print the openkakao:// link for a message
+    Link {
+        chat_id: i64,
+        log_id: i64,
+        #[arg(long, help = "Show the linked message in context right away")]
+        open: bool,
+    },
+    /// Show a linked message with surrounding context from the local cache
+    Open {
+        /// Link of the form openkakao://chat/<chat_id>/<log_id>
+        reference: String,
+    },
     #[command(hide = true)]
     /// List chat rooms via LOCO protocol (legacy command)
     LocoChats {
@@ -416,7 +1377,11 @@ enum Commands {
         count: i32,
         #[arg(long, help = "Resume from this logId (cursor from previous run)")]
         cursor: Option<i64>,
-        #[arg(long, help = "Filter messages after this date (YYYY-MM-DD)")]
+        #[arg(
+            long,
+            value_name = "WHEN",
+            help = "Filter messages from this time on (YYYY-MM-DD, 2d, or a day name like 어제 or 지난주; see read --help)"
+        )]
         since: Option<String>,
         #[arg(long, help = "Fetch all available messages")]
         all: bool,
@@ -501,7 +1466,11 @@ enum Commands {
         chat_id: i64,
         #[arg(short = 'n', long, default_value_t = 30)]
         count: usize,
-        #[arg(long, help = "Filter messages after this date (YYYY-MM-DD)")]
+        #[arg(
+            long,
+            value_name = "WHEN",
+            help = "Filter messages from this time on (YYYY-MM-DD, 2d, or a day name like 어제 or 지난주; see read --help)"
+        )]
         since: Option<String>,
     },
     /// Search messages in local KakaoTalk database (no server contact, safe)
@@ -517,126 +1486,1446 @@ enum Commands {
         /// Also test LOCO booking connectivity (makes network request)
         #[arg(long)]
         loco: bool,
+        /// If KakaoTalk's cache is stale, open the app (macOS) and check again after 20s
+        #[arg(long)]
+        nudge: bool,
+    },
+    /// Best-effort account-restriction warning signs seen recently
+    Health {
+        /// Fetch account settings once now to look for restriction fields
+        #[arg(long)]
+        check: bool,
+    },
+    /// JSON Schema of a command's --json output, or the list of published schemas
+    Schema {
+        /// Command (e.g. chats) or schema id (openkakao.chats.v2)
+        command: Option<String>,
+        /// Print every published schema, keyed by id
+        #[arg(long, conflicts_with = "command")]
+        all: bool,
+    },
+    /// Print the version; --features lists what this binary was built with
+    Version {
+        /// Also list optional capabilities (extract, archive, realtime, keyring, tui, holidays) and whether each is compiled in
+        #[arg(long)]
+        features: bool,
+    },
+    #[command(hide = true)]
+    /// Panic on purpose, to check the crash report
+    DebugPanic {
+        /// Text to put in the panic message
+        text: Option<String>,
     },
 }
 
-fn require_loco_write(config: &config::OpenKakaoConfig) -> Result<()> {
-    if !config.safety.allow_loco_write {
-        anyhow::bail!(
-            "LOCO write operations are disabled by default to protect your account.\n\
-             These operations (send, delete, edit, react) use the LOCO protocol which\n\
-             may result in account suspension or deletion by Kakao.\n\n\
-             To enable, add to ~/.config/openkakao/config.toml:\n\n\
-             [safety]\n\
-             allow_loco_write = true\n\n\
-             Consider using local-read / local-chats / local-search for safe read-only access."
-        );
-    }
-    Ok(())
+#[derive(Subcommand, Debug)]
+enum CredentialsCommand {
+    /// List saved profiles with their user id and whether the token verifies
+    Profiles {
+        #[command(subcommand)]
+        action: Option<ProfilesCommand>,
+    },
+    /// Verify every saved credential file and remove dead or duplicate ones.
+    ///
+    /// Checks each profile in ~/.config/openkakao/credentials/ (and a leftover
+    /// credentials.json).
+    /// Expired tokens are deleted; when several files hold the same token only
+    /// the newest is kept. Kept files are reset to 0600. Dry run unless --apply.
+    Prune {
+        #[arg(
+            long,
+            help = "Delete the files and fix permissions instead of only reporting"
+        )]
+        apply: bool,
+    },
+    /// Move the active profile's credentials file into the keychain.
+    ///
+    /// The file is overwritten and deleted once the keychain entry reads back.
+    Migrate,
+    /// Check every saved credential for values that cannot work as written.
+    ///
+    /// Reports token shape (printable ASCII, length, the '-' before the
+    /// device UUID), user id, A header and user agent problems with the
+    /// field and a fix. Exits 1 when only warnings were found, 2 on errors.
+    Lint,
+    /// Show the active profile's credentials and where they are stored
+    Show {
+        #[arg(long, help = "Print the tokens instead of masking them")]
+        reveal: bool,
+    },
 }
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
-    let config = load_config()?;
-    set_auth_policy(AuthPolicy::from_config(&config.auth));
-    let json = cli.json;
-    let unattended = cli.unattended || config.mode.unattended;
-    let allow_non_interactive_send =
-        cli.allow_non_interactive_send || config.send.allow_non_interactive;
-    let allow_watch_side_effects = cli.allow_watch_side_effects || config.watch.allow_side_effects;
-    let min_unattended_send_interval_secs = config
-        .safety
-        .min_unattended_send_interval_secs
-        .unwrap_or(10);
-    let min_hook_interval_secs = config.safety.min_hook_interval_secs.unwrap_or(2);
-    let min_webhook_interval_secs = config.safety.min_webhook_interval_secs.unwrap_or(2);
-    let hook_timeout_secs = config.safety.hook_timeout_secs.unwrap_or(20);
-    let webhook_timeout_secs = config.safety.webhook_timeout_secs.unwrap_or(10);
-    let no_prefix = if cli.no_prefix {
-        true
-    } else {
-        matches!(config.send.default_prefix, Some(false))
-    };
+#[derive(Subcommand, Debug)]
+enum ProfilesCommand {
+    /// Delete a profile: its saved credentials and its whole state tree
+    /// (archive, media and response caches, state files, outbox)
+    #[command(visible_alias = "remove")]
+    Rm {
+        name: String,
+        #[arg(long, short = 'y', help = "Skip confirmation prompt")]
+        yes: bool,
+    },
+}
 
-    // Respect NO_COLOR env var (https://no-color.org/) and --no-color flag
-    if cli.no_color || std::env::var("NO_COLOR").is_ok() || json {
-        NO_COLOR.store(true, Ordering::Relaxed);
+#[derive(Subcommand, Debug)]
+enum DaemonCommand {
+    /// Run the schedule (same as `daemon` alone)
+    Run,
+    /// Last run, next run and last error of each task
+    Status,
+}
+
+#[derive(Subcommand, Debug)]
+enum LogsCommand {
+    /// Print the last lines of the current log
+    Tail {
+        #[arg(short = 'n', long, default_value_t = 20)]
+        lines: usize,
+        /// Keep printing new lines, across rotations, until interrupted
+        #[arg(short = 'f', long)]
+        follow: bool,
+        /// Only entries at this level or above
+        #[arg(long, value_enum, default_value = "info")]
+        level: logs::Level,
+    },
+    /// Where the current and rotated logs are
+    Path,
+    /// Rotate the current log now
+    Rotate,
+}
+
+#[derive(Subcommand, Debug)]
+enum MemoCommand {
+    /// Read recent memo chat messages (same pipeline as `read`)
+    Read {
+        #[arg(short = 'n', long, default_value_t = 30)]
+        count: usize,
+        #[arg(long, help = "Resume from cursor (logId from previous run)")]
+        cursor: Option<i64>,
+        #[arg(
+            long,
+            value_name = "WHEN",
+            help = "Filter messages from this time on (YYYY-MM-DD, 2d, or a day name like 어제 or 지난주; see read --help)"
+        )]
+        since: Option<String>,
+        #[arg(long, help = "Fetch all available messages")]
+        all: bool,
+        #[arg(long, help = "Force REST read path instead of LOCO")]
+        rest: bool,
+        #[arg(long, help = "Append an openkakao:// message link to each message")]
+        links: bool,
+    },
+    /// Search memo chat messages (same pipeline as `search`)
+    Search {
+        query: String,
+        #[arg(long, help = "Append an openkakao:// message link to each match")]
+        links: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum CacheCommand {
+    /// Message and member counts of the local cache (same as cache-stats)
+    Stats,
+    /// Delete cached messages, member names and title history, of one chat or all
+    Clear {
+        #[arg(conflicts_with_all = ["media", "scrap"])]
+        chat_id: Option<i64>,
+        #[arg(short = 'y', long, help = "Clear without asking for confirmation")]
+        yes: bool,
+        /// Empty the downloaded media cache instead
+        #[arg(long, conflicts_with = "scrap")]
+        media: bool,
+        /// Empty the scrap link preview cache instead
+        #[arg(long)]
+        scrap: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum IgnoreCommand {
+    /// Hide messages from a user id or name (a chat member's with --chat, else a friend's)
+    Add {
+        who: String,
+        /// Only in this chat; a chat entry overrides a global one
+        #[arg(long = "chat")]
+        chat_id: Option<i64>,
+        /// Show this author in --chat even though a global entry hides them
+        #[arg(long, requires = "chat_id")]
+        allow: bool,
+    },
+    /// List ignore entries
+    #[command(visible_alias = "ls")]
+    List,
+    /// Remove the entry for a user id or recorded name (the global one unless --chat)
+    #[command(visible_alias = "remove")]
+    Rm {
+        who: String,
+        #[arg(long = "chat")]
+        chat_id: Option<i64>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ChatsCommand {
+    /// Everything known about one room: members, settings, open link and the last message
+    Info { chat_id: i64 },
+}
+
+#[derive(Subcommand, Debug)]
+enum BoardCommand {
+    /// Print one board post in full with its comments
+    Show {
+        post_id: String,
+        #[arg(long = "chat", help = "Chat the post belongs to, to show author names")]
+        chat_id: Option<i64>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ExportCommand {
+    /// Recompute the hashes in a directory's export manifests and check
+    /// their chain and log_id coverage
+    Verify { dir: std::path::PathBuf },
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    /// Show the active scope (global or workspace) and every file path in use
+    Path,
+}
+
+#[derive(Subcommand, Debug)]
+enum OutboxCommand {
+    /// Show queued messages with their age, attempts and last error
+    List,
+    /// Send queued messages in order, keeping failures for another flush
+    Flush {
+        /// Also send messages older than send.outbox_ttl_secs
+        #[arg(long)]
+        force: bool,
+        #[arg(long, short = 'y', help = "Skip confirmation prompt")]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ArchiveCommand {
+    /// Location and size of the archive, and of the media cache next to it
+    Info,
+    /// Titles a chat has had, as recorded by cache syncs
+    Titles { chat_id: i64 },
+    /// Cached messages of a chat, newest last
+    Read {
+        chat_id: i64,
+        #[arg(short = 'n', long, default_value_t = 50)]
+        count: usize,
+        #[arg(
+            long,
+            conflicts_with = "show_deleted",
+            help = "Show messages deleted for everyone as a placeholder (the default)"
+        )]
+        respect_deletions: bool,
+        #[arg(
+            long,
+            help = "Show the archived content of deleted messages, tagged [deleted]"
+        )]
+        show_deleted: bool,
+        /// Read a chat that is no longer in the chat list (left or deleted)
+        #[arg(long)]
+        include_departed: bool,
+    },
+    /// Messages, characters and first/last message per author from the archive,
+    /// the same whether the chat's content was stored or hashed
+    Stats { chat_id: i64 },
+    /// Jumps in the archived log ids that look like missing messages rather
+    /// than a quiet chat
+    Gaps { chat_id: i64 },
+    /// Fetch the messages around each gap again and merge what was missing
+    Repair {
+        chat_id: i64,
+        /// Pages of history to fetch per gap
+        #[arg(long, default_value_t = commands::analytics::REPAIR_PAGES)]
+        pages: usize,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum OpenchatCommand {
+    /// Show a link's room name, description, members and whether it needs a
+    /// passcode, without joining
+    Info {
+        /// https://open.kakao.com/o/<code> or a numeric link id
+        link: model::OpenLinkRef,
+    },
+    /// Join the room behind a link and print its chat id
+    Join {
+        /// https://open.kakao.com/o/<code> or a numeric link id
+        link: model::OpenLinkRef,
+        /// Passcode of a locked room
+        #[arg(long)]
+        passcode: Option<String>,
+        /// Nickname in the room (default: your profile)
+        #[arg(long)]
+        nickname: Option<String>,
+        #[arg(long, short = 'y', help = "Skip confirmation prompt")]
+        yes: bool,
+    },
+    /// Leave an open chat
+    Leave {
+        chat_id: i64,
+        #[arg(long, short = 'y', help = "Skip confirmation prompt")]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum FriendCommand {
+    /// Find a user by Kakao ID, including people who are not friends yet.
+    ///
+    /// The target may be able to see that they were searched for, so this is
+    /// refused under --read-only.
+    FindById { kakao_id: String },
+}
+
+impl Commands {
+    /// Jobs that can run for a long time on one set of credentials, checked
+    /// for a stale KakaoTalk cache before they start.
+    fn is_long_running(&self) -> bool {
+        matches!(
+            self,
+            Commands::Export { action: None, .. }
+                | Commands::Takeout { .. }
+                | Commands::Watch { .. }
+                | Commands::Download { .. }
+                | Commands::Cache { action: None, .. }
+        )
     }
 
-    match cli.command {
-        Commands::Auth => commands::auth::cmd_auth(json)?,
-        Commands::AuthStatus => commands::auth::cmd_auth_status(json)?,
-        Commands::Login { save } => commands::auth::cmd_login(save)?,
-        Commands::Me => commands::rest::cmd_me(json)?,
+    /// The optional part of the binary the command needs, checked before
+    /// dispatch so a build without it fails with a "not compiled in" error.
+    fn required_capability(&self) -> Option<&'static capabilities::Capability> {
+        match self {
+            Commands::Tui => Some(&capabilities::TUI),
+            Commands::Credentials {
+                action: CredentialsCommand::Migrate,
+            }
+            | Commands::Login {
+                store: Some(credentials::CredentialStore::Keychain),
+                ..
+            } => Some(&capabilities::KEYRING),
+            Commands::Login { .. }
+            | Commands::LocalChats { .. }
+            | Commands::LocalRead { .. }
+            | Commands::LocalSearch { .. }
+            | Commands::LocalSchema => Some(&capabilities::EXTRACT),
+            Commands::Cache { .. }
+            | Commands::CacheSearch { .. }
+            | Commands::CacheReindex { .. }
+            | Commands::CacheStats
+            | Commands::Archive { .. } => Some(&capabilities::ARCHIVE),
+            _ => None,
+        }
+    }
+
+    /// What the command would do that `--read-only` forbids, if anything.
+    fn read_only_violation(&self) -> Option<&'static str> {
+        match self {
+            Commands::Send { queue: true, .. } | Commands::SendFile { queue: true, .. } => None,
+            Commands::Send { .. }
+            | Commands::Dm { send: Some(_), .. }
+            | Commands::SendMe { .. }
+            | Commands::Report {
+                send_to: Some(_), ..
+            }
+            | Commands::SendPhoto { .. }
+            | Commands::SendFile { .. }
+            | Commands::Outbox {
+                action: OutboxCommand::Flush { .. },
+            } => Some("sending messages"),
+            Commands::Delete { .. } | Commands::Edit { .. } | Commands::React { .. } => {
+                Some("changing messages")
+            }
+            Commands::MarkRead { .. } => Some("sending read receipts"),
+            Commands::Favorite { .. } => rest::side_effect_of("add_favorite"),
+            Commands::Unfavorite { .. } => rest::side_effect_of("remove_favorite"),
+            Commands::Hide { .. } => rest::side_effect_of("hide_friend"),
+            Commands::Me {
+                set_status: None,
+                set_nickname: None,
+            } => None,
+            Commands::Me { .. } => rest::side_effect_of("update_profile"),
+            Commands::Unhide { .. } => rest::side_effect_of("unhide_friend"),
+            Commands::Friend {
+                action: FriendCommand::FindById { .. },
+            } => rest::side_effect_of("find_by_kakao_id"),
+            Commands::Openchat {
+                action: OpenchatCommand::Join { .. },
+            } => rest::side_effect_of("join_open_link"),
+            Commands::Openchat {
+                action: OpenchatCommand::Leave { .. },
+            } => rest::side_effect_of("leave_open_chat"),
+            _ => None,
+        }
+    }
+
+    /// What the command will ask to confirm before doing anything, when
+    /// neither `-y/--yes` nor a dry run answers for it. `outbox flush` only
+    /// asks when something is deliverable, so it checks for itself.
+    fn pending_confirmation(&self) -> Option<&'static str> {
+        match self {
+            Commands::Send {
+                yes: false,
+                dry_run: false,
+                queue: false,
+                ..
+            }
+            | Commands::SendMe {
+                yes: false,
+                dry_run: false,
+                ..
+            }
+            | Commands::Dm {
+                send: Some(_),
+                yes: false,
+                ..
+            }
+            | Commands::Report {
+                send_to: Some(_),
+                yes: false,
+                ..
+            } => Some("the send"),
+            Commands::SendPhoto {
+                yes: false,
+                dry_run: false,
+                ..
+            }
+            | Commands::SendFile {
+                yes: false,
+                dry_run: false,
+                queue: false,
+                ..
+            } => Some("the file send"),
+            Commands::Delete {
+                yes: false,
+                dry_run: false,
+                ..
+            } => Some("the delete"),
+            Commands::Edit {
+                yes: false,
+                dry_run: false,
+                ..
+            } => Some("the edit"),
+            Commands::Favorite { yes: false, .. } => Some("adding the favorite"),
+            Commands::Unfavorite { yes: false, .. } => Some("removing the favorite"),
+            Commands::Hide { yes: false, .. } => Some("hiding the friend"),
+            Commands::Openchat {
+                action: OpenchatCommand::Join { yes: false, .. },
+            } => Some("joining the open chat"),
+            Commands::Openchat {
+                action: OpenchatCommand::Leave { yes: false, .. },
+            } => Some("leaving the open chat"),
+            Commands::Unhide {
+                yes: false,
+                dry_run: false,
+                ..
+            } => Some("the unhide"),
+            Commands::Cache {
+                action:
+                    Some(CacheCommand::Clear {
+                        yes: false,
+                        media: false,
+                        scrap: false,
+                        ..
+                    }),
+                ..
+            } => Some("clearing the message cache"),
+            Commands::Init { yes: false } => Some("the setup wizard"),
+            Commands::MarkRead {
+                all: true,
+                yes: false,
+                ..
+            } => Some("marking every unread chat as read"),
+            _ => None,
+        }
+    }
+}
+
+/// Column count `read` wraps bodies at: `--wrap N` (0 turns it off), else
+/// the terminal width, and never with `--no-wrap` or when piped.
+fn read_wrap(wrap: Option<usize>, no_wrap: bool) -> Option<usize> {
+    if no_wrap {
+        return None;
+    }
+    wrap.or_else(wrap::terminal_width).filter(|&cols| cols > 0)
+}
+
+fn require_loco_write(config: &config::OpenKakaoConfig) -> Result<()> {
+    if !config.safety.allow_loco_write {
+        anyhow::bail!(
+            "LOCO write operations are disabled by default to protect your account.\n\
+             These operations (send, delete, edit, react) use the LOCO protocol which\n\
+             may result in account suspension or deletion by Kakao.\n\n\
+             To enable, add to ~/.config/openkakao/config.toml:\n\n\
+             [safety]\n\
+             allow_loco_write = true\n\n\
+             Consider using local-read / local-chats / local-search for safe read-only access."
+        );
+    }
+    Ok(())
+}
+
+/// `--memo` standing in for a chat id: the arguments without the flag and
+/// where the MemoChat id goes (the first positional, right after the
+/// subcommand).
+#[derive(Debug)]
+struct MemoShorthand {
+    args: Vec<OsString>,
+    insert_at: usize,
+}
+
+impl MemoShorthand {
+    fn find(args: &[OsString]) -> Result<Option<Self>> {
+        let Some(flag_at) = args
+            .iter()
+            .take_while(|arg| *arg != "--")
+            .position(|arg| arg == "--memo")
+        else {
+            return Ok(None);
+        };
+        let command = Cli::command();
+        let mut idx = 1;
+        let subcommand = loop {
+            // No subcommand at all: let clap report the usage error.
+            let Some(arg) = args.get(idx) else {
+                return Ok(None);
+            };
+            let text = arg.to_string_lossy();
+            if let Some(long) = text.strip_prefix("--") {
+                let takes_value = !long.contains('=')
+                    && command
+                        .get_arguments()
+                        .any(|a| a.get_long() == Some(long) && a.get_action().takes_values());
+                idx += if takes_value { 2 } else { 1 };
+                continue;
+            }
+            match command.find_subcommand(&*text) {
+                Some(subcommand) => break subcommand,
+                None => return Ok(None),
+            }
+        };
+        // `chats --memo` is a flag of its own.
+        if subcommand
+            .get_arguments()
+            .any(|a| a.get_long() == Some("memo"))
+        {
+            return Ok(None);
+        }
+        if subcommand
+            .get_positionals()
+            .next()
+            .is_none_or(|a| a.get_id() != "chat_id")
+        {
+            anyhow::bail!(
+                "--memo stands in for a chat id, and `{}` does not take one",
+                subcommand.get_name()
+            );
+        }
+        let mut rest = args.to_vec();
+        rest.remove(flag_at);
+        let insert_at = if flag_at < idx { idx } else { idx + 1 };
+        Ok(Some(Self {
+            args: rest,
+            insert_at,
+        }))
+    }
+
+    fn with_chat_id(&self, chat_id: i64) -> Vec<OsString> {
+        let mut args = self.args.clone();
+        args.insert(self.insert_at, chat_id.to_string().into());
+        args
+    }
+}
+
+/// `args` with a `[alias]` from config.toml in the subcommand position
+/// replaced by its command line.
+fn expand_aliases(args: Vec<OsString>) -> Result<Vec<OsString>> {
+    let aliases = config::load_aliases();
+    if aliases.is_empty() {
+        return Ok(args);
+    }
+    let command = Cli::command();
+    let expanded = alias::expand(
+        &args,
+        &aliases,
+        |name| name == "help" || command.find_subcommand(name).is_some(),
+        |long| {
+            command
+                .get_arguments()
+                .any(|a| a.get_long() == Some(long) && a.get_action().takes_values())
+        },
+    )?;
+    Ok(expanded.unwrap_or(args))
+}
+
+/// `archive.read` for `openkakao-rs archive read 1`, naming the `--json`
+/// schema of the run.
+fn subcommand_path(args: &[OsString]) -> String {
+    let Ok(mut matches) = Cli::command().try_get_matches_from(args) else {
+        return String::new();
+    };
+    let mut path = Vec::new();
+    while let Some((name, sub)) = matches.remove_subcommand() {
+        path.push(name);
+        matches = sub;
+    }
+    path.join(".")
+}
+
+fn main() -> Result<()> {
+    crash::install();
+    let args = expand_aliases(std::env::args_os().collect())?;
+    let memo = MemoShorthand::find(&args)?;
+    // With --memo, parse once with a placeholder id so the scope flags are
+    // known before the real id is looked up.
+    let cli = match &memo {
+        Some(memo) => Cli::parse_from(memo.with_chat_id(0)),
+        None => Cli::parse_from(&args),
+    };
+    schema::set_command(subcommand_path(&match &memo {
+        Some(memo) => memo.with_chat_id(0),
+        None => args.clone(),
+    }));
+    if cli.print_expansion {
+        eprintln!("{}", alias::quote_words(&args));
+    }
+    util::VERBOSE.store(cli.verbose > 0, Ordering::Relaxed);
+    init_tracing(cli.verbose);
+    if tracing::enabled!(tracing::Level::DEBUG) {
+        let words: Vec<String> = args
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        tracing::debug!(
+            "openkakao-rs {} {}",
+            env!("CARGO_PKG_VERSION"),
+            capture::scrub_args(&words).join(" ")
+        );
+    }
+    prompt::NO_INPUT.store(cli.no_input, Ordering::Relaxed);
+    paths::init_scope(cli.local, cli.local_credentials)?;
+    if let Some(profile) = &cli.profile {
+        credentials::select_profile(profile)?;
+    }
+    match credentials::migrate_legacy_credentials() {
+        Ok(Some(path)) => eprintln!(
+            "[auth] Moved credentials.json to the default profile: {}",
+            path.display()
+        ),
+        Ok(None) => {}
+        Err(err) => warnings::warn(
+            warnings::WarningCode::StateSaveFailed,
+            format!("Could not migrate credentials.json to the default profile: {err:#}"),
+        ),
+    }
+    match paths::migrate_state_to_default_profile() {
+        Ok(moved) if !moved.is_empty() => eprintln!(
+            "[state] Moved {} state file(s) to the default profile: {}",
+            moved.len(),
+            paths::profile_dir_of(credentials::DEFAULT_PROFILE)?.display()
+        ),
+        Ok(_) => {}
+        Err(err) => warnings::warn(
+            warnings::WarningCode::StateSaveFailed,
+            format!("Could not move state files to the default profile: {err:#}"),
+        ),
+    }
+    let cli = match &memo {
+        Some(memo) => Cli::parse_from(memo.with_chat_id(commands::memo::memo_chat_id()?)),
+        None => cli,
+    };
+    let completion_promise = cli.completion_promise;
+    let fail_on_warnings = cli.fail_on_warnings;
+    let capture_report = cli.capture_report.clone();
+    if capture_report.is_some() {
+        capture::start(cli.capture_bodies);
+    }
+    // `export --append` extends the file in place; the --out redirect would
+    // replace it with the new messages alone.
+    let mut cli = cli;
+    if let Commands::Export {
+        append: true,
+        output,
+        ..
+    } = &mut cli.command
+    {
+        if output.is_none() {
+            *output = cli.out.take().map(|path| path.display().to_string());
+        }
+    }
+    let redirect = match &cli.out {
+        Some(path) => output::StdoutRedirect::open(path)?,
+        None => None,
+    };
+    let mut result = run(cli);
+    warnings::flush();
+    if result.is_ok() && fail_on_warnings && warnings::count() > 0 {
+        result = Err(anyhow::anyhow!(
+            "{} warning(s) raised with --fail-on-warnings",
+            warnings::count()
+        ));
+    }
+    if let Err(err) = &result {
+        print_verbose_error(err);
+    }
+    if let Some(path) = capture_report {
+        write_capture_report(&path, result.as_ref().err())?;
+    }
+    if let Err(err) = &result {
+        if prompt::is_input_required(err) {
+            eprintln!("Error: {err:?}");
+            std::process::exit(prompt::INPUT_REQUIRED_EXIT_CODE);
+        }
+    }
+    result?;
+    // Dropping the redirect on error above discards the partial output.
+    if let Some(redirect) = redirect {
+        redirect.commit()?;
+    }
+    if completion_promise {
+        println!("[DONE]");
+    }
+    let code = util::EXIT_CODE.load(Ordering::Relaxed);
+    if code != 0 {
+        std::process::exit(code);
+    }
+    Ok(())
+}
+
+fn write_capture_report(path: &std::path::Path, err: Option<&anyhow::Error>) -> Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    let config = load_config()
+        .ok()
+        .and_then(|c| serde_json::to_value(c).ok())
+        .unwrap_or_default();
+    let errors = err.map(capture::error_chain).unwrap_or_default();
+    let Some(report) = capture::finish(&args, &config, &errors) else {
+        return Ok(());
+    };
+    output::write_atomic(path, |w| {
+        serde_json::to_writer_pretty(&mut *w, &report)?;
+        Ok(w.write_all(b"\n")?)
+    })?;
+    eprintln!("[capture] Report written to {}", path.display());
+    Ok(())
+}
+
+/// Send this crate's `tracing` events to stderr: debug with `-v` (or
+/// `OPENKAKAO_RS_DEBUG`), trace with `-vv`. Nothing is installed otherwise.
+fn init_tracing(verbosity: u8) {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let level = match verbosity {
+        0 if std::env::var_os("OPENKAKAO_RS_DEBUG").is_some() => tracing::Level::DEBUG,
+        0 => return,
+        1 => tracing::Level::DEBUG,
+        _ => tracing::Level::TRACE,
+    };
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_max_level(level)
+        .finish()
+        .with(tracing_subscriber::filter::Targets::new().with_target("openkakao_rs", level));
+    let _ = tracing::subscriber::set_global_default(subscriber);
+}
+
+/// Under `--verbose`, show how a failing status was classified and, for
+/// LOCO errors, the raw response body.
+fn print_verbose_error(err: &anyhow::Error) {
+    if !util::verbose() {
+        return;
+    }
+    let Some(err) = err.downcast_ref::<error::OpenKakaoError>() else {
+        return;
+    };
+    if let Some(kind) = err.kakao_status() {
+        eprintln!("[verbose] status {} classified as {:?}", kind.code(), kind);
+    }
+    if let error::OpenKakaoError::LocoStatus {
+        command,
+        status,
+        body: Some(body),
+    } = err
+    {
+        let raw = bson::Bson::Document(body.clone()).into_relaxed_extjson();
+        util::print_verbose_payload(command, *status, &raw);
+    }
+}
+
+/// The chat and query of `search QUERY [--chat ID]`, or of the older
+/// `search CHAT_ID QUERY`.
+fn search_target(chat: Option<i64>, terms: &[String]) -> Result<(Option<i64>, &str)> {
+    match terms {
+        [query] => Ok((chat, query)),
+        [chat_id, query] => {
+            if chat.is_some() {
+                anyhow::bail!("Give the chat either as --chat or before the query, not both");
+            }
+            let chat_id = chat_id.parse().map_err(|_| {
+                anyhow::anyhow!("'{chat_id}' is not a chat id; quote a query that has spaces")
+            })?;
+            Ok((Some(chat_id), query))
+        }
+        _ => anyhow::bail!("search takes a query"),
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let config = load_config()?;
+    set_auth_policy(AuthPolicy::from_config(&config.auth));
+    rest::set_observer(Box::new(util::CliRestObserver));
+    bandwidth::global().set_max(cli.max_bytes);
+    if let Some(max_body_bytes) = config.http.max_body_bytes {
+        rest::set_max_body_bytes(max_body_bytes);
+    }
+    if let Some(base_urls) = &config.http.base_urls {
+        rest::set_base_urls(base_urls)?;
+    }
+    let http_options = rest::HttpOptions {
+        timeout: cli.timeout,
+        proxy: cli.proxy.clone(),
+        base_url: cli.base_url.clone(),
+        pilsner_url: cli.pilsner_url.clone(),
+        ..Default::default()
+    }
+    .or(&rest::HttpOptions::from_env()?);
+    http_options.check()?;
+    rest::set_http_options(http_options);
+    if !cli.fresh {
+        match config.http.response_cache.as_deref().unwrap_or("off") {
+            "off" => {}
+            "memory" => response_cache::install(ResponseCache::in_memory()),
+            "disk" => response_cache::install(ResponseCache::on_disk(
+                paths::profile_dir()?.join("response-cache"),
+            )),
+            other => anyhow::bail!(
+                "Unknown [http] response_cache '{}' in config (expected off, memory or disk)",
+                other
+            ),
+        }
+    }
+    let default_limits = names::NameCacheLimits::default();
+    util::set_name_cache_limits(names::NameCacheLimits {
+        max_chats: config
+            .names
+            .max_cached_chats
+            .unwrap_or(default_limits.max_chats)
+            .max(1),
+        ttl: config
+            .names
+            .ttl_secs
+            .map_or(default_limits.ttl, std::time::Duration::from_secs),
+    });
+    urlnorm::set_strip_tracking(cli.strip_tracking);
+    if let Some(fields) = cli.fields.as_deref() {
+        output::set_json_fields(output::parse_fields(fields));
+    }
+    if let Some(dir) = cli.collect_unknown.clone() {
+        strict::set_schema_policy(strict::SchemaPolicy::Collect(dir));
+    } else if cli.strict {
+        strict::set_schema_policy(strict::SchemaPolicy::Strict);
+    }
+    if let Some(capability) = cli.command.required_capability() {
+        capabilities::require(capability)?;
+    }
+    if cli.read_only {
+        if let Some(action) = cli.command.read_only_violation() {
+            anyhow::bail!("--read-only forbids {action}.");
+        }
+        rest::set_read_only(true);
+    }
+    // Fail before any network work instead of at the prompt.
+    if let Some(action) = cli.command.pending_confirmation() {
+        prompt::ensure_confirmable(action)?;
+    }
+    let json = cli.json || config.ui.output.as_deref() == Some("json");
+    let unattended = cli.unattended || config.mode.unattended;
+    let allow_non_interactive_send =
+        cli.allow_non_interactive_send || config.send.allow_non_interactive;
+    let allow_watch_side_effects = cli.allow_watch_side_effects || config.watch.allow_side_effects;
+    let min_unattended_send_interval_secs = config
+        .safety
+        .min_unattended_send_interval_secs
+        .unwrap_or(10);
+    let outbox_ttl_secs = config
+        .send
+        .outbox_ttl_secs
+        .unwrap_or(outbox::DEFAULT_TTL_SECS);
+    let min_hook_interval_secs = config.safety.min_hook_interval_secs.unwrap_or(2);
+    let min_webhook_interval_secs = config.safety.min_webhook_interval_secs.unwrap_or(2);
+    let hook_timeout_secs = config.safety.hook_timeout_secs.unwrap_or(20);
+    let webhook_timeout_secs = config.safety.webhook_timeout_secs.unwrap_or(10);
+    let no_prefix = if cli.no_prefix {
+        true
+    } else {
+        matches!(config.send.default_prefix, Some(false))
+    };
+
+    // Respect NO_COLOR env var (https://no-color.org/) and --no-color flag
+    if cli.no_color || std::env::var("NO_COLOR").is_ok() || json {
+        NO_COLOR.store(true, Ordering::Relaxed);
+    }
+    if let Some(retries) = cli.retries {
+        util::set_retry_policy(retry::RetryPolicy::default().with_retries(retries));
+    }
+    let theme_choice = match cli.theme.as_deref().or(config.ui.theme.as_deref()) {
+        Some(name) => theme::ThemeChoice::from_str(name)?,
+        None => theme::ThemeChoice::Auto,
+    };
+    theme::set_theme(theme_choice.resolve(std::env::var("COLORFGBG").ok().as_deref()));
+    if let Some(lang) = cli.lang.as_deref().or(config.ui.lang.as_deref()) {
+        humanize::set_lang(lang.parse()?);
+    }
+    if cli.show_bidi {
+        SHOW_BIDI.store(true, Ordering::Relaxed);
+    }
+    if cli.no_cache {
+        util::NO_CACHE.store(true, Ordering::Relaxed);
+    }
+    if cli.no_verify {
+        auth_flow::set_verify_tokens(false);
+    }
+    if cli.save_backfill {
+        auth_flow::set_save_backfill(true);
+    }
+    let account_preference = if cli.prefer_saved {
+        Some(account_conflict::AccountChoice::Saved)
+    } else if cli.prefer_extracted {
+        Some(account_conflict::AccountChoice::Extracted)
+    } else {
+        None
+    };
+    account_conflict::set_preference(account_preference, cli.overwrite_saved);
+    if cli.command.is_long_running() {
+        util::warn_if_cache_stale();
+    }
+
+    match cli.command {
+        Commands::Auth => commands::auth::cmd_auth(json)?,
+        Commands::AuthStatus => commands::auth::cmd_auth_status(json)?,
+        Commands::Login { save, store } => commands::auth::cmd_login(save, store)?,
+        Commands::Init { yes } => commands::init::cmd_init(yes, &config)?,
+        Commands::Credentials {
+            action: CredentialsCommand::Prune { apply },
+        } => commands::auth::cmd_credentials_prune(apply, json)?,
+        Commands::Credentials {
+            action: CredentialsCommand::Profiles { action: None },
+        } => commands::auth::cmd_credentials_profiles(json)?,
+        Commands::Credentials {
+            action:
+                CredentialsCommand::Profiles {
+                    action: Some(ProfilesCommand::Rm { name, yes }),
+                },
+        } => commands::auth::cmd_credentials_profiles_rm(&name, yes, json)?,
+        Commands::Credentials {
+            action: CredentialsCommand::Migrate,
+        } => commands::auth::cmd_credentials_migrate(json)?,
+        Commands::Credentials {
+            action: CredentialsCommand::Lint,
+        } => commands::auth::cmd_credentials_lint(json)?,
+        Commands::Credentials {
+            action: CredentialsCommand::Show { reveal },
+        } => commands::auth::cmd_credentials_show(reveal, json)?,
+        Commands::Config {
+            action: ConfigCommand::Path,
+        } => commands::config::cmd_config_path(json)?,
+        Commands::Me {
+            set_status: None,
+            set_nickname: None,
+        } => commands::rest::cmd_me(json)?,
+        Commands::Me {
+            set_status,
+            set_nickname,
+        } => commands::rest::cmd_me_update(set_nickname.as_deref(), set_status.as_deref(), json)?,
+        Commands::Whoami { offline } => commands::rest::cmd_whoami(offline, json)?,
+        Commands::Friends {
+            favorites,
+            hidden,
+            hidden_only,
+            search,
+            fuzzy,
+            download_avatars: Some(dir),
+            jobs,
+            ..
+        } => commands::rest::cmd_friends_avatars(
+            favorites,
+            model::HiddenFilter::from_flags(hidden, hidden_only),
+            NameQuery::from_args(search, fuzzy),
+            &dir,
+            usize::from(jobs),
+            json,
+        )?,
+        Commands::Friends {
+            favorites,
+            hidden,
+            hidden_only,
+            search,
+            fuzzy,
+            raw_phones,
+            export: Some(format),
+            ..
+        } => commands::rest::cmd_friends_export(
+            favorites,
+            model::HiddenFilter::from_flags(hidden, hidden_only),
+            NameQuery::from_args(search, fuzzy),
+            format,
+            raw_phones,
+        )?,
         Commands::Friends {
             favorites,
             hidden,
+            hidden_only,
             search,
+            fuzzy,
+            local,
+            chat_id,
+            user_id,
+            detail,
+            raw_phones,
+            ..
+        } => commands::rest::cmd_friends(
+            favorites,
+            model::HiddenFilter::from_flags(hidden, hidden_only),
+            NameQuery::from_args(search, fuzzy),
             local,
             chat_id,
             user_id,
-        } => commands::rest::cmd_friends(favorites, hidden, search, local, chat_id, user_id, json)?,
+            detail,
+            raw_phones,
+            json,
+        )?,
+        Commands::Friend { action } => match action {
+            FriendCommand::FindById { kakao_id } => {
+                commands::rest::cmd_friend_find_by_id(&kakao_id, json)?
+            }
+        },
+        Commands::Chat { chat_id } => commands::chats::cmd_chat(chat_id, json)?,
+        Commands::Dm {
+            friend,
+            read,
+            count,
+            send,
+            yes,
+        } => {
+            let target = commands::chats::resolve_dm(&friend)?;
+            if read {
+                commands::read::cmd_read(
+                    target.chat_id,
+                    ReadCommandOptions {
+                        count,
+                        cursor: None,
+                        since: None,
+                        until: None,
+                        all: false,
+                        delay_ms: 100,
+                        force: false,
+                        rest: false,
+                        json,
+                        links: false,
+                        receipts: false,
+                        context: None,
+                        from_archive: false,
+                        continue_paging: false,
+                        restart: false,
+                        wrap: read_wrap(None, false),
+                        threads: false,
+                        spam: spam::SpamDisplay::Off,
+                        collapse_ignored: false,
+                        images: commands::read::ImageDisplay::Count,
+                    },
+                )?
+            } else if let Some(message) = send {
+                require_loco_write(&config)?;
+                commands::send::cmd_send(commands::send::SendOptions {
+                    chat_id: target.chat_id,
+                    message: format_outgoing_message(&message, no_prefix),
+                    split: false,
+                    force: false,
+                    skip_confirm: yes,
+                    unattended,
+                    allow_non_interactive: allow_non_interactive_send,
+                    min_interval_secs: min_unattended_send_interval_secs,
+                    json,
+                })?
+            } else {
+                commands::chats::cmd_dm(&target, json)?
+            }
+        }
+        Commands::Notice { chat_id, history } => {
+            commands::chats::cmd_notice(chat_id, history, json)?
+        }
+        Commands::Board {
+            action: Some(BoardCommand::Show { post_id, chat_id }),
+            ..
+        } => commands::chats::cmd_board_show(&post_id, chat_id, json)?,
+        Commands::Board {
+            chat_id: Some(chat_id),
+            count,
+            cursor,
+            ..
+        } => commands::chats::cmd_board(chat_id, count, cursor.as_deref(), json)?,
+        Commands::Board { .. } => unreachable!("clap requires a chat id"),
         Commands::Chats {
+            action: Some(ChatsCommand::Info { chat_id }),
+            ..
+        } => commands::chats::cmd_chat(chat_id, json)?,
+        Commands::Chats { memo: true, .. } => commands::memo::cmd_chats_memo(json)?,
+        Commands::Chats {
+            action: None,
             show_all,
             unread,
             search,
+            fuzzy,
+            chat_type,
+            stale,
+            sort,
+            rest,
+            memo: false,
+            since_state,
+            read_status,
+        } => commands::chats::cmd_chats(
+            show_all,
+            unread,
+            NameQuery::from_args(search, fuzzy),
             chat_type,
+            stale.as_deref(),
+            sort,
+            rest,
+            since_state,
+            read_status,
+            json,
+        )?,
+        Commands::Memo {
+            action:
+                MemoCommand::Read {
+                    count,
+                    cursor,
+                    since,
+                    all,
+                    rest,
+                    links,
+                },
+        } => commands::memo::cmd_memo_read(ReadCommandOptions {
+            count,
+            cursor: cursor.map(cursor::MessageCursor::new),
+            since,
+            until: None,
+            all,
+            delay_ms: 100,
+            force: false,
             rest,
-        } => commands::chats::cmd_chats(show_all, unread, search, chat_type, rest, json)?,
+            json,
+            links,
+            receipts: false,
+            context: None,
+            from_archive: false,
+            continue_paging: false,
+            restart: false,
+            wrap: read_wrap(None, false),
+            threads: false,
+            spam: spam::SpamDisplay::Off,
+            collapse_ignored: false,
+            images: commands::read::ImageDisplay::Count,
+        })?,
+        Commands::Memo {
+            action: MemoCommand::Search { query, links },
+        } => commands::memo::cmd_memo_search(&query, json, links)?,
         Commands::Read {
             chat_id,
             count,
             before,
             cursor,
             since,
+            until,
             all,
             delay_ms,
             force,
             rest,
+            links,
+            receipts,
+            context,
+            from_archive,
+            wrap,
+            no_wrap,
+            continue_paging,
+            restart,
+            threads,
+            flag_spam,
+            hide_spam,
+            collapse_ignored,
+            images,
         } => commands::read::cmd_read(
             chat_id,
             ReadCommandOptions {
                 count,
-                cursor: cursor.or(before),
+                cursor: cursor.or(before).map(cursor::MessageCursor::new),
                 since,
+                until,
                 all,
                 delay_ms,
                 force,
                 rest,
                 json,
+                links,
+                receipts,
+                context,
+                from_archive,
+                continue_paging: continue_paging || restart,
+                restart,
+                wrap: read_wrap(wrap, no_wrap),
+                threads,
+                spam: spam::SpamDisplay::from_flags(flag_spam, hide_spam),
+                collapse_ignored,
+                images,
             },
         )?,
+        Commands::Ignore { action } => match action {
+            IgnoreCommand::Add {
+                who,
+                chat_id,
+                allow,
+            } => commands::ignore::cmd_ignore_add(&who, chat_id, allow, json)?,
+            IgnoreCommand::List => commands::ignore::cmd_ignore_list(json)?,
+            IgnoreCommand::Rm { who, chat_id } => {
+                commands::ignore::cmd_ignore_rm(&who, chat_id, json)?
+            }
+        },
+        Commands::Members {
+            chat_id,
+            rest,
+            export_csv,
+            activity: true,
+            inactive_days,
+            ..
+        } => commands::members::cmd_members_activity(
+            chat_id,
+            rest,
+            inactive_days.map(i64::from),
+            export_csv.as_deref(),
+            json,
+        )?,
         Commands::Members {
             chat_id,
             rest,
             full,
-        } => commands::members::cmd_members(chat_id, rest, full, json)?,
+            limit,
+            export_csv,
+            merge,
+            activity: false,
+            ..
+        } => match (export_csv, merge) {
+            (Some(path), _) => commands::members::cmd_members_roster(
+                chat_id,
+                rest,
+                commands::members::RosterTarget::Fresh(path),
+                json,
+            )?,
+            (None, Some(path)) => commands::members::cmd_members_roster(
+                chat_id,
+                rest,
+                commands::members::RosterTarget::Merge(path),
+                json,
+            )?,
+            (None, None) => commands::members::cmd_members(chat_id, rest, full, limit, json)?,
+        },
         Commands::Chatinfo { chat_id } => commands::rest::cmd_chatinfo(chat_id, json)?,
-        Commands::Settings => commands::rest::cmd_settings(json)?,
-        Commands::Scrap { url } => commands::rest::cmd_scrap(&url, json)?,
+        Commands::Openchat { action } => match action {
+            OpenchatCommand::Info { link } => commands::openchat::cmd_openchat_info(&link, json)?,
+            OpenchatCommand::Join {
+                link,
+                passcode,
+                nickname,
+                yes,
+            } => commands::openchat::cmd_openchat_join(
+                &link,
+                passcode.as_deref(),
+                nickname.as_deref(),
+                yes,
+                json,
+            )?,
+            OpenchatCommand::Leave { chat_id, yes } => {
+                commands::openchat::cmd_openchat_leave(chat_id, yes, json)?
+            }
+        },
+        Commands::Settings { track, changes } => {
+            commands::rest::cmd_settings(track, changes, json)?
+        }
+        Commands::Scrap {
+            url,
+            refresh,
+            cached_only,
+            ttl,
+        } => commands::rest::cmd_scrap(
+            &url,
+            commands::rest::ScrapOptions {
+                no_cache: cli.no_cache,
+                refresh,
+                cached_only,
+                ttl_secs: ttl,
+            },
+            json,
+        )?,
         Commands::Profile {
             user_id,
+            name,
             chat_id,
             local,
-        } => commands::profile::cmd_profile(user_id, chat_id, local, json)?,
-        Commands::Favorite { user_id } => commands::rest::cmd_favorite(user_id, json)?,
-        Commands::Unfavorite { user_id } => commands::rest::cmd_unfavorite(user_id, json)?,
-        Commands::Hide { user_id } => commands::rest::cmd_hide(user_id, json)?,
-        Commands::Unhide { user_id } => commands::rest::cmd_unhide(user_id, json)?,
+        } => commands::profile::cmd_profile(user_id, name.as_deref(), chat_id, local, json)?,
+        Commands::Favorite { user_id, yes } => commands::rest::cmd_favorite(user_id, yes, json)?,
+        Commands::Unfavorite { user_id, yes } => {
+            commands::rest::cmd_unfavorite(user_id, yes, json)?
+        }
+        Commands::Hide { user_id, yes } => commands::rest::cmd_hide(user_id, yes, json)?,
+        Commands::Unhide {
+            user_id,
+            all_hidden,
+            yes,
+            dry_run,
+        } => match user_id {
+            Some(user_id) if !all_hidden => commands::rest::cmd_unhide(user_id, yes, json)?,
+            _ => commands::rest::cmd_unhide_all(yes, dry_run, json)?,
+        },
         Commands::Profiles => commands::rest::cmd_profiles(json)?,
         Commands::Keywords => commands::rest::cmd_keywords(json)?,
+        Commands::Endpoints => commands::rest::cmd_endpoints()?,
         Commands::Unread => commands::rest::cmd_unread(json)?,
+        #[cfg(feature = "tui")]
+        Commands::Tui => commands::tui::cmd_tui(commands::tui::TuiOptions {
+            allow_mark_read: !cli.read_only,
+        })?,
+        #[cfg(not(feature = "tui"))]
+        Commands::Tui => unreachable!("required_capability rejects tui in this build"),
+        Commands::Summary { exact } => commands::rest::cmd_summary(exact, json)?,
+        Commands::ExportDb {
+            path,
+            chats_members,
+        } => commands::rest::cmd_export_db(&path, &chats_members, json)?,
+        Commands::Takeout {
+            dir,
+            include,
+            exclude,
+            max_pages,
+        } => commands::takeout::cmd_takeout(&dir, &include, &exclude, max_pages, json)?,
         Commands::Export {
-            chat_id,
+            action: Some(ExportCommand::Verify { dir }),
+            ..
+        } => commands::rest::cmd_export_verify(&dir, json)?,
+        Commands::Export {
+            action: None,
+            chat_id: Some(chat_id),
             format,
             output,
-        } => commands::rest::cmd_export(chat_id, &format, output.as_deref(), json)?,
-        Commands::Search { chat_id, query } => commands::rest::cmd_search(chat_id, &query, json)?,
-        Commands::Stats {
+            anonymize,
+            no_media,
+            embed_media,
+            redact,
+            key_file,
+            max_pages,
+            append,
+            annotate_titles,
+            threads,
+            no_manifest,
+        } => commands::rest::cmd_export(
             chat_id,
-            limit,
+            commands::rest::ExportOptions {
+                format: &format,
+                output: output.as_deref(),
+                privacy: export::PrivacyFilter::new(anonymize, no_media, &redact)?,
+                redactions: redact.len(),
+                embed_media,
+                key_file: key_file.as_deref(),
+                max_pages,
+                append,
+                annotate_titles,
+                threads,
+                manifest: !no_manifest,
+            },
+            json,
+        )?,
+        Commands::Export { .. } => unreachable!("clap requires a chat id"),
+        Commands::Search {
+            terms,
+            chat_id,
+            regex,
+            case_sensitive,
+            max_pages,
+            links,
+            normalize,
+        } => {
+            let (chat_id, query) = search_target(chat_id, &terms)?;
+            commands::rest::cmd_search(
+                commands::rest::SearchOptions {
+                    query,
+                    chat_id,
+                    regex,
+                    case_sensitive,
+                    normalize,
+                    max_pages,
+                    links,
+                },
+                json,
+            )?
+        }
+        Commands::Stats {
+            chat_id,
+            media: true,
+            pages,
+            ..
+        } => commands::analytics::cmd_stats_media(chat_id, pages, json)?,
+        Commands::Stats {
+            chat_id,
+            limit,
             since,
-        } => commands::analytics::cmd_stats(chat_id, limit, since.as_deref(), json)?,
+            compare,
+            ..
+        } => match compare.as_deref() {
+            Some([range1, range2]) => {
+                commands::analytics::cmd_stats_compare(chat_id, range1, range2, json)?
+            }
+            _ => commands::analytics::cmd_stats(chat_id, limit, since.as_deref(), json)?,
+        },
+        Commands::Report {
+            chat_id,
+            period,
+            format,
+            send_to,
+            yes,
+        } => {
+            let format = report::ReportFormat::from_str(&format)?;
+            let report = commands::analytics::build_report(
+                chat_id,
+                report::ReportPeriod::from_str(&period)?,
+            )?;
+            let text = report::render_report(&report, format);
+            match send_to {
+                Some(target) => {
+                    require_loco_write(&config)?;
+                    commands::send::cmd_send(commands::send::SendOptions {
+                        chat_id: target,
+                        message: text,
+                        split: true,
+                        force: false,
+                        skip_confirm: yes,
+                        unattended,
+                        allow_non_interactive: allow_non_interactive_send,
+                        min_interval_secs: min_unattended_send_interval_secs,
+                        json,
+                    })?
+                }
+                None if json => util::output_json(&report)?,
+                None => print!("{}", text),
+            }
+        }
         Commands::Completions { shell } => {
             generate(
                 shell,
@@ -646,6 +2935,19 @@ fn main() -> Result<()> {
             );
         }
         Commands::Renew => commands::auth::cmd_renew(json)?,
+        Commands::Logs { action } => match action {
+            LogsCommand::Tail {
+                lines,
+                follow,
+                level,
+            } => commands::logs::cmd_logs_tail(lines, follow, level, json)?,
+            LogsCommand::Path => commands::logs::cmd_logs_path(json)?,
+            LogsCommand::Rotate => commands::logs::cmd_logs_rotate(json)?,
+        },
+        Commands::Daemon { action } => match action {
+            None | Some(DaemonCommand::Run) => commands::daemon::cmd_daemon_run(json)?,
+            Some(DaemonCommand::Status) => commands::daemon::cmd_daemon_status(json)?,
+        },
         Commands::Relogin {
             fresh_xvc,
             password,
@@ -658,30 +2960,54 @@ fn main() -> Result<()> {
         Commands::Send {
             chat_id,
             message,
+            split,
             force,
             yes,
             dry_run,
+            queue,
         } => {
             let msg = format_outgoing_message(&message, no_prefix);
-            if dry_run {
-                eprintln!(
-                    "[dry-run] Would send to chat {}: \"{}\"",
+            if queue {
+                commands::outbox::cmd_outbox_queue(commands::outbox::QueueOptions {
                     chat_id,
-                    util::truncate(&msg, 80)
-                );
+                    message: Some(msg),
+                    file: None,
+                    split,
+                    force,
+                    json,
+                })?
+            } else if dry_run {
+                let requests: Vec<serde_json::Value> = commands::send::outgoing_parts(&msg, split)?
+                    .iter()
+                    .map(|part| {
+                        bson::Bson::Document(commands::send::write_body(chat_id, part))
+                            .into_relaxed_extjson()
+                    })
+                    .collect();
                 if json {
                     util::output_json(&serde_json::json!({
                         "dry_run": true,
                         "action": "send",
                         "chat_id": chat_id,
                         "message": msg,
+                        "requests": requests,
                     }))?;
+                } else {
+                    eprintln!(
+                        "[dry-run] Would send {} WRITE request(s) to chat {}:",
+                        requests.len(),
+                        chat_id
+                    );
+                    for request in &requests {
+                        println!("{}", serde_json::to_string_pretty(request)?);
+                    }
                 }
             } else {
                 require_loco_write(&config)?;
                 commands::send::cmd_send(commands::send::SendOptions {
                     chat_id,
                     message: msg,
+                    split,
                     force,
                     skip_confirm: yes,
                     unattended,
@@ -696,11 +3022,7 @@ fn main() -> Result<()> {
             yes,
             dry_run,
         } => {
-            let reader = local_db::LocalDbReader::open()
-                .context("Failed to open local DB to find memo chat")?;
-            let memo_id = reader
-                .find_memo_chat_id()?
-                .context("Could not find memo chat (나와의 채팅) in local database")?;
+            let memo_id = commands::memo::memo_chat_id()?;
             let msg = format_outgoing_message(&message, no_prefix);
             if dry_run {
                 eprintln!(
@@ -721,6 +3043,7 @@ fn main() -> Result<()> {
                 commands::send::cmd_send(commands::send::SendOptions {
                     chat_id: memo_id,
                     message: msg,
+                    split: false,
                     force: false,
                     skip_confirm: yes,
                     unattended,
@@ -764,8 +3087,18 @@ fn main() -> Result<()> {
             force,
             yes,
             dry_run,
+            queue,
         } => {
-            if dry_run {
+            if queue {
+                commands::outbox::cmd_outbox_queue(commands::outbox::QueueOptions {
+                    chat_id,
+                    message: None,
+                    file: Some(file),
+                    split: false,
+                    force,
+                    json,
+                })?
+            } else if dry_run {
                 eprintln!("[dry-run] Would send file '{}' to chat {}", file, chat_id);
                 if json {
                     util::output_json(&serde_json::json!({
@@ -786,6 +3119,23 @@ fn main() -> Result<()> {
                 })?
             }
         }
+        Commands::Outbox {
+            action: OutboxCommand::List,
+        } => commands::outbox::cmd_outbox_list(outbox_ttl_secs, json)?,
+        Commands::Outbox {
+            action: OutboxCommand::Flush { force, yes },
+        } => {
+            require_loco_write(&config)?;
+            commands::outbox::cmd_outbox_flush(commands::outbox::FlushOptions {
+                force,
+                ttl_secs: outbox_ttl_secs,
+                skip_confirm: yes,
+                unattended,
+                allow_non_interactive: allow_non_interactive_send,
+                min_interval_secs: min_unattended_send_interval_secs,
+                json,
+            })?
+        }
         Commands::Delete {
             chat_id,
             log_id,
@@ -817,13 +3167,19 @@ fn main() -> Result<()> {
                 })?
             }
         }
-        Commands::MarkRead { chat_id, log_id } => {
-            commands::send::cmd_mark_read(commands::send::MarkReadOptions {
-                chat_id,
-                log_id,
-                json,
-            })?
-        }
+        Commands::MarkRead {
+            chat_id,
+            log_id,
+            up_to,
+            all,
+            yes,
+        } => commands::send::cmd_mark_read(commands::send::MarkReadOptions {
+            chat_id,
+            log_id: log_id.or(up_to),
+            all,
+            yes,
+            json,
+        })?,
         Commands::React {
             chat_id,
             log_id,
@@ -886,8 +3242,34 @@ fn main() -> Result<()> {
                 })?
             }
         }
+        Commands::Watch {
+            poll_chat_id,
+            all_chats,
+            interval,
+            exec,
+            collapse_ignored,
+            ..
+        } if poll_chat_id.is_some() || all_chats => {
+            commands::tail::cmd_watch_poll(commands::tail::TailOptions {
+                chat_id: poll_chat_id,
+                interval: std::time::Duration::from_secs(interval),
+                exec,
+                exec_timeout: std::time::Duration::from_secs(hook_timeout_secs),
+                unattended,
+                allow_side_effects: allow_watch_side_effects,
+                json,
+                collapse_ignored,
+            })?
+        }
+        Commands::Watch { exec: Some(_), .. } => {
+            anyhow::bail!(
+                "--exec needs CHAT_ID or --all-chats; LOCO watch runs hooks with --hook-cmd"
+            )
+        }
         Commands::Watch {
             chat_id,
+            include,
+            exclude,
             raw,
             read_receipt,
             max_reconnect,
@@ -904,12 +3286,19 @@ fn main() -> Result<()> {
             hook_keyword,
             hook_type,
             hook_fail_fast,
+            notify,
             resume,
             capture,
+            bell,
+            title,
+            dedup_window,
+            collapse_ignored,
+            ..
         } => commands::watch::cmd_watch(WatchOptions {
             unattended,
             allow_side_effects: allow_watch_side_effects,
             filter_chat_id: chat_id,
+            scope: chat_rules::ChatRules::parse(&include, &exclude)?,
             raw,
             read_receipt,
             max_reconnect: config.watch.default_max_reconnect.unwrap_or(max_reconnect),
@@ -931,25 +3320,174 @@ fn main() -> Result<()> {
             webhook_timeout_secs,
             allow_insecure_webhooks: config.safety.allow_insecure_webhooks,
             webhook_format: WebhookFormat::from_str_opt(webhook_format.as_deref())?,
+            notify_backends: notify
+                .iter()
+                .map(|backend| notify::NotifyBackend::parse(backend))
+                .collect::<Result<_>>()?,
+            notify_config: config.notify.clone(),
             resume,
             json,
             capture,
+            bell,
+            title,
+            dedup_window,
+            collapse_ignored,
         })?,
         Commands::Download {
             chat_id,
             log_id,
+            log_id_flag,
             output_dir,
-        } => commands::download::cmd_download(chat_id, log_id, output_dir.as_deref(), json)?,
-        Commands::Cache { chat_id, limit } => commands::analytics::cmd_cache(chat_id, limit, json)?,
+            types,
+            since,
+            concurrency,
+            cache,
+        } => match log_id.or(log_id_flag) {
+            Some(log_id) => commands::download::cmd_download(
+                chat_id,
+                log_id,
+                output_dir.as_deref(),
+                cache,
+                json,
+            )?,
+            None if cache => {
+                anyhow::bail!("--cache applies to a single message: pass its LOG_ID")
+            }
+            None => {
+                let opts = commands::download::BulkDownloadOptions {
+                    kinds: types
+                        .iter()
+                        .map(|t| attachments::AttachmentKind::from_str(t))
+                        .collect::<Result<_>>()?,
+                    out_dir: output_dir.unwrap_or_else(|| "downloads".to_string()),
+                    since: util::parse_since_date(since.as_deref())?,
+                    concurrency: usize::from(concurrency),
+                };
+                commands::download::cmd_download_chat(chat_id, &opts, json)?
+            }
+        },
+        Commands::Files {
+            chat_id,
+            pages,
+            attachment_type,
+            grep,
+            normalize,
+        } => {
+            let filter = attachments::AttachmentFilter {
+                kind: attachment_type
+                    .as_deref()
+                    .map(attachments::AttachmentKind::from_str)
+                    .transpose()?,
+                grep,
+                normalize,
+            };
+            commands::download::cmd_files(chat_id, pages, &filter, json)?
+        }
+        Commands::Links {
+            chat_id,
+            pages,
+            enrich,
+            format,
+        } => {
+            let options = commands::download::LinksOptions {
+                pages,
+                enrich,
+                no_cache: cli.no_cache,
+                format: links::LinksFormat::from_str(&format)?,
+            };
+            commands::download::cmd_links(chat_id, &options, json)?
+        }
+        Commands::Cache {
+            action: Some(CacheCommand::Stats),
+            ..
+        } => commands::analytics::cmd_cache_stats(json)?,
+        Commands::Cache {
+            action: Some(CacheCommand::Clear { media: true, .. }),
+            ..
+        } => commands::analytics::cmd_cache_clear(false, json)?,
+        Commands::Cache {
+            action: Some(CacheCommand::Clear { scrap: true, .. }),
+            ..
+        } => commands::analytics::cmd_cache_clear(true, json)?,
+        Commands::Cache {
+            action: Some(CacheCommand::Clear { chat_id, yes, .. }),
+            ..
+        } => commands::analytics::cmd_cache_clear_messages(chat_id, yes, json)?,
+        Commands::Cache {
+            chat_id: Some(chat_id),
+            limit,
+            no_content,
+            convert,
+            ..
+        } => commands::analytics::cmd_cache(chat_id, limit, no_content, convert, json)?,
+        Commands::Cache {
+            limit,
+            explain,
+            no_content,
+            convert,
+            ..
+        } => commands::analytics::cmd_cache_all(limit, no_content, convert, explain, json)?,
         Commands::CacheSearch {
             query,
             chat_id,
             count,
-        } => commands::analytics::cmd_cache_search(&query, chat_id, count, json)?,
+            links,
+            normalize,
+            include_departed,
+        } => commands::analytics::cmd_cache_search(
+            &query,
+            chat_id,
+            count,
+            json,
+            links,
+            normalize,
+            include_departed,
+        )?,
+        Commands::CacheReindex { normalize } => {
+            commands::analytics::cmd_cache_reindex(normalize, json)?
+        }
         Commands::CacheStats => commands::analytics::cmd_cache_stats(json)?,
+        Commands::Archive {
+            action: ArchiveCommand::Info,
+        } => commands::analytics::cmd_archive_info(json)?,
+        Commands::Archive {
+            action: ArchiveCommand::Titles { chat_id },
+        } => commands::analytics::cmd_archive_titles(chat_id, json)?,
+        Commands::Archive {
+            action: ArchiveCommand::Stats { chat_id },
+        } => commands::analytics::cmd_archive_stats(chat_id, json)?,
+        Commands::Archive {
+            action: ArchiveCommand::Gaps { chat_id },
+        } => commands::analytics::cmd_archive_gaps(chat_id, json)?,
+        Commands::Archive {
+            action: ArchiveCommand::Repair { chat_id, pages },
+        } => commands::analytics::cmd_archive_repair(chat_id, pages, json)?,
+        Commands::Archive {
+            action:
+                ArchiveCommand::Read {
+                    chat_id,
+                    count,
+                    respect_deletions: _,
+                    show_deleted,
+                    include_departed,
+                },
+        } => {
+            let view = if show_deleted {
+                message_db::DeletionView::Show
+            } else {
+                message_db::DeletionView::Respect
+            };
+            commands::analytics::cmd_archive_read(chat_id, count, view, include_departed, json)?
+        }
+        Commands::Link {
+            chat_id,
+            log_id,
+            open,
+        } => commands::analytics::cmd_link(chat_id, log_id, open, json)?,
+        Commands::Open { reference } => commands::analytics::cmd_open(&reference, json)?,
         Commands::LocoChats { show_all } => {
             eprintln!("[deprecated] 'loco-chats' is now hidden. Prefer 'chats' (LOCO by default).");
-            commands::chats::cmd_loco_chats(show_all, false, None, None, json)?
+            commands::chats::cmd_loco_chats(show_all, false, None, None, None, None, false, json)?
         }
         Commands::LocoRead {
             chat_id,
@@ -965,13 +3503,25 @@ fn main() -> Result<()> {
                 chat_id,
                 &commands::read::ReadCommandOptions {
                     count: count as usize,
-                    cursor,
+                    cursor: cursor.map(cursor::MessageCursor::new),
                     since,
+                    until: None,
                     all,
                     delay_ms,
                     force,
                     rest: false,
                     json,
+                    links: false,
+                    receipts: false,
+                    context: None,
+                    from_archive: false,
+                    continue_paging: false,
+                    restart: false,
+                    wrap: None,
+                    threads: false,
+                    spam: spam::SpamDisplay::Off,
+                    collapse_ignored: false,
+                    images: commands::read::ImageDisplay::Count,
                 },
             )?
         }
@@ -979,7 +3529,7 @@ fn main() -> Result<()> {
             eprintln!(
                 "[deprecated] 'loco-members' is now hidden. Prefer 'members' (LOCO by default)."
             );
-            commands::members::cmd_loco_members(chat_id, false, json)?
+            commands::members::cmd_loco_members(chat_id, false, None, json)?
         }
         Commands::LocoChatinfo { chat_id } => {
             eprintln!("[deprecated] 'loco-chatinfo' is now hidden. Prefer 'chatinfo'.");
@@ -1015,7 +3565,7 @@ fn main() -> Result<()> {
             let reader = local_db::LocalDbReader::open()?;
             let chats = reader.list_chats(limit)?;
             if json {
-                println!("{}", serde_json::to_string_pretty(&chats)?);
+                crate::util::output_json(&chats)?;
             } else {
                 if chats.is_empty() {
                     println!("No chats found in local database.");
@@ -1062,7 +3612,7 @@ fn main() -> Result<()> {
             let mut messages = reader.read_messages(chat_id, count, since_ts)?;
             messages.reverse(); // chronological order
             if json {
-                println!("{}", serde_json::to_string_pretty(&messages)?);
+                crate::util::output_json(&messages)?;
             } else {
                 if messages.is_empty() {
                     println!("No messages found in local database for chat {}.", chat_id);
@@ -1078,11 +3628,13 @@ fn main() -> Result<()> {
                         } else {
                             m.sender_name.clone()
                         };
+                        let sender = util::display_safe(&sender);
+                        let message = util::display_safe(&m.message);
                         let type_tag = util::message_type_label(m.message_type);
                         if m.message_type == 1 {
-                            println!("  [{}] {}: {}", ts, sender, m.message);
+                            println!("  [{}] {}: {}", ts, sender, message);
                         } else {
-                            println!("  [{}] {}: [{}] {}", ts, sender, type_tag, m.message);
+                            println!("  [{}] {}: [{}] {}", ts, sender, type_tag, message);
                         }
                     }
                     println!(
@@ -1096,7 +3648,7 @@ fn main() -> Result<()> {
             let reader = local_db::LocalDbReader::open()?;
             let results = reader.search_messages(&query, count)?;
             if json {
-                println!("{}", serde_json::to_string_pretty(&results)?);
+                crate::util::output_json(&results)?;
             } else {
                 if results.is_empty() {
                     println!("No messages matching '{}' in local database.", query);
@@ -1116,8 +3668,8 @@ fn main() -> Result<()> {
                             "  [{}] chat={} {}: {}",
                             ts,
                             m.chat_id,
-                            sender,
-                            util::truncate(&m.message, 80)
+                            util::display_safe(&sender),
+                            util::display_safe(&util::truncate(&m.message, 80))
                         );
                     }
                     println!("\n{} results (local DB, no server contact)", results.len());
@@ -1132,7 +3684,7 @@ fn main() -> Result<()> {
                     .iter()
                     .map(|(name, sql)| serde_json::json!({"name": name, "sql": sql}))
                     .collect();
-                println!("{}", serde_json::to_string_pretty(&items)?);
+                crate::util::output_json(&items)?;
             } else {
                 for (name, sql) in &tables {
                     println!("-- {}", name);
@@ -1141,11 +3693,48 @@ fn main() -> Result<()> {
             }
         }
         Commands::WatchCache { interval } => commands::auth::cmd_watch_cache(interval)?,
-        Commands::Doctor { loco } => commands::doctor::cmd_doctor(json, loco, &config)?,
+        Commands::Doctor { loco, nudge } => {
+            commands::doctor::cmd_doctor(json, loco, nudge, &config)?
+        }
+        Commands::Health { check } => commands::health::cmd_health(check, json)?,
+        Commands::Schema { command, all } => {
+            commands::schema::cmd_schema(command.as_deref(), all, json)?
+        }
+        Commands::Version { features } => commands::config::cmd_version(features, json)?,
+        Commands::DebugPanic { text } => {
+            panic!("debug-panic requested with `{}`", text.unwrap_or_default())
+        }
     }
 
-    if cli.completion_promise {
-        println!("[DONE]");
+    if cli.stats {
+        let stats = util::name_resolver_stats().unwrap_or_default();
+        eprintln!(
+            "[stats] names: friends fetched={} hits={}, members fetched={} hits={} evicted={}, expired={}",
+            stats.friend_fetches,
+            stats.friend_hits,
+            stats.member_fetches,
+            stats.member_hits,
+            stats.member_evictions,
+            stats.expired
+        );
+        if let Some(summary) = rest::endpoint_summary() {
+            eprintln!("[stats] endpoints: {}", summary);
+        }
+        eprintln!("[stats] rate limit: {}", rest::rate_limit_summary());
+        let meter = bandwidth::global();
+        let cap = meter
+            .max()
+            .map(|max| format!(" of {} allowed", util::format_bytes(max)))
+            .unwrap_or_default();
+        eprintln!(
+            "[stats] bytes received: {} ({}{cap})",
+            meter.received(),
+            util::format_bytes(meter.received())
+        );
+        if let Some(cache) = response_cache::global() {
+            let (hits, misses) = cache.stats();
+            eprintln!("[stats] response cache: hits={hits} misses={misses}");
+        }
     }
 
     Ok(())
@@ -1214,6 +3803,32 @@ mod tests {
         }
     }
 
+    #[test]
+    fn send_accepts_split_and_multiline_text() {
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "send",
+            "123",
+            "line one\nline two 🎉",
+            "--split",
+            "--dry-run",
+        ])
+        .expect("send should accept --split");
+
+        match cli.command {
+            Commands::Send {
+                message,
+                split,
+                dry_run,
+                ..
+            } => {
+                assert_eq!(message, "line one\nline two 🎉");
+                assert!(split && dry_run);
+            }
+            other => panic!("expected send command, got {other:?}"),
+        }
+    }
+
     #[test]
     fn unattended_flag_is_available_globally() {
         let cli = Cli::try_parse_from([
@@ -1230,6 +3845,38 @@ mod tests {
         assert!(cli.allow_watch_side_effects);
     }
 
+    #[test]
+    fn stats_flag_does_not_clash_with_stats_command() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "--stats", "stats", "123"])
+            .expect("global --stats should parse alongside the stats command");
+
+        assert!(cli.stats);
+        assert!(matches!(cli.command, Commands::Stats { chat_id: 123, .. }));
+    }
+
+    #[test]
+    fn stats_media_flags_parse() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "stats", "123", "--media", "--pages", "0"])
+            .expect("stats should accept --media");
+        match cli.command {
+            Commands::Stats {
+                chat_id,
+                media,
+                pages,
+                ..
+            } => {
+                assert_eq!(chat_id, 123);
+                assert!(media);
+                assert_eq!(pages, 0);
+            }
+            other => panic!("expected stats command, got {other:?}"),
+        }
+        assert!(Cli::try_parse_from(["openkakao-rs", "stats", "1", "--pages", "2"]).is_err());
+        assert!(
+            Cli::try_parse_from(["openkakao-rs", "stats", "1", "--media", "--limit", "5"]).is_err()
+        );
+    }
+
     #[test]
     fn permission_gate_rejects_missing_opt_in() {
         let err = require_permission(false, "non-interactive send", "set the flags").unwrap_err();
@@ -1388,335 +4035,1274 @@ mod tests {
     }
 
     #[test]
-    fn members_accepts_rest_flag() {
-        let cli = Cli::try_parse_from(["openkakao-rs", "members", "123", "--rest", "--full"])
-            .expect("members should accept --rest and --full");
+    fn chats_accepts_since_state() {
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "chats",
+            "--unread",
+            "--type",
+            "dm",
+            "--since-state",
+        ])
+        .expect("chats should accept --since-state");
 
         match cli.command {
-            Commands::Members {
-                chat_id,
-                rest,
-                full,
+            Commands::Chats {
+                since_state,
+                unread,
+                chat_type,
+                ..
             } => {
-                assert_eq!(chat_id, 123);
-                assert!(rest);
-                assert!(full);
+                assert!(since_state);
+                assert!(unread);
+                assert_eq!(chat_type.as_deref(), Some("dm"));
             }
-            other => panic!("expected members command, got {other:?}"),
+            other => panic!("expected chats command, got {other:?}"),
         }
+        assert!(Cli::try_parse_from(["openkakao-rs", "chats", "--memo", "--since-state"]).is_err());
     }
 
     #[test]
-    fn profile_accepts_chat_id_flag() {
-        let cli = Cli::try_parse_from([
-            "openkakao-rs",
-            "profile",
-            "100000002",
-            "--chat-id",
-            "900000000000001",
-        ])
-        .expect("profile should accept --chat-id");
-
-        match cli.command {
-            Commands::Profile {
-                user_id,
-                chat_id,
-                local,
-            } => {
-                assert_eq!(user_id, 100000002);
-                assert_eq!(chat_id, Some(900000000000001));
-                assert!(!local);
+    fn short_forms_parse_with_global_flags_on_either_side() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "--json", "ls", "-u", "--no-color"])
+            .expect("ls is chats");
+        assert!(cli.json && cli.no_color);
+        assert!(matches!(cli.command, Commands::Chats { unread: true, .. }));
+        let cli = Cli::try_parse_from(["openkakao-rs", "f", "-s", "kim"]).expect("f is friends");
+        assert!(matches!(
+            cli.command,
+            Commands::Friends {
+                search: Some(_),
+                ..
             }
-            other => panic!("expected profile command, got {other:?}"),
-        }
+        ));
+        let cli = Cli::try_parse_from(["openkakao-rs", "r", "42", "-n", "5"]).expect("r is read");
+        assert!(matches!(
+            cli.command,
+            Commands::Read {
+                chat_id: 42,
+                count: 5,
+                ..
+            }
+        ));
+        let cli = Cli::try_parse_from(["openkakao-rs", "m", "42"]).expect("m is members");
+        assert!(matches!(cli.command, Commands::Members { chat_id: 42, .. }));
+        assert_eq!(
+            subcommand_path(&["openkakao-rs".into(), "ls".into()]),
+            "chats"
+        );
     }
 
     #[test]
-    fn friends_accepts_local_flag() {
+    fn departed_chats_are_opt_in_for_cache_reads() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "archive", "read", "42"])
+            .expect("archive read should parse");
+        assert!(matches!(
+            cli.command,
+            Commands::Archive {
+                action: ArchiveCommand::Read {
+                    include_departed: false,
+                    ..
+                },
+            }
+        ));
         let cli = Cli::try_parse_from([
             "openkakao-rs",
-            "friends",
-            "--local",
-            "-s",
-            "Alice",
-            "--chat-id",
-            "900000000000003",
-            "--user-id",
-            "100000003",
+            "archive",
+            "read",
+            "42",
+            "--include-departed",
         ])
-        .expect("friends should accept --local");
+        .expect("archive read --include-departed should parse");
+        assert!(matches!(
+            cli.command,
+            Commands::Archive {
+                action: ArchiveCommand::Read {
+                    include_departed: true,
+                    ..
+                },
+            }
+        ));
+        let cli =
+            Cli::try_parse_from(["openkakao-rs", "cache-search", "회의", "--include-departed"])
+                .expect("cache-search --include-departed should parse");
+        assert!(matches!(
+            cli.command,
+            Commands::CacheSearch {
+                include_departed: true,
+                ..
+            }
+        ));
+    }
 
-        match cli.command {
-            Commands::Friends {
-                local,
-                search,
-                favorites,
-                hidden,
-                chat_id,
-                user_id,
-            } => {
-                assert!(local);
-                assert_eq!(search.as_deref(), Some("Alice"));
-                assert!(!favorites);
-                assert!(!hidden);
-                assert_eq!(chat_id, Some(900000000000003));
-                assert_eq!(user_id, Some(100000003));
+    #[test]
+    fn title_history_commands_parse() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "chats", "--changed"])
+            .expect("--changed is an alias of --since-state");
+        assert!(matches!(
+            cli.command,
+            Commands::Chats {
+                since_state: true,
+                ..
             }
-            other => panic!("expected friends command, got {other:?}"),
+        ));
+        let cli = Cli::try_parse_from(["openkakao-rs", "archive", "titles", "42"])
+            .expect("archive titles should parse");
+        match cli.command {
+            Commands::Archive {
+                action: ArchiveCommand::Titles { chat_id },
+            } => assert_eq!(chat_id, 42),
+            other => panic!("expected archive titles, got {other:?}"),
         }
+        let cli = Cli::try_parse_from(["openkakao-rs", "cache", "42", "--no-content", "--convert"])
+            .expect("cache should accept content flags");
+        assert!(matches!(
+            cli.command,
+            Commands::Cache {
+                chat_id: Some(42),
+                no_content: true,
+                convert: true,
+                ..
+            }
+        ));
+        assert!(matches!(
+            Cli::try_parse_from(["openkakao-rs", "archive", "stats", "42"])
+                .expect("archive stats should parse")
+                .command,
+            Commands::Archive {
+                action: ArchiveCommand::Stats { chat_id: 42 },
+            }
+        ));
+        assert!(matches!(
+            Cli::try_parse_from(["openkakao-rs", "archive", "gaps", "42"])
+                .expect("archive gaps should parse")
+                .command,
+            Commands::Archive {
+                action: ArchiveCommand::Gaps { chat_id: 42 },
+            }
+        ));
+        assert!(matches!(
+            Cli::try_parse_from(["openkakao-rs", "archive", "repair", "42"])
+                .expect("archive repair should parse")
+                .command,
+            Commands::Archive {
+                action: ArchiveCommand::Repair {
+                    chat_id: 42,
+                    pages: commands::analytics::REPAIR_PAGES,
+                },
+            }
+        ));
+        let cli = Cli::try_parse_from(["openkakao-rs", "export", "42", "--annotate-titles"])
+            .expect("export --annotate-titles should parse");
+        assert!(matches!(
+            cli.command,
+            Commands::Export {
+                annotate_titles: true,
+                ..
+            }
+        ));
     }
 
     #[test]
-    fn profile_accepts_local_flag() {
-        let cli = Cli::try_parse_from(["openkakao-rs", "profile", "100000002", "--local"])
-            .expect("profile should accept --local");
+    fn chats_accepts_stale_with_type() {
+        let cli =
+            Cli::try_parse_from(["openkakao-rs", "chats", "--stale", "90d", "--type", "open"])
+                .expect("chats should accept --stale");
 
         match cli.command {
-            Commands::Profile {
-                user_id,
-                chat_id,
-                local,
+            Commands::Chats {
+                stale, chat_type, ..
             } => {
-                assert_eq!(user_id, 100000002);
-                assert_eq!(chat_id, None);
-                assert!(local);
+                assert_eq!(stale.as_deref(), Some("90d"));
+                assert_eq!(chat_type.as_deref(), Some("open"));
             }
-            other => panic!("expected profile command, got {other:?}"),
+            other => panic!("expected chats command, got {other:?}"),
         }
     }
 
     #[test]
-    fn chatinfo_command_is_available() {
-        let cli = Cli::try_parse_from(["openkakao-rs", "chatinfo", "123"])
-            .expect("chatinfo should be available");
+    fn feature_gated_commands_check_their_capability() {
+        let gated: &[(&[&str], &capabilities::Capability)] = &[
+            (&["tui"], &capabilities::TUI),
+            (&["credentials", "migrate"], &capabilities::KEYRING),
+            (
+                &["login", "--save", "--store", "keychain"],
+                &capabilities::KEYRING,
+            ),
+            (&["login"], &capabilities::EXTRACT),
+            (&["local-chats"], &capabilities::EXTRACT),
+            (&["local-schema"], &capabilities::EXTRACT),
+            (&["cache", "1"], &capabilities::ARCHIVE),
+            (&["cache-stats"], &capabilities::ARCHIVE),
+            (&["archive", "stats", "1"], &capabilities::ARCHIVE),
+        ];
+        for (args, capability) in gated {
+            let cli = Cli::try_parse_from(std::iter::once(&"openkakao-rs").chain(args.iter()))
+                .unwrap_or_else(|err| panic!("{args:?} should parse: {err}"));
+            assert_eq!(
+                cli.command.required_capability().map(|c| c.name),
+                Some(capability.name),
+                "{args:?}"
+            );
+        }
 
-        match cli.command {
-            Commands::Chatinfo { chat_id } => assert_eq!(chat_id, 123),
-            other => panic!("expected chatinfo command, got {other:?}"),
+        // Every capability a build can lack is checked by some command,
+        // except holidays, which only widens what --since/--until accept.
+        for capability in capabilities::ALL
+            .iter()
+            .filter(|c| !c.rebuild.is_empty() && c.name != capabilities::HOLIDAYS.name)
+        {
+            assert!(
+                gated.iter().any(|(_, c)| c.name == capability.name),
+                "no command checks {}",
+                capability.name
+            );
         }
+
+        let cli = Cli::try_parse_from(["openkakao-rs", "version", "--features"])
+            .expect("version --features should parse");
+        assert!(matches!(cli.command, Commands::Version { features: true }));
+        assert!(cli.command.required_capability().is_none());
     }
 
     #[test]
-    fn probe_command_is_available() {
-        let cli = Cli::try_parse_from([
-            "openkakao-rs",
-            "probe",
-            "BLSYNC",
-            "--body",
-            "{\"r\":0,\"pr\":0}",
-        ])
-        .expect("probe should be available");
+    fn chats_info_and_sort_parse() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "chats", "info", "42"])
+            .expect("chats info should parse");
+        assert!(matches!(
+            cli.command,
+            Commands::Chats {
+                action: Some(ChatsCommand::Info { chat_id: 42 }),
+                ..
+            }
+        ));
 
-        match cli.command {
-            Commands::Probe { method, body, .. } => {
-                assert_eq!(method, "BLSYNC");
-                assert_eq!(body.as_deref(), Some("{\"r\":0,\"pr\":0}"));
+        let cli = Cli::try_parse_from(["openkakao-rs", "chats", "--sort", "unread"])
+            .expect("chats should accept --sort");
+        assert!(matches!(
+            cli.command,
+            Commands::Chats {
+                action: None,
+                sort: Some(commands::chats::ChatSort::Unread),
+                ..
             }
-            other => panic!("expected probe command, got {other:?}"),
-        }
+        ));
+
+        assert!(Cli::try_parse_from(["openkakao-rs", "chats", "--sort", "size"]).is_err());
+        assert!(Cli::try_parse_from(["openkakao-rs", "chats", "info", "42", "--unread"]).is_err());
     }
 
     #[test]
-    fn profile_hints_command_is_available() {
+    fn stats_accepts_compare_ranges() {
         let cli = Cli::try_parse_from([
             "openkakao-rs",
-            "profile-hints",
-            "--local-graph",
-            "--user-id",
-            "100000003",
-            "--probe-syncmainpf",
-            "--probe-uplinkprof",
+            "stats",
+            "123",
+            "--compare",
+            "2024-01..2024-02",
+            "2024-03..2024-04",
         ])
-        .expect("profile-hints should be available");
+        .expect("stats should accept --compare with two ranges");
 
         match cli.command {
-            Commands::ProfileHints {
-                app_state,
-                app_state_diff,
-                local_graph,
-                user_id,
-                probe_syncmainpf,
-                probe_uplinkprof,
-            } => {
-                assert!(!app_state);
-                assert!(app_state_diff.is_none());
-                assert!(local_graph);
-                assert_eq!(user_id, Some(100000003));
-                assert!(probe_syncmainpf);
-                assert!(probe_uplinkprof);
+            Commands::Stats { compare, .. } => {
+                assert_eq!(
+                    compare,
+                    Some(vec![
+                        "2024-01..2024-02".to_string(),
+                        "2024-03..2024-04".to_string()
+                    ])
+                );
             }
-            other => panic!("expected profile-hints command, got {other:?}"),
+            other => panic!("expected stats command, got {other:?}"),
         }
+
+        assert!(
+            Cli::try_parse_from(["openkakao-rs", "stats", "123", "--compare", "2024-01"]).is_err()
+        );
     }
 
     #[test]
-    fn profile_hints_accepts_app_state_diff() {
-        let cli = Cli::try_parse_from([
+    fn summary_accepts_exact_flag() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "summary", "--exact", "--json"])
+            .expect("summary should accept --exact");
+        assert!(cli.json);
+        assert!(matches!(cli.command, Commands::Summary { exact: true }));
+    }
+
+    #[test]
+    fn strict_and_collect_unknown_are_exclusive() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "read", "1", "--strict"])
+            .expect("read should accept --strict");
+        assert!(cli.strict);
+
+        let cli =
+            Cli::try_parse_from(["openkakao-rs", "--collect-unknown", "fixtures", "read", "1"])
+                .expect("--collect-unknown takes a directory");
+        assert_eq!(
+            cli.collect_unknown.as_deref(),
+            Some(std::path::Path::new("fixtures"))
+        );
+
+        assert!(Cli::try_parse_from([
             "openkakao-rs",
-            "profile-hints",
-            "--app-state",
-            "--app-state-diff",
-            "/tmp/profile-hints-before.json",
+            "--strict",
+            "--collect-unknown",
+            "fixtures",
+            "read",
+            "1"
         ])
-        .expect("profile-hints should accept --app-state-diff");
+        .is_err());
+    }
+
+    #[test]
+    fn members_accepts_rest_flag() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "members", "123", "--rest", "--full"])
+            .expect("members should accept --rest and --full");
 
         match cli.command {
-            Commands::ProfileHints {
-                app_state,
-                app_state_diff,
+            Commands::Members {
+                chat_id,
+                rest,
+                full,
+                limit,
                 ..
             } => {
-                assert!(app_state);
-                assert_eq!(
-                    app_state_diff.as_deref(),
-                    Some("/tmp/profile-hints-before.json")
-                );
+                assert_eq!(chat_id, 123);
+                assert!(rest);
+                assert!(full);
+                assert_eq!(limit, None);
             }
-            other => panic!("expected profile-hints command, got {other:?}"),
+            other => panic!("expected members command, got {other:?}"),
         }
     }
 
     #[test]
-    fn probe_retry_helper_covers_common_socket_failures() {
-        assert!(should_retry_loco_probe_error(&anyhow::anyhow!("early eof")));
-        assert!(should_retry_loco_probe_error(&anyhow::anyhow!(
-            "Connection reset by peer (os error 54)"
-        )));
-        assert!(should_retry_loco_probe_error(&anyhow::anyhow!(
-            "broken pipe"
-        )));
-        assert!(!should_retry_loco_probe_error(&anyhow::anyhow!(
-            "status=-203"
-        )));
+    fn members_accepts_limit_flag() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "members", "123", "--limit", "200"])
+            .expect("members should accept --limit");
+
+        match cli.command {
+            Commands::Members { limit, .. } => assert_eq!(limit, Some(200)),
+            other => panic!("expected members command, got {other:?}"),
+        }
     }
 
     #[test]
-    fn parse_friend_profile_cache_hint_extracts_ids_and_access_permit() {
-        let hint = parse_profile_cache_hint(
-            136,
-            "https://katalk.kakao.com/mac/profile3/friend.json?accessPermit=example-access-permit-token&chatId=900000000000002&id=100000002",
-            true,
+    fn read_continue_flags_parse() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "read", "1", "--continue"])
+            .expect("read should accept --continue");
+        assert!(matches!(
+            cli.command,
+            Commands::Read {
+                continue_paging: true,
+                restart: false,
+                ..
+            }
+        ));
+        assert!(Cli::try_parse_from(["openkakao-rs", "read", "1", "--restart"]).is_ok());
+        assert!(
+            Cli::try_parse_from(["openkakao-rs", "read", "1", "--continue", "--before", "5"])
+                .is_err()
         );
+    }
 
-        assert_eq!(hint.kind, "friend");
-        assert_eq!(hint.user_ids, vec![100000002]);
-        assert_eq!(hint.chat_id, Some(900000000000002));
-        assert_eq!(
-            hint.access_permit.as_deref(),
-            Some("example-access-permit-token")
+    #[test]
+    fn read_since_and_until_parse() {
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "read",
+            "1",
+            "--since",
+            "2024-05-12",
+            "--until",
+            "2024-05-12T18:00",
+        ])
+        .expect("read should accept --since and --until");
+        match cli.command {
+            Commands::Read { since, until, .. } => {
+                assert_eq!(since.as_deref(), Some("2024-05-12"));
+                assert_eq!(until.as_deref(), Some("2024-05-12T18:00"));
+            }
+            other => panic!("expected read, got {other:?}"),
+        }
+        assert!(
+            Cli::try_parse_from(["openkakao-rs", "read", "1", "--continue", "--until", "2d"])
+                .is_err()
         );
-        assert!(hint.data_on_fs);
     }
 
     #[test]
-    fn parse_friends_profile_cache_hint_extracts_ids_list() {
-        let hint = parse_profile_cache_hint(
-            88,
-            "https://katalk.kakao.com/mac/profile3/friends.json?category=action&ids=%5B100000004%2C100000005%2C100000006%5D",
-            false,
+    fn read_wrap_flags_parse() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "read", "1", "--wrap", "60"])
+            .expect("read should accept --wrap");
+        match cli.command {
+            Commands::Read { wrap, no_wrap, .. } => {
+                assert_eq!(wrap, Some(60));
+                assert!(!no_wrap);
+                assert_eq!(read_wrap(wrap, no_wrap), Some(60));
+            }
+            other => panic!("expected read command, got {other:?}"),
+        }
+        assert_eq!(read_wrap(Some(0), false), None);
+        assert_eq!(read_wrap(Some(60), true), None);
+        assert!(
+            Cli::try_parse_from(["openkakao-rs", "read", "1", "--wrap", "60", "--no-wrap"])
+                .is_err()
         );
+    }
 
-        assert_eq!(hint.kind, "friends");
-        assert_eq!(hint.user_ids, vec![100000004, 100000005, 100000006]);
-        assert_eq!(hint.category.as_deref(), Some("action"));
-        assert_eq!(hint.chat_id, None);
-        assert_eq!(hint.access_permit, None);
+    #[test]
+    fn read_threads_flag_parses() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "read", "1", "--threads", "--since", "2d"])
+            .expect("read should accept --threads");
+        assert!(matches!(cli.command, Commands::Read { threads: true, .. }));
+        assert!(
+            Cli::try_parse_from(["openkakao-rs", "read", "1", "--threads", "--context", "5"])
+                .is_err()
+        );
+        assert!(Cli::try_parse_from(["openkakao-rs", "export", "1", "--threads"]).is_ok());
     }
 
     #[test]
-    fn collect_hint_chat_ids_prefers_user_specific_chat_hints() {
-        let hints = vec![
-            ProfileCacheHint {
-                entry_id: 1,
-                kind: "friend".into(),
-                request_key: String::new(),
-                user_ids: vec![100000002],
-                chat_id: Some(900000000000002),
-                access_permit: Some("permit-a".into()),
-                category: None,
-                data_on_fs: true,
-            },
-            ProfileCacheHint {
-                entry_id: 2,
-                kind: "friend".into(),
-                request_key: String::new(),
-                user_ids: vec![100000002],
-                chat_id: Some(900000000000002),
-                access_permit: Some("permit-b".into()),
-                category: None,
-                data_on_fs: true,
-            },
-            ProfileCacheHint {
-                entry_id: 3,
-                kind: "friend".into(),
-                request_key: String::new(),
-                user_ids: vec![100000003],
-                chat_id: Some(900000000000003),
-                access_permit: Some("permit-c".into()),
-                category: None,
-                data_on_fs: true,
-            },
-        ];
+    fn read_images_flag_parses() {
+        use commands::read::ImageDisplay;
+        let cli = Cli::try_parse_from(["openkakao-rs", "read", "1"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Read {
+                images: ImageDisplay::Count,
+                ..
+            }
+        ));
+        let cli = Cli::try_parse_from(["openkakao-rs", "read", "1", "--images", "url"])
+            .expect("read should accept --images url");
+        assert!(matches!(
+            cli.command,
+            Commands::Read {
+                images: ImageDisplay::Url,
+                ..
+            }
+        ));
+        assert!(Cli::try_parse_from(["openkakao-rs", "read", "1", "--images", "all"]).is_err());
+    }
 
-        assert_eq!(
-            collect_hint_chat_ids(&hints, 100000002),
-            vec![900000000000002]
-        );
-        assert_eq!(
-            collect_hint_chat_ids(&hints, 100000003),
-            vec![900000000000003]
-        );
-        assert!(collect_hint_chat_ids(&hints, 999).is_empty());
+    #[test]
+    fn read_spam_flags_parse() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "read", "1", "--hide-spam"])
+            .expect("read should accept --hide-spam");
+        assert!(matches!(
+            cli.command,
+            Commands::Read {
+                flag_spam: false,
+                hide_spam: true,
+                ..
+            }
+        ));
+        assert!(Cli::try_parse_from([
+            "openkakao-rs",
+            "read",
+            "1",
+            "--flag-spam",
+            "--context",
+            "5"
+        ])
+        .is_err());
     }
 
     #[test]
-    fn parse_loco_member_profile_from_getmem_doc() {
-        let doc = bson::doc! {
-            "userId": 100000002_i64,
-            "accountId": 200000001_i64,
-            "nickName": "Alice",
-            "countryIso": "kr",
-            "statusMessage": "hello",
-            "profileImageUrl": "https://example.com/p.jpg",
-            "fullProfileImageUrl": "https://example.com/p-full.jpg",
-            "originalProfileImageUrl": "https://example.com/p-original.jpg",
-            "accessPermit": "permit-token",
-            "suspicion": "",
-            "suspended": false,
-            "memorial": false,
-            "type": 0_i32,
-            "ut": 100_i64,
-        };
+    fn ignore_commands_and_collapse_flags_parse() {
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "ignore",
+            "add",
+            "철수",
+            "--chat",
+            "5",
+            "--allow",
+        ])
+        .expect("ignore add should parse");
+        assert!(matches!(
+            cli.command,
+            Commands::Ignore {
+                action: IgnoreCommand::Add {
+                    chat_id: Some(5),
+                    allow: true,
+                    ..
+                }
+            }
+        ));
+        assert!(Cli::try_parse_from(["openkakao-rs", "ignore", "add", "7", "--allow"]).is_err());
+        assert!(matches!(
+            Cli::try_parse_from(["openkakao-rs", "ignore", "ls"])
+                .expect("ignore ls should parse")
+                .command,
+            Commands::Ignore {
+                action: IgnoreCommand::List
+            }
+        ));
+        assert!(matches!(
+            Cli::try_parse_from(["openkakao-rs", "ignore", "rm", "7"])
+                .expect("ignore rm should parse")
+                .command,
+            Commands::Ignore {
+                action: IgnoreCommand::Rm { chat_id: None, .. }
+            }
+        ));
 
-        let profile = LocoMemberProfile::from_getmem_doc(&doc);
-        assert_eq!(
-            profile,
-            LocoMemberProfile {
-                user_id: 100000002,
-                account_id: 200000001,
-                nickname: "Alice".into(),
-                country_iso: "kr".into(),
-                status_message: "hello".into(),
-                profile_image_url: "https://example.com/p.jpg".into(),
-                full_profile_image_url: "https://example.com/p-full.jpg".into(),
-                original_profile_image_url: "https://example.com/p-original.jpg".into(),
-                access_permit: "permit-token".into(),
-                suspicion: String::new(),
-                suspended: false,
-                memorial: false,
-                member_type: 0,
-                ut: 100,
+        let cli = Cli::try_parse_from(["openkakao-rs", "read", "1", "--collapse-ignored"])
+            .expect("read should accept --collapse-ignored");
+        assert!(matches!(
+            cli.command,
+            Commands::Read {
+                collapse_ignored: true,
+                ..
             }
-        );
-        assert_eq!(profile.as_chat_member().display_name(), "Alice");
+        ));
+        let cli =
+            Cli::try_parse_from(["openkakao-rs", "watch", "--all-chats", "--collapse-ignored"])
+                .expect("watch should accept --collapse-ignored");
+        assert!(matches!(
+            cli.command,
+            Commands::Watch {
+                collapse_ignored: true,
+                ..
+            }
+        ));
     }
 
     #[test]
-    fn local_graph_summary_carries_getmem_tokens() {
-        let snapshot = LocalFriendGraphSnapshot {
-            user_count: 1,
-            chat_count: 1,
-            failed_chat_ids: Vec::new(),
+    fn friends_download_avatars_flags_parse() {
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "friends",
+            "--favorites",
+            "--download-avatars",
+            "photos",
+            "--jobs",
+            "4",
+        ])
+        .expect("friends should accept --download-avatars");
+        match cli.command {
+            Commands::Friends {
+                favorites,
+                download_avatars,
+                jobs,
+                ..
+            } => {
+                assert!(favorites);
+                assert_eq!(download_avatars, Some(std::path::PathBuf::from("photos")));
+                assert_eq!(jobs, 4);
+            }
+            other => panic!("expected friends command, got {other:?}"),
+        }
+        // --jobs only makes sense with downloads, and is bounded.
+        assert!(Cli::try_parse_from(["openkakao-rs", "friends", "--jobs", "2"]).is_err());
+        assert!(Cli::try_parse_from([
+            "openkakao-rs",
+            "friends",
+            "--download-avatars",
+            "d",
+            "--jobs",
+            "0"
+        ])
+        .is_err());
+        assert!(Cli::try_parse_from([
+            "openkakao-rs",
+            "friends",
+            "--download-avatars",
+            "d",
+            "--local"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn members_roster_flags_parse() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "members", "123", "--merge", "roster.csv"])
+            .expect("members should accept --merge");
+        match cli.command {
+            Commands::Members {
+                export_csv, merge, ..
+            } => {
+                assert_eq!(export_csv, None);
+                assert_eq!(merge.as_deref(), Some("roster.csv"));
+            }
+            other => panic!("expected members command, got {other:?}"),
+        }
+
+        assert!(Cli::try_parse_from([
+            "openkakao-rs",
+            "members",
+            "123",
+            "--export-csv",
+            "a.csv",
+            "--merge",
+            "b.csv",
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn profile_accepts_chat_id_flag() {
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "profile",
+            "100000002",
+            "--chat-id",
+            "900000000000001",
+        ])
+        .expect("profile should accept --chat-id");
+
+        match cli.command {
+            Commands::Profile {
+                user_id,
+                chat_id,
+                local,
+                ..
+            } => {
+                assert_eq!(user_id, Some(100000002));
+                assert_eq!(chat_id, Some(900000000000001));
+                assert!(!local);
+            }
+            other => panic!("expected profile command, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn friends_accepts_local_flag() {
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "friends",
+            "--local",
+            "-s",
+            "Alice",
+            "--chat-id",
+            "900000000000003",
+            "--user-id",
+            "100000003",
+        ])
+        .expect("friends should accept --local");
+
+        match cli.command {
+            Commands::Friends {
+                local,
+                search,
+                favorites,
+                hidden,
+                hidden_only,
+                chat_id,
+                user_id,
+                detail,
+                fuzzy,
+                ..
+            } => {
+                assert!(local);
+                assert!(!hidden_only);
+                assert!(!detail);
+                assert!(!fuzzy);
+                assert_eq!(search.as_deref(), Some("Alice"));
+                assert!(!favorites);
+                assert!(!hidden);
+                assert_eq!(chat_id, Some(900000000000003));
+                assert_eq!(user_id, Some(100000003));
+            }
+            other => panic!("expected friends command, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn friends_uuid_is_an_alias_for_detail() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "friends", "--uuid"])
+            .expect("friends should accept --uuid");
+        assert!(matches!(
+            cli.command,
+            Commands::Friends { detail: true, .. }
+        ));
+    }
+
+    #[test]
+    fn friends_raw_phones_parses() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "friends", "--detail", "--raw-phones"])
+            .expect("friends should accept --raw-phones");
+        assert!(matches!(
+            cli.command,
+            Commands::Friends {
+                raw_phones: true,
+                detail: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn read_only_refuses_kakao_id_lookup_but_not_reads() {
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "--read-only",
+            "friend",
+            "find-by-id",
+            "someone",
+        ])
+        .expect("friend find-by-id should parse");
+        assert!(cli.read_only);
+        match &cli.command {
+            Commands::Friend {
+                action: FriendCommand::FindById { kakao_id },
+            } => assert_eq!(kakao_id, "someone"),
+            other => panic!("expected friend find-by-id, got {other:?}"),
+        }
+        assert!(cli.command.read_only_violation().is_some());
+
+        let cli = Cli::try_parse_from(["openkakao-rs", "--read-only", "friends", "-s", "kim"])
+            .expect("friends should parse");
+        assert!(cli.command.read_only_violation().is_none());
+    }
+
+    #[test]
+    fn endpoints_parses_and_read_only_uses_the_catalog() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "--read-only", "endpoints"])
+            .expect("endpoints should parse");
+        assert!(matches!(cli.command, Commands::Endpoints));
+        assert!(cli.command.read_only_violation().is_none());
+
+        let cli = Cli::try_parse_from(["openkakao-rs", "--read-only", "favorite", "42"])
+            .expect("favorite should parse");
+        assert_eq!(
+            cli.command.read_only_violation(),
+            rest::side_effect_of("add_favorite")
+        );
+        assert!(cli.command.read_only_violation().is_some());
+
+        let cli = Cli::try_parse_from(["openkakao-rs", "me"]).expect("me should parse");
+        assert!(cli.command.read_only_violation().is_none());
+        let cli = Cli::try_parse_from(["openkakao-rs", "me", "--set-status", ""])
+            .expect("an empty status should parse");
+        match &cli.command {
+            Commands::Me {
+                set_status,
+                set_nickname: None,
+            } => assert_eq!(set_status.as_deref(), Some("")),
+            other => panic!("expected me --set-status, got {other:?}"),
+        }
+        assert_eq!(
+            cli.command.read_only_violation(),
+            Some("changing your profile")
+        );
+    }
+
+    #[test]
+    fn account_preference_flags_are_global_and_exclusive() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "chats", "--prefer-extracted"])
+            .expect("--prefer-extracted is global");
+        assert!(cli.prefer_extracted && !cli.prefer_saved && !cli.overwrite_saved);
+        let cli = Cli::try_parse_from(["openkakao-rs", "--prefer-saved", "me"])
+            .expect("--prefer-saved parses");
+        assert!(cli.prefer_saved);
+        assert!(Cli::try_parse_from([
+            "openkakao-rs",
+            "me",
+            "--prefer-saved",
+            "--prefer-extracted"
+        ])
+        .is_err());
+        // Overwriting only makes sense when switching to KakaoTalk's account.
+        assert!(Cli::try_parse_from(["openkakao-rs", "me", "--overwrite-saved"]).is_err());
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "me",
+            "--prefer-extracted",
+            "--overwrite-saved",
+        ])
+        .expect("--overwrite-saved with --prefer-extracted");
+        assert!(cli.overwrite_saved);
+    }
+
+    #[test]
+    fn profile_is_global_and_profiles_are_listed_under_credentials() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "login", "--save", "--profile", "work"])
+            .expect("--profile is global");
+        assert_eq!(cli.profile.as_deref(), Some("work"));
+        assert!(!cli.no_verify);
+        let unverified = Cli::try_parse_from(["openkakao-rs", "login", "--no-verify"])
+            .expect("--no-verify is global");
+        assert!(unverified.no_verify);
+        assert!(matches!(
+            cli.command,
+            Commands::Login {
+                save: true,
+                store: None
+            }
+        ));
+        let cli = Cli::try_parse_from(["openkakao-rs", "login", "--save", "--store", "file"])
+            .expect("--store should parse");
+        assert!(matches!(
+            cli.command,
+            Commands::Login {
+                save: true,
+                store: Some(credentials::CredentialStore::File)
+            }
+        ));
+        assert!(Cli::try_parse_from(["openkakao-rs", "login", "--store", "keychain"]).is_err());
+        let cli = Cli::try_parse_from(["openkakao-rs", "credentials", "profiles"])
+            .expect("credentials profiles should parse");
+        assert!(matches!(
+            cli.command,
+            Commands::Credentials {
+                action: CredentialsCommand::Profiles { action: None }
+            }
+        ));
+        let cli = Cli::try_parse_from(["openkakao-rs", "credentials", "profiles", "rm", "work"])
+            .expect("credentials profiles rm should parse");
+        assert!(matches!(
+            cli.command,
+            Commands::Credentials {
+                action: CredentialsCommand::Profiles {
+                    action: Some(ProfilesCommand::Rm { ref name, yes: false }),
+                },
+            } if name == "work"
+        ));
+    }
+
+    #[test]
+    fn only_unanswered_confirmations_are_pending() {
+        let pending = |args: &[&str]| {
+            let cli =
+                Cli::try_parse_from(std::iter::once("openkakao-rs").chain(args.iter().copied()))
+                    .expect("command should parse");
+            cli.command.pending_confirmation()
+        };
+        assert_eq!(pending(&["send", "42", "hi"]), Some("the send"));
+        assert_eq!(pending(&["send", "42", "hi", "-y"]), None);
+        assert_eq!(pending(&["send", "42", "hi", "--dry-run"]), None);
+        assert_eq!(pending(&["send", "42", "hi", "--queue"]), None);
+        assert_eq!(pending(&["dm", "kim"]), None);
+        assert_eq!(pending(&["dm", "kim", "--send", "hi"]), Some("the send"));
+        assert_eq!(pending(&["delete", "42", "7"]), Some("the delete"));
+        assert_eq!(pending(&["favorite", "42"]), Some("adding the favorite"));
+        assert_eq!(pending(&["favorite", "42", "--yes"]), None);
+        assert_eq!(pending(&["unhide", "--all-hidden", "--dry-run"]), None);
+        assert_eq!(pending(&["init"]), Some("the setup wizard"));
+        assert_eq!(pending(&["init", "-y"]), None);
+        assert_eq!(pending(&["chats"]), None);
+        assert!(
+            Cli::try_parse_from(["openkakao-rs", "--no-input", "chats"])
+                .expect("--no-input is global")
+                .no_input
+        );
+    }
+
+    #[test]
+    fn send_queue_and_outbox_parse() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "send", "42", "hi", "--queue", "--split"])
+            .expect("send --queue should parse");
+        match &cli.command {
+            Commands::Send {
+                chat_id: 42,
+                queue: true,
+                split: true,
+                ..
+            } => {}
+            other => panic!("unexpected command: {other:?}"),
+        }
+        assert!(cli.command.read_only_violation().is_none());
+
+        let cli = Cli::try_parse_from(["openkakao-rs", "send-file", "42", "a.jpg", "--queue"])
+            .expect("send-file --queue should parse");
+        assert!(cli.command.read_only_violation().is_none());
+        assert!(
+            Cli::try_parse_from(["openkakao-rs", "send", "42", "hi", "--queue", "-y"]).is_err()
+        );
+
+        let cli = Cli::try_parse_from(["openkakao-rs", "outbox", "flush", "--force", "-y"])
+            .expect("outbox flush should parse");
+        match &cli.command {
+            Commands::Outbox {
+                action:
+                    OutboxCommand::Flush {
+                        force: true,
+                        yes: true,
+                    },
+            } => {}
+            other => panic!("unexpected command: {other:?}"),
+        }
+        assert_eq!(cli.command.read_only_violation(), Some("sending messages"));
+
+        let cli = Cli::try_parse_from(["openkakao-rs", "outbox", "list"]).expect("outbox list");
+        assert!(cli.command.read_only_violation().is_none());
+    }
+
+    #[test]
+    fn profile_takes_an_id_or_a_name() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "profile", "--name", "김민"])
+            .expect("profile should accept --name");
+        assert!(matches!(
+            cli.command,
+            Commands::Profile {
+                user_id: None,
+                name: Some(ref name),
+                ..
+            } if name == "김민"
+        ));
+        assert!(Cli::try_parse_from(["openkakao-rs", "profile"]).is_err());
+        assert!(Cli::try_parse_from(["openkakao-rs", "profile", "1", "--name", "x"]).is_err());
+    }
+
+    #[test]
+    fn profile_accepts_local_flag() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "profile", "100000002", "--local"])
+            .expect("profile should accept --local");
+
+        match cli.command {
+            Commands::Profile {
+                user_id,
+                chat_id,
+                local,
+                ..
+            } => {
+                assert_eq!(user_id, Some(100000002));
+                assert_eq!(chat_id, None);
+                assert!(local);
+            }
+            other => panic!("expected profile command, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn settings_track_and_changes_flags_parse() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "settings", "--changes", "--track"])
+            .expect("settings should accept --changes --track");
+
+        match cli.command {
+            Commands::Settings { track, changes } => assert!(track && changes),
+            other => panic!("expected settings command, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn dm_flags_parse() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "dm", "철수", "--send", "hi", "-y"])
+            .expect("dm should accept --send");
+        assert_eq!(cli.command.read_only_violation(), Some("sending messages"));
+        match cli.command {
+            Commands::Dm {
+                friend,
+                read,
+                send,
+                yes,
+                ..
+            } => {
+                assert_eq!(friend, "철수");
+                assert!(!read && yes);
+                assert_eq!(send.as_deref(), Some("hi"));
+            }
+            other => panic!("expected dm command, got {other:?}"),
+        }
+
+        let cli = Cli::try_parse_from(["openkakao-rs", "dm", "ㅊㅅ", "--read", "-n", "5"])
+            .expect("dm should accept --read");
+        assert_eq!(cli.command.read_only_violation(), None);
+        assert!(
+            Cli::try_parse_from(["openkakao-rs", "dm", "x", "--read", "--send", "hi"]).is_err()
+        );
+        assert!(Cli::try_parse_from(["openkakao-rs", "dm", "x", "-n", "5"]).is_err());
+    }
+
+    #[test]
+    fn chat_detail_command_is_available() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "--json", "chat", "123"])
+            .expect("chat should be available");
+
+        match cli.command {
+            Commands::Chat { chat_id } => assert_eq!(chat_id, 123),
+            other => panic!("expected chat command, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn chatinfo_command_is_available() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "chatinfo", "123"])
+            .expect("chatinfo should be available");
+
+        match cli.command {
+            Commands::Chatinfo { chat_id } => assert_eq!(chat_id, 123),
+            other => panic!("expected chatinfo command, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn probe_command_is_available() {
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "probe",
+            "BLSYNC",
+            "--body",
+            "{\"r\":0,\"pr\":0}",
+        ])
+        .expect("probe should be available");
+
+        match cli.command {
+            Commands::Probe { method, body, .. } => {
+                assert_eq!(method, "BLSYNC");
+                assert_eq!(body.as_deref(), Some("{\"r\":0,\"pr\":0}"));
+            }
+            other => panic!("expected probe command, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn profile_hints_command_is_available() {
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "profile-hints",
+            "--local-graph",
+            "--user-id",
+            "100000003",
+            "--probe-syncmainpf",
+            "--probe-uplinkprof",
+        ])
+        .expect("profile-hints should be available");
+
+        match cli.command {
+            Commands::ProfileHints {
+                app_state,
+                app_state_diff,
+                local_graph,
+                user_id,
+                probe_syncmainpf,
+                probe_uplinkprof,
+            } => {
+                assert!(!app_state);
+                assert!(app_state_diff.is_none());
+                assert!(local_graph);
+                assert_eq!(user_id, Some(100000003));
+                assert!(probe_syncmainpf);
+                assert!(probe_uplinkprof);
+            }
+            other => panic!("expected profile-hints command, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn profile_hints_accepts_app_state_diff() {
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "profile-hints",
+            "--app-state",
+            "--app-state-diff",
+            "/tmp/profile-hints-before.json",
+        ])
+        .expect("profile-hints should accept --app-state-diff");
+
+        match cli.command {
+            Commands::ProfileHints {
+                app_state,
+                app_state_diff,
+                ..
+            } => {
+                assert!(app_state);
+                assert_eq!(
+                    app_state_diff.as_deref(),
+                    Some("/tmp/profile-hints-before.json")
+                );
+            }
+            other => panic!("expected profile-hints command, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn probe_retry_helper_covers_common_socket_failures() {
+        assert!(should_retry_loco_probe_error(&anyhow::anyhow!("early eof")));
+        assert!(should_retry_loco_probe_error(&anyhow::anyhow!(
+            "Connection reset by peer (os error 54)"
+        )));
+        assert!(should_retry_loco_probe_error(&anyhow::anyhow!(
+            "broken pipe"
+        )));
+        assert!(!should_retry_loco_probe_error(&anyhow::anyhow!(
+            "status=-203"
+        )));
+    }
+
+    #[test]
+    fn parse_friend_profile_cache_hint_extracts_ids_and_access_permit() {
+        let hint = parse_profile_cache_hint(
+            136,
+            "https://katalk.kakao.com/mac/profile3/friend.json?accessPermit=example-access-permit-token&chatId=900000000000002&id=100000002",
+            true,
+        );
+
+        assert_eq!(hint.kind, "friend");
+        assert_eq!(hint.user_ids, vec![100000002]);
+        assert_eq!(hint.chat_id, Some(900000000000002));
+        assert_eq!(
+            hint.access_permit.as_deref(),
+            Some("example-access-permit-token")
+        );
+        assert!(hint.data_on_fs);
+    }
+
+    #[test]
+    fn parse_friends_profile_cache_hint_extracts_ids_list() {
+        let hint = parse_profile_cache_hint(
+            88,
+            "https://katalk.kakao.com/mac/profile3/friends.json?category=action&ids=%5B100000004%2C100000005%2C100000006%5D",
+            false,
+        );
+
+        assert_eq!(hint.kind, "friends");
+        assert_eq!(hint.user_ids, vec![100000004, 100000005, 100000006]);
+        assert_eq!(hint.category.as_deref(), Some("action"));
+        assert_eq!(hint.chat_id, None);
+        assert_eq!(hint.access_permit, None);
+    }
+
+    #[test]
+    fn collect_hint_chat_ids_prefers_user_specific_chat_hints() {
+        let hints = vec![
+            ProfileCacheHint {
+                entry_id: 1,
+                kind: "friend".into(),
+                request_key: String::new(),
+                user_ids: vec![100000002],
+                chat_id: Some(900000000000002),
+                access_permit: Some("permit-a".into()),
+                category: None,
+                data_on_fs: true,
+            },
+            ProfileCacheHint {
+                entry_id: 2,
+                kind: "friend".into(),
+                request_key: String::new(),
+                user_ids: vec![100000002],
+                chat_id: Some(900000000000002),
+                access_permit: Some("permit-b".into()),
+                category: None,
+                data_on_fs: true,
+            },
+            ProfileCacheHint {
+                entry_id: 3,
+                kind: "friend".into(),
+                request_key: String::new(),
+                user_ids: vec![100000003],
+                chat_id: Some(900000000000003),
+                access_permit: Some("permit-c".into()),
+                category: None,
+                data_on_fs: true,
+            },
+        ];
+
+        assert_eq!(
+            collect_hint_chat_ids(&hints, 100000002),
+            vec![900000000000002]
+        );
+        assert_eq!(
+            collect_hint_chat_ids(&hints, 100000003),
+            vec![900000000000003]
+        );
+        assert!(collect_hint_chat_ids(&hints, 999).is_empty());
+    }
+
+    #[test]
+    fn parse_loco_member_profile_from_getmem_doc() {
+        let doc = bson::doc! {
+            "userId": 100000002_i64,
+            "accountId": 200000001_i64,
+            "nickName": "Alice",
+            "countryIso": "kr",
+            "statusMessage": "hello",
+            "profileImageUrl": "https://example.com/p.jpg",
+            "fullProfileImageUrl": "https://example.com/p-full.jpg",
+            "originalProfileImageUrl": "https://example.com/p-original.jpg",
+            "accessPermit": "permit-token",
+            "suspicion": "",
+            "suspended": false,
+            "memorial": false,
+            "type": 0_i32,
+            "ut": 100_i64,
+        };
+
+        let profile = LocoMemberProfile::from_getmem_doc(&doc);
+        assert_eq!(
+            profile,
+            LocoMemberProfile {
+                user_id: 100000002,
+                account_id: 200000001,
+                nickname: "Alice".into(),
+                country_iso: "kr".into(),
+                status_message: "hello".into(),
+                profile_image_url: "https://example.com/p.jpg".into(),
+                full_profile_image_url: "https://example.com/p-full.jpg".into(),
+                original_profile_image_url: "https://example.com/p-original.jpg".into(),
+                access_permit: "permit-token".into(),
+                suspicion: String::new(),
+                suspended: false,
+                memorial: false,
+                member_type: 0,
+                ut: 100,
+            }
+        );
+        assert_eq!(profile.as_chat_member().display_name(), "Alice");
+    }
+
+    #[test]
+    fn local_graph_summary_carries_getmem_tokens() {
+        let snapshot = LocalFriendGraphSnapshot {
+            user_count: 1,
+            chat_count: 1,
+            failed_chat_ids: Vec::new(),
+            chat_meta: vec![LocalFriendGraphChatMeta {
+                chat_id: 900000000000002,
+                title: "Example".into(),
+                getmem_token: Some(777),
+                member_count: 2,
+            }],
+            entries: vec![LocalFriendGraphEntry {
+                user_id: 100000002,
+                account_id: 200000001,
+                nickname: "Alice".into(),
+                country_iso: "KR".into(),
+                status_message: String::new(),
+                profile_image_url: String::new(),
+                full_profile_image_url: String::new(),
+                original_profile_image_url: String::new(),
+                access_permits: vec!["permit-token".into()],
+                suspicion: String::new(),
+                suspended: false,
+                memorial: false,
+                member_type: 0,
+                chat_ids: vec![900000000000002],
+                chat_titles: vec!["Example".into()],
+                is_self: false,
+                hidden_like: false,
+                hidden_block_type: None,
+            }],
+        };
+        let hints = vec![ProfileCacheHint {
+            entry_id: 1,
+            kind: "friend".into(),
+            request_key: String::new(),
+            user_ids: vec![100000002],
+            chat_id: Some(900000000000002),
+            access_permit: Some("permit-token".into()),
+            category: None,
+            data_on_fs: true,
+        }];
+
+        let summary = local_graph_hint_summary(&snapshot, &hints);
+        assert_eq!(summary.candidate_matches.len(), 1);
+        assert_eq!(
+            summary.candidate_matches[0].candidate_getmem_tokens,
+            vec![777]
+        );
+    }
+
+    #[test]
+    fn syncmainpf_candidates_include_getmem_token_fields() {
+        let snapshot = LocalFriendGraphSnapshot {
+            user_count: 1,
+            chat_count: 1,
+            failed_chat_ids: Vec::new(),
             chat_meta: vec![LocalFriendGraphChatMeta {
                 chat_id: 900000000000002,
                 title: "Example".into(),
@@ -1744,317 +5330,1470 @@ mod tests {
                 hidden_block_type: None,
             }],
         };
-        let hints = vec![ProfileCacheHint {
-            entry_id: 1,
-            kind: "friend".into(),
-            request_key: String::new(),
-            user_ids: vec![100000002],
-            chat_id: Some(900000000000002),
-            access_permit: Some("permit-token".into()),
-            category: None,
-            data_on_fs: true,
-        }];
 
-        let summary = local_graph_hint_summary(&snapshot, &hints);
-        assert_eq!(summary.candidate_matches.len(), 1);
+        let candidate = build_syncmainpf_candidate(&snapshot, &[], 100000002)
+            .expect("candidate should be built");
+
+        assert_eq!(candidate.getmem_tokens, vec![777]);
+        assert!(candidate
+            .bodies
+            .iter()
+            .any(|body| body.get("token").and_then(|v| v.as_i64()) == Some(777)));
+        assert!(candidate
+            .bodies
+            .iter()
+            .any(|body| body.get("profileToken").and_then(|v| v.as_i64()) == Some(777)));
+        assert!(candidate
+            .uplinkprof_bodies
+            .iter()
+            .any(|body| body.get("token").and_then(|v| v.as_i64()) == Some(777)));
+    }
+
+    #[test]
+    fn legacy_loco_read_remains_available() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "loco-read", "123", "--all"])
+            .expect("legacy loco-read should remain available");
+
+        match cli.command {
+            Commands::LocoRead { chat_id, all, .. } => {
+                assert_eq!(chat_id, 123);
+                assert!(all);
+            }
+            other => panic!("expected loco-read command, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn legacy_loco_chats_remains_available() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "loco-chats", "--all"])
+            .expect("legacy loco-chats should remain available");
+
+        match cli.command {
+            Commands::LocoChats { show_all } => {
+                assert!(show_all);
+            }
+            other => panic!("expected loco-chats command, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn legacy_loco_members_remains_available() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "loco-members", "123"])
+            .expect("legacy loco-members should remain available");
+
+        match cli.command {
+            Commands::LocoMembers { chat_id } => assert_eq!(chat_id, 123),
+            other => panic!("expected loco-members command, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn legacy_loco_chatinfo_remains_available() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "loco-chatinfo", "123"])
+            .expect("legacy loco-chatinfo should remain available");
+
+        match cli.command {
+            Commands::LocoChatinfo { chat_id } => assert_eq!(chat_id, 123),
+            other => panic!("expected loco-chatinfo command, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn legacy_loco_probe_remains_available() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "loco-probe", "BLSYNC"])
+            .expect("legacy loco-probe should remain available");
+
+        match cli.command {
+            Commands::LocoProbe { method, body } => {
+                assert_eq!(method, "BLSYNC");
+                assert!(body.is_none());
+            }
+            other => panic!("expected loco-probe command, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn webhook_header_requires_name_and_value() {
+        assert_eq!(
+            parse_webhook_header("Authorization: Bearer test").unwrap(),
+            ("Authorization".to_string(), "Bearer test".to_string())
+        );
+        assert!(parse_webhook_header("MissingSeparator").is_err());
+        assert!(parse_webhook_header("Header: ").is_err());
+    }
+
+    #[test]
+    fn webhook_signature_is_stable_for_known_input() {
+        let signature = build_webhook_signature("secret", "1700000000", br#"{"ok":true}"#).unwrap();
+        assert_eq!(
+            signature,
+            "sha256=c1afc7c2df3db0690d7d75954610ed1a1d959ce96355ccb8c0a8bc09fd0cfc27"
+        );
+    }
+
+    #[test]
+    fn webhook_url_requires_https_or_loopback_http() {
+        assert!(validate_webhook_url("https://example.com/hook", false).is_ok());
+        assert!(validate_webhook_url("http://localhost:3000/hook", false).is_ok());
+        assert!(validate_webhook_url("http://127.0.0.1:4000/hook", false).is_ok());
+        assert!(validate_webhook_url("http://example.com/hook", false).is_err());
+        assert!(validate_webhook_url("http://example.com/hook", true).is_ok());
+    }
+
+    #[test]
+    fn outbound_message_must_not_be_blank() {
+        assert!(validate_outbound_message("hello").is_ok());
+        assert!(validate_outbound_message("   ").is_err());
+    }
+
+    #[test]
+    fn stats_command_is_available() {
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "stats",
+            "123",
+            "--limit",
+            "500",
+            "--since",
+            "2025-01-01",
+        ])
+        .expect("stats should accept limit and since");
+
+        match cli.command {
+            Commands::Stats {
+                chat_id,
+                limit,
+                since,
+                compare,
+                ..
+            } => {
+                assert_eq!(chat_id, 123);
+                assert_eq!(limit, Some(500));
+                assert_eq!(since.as_deref(), Some("2025-01-01"));
+                assert!(compare.is_none());
+            }
+            other => panic!("expected stats command, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn openkakao_error_loco_status_display() {
+        use crate::error::OpenKakaoError;
+        let err = OpenKakaoError::loco("SYNCMSG", -300);
+        assert!(err.to_string().contains("SYNCMSG"));
+        assert!(err.to_string().contains("-300"));
+        assert!(err.is_retryable());
+    }
+
+    #[test]
+    fn openkakao_error_display_explains_known_status() {
+        use crate::error::OpenKakaoError;
+        let err = OpenKakaoError::RestApi {
+            status: -999,
+            message: String::new(),
+        };
+        assert!(err.to_string().contains("Client version too old"));
+        assert_eq!(
+            err.kakao_status(),
+            Some(crate::status::KakaoStatus::UpgradeRequired)
+        );
+        let err = OpenKakaoError::loco("WRITE", -31337);
+        assert!(err.to_string().contains("unrecognized status -31337"));
+    }
+
+    #[test]
+    fn capture_bodies_requires_capture_report() {
+        assert!(Cli::try_parse_from(["openkakao-rs", "--capture-bodies", "chats"]).is_err());
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "chats",
+            "--capture-report",
+            "report.json",
+            "--capture-bodies",
+        ])
+        .expect("capture flags should parse");
+        assert!(cli.capture_bodies);
+    }
+
+    #[test]
+    fn verbose_is_a_global_flag() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "chats", "--verbose"])
+            .expect("--verbose should be accepted after a subcommand");
+        assert_eq!(cli.verbose, 1);
+        let cli = Cli::try_parse_from(["openkakao-rs", "-vv", "chats"]).unwrap();
+        assert_eq!(cli.verbose, 2);
+    }
+
+    #[test]
+    fn openkakao_error_token_expired_from_950() {
+        use crate::error::OpenKakaoError;
+        let err = OpenKakaoError::loco("LOGINLIST", -950);
+        assert!(matches!(err, OpenKakaoError::TokenExpired));
+        assert!(err.is_retryable());
+    }
+
+    #[test]
+    fn openkakao_error_non_retryable_status() {
+        use crate::error::OpenKakaoError;
+        let err = OpenKakaoError::loco("WRITE", -203);
+        assert!(!err.is_retryable());
+    }
+
+    #[test]
+    fn retries_flag_builds_the_library_policy() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "chats", "--retries", "0"])
+            .expect("--retries should parse");
+        let policy = retry::RetryPolicy::default().with_retries(cli.retries.unwrap());
+        assert_eq!(policy.max_attempts, 1);
+        // Auth failures are left to the re-login chain, not retried.
+        let expired = anyhow::Error::new(crate::error::OpenKakaoError::TokenExpired);
+        assert!(!retry::RetryPolicy::default().is_retryable_error(&expired));
+    }
+
+    #[test]
+    fn check_loco_status_passes_on_zero() {
+        use crate::loco_helpers::check_loco_status;
+        let packet = crate::loco::packet::LocoPacket {
+            packet_id: 1,
+            status_code: 0,
+            method: "TEST".into(),
+            body_type: 0,
+            body: bson::doc! { "status": 0_i32 },
+        };
+        assert!(check_loco_status("TEST", &packet).is_ok());
+    }
+
+    #[test]
+    fn check_loco_status_fails_on_nonzero() {
+        use crate::loco_helpers::check_loco_status;
+        let packet = crate::loco::packet::LocoPacket {
+            packet_id: 1,
+            status_code: 0,
+            method: "SYNCMSG".into(),
+            body_type: 0,
+            body: bson::doc! { "status": -300_i32 },
+        };
+        let err = check_loco_status("SYNCMSG", &packet).unwrap_err();
+        assert!(err.to_string().contains("SYNCMSG"));
+        assert!(err.to_string().contains("-300"));
+    }
+
+    #[test]
+    fn watch_scope_rules_parse() {
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "watch",
+            "--include",
+            "OpenGroup:*",
+            "--include",
+            "chat:18231",
+            "--exclude",
+            "type:dm",
+        ])
+        .expect("watch should accept --include/--exclude");
+
+        match cli.command {
+            Commands::Watch {
+                include, exclude, ..
+            } => {
+                assert_eq!(include, ["OpenGroup:*", "chat:18231"]);
+                assert_eq!(exclude, ["type:dm"]);
+            }
+            other => panic!("expected watch command, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn cache_takes_a_chat_id_or_all() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "cache", "--all", "--explain"])
+            .expect("cache should accept --all --explain");
+        assert!(matches!(
+            cli.command,
+            Commands::Cache {
+                chat_id: None,
+                all: true,
+                explain: true,
+                ..
+            }
+        ));
+
+        let cli = Cli::try_parse_from(["openkakao-rs", "cache", "42"])
+            .expect("cache should accept a chat id");
+        assert!(matches!(
+            cli.command,
+            Commands::Cache {
+                chat_id: Some(42),
+                all: false,
+                ..
+            }
+        ));
+
+        assert!(Cli::try_parse_from(["openkakao-rs", "cache"]).is_err());
+        assert!(Cli::try_parse_from(["openkakao-rs", "cache", "42", "--all"]).is_err());
+        assert!(Cli::try_parse_from(["openkakao-rs", "cache", "42", "--explain"]).is_err());
+
+        let cli = Cli::try_parse_from(["openkakao-rs", "cache", "stats"])
+            .expect("cache stats should parse");
+        assert!(matches!(
+            cli.command,
+            Commands::Cache {
+                action: Some(CacheCommand::Stats),
+                ..
+            }
+        ));
+        let cli = Cli::try_parse_from(["openkakao-rs", "cache", "clear", "42"])
+            .expect("cache clear should take a chat id");
+        assert!(matches!(
+            cli.command,
+            Commands::Cache {
+                action: Some(CacheCommand::Clear {
+                    chat_id: Some(42),
+                    yes: false,
+                    media: false,
+                    scrap: false
+                }),
+                ..
+            }
+        ));
+        assert_eq!(
+            cli.command.pending_confirmation(),
+            Some("clearing the message cache")
+        );
+        assert!(Cli::try_parse_from(["openkakao-rs", "cache", "clear", "-y"]).is_ok());
+        assert!(
+            Cli::try_parse_from(["openkakao-rs", "--no-cache", "read", "42"])
+                .expect("--no-cache is global")
+                .no_cache
+        );
+        assert!(
+            Cli::try_parse_from(["openkakao-rs", "scrap", "u", "--no-cache", "--refresh"]).is_err()
+        );
+    }
+
+    #[test]
+    fn watch_capture_flag_parses() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "watch", "--capture"])
+            .expect("watch should accept --capture");
+
+        match cli.command {
+            Commands::Watch { capture, .. } => {
+                assert!(capture);
+            }
+            other => panic!("expected watch command, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn probe_capture_pushes_flag_parses() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "probe", "PING", "--capture-pushes"])
+            .expect("probe should accept --capture-pushes");
+
+        match cli.command {
+            Commands::Probe {
+                method,
+                capture_pushes,
+                ..
+            } => {
+                assert_eq!(method, "PING");
+                assert!(capture_pushes);
+            }
+            other => panic!("expected probe command, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn delete_command_parses() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "delete", "123", "456", "--force", "-y"])
+            .expect("delete should parse");
+        match cli.command {
+            Commands::Delete {
+                chat_id,
+                log_id,
+                force,
+                yes,
+                dry_run,
+            } => {
+                assert_eq!(chat_id, 123);
+                assert_eq!(log_id, 456);
+                assert!(force);
+                assert!(yes);
+                assert!(!dry_run);
+            }
+            other => panic!("expected delete, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn mark_read_command_parses() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "mark-read", "123", "456"])
+            .expect("mark-read should parse");
+        match cli.command {
+            Commands::MarkRead {
+                chat_id, log_id, ..
+            } => {
+                assert_eq!(chat_id, Some(123));
+                assert_eq!(log_id, Some(456));
+            }
+            other => panic!("expected mark-read, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn board_lists_a_chat_or_shows_one_post() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "board", "42", "-n", "5", "--cursor", "c1"])
+            .expect("board <chat_id> should parse");
+        match cli.command {
+            Commands::Board {
+                action: None,
+                chat_id: Some(42),
+                count: 5,
+                cursor,
+            } => assert_eq!(cursor.as_deref(), Some("c1")),
+            other => panic!("expected board listing, got {other:?}"),
+        }
+
+        let cli = Cli::try_parse_from(["openkakao-rs", "board", "show", "p-9", "--chat", "42"])
+            .expect("board show should parse");
+        match cli.command {
+            Commands::Board {
+                action: Some(BoardCommand::Show { post_id, chat_id }),
+                ..
+            } => {
+                assert_eq!(post_id, "p-9");
+                assert_eq!(chat_id, Some(42));
+            }
+            other => panic!("expected board show, got {other:?}"),
+        }
+        assert!(Cli::try_parse_from(["openkakao-rs", "board"]).is_err());
+        let cli = Cli::try_parse_from(["openkakao-rs", "--read-only", "board", "42"]).unwrap();
+        assert!(cli.command.read_only_violation().is_none());
+    }
+
+    #[test]
+    fn notice_command_parses_and_is_read_only_safe() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "--read-only", "notice", "42", "--history"])
+            .expect("notice --history should parse");
+        assert!(matches!(
+            cli.command,
+            Commands::Notice {
+                chat_id: 42,
+                history: true
+            }
+        ));
+        assert!(cli.command.read_only_violation().is_none());
+    }
+
+    #[test]
+    fn markread_defaults_to_the_newest_message_or_takes_all() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "markread", "123", "--up-to", "456"])
+            .expect("markread --up-to should parse");
+        assert!(matches!(
+            cli.command,
+            Commands::MarkRead {
+                chat_id: Some(123),
+                log_id: None,
+                up_to: Some(456),
+                all: false,
+                ..
+            }
+        ));
+        let cli = Cli::try_parse_from(["openkakao-rs", "markread", "--all"])
+            .expect("markread --all should parse");
         assert_eq!(
-            summary.candidate_matches[0].candidate_getmem_tokens,
-            vec![777]
+            cli.command.pending_confirmation(),
+            Some("marking every unread chat as read")
+        );
+        assert!(Cli::try_parse_from(["openkakao-rs", "markread"]).is_err());
+        assert!(Cli::try_parse_from(["openkakao-rs", "markread", "1", "--all"]).is_err());
+        assert!(
+            Cli::try_parse_from(["openkakao-rs", "markread", "1", "2", "--up-to", "3"]).is_err()
         );
     }
 
     #[test]
-    fn syncmainpf_candidates_include_getmem_token_fields() {
-        let snapshot = LocalFriendGraphSnapshot {
-            user_count: 1,
-            chat_count: 1,
-            failed_chat_ids: Vec::new(),
-            chat_meta: vec![LocalFriendGraphChatMeta {
-                chat_id: 900000000000002,
-                title: "Example".into(),
-                getmem_token: Some(777),
-                member_count: 2,
-            }],
-            entries: vec![LocalFriendGraphEntry {
-                user_id: 100000002,
-                account_id: 200000001,
-                nickname: "Alice".into(),
-                country_iso: "KR".into(),
-                status_message: String::new(),
-                profile_image_url: String::new(),
-                full_profile_image_url: String::new(),
-                original_profile_image_url: String::new(),
-                access_permits: vec!["permit-token".into()],
-                suspicion: String::new(),
-                suspended: false,
-                memorial: false,
-                member_type: 0,
-                chat_ids: vec![900000000000002],
-                chat_titles: vec!["Example".into()],
-                is_self: false,
-                hidden_like: false,
-                hidden_block_type: None,
-            }],
+    fn tui_command_parses_and_is_allowed_read_only() {
+        let cli =
+            Cli::try_parse_from(["openkakao-rs", "--read-only", "tui"]).expect("tui should parse");
+        assert!(matches!(cli.command, Commands::Tui));
+        // Browsing is fine; the TUI disables mark-read itself.
+        assert!(cli.command.read_only_violation().is_none());
+    }
+
+    #[test]
+    fn link_and_open_commands_parse() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "link", "123", "456", "--open"])
+            .expect("link should parse");
+        match cli.command {
+            Commands::Link {
+                chat_id,
+                log_id,
+                open,
+            } => {
+                assert_eq!(chat_id, 123);
+                assert_eq!(log_id, 456);
+                assert!(open);
+            }
+            other => panic!("expected link, got {other:?}"),
+        }
+
+        let cli = Cli::try_parse_from(["openkakao-rs", "open", "openkakao://chat/123/456"])
+            .expect("open should parse");
+        match cli.command {
+            Commands::Open { reference } => assert_eq!(reference, "openkakao://chat/123/456"),
+            other => panic!("expected open, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_and_search_accept_links_flag() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "read", "123", "--links"])
+            .expect("read --links should parse");
+        assert!(matches!(cli.command, Commands::Read { links: true, .. }));
+        let cli = Cli::try_parse_from(["openkakao-rs", "cache-search", "hi", "--links"])
+            .expect("cache-search --links should parse");
+        assert!(matches!(
+            cli.command,
+            Commands::CacheSearch { links: true, .. }
+        ));
+    }
+
+    #[test]
+    fn daemon_runs_by_default_and_has_status() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "daemon"]).expect("daemon should parse");
+        assert!(matches!(cli.command, Commands::Daemon { action: None }));
+        let cli = Cli::try_parse_from(["openkakao-rs", "daemon", "status"])
+            .expect("daemon status should parse");
+        assert!(matches!(
+            cli.command,
+            Commands::Daemon {
+                action: Some(DaemonCommand::Status)
+            }
+        ));
+    }
+
+    #[test]
+    fn logs_tail_takes_follow_and_level() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "logs", "tail", "-f", "--level", "error"])
+            .expect("logs tail should parse");
+        match cli.command {
+            Commands::Logs {
+                action:
+                    LogsCommand::Tail {
+                        lines,
+                        follow,
+                        level,
+                    },
+            } => {
+                assert_eq!(lines, 20);
+                assert!(follow);
+                assert_eq!(level, logs::Level::Error);
+            }
+            other => panic!("expected logs tail, got {other:?}"),
+        }
+        assert!(Cli::try_parse_from(["openkakao-rs", "logs", "tail", "--level", "debug"]).is_err());
+        assert!(Cli::try_parse_from(["openkakao-rs", "logs", "rotate"]).is_ok());
+    }
+
+    #[test]
+    fn friends_export_composes_with_filters() {
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "friends",
+            "--export",
+            "vcf",
+            "--favorites",
+            "-s",
+            "kim",
+        ])
+        .expect("friends --export should parse");
+        match cli.command {
+            Commands::Friends {
+                export, favorites, ..
+            } => {
+                assert_eq!(export, Some(contacts::ContactFormat::Vcf));
+                assert!(favorites);
+            }
+            other => panic!("expected friends, got {other:?}"),
+        }
+        assert!(
+            Cli::try_parse_from(["openkakao-rs", "friends", "--export", "csv", "--local"]).is_err()
+        );
+        assert!(Cli::try_parse_from(["openkakao-rs", "friends", "--export", "xml"]).is_err());
+    }
+
+    #[test]
+    fn search_takes_a_query_and_an_optional_chat() {
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "search",
+            r"\d{3}-\d+",
+            "--regex",
+            "--max-pages",
+            "3",
+        ])
+        .expect("search across chats should parse");
+        let Commands::Search {
+            terms,
+            chat_id,
+            regex: true,
+            max_pages: Some(3),
+            ..
+        } = cli.command
+        else {
+            panic!("expected search --regex --max-pages 3");
         };
+        assert_eq!(
+            search_target(chat_id, &terms).unwrap(),
+            (None, r"\d{3}-\d+")
+        );
 
-        let candidate = build_syncmainpf_candidate(&snapshot, &[], 100000002)
-            .expect("candidate should be built");
+        let legacy = ["42".to_string(), "회의".to_string()];
+        assert_eq!(search_target(None, &legacy).unwrap(), (Some(42), "회의"));
+        assert_eq!(
+            search_target(Some(42), &legacy[1..]).unwrap(),
+            (Some(42), "회의")
+        );
+        assert!(search_target(Some(7), &legacy).is_err());
+        assert!(search_target(None, &["lunch".to_string(), "place".to_string()]).is_err());
+        assert!(
+            Cli::try_parse_from(["openkakao-rs", "search", "x", "--normalize", "--regex"]).is_err()
+        );
+    }
 
-        assert_eq!(candidate.getmem_tokens, vec![777]);
-        assert!(candidate
-            .bodies
-            .iter()
-            .any(|body| body.get("token").and_then(|v| v.as_i64()) == Some(777)));
-        assert!(candidate
-            .bodies
-            .iter()
-            .any(|body| body.get("profileToken").and_then(|v| v.as_i64()) == Some(777)));
-        assert!(candidate
-            .uplinkprof_bodies
-            .iter()
-            .any(|body| body.get("token").and_then(|v| v.as_i64()) == Some(777)));
+    #[test]
+    fn normalize_flags_parse() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "search", "42", "ㅋㅋ", "--normalize"])
+            .expect("search --normalize should parse");
+        assert!(matches!(
+            cli.command,
+            Commands::Search {
+                normalize: true,
+                ..
+            }
+        ));
+        let cli = Cli::try_parse_from(["openkakao-rs", "cache-reindex", "--normalize"])
+            .expect("cache-reindex --normalize should parse");
+        assert!(matches!(
+            cli.command,
+            Commands::CacheReindex { normalize: true }
+        ));
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "files",
+            "42",
+            "--grep",
+            "회의록",
+            "--normalize",
+        ])
+        .expect("files --grep --normalize should parse");
+        assert!(matches!(
+            cli.command,
+            Commands::Files {
+                normalize: true,
+                ..
+            }
+        ));
+        // Normalizing filenames needs something to match.
+        assert!(Cli::try_parse_from(["openkakao-rs", "files", "42", "--normalize"]).is_err());
     }
 
     #[test]
-    fn legacy_loco_read_remains_available() {
-        let cli = Cli::try_parse_from(["openkakao-rs", "loco-read", "123", "--all"])
-            .expect("legacy loco-read should remain available");
+    fn links_scans_five_pages_as_a_table_by_default() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "links", "42"]).expect("links should parse");
+        assert!(matches!(
+            cli.command,
+            Commands::Links {
+                chat_id: 42,
+                pages: 5,
+                enrich: false,
+                ref format,
+            } if format == "table"
+        ));
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "links",
+            "42",
+            "--pages",
+            "0",
+            "--enrich",
+            "--format",
+            "md",
+        ])
+        .expect("links --enrich --format md should parse");
+        assert!(matches!(
+            cli.command,
+            Commands::Links {
+                pages: 0,
+                enrich: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn credentials_prune_is_dry_run_by_default() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "credentials", "prune"])
+            .expect("credentials prune should parse");
+        assert!(matches!(
+            cli.command,
+            Commands::Credentials {
+                action: CredentialsCommand::Prune { apply: false }
+            }
+        ));
+        let cli = Cli::try_parse_from(["openkakao-rs", "credentials", "prune", "--apply"])
+            .expect("credentials prune --apply should parse");
+        assert!(matches!(
+            cli.command,
+            Commands::Credentials {
+                action: CredentialsCommand::Prune { apply: true }
+            }
+        ));
+    }
+
+    #[test]
+    fn fuzzy_requires_search() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "chats", "--search", "김주하", "--fuzzy"])
+            .expect("chats --search --fuzzy should parse");
+        assert!(matches!(cli.command, Commands::Chats { fuzzy: true, .. }));
+        assert!(Cli::try_parse_from(["openkakao-rs", "friends", "--fuzzy"]).is_err());
+    }
+
+    #[test]
+    fn whoami_command_parses() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "whoami", "--offline"])
+            .expect("whoami should parse");
+        assert!(matches!(cli.command, Commands::Whoami { offline: true }));
+    }
+
+    #[test]
+    fn local_scope_flags_precede_the_subcommand() {
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "--local",
+            "--local-credentials",
+            "config",
+            "path",
+        ])
+        .expect("--local config path should parse");
+        assert!(cli.local && cli.local_credentials);
+        assert!(matches!(
+            cli.command,
+            Commands::Config {
+                action: ConfigCommand::Path
+            }
+        ));
+        // `friends --local` keeps its own meaning.
+        let cli = Cli::try_parse_from(["openkakao-rs", "friends", "--local"])
+            .expect("friends --local should parse");
+        assert!(!cli.local);
+        assert!(matches!(cli.command, Commands::Friends { local: true, .. }));
+    }
+
+    #[test]
+    fn read_receipts_flag_parses() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "read", "42", "--receipts"])
+            .expect("read --receipts should parse");
+        assert!(matches!(
+            cli.command,
+            Commands::Read {
+                chat_id: 42,
+                receipts: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn hidden_friend_flags_parse() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "friends", "--hidden-only", "--detail"])
+            .expect("friends --hidden-only should parse");
+        assert!(matches!(
+            cli.command,
+            Commands::Friends {
+                hidden_only: true,
+                detail: true,
+                ..
+            }
+        ));
+
+        let cli = Cli::try_parse_from(["openkakao-rs", "unhide", "--all-hidden", "-y"])
+            .expect("unhide --all-hidden should parse");
+        assert!(matches!(
+            cli.command,
+            Commands::Unhide {
+                user_id: None,
+                all_hidden: true,
+                yes: true,
+                dry_run: false,
+            }
+        ));
+        let cli = Cli::try_parse_from(["openkakao-rs", "unhide", "42"])
+            .expect("unhide <user_id> should parse");
+        assert!(matches!(
+            cli.command,
+            Commands::Unhide {
+                user_id: Some(42),
+                all_hidden: false,
+                ..
+            }
+        ));
+        assert!(Cli::try_parse_from(["openkakao-rs", "unhide"]).is_err());
+        assert!(Cli::try_parse_from(["openkakao-rs", "unhide", "42", "--all-hidden"]).is_err());
+        assert!(Cli::try_parse_from(["openkakao-rs", "unhide", "42", "--dry-run"]).is_err());
+        let cli = Cli::try_parse_from(["openkakao-rs", "unhide", "42", "-y"])
+            .expect("unhide <user_id> -y should parse");
+        assert!(matches!(
+            cli.command,
+            Commands::Unhide {
+                user_id: Some(42),
+                yes: true,
+                ..
+            }
+        ));
+    }
 
+    #[test]
+    fn export_db_defaults_to_no_member_fanout() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "export-db", "account.sqlite"])
+            .expect("export-db should parse");
         match cli.command {
-            Commands::LocoRead { chat_id, all, .. } => {
-                assert_eq!(chat_id, 123);
-                assert!(all);
+            Commands::ExportDb {
+                path,
+                chats_members,
+            } => {
+                assert_eq!(path, "account.sqlite");
+                assert_eq!(chats_members, "none");
             }
-            other => panic!("expected loco-read command, got {other:?}"),
+            other => panic!("expected export-db, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn openchat_join_and_leave_are_side_effects_but_info_is_not() {
+        let info = Cli::try_parse_from([
+            "openkakao-rs",
+            "openchat",
+            "info",
+            "https://open.kakao.com/o/gAbC123",
+        ])
+        .expect("openchat info should parse");
+        assert_eq!(info.command.read_only_violation(), None);
+        assert_eq!(info.command.pending_confirmation(), None);
+
+        let join = Cli::try_parse_from([
+            "openkakao-rs",
+            "openchat",
+            "join",
+            "4567",
+            "--passcode",
+            "1234",
+            "--nickname",
+            "민수",
+        ])
+        .expect("openchat join should parse");
+        assert!(join.command.read_only_violation().is_some());
+        assert!(join.command.pending_confirmation().is_some());
+        match join.command {
+            Commands::Openchat {
+                action:
+                    OpenchatCommand::Join {
+                        link,
+                        passcode,
+                        nickname,
+                        yes: false,
+                    },
+            } => {
+                assert_eq!(link, model::OpenLinkRef::Id(4567));
+                assert_eq!(passcode.as_deref(), Some("1234"));
+                assert_eq!(nickname.as_deref(), Some("민수"));
+            }
+            other => panic!("expected openchat join, got {other:?}"),
+        }
+
+        let leave = Cli::try_parse_from(["openkakao-rs", "openchat", "leave", "99", "-y"])
+            .expect("openchat leave should parse");
+        assert!(leave.command.read_only_violation().is_some());
+        assert_eq!(leave.command.pending_confirmation(), None);
+        assert!(Cli::try_parse_from(["openkakao-rs", "openchat", "info", "not-a-link"]).is_err());
+    }
+
+    #[test]
+    fn takeout_parses_rules_and_page_limit() {
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "takeout",
+            "archive",
+            "--exclude",
+            "type:open",
+            "--max-pages",
+            "5",
+        ])
+        .expect("takeout should parse");
+        assert!(cli.command.is_long_running());
+        match cli.command {
+            Commands::Takeout {
+                dir,
+                include,
+                exclude,
+                max_pages,
+            } => {
+                assert_eq!(dir, std::path::Path::new("archive"));
+                assert!(include.is_empty());
+                assert_eq!(exclude, ["type:open"]);
+                assert_eq!(max_pages, 5);
+            }
+            other => panic!("expected takeout, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn export_privacy_flags_parse() {
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "export",
+            "42",
+            "--anonymize",
+            "--no-media",
+            "--redact",
+            "secret-\\d+",
+            "--redact",
+            "사내",
+            "--key-file",
+            "key.json",
+        ])
+        .expect("export privacy flags should parse");
+        match cli.command {
+            Commands::Export {
+                anonymize,
+                no_media,
+                redact,
+                key_file,
+                ..
+            } => {
+                assert!(anonymize && no_media);
+                assert_eq!(redact, ["secret-\\d+", "사내"]);
+                assert_eq!(key_file.as_deref(), Some("key.json"));
+            }
+            other => panic!("expected export, got {other:?}"),
+        }
+        // The key file only makes sense for an anonymized export.
+        assert!(
+            Cli::try_parse_from(["openkakao-rs", "export", "42", "--key-file", "key.json"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn export_embed_media_parses_and_conflicts_with_no_media() {
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "export",
+            "42",
+            "--format",
+            "mbox",
+            "--embed-media",
+        ])
+        .expect("export --embed-media should parse");
+        match cli.command {
+            Commands::Export {
+                format,
+                embed_media,
+                ..
+            } => {
+                assert_eq!(format, "mbox");
+                assert!(embed_media);
+            }
+            other => panic!("expected export, got {other:?}"),
         }
+        assert!(Cli::try_parse_from([
+            "openkakao-rs",
+            "export",
+            "42",
+            "--embed-media",
+            "--no-media"
+        ])
+        .is_err());
     }
 
     #[test]
-    fn legacy_loco_chats_remains_available() {
-        let cli = Cli::try_parse_from(["openkakao-rs", "loco-chats", "--all"])
-            .expect("legacy loco-chats should remain available");
-
+    fn export_append_and_max_pages_parse() {
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "export",
+            "42",
+            "--format",
+            "jsonl",
+            "--out",
+            "chat.jsonl",
+            "--append",
+            "--max-pages",
+            "5",
+        ])
+        .expect("export --append should parse");
+        assert_eq!(cli.out.as_deref(), Some(std::path::Path::new("chat.jsonl")));
         match cli.command {
-            Commands::LocoChats { show_all } => {
-                assert!(show_all);
+            Commands::Export {
+                format,
+                max_pages,
+                append,
+                ..
+            } => {
+                assert_eq!(format, "jsonl");
+                assert_eq!(max_pages, 5);
+                assert!(append);
             }
-            other => panic!("expected loco-chats command, got {other:?}"),
+            other => panic!("expected export, got {other:?}"),
         }
+        let cli = Cli::try_parse_from(["openkakao-rs", "export", "42"]).expect("export parses");
+        assert!(matches!(
+            cli.command,
+            Commands::Export {
+                max_pages: 100,
+                append: false,
+                ..
+            }
+        ));
+        // Pseudonyms are per run, so they would not line up across appends.
+        assert!(
+            Cli::try_parse_from(["openkakao-rs", "export", "42", "--append", "--anonymize"])
+                .is_err()
+        );
     }
 
     #[test]
-    fn legacy_loco_members_remains_available() {
-        let cli = Cli::try_parse_from(["openkakao-rs", "loco-members", "123"])
-            .expect("legacy loco-members should remain available");
-
+    fn members_activity_flags_parse() {
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "members",
+            "42",
+            "--activity",
+            "--inactive-days",
+            "90",
+            "--export-csv",
+            "lurkers.csv",
+        ])
+        .expect("members --activity should parse");
         match cli.command {
-            Commands::LocoMembers { chat_id } => assert_eq!(chat_id, 123),
-            other => panic!("expected loco-members command, got {other:?}"),
+            Commands::Members {
+                activity,
+                inactive_days,
+                export_csv,
+                ..
+            } => {
+                assert!(activity);
+                assert_eq!(inactive_days, Some(90));
+                assert_eq!(export_csv.as_deref(), Some("lurkers.csv"));
+            }
+            other => panic!("expected members, got {other:?}"),
         }
+        assert!(
+            Cli::try_parse_from(["openkakao-rs", "members", "42", "--inactive-days", "90"])
+                .is_err()
+        );
+        assert!(Cli::try_parse_from([
+            "openkakao-rs",
+            "members",
+            "42",
+            "--activity",
+            "--merge",
+            "r.csv"
+        ])
+        .is_err());
     }
 
     #[test]
-    fn legacy_loco_chatinfo_remains_available() {
-        let cli = Cli::try_parse_from(["openkakao-rs", "loco-chatinfo", "123"])
-            .expect("legacy loco-chatinfo should remain available");
-
+    fn export_verify_and_no_manifest_parse() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "export", "verify", "archive"])
+            .expect("export verify should parse");
         match cli.command {
-            Commands::LocoChatinfo { chat_id } => assert_eq!(chat_id, 123),
-            other => panic!("expected loco-chatinfo command, got {other:?}"),
+            Commands::Export {
+                action: Some(ExportCommand::Verify { dir }),
+                ..
+            } => assert_eq!(dir, std::path::Path::new("archive")),
+            other => panic!("expected export verify, got {other:?}"),
         }
+        let cli = Cli::try_parse_from(["openkakao-rs", "export", "42", "--no-manifest"])
+            .expect("export --no-manifest should parse");
+        assert!(matches!(
+            cli.command,
+            Commands::Export {
+                action: None,
+                chat_id: Some(42),
+                no_manifest: true,
+                ..
+            }
+        ));
+        assert!(Cli::try_parse_from(["openkakao-rs", "export"]).is_err());
     }
 
     #[test]
-    fn legacy_loco_probe_remains_available() {
-        let cli = Cli::try_parse_from(["openkakao-rs", "loco-probe", "BLSYNC"])
-            .expect("legacy loco-probe should remain available");
-
-        match cli.command {
-            Commands::LocoProbe { method, body } => {
-                assert_eq!(method, "BLSYNC");
-                assert!(body.is_none());
-            }
-            other => panic!("expected loco-probe command, got {other:?}"),
-        }
+    fn fields_flag_is_global_and_needs_json() {
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "chats",
+            "--json",
+            "--fields",
+            "chat_id,title",
+        ])
+        .expect("--fields should parse after the subcommand");
+        assert_eq!(cli.fields.as_deref(), Some("chat_id,title"));
+        assert!(Cli::try_parse_from(["openkakao-rs", "chats", "--fields", "title"]).is_err());
     }
 
     #[test]
-    fn webhook_header_requires_name_and_value() {
-        assert_eq!(
-            parse_webhook_header("Authorization: Bearer test").unwrap(),
-            ("Authorization".to_string(), "Bearer test".to_string())
-        );
-        assert!(parse_webhook_header("MissingSeparator").is_err());
-        assert!(parse_webhook_header("Header: ").is_err());
+    fn theme_flag_is_global() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "friends", "--theme", "light"])
+            .expect("--theme should parse after the subcommand");
+        assert_eq!(cli.theme.as_deref(), Some("light"));
+    }
+
+    fn os_args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
     }
 
     #[test]
-    fn webhook_signature_is_stable_for_known_input() {
-        let signature = build_webhook_signature("secret", "1700000000", br#"{"ok":true}"#).unwrap();
-        assert_eq!(
-            signature,
-            "sha256=c1afc7c2df3db0690d7d75954610ed1a1d959ce96355ccb8c0a8bc09fd0cfc27"
-        );
+    fn memo_flag_takes_the_chat_id_position() {
+        let args = os_args(&[
+            "openkakao-rs",
+            "--out",
+            "x.txt",
+            "read",
+            "-n",
+            "5",
+            "--memo",
+        ]);
+        let memo = MemoShorthand::find(&args)
+            .unwrap()
+            .expect("--memo should apply");
+        let cli = Cli::try_parse_from(memo.with_chat_id(42)).expect("rewritten args should parse");
+        assert!(matches!(
+            cli.command,
+            Commands::Read {
+                chat_id: 42,
+                count: 5,
+                ..
+            }
+        ));
+
+        let args = os_args(&["openkakao-rs", "--memo", "mark-read", "7"]);
+        let memo = MemoShorthand::find(&args).unwrap().unwrap();
+        let cli = Cli::try_parse_from(memo.with_chat_id(42)).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::MarkRead {
+                chat_id: Some(42),
+                log_id: Some(7),
+                ..
+            }
+        ));
     }
 
     #[test]
-    fn webhook_url_requires_https_or_loopback_http() {
-        assert!(validate_webhook_url("https://example.com/hook", false).is_ok());
-        assert!(validate_webhook_url("http://localhost:3000/hook", false).is_ok());
-        assert!(validate_webhook_url("http://127.0.0.1:4000/hook", false).is_ok());
-        assert!(validate_webhook_url("http://example.com/hook", false).is_err());
-        assert!(validate_webhook_url("http://example.com/hook", true).is_ok());
+    fn memo_flag_is_left_alone_or_rejected_outside_chat_commands() {
+        let chats = os_args(&["openkakao-rs", "chats", "--memo"]);
+        assert!(MemoShorthand::find(&chats).unwrap().is_none());
+        let cli = Cli::try_parse_from(&chats).unwrap();
+        assert!(matches!(cli.command, Commands::Chats { memo: true, .. }));
+
+        let message = os_args(&["openkakao-rs", "send-me", "--", "--memo"]);
+        assert!(MemoShorthand::find(&message).unwrap().is_none());
+
+        let err = MemoShorthand::find(&os_args(&["openkakao-rs", "friends", "--memo"]))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("`friends` does not take one"), "{err}");
     }
 
     #[test]
-    fn outbound_message_must_not_be_blank() {
-        assert!(validate_outbound_message("hello").is_ok());
-        assert!(validate_outbound_message("   ").is_err());
+    fn memo_read_parses() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "memo", "read", "-n", "10"])
+            .expect("memo read should parse");
+        assert!(matches!(
+            cli.command,
+            Commands::Memo {
+                action: MemoCommand::Read { count: 10, .. }
+            }
+        ));
     }
 
     #[test]
-    fn stats_command_is_available() {
+    fn download_without_log_id_walks_the_whole_chat() {
         let cli = Cli::try_parse_from([
             "openkakao-rs",
-            "stats",
-            "123",
-            "--limit",
-            "500",
+            "download",
+            "1",
+            "--types",
+            "photo,file",
             "--since",
-            "2025-01-01",
+            "2026-01-01",
+            "--concurrency",
+            "6",
         ])
-        .expect("stats should accept limit and since");
-
+        .expect("bulk download should parse");
         match cli.command {
-            Commands::Stats {
-                chat_id,
-                limit,
+            Commands::Download {
+                log_id: None,
+                log_id_flag: None,
+                types,
                 since,
+                concurrency,
+                ..
             } => {
-                assert_eq!(chat_id, 123);
-                assert_eq!(limit, Some(500));
-                assert_eq!(since.as_deref(), Some("2025-01-01"));
+                assert_eq!(types, ["photo", "file"]);
+                assert_eq!(since.as_deref(), Some("2026-01-01"));
+                assert_eq!(concurrency, 6);
             }
-            other => panic!("expected stats command, got {other:?}"),
+            other => panic!("expected bulk download, got {other:?}"),
         }
     }
 
     #[test]
-    fn openkakao_error_loco_status_display() {
-        use crate::error::OpenKakaoError;
-        let err = OpenKakaoError::loco("SYNCMSG", -300);
-        assert!(err.to_string().contains("SYNCMSG"));
-        assert!(err.to_string().contains("-300"));
-        assert!(err.is_retryable());
-    }
-
-    #[test]
-    fn openkakao_error_token_expired_from_950() {
-        use crate::error::OpenKakaoError;
-        let err = OpenKakaoError::loco("LOGINLIST", -950);
-        assert!(matches!(err, OpenKakaoError::TokenExpired));
-        assert!(err.is_retryable());
+    fn download_accepts_log_id_flag_or_positional() {
+        for args in [
+            &["openkakao-rs", "download", "1", "99"][..],
+            &["openkakao-rs", "download", "1", "--log-id", "99"][..],
+        ] {
+            let cli = Cli::try_parse_from(args).expect("download should parse");
+            match cli.command {
+                Commands::Download {
+                    log_id,
+                    log_id_flag,
+                    ..
+                } => assert_eq!(log_id.or(log_id_flag), Some(99)),
+                other => panic!("expected download, got {other:?}"),
+            }
+        }
+        assert!(
+            Cli::try_parse_from(["openkakao-rs", "download", "1", "2", "--types", "photo"])
+                .is_err()
+        );
+        assert!(
+            Cli::try_parse_from(["openkakao-rs", "download", "1", "2", "--log-id", "3"]).is_err()
+        );
     }
 
     #[test]
-    fn openkakao_error_non_retryable_status() {
-        use crate::error::OpenKakaoError;
-        let err = OpenKakaoError::loco("WRITE", -203);
-        assert!(!err.is_retryable());
+    fn download_uses_the_media_cache_only_when_asked() {
+        let cached = |args: &[&str]| match Cli::try_parse_from(args)
+            .expect("download should parse")
+            .command
+        {
+            Commands::Download { cache, .. } => cache,
+            other => panic!("expected download, got {other:?}"),
+        };
+        assert!(!cached(&["openkakao-rs", "download", "1", "99"]));
+        assert!(cached(&["openkakao-rs", "download", "1", "99", "--cache"]));
+        assert!(Cli::try_parse_from([
+            "openkakao-rs",
+            "download",
+            "1",
+            "--types",
+            "photo",
+            "--cache"
+        ])
+        .is_err());
     }
 
     #[test]
-    fn check_loco_status_passes_on_zero() {
-        use crate::loco_helpers::check_loco_status;
-        let packet = crate::loco::packet::LocoPacket {
-            packet_id: 1,
-            status_code: 0,
-            method: "TEST".into(),
-            body_type: 0,
-            body: bson::doc! { "status": 0_i32 },
-        };
-        assert!(check_loco_status("TEST", &packet).is_ok());
+    fn report_command_parses() {
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "report",
+            "--chat",
+            "42",
+            "--period",
+            "month",
+            "--format",
+            "md",
+            "--send-to",
+            "7",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::Report {
+                chat_id,
+                period,
+                format,
+                send_to,
+                yes,
+            } => {
+                assert_eq!(chat_id, 42);
+                assert_eq!(period, "month");
+                assert_eq!(format, "md");
+                assert_eq!(send_to, Some(7));
+                assert!(!yes);
+            }
+            _ => panic!("expected report"),
+        }
     }
 
     #[test]
-    fn check_loco_status_fails_on_nonzero() {
-        use crate::loco_helpers::check_loco_status;
-        let packet = crate::loco::packet::LocoPacket {
-            packet_id: 1,
-            status_code: 0,
-            method: "SYNCMSG".into(),
-            body_type: 0,
-            body: bson::doc! { "status": -300_i32 },
-        };
-        let err = check_loco_status("SYNCMSG", &packet).unwrap_err();
-        assert!(err.to_string().contains("SYNCMSG"));
-        assert!(err.to_string().contains("-300"));
+    fn read_context_parses_and_requires_context_for_archive() {
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "read",
+            "42",
+            "--context",
+            "9001",
+            "-n",
+            "20",
+            "--from-archive",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::Read {
+                chat_id,
+                count,
+                context,
+                from_archive,
+                ..
+            } => {
+                assert_eq!(chat_id, 42);
+                assert_eq!(count, 20);
+                assert_eq!(context, Some(9001));
+                assert!(from_archive);
+            }
+            _ => panic!("expected read"),
+        }
+        assert!(Cli::try_parse_from(["openkakao-rs", "read", "42", "--from-archive"]).is_err());
+        assert!(
+            Cli::try_parse_from(["openkakao-rs", "read", "42", "--context", "1", "--all"]).is_err()
+        );
     }
 
     #[test]
-    fn watch_capture_flag_parses() {
-        let cli = Cli::try_parse_from(["openkakao-rs", "watch", "--capture"])
-            .expect("watch should accept --capture");
+    fn watch_notify_backends_parse() {
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "watch",
+            "--notify",
+            "desktop",
+            "--notify",
+            "telegram",
+        ])
+        .expect("watch should accept repeated --notify");
 
         match cli.command {
-            Commands::Watch { capture, .. } => {
-                assert!(capture);
+            Commands::Watch { notify, .. } => {
+                assert_eq!(notify, ["desktop", "telegram"]);
+                assert_eq!(
+                    notify::NotifyBackend::parse(&notify[1]).unwrap(),
+                    notify::NotifyBackend::Telegram
+                );
             }
             other => panic!("expected watch command, got {other:?}"),
         }
     }
 
     #[test]
-    fn probe_capture_pushes_flag_parses() {
-        let cli = Cli::try_parse_from(["openkakao-rs", "probe", "PING", "--capture-pushes"])
-            .expect("probe should accept --capture-pushes");
+    fn watch_bell_and_title_parse() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "watch", "--bell", "--title"])
+            .expect("watch should accept --bell and --title");
+        match cli.command {
+            Commands::Watch { bell, title, .. } => assert!(bell && title),
+            other => panic!("expected watch command, got {other:?}"),
+        }
+    }
 
+    #[test]
+    fn watch_polls_a_chat_or_all_chats_over_rest() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "watch", "42", "--interval", "2"])
+            .expect("watch <chat_id> should parse");
         match cli.command {
-            Commands::Probe {
-                method,
-                capture_pushes,
+            Commands::Watch {
+                poll_chat_id: Some(42),
+                all_chats: false,
+                interval: 2,
+                chat_id: None,
                 ..
-            } => {
-                assert_eq!(method, "PING");
-                assert!(capture_pushes);
-            }
-            other => panic!("expected probe command, got {other:?}"),
+            } => {}
+            other => panic!("expected polling watch, got {other:?}"),
+        }
+        let cli = Cli::try_parse_from(["openkakao-rs", "watch", "--all-chats", "--exec", "cat"])
+            .expect("watch --all-chats should parse");
+        match cli.command {
+            Commands::Watch {
+                poll_chat_id: None,
+                all_chats: true,
+                interval: 5,
+                exec: Some(exec),
+                ..
+            } => assert_eq!(exec, "cat"),
+            other => panic!("expected polling watch, got {other:?}"),
+        }
+        for args in [
+            &["openkakao-rs", "watch", "42", "--all-chats"][..],
+            &["openkakao-rs", "watch", "42", "--chat-id", "42"],
+            &["openkakao-rs", "watch", "42", "--interval", "0"],
+        ] {
+            assert!(Cli::try_parse_from(args).is_err(), "{args:?}");
         }
     }
 
     #[test]
-    fn delete_command_parses() {
-        let cli = Cli::try_parse_from(["openkakao-rs", "delete", "123", "456", "--force", "-y"])
-            .expect("delete should parse");
-        match cli.command {
-            Commands::Delete {
-                chat_id,
-                log_id,
-                force,
-                yes,
-                dry_run,
-            } => {
-                assert_eq!(chat_id, 123);
-                assert_eq!(log_id, 456);
-                assert!(force);
-                assert!(yes);
-                assert!(!dry_run);
+    fn watch_dedup_window_parses() {
+        for (args, expected) in [
+            (&["openkakao-rs", "watch"][..], dedup::DEFAULT_WINDOW),
+            (&["openkakao-rs", "watch", "--dedup-window", "0"][..], 0),
+        ] {
+            match Cli::try_parse_from(args).unwrap().command {
+                Commands::Watch { dedup_window, .. } => assert_eq!(dedup_window, expected),
+                other => panic!("expected watch command, got {other:?}"),
             }
-            other => panic!("expected delete, got {other:?}"),
         }
     }
 
     #[test]
-    fn mark_read_command_parses() {
-        let cli = Cli::try_parse_from(["openkakao-rs", "mark-read", "123", "456"])
-            .expect("mark-read should parse");
+    fn scrap_cache_flags_parse() {
+        let cli = Cli::try_parse_from([
+            "openkakao-rs",
+            "scrap",
+            "https://example.com",
+            "--cached-only",
+        ])
+        .expect("scrap should accept --cached-only");
         match cli.command {
-            Commands::MarkRead { chat_id, log_id } => {
-                assert_eq!(chat_id, 123);
-                assert_eq!(log_id, 456);
+            Commands::Scrap {
+                cached_only, ttl, ..
+            } => {
+                assert!(cached_only);
+                assert_eq!(ttl, scrap_cache::DEFAULT_TTL_SECS);
             }
-            other => panic!("expected mark-read, got {other:?}"),
+            other => panic!("expected scrap command, got {other:?}"),
         }
+
+        assert!(Cli::try_parse_from([
+            "openkakao-rs",
+            "scrap",
+            "https://example.com",
+            "--no-cache",
+            "--refresh",
+        ])
+        .is_err());
+
+        let cli = Cli::try_parse_from(["openkakao-rs", "cache", "clear", "--scrap"])
+            .expect("cache clear should accept --scrap");
+        assert!(matches!(
+            cli.command,
+            Commands::Cache {
+                action: Some(CacheCommand::Clear { scrap: true, .. }),
+                ..
+            }
+        ));
+        assert!(
+            Cli::try_parse_from(["openkakao-rs", "cache", "clear", "--media", "--scrap"]).is_err()
+        );
+        assert!(Cli::try_parse_from(["openkakao-rs", "cache", "clear", "7", "--media"]).is_err());
+        let media = Cli::try_parse_from(["openkakao-rs", "cache", "clear", "--media"])
+            .expect("cache clear should accept --media");
+        assert_eq!(media.command.pending_confirmation(), None);
+    }
+
+    #[test]
+    fn health_command_parses() {
+        let cli = Cli::try_parse_from(["openkakao-rs", "health", "--check"])
+            .expect("health should accept --check");
+        assert!(matches!(cli.command, Commands::Health { check: true }));
     }
 
     #[test]
@@ -0,0 +1,426 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+use base64::Engine;
+
+/// Domain of the synthetic author addresses and Message-IDs; `.invalid` is
+/// reserved, so nothing here can reach a real mailbox.
+pub const MAIL_DOMAIN: &str = "kakao.invalid";
+
+/// Longest encoded word RFC 2047 allows.
+const MAX_ENCODED_WORD: usize = 75;
+
+/// Base64 line length for MIME bodies.
+const BASE64_LINE: usize = 76;
+
+/// A file attached to one exported message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MailAttachment {
+    pub filename: String,
+    pub content_type: &'static str,
+    pub data: Vec<u8>,
+}
+
+impl MailAttachment {
+    pub fn new(filename: String, data: Vec<u8>) -> Self {
+        let content_type = content_type_for(&filename);
+        Self {
+            filename,
+            content_type,
+            data,
+        }
+    }
+}
+
+/// MIME type from the file extension; `application/octet-stream` when
+/// unknown.
+pub fn content_type_for(filename: &str) -> &'static str {
+    let ext = filename
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "heic" => "image/heic",
+        "mp4" => "video/mp4",
+        "mov" => "video/quicktime",
+        "m4a" => "audio/mp4",
+        "pdf" => "application/pdf",
+        "txt" => "text/plain",
+        "zip" => "application/zip",
+        _ => "application/octet-stream",
+    }
+}
+
+/// One chat message as a mail.
+#[derive(Debug, Clone)]
+pub struct MailMessage<'a> {
+    pub chat_id: i64,
+    pub log_id: i64,
+    /// Local part of the synthetic From address, normally the user id.
+    pub author_local: String,
+    pub author_name: &'a str,
+    pub send_at: i64,
+    pub subject: &'a str,
+    pub body: &'a str,
+    /// Log ids this message replies to, oldest ancestor first; the last one
+    /// is the direct parent.
+    pub references: Vec<i64>,
+    pub attachments: &'a [MailAttachment],
+}
+
+/// `<log_id.chat_id@kakao.invalid>`: stable across exports, so a re-export
+/// threads with an earlier one.
+pub fn message_id(chat_id: i64, log_id: i64) -> String {
+    format!("<{log_id}.{chat_id}@{MAIL_DOMAIN}>")
+}
+
+/// The log id a reply message quotes (`src_logId` in its attachment).
+pub fn replied_log_id(attachment: &str) -> Option<i64> {
+    let value: serde_json::Value = serde_json::from_str(attachment).ok()?;
+    value
+        .get("src_logId")
+        .and_then(|v| v.as_i64().or_else(|| v.as_str()?.parse().ok()))
+        .filter(|&id| id > 0)
+}
+
+/// Ancestors of `log_id` among `parents` (log id -> replied-to log id),
+/// oldest first. Stops at a message the export does not contain, keeping
+/// that id as the oldest reference, and at cycles.
+pub fn reference_chain(log_id: i64, parents: &HashMap<i64, i64>) -> Vec<i64> {
+    let mut chain = Vec::new();
+    let mut current = log_id;
+    while let Some(&parent) = parents.get(&current) {
+        if parent == log_id || chain.contains(&parent) {
+            break;
+        }
+        chain.push(parent);
+        current = parent;
+    }
+    chain.reverse();
+    chain
+}
+
+/// A header value as-is when it is printable ASCII, otherwise as RFC 2047
+/// `B` encoded words of UTF-8, each at most 75 characters and never
+/// splitting a character, folded onto continuation lines.
+pub fn encode_header_text(text: &str) -> String {
+    let plain = text.chars().all(|c| c.is_ascii() && !c.is_ascii_control()) && !text.contains("=?");
+    if plain {
+        return text.to_string();
+    }
+    // 45 bytes encode to 60 characters, within 75 with the 12 of framing.
+    let max_bytes = (MAX_ENCODED_WORD - "=?UTF-8?B??=".len()) / 4 * 3;
+    let mut words = Vec::new();
+    let mut chunk = String::new();
+    for c in text.chars() {
+        if chunk.len() + c.len_utf8() > max_bytes {
+            words.push(encoded_word(&chunk));
+            chunk.clear();
+        }
+        chunk.push(c);
+    }
+    if !chunk.is_empty() {
+        words.push(encoded_word(&chunk));
+    }
+    words.join("\n ")
+}
+
+fn encoded_word(chunk: &str) -> String {
+    format!(
+        "=?UTF-8?B?{}?=",
+        base64::engine::general_purpose::STANDARD.encode(chunk)
+    )
+}
+
+/// `Name <local@kakao.invalid>`, with the name quoted or encoded as the
+/// header syntax needs.
+pub fn mailbox(name: &str, local: &str) -> String {
+    let name = name.trim();
+    let address = format!("{local}@{MAIL_DOMAIN}");
+    if name.is_empty() {
+        return format!("<{address}>");
+    }
+    let is_atom_text = name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == ' ' || "!#$%&'*+-/=?^_`{|}~".contains(c));
+    let display = if is_atom_text {
+        name.to_string()
+    } else if name.is_ascii() {
+        format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        encode_header_text(name)
+    };
+    format!("{display} <{address}>")
+}
+
+/// `filename` parameter for Content-Disposition: quoted when ASCII,
+/// RFC 2231 percent-encoded UTF-8 otherwise.
+fn filename_param(filename: &str) -> String {
+    if filename.is_ascii() && !filename.contains(['"', '\\']) {
+        format!("filename=\"{filename}\"")
+    } else {
+        format!("filename*=UTF-8''{}", urlencoding::encode(filename))
+    }
+}
+
+/// Quote body lines that would read as an mbox separator (mboxrd): any
+/// run of `>` followed by `From ` gets one more `>`.
+fn escape_from_lines(body: &str) -> String {
+    let mut out = String::with_capacity(body.len());
+    for line in body.split_inclusive('\n') {
+        if line.trim_start_matches('>').starts_with("From ") {
+            out.push('>');
+        }
+        out.push_str(line);
+    }
+    out
+}
+
+fn base64_lines(data: &[u8]) -> String {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(data);
+    let mut out = String::with_capacity(encoded.len() + encoded.len() / BASE64_LINE + 1);
+    for line in encoded.as_bytes().chunks(BASE64_LINE) {
+        out.push_str(std::str::from_utf8(line).unwrap_or_default());
+        out.push('\n');
+    }
+    out
+}
+
+fn text_body(body: &str) -> String {
+    let mut text = escape_from_lines(&body.replace("\r\n", "\n"));
+    if !text.ends_with('\n') {
+        text.push('\n');
+    }
+    text
+}
+
+/// One message in mbox form: the `From ` separator line, RFC 5322 headers
+/// and a UTF-8 text body, as `multipart/mixed` when there are attachments.
+/// Ends with the blank line that separates it from the next message.
+pub fn render_message(mail: &MailMessage) -> String {
+    let sent = chrono::DateTime::from_timestamp(mail.send_at, 0).unwrap_or_default();
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "From {}@{} {}",
+        mail.author_local,
+        MAIL_DOMAIN,
+        sent.format("%a %b %e %H:%M:%S %Y")
+    );
+    let _ = writeln!(
+        out,
+        "From: {}",
+        mailbox(mail.author_name, &mail.author_local)
+    );
+    let _ = writeln!(out, "Date: {}", sent.to_rfc2822());
+    let subject = if mail.references.is_empty() {
+        mail.subject.to_string()
+    } else {
+        format!("Re: {}", mail.subject)
+    };
+    let _ = writeln!(out, "Subject: {}", encode_header_text(&subject));
+    let _ = writeln!(out, "Message-ID: {}", message_id(mail.chat_id, mail.log_id));
+    if let Some(&parent) = mail.references.last() {
+        let _ = writeln!(out, "In-Reply-To: {}", message_id(mail.chat_id, parent));
+        let references: Vec<String> = mail
+            .references
+            .iter()
+            .map(|&id| message_id(mail.chat_id, id))
+            .collect();
+        let _ = writeln!(out, "References: {}", references.join("\n "));
+    }
+    let _ = writeln!(out, "X-KakaoTalk-Chat-Id: {}", mail.chat_id);
+    let _ = writeln!(out, "X-KakaoTalk-Log-Id: {}", mail.log_id);
+    out.push_str("MIME-Version: 1.0\n");
+
+    let text_headers = "Content-Type: text/plain; charset=UTF-8\nContent-Transfer-Encoding: 8bit\n";
+    if mail.attachments.is_empty() {
+        out.push_str(text_headers);
+        out.push('\n');
+        out.push_str(&text_body(mail.body));
+    } else {
+        let boundary = format!("openkakao-{}-{}", mail.chat_id, mail.log_id);
+        let _ = writeln!(
+            out,
+            "Content-Type: multipart/mixed; boundary=\"{boundary}\"\n"
+        );
+        let _ = writeln!(out, "--{boundary}");
+        out.push_str(text_headers);
+        out.push('\n');
+        out.push_str(&text_body(mail.body));
+        for attachment in mail.attachments {
+            let _ = writeln!(out, "--{boundary}");
+            let _ = writeln!(
+                out,
+                "Content-Type: {}; name=\"{}\"",
+                attachment.content_type,
+                encode_header_text(&attachment.filename).replace('"', "'")
+            );
+            let _ = writeln!(
+                out,
+                "Content-Disposition: attachment; {}",
+                filename_param(&attachment.filename)
+            );
+            out.push_str("Content-Transfer-Encoding: base64\n\n");
+            out.push_str(&base64_lines(&attachment.data));
+        }
+        let _ = writeln!(out, "--{boundary}--");
+    }
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_words(header: &str) -> String {
+        header
+            .split_whitespace()
+            .map(|word| {
+                let inner = word
+                    .strip_prefix("=?UTF-8?B?")
+                    .and_then(|w| w.strip_suffix("?="))
+                    .expect("encoded word");
+                String::from_utf8(
+                    base64::engine::general_purpose::STANDARD
+                        .decode(inner)
+                        .unwrap(),
+                )
+                .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ascii_headers_stay_readable() {
+        assert_eq!(encode_header_text("Team chat"), "Team chat");
+        assert_eq!(mailbox("Kim", "42"), "Kim <42@kakao.invalid>");
+        assert_eq!(
+            mailbox("Kim, Minsu (PM)", "42"),
+            "\"Kim, Minsu (PM)\" <42@kakao.invalid>"
+        );
+        assert_eq!(mailbox("  ", "7"), "<7@kakao.invalid>");
+        // Text that looks like an encoded word must be encoded itself.
+        assert!(encode_header_text("=?x?=").starts_with("=?UTF-8?B?"));
+    }
+
+    #[test]
+    fn korean_names_are_rfc2047_encoded() {
+        assert_eq!(
+            mailbox("김민수", "42"),
+            "=?UTF-8?B?6rmA66+87IiY?= <42@kakao.invalid>"
+        );
+        let long = "우리 가족 단톡방 2024년 여름휴가 계획 및 준비물 정리";
+        let encoded = encode_header_text(long);
+        for word in encoded.split("\n ") {
+            assert!(word.len() <= MAX_ENCODED_WORD, "{word} too long");
+            assert!(word.starts_with("=?UTF-8?B?") && word.ends_with("?="));
+        }
+        assert!(encoded.contains("\n "), "long text is folded");
+        // No character is split across words, so every word decodes alone.
+        assert_eq!(decode_words(&encoded), long);
+    }
+
+    #[test]
+    fn replies_reference_their_ancestors() {
+        assert_eq!(
+            replied_log_id(r#"{"src_logId":123,"src_userId":7}"#),
+            Some(123)
+        );
+        assert_eq!(replied_log_id(r#"{"src_logId":"456"}"#), Some(456));
+        assert_eq!(replied_log_id(r#"{"name":"a.pdf"}"#), None);
+        assert_eq!(replied_log_id("not json"), None);
+
+        let parents = HashMap::from([(30, 20), (20, 10), (40, 5), (50, 60), (60, 50)]);
+        assert_eq!(reference_chain(30, &parents), [10, 20]);
+        assert_eq!(reference_chain(40, &parents), [5]);
+        assert!(reference_chain(10, &parents).is_empty());
+        // A cycle ends the chain instead of looping.
+        assert_eq!(reference_chain(50, &parents), [60]);
+    }
+
+    fn mail<'a>(body: &'a str, attachments: &'a [MailAttachment]) -> MailMessage<'a> {
+        MailMessage {
+            chat_id: 9,
+            log_id: 30,
+            author_local: "42".into(),
+            author_name: "김민수",
+            send_at: 1_700_000_000,
+            subject: "가족",
+            body,
+            references: vec![10, 20],
+            attachments,
+        }
+    }
+
+    #[test]
+    fn text_message_renders_headers_and_escaped_body() {
+        let rendered = render_message(&mail("안녕\nFrom here on\n>From quoted", &[]));
+        let expected = "\
+From 42@kakao.invalid Tue Nov 14 22:13:20 2023
+From: =?UTF-8?B?6rmA66+87IiY?= <42@kakao.invalid>
+Date: Tue, 14 Nov 2023 22:13:20 +0000
+Subject: =?UTF-8?B?UmU6IOqwgOyhsQ==?=
+Message-ID: <30.9@kakao.invalid>
+In-Reply-To: <20.9@kakao.invalid>
+References: <10.9@kakao.invalid>
+ <20.9@kakao.invalid>
+X-KakaoTalk-Chat-Id: 9
+X-KakaoTalk-Log-Id: 30
+MIME-Version: 1.0
+Content-Type: text/plain; charset=UTF-8
+Content-Transfer-Encoding: 8bit
+
+안녕
+>From here on
+>>From quoted
+
+";
+        assert_eq!(rendered, expected);
+    }
+
+    #[test]
+    fn attachments_become_base64_mime_parts() {
+        let attachments = [
+            MailAttachment::new("photo.JPG".into(), vec![0xff; 100]),
+            MailAttachment::new("회의록.pdf".into(), b"%PDF".to_vec()),
+        ];
+        let rendered = render_message(&mail("see attached", &attachments));
+        let boundary = "openkakao-9-30";
+        assert!(rendered.contains(&format!(
+            "Content-Type: multipart/mixed; boundary=\"{boundary}\"\n\n--{boundary}\n"
+        )));
+        assert!(rendered.contains("Content-Type: image/jpeg; name=\"photo.JPG\"\n"));
+        assert!(rendered.contains("Content-Disposition: attachment; filename=\"photo.JPG\"\n"));
+        assert!(rendered.contains(
+            "Content-Disposition: attachment; filename*=UTF-8''%ED%9A%8C%EC%9D%98%EB%A1%9D.pdf\n"
+        ));
+        assert!(rendered.contains("Content-Type: application/pdf; name=\"=?UTF-8?B?"));
+        assert!(rendered.ends_with(&format!("--{boundary}--\n\n")));
+
+        // The photo's base64 is wrapped at 76 columns and decodes back.
+        let start = rendered.find("filename=\"photo.JPG\"").unwrap();
+        let part = &rendered[start..];
+        let body_start = part.find("\n\n").unwrap() + 2;
+        let body_end = part.find(&format!("--{boundary}")).unwrap();
+        let lines: Vec<&str> = part[body_start..body_end].lines().collect();
+        assert!(lines.iter().all(|l| l.len() <= BASE64_LINE));
+        let decoded = base64::engine::general_purpose::STANDARD
+            .decode(lines.concat())
+            .unwrap();
+        assert_eq!(decoded, vec![0xff; 100]);
+    }
+
+    #[test]
+    fn content_types_follow_extensions() {
+        assert_eq!(content_type_for("a.jpeg"), "image/jpeg");
+        assert_eq!(content_type_for("clip.MP4"), "video/mp4");
+        assert_eq!(content_type_for("archive"), "application/octet-stream");
+        assert_eq!(content_type_for("x.unknown"), "application/octet-stream");
+    }
+}
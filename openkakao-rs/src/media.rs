@@ -2,7 +2,9 @@ use std::path::Path;
 
 use anyhow::Result;
 
-use crate::model::KakaoCredentials;
+use crate::media_cache::MediaCache;
+use crate::output::write_atomic;
+use crate::rest::KakaoRestClient;
 
 /// Detect media type from magic bytes, falling back to file extension.
 /// Returns (kakao_msg_type, extension).
@@ -166,49 +168,35 @@ pub fn sanitize_filename(name: &str) -> String {
     }
 }
 
-/// Download a media file from KakaoTalk CDN.
-pub fn download_media_file(creds: &KakaoCredentials, url: &str, path: &Path) -> Result<u64> {
+/// Download a media file from KakaoTalk CDN. The body lands in a temporary
+/// file beside `path` that only replaces it once complete, so a failed
+/// download leaves nothing behind.
+pub fn download_media_file(client: &KakaoRestClient, url: &str, path: &Path) -> Result<u64> {
     if let Some(parent) = path.parent() {
         std::fs::create_dir_all(parent)?;
     }
 
-    let a_header = if creds.a_header.is_empty() {
-        format!("mac/{}/ko", creds.app_version)
-    } else {
-        creds.a_header.clone()
-    };
-    let user_agent = if creds.user_agent.is_empty() {
-        format!("KT/{} Mc/10.15.7 ko", creds.app_version)
-    } else {
-        creds.user_agent.clone()
-    };
-
-    let client = reqwest::blocking::Client::builder()
-        .timeout(std::time::Duration::from_secs(60))
-        .build()?;
-
-    // Validate URL domain before sending credentials
-    let parsed_url = reqwest::Url::parse(url)?;
-    let host = parsed_url.host_str().unwrap_or("");
-    if !host.ends_with(".kakao.com") && !host.ends_with(".kakaocdn.net") {
-        anyhow::bail!("Refusing to send credentials to non-Kakao domain: {}", host);
-    }
+    let mut bytes = 0;
+    write_atomic(path, |mut out| {
+        bytes = client.download(url, &mut out)?.bytes;
+        Ok(())
+    })?;
+    Ok(bytes)
+}
 
-    let mut response = client
-        .get(url)
-        .header("A", &a_header)
-        .header("User-Agent", &user_agent)
-        .header(
-            "Authorization",
-            format!("{}-{}", creds.oauth_token, creds.device_uuid),
-        )
-        .send()?;
-
-    if !response.status().is_success() {
-        anyhow::bail!("HTTP {}: {}", response.status(), url);
+/// [`download_media_file`] via the shared media cache, so repeated downloads
+/// of the same attachment skip the network.
+pub fn download_media_file_cached(client: &KakaoRestClient, url: &str, path: &Path) -> Result<u64> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
     }
 
-    let mut file = std::fs::File::create(path)?;
-    let bytes = std::io::copy(&mut response, &mut file)?;
+    let mut cache = MediaCache::open()?;
+    let cached = cache.download(client, url)?;
+    let mut bytes = 0;
+    write_atomic(path, |out| {
+        bytes = std::io::copy(&mut std::fs::File::open(&cached)?, out)?;
+        Ok(())
+    })?;
     Ok(bytes)
 }
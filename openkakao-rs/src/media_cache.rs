@@ -0,0 +1,292 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::rest::KakaoRestClient;
+
+/// Default size cap for downloaded media kept on disk.
+pub const DEFAULT_MAX_BYTES: u64 = 256 * 1024 * 1024;
+
+const INDEX_FILE: &str = "index.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    size: u64,
+    /// Logical clock value of the last hit; the lowest is evicted first.
+    last_used: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Index {
+    clock: u64,
+    entries: BTreeMap<String, Entry>,
+}
+
+/// Content-addressable store for downloaded media, keyed by URL and the
+/// account that fetched it, with LRU eviction past a size cap.
+pub struct MediaCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    index: Index,
+}
+
+impl MediaCache {
+    pub fn open() -> Result<Self> {
+        Self::open_at(&cache_dir()?, DEFAULT_MAX_BYTES)
+    }
+
+    pub fn open_at(dir: &Path, max_bytes: u64) -> Result<Self> {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        let index_path = dir.join(INDEX_FILE);
+        // A damaged index only costs refetches, so start over rather than fail.
+        let index = fs::read_to_string(&index_path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            max_bytes,
+            index,
+        })
+    }
+
+    /// Cache key for `url` fetched under `scope` (e.g. the user id), so one
+    /// account never serves another account's authenticated media.
+    pub fn key(url: &str, scope: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(url.as_bytes());
+        hasher.update(b"\n");
+        hasher.update(scope.as_bytes());
+        hex::encode(hasher.finalize())
+    }
+
+    /// Path of a cached entry, marking it as recently used. An index entry
+    /// whose file has gone missing is dropped so the caller refetches.
+    pub fn get(&mut self, key: &str) -> Result<Option<PathBuf>> {
+        if !self.index.entries.contains_key(key) {
+            return Ok(None);
+        }
+        let path = self.dir.join(key);
+        if !path.is_file() {
+            self.index.entries.remove(key);
+            self.save_index()?;
+            return Ok(None);
+        }
+        self.index.clock += 1;
+        let clock = self.index.clock;
+        if let Some(entry) = self.index.entries.get_mut(key) {
+            entry.last_used = clock;
+        }
+        self.save_index()?;
+        Ok(Some(path))
+    }
+
+    /// [`KakaoRestClient::download`] through the cache: a URL already
+    /// fetched by this account is served from disk.
+    pub fn download(&mut self, client: &KakaoRestClient, url: &str) -> Result<PathBuf> {
+        let key = Self::key(url, &client.user_id().to_string());
+        self.get_or_fetch(&key, |file| Ok(client.download(url, file).map(|_| ())?))
+    }
+
+    /// Return the cached file for `key`, or run `fill` to write it first.
+    /// A failed `fill` leaves nothing behind.
+    pub fn get_or_fetch<F>(&mut self, key: &str, fill: F) -> Result<PathBuf>
+    where
+        F: FnOnce(&mut fs::File) -> Result<()>,
+    {
+        if let Some(path) = self.get(key)? {
+            return Ok(path);
+        }
+
+        let tmp_path = self.dir.join(format!("{key}.part"));
+        let result = fs::File::create(&tmp_path)
+            .with_context(|| format!("Failed to create {}", tmp_path.display()))
+            .and_then(|mut file| {
+                fill(&mut file)?;
+                file.flush()?;
+                Ok(())
+            });
+        if let Err(e) = result {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e);
+        }
+
+        let path = self.dir.join(key);
+        fs::rename(&tmp_path, &path)
+            .with_context(|| format!("Failed to move {} into cache", tmp_path.display()))?;
+        let size = fs::metadata(&path)?.len();
+        self.index.clock += 1;
+        self.index.entries.insert(
+            key.to_string(),
+            Entry {
+                size,
+                last_used: self.index.clock,
+            },
+        );
+        self.evict(key)?;
+        self.save_index()?;
+        Ok(path)
+    }
+
+    /// Total bytes of all indexed entries.
+    pub fn total_bytes(&self) -> u64 {
+        self.index.entries.values().map(|e| e.size).sum()
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.entries.len()
+    }
+
+    pub fn max_bytes(&self) -> u64 {
+        self.max_bytes
+    }
+
+    /// Remove every cached file. Returns the number of entries removed.
+    pub fn clear(&mut self) -> Result<usize> {
+        let removed = self.index.entries.len();
+        for key in std::mem::take(&mut self.index.entries).into_keys() {
+            let _ = fs::remove_file(self.dir.join(key));
+        }
+        self.save_index()?;
+        Ok(removed)
+    }
+
+    /// Drop least-recently-used entries until under the cap. `keep` (the
+    /// entry just written) survives even when it alone exceeds the cap.
+    fn evict(&mut self, keep: &str) -> Result<()> {
+        while self.total_bytes() > self.max_bytes {
+            let victim = self
+                .index
+                .entries
+                .iter()
+                .filter(|(k, _)| k.as_str() != keep)
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(k, _)| k.clone());
+            let Some(victim) = victim else { break };
+            self.index.entries.remove(&victim);
+            let _ = fs::remove_file(self.dir.join(&victim));
+        }
+        Ok(())
+    }
+
+    fn save_index(&self) -> Result<()> {
+        let path = self.dir.join(INDEX_FILE);
+        let data = serde_json::to_string(&self.index)?;
+        fs::write(&path, data).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+pub fn cache_dir() -> Result<PathBuf> {
+    Ok(crate::paths::profile_dir()?.join("media-cache"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn put(cache: &mut MediaCache, key: &str, len: usize) -> PathBuf {
+        cache
+            .get_or_fetch(key, |f| Ok(f.write_all(&vec![b'x'; len])?))
+            .unwrap()
+    }
+
+    #[test]
+    fn hit_does_not_refetch_and_counts_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = MediaCache::open_at(dir.path(), 1000).unwrap();
+        let key = MediaCache::key("https://dn.kakaocdn.net/a.jpg", "1");
+        put(&mut cache, &key, 100);
+
+        let path = cache
+            .get_or_fetch(&key, |_| panic!("cached entry must not refetch"))
+            .unwrap();
+        assert_eq!(fs::read(path).unwrap().len(), 100);
+        assert_eq!(cache.total_bytes(), 100);
+        assert_eq!(cache.len(), 1);
+
+        // The index survives reopening.
+        let reopened = MediaCache::open_at(dir.path(), 1000).unwrap();
+        assert_eq!(reopened.total_bytes(), 100);
+    }
+
+    #[test]
+    fn keys_are_scoped() {
+        let url = "https://dn.kakaocdn.net/a.jpg";
+        assert_ne!(MediaCache::key(url, "1"), MediaCache::key(url, "2"));
+        assert_eq!(MediaCache::key(url, "1").len(), 64);
+    }
+
+    #[test]
+    fn evicts_least_recently_used_past_cap() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = MediaCache::open_at(dir.path(), 250).unwrap();
+        put(&mut cache, "a", 100);
+        put(&mut cache, "b", 100);
+        // Touch "a" so "b" is the oldest.
+        assert!(cache.get("a").unwrap().is_some());
+        put(&mut cache, "c", 100);
+
+        assert!(cache.get("b").unwrap().is_none());
+        assert!(!dir.path().join("b").exists());
+        assert!(cache.get("a").unwrap().is_some());
+        assert!(cache.get("c").unwrap().is_some());
+        assert_eq!(cache.total_bytes(), 200);
+    }
+
+    #[test]
+    fn oversized_entry_is_kept_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = MediaCache::open_at(dir.path(), 50).unwrap();
+        put(&mut cache, "a", 10);
+        let path = put(&mut cache, "big", 100);
+        assert!(path.exists());
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.total_bytes(), 100);
+    }
+
+    #[test]
+    fn missing_file_self_heals_by_refetching() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = MediaCache::open_at(dir.path(), 1000).unwrap();
+        let path = put(&mut cache, "a", 10);
+        fs::remove_file(&path).unwrap();
+
+        let mut fetched = false;
+        let path = cache
+            .get_or_fetch("a", |f| {
+                fetched = true;
+                Ok(f.write_all(b"fresh")?)
+            })
+            .unwrap();
+        assert!(fetched);
+        assert_eq!(fs::read(path).unwrap(), b"fresh");
+        assert_eq!(cache.total_bytes(), 5);
+    }
+
+    #[test]
+    fn failed_fetch_leaves_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = MediaCache::open_at(dir.path(), 1000).unwrap();
+        assert!(cache
+            .get_or_fetch("a", |_| Err(anyhow::anyhow!("offline")))
+            .is_err());
+        assert_eq!(cache.len(), 0);
+        assert!(!dir.path().join("a.part").exists());
+    }
+
+    #[test]
+    fn clear_removes_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = MediaCache::open_at(dir.path(), 1000).unwrap();
+        let path = put(&mut cache, "a", 10);
+        put(&mut cache, "b", 10);
+        assert_eq!(cache.clear().unwrap(), 2);
+        assert!(!path.exists());
+        assert_eq!(cache.total_bytes(), 0);
+    }
+}
@@ -1,13 +1,407 @@
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 
 use anyhow::{Context, Result};
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+
+use crate::model::{ChatMessage, MessageContent, Notice, DELETED_MESSAGE_TEXT};
+use crate::normalize::normalize_text;
+
+/// `cache_meta` key recording whether the search index holds normalized
+/// text (see [`MessageDb::reindex`]).
+const NORMALIZE_KEY: &str = "normalize";
+
+/// `cache_meta` key holding the salt of [`ContentMode::Hash`] digests.
+const CONTENT_SALT_KEY: &str = "content_salt";
+
+/// Version [`MessageDb`] upgrades every database to on open.
+pub const SCHEMA_VERSION: i32 = 6;
+
+/// `MIGRATIONS[n]` upgrades a database from version `n` to `n + 1`. Never
+/// edit a released step; append a new one and bump [`SCHEMA_VERSION`].
+const MIGRATIONS: [fn(&Connection) -> Result<()>; SCHEMA_VERSION as usize] = [
+    migrate_v1_base,
+    migrate_v2_members,
+    migrate_v3_deletions,
+    migrate_v4_notices,
+    migrate_v5_content,
+    migrate_v6_departures,
+];
+
+/// Tables from before the schema was versioned. Databases from then are at
+/// version 0 but may already have some of them, so every step is guarded.
+fn migrate_v1_base(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS messages (
+            chat_id     INTEGER NOT NULL,
+            log_id      INTEGER NOT NULL,
+            author_id   INTEGER NOT NULL,
+            author_name TEXT NOT NULL DEFAULT '',
+            message_type INTEGER NOT NULL DEFAULT 1,
+            message     TEXT NOT NULL DEFAULT '',
+            attachment  TEXT NOT NULL DEFAULT '',
+            send_at     INTEGER NOT NULL DEFAULT 0,
+            message_norm TEXT NOT NULL DEFAULT '',
+            PRIMARY KEY (chat_id, log_id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_messages_chat_send
+            ON messages(chat_id, send_at);
+        CREATE INDEX IF NOT EXISTS idx_messages_search
+            ON messages(chat_id, message);
+
+        CREATE TABLE IF NOT EXISTS chat_sync (
+            chat_id         INTEGER PRIMARY KEY,
+            last_log_id     INTEGER NOT NULL DEFAULT 0,
+            synced_at       INTEGER NOT NULL DEFAULT 0,
+            message_count   INTEGER NOT NULL DEFAULT 0
+        );
+
+        CREATE TABLE IF NOT EXISTS cache_meta (
+            key   TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS chat_titles (
+            chat_id     INTEGER NOT NULL,
+            title       TEXT NOT NULL,
+            observed_at INTEGER NOT NULL,
+            PRIMARY KEY (chat_id, observed_at)
+        );",
+    )?;
+    let has_norm: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('messages') WHERE name = 'message_norm'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_norm {
+        conn.execute_batch(
+            "ALTER TABLE messages ADD COLUMN message_norm TEXT NOT NULL DEFAULT ''",
+        )?;
+    }
+    Ok(())
+}
+
+/// Member names per chat, so reads can name authors without a member fetch.
+fn migrate_v2_members(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE chat_members (
+            chat_id    INTEGER NOT NULL,
+            user_id    INTEGER NOT NULL,
+            name       TEXT NOT NULL,
+            updated_at INTEGER NOT NULL,
+            PRIMARY KEY (chat_id, user_id)
+        );",
+    )?;
+    Ok(())
+}
+
+/// Deletions for everyone: `deleted` flags an original whose author
+/// removed it (the content is kept), `deletes_log_id` is set on the delete
+/// marker naming it. Markers cached before this step are picked up here.
+fn migrate_v3_deletions(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE messages ADD COLUMN deleted INTEGER NOT NULL DEFAULT 0;
+         ALTER TABLE messages ADD COLUMN deletes_log_id INTEGER;",
+    )?;
+    backfill_delete_markers(conn)
+}
+
+/// Pinned notices seen by `notice`, one row per revision, since the server
+/// only returns the current one.
+fn migrate_v4_notices(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE chat_notices (
+            chat_id    INTEGER NOT NULL,
+            revision   INTEGER NOT NULL,
+            author_id  INTEGER NOT NULL,
+            content    TEXT NOT NULL,
+            updated_at INTEGER NOT NULL,
+            PRIMARY KEY (chat_id, revision, updated_at)
+        );",
+    )?;
+    Ok(())
+}
+
+/// Per-chat content mode, a digest column for hashed rows and each
+/// message's length, so statistics need not read the text. Chats cached
+/// before this step kept their text.
+fn migrate_v5_content(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE chat_archive (
+            chat_id INTEGER PRIMARY KEY,
+            content TEXT NOT NULL
+        );
+        ALTER TABLE messages ADD COLUMN content_hash TEXT;
+        ALTER TABLE messages ADD COLUMN text_length INTEGER NOT NULL DEFAULT 0;
+        UPDATE messages SET text_length = length(message);
+        INSERT INTO chat_archive (chat_id, content)
+            SELECT DISTINCT chat_id, 'text' FROM messages;",
+    )?;
+    Ok(())
+}
+
+/// Archived chats a sync found missing from the chat list (left, or
+/// deleted), and since when. Rejoining removes the row.
+fn migrate_v6_departures(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE chat_departures (
+            chat_id     INTEGER PRIMARY KEY,
+            departed_at INTEGER NOT NULL
+        );",
+    )?;
+    Ok(())
+}
+
+/// Set `deletes_log_id` on cached markers and flag what they name.
+fn backfill_delete_markers(conn: &Connection) -> Result<()> {
+    let markers: Vec<(i64, i64, i64)> = {
+        let mut stmt = conn.prepare(
+            "SELECT chat_id, log_id, message, attachment FROM messages WHERE message_type = 0",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })?;
+        let mut markers = Vec::new();
+        for row in rows {
+            let (chat_id, log_id, message, attachment) = row?;
+            if let Some(target) = MessageContent::parse(0, &message, &attachment).deleted_log_id() {
+                markers.push((chat_id, log_id, target));
+            }
+        }
+        markers
+    };
+    let mut chats = HashSet::new();
+    for (chat_id, log_id, target) in markers {
+        conn.execute(
+            "UPDATE messages SET deletes_log_id = ?3 WHERE chat_id = ?1 AND log_id = ?2",
+            params![chat_id, log_id, target],
+        )?;
+        chats.insert(chat_id);
+    }
+    for chat_id in chats {
+        flag_deleted(conn, chat_id)?;
+    }
+    Ok(())
+}
+
+/// Flag every message of `chat_id` that a cached delete marker names.
+/// Markers and originals arrive in any order, so this runs after each
+/// batch rather than per marker.
+fn flag_deleted(conn: &Connection, chat_id: i64) -> Result<usize> {
+    conn.execute(
+        "UPDATE messages SET deleted = 1
+         WHERE chat_id = ?1 AND deleted = 0 AND log_id IN (
+             SELECT deletes_log_id FROM messages
+             WHERE chat_id = ?1 AND deletes_log_id IS NOT NULL
+         )",
+        params![chat_id],
+    )
+    .map_err(Into::into)
+}
+
+/// The content mode recorded for `chat_id`, if it was ever archived.
+fn content_mode_in(conn: &Connection, chat_id: i64) -> Result<Option<ContentMode>> {
+    let mode: Option<String> = conn
+        .query_row(
+            "SELECT content FROM chat_archive WHERE chat_id = ?1",
+            params![chat_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    mode.map(|mode| mode.parse()).transpose()
+}
+
+fn record_content_mode(conn: &Connection, chat_id: i64, mode: ContentMode) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO chat_archive (chat_id, content) VALUES (?1, ?2)",
+        params![chat_id, mode.as_str()],
+    )?;
+    Ok(())
+}
+
+/// The archive's digest salt, created on first use. It never leaves the
+/// database, so a digest cannot be matched against guessed text elsewhere,
+/// while equal messages within the archive still share a digest.
+fn content_salt(conn: &Connection) -> Result<String> {
+    let salt: Option<String> = conn
+        .query_row(
+            "SELECT value FROM cache_meta WHERE key = ?1",
+            params![CONTENT_SALT_KEY],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if let Some(salt) = salt {
+        return Ok(salt);
+    }
+    let salt = hex::encode(rand::random::<[u8; 16]>());
+    conn.execute(
+        "INSERT INTO cache_meta (key, value) VALUES (?1, ?2)",
+        params![CONTENT_SALT_KEY, salt],
+    )?;
+    Ok(salt)
+}
+
+/// What the archive keeps of a chat's messages. Feed messages (type 0:
+/// joins, leaves, deletions) are kept as is in both modes, since they carry
+/// no one's words and the archive's own bookkeeping reads them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentMode {
+    /// The text and attachment as sent.
+    Text,
+    /// A salted SHA-256 digest of the text and its length; author, type and
+    /// time are kept, the words and attachment are not.
+    Hash,
+}
+
+impl ContentMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Hash => "hash",
+        }
+    }
+}
+
+impl std::fmt::Display for ContentMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for ContentMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(Self::Text),
+            "hash" => Ok(Self::Hash),
+            other => anyhow::bail!("Unknown archive content mode {other:?}; use text or hash"),
+        }
+    }
+}
+
+/// What a [`ContentMode::Hash`] archive kept of a message in place of its
+/// text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ContentDigest {
+    /// Hex SHA-256 of the archive's salt followed by the text.
+    pub hash: String,
+    /// Length of the text in characters.
+    pub length: i64,
+}
+
+/// One author's share of a chat, from lengths rather than text so it is
+/// the same whichever [`ContentMode`] the chat is archived in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuthorActivity {
+    pub author_id: i64,
+    /// The most recent name cached for the author, if any.
+    pub author_name: String,
+    pub messages: i64,
+    /// Total characters written.
+    pub characters: i64,
+    pub first_at: i64,
+    pub last_at: i64,
+}
+
+/// `text` as a [`ContentMode::Hash`] row stores it.
+fn content_hash(salt: &str, text: &str) -> String {
+    use sha2::Digest;
+    let mut hasher = sha2::Sha256::new();
+    hasher.update(salt.as_bytes());
+    hasher.update(text.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// How `archive read` treats messages deleted for everyone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeletionView {
+    /// Show the placeholder where the original was.
+    Respect,
+    /// Show the original content, flagged as deleted.
+    Show,
+}
+
+/// `rows` (oldest first) as `archive read` shows them, each with whether
+/// its author deleted it. Markers whose original is in `rows` are dropped,
+/// since the original's line already stands for the deletion; a marker
+/// without its original stays as the placeholder.
+pub fn apply_deletions(
+    rows: Vec<CachedMessage>,
+    deleted: &HashSet<i64>,
+    view: DeletionView,
+) -> Vec<(CachedMessage, bool)> {
+    let present: HashSet<i64> = rows.iter().map(|m| m.log_id).collect();
+    rows.into_iter()
+        .filter_map(|mut row| {
+            let content =
+                MessageContent::parse(row.message_type.into(), &row.message, &row.attachment);
+            if let Some(target) = content.deleted_log_id() {
+                if present.contains(&target) {
+                    return None;
+                }
+                row.message = DELETED_MESSAGE_TEXT.to_string();
+                row.attachment.clear();
+                return Some((row, true));
+            }
+            let is_deleted = deleted.contains(&row.log_id);
+            if is_deleted && view == DeletionView::Respect {
+                row.message = DELETED_MESSAGE_TEXT.to_string();
+                row.attachment.clear();
+            }
+            Some((row, is_deleted))
+        })
+        .collect()
+}
 
 /// Local SQLite message cache for offline search and analytics.
 pub struct MessageDb {
     conn: Connection,
 }
 
+/// A chat title and when it was first seen.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TitleEntry {
+    pub title: String,
+    pub observed_at: i64,
+}
+
+/// A room whose title differs from the one last recorded for it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TitleChange {
+    pub chat_id: i64,
+    pub from: String,
+    pub to: String,
+}
+
+/// What [`MessageDb::record_chat_list`] changed, chat ids ascending.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ChatListChanges {
+    /// Archived chats missing from the chat list for the first time.
+    pub departed: Vec<i64>,
+    /// Departed chats in the chat list again.
+    pub returned: Vec<i64>,
+}
+
+/// The title in effect at `at` in `history` (oldest first). Before the
+/// first observation the first title seen is assumed, since nothing older
+/// is known.
+pub fn title_at(history: &[TitleEntry], at: i64) -> Option<&str> {
+    history
+        .iter()
+        .rev()
+        .find(|entry| entry.observed_at <= at)
+        .or_else(|| history.first())
+        .map(|entry| entry.title.as_str())
+}
+
 #[derive(Debug, Clone)]
 pub struct CachedMessage {
     pub chat_id: i64,
@@ -20,6 +414,39 @@ pub struct CachedMessage {
     pub send_at: i64,
 }
 
+impl CachedMessage {
+    /// The row for `msg`, naming its author `author_name`.
+    pub fn from_message(chat_id: i64, msg: &ChatMessage, author_name: String) -> Self {
+        Self {
+            chat_id,
+            log_id: msg.log_id,
+            author_id: msg.author_id,
+            author_name,
+            message_type: msg.message_type as i32,
+            message: msg.message.clone(),
+            attachment: msg.attachment.clone(),
+            send_at: msg.send_at,
+        }
+    }
+
+    /// The message as the server would return it, with the cached author
+    /// name as its payload nickname.
+    pub fn to_message(&self) -> ChatMessage {
+        let mut msg = ChatMessage::from_json(&serde_json::json!({
+            "logId": self.log_id,
+            "authorId": self.author_id,
+            "type": self.message_type,
+            "message": self.message,
+            "attachment": self.attachment,
+            "sendAt": self.send_at,
+        }));
+        if !self.author_name.is_empty() {
+            msg.author_nickname = Some(self.author_name.clone());
+        }
+        msg
+    }
+}
+
 impl MessageDb {
     pub fn open() -> Result<Self> {
         let path = db_path()?;
@@ -38,57 +465,197 @@ impl MessageDb {
     }
 
     fn init_schema(&self) -> Result<()> {
-        self.conn.execute_batch(
-            "CREATE TABLE IF NOT EXISTS messages (
-                chat_id     INTEGER NOT NULL,
-                log_id      INTEGER NOT NULL,
-                author_id   INTEGER NOT NULL,
-                author_name TEXT NOT NULL DEFAULT '',
-                message_type INTEGER NOT NULL DEFAULT 1,
-                message     TEXT NOT NULL DEFAULT '',
-                attachment  TEXT NOT NULL DEFAULT '',
-                send_at     INTEGER NOT NULL DEFAULT 0,
-                PRIMARY KEY (chat_id, log_id)
+        let version: i32 = self
+            .conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version > SCHEMA_VERSION {
+            anyhow::bail!(
+                "The message cache has schema version {version}, newer than this openkakao-rs \
+                 supports ({SCHEMA_VERSION}). Upgrade openkakao-rs or move {} aside.",
+                db_path()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_else(|_| "messages.db".to_string())
             );
-            CREATE INDEX IF NOT EXISTS idx_messages_chat_send
-                ON messages(chat_id, send_at);
-            CREATE INDEX IF NOT EXISTS idx_messages_search
-                ON messages(chat_id, message);
-
-            CREATE TABLE IF NOT EXISTS chat_sync (
-                chat_id         INTEGER PRIMARY KEY,
-                last_log_id     INTEGER NOT NULL DEFAULT 0,
-                synced_at       INTEGER NOT NULL DEFAULT 0,
-                message_count   INTEGER NOT NULL DEFAULT 0
-            );",
-        )?;
+        }
+        for (idx, migrate) in MIGRATIONS.iter().enumerate() {
+            let target = idx as i32 + 1;
+            if version >= target {
+                continue;
+            }
+            let tx = self.conn.unchecked_transaction()?;
+            migrate(&tx)
+                .with_context(|| format!("Failed to upgrade the message cache to v{target}"))?;
+            tx.pragma_update(None, "user_version", target)?;
+            tx.commit()?;
+        }
         Ok(())
     }
 
-    /// Insert or replace a batch of messages.
+    /// Schema version stamped in the database (`PRAGMA user_version`).
+    pub fn schema_version(&self) -> Result<i32> {
+        self.conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(Into::into)
+    }
+
+    /// Whether the search index holds [`normalize_text`] forms, so queries
+    /// must be normalized the same way.
+    pub fn is_normalized(&self) -> Result<bool> {
+        let value: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT value FROM cache_meta WHERE key = ?1",
+                params![NORMALIZE_KEY],
+                |row| row.get(0),
+            )
+            .ok();
+        Ok(value.as_deref() == Some("1"))
+    }
+
+    /// Column the search index covers: the text as sent, or its normalized
+    /// form.
+    fn search_column(&self) -> Result<&'static str> {
+        Ok(if self.is_normalized()? {
+            "message_norm"
+        } else {
+            "message"
+        })
+    }
+
+    /// A query as the index expects it.
+    fn index_query(&self, query: &str) -> Result<String> {
+        Ok(if self.is_normalized()? {
+            normalize_text(query)
+        } else {
+            query.to_string()
+        })
+    }
+
+    /// Rebuild the search index with or without normalization and remember
+    /// the choice, so later syncs and searches use the same form. Returns
+    /// the number of messages indexed.
+    pub fn reindex(&self, normalize: bool) -> Result<i64> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute_batch(
+            "DROP TRIGGER IF EXISTS messages_ai;
+             DROP TRIGGER IF EXISTS messages_ad;
+             DROP TRIGGER IF EXISTS messages_au;
+             DROP TABLE IF EXISTS messages_fts;",
+        )?;
+        if normalize {
+            let rows: Vec<(i64, String)> = {
+                let mut stmt = tx.prepare("SELECT rowid, message FROM messages")?;
+                let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                rows.collect::<rusqlite::Result<_>>()?
+            };
+            let mut update =
+                tx.prepare("UPDATE messages SET message_norm = ?2 WHERE rowid = ?1")?;
+            for (rowid, message) in rows {
+                update.execute(params![rowid, normalize_text(&message)])?;
+            }
+        } else {
+            tx.execute("UPDATE messages SET message_norm = ''", [])?;
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO cache_meta (key, value) VALUES (?1, ?2)",
+            params![NORMALIZE_KEY, if normalize { "1" } else { "0" }],
+        )?;
+        tx.commit()?;
+        self.ensure_fts_table()?;
+        self.total_count()
+    }
+
+    /// Insert or update a batch of messages. A known author name is kept when
+    /// the incoming row has none, so history survives members leaving. Delete
+    /// markers in the batch flag the originals they name, and a flagged
+    /// original keeps its content when the server later serves a stub.
+    /// Rows of a [`ContentMode::Hash`] chat are stored as their digest; a
+    /// chat seen for the first time is recorded as [`ContentMode::Text`].
     pub fn upsert_messages(&self, messages: &[CachedMessage]) -> Result<usize> {
         let tx = self.conn.unchecked_transaction()?;
         let mut stmt = tx.prepare_cached(
-            "INSERT OR REPLACE INTO messages
-             (chat_id, log_id, author_id, author_name, message_type, message, attachment, send_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            "INSERT INTO messages
+             (chat_id, log_id, author_id, author_name, message_type, message, attachment, send_at,
+              message_norm, deletes_log_id, content_hash, text_length)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+             ON CONFLICT(chat_id, log_id) DO UPDATE SET
+                author_id = excluded.author_id,
+                author_name = CASE WHEN excluded.author_name = ''
+                                   THEN messages.author_name
+                                   ELSE excluded.author_name END,
+                message_type = CASE WHEN messages.deleted
+                                    THEN messages.message_type ELSE excluded.message_type END,
+                message = CASE WHEN messages.deleted
+                               THEN messages.message ELSE excluded.message END,
+                attachment = CASE WHEN messages.deleted
+                                  THEN messages.attachment ELSE excluded.attachment END,
+                send_at = excluded.send_at,
+                message_norm = CASE WHEN messages.deleted
+                                    THEN messages.message_norm ELSE excluded.message_norm END,
+                deletes_log_id = excluded.deletes_log_id,
+                content_hash = CASE WHEN messages.deleted
+                                    THEN messages.content_hash ELSE excluded.content_hash END,
+                text_length = CASE WHEN messages.deleted
+                                   THEN messages.text_length ELSE excluded.text_length END",
         )?;
 
+        let mut modes = HashMap::new();
+        for m in messages {
+            if let std::collections::hash_map::Entry::Vacant(entry) = modes.entry(m.chat_id) {
+                let mode = match content_mode_in(&tx, m.chat_id)? {
+                    Some(mode) => mode,
+                    None => {
+                        record_content_mode(&tx, m.chat_id, ContentMode::Text)?;
+                        ContentMode::Text
+                    }
+                };
+                entry.insert(mode);
+            }
+        }
+        let salt = if modes.values().any(|mode| *mode == ContentMode::Hash) {
+            Some(content_salt(&tx)?)
+        } else {
+            None
+        };
+
+        let normalize = self.is_normalized()?;
         let mut count = 0;
         for m in messages {
+            let deletes_log_id = (m.message_type == 0)
+                .then(|| MessageContent::parse(0, &m.message, &m.attachment).deleted_log_id())
+                .flatten();
+            let text_length = m.message.chars().count() as i64;
+            let (message, attachment, hash) = match (&salt, modes[&m.chat_id]) {
+                (Some(salt), ContentMode::Hash) if m.message_type != 0 => {
+                    ("", "", Some(content_hash(salt, &m.message)))
+                }
+                _ => (m.message.as_str(), m.attachment.as_str(), None),
+            };
+            let message_norm = if normalize {
+                normalize_text(message)
+            } else {
+                String::new()
+            };
             stmt.execute(params![
                 m.chat_id,
                 m.log_id,
                 m.author_id,
                 m.author_name,
                 m.message_type,
-                m.message,
-                m.attachment,
+                message,
+                attachment,
                 m.send_at,
+                message_norm,
+                deletes_log_id,
+                hash,
+                text_length,
             ])?;
             count += 1;
         }
         drop(stmt);
+        for chat_id in modes.into_keys() {
+            flag_deleted(&tx, chat_id)?;
+        }
         tx.commit()?;
         Ok(count)
     }
@@ -122,6 +689,106 @@ impl MessageDb {
         Ok(result)
     }
 
+    /// Record the title of each `(chat_id, title)` seen at `observed_at`,
+    /// skipping empty titles and titles equal to the last one recorded for
+    /// the chat. Returns the chats that had a different title before.
+    pub fn record_titles<'a>(
+        &self,
+        titles: impl IntoIterator<Item = (i64, &'a str)>,
+        observed_at: i64,
+    ) -> Result<Vec<TitleChange>> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut changes = Vec::new();
+        {
+            let mut latest = tx.prepare(
+                "SELECT title FROM chat_titles WHERE chat_id = ?1
+                 ORDER BY observed_at DESC LIMIT 1",
+            )?;
+            let mut insert = tx.prepare(
+                "INSERT OR REPLACE INTO chat_titles (chat_id, title, observed_at)
+                 VALUES (?1, ?2, ?3)",
+            )?;
+            for (chat_id, title) in titles {
+                if title.is_empty() {
+                    continue;
+                }
+                let previous: Option<String> = latest
+                    .query_row(params![chat_id], |row| row.get(0))
+                    .optional()?;
+                if previous.as_deref() == Some(title) {
+                    continue;
+                }
+                insert.execute(params![chat_id, title, observed_at])?;
+                if let Some(from) = previous {
+                    changes.push(TitleChange {
+                        chat_id,
+                        from,
+                        to: title.to_string(),
+                    });
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(changes)
+    }
+
+    /// Recorded titles of a chat, oldest first.
+    pub fn title_history(&self, chat_id: i64) -> Result<Vec<TitleEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT title, observed_at FROM chat_titles WHERE chat_id = ?1
+             ORDER BY observed_at ASC",
+        )?;
+        let rows = stmt.query_map(params![chat_id], |row| {
+            Ok(TitleEntry {
+                title: row.get(0)?,
+                observed_at: row.get(1)?,
+            })
+        })?;
+        let mut history = Vec::new();
+        for row in rows {
+            history.push(row?);
+        }
+        Ok(history)
+    }
+
+    /// Keep `notice` as a revision of the chat's notice. Returns whether it
+    /// was new.
+    pub fn record_notice(&self, chat_id: i64, notice: &Notice) -> Result<bool> {
+        let inserted = self.conn.execute(
+            "INSERT OR IGNORE INTO chat_notices (chat_id, revision, author_id, content, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                chat_id,
+                notice.revision,
+                notice.author_id,
+                notice.content,
+                notice.updated_at
+            ],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    /// Recorded notices of a chat, newest first.
+    pub fn notice_history(&self, chat_id: i64) -> Result<Vec<Notice>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT author_id, content, updated_at, revision FROM chat_notices
+             WHERE chat_id = ?1 ORDER BY updated_at DESC, revision DESC",
+        )?;
+        let rows = stmt.query_map(params![chat_id], |row| {
+            Ok(Notice {
+                author_id: row.get(0)?,
+                content: row.get(1)?,
+                updated_at: row.get(2)?,
+                revision: row.get(3)?,
+            })
+        })?;
+        let mut history = Vec::new();
+        for row in rows {
+            history.push(row?);
+        }
+        Ok(history)
+    }
+
     /// Lazily create FTS5 virtual table and sync triggers if not already present.
     fn ensure_fts_table(&self) -> Result<()> {
         let exists: bool = self.conn.query_row(
@@ -131,25 +798,26 @@ impl MessageDb {
         )?;
 
         if !exists {
-            self.conn.execute_batch(
+            let column = self.search_column()?;
+            self.conn.execute_batch(&format!(
                 "CREATE VIRTUAL TABLE messages_fts USING fts5(
-                    message,
+                    {column},
                     content=messages,
                     content_rowid=rowid
                 );
-                INSERT INTO messages_fts(rowid, message)
-                    SELECT rowid, message FROM messages;
+                INSERT INTO messages_fts(rowid, {column})
+                    SELECT rowid, {column} FROM messages;
                 CREATE TRIGGER IF NOT EXISTS messages_ai AFTER INSERT ON messages BEGIN
-                    INSERT INTO messages_fts(rowid, message) VALUES (new.rowid, new.message);
+                    INSERT INTO messages_fts(rowid, {column}) VALUES (new.rowid, new.{column});
                 END;
                 CREATE TRIGGER IF NOT EXISTS messages_ad AFTER DELETE ON messages BEGIN
-                    INSERT INTO messages_fts(messages_fts, rowid, message) VALUES('delete', old.rowid, old.message);
+                    INSERT INTO messages_fts(messages_fts, rowid, {column}) VALUES('delete', old.rowid, old.{column});
                 END;
                 CREATE TRIGGER IF NOT EXISTS messages_au AFTER UPDATE ON messages BEGIN
-                    INSERT INTO messages_fts(messages_fts, rowid, message) VALUES('delete', old.rowid, old.message);
-                    INSERT INTO messages_fts(rowid, message) VALUES (new.rowid, new.message);
-                END;",
-            )?;
+                    INSERT INTO messages_fts(messages_fts, rowid, {column}) VALUES('delete', old.rowid, old.{column});
+                    INSERT INTO messages_fts(rowid, {column}) VALUES (new.rowid, new.{column});
+                END;"
+            ))?;
         }
         Ok(())
     }
@@ -172,6 +840,7 @@ impl MessageDb {
     /// Search messages by text pattern within a chat.
     /// Uses FTS5 if available, falls back to LIKE.
     pub fn search(&self, chat_id: i64, query: &str, limit: usize) -> Result<Vec<CachedMessage>> {
+        let query = &self.index_query(query)?;
         // Empty query: FTS5 MATCH can't handle it, use LIKE '%%' which returns all rows
         if query.trim().is_empty() {
             return self.search_like(chat_id, query, limit);
@@ -219,13 +888,14 @@ impl MessageDb {
 
     fn search_like(&self, chat_id: i64, query: &str, limit: usize) -> Result<Vec<CachedMessage>> {
         let pattern = format!("%{}%", query);
-        let mut stmt = self.conn.prepare(
+        let mut stmt = self.conn.prepare(&format!(
             "SELECT chat_id, log_id, author_id, author_name, message_type, message, attachment, send_at
              FROM messages
-             WHERE chat_id = ?1 AND message LIKE ?2
+             WHERE chat_id = ?1 AND {} LIKE ?2
              ORDER BY send_at DESC
              LIMIT ?3",
-        )?;
+            self.search_column()?
+        ))?;
 
         let rows = stmt.query_map(params![chat_id, pattern, limit as i64], |row| {
             Ok(CachedMessage {
@@ -247,34 +917,47 @@ impl MessageDb {
         Ok(results)
     }
 
-    /// Search messages across all chats.
+    /// Search messages across all chats, leaving out departed ones (see
+    /// [`MessageDb::record_chat_list`]) unless `include_departed`.
     /// Uses FTS5 if available, falls back to LIKE.
-    pub fn search_all(&self, query: &str, limit: usize) -> Result<Vec<CachedMessage>> {
+    pub fn search_all(
+        &self,
+        query: &str,
+        limit: usize,
+        include_departed: bool,
+    ) -> Result<Vec<CachedMessage>> {
+        let query = &self.index_query(query)?;
         if query.trim().is_empty() {
-            return self.search_all_like(query, limit);
+            return self.search_all_like(query, limit, include_departed);
         }
         if !self.has_fts() {
             let _ = self.ensure_fts_table();
         }
         if self.has_fts() {
-            self.search_all_fts(query, limit)
+            self.search_all_fts(query, limit, include_departed)
         } else {
-            self.search_all_like(query, limit)
+            self.search_all_like(query, limit, include_departed)
         }
     }
 
-    fn search_all_fts(&self, query: &str, limit: usize) -> Result<Vec<CachedMessage>> {
+    fn search_all_fts(
+        &self,
+        query: &str,
+        limit: usize,
+        include_departed: bool,
+    ) -> Result<Vec<CachedMessage>> {
         let fts_query = Self::fts_escape(query);
         let mut stmt = self.conn.prepare(
             "SELECT m.chat_id, m.log_id, m.author_id, m.author_name, m.message_type, m.message, m.attachment, m.send_at
              FROM messages m
              JOIN messages_fts fts ON m.rowid = fts.rowid
              WHERE messages_fts MATCH ?1
+               AND (?3 OR m.chat_id NOT IN (SELECT chat_id FROM chat_departures))
              ORDER BY m.send_at DESC
              LIMIT ?2",
         )?;
 
-        let rows = stmt.query_map(params![fts_query, limit as i64], |row| {
+        let rows = stmt.query_map(params![fts_query, limit as i64, include_departed], |row| {
             Ok(CachedMessage {
                 chat_id: row.get(0)?,
                 log_id: row.get(1)?,
@@ -294,17 +977,24 @@ impl MessageDb {
         Ok(results)
     }
 
-    fn search_all_like(&self, query: &str, limit: usize) -> Result<Vec<CachedMessage>> {
+    fn search_all_like(
+        &self,
+        query: &str,
+        limit: usize,
+        include_departed: bool,
+    ) -> Result<Vec<CachedMessage>> {
         let pattern = format!("%{}%", query);
-        let mut stmt = self.conn.prepare(
+        let mut stmt = self.conn.prepare(&format!(
             "SELECT chat_id, log_id, author_id, author_name, message_type, message, attachment, send_at
              FROM messages
-             WHERE message LIKE ?1
+             WHERE {} LIKE ?1
+               AND (?3 OR chat_id NOT IN (SELECT chat_id FROM chat_departures))
              ORDER BY send_at DESC
              LIMIT ?2",
-        )?;
+            self.search_column()?
+        ))?;
 
-        let rows = stmt.query_map(params![pattern, limit as i64], |row| {
+        let rows = stmt.query_map(params![pattern, limit as i64, include_departed], |row| {
             Ok(CachedMessage {
                 chat_id: row.get(0)?,
                 log_id: row.get(1)?,
@@ -351,6 +1041,114 @@ impl MessageDb {
         Ok(results)
     }
 
+    /// Retrieve messages for a chat with `start <= send_at < end`, ordered by
+    /// send_at ascending.
+    pub fn get_messages_between(
+        &self,
+        chat_id: i64,
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<CachedMessage>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT chat_id, log_id, author_id, author_name, message_type, message, attachment, send_at
+             FROM messages WHERE chat_id = ?1 AND send_at >= ?2 AND send_at < ?3
+             ORDER BY send_at ASC",
+        )?;
+        let rows = stmt.query_map(params![chat_id, start, end], |row| {
+            Ok(CachedMessage {
+                chat_id: row.get(0)?,
+                log_id: row.get(1)?,
+                author_id: row.get(2)?,
+                author_name: row.get(3)?,
+                message_type: row.get(4)?,
+                message: row.get(5)?,
+                attachment: row.get(6)?,
+                send_at: row.get(7)?,
+            })
+        })?;
+        let mut results = Vec::new();
+        for row in rows {
+            results.push(row?);
+        }
+        Ok(results)
+    }
+
+    /// Up to `radius` messages either side of `log_id` in a chat, plus the
+    /// message itself if cached, ordered by log_id ascending.
+    pub fn get_context(
+        &self,
+        chat_id: i64,
+        log_id: i64,
+        radius: usize,
+    ) -> Result<Vec<CachedMessage>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT * FROM (
+                 SELECT chat_id, log_id, author_id, author_name, message_type, message, attachment, send_at
+                 FROM messages WHERE chat_id = ?1 AND log_id < ?2 ORDER BY log_id DESC LIMIT ?3
+             )
+             UNION ALL
+             SELECT * FROM (
+                 SELECT chat_id, log_id, author_id, author_name, message_type, message, attachment, send_at
+                 FROM messages WHERE chat_id = ?1 AND log_id >= ?2 ORDER BY log_id ASC LIMIT ?3 + 1
+             )
+             ORDER BY log_id ASC",
+        )?;
+        let rows = stmt.query_map(params![chat_id, log_id, radius as i64], |row| {
+            Ok(CachedMessage {
+                chat_id: row.get(0)?,
+                log_id: row.get(1)?,
+                author_id: row.get(2)?,
+                author_name: row.get(3)?,
+                message_type: row.get(4)?,
+                message: row.get(5)?,
+                attachment: row.get(6)?,
+                send_at: row.get(7)?,
+            })
+        })?;
+        let mut results = Vec::new();
+        for row in rows {
+            results.push(row?);
+        }
+        Ok(results)
+    }
+
+    /// Earliest and latest cached send_at for a chat, if any rows exist.
+    pub fn time_bounds(&self, chat_id: i64) -> Result<Option<(i64, i64)>> {
+        let bounds: (Option<i64>, Option<i64>) = self.conn.query_row(
+            "SELECT MIN(send_at), MAX(send_at) FROM messages WHERE chat_id = ?1",
+            params![chat_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok(match bounds {
+            (Some(first), Some(last)) => Some((first, last)),
+            _ => None,
+        })
+    }
+
+    /// Number of cached messages for a chat.
+    pub fn message_count(&self, chat_id: i64) -> Result<i64> {
+        self.conn
+            .query_row(
+                "SELECT COUNT(*) FROM messages WHERE chat_id = ?1",
+                params![chat_id],
+                |row| row.get(0),
+            )
+            .map_err(Into::into)
+    }
+
+    /// `(log_id, send_at)` of every cached message of a chat, by log_id.
+    pub fn log_times(&self, chat_id: i64) -> Result<Vec<(i64, i64)>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT log_id, send_at FROM messages WHERE chat_id = ?1 ORDER BY log_id")?;
+        let rows = stmt.query_map(params![chat_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        let mut times = Vec::new();
+        for row in rows {
+            times.push(row?);
+        }
+        Ok(times)
+    }
+
     /// Get message count per chat.
     pub fn chat_stats(&self) -> Result<Vec<(i64, i64, i64)>> {
         let mut stmt = self.conn.prepare(
@@ -364,6 +1162,289 @@ impl MessageDb {
         Ok(stats)
     }
 
+    /// The newest `limit` messages of a chat below `before_log_id`, ordered
+    /// by log_id ascending: the cached page the server no longer serves.
+    pub fn get_messages_before(
+        &self,
+        chat_id: i64,
+        before_log_id: i64,
+        limit: usize,
+    ) -> Result<Vec<CachedMessage>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT * FROM (
+                 SELECT chat_id, log_id, author_id, author_name, message_type, message, attachment, send_at
+                 FROM messages WHERE chat_id = ?1 AND log_id < ?2 ORDER BY log_id DESC LIMIT ?3
+             )
+             ORDER BY log_id ASC",
+        )?;
+        let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+        let rows = stmt.query_map(params![chat_id, before_log_id, limit], |row| {
+            Ok(CachedMessage {
+                chat_id: row.get(0)?,
+                log_id: row.get(1)?,
+                author_id: row.get(2)?,
+                author_name: row.get(3)?,
+                message_type: row.get(4)?,
+                message: row.get(5)?,
+                attachment: row.get(6)?,
+                send_at: row.get(7)?,
+            })
+        })?;
+        let mut results = Vec::new();
+        for row in rows {
+            results.push(row?);
+        }
+        Ok(results)
+    }
+
+    /// Log ids of a chat's messages that their author deleted for everyone.
+    pub fn deleted_log_ids(&self, chat_id: i64) -> Result<HashSet<i64>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT log_id FROM messages WHERE chat_id = ?1 AND deleted = 1")?;
+        let rows = stmt.query_map(params![chat_id], |row| row.get(0))?;
+        let mut ids = HashSet::new();
+        for row in rows {
+            ids.insert(row?);
+        }
+        Ok(ids)
+    }
+
+    /// The content mode `chat_id` is archived in; `None` before its first
+    /// sync.
+    pub fn content_mode(&self, chat_id: i64) -> Result<Option<ContentMode>> {
+        content_mode_in(&self.conn, chat_id)
+    }
+
+    /// Archive `chat_id` in `mode` from now on and bring what is cached in
+    /// line. Text to hash digests the cached rows in place; hash to text
+    /// cannot recover the words, so the chat's messages and sync cursor are
+    /// dropped for the next sync to fetch again. Returns the number of rows
+    /// hashed or dropped.
+    pub fn set_content_mode(&self, chat_id: i64, mode: ContentMode) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        let changed = match (content_mode_in(&tx, chat_id)?, mode) {
+            (Some(ContentMode::Text), ContentMode::Hash) => {
+                let salt = content_salt(&tx)?;
+                let rows: Vec<(i64, String)> = {
+                    let mut stmt = tx.prepare(
+                        "SELECT log_id, message FROM messages
+                         WHERE chat_id = ?1 AND message_type != 0 AND content_hash IS NULL",
+                    )?;
+                    let rows =
+                        stmt.query_map(params![chat_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                    rows.collect::<rusqlite::Result<_>>()?
+                };
+                let mut update = tx.prepare(
+                    "UPDATE messages
+                     SET content_hash = ?3, message = '', attachment = '', message_norm = ''
+                     WHERE chat_id = ?1 AND log_id = ?2",
+                )?;
+                for (log_id, message) in &rows {
+                    update.execute(params![chat_id, log_id, content_hash(&salt, message)])?;
+                }
+                rows.len()
+            }
+            (Some(ContentMode::Hash), ContentMode::Text) => {
+                tx.execute("DELETE FROM chat_sync WHERE chat_id = ?1", params![chat_id])?;
+                tx.execute("DELETE FROM messages WHERE chat_id = ?1", params![chat_id])?
+            }
+            _ => 0,
+        };
+        record_content_mode(&tx, chat_id, mode)?;
+        tx.commit()?;
+        Ok(changed)
+    }
+
+    /// Digests of a chat's hashed rows by log_id. Empty for a
+    /// [`ContentMode::Text`] chat.
+    pub fn content_digests(&self, chat_id: i64) -> Result<HashMap<i64, ContentDigest>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT log_id, content_hash, text_length FROM messages
+             WHERE chat_id = ?1 AND content_hash IS NOT NULL",
+        )?;
+        let rows = stmt.query_map(params![chat_id], |row| {
+            Ok((
+                row.get(0)?,
+                ContentDigest {
+                    hash: row.get(1)?,
+                    length: row.get(2)?,
+                },
+            ))
+        })?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Into::into)
+    }
+
+    /// Messages, characters and first and last message per author of a chat
+    /// (feed messages excluded), most active first.
+    pub fn author_activity(&self, chat_id: i64) -> Result<Vec<AuthorActivity>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT author_id,
+                    (SELECT author_name FROM messages AS named
+                     WHERE named.chat_id = ?1 AND named.author_id = m.author_id
+                       AND named.author_name != ''
+                     ORDER BY named.send_at DESC LIMIT 1),
+                    COUNT(*), SUM(text_length), MIN(send_at), MAX(send_at)
+             FROM messages AS m
+             WHERE chat_id = ?1 AND message_type != 0
+             GROUP BY author_id
+             ORDER BY COUNT(*) DESC, author_id ASC",
+        )?;
+        let rows = stmt.query_map(params![chat_id], |row| {
+            Ok(AuthorActivity {
+                author_id: row.get(0)?,
+                author_name: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                messages: row.get(2)?,
+                characters: row.get(3)?,
+                first_at: row.get(4)?,
+                last_at: row.get(5)?,
+            })
+        })?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Into::into)
+    }
+
+    /// Replace the known member names of a chat with `members`.
+    pub fn store_member_names(&self, chat_id: i64, members: &HashMap<i64, String>) -> Result<()> {
+        let now = chrono::Utc::now().timestamp();
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "DELETE FROM chat_members WHERE chat_id = ?1",
+            params![chat_id],
+        )?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO chat_members (chat_id, user_id, name, updated_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for (user_id, name) in members {
+                stmt.execute(params![chat_id, user_id, name, now])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Member names last stored for a chat; empty when none are cached.
+    pub fn member_names(&self, chat_id: i64) -> Result<HashMap<i64, String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT user_id, name FROM chat_members WHERE chat_id = ?1")?;
+        let rows = stmt.query_map(params![chat_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        let mut names = HashMap::new();
+        for row in rows {
+            let (user_id, name) = row?;
+            names.insert(user_id, name);
+        }
+        Ok(names)
+    }
+
+    /// Compare the chats of a full chat list seen at `observed_at` with the
+    /// archive: archived chats missing from it are marked departed as of
+    /// `observed_at`, departed chats back in it are unmarked. A chat that
+    /// stays missing keeps the time it first went missing.
+    pub fn record_chat_list(
+        &self,
+        chat_ids: impl IntoIterator<Item = i64>,
+        observed_at: i64,
+    ) -> Result<ChatListChanges> {
+        let listed: HashSet<i64> = chat_ids.into_iter().collect();
+        let tx = self.conn.unchecked_transaction()?;
+        let archived: Vec<i64> = {
+            let mut stmt = tx.prepare(
+                "SELECT chat_id FROM chat_sync
+                 UNION SELECT DISTINCT chat_id FROM messages
+                 ORDER BY chat_id",
+            )?;
+            let rows = stmt.query_map([], |row| row.get(0))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        let mut changes = ChatListChanges::default();
+        for chat_id in archived {
+            if listed.contains(&chat_id) {
+                continue;
+            }
+            let inserted = tx.execute(
+                "INSERT OR IGNORE INTO chat_departures (chat_id, departed_at) VALUES (?1, ?2)",
+                params![chat_id, observed_at],
+            )?;
+            if inserted > 0 {
+                changes.departed.push(chat_id);
+            }
+        }
+        let departed: Vec<i64> = {
+            let mut stmt = tx.prepare("SELECT chat_id FROM chat_departures ORDER BY chat_id")?;
+            let rows = stmt.query_map([], |row| row.get(0))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        for chat_id in departed {
+            if listed.contains(&chat_id) {
+                tx.execute(
+                    "DELETE FROM chat_departures WHERE chat_id = ?1",
+                    params![chat_id],
+                )?;
+                changes.returned.push(chat_id);
+            }
+        }
+        tx.commit()?;
+        Ok(changes)
+    }
+
+    /// When `chat_id` went missing from the chat list, if it is departed.
+    pub fn departed_at(&self, chat_id: i64) -> Result<Option<i64>> {
+        self.conn
+            .query_row(
+                "SELECT departed_at FROM chat_departures WHERE chat_id = ?1",
+                params![chat_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(Into::into)
+    }
+
+    /// Every departed chat and when it went missing.
+    pub fn departed_chats(&self) -> Result<HashMap<i64, i64>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT chat_id, departed_at FROM chat_departures")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Into::into)
+    }
+
+    /// Delete everything cached for one chat (messages, sync cursor, member
+    /// names, title history, content mode and departure). Returns the number of
+    /// messages removed.
+    pub fn clear_chat(&self, chat_id: i64) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        let removed = tx.execute("DELETE FROM messages WHERE chat_id = ?1", params![chat_id])?;
+        for table in [
+            "chat_sync",
+            "chat_members",
+            "chat_titles",
+            "chat_archive",
+            "chat_departures",
+        ] {
+            tx.execute(
+                &format!("DELETE FROM {table} WHERE chat_id = ?1"),
+                params![chat_id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(removed)
+    }
+
+    /// Delete every cached chat. The search index setting is kept. Returns
+    /// the number of messages removed.
+    pub fn clear_all(&self) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        let removed = tx.execute("DELETE FROM messages", [])?;
+        tx.execute_batch(
+            "DELETE FROM chat_sync; DELETE FROM chat_members; DELETE FROM chat_titles;
+             DELETE FROM chat_archive; DELETE FROM chat_departures;",
+        )?;
+        tx.commit()?;
+        Ok(removed)
+    }
+
     /// Get total message count.
     pub fn total_count(&self) -> Result<i64> {
         self.conn
@@ -372,9 +1453,8 @@ impl MessageDb {
     }
 }
 
-fn db_path() -> Result<PathBuf> {
-    let home = dirs::home_dir().context("Could not resolve home directory")?;
-    Ok(home.join(".config").join("openkakao").join("messages.db"))
+pub fn db_path() -> Result<PathBuf> {
+    Ok(crate::paths::profile_dir()?.join("messages.db"))
 }
 
 #[cfg(test)]
@@ -425,6 +1505,27 @@ mod tests {
         assert_eq!(results.len(), 2);
     }
 
+    #[test]
+    fn count_and_bounds_per_chat() {
+        let db = test_db();
+        assert_eq!(db.message_count(1).unwrap(), 0);
+        assert_eq!(db.time_bounds(1).unwrap(), None);
+        let msg = |chat_id, log_id, send_at| CachedMessage {
+            chat_id,
+            log_id,
+            author_id: 42,
+            author_name: "Alice".into(),
+            message_type: 1,
+            message: "hi".into(),
+            attachment: String::new(),
+            send_at,
+        };
+        db.upsert_messages(&[msg(1, 1, 300), msg(1, 2, 100), msg(2, 3, 50)])
+            .unwrap();
+        assert_eq!(db.message_count(1).unwrap(), 2);
+        assert_eq!(db.time_bounds(1).unwrap(), Some((100, 300)));
+    }
+
     #[test]
     fn sync_cursor_roundtrip() {
         let db = test_db();
@@ -479,7 +1580,7 @@ mod tests {
         ];
 
         db.upsert_messages(&msgs).unwrap();
-        let results = db.search_all("meeting", 10).unwrap();
+        let results = db.search_all("meeting", 10, false).unwrap();
         assert_eq!(results.len(), 2);
     }
 
@@ -613,4 +1714,387 @@ mod tests {
         let results = db.search(1, "\"world\"", 10).unwrap();
         assert_eq!(results.len(), 1);
     }
+
+    fn message(log_id: i64, text: &str) -> CachedMessage {
+        CachedMessage {
+            chat_id: 1,
+            log_id,
+            author_id: 42,
+            author_name: "Alice".into(),
+            message_type: 1,
+            message: text.into(),
+            attachment: String::new(),
+            send_at: 1700000000 + log_id,
+        }
+    }
+
+    #[test]
+    fn normalized_index_matches_variants_and_follows_reindex() {
+        let db = test_db();
+        db.upsert_messages(&[message(1, "ㅋㅋㅋㅋㅋ"), message(2, "ＯＫ！")])
+            .unwrap();
+        assert!(!db.is_normalized().unwrap());
+        // The plain index sees different tokens.
+        assert!(db.search(1, "ㅋㅋ", 10).unwrap().is_empty());
+        assert!(db.search(1, "ok!", 10).unwrap().is_empty());
+
+        assert_eq!(db.reindex(true).unwrap(), 2);
+        assert!(db.is_normalized().unwrap());
+        assert_eq!(db.search(1, "ㅋㅋ", 10).unwrap()[0].log_id, 1);
+        assert_eq!(
+            db.search_all("ㅋㅋㅋㅋㅋㅋㅋ", 10, false).unwrap()[0].log_id,
+            1
+        );
+        assert_eq!(db.search(1, "ok", 10).unwrap()[0].log_id, 2);
+        // Results carry the text as sent.
+        assert_eq!(db.search(1, "ok", 10).unwrap()[0].message, "ＯＫ！");
+
+        // Messages synced later are indexed the same way.
+        db.upsert_messages(&[message(3, "Ｈｅｌｌｏ ㅎㅎㅎ")])
+            .unwrap();
+        assert_eq!(db.search(1, "hello", 10).unwrap()[0].log_id, 3);
+        assert_eq!(db.search_like(1, "hello ㅎㅎ", 10).unwrap()[0].log_id, 3);
+
+        // Switching back restores the plain index.
+        assert_eq!(db.reindex(false).unwrap(), 3);
+        assert!(!db.is_normalized().unwrap());
+        assert!(db.search(1, "ㅋㅋ", 10).unwrap().is_empty());
+        assert_eq!(db.search(1, "ㅋㅋㅋㅋㅋ", 10).unwrap()[0].log_id, 1);
+    }
+
+    #[test]
+    fn titles_are_recorded_only_when_they_change() {
+        let db = test_db();
+        assert!(db
+            .record_titles([(1, "Team"), (2, ""), (3, "Family")], 100)
+            .unwrap()
+            .is_empty());
+        let changes = db
+            .record_titles([(1, "Team"), (3, "Family 🏠")], 200)
+            .unwrap();
+        assert_eq!(
+            changes,
+            [TitleChange {
+                chat_id: 3,
+                from: "Family".into(),
+                to: "Family 🏠".into()
+            }]
+        );
+        db.record_titles([(3, "Family")], 300).unwrap();
+
+        let titles: Vec<_> = db
+            .title_history(3)
+            .unwrap()
+            .into_iter()
+            .map(|entry| (entry.title, entry.observed_at))
+            .collect();
+        assert_eq!(
+            titles,
+            [
+                ("Family".to_string(), 100),
+                ("Family 🏠".to_string(), 200),
+                ("Family".to_string(), 300)
+            ]
+        );
+        assert_eq!(db.title_history(1).unwrap().len(), 1);
+        assert!(db.title_history(2).unwrap().is_empty());
+    }
+
+    #[test]
+    fn title_at_picks_the_title_in_effect() {
+        let history = [
+            TitleEntry {
+                title: "Old".into(),
+                observed_at: 100,
+            },
+            TitleEntry {
+                title: "New".into(),
+                observed_at: 200,
+            },
+        ];
+        assert_eq!(title_at(&history, 50), Some("Old"));
+        assert_eq!(title_at(&history, 150), Some("Old"));
+        assert_eq!(title_at(&history, 200), Some("New"));
+        assert_eq!(title_at(&[], 200), None);
+    }
+
+    #[test]
+    fn old_databases_gain_the_normalized_column() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE messages (
+                chat_id INTEGER NOT NULL, log_id INTEGER NOT NULL,
+                author_id INTEGER NOT NULL, author_name TEXT NOT NULL DEFAULT '',
+                message_type INTEGER NOT NULL DEFAULT 1, message TEXT NOT NULL DEFAULT '',
+                attachment TEXT NOT NULL DEFAULT '', send_at INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (chat_id, log_id));
+             INSERT INTO messages (chat_id, log_id, author_id, message) VALUES (1, 1, 42, 'ㅠㅠㅠ');",
+        )
+        .unwrap();
+        let db = MessageDb { conn };
+        db.init_schema().unwrap();
+        db.reindex(true).unwrap();
+        assert_eq!(db.search(1, "ㅠㅠ", 10).unwrap().len(), 1);
+        assert_eq!(db.schema_version().unwrap(), SCHEMA_VERSION);
+        assert!(db.member_names(1).unwrap().is_empty());
+        // Chats cached before content modes kept their text.
+        assert_eq!(db.content_mode(1).unwrap(), Some(ContentMode::Text));
+        assert_eq!(db.author_activity(1).unwrap()[0].characters, 3);
+    }
+
+    #[test]
+    fn schema_is_stamped_once_and_newer_versions_are_refused() {
+        let db = test_db();
+        assert_eq!(db.schema_version().unwrap(), SCHEMA_VERSION);
+        // Reopening an up-to-date database runs no migration again.
+        db.init_schema().unwrap();
+
+        db.conn
+            .pragma_update(None, "user_version", SCHEMA_VERSION + 1)
+            .unwrap();
+        let err = db.init_schema().unwrap_err().to_string();
+        assert!(err.contains("newer"), "{err}");
+    }
+
+    #[test]
+    fn notices_are_kept_once_per_revision() {
+        let db = test_db();
+        let notice = |revision: i64, content: &str| {
+            Notice::from_meta(&serde_json::json!({
+                "type": 1,
+                "authorId": 7,
+                "content": content,
+                "updatedAt": 1_700_000_000 + revision,
+                "revision": revision,
+            }))
+            .unwrap()
+        };
+        assert!(db.record_notice(1, &notice(1, "first")).unwrap());
+        assert!(!db.record_notice(1, &notice(1, "first")).unwrap());
+        assert!(db.record_notice(1, &notice(2, "second")).unwrap());
+        assert!(db.record_notice(2, &notice(1, "other room")).unwrap());
+        let history: Vec<String> = db
+            .notice_history(1)
+            .unwrap()
+            .into_iter()
+            .map(|n| n.content)
+            .collect();
+        assert_eq!(history, ["second", "first"]);
+    }
+
+    fn row(chat_id: i64, log_id: i64) -> CachedMessage {
+        CachedMessage {
+            chat_id,
+            log_id,
+            author_id: 7,
+            author_name: String::new(),
+            message_type: 1,
+            message: format!("m{log_id}"),
+            attachment: String::new(),
+            send_at: 1_700_000_000 + log_id,
+        }
+    }
+
+    #[test]
+    fn older_pages_and_member_names_come_from_the_cache() {
+        let db = test_db();
+        let msgs: Vec<_> = (1..=5).map(|id| row(1, id)).collect();
+        db.upsert_messages(&msgs).unwrap();
+        let page = db.get_messages_before(1, 5, 2).unwrap();
+        assert_eq!(page.iter().map(|m| m.log_id).collect::<Vec<_>>(), [3, 4]);
+        assert!(db.get_messages_before(1, 1, 10).unwrap().is_empty());
+
+        db.store_member_names(1, &HashMap::from([(7, "Kim".to_string())]))
+            .unwrap();
+        db.store_member_names(1, &HashMap::from([(8, "Lee".to_string())]))
+            .unwrap();
+        assert_eq!(
+            db.member_names(1).unwrap(),
+            HashMap::from([(8, "Lee".to_string())])
+        );
+    }
+
+    #[test]
+    fn cached_rows_round_trip_to_messages() {
+        let mut row = row(3, 9);
+        row.author_name = "Kim".into();
+        let msg = row.to_message();
+        assert_eq!((msg.log_id, msg.author_id), (9, 7));
+        assert_eq!(msg.send_at, 1_700_000_009);
+        assert_eq!(msg.message, "m9");
+        assert_eq!(msg.author_nickname.as_deref(), Some("Kim"));
+        let back = CachedMessage::from_message(3, &msg, "Kim".into());
+        assert_eq!((back.log_id, back.message), (9, "m9".to_string()));
+    }
+
+    /// An original and the marker its author's delete-for-everyone left.
+    fn deleted_pair() -> [CachedMessage; 2] {
+        let original = CachedMessage {
+            message: "wrong room, sorry".into(),
+            ..row(4, 100)
+        };
+        let marker = CachedMessage {
+            message_type: 0,
+            message: r#"{"feedType":14,"logId":"100","hidden":true}"#.into(),
+            ..row(4, 101)
+        };
+        [original, marker]
+    }
+
+    #[test]
+    fn delete_markers_flag_their_original_in_either_order() {
+        let [original, marker] = deleted_pair();
+        let db = test_db();
+        db.upsert_messages(&[original.clone(), marker.clone(), row(4, 102)])
+            .unwrap();
+        assert_eq!(db.deleted_log_ids(4).unwrap(), HashSet::from([100]));
+
+        // The marker may be cached before an older page brings the original.
+        let db = test_db();
+        db.upsert_messages(std::slice::from_ref(&marker)).unwrap();
+        assert!(db.deleted_log_ids(4).unwrap().is_empty());
+        db.upsert_messages(std::slice::from_ref(&original)).unwrap();
+        assert_eq!(db.deleted_log_ids(4).unwrap(), HashSet::from([100]));
+
+        // A later fetch serving a stub does not erase the archived content.
+        let stub = CachedMessage {
+            message: String::new(),
+            ..original.clone()
+        };
+        db.upsert_messages(&[stub]).unwrap();
+        let kept = db.get_messages(4, 0).unwrap();
+        assert_eq!(kept[0].message, "wrong room, sorry");
+    }
+
+    #[test]
+    fn archive_reads_respect_or_reveal_deletions() {
+        let [original, marker] = deleted_pair();
+        let rows = vec![row(4, 99), original, marker.clone()];
+        let deleted = HashSet::from([100]);
+        let text = |view| {
+            apply_deletions(rows.clone(), &deleted, view)
+                .into_iter()
+                .map(|(m, is_deleted)| (m.log_id, m.message, is_deleted))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            text(DeletionView::Respect),
+            [
+                (99, "m99".to_string(), false),
+                (100, DELETED_MESSAGE_TEXT.to_string(), true)
+            ]
+        );
+        assert_eq!(
+            text(DeletionView::Show),
+            [
+                (99, "m99".to_string(), false),
+                (100, "wrong room, sorry".to_string(), true)
+            ]
+        );
+        // Without its original the marker itself stands in for it.
+        let alone = apply_deletions(vec![marker], &HashSet::new(), DeletionView::Show);
+        assert_eq!(alone[0].0.message, DELETED_MESSAGE_TEXT);
+    }
+
+    #[test]
+    fn markers_cached_before_v3_are_flagged_on_upgrade() {
+        let db = test_db();
+        let [original, marker] = deleted_pair();
+        db.upsert_messages(&[original, marker]).unwrap();
+        db.conn
+            .execute_batch("UPDATE messages SET deleted = 0, deletes_log_id = NULL")
+            .unwrap();
+        backfill_delete_markers(&db.conn).unwrap();
+        assert_eq!(db.deleted_log_ids(4).unwrap(), HashSet::from([100]));
+    }
+
+    #[test]
+    fn clearing_one_chat_keeps_the_others() {
+        let db = test_db();
+        db.upsert_messages(&[row(1, 1), row(1, 2), row(2, 1)])
+            .unwrap();
+        db.store_member_names(1, &HashMap::from([(7, "Kim".to_string())]))
+            .unwrap();
+        assert_eq!(db.clear_chat(1).unwrap(), 2);
+        assert!(db.member_names(1).unwrap().is_empty());
+        assert_eq!(db.message_count(2).unwrap(), 1);
+        assert_eq!(db.clear_all().unwrap(), 1);
+        assert_eq!(db.total_count().unwrap(), 0);
+    }
+
+    /// The same conversation, with a join feed, for chat `chat_id`.
+    fn conversation(chat_id: i64) -> Vec<CachedMessage> {
+        let say = |log_id: i64, author_id: i64, text: &str| CachedMessage {
+            author_id,
+            message: text.into(),
+            ..row(chat_id, log_id)
+        };
+        vec![
+            CachedMessage {
+                message_type: 0,
+                message: r#"{"feedType":4,"members":[{"userId":8}]}"#.into(),
+                ..row(chat_id, 1)
+            },
+            say(2, 7, "안녕하세요"),
+            say(3, 8, "hi"),
+            say(4, 7, "안녕하세요"),
+        ]
+    }
+
+    #[test]
+    fn hashed_chats_keep_no_text_and_the_same_statistics() {
+        let db = test_db();
+        db.upsert_messages(&conversation(1)).unwrap();
+        db.set_content_mode(2, ContentMode::Hash).unwrap();
+        db.upsert_messages(&conversation(2)).unwrap();
+        assert_eq!(db.content_mode(1).unwrap(), Some(ContentMode::Text));
+        assert_eq!(db.content_mode(2).unwrap(), Some(ContentMode::Hash));
+
+        assert_eq!(db.message_count(1).unwrap(), db.message_count(2).unwrap());
+        assert_eq!(db.time_bounds(1).unwrap(), db.time_bounds(2).unwrap());
+        let activity = db.author_activity(1).unwrap();
+        assert_eq!(activity, db.author_activity(2).unwrap());
+        assert_eq!((activity[0].author_id, activity[0].characters), (7, 10));
+        let counts: HashMap<i64, i64> = db
+            .chat_stats()
+            .unwrap()
+            .into_iter()
+            .map(|(chat_id, count, _)| (chat_id, count))
+            .collect();
+        assert_eq!(counts[&1], counts[&2]);
+
+        let rows = db.get_messages_before(2, i64::MAX, 10).unwrap();
+        assert!(rows[1..].iter().all(|m| m.message.is_empty()));
+        assert!(rows[0].message.contains("feedType"));
+        assert!(db.search(2, "hi", 10).unwrap().is_empty());
+        let digests = db.content_digests(2).unwrap();
+        assert_eq!(digests.len(), 3);
+        assert_eq!(digests[&2], digests[&4]);
+        assert_ne!(digests[&2].hash, digests[&3].hash);
+        assert_eq!(digests[&3].length, 2);
+        assert!(db.content_digests(1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn switching_modes_hashes_in_place_or_drops_for_a_refetch() {
+        let db = test_db();
+        db.upsert_messages(&conversation(1)).unwrap();
+        db.update_sync_cursor(1, 4).unwrap();
+        let before = db.author_activity(1).unwrap();
+
+        assert_eq!(db.set_content_mode(1, ContentMode::Hash).unwrap(), 3);
+        assert_eq!(db.author_activity(1).unwrap(), before);
+        assert_eq!(db.content_digests(1).unwrap().len(), 3);
+        // Hashing what is already hashed changes nothing.
+        assert_eq!(db.set_content_mode(1, ContentMode::Hash).unwrap(), 0);
+
+        assert_eq!(db.set_content_mode(1, ContentMode::Text).unwrap(), 4);
+        assert_eq!(db.get_sync_cursor(1).unwrap(), None);
+        assert_eq!(db.content_mode(1).unwrap(), Some(ContentMode::Text));
+        db.upsert_messages(&conversation(1)).unwrap();
+        assert_eq!(db.search(1, "hi", 10).unwrap().len(), 1);
+
+        db.clear_chat(1).unwrap();
+        assert_eq!(db.content_mode(1).unwrap(), None);
+    }
 }
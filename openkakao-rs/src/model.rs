@@ -1,8 +1,13 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use zeroize::Zeroize;
 
+use crate::error::OpenKakaoError;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct KakaoCredentials {
     pub oauth_token: String,
     pub user_id: i64,
@@ -50,6 +55,76 @@ impl KakaoCredentials {
     }
 }
 
+/// A field [`KakaoCredentials`] typed in by hand or taken from the
+/// environment often lacks, and how the client copes without it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialGap {
+    /// `user_id` is 0. The REST client can fetch it from `profile3/me`
+    /// ([`backfill_user_id`](crate::rest::KakaoRestClient::backfill_user_id)).
+    UserId,
+    /// No `A` header: it is derived from `app_version`, so requests only
+    /// approximate the app's.
+    AHeader,
+    /// No device UUID: LOCO login and media downloads are refused.
+    DeviceUuid,
+}
+
+impl CredentialGap {
+    pub fn label(&self) -> &'static str {
+        match self {
+            CredentialGap::UserId => "user_id",
+            CredentialGap::AHeader => "A header",
+            CredentialGap::DeviceUuid => "device_uuid",
+        }
+    }
+
+    /// What is done about it, for warnings and `doctor`.
+    pub fn consequence(&self) -> &'static str {
+        match self {
+            CredentialGap::UserId => "fetched from profile3/me on first use",
+            CredentialGap::AHeader => "derived from the app version; requests are approximate",
+            CredentialGap::DeviceUuid => {
+                "LOCO (watch, send, read --all) and media downloads are skipped"
+            }
+        }
+    }
+}
+
+impl KakaoCredentials {
+    /// What these credentials lack, in [`CredentialGap`] order.
+    pub fn gaps(&self) -> Vec<CredentialGap> {
+        let mut gaps = Vec::new();
+        if self.user_id == 0 {
+            gaps.push(CredentialGap::UserId);
+        }
+        if self.is_approximate() {
+            gaps.push(CredentialGap::AHeader);
+        }
+        if self.device_uuid.trim().is_empty() {
+            gaps.push(CredentialGap::DeviceUuid);
+        }
+        gaps
+    }
+
+    /// Whether the `A` header sent is derived (`mac/<app_version>/ko`)
+    /// rather than the one the app sends.
+    pub fn is_approximate(&self) -> bool {
+        self.a_header.trim().is_empty()
+    }
+
+    /// An error naming `feature` when there is no device UUID, which it
+    /// needs; checked before anything is sent.
+    pub fn require_device_uuid(&self, feature: &str) -> Result<(), OpenKakaoError> {
+        if !self.device_uuid.trim().is_empty() {
+            return Ok(());
+        }
+        Err(OpenKakaoError::Other(anyhow::anyhow!(
+            "{feature} needs a device UUID, which these credentials lack; run 'openkakao-rs login --save' with KakaoTalk running"
+        )))
+    }
+}
+
 impl Drop for KakaoCredentials {
     fn drop(&mut self) {
         self.oauth_token.zeroize();
@@ -63,39 +138,370 @@ impl Drop for KakaoCredentials {
 }
 
 #[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[non_exhaustive]
 pub struct Friend {
     pub user_id: i64,
     pub nickname: String,
     pub friend_nickname: String,
     pub phone_number: String,
+    /// ISO country of the friend's account, when the payload names it.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub country_iso: String,
     pub status_message: String,
     pub favorite: bool,
     pub hidden: bool,
+    /// Kakao Channel (plus friend) account rather than a person.
+    pub channel: bool,
+    /// User-settable Kakao ID (talk UUID), the handle people share.
+    pub uuid: String,
+    /// Full-size profile image; empty when the friend has none set.
+    pub profile_image_url: String,
+    /// Our 1:1 chat with this friend, when the friends payload names it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub direct_chat_id: Option<i64>,
 }
 
 impl Friend {
     pub fn display_name(&self) -> String {
-        if self.friend_nickname.is_empty() {
-            self.nickname.clone()
-        } else {
-            self.friend_nickname.clone()
-        }
+        display_name(&self.nickname, &self.friend_nickname, "")
     }
 
     pub fn from_json(v: &Value) -> Self {
+        let mut profile_image_url = json_string(v, "fullProfileImageUrl");
+        if profile_image_url.is_empty() {
+            profile_image_url = json_string(v, "profileImageUrl");
+        }
         Self {
             user_id: json_i64(v, "userId"),
             nickname: json_string(v, "nickName"),
             friend_nickname: json_string(v, "friendNickName"),
             phone_number: json_string(v, "phoneNumber"),
+            country_iso: json_string(v, "countryIso"),
             status_message: json_string(v, "statusMessage"),
             favorite: v.get("favorite").and_then(Value::as_bool).unwrap_or(false),
             hidden: v.get("hidden").and_then(Value::as_bool).unwrap_or(false),
+            channel: v.get("plusFriendProfile").is_some_and(|p| !p.is_null())
+                || json_string(v, "type").to_ascii_lowercase().contains("plus"),
+            uuid: kakao_id(v),
+            profile_image_url,
+            direct_chat_id: Some(json_i64(v, "directChatId")).filter(|id| *id != 0),
+        }
+    }
+}
+
+/// Shown when a person has no usable name at all (withdrawn accounts,
+/// members the server sent without a profile).
+pub const UNKNOWN_DISPLAY_NAME: &str = "(알 수 없음)";
+
+/// The one name-fallback policy for people: the nickname you gave a friend,
+/// then the open chat profile they use in that room, then their own
+/// nickname. Blank or whitespace-only names count as missing.
+pub fn display_name(nickname: &str, friend_nickname: &str, open_profile_name: &str) -> String {
+    known_display_name(nickname, friend_nickname, open_profile_name)
+        .unwrap_or(UNKNOWN_DISPLAY_NAME)
+        .to_string()
+}
+
+fn known_display_name<'a>(
+    nickname: &'a str,
+    friend_nickname: &'a str,
+    open_profile_name: &'a str,
+) -> Option<&'a str> {
+    [friend_nickname, open_profile_name, nickname]
+        .into_iter()
+        .map(str::trim)
+        .find(|name| !name.is_empty())
+}
+
+/// Open chat profile nickname (`openProfile.nickName`), empty outside open chats.
+fn open_profile_name(v: &Value) -> String {
+    v.get("openProfile")
+        .map(|p| json_string(p, "nickName"))
+        .unwrap_or_default()
+}
+
+fn kakao_id(v: &Value) -> String {
+    let id = json_string(v, "UUID");
+    if id.is_empty() {
+        json_string(v, "uuid")
+    } else {
+        id
+    }
+}
+
+/// Result of a Kakao ID search; `friend` is false for users not yet added.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct KakaoIdMatch {
+    pub user_id: i64,
+    pub nickname: String,
+    pub uuid: String,
+    pub status_message: String,
+    pub profile_image_url: String,
+    pub friend: bool,
+}
+
+impl KakaoIdMatch {
+    /// Parse the `member` object of a `find_by_uuid` response.
+    pub fn from_json(v: &Value) -> Self {
+        let mut profile_image_url = json_string(v, "fullProfileImageUrl");
+        if profile_image_url.is_empty() {
+            profile_image_url = json_string(v, "profileImageUrl");
+        }
+        Self {
+            user_id: json_i64(v, "userId"),
+            nickname: json_string(v, "nickName"),
+            uuid: kakao_id(v),
+            status_message: json_string(v, "statusMessage"),
+            profile_image_url,
+            friend: v.get("friend").and_then(Value::as_bool).unwrap_or(false),
+        }
+    }
+}
+
+/// Scheme of message references printed by `--links` and resolved by `open`.
+pub const MESSAGE_LINK_SCHEME: &str = "openkakao";
+
+/// Reference to one message: `openkakao://chat/<chat_id>/<log_id>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageLink {
+    pub chat_id: i64,
+    pub log_id: i64,
+}
+
+impl MessageLink {
+    pub fn new(chat_id: i64, log_id: i64) -> Self {
+        Self { chat_id, log_id }
+    }
+}
+
+impl std::fmt::Display for MessageLink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{MESSAGE_LINK_SCHEME}://chat/{}/{}",
+            self.chat_id, self.log_id
+        )
+    }
+}
+
+impl std::str::FromStr for MessageLink {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!("invalid message link '{s}'; expected {MESSAGE_LINK_SCHEME}://chat/<chat_id>/<log_id>")
+        };
+        let rest = s
+            .strip_prefix(MESSAGE_LINK_SCHEME)
+            .and_then(|r| r.strip_prefix("://chat/"))
+            .ok_or_else(invalid)?;
+        let (chat_id, log_id) = rest
+            .trim_end_matches('/')
+            .split_once('/')
+            .ok_or_else(invalid)?;
+        Ok(Self {
+            chat_id: chat_id.parse().map_err(|_| invalid())?,
+            log_id: log_id.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Host of open chat links.
+pub const OPEN_LINK_HOST: &str = "open.kakao.com";
+
+/// What `openchat info` and `openchat join` are given: an
+/// `https://open.kakao.com/o/<code>` link or a numeric link id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OpenLinkRef {
+    Url(String),
+    Id(i64),
+}
+
+impl std::str::FromStr for OpenLinkRef {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Ok(id) = s.parse::<i64>() {
+            return if id > 0 {
+                Ok(Self::Id(id))
+            } else {
+                Err(format!("invalid open chat link id '{s}'"))
+            };
+        }
+        let rest = s
+            .strip_prefix("https://")
+            .or_else(|| s.strip_prefix("http://"))
+            .unwrap_or(s);
+        let code = rest
+            .strip_prefix(OPEN_LINK_HOST)
+            .and_then(|r| r.strip_prefix("/o/"))
+            .map(|r| r.split(['?', '#']).next().unwrap_or_default())
+            .map(|r| r.trim_end_matches('/'))
+            .filter(|code| !code.is_empty() && code.chars().all(|c| c.is_ascii_alphanumeric()))
+            .ok_or_else(|| {
+                format!("invalid open chat link '{s}'; expected https://{OPEN_LINK_HOST}/o/<code> or a link id")
+            })?;
+        Ok(Self::Url(format!("https://{OPEN_LINK_HOST}/o/{code}")))
+    }
+}
+
+impl std::fmt::Display for OpenLinkRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Url(url) => f.write_str(url),
+            Self::Id(id) => write!(f, "link {id}"),
+        }
+    }
+}
+
+/// An open chat link as `openchat info` shows it, looked up without
+/// joining. `chat_id` is set when I am already in the room.
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct OpenLink {
+    pub link_id: i64,
+    pub name: String,
+    pub url: String,
+    pub description: String,
+    /// `multi` (a group room) or `direct` (a one-to-one profile link).
+    pub kind: String,
+    pub host_nickname: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub member_count: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub member_limit: Option<i64>,
+    pub passcode_required: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chat_id: Option<i64>,
+}
+
+impl OpenLink {
+    /// Parse the `openLink` object of an `openlink/info` response (or the
+    /// response itself when the link is not nested).
+    pub fn from_json(v: &Value) -> Self {
+        let link = v.get("openLink").filter(|l| l.is_object()).unwrap_or(v);
+        let first = |keys: &[&str]| {
+            keys.iter()
+                .map(|key| json_string(link, key))
+                .find(|s| !s.is_empty())
+                .unwrap_or_default()
+        };
+        let positive = |keys: &[&str]| keys.iter().map(|key| json_i64(link, key)).find(|n| *n > 0);
+        let host = link
+            .get("openLinkOwner")
+            .or_else(|| link.get("host"))
+            .map(|owner| json_string(owner, "nickname"))
+            .unwrap_or_default();
+        Self {
+            link_id: positive(&["linkId", "li"]).unwrap_or_default(),
+            name: first(&["linkName", "name", "ln"]),
+            url: first(&["linkURL", "linkUrl", "url", "lu"]),
+            description: first(&["description", "desc"]),
+            kind: match json_i64(link, "linkType") {
+                2 => "direct",
+                _ => "multi",
+            }
+            .to_string(),
+            host_nickname: host,
+            member_count: positive(&["activeMembersCount", "memberCount", "headCount"]),
+            member_limit: positive(&["maxUser", "memberLimit", "maxUserLimit"]),
+            passcode_required: ["passcodeRequired", "hasPasscode", "locked"]
+                .iter()
+                .any(|key| link.get(*key).and_then(Value::as_bool) == Some(true)),
+            chat_id: positive(&["chatId", "joinedChatId"]),
+        }
+    }
+
+    /// Whether the room has no seat left, when both counts are known.
+    pub fn is_full(&self) -> bool {
+        matches!((self.member_count, self.member_limit), (Some(count), Some(limit)) if count >= limit)
+    }
+}
+
+/// Which friends a listing keeps with respect to the hidden flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HiddenFilter {
+    /// Default listing: hidden friends are left out.
+    Exclude,
+    /// `--hidden`: everyone.
+    Include,
+    /// `--hidden-only`: hidden friends alone.
+    Only,
+}
+
+impl HiddenFilter {
+    pub fn from_flags(hidden: bool, hidden_only: bool) -> Self {
+        if hidden_only {
+            HiddenFilter::Only
+        } else if hidden {
+            HiddenFilter::Include
+        } else {
+            HiddenFilter::Exclude
+        }
+    }
+
+    pub fn keeps(&self, friend: &Friend) -> bool {
+        match self {
+            HiddenFilter::Exclude => !friend.hidden,
+            HiddenFilter::Include => true,
+            HiddenFilter::Only => friend.hidden,
+        }
+    }
+}
+
+/// Friend list totals for `summary`.
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq, Eq)]
+pub struct FriendCounts {
+    pub total: usize,
+    pub favorites: usize,
+    pub hidden: usize,
+    pub channels: usize,
+}
+
+impl FriendCounts {
+    pub fn from_friends(friends: &[Friend]) -> Self {
+        Self {
+            total: friends.len(),
+            favorites: friends.iter().filter(|f| f.favorite).count(),
+            hidden: friends.iter().filter(|f| f.hidden).count(),
+            channels: friends.iter().filter(|f| f.channel).count(),
         }
     }
 }
 
+/// Chat list totals for `summary`, keyed by raw chat type.
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+pub struct ChatCounts {
+    pub total: usize,
+    pub by_type: std::collections::BTreeMap<String, usize>,
+    pub unread_chats: usize,
+    pub unread_messages: i64,
+}
+
+impl ChatCounts {
+    pub fn from_rooms(rooms: &[ChatRoom]) -> Self {
+        let mut counts = Self {
+            total: rooms.len(),
+            ..Self::default()
+        };
+        for room in rooms {
+            *counts.by_type.entry(room.kind.clone()).or_insert(0) += 1;
+            if room.unread_count > 0 {
+                counts.unread_chats += 1;
+                counts.unread_messages += room.unread_count;
+            }
+        }
+        counts
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[non_exhaustive]
 pub struct MyProfile {
     pub nickname: String,
     pub status_message: String,
@@ -105,13 +511,421 @@ pub struct MyProfile {
     pub profile_image_url: String,
 }
 
+/// Everything `profile3/friend` tells about another user.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[non_exhaustive]
+pub struct MemberProfile {
+    pub user_id: i64,
+    pub nickname: String,
+    pub status_message: String,
+    /// Thumbnail, full-size and original profile images; empty when unset.
+    pub profile_image_url: String,
+    pub full_profile_image_url: String,
+    pub original_profile_image_url: String,
+    pub background_image_url: String,
+    pub original_background_image_url: String,
+    /// Whether the profile shows a birthday today.
+    pub birthday: bool,
+    /// Kakao's account type, as sent (e.g. `100` for a person); empty when
+    /// absent.
+    pub account_type: String,
+    /// Kakao Channel (plus friend) account rather than a person.
+    pub channel: bool,
+}
+
+impl MemberProfile {
+    /// Parse the `profile` object of a `profile3/friend` answer. `None` when
+    /// there is none, or it names no one (deleted accounts come back empty).
+    pub fn from_json(v: &Value) -> Option<Self> {
+        let p = v.get("profile").filter(|p| p.is_object())?;
+        let first = |keys: &[&str]| {
+            keys.iter()
+                .map(|key| json_string(p, key))
+                .find(|value| !value.is_empty())
+                .unwrap_or_default()
+        };
+        let profile = Self {
+            user_id: json_i64(p, "userId"),
+            nickname: first(&["nickname", "nickName"]),
+            status_message: json_string(p, "statusMessage"),
+            profile_image_url: json_string(p, "profileImageUrl"),
+            full_profile_image_url: json_string(p, "fullProfileImageUrl"),
+            original_profile_image_url: json_string(p, "originalProfileImageUrl"),
+            background_image_url: first(&["backgroundImageUrl", "bgImageUrl"]),
+            original_background_image_url: first(&[
+                "originalBackgroundImageUrl",
+                "originalBgImageUrl",
+            ]),
+            birthday: ["isBirthday", "birthday"]
+                .iter()
+                .any(|key| p.get(*key).and_then(Value::as_bool).unwrap_or(false)),
+            account_type: match p.get("type") {
+                Some(Value::String(kind)) => kind.clone(),
+                Some(Value::Number(kind)) => kind.to_string(),
+                _ => String::new(),
+            },
+            channel: p.get("plusFriendProfile").is_some_and(|p| !p.is_null()),
+        };
+        (profile.user_id != 0 || !profile.nickname.is_empty()).then_some(profile)
+    }
+}
+
+/// `type` of the chat meta that holds a room's pinned notice (공지).
+pub const NOTICE_META_TYPE: i64 = 1;
+
+/// A room's pinned notice, from the `chatMetas` of a LOCO `CHATINFO`
+/// answer.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[non_exhaustive]
+pub struct Notice {
+    pub author_id: i64,
+    pub content: String,
+    /// Unix seconds of the last change.
+    pub updated_at: i64,
+    /// Bumped by the server on every change.
+    pub revision: i64,
+}
+
+impl Notice {
+    /// The notice among the metas of a `CHATINFO` answer (`chatInfo.chatMetas`
+    /// or a top-level `chatMetas`). `None` when the room has none or it was
+    /// cleared.
+    pub fn from_chat_info(v: &Value) -> Option<Self> {
+        let metas = v
+            .pointer("/chatInfo/chatMetas")
+            .or_else(|| v.get("chatMetas"))
+            .and_then(Value::as_array)?;
+        let meta = metas
+            .iter()
+            .find(|meta| json_i64(meta, "type") == NOTICE_META_TYPE)?;
+        Self::from_meta(meta)
+    }
+
+    /// One notice meta. Its `content` is the text, or a JSON object with the
+    /// text under `message` or `text`.
+    pub fn from_meta(meta: &Value) -> Option<Self> {
+        let raw = json_string(meta, "content");
+        let content = serde_json::from_str::<Value>(&raw)
+            .ok()
+            .filter(Value::is_object)
+            .map(|inner| {
+                ["message", "text"]
+                    .iter()
+                    .map(|key| json_string(&inner, key))
+                    .find(|text| !text.is_empty())
+                    .unwrap_or_default()
+            })
+            .unwrap_or(raw);
+        if content.trim().is_empty() {
+            return None;
+        }
+        Some(Self {
+            author_id: json_i64(meta, "authorId"),
+            content,
+            updated_at: crate::timestamp::unix_secs(json_i64(meta, "updatedAt") as f64),
+            revision: json_i64(meta, "revision"),
+        })
+    }
+}
+
+/// A post on the board (게시판) of an open chat community, from the moim
+/// API. Key names differ between app versions, so each field is read from
+/// the first key present.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[non_exhaustive]
+pub struct BoardPost {
+    /// Opaque; numeric ids are kept as their decimal text.
+    pub post_id: String,
+    /// `text`, `poll`, `image`, `notice`, ... as the server names it.
+    pub kind: String,
+    pub author_id: i64,
+    /// Unix seconds.
+    pub created_at: i64,
+    pub title: String,
+    pub content: String,
+    pub comment_count: i64,
+    /// Pinned as the community notice.
+    pub notice: bool,
+    /// Filled in by `board show` only.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub comments: Vec<BoardComment>,
+}
+
+/// A comment under a [`BoardPost`].
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[non_exhaustive]
+pub struct BoardComment {
+    pub comment_id: String,
+    pub author_id: i64,
+    /// Unix seconds.
+    pub created_at: i64,
+    pub content: String,
+}
+
+impl BoardPost {
+    pub fn from_json(v: &Value) -> Self {
+        let kind = first_string(v, &["object_type", "type", "postType", "post_type"]);
+        let poll_subject = v
+            .get("poll")
+            .map(|poll| first_string(poll, &["subject", "title"]))
+            .unwrap_or_default();
+        let title = first_string(v, &["title", "subject"]);
+        Self {
+            post_id: first_id(v, &["id", "post_id", "postId"]),
+            notice: kind.eq_ignore_ascii_case("notice")
+                || ["notice", "is_notice", "isNotice"]
+                    .iter()
+                    .any(|key| v.get(*key).and_then(Value::as_bool) == Some(true)),
+            kind: kind.to_ascii_lowercase(),
+            author_id: board_author(v),
+            created_at: first_timestamp(v, &["created_at", "createdAt", "date"]),
+            title: if title.is_empty() {
+                poll_subject
+            } else {
+                title
+            },
+            content: board_text(v),
+            comment_count: first_positive(v, &["comment_count", "commentCount", "comments_count"]),
+            comments: Vec::new(),
+        }
+    }
+
+    /// The title, or else the first non-blank line of the text.
+    pub fn preview(&self) -> &str {
+        if !self.title.trim().is_empty() {
+            return self.title.trim();
+        }
+        self.content
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .unwrap_or_default()
+    }
+}
+
+impl BoardComment {
+    pub fn from_json(v: &Value) -> Self {
+        Self {
+            comment_id: first_id(v, &["id", "comment_id", "commentId"]),
+            author_id: board_author(v),
+            created_at: first_timestamp(v, &["created_at", "createdAt", "date"]),
+            content: board_text(v),
+        }
+    }
+}
+
+/// One page of board posts or comments, with the cursor of the next
+/// (older) page.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct BoardPage<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+impl<T> BoardPage<T> {
+    /// Items are under `posts`, `comments` or `items`, whichever the
+    /// answer has.
+    pub fn from_json(v: &Value, parse: fn(&Value) -> T) -> Self {
+        let items = ["posts", "comments", "items"]
+            .iter()
+            .find_map(|key| v.get(*key).and_then(Value::as_array))
+            .map(|arr| arr.iter().map(parse).collect())
+            .unwrap_or_default();
+        Self {
+            items,
+            next_cursor: next_cursor(v),
+        }
+    }
+}
+
+/// The writer of a post or comment: a flat id, or the `id` of a nested
+/// `owner`/`writer`/`author` object.
+fn board_author(v: &Value) -> i64 {
+    let flat = first_positive(
+        v,
+        &[
+            "owner_id",
+            "author_id",
+            "writer_id",
+            "authorId",
+            "userId",
+            "user_id",
+        ],
+    );
+    if flat > 0 {
+        return flat;
+    }
+    ["owner", "writer", "author"]
+        .iter()
+        .filter_map(|key| v.get(*key))
+        .map(|nested| first_positive(nested, &["id", "user_id", "userId"]))
+        .find(|id| *id > 0)
+        .unwrap_or_default()
+}
+
+/// The text of a post or comment. Rich content comes as an array of
+/// `{"text": ...}` segments, sometimes itself encoded as a JSON string.
+fn board_text(v: &Value) -> String {
+    let joined = |segments: &[Value]| {
+        segments
+            .iter()
+            .map(|segment| match segment {
+                Value::String(text) => text.clone(),
+                other => json_string(other, "text"),
+            })
+            .collect::<String>()
+    };
+    for key in ["content", "text", "message", "body"] {
+        match v.get(key) {
+            Some(Value::Array(segments)) => return joined(segments),
+            Some(Value::String(raw)) if !raw.is_empty() => {
+                return match serde_json::from_str::<Value>(raw) {
+                    Ok(Value::Array(segments)) => joined(&segments),
+                    _ => raw.clone(),
+                };
+            }
+            _ => {}
+        }
+    }
+    String::new()
+}
+
+/// An id that is sometimes a number and sometimes a string.
+fn first_id(v: &Value, keys: &[&str]) -> String {
+    keys.iter()
+        .find_map(|key| match v.get(*key) {
+            Some(Value::String(s)) if !s.is_empty() => Some(s.clone()),
+            Some(Value::Number(n)) => Some(n.to_string()),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+fn first_string(v: &Value, keys: &[&str]) -> String {
+    keys.iter()
+        .map(|key| json_string(v, key))
+        .find(|s| !s.is_empty())
+        .unwrap_or_default()
+}
+
+fn first_positive(v: &Value, keys: &[&str]) -> i64 {
+    keys.iter()
+        .map(|key| json_i64(v, key))
+        .find(|n| *n > 0)
+        .unwrap_or_default()
+}
+
+fn first_timestamp(v: &Value, keys: &[&str]) -> i64 {
+    keys.iter()
+        .map(|key| json_timestamp(v, key))
+        .find(|secs| *secs > 0)
+        .unwrap_or_default()
+}
+
+/// Who the credentials belong to, as reported by `whoami`.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Identity {
+    pub user_id: i64,
+    /// `None` when answered offline from credentials alone.
+    pub nickname: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[non_exhaustive]
 pub struct ChatRoom {
     pub chat_id: i64,
     pub kind: String,
     pub title: String,
     pub unread_count: i64,
     pub display_members: Vec<Value>,
+    /// Unix time of the last message, when the payload carries one.
+    pub last_active_at: Option<i64>,
+    /// Unix time the server last changed the room (`lastUpdatedAt`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_updated_at: Option<i64>,
+    /// The newest message, when the payload carries one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_message: Option<ChatMessage>,
+    #[serde(flatten)]
+    pub settings: RoomSettings,
+}
+
+/// Per-room flags and counters from the chat list payload, for `chat
+/// <chat_id>`. Fields the payload does not carry stay at their defaults.
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RoomSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub member_count: Option<i64>,
+    pub pinned: bool,
+    pub muted: bool,
+    /// Last log id I have read in this room.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub watermark: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_log_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_link: Option<OpenLinkInfo>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OpenLinkInfo {
+    pub link_id: i64,
+    pub name: String,
+    pub url: String,
+}
+
+impl RoomSettings {
+    pub fn from_json(v: &Value) -> Self {
+        let first_positive =
+            |v: &Value, keys: &[&str]| keys.iter().map(|key| json_i64(v, key)).find(|n| *n > 0);
+        let flag = |keys: &[&str]| {
+            keys.iter()
+                .any(|key| v.get(*key).and_then(Value::as_bool) == Some(true))
+        };
+
+        // Open chats carry the link either nested or flattened into the room.
+        let link = v.get("openLink").filter(|l| l.is_object()).unwrap_or(v);
+        let open_link = first_positive(link, &["linkId", "li"]).map(|link_id| OpenLinkInfo {
+            link_id,
+            name: [json_string(link, "name"), json_string(link, "linkName")]
+                .into_iter()
+                .find(|s| !s.is_empty())
+                .unwrap_or_default(),
+            url: [
+                json_string(link, "linkURL"),
+                json_string(link, "linkUrl"),
+                json_string(link, "url"),
+            ]
+            .into_iter()
+            .find(|s| !s.is_empty())
+            .unwrap_or_default(),
+        });
+
+        Self {
+            member_count: first_positive(
+                v,
+                &["activeMembersCount", "activeMemberCount", "memberCount"],
+            ),
+            pinned: flag(&["pinned", "isPinned"]),
+            muted: flag(&["muted", "isMuted"])
+                || v.get("pushAlert").and_then(Value::as_bool) == Some(false),
+            watermark: first_positive(v, &["lastSeenLogId", "lastReadLogId", "watermark"]),
+            last_log_id: first_positive(v, &["lastLogId"]).or_else(|| {
+                v.get("lastMessage")
+                    .and_then(|m| first_positive(m, &["logId"]))
+            }),
+            open_link,
+        }
+    }
 }
 
 impl ChatRoom {
@@ -120,20 +934,11 @@ impl ChatRoom {
             return self.title.clone();
         }
 
-        let mut names = Vec::new();
-        for member in &self.display_members {
-            if let Some(name) = member.get("friendNickName").and_then(Value::as_str) {
-                if !name.is_empty() {
-                    names.push(name.to_string());
-                    continue;
-                }
-            }
-            if let Some(name) = member.get("nickName").and_then(Value::as_str) {
-                if !name.is_empty() {
-                    names.push(name.to_string());
-                }
-            }
-        }
+        let names: Vec<String> = self
+            .display_member_names()
+            .into_iter()
+            .map(|(_, name)| name)
+            .collect();
 
         if names.is_empty() {
             "(empty)".to_string()
@@ -142,6 +947,21 @@ impl ChatRoom {
         }
     }
 
+    /// User id and best known name of each display member that has one.
+    pub fn display_member_names(&self) -> Vec<(i64, String)> {
+        self.display_members
+            .iter()
+            .filter_map(|member| {
+                known_display_name(
+                    &json_string(member, "nickName"),
+                    &json_string(member, "friendNickName"),
+                    &open_profile_name(member),
+                )
+                .map(|name| (json_i64(member, "userId"), name.to_string()))
+            })
+            .collect()
+    }
+
     pub fn from_json(v: &Value) -> Self {
         let display_members = v
             .get("displayMembers")
@@ -149,66 +969,590 @@ impl ChatRoom {
             .cloned()
             .unwrap_or_default();
 
+        let last_active_at = v
+            .get("lastMessage")
+            .map(|m| json_timestamp(m, "sendAt"))
+            .filter(|ts| *ts > 0)
+            .or_else(|| {
+                ["lastLogSendAt", "lastUpdatedAt", "lastMessageAt"]
+                    .iter()
+                    .map(|key| json_timestamp(v, key))
+                    .find(|ts| *ts > 0)
+            });
+
+        // Memo chats and rooms with nothing sent yet carry no message, or
+        // an empty stub of one.
+        let last_message = v
+            .get("lastMessage")
+            .filter(|m| m.is_object())
+            .map(ChatMessage::from_json)
+            .filter(|m| m.log_id > 0 && m.send_at > 0);
+
         Self {
             chat_id: json_i64(v, "chatId"),
             kind: json_string(v, "type"),
             title: json_string(v, "title"),
             unread_count: json_i64(v, "unreadCount"),
             display_members,
+            last_active_at,
+            last_updated_at: Some(json_timestamp(v, "lastUpdatedAt")).filter(|ts| *ts > 0),
+            last_message,
+            settings: RoomSettings::from_json(v),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[non_exhaustive]
+pub struct ChatMessage {
+    pub log_id: i64,
+    pub author_id: i64,
+    pub message_type: i64,
+    pub message: String,
+    pub attachment: String,
+    pub send_at: i64,
+    /// Author nickname as embedded in the message payload at send time.
+    pub author_nickname: Option<String>,
+    /// `message_type`, `message` and `attachment` read together.
+    pub content: MessageContent,
+}
+
+impl ChatMessage {
+    pub fn from_json(v: &Value) -> Self {
+        let author_nickname = ["authorNickname", "authorNickName", "authorName", "nickName"]
+            .iter()
+            .map(|key| json_string(v, key))
+            .find(|name| !name.is_empty());
+        // Usually a JSON string; some payloads embed the object itself.
+        let attachment = match v.get("attachment") {
+            Some(Value::String(s)) => s.clone(),
+            Some(obj @ Value::Object(_)) => obj.to_string(),
+            _ => String::new(),
+        };
+        let message_type = json_i64(v, "type");
+        let message = json_string(v, "message");
+        let content = MessageContent::parse(message_type, &message, &attachment);
+
+        Self {
+            log_id: json_i64(v, "logId"),
+            author_id: json_i64(v, "authorId"),
+            message_type,
+            message,
+            attachment,
+            send_at: json_timestamp(v, "sendAt"),
+            author_nickname,
+            content,
+        }
+    }
+
+    /// The author's name from the member map, else the nickname carried in
+    /// the payload; `None` when neither knows it.
+    pub fn author_name(&self, names: &HashMap<i64, String>) -> Option<String> {
+        names
+            .get(&self.author_id)
+            .cloned()
+            .or_else(|| self.author_nickname.clone())
+    }
+
+    /// Name to show for the author: [`author_name`](Self::author_name), then
+    /// the raw user id.
+    pub fn author_display_name(&self, names: &HashMap<i64, String>) -> String {
+        self.author_name(names)
+            .unwrap_or_else(|| self.author_id.to_string())
+    }
+}
+
+/// What a chat log carries, parsed from its type and `attachment` JSON.
+///
+/// Parsing never fails: a missing or malformed attachment, or a type not
+/// listed here, gives [`MessageContent::Other`] with the raw text so callers
+/// can keep their placeholders.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum MessageContent {
+    /// Type 1.
+    Text {
+        text: String,
+    },
+    /// Type 2. Width and height are 0 when the payload omits them.
+    Photo {
+        url: String,
+        width: u64,
+        height: u64,
+    },
+    /// Type 27, an album: its photos in the order they were sent.
+    MultiPhoto {
+        photos: Vec<AlbumPhoto>,
+    },
+    /// Type 3.
+    Video {
+        url: String,
+        duration_secs: u64,
+    },
+    /// Type 18, and type 26 when it carries a file rather than a reply.
+    File {
+        name: String,
+        size: u64,
+        url: String,
+    },
+    /// Type 26: `text` answering the message `src_log_id`.
+    Reply {
+        src_log_id: i64,
+        src_message: String,
+        text: String,
+    },
+    /// Types 12, 20, 25 and 71 when the attachment names an emoticon.
+    /// `resource` is the id from its `path`, empty when there is none.
+    Emoticon {
+        name: String,
+        resource: String,
+    },
+    /// Type 0 feed 14: the author deleted `src_log_id` for everyone, which
+    /// Kakao allows within five minutes of sending.
+    Deleted {
+        src_log_id: i64,
+    },
+    Other {
+        message_type: i64,
+        text: String,
+    },
+}
+
+/// One photo of a [`MessageContent::MultiPhoto`] album. Width and height
+/// are 0 when the payload omits them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AlbumPhoto {
+    pub url: String,
+    pub width: u64,
+    pub height: u64,
+}
+
+/// How a message its author deleted for everyone is shown.
+pub const DELETED_MESSAGE_TEXT: &str = "(message deleted by author)";
+
+/// Feed type of the marker left when a message is deleted for everyone.
+const FEED_DELETE_TO_ALL: i64 = 14;
+
+/// Where `k` media keys are served from when the attachment has no `url`.
+const MEDIA_KEY_BASE: &str = "https://dn-m.talk.kakao.com/talkm/";
+
+impl MessageContent {
+    /// Content of a chat log of `message_type` with text `message` and the
+    /// raw `attachment` JSON string.
+    ///
+    /// ```
+    /// use openkakao_rs::model::MessageContent;
+    ///
+    /// let photo = MessageContent::parse(2, "", r#"{"url":"https://x/p.jpg","w":4,"h":3}"#);
+    /// assert!(matches!(photo, MessageContent::Photo { width: 4, .. }));
+    /// let broken = MessageContent::parse(2, "", "{not json");
+    /// assert!(matches!(broken, MessageContent::Other { message_type: 2, .. }));
+    /// ```
+    pub fn parse(message_type: i64, message: &str, attachment: &str) -> Self {
+        if message_type == 1 {
+            return Self::Text {
+                text: message.to_string(),
+            };
+        }
+        if message_type == 0 {
+            if let Some(deleted) = Self::delete_marker(message, attachment) {
+                return deleted;
+            }
+        }
+        let parsed = serde_json::from_str::<Value>(attachment)
+            .ok()
+            .filter(Value::is_object)
+            .and_then(|att| match message_type {
+                2 => Self::photo(&att),
+                3 => Self::video(&att),
+                12 | 20 | 25 | 71 => Self::emoticon(&att),
+                18 => Self::file(&att),
+                26 => Self::reply(&att, message).or_else(|| Self::file(&att)),
+                27 => Self::multi_photo(&att),
+                _ => None,
+            });
+        parsed.unwrap_or_else(|| Self::Other {
+            message_type,
+            text: message.to_string(),
+        })
+    }
+
+    fn media_url(att: &Value) -> Option<String> {
+        let url = json_string(att, "url");
+        if !url.is_empty() {
+            return Some(url);
+        }
+        let key = json_string(att, "k");
+        (!key.is_empty()).then(|| format!("{MEDIA_KEY_BASE}{key}"))
+    }
+
+    fn photo(att: &Value) -> Option<Self> {
+        Some(Self::Photo {
+            url: Self::media_url(att)?,
+            width: json_i64(att, "w").max(0) as u64,
+            height: json_i64(att, "h").max(0) as u64,
+        })
+    }
+
+    fn video(att: &Value) -> Option<Self> {
+        Some(Self::Video {
+            url: Self::media_url(att)?,
+            duration_secs: json_i64(att, "d").max(0) as u64,
+        })
+    }
+
+    /// Photos from `imageUrls`, else from the `kl` media keys, sized by the
+    /// `wl`/`hl` lists at the same position. An entry that is not a
+    /// non-empty string is skipped rather than failing the whole album.
+    fn multi_photo(att: &Value) -> Option<Self> {
+        let entries = |key: &str, base: &str| -> Vec<AlbumPhoto> {
+            let Some(items) = att.get(key).and_then(Value::as_array) else {
+                return Vec::new();
+            };
+            let size = |list: &str, index: usize| {
+                att.get(list)
+                    .and_then(|sizes| sizes.get(index))
+                    .and_then(Value::as_u64)
+                    .unwrap_or(0)
+            };
+            items
+                .iter()
+                .enumerate()
+                .filter_map(|(index, item)| {
+                    let item = item.as_str().filter(|s| !s.is_empty())?;
+                    Some(AlbumPhoto {
+                        url: format!("{base}{item}"),
+                        width: size("wl", index),
+                        height: size("hl", index),
+                    })
+                })
+                .collect()
+        };
+        let mut photos = entries("imageUrls", "");
+        if photos.is_empty() {
+            photos = entries("kl", MEDIA_KEY_BASE);
+        }
+        (!photos.is_empty()).then_some(Self::MultiPhoto { photos })
+    }
+
+    fn file(att: &Value) -> Option<Self> {
+        let name = json_string(att, "name");
+        if name.is_empty() {
+            return None;
+        }
+        let size = match json_i64(att, "size") {
+            0 => json_i64(att, "s"),
+            size => size,
+        };
+        Some(Self::File {
+            name,
+            size: size.max(0) as u64,
+            url: Self::media_url(att).unwrap_or_default(),
+        })
+    }
+
+    fn reply(att: &Value, message: &str) -> Option<Self> {
+        let src_log_id = json_i64(att, "src_logId");
+        if src_log_id == 0 {
+            return None;
+        }
+        Some(Self::Reply {
+            src_log_id,
+            src_message: json_string(att, "src_message"),
+            text: message.to_string(),
+        })
+    }
+
+    /// The feed JSON travels in `message` or, in some payloads, `attachment`.
+    fn delete_marker(message: &str, attachment: &str) -> Option<Self> {
+        let feed = [message, attachment]
+            .iter()
+            .find_map(|raw| serde_json::from_str::<Value>(raw).ok())?;
+        if feed.get("feedType")?.as_i64()? != FEED_DELETE_TO_ALL {
+            return None;
+        }
+        let src_log_id = json_i64(&feed, "logId");
+        (src_log_id != 0).then_some(Self::Deleted { src_log_id })
+    }
+
+    /// The log id a delete marker removes, `None` for anything else.
+    pub fn deleted_log_id(&self) -> Option<i64> {
+        match self {
+            Self::Deleted { src_log_id } => Some(*src_log_id),
+            _ => None,
+        }
+    }
+
+    /// The log id a reply answers, `None` for anything else.
+    pub fn reply_to(&self) -> Option<i64> {
+        match self {
+            Self::Reply { src_log_id, .. } => Some(*src_log_id),
+            _ => None,
+        }
+    }
+
+    fn emoticon(att: &Value) -> Option<Self> {
+        let name = ["alt", "name"]
+            .iter()
+            .map(|key| json_string(att, key))
+            .find(|name| !name.is_empty())
+            .unwrap_or_default();
+        let resource = crate::emoticons::resource_id(&json_string(att, "path")).to_string();
+        (!name.is_empty() || !resource.is_empty()).then_some(Self::Emoticon { name, resource })
+    }
+
+    /// `(이모티콘: 라이언 하트)` for an emoticon, `None` for anything else.
+    pub fn emoticon_text(&self) -> Option<String> {
+        match self {
+            Self::Emoticon { name, resource } => Some(crate::emoticons::render(resource, name)),
+            _ => None,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
-pub struct ChatMessage {
-    pub log_id: i64,
-    pub author_id: i64,
-    pub message_type: i64,
-    pub message: String,
-    pub attachment: String,
-    pub send_at: i64,
+/// Pick the messages a reader sees, independent of fetch order.
+///
+/// Ordering contract: the result is the newest `count` messages with
+/// `log_id < before` (when given), deduplicated and sorted oldest → newest by
+/// log_id. `read` renders it as-is and exports use the same ascending order.
+pub fn select_window(
+    messages: Vec<ChatMessage>,
+    count: usize,
+    before: Option<i64>,
+) -> Vec<ChatMessage> {
+    select_window_by(messages, count, before, |m| m.log_id)
 }
 
-impl ChatMessage {
-    pub fn from_json(v: &Value) -> Self {
-        Self {
-            log_id: json_i64(v, "logId"),
-            author_id: json_i64(v, "authorId"),
-            message_type: json_i64(v, "type"),
-            message: json_string(v, "message"),
-            attachment: json_string(v, "attachment"),
-            send_at: json_i64(v, "sendAt"),
-        }
+/// [`select_window`] for any message representation, keyed by `log_id`.
+/// Items with log_id 0 (unknown) are never treated as duplicates.
+pub fn select_window_by<T, F>(
+    mut items: Vec<T>,
+    count: usize,
+    before: Option<i64>,
+    log_id: F,
+) -> Vec<T>
+where
+    F: Fn(&T) -> i64,
+{
+    if let Some(before) = before {
+        items.retain(|m| log_id(m) < before);
     }
+    items.sort_by_key(|m| log_id(m));
+    items.dedup_by(|a, b| log_id(a) != 0 && log_id(a) == log_id(b));
+    if items.len() > count {
+        items.drain(..items.len() - count);
+    }
+    items
+}
+
+/// Messages around one log id, as picked by [`select_context_by`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ContextWindow<T> {
+    /// Oldest → newest by log_id.
+    pub items: Vec<T>,
+    /// Whether `target` itself is among `items`.
+    pub found: bool,
+}
+
+/// Up to `count / 2` messages either side of `target`, ordered like
+/// [`select_window_by`]. When `target` is missing, the `count` messages
+/// nearest to where it would sit are returned with `found: false`.
+pub fn select_context_by<T, F>(
+    items: Vec<T>,
+    target: i64,
+    count: usize,
+    log_id: F,
+) -> ContextWindow<T>
+where
+    F: Fn(&T) -> i64,
+{
+    let mut items = select_window_by(items, usize::MAX, None, &log_id);
+    let half = count / 2;
+    let pos = items.partition_point(|m| log_id(m) < target);
+    let found = items.get(pos).is_some_and(|m| log_id(m) == target);
+    let (start, end) = if found {
+        (pos.saturating_sub(half), (pos + 1 + half).min(items.len()))
+    } else {
+        let start = pos
+            .saturating_sub(half)
+            .min(items.len().saturating_sub(count));
+        (start, (start + count).min(items.len()))
+    };
+    items.truncate(end);
+    items.drain(..start);
+    ContextWindow { items, found }
 }
 
 #[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[non_exhaustive]
 pub struct ChatMember {
     pub user_id: i64,
     pub nickname: String,
     pub friend_nickname: String,
+    /// Profile shown in an open chat instead of the real nickname.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub open_profile_name: String,
     pub country_iso: String,
+    /// Last log id this member has read, when the payload carries it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub watermark: Option<i64>,
 }
 
 impl ChatMember {
-    pub fn display_name(&self) -> String {
-        if self.friend_nickname.is_empty() {
-            self.nickname.clone()
-        } else {
-            self.friend_nickname.clone()
+    /// A member known only by id and nickname.
+    pub fn new(user_id: i64, nickname: impl Into<String>) -> Self {
+        Self {
+            user_id,
+            nickname: nickname.into(),
+            friend_nickname: String::new(),
+            open_profile_name: String::new(),
+            country_iso: String::new(),
+            watermark: None,
         }
     }
 
+    pub fn display_name(&self) -> String {
+        display_name(
+            &self.nickname,
+            &self.friend_nickname,
+            &self.open_profile_name,
+        )
+    }
+
     pub fn from_json(v: &Value) -> Self {
         Self {
             user_id: json_i64(v, "userId"),
             nickname: json_string(v, "nickName"),
             friend_nickname: json_string(v, "friendNickName"),
+            open_profile_name: open_profile_name(v),
             country_iso: json_string(v, "countryIso"),
+            watermark: ["watermark", "lastReadLogId"]
+                .iter()
+                .map(|key| json_i64(v, key))
+                .find(|n| *n > 0),
+        }
+    }
+}
+
+/// Per-member read watermarks of one chat, used for the app's "unread by N"
+/// countdown next to each message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadWatermarks {
+    viewer_id: i64,
+    by_user: HashMap<i64, i64>,
+}
+
+impl ReadWatermarks {
+    /// From CHATONROOM's parallel `a` (user ids) and `w` (watermarks) arrays.
+    /// `None` when they are missing, empty or of different lengths, which is
+    /// how rooms without receipts (e.g. some open chats) answer.
+    pub fn from_parallel(viewer_id: i64, user_ids: &[i64], watermarks: &[i64]) -> Option<Self> {
+        if user_ids.is_empty() || user_ids.len() != watermarks.len() {
+            return None;
+        }
+        Some(Self {
+            viewer_id,
+            by_user: user_ids
+                .iter()
+                .copied()
+                .zip(watermarks.iter().copied())
+                .collect(),
+        })
+    }
+
+    /// From a member list; `None` unless every member carries a watermark,
+    /// since a partial set would undercount.
+    pub fn from_members(viewer_id: i64, members: &[ChatMember]) -> Option<Self> {
+        if members.is_empty() {
+            return None;
+        }
+        let by_user = members
+            .iter()
+            .map(|m| m.watermark.map(|w| (m.user_id, w)))
+            .collect::<Option<HashMap<_, _>>>()?;
+        Some(Self { viewer_id, by_user })
+    }
+
+    /// Members who have not read `log_id` yet. The author and the viewer
+    /// count as having read it, like in the app.
+    pub fn unread_by(&self, log_id: i64, author_id: i64) -> usize {
+        self.by_user
+            .iter()
+            .filter(|(user_id, watermark)| {
+                **user_id != author_id && **user_id != self.viewer_id && **watermark < log_id
+            })
+            .count()
+    }
+}
+
+/// One page of the pilsner chat members listing.
+///
+/// Large open chats split the member list across pages. The continuation
+/// token has shown up both as a number and as an opaque string, so it is kept
+/// as a string and passed back verbatim.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ChatMembersPage {
+    pub members: Vec<ChatMember>,
+    pub next_cursor: Option<String>,
+    pub total: Option<i64>,
+}
+
+impl ChatMembersPage {
+    pub fn from_json(v: &Value) -> Self {
+        let members = v
+            .get("members")
+            .and_then(Value::as_array)
+            .map(|arr| arr.iter().map(ChatMember::from_json).collect())
+            .unwrap_or_default();
+
+        let next_cursor = next_cursor(v);
+        let total = ["totalCount", "total", "memberCount", "activeMembersCount"]
+            .iter()
+            .map(|key| json_i64(v, key))
+            .find(|n| *n > 0);
+
+        Self {
+            members,
+            next_cursor,
+            total,
         }
     }
 }
 
+/// The continuation token of a paged answer, unless it says it is the last
+/// page. It has shown up both as a number and as an opaque string.
+fn next_cursor(v: &Value) -> Option<String> {
+    if v.get("last").and_then(Value::as_bool).unwrap_or(false) {
+        return None;
+    }
+    ["nextCursor", "next_cursor", "cursor", "nextToken", "token"]
+        .iter()
+        .find_map(|key| match v.get(*key) {
+            Some(Value::String(s)) if !s.is_empty() && s != "0" => Some(s.clone()),
+            Some(Value::Number(n)) if n.as_i64().unwrap_or(0) != 0 => Some(n.to_string()),
+            _ => None,
+        })
+}
+
+/// A timestamp field as Unix seconds, whichever epoch the payload used; 0
+/// when missing or implausible.
+pub(crate) fn json_timestamp(v: &Value, key: &str) -> i64 {
+    let raw = match v.get(key) {
+        Some(Value::Number(n)) => n.as_f64(),
+        Some(Value::String(s)) => s.parse::<f64>().ok(),
+        _ => None,
+    };
+    raw.map_or(0, crate::timestamp::unix_secs)
+}
+
+#[doc(hidden)]
 pub fn json_i64(v: &Value, key: &str) -> i64 {
     if let Some(n) = v.get(key).and_then(Value::as_i64) {
         return n;
@@ -222,6 +1566,7 @@ pub fn json_i64(v: &Value, key: &str) -> i64 {
     0
 }
 
+#[doc(hidden)]
 pub fn json_string(v: &Value, key: &str) -> String {
     v.get(key)
         .and_then(Value::as_str)
@@ -264,6 +1609,120 @@ mod tests {
         assert_eq!(json_string(&v, "name"), "");
     }
 
+    #[test]
+    fn notice_is_the_type_1_chat_meta() {
+        let info = json!({
+            "status": 0,
+            "chatInfo": {
+                "chatMetas": [
+                    {"type": 3, "content": "Team", "revision": 2},
+                    {"type": 1, "authorId": 7, "content": "회의는 목요일 3시\n자료 필참",
+                     "updatedAt": 1_700_000_000_000_i64, "revision": 5},
+                ]
+            }
+        });
+        let notice = Notice::from_chat_info(&info).unwrap();
+        assert_eq!(notice.author_id, 7);
+        assert_eq!(notice.content, "회의는 목요일 3시\n자료 필참");
+        assert_eq!(notice.updated_at, 1_700_000_000);
+        assert_eq!(notice.revision, 5);
+
+        let wrapped = json!({"type": 1, "content": r#"{"message":"hello"}"#, "updatedAt": 1});
+        assert_eq!(Notice::from_meta(&wrapped).unwrap().content, "hello");
+
+        let cleared = json!({"chatMetas": [{"type": 1, "content": ""}]});
+        assert_eq!(Notice::from_chat_info(&cleared), None);
+        assert_eq!(Notice::from_chat_info(&json!({"chatInfo": {}})), None);
+    }
+
+    #[test]
+    fn board_posts_parse_from_either_key_style() {
+        let page = BoardPage::from_json(
+            &json!({
+                "posts": [
+                    {
+                        "id": 9001,
+                        "object_type": "TEXT",
+                        "owner": {"id": 77},
+                        "created_at": 1_700_000_000_000i64,
+                        "content": r#"[{"text":"첫 줄","type":"text"},{"text":"\n둘째 줄","type":"text"}]"#,
+                        "comment_count": 3
+                    },
+                    {
+                        "postId": "p-2",
+                        "postType": "POLL",
+                        "authorId": 5,
+                        "createdAt": 1_700_000_100,
+                        "poll": {"subject": "점심 메뉴?"},
+                        "commentCount": "1",
+                        "isNotice": true
+                    }
+                ],
+                "next_cursor": "abc"
+            }),
+            BoardPost::from_json,
+        );
+        assert_eq!(page.next_cursor.as_deref(), Some("abc"));
+        let [text, poll] = &page.items[..] else {
+            panic!("two posts expected");
+        };
+        assert_eq!(text.post_id, "9001");
+        assert_eq!(text.kind, "text");
+        assert_eq!(text.author_id, 77);
+        assert_eq!(text.created_at, 1_700_000_000);
+        assert_eq!(text.content, "첫 줄\n둘째 줄");
+        assert_eq!(text.preview(), "첫 줄");
+        assert_eq!(text.comment_count, 3);
+        assert!(!text.notice);
+
+        assert_eq!(poll.post_id, "p-2");
+        assert_eq!(poll.kind, "poll");
+        assert_eq!(poll.preview(), "점심 메뉴?");
+        assert_eq!(poll.comment_count, 1);
+        assert!(poll.notice);
+
+        let comments = BoardPage::from_json(
+            &json!({"comments": [{"commentId": 4, "writer_id": 8, "text": "ok"}], "last": true, "nextCursor": "x"}),
+            BoardComment::from_json,
+        );
+        assert_eq!(comments.next_cursor, None);
+        assert_eq!(comments.items[0].comment_id, "4");
+        assert_eq!(comments.items[0].author_id, 8);
+        assert_eq!(comments.items[0].content, "ok");
+        assert!(BoardPage::from_json(&json!({}), BoardPost::from_json)
+            .items
+            .is_empty());
+    }
+
+    #[test]
+    fn member_profile_reads_both_key_spellings_and_rejects_empty_answers() {
+        let profile = MemberProfile::from_json(&json!({"status": 0, "profile": {
+            "userId": 5,
+            "nickName": "Dana",
+            "statusMessage": "hi",
+            "fullProfileImageUrl": "https://p/full.jpg",
+            "bgImageUrl": "https://p/bg.jpg",
+            "originalBgImageUrl": "https://p/bg-orig.jpg",
+            "isBirthday": true,
+            "type": 100,
+        }}))
+        .unwrap();
+        assert_eq!(profile.user_id, 5);
+        assert_eq!(profile.nickname, "Dana");
+        assert_eq!(profile.background_image_url, "https://p/bg.jpg");
+        assert_eq!(
+            profile.original_background_image_url,
+            "https://p/bg-orig.jpg"
+        );
+        assert!(profile.birthday);
+        assert_eq!(profile.account_type, "100");
+        assert!(!profile.channel);
+
+        assert!(MemberProfile::from_json(&json!({"status": 0})).is_none());
+        assert!(MemberProfile::from_json(&json!({"status": 0, "profile": null})).is_none());
+        assert!(MemberProfile::from_json(&json!({"profile": {"statusMessage": ""}})).is_none());
+    }
+
     #[test]
     fn test_friend_display_name_uses_friend_nickname() {
         let f = Friend {
@@ -271,9 +1730,14 @@ mod tests {
             nickname: "Original".to_string(),
             friend_nickname: "Custom".to_string(),
             phone_number: String::new(),
+            country_iso: String::new(),
             status_message: String::new(),
             favorite: false,
             hidden: false,
+            channel: false,
+            uuid: String::new(),
+            profile_image_url: String::new(),
+            direct_chat_id: None,
         };
         assert_eq!(f.display_name(), "Custom");
     }
@@ -285,13 +1749,49 @@ mod tests {
             nickname: "Original".to_string(),
             friend_nickname: String::new(),
             phone_number: String::new(),
+            country_iso: String::new(),
             status_message: String::new(),
             favorite: false,
             hidden: false,
+            channel: false,
+            uuid: String::new(),
+            profile_image_url: String::new(),
+            direct_chat_id: None,
         };
         assert_eq!(f.display_name(), "Original");
     }
 
+    #[test]
+    fn test_hidden_filter() {
+        let friend = |user_id: i64, hidden: bool| Friend {
+            user_id,
+            nickname: String::new(),
+            friend_nickname: String::new(),
+            phone_number: String::new(),
+            country_iso: String::new(),
+            status_message: String::new(),
+            favorite: false,
+            hidden,
+            channel: false,
+            uuid: String::new(),
+            profile_image_url: String::new(),
+            direct_chat_id: None,
+        };
+        let friends = [friend(1, false), friend(2, true)];
+        let kept = |filter: HiddenFilter| {
+            friends
+                .iter()
+                .filter(|f| filter.keeps(f))
+                .map(|f| f.user_id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(kept(HiddenFilter::from_flags(false, false)), vec![1]);
+        assert_eq!(kept(HiddenFilter::from_flags(true, false)), vec![1, 2]);
+        assert_eq!(kept(HiddenFilter::from_flags(false, true)), vec![2]);
+        // --hidden-only wins over --hidden.
+        assert_eq!(HiddenFilter::from_flags(true, true), HiddenFilter::Only);
+    }
+
     #[test]
     fn test_friend_from_json() {
         let v = json!({
@@ -302,13 +1802,123 @@ mod tests {
             "statusMessage": "Hello",
             "favorite": true,
             "hidden": false,
+            "UUID": "nick_kr",
         });
         let f = Friend::from_json(&v);
         assert_eq!(f.user_id, 12345);
         assert_eq!(f.nickname, "Nick");
         assert_eq!(f.friend_nickname, "Friend");
+        assert_eq!(f.uuid, "nick_kr");
         assert!(f.favorite);
         assert!(!f.hidden);
+        assert!(!f.channel);
+    }
+
+    #[test]
+    fn test_message_link_round_trip() {
+        let link = MessageLink::new(900000000000003, 3141592653);
+        let text = link.to_string();
+        assert_eq!(text, "openkakao://chat/900000000000003/3141592653");
+        assert_eq!(text.parse::<MessageLink>(), Ok(link));
+        assert!("kakaotalk://chat/1/2".parse::<MessageLink>().is_err());
+        assert!("openkakao://chat/1".parse::<MessageLink>().is_err());
+        assert!("openkakao://chat/x/2".parse::<MessageLink>().is_err());
+    }
+
+    #[test]
+    fn test_open_link_ref_parse() {
+        let url = OpenLinkRef::Url("https://open.kakao.com/o/gAbC123".into());
+        for text in [
+            "https://open.kakao.com/o/gAbC123",
+            "open.kakao.com/o/gAbC123/",
+            "http://open.kakao.com/o/gAbC123?ref=share",
+        ] {
+            assert_eq!(text.parse::<OpenLinkRef>(), Ok(url.clone()), "{text}");
+        }
+        assert_eq!(" 4567 ".parse::<OpenLinkRef>(), Ok(OpenLinkRef::Id(4567)));
+        assert!("0".parse::<OpenLinkRef>().is_err());
+        assert!("https://open.kakao.com/o/".parse::<OpenLinkRef>().is_err());
+        assert!("https://evil.example/o/gAbC123"
+            .parse::<OpenLinkRef>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_open_link_from_json() {
+        let link = OpenLink::from_json(&json!({
+            "status": 0,
+            "openLink": {
+                "linkId": 4567,
+                "linkName": "Rust KR",
+                "linkURL": "https://open.kakao.com/o/gAbC123",
+                "description": "러스트 이야기",
+                "linkType": 1,
+                "activeMembersCount": 300,
+                "maxUser": 300,
+                "passcodeRequired": true,
+                "openLinkOwner": {"nickname": "host"},
+            }
+        }));
+        assert_eq!(link.link_id, 4567);
+        assert_eq!(link.name, "Rust KR");
+        assert_eq!(link.description, "러스트 이야기");
+        assert_eq!(link.kind, "multi");
+        assert_eq!(link.host_nickname, "host");
+        assert_eq!(
+            (link.member_count, link.member_limit),
+            (Some(300), Some(300))
+        );
+        assert!(link.passcode_required);
+        assert!(link.is_full());
+        assert_eq!(link.chat_id, None);
+
+        let direct = OpenLink::from_json(&json!({"li": 9, "ln": "1:1", "linkType": 2}));
+        assert_eq!((direct.link_id, direct.kind.as_str()), (9, "direct"));
+        assert!(!direct.passcode_required && !direct.is_full());
+    }
+
+    #[test]
+    fn test_kakao_id_match_from_json() {
+        let m = KakaoIdMatch::from_json(&json!({
+            "userId": 77,
+            "nickName": "Stranger",
+            "uuid": "stranger01",
+            "profileImageUrl": "https://p.kakaocdn.net/s.jpg",
+        }));
+        assert_eq!(m.user_id, 77);
+        assert_eq!(m.uuid, "stranger01");
+        assert_eq!(m.profile_image_url, "https://p.kakaocdn.net/s.jpg");
+        assert!(!m.friend);
+    }
+
+    #[test]
+    fn test_summary_counts() {
+        let friends = vec![
+            Friend::from_json(&json!({"userId": 1, "favorite": true})),
+            Friend::from_json(&json!({"userId": 2, "hidden": true})),
+            Friend::from_json(&json!({"userId": 3, "plusFriendProfile": {"name": "Shop"}})),
+        ];
+        assert_eq!(
+            FriendCounts::from_friends(&friends),
+            FriendCounts {
+                total: 3,
+                favorites: 1,
+                hidden: 1,
+                channels: 1,
+            }
+        );
+
+        let rooms = vec![
+            ChatRoom::from_json(&json!({"chatId": 1, "type": "DirectChat", "unreadCount": 2})),
+            ChatRoom::from_json(&json!({"chatId": 2, "type": "DirectChat"})),
+            ChatRoom::from_json(&json!({"chatId": 3, "type": "OM", "unreadCount": 5})),
+        ];
+        let chats = ChatCounts::from_rooms(&rooms);
+        assert_eq!(chats.total, 3);
+        assert_eq!(chats.by_type.get("DirectChat"), Some(&2));
+        assert_eq!(chats.by_type.get("OM"), Some(&1));
+        assert_eq!(chats.unread_chats, 2);
+        assert_eq!(chats.unread_messages, 7);
     }
 
     #[test]
@@ -319,6 +1929,10 @@ mod tests {
             title: "My Chat".to_string(),
             unread_count: 0,
             display_members: vec![],
+            last_active_at: None,
+            last_updated_at: None,
+            last_message: None,
+            settings: RoomSettings::default(),
         };
         assert_eq!(room.display_title(), "My Chat");
     }
@@ -334,6 +1948,10 @@ mod tests {
                 json!({"friendNickName": "Alice", "nickName": "A"}),
                 json!({"friendNickName": "", "nickName": "Bob"}),
             ],
+            last_active_at: None,
+            last_updated_at: None,
+            last_message: None,
+            settings: RoomSettings::default(),
         };
         assert_eq!(room.display_title(), "Alice, Bob");
     }
@@ -346,6 +1964,10 @@ mod tests {
             title: String::new(),
             unread_count: 0,
             display_members: vec![],
+            last_active_at: None,
+            last_updated_at: None,
+            last_message: None,
+            settings: RoomSettings::default(),
         };
         assert_eq!(room.display_title(), "(empty)");
     }
@@ -365,6 +1987,181 @@ mod tests {
         assert_eq!(room.unread_count, 5);
     }
 
+    #[test]
+    fn test_chatroom_last_active_at() {
+        let v = json!({"chatId": 1, "lastMessage": {"sendAt": 1700000000}});
+        assert_eq!(ChatRoom::from_json(&v).last_active_at, Some(1700000000));
+
+        let v = json!({"chatId": 1, "lastUpdatedAt": 1700000000123_i64});
+        assert_eq!(ChatRoom::from_json(&v).last_active_at, Some(1700000000));
+
+        let v = json!({"chatId": 1});
+        assert_eq!(ChatRoom::from_json(&v).last_active_at, None);
+
+        // A bogus lastMessage time falls through to the next field.
+        let v = json!({"chatId": 1, "lastMessage": {"sendAt": 5}, "lastLogSendAt": 1700000000});
+        assert_eq!(ChatRoom::from_json(&v).last_active_at, Some(1700000000));
+    }
+
+    #[test]
+    fn rooms_keep_their_last_message_and_omit_what_is_missing() {
+        let room = ChatRoom::from_json(&json!({
+            "chatId": 7,
+            "type": "MultiChat",
+            "lastUpdatedAt": 1700000100,
+            "displayMembers": [
+                {"userId": 5, "nickName": "Dana"},
+                {"userId": 6, "friendNickName": "민수", "nickName": "ms"},
+                {"userId": 8},
+            ],
+            "lastMessage": {
+                "logId": 950, "authorId": 5, "type": 1, "message": "점심?", "sendAt": 1700000000,
+            },
+        }));
+        assert_eq!(room.last_updated_at, Some(1700000100));
+        let last = room.last_message.as_ref().unwrap();
+        assert_eq!((last.log_id, last.author_id), (950, 5));
+        assert_eq!(last.message, "점심?");
+        assert_eq!(
+            room.display_member_names(),
+            [(5, "Dana".to_string()), (6, "민수".to_string())]
+        );
+
+        // A memo chat: no message, no update time, no members.
+        let memo = ChatRoom::from_json(&json!({
+            "chatId": 9, "type": "MemoChat", "lastMessage": {"logId": 0, "sendAt": 0},
+        }));
+        assert!(memo.last_message.is_none());
+        assert_eq!(memo.last_updated_at, None);
+        let json = serde_json::to_value(&memo).unwrap();
+        assert!(json.get("last_message").is_none());
+        assert!(json.get("last_updated_at").is_none());
+    }
+
+    #[test]
+    fn test_room_settings_from_json() {
+        let v = json!({
+            "chatId": 7,
+            "type": "OM",
+            "activeMembersCount": 42,
+            "pushAlert": false,
+            "isPinned": true,
+            "lastSeenLogId": "900",
+            "lastMessage": {"logId": 950, "sendAt": 1700000000},
+            "openLink": {"linkId": 5, "name": "Rust KR", "linkURL": "https://open.kakao.com/o/abc"},
+        });
+        let settings = ChatRoom::from_json(&v).settings;
+        assert_eq!(settings.member_count, Some(42));
+        assert!(settings.pinned && settings.muted);
+        assert_eq!(settings.watermark, Some(900));
+        assert_eq!(settings.last_log_id, Some(950));
+        assert_eq!(
+            settings.open_link,
+            Some(OpenLinkInfo {
+                link_id: 5,
+                name: "Rust KR".into(),
+                url: "https://open.kakao.com/o/abc".into(),
+            })
+        );
+
+        // Flattened link fields; nothing else known.
+        let settings = RoomSettings::from_json(&json!({"li": 9, "linkName": "x"}));
+        assert_eq!(
+            settings.open_link.map(|l| (l.link_id, l.name)),
+            Some((9, "x".into()))
+        );
+        assert_eq!(settings.member_count, None);
+        assert!(!settings.pinned && !settings.muted);
+
+        // Alerts on, no link: a plain room.
+        let settings = RoomSettings::from_json(&json!({"pushAlert": true, "lastLogId": 3}));
+        assert_eq!(
+            settings,
+            RoomSettings {
+                last_log_id: Some(3),
+                ..RoomSettings::default()
+            }
+        );
+    }
+
+    /// `--json` output is scripted against; renaming a field breaks users.
+    #[test]
+    fn test_json_field_names_are_stable() {
+        fn keys(value: impl Serialize) -> Vec<String> {
+            let mut keys: Vec<String> = serde_json::to_value(value)
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .keys()
+                .cloned()
+                .collect();
+            keys.sort();
+            keys
+        }
+        let empty = json!({});
+        assert_eq!(
+            keys(ChatMessage::from_json(&empty)),
+            [
+                "attachment",
+                "author_id",
+                "author_nickname",
+                "content",
+                "log_id",
+                "message",
+                "message_type",
+                "send_at"
+            ]
+        );
+        assert_eq!(
+            keys(ChatRoom::from_json(&empty)),
+            [
+                "chat_id",
+                "display_members",
+                "kind",
+                "last_active_at",
+                "muted",
+                "pinned",
+                "title",
+                "unread_count"
+            ]
+        );
+        assert_eq!(
+            keys(Friend::from_json(&empty)),
+            [
+                "channel",
+                "favorite",
+                "friend_nickname",
+                "hidden",
+                "nickname",
+                "phone_number",
+                "profile_image_url",
+                "status_message",
+                "user_id",
+                "uuid"
+            ]
+        );
+        assert_eq!(
+            keys(ChatMember::from_json(&empty)),
+            ["country_iso", "friend_nickname", "nickname", "user_id"]
+        );
+    }
+
+    #[test]
+    fn test_chat_message_send_at_epochs() {
+        let send_at =
+            |raw: Value| ChatMessage::from_json(&json!({"logId": 1, "sendAt": raw})).send_at;
+        assert_eq!(send_at(json!(1700000000)), 1700000000);
+        assert_eq!(send_at(json!(1700000000123_i64)), 1700000000);
+        assert_eq!(send_at(json!(721692800.25)), 1700000000);
+        assert_eq!(send_at(json!("1700000000")), 1700000000);
+        // Implausible values are unknown, not 1970.
+        assert_eq!(send_at(json!(0)), 0);
+        assert_eq!(send_at(json!(-1)), 0);
+        assert_eq!(send_at(json!(42)), 0);
+        assert_eq!(send_at(json!("soon")), 0);
+        assert_eq!(ChatMessage::from_json(&json!({"logId": 1})).send_at, 0);
+    }
+
     #[test]
     fn test_chat_message_from_json() {
         let v = json!({
@@ -382,17 +2179,468 @@ mod tests {
         assert_eq!(msg.send_at, 1700000000);
     }
 
+    #[test]
+    fn test_chat_message_author_falls_back_to_payload_nickname() {
+        let v = json!({
+            "logId": 100,
+            "authorId": 200,
+            "authorNickname": "Departed",
+            "type": 1,
+            "message": "bye",
+        });
+        let msg = ChatMessage::from_json(&v);
+        assert_eq!(msg.author_nickname.as_deref(), Some("Departed"));
+
+        // Member list that no longer contains the author.
+        let mut names = HashMap::new();
+        names.insert(300, "Someone else".to_string());
+        assert_eq!(msg.author_display_name(&names), "Departed");
+
+        names.insert(200, "Current".to_string());
+        assert_eq!(msg.author_display_name(&names), "Current");
+
+        // Neither source: the id is shown, but no name is known.
+        let anonymous = ChatMessage::from_json(&json!({"logId": 101, "authorId": 201}));
+        assert_eq!(anonymous.author_name(&names), None);
+        assert_eq!(anonymous.author_display_name(&names), "201");
+    }
+
+    #[test]
+    fn test_message_content_parses_rich_types() {
+        let content = |message_type: i64, message: &str, attachment: Value| {
+            ChatMessage::from_json(&json!({
+                "logId": 1,
+                "type": message_type,
+                "message": message,
+                "attachment": attachment.to_string(),
+            }))
+            .content
+        };
+        assert_eq!(
+            content(2, "", json!({"url": "https://x/p.jpg", "w": 640, "h": 480})),
+            MessageContent::Photo {
+                url: "https://x/p.jpg".into(),
+                width: 640,
+                height: 480
+            }
+        );
+        assert_eq!(
+            content(3, "", json!({"k": "v/abc.mp4", "d": 65})),
+            MessageContent::Video {
+                url: format!("{MEDIA_KEY_BASE}v/abc.mp4"),
+                duration_secs: 65
+            }
+        );
+        assert_eq!(
+            content(12, "", json!({"name": "(이모티콘)", "alt": "춘식이 하트"})),
+            MessageContent::Emoticon {
+                name: "춘식이 하트".into(),
+                resource: String::new()
+            }
+        );
+        assert_eq!(
+            content(
+                18,
+                "",
+                json!({"name": "a.pdf", "size": 2048, "url": "https://x/a"})
+            ),
+            MessageContent::File {
+                name: "a.pdf".into(),
+                size: 2048,
+                url: "https://x/a".into()
+            }
+        );
+        assert_eq!(
+            content(
+                26,
+                "yes",
+                json!({"src_logId": 77, "src_message": "lunch?", "src_type": 1})
+            ),
+            MessageContent::Reply {
+                src_log_id: 77,
+                src_message: "lunch?".into(),
+                text: "yes".into()
+            }
+        );
+        // Files sent by `send-file` use type 26 with a name and "s".
+        assert!(matches!(
+            content(26, "", json!({"name": "b.zip", "s": 10})),
+            MessageContent::File { size: 10, .. }
+        ));
+        assert_eq!(
+            content(27, "", json!({"kl": ["a.jpg", "b.jpg"]})),
+            MessageContent::MultiPhoto {
+                photos: ["a.jpg", "b.jpg"]
+                    .map(|key| AlbumPhoto {
+                        url: format!("{MEDIA_KEY_BASE}{key}"),
+                        width: 0,
+                        height: 0,
+                    })
+                    .to_vec()
+            }
+        );
+        assert!(matches!(
+            content(71, "", json!({"path": "x.webp", "name": "(Emoticons)"})),
+            MessageContent::Emoticon { .. }
+        ));
+        let sticker = content(
+            12,
+            "",
+            json!({"path": "2202001.emot_001.webp", "name": "(Emoticons)"}),
+        );
+        assert_eq!(
+            sticker.emoticon_text().as_deref(),
+            Some("(이모티콘: 라이언 하트)")
+        );
+        let marker = content(
+            0,
+            r#"{"feedType":14,"logId":"3100","hidden":true}"#,
+            json!({}),
+        );
+        assert_eq!(marker, MessageContent::Deleted { src_log_id: 3100 });
+        assert_eq!(marker.deleted_log_id(), Some(3100));
+        // Other feeds (joins, leaves) are not deletions.
+        assert!(matches!(
+            content(0, r#"{"feedType":4,"members":[]}"#, json!({})),
+            MessageContent::Other { .. }
+        ));
+    }
+
+    /// A type 27 attachment as Kakao sends it: `count` photos with media
+    /// keys, widths, heights and sizes at matching positions.
+    fn album_attachment(count: usize) -> Value {
+        json!({
+            "kl": (1..=count).map(|i| format!("album/{i}.jpg")).collect::<Vec<_>>(),
+            "wl": (1..=count).map(|i| 100 + i).collect::<Vec<_>>(),
+            "hl": (1..=count).map(|i| 200 + i).collect::<Vec<_>>(),
+            "sl": (1..=count).map(|i| 1000 * i).collect::<Vec<_>>(),
+        })
+    }
+
+    fn album_photos(attachment: &Value) -> Vec<AlbumPhoto> {
+        match MessageContent::parse(27, "", &attachment.to_string()) {
+            MessageContent::MultiPhoto { photos } => photos,
+            other => panic!("expected an album, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn albums_keep_their_photos_in_order_with_sizes() {
+        let photos = album_photos(&album_attachment(2));
+        assert_eq!(
+            photos,
+            vec![
+                AlbumPhoto {
+                    url: format!("{MEDIA_KEY_BASE}album/1.jpg"),
+                    width: 101,
+                    height: 201,
+                },
+                AlbumPhoto {
+                    url: format!("{MEDIA_KEY_BASE}album/2.jpg"),
+                    width: 102,
+                    height: 202,
+                },
+            ]
+        );
+        // Full URLs win over media keys.
+        let mut attachment = album_attachment(2);
+        attachment["imageUrls"] = json!(["https://x/1.jpg", "https://x/2.jpg"]);
+        let photos = album_photos(&attachment);
+        assert_eq!(photos[1].url, "https://x/2.jpg");
+        assert_eq!((photos[1].width, photos[1].height), (102, 202));
+    }
+
+    #[test]
+    fn a_malformed_album_entry_is_skipped_not_fatal() {
+        let mut attachment = album_attachment(30);
+        attachment["kl"][14] = Value::Null;
+        attachment["kl"][20] = json!("");
+        // A short size list leaves the photos past it unsized.
+        attachment["wl"].as_array_mut().unwrap().truncate(25);
+        let photos = album_photos(&attachment);
+        assert_eq!(photos.len(), 28);
+        assert!(photos[13].url.ends_with("album/14.jpg"));
+        // The photo after the bad one keeps its own position's size.
+        assert!(photos[14].url.ends_with("album/16.jpg"));
+        assert_eq!((photos[14].width, photos[14].height), (116, 216));
+        assert!(photos[27].url.ends_with("album/30.jpg"));
+        assert_eq!((photos[27].width, photos[27].height), (0, 230));
+        // An album with nothing usable is no album.
+        assert!(matches!(
+            MessageContent::parse(27, "raw", r#"{"kl":[null, 3]}"#),
+            MessageContent::Other { .. }
+        ));
+    }
+
+    #[test]
+    fn test_message_content_falls_back_on_bad_attachments() {
+        let other = |message_type: i64, attachment: Value| {
+            ChatMessage::from_json(&json!({
+                "type": message_type,
+                "message": "raw",
+                "attachment": attachment,
+            }))
+            .content
+        };
+        let expected = |message_type| MessageContent::Other {
+            message_type,
+            text: "raw".into(),
+        };
+        assert_eq!(other(2, json!("{broken")), expected(2));
+        assert_eq!(other(2, json!("")), expected(2));
+        assert_eq!(other(2, json!("[1, 2]")), expected(2));
+        assert_eq!(other(18, json!({"size": 5})), expected(18));
+        assert_eq!(other(27, json!({"kl": []})), expected(27));
+        assert_eq!(other(26, json!({"src_message": "no id"})), expected(26));
+        assert_eq!(other(99, json!({"url": "https://x"})), expected(99));
+        // An embedded object is accepted and kept as a string.
+        let msg = ChatMessage::from_json(&json!({
+            "type": 2,
+            "attachment": {"url": "https://x/p.png"},
+        }));
+        assert!(matches!(msg.content, MessageContent::Photo { .. }));
+        assert_eq!(msg.attachment, r#"{"url":"https://x/p.png"}"#);
+    }
+
+    #[test]
+    fn test_chat_message_author_without_nickname_uses_id() {
+        let msg = ChatMessage::from_json(&json!({"logId": 1, "authorId": 200}));
+        assert!(msg.author_nickname.is_none());
+        assert_eq!(msg.author_display_name(&HashMap::new()), "200");
+    }
+
+    fn log_ids(messages: &[ChatMessage]) -> Vec<i64> {
+        messages.iter().map(|m| m.log_id).collect()
+    }
+
+    fn page(ids: &[i64]) -> Vec<ChatMessage> {
+        ids.iter()
+            .map(|&id| ChatMessage::from_json(&json!({"logId": id, "sendAt": id})))
+            .collect()
+    }
+
+    #[test]
+    fn test_select_window_single_page_newest_first() {
+        // The server returns newest first; the window is the newest `count`,
+        // rendered oldest → newest.
+        let window = select_window(page(&[50, 40, 30, 20, 10]), 3, None);
+        assert_eq!(log_ids(&window), vec![30, 40, 50]);
+    }
+
+    #[test]
+    fn test_select_window_multi_page_with_overlap() {
+        let mut messages = page(&[60, 50, 40]);
+        messages.extend(page(&[40, 30, 20]));
+        messages.extend(page(&[20, 10]));
+        let window = select_window(messages, 4, None);
+        assert_eq!(log_ids(&window), vec![30, 40, 50, 60]);
+    }
+
+    #[test]
+    fn test_select_window_exact_and_short_counts() {
+        assert_eq!(
+            log_ids(&select_window(page(&[3, 1, 2]), 3, None)),
+            vec![1, 2, 3]
+        );
+        assert_eq!(
+            log_ids(&select_window(page(&[3, 1, 2]), 10, None)),
+            vec![1, 2, 3]
+        );
+        assert!(select_window(page(&[3, 1, 2]), 0, None).is_empty());
+    }
+
+    #[test]
+    fn test_select_window_before_is_exclusive() {
+        let window = select_window(page(&[50, 40, 30, 20, 10]), 2, Some(40));
+        assert_eq!(log_ids(&window), vec![20, 30]);
+        assert!(select_window(page(&[50, 40]), 5, Some(10)).is_empty());
+    }
+
+    #[test]
+    fn test_select_window_empty() {
+        assert!(select_window(Vec::new(), 30, None).is_empty());
+        assert!(select_window(Vec::new(), 30, Some(100)).is_empty());
+    }
+
+    #[test]
+    fn test_select_context_centers_on_target() {
+        let messages = page(&[70, 60, 50, 40, 30, 20, 10]);
+        let window = select_context_by(messages.clone(), 40, 4, |m| m.log_id);
+        assert!(window.found);
+        assert_eq!(log_ids(&window.items), vec![20, 30, 40, 50, 60]);
+        // Near the edge there is simply less on one side.
+        let window = select_context_by(messages, 60, 4, |m| m.log_id);
+        assert_eq!(log_ids(&window.items), vec![40, 50, 60, 70]);
+    }
+
+    #[test]
+    fn test_select_context_missing_target_returns_nearest_range() {
+        let messages = page(&[70, 60, 50, 40, 30, 20, 10]);
+        let window = select_context_by(messages.clone(), 35, 4, |m| m.log_id);
+        assert!(!window.found);
+        assert_eq!(log_ids(&window.items), vec![20, 30, 40, 50]);
+        // Older than everything available: the oldest `count` messages.
+        let window = select_context_by(messages.clone(), 5, 3, |m| m.log_id);
+        assert_eq!(log_ids(&window.items), vec![10, 20, 30]);
+        let window = select_context_by(messages, 99, 3, |m| m.log_id);
+        assert_eq!(log_ids(&window.items), vec![50, 60, 70]);
+    }
+
+    #[test]
+    fn test_select_window_keeps_unknown_log_ids() {
+        let window = select_window(page(&[0, 0, 5]), 10, None);
+        assert_eq!(log_ids(&window), vec![0, 0, 5]);
+    }
+
     #[test]
     fn test_chat_member_display_name() {
         let m = ChatMember {
             user_id: 1,
             nickname: "Nick".to_string(),
             friend_nickname: "Custom".to_string(),
+            open_profile_name: String::new(),
             country_iso: "KR".to_string(),
+            watermark: None,
         };
         assert_eq!(m.display_name(), "Custom");
     }
 
+    #[test]
+    fn test_display_name_fallback_order() {
+        assert_eq!(display_name("Nick", "Friend", "Open"), "Friend");
+        assert_eq!(display_name("Nick", "", "Open"), "Open");
+        assert_eq!(display_name("Nick", "  ", ""), "Nick");
+        assert_eq!(display_name(" Nick ", "", ""), "Nick");
+        assert_eq!(display_name("", "", ""), UNKNOWN_DISPLAY_NAME);
+        assert_eq!(display_name("\t", " ", "\n"), UNKNOWN_DISPLAY_NAME);
+    }
+
+    /// Every combination of missing / blank / set names must come out the
+    /// same from the function and each model that uses it.
+    #[test]
+    fn test_display_name_matrix_agrees_across_models() {
+        let options = ["", "  ", "value"];
+        for nickname in options {
+            for friend_nickname in options {
+                for open_name in options {
+                    let label = |base: &str, v: &str| {
+                        if v == "value" {
+                            base.to_string()
+                        } else {
+                            v.to_string()
+                        }
+                    };
+                    let (nick, friend, open) = (
+                        label("Nick", nickname),
+                        label("Friend", friend_nickname),
+                        label("Open", open_name),
+                    );
+                    let expected = display_name(&nick, &friend, &open);
+                    let case = format!("{nick:?}/{friend:?}/{open:?}");
+
+                    let member = ChatMember::from_json(&json!({
+                        "userId": 1,
+                        "nickName": nick,
+                        "friendNickName": friend,
+                        "openProfile": {"nickName": open},
+                    }));
+                    assert_eq!(member.display_name(), expected, "member {case}");
+
+                    let room = ChatRoom {
+                        chat_id: 1,
+                        kind: "OM".to_string(),
+                        title: String::new(),
+                        unread_count: 0,
+                        display_members: vec![json!({
+                            "nickName": nick,
+                            "friendNickName": friend,
+                            "openProfile": {"nickName": open},
+                        })],
+                        last_active_at: None,
+                        last_updated_at: None,
+                        last_message: None,
+                        settings: RoomSettings::default(),
+                    };
+                    let title = if expected == UNKNOWN_DISPLAY_NAME {
+                        "(empty)".to_string()
+                    } else {
+                        expected.clone()
+                    };
+                    assert_eq!(room.display_title(), title, "room {case}");
+
+                    let friend_model = Friend::from_json(&json!({
+                        "userId": 1,
+                        "nickName": nick,
+                        "friendNickName": friend,
+                    }));
+                    assert_eq!(
+                        friend_model.display_name(),
+                        display_name(&nick, &friend, ""),
+                        "friend {case}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_read_watermarks_count_members_behind() {
+        // Viewer 1, author 2; members 3 and 4 have read up to 100 and 90.
+        let marks = ReadWatermarks::from_parallel(1, &[1, 2, 3, 4], &[50, 80, 100, 90]).unwrap();
+        assert_eq!(marks.unread_by(80, 2), 0);
+        assert_eq!(marks.unread_by(95, 2), 1);
+        assert_eq!(marks.unread_by(101, 2), 2);
+        // The viewer's own stale watermark never counts.
+        assert_eq!(marks.unread_by(101, 3), 2);
+        assert_eq!(marks.unread_by(85, 3), 1);
+    }
+
+    #[test]
+    fn test_read_watermarks_absent_for_unsupported_rooms() {
+        assert!(ReadWatermarks::from_parallel(1, &[], &[]).is_none());
+        assert!(ReadWatermarks::from_parallel(1, &[1, 2], &[10]).is_none());
+
+        let v = json!({"members": [
+            {"userId": 2, "nickName": "A", "watermark": 120},
+            {"userId": 3, "nickName": "B"},
+        ]});
+        let page = ChatMembersPage::from_json(&v);
+        assert_eq!(page.members[0].watermark, Some(120));
+        assert_eq!(page.members[1].watermark, None);
+        assert!(ReadWatermarks::from_members(1, &page.members).is_none());
+        assert!(ReadWatermarks::from_members(1, &page.members[..1]).is_some());
+    }
+
+    #[test]
+    fn test_chat_members_page_reads_cursor_and_total() {
+        let v = json!({
+            "members": [{"userId": 1, "nickName": "A"}, {"userId": 2, "nickName": "B"}],
+            "nextCursor": 2,
+            "totalCount": 1500,
+        });
+        let page = ChatMembersPage::from_json(&v);
+        assert_eq!(page.members.len(), 2);
+        assert_eq!(page.next_cursor.as_deref(), Some("2"));
+        assert_eq!(page.total, Some(1500));
+    }
+
+    #[test]
+    fn test_chat_members_page_accepts_string_token() {
+        let v = json!({"members": [], "nextToken": "abc"});
+        let page = ChatMembersPage::from_json(&v);
+        assert_eq!(page.next_cursor.as_deref(), Some("abc"));
+        assert_eq!(page.total, None);
+    }
+
+    #[test]
+    fn test_chat_members_page_last_page_has_no_cursor() {
+        let v = json!({"members": [], "nextCursor": 99, "last": true});
+        assert!(ChatMembersPage::from_json(&v).next_cursor.is_none());
+
+        let v = json!({"members": [], "nextCursor": 0});
+        assert!(ChatMembersPage::from_json(&v).next_cursor.is_none());
+    }
+
     #[test]
     fn test_credentials_serialize_roundtrip() {
         let creds = KakaoCredentials::new(
@@ -0,0 +1,483 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::hangul::name_matches;
+use crate::model::{json_i64, ChatMember, ChatRoom, Friend};
+
+/// Cache-hit counters for a [`NameResolver`].
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct NameResolverStats {
+    pub friend_fetches: usize,
+    pub friend_hits: usize,
+    pub member_fetches: usize,
+    pub member_hits: usize,
+    /// Member lists dropped to stay under [`NameCacheLimits::max_chats`].
+    pub member_evictions: usize,
+    /// Lookups that found a list older than [`NameCacheLimits::ttl`] and
+    /// refetched it (also counted in the `*_fetches` fields).
+    pub expired: usize,
+}
+
+/// Bounds on what a [`NameResolver`] keeps, so long `watch` sessions that
+/// touch many chats do not grow without limit and pick up nickname changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NameCacheLimits {
+    /// Member lists kept at once; the least recently used goes first.
+    pub max_chats: usize,
+    /// Age after which a friends or member list is refetched on next use.
+    pub ttl: Duration,
+}
+
+impl Default for NameCacheLimits {
+    fn default() -> Self {
+        Self {
+            max_chats: 256,
+            ttl: Duration::from_secs(30 * 60),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Cached {
+    names: HashMap<i64, String>,
+    fetched_at: Instant,
+    last_used: u64,
+}
+
+/// In-memory name cache.
+///
+/// The friends list and each chat's member list are loaded on first use and
+/// served from memory until they are older than the TTL; member lists are
+/// also evicted least-recently-used once `max_chats` is reached. Loading is
+/// left to the caller through closures so the resolver works with any
+/// transport.
+#[derive(Debug)]
+pub struct NameResolver {
+    my_user_id: i64,
+    limits: NameCacheLimits,
+    clock: fn() -> Instant,
+    friends: Option<(HashMap<i64, String>, Instant)>,
+    /// Friend user_id → their 1:1 chat, from the same friends fetch.
+    direct_chats: HashMap<i64, i64>,
+    chats: HashMap<i64, Cached>,
+    /// Monotonic use counter for LRU order.
+    tick: u64,
+    stats: NameResolverStats,
+}
+
+impl NameResolver {
+    pub fn new(my_user_id: i64) -> Self {
+        Self::with_limits(my_user_id, NameCacheLimits::default())
+    }
+
+    pub fn with_limits(my_user_id: i64, limits: NameCacheLimits) -> Self {
+        Self::with_clock(my_user_id, limits, Instant::now)
+    }
+
+    /// [`Self::with_limits`] reading time from `clock`, for tests.
+    pub fn with_clock(my_user_id: i64, limits: NameCacheLimits, clock: fn() -> Instant) -> Self {
+        Self {
+            my_user_id,
+            limits,
+            clock,
+            friends: None,
+            direct_chats: HashMap::new(),
+            chats: HashMap::new(),
+            tick: 0,
+            stats: NameResolverStats::default(),
+        }
+    }
+
+    fn is_fresh(&self, fetched_at: Instant) -> bool {
+        (self.clock)().saturating_duration_since(fetched_at) < self.limits.ttl
+    }
+
+    /// user_id → friend display name, loading the friends list on first use
+    /// and again once it has expired.
+    pub fn friend_names<F>(&mut self, fetch: F) -> Result<&HashMap<i64, String>>
+    where
+        F: FnOnce() -> Result<Vec<Friend>>,
+    {
+        match &self.friends {
+            Some((_, fetched_at)) if self.is_fresh(*fetched_at) => self.stats.friend_hits += 1,
+            cached => {
+                if cached.is_some() {
+                    self.stats.expired += 1;
+                }
+                self.stats.friend_fetches += 1;
+                let friends = fetch()?;
+                let names = friends
+                    .iter()
+                    .map(|f| (f.user_id, f.display_name()))
+                    .collect();
+                self.direct_chats = friends
+                    .iter()
+                    .filter_map(|f| Some((f.user_id, f.direct_chat_id?)))
+                    .collect();
+                self.friends = Some((names, (self.clock)()));
+            }
+        }
+        Ok(&self
+            .friends
+            .get_or_insert_with(|| (HashMap::new(), (self.clock)()))
+            .0)
+    }
+
+    /// user_id → member display name for a chat, loading the member list on
+    /// first use and again once it has expired. The current user always maps
+    /// to "Me".
+    pub fn member_names<F>(&mut self, chat_id: i64, fetch: F) -> Result<&HashMap<i64, String>>
+    where
+        F: FnOnce() -> Result<Vec<ChatMember>>,
+    {
+        self.tick += 1;
+        let fresh = match self.chats.get(&chat_id) {
+            Some(cached) if self.is_fresh(cached.fetched_at) => true,
+            Some(_) => {
+                self.stats.expired += 1;
+                false
+            }
+            None => false,
+        };
+        if fresh {
+            self.stats.member_hits += 1;
+        } else {
+            self.stats.member_fetches += 1;
+            let members = fetch()?;
+            let mut names: HashMap<i64, String> = members
+                .iter()
+                .map(|m| (m.user_id, m.display_name()))
+                .collect();
+            names.insert(self.my_user_id, "Me".to_string());
+            if !self.chats.contains_key(&chat_id) {
+                self.evict_to(self.limits.max_chats.saturating_sub(1));
+            }
+            self.chats.insert(
+                chat_id,
+                Cached {
+                    names,
+                    fetched_at: (self.clock)(),
+                    last_used: 0,
+                },
+            );
+        }
+        let cached = self
+            .chats
+            .get_mut(&chat_id)
+            .expect("member list was just cached");
+        cached.last_used = self.tick;
+        Ok(&cached.names)
+    }
+
+    /// Drop least recently used member lists until at most `keep` remain.
+    fn evict_to(&mut self, keep: usize) {
+        while self.chats.len() > keep {
+            let Some(oldest) = self
+                .chats
+                .iter()
+                .min_by_key(|(_, cached)| cached.last_used)
+                .map(|(chat_id, _)| *chat_id)
+            else {
+                break;
+            };
+            self.chats.remove(&oldest);
+            self.stats.member_evictions += 1;
+        }
+    }
+
+    /// Resolve a user from already-loaded data: chat members first, then
+    /// friends. Never triggers a fetch; expired lists still answer until
+    /// their next refetch.
+    pub fn resolve(&self, chat_id: i64, user_id: i64) -> Option<String> {
+        if user_id == self.my_user_id {
+            return Some("Me".to_string());
+        }
+        self.chats
+            .get(&chat_id)
+            .and_then(|cached| cached.names.get(&user_id))
+            .or_else(|| {
+                self.friends
+                    .as_ref()
+                    .and_then(|(names, _)| names.get(&user_id))
+            })
+            .cloned()
+    }
+
+    /// Friends whose display name matches `query`, as `(user_id, name)`
+    /// sorted by name. Names equal to the query (ignoring case) win over
+    /// substring and 초성 matches, so "Kim" is not ambiguous with "Kimberly".
+    pub fn find_friends<F>(&mut self, query: &str, fetch: F) -> Result<Vec<(i64, String)>>
+    where
+        F: FnOnce() -> Result<Vec<Friend>>,
+    {
+        let names = self.friend_names(fetch)?;
+        let wanted = query.trim().to_lowercase();
+        let mut exact = Vec::new();
+        let mut partial = Vec::new();
+        for (user_id, name) in names {
+            if name.to_lowercase() == wanted {
+                exact.push((*user_id, name.clone()));
+            } else if name_matches(name, query) {
+                partial.push((*user_id, name.clone()));
+            }
+        }
+        let mut found = if exact.is_empty() { partial } else { exact };
+        found.sort_by(|a, b| a.1.cmp(&b.1).then(a.0.cmp(&b.0)));
+        Ok(found)
+    }
+
+    /// Our 1:1 chat with `user_id`: the `directChatId` from the friends list
+    /// when it carried one, otherwise the DirectChat in `chats` whose other
+    /// member is that user. `chats` is only called when needed.
+    pub fn direct_chat<F>(&self, user_id: i64, chats: F) -> Result<Option<DirectChat>>
+    where
+        F: FnOnce() -> Result<Vec<ChatRoom>>,
+    {
+        if let Some(chat_id) = self.direct_chats.get(&user_id) {
+            return Ok(Some(DirectChat {
+                chat_id: *chat_id,
+                from_friends_list: true,
+            }));
+        }
+        Ok(chats()?
+            .iter()
+            .find(|room| {
+                room.kind == "DirectChat"
+                    && room
+                        .display_members
+                        .iter()
+                        .any(|member| json_i64(member, "userId") == user_id)
+            })
+            .map(|room| DirectChat {
+                chat_id: room.chat_id,
+                from_friends_list: false,
+            }))
+    }
+
+    pub fn stats(&self) -> NameResolverStats {
+        self.stats
+    }
+}
+
+/// Where [`NameResolver::direct_chat`] found the chat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DirectChat {
+    pub chat_id: i64,
+    /// From the friends payload rather than a scan of the chat list.
+    pub from_friends_list: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(user_id: i64, nickname: &str) -> ChatMember {
+        ChatMember {
+            user_id,
+            nickname: nickname.to_string(),
+            friend_nickname: String::new(),
+            open_profile_name: String::new(),
+            country_iso: String::new(),
+            watermark: None,
+        }
+    }
+
+    #[test]
+    fn member_list_is_fetched_once_per_chat() {
+        let mut resolver = NameResolver::new(1);
+        let mut calls = 0;
+        for _ in 0..3 {
+            resolver
+                .member_names(10, || {
+                    calls += 1;
+                    Ok(vec![member(2, "Alice")])
+                })
+                .unwrap();
+        }
+        assert_eq!(calls, 1);
+        let stats = resolver.stats();
+        assert_eq!(stats.member_fetches, 1);
+        assert_eq!(stats.member_hits, 2);
+        assert_eq!(resolver.resolve(10, 2).as_deref(), Some("Alice"));
+        assert_eq!(resolver.resolve(10, 1).as_deref(), Some("Me"));
+    }
+
+    #[test]
+    fn failed_fetch_is_not_cached() {
+        let mut resolver = NameResolver::new(1);
+        assert!(resolver
+            .member_names(10, || Err(anyhow::anyhow!("offline")))
+            .is_err());
+        let names = resolver
+            .member_names(10, || Ok(vec![member(2, "Bob")]))
+            .unwrap();
+        assert_eq!(names.get(&2).map(String::as_str), Some("Bob"));
+        assert_eq!(resolver.stats().member_fetches, 2);
+    }
+
+    thread_local! {
+        static ELAPSED: std::cell::Cell<Duration> = const { std::cell::Cell::new(Duration::ZERO) };
+    }
+
+    /// Test clock: a fixed origin plus whatever [`advance`] added.
+    fn mock_now() -> Instant {
+        static ORIGIN: std::sync::OnceLock<Instant> = std::sync::OnceLock::new();
+        *ORIGIN.get_or_init(Instant::now) + ELAPSED.with(|e| e.get())
+    }
+
+    fn advance(by: Duration) {
+        ELAPSED.with(|e| e.set(e.get() + by));
+    }
+
+    fn small_resolver() -> NameResolver {
+        let limits = NameCacheLimits {
+            max_chats: 2,
+            ttl: Duration::from_secs(60),
+        };
+        NameResolver::with_clock(1, limits, mock_now)
+    }
+
+    #[test]
+    fn least_recently_used_chat_is_evicted() {
+        let mut resolver = small_resolver();
+        let fetch = |name: &'static str| move || Ok(vec![member(2, name)]);
+        resolver.member_names(10, fetch("A")).unwrap();
+        resolver.member_names(20, fetch("B")).unwrap();
+        // Touch 10 so 20 becomes the least recently used.
+        resolver.member_names(10, fetch("A")).unwrap();
+        resolver.member_names(30, fetch("C")).unwrap();
+
+        assert_eq!(resolver.resolve(10, 2).as_deref(), Some("A"));
+        assert_eq!(resolver.resolve(20, 2), None);
+        assert_eq!(resolver.resolve(30, 2).as_deref(), Some("C"));
+        let stats = resolver.stats();
+        assert_eq!(stats.member_evictions, 1);
+        assert_eq!(stats.member_fetches, 3);
+        assert_eq!(stats.member_hits, 1);
+    }
+
+    #[test]
+    fn expired_lists_refetch_on_next_use() {
+        let mut resolver = small_resolver();
+        resolver
+            .member_names(10, || Ok(vec![member(2, "Old nick")]))
+            .unwrap();
+        advance(Duration::from_secs(59));
+        resolver.member_names(10, || panic!("still fresh")).unwrap();
+
+        advance(Duration::from_secs(2));
+        // Lookups without a fetch keep answering from the stale list.
+        assert_eq!(resolver.resolve(10, 2).as_deref(), Some("Old nick"));
+        let names = resolver
+            .member_names(10, || Ok(vec![member(2, "New nick")]))
+            .unwrap();
+        assert_eq!(names.get(&2).map(String::as_str), Some("New nick"));
+        let stats = resolver.stats();
+        assert_eq!(stats.expired, 1);
+        assert_eq!(stats.member_fetches, 2);
+        assert_eq!(stats.member_evictions, 0);
+    }
+
+    #[test]
+    fn resolve_falls_back_to_friends() {
+        let mut resolver = NameResolver::new(1);
+        resolver
+            .friend_names(|| {
+                Ok(vec![Friend {
+                    user_id: 3,
+                    nickname: "Carol".into(),
+                    friend_nickname: "C".into(),
+                    phone_number: String::new(),
+                    country_iso: String::new(),
+                    status_message: String::new(),
+                    favorite: false,
+                    hidden: false,
+                    channel: false,
+                    uuid: String::new(),
+                    profile_image_url: String::new(),
+                    direct_chat_id: None,
+                }])
+            })
+            .unwrap();
+        assert_eq!(resolver.resolve(99, 3).as_deref(), Some("C"));
+        assert_eq!(resolver.resolve(99, 4), None);
+    }
+
+    fn friends_fixture() -> Vec<Friend> {
+        vec![
+            Friend::from_json(&serde_json::json!({"userId": 2, "nickName": "철수"})),
+            Friend::from_json(&serde_json::json!({"userId": 3, "nickName": "김철수"})),
+            Friend::from_json(&serde_json::json!({
+                "userId": 4,
+                "nickName": "Kim",
+                "directChatId": 400,
+            })),
+            Friend::from_json(&serde_json::json!({"userId": 5, "nickName": "Kimberly"})),
+        ]
+    }
+
+    fn room(v: serde_json::Value) -> ChatRoom {
+        ChatRoom::from_json(&v)
+    }
+
+    #[test]
+    fn find_friends_prefers_exact_names() {
+        let mut resolver = NameResolver::new(1);
+        let found = resolver
+            .find_friends("철수", || Ok(friends_fixture()))
+            .unwrap();
+        assert_eq!(found, [(2, "철수".to_string())]);
+        let found = resolver.find_friends("kim", || panic!("cached")).unwrap();
+        assert_eq!(found, [(4, "Kim".to_string())]);
+        // No exact hit: every substring or 초성 match, so callers can list them.
+        let found = resolver.find_friends("ㅊㅅ", || panic!("cached")).unwrap();
+        assert_eq!(found.iter().map(|f| f.0).collect::<Vec<_>>(), [3, 2]);
+        assert!(resolver
+            .find_friends("nobody", || panic!("cached"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn direct_chat_uses_friends_payload_then_scans_chats() {
+        let mut resolver = NameResolver::new(1);
+        resolver.friend_names(|| Ok(friends_fixture())).unwrap();
+
+        // directChatId present: the chat list is not needed.
+        let dm = resolver.direct_chat(4, || panic!("not needed")).unwrap();
+        assert_eq!(
+            dm,
+            Some(DirectChat {
+                chat_id: 400,
+                from_friends_list: true
+            })
+        );
+
+        // directChatId missing: found through the DM's display member. A
+        // group chat with the same member does not count.
+        let chats = || {
+            Ok(vec![
+                room(serde_json::json!({
+                    "chatId": 10, "type": "MultiChat",
+                    "displayMembers": [{"userId": 2}, {"userId": 3}],
+                })),
+                room(serde_json::json!({
+                    "chatId": 20, "type": "DirectChat",
+                    "displayMembers": [{"userId": "2", "nickName": "철수"}],
+                })),
+            ])
+        };
+        let dm = resolver.direct_chat(2, chats).unwrap();
+        assert_eq!(
+            dm.map(|d| (d.chat_id, d.from_friends_list)),
+            Some((20, false))
+        );
+
+        // No DM with this friend yet.
+        assert_eq!(resolver.direct_chat(3, chats).unwrap(), None);
+    }
+}
@@ -0,0 +1,334 @@
+//! Search normalization behind `--normalize`.
+//!
+//! Chat text spells the same thing many ways: `ㅋㅋ` and `ㅋㅋㅋㅋㅋ`,
+//! `ＯＫ！` and `OK!`, composed `한` and the decomposed jamo macOS file names
+//! use. [`normalize_text`] maps all of these to one form, so comparing
+//! normalized needle and haystack finds what a reader would call a match.
+//! The archive applies the same function when indexing and when querying.
+//!
+//! Phone numbers get the same treatment for display: [`format_phone`]
+//! folds them and renders the international form, using a small table of
+//! calling codes rather than a full numbering-plan database.
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::hangul::is_compatibility_jamo;
+
+/// Length a run of one repeated jamo (`ㅋㅋㅋㅋ`, `ㅠㅠㅠ`) collapses to.
+pub const JAMO_RUN_LENGTH: usize = 2;
+
+/// NFC, full-width forms folded to ASCII, Latin letters lowercased, and runs
+/// of a repeated compatibility jamo cut to [`JAMO_RUN_LENGTH`].
+///
+/// Idempotent, and never longer (in characters) than the NFC form of the
+/// input.
+pub fn normalize_text(text: &str) -> String {
+    // Folding can expose new compositions (`Ａ` + U+0301 -> `A` + U+0301),
+    // hence a second NFC pass.
+    let folded: String = text.nfc().map(fold_width).map(fold_latin_case).collect();
+    let mut out = String::with_capacity(folded.len());
+    let mut run: Option<(char, usize)> = None;
+    for c in folded.nfc() {
+        run = match run {
+            Some((prev, len)) if prev == c && is_compatibility_jamo(c) => {
+                if len >= JAMO_RUN_LENGTH {
+                    continue;
+                }
+                Some((c, len + 1))
+            }
+            _ => Some((c, 1)),
+        };
+        out.push(c);
+    }
+    out
+}
+
+/// Whether `haystack` contains `needle` once both are normalized.
+pub fn contains_normalized(haystack: &str, needle: &str) -> bool {
+    normalize_text(haystack).contains(&normalize_text(needle))
+}
+
+/// Full-width ASCII variants (U+FF01..U+FF5E) and the ideographic space.
+fn fold_width(c: char) -> char {
+    match c {
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+        '\u{3000}' => ' ',
+        _ => c,
+    }
+}
+
+/// Lowercase Latin letters only, and only where that stays one character
+/// (`İ` would become two).
+fn fold_latin_case(c: char) -> char {
+    let is_latin = matches!(c, 'A'..='Z' | '\u{C0}'..='\u{24F}' | '\u{1E00}'..='\u{1EFF}');
+    if !is_latin {
+        return c;
+    }
+    let mut lower = c.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(l), None) => l,
+        _ => c,
+    }
+}
+
+/// Calling codes of the countries most KakaoTalk accounts are in. Where
+/// codes share a prefix the longer one comes first, and of countries
+/// sharing a code the first listed is the one inferred.
+const CALLING_CODES: &[(&str, &str)] = &[
+    ("KR", "82"),
+    ("JP", "81"),
+    ("US", "1"),
+    ("CA", "1"),
+    ("CN", "86"),
+    ("TW", "886"),
+    ("HK", "852"),
+    ("MO", "853"),
+    ("VN", "84"),
+    ("TH", "66"),
+    ("PH", "63"),
+    ("ID", "62"),
+    ("MY", "60"),
+    ("SG", "65"),
+    ("MN", "976"),
+    ("UZ", "998"),
+    ("RU", "7"),
+    ("KZ", "7"),
+    ("IN", "91"),
+    ("AU", "61"),
+    ("NZ", "64"),
+    ("GB", "44"),
+    ("DE", "49"),
+    ("FR", "33"),
+    ("AE", "971"),
+];
+
+/// Countries whose national numbers start with a trunk `0` that the
+/// international form drops.
+const TRUNK_ZERO: &[&str] = &[
+    "KR", "JP", "CN", "TW", "VN", "TH", "PH", "ID", "MY", "MN", "AU", "NZ", "GB", "DE", "FR", "AE",
+];
+
+/// The country a `+<code>` number belongs to; `None` for national numbers
+/// and unknown codes.
+pub fn infer_phone_country(raw: &str) -> Option<&'static str> {
+    let (international, digits) = phone_digits(raw)?;
+    if !international {
+        return None;
+    }
+    split_calling_code(&digits).map(|(country, _, _)| country)
+}
+
+/// `raw` in international form (`+82 10-1234-5678`). Numbers with a
+/// `+<code>` prefix keep their own country; others are read as national
+/// numbers of `country_iso`, or of KR when that is empty. Anything that does
+/// not look like a phone number, or whose code is not in the table, comes
+/// back unchanged.
+pub fn format_phone(raw: &str, country_iso: &str) -> String {
+    let formatted = phone_digits(raw).and_then(|(international, digits)| {
+        let (country, code, national) = if international {
+            split_calling_code(&digits)?
+        } else {
+            let wanted = if country_iso.trim().is_empty() {
+                "KR".to_string()
+            } else {
+                country_iso.trim().to_ascii_uppercase()
+            };
+            let (country, code) = CALLING_CODES
+                .iter()
+                .copied()
+                .find(|(country, _)| *country == wanted)?;
+            let national = if TRUNK_ZERO.contains(&country) {
+                digits.strip_prefix('0').unwrap_or(&digits)
+            } else if code == "1" && digits.len() == 11 {
+                digits.strip_prefix('1').unwrap_or(&digits)
+            } else {
+                &digits
+            };
+            (country, code, national.to_string())
+        };
+        if !(6..=12).contains(&national.len()) {
+            return None;
+        }
+        Some(format!("+{code} {}", group_national(country, &national)))
+    });
+    formatted.unwrap_or_else(|| raw.to_string())
+}
+
+/// Whether `raw` starts with `+`, and its digits. `None` unless it is only
+/// digits and the usual separators (full-width forms included).
+fn phone_digits(raw: &str) -> Option<(bool, String)> {
+    let folded: String = raw.trim().chars().map(fold_width).collect();
+    let (international, rest) = match folded.strip_prefix('+') {
+        Some(rest) => (true, rest),
+        None => (false, folded.as_str()),
+    };
+    if !rest
+        .chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, ' ' | '-' | '.' | '(' | ')'))
+    {
+        return None;
+    }
+    let digits: String = rest.chars().filter(char::is_ascii_digit).collect();
+    (7..=15)
+        .contains(&digits.len())
+        .then_some((international, digits))
+}
+
+fn split_calling_code(digits: &str) -> Option<(&'static str, &'static str, String)> {
+    let mut codes: Vec<(&str, &str)> = CALLING_CODES.to_vec();
+    // Longest code first; the stable sort keeps the table order otherwise.
+    codes.sort_by_key(|(_, code)| std::cmp::Reverse(code.len()));
+    codes.into_iter().find_map(|(country, code)| {
+        digits
+            .strip_prefix(code)
+            .map(|national| (country, code, national.to_string()))
+    })
+}
+
+/// The national part with the separators people there write: area or
+/// mobile prefix, then the subscriber number split before its last four
+/// digits. Countries without a rule are left ungrouped.
+fn group_national(country: &str, national: &str) -> String {
+    let prefix_len = match country {
+        // Seoul is 2; mobile (10, 11, ...) and other areas take two digits.
+        "KR" if national.starts_with('2') => 1,
+        "KR" => 2,
+        // Mobile 70/80/90 and Osaka 6 aside, Tokyo is 3.
+        "JP" if national.len() == 10 && national[..2].ends_with('0') => 2,
+        "JP" if national.starts_with('3') || national.starts_with('6') => 1,
+        "US" | "CA" if national.len() == 10 => 3,
+        _ => return national.to_string(),
+    };
+    let (prefix, subscriber) = national.split_at(prefix_len);
+    if subscriber.len() < 7 {
+        return format!("{prefix}-{subscriber}");
+    }
+    let (exchange, line) = subscriber.split_at(subscriber.len() - 4);
+    format!("{prefix}-{exchange}-{line}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn laughter_and_crying_runs_collapse() {
+        assert_eq!(normalize_text("ㅋㅋㅋㅋㅋ"), "ㅋㅋ");
+        assert_eq!(normalize_text("ㅋㅋ"), "ㅋㅋ");
+        assert_eq!(normalize_text("ㅋ"), "ㅋ");
+        assert_eq!(normalize_text("좋아ㅠㅠㅠㅠ ㅎㅎㅎ"), "좋아ㅠㅠ ㅎㅎ");
+        // Only runs of the same jamo; syllables and other letters stay.
+        assert_eq!(normalize_text("ㅋㅎㅋㅎ"), "ㅋㅎㅋㅎ");
+        assert_eq!(normalize_text("하하하하"), "하하하하");
+        assert_eq!(normalize_text("~~~!!!"), "~~~!!!");
+        assert!(contains_normalized("진짜 웃기다ㅋㅋㅋㅋㅋㅋ", "ㅋㅋㅋ"));
+    }
+
+    #[test]
+    fn width_case_and_composition_fold() {
+        assert_eq!(normalize_text("ＯＫ！　Ｔｈａｎｋｓ"), "ok! thanks");
+        assert_eq!(normalize_text("Café ÉCOLE"), "café école");
+        // Decomposed jamo (as in macOS file names) compose.
+        assert_eq!(normalize_text("\u{1112}\u{1161}\u{11AB}"), "한");
+        // A full-width letter with a combining accent composes after folding.
+        assert_eq!(normalize_text("\u{FF25}\u{0301}"), "é");
+        // Non-Latin scripts keep their case; İ would grow, so it stays.
+        assert_eq!(normalize_text("ΩΣ İ"), "ΩΣ İ");
+        assert!(contains_normalized("회의록_FINAL.pdf", "final"));
+        assert!(!contains_normalized("회의록.pdf", "보고서"));
+    }
+
+    /// Random strings over the characters the normalizer treats specially.
+    fn samples() -> impl Iterator<Item = String> {
+        const ALPHABET: &[char] = &[
+            'ㅋ', 'ㅎ', 'ㅠ', 'ㅜ', '가', '한', 'a', 'A', 'Z', 'É', 'é', 'İ', 'Ω', '1', ' ', '!',
+            '~', 'Ａ', 'ｂ', '！', '　', '\u{1100}', '\u{1161}', '\u{11A8}', '\u{0301}',
+            '\u{0344}', '\u{FFE6}',
+        ];
+        let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        (0..2000).map(move |_| {
+            let len = (next() % 24) as usize;
+            (0..len)
+                .map(|_| ALPHABET[(next() % ALPHABET.len() as u64) as usize])
+                .collect()
+        })
+    }
+
+    #[test]
+    fn phones_render_in_international_form() {
+        // KR is the default country.
+        assert_eq!(format_phone("010-1234-5678", ""), "+82 10-1234-5678");
+        assert_eq!(format_phone("01012345678", "kr"), "+82 10-1234-5678");
+        assert_eq!(format_phone("+82 10 1234 5678", ""), "+82 10-1234-5678");
+        assert_eq!(format_phone("02-123-4567", "KR"), "+82 2-123-4567");
+        assert_eq!(format_phone("031-1234-5678", ""), "+82 31-1234-5678");
+        assert_eq!(
+            format_phone("０１０-１２３４-５６７８", ""),
+            "+82 10-1234-5678"
+        );
+
+        assert_eq!(format_phone("090-1234-5678", "JP"), "+81 90-1234-5678");
+        assert_eq!(format_phone("03-1234-5678", "JP"), "+81 3-1234-5678");
+        // The number's own code wins over the profile country.
+        assert_eq!(format_phone("+81 80 1234 5678", "KR"), "+81 80-1234-5678");
+
+        assert_eq!(format_phone("(555) 123-4567", "US"), "+1 555-123-4567");
+        assert_eq!(format_phone("1-555-123-4567", "US"), "+1 555-123-4567");
+        assert_eq!(format_phone("+1 555.123.4567", ""), "+1 555-123-4567");
+
+        // A known country without a grouping rule keeps its digits together.
+        assert_eq!(format_phone("+65 9123 4567", ""), "+65 91234567");
+    }
+
+    #[test]
+    fn malformed_phones_pass_through_unchanged() {
+        for raw in [
+            "",
+            "   ",
+            "unknown",
+            "010-1234-5678 ext 9",
+            "12-34",
+            "+",
+            "+999 1234 5678",
+            "1234567890123456",
+            "+82 10",
+        ] {
+            assert_eq!(format_phone(raw, "KR"), raw, "{raw:?}");
+        }
+        // An unknown profile country leaves national numbers alone.
+        assert_eq!(format_phone("010-1234-5678", "ZZ"), "010-1234-5678");
+    }
+
+    #[test]
+    fn country_is_inferred_from_the_calling_code() {
+        assert_eq!(infer_phone_country("+82 10-1234-5678"), Some("KR"));
+        assert_eq!(infer_phone_country("+81 90 1234 5678"), Some("JP"));
+        assert_eq!(infer_phone_country("+1 555 123 4567"), Some("US"));
+        assert_eq!(infer_phone_country("+886 912 345 678"), Some("TW"));
+        assert_eq!(infer_phone_country("+7 912 123 4567"), Some("RU"));
+        assert_eq!(infer_phone_country("010-1234-5678"), None);
+        assert_eq!(infer_phone_country("+999 1234 5678"), None);
+    }
+
+    #[test]
+    fn normalization_is_idempotent() {
+        for input in samples() {
+            let once = normalize_text(&input);
+            assert_eq!(normalize_text(&once), once, "input {input:?}");
+        }
+    }
+
+    #[test]
+    fn normalization_never_grows_past_nfc() {
+        for input in samples() {
+            let out = normalize_text(&input).chars().count();
+            assert!(out <= input.nfc().count(), "input {input:?}");
+        }
+    }
+}
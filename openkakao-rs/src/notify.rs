@@ -0,0 +1,401 @@
+use std::io::Write;
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+
+/// Characters of message text kept in a notification body.
+const PREVIEW_CHARS: usize = 120;
+
+const TELEGRAM_API: &str = "https://api.telegram.org";
+
+/// What every notification backend receives for one incoming message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Notification {
+    pub chat_id: i64,
+    pub log_id: i64,
+    pub chat_title: String,
+    pub author: String,
+    /// The message text, shortened to a single notification-sized line.
+    pub preview: String,
+}
+
+impl Notification {
+    pub fn new(chat_id: i64, log_id: i64, chat_title: &str, author: &str, message: &str) -> Self {
+        let flat = message.split_whitespace().collect::<Vec<_>>().join(" ");
+        let preview = if flat.chars().count() > PREVIEW_CHARS {
+            let mut cut: String = flat.chars().take(PREVIEW_CHARS).collect();
+            cut.push('…');
+            cut
+        } else {
+            flat
+        };
+        Self {
+            chat_id,
+            log_id,
+            chat_title: chat_title.to_string(),
+            author: author.to_string(),
+            preview,
+        }
+    }
+}
+
+/// A place `watch --notify` can deliver messages to. Implement this to plug
+/// in another service; errors are reported by the caller and never stop the
+/// watch loop.
+///
+/// ```
+/// use openkakao_rs::notify::{Notification, Notifier};
+///
+/// struct Count(std::sync::atomic::AtomicUsize);
+///
+/// impl Notifier for Count {
+///     fn name(&self) -> &str {
+///         "count"
+///     }
+///     fn notify(&self, _: &Notification) -> anyhow::Result<()> {
+///         self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+///         Ok(())
+///     }
+/// }
+///
+/// let counter = Count(Default::default());
+/// counter.notify(&Notification::new(1, 2, "Team", "Alice", "hi")).unwrap();
+/// ```
+pub trait Notifier: Send + Sync {
+    fn name(&self) -> &str;
+    fn notify(&self, notification: &Notification) -> Result<()>;
+}
+
+/// `--notify` value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotifyBackend {
+    Macos,
+    Desktop,
+    Telegram,
+    Stdout,
+}
+
+impl NotifyBackend {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "macos" => Ok(Self::Macos),
+            "desktop" => Ok(Self::Desktop),
+            "telegram" => Ok(Self::Telegram),
+            "stdout" => Ok(Self::Stdout),
+            _ => Err(anyhow!(
+                "Unknown notify backend '{}'. Use: macos, desktop, telegram, stdout",
+                s
+            )),
+        }
+    }
+}
+
+/// One line per notification on stdout (or any writer, for tests and
+/// embedders that log elsewhere).
+pub struct StdoutNotifier {
+    out: Mutex<Box<dyn Write + Send>>,
+}
+
+impl StdoutNotifier {
+    pub fn new() -> Self {
+        Self::with_writer(Box::new(std::io::stdout()))
+    }
+
+    pub fn with_writer(out: Box<dyn Write + Send>) -> Self {
+        Self {
+            out: Mutex::new(out),
+        }
+    }
+}
+
+impl Default for StdoutNotifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Notifier for StdoutNotifier {
+    fn name(&self) -> &str {
+        "stdout"
+    }
+
+    fn notify(&self, n: &Notification) -> Result<()> {
+        let mut out = self.out.lock().unwrap_or_else(|e| e.into_inner());
+        writeln!(
+            out,
+            "[notify] {} — {}: {}",
+            n.chat_title, n.author, n.preview
+        )?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// macOS Notification Center through `osascript`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MacosNotifier;
+
+impl MacosNotifier {
+    /// The `osascript` invocation for `n`, without running it.
+    pub fn command(&self, n: &Notification) -> Command {
+        let script = format!(
+            "display notification \"{}\" with title \"{}\" subtitle \"{}\"",
+            applescript_escape(&n.preview),
+            applescript_escape(&n.chat_title),
+            applescript_escape(&n.author)
+        );
+        let mut command = Command::new("osascript");
+        command.arg("-e").arg(script);
+        command
+    }
+}
+
+impl Notifier for MacosNotifier {
+    fn name(&self) -> &str {
+        "macos"
+    }
+
+    fn notify(&self, n: &Notification) -> Result<()> {
+        run_command(self.command(n), "osascript")
+    }
+}
+
+/// freedesktop notifications through `notify-send` (libnotify).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DesktopNotifier;
+
+impl DesktopNotifier {
+    /// The `notify-send` invocation for `n`, without running it.
+    pub fn command(&self, n: &Notification) -> Command {
+        let mut command = Command::new("notify-send");
+        command
+            .arg("--app-name=openkakao")
+            .arg(format!("{} — {}", n.chat_title, n.author))
+            .arg(&n.preview);
+        command
+    }
+}
+
+impl Notifier for DesktopNotifier {
+    fn name(&self) -> &str {
+        "desktop"
+    }
+
+    fn notify(&self, n: &Notification) -> Result<()> {
+        run_command(self.command(n), "notify-send")
+    }
+}
+
+/// Telegram Bot API `sendMessage` to one chat.
+#[derive(Debug, Clone)]
+pub struct TelegramNotifier {
+    bot_token: String,
+    chat_id: String,
+    api_base: String,
+}
+
+impl TelegramNotifier {
+    pub fn new(bot_token: impl Into<String>, chat_id: impl Into<String>) -> Self {
+        Self {
+            bot_token: bot_token.into(),
+            chat_id: chat_id.into(),
+            api_base: TELEGRAM_API.to_string(),
+        }
+    }
+
+    /// Send to another Bot API server (a local one, or a test double).
+    pub fn with_api_base(mut self, api_base: impl Into<String>) -> Self {
+        self.api_base = api_base.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// The JSON body posted for `n`.
+    pub fn payload(&self, n: &Notification) -> serde_json::Value {
+        serde_json::json!({
+            "chat_id": self.chat_id,
+            "text": format!("{} — {}\n{}", n.chat_title, n.author, n.preview),
+            "disable_web_page_preview": true,
+        })
+    }
+}
+
+impl Notifier for TelegramNotifier {
+    fn name(&self) -> &str {
+        "telegram"
+    }
+
+    fn notify(&self, n: &Notification) -> Result<()> {
+        let url = format!("{}/bot{}/sendMessage", self.api_base, self.bot_token);
+        let response = reqwest::blocking::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()?
+            .post(url)
+            .json(&self.payload(n))
+            .send()
+            // The URL carries the bot token; keep it out of the error.
+            .map_err(|e| anyhow!("telegram request failed: {}", e.without_url()))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().unwrap_or_default();
+            anyhow::bail!("telegram returned HTTP {}: {}", status.as_u16(), body);
+        }
+        Ok(())
+    }
+}
+
+fn run_command(mut command: Command, program: &str) -> Result<()> {
+    let status = command
+        .status()
+        .with_context(|| format!("failed to run {}", program))?;
+    if !status.success() {
+        anyhow::bail!("{} exited with {}", program, status);
+    }
+    Ok(())
+}
+
+fn applescript_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::sync::Arc;
+
+    fn sample() -> Notification {
+        Notification::new(42, 7, "Team \"A\"", "Alice", "deploy\nis   done")
+    }
+
+    fn args(command: &Command) -> Vec<String> {
+        command
+            .get_args()
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn preview_is_one_shortened_line() {
+        assert_eq!(sample().preview, "deploy is done");
+        let long = Notification::new(1, 1, "t", "a", &"가".repeat(200));
+        assert_eq!(long.preview.chars().count(), PREVIEW_CHARS + 1);
+        assert!(long.preview.ends_with('…'));
+    }
+
+    #[test]
+    fn macos_builds_escaped_osascript() {
+        let command = MacosNotifier.command(&sample());
+        assert_eq!(command.get_program(), "osascript");
+        assert_eq!(
+            args(&command),
+            [
+                "-e",
+                r#"display notification "deploy is done" with title "Team \"A\"" subtitle "Alice""#
+            ]
+        );
+    }
+
+    #[test]
+    fn desktop_builds_notify_send() {
+        let command = DesktopNotifier.command(&sample());
+        assert_eq!(command.get_program(), "notify-send");
+        assert_eq!(
+            args(&command),
+            [
+                "--app-name=openkakao",
+                "Team \"A\" — Alice",
+                "deploy is done"
+            ]
+        );
+    }
+
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn stdout_writes_one_line() {
+        let buf = Shared::default();
+        let notifier = StdoutNotifier::with_writer(Box::new(buf.clone()));
+        notifier.notify(&sample()).unwrap();
+        assert_eq!(
+            String::from_utf8(buf.0.lock().unwrap().clone()).unwrap(),
+            "[notify] Team \"A\" — Alice: deploy is done\n"
+        );
+    }
+
+    #[test]
+    fn telegram_posts_send_message() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            // Headers and the small JSON body arrive together or shortly after.
+            while !String::from_utf8_lossy(&request).contains("}") {
+                let n = stream.read(&mut buf).unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            let body = r#"{"ok":true}"#;
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+            String::from_utf8_lossy(&request).into_owned()
+        });
+
+        TelegramNotifier::new("123:abc", "-100")
+            .with_api_base(&base)
+            .notify(&sample())
+            .unwrap();
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /bot123:abc/sendMessage "));
+        assert!(request.contains(r#""chat_id":"-100""#));
+        assert!(request.contains(r#"Team \"A\" — Alice\ndeploy is done"#));
+    }
+
+    #[test]
+    fn telegram_error_status_is_reported_without_token() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = stream.read(&mut buf);
+            let body = r#"{"ok":false,"description":"Unauthorized"}"#;
+            let _ = write!(
+                stream,
+                "HTTP/1.1 401 Unauthorized\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+        });
+        let err = TelegramNotifier::new("secret-token", "1")
+            .with_api_base(&base)
+            .notify(&sample())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("HTTP 401"));
+        assert!(!err.contains("secret-token"));
+        assert!(NotifyBackend::parse("pager").is_err());
+    }
+}
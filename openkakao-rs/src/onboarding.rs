@@ -0,0 +1,255 @@
+//! The steps of `init` and what each answer changes. The command asks the
+//! questions; this module decides which step comes next and which config
+//! keys differ from what is already there, so a re-run over a finished
+//! setup writes nothing.
+
+use crate::config::OpenKakaoConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// Find credentials: saved file, environment, KakaoTalk cache, or pasted.
+    Credentials,
+    /// Ask Kakao whether the token is accepted.
+    Verify,
+    /// Offer to save the credentials to the keychain or credentials file.
+    Save,
+    /// Default output format.
+    OutputFormat,
+    /// Minimum seconds between unattended sends.
+    Throttle,
+    /// Write the changed config keys.
+    WriteConfig,
+    Summary,
+    Done,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Done,
+    Skipped,
+    Failed,
+}
+
+impl Step {
+    pub const FIRST: Step = Step::Credentials;
+
+    /// The step after this one ended with `outcome`. Without credentials
+    /// there is nothing to verify or save; a token Kakao refuses is not
+    /// offered for saving.
+    pub fn next(self, outcome: Outcome) -> Step {
+        match (self, outcome) {
+            (Step::Credentials, Outcome::Done) => Step::Verify,
+            (Step::Credentials, _) => Step::OutputFormat,
+            (Step::Verify, Outcome::Failed) => Step::OutputFormat,
+            (Step::Verify, _) => Step::Save,
+            (Step::Save, _) => Step::OutputFormat,
+            (Step::OutputFormat, _) => Step::Throttle,
+            (Step::Throttle, _) => Step::WriteConfig,
+            (Step::WriteConfig, _) => Step::Summary,
+            (Step::Summary, _) | (Step::Done, _) => Step::Done,
+        }
+    }
+
+    /// Heading shown before the step's question.
+    pub fn title(self) -> &'static str {
+        match self {
+            Step::Credentials => "Credentials",
+            Step::Verify => "Verify token",
+            Step::Save => "Save credentials",
+            Step::OutputFormat => "Output format",
+            Step::Throttle => "Send throttle",
+            Step::WriteConfig => "Write config",
+            Step::Summary => "Summary",
+            Step::Done => "Done",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+impl OutputFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OutputFormat::Text => "text",
+            OutputFormat::Json => "json",
+        }
+    }
+
+    /// The `[ui] output` value of `config`; anything but "json" is text.
+    pub fn of(config: &OpenKakaoConfig) -> Self {
+        match config.ui.output.as_deref() {
+            Some("json") => OutputFormat::Json,
+            _ => OutputFormat::Text,
+        }
+    }
+}
+
+/// `answer` to "Output format [current]": empty keeps `current`.
+pub fn parse_output_answer(answer: &str, current: OutputFormat) -> Result<OutputFormat, String> {
+    match answer.trim().to_ascii_lowercase().as_str() {
+        "" => Ok(current),
+        "text" | "t" => Ok(OutputFormat::Text),
+        "json" | "j" => Ok(OutputFormat::Json),
+        other => Err(format!("'{other}' is not text or json")),
+    }
+}
+
+/// `answer` to "Seconds between unattended sends [current]": empty keeps
+/// `current`.
+pub fn parse_interval_answer(answer: &str, current: u64) -> Result<u64, String> {
+    let answer = answer.trim().trim_end_matches('s');
+    if answer.is_empty() {
+        return Ok(current);
+    }
+    match answer.parse::<u64>() {
+        Ok(0) => Err("the interval must be at least 1 second".to_string()),
+        Ok(secs) => Ok(secs),
+        Err(_) => Err(format!("'{answer}' is not a number of seconds")),
+    }
+}
+
+/// What the wizard collected; `None` means the step was skipped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Answers {
+    pub output: Option<OutputFormat>,
+    pub send_interval_secs: Option<u64>,
+}
+
+/// One config key to set: `[section] key = value`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigEdit {
+    pub section: &'static str,
+    pub key: &'static str,
+    pub value: toml::Value,
+}
+
+impl Answers {
+    /// The keys whose answer differs from `current`. A text output format
+    /// that was never set stays unset, so the file only gains what the user
+    /// actually changed.
+    pub fn config_edits(&self, current: &OpenKakaoConfig) -> Vec<ConfigEdit> {
+        let mut edits = Vec::new();
+        if let Some(output) = self.output {
+            if output != OutputFormat::of(current) {
+                edits.push(ConfigEdit {
+                    section: "ui",
+                    key: "output",
+                    value: output.as_str().into(),
+                });
+            }
+        }
+        if let Some(secs) = self.send_interval_secs {
+            if Some(secs) != current.safety.min_unattended_send_interval_secs {
+                edits.push(ConfigEdit {
+                    section: "safety",
+                    key: "min_unattended_send_interval_secs",
+                    value: toml::Value::Integer(secs.min(i64::MAX as u64) as i64),
+                });
+            }
+        }
+        edits
+    }
+}
+
+/// The command to try first once the wizard is done.
+pub fn suggested_first_command(has_credentials: bool) -> &'static str {
+    if has_credentials {
+        "openkakao-rs chats"
+    } else {
+        "openkakao-rs doctor"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn walk(outcomes: &[Outcome]) -> Vec<Step> {
+        let mut step = Step::FIRST;
+        let mut seen = vec![step];
+        for outcome in outcomes {
+            step = step.next(*outcome);
+            seen.push(step);
+        }
+        seen
+    }
+
+    #[test]
+    fn every_step_runs_when_each_succeeds() {
+        let steps = walk(&[Outcome::Done; 7]);
+        assert_eq!(
+            steps,
+            [
+                Step::Credentials,
+                Step::Verify,
+                Step::Save,
+                Step::OutputFormat,
+                Step::Throttle,
+                Step::WriteConfig,
+                Step::Summary,
+                Step::Done,
+            ]
+        );
+    }
+
+    #[test]
+    fn skipping_credentials_goes_straight_to_preferences() {
+        assert_eq!(Step::Credentials.next(Outcome::Skipped), Step::OutputFormat);
+        assert_eq!(Step::Credentials.next(Outcome::Failed), Step::OutputFormat);
+        // An unverified token may still be saved; a refused one is not.
+        assert_eq!(Step::Verify.next(Outcome::Skipped), Step::Save);
+        assert_eq!(Step::Verify.next(Outcome::Failed), Step::OutputFormat);
+        // Skipping everything still reaches the end.
+        assert_eq!(walk(&[Outcome::Skipped; 6]).last(), Some(&Step::Done));
+        assert_eq!(Step::Done.next(Outcome::Done), Step::Done);
+    }
+
+    #[test]
+    fn empty_answers_keep_current_values() {
+        assert_eq!(
+            parse_output_answer("", OutputFormat::Json),
+            Ok(OutputFormat::Json)
+        );
+        assert_eq!(
+            parse_output_answer(" JSON ", OutputFormat::Text),
+            Ok(OutputFormat::Json)
+        );
+        assert!(parse_output_answer("yaml", OutputFormat::Text).is_err());
+        assert_eq!(parse_interval_answer("", 10), Ok(10));
+        assert_eq!(parse_interval_answer("30s", 10), Ok(30));
+        assert!(parse_interval_answer("0", 10).is_err());
+        assert!(parse_interval_answer("soon", 10).is_err());
+    }
+
+    #[test]
+    fn rerun_with_current_values_changes_nothing() {
+        let mut config = OpenKakaoConfig::default();
+        let keep = Answers {
+            output: Some(OutputFormat::Text),
+            send_interval_secs: Some(10),
+        };
+        assert!(keep.config_edits(&config).is_empty());
+        assert!(Answers::default().config_edits(&config).is_empty());
+
+        let change = Answers {
+            output: Some(OutputFormat::Json),
+            send_interval_secs: Some(30),
+        };
+        let edits = change.config_edits(&config);
+        assert_eq!(
+            edits.iter().map(|e| (e.section, e.key)).collect::<Vec<_>>(),
+            [
+                ("ui", "output"),
+                ("safety", "min_unattended_send_interval_secs")
+            ]
+        );
+
+        config.ui.output = Some("json".into());
+        config.safety.min_unattended_send_interval_secs = Some(30);
+        assert!(change.config_edits(&config).is_empty());
+    }
+}
@@ -0,0 +1,484 @@
+//! Messages queued by `send --queue` and `send-file --queue` until
+//! `outbox flush` delivers them.
+//!
+//! `outbox.jsonl` starts with an `{"outbox_version":1,...}` header; each line
+//! after it is one event (`queued`, `failed`, `sent`) appended with a
+//! single write and synced, so a crash loses at most the line being
+//! written. A torn last line is ignored on load, and the next write
+//! compacts the log into one `queued` line per pending item through a temp
+//! file and rename.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Format written by this build. Older files load; newer ones are refused.
+pub const OUTBOX_VERSION: u32 = 1;
+
+/// Age after which a queued item needs `outbox flush --force`.
+pub const DEFAULT_TTL_SECS: u64 = 24 * 60 * 60;
+
+const OUTBOX_FILE: &str = "outbox.jsonl";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    outbox_version: u32,
+    /// Keeps ids unique after compaction drops the newest items.
+    #[serde(default)]
+    next_id: u64,
+}
+
+/// One queued message: text, a file to upload, or both (text first).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutboxItem {
+    pub id: u64,
+    pub chat_id: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    /// Send text over the length limit as several messages.
+    #[serde(default)]
+    pub split: bool,
+    /// Allow an open chat, as `send --force`.
+    #[serde(default)]
+    pub force: bool,
+    /// Unix seconds.
+    pub queued_at: i64,
+    #[serde(default)]
+    pub attempts: u32,
+    /// Text parts already delivered by an attempt that failed later on, so
+    /// a retry resumes instead of repeating them.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub parts_sent: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_attempt_at: Option<i64>,
+}
+
+impl OutboxItem {
+    /// Older than `ttl_secs` at `now`.
+    pub fn is_stale(&self, now: i64, ttl_secs: u64) -> bool {
+        now.saturating_sub(self.queued_at) > ttl_secs as i64
+    }
+}
+
+/// What a new item carries; the outbox assigns the id and time.
+#[derive(Debug, Clone, Default)]
+pub struct NewItem {
+    pub chat_id: i64,
+    pub text: Option<String>,
+    pub file: Option<PathBuf>,
+    pub split: bool,
+    pub force: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event {
+    Queued {
+        item: OutboxItem,
+    },
+    Failed {
+        id: u64,
+        error: String,
+        at: i64,
+        #[serde(default)]
+        parts_sent: usize,
+    },
+    Sent {
+        id: u64,
+    },
+}
+
+pub struct Outbox {
+    path: PathBuf,
+    items: Vec<OutboxItem>,
+    next_id: u64,
+    /// Events on disk beyond one per pending item, or a torn last line.
+    needs_compact: bool,
+}
+
+impl Outbox {
+    pub fn open() -> Result<Self> {
+        Self::open_at(&outbox_path()?)
+    }
+
+    pub fn open_at(path: &Path) -> Result<Self> {
+        let mut outbox = Self {
+            path: path.to_path_buf(),
+            items: Vec::new(),
+            next_id: 1,
+            needs_compact: false,
+        };
+        let data = match fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(outbox),
+            Err(err) => {
+                return Err(err).with_context(|| format!("Failed to read {}", path.display()))
+            }
+        };
+        let complete = data.ends_with('\n');
+        let lines: Vec<&str> = data.lines().collect();
+        let Some((header, events)) = lines.split_first() else {
+            return Ok(outbox);
+        };
+        let header: Header = serde_json::from_str(header)
+            .with_context(|| format!("{} does not start with an outbox header", path.display()))?;
+        if header.outbox_version > OUTBOX_VERSION {
+            anyhow::bail!(
+                "{} is outbox version {}, newer than this openkakao-rs supports ({}). Upgrade to flush it.",
+                path.display(),
+                header.outbox_version,
+                OUTBOX_VERSION
+            );
+        }
+        outbox.next_id = header.next_id.max(1);
+        for (idx, line) in events.iter().enumerate() {
+            let last = idx + 1 == events.len();
+            match serde_json::from_str::<Event>(line) {
+                Ok(event) => outbox.apply(event),
+                // Interrupted mid-append: the event never happened.
+                Err(_) if last && !complete => outbox.needs_compact = true,
+                Err(err) => {
+                    return Err(err)
+                        .with_context(|| format!("{} line {} is damaged", path.display(), idx + 2))
+                }
+            }
+        }
+        if events.len() > outbox.items.len() {
+            outbox.needs_compact = true;
+        }
+        Ok(outbox)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Pending items, oldest first.
+    pub fn items(&self) -> &[OutboxItem] {
+        &self.items
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Queue `new` at `now` (unix seconds).
+    pub fn push(&mut self, new: NewItem, now: i64) -> Result<OutboxItem> {
+        let item = OutboxItem {
+            id: self.next_id,
+            chat_id: new.chat_id,
+            text: new.text,
+            file: new.file,
+            split: new.split,
+            force: new.force,
+            queued_at: now,
+            attempts: 0,
+            parts_sent: 0,
+            last_error: None,
+            last_attempt_at: None,
+        };
+        let event = || Event::Queued { item: item.clone() };
+        self.append(event())?;
+        self.apply(event());
+        Ok(item)
+    }
+
+    /// Keep item `id` with the error of a failed delivery attempt, after
+    /// `parts_sent` of its text parts went through in total.
+    pub fn record_failure(
+        &mut self,
+        id: u64,
+        error: &str,
+        parts_sent: usize,
+        now: i64,
+    ) -> Result<()> {
+        let event = || Event::Failed {
+            id,
+            error: error.to_string(),
+            at: now,
+            parts_sent,
+        };
+        self.append(event())?;
+        self.apply(event());
+        Ok(())
+    }
+
+    /// Drop item `id`; only call once the server confirmed it.
+    pub fn record_sent(&mut self, id: u64) -> Result<()> {
+        self.append(Event::Sent { id })?;
+        self.apply(Event::Sent { id });
+        Ok(())
+    }
+
+    /// Rewrite the file as one `queued` line per pending item, or remove it
+    /// when nothing is pending.
+    pub fn compact(&mut self) -> Result<()> {
+        if self.items.is_empty() {
+            match fs::remove_file(&self.path) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                    return Err(err)
+                        .with_context(|| format!("Failed to remove {}", self.path.display()))
+                }
+                _ => {}
+            }
+        } else {
+            if let Some(parent) = self.path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let items = &self.items;
+            let next_id = self.next_id;
+            crate::output::write_atomic(&self.path, |out| {
+                writeln!(out, "{}", header_line(next_id)?)?;
+                for item in items {
+                    let event = Event::Queued { item: item.clone() };
+                    writeln!(out, "{}", serde_json::to_string(&event)?)?;
+                }
+                Ok(())
+            })?;
+        }
+        self.needs_compact = false;
+        Ok(())
+    }
+
+    fn apply(&mut self, event: Event) {
+        match event {
+            Event::Queued { item } => {
+                self.next_id = self.next_id.max(item.id + 1);
+                self.items.retain(|existing| existing.id != item.id);
+                self.items.push(item);
+            }
+            Event::Failed {
+                id,
+                error,
+                at,
+                parts_sent,
+            } => {
+                if let Some(item) = self.items.iter_mut().find(|item| item.id == id) {
+                    item.attempts += 1;
+                    item.parts_sent = parts_sent;
+                    item.last_error = Some(error);
+                    item.last_attempt_at = Some(at);
+                }
+            }
+            Event::Sent { id } => self.items.retain(|item| item.id != id),
+        }
+    }
+
+    fn append(&mut self, event: Event) -> Result<()> {
+        if self.needs_compact || !self.path.exists() {
+            self.compact()?;
+            if !self.path.exists() {
+                write_header(&self.path, self.next_id)?;
+            }
+        }
+        let mut line = serde_json::to_string(&event)?;
+        line.push('\n');
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        file.write_all(line.as_bytes())
+            .and_then(|_| file.sync_data())
+            .with_context(|| format!("Failed to write {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+fn header_line(next_id: u64) -> Result<String> {
+    Ok(serde_json::to_string(&Header {
+        outbox_version: OUTBOX_VERSION,
+        next_id,
+    })?)
+}
+
+fn write_header(path: &Path, next_id: u64) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    crate::output::write_atomic(path, |out| {
+        writeln!(out, "{}", header_line(next_id)?)?;
+        Ok(())
+    })
+}
+
+pub fn outbox_path() -> Result<PathBuf> {
+    Ok(crate::paths::profile_dir()?.join(OUTBOX_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(chat_id: i64, text: &str) -> NewItem {
+        NewItem {
+            chat_id,
+            text: Some(text.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn queued_items_survive_reopen_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("outbox.jsonl");
+        let mut outbox = Outbox::open_at(&path).unwrap();
+        assert!(outbox.is_empty());
+        outbox.push(text(1, "first"), 100).unwrap();
+        outbox
+            .push(
+                NewItem {
+                    chat_id: 2,
+                    file: Some(PathBuf::from("/tmp/photo.jpg")),
+                    ..Default::default()
+                },
+                200,
+            )
+            .unwrap();
+
+        let reopened = Outbox::open_at(&path).unwrap();
+        let ids: Vec<u64> = reopened.items().iter().map(|item| item.id).collect();
+        assert_eq!(ids, [1, 2]);
+        assert_eq!(reopened.items()[0].text.as_deref(), Some("first"));
+        assert_eq!(
+            reopened.items()[1].file.as_deref(),
+            Some(Path::new("/tmp/photo.jpg"))
+        );
+        let data = fs::read_to_string(&path).unwrap();
+        assert!(data.starts_with("{\"outbox_version\":1,"), "{data}");
+    }
+
+    #[test]
+    fn sent_items_go_and_failures_stay_with_their_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("outbox.jsonl");
+        let mut outbox = Outbox::open_at(&path).unwrap();
+        outbox.push(text(1, "a"), 100).unwrap();
+        outbox.push(text(1, "b"), 100).unwrap();
+        outbox.record_sent(1).unwrap();
+        outbox
+            .record_failure(2, "connection reset", 0, 150)
+            .unwrap();
+        outbox.record_failure(2, "timed out", 1, 160).unwrap();
+
+        let reopened = Outbox::open_at(&path).unwrap();
+        assert_eq!(reopened.items().len(), 1);
+        let item = &reopened.items()[0];
+        assert_eq!(item.id, 2);
+        assert_eq!(item.attempts, 2);
+        assert_eq!(item.last_error.as_deref(), Some("timed out"));
+        assert_eq!(item.last_attempt_at, Some(160));
+        assert_eq!(item.parts_sent, 1);
+    }
+
+    #[test]
+    fn ids_are_not_reused_after_a_send() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("outbox.jsonl");
+        let mut outbox = Outbox::open_at(&path).unwrap();
+        outbox.push(text(1, "a"), 100).unwrap();
+        outbox.push(text(1, "b"), 100).unwrap();
+        outbox.record_sent(2).unwrap();
+        outbox.compact().unwrap();
+
+        let mut reopened = Outbox::open_at(&path).unwrap();
+        assert_eq!(reopened.push(text(1, "c"), 100).unwrap().id, 3);
+    }
+
+    #[test]
+    fn torn_last_line_is_dropped_and_compacted_before_the_next_append() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("outbox.jsonl");
+        let mut outbox = Outbox::open_at(&path).unwrap();
+        outbox.push(text(1, "kept"), 100).unwrap();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"event\":\"queued\",\"item\":{\"id\":2,\"ch")
+            .unwrap();
+
+        let mut reopened = Outbox::open_at(&path).unwrap();
+        assert_eq!(reopened.items().len(), 1);
+        reopened.push(text(1, "next"), 110).unwrap();
+
+        let data = fs::read_to_string(&path).unwrap();
+        assert_eq!(data.lines().count(), 3);
+        let texts: Vec<_> = Outbox::open_at(&path)
+            .unwrap()
+            .items()
+            .iter()
+            .map(|item| item.text.clone().unwrap())
+            .collect();
+        assert_eq!(texts, ["kept", "next"]);
+    }
+
+    #[test]
+    fn damaged_middle_line_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("outbox.jsonl");
+        fs::write(
+            &path,
+            "{\"outbox_version\":1}\nnot json\n{\"event\":\"sent\",\"id\":1}\n",
+        )
+        .unwrap();
+        let err = Outbox::open_at(&path).err().unwrap();
+        assert!(format!("{err:#}").contains("line 2 is damaged"), "{err:#}");
+    }
+
+    #[test]
+    fn newer_versions_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("outbox.jsonl");
+        fs::write(&path, "{\"outbox_version\":2}\n").unwrap();
+        let err = Outbox::open_at(&path).err().unwrap();
+        assert!(err.to_string().contains("newer"), "{err}");
+    }
+
+    #[test]
+    fn compact_keeps_one_line_per_item_and_removes_an_empty_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("outbox.jsonl");
+        let mut outbox = Outbox::open_at(&path).unwrap();
+        outbox.push(text(1, "a"), 100).unwrap();
+        outbox.push(text(1, "b"), 100).unwrap();
+        outbox.record_failure(1, "offline", 0, 120).unwrap();
+        outbox.record_sent(2).unwrap();
+        outbox.compact().unwrap();
+
+        let data = fs::read_to_string(&path).unwrap();
+        assert_eq!(data.lines().count(), 2);
+        let reopened = Outbox::open_at(&path).unwrap();
+        assert_eq!(reopened.items()[0].last_error.as_deref(), Some("offline"));
+
+        outbox.record_sent(1).unwrap();
+        outbox.compact().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn staleness_uses_the_ttl() {
+        let item = OutboxItem {
+            id: 1,
+            chat_id: 1,
+            text: None,
+            file: None,
+            split: false,
+            force: false,
+            queued_at: 1_000,
+            attempts: 0,
+            parts_sent: 0,
+            last_error: None,
+            last_attempt_at: None,
+        };
+        assert!(!item.is_stale(1_000 + 60, 60));
+        assert!(item.is_stale(1_000 + 61, 60));
+    }
+}
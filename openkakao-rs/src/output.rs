@@ -0,0 +1,336 @@
+use std::io::Write;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use anyhow::{Context, Result};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json::Value;
+use tempfile::NamedTempFile;
+
+static JSON_FIELDS: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Set once from `--fields`.
+pub fn set_json_fields(fields: Vec<String>) {
+    let _ = JSON_FIELDS.set(fields);
+}
+
+/// `--fields` selection; empty means every field.
+pub fn json_fields() -> &'static [String] {
+    JSON_FIELDS.get().map(Vec::as_slice).unwrap_or_default()
+}
+
+/// `--fields a,b.c` as a list: trimmed, empty entries dropped.
+pub fn parse_fields(spec: &str) -> Vec<String> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|f| !f.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// One projected object: the requested fields in the requested order, keyed
+/// by the path as given (`author.name` stays one flat key).
+#[derive(Debug, PartialEq)]
+pub struct Projected(Vec<(String, Value)>);
+
+impl Serialize for Projected {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (key, value) in &self.0 {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+/// `--fields` output: one object, or one per element of a listing.
+#[derive(Debug, PartialEq, serde::Serialize)]
+#[serde(untagged)]
+pub enum Projection {
+    One(Projected),
+    Many(Vec<Projected>),
+}
+
+/// Keep only `fields` of a command's JSON output: of the object itself, or
+/// of each element when it is an array. Dot paths reach into nested objects
+/// (and arrays, by index); an element without the field gets `null`. A field
+/// that no element has is an error listing the available top-level fields.
+pub fn project(value: &Value, fields: &[String]) -> Result<Projection> {
+    let items: Vec<&Value> = match value {
+        Value::Array(items) => items.iter().collect(),
+        other => vec![other],
+    };
+    if items.iter().any(|item| !item.is_object()) {
+        anyhow::bail!("--fields only applies to JSON objects or lists of objects");
+    }
+    if !items.is_empty() {
+        for field in fields {
+            if !items.iter().any(|item| lookup(item, field).is_some()) {
+                let mut valid: Vec<&str> = items
+                    .iter()
+                    .filter_map(|item| item.as_object())
+                    .flat_map(|object| object.keys().map(String::as_str))
+                    .collect();
+                valid.sort_unstable();
+                valid.dedup();
+                anyhow::bail!(
+                    "unknown field '{}'. Available fields: {} (use dots for nested ones)",
+                    field,
+                    valid.join(", ")
+                );
+            }
+        }
+    }
+    let pick = |item: &Value| {
+        Projected(
+            fields
+                .iter()
+                .map(|field| {
+                    (
+                        field.clone(),
+                        lookup(item, field).cloned().unwrap_or(Value::Null),
+                    )
+                })
+                .collect(),
+        )
+    };
+    Ok(match value {
+        Value::Array(_) => Projection::Many(items.into_iter().map(pick).collect()),
+        _ => Projection::One(pick(value)),
+    })
+}
+
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(value, |current, segment| match current {
+            Value::Object(object) => object.get(segment),
+            Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        })
+}
+
+/// Temp file next to `path`, so the final rename stays on one filesystem.
+fn temp_beside(path: &Path) -> Result<NamedTempFile> {
+    let dir = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    NamedTempFile::new_in(dir)
+        .with_context(|| format!("Failed to create a temp file in {}", dir.display()))
+}
+
+/// Write `path` atomically: `fill` writes into a temp file that replaces
+/// `path` only once it succeeds, so a failure never leaves a truncated file
+/// in place of a previous good one.
+pub fn write_atomic<F>(path: &Path, fill: F) -> Result<()>
+where
+    F: FnOnce(&mut dyn Write) -> Result<()>,
+{
+    let mut temp = temp_beside(path)?;
+    fill(temp.as_file_mut())?;
+    temp.as_file_mut().flush()?;
+    temp.persist(path)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+pub use redirect::StdoutRedirect;
+
+#[cfg(unix)]
+mod redirect {
+    use std::io::Write;
+    use std::os::fd::{AsRawFd, RawFd};
+    use std::path::{Path, PathBuf};
+
+    use anyhow::{Context, Result};
+    use tempfile::NamedTempFile;
+
+    /// Points the process's stdout at a temp file for `--out <path>`.
+    /// Diagnostics on stderr stay on the terminal. [`commit`](Self::commit)
+    /// moves the file into place; dropping without committing discards it.
+    pub struct StdoutRedirect {
+        temp: Option<NamedTempFile>,
+        saved_stdout: RawFd,
+        target: PathBuf,
+    }
+
+    impl StdoutRedirect {
+        /// `None` for `-`, which means plain stdout.
+        pub fn open(target: &Path) -> Result<Option<Self>> {
+            if target == Path::new("-") {
+                return Ok(None);
+            }
+            let temp = super::temp_beside(target)?;
+            std::io::stdout().flush()?;
+            // SAFETY: plain descriptor calls on fds we own; the saved copy is
+            // restored and closed in `restore`.
+            let saved_stdout = unsafe { libc::dup(libc::STDOUT_FILENO) };
+            if saved_stdout < 0 {
+                return Err(std::io::Error::last_os_error()).context("Failed to save stdout");
+            }
+            if unsafe { libc::dup2(temp.as_file().as_raw_fd(), libc::STDOUT_FILENO) } < 0 {
+                let err = std::io::Error::last_os_error();
+                unsafe { libc::close(saved_stdout) };
+                return Err(err).context("Failed to redirect stdout");
+            }
+            Ok(Some(Self {
+                temp: Some(temp),
+                saved_stdout,
+                target: target.to_path_buf(),
+            }))
+        }
+
+        /// Restore stdout and move the captured output to the target path.
+        pub fn commit(mut self) -> Result<()> {
+            self.restore();
+            let temp = self.temp.take().expect("redirect already finished");
+            temp.persist(&self.target)
+                .with_context(|| format!("Failed to write {}", self.target.display()))?;
+            Ok(())
+        }
+
+        fn restore(&mut self) {
+            if self.saved_stdout < 0 {
+                return;
+            }
+            let _ = std::io::stdout().flush();
+            unsafe {
+                libc::dup2(self.saved_stdout, libc::STDOUT_FILENO);
+                libc::close(self.saved_stdout);
+            }
+            self.saved_stdout = -1;
+        }
+    }
+
+    impl Drop for StdoutRedirect {
+        fn drop(&mut self) {
+            self.restore();
+        }
+    }
+}
+
+/// Without descriptors to swap, `--out` runs the command again as a child
+/// whose stdout is piped into [`write_atomic`], and exits with its status.
+/// The child sees [`CHILD_ENV`] and writes to its stdout as usual.
+#[cfg(not(unix))]
+mod redirect {
+    use std::path::Path;
+    use std::process::{Command, Stdio};
+
+    use anyhow::{Context, Result};
+
+    const CHILD_ENV: &str = "OPENKAKAO_RS_OUT_CHILD";
+
+    /// `--out <path>` on targets without `dup2`.
+    pub struct StdoutRedirect(());
+
+    impl StdoutRedirect {
+        /// `None` for `-`, which means plain stdout, and in the child.
+        pub fn open(target: &Path) -> Result<Option<Self>> {
+            if target == Path::new("-") || std::env::var_os(CHILD_ENV).is_some() {
+                return Ok(None);
+            }
+            let exe = std::env::current_exe().context("Failed to locate the running binary")?;
+            let mut child = Command::new(exe)
+                .args(std::env::args_os().skip(1))
+                .env(CHILD_ENV, "1")
+                .stdout(Stdio::piped())
+                .spawn()
+                .context("Failed to run the command for --out")?;
+            let mut stdout = child.stdout.take().expect("stdout is piped");
+            let mut status = None;
+            let written = super::write_atomic(target, |w| {
+                std::io::copy(&mut stdout, w)?;
+                let exit = child.wait()?;
+                status = Some(exit);
+                if !exit.success() {
+                    // The child reported its error on stderr already.
+                    anyhow::bail!("command failed");
+                }
+                Ok(())
+            });
+            match status {
+                Some(exit) if exit.success() => written?,
+                Some(_) => {}
+                None => return written.map(|()| None),
+            }
+            std::process::exit(status.and_then(|s| s.code()).unwrap_or(1));
+        }
+
+        /// Nothing left to do: [`open`](Self::open) never returns a redirect.
+        pub fn commit(self) -> Result<()> {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_write_keeps_previous_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.txt");
+        std::fs::write(&path, "good").unwrap();
+
+        let result = write_atomic(&path, |w| {
+            w.write_all(b"partial")?;
+            anyhow::bail!("interrupted")
+        });
+        assert!(result.is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "good");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        write_atomic(&path, |w| Ok(w.write_all(b"new")?)).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+    }
+
+    fn fields(spec: &str) -> Vec<String> {
+        parse_fields(spec)
+    }
+
+    #[test]
+    fn projects_each_listing_element_in_requested_order() {
+        let chats = serde_json::json!([
+            {"chat_id": 1, "title": "Team", "unread": 3, "last": {"author": "Kim", "text": "hi"}},
+            {"chat_id": 2, "title": "가족", "unread": 0},
+        ]);
+        let out = project(&chats, &fields("title, chat_id,last.author")).unwrap();
+        assert_eq!(
+            serde_json::to_string(&out).unwrap(),
+            r#"[{"title":"Team","chat_id":1,"last.author":"Kim"},{"title":"가족","chat_id":2,"last.author":null}]"#
+        );
+    }
+
+    #[test]
+    fn projects_a_single_object_and_indexes_arrays() {
+        let status = serde_json::json!({"status": "ok", "members": [{"name": "Kim"}]});
+        let out = project(&status, &fields("members.0.name,status")).unwrap();
+        assert_eq!(
+            serde_json::to_string(&out).unwrap(),
+            r#"{"members.0.name":"Kim","status":"ok"}"#
+        );
+    }
+
+    #[test]
+    fn unknown_fields_list_the_valid_ones() {
+        let chats = serde_json::json!([{"chat_id": 1, "title": "Team"}, {"kind": "DirectChat"}]);
+        let err = project(&chats, &fields("chat_id,titel"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("unknown field 'titel'"), "{err}");
+        assert!(err.contains("chat_id, kind, title"), "{err}");
+        assert!(project(&serde_json::json!([1, 2]), &fields("a")).is_err());
+        // Nothing to check against in an empty listing.
+        assert_eq!(
+            project(&serde_json::json!([]), &fields("a")).unwrap(),
+            Projection::Many(Vec::new())
+        );
+    }
+
+    #[test]
+    fn dash_means_stdout() {
+        assert!(StdoutRedirect::open(Path::new("-")).unwrap().is_none());
+    }
+}
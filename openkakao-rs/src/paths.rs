@@ -0,0 +1,244 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+/// Name of the per-project state directory, found like git finds `.git`.
+pub const WORKSPACE_DIR: &str = ".openkakao";
+
+/// Written into a new workspace directory so tokens are not committed by
+/// accident when `--local-credentials` is used.
+const WORKSPACE_GITIGNORE: &str = "credentials.json\ncredentials/\n";
+
+/// Where config, caches and state files live for this run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Scope {
+    /// `~/.config/openkakao`
+    Global,
+    /// A `.openkakao/` directory in or above the working directory.
+    Workspace { dir: PathBuf },
+}
+
+impl Scope {
+    /// One-line description for `doctor` and `config path`.
+    pub fn describe(&self) -> String {
+        match self {
+            Scope::Global => "global".to_string(),
+            Scope::Workspace { dir } => format!("workspace ({})", dir.display()),
+        }
+    }
+}
+
+static SCOPE: OnceLock<Scope> = OnceLock::new();
+static LOCAL_CREDENTIALS: AtomicBool = AtomicBool::new(false);
+
+/// Nearest `.openkakao/` directory at or above `start`.
+pub fn find_workspace(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(WORKSPACE_DIR))
+        .find(|candidate| candidate.is_dir())
+}
+
+/// Pick the scope once at startup. A workspace found from the current
+/// directory is used automatically; `force_local` creates `./.openkakao`
+/// when none exists. Credentials stay global unless `local_credentials`.
+pub fn init_scope(force_local: bool, local_credentials: bool) -> Result<Scope> {
+    let cwd = std::env::current_dir().context("Could not resolve the working directory")?;
+    let scope = match find_workspace(&cwd) {
+        Some(dir) => Scope::Workspace { dir },
+        None if force_local => {
+            let dir = cwd.join(WORKSPACE_DIR);
+            std::fs::create_dir_all(&dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+            let gitignore = dir.join(".gitignore");
+            if !gitignore.exists() {
+                std::fs::write(&gitignore, WORKSPACE_GITIGNORE)
+                    .with_context(|| format!("Failed to write {}", gitignore.display()))?;
+            }
+            Scope::Workspace { dir }
+        }
+        None => Scope::Global,
+    };
+    LOCAL_CREDENTIALS.store(
+        local_credentials && scope != Scope::Global,
+        Ordering::Relaxed,
+    );
+    let _ = SCOPE.set(scope.clone());
+    Ok(scope)
+}
+
+/// The active scope; global until [`init_scope`] says otherwise.
+pub fn scope() -> Scope {
+    SCOPE.get().cloned().unwrap_or(Scope::Global)
+}
+
+/// `~/.config/openkakao`, regardless of scope.
+pub fn global_dir() -> Result<PathBuf> {
+    let home = dirs::home_dir().context("Could not resolve home directory")?;
+    Ok(home.join(".config").join("openkakao"))
+}
+
+/// Directory for config, message cache, media cache and state files.
+pub fn state_dir() -> Result<PathBuf> {
+    match scope() {
+        Scope::Global => global_dir(),
+        Scope::Workspace { dir } => Ok(dir),
+    }
+}
+
+/// Directory for credential files: global unless `--local-credentials`.
+pub fn credentials_dir() -> Result<PathBuf> {
+    if LOCAL_CREDENTIALS.load(Ordering::Relaxed) {
+        state_dir()
+    } else {
+        global_dir()
+    }
+}
+
+/// Whether credentials follow the workspace for this run.
+pub fn credentials_are_local() -> bool {
+    LOCAL_CREDENTIALS.load(Ordering::Relaxed)
+}
+
+/// Profile of a run without `--profile`.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Directory under [`state_dir`] holding one state tree per profile.
+pub const PROFILES_DIR: &str = "profiles";
+
+/// What each profile keeps apart from the others, by name in its
+/// [`profile_dir`]: the archive, the media and response caches, the state
+/// and watch files, the outbox, the ignore list, settings snapshots and
+/// daemon status. `config.toml`, the link preview cache, logs and crash
+/// reports stay shared in [`state_dir`].
+pub const PROFILE_STATE: [&str; 11] = [
+    "messages.db",
+    "messages.db-wal",
+    "messages.db-shm",
+    "media-cache",
+    "response-cache",
+    "state.json",
+    "watch_state.json",
+    "outbox.jsonl",
+    "ignore.json",
+    "settings-history.json",
+    "daemon-status.json",
+];
+
+static PROFILE: OnceLock<String> = OnceLock::new();
+
+/// Keep this run's state in profile `name`'s tree. Set once at startup
+/// with the credentials profile.
+pub fn set_profile(name: &str) {
+    let _ = PROFILE.set(name.to_string());
+}
+
+/// The profile set with [`set_profile`], [`DEFAULT_PROFILE`] before that.
+pub fn profile() -> &'static str {
+    PROFILE.get().map_or(DEFAULT_PROFILE, String::as_str)
+}
+
+/// `<state dir>/profiles/<name>`: every [`PROFILE_STATE`] entry of one
+/// profile, and nothing of any other.
+pub fn profile_dir_of(name: &str) -> Result<PathBuf> {
+    Ok(state_dir()?.join(PROFILES_DIR).join(name))
+}
+
+/// [`profile_dir_of`] the active [`profile`].
+pub fn profile_dir() -> Result<PathBuf> {
+    profile_dir_of(profile())
+}
+
+/// Move state written before profiles had their own trees from the state
+/// directory into the default profile's. Entries the default profile
+/// already has are left where they are. Returns the moved entries' new
+/// paths.
+pub fn migrate_state_to_default_profile() -> Result<Vec<PathBuf>> {
+    migrate_state_in(&state_dir()?, &profile_dir_of(DEFAULT_PROFILE)?)
+}
+
+fn migrate_state_in(root: &Path, target: &Path) -> Result<Vec<PathBuf>> {
+    let mut moved = Vec::new();
+    for name in PROFILE_STATE {
+        let from = root.join(name);
+        let to = target.join(name);
+        if !from.exists() || to.exists() {
+            continue;
+        }
+        std::fs::create_dir_all(target)
+            .with_context(|| format!("Failed to create {}", target.display()))?;
+        std::fs::rename(&from, &to)
+            .with_context(|| format!("Failed to move {} to {}", from.display(), to.display()))?;
+        moved.push(to);
+    }
+    Ok(moved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_workspace_in_parent_directories() {
+        let root = tempfile::tempdir().unwrap();
+        let nested = root.path().join("a").join("b");
+        std::fs::create_dir_all(&nested).unwrap();
+        assert_eq!(find_workspace(&nested), None);
+
+        std::fs::create_dir(root.path().join(WORKSPACE_DIR)).unwrap();
+        assert_eq!(
+            find_workspace(&nested),
+            Some(root.path().join(WORKSPACE_DIR))
+        );
+    }
+
+    #[test]
+    fn a_plain_file_is_not_a_workspace() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(root.path().join(WORKSPACE_DIR), "").unwrap();
+        assert_eq!(find_workspace(root.path()), None);
+    }
+
+    #[test]
+    fn default_scope_is_global() {
+        assert_eq!(scope(), Scope::Global);
+        assert_eq!(state_dir().unwrap(), global_dir().unwrap());
+        assert_eq!(credentials_dir().unwrap(), global_dir().unwrap());
+        assert_eq!(
+            profile_dir().unwrap(),
+            global_dir().unwrap().join("profiles").join("default")
+        );
+    }
+
+    #[test]
+    fn pre_profile_state_moves_to_the_default_profile_once() {
+        let root = tempfile::tempdir().unwrap();
+        let target = root.path().join(PROFILES_DIR).join(DEFAULT_PROFILE);
+        assert!(migrate_state_in(root.path(), &target).unwrap().is_empty());
+
+        std::fs::write(root.path().join("state.json"), "old").unwrap();
+        std::fs::create_dir(root.path().join("media-cache")).unwrap();
+        std::fs::write(root.path().join("config.toml"), "").unwrap();
+        let moved = migrate_state_in(root.path(), &target).unwrap();
+        assert_eq!(
+            moved,
+            [target.join("media-cache"), target.join("state.json")]
+        );
+        assert!(target.join("media-cache").is_dir());
+        // Shared files stay put.
+        assert!(root.path().join("config.toml").exists());
+
+        // A newer leftover never replaces the profile's own file.
+        std::fs::write(root.path().join("state.json"), "newer").unwrap();
+        assert!(migrate_state_in(root.path(), &target).unwrap().is_empty());
+        assert_eq!(
+            std::fs::read_to_string(target.join("state.json")).unwrap(),
+            "old"
+        );
+    }
+}
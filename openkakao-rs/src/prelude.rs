@@ -0,0 +1,94 @@
+//! The types most programs need, for a single glob import:
+//!
+//! ```no_run
+//! use openkakao_rs::prelude::*;
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! // Credentials saved by `openkakao-rs login --save`.
+//! let path = openkakao_rs::paths::credentials_dir()?.join("credentials.json");
+//! let creds: KakaoCredentials = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+//!
+//! let rt = tokio::runtime::Runtime::new()?;
+//! rt.block_on(async {
+//!     let mut client = LocoClient::new(creds).with_retry_policy(RetryPolicy::default());
+//!     client.full_connect_with_retry().await?;
+//!
+//!     // List chats...
+//!     let chats = client
+//!         .send_command(
+//!             "LCHATLIST",
+//!             bson::doc! {
+//!                 "chatIds": bson::Bson::Array(vec![]),
+//!                 "maxIds": bson::Bson::Array(vec![]),
+//!                 "lastTokenId": 0_i64,
+//!                 "lastChatId": 0_i64,
+//!             },
+//!         )
+//!         .await?;
+//!     if chats.status() != 0 {
+//!         return Err(OpenKakaoError::loco("LCHATLIST", chats.status()).into());
+//!     }
+//!     let first = chats
+//!         .body
+//!         .get_array("chatDatas")?
+//!         .iter()
+//!         .filter_map(|c| c.as_document())
+//!         .find_map(|c| Some((c.get_i64("c").ok()?, c.get_i64("s").ok()?)));
+//!
+//!     // ...and read the most recent messages of the first one.
+//!     if let Some((chat_id, last_log_id)) = first {
+//!         let page = client
+//!             .send_command(
+//!                 "SYNCMSG",
+//!                 bson::doc! { "chatId": chat_id, "cur": 0_i64, "cnt": 50_i32, "max": last_log_id },
+//!             )
+//!             .await?;
+//!         for log in page.body.get_array("chatLogs").into_iter().flatten() {
+//!             if let Some(log) = log.as_document() {
+//!                 println!("{}: {}", log.get_i64("authorId")?, log.get_str("message").unwrap_or(""));
+//!             }
+//!         }
+//!     }
+//!     client.disconnect_graceful().await;
+//!     Ok(())
+//! })
+//! # }
+//! ```
+//!
+//! [`KakaoRestClient`] is the simpler choice for reading: one blocking HTTP
+//! request per call, no session to keep alive.
+//!
+//! Stability notes for this surface:
+//! - Everything re-exported here follows semver; reach into the modules
+//!   directly for anything else.
+//! - Response models, status and error enums are `#[non_exhaustive]`: match
+//!   with a wildcard arm and read fields rather than building the structs.
+//! - `KakaoCredentials` is built with [`KakaoCredentials::new`] or
+//!   deserialized; optional fields are then set directly.
+//! - Parsing helpers shared between modules are `pub(crate)` or
+//!   `#[doc(hidden)]`.
+//! - REST methods return [`OpenKakaoError`]: `RestApi` for a non-zero Kakao
+//!   status (an expired token among them), `Http` and `Network` for
+//!   transport failures, `Parse` for an answer that is not the JSON expected.
+//! - `LocalDbReader`, `auth` and friends need the `extract` feature, `MessageDb` the
+//!   `archive` feature, and `paths` the `store` feature.
+
+pub use crate::credential_provider::{CredentialProvider, ProviderChain};
+pub use crate::cursor::{ChatListCursor, MessageCursor};
+pub use crate::error::OpenKakaoError;
+#[cfg(feature = "extract")]
+pub use crate::local_db::{LocalChat, LocalDbReader, LocalMessage};
+pub use crate::loco::client::LocoClient;
+#[cfg(feature = "archive")]
+pub use crate::message_db::{CachedMessage, MessageDb};
+pub use crate::model::{
+    AlbumPhoto, ChatMember, ChatMembersPage, ChatMessage, ChatRoom, Friend, KakaoCredentials,
+    MessageContent, MessageLink, MyProfile,
+};
+pub use crate::progress::{NoProgress, ProgressEvent, ProgressSink};
+pub use crate::rest::{
+    HttpOptions, KakaoRestClient, KakaoRestClientBuilder, RequestOptions, TokenStatus,
+};
+pub use crate::rest_async::AsyncKakaoRestClient;
+pub use crate::retry::RetryPolicy;
+pub use crate::status::KakaoStatus;
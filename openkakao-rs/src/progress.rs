@@ -0,0 +1,117 @@
+use std::time::Duration;
+
+/// Progress of a bulk fetch (message history, member lists, syncs).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ProgressEvent {
+    /// A page came back. `items` is the page size, `total` the running count.
+    PageFetched {
+        page: usize,
+        items: usize,
+        total: usize,
+    },
+    /// A request failed and is about to be retried.
+    Retrying { attempt: u32, reason: String },
+    /// The fetch is pausing before the next page.
+    Throttled { wait: Duration },
+    /// The fetch ended early (the `--max-bytes` cap) and keeps the `total`
+    /// items fetched before it.
+    Stopped { total: usize, reason: String },
+    /// The fetch finished with `total` items.
+    Done { total: usize },
+}
+
+/// Receiver for [`ProgressEvent`]s. Closures taking `&ProgressEvent` work
+/// directly.
+pub trait ProgressSink {
+    fn emit(&self, event: &ProgressEvent);
+}
+
+impl<F: Fn(&ProgressEvent)> ProgressSink for F {
+    fn emit(&self, event: &ProgressEvent) {
+        self(event)
+    }
+}
+
+/// Sink that drops every event.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoProgress;
+
+impl ProgressSink for NoProgress {
+    fn emit(&self, _event: &ProgressEvent) {}
+}
+
+/// Sink that prints the CLI's `[label] Batch N: ...` lines to stderr.
+#[derive(Debug, Clone, Copy)]
+pub struct StderrProgress {
+    pub label: &'static str,
+}
+
+impl StderrProgress {
+    pub fn new(label: &'static str) -> Self {
+        Self { label }
+    }
+
+    pub fn format(&self, event: &ProgressEvent) -> Option<String> {
+        match event {
+            ProgressEvent::PageFetched { page, items, total } => Some(format!(
+                "[{}] Batch {}: {} items (total: {})",
+                self.label, page, items, total
+            )),
+            ProgressEvent::Retrying { attempt, reason } => Some(format!(
+                "[{}] Retrying (attempt {}): {}",
+                self.label, attempt, reason
+            )),
+            // Throttle pauses are short and frequent; printing them is noise.
+            ProgressEvent::Throttled { .. } => None,
+            ProgressEvent::Stopped { total, reason } => Some(format!(
+                "[{}] Stopped early: {}; keeping {} items",
+                self.label, reason, total
+            )),
+            ProgressEvent::Done { total } => {
+                Some(format!("[{}] Done: {} items", self.label, total))
+            }
+        }
+    }
+}
+
+impl ProgressSink for StderrProgress {
+    fn emit(&self, event: &ProgressEvent) {
+        if let Some(line) = self.format(event) {
+            eprintln!("{}", line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[test]
+    fn closures_are_sinks() {
+        let seen = RefCell::new(Vec::new());
+        let sink = |event: &ProgressEvent| seen.borrow_mut().push(event.clone());
+        sink.emit(&ProgressEvent::Done { total: 3 });
+        assert_eq!(seen.into_inner(), vec![ProgressEvent::Done { total: 3 }]);
+    }
+
+    #[test]
+    fn stderr_progress_formats_pages_and_skips_throttle() {
+        let sink = StderrProgress::new("loco-read");
+        assert_eq!(
+            sink.format(&ProgressEvent::PageFetched {
+                page: 2,
+                items: 50,
+                total: 100
+            })
+            .as_deref(),
+            Some("[loco-read] Batch 2: 50 items (total: 100)")
+        );
+        assert!(sink
+            .format(&ProgressEvent::Throttled {
+                wait: Duration::from_millis(100)
+            })
+            .is_none());
+    }
+}
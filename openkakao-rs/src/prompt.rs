@@ -0,0 +1,133 @@
+//! Every stdin prompt goes through this module, so `--no-input` can promise
+//! that a run never waits for an answer: confirmations, credential entry and
+//! any other question either get their answer from a flag or fail with
+//! [`InputRequired`] (exit code [`INPUT_REQUIRED_EXIT_CODE`]).
+
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{Context, Result};
+
+/// Set to `1` to refuse every stdin prompt (credential entry, confirmations).
+pub const NO_INTERACTIVE_ENV: &str = "OPENKAKAO_NO_INTERACTIVE";
+
+/// Exit status of a run that stopped because it needed an answer it could
+/// not ask for.
+pub const INPUT_REQUIRED_EXIT_CODE: i32 = 6;
+
+/// Set by `--no-input`.
+pub static NO_INPUT: AtomicBool = AtomicBool::new(false);
+
+/// A prompt was needed while prompting is off.
+#[derive(Debug, thiserror::Error)]
+#[error("{what} needs an answer, but {reason}. {hint}")]
+pub struct InputRequired {
+    pub what: String,
+    pub reason: String,
+    pub hint: String,
+}
+
+fn interactive_disabled_by_env(value: Option<&str>) -> bool {
+    matches!(value.map(str::trim), Some("1" | "true" | "yes"))
+}
+
+/// Whether `--no-input` or `OPENKAKAO_NO_INTERACTIVE` forbids prompting.
+pub fn interactive_disabled() -> bool {
+    NO_INPUT.load(Ordering::Relaxed)
+        || interactive_disabled_by_env(std::env::var(NO_INTERACTIVE_ENV).ok().as_deref())
+}
+
+/// Why stdin prompts are unavailable, or `None` when prompting is fine.
+pub fn non_interactive_reason() -> Option<String> {
+    if NO_INPUT.load(Ordering::Relaxed) {
+        Some("prompts are disabled by --no-input".to_string())
+    } else if interactive_disabled() {
+        Some(format!(
+            "interactive prompts are disabled by {NO_INTERACTIVE_ENV}"
+        ))
+    } else if !io::stdin().is_terminal() {
+        Some("stdin is not a terminal".to_string())
+    } else {
+        None
+    }
+}
+
+/// Fail when `action` needs a yes/no answer and stdin cannot give one.
+pub fn ensure_confirmable(action: &str) -> Result<()> {
+    match non_interactive_reason() {
+        Some(reason) => Err(InputRequired {
+            what: format!("Confirming {action}"),
+            reason,
+            hint: "Pass -y/--yes to proceed without asking.".to_string(),
+        }
+        .into()),
+        None => Ok(()),
+    }
+}
+
+/// Print `question` to stderr and read a yes/no answer; anything but `y`
+/// declines.
+pub fn confirm(question: &str) -> Result<bool> {
+    ensure_confirmable("this action")?;
+    eprint!("{question}");
+    io::stderr().flush()?;
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    Ok(input.trim().eq_ignore_ascii_case("y"))
+}
+
+/// Print `label` and read one trimmed line. Callers check
+/// [`non_interactive_reason`] first to explain what to provide instead.
+pub fn line(label: &str) -> Result<String> {
+    if let Some(reason) = non_interactive_reason() {
+        return Err(InputRequired {
+            what: label.trim_end_matches([' ', ':']).to_string(),
+            reason,
+            hint: "Provide it through the environment or a flag instead.".to_string(),
+        }
+        .into());
+    }
+    print!("{}", label);
+    io::stdout().flush().context("Failed to flush stdout")?;
+    let mut input = String::new();
+    io::stdin()
+        .read_line(&mut input)
+        .context("Failed to read stdin")?;
+    Ok(input.trim().to_string())
+}
+
+/// Whether `err` (or anything it wraps) is an [`InputRequired`].
+pub fn is_input_required(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| cause.is::<InputRequired>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_interactive_env_values() {
+        assert!(interactive_disabled_by_env(Some("1")));
+        assert!(interactive_disabled_by_env(Some("true")));
+        assert!(!interactive_disabled_by_env(Some("0")));
+        assert!(!interactive_disabled_by_env(Some("")));
+        assert!(!interactive_disabled_by_env(None));
+    }
+
+    #[test]
+    fn input_required_is_found_through_context() {
+        let err = anyhow::Error::new(InputRequired {
+            what: "Confirming the send".into(),
+            reason: "prompts are disabled by --no-input".into(),
+            hint: "Pass -y/--yes to proceed without asking.".into(),
+        })
+        .context("send failed");
+        assert!(is_input_required(&err));
+        assert!(!is_input_required(&anyhow::anyhow!("other")));
+        assert_eq!(
+            err.root_cause().to_string(),
+            "Confirming the send needs an answer, but prompts are disabled by --no-input. \
+             Pass -y/--yes to proceed without asking."
+        );
+    }
+}
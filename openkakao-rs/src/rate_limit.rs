@@ -0,0 +1,494 @@
+//! Rate-limit hints from Kakao responses and the request interval they
+//! adapt.
+//!
+//! Kakao documents no rate-limit headers, but some answers (notably 429s)
+//! carry `X-RateLimit-*`/`RateLimit-*` headers or a retry window in the
+//! body. Each response's hints become a [`RateLimitInfo`]; [`Adaptive`]
+//! turns the sequence of them into the spacing between REST requests,
+//! widening it as the remaining quota drops and narrowing it back a quarter
+//! at a time once the pressure is gone. It never goes below the static
+//! interval a command asked for, so a run that sees no hints throttles
+//! exactly as before.
+
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::download_pool::Throttle;
+
+/// The smallest interval a tightening sets.
+pub const FLOOR: Duration = Duration::from_millis(250);
+
+/// The widest interval; a longer wait is the retry loop's business.
+pub const MAX_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Below this share of the quota left, the interval starts to widen.
+const PRESSURE_SHARE: f64 = 0.5;
+
+/// What one response said about the rate limit. Fields are `None` when the
+/// response did not say.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RateLimitInfo {
+    pub limit: Option<u64>,
+    pub remaining: Option<u64>,
+    /// Until the quota window resets.
+    #[serde(with = "opt_secs", skip_serializing_if = "Option::is_none")]
+    pub reset: Option<Duration>,
+    /// From `Retry-After` or a retry window in a 429 body.
+    #[serde(with = "opt_secs", skip_serializing_if = "Option::is_none")]
+    pub retry_after: Option<Duration>,
+    /// The response was a 429.
+    pub throttled: bool,
+}
+
+mod opt_secs {
+    use std::time::Duration;
+
+    use serde::Serializer;
+
+    pub fn serialize<S: Serializer>(value: &Option<Duration>, s: S) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => s.serialize_some(&value.as_secs()),
+            None => s.serialize_none(),
+        }
+    }
+}
+
+impl RateLimitInfo {
+    /// Hints in `headers` (name, value pairs; names in any case). A header
+    /// given more than once, or under both the `X-RateLimit-` and
+    /// `RateLimit-` spellings with different values, counts at its most
+    /// cautious: the lowest limit and remaining, the latest reset. A reset
+    /// past one billion is a Unix time and is measured from `now_unix`.
+    pub fn from_headers<'a>(
+        headers: impl IntoIterator<Item = (&'a str, &'a str)>,
+        now_unix: i64,
+    ) -> Self {
+        let mut info = Self::default();
+        for (name, value) in headers {
+            let name = name.to_ascii_lowercase();
+            let name = name
+                .strip_prefix("x-")
+                .unwrap_or(&name)
+                .replace("rate-limit", "ratelimit");
+            let Some(number) = leading_number(value) else {
+                continue;
+            };
+            match name.as_str() {
+                "ratelimit-limit" => info.limit = min_some(info.limit, number),
+                "ratelimit-remaining" => info.remaining = min_some(info.remaining, number),
+                "ratelimit-reset" => {
+                    let secs = if number > 1_000_000_000 {
+                        number.saturating_sub(now_unix.max(0) as u64)
+                    } else {
+                        number
+                    };
+                    info.reset = info.reset.max(Some(Duration::from_secs(secs)));
+                }
+                "retry-after" => {
+                    info.retry_after = info.retry_after.max(Some(Duration::from_secs(number)));
+                }
+                _ => {}
+            }
+        }
+        info
+    }
+
+    /// Mark a 429 and take a retry window from its body (`retry_after` or
+    /// `retryAfter`, in seconds) when the headers gave none.
+    pub fn throttled_with_body(mut self, body: &[u8]) -> Self {
+        self.throttled = true;
+        if self.retry_after.is_none() {
+            let json = serde_json::from_slice::<Value>(body).ok();
+            self.retry_after = ["retry_after", "retryAfter"]
+                .iter()
+                .find_map(|key| match json.as_ref()?.get(key)? {
+                    Value::Number(n) => n.as_u64(),
+                    Value::String(s) => leading_number(s),
+                    _ => None,
+                })
+                .map(Duration::from_secs);
+        }
+        self
+    }
+
+    /// Whether the response said anything about the rate limit.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// `remaining` no greater than `limit`, and a zero limit dropped as
+    /// meaningless.
+    fn normalized(&self) -> Self {
+        let limit = self.limit.filter(|&limit| limit > 0);
+        Self {
+            limit,
+            remaining: match (self.remaining, limit) {
+                (Some(remaining), Some(limit)) => Some(remaining.min(limit)),
+                (remaining, _) => remaining,
+            },
+            ..self.clone()
+        }
+    }
+
+    /// The interval these hints ask for, or `None` when they show no
+    /// pressure. `current` is the interval in force, which a 429 doubles.
+    fn target(&self, current: Duration) -> Option<Duration> {
+        let info = self.normalized();
+        if info.throttled {
+            return Some((current * 2).max(FLOOR));
+        }
+        let remaining = info.remaining?;
+        if remaining == 0 {
+            return Some(info.reset.or(info.retry_after).unwrap_or(MAX_INTERVAL));
+        }
+        let share = info.limit.map(|limit| remaining as f64 / limit as f64);
+        if share.is_some_and(|share| share >= PRESSURE_SHARE) {
+            return None;
+        }
+        let scaled = match share {
+            Some(share) => FLOOR.mul_f64(PRESSURE_SHARE / share),
+            None => FLOOR,
+        };
+        // With a known window, the remaining requests spread across it.
+        let spread = info.reset.map_or(Duration::ZERO, |reset| {
+            reset / remaining.min(u64::from(u32::MAX)) as u32
+        });
+        Some(scaled.max(spread))
+    }
+
+    /// `limit=100 remaining=3 reset=20s`, listing what was given.
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(limit) = self.limit {
+            parts.push(format!("limit={limit}"));
+        }
+        if let Some(remaining) = self.remaining {
+            parts.push(format!("remaining={remaining}"));
+        }
+        if let Some(reset) = self.reset {
+            parts.push(format!("reset={}s", reset.as_secs()));
+        }
+        if let Some(retry_after) = self.retry_after {
+            parts.push(format!("retry_after={}s", retry_after.as_secs()));
+        }
+        if self.throttled {
+            parts.push("429".to_string());
+        }
+        parts.join(" ")
+    }
+}
+
+fn leading_number(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let end = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    value[..end].parse().ok()
+}
+
+fn min_some(current: Option<u64>, value: u64) -> Option<u64> {
+    Some(current.map_or(value, |current| current.min(value)))
+}
+
+/// The request interval, adapted to what responses say. Deterministic: the
+/// same base and sequence of observations always give the same intervals.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Adaptive {
+    base: Duration,
+    interval: Duration,
+    /// The last response that carried hints.
+    last: Option<RateLimitInfo>,
+    observed: u64,
+}
+
+impl Adaptive {
+    pub fn new(base: Duration) -> Self {
+        Self {
+            base,
+            interval: base,
+            ..Self::default()
+        }
+    }
+
+    /// Change the static interval; the adapted one never drops below it.
+    pub fn set_base(&mut self, base: Duration) {
+        self.base = base;
+        self.interval = self.interval.max(base);
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Account for one response and return the interval from now on.
+    /// Pressure widens the interval at once; a response without any
+    /// narrows it by a quarter, snapping to the base once within 10ms.
+    pub fn observe(&mut self, info: &RateLimitInfo) -> Duration {
+        if !info.is_empty() {
+            self.last = Some(info.clone());
+            self.observed += 1;
+        }
+        let target = info.target(self.interval);
+        match target {
+            Some(target) if target > self.interval => {
+                self.interval = target.min(MAX_INTERVAL).max(self.base);
+            }
+            _ => {
+                let floor = target.unwrap_or_default().max(self.base);
+                let loosened = self.interval - self.interval / 4;
+                self.interval = if loosened.saturating_sub(floor) < Duration::from_millis(10) {
+                    floor
+                } else {
+                    loosened
+                };
+            }
+        }
+        self.interval
+    }
+
+    /// One line for `--stats`.
+    pub fn summary(&self) -> String {
+        let last = match &self.last {
+            Some(last) => format!(
+                "{} response(s) with limit hints, last: {}",
+                self.observed,
+                last.summary()
+            ),
+            None => "no limit hints seen".to_string(),
+        };
+        format!(
+            "interval {}ms (static {}ms), {last}",
+            self.interval.as_millis(),
+            self.base.as_millis()
+        )
+    }
+}
+
+/// Request spacing shared by the clients holding it: slots handed out in
+/// call order, [`Adaptive::interval`] apart.
+#[derive(Debug)]
+pub struct Pacing {
+    slots: Throttle,
+    adaptive: Mutex<Adaptive>,
+}
+
+impl Default for Pacing {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Pacing {
+    pub fn new() -> Self {
+        Self {
+            slots: Throttle::new(Duration::ZERO),
+            adaptive: Mutex::new(Adaptive::new(Duration::ZERO)),
+        }
+    }
+
+    fn adaptive(&self) -> MutexGuard<'_, Adaptive> {
+        self.adaptive
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// See [`Adaptive::set_base`].
+    pub fn set_base(&self, base: Duration) {
+        self.adaptive().set_base(base);
+    }
+
+    /// Block until this caller's slot.
+    pub fn wait(&self) {
+        let interval = self.adaptive().interval();
+        self.slots.wait_for(interval);
+    }
+
+    /// See [`Adaptive::observe`].
+    pub fn observe(&self, info: &RateLimitInfo) -> Duration {
+        let mut adaptive = self.adaptive();
+        let before = adaptive.interval();
+        let after = adaptive.observe(info);
+        if after != before {
+            tracing::debug!(
+                "request interval {:?} -> {:?} ({})",
+                before,
+                after,
+                info.summary()
+            );
+        }
+        after
+    }
+
+    pub fn summary(&self) -> String {
+        self.adaptive().summary()
+    }
+}
+
+static GLOBAL: OnceLock<Arc<Pacing>> = OnceLock::new();
+
+/// The process-wide pacing that clients use unless given their own.
+pub fn global() -> Arc<Pacing> {
+    GLOBAL.get_or_init(|| Arc::new(Pacing::new())).clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    fn quota(limit: u64, remaining: u64) -> RateLimitInfo {
+        RateLimitInfo {
+            limit: Some(limit),
+            remaining: Some(remaining),
+            ..RateLimitInfo::default()
+        }
+    }
+
+    #[test]
+    fn headers_parse_in_either_spelling() {
+        let info = RateLimitInfo::from_headers(
+            [
+                ("X-RateLimit-Limit", "100"),
+                ("x-ratelimit-remaining", " 7 "),
+                ("RateLimit-Reset", "20;w=60"),
+                ("Retry-After", "3"),
+                ("Content-Type", "application/json"),
+            ],
+            0,
+        );
+        assert_eq!(info.limit, Some(100));
+        assert_eq!(info.remaining, Some(7));
+        assert_eq!(info.reset, Some(Duration::from_secs(20)));
+        assert_eq!(info.retry_after, Some(Duration::from_secs(3)));
+        assert!(!info.throttled);
+
+        let epoch =
+            RateLimitInfo::from_headers([("X-Rate-Limit-Reset", "1700000045")], 1_700_000_000);
+        assert_eq!(epoch.reset, Some(Duration::from_secs(45)));
+
+        assert!(RateLimitInfo::from_headers([("Server", "nginx")], 0).is_empty());
+    }
+
+    #[test]
+    fn contradictory_headers_count_at_their_most_cautious() {
+        let info = RateLimitInfo::from_headers(
+            [
+                ("X-RateLimit-Limit", "100"),
+                ("RateLimit-Limit", "60"),
+                ("X-RateLimit-Remaining", "40"),
+                ("RateLimit-Remaining", "90"),
+                ("X-RateLimit-Reset", "5"),
+                ("RateLimit-Reset", "30"),
+                ("X-RateLimit-Remaining", "soon"),
+            ],
+            0,
+        );
+        assert_eq!(info.limit, Some(60));
+        assert_eq!(info.remaining, Some(40));
+        assert_eq!(info.reset, Some(Duration::from_secs(30)));
+
+        // More left than the limit allows is read as the limit.
+        assert_eq!(quota(10, 50).target(Duration::ZERO), None);
+        // A zero limit says nothing; the remaining count alone decides.
+        assert_eq!(quota(0, 5).target(Duration::ZERO), Some(FLOOR));
+    }
+
+    #[test]
+    fn retry_windows_come_from_429_bodies() {
+        let info = RateLimitInfo::default().throttled_with_body(br#"{"retryAfter": "12"}"#);
+        assert!(info.throttled);
+        assert_eq!(info.retry_after, Some(Duration::from_secs(12)));
+
+        let header_wins = RateLimitInfo {
+            retry_after: Some(Duration::from_secs(2)),
+            ..RateLimitInfo::default()
+        }
+        .throttled_with_body(br#"{"retry_after": 99}"#);
+        assert_eq!(header_wins.retry_after, Some(Duration::from_secs(2)));
+
+        let unparsable = RateLimitInfo::default().throttled_with_body(b"<html>");
+        assert_eq!(unparsable.retry_after, None);
+    }
+
+    #[test]
+    fn interval_tightens_as_quota_drops_and_loosens_gradually() {
+        let mut adaptive = Adaptive::new(Duration::ZERO);
+        let intervals: Vec<u128> = [
+            quota(100, 80),
+            quota(100, 50),
+            quota(100, 25),
+            quota(100, 5),
+            quota(100, 40),
+            RateLimitInfo::default(),
+            RateLimitInfo::default(),
+            quota(100, 90),
+        ]
+        .iter()
+        .map(|info| adaptive.observe(info).as_millis())
+        .collect();
+        assert_eq!(
+            intervals,
+            [
+                0, 0, 500, 2500,
+                // Pressure eased: a quarter off per response, not a jump.
+                1875, 1406, 1054, 791,
+            ]
+        );
+        assert!(adaptive.summary().contains("limit=100 remaining=90"));
+    }
+
+    #[test]
+    fn exhausted_quota_and_429s_widen_up_to_the_cap() {
+        let mut adaptive = Adaptive::new(ms(100));
+        let exhausted = RateLimitInfo {
+            reset: Some(Duration::from_secs(4)),
+            ..quota(100, 0)
+        };
+        assert_eq!(adaptive.observe(&exhausted), Duration::from_secs(4));
+
+        let throttled = RateLimitInfo {
+            throttled: true,
+            ..RateLimitInfo::default()
+        };
+        assert_eq!(adaptive.observe(&throttled), Duration::from_secs(8));
+        assert_eq!(adaptive.observe(&throttled), Duration::from_secs(16));
+        assert_eq!(adaptive.observe(&throttled), MAX_INTERVAL);
+        assert_eq!(adaptive.observe(&throttled), MAX_INTERVAL);
+
+        // Spread over a known window: 10 requests left in 20s.
+        let mut spread = Adaptive::new(Duration::ZERO);
+        let windowed = RateLimitInfo {
+            reset: Some(Duration::from_secs(20)),
+            ..quota(100, 10)
+        };
+        assert_eq!(spread.observe(&windowed), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn no_hints_keep_the_static_interval() {
+        let mut adaptive = Adaptive::new(ms(200));
+        for _ in 0..5 {
+            assert_eq!(adaptive.observe(&RateLimitInfo::default()), ms(200));
+        }
+        assert_eq!(
+            adaptive.summary(),
+            "interval 200ms (static 200ms), no limit hints seen"
+        );
+
+        // Loosening stops at the base, and a raised base lifts the interval.
+        let mut adaptive = Adaptive::new(ms(200));
+        adaptive.observe(&quota(100, 10));
+        for _ in 0..20 {
+            adaptive.observe(&RateLimitInfo::default());
+        }
+        assert_eq!(adaptive.interval(), ms(200));
+        adaptive.set_base(ms(300));
+        assert_eq!(adaptive.interval(), ms(300));
+    }
+}
@@ -0,0 +1,569 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, TimeZone, Timelike};
+use serde::Serialize;
+
+use crate::message_db::CachedMessage;
+use crate::util::percent_change;
+
+const TOP_AUTHORS: usize = 5;
+const TOP_KEYWORDS: usize = 5;
+
+/// Words too common to say anything about a week of chat.
+const STOPWORDS: &[&str] = &[
+    "the",
+    "and",
+    "for",
+    "you",
+    "that",
+    "this",
+    "with",
+    "are",
+    "was",
+    "have",
+    "not",
+    "but",
+    "http",
+    "https",
+    "www",
+    "com",
+    "그리고",
+    "그래서",
+    "근데",
+    "그냥",
+    "진짜",
+    "너무",
+    "이거",
+    "저거",
+    "우리",
+    "하고",
+    "있어",
+    "없어",
+    "ㅋㅋ",
+    "ㅋㅋㅋ",
+    "ㅎㅎ",
+    "ㅎㅎㅎ",
+    "ㅠㅠ",
+];
+
+/// `report --period` value; each period is the window ending now.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportPeriod {
+    Week,
+    Month,
+}
+
+impl ReportPeriod {
+    pub fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "week" | "weekly" => Ok(Self::Week),
+            "month" | "monthly" => Ok(Self::Month),
+            _ => Err(anyhow!("Unknown period '{}'. Use: week, month", s)),
+        }
+    }
+
+    pub fn days(&self) -> i64 {
+        match self {
+            Self::Week => 7,
+            Self::Month => 30,
+        }
+    }
+
+    /// `(previous_start, start, end)` for the period ending at `now`.
+    pub fn windows(&self, now: i64) -> (i64, i64, i64) {
+        let span = self.days() * 86_400;
+        (now - 2 * span, now - span, now)
+    }
+
+    fn adjective(&self) -> &'static str {
+        match self {
+            Self::Week => "Weekly",
+            Self::Month => "Monthly",
+        }
+    }
+
+    fn noun(&self) -> &'static str {
+        match self {
+            Self::Week => "week",
+            Self::Month => "month",
+        }
+    }
+}
+
+/// `report --format` value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Text,
+    Markdown,
+}
+
+impl ReportFormat {
+    pub fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "text" | "txt" => Ok(Self::Text),
+            "md" | "markdown" => Ok(Self::Markdown),
+            _ => Err(anyhow!("Unknown report format '{}'. Use: text, md", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Ranked {
+    pub name: String,
+    pub count: usize,
+}
+
+/// Everything a report shows, computed once so rendering is a pure function.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ActivityReport {
+    pub chat_id: i64,
+    pub chat_title: String,
+    pub period: ReportPeriod,
+    pub start: i64,
+    pub end: i64,
+    /// Inclusive local dates, e.g. `2026-10-09 – 2026-10-15`.
+    pub range_label: String,
+    pub messages: usize,
+    pub previous_messages: usize,
+    /// Percent change from the previous period; `None` when it was empty.
+    pub delta: Option<f64>,
+    pub top_authors: Vec<Ranked>,
+    /// Busiest local date, labelled `YYYY-MM-DD (Mon)`.
+    pub busiest_day: Option<Ranked>,
+    /// Busiest local hour of day (0-23) and its message count.
+    pub busiest_hour: Option<(u32, usize)>,
+    pub keywords: Vec<Ranked>,
+    pub joined: Vec<String>,
+    pub left: Vec<String>,
+}
+
+/// Chat metadata plus the messages to aggregate.
+pub struct ReportInput<'a> {
+    pub chat_id: i64,
+    pub chat_title: &'a str,
+    pub period: ReportPeriod,
+    pub now: i64,
+    /// Messages from at least the previous period's start up to `now`.
+    pub messages: &'a [CachedMessage],
+    /// Names for authors whose messages carry none.
+    pub names: &'a HashMap<i64, String>,
+}
+
+impl ActivityReport {
+    /// Aggregate `input`, bucketing days and hours in `tz`.
+    pub fn build<Tz: TimeZone>(input: &ReportInput<'_>, tz: &Tz) -> Self
+    where
+        Tz::Offset: std::fmt::Display,
+    {
+        let (previous_start, start, end) = input.period.windows(input.now);
+        let mut authors: HashMap<i64, usize> = HashMap::new();
+        let mut names: HashMap<i64, &str> = HashMap::new();
+        let mut days: BTreeMap<String, usize> = BTreeMap::new();
+        let mut hours = [0usize; 24];
+        let mut words: HashMap<String, usize> = HashMap::new();
+        let (mut joined, mut left) = (Vec::new(), Vec::new());
+        let mut messages = 0;
+        let mut previous_messages = 0;
+
+        for message in input.messages {
+            if message.send_at >= previous_start && message.send_at < start {
+                if message.message_type != 0 {
+                    previous_messages += 1;
+                }
+                continue;
+            }
+            if message.send_at < start || message.send_at >= end {
+                continue;
+            }
+            if message.message_type == 0 {
+                if let Some((event, members)) = member_event(message) {
+                    match event {
+                        MemberEvent::Joined => {
+                            joined.extend(members.into_iter().filter_map(|m| m.name))
+                        }
+                        MemberEvent::Left => {
+                            left.extend(members.into_iter().filter_map(|m| m.name))
+                        }
+                    }
+                }
+                continue;
+            }
+            messages += 1;
+            *authors.entry(message.author_id).or_insert(0) += 1;
+            if !message.author_name.is_empty() {
+                names.insert(message.author_id, &message.author_name);
+            }
+            if let Some(dt) = tz.timestamp_opt(message.send_at, 0).single() {
+                let day = format!("{} ({})", dt.format("%Y-%m-%d"), dt.weekday());
+                *days.entry(day).or_insert(0) += 1;
+                hours[dt.hour() as usize] += 1;
+            }
+            if message.message_type == 1 {
+                for word in keywords_in(&message.message) {
+                    *words.entry(word).or_insert(0) += 1;
+                }
+            }
+        }
+
+        let author_name = |user_id: i64| {
+            names
+                .get(&user_id)
+                .map(|n| n.to_string())
+                .or_else(|| input.names.get(&user_id).cloned())
+                .unwrap_or_else(|| format!("User#{}", user_id))
+        };
+        let mut top_authors: Vec<(i64, usize)> = authors.into_iter().collect();
+        top_authors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        let top_authors = top_authors
+            .into_iter()
+            .take(TOP_AUTHORS)
+            .map(|(user_id, count)| Ranked {
+                name: author_name(user_id),
+                count,
+            })
+            .collect();
+
+        let mut keywords: Vec<Ranked> = words
+            .into_iter()
+            .filter(|(_, count)| *count > 1)
+            .map(|(name, count)| Ranked { name, count })
+            .collect();
+        keywords.sort_by(|a, b| b.count.cmp(&a.count).then(a.name.cmp(&b.name)));
+        keywords.truncate(TOP_KEYWORDS);
+
+        // Earliest wins ties, for both days and hours.
+        let busiest_day = days
+            .into_iter()
+            .fold(None::<Ranked>, |best, (name, count)| match best {
+                Some(b) if b.count >= count => Some(b),
+                _ => Some(Ranked { name, count }),
+            });
+        let busiest_hour = hours
+            .iter()
+            .enumerate()
+            .filter(|(_, &count)| count > 0)
+            .fold(None::<(u32, usize)>, |best, (hour, &count)| match best {
+                Some(b) if b.1 >= count => Some(b),
+                _ => Some((hour as u32, count)),
+            });
+
+        let date = |epoch: i64| {
+            tz.timestamp_opt(epoch, 0)
+                .single()
+                .map(|dt| dt.format("%Y-%m-%d").to_string())
+                .unwrap_or_default()
+        };
+
+        Self {
+            chat_id: input.chat_id,
+            chat_title: input.chat_title.to_string(),
+            period: input.period,
+            start,
+            end,
+            range_label: format!("{} – {}", date(start), date(end - 1)),
+            messages,
+            previous_messages,
+            delta: percent_change(previous_messages as f64, messages as f64),
+            top_authors,
+            busiest_day,
+            busiest_hour,
+            keywords,
+            joined,
+            left,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemberEvent {
+    Joined,
+    Left,
+}
+
+/// One member named in a feed message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeedMember {
+    pub user_id: i64,
+    pub name: Option<String>,
+}
+
+/// Feed messages (type 0) carry `{"feedType": n, "members": [...]}`:
+/// 1 invited and 4 joined by link count as joins, 2 left and 6 kicked as
+/// departures.
+pub fn member_event(message: &CachedMessage) -> Option<(MemberEvent, Vec<FeedMember>)> {
+    let feed: serde_json::Value = [&message.message, &message.attachment]
+        .iter()
+        .find_map(|raw| serde_json::from_str(raw).ok())?;
+    let event = match feed.get("feedType")?.as_i64()? {
+        1 | 4 => MemberEvent::Joined,
+        2 | 6 => MemberEvent::Left,
+        _ => return None,
+    };
+    let members = feed
+        .get("members")
+        .and_then(|m| m.as_array())
+        .map(|members| {
+            members
+                .iter()
+                .map(|m| FeedMember {
+                    user_id: m.get("userId").and_then(|id| id.as_i64()).unwrap_or(0),
+                    name: m
+                        .get("nickName")
+                        .and_then(|n| n.as_str())
+                        .map(str::to_string),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    Some((event, members))
+}
+
+/// Lowercased words of two or more characters, without numbers, links
+/// and stopwords.
+fn keywords_in(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split_whitespace()
+        .filter(|token| !token.contains("://"))
+        .flat_map(|token| token.split(|c: char| !c.is_alphanumeric()))
+        .map(str::to_lowercase)
+        .filter(|word| {
+            word.chars().count() >= 2
+                && !word.chars().all(|c| c.is_ascii_digit())
+                && !STOPWORDS.contains(&word.as_str())
+        })
+}
+
+/// Render `report` as plain text or Markdown, ready to post into a chat.
+pub fn render_report(report: &ActivityReport, format: ReportFormat) -> String {
+    let md = format == ReportFormat::Markdown;
+    let mut out = String::new();
+    let title = format!(
+        "{} report: {}",
+        report.period.adjective(),
+        report.chat_title
+    );
+    if md {
+        out.push_str(&format!("## {}\n_{}_\n\n", title, report.range_label));
+    } else {
+        out.push_str(&format!("{}\n{}\n\n", title, report.range_label));
+    }
+
+    let bullet = |label: &str, value: String| {
+        if md {
+            format!("- **{}:** {}\n", label, value)
+        } else {
+            format!("{}: {}\n", label, value)
+        }
+    };
+    let delta = match report.delta {
+        Some(d) => format!("{:+.1}%", d),
+        None => "n/a".to_string(),
+    };
+    out.push_str(&bullet(
+        "Messages",
+        format!(
+            "{} (previous {}: {}, {})",
+            report.messages,
+            report.period.noun(),
+            report.previous_messages,
+            delta
+        ),
+    ));
+    if let Some(day) = &report.busiest_day {
+        out.push_str(&bullet(
+            "Busiest day",
+            format!("{}, {} messages", day.name, day.count),
+        ));
+    }
+    if let Some((hour, count)) = report.busiest_hour {
+        out.push_str(&bullet(
+            "Busiest hour",
+            format!("{:02}:00–{:02}:59, {} messages", hour, hour, count),
+        ));
+    }
+    if !report.keywords.is_empty() {
+        let words: Vec<String> = report
+            .keywords
+            .iter()
+            .map(|k| format!("{} ({})", k.name, k.count))
+            .collect();
+        out.push_str(&bullet("Top keywords", words.join(", ")));
+    }
+    if !report.joined.is_empty() {
+        out.push_str(&bullet("Joined", report.joined.join(", ")));
+    }
+    if !report.left.is_empty() {
+        out.push_str(&bullet("Left", report.left.join(", ")));
+    }
+
+    if !report.top_authors.is_empty() {
+        out.push_str(if md {
+            "\n### Top authors\n"
+        } else {
+            "\nTop authors\n"
+        });
+        for (i, author) in report.top_authors.iter().enumerate() {
+            let share = author.count as f64 / report.messages.max(1) as f64 * 100.0;
+            let line = format!("{} — {} ({:.0}%)", author.name, author.count, share);
+            if md {
+                out.push_str(&format!("{}. {}\n", i + 1, line));
+            } else {
+                out.push_str(&format!("  {}. {}\n", i + 1, line));
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    // 2026-10-16 00:00:00 UTC, a Friday.
+    const NOW: i64 = 1_792_108_800;
+
+    fn msg(author: (i64, &str), message_type: i32, text: &str, send_at: i64) -> CachedMessage {
+        CachedMessage {
+            chat_id: 42,
+            log_id: send_at,
+            author_id: author.0,
+            author_name: author.1.to_string(),
+            message_type,
+            message: text.to_string(),
+            attachment: String::new(),
+            send_at,
+        }
+    }
+
+    fn fixture() -> ActivityReport {
+        let alice = (1, "Alice");
+        let bob = (2, "Bob");
+        let carol = (3, "");
+        let day = 86_400;
+        let messages = vec![
+            // Previous week: two messages.
+            msg(alice, 1, "old news", NOW - 10 * day),
+            msg(bob, 1, "older news", NOW - 9 * day),
+            // This week.
+            msg(
+                alice,
+                1,
+                "deploy is done https://x.example/deploy",
+                NOW - 3 * day + 9 * 3600,
+            ),
+            msg(
+                alice,
+                1,
+                "Deploy again, 그리고 lunch?",
+                NOW - 3 * day + 9 * 3600 + 60,
+            ),
+            msg(bob, 1, "lunch! deploy", NOW - 3 * day + 21 * 3600),
+            msg(carol, 2, "", NOW - day + 9 * 3600),
+            msg(
+                (3, ""),
+                0,
+                r#"{"feedType":4,"members":[{"userId":3,"nickName":"Carol"}]}"#,
+                NOW - 2 * day,
+            ),
+            msg(
+                (4, ""),
+                0,
+                r#"{"feedType":2,"members":[{"userId":4,"nickName":"Dave"}]}"#,
+                NOW - day,
+            ),
+        ];
+        let names = HashMap::from([(3, "Carol".to_string())]);
+        ActivityReport::build(
+            &ReportInput {
+                chat_id: 42,
+                chat_title: "Team",
+                period: ReportPeriod::Week,
+                now: NOW,
+                messages: &messages,
+                names: &names,
+            },
+            &Utc,
+        )
+    }
+
+    #[test]
+    fn aggregates_the_current_period_against_the_previous_one() {
+        let report = fixture();
+        assert_eq!(report.messages, 4);
+        assert_eq!(report.previous_messages, 2);
+        assert_eq!(report.delta, Some(100.0));
+        assert_eq!(report.range_label, "2026-10-09 – 2026-10-15");
+        assert_eq!(report.top_authors[0].name, "Alice");
+        assert_eq!(report.top_authors[2].name, "Carol");
+        assert_eq!(report.joined, ["Carol"]);
+        assert_eq!(report.left, ["Dave"]);
+        assert_eq!(report.busiest_hour, Some((9, 3)));
+    }
+
+    #[test]
+    fn text_snapshot() {
+        assert_eq!(
+            render_report(&fixture(), ReportFormat::Text),
+            "Weekly report: Team\n\
+             2026-10-09 – 2026-10-15\n\
+             \n\
+             Messages: 4 (previous week: 2, +100.0%)\n\
+             Busiest day: 2026-10-13 (Tue), 3 messages\n\
+             Busiest hour: 09:00–09:59, 3 messages\n\
+             Top keywords: deploy (3), lunch (2)\n\
+             Joined: Carol\n\
+             Left: Dave\n\
+             \n\
+             Top authors\n  \
+             1. Alice — 2 (50%)\n  \
+             2. Bob — 1 (25%)\n  \
+             3. Carol — 1 (25%)\n"
+        );
+    }
+
+    #[test]
+    fn markdown_snapshot() {
+        assert_eq!(
+            render_report(&fixture(), ReportFormat::Markdown),
+            "## Weekly report: Team\n\
+             _2026-10-09 – 2026-10-15_\n\
+             \n\
+             - **Messages:** 4 (previous week: 2, +100.0%)\n\
+             - **Busiest day:** 2026-10-13 (Tue), 3 messages\n\
+             - **Busiest hour:** 09:00–09:59, 3 messages\n\
+             - **Top keywords:** deploy (3), lunch (2)\n\
+             - **Joined:** Carol\n\
+             - **Left:** Dave\n\
+             \n\
+             ### Top authors\n\
+             1. Alice — 2 (50%)\n\
+             2. Bob — 1 (25%)\n\
+             3. Carol — 1 (25%)\n"
+        );
+    }
+
+    #[test]
+    fn empty_previous_period_has_no_delta() {
+        let names = HashMap::new();
+        let report = ActivityReport::build(
+            &ReportInput {
+                chat_id: 1,
+                chat_title: "Quiet",
+                period: ReportPeriod::Month,
+                now: NOW,
+                messages: &[],
+                names: &names,
+            },
+            &Utc,
+        );
+        assert_eq!(report.delta, None);
+        assert_eq!(
+            render_report(&report, ReportFormat::Text),
+            "Monthly report: Quiet\n2026-09-16 – 2026-10-15\n\nMessages: 0 (previous month: 0, n/a)\n"
+        );
+        assert!(ReportPeriod::from_str("year").is_err());
+        assert!(ReportFormat::from_str("html").is_err());
+    }
+}
@@ -0,0 +1,378 @@
+//! Opt-in cache of REST answers from read-only endpoints, for commands that
+//! ask the same thing several times within seconds (`summary`, `digest`,
+//! the TUI).
+//!
+//! Only endpoints listed in [`policy`] are cached, each for its own time:
+//! chat lists and messages 30 seconds, members, friends and profiles 10
+//! minutes, account settings an hour. Keys are the user id, method, URL and
+//! form body, so two accounts never share an answer. A mutating REST call
+//! drops what it made stale, and so do LOCO writes (a sent, edited or
+//! deleted message, a read receipt) for their chat. The CLI installs the
+//! cache from `[http] response_cache` unless `--fresh` is given; without it
+//! nothing is cached.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::rest::endpoint_for;
+
+/// What a cached answer is about, so a change can drop what it made stale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    ChatList,
+    Chat(i64),
+    Friends,
+    Account,
+}
+
+impl Scope {
+    /// Whether a change to `changed` makes an answer about `self` stale. A
+    /// chat's new message also changes the chat list's last message and
+    /// unread counts.
+    fn stale_after(self, changed: Scope) -> bool {
+        self == changed || (matches!(changed, Scope::Chat(_)) && self == Scope::ChatList)
+    }
+}
+
+pub const CHATS_TTL: Duration = Duration::from_secs(30);
+pub const FRIENDS_TTL: Duration = Duration::from_secs(10 * 60);
+pub const SETTINGS_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// LOCO commands that change a chat's messages or read state.
+pub const LOCO_WRITES: &[&str] = &[
+    "WRITE",
+    "FORWARD",
+    "DELETEMSG",
+    "REWRITE",
+    "ACTION",
+    "NOTIREAD",
+];
+
+/// How long an answer of `url` may be served again and what it is about;
+/// `None` for endpoints that are never cached (mutations, token and login
+/// calls, link previews, which have their own cache).
+pub fn policy(http_method: &str, url: &str) -> Option<(Duration, Scope)> {
+    let spec = endpoint_for(http_method, url).filter(|spec| spec.side_effect.is_none())?;
+    match spec.path {
+        "/messaging/chats" => Some((CHATS_TTL, Scope::ChatList)),
+        "/messaging/chats/{chat_id}/messages" => Some((CHATS_TTL, Scope::Chat(chat_id_of(url)?))),
+        "/messaging/chats/{chat_id}/members" => Some((FRIENDS_TTL, Scope::Chat(chat_id_of(url)?))),
+        "/mac/friends/update.json" | "/mac/profile3/friend.json" | "/mac/profile/list.json" => {
+            Some((FRIENDS_TTL, Scope::Friends))
+        }
+        "/mac/profile3/me.json"
+        | "/mac/account/more_settings.json"
+        | "/mac/alarm_keywords/list.json" => Some((SETTINGS_TTL, Scope::Account)),
+        _ => None,
+    }
+}
+
+/// What a mutating request to `url` makes stale.
+pub fn invalidated_by(http_method: &str, url: &str) -> Option<Scope> {
+    let spec = endpoint_for(http_method, url).filter(|spec| spec.side_effect.is_some())?;
+    if spec.path.starts_with("/mac/friends/") {
+        Some(Scope::Friends)
+    } else if spec.path.starts_with("/messaging/chats/") {
+        chat_id_of(url).map(Scope::Chat)
+    } else if spec.path.starts_with("/mac/profile3/") {
+        Some(Scope::Account)
+    } else {
+        None
+    }
+}
+
+/// The id after `/messaging/chats/` in `url`.
+fn chat_id_of(url: &str) -> Option<i64> {
+    let (_, rest) = url.split_once("/messaging/chats/")?;
+    rest.split(['/', '?', '#']).next()?.parse().ok()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    key: String,
+    /// Unix seconds.
+    stored_at: u64,
+    ttl_secs: u64,
+    scope: Scope,
+    value: Value,
+}
+
+impl Entry {
+    fn fresh(&self, now: u64) -> bool {
+        now.saturating_sub(self.stored_at) < self.ttl_secs
+    }
+}
+
+/// Cached answers in memory, and also in a directory when given one so
+/// separate runs share them.
+#[derive(Debug, Default)]
+pub struct ResponseCache {
+    entries: Mutex<HashMap<String, Entry>>,
+    dir: Option<PathBuf>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ResponseCache {
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Also keep answers as files in `dir`, one per key.
+    pub fn on_disk(dir: PathBuf) -> Self {
+        Self {
+            dir: Some(dir),
+            ..Self::default()
+        }
+    }
+
+    pub fn key(user_id: i64, http_method: &str, url: &str, body: Option<&str>) -> String {
+        format!(
+            "{user_id} {} {url} {}",
+            http_method.to_ascii_uppercase(),
+            body.unwrap_or_default()
+        )
+    }
+
+    /// The answer stored under `key` if it is still fresh at `now` (unix
+    /// seconds). Counts a hit or a miss.
+    pub fn get(&self, key: &str, now: u64) -> Option<Value> {
+        let found = self.lookup(key, now);
+        let counter = if found.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        found
+    }
+
+    fn lookup(&self, key: &str, now: u64) -> Option<Value> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(entry) = entries.get(key) {
+            if entry.fresh(now) {
+                return Some(entry.value.clone());
+            }
+            entries.remove(key);
+        }
+        let path = self.file_of(key)?;
+        let entry: Entry = serde_json::from_slice(&std::fs::read(&path).ok()?).ok()?;
+        if entry.key != key || !entry.fresh(now) {
+            let _ = std::fs::remove_file(&path);
+            return None;
+        }
+        let value = entry.value.clone();
+        entries.insert(key.to_string(), entry);
+        Some(value)
+    }
+
+    pub fn put(&self, key: &str, value: &Value, ttl: Duration, scope: Scope, now: u64) {
+        let entry = Entry {
+            key: key.to_string(),
+            stored_at: now,
+            ttl_secs: ttl.as_secs(),
+            scope,
+            value: value.clone(),
+        };
+        if let Some(path) = self.file_of(key) {
+            // Best effort: a failed write only costs a later request.
+            let written = path
+                .parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|()| std::fs::write(&path, serde_json::to_vec(&entry)?));
+            let _ = written;
+        }
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(key.to_string(), entry);
+    }
+
+    /// Drop every answer that `changed` made stale, in memory and on disk.
+    pub fn invalidate(&self, changed: Scope) {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|_, entry| !entry.scope.stale_after(changed));
+        let Some(files) = self
+            .dir
+            .as_ref()
+            .and_then(|dir| std::fs::read_dir(dir).ok())
+        else {
+            return;
+        };
+        for path in files.flatten().map(|file| file.path()) {
+            let stale = std::fs::read(&path)
+                .ok()
+                .and_then(|data| serde_json::from_slice::<Entry>(&data).ok())
+                .is_none_or(|entry| entry.scope.stale_after(changed));
+            if stale {
+                let _ = std::fs::remove_file(&path);
+            }
+        }
+    }
+
+    /// `(hits, misses)` so far.
+    pub fn stats(&self) -> (u64, u64) {
+        (
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+        )
+    }
+
+    fn file_of(&self, key: &str) -> Option<PathBuf> {
+        let digest = Sha256::digest(key.as_bytes());
+        Some(
+            self.dir
+                .as_ref()?
+                .join(format!("{}.json", hex::encode(digest))),
+        )
+    }
+}
+
+static CACHE: OnceLock<ResponseCache> = OnceLock::new();
+
+/// Cache REST answers for the rest of the process. Only the first call
+/// counts.
+pub fn install(cache: ResponseCache) {
+    let _ = CACHE.set(cache);
+}
+
+/// The installed cache, if any.
+pub fn global() -> Option<&'static ResponseCache> {
+    CACHE.get()
+}
+
+/// A LOCO write to `chat_id` happened: drop its cached messages and the
+/// chat list.
+pub fn invalidate_chat(chat_id: i64) {
+    if let Some(cache) = global() {
+        cache.invalidate(Scope::Chat(chat_id));
+    }
+}
+
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const PILSNER: &str = "https://talk-pilsner.kakao.com";
+    const KATALK: &str = "https://katalk.kakao.com";
+
+    #[test]
+    fn policy_follows_the_endpoint_catalog() {
+        assert_eq!(
+            policy("GET", &format!("{PILSNER}/messaging/chats?cursor=5")),
+            Some((CHATS_TTL, Scope::ChatList))
+        );
+        assert_eq!(
+            policy("GET", &format!("{PILSNER}/messaging/chats/42/messages")),
+            Some((CHATS_TTL, Scope::Chat(42)))
+        );
+        assert_eq!(
+            policy("POST", &format!("{KATALK}/mac/friends/update.json")),
+            Some((FRIENDS_TTL, Scope::Friends))
+        );
+        assert_eq!(
+            policy("POST", &format!("{KATALK}/mac/account/more_settings.json")),
+            Some((SETTINGS_TTL, Scope::Account))
+        );
+        // Mutations, token calls and unknown URLs are never cached.
+        assert_eq!(
+            policy("POST", &format!("{KATALK}/mac/friends/hide.json")),
+            None
+        );
+        assert_eq!(
+            policy("POST", &format!("{KATALK}/mac/account/renew_token.json")),
+            None
+        );
+        assert_eq!(policy("GET", "https://example.com/messaging/chats"), None);
+
+        assert_eq!(
+            invalidated_by("POST", &format!("{KATALK}/mac/friends/add_favorite.json")),
+            Some(Scope::Friends)
+        );
+        assert_eq!(
+            invalidated_by("POST", &format!("{KATALK}/mac/friends/update.json")),
+            None
+        );
+        assert_eq!(
+            invalidated_by("POST", &format!("{KATALK}/mac/profile3/edit.json")),
+            Some(Scope::Account)
+        );
+    }
+
+    #[test]
+    fn keys_separate_accounts_methods_and_bodies() {
+        let url = format!("{KATALK}/mac/profile3/friend.json");
+        let key = ResponseCache::key(1, "post", &url, Some("id=5"));
+        assert_eq!(key, ResponseCache::key(1, "POST", &url, Some("id=5")));
+        assert_ne!(key, ResponseCache::key(2, "POST", &url, Some("id=5")));
+        assert_ne!(key, ResponseCache::key(1, "POST", &url, Some("id=6")));
+        assert_ne!(key, ResponseCache::key(1, "GET", &url, Some("id=5")));
+    }
+
+    #[test]
+    fn answers_expire_after_their_ttl_and_count_hits() {
+        let cache = ResponseCache::in_memory();
+        cache.put("k", &json!({"status": 0}), CHATS_TTL, Scope::ChatList, 1000);
+        assert_eq!(cache.get("k", 1029), Some(json!({"status": 0})));
+        assert_eq!(cache.get("k", 1030), None);
+        assert_eq!(cache.get("k", 1001), None, "expired entries are dropped");
+        assert_eq!(cache.get("other", 1000), None);
+        assert_eq!(cache.stats(), (1, 3));
+    }
+
+    #[test]
+    fn a_chat_change_drops_its_answers_and_the_chat_list() {
+        let cache = ResponseCache::in_memory();
+        let value = json!({});
+        cache.put("list", &value, CHATS_TTL, Scope::ChatList, 0);
+        cache.put("chat1", &value, CHATS_TTL, Scope::Chat(1), 0);
+        cache.put("chat2", &value, CHATS_TTL, Scope::Chat(2), 0);
+        cache.put("friends", &value, FRIENDS_TTL, Scope::Friends, 0);
+
+        cache.invalidate(Scope::Chat(1));
+        assert!(cache.get("chat1", 1).is_none());
+        assert!(cache.get("list", 1).is_none());
+        assert!(cache.get("chat2", 1).is_some());
+        assert!(cache.get("friends", 1).is_some());
+
+        cache.invalidate(Scope::Friends);
+        assert!(cache.get("friends", 1).is_none());
+        assert!(cache.get("chat2", 1).is_some());
+    }
+
+    #[test]
+    fn disk_entries_outlive_the_process_until_invalidated() {
+        let dir = tempfile::tempdir().unwrap();
+        let value = json!({"chats": [1]});
+        ResponseCache::on_disk(dir.path().to_path_buf()).put(
+            "list",
+            &value,
+            CHATS_TTL,
+            Scope::ChatList,
+            100,
+        );
+        let next_run = ResponseCache::on_disk(dir.path().to_path_buf());
+        assert_eq!(next_run.get("list", 110), Some(value));
+        next_run.invalidate(Scope::Chat(9));
+        let third_run = ResponseCache::on_disk(dir.path().to_path_buf());
+        assert_eq!(third_run.get("list", 110), None);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+}
@@ -1,516 +1,4620 @@
-use std::time::Duration;
+use std::collections::{HashMap, HashSet};
+use std::io::{BufReader, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, RwLock};
+use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, Context, Result};
 use reqwest::blocking::Client;
 use reqwest::header::{
-    HeaderMap, HeaderValue, ACCEPT, ACCEPT_LANGUAGE, AUTHORIZATION, CONTENT_TYPE,
+    HeaderMap, HeaderValue, ACCEPT, ACCEPT_ENCODING, ACCEPT_LANGUAGE, AUTHORIZATION,
+    CONTENT_ENCODING, CONTENT_TYPE, RANGE, RETRY_AFTER,
 };
+use serde::Serialize;
 use serde_json::Value;
 
 use sha2::{Digest, Sha512};
 
+use crate::bandwidth::{self, ByteMeter, CountingReader};
+use crate::capture;
+use crate::cursor::{ChatListCursor, MessageCursor};
+use crate::download_pool::{Fetch, PartialFile};
+use crate::endpoints::EndpointPool;
 use crate::error::OpenKakaoError;
 use crate::model::{
-    json_i64, json_string, ChatMember, ChatMessage, ChatRoom, Friend, KakaoCredentials, MyProfile,
+    json_i64, json_string, select_window, BoardComment, BoardPage, BoardPost, ChatMember,
+    ChatMembersPage, ChatMessage, ChatRoom, Friend, Identity, KakaoCredentials, KakaoIdMatch,
+    MemberProfile, MyProfile, OpenLink, OpenLinkRef,
 };
+use crate::progress::{NoProgress, ProgressEvent, ProgressSink};
+use crate::rate_limit::{self, Pacing, RateLimitInfo};
+use crate::response_cache::{self, ResponseCache};
+use crate::retry::RetryPolicy;
 
 const BASE_URL: &str = "https://katalk.kakao.com";
 const PILSNER_URL: &str = "https://talk-pilsner.kakao.com";
+const MOIM_URL: &str = "https://talkmoim-api.kakao.com";
 
-pub struct KakaoRestClient {
-    creds: KakaoCredentials,
-    client: Client,
+/// The REST host families. An extracted token is sometimes accepted by one
+/// and rejected by another, which otherwise looks like random failures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EndpointFamily {
+    /// `katalk.kakao.com`: profile, friends, settings, chat list.
+    Katalk,
+    /// `talk-pilsner.kakao.com`: messages and members.
+    Pilsner,
+    /// `talkmoim-api.kakao.com`: the board of open chat communities.
+    Moim,
 }
 
-impl KakaoRestClient {
-    pub fn new(creds: KakaoCredentials) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(15))
-            .build()
-            .context("Failed to build HTTP client")?;
+impl EndpointFamily {
+    pub fn of(url: &str) -> Self {
+        if url.starts_with(PILSNER_URL) {
+            EndpointFamily::Pilsner
+        } else if url.starts_with(MOIM_URL) {
+            EndpointFamily::Moim
+        } else {
+            EndpointFamily::Katalk
+        }
+    }
 
-        Ok(Self { creds, client })
+    pub fn label(&self) -> &'static str {
+        match self {
+            EndpointFamily::Katalk => "katalk",
+            EndpointFamily::Pilsner => "pilsner",
+            EndpointFamily::Moim => "moim",
+        }
     }
 
-    pub fn verify_token(&self) -> Result<bool> {
-        let r = self.request_raw(
-            "POST",
-            &format!("{BASE_URL}/mac/account/more_settings.json"),
-            Some("since=0&locale_country=KR"),
-        )?;
-        Ok(json_i64(&r, "status") == 0)
+    /// What the user loses, and what to do about it, when the token is
+    /// rejected by this family.
+    pub fn unavailable_hint(&self) -> &'static str {
+        match self {
+            EndpointFamily::Katalk => {
+                "profile, friends and settings unavailable with this token; run 'openkakao-rs relogin'"
+            }
+            EndpointFamily::Pilsner => {
+                "messages unavailable with this token; re-open the chat list in the Mac app"
+            }
+            EndpointFamily::Moim => {
+                "board posts unavailable with this token; run 'openkakao-rs relogin'"
+            }
+        }
     }
+}
 
-    pub fn get_my_profile(&self) -> Result<MyProfile> {
-        let profile = self.request(
-            "POST",
-            &format!("{BASE_URL}/mac/profile3/me.json"),
-            Some("since=0"),
-        )?;
-        let settings = self.request(
-            "POST",
-            &format!("{BASE_URL}/mac/account/more_settings.json"),
-            Some("since=0&locale_country=KR"),
-        )?;
+/// One katalk, pilsner or moim call made by [`KakaoRestClient`]. The
+/// [`ENDPOINT_CATALOG`] built from these is both what `endpoints` prints and
+/// what the read-only guard consults.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EndpointSpec {
+    pub http_method: &'static str,
+    /// Path with `{chat_id}`-style placeholders, without the query string.
+    pub path: &'static str,
+    pub base: EndpointFamily,
+    /// What the call does that read-only mode forbids (it changes server
+    /// state or is visible to other users); `None` for plain reads.
+    pub side_effect: Option<&'static str>,
+    /// Public [`KakaoRestClient`] methods that send it.
+    pub methods: &'static [&'static str],
+    /// CLI commands that use it.
+    pub commands: &'static [&'static str],
+}
 
-        let p = profile.get("profile").cloned().unwrap_or(Value::Null);
+const FRIENDS_LIST_CHANGE: &str = "changing the friends list";
+const OPEN_CHAT_MEMBERSHIP: &str = "joining or leaving open chats";
 
-        Ok(MyProfile {
-            nickname: json_string(&p, "nickname"),
-            status_message: json_string(&p, "statusMessage"),
-            account_id: json_i64(&settings, "accountId"),
-            email: json_string(&settings, "emailAddress"),
-            user_id: {
-                let id = json_i64(&p, "userId");
-                if id == 0 {
-                    self.creds.user_id
-                } else {
-                    id
-                }
-            },
-            profile_image_url: json_string(&p, "fullProfileImageUrl"),
+/// Longest nickname Kakao accepts, in characters.
+pub const MAX_NICKNAME_CHARS: usize = 20;
+/// Longest status message Kakao accepts, in characters.
+pub const MAX_STATUS_MESSAGE_CHARS: usize = 60;
+
+/// Every REST endpoint the client calls. A test keeps it in step with the
+/// public methods and the URLs they build.
+pub const ENDPOINT_CATALOG: &[EndpointSpec] = &[
+    EndpointSpec {
+        http_method: "POST",
+        path: "/mac/account/more_settings.json",
+        base: EndpointFamily::Katalk,
+        side_effect: None,
+        methods: &[
+            "check_token",
+            "verify_token",
+            "probe_scope",
+            "get_my_profile",
+            "get_settings",
+        ],
+        commands: &[
+            "auth",
+            "login",
+            "doctor",
+            "me",
+            "summary",
+            "export-db",
+            "settings",
+            "health",
+            "takeout",
+        ],
+    },
+    EndpointSpec {
+        http_method: "POST",
+        path: "/mac/profile3/me.json",
+        base: EndpointFamily::Katalk,
+        side_effect: None,
+        methods: &["get_my_profile", "get_identity", "backfill_user_id"],
+        commands: &["me", "whoami", "summary", "export-db", "takeout"],
+    },
+    EndpointSpec {
+        http_method: "POST",
+        path: "/mac/profile3/edit.json",
+        base: EndpointFamily::Katalk,
+        side_effect: Some("changing your profile"),
+        methods: &["update_profile"],
+        commands: &["me"],
+    },
+    EndpointSpec {
+        http_method: "POST",
+        path: "/mac/profile3/friend.json",
+        base: EndpointFamily::Katalk,
+        side_effect: None,
+        methods: &["get_friend_profile", "get_member_profile"],
+        commands: &["profile"],
+    },
+    EndpointSpec {
+        http_method: "GET",
+        path: "/mac/profile/list.json",
+        base: EndpointFamily::Katalk,
+        side_effect: None,
+        methods: &["get_profiles"],
+        commands: &["profiles"],
+    },
+    EndpointSpec {
+        http_method: "POST",
+        path: "/mac/friends/update.json",
+        base: EndpointFamily::Katalk,
+        side_effect: None,
+        methods: &["get_friends"],
+        commands: &[
+            "friends",
+            "friend",
+            "members",
+            "dm",
+            "search",
+            "summary",
+            "unhide",
+            "export-db",
+            "takeout",
+        ],
+    },
+    EndpointSpec {
+        http_method: "POST",
+        path: "/mac/friends/find_by_uuid.json",
+        base: EndpointFamily::Katalk,
+        side_effect: Some("Kakao ID lookups, which the target may notice"),
+        methods: &["find_by_kakao_id"],
+        commands: &["friend"],
+    },
+    EndpointSpec {
+        http_method: "POST",
+        path: "/mac/friends/add_favorite.json",
+        base: EndpointFamily::Katalk,
+        side_effect: Some(FRIENDS_LIST_CHANGE),
+        methods: &["add_favorite"],
+        commands: &["favorite"],
+    },
+    EndpointSpec {
+        http_method: "POST",
+        path: "/mac/friends/remove_favorite.json",
+        base: EndpointFamily::Katalk,
+        side_effect: Some(FRIENDS_LIST_CHANGE),
+        methods: &["remove_favorite"],
+        commands: &["unfavorite"],
+    },
+    EndpointSpec {
+        http_method: "POST",
+        path: "/mac/friends/hide.json",
+        base: EndpointFamily::Katalk,
+        side_effect: Some(FRIENDS_LIST_CHANGE),
+        methods: &["hide_friend"],
+        commands: &["hide"],
+    },
+    EndpointSpec {
+        http_method: "POST",
+        path: "/mac/friends/unhide.json",
+        base: EndpointFamily::Katalk,
+        side_effect: Some(FRIENDS_LIST_CHANGE),
+        methods: &["unhide_friend"],
+        commands: &["unhide"],
+    },
+    EndpointSpec {
+        http_method: "GET",
+        path: "/mac/alarm_keywords/list.json",
+        base: EndpointFamily::Katalk,
+        side_effect: None,
+        methods: &["get_alarm_keywords"],
+        commands: &["keywords"],
+    },
+    EndpointSpec {
+        http_method: "POST",
+        path: "/mac/account/renew_token.json",
+        base: EndpointFamily::Katalk,
+        side_effect: None,
+        methods: &["renew_token"],
+        commands: &["renew", "relogin"],
+    },
+    EndpointSpec {
+        http_method: "POST",
+        path: "/mac/account/oauth2_token.json",
+        base: EndpointFamily::Katalk,
+        side_effect: None,
+        methods: &["oauth2_token"],
+        commands: &["renew", "relogin"],
+    },
+    EndpointSpec {
+        http_method: "POST",
+        path: "/mac/account/login.json",
+        base: EndpointFamily::Katalk,
+        side_effect: None,
+        methods: &["login_direct", "login_with_xvc"],
+        commands: &["relogin"],
+    },
+    EndpointSpec {
+        http_method: "POST",
+        path: "/mac/scrap/preview.json",
+        base: EndpointFamily::Katalk,
+        side_effect: None,
+        methods: &["get_scrap_preview"],
+        commands: &["scrap", "links"],
+    },
+    EndpointSpec {
+        http_method: "POST",
+        path: "/mac/openlink/info.json",
+        base: EndpointFamily::Katalk,
+        side_effect: None,
+        methods: &["get_open_link"],
+        commands: &["openchat"],
+    },
+    EndpointSpec {
+        http_method: "POST",
+        path: "/mac/openlink/join.json",
+        base: EndpointFamily::Katalk,
+        side_effect: Some(OPEN_CHAT_MEMBERSHIP),
+        methods: &["join_open_link"],
+        commands: &["openchat"],
+    },
+    EndpointSpec {
+        http_method: "POST",
+        path: "/mac/openlink/leave.json",
+        base: EndpointFamily::Katalk,
+        side_effect: Some(OPEN_CHAT_MEMBERSHIP),
+        methods: &["leave_open_chat"],
+        commands: &["openchat"],
+    },
+    EndpointSpec {
+        http_method: "GET",
+        path: "/messaging/chats",
+        base: EndpointFamily::Pilsner,
+        side_effect: None,
+        methods: &[
+            "verify_pilsner_token",
+            "probe_scope",
+            "get_chats",
+            "get_all_chats",
+        ],
+        commands: &[
+            "auth",
+            "doctor",
+            "chats",
+            "chat",
+            "dm",
+            "unread",
+            "summary",
+            "export",
+            "export-db",
+            "cache",
+            "search",
+            "mark-read",
+            "tui",
+            "takeout",
+        ],
+    },
+    EndpointSpec {
+        http_method: "GET",
+        path: "/messaging/chats/{chat_id}/members",
+        base: EndpointFamily::Pilsner,
+        side_effect: None,
+        methods: &[
+            "get_chat_members",
+            "get_chat_members_page",
+            "get_chat_members_limited",
+        ],
+        commands: &[
+            "members",
+            "chat",
+            "read",
+            "files",
+            "links",
+            "search",
+            "export",
+            "export-db",
+            "tui",
+            "takeout",
+        ],
+    },
+    EndpointSpec {
+        http_method: "GET",
+        path: "/messaging/chats/{chat_id}/messages",
+        base: EndpointFamily::Pilsner,
+        side_effect: None,
+        methods: &[
+            "get_messages",
+            "get_messages_with_options",
+            "get_recent_messages",
+            "get_all_messages",
+            "get_all_messages_with_progress",
+        ],
+        commands: &[
+            "read",
+            "members",
+            "files",
+            "links",
+            "search",
+            "stats",
+            "export",
+            "mark-read",
+            "tui",
+            "takeout",
+        ],
+    },
+    EndpointSpec {
+        http_method: "GET",
+        path: "/chats/{chat_id}/posts",
+        base: EndpointFamily::Moim,
+        side_effect: None,
+        methods: &["get_board_posts"],
+        commands: &["board"],
+    },
+    EndpointSpec {
+        http_method: "GET",
+        path: "/posts/{post_id}",
+        base: EndpointFamily::Moim,
+        side_effect: None,
+        methods: &["get_board_post"],
+        commands: &["board"],
+    },
+    EndpointSpec {
+        http_method: "GET",
+        path: "/posts/{post_id}/comments",
+        base: EndpointFamily::Moim,
+        side_effect: None,
+        methods: &["get_board_comments"],
+        commands: &["board"],
+    },
+];
+
+impl EndpointSpec {
+    /// Whether `url` (any host, with or without a query) is this endpoint.
+    pub fn matches(&self, http_method: &str, url: &str) -> bool {
+        if !self.http_method.eq_ignore_ascii_case(http_method)
+            || EndpointFamily::of(url) != self.base
+        {
+            return false;
+        }
+        let path = url
+            .split_once("://")
+            .map_or(url, |(_, rest)| rest.find('/').map_or("", |i| &rest[i..]));
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        let mut want = self.path.split('/');
+        let mut got = path.split('/');
+        loop {
+            match (want.next(), got.next()) {
+                (None, None) => return true,
+                (Some(w), Some(g)) if w == g || (w.starts_with('{') && !g.is_empty()) => {}
+                _ => return false,
+            }
+        }
+    }
+
+    /// The catalog entry as printed by `endpoints --json`.
+    pub fn to_json(&self) -> Value {
+        serde_json::json!({
+            "http_method": self.http_method,
+            "path": self.path,
+            "base": self.base,
+            "mutates": self.side_effect.is_some(),
+            "side_effect": self.side_effect,
+            "methods": self.methods,
+            "commands": self.commands,
         })
     }
+}
 
-    pub fn get_friend_profile(&self, user_id: i64) -> Result<Value> {
-        self.request(
-            "POST",
-            &format!("{BASE_URL}/mac/profile3/friend.json"),
-            Some(&format!("id={user_id}")),
-        )
+/// The catalog entry for a request, if the client knows it.
+pub fn endpoint_for(http_method: &str, url: &str) -> Option<&'static EndpointSpec> {
+    ENDPOINT_CATALOG
+        .iter()
+        .find(|spec| spec.matches(http_method, url))
+}
+
+/// What the public client method `method` does that read-only mode forbids,
+/// from the catalog.
+pub fn side_effect_of(method: &str) -> Option<&'static str> {
+    ENDPOINT_CATALOG
+        .iter()
+        .filter(|spec| spec.methods.contains(&method))
+        .find_map(|spec| spec.side_effect)
+}
+
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// Refuse, before sending, every request whose catalog entry has a side
+/// effect.
+pub fn set_read_only(read_only: bool) {
+    READ_ONLY.store(read_only, Ordering::Relaxed);
+}
+
+fn check_read_only(http_method: &str, url: &str) -> Result<()> {
+    if !READ_ONLY.load(Ordering::Relaxed) {
+        return Ok(());
     }
+    match endpoint_for(http_method, url).and_then(|spec| spec.side_effect) {
+        Some(effect) => Err(anyhow!(
+            "Read-only mode forbids {effect} ({http_method} {}).",
+            redact_endpoint(url)
+        )),
+        None => Ok(()),
+    }
+}
 
-    pub fn get_profiles(&self) -> Result<Value> {
-        self.request("GET", &format!("{BASE_URL}/mac/profile/list.json"), None)
+/// Whether a request may be sent again after a 5xx: catalogued endpoints
+/// without side effects, and uncatalogued GETs.
+fn repeatable_after_server_error(http_method: &str, url: &str) -> bool {
+    match endpoint_for(http_method, url) {
+        Some(spec) => spec.side_effect.is_none(),
+        None => http_method == "GET",
     }
+}
 
-    pub fn get_friends(&self) -> Result<Vec<Friend>> {
-        let r = self.request(
-            "POST",
-            &format!("{BASE_URL}/mac/friends/update.json"),
-            Some("since=0"),
-        )?;
+/// A `Retry-After` value in seconds; the HTTP-date form is left to the
+/// backoff.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    value.trim().parse().ok().map(Duration::from_secs)
+}
 
-        let mut out = Vec::new();
-        if let Some(arr) = r.get("friends").and_then(Value::as_array) {
-            for item in arr {
-                out.push(Friend::from_json(item));
+/// What the server said about a token, from [`KakaoRestClient::check_token`].
+/// Only `Invalid` is a verdict on the token; the other failures are
+/// transient and say nothing about it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum TokenStatus {
+    Valid,
+    /// Kakao answered and refused the token (expired, other device, ...).
+    Invalid {
+        kakao_status: i64,
+        message: String,
+    },
+    /// Kakao asked to slow down: HTTP 429 or a spam/temporary-restriction status.
+    RateLimited,
+    /// No usable answer: connection failure, timeout, 5xx or maintenance.
+    NetworkError {
+        message: String,
+    },
+}
+
+impl TokenStatus {
+    /// One line for `auth` and `login`.
+    pub fn reason(&self) -> String {
+        match self {
+            TokenStatus::Valid => "valid".into(),
+            TokenStatus::Invalid {
+                kakao_status,
+                message,
+            } => {
+                let status = crate::status::KakaoStatus::from_code(*kakao_status);
+                let detail = if message.is_empty() {
+                    status.explanation().to_string()
+                } else {
+                    message.clone()
+                };
+                format!("rejected (status {kakao_status}): {detail}")
             }
-        } else if let Some(arr) = r.get("added").and_then(Value::as_array) {
-            for item in arr {
-                out.push(Friend::from_json(item));
+            TokenStatus::RateLimited => {
+                "rate limited by Kakao; the token was not checked, try again later".into()
+            }
+            TokenStatus::NetworkError { message } => {
+                format!("could not reach Kakao, the token was not checked: {message}")
             }
         }
+    }
+}
 
-        Ok(out)
+/// Map the `more_settings` answer (or failure) to a [`TokenStatus`].
+fn token_status(result: Result<Value, OpenKakaoError>) -> TokenStatus {
+    use crate::status::KakaoStatus;
+    let (status, message) = match result {
+        Ok(value) => (json_i64(&value, "status"), json_string(&value, "message")),
+        Err(OpenKakaoError::RestApi { status, message }) => (status, message),
+        Err(OpenKakaoError::Unauthorized { message }) => (-401, message),
+        Err(OpenKakaoError::Forbidden { message }) => (-403, message),
+        Err(OpenKakaoError::RateLimited { .. }) => return TokenStatus::RateLimited,
+        Err(http @ OpenKakaoError::Http { status, .. }) if status < 500 => {
+            return TokenStatus::Invalid {
+                kakao_status: -(status as i64),
+                message: http.to_string(),
+            }
+        }
+        // Network failures, 5xx and bodies that are not JSON say nothing
+        // about the token.
+        Err(err) => {
+            return TokenStatus::NetworkError {
+                message: format!("{err:#}"),
+            }
+        }
+    };
+    match KakaoStatus::from_code(status) {
+        KakaoStatus::Success => TokenStatus::Valid,
+        KakaoStatus::SpamLimited | KakaoStatus::TemporarilyRestricted => TokenStatus::RateLimited,
+        known @ (KakaoStatus::ServerError | KakaoStatus::Maintenance) => {
+            TokenStatus::NetworkError {
+                message: known.explanation().into(),
+            }
+        }
+        _ => TokenStatus::Invalid {
+            kakao_status: status,
+            message,
+        },
     }
+}
 
-    pub fn add_favorite(&self, user_id: i64) -> Result<Value> {
-        self.request(
-            "POST",
-            &format!("{BASE_URL}/mac/friends/add_favorite.json"),
-            Some(&format!("id={user_id}")),
-        )
+/// Per-family validity of one credential. `None` means not probed, or the
+/// probe failed for a reason other than the token (network, server error).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct TokenScope {
+    pub katalk: Option<bool>,
+    pub pilsner: Option<bool>,
+}
+
+impl TokenScope {
+    pub fn get(&self, family: EndpointFamily) -> Option<bool> {
+        match family {
+            EndpointFamily::Katalk => self.katalk,
+            EndpointFamily::Pilsner => self.pilsner,
+            // Never probed: the board is reached only by `board`.
+            EndpointFamily::Moim => None,
+        }
     }
 
-    pub fn remove_favorite(&self, user_id: i64) -> Result<Value> {
-        self.request(
-            "POST",
-            &format!("{BASE_URL}/mac/friends/remove_favorite.json"),
-            Some(&format!("id={user_id}")),
+    /// Usable for everything: neither family is known to reject the token.
+    pub fn is_complete(&self) -> bool {
+        self.katalk == Some(true) && self.pilsner != Some(false)
+    }
+
+    /// Families known to reject the token.
+    pub fn rejected(&self) -> Vec<EndpointFamily> {
+        [EndpointFamily::Katalk, EndpointFamily::Pilsner]
+            .into_iter()
+            .filter(|family| self.get(*family) == Some(false))
+            .collect()
+    }
+
+    /// `katalk=ok, pilsner=rejected` for `auth` and `doctor`.
+    pub fn summary(&self) -> String {
+        let label = |valid: Option<bool>| match valid {
+            Some(true) => "ok",
+            Some(false) => "rejected",
+            None => "unknown",
+        };
+        format!(
+            "katalk={}, pilsner={}",
+            label(self.katalk),
+            label(self.pilsner)
         )
     }
+}
 
-    pub fn hide_friend(&self, user_id: i64) -> Result<Value> {
-        self.request(
-            "POST",
-            &format!("{BASE_URL}/mac/friends/hide.json"),
-            Some(&format!("id={user_id}")),
+fn with_cursor(url: String, cursor: Option<&str>) -> String {
+    match cursor {
+        Some(c) => format!("{url}?cursor={}", urlencoding::encode(c)),
+        None => url,
+    }
+}
+
+/// Token validity per (family, token digest), learned from probes and from
+/// ordinary responses. Lives for one run so probing happens at most once.
+static TOKEN_SCOPES: OnceLock<Mutex<HashMap<(EndpointFamily, String), bool>>> = OnceLock::new();
+/// Families already warned about this run.
+static SCOPE_WARNED: Mutex<Vec<EndpointFamily>> = Mutex::new(Vec::new());
+
+fn token_scopes() -> std::sync::MutexGuard<'static, HashMap<(EndpointFamily, String), bool>> {
+    TOKEN_SCOPES
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn token_digest(token: &str) -> String {
+    hex::encode(Sha512::digest(token.as_bytes()))
+}
+
+/// Pause between member pages so bulk member fetches stay gentle on pilsner.
+const MEMBER_PAGE_DELAY: Duration = Duration::from_millis(100);
+
+/// Timeout for short, repeated fetches (follow-style polling) so a stalled
+/// connection costs one poll rather than the full client default.
+pub const POLL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Client-wide request timeout unless [`HttpOptions::timeout`] says otherwise.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Per-request overrides for the shared HTTP client.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RequestOptions {
+    /// Replaces the client-wide timeout for this request only.
+    pub timeout: Option<Duration>,
+}
+
+impl RequestOptions {
+    pub fn polling() -> Self {
+        Self {
+            timeout: Some(POLL_TIMEOUT),
+        }
+    }
+}
+
+/// How long [`KakaoRestClient`] keeps retrying one request by default.
+pub const DEFAULT_RETRY_DEADLINE: Duration = Duration::from_secs(60);
+
+/// Binary downloads (media, profile images) can be large; allow well beyond
+/// the 15s client default.
+pub const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// What [`KakaoRestClient::download`] received.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadInfo {
+    pub content_type: Option<String>,
+    /// `Content-Length` as announced by the server, if any.
+    pub content_length: Option<u64>,
+    /// Bytes actually written.
+    pub bytes: u64,
+    /// URL after redirects.
+    pub final_url: String,
+}
+
+/// Default cap on a REST response body.
+pub const DEFAULT_MAX_BODY_BYTES: usize = 4 * 1024 * 1024;
+
+/// Endpoints whose payloads grow with the account (friends, message pages,
+/// member lists) get this multiple of the regular cap.
+const LARGE_BODY_MULTIPLIER: usize = 8;
+
+static MAX_BODY_BYTES: OnceLock<usize> = OnceLock::new();
+
+/// Set the response size cap used by clients created after this call.
+pub fn set_max_body_bytes(max_body_bytes: usize) {
+    let _ = MAX_BODY_BYTES.set(max_body_bytes);
+}
+
+/// Space every REST request and download started after this call at least
+/// `interval` apart, across threads. Batch downloads set it so parallel
+/// workers and the API calls around them share one request rate. Clients
+/// widen it further when responses carry rate-limit hints.
+pub fn set_request_interval(interval: Duration) {
+    rate_limit::global().set_base(interval);
+}
+
+/// The adaptive request interval and the last rate-limit hints seen, for
+/// `--stats`.
+pub fn rate_limit_summary() -> String {
+    rate_limit::global().summary()
+}
+
+/// Connection settings for [`KakaoRestClient`]. Fields left `None` keep the
+/// defaults: [`DEFAULT_TIMEOUT`], the system proxy settings, the user agent
+/// from the credentials and the Kakao hosts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HttpOptions {
+    pub timeout: Option<Duration>,
+    pub connect_timeout: Option<Duration>,
+    /// `http://`, `https://`, `socks5://` or `socks5h://` (names resolved by
+    /// the proxy), with `user:password@` when it needs them.
+    pub proxy: Option<String>,
+    /// Sent instead of the credentials' user agent, except by
+    /// [`KakaoRestClient::login_direct`], whose X-VC is derived from it.
+    pub user_agent: Option<String>,
+    /// Replaces `https://katalk.kakao.com`. Must be an https Kakao host or a
+    /// loopback address (a local mock server); a path prefix is kept.
+    pub base_url: Option<String>,
+    /// Replaces `https://talk-pilsner.kakao.com`, like `base_url`.
+    pub pilsner_url: Option<String>,
+}
+
+impl HttpOptions {
+    /// `OPENKAKAO_PROXY`, `OPENKAKAO_TIMEOUT` (seconds), `OPENKAKAO_BASE_URL`
+    /// and `OPENKAKAO_PILSNER_URL`. Unset or empty variables stay `None`.
+    pub fn from_env() -> Result<Self, OpenKakaoError> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    fn from_vars(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, OpenKakaoError> {
+        let var = |name: &str| {
+            lookup(name)
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let timeout = match var("OPENKAKAO_TIMEOUT") {
+            Some(secs) => {
+                Some(parse_timeout_secs(&secs).map_err(|err| anyhow!("OPENKAKAO_TIMEOUT: {err}"))?)
+            }
+            None => None,
+        };
+        Ok(Self {
+            timeout,
+            proxy: var("OPENKAKAO_PROXY"),
+            base_url: var("OPENKAKAO_BASE_URL"),
+            pilsner_url: var("OPENKAKAO_PILSNER_URL"),
+            ..Self::default()
+        })
+    }
+
+    /// Reject a malformed proxy, user agent or base URL up front rather than
+    /// when the first client is built.
+    pub fn check(&self) -> Result<(), OpenKakaoError> {
+        self.bases()?;
+        if let Some(proxy) = &self.proxy {
+            proxy_for(proxy)?;
+        }
+        if let Some(user_agent) = &self.user_agent {
+            HeaderValue::from_str(user_agent).context("Invalid User-Agent override")?;
+        }
+        Ok(())
+    }
+
+    /// The katalk and pilsner bases requests go to.
+    fn bases(&self) -> Result<(String, String), OpenKakaoError> {
+        let base = |url: &Option<String>, default: &str| match url {
+            Some(url) => override_base(url, default),
+            None => Ok(default.to_string()),
+        };
+        Ok((
+            base(&self.base_url, BASE_URL)?,
+            base(&self.pilsner_url, PILSNER_URL)?,
+        ))
+    }
+
+    /// `self`, with the fields it leaves unset taken from `fallback`.
+    pub fn or(self, fallback: &HttpOptions) -> Self {
+        Self {
+            timeout: self.timeout.or(fallback.timeout),
+            connect_timeout: self.connect_timeout.or(fallback.connect_timeout),
+            proxy: self.proxy.or_else(|| fallback.proxy.clone()),
+            user_agent: self.user_agent.or_else(|| fallback.user_agent.clone()),
+            base_url: self.base_url.or_else(|| fallback.base_url.clone()),
+            pilsner_url: self.pilsner_url.or_else(|| fallback.pilsner_url.clone()),
+        }
+    }
+}
+
+/// A timeout in seconds as `--timeout` and `OPENKAKAO_TIMEOUT` take it:
+/// a positive number, fractions allowed (`2.5`).
+pub fn parse_timeout_secs(input: &str) -> Result<Duration, String> {
+    let secs: f64 = input
+        .trim()
+        .trim_end_matches('s')
+        .parse()
+        .map_err(|_| format!("invalid timeout '{input}': expected seconds, e.g. 30 or 2.5"))?;
+    if secs <= 0.0 {
+        return Err(format!("invalid timeout '{input}': must be more than 0"));
+    }
+    Duration::try_from_secs_f64(secs).map_err(|_| format!("invalid timeout '{input}': too large"))
+}
+
+static HTTP_OPTIONS: OnceLock<HttpOptions> = OnceLock::new();
+
+/// Use `options` for whatever the builder of a client created after this
+/// call leaves unset, [`KakaoRestClient::new`] included. Only the first call
+/// counts. The CLI installs its `--proxy`/`--timeout`/`--base-url` flags and
+/// [`HttpOptions::from_env`] here.
+pub fn set_http_options(options: HttpOptions) {
+    let _ = HTTP_OPTIONS.set(options);
+}
+
+fn proxy_for(url: &str) -> Result<reqwest::Proxy, OpenKakaoError> {
+    let scheme = url.split_once("://").map_or("", |(scheme, _)| scheme);
+    if !matches!(scheme, "http" | "https" | "socks5" | "socks5h") {
+        return Err(anyhow!(
+            "Proxy must be an http://, https://, socks5:// or socks5h:// URL: {url}"
         )
+        .into());
     }
+    Ok(reqwest::Proxy::all(url).with_context(|| format!("Invalid proxy URL: {url}"))?)
+}
 
-    pub fn unhide_friend(&self, user_id: i64) -> Result<Value> {
-        self.request(
-            "POST",
-            &format!("{BASE_URL}/mac/friends/unhide.json"),
-            Some(&format!("id={user_id}")),
+/// `url` as a replacement for `default`, without a trailing slash: an https
+/// Kakao host, or any loopback address so tests can point at a mock server.
+fn override_base(url: &str, default: &str) -> Result<String, OpenKakaoError> {
+    let parsed = reqwest::Url::parse(url).with_context(|| format!("Invalid base URL: {url}"))?;
+    let host = parsed.host_str().unwrap_or("");
+    let loopback = host == "localhost"
+        || host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback());
+    let allowed = match parsed.scheme() {
+        "https" => loopback || is_kakao_host(host),
+        "http" => loopback,
+        _ => false,
+    };
+    if !allowed || parsed.query().is_some() {
+        return Err(anyhow!(
+            "Replacement for {default} must be an https Kakao host or a loopback address: {url}"
         )
+        .into());
+    }
+    Ok(url.trim_end_matches('/').to_string())
+}
+
+/// Side effects the library leaves to its host. The CLI installs one to
+/// record restriction signs, print coded warnings and progress notes, and
+/// dump payloads under `--verbose`; without one these events are dropped.
+pub trait RestObserver: Send + Sync {
+    /// A JSON answer carried a non-zero Kakao `status`. `source` is the
+    /// endpoint path with ids redacted, or `verify` for
+    /// [`KakaoRestClient::check_token`].
+    fn status(&self, _source: &str, _status: i64) {}
+    /// A non-zero status is about to be returned as an error; `payload` is
+    /// the raw answer.
+    fn error_payload(&self, _url: &str, _status: i64, _payload: &Value) {}
+    /// This run already knows the token is rejected by `family`; called
+    /// once per family, before the next request to it.
+    fn scope_rejected(&self, _family: EndpointFamily) {}
+    /// `method` on `endpoint` (ids redacted) failed with `error`; retry
+    /// `attempt` of `retries` follows after `wait`.
+    fn retry(
+        &self,
+        _method: &str,
+        _endpoint: &str,
+        _error: &anyhow::Error,
+        _attempt: u32,
+        _retries: u32,
+        _wait: Duration,
+    ) {
+    }
+    /// The saved token was rejected; the client is recovering a new one
+    /// before repeating the request.
+    fn token_recovering(&self) {}
+    /// The katalk mirror `base` could not be reached; the request moves on
+    /// to the next one.
+    fn mirror_failed(&self, _base: &str, _error: &anyhow::Error) {}
+}
+
+static OBSERVER: OnceLock<Box<dyn RestObserver>> = OnceLock::new();
+
+/// Install the process-wide [`RestObserver`]. Only the first call counts.
+pub fn set_observer(observer: Box<dyn RestObserver>) {
+    let _ = OBSERVER.set(observer);
+}
+
+fn observer() -> Option<&'static dyn RestObserver> {
+    OBSERVER.get().map(|observer| observer.as_ref())
+}
+
+/// `url`'s path with numeric segments (chat and user ids) replaced by
+/// `:id`, so observations can be grouped and shared without identifiers.
+pub fn redact_endpoint(url: &str) -> String {
+    let path = url
+        .split_once("://")
+        .map_or(url, |(_, rest)| rest.find('/').map_or("", |i| &rest[i..]));
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    path.split('/')
+        .map(|segment| {
+            if !segment.is_empty() && segment.chars().all(|c| c.is_ascii_digit() || c == '-') {
+                ":id"
+            } else {
+                segment
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+static ENDPOINTS: OnceLock<Mutex<EndpointPool>> = OnceLock::new();
+
+/// How long a mirror gets to answer the first-use latency probe.
+const ENDPOINT_PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Use `[http] base_urls` for katalk requests. Each must be an https Kakao
+/// host. An empty list, or just the default URL, keeps today's direct path.
+pub fn set_base_urls(base_urls: &[String]) -> Result<(), OpenKakaoError> {
+    for base in base_urls {
+        let parsed =
+            reqwest::Url::parse(base).with_context(|| format!("Invalid base URL: {base}"))?;
+        let host = parsed.host_str().unwrap_or("");
+        if parsed.scheme() != "https" || !is_kakao_host(host) {
+            return Err(anyhow!("base_urls entry must be an https Kakao host: {base}").into());
+        }
+    }
+    let pool = EndpointPool::new(base_urls);
+    if pool.bases().is_empty() || pool.bases() == [BASE_URL] {
+        return Ok(());
+    }
+    let _ = ENDPOINTS.set(Mutex::new(pool));
+    Ok(())
+}
+
+/// Mirror latencies, the active one and failovers, for `--stats`; `None`
+/// when no mirrors are configured.
+pub fn endpoint_summary() -> Option<String> {
+    ENDPOINTS.get().map(|pool| lock_pool(pool).summary())
+}
+
+fn lock_pool(pool: &Mutex<EndpointPool>) -> MutexGuard<'_, EndpointPool> {
+    pool.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Whether another mirror may serve a request that failed with `err`. A
+/// refused or reset connection never reached the server. A timeout or a
+/// broken body may have, so only `repeatable` requests move on after one.
+fn may_fail_over(err: &anyhow::Error, repeatable: bool) -> bool {
+    err.chain()
+        .any(|cause| match cause.downcast_ref::<reqwest::Error>() {
+            Some(e) => e.is_connect() || (repeatable && e.is_timeout()),
+            None => {
+                repeatable
+                    && matches!(
+                        cause.downcast_ref::<OpenKakaoError>(),
+                        Some(OpenKakaoError::Network {
+                            is_transient: true,
+                            ..
+                        })
+                    )
+            }
+        })
+}
+
+/// How much of a response is kept for parse-error diagnostics.
+const SNIPPET_BYTES: usize = 8 * 1024;
+
+/// Deferred credential verification for [`KakaoRestClient::with_reauth`].
+pub trait Reauth: Send {
+    /// The unverified credentials were accepted by a request.
+    fn confirmed(&self) {}
+    /// The server rejected them; produce replacements (or fail).
+    fn recover(self: Box<Self>) -> Result<KakaoCredentials>;
+}
+
+/// Whether `result` says the server rejected the credentials: an auth
+/// status in the JSON body, HTTP 401/403 or pilsner's `UNAUTHENTICATED`.
+/// Pilsner and moim also turn down valid tokens that lack their scope, so
+/// their rejections are confirmed against katalk before recovering.
+fn rejects_credentials(result: &Result<Value>) -> bool {
+    match result {
+        Ok(value) => value
+            .get("status")
+            .and_then(Value::as_i64)
+            .is_some_and(|status| {
+                OpenKakaoError::RestApi {
+                    status,
+                    message: String::new(),
+                }
+                .is_auth_failure()
+            }),
+        Err(err) => err
+            .downcast_ref::<OpenKakaoError>()
+            .is_some_and(|e| e.is_auth_failure() || matches!(e.status_code(), Some(-401 | -403))),
+    }
+}
+
+/// Use rest_token for pilsner endpoints, oauth_token for katalk endpoints.
+fn token_of(creds: &KakaoCredentials, family: EndpointFamily) -> String {
+    match family {
+        EndpointFamily::Pilsner => creds
+            .rest_token
+            .clone()
+            .unwrap_or_else(|| creds.oauth_token.clone()),
+        EndpointFamily::Katalk | EndpointFamily::Moim => creds.oauth_token.clone(),
+    }
+}
+
+pub struct KakaoRestClient {
+    /// Swapped whole by the re-auth hook or [`Self::replace_credentials`];
+    /// each request builds its headers from one snapshot.
+    creds: RwLock<Arc<KakaoCredentials>>,
+    client: Client,
+    max_body_bytes: usize,
+    /// Pending until the first request settles whether the credentials work.
+    reauth: Mutex<Option<Box<dyn Reauth>>>,
+    retry_policy: RetryPolicy,
+    retry_deadline: Duration,
+    /// Where response bytes are counted; [`bandwidth::global`] by default.
+    meter: Arc<ByteMeter>,
+    /// Request spacing; [`rate_limit::global`] by default.
+    pacing: Arc<Pacing>,
+    /// Where katalk and pilsner requests go; the Kakao hosts by default.
+    katalk_base: String,
+    pilsner_base: String,
+    user_agent: Option<String>,
+    #[cfg(feature = "fault-injection")]
+    transport: Option<Box<dyn crate::fault::Transport>>,
+}
+
+/// A [`KakaoRestClient`] with connection settings other than the defaults.
+/// Settings not given here come from [`set_http_options`], if it was
+/// called.
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use openkakao_rs::model::KakaoCredentials;
+/// use openkakao_rs::rest::KakaoRestClient;
+///
+/// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+/// # let creds = KakaoCredentials::new("t".into(), 1, "d".into(), "26.1.0".into(), String::new(), String::new());
+/// let client = KakaoRestClient::builder(creds)
+///     .proxy("socks5h://127.0.0.1:1080")
+///     .timeout(Duration::from_secs(30))
+///     .connect_timeout(Duration::from_secs(5))
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[must_use]
+pub struct KakaoRestClientBuilder {
+    creds: KakaoCredentials,
+    options: HttpOptions,
+}
+
+impl KakaoRestClientBuilder {
+    /// Whole-request timeout (default 15s).
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+
+    /// Time allowed to establish the connection (default: no separate limit).
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.options.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Send everything, downloads included, through this proxy; see
+    /// [`HttpOptions::proxy`].
+    pub fn proxy(mut self, url: impl Into<String>) -> Self {
+        self.options.proxy = Some(url.into());
+        self
+    }
+
+    /// See [`HttpOptions::user_agent`].
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.options.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Send katalk requests here; see [`HttpOptions::base_url`].
+    pub fn base_url(mut self, url: impl Into<String>) -> Self {
+        self.options.base_url = Some(url.into());
+        self
+    }
+
+    /// Send pilsner (messages, members) requests here.
+    pub fn pilsner_url(mut self, url: impl Into<String>) -> Self {
+        self.options.pilsner_url = Some(url.into());
+        self
+    }
+
+    /// Take whatever has not been set yet from `options`, e.g.
+    /// [`HttpOptions::from_env`].
+    pub fn options(mut self, options: HttpOptions) -> Self {
+        self.options = self.options.or(&options);
+        self
+    }
+
+    pub fn build(self) -> Result<KakaoRestClient, OpenKakaoError> {
+        let options = match HTTP_OPTIONS.get() {
+            Some(process) => self.options.or(process),
+            None => self.options,
+        };
+        options.check()?;
+        let (katalk_base, pilsner_base) = options.bases()?;
+
+        let mut http = Client::builder().timeout(options.timeout.unwrap_or(DEFAULT_TIMEOUT));
+        if let Some(timeout) = options.connect_timeout {
+            http = http.connect_timeout(timeout);
+        }
+        if let Some(proxy) = &options.proxy {
+            http = http.proxy(proxy_for(proxy)?);
+        }
+        let client = http.build().context("Failed to build HTTP client")?;
+
+        Ok(KakaoRestClient {
+            creds: RwLock::new(Arc::new(self.creds)),
+            client,
+            max_body_bytes: MAX_BODY_BYTES
+                .get()
+                .copied()
+                .unwrap_or(DEFAULT_MAX_BODY_BYTES),
+            reauth: Mutex::new(None),
+            retry_policy: RetryPolicy::default(),
+            retry_deadline: DEFAULT_RETRY_DEADLINE,
+            meter: bandwidth::global(),
+            pacing: rate_limit::global(),
+            katalk_base,
+            pilsner_base,
+            user_agent: options.user_agent,
+            #[cfg(feature = "fault-injection")]
+            transport: None,
+        })
+    }
+}
+
+impl KakaoRestClient {
+    /// A client for `creds`. Nothing is sent until the first request.
+    ///
+    /// ```no_run
+    /// use openkakao_rs::model::KakaoCredentials;
+    /// use openkakao_rs::rest::KakaoRestClient;
+    ///
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// let creds = KakaoCredentials::new(
+    ///     "token-device-uuid".into(),
+    ///     42,
+    ///     "device-uuid".into(),
+    ///     "26.1.0".into(),
+    ///     "KT/26.1.0 Mc/26.1.0 ko".into(),
+    ///     "mac/26.1.0/ko".into(),
+    /// );
+    /// let client = KakaoRestClient::new(creds)?;
+    /// for chat in client.get_all_chats()? {
+    ///     println!("{} {}", chat.chat_id, chat.display_title());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(creds: KakaoCredentials) -> Result<Self, OpenKakaoError> {
+        Self::builder(creds).build()
+    }
+
+    /// Start a client for `creds` with a different timeout, proxy, user
+    /// agent or hosts; see [`KakaoRestClientBuilder`].
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use std::time::Duration;
+    /// use openkakao_rs::rest::KakaoRestClient;
+    /// use openkakao_rs::testing;
+    ///
+    /// let client = KakaoRestClient::builder(testing::credentials())
+    ///     .timeout(Duration::from_secs(30))
+    ///     .user_agent("my-tool/1.0")
+    ///     .build()?;
+    /// assert_eq!(client.user_id(), testing::USER_ID);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn builder(creds: KakaoCredentials) -> KakaoRestClientBuilder {
+        KakaoRestClientBuilder {
+            creds,
+            options: HttpOptions::default(),
+        }
+    }
+
+    /// Use the credentials unverified instead of spending a request on a
+    /// token check up front. The first request that gets an answer settles
+    /// it: success calls [`Reauth::confirmed`]; an auth error (-950 or HTTP
+    /// 401/403; from pilsner or moim only once katalk rejects the token
+    /// too) calls [`Reauth::recover`] once,
+    /// swaps in the credentials it returns and retries that request.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::model::KakaoCredentials;
+    /// use openkakao_rs::rest::Reauth;
+    /// use openkakao_rs::testing;
+    ///
+    /// // Log in again if the saved token turns out to be stale.
+    /// struct LogInAgain;
+    ///
+    /// impl Reauth for LogInAgain {
+    ///     fn recover(self: Box<Self>) -> anyhow::Result<KakaoCredentials> {
+    ///         Ok(testing::credentials())
+    ///     }
+    /// }
+    ///
+    /// let client = testing::client().with_reauth(Box::new(LogInAgain));
+    /// assert_eq!(client.get_identity()?.user_id, testing::USER_ID);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_reauth(self, reauth: Box<dyn Reauth>) -> Self {
+        *self.reauth.lock().unwrap_or_else(|e| e.into_inner()) = Some(reauth);
+        self
+    }
+
+    /// Retry HTTP 429 and 5xx answers per `policy` instead of
+    /// [`RetryPolicy::default`]: up to `max_attempts` tries, waiting what
+    /// `Retry-After` asks for or `backoff_base` doubled each time (with
+    /// jitter). Only requests without side effects are repeated after a
+    /// 5xx; a 429 means the request was not processed, so any is.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use openkakao_rs::model::KakaoCredentials;
+    /// use openkakao_rs::rest::KakaoRestClient;
+    /// use openkakao_rs::retry::RetryPolicy;
+    ///
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// # let creds = KakaoCredentials::new("t".into(), 1, "d".into(), "26.1.0".into(), String::new(), String::new());
+    /// let client = KakaoRestClient::new(creds)?
+    ///     .with_retry_policy(RetryPolicy {
+    ///         max_attempts: 6,
+    ///         backoff_base: Duration::from_secs(1),
+    ///         ..RetryPolicy::default()
+    ///     })
+    ///     .with_retry_deadline(Duration::from_secs(120));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Stop retrying a request once this much time has passed since its
+    /// first attempt, or when the next wait would go past it. Default 60s.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use std::time::Duration;
+    ///
+    /// let client = openkakao_rs::testing::client().with_retry_deadline(Duration::from_secs(10));
+    /// let (chats, _cursor) = client.get_chats(None)?;
+    /// assert!(!chats.is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_retry_deadline(mut self, deadline: Duration) -> Self {
+        self.retry_deadline = deadline;
+        self
+    }
+
+    /// Count received bytes in `meter`, and stop at its cap, instead of the
+    /// process-wide [`bandwidth::global`] meter.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use std::sync::Arc;
+    /// use openkakao_rs::bandwidth::ByteMeter;
+    ///
+    /// let meter = Arc::new(ByteMeter::with_max(10 * 1024 * 1024));
+    /// let client = openkakao_rs::testing::client().with_byte_meter(Arc::clone(&meter));
+    /// client.get_friends()?;
+    /// assert!(meter.received() > 0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_byte_meter(mut self, meter: Arc<ByteMeter>) -> Self {
+        self.meter = meter;
+        self
+    }
+
+    /// Space requests with `pacing`, and feed it this client's rate-limit
+    /// hints, instead of the process-wide [`rate_limit::global`] one.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use std::sync::Arc;
+    /// use openkakao_rs::rate_limit::Pacing;
+    /// use openkakao_rs::testing;
+    ///
+    /// // Two clients of one job share a pace, so together they stay polite.
+    /// let pacing = Arc::new(Pacing::new());
+    /// let chats = testing::client().with_pacing(Arc::clone(&pacing));
+    /// let friends = testing::client().with_pacing(pacing);
+    /// chats.get_chats(None)?;
+    /// friends.get_friends()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_pacing(mut self, pacing: Arc<Pacing>) -> Self {
+        self.pacing = pacing;
+        self
+    }
+
+    /// Send REST requests through `transport` instead of HTTP, for tests
+    /// that script latency and failures (see [`crate::fault`]). Binary
+    /// downloads still use HTTP.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::fault::{Fault, FaultyTransport, Scenario};
+    /// use openkakao_rs::rest::KakaoRestClient;
+    /// use openkakao_rs::retry::RetryPolicy;
+    /// use openkakao_rs::testing;
+    ///
+    /// // The first request fails as if the connection dropped.
+    /// let transport = FaultyTransport::new(testing::server(), Scenario::new().then(Fault::Drop));
+    /// let client = KakaoRestClient::new(testing::credentials())?
+    ///     .with_retry_policy(RetryPolicy::default().with_retries(0))
+    ///     .with_transport(transport);
+    /// assert!(client.get_friends().unwrap_err().is_retryable());
+    /// assert_eq!(client.get_friends()?.len(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "fault-injection")]
+    pub fn with_transport(mut self, transport: impl crate::fault::Transport + 'static) -> Self {
+        self.transport = Some(Box::new(transport));
+        self
+    }
+
+    /// The account these requests are made as. Stays the same across a
+    /// [`Reauth`] swap.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// let client = openkakao_rs::testing::client();
+    /// assert_eq!(client.user_id(), openkakao_rs::testing::USER_ID);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn user_id(&self) -> i64 {
+        self.creds().user_id
+    }
+
+    /// [`Self::user_id`], fetched from `profile3/me` and kept for later
+    /// requests when the credentials have none (0), as hand-entered ones
+    /// often do. Sends nothing when the id is known.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::rest::KakaoRestClient;
+    /// use openkakao_rs::testing;
+    ///
+    /// let mut creds = testing::credentials();
+    /// creds.user_id = 0;
+    /// let client = KakaoRestClient::new(creds)?.with_transport(testing::server());
+    /// assert_eq!(client.backfill_user_id()?, testing::USER_ID);
+    /// assert_eq!(client.user_id(), testing::USER_ID);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn backfill_user_id(&self) -> Result<i64, OpenKakaoError> {
+        let known = self.user_id();
+        if known != 0 {
+            return Ok(known);
+        }
+        let fetched = self.get_identity()?.user_id;
+        if fetched != 0 {
+            let mut slot = self.creds.write().unwrap_or_else(|e| e.into_inner());
+            Arc::make_mut(&mut slot).user_id = fetched;
+        }
+        Ok(fetched)
+    }
+
+    fn creds(&self) -> Arc<KakaoCredentials> {
+        Arc::clone(&self.creds.read().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// Swap in `creds` for every later request, e.g. after another process
+    /// refreshed the saved token, and return the ones replaced. Requests
+    /// already in flight finish with the headers they were built with.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::testing;
+    ///
+    /// let client = testing::client();
+    /// let mut refreshed = testing::credentials();
+    /// refreshed.oauth_token = "refreshed-token".into();
+    /// let previous = client.replace_credentials(refreshed);
+    /// assert_eq!(previous.oauth_token, testing::TOKEN);
+    /// // Later requests carry the new token.
+    /// client.get_friends()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn replace_credentials(&self, creds: KakaoCredentials) -> KakaoCredentials {
+        let previous = std::mem::replace(
+            &mut *self.creds.write().unwrap_or_else(|e| e.into_inner()),
+            Arc::new(creds),
+        );
+        Arc::unwrap_or_clone(previous)
+    }
+
+    /// Where a request for `url`, built on the Kakao hosts, is actually
+    /// sent: the katalk and pilsner hosts swapped for their overrides.
+    fn resolve(&self, url: &str) -> String {
+        if let Some(path) = url.strip_prefix(BASE_URL) {
+            format!("{}{path}", self.katalk_base)
+        } else if let Some(path) = url.strip_prefix(PILSNER_URL) {
+            format!("{}{path}", self.pilsner_base)
+        } else {
+            url.to_string()
+        }
+    }
+
+    fn body_limit_for(&self, url: &str) -> usize {
+        let path = url.split('?').next().unwrap_or(url);
+        if path.ends_with("/friends/update.json")
+            || path.ends_with("/messages")
+            || path.ends_with("/members")
+        {
+            self.max_body_bytes.saturating_mul(LARGE_BODY_MULTIPLIER)
+        } else {
+            self.max_body_bytes
+        }
+    }
+
+    /// Check the token against katalk with one cheap settings request and
+    /// say why it failed, if it did.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::rest::TokenStatus;
+    ///
+    /// match openkakao_rs::testing::client().check_token() {
+    ///     TokenStatus::Valid => println!("token ok"),
+    ///     TokenStatus::Invalid { message, .. } => panic!("log in again: {message}"),
+    ///     other => println!("could not tell: {}", other.reason()),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn check_token(&self) -> TokenStatus {
+        let result = self.request_raw(
+            "POST",
+            &format!("{BASE_URL}/mac/account/more_settings.json"),
+            Some("since=0&locale_country=KR"),
+        );
+        if let Ok(value) = &result {
+            let status = json_i64(value, "status");
+            if let Some(observer) = observer().filter(|_| status != 0) {
+                observer.status("verify", status);
+            }
+        }
+        let status = token_status(result);
+        match &status {
+            TokenStatus::Valid => self.record_scope(EndpointFamily::Katalk, true),
+            TokenStatus::Invalid { .. } => self.record_scope(EndpointFamily::Katalk, false),
+            TokenStatus::RateLimited | TokenStatus::NetworkError { .. } => {}
+        }
+        status
+    }
+
+    /// [`Self::check_token`] as a bool. Failures that say nothing about the
+    /// token (rate limiting, network) are errors rather than `false`.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// assert!(openkakao_rs::testing::client().verify_token()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn verify_token(&self) -> Result<bool, OpenKakaoError> {
+        match self.check_token() {
+            TokenStatus::Valid => Ok(true),
+            TokenStatus::Invalid { .. } => Ok(false),
+            other => Err(anyhow!("Token check inconclusive: {}", other.reason()).into()),
+        }
+    }
+
+    /// Whether pilsner accepts this token, from one cheap chat-list GET.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// assert!(openkakao_rs::testing::client().verify_pilsner_token()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn verify_pilsner_token(&self) -> Result<bool, OpenKakaoError> {
+        match self.request_raw("GET", &format!("{PILSNER_URL}/messaging/chats"), None) {
+            Ok(_) => {
+                self.record_scope(EndpointFamily::Pilsner, true);
+                Ok(true)
+            }
+            // send_json records a 401/403 or UNAUTHENTICATED rejection.
+            Err(_) if self.known_scope().pilsner == Some(false) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// What this run has learned about the token so far, without any request.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// let client = openkakao_rs::testing::client();
+    /// assert_eq!(client.known_scope().katalk, None);
+    /// client.verify_token()?;
+    /// assert_eq!(client.known_scope().katalk, Some(true));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn known_scope(&self) -> TokenScope {
+        let scopes = token_scopes();
+        let lookup = |family: EndpointFamily| {
+            scopes
+                .get(&(family, token_digest(&self.token_for_family(family))))
+                .copied()
+        };
+        TokenScope {
+            katalk: lookup(EndpointFamily::Katalk),
+            pilsner: lookup(EndpointFamily::Pilsner),
+        }
+    }
+
+    /// Probe whichever families are still unknown for this token. Results are
+    /// cached for the rest of the run, so repeated calls cost nothing.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// let scope = openkakao_rs::testing::client().probe_scope();
+    /// assert!(scope.is_complete());
+    /// println!("{}", scope.summary());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn probe_scope(&self) -> TokenScope {
+        let known = self.known_scope();
+        if known.katalk.is_none() {
+            self.check_token();
+        }
+        if known.pilsner.is_none() {
+            let _ = self.verify_pilsner_token();
+        }
+        self.known_scope()
+    }
+
+    fn record_scope(&self, family: EndpointFamily, valid: bool) {
+        token_scopes().insert(
+            (family, token_digest(&self.token_for_family(family))),
+            valid,
+        );
+    }
+
+    /// Warn once per family, before the request, when this run already
+    /// knows the token is rejected there.
+    fn warn_if_out_of_scope(&self, url: &str) {
+        let family = EndpointFamily::of(url);
+        if self.known_scope().get(family) != Some(false) {
+            return;
+        }
+        let mut warned = SCOPE_WARNED
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if warned.contains(&family) {
+            return;
+        }
+        warned.push(family);
+        if let Some(observer) = observer() {
+            observer.scope_rejected(family);
+        }
+    }
+
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::testing;
+    ///
+    /// let me = testing::client().get_my_profile()?;
+    /// assert_eq!(me.user_id, testing::USER_ID);
+    /// println!("{} <{}>: {}", me.nickname, me.email, me.status_message);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_my_profile(&self) -> Result<MyProfile, OpenKakaoError> {
+        let profile = self.request(
+            "POST",
+            &format!("{BASE_URL}/mac/profile3/me.json"),
+            Some("since=0"),
+        )?;
+        let settings = self.request(
+            "POST",
+            &format!("{BASE_URL}/mac/account/more_settings.json"),
+            Some("since=0&locale_country=KR"),
+        )?;
+
+        let p = profile.get("profile").cloned().unwrap_or(Value::Null);
+
+        Ok(MyProfile {
+            nickname: json_string(&p, "nickname"),
+            status_message: json_string(&p, "statusMessage"),
+            account_id: json_i64(&settings, "accountId"),
+            email: json_string(&settings, "emailAddress"),
+            user_id: {
+                let id = json_i64(&p, "userId");
+                if id == 0 {
+                    self.creds().user_id
+                } else {
+                    id
+                }
+            },
+            profile_image_url: json_string(&p, "fullProfileImageUrl"),
+        })
+    }
+
+    /// User id and nickname from `profile3/me` alone, skipping the settings
+    /// call that [`get_my_profile`](Self::get_my_profile) also makes.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::testing;
+    ///
+    /// let me = testing::client().get_identity()?;
+    /// assert_eq!(me.user_id, testing::USER_ID);
+    /// assert_eq!(me.nickname.as_deref(), Some("민수"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_identity(&self) -> Result<Identity, OpenKakaoError> {
+        let profile = self.request(
+            "POST",
+            &format!("{BASE_URL}/mac/profile3/me.json"),
+            Some("since=0"),
+        )?;
+        let p = profile.get("profile").cloned().unwrap_or(Value::Null);
+        let user_id = match json_i64(&p, "userId") {
+            0 => self.creds().user_id,
+            id => id,
+        };
+        Ok(Identity {
+            user_id,
+            nickname: Some(json_string(&p, "nickname")),
+        })
+    }
+
+    /// Change the nickname and/or status message in one request. An empty
+    /// `status_message` clears the status; an empty nickname, or either
+    /// value over Kakao's length limit, is refused before anything is sent.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// let client = openkakao_rs::testing::client();
+    /// client.update_profile(None, Some("회의 중"))?;
+    /// // Refused before anything is sent.
+    /// assert!(client.update_profile(Some(" "), None).is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn update_profile(
+        &self,
+        nickname: Option<&str>,
+        status_message: Option<&str>,
+    ) -> Result<Value, OpenKakaoError> {
+        let invalid = |message: String| OpenKakaoError::Other(anyhow!(message));
+        let mut fields = Vec::new();
+        if let Some(nickname) = nickname {
+            let len = nickname.chars().count();
+            if nickname.trim().is_empty() {
+                return Err(invalid("The nickname cannot be empty.".to_string()));
+            }
+            if len > MAX_NICKNAME_CHARS {
+                return Err(invalid(format!(
+                    "The nickname is {len} characters; Kakao allows at most {MAX_NICKNAME_CHARS}."
+                )));
+            }
+            fields.push(format!("nickname={}", urlencoding::encode(nickname)));
+        }
+        if let Some(status_message) = status_message {
+            let len = status_message.chars().count();
+            if len > MAX_STATUS_MESSAGE_CHARS {
+                return Err(invalid(format!(
+                    "The status message is {len} characters; Kakao allows at most \
+                     {MAX_STATUS_MESSAGE_CHARS}."
+                )));
+            }
+            fields.push(format!(
+                "statusMessage={}",
+                urlencoding::encode(status_message)
+            ));
+        }
+        if fields.is_empty() {
+            return Err(invalid(
+                "Nothing to update: give a nickname or a status message.".to_string(),
+            ));
+        }
+        self.request(
+            "POST",
+            &format!("{BASE_URL}/mac/profile3/edit.json"),
+            Some(&fields.join("&")),
+        )
+    }
+
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::testing;
+    ///
+    /// let answer = testing::client().get_friend_profile(testing::FRIEND_ID)?;
+    /// assert_eq!(answer["profile"]["nickname"], "지영");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_friend_profile(&self, user_id: i64) -> Result<Value, OpenKakaoError> {
+        self.request(
+            "POST",
+            &format!("{BASE_URL}/mac/profile3/friend.json"),
+            Some(&format!("id={user_id}")),
+        )
+    }
+
+    /// Another user's profile, typed. A deleted account, or one that blocked
+    /// us, fails with [`OpenKakaoError::ProfileUnavailable`]: Kakao answers
+    /// those with an empty profile or a status code of its own.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::testing;
+    ///
+    /// let profile = testing::client().get_member_profile(testing::FRIEND_ID)?;
+    /// assert_eq!(profile.nickname, "지영");
+    /// println!("{}: {}", profile.nickname, profile.status_message);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_member_profile(&self, user_id: i64) -> Result<MemberProfile, OpenKakaoError> {
+        let answer = match self.get_friend_profile(user_id) {
+            Ok(answer) => answer,
+            Err(OpenKakaoError::RestApi { status, .. })
+                if matches!(
+                    crate::status::KakaoStatus::from_code(status),
+                    crate::status::KakaoStatus::Unknown(_) | crate::status::KakaoStatus::BadRequest
+                ) =>
+            {
+                return Err(OpenKakaoError::ProfileUnavailable { user_id });
+            }
+            Err(err) => return Err(err),
+        };
+        let mut profile = MemberProfile::from_json(&answer)
+            .ok_or(OpenKakaoError::ProfileUnavailable { user_id })?;
+        if profile.user_id == 0 {
+            profile.user_id = user_id;
+        }
+        Ok(profile)
+    }
+
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// let answer = openkakao_rs::testing::client().get_profiles()?;
+    /// let profiles = answer["profiles"].as_array().map_or(0, Vec::len);
+    /// println!("{profiles} extra profiles");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_profiles(&self) -> Result<Value, OpenKakaoError> {
+        self.request("GET", &format!("{BASE_URL}/mac/profile/list.json"), None)
+    }
+
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::testing;
+    ///
+    /// let friends = testing::client().get_friends()?;
+    /// for friend in friends.iter().filter(|f| !f.hidden) {
+    ///     println!("{} {}", friend.user_id, friend.nickname);
+    /// }
+    /// assert_eq!(friends[0].direct_chat_id, Some(testing::CHAT_ID));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_friends(&self) -> Result<Vec<Friend>, OpenKakaoError> {
+        let r = self.request(
+            "POST",
+            &format!("{BASE_URL}/mac/friends/update.json"),
+            Some("since=0"),
+        )?;
+
+        let mut out = Vec::new();
+        if let Some(arr) = r.get("friends").and_then(Value::as_array) {
+            for item in arr {
+                out.push(Friend::from_json(item));
+            }
+        } else if let Some(arr) = r.get("added").and_then(Value::as_array) {
+            for item in arr {
+                out.push(Friend::from_json(item));
+            }
+        }
+
+        Ok(out)
+    }
+
+    /// Look up a user by Kakao ID. `Ok(None)` when no account uses that ID.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::testing;
+    ///
+    /// let client = testing::client();
+    /// let found = client.find_by_kakao_id(testing::KAKAO_ID)?.expect("the ID is in use");
+    /// assert_eq!(found.user_id, testing::USER_ID);
+    /// assert!(client.find_by_kakao_id("nobody-has-this-id")?.is_none());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn find_by_kakao_id(&self, kakao_id: &str) -> Result<Option<KakaoIdMatch>, OpenKakaoError> {
+        let parsed = self.request_raw(
+            "POST",
+            &format!("{BASE_URL}/mac/friends/find_by_uuid.json"),
+            Some(&format!("uuid={}", urlencoding::encode(kakao_id))),
+        )?;
+        parse_kakao_id_search(&parsed)
+    }
+
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::testing;
+    ///
+    /// let answer = testing::client().add_favorite(testing::FRIEND_ID)?;
+    /// assert_eq!(answer["status"], 0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn add_favorite(&self, user_id: i64) -> Result<Value, OpenKakaoError> {
+        self.request(
+            "POST",
+            &format!("{BASE_URL}/mac/friends/add_favorite.json"),
+            Some(&format!("id={user_id}")),
+        )
+    }
+
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::testing;
+    ///
+    /// let answer = testing::client().remove_favorite(testing::FRIEND_ID)?;
+    /// assert_eq!(answer["status"], 0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn remove_favorite(&self, user_id: i64) -> Result<Value, OpenKakaoError> {
+        self.request(
+            "POST",
+            &format!("{BASE_URL}/mac/friends/remove_favorite.json"),
+            Some(&format!("id={user_id}")),
+        )
+    }
+
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::testing;
+    ///
+    /// let answer = testing::client().hide_friend(testing::FRIEND_ID)?;
+    /// assert_eq!(answer["status"], 0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn hide_friend(&self, user_id: i64) -> Result<Value, OpenKakaoError> {
+        self.request(
+            "POST",
+            &format!("{BASE_URL}/mac/friends/hide.json"),
+            Some(&format!("id={user_id}")),
+        )
+    }
+
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::testing;
+    ///
+    /// let answer = testing::client().unhide_friend(testing::FRIEND_ID)?;
+    /// assert_eq!(answer["status"], 0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn unhide_friend(&self, user_id: i64) -> Result<Value, OpenKakaoError> {
+        self.request(
+            "POST",
+            &format!("{BASE_URL}/mac/friends/unhide.json"),
+            Some(&format!("id={user_id}")),
+        )
+    }
+
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// let answer = openkakao_rs::testing::client().get_alarm_keywords()?;
+    /// let keywords: Vec<&str> = answer["keywords"]
+    ///     .as_array()
+    ///     .into_iter()
+    ///     .flatten()
+    ///     .filter_map(|k| k.as_str())
+    ///     .collect();
+    /// assert_eq!(keywords, ["점심", "회의"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_alarm_keywords(&self) -> Result<Value, OpenKakaoError> {
+        self.request(
+            "GET",
+            &format!("{BASE_URL}/mac/alarm_keywords/list.json"),
+            None,
+        )
+    }
+
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// let client = openkakao_rs::testing::client();
+    /// let mut cursor = None;
+    /// loop {
+    ///     let (chats, next) = client.get_chats(cursor)?;
+    ///     for chat in &chats {
+    ///         println!("{} {}", chat.chat_id, chat.display_title());
+    ///     }
+    ///     match next {
+    ///         Some(next) => cursor = Some(next),
+    ///         None => break,
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_chats(
+        &self,
+        cursor: Option<ChatListCursor>,
+    ) -> Result<(Vec<ChatRoom>, Option<ChatListCursor>), OpenKakaoError> {
+        let url = if let Some(c) = cursor {
+            format!("{PILSNER_URL}/messaging/chats?cursor={c}")
+        } else {
+            format!("{PILSNER_URL}/messaging/chats")
+        };
+
+        let r = self.request("GET", &url, None)?;
+        let mut rooms = Vec::new();
+
+        if let Some(chats) = r.get("chats").and_then(Value::as_array) {
+            for chat in chats {
+                rooms.push(ChatRoom::from_json(chat));
+            }
+        }
+
+        let next_cursor = if r.get("last").and_then(Value::as_bool).unwrap_or(false) {
+            None
+        } else {
+            let n = json_i64(&r, "nextCursor");
+            (n != 0).then_some(ChatListCursor::new(n))
+        };
+
+        Ok((rooms, next_cursor))
+    }
+
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// let chats = openkakao_rs::testing::client().get_all_chats()?;
+    /// let titles: Vec<String> = chats.iter().map(|chat| chat.display_title()).collect();
+    /// assert_eq!(titles, ["가족", "Rust KR"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_all_chats(&self) -> Result<Vec<ChatRoom>, OpenKakaoError> {
+        let mut all = Vec::new();
+        let mut cursor = None;
+
+        loop {
+            let (rooms, next_cursor) = self.get_chats(cursor)?;
+            all.extend(rooms);
+            if next_cursor.is_none() {
+                break;
+            }
+            cursor = next_cursor;
+        }
+
+        Ok(all)
+    }
+
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::testing;
+    ///
+    /// let members = testing::client().get_chat_members(testing::CHAT_ID)?;
+    /// let names: Vec<&str> = members.iter().map(|m| m.nickname.as_str()).collect();
+    /// assert_eq!(names, ["민수", "지영"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_chat_members(&self, chat_id: i64) -> Result<Vec<ChatMember>, OpenKakaoError> {
+        Ok(self.get_chat_members_limited(chat_id, None, &NoProgress)?.0)
+    }
+
+    /// One page of an open chat community's board, newest first. `cursor` is
+    /// the continuation token of the previous page.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::testing;
+    ///
+    /// let page = testing::client().get_board_posts(testing::OPEN_CHAT_ID, None)?;
+    /// for post in &page.items {
+    ///     println!("{} ({} comments)", post.title, post.comment_count);
+    /// }
+    /// assert_eq!(page.items[0].post_id, testing::POST_ID);
+    /// assert!(page.next_cursor.is_none());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_board_posts(
+        &self,
+        chat_id: i64,
+        cursor: Option<&str>,
+    ) -> Result<BoardPage<BoardPost>, OpenKakaoError> {
+        let url = with_cursor(format!("{MOIM_URL}/chats/{chat_id}/posts"), cursor);
+        let r = self.request("GET", &url, None)?;
+        Ok(BoardPage::from_json(&r, BoardPost::from_json))
+    }
+
+    /// One board post, without its comments. The answer is the post itself
+    /// or wraps it under `post`.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::testing;
+    ///
+    /// let post = testing::client().get_board_post(testing::POST_ID)?;
+    /// assert_eq!(post.title, "정기 모임");
+    /// assert_eq!(post.author_id, testing::USER_ID);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_board_post(&self, post_id: &str) -> Result<BoardPost, OpenKakaoError> {
+        let url = format!("{MOIM_URL}/posts/{}", urlencoding::encode(post_id));
+        let r = self.request("GET", &url, None)?;
+        Ok(BoardPost::from_json(r.get("post").unwrap_or(&r)))
+    }
+
+    /// One page of the comments under a board post, oldest first.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::testing;
+    ///
+    /// let page = testing::client().get_board_comments(testing::POST_ID, None)?;
+    /// assert_eq!(page.items[0].content, "참석합니다");
+    /// assert_eq!(page.items[0].author_id, testing::FRIEND_ID);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_board_comments(
+        &self,
+        post_id: &str,
+        cursor: Option<&str>,
+    ) -> Result<BoardPage<BoardComment>, OpenKakaoError> {
+        let url = with_cursor(
+            format!("{MOIM_URL}/posts/{}/comments", urlencoding::encode(post_id)),
+            cursor,
+        );
+        let r = self.request("GET", &url, None)?;
+        Ok(BoardPage::from_json(&r, BoardComment::from_json))
+    }
+
+    /// Get one page of chat members. `cursor` is the continuation token
+    /// returned by the previous page.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::testing;
+    ///
+    /// let page = testing::client().get_chat_members_page(testing::CHAT_ID, None)?;
+    /// assert_eq!(page.members.len(), 2);
+    /// assert_eq!(page.total, Some(2));
+    /// assert!(page.next_cursor.is_none());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_chat_members_page(
+        &self,
+        chat_id: i64,
+        cursor: Option<&str>,
+    ) -> Result<ChatMembersPage, OpenKakaoError> {
+        let url = if let Some(c) = cursor {
+            format!(
+                "{PILSNER_URL}/messaging/chats/{chat_id}/members?cursor={}",
+                urlencoding::encode(c)
+            )
+        } else {
+            format!("{PILSNER_URL}/messaging/chats/{chat_id}/members")
+        };
+
+        let r = self.request("GET", &url, None)?;
+        Ok(ChatMembersPage::from_json(&r))
+    }
+
+    /// Follow member pagination until the server stops returning a cursor or
+    /// `limit` members have been collected. Members are deduplicated by
+    /// user_id. Returns the members and the total advertised by the server,
+    /// if any.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::progress::NoProgress;
+    /// use openkakao_rs::testing;
+    ///
+    /// let client = testing::client();
+    /// let (members, total) = client.get_chat_members_limited(testing::CHAT_ID, Some(1), &NoProgress)?;
+    /// assert_eq!(members.len(), 1);
+    /// assert_eq!(total, Some(2));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_chat_members_limited(
+        &self,
+        chat_id: i64,
+        limit: Option<usize>,
+        progress: &dyn ProgressSink,
+    ) -> Result<(Vec<ChatMember>, Option<i64>), OpenKakaoError> {
+        let max = limit.unwrap_or(usize::MAX);
+        let mut all: Vec<ChatMember> = Vec::new();
+        let mut seen = HashSet::new();
+        let mut total = None;
+        let mut cursor: Option<String> = None;
+        let mut page_num = 0usize;
+
+        loop {
+            let page = self.get_chat_members_page(chat_id, cursor.as_deref())?;
+            total = page.total.or(total);
+            page_num += 1;
+            let page_items = page.members.len();
+
+            let mut added = 0usize;
+            for member in page.members {
+                if all.len() >= max {
+                    break;
+                }
+                if seen.insert(member.user_id) {
+                    all.push(member);
+                    added += 1;
+                }
+            }
+
+            progress.emit(&ProgressEvent::PageFetched {
+                page: page_num,
+                items: page_items,
+                total: all.len(),
+            });
+
+            // Stop on the last page, on a repeated cursor, or when a page adds
+            // nothing new; the latter guards against servers echoing a cursor.
+            match page.next_cursor {
+                Some(next) if all.len() < max && added > 0 && cursor.as_ref() != Some(&next) => {
+                    cursor = Some(next);
+                }
+                _ => break,
+            }
+
+            progress.emit(&ProgressEvent::Throttled {
+                wait: MEMBER_PAGE_DELAY,
+            });
+            std::thread::sleep(MEMBER_PAGE_DELAY);
+        }
+
+        progress.emit(&ProgressEvent::Done { total: all.len() });
+        Ok((all, total))
+    }
+
+    /// Get one page of messages. Returns the messages and the cursor of the
+    /// next, older page; `None` means no more pages.
+    ///
+    /// NOTE: `fromLogId` and `sinceMessageId` do NOT work for pagination.
+    /// Only `?cursor=` works.
+    ///
+    /// An expired token and a network blip are different errors:
+    ///
+    /// ```
+    /// use openkakao_rs::error::OpenKakaoError;
+    /// use openkakao_rs::rest::KakaoRestClient;
+    ///
+    /// fn newest(client: &KakaoRestClient, chat_id: i64) -> Result<(), OpenKakaoError> {
+    ///     match client.get_messages(chat_id, None) {
+    ///         Ok((messages, _next_cursor)) => {
+    ///             for msg in messages {
+    ///                 println!("{}: {}", msg.author_id, msg.message);
+    ///             }
+    ///             Ok(())
+    ///         }
+    ///         Err(err) if err.is_auth_failure() => {
+    ///             eprintln!("token expired, log in again");
+    ///             Err(err)
+    ///         }
+    ///         Err(err) if err.is_retryable() => {
+    ///             eprintln!("try again later: {err}");
+    ///             Ok(())
+    ///         }
+    ///         Err(err) => Err(err),
+    ///     }
+    /// }
+    /// # let client = openkakao_rs::testing::client();
+    /// # newest(&client, openkakao_rs::testing::CHAT_ID).unwrap();
+    /// ```
+    pub fn get_messages(
+        &self,
+        chat_id: i64,
+        cursor: Option<MessageCursor>,
+    ) -> Result<(Vec<ChatMessage>, Option<MessageCursor>), OpenKakaoError> {
+        self.get_messages_with_options(chat_id, cursor, RequestOptions::default())
+    }
+
+    /// [`Self::get_messages`] with per-request overrides, e.g.
+    /// [`RequestOptions::polling`] for loops that re-fetch the newest page.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::rest::RequestOptions;
+    /// use openkakao_rs::testing;
+    ///
+    /// let client = testing::client();
+    /// let (newest, _cursor) =
+    ///     client.get_messages_with_options(testing::CHAT_ID, None, RequestOptions::polling())?;
+    /// assert_eq!(newest[0].log_id, 3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_messages_with_options(
+        &self,
+        chat_id: i64,
+        cursor: Option<MessageCursor>,
+        options: RequestOptions,
+    ) -> Result<(Vec<ChatMessage>, Option<MessageCursor>), OpenKakaoError> {
+        let url = if let Some(c) = cursor {
+            format!("{PILSNER_URL}/messaging/chats/{chat_id}/messages?cursor={c}")
+        } else {
+            format!("{PILSNER_URL}/messaging/chats/{chat_id}/messages")
+        };
+
+        let r = self.request_with("GET", &url, None, options)?;
+
+        let mut messages = Vec::new();
+        if let Some(arr) = r.get("chatLogs").and_then(Value::as_array) {
+            for msg in arr {
+                messages.push(ChatMessage::from_json(msg));
+            }
+        }
+
+        let next_cursor = r
+            .get("nextCursor")
+            .and_then(Value::as_i64)
+            .filter(|&n| n != 0)
+            .map(MessageCursor::new);
+        Ok((messages, next_cursor))
+    }
+
+    /// The newest `count` messages before `cursor` (from the newest when
+    /// `None`), following `nextCursor` across at most `max_pages` pages.
+    /// Returns them deduplicated, oldest → newest, with the cursor that
+    /// continues past the oldest one (`None` at the start of the history). When
+    /// the last page held more than `count` the older surplus is dropped and
+    /// the cursor is the oldest kept log id, so the next call returns it.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::testing;
+    ///
+    /// let client = testing::client();
+    /// let (messages, cursor) = client.get_recent_messages(testing::CHAT_ID, None, 2, 10)?;
+    /// let texts: Vec<&str> = messages.iter().map(|m| m.message.as_str()).collect();
+    /// assert_eq!(texts, ["지금 가는 중", "저녁 먹었어?"]);
+    /// // The cursor continues with the older ones; `None` means there are none.
+    /// let (older, cursor) = client.get_recent_messages(testing::CHAT_ID, cursor, 2, 10)?;
+    /// assert_eq!(older[0].message, "어디야?");
+    /// assert_eq!(cursor, None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_recent_messages(
+        &self,
+        chat_id: i64,
+        cursor: Option<MessageCursor>,
+        count: usize,
+        max_pages: usize,
+    ) -> Result<(Vec<ChatMessage>, Option<MessageCursor>), OpenKakaoError> {
+        let mut all = Vec::new();
+        let mut page_cursor = cursor;
+        let mut next = None;
+        for _ in 0..max_pages {
+            let (messages, next_cursor) = match self.get_messages(chat_id, page_cursor) {
+                Ok(page) => page,
+                Err(OpenKakaoError::ByteCapReached { .. }) if !all.is_empty() => break,
+                Err(err) => return Err(err),
+            };
+            let before = all.len();
+            all = select_window(
+                all.into_iter().chain(messages).collect(),
+                usize::MAX,
+                cursor.map(MessageCursor::get),
+            );
+            next = next_cursor;
+            if all.len() >= count
+                || all.len() == before
+                || next_cursor.is_none()
+                || page_cursor == next_cursor
+            {
+                break;
+            }
+            page_cursor = next_cursor;
+        }
+        if all.len() > count {
+            all = select_window(all, count, None);
+            next = all
+                .first()
+                .map_or(next, |m| Some(MessageCursor::new(m.log_id)));
+        }
+        Ok((all, next))
+    }
+
+    /// Fetch all available messages using cursor pagination.
+    ///
+    /// The pilsner server only caches messages for chats recently opened
+    /// in the KakaoTalk Mac app. Most chats will return empty results.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::testing;
+    ///
+    /// let messages = testing::client().get_all_messages(testing::CHAT_ID, 10)?;
+    /// let ids: Vec<i64> = messages.iter().map(|m| m.log_id).collect();
+    /// assert_eq!(ids, [1, 2, 3]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_all_messages(
+        &self,
+        chat_id: i64,
+        max_pages: usize,
+    ) -> Result<Vec<ChatMessage>, OpenKakaoError> {
+        self.get_all_messages_with_progress(chat_id, max_pages, &NoProgress)
+    }
+
+    /// [`Self::get_all_messages`] reporting each page to `progress`.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::progress::ProgressEvent;
+    /// use openkakao_rs::testing;
+    ///
+    /// let report = |event: &ProgressEvent| {
+    ///     if let ProgressEvent::PageFetched { page, total, .. } = event {
+    ///         eprintln!("page {page}: {total} messages so far");
+    ///     }
+    /// };
+    /// let messages = testing::client().get_all_messages_with_progress(testing::CHAT_ID, 10, &report)?;
+    /// assert_eq!(messages.len(), 3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_all_messages_with_progress(
+        &self,
+        chat_id: i64,
+        max_pages: usize,
+        progress: &dyn ProgressSink,
+    ) -> Result<Vec<ChatMessage>, OpenKakaoError> {
+        let mut all = Vec::new();
+        let mut cursor = None;
+
+        for page in 1..=max_pages {
+            let (messages, next_cursor) = match self.get_messages(chat_id, cursor) {
+                Ok(page) => page,
+                // The cap ends the history here; the pages so far are whole.
+                Err(err @ OpenKakaoError::ByteCapReached { .. }) if !all.is_empty() => {
+                    progress.emit(&ProgressEvent::Stopped {
+                        total: all.len(),
+                        reason: err.to_string(),
+                    });
+                    break;
+                }
+                Err(err) => return Err(err),
+            };
+            if messages.is_empty() {
+                break;
+            }
+            let items = messages.len();
+            all.extend(messages);
+            progress.emit(&ProgressEvent::PageFetched {
+                page,
+                items,
+                total: all.len(),
+            });
+            if next_cursor.is_none() {
+                break;
+            }
+            cursor = next_cursor;
+        }
+
+        let all = select_window(all, usize::MAX, None);
+        progress.emit(&ProgressEvent::Done { total: all.len() });
+        Ok(all)
+    }
+
+    /// Attempt to renew the OAuth token using a refresh_token (legacy endpoint).
+    /// Returns the raw JSON response (may contain access_token, refresh_token, etc.)
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// let answer = openkakao_rs::testing::client().renew_token("refresh-token")?;
+    /// if let Some(token) = answer["access_token"].as_str() {
+    ///     println!("new token ends in {}", &token[token.len() - 4..]);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn renew_token(&self, refresh_token: &str) -> Result<Value, OpenKakaoError> {
+        let encoded_token = urlencoding::encode(refresh_token);
+        let body = format!("grant_type=refresh_token&refresh_token={encoded_token}");
+        self.request_raw(
+            "POST",
+            &format!("{BASE_URL}/mac/account/renew_token.json"),
+            Some(&body),
+        )
+    }
+
+    /// Attempt to refresh the OAuth token using oauth2_token.json (node-kakao style).
+    /// Sends both access_token and refresh_token as required by Kakao's OAuth.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// let answer = openkakao_rs::testing::client().oauth2_token("refresh-token")?;
+    /// assert_eq!(answer["refresh_token"], "renewed-refresh-token");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn oauth2_token(&self, refresh_token: &str) -> Result<Value, OpenKakaoError> {
+        let oauth_token = self.creds().oauth_token.clone();
+        let access_token = urlencoding::encode(&oauth_token);
+        let refresh = urlencoding::encode(refresh_token);
+        let body =
+            format!("grant_type=refresh_token&access_token={access_token}&refresh_token={refresh}");
+        self.request_raw(
+            "POST",
+            &format!("{BASE_URL}/mac/account/oauth2_token.json"),
+            Some(&body),
+        )
+    }
+
+    /// Call login.json with cached credentials and X-VC header.
+    /// Returns the raw JSON response.
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::rest::KakaoRestClient;
+    ///
+    /// let client = KakaoRestClient::new(openkakao_rs::testing::credentials())?;
+    /// let user_agent = "KT/26.1.0 Mc/26.1.0 ko";
+    /// let x_vc = KakaoRestClient::generate_xvc(user_agent, "me@example.com", "device-uuid");
+    /// let answer = client.login_direct("me@example.com", "password", "device-uuid", "MacBook", &x_vc)?;
+    /// if answer["status"] == 0 {
+    ///     println!("logged in as {}", answer["userId"]);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn login_direct(
+        &self,
+        email: &str,
+        password: &str,
+        device_uuid: &str,
+        device_name: &str,
+        x_vc: &str,
+    ) -> Result<Value, OpenKakaoError> {
+        let user_agent = if self.creds().user_agent.is_empty() {
+            format!("KT/{} Mc/26.1.0 ko", self.creds().app_version)
+        } else {
+            self.creds().user_agent.clone()
+        };
+        self.login_direct_with_ua(email, password, device_uuid, device_name, x_vc, &user_agent)
+    }
+
+    fn login_direct_with_ua(
+        &self,
+        email: &str,
+        password: &str,
+        device_uuid: &str,
+        device_name: &str,
+        x_vc: &str,
+        user_agent: &str,
+    ) -> Result<Value, OpenKakaoError> {
+        let encoded_name = urlencoding::encode(device_name);
+        let encoded_uuid = urlencoding::encode(device_uuid);
+        let encoded_password = urlencoding::encode(password);
+        let encoded_email = urlencoding::encode(email);
+        let body = format!(
+            "device_name={encoded_name}&device_uuid={encoded_uuid}&email={encoded_email}&os_version=26.1.0&password={encoded_password}&permanent=1"
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/x-www-form-urlencoded"),
+        );
+        headers.insert(ACCEPT, HeaderValue::from_static("application/json"));
+        headers.insert(ACCEPT_LANGUAGE, HeaderValue::from_static("ko"));
+
+        let a_header = if self.creds().a_header.is_empty() {
+            format!("mac/{}/ko", self.creds().app_version)
+        } else {
+            self.creds().a_header.clone()
+        };
+        headers.insert(
+            "A",
+            HeaderValue::from_str(&a_header)
+                .with_context(|| format!("Invalid A header; {LINT_HINT}"))?,
+        );
+
+        headers.insert(
+            "User-Agent",
+            HeaderValue::from_str(user_agent)
+                .with_context(|| format!("Invalid User-Agent header; {LINT_HINT}"))?,
+        );
+
+        if !x_vc.is_empty() {
+            headers.insert(
+                "X-VC",
+                HeaderValue::from_str(x_vc).context("Invalid X-VC header")?,
+            );
+        }
+
+        let response = self
+            .client
+            .post(self.resolve(&format!("{BASE_URL}/mac/account/login.json")))
+            .headers(headers)
+            .body(body)
+            .send()
+            .context("login.json request failed")?;
+
+        let text = response.text().context("Failed to read response")?;
+        let parsed: Value = serde_json::from_str(&text).with_context(|| {
+            format!(
+                "Failed to parse login response: {}",
+                &text[..200.min(text.len())]
+            )
+        })?;
+
+        Ok(parsed)
+    }
+
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// let settings = openkakao_rs::testing::client().get_settings()?;
+    /// assert_eq!(settings["accountId"], 1234);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_settings(&self) -> Result<Value, OpenKakaoError> {
+        self.request(
+            "POST",
+            &format!("{BASE_URL}/mac/account/more_settings.json"),
+            Some("since=0&locale_country=KR"),
+        )
+    }
+
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// let preview = openkakao_rs::testing::client().get_scrap_preview("https://example.com")?;
+    /// assert_eq!(preview["title"], "Example Domain");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_scrap_preview(&self, url: &str) -> Result<Value, OpenKakaoError> {
+        let encoded = urlencoding::encode(url);
+        let body = format!("url={encoded}");
+        self.request(
+            "POST",
+            &format!("{BASE_URL}/mac/scrap/preview.json"),
+            Some(&body),
+        )
+    }
+
+    /// Look up an open chat link without joining it.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::model::OpenLinkRef;
+    /// use openkakao_rs::testing;
+    ///
+    /// let link = testing::client().get_open_link(&OpenLinkRef::Id(testing::LINK_ID))?;
+    /// assert_eq!(link.name, "Rust KR");
+    /// if !link.passcode_required {
+    ///     println!("{} of {:?} members", link.member_count.unwrap_or(0), link.member_limit);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_open_link(&self, link: &OpenLinkRef) -> Result<OpenLink, OpenKakaoError> {
+        let body = match link {
+            OpenLinkRef::Url(url) => format!("link_url={}", urlencoding::encode(url)),
+            OpenLinkRef::Id(id) => format!("link_id={id}"),
+        };
+        let parsed = self.request(
+            "POST",
+            &format!("{BASE_URL}/mac/openlink/info.json"),
+            Some(&body),
+        )?;
+        Ok(OpenLink::from_json(&parsed))
+    }
+
+    /// Join the open chat behind `link_id` and return its chat id. A full
+    /// room or a wrong passcode comes back as [`OpenKakaoError::RestApi`]
+    /// with the server's message.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::testing;
+    ///
+    /// let chat_id = testing::client().join_open_link(testing::LINK_ID, None, Some("민수"))?;
+    /// assert_eq!(chat_id, testing::OPEN_CHAT_ID);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn join_open_link(
+        &self,
+        link_id: i64,
+        passcode: Option<&str>,
+        nickname: Option<&str>,
+    ) -> Result<i64, OpenKakaoError> {
+        let mut body = format!("link_id={link_id}");
+        if let Some(passcode) = passcode {
+            body.push_str(&format!("&passcode={}", urlencoding::encode(passcode)));
+        }
+        if let Some(nickname) = nickname {
+            body.push_str(&format!("&nickname={}", urlencoding::encode(nickname)));
+        }
+        let parsed = self.request(
+            "POST",
+            &format!("{BASE_URL}/mac/openlink/join.json"),
+            Some(&body),
+        )?;
+        let chat_id = match json_i64(&parsed, "chatId") {
+            0 => parsed
+                .get("chatRoom")
+                .map_or(0, |room| json_i64(room, "chatId")),
+            id => id,
+        };
+        if chat_id == 0 {
+            return Err(OpenKakaoError::Parse {
+                message: "openlink/join answered without a chatId".to_string(),
+            });
+        }
+        Ok(chat_id)
+    }
+
+    /// Leave the open chat `chat_id`.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::testing;
+    ///
+    /// testing::client().leave_open_chat(testing::OPEN_CHAT_ID)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn leave_open_chat(&self, chat_id: i64) -> Result<Value, OpenKakaoError> {
+        self.request(
+            "POST",
+            &format!("{BASE_URL}/mac/openlink/leave.json"),
+            Some(&format!("chat_id={chat_id}")),
+        )
+    }
+
+    /// Generate X-VC header for Mac KakaoTalk.
+    /// Algorithm: `SHA-512("YLLAS|{loginId}|{uuid}|GRAEB|{userAgent}")[0:16]`
+    ///
+    /// ```
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::rest::KakaoRestClient;
+    ///
+    /// let x_vc = KakaoRestClient::generate_xvc("KT/26.1.0 Mc/26.1.0 ko", "me@example.com", "device-uuid");
+    /// assert_eq!(x_vc.len(), 16);
+    /// assert_eq!(
+    ///     x_vc,
+    ///     KakaoRestClient::generate_xvc("KT/26.1.0 Mc/26.1.0 ko", "me@example.com", "device-uuid")
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn generate_xvc(user_agent: &str, login_id: &str, device_uuid: &str) -> String {
+        let input = format!("YLLAS|{login_id}|{device_uuid}|GRAEB|{user_agent}");
+        let h = hex::encode(Sha512::digest(input.as_bytes()));
+        h[..16].to_string()
+    }
+
+    /// Login using the Mac X-VC algorithm.
+    /// Always uses the short User-Agent format ("KT/{ver} Mc/{os} ko") for both
+    /// the X-VC hash and the request header, matching what the real app sends.
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::rest::KakaoRestClient;
+    ///
+    /// let client = KakaoRestClient::new(openkakao_rs::testing::credentials())?;
+    /// let answer = client.login_with_xvc("me@example.com", "password", "device-uuid", "MacBook")?;
+    /// match answer["status"].as_i64() {
+    ///     Some(0) => println!("token: {}", answer["access_token"]),
+    ///     status => println!("login refused: {status:?}"),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn login_with_xvc(
+        &self,
+        email: &str,
+        password: &str,
+        device_uuid: &str,
+        device_name: &str,
+    ) -> Result<Value, OpenKakaoError> {
+        let user_agent = format!("KT/{} Mc/26.1.0 ko", self.creds().app_version);
+
+        let xvc = Self::generate_xvc(&user_agent, email, device_uuid);
+        self.login_direct_with_ua(email, password, device_uuid, device_name, &xvc, &user_agent)
+    }
+
+    fn request(
+        &self,
+        method: &str,
+        url: &str,
+        body: Option<&str>,
+    ) -> Result<Value, OpenKakaoError> {
+        self.request_with(method, url, body, RequestOptions::default())
+    }
+
+    fn request_with(
+        &self,
+        method: &str,
+        url: &str,
+        body: Option<&str>,
+        options: RequestOptions,
+    ) -> Result<Value, OpenKakaoError> {
+        let cache = response_cache::global();
+        let cached = cache.and_then(|cache| {
+            let (ttl, scope) = response_cache::policy(method, url)?;
+            Some((
+                cache,
+                ResponseCache::key(self.user_id(), method, &self.resolve(url), body),
+                ttl,
+                scope,
+            ))
+        });
+        let now = response_cache::now_secs();
+        if let Some(hit) = cached
+            .as_ref()
+            .and_then(|(cache, key, ..)| cache.get(key, now))
+        {
+            return Ok(hit);
+        }
+        let parsed = self.request_raw_with(method, url, body, options)?;
+        if let Some(status) = parsed.get("status").and_then(Value::as_i64) {
+            if status != 0 {
+                if let Some(observer) = observer() {
+                    observer.status(&redact_endpoint(url), status);
+                }
+                return Err(status_error(url, status, &parsed));
+            }
+        }
+        if let Some((cache, key, ttl, scope)) = cached {
+            cache.put(&key, &parsed, ttl, scope, now);
+        } else if let (Some(cache), Some(changed)) =
+            (cache, response_cache::invalidated_by(method, url))
+        {
+            cache.invalidate(changed);
+        }
+        Ok(parsed)
+    }
+
+    fn request_raw(
+        &self,
+        method: &str,
+        url: &str,
+        body: Option<&str>,
+    ) -> Result<Value, OpenKakaoError> {
+        Ok(self.request_raw_with(method, url, body, RequestOptions::default())?)
+    }
+
+    /// Stream a binary response (media, profile images, emoticons) into
+    /// `dest` with the standard auth headers. Nothing is buffered beyond the
+    /// copy loop, so large files do not live in memory.
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::rest::KakaoRestClient;
+    ///
+    /// let client = KakaoRestClient::new(openkakao_rs::testing::credentials())?;
+    /// let mut photo = Vec::new();
+    /// let info = client.download("https://dn-m.talk.kakao.com/talkm/abc/photo.jpg", &mut photo)?;
+    /// println!("{} bytes of {:?}", info.bytes, info.content_type);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn download(
+        &self,
+        url: &str,
+        dest: &mut impl Write,
+    ) -> Result<DownloadInfo, OpenKakaoError> {
+        capture::note_request("rest", "GET", url);
+        let started = Instant::now();
+        let result = self.download_inner(url, dest);
+        if capture::is_active() {
+            capture::record(capture::Exchange {
+                transport: "rest",
+                method: "GET".to_string(),
+                endpoint: url.to_string(),
+                status: result.as_ref().ok().map(|_| 0),
+                latency: started.elapsed(),
+                error: result.as_ref().err().map(|e| format!("{e:#}")),
+                ..Default::default()
+            });
+        }
+        result
+    }
+
+    fn download_inner(
+        &self,
+        url: &str,
+        dest: &mut impl Write,
+    ) -> Result<DownloadInfo, OpenKakaoError> {
+        let mut response = self.download_request(url, 0)?;
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let content_length = response.content_length();
+        let final_url = response.url().to_string();
+        let mut body = CountingReader::new(&mut response, self.meter.clone());
+        let bytes = match std::io::copy(&mut body, dest) {
+            Ok(bytes) => bytes,
+            Err(err) => return Err(self.stream_error(url, err)),
+        };
+
+        Ok(DownloadInfo {
+            content_type,
+            content_length,
+            bytes,
+            final_url,
+        })
+    }
+
+    /// GET a CDN or API resource with the download credentials, asking for
+    /// the bytes from `offset` on when it is non-zero.
+    fn download_request(&self, url: &str, offset: u64) -> Result<reqwest::blocking::Response> {
+        let parsed_url = reqwest::Url::parse(url).with_context(|| format!("Invalid URL: {url}"))?;
+        let host = parsed_url.host_str().unwrap_or("");
+        if !is_kakao_host(host) {
+            anyhow::bail!("Refusing to send credentials to non-Kakao domain: {}", host);
+        }
+
+        // CDN downloads authenticate with "<oauth_token>-<device_uuid>".
+        let creds = self.creds();
+        let token = if url.starts_with(PILSNER_URL) || url.starts_with(BASE_URL) {
+            token_of(&creds, EndpointFamily::of(url))
+        } else {
+            creds.require_device_uuid("Media download")?;
+            format!("{}-{}", creds.oauth_token, creds.device_uuid)
+        };
+        let mut headers = self.standard_headers(&creds, &token)?;
+        if offset > 0 {
+            headers.insert(RANGE, HeaderValue::from_str(&format!("bytes={offset}-"))?);
+        }
+
+        self.meter.check(0)?;
+        self.pacing.wait();
+        let target = self.resolve(url);
+        tracing::debug!("GET {target} [{}]", crate::trace::redact_headers(&headers));
+        let started = Instant::now();
+        let response = self
+            .client
+            .get(&target)
+            .headers(headers)
+            .timeout(DOWNLOAD_TIMEOUT)
+            .send()
+            .map_err(|err| {
+                tracing::debug!("GET {url} failed after {:?}: {err}", started.elapsed());
+                OpenKakaoError::Network {
+                    message: format!("GET {url}: {err}"),
+                    is_transient: true,
+                }
+            })?;
+        let status = response.status();
+        tracing::debug!(
+            "GET {url} -> HTTP {} in {:?}",
+            status.as_u16(),
+            started.elapsed()
+        );
+        self.observe_rate_limit(
+            response.headers(),
+            (status.as_u16() == 429).then_some(&[][..]),
+        );
+        // Asked for bytes past the end: what we have is the whole file.
+        if offset > 0 && status == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+            return Ok(response);
+        }
+        if !status.is_success() {
+            return Err(OpenKakaoError::Network {
+                message: format!("HTTP {}: {url}", status.as_u16()),
+                is_transient: status.is_server_error() || matches!(status.as_u16(), 408 | 429),
+            }
+            .into());
+        }
+        // Refuse a file that cannot fit before spending any of the cap on it.
+        if let Some(length) = response.content_length() {
+            self.meter.check(length)?;
+        }
+        Ok(response)
+    }
+
+    /// The error for a body that stopped streaming: the byte cap when that
+    /// is what stopped it, otherwise a transient network error.
+    fn stream_error(&self, url: &str, err: std::io::Error) -> OpenKakaoError {
+        if self.meter.exceeded() {
+            return self.meter.cap_error();
+        }
+        OpenKakaoError::Network {
+            message: format!("Failed to stream response body: {url}: {err}"),
+            is_transient: true,
+        }
+    }
+
+    /// [`Self::download`] into a [`PartialFile`], continuing after the
+    /// bytes it already holds when the server honours the range.
+    fn download_resumable(&self, url: &str, out: &mut PartialFile) -> Result<()> {
+        let offset = out.offset();
+        let mut response = self.download_request(url, offset)?;
+        match response.status() {
+            reqwest::StatusCode::RANGE_NOT_SATISFIABLE => return Ok(()),
+            reqwest::StatusCode::PARTIAL_CONTENT if offset > 0 => {}
+            _ => out.restart()?,
+        }
+        let mut body = CountingReader::new(&mut response, self.meter.clone());
+        std::io::copy(&mut body, out).map_err(|err| self.stream_error(url, err))?;
+        Ok(())
+    }
+
+    /// [`Self::download`] into memory, for small resources.
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+    /// use openkakao_rs::rest::KakaoRestClient;
+    ///
+    /// let client = KakaoRestClient::new(openkakao_rs::testing::credentials())?;
+    /// let image = client.get_bytes("https://p.kakaocdn.net/th/talkp/profile.jpg")?;
+    /// println!("{} bytes", image.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_bytes(&self, url: &str) -> Result<Vec<u8>, OpenKakaoError> {
+        let mut buf = Vec::new();
+        self.download(url, &mut buf)?;
+        Ok(buf)
+    }
+
+    fn token_for_family(&self, family: EndpointFamily) -> String {
+        token_of(&self.creds(), family)
+    }
+
+    /// Authorization, A, User-Agent and Accept-Language headers shared by
+    /// every request, all taken from the one `creds` snapshot.
+    fn standard_headers(&self, creds: &KakaoCredentials, token: &str) -> Result<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT_LANGUAGE, HeaderValue::from_static("ko"));
+        let auth = HeaderValue::from_str(token)
+            .with_context(|| format!("Invalid Authorization header; {LINT_HINT}"))?;
+        headers.insert(AUTHORIZATION, auth);
+
+        let a_header = if creds.a_header.is_empty() {
+            format!("mac/{}/ko", creds.app_version)
+        } else {
+            creds.a_header.clone()
+        };
+        headers.insert(
+            "A",
+            HeaderValue::from_str(&a_header)
+                .with_context(|| format!("Invalid A header; {LINT_HINT}"))?,
+        );
+
+        let user_agent = if let Some(user_agent) = &self.user_agent {
+            user_agent.clone()
+        } else if creds.user_agent.is_empty() {
+            format!("KT/{} Mc/26.1.0 ko", creds.app_version)
+        } else {
+            creds.user_agent.clone()
+        };
+        headers.insert(
+            "User-Agent",
+            HeaderValue::from_str(&user_agent)
+                .with_context(|| format!("Invalid User-Agent header; {LINT_HINT}"))?,
+        );
+        Ok(headers)
+    }
+
+    fn request_raw_with(
+        &self,
+        method: &str,
+        url: &str,
+        body: Option<&str>,
+        options: RequestOptions,
+    ) -> Result<Value> {
+        check_read_only(method, url)?;
+        let result = self.request_raw_retrying(method, url, body, options);
+        let rejected = rejects_credentials(&result);
+        let reauth = {
+            let mut slot = self.reauth.lock().unwrap_or_else(|e| e.into_inner());
+            // Network errors and the like say nothing about the token yet.
+            if slot.is_none() || (result.is_err() && !rejected) {
+                return result;
+            }
+            slot.take()
+        };
+        let Some(reauth) = reauth else {
+            return result;
+        };
+        if !rejected {
+            reauth.confirmed();
+            return result;
+        }
+        if EndpointFamily::of(url) != EndpointFamily::Katalk {
+            // The slot is empty, so this check runs without reauth.
+            match self.check_token() {
+                TokenStatus::Invalid { .. } => {}
+                TokenStatus::Valid => {
+                    // A scope refusal: the token itself is good.
+                    reauth.confirmed();
+                    return result;
+                }
+                TokenStatus::RateLimited | TokenStatus::NetworkError { .. } => {
+                    // Undecided; a later request settles it.
+                    *self.reauth.lock().unwrap_or_else(|e| e.into_inner()) = Some(reauth);
+                    return result;
+                }
+            }
+        }
+        if let Some(observer) = observer() {
+            observer.token_recovering();
+        }
+        let creds = reauth.recover()?;
+        self.replace_credentials(creds);
+        self.request_raw_retrying(method, url, body, options)
+    }
+
+    /// [`Self::request_raw_once`], repeated after HTTP 429 and 5xx answers
+    /// per the retry policy and deadline. Each attempt is a full request,
+    /// so a page fetched by a pagination loop comes back once.
+    fn request_raw_retrying(
+        &self,
+        method: &str,
+        url: &str,
+        body: Option<&str>,
+        options: RequestOptions,
+    ) -> Result<Value> {
+        let started = Instant::now();
+        let mut attempt = 1;
+        loop {
+            let result = self.request_raw_once(method, url, body, options);
+            let Err(err) = &result else {
+                return result;
+            };
+            let Some(wait) = self.retry_wait(method, url, err, attempt) else {
+                return result;
+            };
+            if started.elapsed() + wait > self.retry_deadline {
+                return result;
+            }
+            if let Some(observer) = observer() {
+                observer.retry(
+                    method,
+                    &redact_endpoint(url),
+                    err,
+                    attempt,
+                    self.retry_policy.max_attempts - 1,
+                    wait,
+                );
+            }
+            std::thread::sleep(wait);
+            attempt += 1;
+        }
+    }
+
+    /// How long to wait before trying again after `attempt` failed with
+    /// `err`, or `None` when it should not be retried.
+    fn retry_wait(
+        &self,
+        method: &str,
+        url: &str,
+        err: &anyhow::Error,
+        attempt: u32,
+    ) -> Option<Duration> {
+        if attempt >= self.retry_policy.max_attempts {
+            return None;
+        }
+        let typed = err.downcast_ref::<OpenKakaoError>()?;
+        let (rate_limited, retry_after) = match typed {
+            OpenKakaoError::RateLimited { retry_after } => (true, retry_after),
+            OpenKakaoError::Http {
+                status,
+                retry_after,
+                ..
+            } if *status >= 500 => (false, retry_after),
+            _ => return None,
+        };
+        if !(self.retry_policy.retryable)(typed) {
+            return None;
+        }
+        // A 5xx may have been acted on before it failed.
+        if !rate_limited && !repeatable_after_server_error(method, url) {
+            return None;
+        }
+        Some(retry_after.unwrap_or_else(|| self.retry_policy.delay(attempt)))
+    }
+
+    fn request_raw_once(
+        &self,
+        method: &str,
+        url: &str,
+        body: Option<&str>,
+        options: RequestOptions,
+    ) -> Result<Value> {
+        self.warn_if_out_of_scope(url);
+        self.meter.check(0)?;
+        self.pacing.wait();
+        capture::note_request("rest", method, url);
+        if !capture::is_active() {
+            return self.send_routed(method, url, body, options);
+        }
+        let started = Instant::now();
+        let result = self.send_routed(method, url, body, options);
+        let bodies = capture::wants_bodies();
+        capture::record(capture::Exchange {
+            transport: "rest",
+            method: method.to_string(),
+            endpoint: url.to_string(),
+            status: result
+                .as_ref()
+                .ok()
+                .and_then(|v| v.get("status"))
+                .and_then(Value::as_i64),
+            latency: started.elapsed(),
+            error: result.as_ref().err().map(|e| format!("{e:#}")),
+            request: body.filter(|_| bodies).map(capture::form_to_json),
+            response: result.as_ref().ok().filter(|_| bodies).cloned(),
+        });
+        result
+    }
+
+    /// Katalk URLs go through the configured mirrors, if any, unless the
+    /// client has its own base URL; everything else is sent to its host or
+    /// that host's override.
+    fn send_routed(
+        &self,
+        method: &str,
+        url: &str,
+        body: Option<&str>,
+        options: RequestOptions,
+    ) -> Result<Value> {
+        #[cfg(feature = "fault-injection")]
+        if let Some(transport) = &self.transport {
+            let authorization = token_of(&self.creds(), EndpointFamily::of(url));
+            let answer = transport.send(&crate::fault::Request {
+                method,
+                url,
+                body,
+                timeout: options.timeout,
+                authorization: &authorization,
+            })?;
+            // A scripted answer counts as its JSON would on the wire.
+            self.meter.record(answer.to_string().len() as u64);
+            return Ok(answer);
+        }
+        let mirrors = ENDPOINTS.get().filter(|_| self.katalk_base == BASE_URL);
+        match (mirrors, url.strip_prefix(BASE_URL)) {
+            (Some(pool), Some(path)) => self.send_with_failover(pool, method, path, body, options),
+            _ => self.send_json(method, url, body, options),
+        }
+    }
+
+    /// Try the active mirror first and move on only when the connection
+    /// fails, or times out for a request without side effects; any answer,
+    /// even an error status, is final.
+    fn send_with_failover(
+        &self,
+        pool: &Mutex<EndpointPool>,
+        method: &str,
+        path: &str,
+        body: Option<&str>,
+        options: RequestOptions,
+    ) -> Result<Value> {
+        // The pool is never locked across network I/O.
+        if lock_pool(pool).needs_probe() {
+            let bases = lock_pool(pool).bases();
+            let latencies: Vec<Option<Duration>> =
+                bases.iter().map(|base| self.probe_latency(base)).collect();
+            lock_pool(pool).record_probe(&latencies);
+        }
+
+        let repeatable = repeatable_after_server_error(method, &format!("{BASE_URL}{path}"));
+        let candidates = lock_pool(pool).candidates();
+        let mut last_error = None;
+        for base in candidates {
+            match self.send_json(method, &format!("{base}{path}"), body, options) {
+                Ok(value) => {
+                    lock_pool(pool).succeeded(&base);
+                    return Ok(value);
+                }
+                Err(err) if may_fail_over(&err, repeatable) => {
+                    if let Some(observer) = observer() {
+                        observer.mirror_failed(&base, &err);
+                    }
+                    lock_pool(pool).failed(&base);
+                    last_error = Some(err);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| anyhow!("No katalk base URL configured")))
+    }
+
+    /// Round-trip of a bare HEAD to `base`; `None` when it cannot connect.
+    fn probe_latency(&self, base: &str) -> Option<Duration> {
+        let started = Instant::now();
+        self.client
+            .head(base)
+            .timeout(ENDPOINT_PROBE_TIMEOUT)
+            .send()
+            .ok()
+            .map(|_| started.elapsed())
+    }
+
+    /// Feed one response's rate-limit headers (and, for a 429, its body) to
+    /// this client's pacing. Returns what they said.
+    fn observe_rate_limit(
+        &self,
+        headers: &HeaderMap,
+        throttled_body: Option<&[u8]>,
+    ) -> RateLimitInfo {
+        let info = RateLimitInfo::from_headers(
+            headers
+                .iter()
+                .filter_map(|(name, value)| Some((name.as_str(), value.to_str().ok()?))),
+            chrono::Utc::now().timestamp(),
+        );
+        let info = match throttled_body {
+            Some(body) => info.throttled_with_body(body),
+            None => info,
+        };
+        self.pacing.observe(&info);
+        info
+    }
+
+    fn send_json(
+        &self,
+        method: &str,
+        url: &str,
+        body: Option<&str>,
+        options: RequestOptions,
+    ) -> Result<Value> {
+        let creds = self.creds();
+        let mut headers =
+            self.standard_headers(&creds, &token_of(&creds, EndpointFamily::of(url)))?;
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/x-www-form-urlencoded"),
+        );
+        headers.insert(ACCEPT, HeaderValue::from_static("application/json"));
+        headers.insert(
+            ACCEPT_ENCODING,
+            HeaderValue::from_static("gzip, deflate, br"),
+        );
+
+        let target = self.resolve(url);
+        tracing::debug!(
+            "{method} {target} [{}]",
+            crate::trace::redact_headers(&headers)
+        );
+        let request = match method {
+            "GET" => self.client.get(&target).headers(headers),
+            "POST" => self
+                .client
+                .post(&target)
+                .headers(headers)
+                .body(body.unwrap_or_default().to_string()),
+            _ => return Err(anyhow!("Unsupported HTTP method: {method}")),
+        };
+        let request = match options.timeout {
+            Some(timeout) => request.timeout(timeout),
+            None => request,
+        };
+
+        let started = Instant::now();
+        let response = request.send().map_err(|err| {
+            tracing::debug!(
+                "{method} {target} failed after {:?}: {err}",
+                started.elapsed()
+            );
+            let is_transient = err.is_connect() || err.is_timeout();
+            // The reqwest error stays in the chain: failover needs to tell a
+            // refused connection from a timeout.
+            anyhow::Error::new(err).context(OpenKakaoError::Network {
+                message: format!("HTTP request failed: {method} {target}"),
+                is_transient,
+            })
+        })?;
+        let http_status = response.status();
+        tracing::debug!(
+            "{method} {target} -> HTTP {} in {:?}",
+            http_status.as_u16(),
+            started.elapsed()
+        );
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_retry_after);
+        // A 429's hints are read with its body, which may hold the window.
+        let throttled_headers = (http_status.as_u16() == 429).then(|| response.headers().clone());
+        if throttled_headers.is_none() {
+            self.observe_rate_limit(response.headers(), None);
+        }
+        let encoding = response
+            .headers()
+            .get(CONTENT_ENCODING)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_ascii_lowercase);
+        let limit = self.body_limit_for(url);
+        // Counted before decoding, so a compressed answer costs its wire size.
+        let counted = CountingReader::new(response, self.meter.clone());
+        let mut reader = BoundedReader::new(decode_body(encoding.as_deref(), counted), limit);
+
+        if !http_status.is_success() {
+            let mut raw = Vec::new();
+            if let Err(err) = reader.read_to_end(&mut raw) {
+                return Err(self.stream_error(url, err).into());
+            }
+            tracing::trace!(
+                "{method} {target} body: {}",
+                crate::trace::body_excerpt(&raw, crate::trace::BODY_EXCERPT_LIMIT)
+            );
+            let retry_after = match &throttled_headers {
+                Some(headers) => self.observe_rate_limit(headers, Some(&raw)).retry_after,
+                None => retry_after,
+            };
+            let err = OpenKakaoError::from_http(http_status.as_u16(), &raw, retry_after);
+            if matches!(
+                err,
+                OpenKakaoError::Unauthorized { .. } | OpenKakaoError::Forbidden { .. }
+            ) {
+                self.record_scope(EndpointFamily::of(url), false);
+            }
+            if let (OpenKakaoError::RestApi { status, .. }, Some(observer)) = (&err, observer()) {
+                if let Ok(parsed) = serde_json::from_slice::<Value>(&raw) {
+                    observer.error_payload(url, *status, &parsed);
+                }
+            }
+            return Err(err.into());
+        }
+
+        let parsed = serde_json::from_reader::<_, Value>(BufReader::new(&mut reader));
+        if self.meter.exceeded() {
+            return Err(self.meter.cap_error().into());
+        }
+        if reader.exceeded() {
+            return Err(OpenKakaoError::Parse {
+                message: format!("Response too large: {method} {url} exceeded {limit} bytes"),
+            }
+            .into());
+        }
+        let parsed = parsed.map_err(|err| OpenKakaoError::Parse {
+            message: format!(
+                "Expected JSON from {method} {url} (HTTP {http_status}), got {}: {err}",
+                crate::error::body_snippet(reader.prefix())
+            ),
+        })?;
+        if tracing::enabled!(tracing::Level::TRACE) {
+            tracing::trace!(
+                "{method} {target} body: {}",
+                crate::trace::excerpt(
+                    &crate::trace::redact_json(&parsed).to_string(),
+                    crate::trace::BODY_EXCERPT_LIMIT
+                )
+            );
+        }
+
+        Ok(parsed)
+    }
+}
+
+/// Where a header that cannot be built from the credentials points.
+const LINT_HINT: &str = "check the saved credentials with 'openkakao-rs credentials lint'";
+
+/// Typed error for a non-zero `status` in a JSON response. The raw payload
+/// goes to the observer first.
+fn status_error(label: &str, status: i64, parsed: &Value) -> OpenKakaoError {
+    if let Some(observer) = observer() {
+        observer.error_payload(label, status, parsed);
+    }
+    let message = parsed
+        .get("message")
+        .or_else(|| parsed.get("msg"))
+        .and_then(Value::as_str)
+        .unwrap_or("")
+        .to_string();
+    OpenKakaoError::RestApi { status, message }
+}
+
+/// Status the Kakao ID search answers with when no account uses the ID.
+const KAKAO_ID_NOT_FOUND: i64 = -1001;
+
+fn parse_kakao_id_search(parsed: &Value) -> Result<Option<KakaoIdMatch>, OpenKakaoError> {
+    let status = parsed.get("status").and_then(Value::as_i64).unwrap_or(0);
+    if status == KAKAO_ID_NOT_FOUND {
+        return Ok(None);
+    }
+    if status != 0 {
+        return Err(status_error("find_by_uuid", status, parsed));
+    }
+    Ok(parsed
+        .get("member")
+        .filter(|m| json_i64(m, "userId") != 0)
+        .map(KakaoIdMatch::from_json))
+}
+
+fn is_kakao_host(host: &str) -> bool {
+    host.ends_with(".kakao.com") || host.ends_with(".kakaocdn.net")
+}
+
+/// `raw` decoded per its `Content-Encoding`. reqwest's own decompression
+/// is off so that [`CountingReader`] below this sees the compressed bytes.
+fn decode_body<'a>(encoding: Option<&str>, raw: impl Read + 'a) -> Box<dyn Read + 'a> {
+    match encoding {
+        Some("gzip" | "x-gzip") => Box::new(flate2::read::MultiGzDecoder::new(raw)),
+        Some("deflate") => Box::new(flate2::read::ZlibDecoder::new(raw)),
+        Some("br") => Box::new(brotli::Decompressor::new(raw, 4096)),
+        _ => Box::new(raw),
+    }
+}
+
+/// Reader that fails once more than `limit` bytes have been read and keeps
+/// the first few KB so parse errors can still show what came back.
+struct BoundedReader<R> {
+    inner: R,
+    limit: usize,
+    read: usize,
+    exceeded: bool,
+    prefix: Vec<u8>,
+}
+
+impl<R: Read> BoundedReader<R> {
+    fn new(inner: R, limit: usize) -> Self {
+        Self {
+            inner,
+            limit,
+            read: 0,
+            exceeded: false,
+            prefix: Vec::new(),
+        }
+    }
+
+    fn exceeded(&self) -> bool {
+        self.exceeded
+    }
+
+    fn prefix(&self) -> &[u8] {
+        &self.prefix
+    }
+}
+
+impl<R: Read> Read for BoundedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.exceeded {
+            return Err(std::io::Error::other("response body exceeds size limit"));
+        }
+        // Allow one byte past the limit so an exactly-sized body is accepted.
+        let allowed = self
+            .limit
+            .saturating_add(1)
+            .saturating_sub(self.read)
+            .min(buf.len());
+        let n = self.inner.read(&mut buf[..allowed])?;
+        self.read += n;
+        if self.read > self.limit {
+            self.exceeded = true;
+            return Err(std::io::Error::other("response body exceeds size limit"));
+        }
+        if self.prefix.len() < SNIPPET_BYTES {
+            let take = n.min(SNIPPET_BYTES - self.prefix.len());
+            self.prefix.extend_from_slice(&buf[..take]);
+        }
+        Ok(n)
+    }
+}
+
+impl Fetch for KakaoRestClient {
+    fn fetch(&self, url: &str, out: &mut PartialFile) -> Result<()> {
+        capture::note_request("rest", "GET", url);
+        let started = Instant::now();
+        let result = self.download_resumable(url, out);
+        if capture::is_active() {
+            capture::record(capture::Exchange {
+                transport: "rest",
+                method: "GET".to_string(),
+                endpoint: url.to_string(),
+                status: result.as_ref().ok().map(|_| 0),
+                latency: started.elapsed(),
+                error: result.as_ref().err().map(|e| format!("{e:#}")),
+                ..Default::default()
+            });
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Instant;
+
+    /// A client whose 429/5xx retries wait milliseconds, not seconds.
+    fn test_client() -> KakaoRestClient {
+        KakaoRestClient::new(KakaoCredentials::new(
+            "token".into(),
+            1,
+            "uuid".into(),
+            "3.7.0".into(),
+            "agent".into(),
+            "mac/3.7.0/ko".into(),
+        ))
+        .unwrap()
+        .with_retry_policy(RetryPolicy {
+            backoff_base: Duration::from_millis(1),
+            jitter: 0.0,
+            ..RetryPolicy::default()
+        })
+        .with_pacing(Arc::new(Pacing::new()))
+    }
+
+    /// Public `KakaoRestClient` methods, read from this file.
+    fn public_client_methods() -> Vec<&'static str> {
+        let source = include_str!("rest.rs");
+        let mut methods = Vec::new();
+        let mut in_client = false;
+        for line in source.lines() {
+            if line == "impl KakaoRestClient {" {
+                in_client = true;
+            } else if line == "}" {
+                in_client = false;
+            } else if let Some(rest) = line.strip_prefix("    pub fn ").filter(|_| in_client) {
+                methods.push(rest.split(['(', '<']).next().unwrap());
+            }
+        }
+        methods
+    }
+
+    #[test]
+    fn every_public_method_has_an_example() {
+        let source = include_str!("rest.rs");
+        let lines: Vec<&str> = source.lines().collect();
+        let client = lines
+            .iter()
+            .position(|line| *line == "impl KakaoRestClient {")
+            .unwrap();
+        let mut checked = 0;
+        for method in public_client_methods() {
+            let at = client
+                + lines[client..]
+                    .iter()
+                    .position(|line| line.starts_with(&format!("    pub fn {method}(")))
+                    .unwrap();
+            let doc = lines[..at]
+                .iter()
+                .rev()
+                .skip_while(|line| line.starts_with("    #["))
+                .take_while(|line| line.starts_with("    ///"));
+            let mut doc = doc.peekable();
+            assert!(doc.peek().is_some(), "{method} has no documentation");
+            assert!(
+                doc.any(|line| line.starts_with("    /// ```")),
+                "{method} has no example"
+            );
+            checked += 1;
+        }
+        assert!(checked > 50, "only {checked} methods found");
+    }
+
+    #[test]
+    fn every_public_method_is_in_the_catalog() {
+        // Constructors, accessors and calls to arbitrary media URLs.
+        let not_endpoints = [
+            "new",
+            "builder",
+            "with_reauth",
+            "with_retry_policy",
+            "with_retry_deadline",
+            "with_transport",
+            "with_byte_meter",
+            "with_pacing",
+            "user_id",
+            "known_scope",
+            "replace_credentials",
+            "generate_xvc",
+            "download",
+            "get_bytes",
+        ];
+        let methods = public_client_methods();
+        assert!(methods.contains(&"get_messages"));
+        for method in methods {
+            if not_endpoints.contains(&method) {
+                continue;
+            }
+            assert!(
+                ENDPOINT_CATALOG
+                    .iter()
+                    .any(|spec| spec.methods.contains(&method)),
+                "{method} is missing from ENDPOINT_CATALOG"
+            );
+        }
+        for spec in ENDPOINT_CATALOG {
+            for method in spec.methods {
+                assert!(
+                    public_client_methods().contains(method),
+                    "{method} in ENDPOINT_CATALOG is not a public method"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn every_url_built_is_in_the_catalog() {
+        let source = include_str!("rest.rs");
+        let code = &source[..source.find("#[cfg(test)]\nmod tests").unwrap()];
+        let mut checked = 0;
+        for (prefix, base) in [
+            ("{BASE_URL}", BASE_URL),
+            ("{PILSNER_URL}", PILSNER_URL),
+            ("{MOIM_URL}", MOIM_URL),
+        ] {
+            for (at, _) in code.match_indices(&format!("\"{prefix}/")) {
+                let path = code[at + 1 + prefix.len()..]
+                    .split(['"', '?'])
+                    .next()
+                    .unwrap();
+                let url = format!("{base}{}", path.replace("{chat_id}", "123"));
+                assert!(
+                    ENDPOINT_CATALOG
+                        .iter()
+                        .any(|spec| spec.matches(spec.http_method, &url)),
+                    "{url} is missing from ENDPOINT_CATALOG"
+                );
+                checked += 1;
+            }
+        }
+        assert!(checked > 10);
+    }
+
+    #[test]
+    fn catalog_marks_side_effects_for_the_read_only_guard() {
+        let favorite = endpoint_for("POST", &format!("{BASE_URL}/mac/friends/add_favorite.json"));
+        assert_eq!(
+            favorite.and_then(|spec| spec.side_effect),
+            Some("changing the friends list")
+        );
+        let messages = endpoint_for(
+            "GET",
+            &format!("{PILSNER_URL}/messaging/chats/-42/messages?cursor=9"),
+        )
+        .unwrap();
+        assert_eq!(messages.side_effect, None);
+        assert!(messages.methods.contains(&"get_messages"));
+        assert!(endpoint_for("POST", &format!("{PILSNER_URL}/messaging/chats")).is_none());
+        assert!(endpoint_for("GET", &format!("{PILSNER_URL}/messaging/chats/1/other")).is_none());
+
+        assert!(side_effect_of("find_by_kakao_id").is_some());
+        assert!(side_effect_of("hide_friend").is_some());
+        assert_eq!(side_effect_of("get_friends"), None);
+
+        let json = favorite.unwrap().to_json();
+        assert_eq!(json["mutates"], true);
+        assert_eq!(json["base"], "katalk");
+        assert_eq!(json["commands"][0], "favorite");
+    }
+
+    #[test]
+    fn endpoints_are_redacted() {
+        assert_eq!(
+            redact_endpoint("https://katalk.kakao.com/mac/chats/1234567/messages.json?cursor=9"),
+            "/mac/chats/:id/messages.json"
+        );
+        assert_eq!(
+            redact_endpoint("https://talk-pilsner.kakao.com/messaging/chats/-98765"),
+            "/messaging/chats/:id"
+        );
+    }
+
+    #[test]
+    fn endpoint_family_follows_host() {
+        assert_eq!(
+            EndpointFamily::of(&format!("{PILSNER_URL}/messaging/chats")),
+            EndpointFamily::Pilsner
+        );
+        assert_eq!(
+            EndpointFamily::of(&format!("{BASE_URL}/mac/profile3/me.json")),
+            EndpointFamily::Katalk
+        );
+    }
+
+    #[test]
+    fn token_scope_is_cached_per_token_and_family() {
+        let mut creds = KakaoCredentials::new(
+            "scope-test-token".into(),
+            1,
+            "uuid".into(),
+            "3.7.0".into(),
+            "agent".into(),
+            "mac/3.7.0/ko".into(),
+        );
+        let client = KakaoRestClient::new(creds.clone()).unwrap();
+        assert_eq!(client.known_scope(), TokenScope::default());
+
+        client.record_scope(EndpointFamily::Katalk, true);
+        client.record_scope(EndpointFamily::Pilsner, false);
+        let scope = client.known_scope();
+        assert_eq!(scope.summary(), "katalk=ok, pilsner=rejected");
+        assert!(!scope.is_complete());
+        assert_eq!(scope.rejected(), vec![EndpointFamily::Pilsner]);
+
+        // A separate pilsner bearer token is a different credential there.
+        creds.rest_token = Some("scope-test-bearer".into());
+        let other = KakaoRestClient::new(creds).unwrap();
+        assert_eq!(other.known_scope().katalk, Some(true));
+        assert_eq!(other.known_scope().pilsner, None);
+        assert!(other.known_scope().is_complete());
+    }
+
+    #[test]
+    fn per_request_timeout_overrides_client_default() {
+        // Server that accepts and then never answers within the test window.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            if let Ok((stream, _)) = listener.accept() {
+                std::thread::sleep(Duration::from_secs(5));
+                drop(stream);
+            }
+        });
+
+        let started = Instant::now();
+        let result = test_client().request_raw_with(
+            "GET",
+            &format!("http://{addr}/slow"),
+            None,
+            RequestOptions {
+                timeout: Some(Duration::from_millis(300)),
+            },
+        );
+        assert!(result.is_err());
+        assert!(started.elapsed() < Duration::from_secs(3));
+    }
+
+    /// Local mirror answering every request with `status_line` and a small
+    /// JSON body; returns its base URL and a count of non-probe requests.
+    fn mirror(status_line: &'static str) -> (String, std::sync::Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let hits = std::sync::Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut buf = [0u8; 4096];
+                let n = stream.read(&mut buf).unwrap_or(0);
+                if !buf[..n].starts_with(b"HEAD") {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                }
+                let body = r#"{"status":0}"#;
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {status_line}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+            }
+        });
+        (base, hits)
+    }
+
+    fn dead_mirror() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}", listener.local_addr().unwrap())
+    }
+
+    #[test]
+    fn connect_failure_fails_over_to_next_mirror() {
+        let dead = dead_mirror();
+        let (live, hits) = mirror("200 OK");
+        let pool = Mutex::new(EndpointPool::new(&[dead.clone(), live.clone()]));
+        let client = test_client();
+
+        let value = client
+            .send_with_failover(
+                &pool,
+                "GET",
+                "/mac/profile3/me.json",
+                None,
+                RequestOptions::default(),
+            )
+            .unwrap();
+        assert_eq!(value["status"], 0);
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+        let pool = lock_pool(&pool);
+        // The probe already ranked the dead mirror last.
+        assert_eq!(pool.candidates()[0], live);
+        assert!(pool.summary().contains(&format!("{dead} (unreachable)")));
+    }
+
+    /// Server answering the n-th request with the n-th `(status line,
+    /// extra headers)` pair (the last one repeats) and a small JSON body;
+    /// returns its base URL and the number of requests served.
+    fn scripted_server(
+        replies: Vec<(&'static str, &'static str)>,
+    ) -> (String, std::sync::Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let hits = std::sync::Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut buf = [0u8; 4096];
+                let _ = stream.read(&mut buf);
+                let n = counter.fetch_add(1, Ordering::SeqCst);
+                let (status_line, headers) = replies[n.min(replies.len() - 1)];
+                let body = r#"{"status":0}"#;
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {status_line}\r\n{headers}Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+            }
+        });
+        (base, hits)
+    }
+
+    #[test]
+    fn rate_limit_is_retried_after_the_advertised_wait() {
+        let (base, hits) = scripted_server(vec![
+            ("429 Too Many Requests", "Retry-After: 0\r\n"),
+            ("200 OK", "Content-Type: application/json\r\n"),
+        ]);
+        let value = test_client()
+            .request_raw_with("GET", &format!("{base}/x"), None, RequestOptions::default())
+            .unwrap();
+        assert_eq!(value["status"], 0);
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn server_errors_are_retried_a_bounded_number_of_times() {
+        let (base, hits) = scripted_server(vec![("503 Service Unavailable", "")]);
+        let err = test_client()
+            .request_raw_with("GET", &format!("{base}/x"), None, RequestOptions::default())
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<OpenKakaoError>(),
+            Some(OpenKakaoError::Http { status: 503, .. })
+        ));
+        assert_eq!(
+            hits.load(Ordering::SeqCst),
+            RetryPolicy::default().max_attempts as usize
+        );
+    }
+
+    #[test]
+    fn unauthorized_is_a_token_error_not_a_parse_error() {
+        let (base, hits) = scripted_server(vec![("401 Unauthorized", "")]);
+        let err = test_client()
+            .request_raw_with("GET", &format!("{base}/x"), None, RequestOptions::default())
+            .unwrap_err();
+        let text = format!("{err:#}");
+        assert!(text.contains("Token invalid"), "{text}");
+        assert!(!text.contains("parse"), "{text}");
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn retry_after_seconds_are_parsed_and_dates_left_to_backoff() {
+        assert_eq!(parse_retry_after(" 7 "), Some(Duration::from_secs(7)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2026 07:28:00 GMT"), None);
+    }
+
+    #[test]
+    fn http_error_status_does_not_fail_over() {
+        let (first, first_hits) = mirror("401 Unauthorized");
+        let (second, second_hits) = mirror("200 OK");
+        let pool = Mutex::new(EndpointPool::new(&[first.clone(), second]));
+        lock_pool(&pool).record_probe(&[
+            Some(Duration::from_millis(1)),
+            Some(Duration::from_millis(2)),
+        ]);
+
+        let result = test_client().send_with_failover(
+            &pool,
+            "GET",
+            "/mac/profile3/me.json",
+            None,
+            RequestOptions::default(),
+        );
+        assert!(result.is_err());
+        assert_eq!(first_hits.load(Ordering::SeqCst), 1);
+        assert_eq!(second_hits.load(Ordering::SeqCst), 0);
+        assert!(lock_pool(&pool).summary().ends_with("failovers=0"));
+    }
+
+    /// Local mirror that reads each request and never answers; returns its
+    /// base URL and a count of non-probe requests.
+    fn silent_mirror() -> (String, std::sync::Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let hits = std::sync::Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        std::thread::spawn(move || {
+            let mut held = Vec::new();
+            for mut stream in listener.incoming().flatten() {
+                let mut buf = [0u8; 4096];
+                let n = stream.read(&mut buf).unwrap_or(0);
+                if !buf[..n].starts_with(b"HEAD") {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                }
+                held.push(stream);
+            }
+        });
+        (base, hits)
+    }
+
+    /// A silent mirror ranked ahead of a live one.
+    fn silent_then_live() -> (
+        Mutex<EndpointPool>,
+        std::sync::Arc<AtomicUsize>,
+        std::sync::Arc<AtomicUsize>,
+    ) {
+        let (silent, silent_hits) = silent_mirror();
+        let (live, live_hits) = mirror("200 OK");
+        let pool = Mutex::new(EndpointPool::new(&[silent, live]));
+        lock_pool(&pool).record_probe(&[
+            Some(Duration::from_millis(1)),
+            Some(Duration::from_millis(2)),
+        ]);
+        (pool, silent_hits, live_hits)
+    }
+
+    #[test]
+    fn timed_out_mutation_is_sent_exactly_once() {
+        let (pool, silent_hits, live_hits) = silent_then_live();
+        let result = test_client().send_with_failover(
+            &pool,
+            "POST",
+            "/mac/friends/add_favorite.json",
+            Some("id=1"),
+            RequestOptions {
+                timeout: Some(Duration::from_millis(300)),
+            },
+        );
+        assert!(result.is_err());
+        assert_eq!(silent_hits.load(Ordering::SeqCst), 1);
+        assert_eq!(live_hits.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn timed_out_read_fails_over_to_next_mirror() {
+        let (pool, silent_hits, live_hits) = silent_then_live();
+        let value = test_client()
+            .send_with_failover(
+                &pool,
+                "GET",
+                "/mac/profile3/me.json",
+                None,
+                RequestOptions {
+                    timeout: Some(Duration::from_millis(300)),
+                },
+            )
+            .unwrap();
+        assert_eq!(value["status"], 0);
+        assert_eq!(silent_hits.load(Ordering::SeqCst), 1);
+        assert_eq!(live_hits.load(Ordering::SeqCst), 1);
+    }
+
+    /// Server accepting only `fresh-token`: status 0 for it, -950 (or
+    /// `rejection`, when set) for anything else. Counts every request.
+    fn auth_server(rejection: Option<&'static str>) -> (String, std::sync::Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let hits = std::sync::Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut buf = [0u8; 4096];
+                let n = stream.read(&mut buf).unwrap_or(0);
+                counter.fetch_add(1, Ordering::SeqCst);
+                let accepted = String::from_utf8_lossy(&buf[..n]).contains("fresh-token");
+                let (status_line, body) = match (accepted, rejection) {
+                    (true, _) => ("200 OK", r#"{"status":0}"#),
+                    (false, None) => ("200 OK", r#"{"status":-950}"#),
+                    (false, Some(status_line)) => (status_line, "{}"),
+                };
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {status_line}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+            }
+        });
+        (base, hits)
+    }
+
+    fn client_with_token(token: &str) -> KakaoRestClient {
+        let mut creds = (*test_client().creds()).clone();
+        creds.oauth_token = token.into();
+        KakaoRestClient::new(creds).unwrap()
+    }
+
+    /// Reauth double handing out `token` and counting its calls.
+    #[derive(Clone, Default)]
+    struct CountingReauth {
+        token: &'static str,
+        confirmed: std::sync::Arc<AtomicUsize>,
+        recovered: std::sync::Arc<AtomicUsize>,
+    }
+
+    impl Reauth for CountingReauth {
+        fn confirmed(&self) {
+            self.confirmed.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn recover(self: Box<Self>) -> Result<KakaoCredentials> {
+            self.recovered.fetch_add(1, Ordering::SeqCst);
+            Ok((*client_with_token(self.token).creds()).clone())
+        }
+    }
+
+    /// Server answering every request with `status_line` and `body`.
+    fn canned_server(status_line: &'static str, body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut buf = [0u8; 4096];
+                let _ = stream.read(&mut buf);
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {status_line}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+            }
+        });
+        base
+    }
+
+    fn checked(status_line: &'static str, body: &'static str) -> TokenStatus {
+        let url = format!(
+            "{}/mac/account/more_settings.json",
+            canned_server(status_line, body)
+        );
+        token_status(test_client().request_raw("POST", &url, Some("since=0")))
+    }
+
+    #[test]
+    fn token_status_maps_each_kind_of_answer() {
+        assert_eq!(checked("200 OK", r#"{"status":0}"#), TokenStatus::Valid);
+        assert_eq!(
+            checked("200 OK", r#"{"status":-950,"message":"expired"}"#),
+            TokenStatus::Invalid {
+                kakao_status: -950,
+                message: "expired".into()
+            }
+        );
+        // Logged in on another device.
+        assert!(matches!(
+            checked("200 OK", r#"{"status":-100}"#),
+            TokenStatus::Invalid {
+                kakao_status: -100,
+                ..
+            }
+        ));
+        assert!(matches!(
+            checked("401 Unauthorized", "{}"),
+            TokenStatus::Invalid {
+                kakao_status: -401,
+                ..
+            }
+        ));
+        assert!(matches!(
+            checked("401 Unauthorized", "<html><title>401 Authorization Required</title></html>"),
+            TokenStatus::Invalid {
+                kakao_status: -401,
+                message,
+            } if message == "HTML page \"401 Authorization Required\""
+        ));
+        assert_eq!(
+            checked("429 Too Many Requests", "{}"),
+            TokenStatus::RateLimited
+        );
+        assert_eq!(
+            checked("400 Bad Request", r#"{"status":-805}"#),
+            TokenStatus::RateLimited
+        );
+        assert!(matches!(
+            checked("200 OK", "<!DOCTYPE html><html></html>"),
+            TokenStatus::NetworkError { message } if message.contains("got HTML page")
+        ));
+        assert_eq!(
+            checked("200 OK", r#"{"status":-805}"#),
+            TokenStatus::RateLimited
+        );
+        assert_eq!(
+            checked("200 OK", r#"{"status":-303}"#),
+            TokenStatus::RateLimited
+        );
+        assert!(matches!(
+            checked("503 Service Unavailable", "{}"),
+            TokenStatus::NetworkError { .. }
+        ));
+        assert!(matches!(
+            checked("200 OK", r#"{"status":-9797}"#),
+            TokenStatus::NetworkError { .. }
+        ));
     }
 
-    pub fn get_alarm_keywords(&self) -> Result<Value> {
-        self.request(
-            "GET",
-            &format!("{BASE_URL}/mac/alarm_keywords/list.json"),
-            None,
-        )
+    #[test]
+    fn unreachable_server_is_a_network_error() {
+        // Bind and drop to get a port nothing listens on.
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let url = format!("http://127.0.0.1:{port}/mac/account/more_settings.json");
+        let status = token_status(test_client().request_raw("POST", &url, None));
+        assert!(matches!(status, TokenStatus::NetworkError { .. }));
+        assert!(status.reason().starts_with("could not reach Kakao"));
     }
 
-    pub fn get_chats(&self, cursor: Option<i64>) -> Result<(Vec<ChatRoom>, Option<i64>)> {
-        let url = if let Some(c) = cursor {
-            format!("{PILSNER_URL}/messaging/chats?cursor={c}")
-        } else {
-            format!("{PILSNER_URL}/messaging/chats")
+    #[test]
+    fn token_status_reasons_name_the_cause() {
+        let invalid = TokenStatus::Invalid {
+            kakao_status: -950,
+            message: String::new(),
         };
+        assert!(invalid.reason().contains("status -950"));
+        assert!(invalid.reason().contains("Authentication rejected"));
+        assert!(TokenStatus::RateLimited.reason().contains("rate limited"));
+    }
 
-        let r = self.request("GET", &url, None)?;
-        let mut rooms = Vec::new();
+    #[test]
+    fn lazy_verification_costs_no_extra_request() {
+        let (base, hits) = auth_server(None);
+        let url = format!("{base}/mac/profile3/me.json");
 
-        if let Some(chats) = r.get("chats").and_then(Value::as_array) {
-            for chat in chats {
-                rooms.push(ChatRoom::from_json(chat));
-            }
-        }
+        // Eager: the verify_token request first, then the command's request.
+        let eager = client_with_token("fresh-token");
+        let verify = format!("{base}/mac/account/more_settings.json");
+        eager
+            .request_raw("POST", &verify, Some("since=0&locale_country=KR"))
+            .unwrap();
+        eager.request_raw("GET", &url, None).unwrap();
+        assert_eq!(hits.swap(0, Ordering::SeqCst), 2);
 
-        let next_cursor = if r.get("last").and_then(Value::as_bool).unwrap_or(false) {
-            None
-        } else {
-            let n = json_i64(&r, "nextCursor");
-            if n == 0 {
-                None
-            } else {
-                Some(n)
-            }
+        let reauth = CountingReauth {
+            token: "fresh-token",
+            ..Default::default()
         };
-
-        Ok((rooms, next_cursor))
+        let lazy = client_with_token("fresh-token").with_reauth(Box::new(reauth.clone()));
+        lazy.request_raw("GET", &url, None).unwrap();
+        lazy.request_raw("GET", &url, None).unwrap();
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+        assert_eq!(reauth.confirmed.load(Ordering::SeqCst), 1);
+        assert_eq!(reauth.recovered.load(Ordering::SeqCst), 0);
     }
 
-    pub fn get_all_chats(&self) -> Result<Vec<ChatRoom>> {
-        let mut all = Vec::new();
-        let mut cursor: Option<i64> = None;
+    #[test]
+    fn rejected_token_reauths_once_and_retries() {
+        for rejection in [None, Some("401 Unauthorized")] {
+            let (base, hits) = auth_server(rejection);
+            let url = format!("{base}/mac/profile3/me.json");
+            let reauth = CountingReauth {
+                token: "fresh-token",
+                ..Default::default()
+            };
+            let client = client_with_token("stale-token").with_reauth(Box::new(reauth.clone()));
 
-        loop {
-            let (rooms, next_cursor) = self.get_chats(cursor)?;
-            all.extend(rooms);
-            if next_cursor.is_none() {
-                break;
-            }
-            cursor = next_cursor;
-        }
+            let value = client.request_raw("GET", &url, None).unwrap();
+            assert_eq!(value["status"], 0, "{rejection:?}");
+            assert_eq!(hits.load(Ordering::SeqCst), 2, "{rejection:?}");
+            assert_eq!(reauth.recovered.load(Ordering::SeqCst), 1);
+            assert_eq!(reauth.confirmed.load(Ordering::SeqCst), 0);
 
-        Ok(all)
+            // Later requests use the recovered token directly.
+            client.request_raw("GET", &url, None).unwrap();
+            assert_eq!(hits.load(Ordering::SeqCst), 3);
+        }
     }
 
-    pub fn get_chat_members(&self, chat_id: i64) -> Result<Vec<ChatMember>> {
-        let r = self.request(
-            "GET",
-            &format!("{PILSNER_URL}/messaging/chats/{chat_id}/members"),
-            None,
-        )?;
-
-        let mut members = Vec::new();
-        if let Some(arr) = r.get("members").and_then(Value::as_array) {
-            for member in arr {
-                members.push(ChatMember::from_json(member));
+    /// Server for a stale token on pilsner: `fresh-token` is accepted
+    /// everywhere, anything else gets 401 from `/messaging` and, from the
+    /// katalk token check, -950 unless `katalk_accepts`.
+    fn pilsner_auth_server(katalk_accepts: bool) -> (String, std::sync::Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let hits = std::sync::Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut buf = [0u8; 4096];
+                let n = stream.read(&mut buf).unwrap_or(0);
+                counter.fetch_add(1, Ordering::SeqCst);
+                let request = String::from_utf8_lossy(&buf[..n]);
+                let (status_line, body) = if request.contains("fresh-token") {
+                    ("200 OK", r#"{"status":0,"chats":[],"last":true}"#)
+                } else if request.contains("/messaging/") {
+                    ("401 Unauthorized", r#"{"reason":"UNAUTHENTICATED"}"#)
+                } else if katalk_accepts {
+                    ("200 OK", r#"{"status":0}"#)
+                } else {
+                    ("200 OK", r#"{"status":-950}"#)
+                };
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {status_line}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
             }
-        }
+        });
+        (base, hits)
+    }
 
-        Ok(members)
+    fn client_on(base: &str, token: &str) -> KakaoRestClient {
+        let mut creds = (*test_client().creds()).clone();
+        creds.oauth_token = token.into();
+        KakaoRestClient::builder(creds)
+            .options(HttpOptions {
+                base_url: Some(base.to_string()),
+                pilsner_url: Some(base.to_string()),
+                ..Default::default()
+            })
+            .build()
+            .unwrap()
     }
 
-    /// Get one page of messages. Returns (messages, next_cursor).
-    /// next_cursor=0 means no more pages.
-    ///
-    /// NOTE: `fromLogId` and `sinceMessageId` do NOT work for pagination.
-    /// Only `?cursor=` works.
-    pub fn get_messages(
-        &self,
-        chat_id: i64,
-        cursor: Option<i64>,
-    ) -> Result<(Vec<ChatMessage>, i64)> {
-        let url = if let Some(c) = cursor {
-            format!("{PILSNER_URL}/messaging/chats/{chat_id}/messages?cursor={c}")
-        } else {
-            format!("{PILSNER_URL}/messaging/chats/{chat_id}/messages")
+    #[test]
+    fn pilsner_rejection_confirmed_by_katalk_reauths() {
+        let (base, hits) = pilsner_auth_server(false);
+        let reauth = CountingReauth {
+            token: "fresh-token",
+            ..Default::default()
         };
+        let client = client_on(&base, "stale-token").with_reauth(Box::new(reauth.clone()));
 
-        let r = self.request("GET", &url, None)?;
+        let url = format!("{PILSNER_URL}/messaging/chats");
+        let value = client.request_raw("GET", &url, None).unwrap();
+        assert_eq!(value["status"], 0);
+        // The pilsner request, the katalk check, the retry.
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+        assert_eq!(reauth.recovered.load(Ordering::SeqCst), 1);
+        assert_eq!(reauth.confirmed.load(Ordering::SeqCst), 0);
+    }
 
-        let mut messages = Vec::new();
-        if let Some(arr) = r.get("chatLogs").and_then(Value::as_array) {
-            for msg in arr {
-                messages.push(ChatMessage::from_json(msg));
-            }
-        }
+    #[test]
+    fn pilsner_scope_refusal_keeps_a_token_katalk_accepts() {
+        let (base, hits) = pilsner_auth_server(true);
+        let reauth = CountingReauth {
+            token: "fresh-token",
+            ..Default::default()
+        };
+        let client = client_on(&base, "stale-token").with_reauth(Box::new(reauth.clone()));
 
-        let next_cursor = r.get("nextCursor").and_then(Value::as_i64).unwrap_or(0);
-        Ok((messages, next_cursor))
+        let url = format!("{PILSNER_URL}/messaging/chats");
+        assert!(client.request_raw("GET", &url, None).is_err());
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+        assert_eq!(reauth.recovered.load(Ordering::SeqCst), 0);
+        assert_eq!(reauth.confirmed.load(Ordering::SeqCst), 1);
     }
 
-    /// Fetch all available messages using cursor pagination.
-    ///
-    /// The pilsner server only caches messages for chats recently opened
-    /// in the KakaoTalk Mac app. Most chats will return empty results.
-    pub fn get_all_messages(&self, chat_id: i64, max_pages: usize) -> Result<Vec<ChatMessage>> {
-        let mut all = Vec::new();
-        let mut cursor: Option<i64> = None;
-
-        for _ in 0..max_pages {
-            let (messages, next_cursor) = self.get_messages(chat_id, cursor)?;
-            if messages.is_empty() {
-                break;
-            }
-            all.extend(messages);
-            if next_cursor == 0 {
-                break;
-            }
-            cursor = Some(next_cursor);
-        }
+    #[test]
+    fn failed_reauth_is_not_retried_again() {
+        let (base, hits) = auth_server(Some("401 Unauthorized"));
+        let url = format!("{base}/mac/profile3/me.json");
+        let reauth = CountingReauth {
+            token: "still-stale",
+            ..Default::default()
+        };
+        let client = client_with_token("stale-token").with_reauth(Box::new(reauth.clone()));
 
-        all.sort_by_key(|m| m.log_id);
-        all.dedup_by_key(|m| m.log_id);
-        Ok(all)
+        assert!(client.request_raw("GET", &url, None).is_err());
+        assert!(client.request_raw("GET", &url, None).is_err());
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+        assert_eq!(reauth.recovered.load(Ordering::SeqCst), 1);
     }
 
-    /// Attempt to renew the OAuth token using a refresh_token (legacy endpoint).
-    /// Returns the raw JSON response (may contain access_token, refresh_token, etc.)
-    pub fn renew_token(&self, refresh_token: &str) -> Result<Value> {
-        let encoded_token = urlencoding::encode(refresh_token);
-        let body = format!("grant_type=refresh_token&refresh_token={encoded_token}");
-        self.request_raw(
-            "POST",
-            &format!("{BASE_URL}/mac/account/renew_token.json"),
-            Some(&body),
-        )
+    #[test]
+    fn base_urls_must_be_https_kakao_hosts() {
+        assert!(set_base_urls(&["http://katalk.kakao.com".into()]).is_err());
+        assert!(set_base_urls(&["https://example.com".into()]).is_err());
+        // The default URL alone leaves requests on the direct path.
+        set_base_urls(&[format!("{BASE_URL}/")]).unwrap();
+        assert!(endpoint_summary().is_none());
     }
 
-    /// Attempt to refresh the OAuth token using oauth2_token.json (node-kakao style).
-    /// Sends both access_token and refresh_token as required by Kakao's OAuth.
-    pub fn oauth2_token(&self, refresh_token: &str) -> Result<Value> {
-        let access_token = urlencoding::encode(&self.creds.oauth_token);
-        let refresh = urlencoding::encode(refresh_token);
-        let body =
-            format!("grant_type=refresh_token&access_token={access_token}&refresh_token={refresh}");
-        self.request_raw(
-            "POST",
-            &format!("{BASE_URL}/mac/account/oauth2_token.json"),
-            Some(&body),
-        )
-    }
+    #[test]
+    fn http_options_read_env_vars_and_fill_gaps() {
+        let vars: HashMap<&str, &str> = [
+            ("OPENKAKAO_PROXY", " socks5h://127.0.0.1:1080 "),
+            ("OPENKAKAO_TIMEOUT", "2.5"),
+            ("OPENKAKAO_BASE_URL", ""),
+        ]
+        .into();
+        let env = HttpOptions::from_vars(|name| vars.get(name).map(|v| v.to_string())).unwrap();
+        assert_eq!(env.proxy.as_deref(), Some("socks5h://127.0.0.1:1080"));
+        assert_eq!(env.timeout, Some(Duration::from_millis(2500)));
+        assert_eq!(env.base_url, None);
 
-    /// Call login.json with cached credentials and X-VC header.
-    /// Returns the raw JSON response.
-    pub fn login_direct(
-        &self,
-        email: &str,
-        password: &str,
-        device_uuid: &str,
-        device_name: &str,
-        x_vc: &str,
-    ) -> Result<Value> {
-        let user_agent = if self.creds.user_agent.is_empty() {
-            format!("KT/{} Mc/26.1.0 ko", self.creds.app_version)
-        } else {
-            self.creds.user_agent.clone()
+        let flags = HttpOptions {
+            timeout: Some(Duration::from_secs(40)),
+            base_url: Some("http://127.0.0.1:9000".into()),
+            ..HttpOptions::default()
         };
-        self.login_direct_with_ua(email, password, device_uuid, device_name, x_vc, &user_agent)
-    }
+        let merged = flags.or(&env);
+        assert_eq!(merged.timeout, Some(Duration::from_secs(40)));
+        assert_eq!(merged.proxy, env.proxy);
+        assert!(merged.check().is_ok());
 
-    fn login_direct_with_ua(
-        &self,
-        email: &str,
-        password: &str,
-        device_uuid: &str,
-        device_name: &str,
-        x_vc: &str,
-        user_agent: &str,
-    ) -> Result<Value> {
-        let encoded_name = urlencoding::encode(device_name);
-        let encoded_uuid = urlencoding::encode(device_uuid);
-        let encoded_password = urlencoding::encode(password);
-        let encoded_email = urlencoding::encode(email);
-        let body = format!(
-            "device_name={encoded_name}&device_uuid={encoded_uuid}&email={encoded_email}&os_version=26.1.0&password={encoded_password}&permanent=1"
-        );
+        let bad =
+            HttpOptions::from_vars(|name| (name == "OPENKAKAO_TIMEOUT").then(|| "soon".into()));
+        assert!(bad.unwrap_err().to_string().contains("OPENKAKAO_TIMEOUT"));
+        assert!(parse_timeout_secs("0").is_err());
+        assert_eq!(parse_timeout_secs("30s"), Ok(Duration::from_secs(30)));
+    }
 
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            CONTENT_TYPE,
-            HeaderValue::from_static("application/x-www-form-urlencoded"),
-        );
-        headers.insert(ACCEPT, HeaderValue::from_static("application/json"));
-        headers.insert(ACCEPT_LANGUAGE, HeaderValue::from_static("ko"));
+    #[test]
+    fn base_url_overrides_are_kakao_hosts_or_loopback() {
+        let check = |url: &str| {
+            HttpOptions {
+                base_url: Some(url.into()),
+                ..HttpOptions::default()
+            }
+            .check()
+        };
+        assert!(check("https://katalk.kakao.com").is_ok());
+        assert!(check("http://localhost:8080/mock/").is_ok());
+        assert!(check("http://[::1]:8080").is_ok());
+        assert!(check("http://katalk.kakao.com").is_err());
+        assert!(check("https://example.com").is_err());
+        assert!(check("http://10.0.0.5:8080").is_err());
+        assert!(check("not a url").is_err());
 
-        let a_header = if self.creds.a_header.is_empty() {
-            format!("mac/{}/ko", self.creds.app_version)
-        } else {
-            self.creds.a_header.clone()
+        let proxy = |url: &str| {
+            HttpOptions {
+                proxy: Some(url.into()),
+                ..HttpOptions::default()
+            }
+            .check()
         };
-        headers.insert(
-            "A",
-            HeaderValue::from_str(&a_header).context("Invalid A header")?,
-        );
+        assert!(proxy("http://user:pw@proxy.local:3128").is_ok());
+        assert!(proxy("socks5://127.0.0.1:1080").is_ok());
+        assert!(proxy("ftp://proxy.local").is_err());
+        assert!(proxy("proxy.local:3128").is_err());
+    }
 
-        headers.insert(
-            "User-Agent",
-            HeaderValue::from_str(user_agent).context("Invalid User-Agent header")?,
+    #[test]
+    fn overridden_hosts_keep_the_path_and_the_endpoint_family() {
+        let client = KakaoRestClient::builder(KakaoCredentials::new(
+            "token".into(),
+            1,
+            "d".into(),
+            "26.1.0".into(),
+            String::new(),
+            String::new(),
+        ))
+        .base_url("http://127.0.0.1:9000/katalk/")
+        .pilsner_url("http://127.0.0.1:9000/pilsner")
+        .build()
+        .unwrap();
+        assert_eq!(
+            client.resolve(&format!("{BASE_URL}/mac/profile3/me.json")),
+            "http://127.0.0.1:9000/katalk/mac/profile3/me.json"
         );
+        assert_eq!(
+            client.resolve(&format!("{PILSNER_URL}/messaging/chats?cursor=7")),
+            "http://127.0.0.1:9000/pilsner/messaging/chats?cursor=7"
+        );
+        let cdn = "https://dn-m.talk.kakao.com/talkm/a.jpg";
+        assert_eq!(client.resolve(cdn), cdn);
+    }
 
-        if !x_vc.is_empty() {
-            headers.insert(
-                "X-VC",
-                HeaderValue::from_str(x_vc).context("Invalid X-VC header")?,
-            );
-        }
+    fn large_fixture(members: usize) -> Vec<u8> {
+        let list: Vec<Value> = (0..members)
+            .map(|i| serde_json::json!({"userId": i, "nickName": format!("member-{i}")}))
+            .collect();
+        serde_json::to_vec(&serde_json::json!({"status": 0, "members": list})).unwrap()
+    }
 
-        let response = self
-            .client
-            .post(format!("{BASE_URL}/mac/account/login.json"))
-            .headers(headers)
-            .body(body)
-            .send()
-            .context("login.json request failed")?;
+    #[test]
+    fn bounded_reader_parses_body_within_limit() {
+        let body = large_fixture(10_000);
+        let mut reader = BoundedReader::new(Cursor::new(body.clone()), body.len());
+        let parsed: Value = serde_json::from_reader(BufReader::new(&mut reader)).unwrap();
+        assert!(!reader.exceeded());
+        assert_eq!(parsed["members"].as_array().unwrap().len(), 10_000);
+    }
 
-        let text = response.text().context("Failed to read response")?;
-        let parsed: Value = serde_json::from_str(&text).with_context(|| {
-            format!(
-                "Failed to parse login response: {}",
-                &text[..200.min(text.len())]
-            )
-        })?;
+    #[test]
+    fn bounded_reader_without_a_limit_does_not_overflow() {
+        let body = large_fixture(100);
+        let mut reader = BoundedReader::new(Cursor::new(body), usize::MAX);
+        let parsed: Value = serde_json::from_reader(BufReader::new(&mut reader)).unwrap();
+        assert!(!reader.exceeded());
+        assert_eq!(parsed["members"].as_array().unwrap().len(), 100);
+    }
 
-        Ok(parsed)
+    #[test]
+    fn bounded_reader_rejects_oversized_body() {
+        let body = large_fixture(10_000);
+        let mut reader = BoundedReader::new(Cursor::new(body.clone()), body.len() - 1);
+        let parsed = serde_json::from_reader::<_, Value>(BufReader::new(&mut reader));
+        assert!(parsed.is_err());
+        assert!(reader.exceeded());
     }
 
-    pub fn get_settings(&self) -> Result<Value> {
-        self.request(
-            "POST",
-            &format!("{BASE_URL}/mac/account/more_settings.json"),
-            Some("since=0&locale_country=KR"),
-        )
+    #[test]
+    fn bounded_reader_keeps_snippet_for_parse_errors() {
+        let body = b"<html>gateway error</html>".to_vec();
+        let mut reader = BoundedReader::new(Cursor::new(body), 1024);
+        assert!(serde_json::from_reader::<_, Value>(BufReader::new(&mut reader)).is_err());
+        assert!(!reader.exceeded());
+        assert!(reader.prefix().starts_with(b"<html>"));
+        assert_eq!(crate::error::body_snippet(reader.prefix()), "HTML page");
     }
 
-    pub fn get_scrap_preview(&self, url: &str) -> Result<Value> {
-        let encoded = urlencoding::encode(url);
-        let body = format!("url={encoded}");
-        self.request(
-            "POST",
-            &format!("{BASE_URL}/mac/scrap/preview.json"),
-            Some(&body),
-        )
+    /// Writer that only counts, remembering the largest single write.
+    #[derive(Default)]
+    struct CountingWriter {
+        total: u64,
+        largest_write: usize,
     }
 
-    /// Generate X-VC header for Mac KakaoTalk.
-    /// Algorithm: SHA-512("YLLAS|{loginId}|{uuid}|GRAEB|{userAgent}")[0:16]
-    pub fn generate_xvc(user_agent: &str, login_id: &str, device_uuid: &str) -> String {
-        let input = format!("YLLAS|{login_id}|{device_uuid}|GRAEB|{user_agent}");
-        let h = hex::encode(Sha512::digest(input.as_bytes()));
-        h[..16].to_string()
+    impl Write for CountingWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.total += buf.len() as u64;
+            self.largest_write = self.largest_write.max(buf.len());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
     }
 
-    /// Login using the Mac X-VC algorithm.
-    /// Always uses the short User-Agent format ("KT/{ver} Mc/{os} ko") for both
-    /// the X-VC hash and the request header, matching what the real app sends.
-    pub fn login_with_xvc(
-        &self,
-        email: &str,
-        password: &str,
-        device_uuid: &str,
-        device_name: &str,
-    ) -> Result<Value> {
-        let user_agent = format!("KT/{} Mc/26.1.0 ko", self.creds.app_version);
+    #[test]
+    fn download_streams_large_body_without_buffering() {
+        const BODY_LEN: usize = 16 * 1024 * 1024;
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut byte = [0u8; 1];
+            while !request.ends_with(b"\r\n\r\n") && stream.read(&mut byte).unwrap_or(0) == 1 {
+                request.push(byte[0]);
+            }
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: image/jpeg\r\nContent-Length: {BODY_LEN}\r\n\r\n"
+            );
+            stream.write_all(head.as_bytes()).unwrap();
+            let chunk = vec![0xAB; 64 * 1024];
+            for _ in 0..BODY_LEN / chunk.len() {
+                if stream.write_all(&chunk).is_err() {
+                    return;
+                }
+            }
+        });
 
-        let xvc = Self::generate_xvc(&user_agent, email, device_uuid);
-        self.login_direct_with_ua(email, password, device_uuid, device_name, &xvc, &user_agent)
+        let mut sink = CountingWriter::default();
+        let info = client_on(&format!("http://{addr}"), "test-token")
+            .download(&format!("{BASE_URL}/photo.jpg"), &mut sink)
+            .unwrap();
+
+        assert_eq!(info.bytes, BODY_LEN as u64);
+        assert_eq!(info.content_length, Some(BODY_LEN as u64));
+        assert_eq!(info.content_type.as_deref(), Some("image/jpeg"));
+        assert_eq!(info.final_url, format!("http://{addr}/photo.jpg"));
+        assert_eq!(sink.total, BODY_LEN as u64);
+        assert!(sink.largest_write < BODY_LEN / 16);
     }
 
-    fn request(&self, method: &str, url: &str, body: Option<&str>) -> Result<Value> {
-        let parsed = self.request_raw(method, url, body)?;
-        if let Some(status) = parsed.get("status").and_then(Value::as_i64) {
-            if status != 0 {
-                let message = parsed
-                    .get("message")
-                    .or_else(|| parsed.get("msg"))
-                    .and_then(Value::as_str)
-                    .unwrap_or("")
-                    .to_string();
-                return Err(OpenKakaoError::RestApi { status, message }.into());
+    /// Local server answering every request with `headers` and `body`
+    /// as-is; returns its base URL and how many requests reached it.
+    fn raw_server(headers: String, body: Vec<u8>) -> (String, std::sync::Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let hits = std::sync::Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut buf = [0u8; 4096];
+                let _ = stream.read(&mut buf);
+                counter.fetch_add(1, Ordering::SeqCst);
+                let _ = stream.write_all(headers.as_bytes());
+                let _ = stream.write_all(&body);
             }
-        }
-        Ok(parsed)
+        });
+        (base, hits)
     }
 
-    fn request_raw(&self, method: &str, url: &str, body: Option<&str>) -> Result<Value> {
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            CONTENT_TYPE,
-            HeaderValue::from_static("application/x-www-form-urlencoded"),
+    #[test]
+    fn gzip_answers_count_at_their_wire_size() {
+        let plain = serde_json::json!({
+            "status": 0,
+            "chatLogs": vec![serde_json::json!({"logId": 1, "message": "hello"}); 200],
+        })
+        .to_string();
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(plain.as_bytes()).unwrap();
+        let gz = encoder.finish().unwrap();
+        assert!(gz.len() * 4 < plain.len());
+        let headers = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            gz.len()
         );
-        headers.insert(ACCEPT, HeaderValue::from_static("application/json"));
-        headers.insert(ACCEPT_LANGUAGE, HeaderValue::from_static("ko"));
+        let (base, _) = raw_server(headers, gz.clone());
 
-        // Use rest_token for pilsner endpoints, oauth_token for katalk endpoints
-        let token = if url.starts_with(PILSNER_URL) {
-            self.creds
-                .rest_token
-                .as_deref()
-                .unwrap_or(&self.creds.oauth_token)
-        } else {
-            &self.creds.oauth_token
-        };
-        let auth = HeaderValue::from_str(token).context("Invalid Authorization header")?;
-        headers.insert(AUTHORIZATION, auth);
+        let meter = Arc::new(ByteMeter::new());
+        let client = test_client().with_byte_meter(meter.clone());
+        let value = client
+            .request_raw("GET", &format!("{base}/chats"), None)
+            .unwrap();
+        assert_eq!(value["chatLogs"].as_array().map(Vec::len), Some(200));
+        assert_eq!(meter.received(), gz.len() as u64);
+    }
 
-        let a_header = if self.creds.a_header.is_empty() {
-            format!("mac/{}/ko", self.creds.app_version)
-        } else {
-            self.creds.a_header.clone()
-        };
-        headers.insert(
-            "A",
-            HeaderValue::from_str(&a_header).context("Invalid A header")?,
+    #[test]
+    fn byte_cap_stops_requests_and_oversized_downloads_before_reading() {
+        let body = vec![0xAB; 1000];
+        let headers = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            body.len()
         );
+        let (base, hits) = raw_server(headers, body);
 
-        let user_agent = if self.creds.user_agent.is_empty() {
-            format!("KT/{} Mc/26.1.0 ko", self.creds.app_version)
-        } else {
-            self.creds.user_agent.clone()
-        };
-        headers.insert(
-            "User-Agent",
-            HeaderValue::from_str(&user_agent).context("Invalid User-Agent header")?,
+        let meter = Arc::new(ByteMeter::with_max(600));
+        let client = client_on(&base, "test-token").with_byte_meter(meter.clone());
+        let mut sink = Vec::new();
+        let err = client
+            .download(&format!("{BASE_URL}/photo.jpg"), &mut sink)
+            .unwrap_err();
+        assert!(
+            matches!(err, OpenKakaoError::ByteCapReached { max: 600, .. }),
+            "{err}"
         );
+        assert!(sink.is_empty());
+        assert_eq!(meter.received(), 0);
 
-        let request = match method {
-            "GET" => self.client.get(url).headers(headers),
-            "POST" => self
-                .client
-                .post(url)
-                .headers(headers)
-                .body(body.unwrap_or_default().to_string()),
-            _ => return Err(anyhow!("Unsupported HTTP method: {method}")),
-        };
-
-        let response = request
-            .send()
-            .with_context(|| format!("HTTP request failed: {method} {url}"))?;
-        let http_status = response.status();
-        let text = response
-            .text()
-            .context("Failed to read HTTP response body")?;
+        // Once the cap is used up nothing else is sent.
+        meter.record(600);
+        let sent = hits.load(Ordering::SeqCst);
+        let err = client
+            .request_raw("GET", &format!("{base}/chats"), None)
+            .unwrap_err();
+        assert!(
+            matches!(err, OpenKakaoError::ByteCapReached { .. }),
+            "{err}"
+        );
+        assert_eq!(hits.load(Ordering::SeqCst), sent);
+    }
 
-        // Detect pilsner UNAUTHENTICATED (HTTP 401/403 or JSON reason field)
-        if !http_status.is_success() {
-            // Try to parse for a reason field
-            if let Ok(parsed) = serde_json::from_str::<Value>(&text) {
-                if parsed.get("reason").and_then(Value::as_str) == Some("UNAUTHENTICATED") {
-                    return Err(OpenKakaoError::RestApi {
-                        status: -(http_status.as_u16() as i64),
-                        message: "UNAUTHENTICATED: pilsner requires Cache.db bearer token".into(),
-                    }
-                    .into());
-                }
-            }
-            return Err(anyhow!("HTTP {}: {}", http_status.as_u16(), text));
-        }
+    #[test]
+    fn download_refuses_foreign_hosts() {
+        let err = test_client()
+            .download("https://example.com/x.jpg", &mut Vec::new())
+            .unwrap_err();
+        assert!(err.to_string().contains("non-Kakao domain"));
+    }
 
-        let parsed: Value = serde_json::from_str(&text).with_context(|| {
-            format!(
-                "Failed to parse JSON response (HTTP {http_status}): {}",
-                text.chars().take(200).collect::<String>()
-            )
-        })?;
+    #[test]
+    fn kakao_id_search_distinguishes_not_found_from_errors() {
+        let found = parse_kakao_id_search(&serde_json::json!({
+            "status": 0,
+            "member": {"userId": 5, "nickName": "Dana", "UUID": "dana"},
+        }))
+        .unwrap()
+        .expect("member present");
+        assert_eq!(found.nickname, "Dana");
 
-        Ok(parsed)
+        let missing = serde_json::json!({"status": KAKAO_ID_NOT_FOUND});
+        assert!(parse_kakao_id_search(&missing).unwrap().is_none());
+        assert!(parse_kakao_id_search(&serde_json::json!({"status": 0}))
+            .unwrap()
+            .is_none());
+        assert!(parse_kakao_id_search(&serde_json::json!({"status": -500})).is_err());
     }
 }
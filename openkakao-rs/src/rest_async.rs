@@ -0,0 +1,527 @@
+//! [`AsyncKakaoRestClient`]: the REST client for tokio programs.
+//!
+//! Each method runs the [`KakaoRestClient`] method of the same name on
+//! tokio's blocking pool. Headers, retries, re-auth, the response cache and
+//! JSON parsing are therefore the blocking client's own and cannot drift
+//! from it; a test fails when a public blocking method has no async
+//! counterpart here.
+
+use std::io::Write;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use serde_json::Value;
+use tokio::sync::Semaphore;
+use tokio::task::{self, JoinSet};
+
+use crate::cursor::{ChatListCursor, MessageCursor};
+use crate::error::OpenKakaoError;
+use crate::model::{
+    BoardComment, BoardPage, BoardPost, ChatMember, ChatMembersPage, ChatMessage, ChatRoom, Friend,
+    Identity, KakaoCredentials, KakaoIdMatch, MemberProfile, MyProfile, OpenLink, OpenLinkRef,
+};
+use crate::progress::{NoProgress, ProgressSink};
+use crate::rest::{
+    DownloadInfo, KakaoRestClient, KakaoRestClientBuilder, RequestOptions, TokenScope, TokenStatus,
+};
+
+/// A `concurrency` for [`AsyncKakaoRestClient::get_all_chats_with_members`]
+/// that stays well clear of the server's rate limits.
+pub const DEFAULT_MEMBER_CONCURRENCY: usize = 4;
+
+/// A cheaply cloneable handle; clones share one [`KakaoRestClient`].
+///
+/// ```no_run
+/// use openkakao_rs::model::KakaoCredentials;
+/// use openkakao_rs::rest_async::{AsyncKakaoRestClient, DEFAULT_MEMBER_CONCURRENCY};
+///
+/// # async fn run() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+/// # let creds = KakaoCredentials::new("t".into(), 1, "d".into(), "26.1.0".into(), String::new(), String::new());
+/// let client = AsyncKakaoRestClient::new(creds).await?;
+/// for (chat, members) in client.get_all_chats_with_members(DEFAULT_MEMBER_CONCURRENCY).await? {
+///     match members {
+///         Ok(members) => println!("{}: {} members", chat.display_title(), members.len()),
+///         Err(err) => eprintln!("{}: {err}", chat.display_title()),
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct AsyncKakaoRestClient {
+    inner: Arc<Shared>,
+}
+
+/// The blocking client owns a runtime of its own, which tokio refuses to
+/// drop from async code; the last handle lets it go on a plain thread.
+struct Shared(Option<KakaoRestClient>);
+
+impl std::ops::Deref for Shared {
+    type Target = KakaoRestClient;
+
+    fn deref(&self) -> &KakaoRestClient {
+        self.0.as_ref().expect("client is only taken on drop")
+    }
+}
+
+impl Drop for Shared {
+    fn drop(&mut self) {
+        if let Some(client) = self.0.take() {
+            if tokio::runtime::Handle::try_current().is_ok() {
+                std::thread::spawn(move || drop(client));
+            }
+        }
+    }
+}
+
+impl From<KakaoRestClient> for AsyncKakaoRestClient {
+    /// Wrap a blocking client configured with its `with_*` builders. The
+    /// blocking client cannot be built on a runtime thread; build it inside
+    /// `spawn_blocking` or before the runtime starts.
+    fn from(client: KakaoRestClient) -> Self {
+        Self {
+            inner: Arc::new(Shared(Some(client))),
+        }
+    }
+}
+
+impl AsyncKakaoRestClient {
+    /// A client for `creds`, built on the blocking pool. Nothing is sent
+    /// until the first request.
+    pub async fn new(creds: KakaoCredentials) -> Result<Self, OpenKakaoError> {
+        Self::from_builder(KakaoRestClient::builder(creds)).await
+    }
+
+    /// [`KakaoRestClientBuilder::build`] on the blocking pool, for a proxy,
+    /// timeout or base URL other than the defaults.
+    pub async fn from_builder(builder: KakaoRestClientBuilder) -> Result<Self, OpenKakaoError> {
+        let client = task::spawn_blocking(move || builder.build())
+            .await
+            .map_err(|err| anyhow!("REST task failed: {err}"))??;
+        Ok(client.into())
+    }
+
+    /// The blocking client underneath, for code already off the runtime.
+    pub fn blocking(&self) -> &KakaoRestClient {
+        &self.inner
+    }
+
+    /// Run `call` on the blocking pool.
+    async fn run<T, F>(&self, call: F) -> Result<T, OpenKakaoError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&KakaoRestClient) -> Result<T, OpenKakaoError> + Send + 'static,
+    {
+        let client = Arc::clone(&self.inner);
+        task::spawn_blocking(move || call(&client))
+            .await
+            .map_err(|err| anyhow!("REST task failed: {err}"))?
+    }
+
+    pub fn user_id(&self) -> i64 {
+        self.inner.user_id()
+    }
+
+    pub async fn check_token(&self) -> TokenStatus {
+        let client = Arc::clone(&self.inner);
+        task::spawn_blocking(move || client.check_token())
+            .await
+            .unwrap_or_else(|err| TokenStatus::NetworkError {
+                message: format!("REST task failed: {err}"),
+            })
+    }
+
+    pub async fn verify_token(&self) -> Result<bool, OpenKakaoError> {
+        self.run(|client| client.verify_token()).await
+    }
+
+    pub async fn verify_pilsner_token(&self) -> Result<bool, OpenKakaoError> {
+        self.run(|client| client.verify_pilsner_token()).await
+    }
+
+    pub fn known_scope(&self) -> TokenScope {
+        self.inner.known_scope()
+    }
+
+    pub fn replace_credentials(&self, creds: KakaoCredentials) -> KakaoCredentials {
+        self.inner.replace_credentials(creds)
+    }
+
+    pub async fn probe_scope(&self) -> TokenScope {
+        let client = Arc::clone(&self.inner);
+        task::spawn_blocking(move || client.probe_scope())
+            .await
+            .unwrap_or_default()
+    }
+
+    pub async fn get_my_profile(&self) -> Result<MyProfile, OpenKakaoError> {
+        self.run(|client| client.get_my_profile()).await
+    }
+
+    pub async fn get_identity(&self) -> Result<Identity, OpenKakaoError> {
+        self.run(|client| client.get_identity()).await
+    }
+
+    pub async fn backfill_user_id(&self) -> Result<i64, OpenKakaoError> {
+        self.run(|client| client.backfill_user_id()).await
+    }
+
+    pub async fn get_friend_profile(&self, user_id: i64) -> Result<Value, OpenKakaoError> {
+        self.run(move |client| client.get_friend_profile(user_id))
+            .await
+    }
+
+    pub async fn get_member_profile(&self, user_id: i64) -> Result<MemberProfile, OpenKakaoError> {
+        self.run(move |client| client.get_member_profile(user_id))
+            .await
+    }
+
+    pub async fn get_profiles(&self) -> Result<Value, OpenKakaoError> {
+        self.run(|client| client.get_profiles()).await
+    }
+
+    pub async fn get_friends(&self) -> Result<Vec<Friend>, OpenKakaoError> {
+        self.run(|client| client.get_friends()).await
+    }
+
+    pub async fn find_by_kakao_id(
+        &self,
+        kakao_id: &str,
+    ) -> Result<Option<KakaoIdMatch>, OpenKakaoError> {
+        let kakao_id = kakao_id.to_string();
+        self.run(move |client| client.find_by_kakao_id(&kakao_id))
+            .await
+    }
+
+    pub async fn add_favorite(&self, user_id: i64) -> Result<Value, OpenKakaoError> {
+        self.run(move |client| client.add_favorite(user_id)).await
+    }
+
+    pub async fn remove_favorite(&self, user_id: i64) -> Result<Value, OpenKakaoError> {
+        self.run(move |client| client.remove_favorite(user_id))
+            .await
+    }
+
+    pub async fn hide_friend(&self, user_id: i64) -> Result<Value, OpenKakaoError> {
+        self.run(move |client| client.hide_friend(user_id)).await
+    }
+
+    pub async fn unhide_friend(&self, user_id: i64) -> Result<Value, OpenKakaoError> {
+        self.run(move |client| client.unhide_friend(user_id)).await
+    }
+
+    pub async fn get_alarm_keywords(&self) -> Result<Value, OpenKakaoError> {
+        self.run(|client| client.get_alarm_keywords()).await
+    }
+
+    pub async fn get_chats(
+        &self,
+        cursor: Option<ChatListCursor>,
+    ) -> Result<(Vec<ChatRoom>, Option<ChatListCursor>), OpenKakaoError> {
+        self.run(move |client| client.get_chats(cursor)).await
+    }
+
+    pub async fn get_all_chats(&self) -> Result<Vec<ChatRoom>, OpenKakaoError> {
+        self.run(|client| client.get_all_chats()).await
+    }
+
+    /// Every chat with its member list, fetching at most `concurrency`
+    /// member lists at a time (at least one). Chats keep the order of
+    /// [`Self::get_all_chats`]; a chat whose members could not be fetched
+    /// carries that error instead of failing the rest.
+    pub async fn get_all_chats_with_members(
+        &self,
+        concurrency: usize,
+    ) -> Result<Vec<(ChatRoom, Result<Vec<ChatMember>, OpenKakaoError>)>, OpenKakaoError> {
+        let chats = self.get_all_chats().await?;
+        let permits = Arc::new(Semaphore::new(concurrency.max(1)));
+        let mut fetches = JoinSet::new();
+        for (index, chat) in chats.iter().enumerate() {
+            let client = self.clone();
+            let permits = Arc::clone(&permits);
+            let chat_id = chat.chat_id;
+            fetches.spawn(async move {
+                let _permit = permits.acquire_owned().await;
+                (index, client.get_chat_members(chat_id).await)
+            });
+        }
+
+        let mut members: Vec<Option<Result<Vec<ChatMember>, OpenKakaoError>>> =
+            chats.iter().map(|_| None).collect();
+        while let Some(joined) = fetches.join_next().await {
+            let (index, result) = joined.map_err(|err| anyhow!("REST task failed: {err}"))?;
+            members[index] = Some(result);
+        }
+        Ok(chats
+            .into_iter()
+            .zip(members)
+            .map(|(chat, result)| {
+                let result = result.unwrap_or_else(|| Err(anyhow!("member fetch lost").into()));
+                (chat, result)
+            })
+            .collect())
+    }
+
+    pub async fn get_chat_members(&self, chat_id: i64) -> Result<Vec<ChatMember>, OpenKakaoError> {
+        self.run(move |client| client.get_chat_members(chat_id))
+            .await
+    }
+
+    pub async fn get_board_posts(
+        &self,
+        chat_id: i64,
+        cursor: Option<&str>,
+    ) -> Result<BoardPage<BoardPost>, OpenKakaoError> {
+        let cursor = cursor.map(str::to_string);
+        self.run(move |client| client.get_board_posts(chat_id, cursor.as_deref()))
+            .await
+    }
+
+    pub async fn get_board_post(&self, post_id: &str) -> Result<BoardPost, OpenKakaoError> {
+        let post_id = post_id.to_string();
+        self.run(move |client| client.get_board_post(&post_id))
+            .await
+    }
+
+    pub async fn get_board_comments(
+        &self,
+        post_id: &str,
+        cursor: Option<&str>,
+    ) -> Result<BoardPage<BoardComment>, OpenKakaoError> {
+        let post_id = post_id.to_string();
+        let cursor = cursor.map(str::to_string);
+        self.run(move |client| client.get_board_comments(&post_id, cursor.as_deref()))
+            .await
+    }
+
+    pub async fn get_chat_members_page(
+        &self,
+        chat_id: i64,
+        cursor: Option<&str>,
+    ) -> Result<ChatMembersPage, OpenKakaoError> {
+        let cursor = cursor.map(str::to_string);
+        self.run(move |client| client.get_chat_members_page(chat_id, cursor.as_deref()))
+            .await
+    }
+
+    /// See [`KakaoRestClient::get_chat_members_limited`]; `progress` is
+    /// called from the blocking pool.
+    pub async fn get_chat_members_limited(
+        &self,
+        chat_id: i64,
+        limit: Option<usize>,
+        progress: impl ProgressSink + Send + 'static,
+    ) -> Result<(Vec<ChatMember>, Option<i64>), OpenKakaoError> {
+        self.run(move |client| client.get_chat_members_limited(chat_id, limit, &progress))
+            .await
+    }
+
+    pub async fn get_messages(
+        &self,
+        chat_id: i64,
+        cursor: Option<MessageCursor>,
+    ) -> Result<(Vec<ChatMessage>, Option<MessageCursor>), OpenKakaoError> {
+        self.run(move |client| client.get_messages(chat_id, cursor))
+            .await
+    }
+
+    pub async fn get_messages_with_options(
+        &self,
+        chat_id: i64,
+        cursor: Option<MessageCursor>,
+        options: RequestOptions,
+    ) -> Result<(Vec<ChatMessage>, Option<MessageCursor>), OpenKakaoError> {
+        self.run(move |client| client.get_messages_with_options(chat_id, cursor, options))
+            .await
+    }
+
+    pub async fn get_recent_messages(
+        &self,
+        chat_id: i64,
+        cursor: Option<MessageCursor>,
+        count: usize,
+        max_pages: usize,
+    ) -> Result<(Vec<ChatMessage>, Option<MessageCursor>), OpenKakaoError> {
+        self.run(move |client| client.get_recent_messages(chat_id, cursor, count, max_pages))
+            .await
+    }
+
+    pub async fn get_all_messages(
+        &self,
+        chat_id: i64,
+        max_pages: usize,
+    ) -> Result<Vec<ChatMessage>, OpenKakaoError> {
+        self.get_all_messages_with_progress(chat_id, max_pages, NoProgress)
+            .await
+    }
+
+    /// See [`KakaoRestClient::get_all_messages_with_progress`]; `progress`
+    /// is called from the blocking pool.
+    pub async fn get_all_messages_with_progress(
+        &self,
+        chat_id: i64,
+        max_pages: usize,
+        progress: impl ProgressSink + Send + 'static,
+    ) -> Result<Vec<ChatMessage>, OpenKakaoError> {
+        self.run(move |client| client.get_all_messages_with_progress(chat_id, max_pages, &progress))
+            .await
+    }
+
+    pub async fn renew_token(&self, refresh_token: &str) -> Result<Value, OpenKakaoError> {
+        let refresh_token = refresh_token.to_string();
+        self.run(move |client| client.renew_token(&refresh_token))
+            .await
+    }
+
+    pub async fn oauth2_token(&self, refresh_token: &str) -> Result<Value, OpenKakaoError> {
+        let refresh_token = refresh_token.to_string();
+        self.run(move |client| client.oauth2_token(&refresh_token))
+            .await
+    }
+
+    pub async fn login_direct(
+        &self,
+        email: &str,
+        password: &str,
+        device_uuid: &str,
+        device_name: &str,
+        x_vc: &str,
+    ) -> Result<Value, OpenKakaoError> {
+        let args = [email, password, device_uuid, device_name, x_vc].map(str::to_string);
+        self.run(move |client| {
+            let [email, password, device_uuid, device_name, x_vc] = &args;
+            client.login_direct(email, password, device_uuid, device_name, x_vc)
+        })
+        .await
+    }
+
+    pub async fn get_settings(&self) -> Result<Value, OpenKakaoError> {
+        self.run(|client| client.get_settings()).await
+    }
+
+    pub async fn get_scrap_preview(&self, url: &str) -> Result<Value, OpenKakaoError> {
+        let url = url.to_string();
+        self.run(move |client| client.get_scrap_preview(&url)).await
+    }
+
+    pub async fn get_open_link(&self, link: &OpenLinkRef) -> Result<OpenLink, OpenKakaoError> {
+        let link = link.clone();
+        self.run(move |client| client.get_open_link(&link)).await
+    }
+
+    pub async fn join_open_link(
+        &self,
+        link_id: i64,
+        passcode: Option<&str>,
+        nickname: Option<&str>,
+    ) -> Result<i64, OpenKakaoError> {
+        let passcode = passcode.map(str::to_string);
+        let nickname = nickname.map(str::to_string);
+        self.run(move |client| {
+            client.join_open_link(link_id, passcode.as_deref(), nickname.as_deref())
+        })
+        .await
+    }
+
+    pub async fn update_profile(
+        &self,
+        nickname: Option<&str>,
+        status_message: Option<&str>,
+    ) -> Result<Value, OpenKakaoError> {
+        let nickname = nickname.map(str::to_string);
+        let status_message = status_message.map(str::to_string);
+        self.run(move |client| {
+            client.update_profile(nickname.as_deref(), status_message.as_deref())
+        })
+        .await
+    }
+
+    pub async fn leave_open_chat(&self, chat_id: i64) -> Result<Value, OpenKakaoError> {
+        self.run(move |client| client.leave_open_chat(chat_id))
+            .await
+    }
+
+    pub async fn login_with_xvc(
+        &self,
+        email: &str,
+        password: &str,
+        device_uuid: &str,
+        device_name: &str,
+    ) -> Result<Value, OpenKakaoError> {
+        let args = [email, password, device_uuid, device_name].map(str::to_string);
+        self.run(move |client| {
+            let [email, password, device_uuid, device_name] = &args;
+            client.login_with_xvc(email, password, device_uuid, device_name)
+        })
+        .await
+    }
+
+    /// See [`KakaoRestClient::download`]. `dest` is written on the blocking
+    /// pool and handed back with the result.
+    pub async fn download<W: Write + Send + 'static>(
+        &self,
+        url: &str,
+        mut dest: W,
+    ) -> Result<(DownloadInfo, W), OpenKakaoError> {
+        let url = url.to_string();
+        self.run(move |client| {
+            let info = client.download(&url, &mut dest)?;
+            Ok((info, dest))
+        })
+        .await
+    }
+
+    pub async fn get_bytes(&self, url: &str) -> Result<Vec<u8>, OpenKakaoError> {
+        let url = url.to_string();
+        self.run(move |client| client.get_bytes(&url)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    /// Public methods of `impl KakaoRestClient` in `source`.
+    fn client_methods(source: &str, prefix: &str) -> Vec<String> {
+        let mut methods = Vec::new();
+        let mut in_client = false;
+        for line in source.lines() {
+            if line == "impl KakaoRestClient {" || line == "impl AsyncKakaoRestClient {" {
+                in_client = true;
+            } else if line == "}" {
+                in_client = false;
+            } else if let Some(rest) = line.strip_prefix(prefix).filter(|_| in_client) {
+                methods.push(rest.split(['(', '<']).next().unwrap().to_string());
+            }
+        }
+        methods
+    }
+
+    #[test]
+    fn every_blocking_method_has_an_async_counterpart() {
+        // Builders (use `From<KakaoRestClient>` or `from_builder`) and pure
+        // helpers.
+        let builders = [
+            "builder",
+            "with_reauth",
+            "with_retry_policy",
+            "with_retry_deadline",
+            "with_byte_meter",
+            "with_pacing",
+            "with_transport",
+            "generate_xvc",
+        ];
+        let source = include_str!("rest_async.rs");
+        let mut wrapped = client_methods(source, "    pub async fn ");
+        wrapped.extend(client_methods(source, "    pub fn "));
+        let blocking = client_methods(include_str!("rest.rs"), "    pub fn ");
+        assert!(blocking.iter().any(|m| m == "get_messages"));
+        for method in blocking {
+            if builders.contains(&method.as_str()) {
+                continue;
+            }
+            assert!(
+                wrapped.contains(&method),
+                "KakaoRestClient::{method} has no AsyncKakaoRestClient counterpart"
+            );
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::error::OpenKakaoError;
+
+/// Status codes that mean "fix something first" rather than "try again",
+/// for errors that only carry them in their message.
+const NON_TRANSIENT_STATUSES: &[&str] = &["-950", "-999", "-400"];
+
+/// How many times to attempt an operation and how long to wait in between.
+///
+/// The CLI builds one from `--retries` and hands it to
+/// [`LocoClient`](crate::loco::client::LocoClient); embedders can do the
+/// same, or reuse [`RetryPolicy::default`] in their own loops:
+///
+/// ```
+/// use std::time::Duration;
+/// use openkakao_rs::error::OpenKakaoError;
+/// use openkakao_rs::retry::RetryPolicy;
+///
+/// let policy = RetryPolicy {
+///     max_attempts: 6,
+///     backoff_base: Duration::from_secs(1),
+///     jitter: 0.0,
+///     retryable: |e| e.is_retryable(),
+/// };
+/// assert_eq!(policy.backoff(3), Duration::from_secs(4));
+///
+/// let busy = anyhow::Error::new(OpenKakaoError::loco("SYNCMSG", -300));
+/// assert!(policy.should_retry(&busy, 1));
+/// assert!(!policy.should_retry(&busy, 6));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total tries including the first; `1` never retries.
+    pub max_attempts: u32,
+    /// Wait before the first retry, doubled for each retry after it.
+    pub backoff_base: Duration,
+    /// Random spread applied to each wait, as a fraction (`0.2` = ±20%).
+    pub jitter: f64,
+    /// Which typed errors deserve another attempt.
+    pub retryable: fn(&OpenKakaoError) -> bool,
+}
+
+impl Default for RetryPolicy {
+    /// Four attempts, 500ms doubling backoff with ±20% jitter, retrying
+    /// transient errors but not auth failures:
+    ///
+    /// ```
+    /// use openkakao_rs::error::OpenKakaoError;
+    /// use openkakao_rs::retry::RetryPolicy;
+    ///
+    /// let policy = RetryPolicy::default();
+    /// assert_eq!(policy.max_attempts, 4);
+    /// assert!((policy.retryable)(&OpenKakaoError::loco("SYNCMSG", -500)));
+    /// assert!(!(policy.retryable)(&OpenKakaoError::TokenExpired));
+    /// ```
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            backoff_base: Duration::from_millis(500),
+            jitter: 0.2,
+            retryable: default_retryable,
+        }
+    }
+}
+
+/// The predicate behind [`RetryPolicy::default`]: transient, and not
+/// something only new credentials can fix.
+pub fn default_retryable(error: &OpenKakaoError) -> bool {
+    error.is_retryable() && !error.is_auth_failure()
+}
+
+impl RetryPolicy {
+    /// This policy with `retries` attempts after the first one.
+    ///
+    /// ```
+    /// use openkakao_rs::retry::RetryPolicy;
+    ///
+    /// assert_eq!(RetryPolicy::default().with_retries(0).max_attempts, 1);
+    /// ```
+    pub fn with_retries(self, retries: u32) -> Self {
+        Self {
+            max_attempts: retries.saturating_add(1),
+            ..self
+        }
+    }
+
+    /// Wait before retry number `retry` (1-based), without jitter.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.backoff_base.saturating_mul(factor)
+    }
+
+    /// [`backoff`](Self::backoff) with jitter applied.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let base = self.backoff(retry);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return base;
+        }
+        let factor = rand::thread_rng().gen_range(1.0 - jitter..=1.0 + jitter);
+        base.mul_f64(factor)
+    }
+
+    /// Whether to try again after `attempt` attempts failed with `error`.
+    pub fn should_retry(&self, error: &anyhow::Error, attempt: u32) -> bool {
+        attempt < self.max_attempts && self.is_retryable_error(error)
+    }
+
+    /// Whether `error` is worth retrying at all, regardless of attempts.
+    ///
+    /// Typed errors go through [`retryable`](Self::retryable). Untyped ones
+    /// (socket, TLS) count as transient unless their message carries a
+    /// login or protocol status such as `-950`.
+    pub fn is_retryable_error(&self, error: &anyhow::Error) -> bool {
+        match error.downcast_ref::<OpenKakaoError>() {
+            Some(typed) => (self.retryable)(typed),
+            None => {
+                let message = error.to_string();
+                !NON_TRANSIENT_STATUSES
+                    .iter()
+                    .any(|status| message.contains(status))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_and_jitter_stays_in_range() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(4), Duration::from_millis(4000));
+        for _ in 0..50 {
+            let delay = policy.delay(2);
+            assert!(delay >= Duration::from_millis(800) && delay <= Duration::from_millis(1200));
+        }
+        // Huge retry numbers saturate instead of overflowing.
+        assert!(policy.backoff(u32::MAX) > Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn untyped_errors_retry_unless_they_carry_a_login_status() {
+        let policy = RetryPolicy::default();
+        assert!(policy.should_retry(&anyhow::anyhow!("early eof"), 1));
+        assert!(!policy.should_retry(&anyhow::anyhow!("LOGINLIST status=-950"), 1));
+        assert!(!policy.should_retry(&anyhow::anyhow!("early eof"), 4));
+    }
+}
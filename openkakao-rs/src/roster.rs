@@ -0,0 +1,615 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+
+use crate::message_db::CachedMessage;
+use crate::report::{member_event, MemberEvent};
+
+/// Columns `members --export-csv` writes, in order. `--merge` updates these
+/// and leaves every other column of the roster alone.
+pub const COLUMNS: &[&str] = &[
+    "user_id",
+    "display_name",
+    "role",
+    "country",
+    "joined_at",
+    "friend",
+    "profile_image_url",
+    "status",
+];
+
+const ACTIVE: &str = "active";
+const DEPARTED: &str = "departed";
+
+/// One member's row of the contact sheet. Empty strings mean "not known
+/// this time"; a merge keeps the roster's old value for those.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RosterRow {
+    pub user_id: i64,
+    pub display_name: String,
+    pub role: String,
+    pub country: String,
+    /// `YYYY-MM-DD` of the latest join seen in the local message cache.
+    pub joined_at: String,
+    /// `yes` / `no`, or empty when the friend list was unavailable.
+    pub friend: String,
+    pub profile_image_url: String,
+}
+
+impl RosterRow {
+    fn value(&self, column: &str) -> Option<String> {
+        Some(match column {
+            "user_id" => self.user_id.to_string(),
+            "display_name" => self.display_name.clone(),
+            "role" => self.role.clone(),
+            "country" => self.country.clone(),
+            "joined_at" => self.joined_at.clone(),
+            "friend" => self.friend.clone(),
+            "profile_image_url" => self.profile_image_url.clone(),
+            "status" => ACTIVE.to_string(),
+            _ => return None,
+        })
+    }
+}
+
+/// Open chat member `type` from GETMEM as a role name; other values are
+/// kept as the number.
+pub fn open_role(member_type: i32) -> String {
+    match member_type {
+        1 => "owner".into(),
+        2 => "member".into(),
+        4 => "manager".into(),
+        8 => "bot".into(),
+        0 => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// Latest join time per user id from cached feed messages (invited or
+/// joined by link).
+pub fn join_times(messages: &[CachedMessage]) -> HashMap<i64, i64> {
+    let mut joined = HashMap::new();
+    for message in messages.iter().filter(|m| m.message_type == 0) {
+        if let Some((MemberEvent::Joined, members)) = member_event(message) {
+            for member in members.into_iter().filter(|m| m.user_id != 0) {
+                let at = joined.entry(member.user_id).or_insert(message.send_at);
+                *at = (*at).max(message.send_at);
+            }
+        }
+    }
+    joined
+}
+
+/// A fresh roster as CSV.
+pub fn write_roster(rows: &[RosterRow]) -> Result<String> {
+    let mut out = csv::Writer::from_writer(Vec::new());
+    out.write_record(COLUMNS)?;
+    for row in rows {
+        out.write_record(COLUMNS.iter().map(|c| row.value(c).unwrap_or_default()))?;
+    }
+    Ok(String::from_utf8(out.into_inner()?)?)
+}
+
+/// What [`merge_roster`] changed.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct MergeSummary {
+    pub updated: usize,
+    pub added: usize,
+    /// Members on the roster who are no longer in the chat, newly flagged.
+    pub departed: usize,
+    /// Members flagged departed earlier who are back.
+    pub returned: usize,
+}
+
+/// Update an existing roster from the current members. Rows are matched on
+/// `user_id`: matched rows get the known columns refreshed (a value not
+/// known this time keeps the old one) and `status=active`; rows whose member
+/// left get `status=departed` and otherwise stay as they were; new members
+/// are appended. Columns the tool does not know, their order and rows
+/// without a user id are preserved; missing known columns are added at the
+/// end.
+pub fn merge_roster(existing: &str, rows: &[RosterRow]) -> Result<(String, MergeSummary)> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(existing.as_bytes());
+    let mut header: Vec<String> = reader
+        .headers()
+        .context("Failed to read the roster header")?
+        .iter()
+        .map(str::to_string)
+        .collect();
+    let id_column = header
+        .iter()
+        .position(|h| h.trim() == "user_id")
+        .ok_or_else(|| anyhow!("roster has no user_id column to match members on"))?;
+    for column in COLUMNS {
+        if !header.iter().any(|h| h.trim() == *column) {
+            header.push(column.to_string());
+        }
+    }
+
+    let current: HashMap<i64, &RosterRow> = rows.iter().map(|r| (r.user_id, r)).collect();
+    let mut seen = HashSet::new();
+    let mut summary = MergeSummary::default();
+    let mut out = csv::Writer::from_writer(Vec::new());
+    out.write_record(&header)?;
+
+    for record in reader.records() {
+        let record = record.context("Failed to read a roster row")?;
+        let mut fields: Vec<String> = record.iter().map(str::to_string).collect();
+        fields.resize(header.len(), String::new());
+        let user_id = fields[id_column].trim().parse::<i64>().ok();
+        let status = header.iter().position(|h| h.trim() == "status");
+        match user_id.and_then(|id| current.get(&id).map(|row| (id, row))) {
+            Some((id, row)) => {
+                seen.insert(id);
+                if status.is_some_and(|i| fields[i] == DEPARTED) {
+                    summary.returned += 1;
+                } else {
+                    summary.updated += 1;
+                }
+                for (i, column) in header.iter().enumerate() {
+                    if let Some(value) = row.value(column.trim()) {
+                        if !value.is_empty() {
+                            fields[i] = value;
+                        }
+                    }
+                }
+            }
+            None if user_id.is_some() => {
+                if let Some(i) = status {
+                    if fields[i] != DEPARTED {
+                        fields[i] = DEPARTED.to_string();
+                        summary.departed += 1;
+                    }
+                }
+            }
+            None => {}
+        }
+        out.write_record(&fields)?;
+    }
+
+    for row in rows.iter().filter(|r| !seen.contains(&r.user_id)) {
+        summary.added += 1;
+        out.write_record(
+            header
+                .iter()
+                .map(|column| row.value(column.trim()).unwrap_or_default()),
+        )?;
+    }
+    Ok((String::from_utf8(out.into_inner()?)?, summary))
+}
+
+/// The message history `members --activity` judged members by.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct HistoryCoverage {
+    /// `archive` (the local message cache) or `server` (what the server
+    /// still hands out).
+    pub source: String,
+    pub messages: usize,
+    /// Send time of the oldest and newest message.
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    /// Whole days from the oldest message to now.
+    pub days: i64,
+}
+
+/// One member's last sign of life in the covered history.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MemberActivity {
+    pub user_id: i64,
+    pub display_name: String,
+    pub role: String,
+    /// Latest join seen in the history.
+    pub joined_at: Option<i64>,
+    pub last_message_at: Option<i64>,
+    pub message_count: usize,
+    /// Days since the last message, or since joining for someone who has
+    /// not written; `None` without any history.
+    pub inactive_days: Option<i64>,
+    /// Silent through all of the history, which may not reach back to
+    /// their last message: `inactive_days` is then only a lower bound.
+    pub before_history: bool,
+}
+
+impl MemberActivity {
+    /// Known to have been inactive for at least `days`.
+    pub fn inactive_for(&self, days: i64) -> bool {
+        self.inactive_days.is_some_and(|inactive| inactive >= days)
+    }
+}
+
+/// Join `members` against `messages` of their chat: message count, last
+/// message and join time per member, longest inactive first (members
+/// silent through all of the history before everyone else, in user id
+/// order). Feed messages (joins, leaves) count as joins, not messages.
+pub fn member_activity(
+    members: &[RosterRow],
+    messages: &[CachedMessage],
+    now: i64,
+    source: &str,
+) -> (Vec<MemberActivity>, HistoryCoverage) {
+    let coverage = HistoryCoverage {
+        source: source.to_string(),
+        messages: messages.len(),
+        from: messages.iter().map(|m| m.send_at).min(),
+        to: messages.iter().map(|m| m.send_at).max(),
+        days: messages
+            .iter()
+            .map(|m| m.send_at)
+            .min()
+            .map_or(0, |from| (now - from).max(0) / 86_400),
+    };
+    let joined = join_times(messages);
+    let mut written: HashMap<i64, (usize, i64)> = HashMap::new();
+    for message in messages.iter().filter(|m| m.message_type != 0) {
+        let entry = written.entry(message.author_id).or_insert((0, i64::MIN));
+        entry.0 += 1;
+        entry.1 = entry.1.max(message.send_at);
+    }
+
+    let mut activity: Vec<MemberActivity> = members
+        .iter()
+        .map(|member| {
+            let (message_count, last_message_at) = written
+                .get(&member.user_id)
+                .map_or((0, None), |&(count, last)| (count, Some(last)));
+            let joined_at = joined.get(&member.user_id).copied();
+            let last_seen = last_message_at.or(joined_at);
+            let before_history = last_seen.is_none();
+            let since = last_seen.or(coverage.from);
+            MemberActivity {
+                user_id: member.user_id,
+                display_name: member.display_name.clone(),
+                role: member.role.clone(),
+                joined_at,
+                last_message_at,
+                message_count,
+                inactive_days: since.map(|at| (now - at).max(0) / 86_400),
+                before_history,
+            }
+        })
+        .collect();
+    activity.sort_by_key(|a| {
+        (
+            !a.before_history,
+            a.last_message_at.or(a.joined_at),
+            a.user_id,
+        )
+    });
+    (activity, coverage)
+}
+
+/// Columns of `members --activity --export-csv`.
+pub const ACTIVITY_COLUMNS: &[&str] = &[
+    "user_id",
+    "display_name",
+    "role",
+    "joined_at",
+    "last_message_at",
+    "message_count",
+    "inactive_days",
+    "before_history",
+];
+
+/// `members --activity` as CSV; times as `YYYY-MM-DD` in `format_day`.
+pub fn write_activity(
+    activity: &[MemberActivity],
+    format_day: impl Fn(i64) -> String,
+) -> Result<String> {
+    let mut out = csv::Writer::from_writer(Vec::new());
+    out.write_record(ACTIVITY_COLUMNS)?;
+    for a in activity {
+        out.write_record([
+            a.user_id.to_string(),
+            a.display_name.clone(),
+            a.role.clone(),
+            a.joined_at.map(&format_day).unwrap_or_default(),
+            a.last_message_at.map(&format_day).unwrap_or_default(),
+            a.message_count.to_string(),
+            a.inactive_days.map(|d| d.to_string()).unwrap_or_default(),
+            if a.before_history { "yes" } else { "no" }.to_string(),
+        ])?;
+    }
+    Ok(String::from_utf8(out.into_inner()?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(user_id: i64, name: &str, friend: &str) -> RosterRow {
+        RosterRow {
+            user_id,
+            display_name: name.into(),
+            role: "member".into(),
+            country: "KR".into(),
+            friend: friend.into(),
+            profile_image_url: format!("https://p.kakaocdn.net/{user_id}.jpg"),
+            ..RosterRow::default()
+        }
+    }
+
+    fn parse(csv_text: &str) -> Vec<Vec<String>> {
+        csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(csv_text.as_bytes())
+            .records()
+            .map(|r| r.unwrap().iter().map(str::to_string).collect())
+            .collect()
+    }
+
+    #[test]
+    fn fresh_roster_has_one_row_per_member() {
+        let csv_text = write_roster(&[row(1, "김민수, 팀장", "yes")]).unwrap();
+        let rows = parse(&csv_text);
+        assert_eq!(rows[0], COLUMNS);
+        assert_eq!(
+            rows[1],
+            [
+                "1",
+                "김민수, 팀장",
+                "member",
+                "KR",
+                "",
+                "yes",
+                "https://p.kakaocdn.net/1.jpg",
+                "active"
+            ]
+        );
+    }
+
+    /// A roster an admin has been keeping by hand: reordered columns, their
+    /// own `notes` and `dues` columns, a departed member and a blank line item.
+    const SAMPLE: &str = "\
+notes,user_id,display_name,dues,status,joined_at,country
+moderator,1,Old Name,paid,active,2023-01-05,KR
+,2,Bora,unpaid,active,2023-02-10,KR
+left in spring,3,Chul,paid,departed,2023-03-01,JP
+header note,,,,,,
+";
+
+    #[test]
+    fn merge_updates_known_columns_and_keeps_the_rest() {
+        let members = [
+            row(1, "New Name", "yes"),
+            row(3, "Chul", "no"),
+            row(4, "Dana", ""),
+        ];
+        let (merged, summary) = merge_roster(SAMPLE, &members).unwrap();
+        assert_eq!(
+            summary,
+            MergeSummary {
+                updated: 1,
+                added: 1,
+                departed: 1,
+                returned: 1,
+            }
+        );
+        let rows = parse(&merged);
+        assert_eq!(
+            rows[0],
+            [
+                "notes",
+                "user_id",
+                "display_name",
+                "dues",
+                "status",
+                "joined_at",
+                "country",
+                "role",
+                "friend",
+                "profile_image_url"
+            ]
+        );
+        // Renamed; notes, dues and the join date (unknown now) kept.
+        assert_eq!(
+            rows[1],
+            [
+                "moderator",
+                "1",
+                "New Name",
+                "paid",
+                "active",
+                "2023-01-05",
+                "KR",
+                "member",
+                "yes",
+                "https://p.kakaocdn.net/1.jpg"
+            ]
+        );
+        // Left the chat: flagged, everything else untouched.
+        assert_eq!(
+            rows[2],
+            [
+                "",
+                "2",
+                "Bora",
+                "unpaid",
+                "departed",
+                "2023-02-10",
+                "KR",
+                "",
+                "",
+                ""
+            ]
+        );
+        // Came back: active again, country updated.
+        assert_eq!(rows[3][4], "active");
+        assert_eq!(rows[3][6], "KR");
+        assert_eq!(rows[3][0], "left in spring");
+        // Rows without a user id pass through.
+        assert_eq!(rows[4][0], "header note");
+        // New member appended, unknown friend status left blank.
+        assert_eq!(
+            rows[5],
+            [
+                "",
+                "4",
+                "Dana",
+                "",
+                "active",
+                "",
+                "KR",
+                "member",
+                "",
+                "https://p.kakaocdn.net/4.jpg"
+            ]
+        );
+        assert_eq!(rows.len(), 6);
+    }
+
+    #[test]
+    fn merging_twice_changes_nothing_more() {
+        let members = [row(1, "New Name", "yes"), row(4, "Dana", "no")];
+        let (once, _) = merge_roster(SAMPLE, &members).unwrap();
+        let (twice, summary) = merge_roster(&once, &members).unwrap();
+        assert_eq!(once, twice);
+        assert_eq!(summary.added + summary.departed + summary.returned, 0);
+        assert_eq!(summary.updated, 2);
+    }
+
+    #[test]
+    fn roster_without_user_id_is_refused() {
+        let err = merge_roster("name,notes\nKim,x\n", &[row(1, "Kim", "")]).unwrap_err();
+        assert!(err.to_string().contains("user_id"));
+    }
+
+    #[test]
+    fn join_times_come_from_feed_messages() {
+        let feed = |log_id, feed_type, user_id, send_at| CachedMessage {
+            chat_id: 1,
+            log_id,
+            author_id: 0,
+            author_name: String::new(),
+            message_type: 0,
+            message: format!(
+                r#"{{"feedType":{feed_type},"members":[{{"userId":{user_id},"nickName":"x"}}]}}"#
+            ),
+            attachment: String::new(),
+            send_at,
+        };
+        let joined = join_times(&[feed(1, 1, 7, 100), feed(2, 2, 7, 200), feed(3, 4, 7, 300)]);
+        assert_eq!(joined.get(&7), Some(&300));
+        assert_eq!(open_role(4), "manager");
+        assert_eq!(open_role(16), "16");
+    }
+
+    const DAY: i64 = 86_400;
+
+    fn text(log_id: i64, author_id: i64, send_at: i64) -> CachedMessage {
+        CachedMessage {
+            chat_id: 1,
+            log_id,
+            author_id,
+            author_name: String::new(),
+            message_type: 1,
+            message: "hi".into(),
+            attachment: String::new(),
+            send_at,
+        }
+    }
+
+    fn joined(log_id: i64, user_id: i64, send_at: i64) -> CachedMessage {
+        CachedMessage {
+            message_type: 0,
+            message: format!(r#"{{"feedType":4,"members":[{{"userId":{user_id}}}]}}"#),
+            author_id: user_id,
+            ..text(log_id, 0, send_at)
+        }
+    }
+
+    #[test]
+    fn activity_ranks_the_longest_silent_first() {
+        let now = 100 * DAY;
+        // 1 writes often, 2 wrote once long ago, 3 joined and never wrote,
+        // 4 and 5 never appear in the 40-day history at all.
+        let messages = [
+            text(1, 1, 60 * DAY),
+            text(2, 2, 61 * DAY),
+            joined(3, 3, 70 * DAY),
+            text(4, 1, 90 * DAY),
+            text(5, 1, 99 * DAY),
+        ];
+        let members = [
+            row(5, "Eun", ""),
+            row(1, "Ann", ""),
+            row(3, "Cho", ""),
+            row(2, "Bo", ""),
+            row(4, "Dae", ""),
+        ];
+        let (activity, coverage) = member_activity(&members, &messages, now, "archive");
+        assert_eq!(
+            coverage,
+            HistoryCoverage {
+                source: "archive".into(),
+                messages: 5,
+                from: Some(60 * DAY),
+                to: Some(99 * DAY),
+                days: 40,
+            }
+        );
+        let order: Vec<_> = activity
+            .iter()
+            .map(|a| {
+                (
+                    a.user_id,
+                    a.message_count,
+                    a.inactive_days,
+                    a.before_history,
+                )
+            })
+            .collect();
+        assert_eq!(
+            order,
+            [
+                (4, 0, Some(40), true),
+                (5, 0, Some(40), true),
+                (2, 1, Some(39), false),
+                (3, 0, Some(30), false),
+                (1, 3, Some(1), false),
+            ]
+        );
+        assert_eq!(activity[3].joined_at, Some(70 * DAY));
+        assert_eq!(activity[3].last_message_at, None);
+        assert_eq!(activity[4].last_message_at, Some(99 * DAY));
+    }
+
+    #[test]
+    fn inactivity_is_only_claimed_within_the_history() {
+        let now = 10 * DAY;
+        let members = [row(1, "Ann", ""), row(2, "Bo", "")];
+        // A three-day cache: Bo's silence says nothing about a 30-day cut.
+        let (activity, coverage) = member_activity(&members, &[text(1, 1, 7 * DAY)], now, "server");
+        assert_eq!(coverage.days, 3);
+        let bo = activity.iter().find(|a| a.user_id == 2).unwrap();
+        assert!(bo.before_history);
+        assert!(bo.inactive_for(3));
+        assert!(!bo.inactive_for(30));
+
+        // No history at all: nobody is known to be inactive.
+        let (activity, coverage) = member_activity(&members, &[], now, "server");
+        assert_eq!((coverage.from, coverage.days), (None, 0));
+        assert!(activity
+            .iter()
+            .all(|a| a.inactive_days.is_none() && !a.inactive_for(0)));
+    }
+
+    #[test]
+    fn activity_csv_has_a_row_per_member() {
+        let (activity, _) = member_activity(
+            &[row(1, "김민수, 팀장", "")],
+            &[text(1, 1, 2 * DAY)],
+            3 * DAY,
+            "archive",
+        );
+        let rows = parse(&write_activity(&activity, |at| format!("day{}", at / DAY)).unwrap());
+        assert_eq!(rows[0], ACTIVITY_COLUMNS);
+        assert_eq!(
+            rows[1],
+            ["1", "김민수, 팀장", "member", "", "day2", "1", "1", "no"]
+        );
+    }
+}
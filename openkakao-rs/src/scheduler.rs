@@ -0,0 +1,505 @@
+//! Timing for `daemon`: when each task runs next, quiet hours and not
+//! starting a task that is still running. No I/O and no clock of its own;
+//! the caller passes `now` (see [`Clock`]).
+
+use std::collections::HashSet;
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{Duration, Local, NaiveDateTime, NaiveTime};
+
+use crate::config::DaemonConfig;
+
+/// Source of the current local time, replaced by a fixed one in tests.
+pub trait Clock {
+    fn now(&self) -> NaiveDateTime;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> NaiveDateTime {
+        Local::now().naive_local()
+    }
+}
+
+/// A daily window, in local time, in which a task does not start. May
+/// wrap midnight (`23:00-07:00`); the end is exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl QuietHours {
+    /// `HH:MM-HH:MM`.
+    pub fn parse(s: &str) -> Result<Self> {
+        let invalid = || anyhow!("Invalid quiet hours '{}'. Expected e.g. 23:00-07:00.", s);
+        let (start, end) = s.split_once('-').ok_or_else(invalid)?;
+        let time = |t: &str| NaiveTime::parse_from_str(t.trim(), "%H:%M").map_err(|_| invalid());
+        Ok(Self {
+            start: time(start)?,
+            end: time(end)?,
+        })
+    }
+
+    pub fn contains(&self, t: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= t && t < self.end
+        } else {
+            t >= self.start || t < self.end
+        }
+    }
+
+    /// `at`, or the end of the quiet window `at` falls in.
+    pub fn first_allowed(&self, at: NaiveDateTime) -> NaiveDateTime {
+        if !self.contains(at.time()) {
+            return at;
+        }
+        let date = if self.start > self.end && at.time() >= self.start {
+            at.date() + Duration::days(1)
+        } else {
+            at.date()
+        };
+        date.and_time(self.end)
+    }
+}
+
+/// What the scheduler needs to know about a configured task.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskPlan {
+    pub name: String,
+    pub every: Duration,
+    pub quiet: Option<QuietHours>,
+}
+
+impl TaskPlan {
+    fn allowed(&self, at: NaiveDateTime) -> NaiveDateTime {
+        self.quiet.map_or(at, |quiet| quiet.first_allowed(at))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskStatus {
+    pub last_run: Option<NaiveDateTime>,
+    pub last_duration: Option<Duration>,
+    pub next_run: NaiveDateTime,
+    pub last_error: Option<String>,
+    pub running: bool,
+    pub runs: u64,
+    pub failures: u64,
+}
+
+/// What a daemon task does; each reuses the command of the same name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskKind {
+    /// `cache` for the configured chats, or every chat.
+    Sync,
+    /// `renew`: refresh the OAuth token.
+    Renew,
+    /// `chats --since-state`: record which chats gained unread messages.
+    Unread,
+}
+
+impl TaskKind {
+    pub fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "sync" => Ok(Self::Sync),
+            "renew" => Ok(Self::Renew),
+            "unread" => Ok(Self::Unread),
+            _ => Err(anyhow!(
+                "Unknown daemon task kind '{}'. Use: sync, renew, unread",
+                s
+            )),
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Sync => "sync",
+            Self::Renew => "renew",
+            Self::Unread => "unread",
+        }
+    }
+}
+
+/// A configured task, checked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DaemonTask {
+    pub plan: TaskPlan,
+    pub kind: TaskKind,
+    pub chats: Vec<i64>,
+}
+
+/// The `[[daemon.tasks]]` entries as tasks: kinds known, names unique,
+/// intervals at least a minute.
+pub fn plan_tasks(config: &DaemonConfig) -> Result<Vec<DaemonTask>> {
+    let mut names = HashSet::new();
+    config
+        .tasks
+        .iter()
+        .map(|task| {
+            let kind = TaskKind::from_str(&task.kind)?;
+            let name = task.name.clone().unwrap_or_else(|| task.kind.clone());
+            if !names.insert(name.clone()) {
+                bail!("Daemon task '{}' is listed twice; give one a name", name);
+            }
+            let every = crate::util::parse_duration_secs(&task.every)?;
+            if every < 60 {
+                bail!(
+                    "Daemon task '{}' runs too often ({}); use 1m or more",
+                    name,
+                    task.every
+                );
+            }
+            if !task.chats.is_empty() && kind != TaskKind::Sync {
+                bail!("Daemon task '{}': chats only apply to sync", name);
+            }
+            Ok(DaemonTask {
+                plan: TaskPlan {
+                    name,
+                    every: Duration::seconds(every),
+                    quiet: task
+                        .quiet_hours
+                        .as_deref()
+                        .map(QuietHours::parse)
+                        .transpose()?,
+                },
+                kind,
+                chats: task.chats.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Tasks in config order with their run state.
+#[derive(Debug)]
+pub struct Scheduler {
+    tasks: Vec<(TaskPlan, TaskStatus)>,
+}
+
+impl Scheduler {
+    /// Every task is due right away, unless that is inside its quiet hours.
+    pub fn new(plans: Vec<TaskPlan>, now: NaiveDateTime) -> Self {
+        let tasks = plans
+            .into_iter()
+            .map(|plan| {
+                let next_run = plan.allowed(now);
+                let status = TaskStatus {
+                    last_run: None,
+                    last_duration: None,
+                    next_run,
+                    last_error: None,
+                    running: false,
+                    runs: 0,
+                    failures: 0,
+                };
+                (plan, status)
+            })
+            .collect();
+        Self { tasks }
+    }
+
+    /// The first task, in config order, due at `now`, now marked running.
+    /// A running task is not started again, and runs missed while something
+    /// else ran are not made up: a late task runs once. A task that became
+    /// due inside its quiet hours is moved to their end instead.
+    pub fn next_due(&mut self, now: NaiveDateTime) -> Option<String> {
+        for (plan, status) in &mut self.tasks {
+            if status.running || status.next_run > now {
+                continue;
+            }
+            let allowed = plan.allowed(now);
+            if allowed > now {
+                status.next_run = allowed;
+                continue;
+            }
+            status.running = true;
+            return Some(plan.name.clone());
+        }
+        None
+    }
+
+    /// Record a finished run; the next one is one interval after it ended.
+    pub fn finish(
+        &mut self,
+        name: &str,
+        started: NaiveDateTime,
+        finished: NaiveDateTime,
+        result: std::result::Result<(), String>,
+    ) {
+        let Some((plan, status)) = self.tasks.iter_mut().find(|(plan, _)| plan.name == name) else {
+            return;
+        };
+        status.running = false;
+        status.runs += 1;
+        status.last_run = Some(started);
+        status.last_duration = Some(finished - started);
+        status.next_run = plan.allowed(finished + plan.every);
+        match result {
+            Ok(()) => status.last_error = None,
+            Err(err) => {
+                status.failures += 1;
+                status.last_error = Some(err);
+            }
+        }
+    }
+
+    /// Switch to `plans`, e.g. after the config file changed. A task whose
+    /// name is still there keeps its history and is next due one (possibly
+    /// new) interval after its last run ended; a new task is due right away.
+    pub fn replan(&mut self, plans: Vec<TaskPlan>, now: NaiveDateTime) {
+        let mut old = std::mem::take(&mut self.tasks);
+        let fresh = Scheduler::new(plans, now).tasks;
+        self.tasks = fresh
+            .into_iter()
+            .map(|(plan, status)| {
+                let Some(at) = old.iter().position(|(kept, _)| kept.name == plan.name) else {
+                    return (plan, status);
+                };
+                let (_, mut kept) = old.swap_remove(at);
+                if let (Some(started), Some(took)) = (kept.last_run, kept.last_duration) {
+                    kept.next_run = plan.allowed(started + took + plan.every);
+                }
+                (plan, kept)
+            })
+            .collect();
+    }
+
+    /// Earliest next run of a task that is not running.
+    pub fn next_wakeup(&self) -> Option<NaiveDateTime> {
+        self.tasks
+            .iter()
+            .filter(|(_, status)| !status.running)
+            .map(|(_, status)| status.next_run)
+            .min()
+    }
+
+    pub fn tasks(&self) -> impl Iterator<Item = (&TaskPlan, &TaskStatus)> {
+        self.tasks.iter().map(|(plan, status)| (plan, status))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    /// A clock tests move by hand.
+    struct FakeClock(Cell<NaiveDateTime>);
+
+    impl FakeClock {
+        fn at(s: &str) -> Self {
+            Self(Cell::new(dt(s)))
+        }
+
+        fn advance(&self, minutes: i64) {
+            self.0.set(self.0.get() + Duration::minutes(minutes));
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> NaiveDateTime {
+            self.0.get()
+        }
+    }
+
+    fn dt(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn plan(name: &str, minutes: i64, quiet: Option<&str>) -> TaskPlan {
+        TaskPlan {
+            name: name.into(),
+            every: Duration::minutes(minutes),
+            quiet: quiet.map(|q| QuietHours::parse(q).unwrap()),
+        }
+    }
+
+    fn start_due(scheduler: &mut Scheduler, now: NaiveDateTime) -> Vec<String> {
+        std::iter::from_fn(|| scheduler.next_due(now)).collect()
+    }
+
+    fn status<'a>(scheduler: &'a Scheduler, name: &str) -> &'a TaskStatus {
+        scheduler
+            .tasks()
+            .find(|(plan, _)| plan.name == name)
+            .map(|(_, status)| status)
+            .unwrap()
+    }
+
+    #[test]
+    fn replan_keeps_history_of_tasks_still_configured() {
+        let start = dt("2024-05-01 12:00");
+        let mut scheduler =
+            Scheduler::new(vec![plan("sync", 15, None), plan("old", 5, None)], start);
+        assert_eq!(start_due(&mut scheduler, start), ["sync", "old"]);
+        let end = start + Duration::minutes(2);
+        scheduler.finish("sync", start, end, Ok(()));
+        scheduler.finish("old", start, end, Err("boom".into()));
+
+        let later = dt("2024-05-01 12:05");
+        scheduler.replan(vec![plan("new", 60, None), plan("sync", 30, None)], later);
+        let names: Vec<_> = scheduler
+            .tasks()
+            .map(|(plan, _)| plan.name.clone())
+            .collect();
+        assert_eq!(names, ["new", "sync"]);
+        assert_eq!(status(&scheduler, "sync").runs, 1);
+        assert_eq!(status(&scheduler, "sync").next_run, dt("2024-05-01 12:32"));
+        assert_eq!(status(&scheduler, "new").next_run, later);
+        assert_eq!(start_due(&mut scheduler, later), ["new"]);
+    }
+
+    #[test]
+    fn config_tasks_are_checked() {
+        let task = |kind: &str, name: Option<&str>, every: &str| crate::config::DaemonTaskConfig {
+            kind: kind.into(),
+            name: name.map(str::to_string),
+            every: every.into(),
+            quiet_hours: None,
+            chats: Vec::new(),
+        };
+        let config = |tasks| DaemonConfig {
+            tasks,
+            ..Default::default()
+        };
+        let tasks = plan_tasks(&config(vec![
+            task("sync", None, "15m"),
+            task("sync", Some("sync-hourly"), "1h"),
+            task("renew", None, "6h"),
+        ]))
+        .unwrap();
+        assert_eq!(tasks[0].plan.name, "sync");
+        assert_eq!(tasks[1].plan.every, Duration::hours(1));
+        assert_eq!(tasks[2].kind, TaskKind::Renew);
+
+        let err = |tasks| plan_tasks(&config(tasks)).unwrap_err().to_string();
+        assert!(err(vec![task("sync", None, "15m"), task("sync", None, "1h")]).contains("twice"));
+        assert!(err(vec![task("watch", None, "15m")]).contains("Unknown"));
+        assert!(err(vec![task("renew", None, "30s")]).contains("too often"));
+        let mut unread = task("unread", None, "5m");
+        unread.chats = vec![1];
+        assert!(err(vec![unread]).contains("only apply to sync"));
+    }
+
+    #[test]
+    fn quiet_hours_wrap_midnight() {
+        let quiet = QuietHours::parse("23:00-07:00").unwrap();
+        let t = |s| NaiveTime::parse_from_str(s, "%H:%M").unwrap();
+        assert!(quiet.contains(t("23:00")) && quiet.contains(t("03:00")));
+        assert!(!quiet.contains(t("07:00")) && !quiet.contains(t("12:00")));
+        assert_eq!(
+            quiet.first_allowed(dt("2026-10-16 23:30")),
+            dt("2026-10-17 07:00")
+        );
+        assert_eq!(
+            quiet.first_allowed(dt("2026-10-17 02:00")),
+            dt("2026-10-17 07:00")
+        );
+        assert_eq!(
+            quiet.first_allowed(dt("2026-10-17 12:00")),
+            dt("2026-10-17 12:00")
+        );
+
+        let lunch = QuietHours::parse("12:00-13:00").unwrap();
+        assert_eq!(
+            lunch.first_allowed(dt("2026-10-16 12:15")),
+            dt("2026-10-16 13:00")
+        );
+        assert!(QuietHours::parse("23:00").is_err());
+        assert!(QuietHours::parse("25:00-07:00").is_err());
+    }
+
+    #[test]
+    fn tasks_run_at_start_then_every_interval_after_finishing() {
+        let clock = FakeClock::at("2026-10-16 10:00");
+        let mut scheduler = Scheduler::new(
+            vec![plan("sync", 15, None), plan("renew", 60, None)],
+            clock.now(),
+        );
+        assert_eq!(start_due(&mut scheduler, clock.now()), ["sync", "renew"]);
+
+        // sync takes 5 minutes; the next run counts from when it ended.
+        clock.advance(5);
+        scheduler.finish("sync", dt("2026-10-16 10:00"), clock.now(), Ok(()));
+        scheduler.finish("renew", clock.now(), clock.now(), Err("no token".into()));
+        assert_eq!(status(&scheduler, "sync").next_run, dt("2026-10-16 10:20"));
+        assert_eq!(
+            status(&scheduler, "sync").last_duration,
+            Some(Duration::minutes(5))
+        );
+        let renew = status(&scheduler, "renew");
+        assert_eq!(renew.last_error.as_deref(), Some("no token"));
+        assert_eq!((renew.runs, renew.failures), (1, 1));
+        assert_eq!(scheduler.next_wakeup(), Some(dt("2026-10-16 10:20")));
+
+        clock.advance(10);
+        assert!(start_due(&mut scheduler, clock.now()).is_empty());
+        clock.advance(5);
+        assert_eq!(start_due(&mut scheduler, clock.now()), ["sync"]);
+    }
+
+    #[test]
+    fn running_tasks_are_not_started_twice_and_missed_runs_coalesce() {
+        let clock = FakeClock::at("2026-10-16 10:00");
+        let mut scheduler = Scheduler::new(vec![plan("sync", 5, None)], clock.now());
+        assert_eq!(start_due(&mut scheduler, clock.now()), ["sync"]);
+        // Still running an hour later: not started again, no wakeup pending.
+        clock.advance(60);
+        assert!(start_due(&mut scheduler, clock.now()).is_empty());
+        assert_eq!(scheduler.next_wakeup(), None);
+
+        scheduler.finish("sync", dt("2026-10-16 10:00"), clock.now(), Ok(()));
+        // Twelve intervals were missed; the task runs once, five minutes on.
+        assert!(start_due(&mut scheduler, clock.now()).is_empty());
+        clock.advance(5);
+        assert_eq!(start_due(&mut scheduler, clock.now()), ["sync"]);
+        assert!(start_due(&mut scheduler, clock.now()).is_empty());
+    }
+
+    #[test]
+    fn quiet_hours_postpone_first_and_later_runs() {
+        let clock = FakeClock::at("2026-10-16 23:30");
+        let mut scheduler = Scheduler::new(
+            vec![
+                plan("sync", 60, Some("23:00-07:00")),
+                plan("renew", 60, None),
+            ],
+            clock.now(),
+        );
+        assert_eq!(status(&scheduler, "sync").next_run, dt("2026-10-17 07:00"));
+        assert_eq!(start_due(&mut scheduler, clock.now()), ["renew"]);
+
+        // A run ending just before quiet hours resumes after them.
+        let mut scheduler = Scheduler::new(
+            vec![plan("sync", 60, Some("23:00-07:00"))],
+            dt("2026-10-16 22:10"),
+        );
+        assert_eq!(start_due(&mut scheduler, dt("2026-10-16 22:10")), ["sync"]);
+        scheduler.finish(
+            "sync",
+            dt("2026-10-16 22:10"),
+            dt("2026-10-16 22:20"),
+            Ok(()),
+        );
+        assert_eq!(status(&scheduler, "sync").next_run, dt("2026-10-17 07:00"));
+
+        // Due before quiet hours but only reached inside them (another task
+        // ran long): moved to their end rather than run.
+        let mut scheduler = Scheduler::new(
+            vec![plan("sync", 30, Some("23:00-07:00"))],
+            dt("2026-10-16 22:00"),
+        );
+        start_due(&mut scheduler, dt("2026-10-16 22:00"));
+        scheduler.finish(
+            "sync",
+            dt("2026-10-16 22:00"),
+            dt("2026-10-16 22:10"),
+            Ok(()),
+        );
+        assert_eq!(status(&scheduler, "sync").next_run, dt("2026-10-16 22:40"));
+        assert!(start_due(&mut scheduler, dt("2026-10-16 23:05")).is_empty());
+        assert_eq!(status(&scheduler, "sync").next_run, dt("2026-10-17 07:00"));
+        assert_eq!(start_due(&mut scheduler, dt("2026-10-17 07:00")), ["sync"]);
+    }
+}
@@ -0,0 +1,262 @@
+//! The versioned envelope around `--json` output, and the JSON Schema
+//! documents `schema` publishes for it.
+//!
+//! Every JSON document a command prints is wrapped as
+//! `{"schema": "openkakao.<command>.v<N>", "generated_at": ..., "data": ...}`,
+//! where `<command>` is the subcommand path (`archive.read`). `N` starts at 1
+//! and is bumped in [`SCHEMAS`] when a field of that output is renamed,
+//! removed or changes type; new fields are not a breaking change. The
+//! documents of [`SCHEMAS`] are checked in under `schemas/`, and a test fails
+//! when the generated ones drift from them.
+
+use std::sync::OnceLock;
+
+use schemars::generate::SchemaSettings;
+use schemars::{JsonSchema, Schema};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::commands::chats::ChatListing;
+use crate::model::{ChatMember, ChatMessage, ChatRoom, Friend, Identity, MyProfile};
+
+pub const SCHEMA_PREFIX: &str = "openkakao";
+
+static COMMAND: OnceLock<String> = OnceLock::new();
+
+/// Set once from the parsed command line, e.g. `chats` or `archive.read`.
+pub fn set_command(path: String) {
+    let _ = COMMAND.set(path);
+}
+
+pub fn command() -> &'static str {
+    COMMAND.get().map(String::as_str).unwrap_or_default()
+}
+
+/// An output with a published schema.
+#[derive(Debug, Clone, Copy)]
+pub struct OutputSchema {
+    /// Subcommand path the output belongs to.
+    pub command: &'static str,
+    /// Bumped on breaking changes to the output.
+    pub version: u32,
+    pub about: &'static str,
+}
+
+pub const SCHEMAS: &[OutputSchema] = &[
+    OutputSchema {
+        command: "chats",
+        version: 2,
+        about: "Chat rooms: the LOCO listing, or chat list entries with --rest",
+    },
+    OutputSchema {
+        command: "read",
+        version: 2,
+        about: "Messages, oldest first",
+    },
+    OutputSchema {
+        command: "members",
+        version: 1,
+        about: "Members of one chat",
+    },
+    OutputSchema {
+        command: "friends",
+        version: 1,
+        about: "Friends list",
+    },
+    OutputSchema {
+        command: "me",
+        version: 1,
+        about: "Own profile",
+    },
+    OutputSchema {
+        command: "whoami",
+        version: 1,
+        about: "User id and nickname of the credentials",
+    },
+];
+
+/// `openkakao.<command>.v<N>`; commands without a published schema are at
+/// version 1.
+pub fn schema_id(command: &str) -> String {
+    let version = find(command).map_or(1, |schema| schema.version);
+    format!("{SCHEMA_PREFIX}.{command}.v{version}")
+}
+
+pub fn find(command: &str) -> Option<&'static OutputSchema> {
+    SCHEMAS.iter().find(|schema| schema.command == command)
+}
+
+/// What `--json` prints.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Envelope<T> {
+    /// Which output this is and its version, `openkakao.<command>.v<N>`.
+    pub schema: String,
+    /// RFC 3339 time the output was produced.
+    pub generated_at: String,
+    pub data: T,
+}
+
+impl<T> Envelope<T> {
+    pub fn new(command: &str, data: T) -> Self {
+        Self {
+            schema: schema_id(command),
+            generated_at: chrono::Utc::now().to_rfc3339(),
+            data,
+        }
+    }
+}
+
+/// `chats` prints LOCO listings by default and chat list entries over REST.
+#[derive(JsonSchema)]
+#[schemars(untagged)]
+#[expect(dead_code, reason = "only used for its JsonSchema")]
+enum ChatsData {
+    Loco(Vec<ChatListing>),
+    Rest(Vec<ChatRoom>),
+}
+
+/// The schema of `Envelope<T>` as serialized, so fields skipped when empty
+/// are optional.
+fn output_schema<T: JsonSchema>() -> Schema {
+    SchemaSettings::draft2020_12()
+        .for_serialize()
+        .into_generator()
+        .into_root_schema_for::<Envelope<T>>()
+}
+
+impl OutputSchema {
+    pub fn id(&self) -> String {
+        format!("{SCHEMA_PREFIX}.{}.v{}", self.command, self.version)
+    }
+
+    /// The JSON Schema of this output, envelope included.
+    pub fn document(&self) -> Schema {
+        let mut schema = match self.command {
+            "chats" => output_schema::<ChatsData>(),
+            "read" => output_schema::<Vec<ChatMessage>>(),
+            "members" => output_schema::<Vec<ChatMember>>(),
+            "friends" => output_schema::<Vec<Friend>>(),
+            "me" => output_schema::<MyProfile>(),
+            "whoami" => output_schema::<Identity>(),
+            _ => output_schema::<Value>(),
+        };
+        let id = self.id();
+        if let Some(properties) = schema.get_mut("properties").and_then(Value::as_object_mut) {
+            properties.insert("schema".into(), serde_json::json!({ "const": id }));
+        }
+        schema.insert("title".into(), id.into());
+        schema.insert("description".into(), self.about.into());
+        schema
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::path::PathBuf;
+
+    fn sample(command: &str) -> Value {
+        let message =
+            json!({"logId": 7, "authorId": 1, "type": 1, "message": "hi", "sendAt": 1_700_000_000});
+        let data = match command {
+            "chats" => serde_json::to_value(vec![ChatListing {
+                chat_id: 1,
+                kind: "MultiChat".into(),
+                title: "Team".into(),
+                has_unread: true,
+                unread_count: Some(2),
+                active_members: Some(3),
+                last_log_id: Some(9),
+                last_seen_log_id: None,
+                last_active_at: Some(1_700_000_000),
+                name: None,
+                renamed_from: None,
+            }]),
+            "read" => serde_json::to_value(vec![
+                ChatMessage::from_json(&message),
+                ChatMessage::from_json(
+                    &json!({"logId": 8, "type": 2, "attachment": r#"{"url":"https://x/p.jpg"}"#}),
+                ),
+            ]),
+            "members" => serde_json::to_value(vec![ChatMember::new(5, "Dana")]),
+            "friends" => serde_json::to_value(vec![Friend::from_json(
+                &json!({"userId": 5, "nickName": "Dana", "favorite": true}),
+            )]),
+            // Built from two REST answers; the shape it serializes to.
+            "me" => Ok(json!({
+                "nickname": "Me",
+                "status_message": "",
+                "account_id": 2,
+                "email": "",
+                "user_id": 1,
+                "profile_image_url": "",
+            })),
+            "whoami" => serde_json::to_value(Identity {
+                user_id: 1,
+                nickname: None,
+            }),
+            other => panic!("no sample for {other}"),
+        };
+        serde_json::to_value(Envelope::new(command, data.unwrap())).unwrap()
+    }
+
+    #[test]
+    fn sample_outputs_match_their_schemas() {
+        for schema in SCHEMAS {
+            let document = serde_json::to_value(schema.document()).unwrap();
+            let validator = jsonschema::validator_for(&document).unwrap();
+            let output = sample(schema.command);
+            let errors: Vec<String> = validator
+                .iter_errors(&output)
+                .map(|err| format!("{} at {}", err, err.instance_path))
+                .collect();
+            assert!(errors.is_empty(), "{}: {:?}", schema.id(), errors);
+        }
+
+        // A renamed field is caught.
+        let document = serde_json::to_value(find("whoami").unwrap().document()).unwrap();
+        let validator = jsonschema::validator_for(&document).unwrap();
+        let mut output = sample("whoami");
+        output["data"] = json!({"id": 1});
+        assert!(!validator.is_valid(&output));
+        output = sample("whoami");
+        output["schema"] = "openkakao.whoami.v0".into();
+        assert!(!validator.is_valid(&output));
+    }
+
+    /// Run with `OPENKAKAO_UPDATE_SCHEMAS=1` after bumping a version.
+    #[test]
+    fn published_schemas_match_the_checked_in_documents() {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("schemas");
+        let update = std::env::var_os("OPENKAKAO_UPDATE_SCHEMAS").is_some();
+        for schema in SCHEMAS {
+            let path = dir.join(format!("{}.json", schema.id()));
+            let generated = serde_json::to_string_pretty(&schema.document()).unwrap() + "\n";
+            if update {
+                std::fs::create_dir_all(&dir).unwrap();
+                std::fs::write(&path, &generated).unwrap();
+                continue;
+            }
+            let checked_in = std::fs::read_to_string(&path).unwrap_or_default();
+            assert!(
+                checked_in == generated,
+                "{} changed. If the change is breaking, bump its version in SCHEMAS; \
+                 then re-run with OPENKAKAO_UPDATE_SCHEMAS=1 and commit schemas/",
+                schema.id()
+            );
+        }
+    }
+
+    #[test]
+    fn ids_carry_the_published_version() {
+        assert_eq!(schema_id("chats"), "openkakao.chats.v2");
+        assert_eq!(schema_id("archive.read"), "openkakao.archive.read.v1");
+        let envelope = serde_json::to_value(Envelope::new("read", json!([]))).unwrap();
+        assert_eq!(envelope["schema"], "openkakao.read.v2");
+        assert!(
+            chrono::DateTime::parse_from_rfc3339(envelope["generated_at"].as_str().unwrap())
+                .is_ok()
+        );
+    }
+}
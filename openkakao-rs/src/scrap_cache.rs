@@ -0,0 +1,164 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+/// How long a cached link preview is served without refetching.
+pub const DEFAULT_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// One cached `scrap` result: the preview JSON exactly as the server sent it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScrapEntry {
+    /// Canonical form of the requested URL.
+    pub url: String,
+    /// Unix time the preview was fetched.
+    pub fetched_at: i64,
+    pub preview: Value,
+}
+
+impl ScrapEntry {
+    pub fn is_fresh(&self, now: i64, ttl_secs: u64) -> bool {
+        is_fresh(self.fetched_at, now, ttl_secs)
+    }
+}
+
+/// An entry younger than `ttl_secs` is fresh. One stamped in the future
+/// (clock changes) is treated as stale so it gets refetched.
+pub fn is_fresh(fetched_at: i64, now: i64, ttl_secs: u64) -> bool {
+    let age = now - fetched_at;
+    age >= 0 && (age as u64) < ttl_secs
+}
+
+/// Cache key form of a URL (see [`crate::urlnorm::normalize`]). Text that
+/// does not parse as a URL is only trimmed.
+pub fn canonical_url(url: &str) -> String {
+    crate::urlnorm::normalize(url, false).unwrap_or_else(|_| url.trim().to_string())
+}
+
+/// Link previews from `scrap`, one JSON file per canonical URL.
+pub struct ScrapCache {
+    dir: PathBuf,
+}
+
+impl ScrapCache {
+    pub fn open() -> Result<Self> {
+        Self::open_at(&cache_dir()?)
+    }
+
+    pub fn open_at(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+        })
+    }
+
+    fn path(&self, url: &str) -> PathBuf {
+        let key = hex::encode(Sha256::digest(canonical_url(url).as_bytes()));
+        self.dir.join(format!("{key}.json"))
+    }
+
+    /// The cached entry for `url`, fresh or not. Unreadable entries count as
+    /// missing.
+    pub fn get(&self, url: &str) -> Option<ScrapEntry> {
+        let data = fs::read_to_string(self.path(url)).ok()?;
+        serde_json::from_str(&data).ok()
+    }
+
+    pub fn put(&self, url: &str, preview: &Value, now: i64) -> Result<()> {
+        let entry = ScrapEntry {
+            url: canonical_url(url),
+            fetched_at: now,
+            preview: preview.clone(),
+        };
+        crate::output::write_atomic(&self.path(url), |out| {
+            serde_json::to_writer(&mut *out, &entry)?;
+            Ok(())
+        })
+    }
+
+    /// Remove every entry; returns how many there were.
+    pub fn clear(&self) -> Result<usize> {
+        let mut removed = 0;
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+pub fn cache_dir() -> Result<PathBuf> {
+    Ok(crate::paths::state_dir()?.join("scrap-cache"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn canonical_url_normalizes_equivalent_forms() {
+        let canonical = "https://example.com/a/b";
+        for url in [
+            "https://example.com/a/b",
+            "https://example.com/a/b/",
+            "https://EXAMPLE.com:443/a/b",
+            "https://example.com/a/b#section",
+            "  https://example.com/a/b/#top ",
+        ] {
+            assert_eq!(canonical_url(url), canonical, "{url}");
+        }
+        assert_eq!(
+            canonical_url("http://example.com:80/"),
+            "http://example.com"
+        );
+        assert_eq!(canonical_url("https://example.com"), "https://example.com");
+        assert_eq!(
+            canonical_url("https://example.com:8443/x/"),
+            "https://example.com:8443/x"
+        );
+    }
+
+    #[test]
+    fn canonical_url_keeps_query_and_case_of_path() {
+        assert_eq!(
+            canonical_url("https://example.com/Path/?q=1#frag"),
+            "https://example.com/Path?q=1"
+        );
+        assert_eq!(canonical_url("not a url"), "not a url");
+    }
+
+    #[test]
+    fn ttl_boundaries() {
+        assert!(is_fresh(1_000, 1_000, 60));
+        assert!(is_fresh(1_000, 1_059, 60));
+        assert!(!is_fresh(1_000, 1_060, 60));
+        assert!(!is_fresh(1_000, 999, 60));
+        assert!(!is_fresh(1_000, 1_000, 0));
+    }
+
+    #[test]
+    fn entries_round_trip_by_canonical_url_and_clear() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ScrapCache::open_at(dir.path()).unwrap();
+        let preview = json!({"title": "Example", "canonicalUrl": "https://example.com/a"});
+        cache.put("https://example.com/a/", &preview, 100).unwrap();
+
+        let entry = cache.get("https://example.com:443/a#x").unwrap();
+        assert_eq!(entry.url, "https://example.com/a");
+        assert_eq!(entry.fetched_at, 100);
+        assert_eq!(entry.preview, preview);
+        assert!(entry.is_fresh(150, DEFAULT_TTL_SECS));
+        assert!(cache.get("https://example.com/b").is_none());
+
+        assert_eq!(cache.clear().unwrap(), 1);
+        assert!(cache.get("https://example.com/a").is_none());
+    }
+}
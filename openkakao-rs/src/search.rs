@@ -0,0 +1,260 @@
+//! Matching for `search`: a plain, `--regex` or `--normalize` query tried
+//! against each line of a message, with the byte ranges to highlight.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use anyhow::{Context, Result};
+use openkakao_rs::model::ChatMessage;
+use openkakao_rs::normalize::normalize_text;
+use regex::{Regex, RegexBuilder};
+
+pub struct Matcher {
+    kind: Kind,
+}
+
+enum Kind {
+    Pattern(Regex),
+    /// Normalized query; matched against normalized lines, so there are no
+    /// offsets into the original to highlight.
+    Normalized(String),
+}
+
+/// One line of a message that matched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchedLine {
+    pub line: String,
+    /// Byte ranges of the matches in `line`, in order.
+    pub ranges: Vec<Range<usize>>,
+}
+
+impl Matcher {
+    /// Plain queries match literally and, like `--regex` ones, ignore case
+    /// unless `case_sensitive`.
+    pub fn new(query: &str, regex: bool, case_sensitive: bool, normalize: bool) -> Result<Self> {
+        if query.is_empty() {
+            anyhow::bail!("The search query is empty");
+        }
+        if normalize {
+            return Ok(Self {
+                kind: Kind::Normalized(normalize_text(query)),
+            });
+        }
+        let pattern = if regex {
+            query.to_string()
+        } else {
+            regex::escape(query)
+        };
+        let compiled = RegexBuilder::new(&pattern)
+            .case_insensitive(!case_sensitive)
+            .build()
+            .with_context(|| format!("Invalid --regex pattern '{query}'"))?;
+        Ok(Self {
+            kind: Kind::Pattern(compiled),
+        })
+    }
+
+    /// The lines of `text` with a match.
+    pub fn matching_lines(&self, text: &str) -> Vec<MatchedLine> {
+        text.lines()
+            .filter_map(|line| {
+                let ranges = match &self.kind {
+                    Kind::Pattern(regex) => {
+                        let ranges: Vec<_> = regex
+                            .find_iter(line)
+                            .map(|m| m.range())
+                            .filter(|range| !range.is_empty())
+                            .collect();
+                        if ranges.is_empty() {
+                            return None;
+                        }
+                        ranges
+                    }
+                    Kind::Normalized(query) => {
+                        if !normalize_text(line).contains(query.as_str()) {
+                            return None;
+                        }
+                        Vec::new()
+                    }
+                };
+                Some(MatchedLine {
+                    line: line.to_string(),
+                    ranges,
+                })
+            })
+            .collect()
+    }
+}
+
+impl MatchedLine {
+    /// `line` cut into `(text, is_match)` pieces.
+    pub fn segments(&self) -> Vec<(&str, bool)> {
+        let mut out = Vec::new();
+        let mut at = 0;
+        for range in &self.ranges {
+            if range.start > at {
+                out.push((&self.line[at..range.start], false));
+            }
+            out.push((&self.line[range.clone()], true));
+            at = range.end;
+        }
+        if at < self.line.len() || out.is_empty() {
+            out.push((&self.line[at..], false));
+        }
+        out
+    }
+}
+
+/// What one `search` run went through, for the closing summary.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ScanStats {
+    /// Chats with messages to search.
+    pub chats: usize,
+    /// Of those, chats read (at least partly) from the local cache.
+    pub cached_chats: usize,
+    /// Chats the server returned nothing for and the cache does not hold.
+    pub skipped_chats: usize,
+    /// REST message pages fetched.
+    pub pages: usize,
+    pub messages: usize,
+}
+
+impl ScanStats {
+    pub fn summary(&self, matches: usize) -> String {
+        let mut notes = Vec::new();
+        if self.cached_chats > 0 {
+            notes.push(format!("{} from the local cache", self.cached_chats));
+        }
+        if self.skipped_chats > 0 {
+            notes.push(format!(
+                "{} skipped: not cached on the server",
+                self.skipped_chats
+            ));
+        }
+        let notes = if notes.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", notes.join(", "))
+        };
+        format!(
+            "Scanned {} chat{}{notes}, {} page{}, {} message{}; {} match{}.",
+            self.chats,
+            plural(self.chats),
+            self.pages,
+            plural(self.pages),
+            self.messages,
+            plural(self.messages),
+            matches,
+            if matches == 1 { "" } else { "es" }
+        )
+    }
+}
+
+fn plural(n: usize) -> &'static str {
+    if n == 1 {
+        ""
+    } else {
+        "s"
+    }
+}
+
+/// Cached messages and newer ones fetched from the server, oldest first and
+/// once each; the fetched copy wins.
+pub fn merge_messages(cached: Vec<ChatMessage>, fetched: Vec<ChatMessage>) -> Vec<ChatMessage> {
+    let by_log_id: HashMap<i64, ChatMessage> = cached
+        .into_iter()
+        .chain(fetched)
+        .map(|msg| (msg.log_id, msg))
+        .collect();
+    let mut merged: Vec<ChatMessage> = by_log_id.into_values().collect();
+    merged.sort_by_key(|msg| (msg.send_at, msg.log_id));
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn message(log_id: i64, send_at: i64, text: &str) -> ChatMessage {
+        ChatMessage::from_json(
+            &json!({"logId": log_id, "authorId": 1, "type": 1, "message": text, "sendAt": send_at}),
+        )
+    }
+
+    #[test]
+    fn plain_queries_match_literally_and_ignore_case() {
+        let matcher = Matcher::new("Bank (KB)", false, false, false).unwrap();
+        let lines = matcher.matching_lines("hi\nmy bank (kb) 123-45 and BANK (KB) again");
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].ranges, vec![3..12, 24..33]);
+        assert_eq!(
+            lines[0].segments(),
+            vec![
+                ("my ", false),
+                ("bank (kb)", true),
+                (" 123-45 and ", false),
+                ("BANK (KB)", true),
+                (" again", false)
+            ]
+        );
+
+        let strict = Matcher::new("Bank", false, true, false).unwrap();
+        assert!(strict.matching_lines("bank").is_empty());
+    }
+
+    #[test]
+    fn regex_queries_highlight_every_match_on_a_line() {
+        let matcher = Matcher::new(r"\d{3}-\d{2,}", true, false, false).unwrap();
+        let lines = matcher.matching_lines("계좌 110-123456 입니다\nnone here\n또 333-44");
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].segments()[1], ("110-123456", true));
+        assert_eq!(lines[1].segments(), vec![("또 ", false), ("333-44", true)]);
+
+        assert!(Matcher::new("(", true, false, false).is_err());
+        assert!(Matcher::new("", false, false, false).is_err());
+        // Patterns that only match empty text select nothing.
+        assert!(Matcher::new("x*", true, false, false)
+            .unwrap()
+            .matching_lines("abc")
+            .is_empty());
+    }
+
+    #[test]
+    fn normalized_queries_match_whole_lines() {
+        let matcher = Matcher::new("ㅋㅋ", false, false, true).unwrap();
+        let lines = matcher.matching_lines("ㅋㅋㅋㅋ 대박\nok");
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].segments(), vec![("ㅋㅋㅋㅋ 대박", false)]);
+    }
+
+    #[test]
+    fn fetched_messages_replace_their_cached_copies() {
+        let merged = merge_messages(
+            vec![message(1, 100, "old"), message(2, 200, "cached")],
+            vec![message(3, 300, "new"), message(2, 200, "edited")],
+        );
+        let texts: Vec<&str> = merged.iter().map(|m| m.message.as_str()).collect();
+        assert_eq!(texts, ["old", "edited", "new"]);
+    }
+
+    #[test]
+    fn summary_counts_what_was_scanned() {
+        let stats = ScanStats {
+            chats: 3,
+            cached_chats: 2,
+            skipped_chats: 1,
+            pages: 1,
+            messages: 250,
+        };
+        assert_eq!(
+            stats.summary(1),
+            "Scanned 3 chats (2 from the local cache, 1 skipped: not cached on the server), \
+             1 page, 250 messages; 1 match."
+        );
+        assert_eq!(
+            ScanStats::default().summary(0),
+            "Scanned 0 chats, 0 pages, 0 messages; 0 matches."
+        );
+    }
+}
@@ -0,0 +1,334 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::output::write_atomic;
+
+/// Settings paths left out of snapshots: they change on every request (or
+/// describe the response rather than the account) and would make every
+/// snapshot differ.
+pub const EXCLUDED_FIELDS: &[&str] = &["server_time", "serverTime", "status"];
+
+/// Snapshots kept by `settings --track`; the oldest are dropped first.
+const MAX_SNAPSHOTS: usize = 30;
+
+/// The settings payload flattened to `path -> value`. Objects are walked
+/// into dotted paths; arrays stay whole with their items sorted, so a
+/// reordered list is not a change and an added item diffs as one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SettingsSnapshot {
+    pub taken_at: String,
+    pub fields: BTreeMap<String, Value>,
+}
+
+impl SettingsSnapshot {
+    pub fn new(settings: &Value, taken_at: String) -> Self {
+        Self {
+            taken_at,
+            fields: normalize(settings),
+        }
+    }
+}
+
+pub fn normalize(settings: &Value) -> BTreeMap<String, Value> {
+    fn walk(prefix: &str, value: &Value, out: &mut BTreeMap<String, Value>) {
+        match value {
+            Value::Object(object) => {
+                for (key, value) in object {
+                    let path = if prefix.is_empty() {
+                        key.clone()
+                    } else {
+                        format!("{prefix}.{key}")
+                    };
+                    if !EXCLUDED_FIELDS.contains(&path.as_str()) {
+                        walk(&path, value, out);
+                    }
+                }
+            }
+            Value::Array(items) => {
+                let mut items = items.clone();
+                items.sort_by_cached_key(Value::to_string);
+                out.insert(prefix.to_string(), Value::Array(items));
+            }
+            other => {
+                out.insert(prefix.to_string(), other.clone());
+            }
+        }
+    }
+    let mut fields = BTreeMap::new();
+    walk("", settings, &mut fields);
+    fields
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SettingsChange {
+    Added {
+        path: String,
+        value: Value,
+    },
+    Removed {
+        path: String,
+        value: Value,
+    },
+    Changed {
+        path: String,
+        old: Value,
+        new: Value,
+    },
+    ItemAdded {
+        path: String,
+        item: Value,
+    },
+    ItemRemoved {
+        path: String,
+        item: Value,
+    },
+}
+
+impl SettingsChange {
+    pub fn path(&self) -> &str {
+        match self {
+            Self::Added { path, .. }
+            | Self::Removed { path, .. }
+            | Self::Changed { path, .. }
+            | Self::ItemAdded { path, .. }
+            | Self::ItemRemoved { path, .. } => path,
+        }
+    }
+
+    /// A device appeared on the account: someone may have signed in.
+    pub fn is_new_device(&self) -> bool {
+        matches!(self, Self::Added { .. } | Self::ItemAdded { .. })
+            && self.path().to_lowercase().contains("device")
+    }
+}
+
+/// Field-level differences from `old` to `new`, new devices first.
+pub fn diff(old: &SettingsSnapshot, new: &SettingsSnapshot) -> Vec<SettingsChange> {
+    let paths: BTreeSet<&String> = old.fields.keys().chain(new.fields.keys()).collect();
+    let mut changes = Vec::new();
+    for path in paths {
+        let path_owned = || path.to_string();
+        match (old.fields.get(path), new.fields.get(path)) {
+            (None, Some(value)) => changes.push(SettingsChange::Added {
+                path: path_owned(),
+                value: value.clone(),
+            }),
+            (Some(value), None) => changes.push(SettingsChange::Removed {
+                path: path_owned(),
+                value: value.clone(),
+            }),
+            (Some(Value::Array(before)), Some(Value::Array(after))) => {
+                for item in after.iter().filter(|item| !before.contains(item)) {
+                    changes.push(SettingsChange::ItemAdded {
+                        path: path_owned(),
+                        item: item.clone(),
+                    });
+                }
+                for item in before.iter().filter(|item| !after.contains(item)) {
+                    changes.push(SettingsChange::ItemRemoved {
+                        path: path_owned(),
+                        item: item.clone(),
+                    });
+                }
+            }
+            (Some(before), Some(after)) if before != after => {
+                changes.push(SettingsChange::Changed {
+                    path: path_owned(),
+                    old: before.clone(),
+                    new: after.clone(),
+                })
+            }
+            _ => {}
+        }
+    }
+    changes.sort_by_key(|change| !change.is_new_device());
+    changes
+}
+
+/// Text for `settings --changes`: a header with both snapshot times, then
+/// one line per change. New devices get a loud marker of their own.
+pub fn render_changes(
+    old: &SettingsSnapshot,
+    new: &SettingsSnapshot,
+    changes: &[SettingsChange],
+) -> String {
+    let mut out = format!("Settings {} -> {}\n", old.taken_at, new.taken_at);
+    if changes.is_empty() {
+        out.push_str("  No changes.\n");
+        return out;
+    }
+    for change in changes {
+        let line = match change {
+            SettingsChange::Added { path, value }
+            | SettingsChange::ItemAdded { path, item: value }
+                if change.is_new_device() =>
+            {
+                format!("!! NEW DEVICE {path}: {value}")
+            }
+            SettingsChange::Added { path, value } => format!("+  {path}: {value}"),
+            SettingsChange::Removed { path, value } => format!("-  {path}: {value}"),
+            SettingsChange::Changed { path, old, new } => format!("~  {path}: {old} -> {new}"),
+            SettingsChange::ItemAdded { path, item } => format!("+  {path}[]: {item}"),
+            SettingsChange::ItemRemoved { path, item } => format!("-  {path}[]: {item}"),
+        };
+        out.push_str("  ");
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+pub fn history_path() -> Result<PathBuf> {
+    Ok(crate::paths::profile_dir()?.join("settings-history.json"))
+}
+
+/// Stored snapshots, oldest first; empty when nothing was tracked yet.
+pub fn load_history(path: &Path) -> Result<Vec<SettingsSnapshot>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let data =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&data).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Append `snapshot` to the history at `path`, keeping the newest
+/// [`MAX_SNAPSHOTS`].
+pub fn record_snapshot(path: &Path, snapshot: SettingsSnapshot) -> Result<()> {
+    let mut history = load_history(path)?;
+    history.push(snapshot);
+    let excess = history.len().saturating_sub(MAX_SNAPSHOTS);
+    history.drain(..excess);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let data = serde_json::to_string_pretty(&history)?;
+    write_atomic(path, |w| Ok(w.write_all(data.as_bytes())?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn snapshot(at: &str, settings: Value) -> SettingsSnapshot {
+        SettingsSnapshot::new(&settings, at.to_string())
+    }
+
+    #[test]
+    fn normalization_flattens_and_drops_volatile_fields() {
+        let fields = normalize(&json!({
+            "status": 0,
+            "server_time": 1_792_108_800,
+            "emailAddress": "a@example.com",
+            "profile": {"nickname": "Kim", "status": 3},
+            "devices": [{"name": "iPad"}, {"name": "Android"}],
+        }));
+        // Only the listed top-level paths are excluded.
+        assert_eq!(
+            fields.keys().collect::<Vec<_>>(),
+            [
+                "devices",
+                "emailAddress",
+                "profile.nickname",
+                "profile.status"
+            ]
+        );
+        // Item order does not matter.
+        assert_eq!(
+            fields["devices"],
+            normalize(&json!({"devices": [{"name": "Android"}, {"name": "iPad"}]}))["devices"]
+        );
+    }
+
+    #[test]
+    fn diff_reports_fields_and_calls_out_new_devices() {
+        let old = snapshot(
+            "2026-10-01T00:00:00Z",
+            json!({
+                "server_time": 1,
+                "emailAddress": "a@example.com",
+                "profile": {"nickname": "Kim", "statusMessage": "hi"},
+                "devices": [{"name": "iPhone"}],
+            }),
+        );
+        let new = snapshot(
+            "2026-10-02T00:00:00Z",
+            json!({
+                "server_time": 2,
+                "emailAddress": "b@example.com",
+                "profile": {"nickname": "Kim", "birthday": "0101"},
+                "devices": [{"name": "iPhone"}, {"name": "Unknown PC"}],
+            }),
+        );
+        let changes = diff(&old, &new);
+        assert_eq!(
+            changes,
+            [
+                SettingsChange::ItemAdded {
+                    path: "devices".into(),
+                    item: json!({"name": "Unknown PC"}),
+                },
+                SettingsChange::Changed {
+                    path: "emailAddress".into(),
+                    old: json!("a@example.com"),
+                    new: json!("b@example.com"),
+                },
+                SettingsChange::Added {
+                    path: "profile.birthday".into(),
+                    value: json!("0101"),
+                },
+                SettingsChange::Removed {
+                    path: "profile.statusMessage".into(),
+                    value: json!("hi"),
+                },
+            ]
+        );
+        assert!(changes[0].is_new_device());
+        assert!(!changes[1].is_new_device());
+
+        assert_eq!(
+            render_changes(&old, &new, &changes),
+            "Settings 2026-10-01T00:00:00Z -> 2026-10-02T00:00:00Z\n\
+             \x20 !! NEW DEVICE devices: {\"name\":\"Unknown PC\"}\n\
+             \x20 ~  emailAddress: \"a@example.com\" -> \"b@example.com\"\n\
+             \x20 +  profile.birthday: \"0101\"\n\
+             \x20 -  profile.statusMessage: \"hi\"\n"
+        );
+    }
+
+    #[test]
+    fn identical_snapshots_render_no_changes() {
+        let old = snapshot("t1", json!({"server_time": 1, "countryIso": "KR"}));
+        let new = snapshot("t2", json!({"server_time": 2, "countryIso": "KR"}));
+        let changes = diff(&old, &new);
+        assert!(changes.is_empty());
+        assert_eq!(
+            render_changes(&old, &new, &changes),
+            "Settings t1 -> t2\n  No changes.\n"
+        );
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings-history.json");
+        for i in 0..MAX_SNAPSHOTS + 3 {
+            record_snapshot(&path, snapshot(&format!("t{i}"), json!({"n": i}))).unwrap();
+        }
+        let history = load_history(&path).unwrap();
+        assert_eq!(history.len(), MAX_SNAPSHOTS);
+        assert_eq!(history[0].taken_at, "t3");
+        assert_eq!(
+            history.last().unwrap().taken_at,
+            format!("t{}", MAX_SNAPSHOTS + 2)
+        );
+    }
+}
@@ -0,0 +1,406 @@
+//! Local spam heuristics for `read --flag-spam` / `--hide-spam`.
+//!
+//! A display filter only: every signal is computed from the messages being
+//! shown and the `[spam]` config section, and nothing is sent to Kakao.
+//! Each heuristic that fires adds one signal; a message is flagged once it
+//! has `threshold` of them.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::config::SpamConfig;
+use crate::urlnorm::extract_urls;
+
+/// Phrases common in Korean open-chat investment and ad spam. `[spam]
+/// phrases` adds to them.
+const BUILTIN_PHRASES: &[&str] = &[
+    "리딩방",
+    "무료 리딩",
+    "수익 보장",
+    "원금 보장",
+    "고수익 보장",
+    "코인 추천",
+    "급등주",
+    "종목 추천",
+    "투자 상담",
+    "부업 문의",
+    "재택 부업",
+    "t.me/",
+    "open.kakao.com/o/",
+    "airdrop",
+    "free crypto",
+];
+
+/// Messages shorter than this are never counted as repeats: "ㅋㅋ" or
+/// "ok" sent three times is not a bot.
+const MIN_REPEAT_CHARS: usize = 8;
+
+/// What `read` does with flagged messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpamDisplay {
+    /// No scoring.
+    #[default]
+    Off,
+    /// `--flag-spam`: show them prefixed with `[spam?]`.
+    Flag,
+    /// `--hide-spam`: leave them out.
+    Hide,
+}
+
+impl SpamDisplay {
+    pub fn from_flags(flag: bool, hide: bool) -> Self {
+        if hide {
+            SpamDisplay::Hide
+        } else if flag {
+            SpamDisplay::Flag
+        } else {
+            SpamDisplay::Off
+        }
+    }
+}
+
+/// Tunables of the heuristics; [`Default`] is what an empty `[spam]`
+/// section gives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpamRules {
+    /// Signals a message needs to be flagged.
+    pub threshold: usize,
+    /// Distinct links in one message that count as a link dump.
+    pub max_urls: usize,
+    /// Copies of the same text by one author, within `repeat_window_secs`
+    /// of each other, that count as repetition.
+    pub repeat_count: usize,
+    pub repeat_window_secs: i64,
+    /// A link posted this soon after its author joined counts.
+    pub new_member_secs: i64,
+    /// Lowercased phrases whose presence counts.
+    pub phrases: Vec<String>,
+}
+
+impl Default for SpamRules {
+    fn default() -> Self {
+        Self {
+            threshold: 1,
+            max_urls: 3,
+            repeat_count: 3,
+            repeat_window_secs: 10 * 60,
+            new_member_secs: 10 * 60,
+            phrases: BUILTIN_PHRASES.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+}
+
+impl SpamRules {
+    /// The defaults with `config`'s overrides and extra phrases.
+    pub fn from_config(config: &SpamConfig) -> Self {
+        let defaults = Self::default();
+        let mut phrases = defaults.phrases;
+        for phrase in &config.phrases {
+            let phrase = phrase.trim().to_lowercase();
+            if !phrase.is_empty() && !phrases.contains(&phrase) {
+                phrases.push(phrase);
+            }
+        }
+        Self {
+            threshold: config.threshold.unwrap_or(defaults.threshold).max(1),
+            max_urls: config.max_urls.unwrap_or(defaults.max_urls).max(1),
+            repeat_count: config.repeat_count.unwrap_or(defaults.repeat_count).max(2),
+            repeat_window_secs: config
+                .repeat_window_secs
+                .unwrap_or(defaults.repeat_window_secs),
+            new_member_secs: config.new_member_secs.unwrap_or(defaults.new_member_secs),
+            phrases,
+        }
+    }
+}
+
+/// One message as the heuristics see it.
+#[derive(Debug, Clone)]
+pub struct Post<'a> {
+    pub author_id: i64,
+    pub send_at: i64,
+    pub text: &'a str,
+    /// Users a join feed in this message announces.
+    pub joined: Vec<i64>,
+}
+
+impl<'a> Post<'a> {
+    /// A message from its raw fields; joins are read from type-0 feeds.
+    pub fn new(author_id: i64, send_at: i64, message_type: i64, message: &'a str) -> Self {
+        let joined = if message_type == 0 {
+            joined_users(message)
+        } else {
+            Vec::new()
+        };
+        Self {
+            author_id,
+            send_at,
+            text: if message_type == 0 { "" } else { message },
+            joined,
+        }
+    }
+}
+
+/// User ids of an invite (1) or open chat join (4) feed.
+fn joined_users(feed: &str) -> Vec<i64> {
+    let Ok(feed) = serde_json::from_str::<serde_json::Value>(feed) else {
+        return Vec::new();
+    };
+    if !matches!(
+        feed.get("feedType").and_then(|t| t.as_i64()),
+        Some(1) | Some(4)
+    ) {
+        return Vec::new();
+    }
+    feed.get("members")
+        .and_then(|m| m.as_array())
+        .map(|members| {
+            members
+                .iter()
+                .filter_map(|m| m.get("userId").and_then(|id| id.as_i64()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Why a message looks like spam.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Signal {
+    /// This many distinct links.
+    Links(usize),
+    /// The same text this many times by the same author in the window.
+    Repeated(usize),
+    /// Contains this listed phrase.
+    Phrase(String),
+    /// A link this many seconds after its author joined.
+    NewMemberLink(i64),
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Signal::Links(count) => write!(f, "{count} links"),
+            Signal::Repeated(count) => write!(f, "sent {count} times"),
+            Signal::Phrase(phrase) => write!(f, "phrase \"{phrase}\""),
+            Signal::NewMemberLink(secs) => write!(f, "link {secs}s after joining"),
+        }
+    }
+}
+
+/// Lowercased with runs of whitespace collapsed, for phrase and repeat
+/// matching.
+fn fold(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+pub fn link_signal(post: &Post, rules: &SpamRules) -> Option<Signal> {
+    let links = extract_urls(post.text, false).len();
+    (links >= rules.max_urls).then_some(Signal::Links(links))
+}
+
+pub fn phrase_signal(post: &Post, rules: &SpamRules) -> Option<Signal> {
+    let text = fold(post.text);
+    rules
+        .phrases
+        .iter()
+        .find(|phrase| text.contains(phrase.as_str()))
+        .map(|phrase| Signal::Phrase(phrase.clone()))
+}
+
+/// For each post, how many copies of its text its author sent within the
+/// repeat window of it (itself included), when that reaches
+/// `repeat_count`.
+pub fn repeat_signals(posts: &[Post], rules: &SpamRules) -> Vec<Option<Signal>> {
+    let folded: Vec<String> = posts.iter().map(|post| fold(post.text)).collect();
+    let mut groups: HashMap<(i64, &str), Vec<usize>> = HashMap::new();
+    for (index, post) in posts.iter().enumerate() {
+        if folded[index].chars().count() >= MIN_REPEAT_CHARS {
+            groups
+                .entry((post.author_id, folded[index].as_str()))
+                .or_default()
+                .push(index);
+        }
+    }
+    let mut signals = vec![None; posts.len()];
+    for indices in groups.values() {
+        for &index in indices {
+            let copies = indices
+                .iter()
+                .filter(|&&other| {
+                    (posts[other].send_at - posts[index].send_at).abs() <= rules.repeat_window_secs
+                })
+                .count();
+            if copies >= rules.repeat_count {
+                signals[index] = Some(Signal::Repeated(copies));
+            }
+        }
+    }
+    signals
+}
+
+/// For each post with a link, the join it follows within
+/// `new_member_secs`, from join feeds among `posts`.
+pub fn new_member_signals(posts: &[Post], rules: &SpamRules) -> Vec<Option<Signal>> {
+    let mut joined_at: HashMap<i64, i64> = HashMap::new();
+    for post in posts {
+        for user_id in &post.joined {
+            joined_at.insert(*user_id, post.send_at);
+        }
+    }
+    posts
+        .iter()
+        .map(|post| {
+            let since_join = post.send_at - *joined_at.get(&post.author_id)?;
+            ((0..=rules.new_member_secs).contains(&since_join)
+                && !extract_urls(post.text, false).is_empty())
+            .then_some(Signal::NewMemberLink(since_join))
+        })
+        .collect()
+}
+
+/// The signals of every post, index-aligned with `posts`.
+pub fn score(posts: &[Post], rules: &SpamRules) -> Vec<Vec<Signal>> {
+    let repeats = repeat_signals(posts, rules);
+    let joins = new_member_signals(posts, rules);
+    posts
+        .iter()
+        .zip(repeats)
+        .zip(joins)
+        .map(|((post, repeat), join)| {
+            [
+                link_signal(post, rules),
+                repeat,
+                phrase_signal(post, rules),
+                join,
+            ]
+            .into_iter()
+            .flatten()
+            .collect()
+        })
+        .collect()
+}
+
+/// Whether `signals` are enough to flag a message.
+pub fn is_flagged(signals: &[Signal], rules: &SpamRules) -> bool {
+    signals.len() >= rules.threshold
+}
+
+/// Flagged messages per author, most first.
+pub fn flagged_by_author(posts: &[Post], flagged: &[bool]) -> Vec<(i64, usize)> {
+    let mut counts: HashMap<i64, usize> = HashMap::new();
+    for (post, _) in posts.iter().zip(flagged).filter(|(_, flagged)| **flagged) {
+        *counts.entry(post.author_id).or_default() += 1;
+    }
+    let mut counts: Vec<(i64, usize)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn say(author_id: i64, send_at: i64, text: &str) -> Post<'_> {
+        Post::new(author_id, send_at, 1, text)
+    }
+
+    #[test]
+    fn link_dumps_need_max_urls_distinct_links() {
+        let rules = SpamRules::default();
+        let one = say(1, 0, "see https://example.com");
+        assert_eq!(link_signal(&one, &rules), None);
+        let dump = say(1, 0, "https://a.com https://b.com www.c.com https://a.com");
+        assert_eq!(link_signal(&dump, &rules), Some(Signal::Links(3)));
+    }
+
+    #[test]
+    fn phrases_match_across_case_and_spacing() {
+        let rules = SpamRules::from_config(&SpamConfig {
+            phrases: vec!["Lucky Draw".into()],
+            ..Default::default()
+        });
+        assert_eq!(
+            phrase_signal(&say(1, 0, "오늘  무료   리딩 합니다"), &rules),
+            Some(Signal::Phrase("무료 리딩".into()))
+        );
+        assert_eq!(
+            phrase_signal(&say(1, 0, "join the LUCKY draw now"), &rules),
+            Some(Signal::Phrase("lucky draw".into()))
+        );
+        assert_eq!(phrase_signal(&say(1, 0, "점심 뭐 먹지"), &rules), None);
+    }
+
+    #[test]
+    fn repeats_count_one_author_within_the_window() {
+        let rules = SpamRules::default();
+        let text = "지금 바로 참여하세요 선착순";
+        let posts = [
+            say(1, 0, text),
+            say(1, 60, text),
+            say(2, 90, text),
+            say(1, 120, text),
+            // Long after the others: only two copies are within its window.
+            say(1, 5000, text),
+            say(3, 0, "ㅋㅋㅋ"),
+            say(3, 1, "ㅋㅋㅋ"),
+            say(3, 2, "ㅋㅋㅋ"),
+        ];
+        let signals = repeat_signals(&posts, &rules);
+        assert_eq!(signals[0], Some(Signal::Repeated(3)));
+        assert_eq!(signals[3], Some(Signal::Repeated(3)));
+        assert_eq!(signals[2], None, "another author's copy is their own");
+        assert_eq!(signals[4], None);
+        assert!(signals[5..].iter().all(Option::is_none), "short texts");
+    }
+
+    #[test]
+    fn links_right_after_joining_count() {
+        let rules = SpamRules::default();
+        let join = r#"{"feedType":4,"members":[{"userId":9,"nickName":"new"}]}"#;
+        let posts = [
+            Post::new(9, 100, 0, join),
+            say(9, 160, "https://scam.example"),
+            say(9, 170, "hello everyone"),
+            say(9, 100 + 3600, "https://later.example"),
+            say(8, 160, "https://old.member.example"),
+        ];
+        let signals = new_member_signals(&posts, &rules);
+        assert_eq!(signals[1], Some(Signal::NewMemberLink(60)));
+        assert_eq!(signals[2], None, "no link");
+        assert_eq!(signals[3], None, "long after joining");
+        assert_eq!(signals[4], None, "no join seen");
+        assert!(posts[0].text.is_empty());
+    }
+
+    #[test]
+    fn the_threshold_counts_signals_and_authors_are_summed() {
+        let mut rules = SpamRules::default();
+        let posts = [
+            say(1, 0, "리딩방 https://a.com https://b.com https://c.com"),
+            say(2, 0, "리딩방 있나요?"),
+            say(3, 0, "안녕하세요"),
+        ];
+        let signals = score(&posts, &rules);
+        assert_eq!(signals[0].len(), 2);
+        assert_eq!(signals[1].len(), 1);
+        assert!(signals[2].is_empty());
+
+        let flagged: Vec<bool> = signals.iter().map(|s| is_flagged(s, &rules)).collect();
+        assert_eq!(flagged, [true, true, false]);
+        assert_eq!(flagged_by_author(&posts, &flagged), [(1, 1), (2, 1)]);
+
+        rules.threshold = 2;
+        assert!(is_flagged(&signals[0], &rules));
+        assert!(!is_flagged(&signals[1], &rules));
+    }
+
+    #[test]
+    fn display_flags_prefer_hiding() {
+        assert_eq!(SpamDisplay::from_flags(false, false), SpamDisplay::Off);
+        assert_eq!(SpamDisplay::from_flags(true, false), SpamDisplay::Flag);
+        assert_eq!(SpamDisplay::from_flags(true, true), SpamDisplay::Hide);
+    }
+}
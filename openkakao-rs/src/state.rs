@@ -1,11 +1,14 @@
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::cursor::{Cursor, MessageCursor};
+
 const RELOGIN_MIN_INTERVAL_SECS: i64 = 5 * 60;
 const RELOGIN_MIN_INTERVAL_PASSWORD_CMD_SECS: i64 = 60;
 const RENEW_MIN_INTERVAL_SECS: i64 = 2 * 60;
@@ -28,6 +31,81 @@ pub struct OpenKakaoState {
     pub last_webhook_at: Option<String>,
     pub last_guard_reason: Option<String>,
     pub last_guard_at: Option<String>,
+    /// MemoChat (나와의 채팅) id, remembered per account for `--memo`.
+    pub memo_chat_id: Option<i64>,
+    pub memo_chat_user_id: Option<i64>,
+    /// Restriction early-warning history for `health`, bounded in size.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub health_observations: Vec<crate::health::HealthObservation>,
+    /// Last seen restriction-like settings values, to notice them flip.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub health_settings_flags: BTreeMap<String, String>,
+    /// Oldest log id shown by the last `read --continue`, per chat: the
+    /// next invocation pages back from there.
+    #[serde(
+        default,
+        skip_serializing_if = "BTreeMap::is_empty",
+        deserialize_with = "read_cursors_compat"
+    )]
+    pub read_cursors: BTreeMap<i64, Cursor>,
+    /// Unread state per room as of the last `chats --since-state`, keyed by
+    /// account user id so several accounts do not overwrite each other.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub chat_unread: BTreeMap<i64, BTreeMap<i64, UnreadMark>>,
+    /// Which account to use when the saved credentials and KakaoTalk are
+    /// logged in as different ones, keyed by
+    /// [`AccountConflict::key`](crate::account_conflict::AccountConflict::key).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub account_choices: BTreeMap<String, crate::account_conflict::AccountChoice>,
+}
+
+/// `read_cursors` as saved. Files from before cursors carried their kind
+/// hold bare numbers, which were always message cursors.
+fn read_cursors_compat<'de, D>(deserializer: D) -> Result<BTreeMap<i64, Cursor>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Saved {
+        Tagged(Cursor),
+        Bare(i64),
+    }
+    let saved = BTreeMap::<i64, Saved>::deserialize(deserializer)?;
+    Ok(saved
+        .into_iter()
+        .map(|(chat_id, cursor)| {
+            let cursor = match cursor {
+                Saved::Tagged(cursor) => cursor,
+                Saved::Bare(raw) => MessageCursor::new(raw).into(),
+            };
+            (chat_id, cursor)
+        })
+        .collect())
+}
+
+/// What `chats --since-state` remembers about one room.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnreadMark {
+    /// Unread messages; LOCO listings only know unread or not, stored as 1.
+    pub unread: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_log_id: Option<i64>,
+}
+
+impl UnreadMark {
+    /// Unread now, and either was not before or has more (or newer) unread
+    /// messages than `before`.
+    pub fn grew_since(&self, before: Option<&UnreadMark>) -> bool {
+        if self.unread <= 0 {
+            return false;
+        }
+        let Some(before) = before.filter(|b| b.unread > 0) else {
+            return true;
+        };
+        self.unread > before.unread
+            || matches!((self.last_log_id, before.last_log_id), (Some(now), Some(then)) if now > then)
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -59,18 +137,20 @@ pub struct SafetySnapshot {
 }
 
 pub fn state_path() -> Result<PathBuf> {
-    let home = dirs::home_dir().context("Could not resolve home directory")?;
-    Ok(home.join(".config").join("openkakao").join("state.json"))
+    Ok(crate::paths::profile_dir()?.join("state.json"))
 }
 
 pub fn load_state() -> Result<OpenKakaoState> {
-    let path = state_path()?;
+    load_state_from(&state_path()?)
+}
+
+fn load_state_from(path: &Path) -> Result<OpenKakaoState> {
     if !path.exists() {
         return Ok(OpenKakaoState::default());
     }
 
     let data =
-        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
     let state: OpenKakaoState = serde_json::from_str(&data)
         .with_context(|| format!("Failed to parse {}", path.display()))?;
     Ok(state)
@@ -78,6 +158,11 @@ pub fn load_state() -> Result<OpenKakaoState> {
 
 pub fn save_state(state: &OpenKakaoState) -> Result<PathBuf> {
     let path = state_path()?;
+    save_state_to(&path, state)?;
+    Ok(path)
+}
+
+fn save_state_to(path: &Path, state: &OpenKakaoState) -> Result<()> {
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)
             .with_context(|| format!("Failed to create {}", parent.display()))?;
@@ -93,17 +178,17 @@ pub fn save_state(state: &OpenKakaoState) -> Result<PathBuf> {
             .create(true)
             .truncate(true)
             .mode(0o600)
-            .open(&path)
+            .open(path)
             .with_context(|| format!("Failed to create {}", path.display()))?
     };
     #[cfg(not(unix))]
     let mut file =
-        fs::File::create(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+        fs::File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
 
     file.write_all(data.as_bytes())
         .with_context(|| format!("Failed to write {}", path.display()))?;
 
-    Ok(path)
+    Ok(())
 }
 
 fn parse_ts(value: Option<&str>) -> Option<DateTime<Utc>> {
@@ -207,6 +292,165 @@ pub fn record_guard(reason: &str) -> Result<()> {
     })
 }
 
+/// The remembered MemoChat id, if it was resolved for `user_id`.
+pub fn cached_memo_chat_id(user_id: i64) -> Result<Option<i64>> {
+    let state = load_state()?;
+    Ok(state
+        .memo_chat_id
+        .filter(|_| state.memo_chat_user_id == Some(user_id)))
+}
+
+pub fn record_memo_chat_id(user_id: i64, chat_id: i64) -> Result<()> {
+    mutate_state(|state| {
+        state.memo_chat_id = Some(chat_id);
+        state.memo_chat_user_id = Some(user_id);
+    })
+}
+
+/// The account chosen earlier for the saved/extracted pair `key`.
+pub fn remembered_account_choice(
+    key: &str,
+) -> Result<Option<crate::account_conflict::AccountChoice>> {
+    Ok(load_state()?.account_choices.get(key).copied())
+}
+
+pub fn remember_account_choice(
+    key: &str,
+    choice: crate::account_conflict::AccountChoice,
+) -> Result<()> {
+    mutate_state(|state| {
+        state.account_choices.insert(key.to_string(), choice);
+    })
+}
+
+/// `read --continue` paging through one chat, with the cursor kept in the
+/// state file's `read_cursors` between invocations.
+pub struct ReadPaging {
+    path: PathBuf,
+    chat_id: i64,
+}
+
+/// One page of a `read --continue` session.
+pub struct ContinuedPage<T> {
+    pub messages: Vec<T>,
+    /// Cursor the page was fetched before; `None` for the newest page.
+    pub cursor: Option<MessageCursor>,
+    /// The saved cursor was rejected and dropped, so this is the newest page.
+    pub reset: bool,
+}
+
+impl ReadPaging {
+    pub fn new(chat_id: i64) -> Result<Self> {
+        Ok(Self {
+            path: state_path()?,
+            chat_id,
+        })
+    }
+
+    /// Forget the saved cursor; the next page is the newest one.
+    pub fn restart(&self) -> Result<()> {
+        self.store(None)
+    }
+
+    /// Fetch the page before the saved cursor and save its oldest log id for
+    /// the next invocation. When the server rejects the saved cursor (the
+    /// history shifted under it) the cursor is dropped and the newest page is
+    /// fetched instead. An empty page leaves the cursor where it was. A
+    /// saved cursor of another kind is refused rather than sent.
+    pub fn next_page<T>(
+        &self,
+        mut fetch: impl FnMut(Option<MessageCursor>) -> Result<Vec<T>>,
+        log_id: impl Fn(&T) -> i64,
+    ) -> Result<ContinuedPage<T>> {
+        let saved = load_state_from(&self.path)?
+            .read_cursors
+            .get(&self.chat_id)
+            .copied()
+            .map(MessageCursor::try_from)
+            .transpose()
+            .with_context(|| {
+                format!(
+                    "The saved --continue cursor for chat {} cannot page messages; run with --restart",
+                    self.chat_id
+                )
+            })?;
+        let (messages, cursor, reset) = match fetch(saved) {
+            Ok(messages) => (messages, saved, false),
+            Err(err) if saved.is_some() && cursor_rejected(&err) => {
+                self.store(None)?;
+                (fetch(None)?, None, true)
+            }
+            Err(err) => return Err(err),
+        };
+        if let Some(oldest) = messages.iter().map(&log_id).filter(|&id| id != 0).min() {
+            self.store(Some(MessageCursor::new(oldest)))?;
+        }
+        Ok(ContinuedPage {
+            messages,
+            cursor,
+            reset,
+        })
+    }
+
+    fn store(&self, cursor: Option<MessageCursor>) -> Result<()> {
+        let mut state = load_state_from(&self.path)?;
+        match cursor {
+            Some(cursor) => state.read_cursors.insert(self.chat_id, cursor.into()),
+            None => state.read_cursors.remove(&self.chat_id),
+        };
+        save_state_to(&self.path, &state)
+    }
+}
+
+/// `chats --since-state` exit status when no room changed, so a script can
+/// tell "nothing new" (5) from "something new" (0) and from failure (1).
+pub const NO_UNREAD_CHANGES_EXIT_CODE: i32 = 5;
+
+pub fn since_state_exit_code(changed_rooms: usize) -> i32 {
+    if changed_rooms > 0 {
+        0
+    } else {
+        NO_UNREAD_CHANGES_EXIT_CODE
+    }
+}
+
+/// Compare `current` with the marks saved for `user_id`, save `current` in
+/// their place and return the rooms that became unread or gained unread
+/// messages. Rooms missing from `current` (not fetched this time) keep their
+/// saved mark.
+pub fn update_unread_marks(
+    user_id: i64,
+    current: &BTreeMap<i64, UnreadMark>,
+) -> Result<BTreeSet<i64>> {
+    update_unread_marks_at(&state_path()?, user_id, current)
+}
+
+fn update_unread_marks_at(
+    path: &Path,
+    user_id: i64,
+    current: &BTreeMap<i64, UnreadMark>,
+) -> Result<BTreeSet<i64>> {
+    let mut state = load_state_from(path)?;
+    let saved = state.chat_unread.entry(user_id).or_default();
+    let changed = current
+        .iter()
+        .filter(|(chat_id, mark)| mark.grew_since(saved.get(chat_id)))
+        .map(|(&chat_id, _)| chat_id)
+        .collect();
+    saved.extend(current);
+    save_state_to(path, &state)?;
+    Ok(changed)
+}
+
+/// A server-side refusal of the request, as opposed to a network or auth
+/// problem that says nothing about the cursor.
+fn cursor_rejected(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<crate::error::OpenKakaoError>(),
+        Some(e @ crate::error::OpenKakaoError::RestApi { .. }) if !e.is_auth_failure()
+    )
+}
+
 pub fn record_success(transport: &str, recovery_source: Option<&str>) -> Result<()> {
     mutate_state(|state| {
         state.last_success_at = Some(now_string());
@@ -317,6 +561,18 @@ pub fn recovery_state_summary() -> Result<String> {
     ))
 }
 
+/// Update the health history and settings snapshot together.
+pub fn update_health(
+    update: impl FnOnce(&mut Vec<crate::health::HealthObservation>, &mut BTreeMap<String, String>),
+) -> Result<()> {
+    mutate_state(|state| {
+        update(
+            &mut state.health_observations,
+            &mut state.health_settings_flags,
+        )
+    })
+}
+
 fn mutate_state(mutator: impl FnOnce(&mut OpenKakaoState)) -> Result<()> {
     let mut state = load_state()?;
     mutator(&mut state);
@@ -346,6 +602,222 @@ mod tests {
         assert_eq!(secs, 1800);
     }
 
+    fn saved(raw: i64) -> Cursor {
+        MessageCursor::new(raw).into()
+    }
+
+    /// A chat whose server keeps log ids 1..=50 and returns pages of 10
+    /// older than the cursor; cursors in `stale` are refused.
+    fn server(stale: &'static [i64]) -> impl FnMut(Option<MessageCursor>) -> Result<Vec<i64>> {
+        move |cursor| {
+            let cursor = cursor.map(MessageCursor::get);
+            if cursor.is_some_and(|c| stale.contains(&c)) {
+                return Err(crate::error::OpenKakaoError::RestApi {
+                    status: -805,
+                    message: "invalid cursor".into(),
+                }
+                .into());
+            }
+            let before = cursor.unwrap_or(51);
+            Ok(((before - 10).max(1)..before).collect())
+        }
+    }
+
+    #[test]
+    fn read_continue_pages_back_across_invocations() {
+        let dir = tempfile::tempdir().unwrap();
+        let paging = |chat_id| ReadPaging {
+            path: dir.path().join("state.json"),
+            chat_id,
+        };
+
+        // First invocation starts at the newest page.
+        let first = paging(7).next_page(server(&[]), |&id| id).unwrap();
+        assert_eq!(first.messages, (41..=50).collect::<Vec<_>>());
+        assert_eq!(first.cursor, None);
+
+        // Second resumes before the oldest message the first one showed.
+        let second = paging(7).next_page(server(&[]), |&id| id).unwrap();
+        assert_eq!(second.cursor, Some(MessageCursor::new(41)));
+        assert_eq!(second.messages, (31..=40).collect::<Vec<_>>());
+
+        // Third: the history shifted and the saved cursor is refused, so it
+        // falls back to the newest page and starts the session over.
+        let third = paging(7).next_page(server(&[31]), |&id| id).unwrap();
+        assert!(third.reset);
+        assert_eq!(third.cursor, None);
+        assert_eq!(third.messages, (41..=50).collect::<Vec<_>>());
+
+        let state = load_state_from(&dir.path().join("state.json")).unwrap();
+        assert_eq!(state.read_cursors.get(&7), Some(&saved(41)));
+    }
+
+    #[test]
+    fn read_continue_restart_and_other_chats() {
+        let dir = tempfile::tempdir().unwrap();
+        let paging = |chat_id| ReadPaging {
+            path: dir.path().join("state.json"),
+            chat_id,
+        };
+        paging(1).next_page(server(&[]), |&id| id).unwrap();
+        paging(1).next_page(server(&[]), |&id| id).unwrap();
+        paging(2).next_page(server(&[]), |&id| id).unwrap();
+
+        paging(1).restart().unwrap();
+        let page = paging(1).next_page(server(&[]), |&id| id).unwrap();
+        assert_eq!(page.cursor, None);
+        // Chat 2 kept its own place.
+        assert_eq!(
+            paging(2).next_page(server(&[]), |&id| id).unwrap().cursor,
+            Some(MessageCursor::new(41))
+        );
+
+        // Running off the start of history keeps the last cursor.
+        let path = dir.path().join("state.json");
+        let mut state = load_state_from(&path).unwrap();
+        state.read_cursors.insert(3, saved(1));
+        save_state_to(&path, &state).unwrap();
+        let empty = paging(3).next_page(server(&[]), |&id| id).unwrap();
+        assert!(empty.messages.is_empty());
+        assert_eq!(
+            load_state_from(&path).unwrap().read_cursors.get(&3),
+            Some(&saved(1))
+        );
+    }
+
+    #[test]
+    fn saved_cursors_keep_their_kind_and_the_wrong_kind_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let paging = |chat_id| ReadPaging {
+            path: path.clone(),
+            chat_id,
+        };
+        paging(7).next_page(server(&[]), |&id| id).unwrap();
+        let data: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(
+            data["read_cursors"]["7"],
+            serde_json::json!({"kind": "messages", "cursor": 41})
+        );
+        assert_eq!(
+            load_state_from(&path).unwrap().read_cursors.get(&7),
+            Some(&saved(41))
+        );
+
+        // A chat list cursor is never sent to the message listing.
+        let mut state = load_state_from(&path).unwrap();
+        state
+            .read_cursors
+            .insert(8, crate::cursor::ChatListCursor::new(3).into());
+        save_state_to(&path, &state).unwrap();
+        let err = paging(8)
+            .next_page(|_| -> Result<Vec<i64>> { unreachable!() }, |&id| id)
+            .err()
+            .unwrap();
+        assert!(
+            format!("{err:#}").contains("found a chat list cursor"),
+            "{err:#}"
+        );
+    }
+
+    #[test]
+    fn bare_cursors_from_older_state_files_are_message_cursors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        fs::write(&path, r#"{"read_cursors": {"7": 41}}"#).unwrap();
+        let state = load_state_from(&path).unwrap();
+        assert_eq!(state.read_cursors.get(&7), Some(&saved(41)));
+    }
+
+    #[test]
+    fn network_errors_keep_the_cursor() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let mut state = OpenKakaoState::default();
+        state.read_cursors.insert(1, saved(30));
+        save_state_to(&path, &state).unwrap();
+
+        let paging = ReadPaging {
+            path: path.clone(),
+            chat_id: 1,
+        };
+        let result = paging.next_page(
+            |_| -> Result<Vec<i64>> { Err(anyhow::anyhow!("connection reset")) },
+            |&id| id,
+        );
+        assert!(result.is_err());
+        assert_eq!(
+            load_state_from(&path).unwrap().read_cursors.get(&1),
+            Some(&saved(30))
+        );
+    }
+
+    #[test]
+    fn unread_marks_report_only_new_or_growing_rooms() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let mark = |unread, last_log_id| UnreadMark {
+            unread,
+            last_log_id,
+        };
+        let snapshot = |marks: &[(i64, UnreadMark)]| marks.iter().copied().collect();
+
+        // First run: everything unread is new.
+        let first = snapshot(&[(1, mark(2, None)), (2, mark(0, None)), (3, mark(1, None))]);
+        let changed = update_unread_marks_at(&path, 100, &first).unwrap();
+        assert_eq!(changed, BTreeSet::from([1, 3]));
+        assert_eq!(since_state_exit_code(changed.len()), 0);
+
+        // Nothing moved: no changes.
+        let changed = update_unread_marks_at(&path, 100, &first).unwrap();
+        assert!(changed.is_empty());
+        assert_eq!(
+            since_state_exit_code(changed.len()),
+            NO_UNREAD_CHANGES_EXIT_CODE
+        );
+
+        // Room 1 grew, room 2 became unread, room 3 was read.
+        let second = snapshot(&[(1, mark(5, None)), (2, mark(1, None)), (3, mark(0, None))]);
+        let changed = update_unread_marks_at(&path, 100, &second).unwrap();
+        assert_eq!(changed, BTreeSet::from([1, 2]));
+
+        // Fewer unread (partly read) is not a change; unread again after
+        // being read is.
+        let third = snapshot(&[(1, mark(3, None)), (3, mark(1, None))]);
+        let changed = update_unread_marks_at(&path, 100, &third).unwrap();
+        assert_eq!(changed, BTreeSet::from([3]));
+        // Room 2 was not in the listing and keeps its mark.
+        let state = load_state_from(&path).unwrap();
+        assert_eq!(state.chat_unread[&100][&2], mark(1, None));
+
+        // LOCO marks only know unread or not: a newer last log id counts.
+        let loco = snapshot(&[(3, mark(1, Some(50)))]);
+        update_unread_marks_at(&path, 100, &loco).unwrap();
+        let newer = snapshot(&[(3, mark(1, Some(51)))]);
+        let changed = update_unread_marks_at(&path, 100, &newer).unwrap();
+        assert_eq!(changed, BTreeSet::from([3]));
+    }
+
+    #[test]
+    fn unread_marks_are_kept_per_account() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let unread = BTreeMap::from([(
+            1,
+            UnreadMark {
+                unread: 1,
+                last_log_id: None,
+            },
+        )]);
+        update_unread_marks_at(&path, 100, &unread).unwrap();
+        // Another account sees the same room id for the first time.
+        let changed = update_unread_marks_at(&path, 200, &unread).unwrap();
+        assert_eq!(changed, BTreeSet::from([1]));
+        let changed = update_unread_marks_at(&path, 100, &unread).unwrap();
+        assert!(changed.is_empty());
+    }
+
     #[test]
     fn rate_limit_reports_remaining_time() {
         let now = Utc::now();
@@ -0,0 +1,255 @@
+use std::fmt;
+
+use serde::Serialize;
+
+/// Interpretation of a status code returned by Kakao's LOCO or REST
+/// servers. Codes not in the table keep their raw number in `Unknown`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum KakaoStatus {
+    Success,
+    NoStatus,
+    MissingParameter,
+    Unsupported,
+    AccountRestricted,
+    SpamLimited,
+    BadRequest,
+    ServerError,
+    TemporarilyRestricted,
+    AuthRejected,
+    AccountBlocked,
+    AuthRequired,
+    UpgradeRequired,
+    Maintenance,
+    WrongPassword,
+    AccountNotFound,
+    VerificationRequired,
+    DeviceNotRegistered,
+    HttpUnauthorized,
+    HttpForbidden,
+    Unknown(i64),
+}
+
+/// (code, status, explanation, suggested action)
+const KNOWN_STATUSES: &[(i64, KakaoStatus, &str, &str)] = &[
+    (0, KakaoStatus::Success, "Success", "None."),
+    (
+        -1,
+        KakaoStatus::NoStatus,
+        "Connection failed or the response carried no status",
+        "Run 'openkakao-rs doctor --loco' to check connectivity.",
+    ),
+    (
+        -203,
+        KakaoStatus::MissingParameter,
+        "Missing required parameter",
+        "The method exists but the request body is incomplete; this is a client bug.",
+    ),
+    (
+        -300,
+        KakaoStatus::Unsupported,
+        "Unsupported request or device mismatch",
+        "Retry once; if it persists, the method is likely not valid for the macOS client.",
+    ),
+    (
+        -301,
+        KakaoStatus::AccountRestricted,
+        "Account restricted, possibly under review",
+        "Stop and check the KakaoTalk app. Do not retry aggressively.",
+    ),
+    (
+        -303,
+        KakaoStatus::SpamLimited,
+        "Sending too fast; spam limit reached",
+        "Wait several minutes before sending again.",
+    ),
+    (
+        -400,
+        KakaoStatus::BadRequest,
+        "Bad request",
+        "Check the command arguments; the server rejected the request shape.",
+    ),
+    (
+        -500,
+        KakaoStatus::ServerError,
+        "Kakao internal server error",
+        "Retry later; the failure is on Kakao's side.",
+    ),
+    (
+        -805,
+        KakaoStatus::TemporarilyRestricted,
+        "Temporarily restricted",
+        "Stop automated traffic and wait before retrying.",
+    ),
+    (
+        -950,
+        KakaoStatus::AuthRejected,
+        "Authentication rejected: token expired, session replaced, or account restricted",
+        "Open KakaoTalk, browse a few chats, then run 'openkakao-rs login --save'. \
+         If it keeps happening, check the app for a restriction notice.",
+    ),
+    (
+        -997,
+        KakaoStatus::AccountBlocked,
+        "Account blocked or needs re-verification",
+        "Open the KakaoTalk app and complete any verification it asks for.",
+    ),
+    (
+        -998,
+        KakaoStatus::AuthRequired,
+        "Authentication required",
+        "Run 'openkakao-rs relogin' or 'openkakao-rs login --save'.",
+    ),
+    (
+        -999,
+        KakaoStatus::UpgradeRequired,
+        "Client version too old",
+        "Update KakaoTalk, then run 'openkakao-rs login --save' to pick up the new version.",
+    ),
+    (
+        -9797,
+        KakaoStatus::Maintenance,
+        "Kakao servers under maintenance",
+        "Retry after the maintenance window.",
+    ),
+    (
+        12,
+        KakaoStatus::WrongPassword,
+        "Login rejected: wrong password",
+        "Check the password returned by [auth] password_cmd.",
+    ),
+    (
+        30,
+        KakaoStatus::AccountNotFound,
+        "Login rejected: no account for this email",
+        "Check the email used for relogin.",
+    ),
+    (
+        32,
+        KakaoStatus::VerificationRequired,
+        "Login needs extra verification",
+        "Log in once in the KakaoTalk app to complete verification, then retry.",
+    ),
+    (
+        -100,
+        KakaoStatus::DeviceNotRegistered,
+        "Device not registered for this account",
+        "Log in with the KakaoTalk app on this Mac, then run 'openkakao-rs login --save'.",
+    ),
+    // HTTP 401/403 from the REST API, negated so they cannot collide with
+    // Kakao's own codes.
+    (
+        -401,
+        KakaoStatus::HttpUnauthorized,
+        "Token invalid: the REST API answered HTTP 401",
+        "Open KakaoTalk, open the chat list once, then run 'openkakao-rs login --save'.",
+    ),
+    (
+        -403,
+        KakaoStatus::HttpForbidden,
+        "Token not accepted for this endpoint: the REST API answered HTTP 403",
+        "Run 'openkakao-rs auth-status' to see which endpoints the token can reach.",
+    ),
+];
+
+impl KakaoStatus {
+    pub fn from_code(code: i64) -> Self {
+        KNOWN_STATUSES
+            .iter()
+            .find(|(c, ..)| *c == code)
+            .map(|(_, status, ..)| *status)
+            .unwrap_or(KakaoStatus::Unknown(code))
+    }
+
+    pub fn code(&self) -> i64 {
+        match self {
+            KakaoStatus::Unknown(code) => *code,
+            _ => self.entry().map_or(0, |(code, ..)| *code),
+        }
+    }
+
+    pub fn is_known(&self) -> bool {
+        !matches!(self, KakaoStatus::Unknown(_))
+    }
+
+    /// Short description of what the status means.
+    pub fn explanation(&self) -> &'static str {
+        self.entry()
+            .map(|(_, _, text, _)| *text)
+            .unwrap_or("Unrecognized Kakao status")
+    }
+
+    /// What the user can do about it.
+    pub fn suggestion(&self) -> &'static str {
+        self.entry()
+            .map(|(.., action)| *action)
+            .unwrap_or("Re-run with --verbose to see the raw response.")
+    }
+
+    fn entry(&self) -> Option<&'static (i64, KakaoStatus, &'static str, &'static str)> {
+        KNOWN_STATUSES.iter().find(|(_, status, ..)| status == self)
+    }
+}
+
+impl fmt::Display for KakaoStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KakaoStatus::Unknown(code) => write!(f, "unrecognized status {code}"),
+            known => write!(f, "{}. {}", known.explanation(), known.suggestion()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_codes_round_trip() {
+        for (code, status, ..) in KNOWN_STATUSES {
+            assert_eq!(KakaoStatus::from_code(*code), *status);
+            assert_eq!(status.code(), *code);
+            assert!(status.is_known());
+        }
+    }
+
+    #[test]
+    fn table_has_no_duplicate_codes() {
+        let mut codes: Vec<i64> = KNOWN_STATUSES.iter().map(|(c, ..)| *c).collect();
+        codes.sort_unstable();
+        codes.dedup();
+        assert_eq!(codes.len(), KNOWN_STATUSES.len());
+    }
+
+    #[test]
+    fn unknown_codes_keep_the_raw_number() {
+        let status = KakaoStatus::from_code(-4242);
+        assert_eq!(status, KakaoStatus::Unknown(-4242));
+        assert_eq!(status.code(), -4242);
+        assert!(!status.is_known());
+        assert!(status.to_string().contains("-4242"));
+    }
+
+    #[test]
+    fn display_includes_explanation_and_action() {
+        let text = KakaoStatus::from_code(-950).to_string();
+        assert!(text.contains("Authentication rejected"));
+        assert!(text.contains("login --save"));
+    }
+
+    #[test]
+    fn http_auth_statuses_say_the_token_is_invalid() {
+        let text = KakaoStatus::from_code(-401).to_string();
+        assert!(text.starts_with("Token invalid"));
+        assert!(!text.contains("unrecognized"));
+    }
+
+    #[test]
+    fn serializes_as_snake_case() {
+        assert_eq!(
+            serde_json::to_value(KakaoStatus::AuthRejected).unwrap(),
+            "auth_rejected"
+        );
+    }
+}
@@ -0,0 +1,231 @@
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+use crate::error::OpenKakaoError;
+use crate::util::message_type_label;
+
+/// Set to `1` to behave as if `--strict` was passed.
+pub const STRICT_ENV: &str = "OPENKAKAO_STRICT";
+
+/// Fields every message must carry, as (LOCO/REST payload key, normalized key).
+const REQUIRED_FIELDS: &[[&str; 2]] = &[
+    ["logId", "log_id"],
+    ["authorId", "author_id"],
+    ["type", "message_type"],
+    ["sendAt", "send_at"],
+];
+
+/// How much of the redacted payload goes into a strict-mode error.
+const SNIPPET_CHARS: usize = 300;
+
+static SCHEMA_POLICY: OnceLock<SchemaPolicy> = OnceLock::new();
+
+/// What to do when a message payload does not match what we know how to render.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum SchemaPolicy {
+    /// Render placeholders and carry on.
+    #[default]
+    Lenient,
+    /// Fail the command with [`OpenKakaoError::SchemaDrift`].
+    Strict,
+    /// Keep going, but dump each offending raw object into this directory.
+    Collect(PathBuf),
+}
+
+pub fn set_schema_policy(policy: SchemaPolicy) {
+    let _ = SCHEMA_POLICY.set(policy);
+}
+
+pub fn schema_policy() -> SchemaPolicy {
+    if let Some(policy) = SCHEMA_POLICY.get() {
+        return policy.clone();
+    }
+    if std::env::var(STRICT_ENV).is_ok_and(|v| v.trim() == "1") {
+        SchemaPolicy::Strict
+    } else {
+        SchemaPolicy::Lenient
+    }
+}
+
+fn field<'a>(msg: &'a Value, keys: &[&str]) -> Option<&'a Value> {
+    keys.iter().find_map(|k| msg.get(*k))
+}
+
+/// First problem with a message object, either a raw chat log (`logId`,
+/// `type`, ...) or a normalized one (`log_id`, `message_type`, ...), or
+/// `None` if it looks like something we can render.
+pub fn find_schema_issue(msg: &Value) -> Option<String> {
+    if let Some(missing) = REQUIRED_FIELDS
+        .iter()
+        .find(|keys| field(msg, *keys).is_none())
+    {
+        return Some(format!("missing field '{}'", missing[0]));
+    }
+    let msg_type = field(msg, &["type", "message_type"])
+        .and_then(Value::as_i64)
+        .unwrap_or(0);
+    if message_type_label(msg_type as i32) == "unknown" {
+        return Some(format!("unknown message type {msg_type}"));
+    }
+    let attachment = msg.get("attachment").and_then(Value::as_str).unwrap_or("");
+    if !attachment.is_empty() && serde_json::from_str::<Value>(attachment).is_err() {
+        return Some("unparseable attachment".to_string());
+    }
+    None
+}
+
+/// [`check_message`] for a raw LOCO chat log.
+pub fn check_chat_log(chat_id: i64, doc: &bson::Document) -> Result<()> {
+    if schema_policy() == SchemaPolicy::Lenient {
+        return Ok(());
+    }
+    check_message(
+        chat_id,
+        &bson::Bson::Document(doc.clone()).into_relaxed_extjson(),
+    )
+}
+
+/// Apply the active [`SchemaPolicy`] to one message of `chat_id`.
+pub fn check_message(chat_id: i64, msg: &Value) -> Result<()> {
+    let policy = schema_policy();
+    if policy == SchemaPolicy::Lenient {
+        return Ok(());
+    }
+    let Some(issue) = find_schema_issue(msg) else {
+        return Ok(());
+    };
+    let log_id = field(msg, &["logId", "log_id"])
+        .and_then(Value::as_i64)
+        .unwrap_or(0);
+
+    match policy {
+        SchemaPolicy::Lenient => Ok(()),
+        SchemaPolicy::Strict => {
+            let mut snippet = redact(msg).to_string();
+            if snippet.chars().count() > SNIPPET_CHARS {
+                snippet = snippet.chars().take(SNIPPET_CHARS).collect::<String>() + "...";
+            }
+            Err(OpenKakaoError::SchemaDrift {
+                chat_id,
+                log_id,
+                issue,
+                snippet,
+            }
+            .into())
+        }
+        SchemaPolicy::Collect(dir) => {
+            std::fs::create_dir_all(&dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+            let path = dir.join(format!("{chat_id}_{log_id}.json"));
+            std::fs::write(&path, serde_json::to_string_pretty(msg)?)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            eprintln!(
+                "[strict] {issue} (chat {chat_id}, logId {log_id}) → {}",
+                path.display()
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Replace every string with its length so snippets show shape, not content.
+/// Attachments are parsed first so their keys stay visible.
+fn redact(value: &Value) -> Value {
+    match value {
+        Value::String(s) => match serde_json::from_str::<Value>(s) {
+            Ok(inner @ (Value::Object(_) | Value::Array(_))) => redact(&inner),
+            _ => Value::String(format!("<{} chars>", s.chars().count())),
+        },
+        Value::Array(items) => Value::Array(items.iter().map(redact).collect()),
+        Value::Object(map) => {
+            Value::Object(map.iter().map(|(k, v)| (k.clone(), redact(v))).collect())
+        }
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn message(message_type: i64, attachment: &str) -> Value {
+        json!({
+            "log_id": 10,
+            "author_id": 20,
+            "message_type": message_type,
+            "message": "secret text",
+            "attachment": attachment,
+            "send_at": 1700000000,
+        })
+    }
+
+    #[test]
+    fn known_messages_pass() {
+        assert_eq!(find_schema_issue(&message(1, "")), None);
+        assert_eq!(find_schema_issue(&message(2, r#"{"w":1,"h":1}"#)), None);
+    }
+
+    #[test]
+    fn detects_unknown_type_bad_attachment_and_missing_fields() {
+        assert_eq!(
+            find_schema_issue(&message(999, "")).as_deref(),
+            Some("unknown message type 999")
+        );
+        assert_eq!(
+            find_schema_issue(&message(2, "{not json")).as_deref(),
+            Some("unparseable attachment")
+        );
+        let mut msg = message(1, "");
+        msg.as_object_mut().unwrap().remove("send_at");
+        assert_eq!(
+            find_schema_issue(&msg).as_deref(),
+            Some("missing field 'sendAt'")
+        );
+    }
+
+    #[test]
+    fn raw_chat_logs_use_payload_keys() {
+        let doc = bson::doc! {
+            "logId": 5_i64,
+            "authorId": 6_i64,
+            "type": 1_i32,
+            "message": "hi",
+            "sendAt": 1700000000_i64,
+        };
+        let raw = bson::Bson::Document(doc).into_relaxed_extjson();
+        assert_eq!(find_schema_issue(&raw), None);
+
+        let raw = json!({"logId": 5, "authorId": 6, "type": 4242, "sendAt": 1});
+        assert_eq!(
+            find_schema_issue(&raw).as_deref(),
+            Some("unknown message type 4242")
+        );
+    }
+
+    #[test]
+    fn redaction_hides_text_but_keeps_shape() {
+        let redacted = redact(&message(2, r#"{"url":"https://x","w":3}"#));
+        assert_eq!(redacted["message"], "<11 chars>");
+        assert_eq!(redacted["log_id"], 10);
+        assert_eq!(redacted["attachment"]["url"], "<9 chars>");
+        assert_eq!(redacted["attachment"]["w"], 3);
+    }
+
+    #[test]
+    fn schema_drift_error_names_chat_and_log_id() {
+        let err = OpenKakaoError::SchemaDrift {
+            chat_id: 1,
+            log_id: 10,
+            issue: "unknown message type 999".into(),
+            snippet: "{}".into(),
+        };
+        let text = err.to_string();
+        assert!(text.contains("chat 1"));
+        assert!(text.contains("logId 10"));
+        assert!(text.contains("unknown message type 999"));
+    }
+}
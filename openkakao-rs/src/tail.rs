@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+
+use crate::model::{ChatMessage, ChatRoom};
+
+/// Highest log id already shown per chat. Polling re-fetches the newest
+/// page, which overlaps the previous one; only messages above the mark get
+/// through, so nothing is printed twice.
+#[derive(Debug, Default)]
+pub struct Watermarks {
+    marks: HashMap<i64, i64>,
+}
+
+impl Watermarks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, chat_id: i64) -> Option<i64> {
+        self.marks.get(&chat_id).copied()
+    }
+
+    /// Raise the mark of `chat_id` to `log_id`; never lowers it.
+    pub fn raise(&mut self, chat_id: i64, log_id: i64) {
+        let mark = self.marks.entry(chat_id).or_insert(log_id);
+        *mark = (*mark).max(log_id);
+    }
+
+    /// Messages of `page` above the mark of `chat_id`, oldest first and each
+    /// log id once, raising the mark past them. A chat without a mark yet
+    /// keeps only its newest `first` messages and is marked at the newest.
+    pub fn take_new(
+        &mut self,
+        chat_id: i64,
+        mut page: Vec<ChatMessage>,
+        first: usize,
+    ) -> Vec<ChatMessage> {
+        page.sort_by_key(|m| m.log_id);
+        page.dedup_by_key(|m| m.log_id);
+        let Some(newest) = page.last().map(|m| m.log_id) else {
+            return page;
+        };
+        let fresh = match self.get(chat_id) {
+            Some(mark) => page.into_iter().filter(|m| m.log_id > mark).collect(),
+            None => {
+                let skip = page.len().saturating_sub(first);
+                page.split_off(skip)
+            }
+        };
+        self.raise(chat_id, newest);
+        fresh
+    }
+
+    /// Whether `page` may stop short of the mark, i.e. more new messages
+    /// arrived than one page holds and the next older page is needed too.
+    pub fn has_gap(&self, chat_id: i64, page: &[ChatMessage]) -> bool {
+        match (self.get(chat_id), page.iter().map(|m| m.log_id).min()) {
+            (Some(mark), Some(oldest)) => oldest > mark,
+            _ => false,
+        }
+    }
+}
+
+/// Unread counts from the previous chat list poll, to tell which rooms got
+/// new messages since.
+#[derive(Debug, Default)]
+pub struct UnreadTracker {
+    counts: Option<HashMap<i64, i64>>,
+}
+
+impl UnreadTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rooms whose unread count rose since the last call, with the increase.
+    /// The first call only records the counts. Rooms not seen before count
+    /// from zero.
+    pub fn grown(&mut self, rooms: &[ChatRoom]) -> Vec<(i64, i64)> {
+        let current: HashMap<i64, i64> = rooms
+            .iter()
+            .map(|room| (room.chat_id, room.unread_count))
+            .collect();
+        let grown = match &self.counts {
+            None => Vec::new(),
+            Some(previous) => rooms
+                .iter()
+                .filter_map(|room| {
+                    let before = previous.get(&room.chat_id).copied().unwrap_or(0);
+                    (room.unread_count > before).then(|| (room.chat_id, room.unread_count - before))
+                })
+                .collect(),
+        };
+        self.counts = Some(current);
+        grown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn page(ids: &[i64]) -> Vec<ChatMessage> {
+        ids.iter()
+            .map(|id| ChatMessage::from_json(&json!({"logId": id, "type": 1, "message": "hi"})))
+            .collect()
+    }
+
+    fn ids(messages: &[ChatMessage]) -> Vec<i64> {
+        messages.iter().map(|m| m.log_id).collect()
+    }
+
+    fn room(chat_id: i64, unread: i64) -> ChatRoom {
+        ChatRoom::from_json(&json!({"chatId": chat_id, "unreadCount": unread}))
+    }
+
+    #[test]
+    fn overlapping_pages_print_each_message_once() {
+        let mut marks = Watermarks::new();
+        assert!(marks.take_new(1, page(&[12, 11, 10]), 0).is_empty());
+        assert_eq!(marks.get(1), Some(12));
+
+        assert_eq!(
+            ids(&marks.take_new(1, page(&[14, 13, 12, 11]), 0)),
+            [13, 14]
+        );
+        assert!(marks.take_new(1, page(&[14, 13, 12]), 0).is_empty());
+        // A server answering with an older page never lowers the mark.
+        assert!(marks.take_new(1, page(&[9, 8]), 0).is_empty());
+        assert_eq!(marks.get(1), Some(14));
+        assert_eq!(ids(&marks.take_new(1, page(&[15, 15, 14]), 0)), [15]);
+    }
+
+    #[test]
+    fn unmarked_chats_keep_only_the_newest_messages() {
+        let mut marks = Watermarks::new();
+        assert_eq!(ids(&marks.take_new(2, page(&[5, 7, 6]), 2)), [6, 7]);
+        assert_eq!(marks.get(2), Some(7));
+        assert!(marks.take_new(3, Vec::new(), 2).is_empty());
+        assert_eq!(marks.get(3), None);
+    }
+
+    #[test]
+    fn gaps_are_detected_when_a_page_does_not_reach_the_mark() {
+        let mut marks = Watermarks::new();
+        marks.raise(1, 10);
+        assert!(marks.has_gap(1, &page(&[30, 20])));
+        assert!(!marks.has_gap(1, &page(&[30, 10])));
+        assert!(!marks.has_gap(2, &page(&[30])));
+    }
+
+    #[test]
+    fn unread_growth_is_reported_from_the_second_poll() {
+        let mut tracker = UnreadTracker::new();
+        assert!(tracker.grown(&[room(1, 3), room(2, 0)]).is_empty());
+        assert_eq!(
+            tracker.grown(&[room(1, 5), room(2, 0), room(3, 1)]),
+            [(1, 2), (3, 1)]
+        );
+        // Reading a room elsewhere lowers its count; that is not growth.
+        assert!(tracker
+            .grown(&[room(1, 0), room(2, 0), room(3, 1)])
+            .is_empty());
+        assert_eq!(tracker.grown(&[room(1, 1)]), [(1, 1)]);
+    }
+}
@@ -0,0 +1,582 @@
+//! Read-only export of everything the REST API will hand over, behind
+//! `takeout <dir>`.
+//!
+//! The directory gets one JSON file per section and per chat, and an
+//! `index.json` manifest:
+//!
+//! ```text
+//! <dir>/
+//! ├── index.json                  manifest: sections, counts, timestamps, notes
+//! ├── profile.json                own profile (profile3/me and settings fields)
+//! ├── settings.json               raw more_settings answer
+//! ├── friends.json                friend list, hidden friends included
+//! ├── chats.json                  the whole chat list
+//! └── chats/<chat_id>/
+//!     ├── members.json            member list
+//!     └── messages.json           history the server still has, oldest first
+//! ```
+//!
+//! The manifest is rewritten after every section, so an interrupted run (or
+//! one stopped by the byte cap) picks up where it left off: sections marked
+//! `done` are not fetched again, failed ones are. The chats a run covers are
+//! fixed when the chat list is saved. Nothing here writes to the account.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::OpenKakaoError;
+use crate::model::{ChatMessage, ChatRoom};
+use crate::rest::KakaoRestClient;
+
+/// Name of the manifest in the takeout directory.
+pub const MANIFEST_FILE: &str = "index.json";
+
+/// Layout version in `index.json`; bumped when files move or change shape.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Message pages fetched per chat unless [`TakeoutOptions::max_pages`] says
+/// otherwise.
+pub const DEFAULT_MAX_PAGES: usize = 50;
+
+/// Top-level sections, in the order they are fetched.
+pub const SECTIONS: &[&str] = &["profile", "settings", "friends", "chats"];
+
+/// How a section ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SectionState {
+    /// Saved; a resumed run skips it.
+    Done,
+    /// The request failed; a resumed run tries again.
+    Failed,
+    /// The server will not give it to this token (HTTP 401/403 from pilsner);
+    /// a resumed run tries again.
+    Unavailable,
+}
+
+/// One section in the manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Section {
+    pub state: SectionState,
+    /// The file, relative to the takeout directory, once saved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// Entries in the file (friends, chats, members, messages).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<usize>,
+    /// When it was saved or last attempted (RFC 3339).
+    pub at: String,
+    /// A limitation of what was saved, or why nothing was.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl Section {
+    fn done(file: String, count: usize, note: Option<String>) -> Self {
+        Self {
+            state: SectionState::Done,
+            file: Some(file),
+            count: Some(count),
+            at: now(),
+            note,
+        }
+    }
+
+    fn missing(state: SectionState, reason: String) -> Self {
+        Self {
+            state,
+            file: None,
+            count: None,
+            at: now(),
+            note: Some(reason),
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.state == SectionState::Done
+    }
+}
+
+/// A chat the takeout covers.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatEntry {
+    pub title: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub members: Option<Section>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub messages: Option<Section>,
+}
+
+/// `index.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format: u32,
+    pub user_id: i64,
+    pub started_at: String,
+    pub updated_at: String,
+    /// Set once every section is done.
+    #[serde(default)]
+    pub finished_at: Option<String>,
+    /// Why the last run stopped before trying everything (byte cap, token
+    /// rejected); cleared when a run gets through.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stopped: Option<String>,
+    /// `profile`, `settings`, `friends` and `chats`.
+    #[serde(default)]
+    pub sections: BTreeMap<String, Section>,
+    /// Chats the takeout covers, by chat id.
+    #[serde(default)]
+    pub chats: BTreeMap<i64, ChatEntry>,
+    /// Chats left out, with the rule that left them out.
+    #[serde(default)]
+    pub skipped_chats: BTreeMap<i64, String>,
+}
+
+impl Manifest {
+    fn new(user_id: i64) -> Self {
+        let at = now();
+        Self {
+            format: FORMAT_VERSION,
+            user_id,
+            started_at: at.clone(),
+            updated_at: at,
+            finished_at: None,
+            stopped: None,
+            sections: BTreeMap::new(),
+            chats: BTreeMap::new(),
+            skipped_chats: BTreeMap::new(),
+        }
+    }
+
+    /// The manifest in `dir`, if there is one.
+    pub fn load(dir: &Path) -> Result<Option<Self>, OpenKakaoError> {
+        let path = dir.join(MANIFEST_FILE);
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        serde_json::from_slice(&data)
+            .map(Some)
+            .map_err(|err| OpenKakaoError::Parse {
+                message: format!("{} is not a takeout manifest: {err}", path.display()),
+            })
+    }
+
+    /// Every section with its manifest key (`friends`,
+    /// `chats/123/messages`), in fetch order.
+    pub fn entries(&self) -> Vec<(String, &Section)> {
+        let mut out: Vec<(String, &Section)> = SECTIONS
+            .iter()
+            .filter_map(|name| Some((name.to_string(), self.sections.get(*name)?)))
+            .collect();
+        for (chat_id, chat) in &self.chats {
+            for (part, section) in [("members", &chat.members), ("messages", &chat.messages)] {
+                if let Some(section) = section {
+                    out.push((format!("chats/{chat_id}/{part}"), section));
+                }
+            }
+        }
+        out
+    }
+
+    /// Sections not tried yet: missing top-level ones (with the chat list
+    /// missing, its chats are unknown) and chat parts without an entry.
+    pub fn pending(&self) -> usize {
+        let top = SECTIONS
+            .iter()
+            .filter(|name| !self.sections.contains_key(**name))
+            .count();
+        let chats: usize = self
+            .chats
+            .values()
+            .map(|chat| usize::from(chat.members.is_none()) + usize::from(chat.messages.is_none()))
+            .sum();
+        top + chats
+    }
+
+    /// Whether every section is done.
+    pub fn is_complete(&self) -> bool {
+        self.pending() == 0 && self.entries().iter().all(|(_, section)| section.is_done())
+    }
+}
+
+/// Knobs of [`run`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TakeoutOptions {
+    /// Message pages fetched per chat before giving up on older history.
+    pub max_pages: usize,
+}
+
+impl Default for TakeoutOptions {
+    fn default() -> Self {
+        Self {
+            max_pages: DEFAULT_MAX_PAGES,
+        }
+    }
+}
+
+/// What to do after a section failed.
+enum Outcome {
+    /// Record it and go on.
+    Record(Section),
+    /// Record it and stop the run: the byte cap is used up, or the token
+    /// was rejected and every later request would be too.
+    Stop(Section, String),
+}
+
+fn classify(err: &OpenKakaoError, pilsner: bool) -> Outcome {
+    let reason = err.to_string();
+    match err {
+        OpenKakaoError::ByteCapReached { .. } => Outcome::Stop(
+            Section::missing(SectionState::Failed, reason.clone()),
+            reason,
+        ),
+        // Pilsner turns down tokens that lack its scope; katalk would not.
+        OpenKakaoError::Unauthorized { .. } | OpenKakaoError::Forbidden { .. } if pilsner => {
+            Outcome::Record(Section::missing(SectionState::Unavailable, reason))
+        }
+        err if err.is_auth_failure()
+            || matches!(
+                err,
+                OpenKakaoError::Unauthorized { .. } | OpenKakaoError::Forbidden { .. }
+            ) =>
+        {
+            Outcome::Stop(
+                Section::missing(SectionState::Failed, reason.clone()),
+                reason,
+            )
+        }
+        _ => Outcome::Record(Section::missing(SectionState::Failed, reason)),
+    }
+}
+
+/// Export into `dir`, resuming from its `index.json` if it has one.
+/// `skip` says why a chat is left out (`None` keeps it); it is asked once,
+/// when the chat list is saved. `report` hears about each section as it
+/// ends, with its manifest key. Returns the manifest as saved; a stop (byte
+/// cap, rejected token) is in [`Manifest::stopped`], not an error.
+///
+/// Fails without writing anything when `dir` holds other files but no
+/// manifest, or the manifest of another account.
+pub fn run(
+    client: &KakaoRestClient,
+    dir: &Path,
+    options: TakeoutOptions,
+    skip: &dyn Fn(&ChatRoom) -> Option<String>,
+    report: &dyn Fn(&str, &Section),
+) -> Result<Manifest, OpenKakaoError> {
+    let manifest = match Manifest::load(dir)? {
+        Some(manifest) => {
+            if manifest.format != FORMAT_VERSION {
+                return Err(anyhow::anyhow!(
+                    "{} is takeout format {}, this version writes {FORMAT_VERSION}; use a new directory",
+                    dir.join(MANIFEST_FILE).display(),
+                    manifest.format
+                )
+                .into());
+            }
+            if manifest.user_id != client.user_id() && client.user_id() != 0 {
+                return Err(anyhow::anyhow!(
+                    "{} belongs to user {}, not {}; use a new directory",
+                    dir.display(),
+                    manifest.user_id,
+                    client.user_id()
+                )
+                .into());
+            }
+            manifest
+        }
+        None => {
+            let occupied = fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_some());
+            if occupied {
+                return Err(anyhow::anyhow!(
+                    "{} is not empty and has no {MANIFEST_FILE}; pick an empty or new directory",
+                    dir.display()
+                )
+                .into());
+            }
+            Manifest::new(client.user_id())
+        }
+    };
+    fs::create_dir_all(dir)?;
+    let mut takeout = Takeout {
+        client,
+        dir,
+        options,
+        report,
+        manifest,
+    };
+    takeout.manifest.stopped = None;
+    let stop = takeout.fetch_all(skip)?;
+    takeout.manifest.stopped = stop;
+    if takeout.manifest.is_complete() {
+        takeout.manifest.finished_at = Some(now());
+    }
+    takeout.save()?;
+    Ok(takeout.manifest)
+}
+
+struct Takeout<'a> {
+    client: &'a KakaoRestClient,
+    dir: &'a Path,
+    options: TakeoutOptions,
+    report: &'a dyn Fn(&str, &Section),
+    manifest: Manifest,
+}
+
+/// A section as fetched, before it is written.
+struct Fetched {
+    value: serde_json::Value,
+    count: usize,
+    note: Option<String>,
+}
+
+impl Fetched {
+    fn one(item: &impl Serialize) -> Result<Self, OpenKakaoError> {
+        Ok(Self {
+            value: to_json(item)?,
+            count: 1,
+            note: None,
+        })
+    }
+
+    fn list<T: Serialize>(items: &[T]) -> Result<Self, OpenKakaoError> {
+        Ok(Self {
+            value: to_json(items)?,
+            count: items.len(),
+            note: None,
+        })
+    }
+}
+
+impl Takeout<'_> {
+    /// Every section not done yet. Returns why it stopped early, if it did.
+    fn fetch_all(
+        &mut self,
+        skip: &dyn Fn(&ChatRoom) -> Option<String>,
+    ) -> Result<Option<String>, OpenKakaoError> {
+        let client = self.client;
+        for name in ["profile", "settings", "friends"] {
+            if self.is_done(name) {
+                continue;
+            }
+            let fetched = match name {
+                "profile" => client.get_my_profile().and_then(|p| Fetched::one(&p)),
+                "settings" => client.get_settings().and_then(|v| Fetched::one(&v)),
+                _ => client.get_friends().and_then(|f| Fetched::list(&f)),
+            };
+            if let Some(stop) = self.settle(name, fetched, false)? {
+                return Ok(Some(stop));
+            }
+        }
+
+        if !self.is_done("chats") {
+            let fetched = client.get_all_chats().and_then(|rooms| {
+                let fetched = Fetched::list(&rooms)?;
+                self.select_chats(&rooms, skip);
+                Ok(fetched)
+            });
+            if let Some(stop) = self.settle("chats", fetched, true)? {
+                return Ok(Some(stop));
+            }
+        }
+
+        let chat_ids: Vec<i64> = self.manifest.chats.keys().copied().collect();
+        for chat_id in chat_ids {
+            let chat = &self.manifest.chats[&chat_id];
+            let (members_done, messages_done) = (
+                chat.members.as_ref().is_some_and(Section::is_done),
+                chat.messages.as_ref().is_some_and(Section::is_done),
+            );
+            if !members_done {
+                let fetched = client
+                    .get_chat_members(chat_id)
+                    .and_then(|members| Fetched::list(&members));
+                let key = format!("chats/{chat_id}/members");
+                if let Some(stop) = self.settle(&key, fetched, true)? {
+                    return Ok(Some(stop));
+                }
+            }
+            if !messages_done {
+                let fetched = self.history(chat_id).and_then(|(messages, note)| {
+                    Ok(Fetched {
+                        note: Some(note),
+                        ..Fetched::list(&messages)?
+                    })
+                });
+                let key = format!("chats/{chat_id}/messages");
+                if let Some(stop) = self.settle(&key, fetched, true)? {
+                    return Ok(Some(stop));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// The messages the server still has for `chat_id`, oldest first, and
+    /// a note on how far back they go.
+    fn history(&self, chat_id: i64) -> Result<(Vec<ChatMessage>, String), OpenKakaoError> {
+        let mut all: Vec<ChatMessage> = Vec::new();
+        let mut cursor = None;
+        let mut reached_start = false;
+        for _ in 0..self.options.max_pages {
+            let (messages, next) = self.client.get_messages(chat_id, cursor)?;
+            let last_page = messages.is_empty() || next.is_none() || cursor == next;
+            all.extend(messages);
+            if last_page {
+                reached_start = true;
+                break;
+            }
+            cursor = next;
+        }
+        all.sort_by_key(|m| (m.send_at, m.log_id));
+        all.dedup_by_key(|m| m.log_id);
+        let note = match all.first() {
+            None => "The server keeps no messages for this chat; only chats recently opened \
+                     in the KakaoTalk Mac app have history over REST"
+                .to_string(),
+            Some(oldest) if reached_start => format!(
+                "History starts at {} (logId {}); the server keeps nothing older",
+                format_time(oldest.send_at),
+                oldest.log_id
+            ),
+            Some(oldest) => format!(
+                "Stopped after {} pages at {} (logId {}); older messages were not fetched",
+                self.options.max_pages,
+                format_time(oldest.send_at),
+                oldest.log_id
+            ),
+        };
+        Ok((all, note))
+    }
+
+    /// Chat entries for the chats `skip` keeps, and the reasons for the rest.
+    fn select_chats(&mut self, rooms: &[ChatRoom], skip: &dyn Fn(&ChatRoom) -> Option<String>) {
+        for room in rooms {
+            match skip(room) {
+                Some(reason) => {
+                    self.manifest.skipped_chats.insert(room.chat_id, reason);
+                }
+                None => {
+                    self.manifest
+                        .chats
+                        .entry(room.chat_id)
+                        .or_insert_with(|| ChatEntry {
+                            title: room.title.clone(),
+                            kind: room.kind.clone(),
+                            ..ChatEntry::default()
+                        });
+                }
+            }
+        }
+    }
+
+    fn is_done(&self, name: &str) -> bool {
+        self.manifest
+            .sections
+            .get(name)
+            .is_some_and(Section::is_done)
+    }
+
+    /// Write what was fetched for `key` to `<key>.json`, record the section
+    /// in the manifest, save it and tell `report`. Returns why the run has
+    /// to stop, if it does; failing to write is an error of its own.
+    fn settle(
+        &mut self,
+        key: &str,
+        fetched: Result<Fetched, OpenKakaoError>,
+        pilsner: bool,
+    ) -> Result<Option<String>, OpenKakaoError> {
+        let (section, stop) = match fetched {
+            Ok(fetched) => {
+                let file = format!("{key}.json");
+                self.write(&file, &fetched.value)?;
+                (Section::done(file, fetched.count, fetched.note), None)
+            }
+            Err(err) => match classify(&err, pilsner) {
+                Outcome::Record(section) => (section, None),
+                Outcome::Stop(section, reason) => (section, Some(reason)),
+            },
+        };
+        (self.report)(key, &section);
+        match key.split('/').collect::<Vec<_>>()[..] {
+            ["chats", chat_id, part] => {
+                let chat = chat_id
+                    .parse()
+                    .ok()
+                    .and_then(|chat_id: i64| self.manifest.chats.get_mut(&chat_id));
+                if let Some(chat) = chat {
+                    match part {
+                        "members" => chat.members = Some(section),
+                        _ => chat.messages = Some(section),
+                    }
+                }
+            }
+            _ => {
+                self.manifest.sections.insert(key.to_string(), section);
+            }
+        }
+        self.save()?;
+        Ok(stop)
+    }
+
+    fn save(&mut self) -> Result<(), OpenKakaoError> {
+        self.manifest.updated_at = now();
+        let value = to_json(&self.manifest)?;
+        self.write(MANIFEST_FILE, &value)
+    }
+
+    /// Write `value` to `file` under the takeout directory, replacing it in
+    /// one rename so an interrupted run never leaves half a file.
+    fn write(&self, file: &str, value: &serde_json::Value) -> Result<(), OpenKakaoError> {
+        let path = self.dir.join(file);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|err| local_error(&path, err))?;
+        }
+        let temp = temp_path(&path);
+        let text = serde_json::to_vec_pretty(value).map_err(|err| OpenKakaoError::Parse {
+            message: err.to_string(),
+        })?;
+        fs::write(&temp, text).map_err(|err| local_error(&path, err))?;
+        fs::rename(&temp, &path).map_err(|err| local_error(&path, err))?;
+        Ok(())
+    }
+}
+
+fn to_json(value: &(impl Serialize + ?Sized)) -> Result<serde_json::Value, OpenKakaoError> {
+    serde_json::to_value(value).map_err(|err| OpenKakaoError::Parse {
+        message: err.to_string(),
+    })
+}
+
+/// `path` with `.tmp` added to its name.
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// Failing to write the takeout is not the server's doing: it ends the
+/// run instead of being recorded against a section.
+fn local_error(path: &Path, err: io::Error) -> OpenKakaoError {
+    OpenKakaoError::Other(
+        anyhow::Error::new(err).context(format!("Failed to write {}", path.display())),
+    )
+}
+
+fn now() -> String {
+    chrono::Utc::now().to_rfc3339()
+}
+
+fn format_time(unix: i64) -> String {
+    chrono::DateTime::from_timestamp(unix, 0)
+        .map(|at| at.to_rfc3339())
+        .unwrap_or_else(|| unix.to_string())
+}
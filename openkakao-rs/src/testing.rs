@@ -0,0 +1,292 @@
+//! A fake Kakao server to run a [`KakaoRestClient`] against without the
+//! network, for examples and for tests of code built on the library.
+//!
+//! [`client`] answers every REST endpoint from a small, consistent set of
+//! fixtures ([`server`]): you ([`USER_ID`]), one friend ([`FRIEND_ID`]),
+//! a group chat ([`CHAT_ID`]) with three messages over two pages, and an
+//! open chat ([`OPEN_CHAT_ID`]) with a board. Status checks, pagination
+//! and parsing run as they do against the real servers. For other answers,
+//! build a [`Canned`] server from the fixture helpers ([`chat`],
+//! [`message`], ...) and pass it to [`client_with`]. Binary downloads
+//! ([`KakaoRestClient::download`], [`KakaoRestClient::get_bytes`]) and
+//! `login.json` still need the network.
+//!
+//! The documentation examples of [`KakaoRestClient`] run against it.
+//!
+//! Built with the `testing` feature.
+//!
+//! ```
+//! use openkakao_rs::testing::{self, Canned};
+//! use serde_json::json;
+//!
+//! # fn main() -> Result<(), openkakao_rs::error::OpenKakaoError> {
+//! // The fixtures as they are.
+//! let client = testing::client();
+//! assert_eq!(client.get_all_chats()?.len(), 2);
+//!
+//! // Or a server of your own.
+//! let server = Canned::new().route("/messaging/chats", |_| {
+//!     json!({"chats": [testing::chat(7, "스터디")], "last": true})
+//! });
+//! let chats = testing::client_with(server).get_all_chats()?;
+//! assert_eq!(chats[0].title, "스터디");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use crate::credential_provider::CredentialProvider;
+use crate::model::KakaoCredentials;
+use crate::rate_limit::Pacing;
+use crate::rest::KakaoRestClient;
+use crate::retry::RetryPolicy;
+
+pub use crate::fault::{cursor_of, Canned, Request, Transport};
+
+/// The account [`credentials`] belong to.
+pub const USER_ID: i64 = 42;
+/// [`USER_ID`]'s Kakao ID, found by `find_by_kakao_id`.
+pub const KAKAO_ID: &str = "minsu";
+/// The one friend.
+pub const FRIEND_ID: i64 = 100;
+/// A group chat of [`USER_ID`] and [`FRIEND_ID`] with messages 1 to 3.
+pub const CHAT_ID: i64 = 9001;
+/// An open chat with a board post ([`POST_ID`]).
+pub const OPEN_CHAT_ID: i64 = 9002;
+/// The link [`OPEN_CHAT_ID`] is joined by.
+pub const LINK_ID: i64 = 4567;
+/// The board post in [`OPEN_CHAT_ID`], with one comment.
+pub const POST_ID: &str = "post-1";
+/// The OAuth token of [`credentials`].
+pub const TOKEN: &str = "test-token";
+
+/// Credentials for [`USER_ID`]. Only a server from this module accepts them.
+pub fn credentials() -> KakaoCredentials {
+    KakaoCredentials::new(
+        TOKEN.into(),
+        USER_ID,
+        "test-device-uuid".into(),
+        "26.1.0".into(),
+        "KT/26.1.0 Mc/26.1.0 ko".into(),
+        "mac/26.1.0/ko".into(),
+    )
+}
+
+/// A client for [`credentials`] answered by [`server`].
+pub fn client() -> KakaoRestClient {
+    client_with(server())
+}
+
+/// A client for [`credentials`] answered by `transport`. Failures are
+/// reported at once instead of retried, and requests are not spaced out.
+pub fn client_with(transport: impl Transport + 'static) -> KakaoRestClient {
+    KakaoRestClient::new(credentials())
+        .expect("a client without network settings always builds")
+        .with_retry_policy(RetryPolicy::default().with_retries(0))
+        .with_pacing(Arc::new(Pacing::new()))
+        .with_transport(transport)
+}
+
+/// Every REST endpoint of [`KakaoRestClient`], answered from the fixtures
+/// described in the [module documentation](self).
+pub fn server() -> Canned {
+    let ok = |_: &Request<'_>| json!({"status": 0});
+    Canned::new()
+        // Pilsner: chats, members and messages.
+        .route(
+            &format!("/chats/{CHAT_ID}/messages"),
+            |request| match cursor_of(request.url) {
+                None => json!({
+                    "chatLogs": [
+                        message(3, FRIEND_ID, "저녁 먹었어?"),
+                        message(2, USER_ID, "지금 가는 중"),
+                    ],
+                    "nextCursor": 2,
+                }),
+                Some(_) => json!({
+                    "chatLogs": [message(1, FRIEND_ID, "어디야?")],
+                    "nextCursor": 0,
+                }),
+            },
+        )
+        .route("/members", |_| {
+            json!({
+                "members": [member(USER_ID, "민수"), member(FRIEND_ID, "지영")],
+                "totalCount": 2,
+                "last": true,
+            })
+        })
+        .route("/messaging/chats", |request| match cursor_of(request.url) {
+            None => json!({"chats": [chat(CHAT_ID, "가족")], "nextCursor": 1, "last": false}),
+            Some(_) => json!({"chats": [chat(OPEN_CHAT_ID, "Rust KR")], "last": true}),
+        })
+        // Moim: the open chat board.
+        .route("/comments", |_| {
+            json!({
+                "comments": [{
+                    "id": "comment-1",
+                    "owner_id": FRIEND_ID,
+                    "created_at": 1_700_000_600,
+                    "content": "참석합니다",
+                }],
+                "last": true,
+            })
+        })
+        .route(
+            "talkmoim-api.kakao.com/posts/",
+            |_| json!({"post": board_post()}),
+        )
+        .route(
+            "talkmoim-api.kakao.com/chats/",
+            |_| json!({"posts": [board_post()], "last": true}),
+        )
+        // Katalk: account and profile.
+        .route("/profile3/me.json", |_| {
+            json!({
+                "status": 0,
+                "profile": {"userId": USER_ID, "nickname": "민수", "statusMessage": "출근 중"},
+            })
+        })
+        .route(
+            "/more_settings.json",
+            |_| json!({"status": 0, "accountId": 1234, "emailAddress": "minsu@example.com"}),
+        )
+        .route("/profile3/friend.json", |_| {
+            json!({
+                "status": 0,
+                "profile": {"userId": FRIEND_ID, "nickname": "지영", "statusMessage": "여행 중"},
+            })
+        })
+        .route("/profile3/edit.json", ok)
+        .route(
+            "/profile/list.json",
+            |_| json!({"status": 0, "profiles": []}),
+        )
+        .route(
+            "/friends/update.json",
+            |_| json!({"status": 0, "friends": [friend(FRIEND_ID, "지영")]}),
+        )
+        .route("/friends/find_by_uuid.json", |request| {
+            if request.body == Some(&format!("uuid={KAKAO_ID}")) {
+                json!({
+                    "status": 0,
+                    "member": {"userId": USER_ID, "nickName": "민수", "uuid": KAKAO_ID},
+                })
+            } else {
+                json!({"status": -1001})
+            }
+        })
+        .route("/friends/", ok)
+        .route(
+            "/alarm_keywords/list.json",
+            |_| json!({"status": 0, "keywords": ["점심", "회의"]}),
+        )
+        .route("_token.json", |_| {
+            json!({
+                "status": 0,
+                "access_token": "renewed-token",
+                "refresh_token": "renewed-refresh-token",
+                "expires_in": 43_199,
+            })
+        })
+        .route("/scrap/preview.json", |_| {
+            json!({
+                "status": 0,
+                "title": "Example Domain",
+                "url": "https://example.com/",
+            })
+        })
+        // Katalk: open chats.
+        .route("/openlink/info.json", |_| {
+            json!({
+                "status": 0,
+                "openLink": {
+                    "linkId": LINK_ID,
+                    "linkName": "Rust KR",
+                    "activeMembersCount": 12,
+                    "maxUser": 300,
+                    "passcodeRequired": false,
+                },
+            })
+        })
+        .route(
+            "/openlink/join.json",
+            |_| json!({"status": 0, "chatId": OPEN_CHAT_ID}),
+        )
+        .route("/openlink/leave.json", ok)
+}
+
+/// A chat list entry of the pilsner `messaging/chats` answer.
+pub fn chat(chat_id: i64, title: &str) -> Value {
+    json!({"chatId": chat_id, "type": "MultiChat", "title": title, "unreadCount": 0})
+}
+
+/// A text message (type 1) of the pilsner `messages` answer, sent
+/// `log_id` minutes after 2023-11-14 22:13:20 UTC.
+pub fn message(log_id: i64, author_id: i64, text: &str) -> Value {
+    json!({
+        "logId": log_id,
+        "authorId": author_id,
+        "type": 1,
+        "message": text,
+        "sendAt": 1_700_000_000 + 60 * log_id,
+    })
+}
+
+/// A member of the pilsner `members` answer.
+pub fn member(user_id: i64, nickname: &str) -> Value {
+    json!({"userId": user_id, "nickName": nickname})
+}
+
+/// A friend of the katalk `friends/update.json` answer.
+pub fn friend(user_id: i64, nickname: &str) -> Value {
+    json!({
+        "userId": user_id,
+        "nickName": nickname,
+        "directChatId": CHAT_ID,
+        "favorite": false,
+        "hidden": false,
+    })
+}
+
+fn board_post() -> Value {
+    json!({
+        "id": POST_ID,
+        "object_type": "TEXT",
+        "owner_id": USER_ID,
+        "created_at": 1_700_000_000,
+        "title": "정기 모임",
+        "content": "토요일 2시",
+        "comment_count": 1,
+    })
+}
+
+/// A [`CredentialProvider`] handing out fixed credentials, best first.
+#[derive(Debug, Clone)]
+pub struct StaticProvider {
+    name: String,
+    credentials: Vec<KakaoCredentials>,
+}
+
+impl StaticProvider {
+    pub fn new(name: &str, credentials: Vec<KakaoCredentials>) -> Self {
+        Self {
+            name: name.into(),
+            credentials,
+        }
+    }
+}
+
+impl CredentialProvider for StaticProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn provide(&self) -> Result<Vec<KakaoCredentials>> {
+        Ok(self.credentials.clone())
+    }
+}
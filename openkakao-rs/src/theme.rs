@@ -0,0 +1,174 @@
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, Result};
+use owo_colors::Style;
+
+/// Styles for every colored element of human output. Themes are plain data
+/// so another palette (or one read from config) is just another value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub name: &'static str,
+    /// Section titles (`print_section_title`).
+    pub title: Style,
+    /// Table header row.
+    pub header: Style,
+    /// Table rule under the header.
+    pub separator: Style,
+    /// Message timestamps in `read` and `watch`.
+    pub timestamp: Style,
+    /// Message authors and other emphasized names.
+    pub author: Style,
+    /// Secondary details: ids, links, unread counts, `[sync]`-style tags.
+    pub muted: Style,
+    /// Chat labels in `watch`.
+    pub chat: Style,
+    /// Matched text in `search` results.
+    pub highlight: Style,
+    pub ok: Style,
+    pub warn: Style,
+    pub fail: Style,
+}
+
+/// Tuned for dark backgrounds; relies on faint text for secondary details.
+pub const DARK: Theme = Theme {
+    name: "dark",
+    title: Style::new().bold().cyan(),
+    header: Style::new().bold(),
+    separator: Style::new().dimmed(),
+    timestamp: Style::new().dimmed(),
+    author: Style::new().bold(),
+    muted: Style::new().dimmed(),
+    chat: Style::new().cyan(),
+    highlight: Style::new().bold().yellow(),
+    ok: Style::new().green(),
+    warn: Style::new().yellow(),
+    fail: Style::new().red(),
+};
+
+/// For light backgrounds: no faint text and no cyan or yellow, which wash
+/// out on white.
+pub const LIGHT: Theme = Theme {
+    name: "light",
+    title: Style::new().bold().blue(),
+    header: Style::new().bold(),
+    separator: Style::new(),
+    timestamp: Style::new().blue(),
+    author: Style::new().bold(),
+    muted: Style::new().magenta(),
+    chat: Style::new().blue(),
+    highlight: Style::new().bold().underline(),
+    ok: Style::new().green(),
+    warn: Style::new().magenta(),
+    fail: Style::new().red(),
+};
+
+/// `--theme` / `[ui] theme` value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThemeChoice {
+    Dark,
+    Light,
+    Auto,
+}
+
+impl ThemeChoice {
+    pub fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "dark" => Ok(Self::Dark),
+            "light" => Ok(Self::Light),
+            "auto" => Ok(Self::Auto),
+            _ => Err(anyhow!("Unknown theme '{}'. Use: dark, light, auto", s)),
+        }
+    }
+
+    /// Resolve `auto` from `COLORFGBG` (set by rxvt, Konsole and others);
+    /// anything undetectable falls back to dark.
+    pub fn resolve(self, colorfgbg: Option<&str>) -> Theme {
+        match self {
+            Self::Dark => DARK,
+            Self::Light => LIGHT,
+            Self::Auto => match colorfgbg.and_then(background_is_light) {
+                Some(true) => LIGHT,
+                _ => DARK,
+            },
+        }
+    }
+}
+
+/// Parse `COLORFGBG` ("fg;bg" or "fg;default;bg"): background 7 or 9-15 is
+/// light, 0-6 and 8 are dark.
+fn background_is_light(value: &str) -> Option<bool> {
+    let bg: u8 = value.rsplit(';').next()?.trim().parse().ok()?;
+    match bg {
+        7 | 9..=15 => Some(true),
+        0..=6 | 8 => Some(false),
+        _ => None,
+    }
+}
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// Set once from `--theme` / config at startup.
+pub fn set_theme(theme: Theme) {
+    let _ = THEME.set(theme);
+}
+
+/// The active theme; dark until [`set_theme`] says otherwise.
+pub fn theme() -> &'static Theme {
+    THEME.get().unwrap_or(&DARK)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use owo_colors::OwoColorize;
+
+    /// A `read` line, a section title and a table rule, as commands render them.
+    fn render(theme: &Theme) -> String {
+        format!(
+            "{} {}: hi{}\n{}\n{}",
+            "12:30".style(theme.timestamp),
+            "Alice".style(theme.author),
+            " · 2".style(theme.muted),
+            "Friends".style(theme.title),
+            "-----".style(theme.separator),
+        )
+    }
+
+    #[test]
+    fn dark_theme_snapshot() {
+        assert_eq!(
+            render(&DARK),
+            "\u{1b}[2m12:30\u{1b}[0m \u{1b}[1mAlice\u{1b}[0m: hi\u{1b}[2m · 2\u{1b}[0m\n\
+             \u{1b}[36;1mFriends\u{1b}[0m\n\
+             \u{1b}[2m-----\u{1b}[0m"
+        );
+    }
+
+    #[test]
+    fn light_theme_snapshot() {
+        assert_eq!(
+            render(&LIGHT),
+            "\u{1b}[34m12:30\u{1b}[0m \u{1b}[1mAlice\u{1b}[0m: hi\u{1b}[35m · 2\u{1b}[0m\n\
+             \u{1b}[34;1mFriends\u{1b}[0m\n\
+             -----"
+        );
+    }
+
+    #[test]
+    fn light_theme_avoids_faint_text() {
+        for style in [LIGHT.separator, LIGHT.timestamp, LIGHT.muted] {
+            assert!(!format!("{}", "x".style(style)).contains("[2m"));
+        }
+    }
+
+    #[test]
+    fn auto_reads_colorfgbg_and_defaults_to_dark() {
+        assert_eq!(ThemeChoice::Auto.resolve(Some("0;15")).name, "light");
+        assert_eq!(ThemeChoice::Auto.resolve(Some("0;default;7")).name, "light");
+        assert_eq!(ThemeChoice::Auto.resolve(Some("15;0")).name, "dark");
+        assert_eq!(ThemeChoice::Auto.resolve(Some("garbage")).name, "dark");
+        assert_eq!(ThemeChoice::Auto.resolve(None).name, "dark");
+        assert_eq!(ThemeChoice::Light.resolve(Some("15;0")).name, "light");
+        assert!(ThemeChoice::from_str("solarized").is_err());
+    }
+}
@@ -0,0 +1,243 @@
+//! Conversation threads from reply chains, behind `read --threads` and
+//! `export --threads`.
+//!
+//! A reply names the log id it answers ([`MessageContent::Reply`]), so a
+//! window of messages falls into threads: a root message followed by its
+//! replies, their replies, and so on, each level in chronological order.
+//! A reply whose source is not in the window cannot be placed under it and
+//! starts a thread of its own as an *orphan*. Chains that loop (which the
+//! server should never produce, but cached or edited data might) are cut at
+//! their earliest message, which is treated like an orphan.
+//!
+//! [`MessageContent::Reply`]: crate::model::MessageContent::Reply
+
+use std::collections::HashMap;
+
+use crate::model::ChatMessage;
+
+/// Indentation stops growing past this depth, so long chains stay readable.
+pub const MAX_INDENT_DEPTH: usize = 8;
+
+/// What threading needs to know about one message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThreadKey {
+    pub log_id: i64,
+    pub send_at: i64,
+    /// The log id this message replies to, `None` when it is not a reply.
+    pub reply_to: Option<i64>,
+}
+
+impl From<&ChatMessage> for ThreadKey {
+    fn from(msg: &ChatMessage) -> Self {
+        Self {
+            log_id: msg.log_id,
+            send_at: msg.send_at,
+            reply_to: msg.content.reply_to(),
+        }
+    }
+}
+
+/// One message in display order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThreadEntry {
+    /// Position of the message in the input.
+    pub index: usize,
+    /// 0 for the message that starts a thread.
+    pub depth: usize,
+    /// Position in the input of the thread's first message.
+    pub root: usize,
+    /// A reply shown at the top level because its source is not in the
+    /// window (or its chain loops).
+    pub orphan: bool,
+}
+
+impl ThreadEntry {
+    /// The entry for `index` when messages are shown without threading.
+    pub fn flat(index: usize) -> Self {
+        Self {
+            index,
+            depth: 0,
+            root: index,
+            orphan: false,
+        }
+    }
+
+    /// Two spaces per level, up to [`MAX_INDENT_DEPTH`] levels.
+    pub fn indent(&self) -> String {
+        "  ".repeat(self.depth.min(MAX_INDENT_DEPTH))
+    }
+}
+
+/// Every message once, grouped into threads: threads ordered by their first
+/// message, and each message followed by its replies (depth first), oldest
+/// first at every level. Ties in `send_at` are broken by log id.
+///
+/// ```
+/// use openkakao_rs::threads::{thread_order, ThreadKey};
+///
+/// let key = |log_id, reply_to| ThreadKey { log_id, send_at: log_id, reply_to };
+/// let keys = [key(1, None), key(2, None), key(3, Some(1)), key(4, Some(9))];
+/// let order: Vec<(usize, usize)> = thread_order(&keys, |k| *k)
+///     .iter()
+///     .map(|e| (e.index, e.depth))
+///     .collect();
+/// assert_eq!(order, [(0, 0), (2, 1), (1, 0), (3, 0)]);
+/// ```
+pub fn thread_order<T>(items: &[T], key: impl Fn(&T) -> ThreadKey) -> Vec<ThreadEntry> {
+    let keys: Vec<ThreadKey> = items.iter().map(key).collect();
+    let mut chronological: Vec<usize> = (0..keys.len()).collect();
+    chronological.sort_by_key(|&i| (keys[i].send_at, keys[i].log_id, i));
+
+    let mut position: HashMap<i64, usize> = HashMap::new();
+    for (i, key) in keys.iter().enumerate() {
+        position.entry(key.log_id).or_insert(i);
+    }
+    let parent: Vec<Option<usize>> = keys
+        .iter()
+        .enumerate()
+        .map(|(i, key)| {
+            let source = *position.get(&key.reply_to?)?;
+            (source != i).then_some(source)
+        })
+        .collect();
+    let mut children: Vec<Vec<usize>> = vec![Vec::new(); keys.len()];
+    for &i in &chronological {
+        if let Some(p) = parent[i] {
+            children[p].push(i);
+        }
+    }
+
+    // Messages with no parent in the window start threads. Whatever they do
+    // not reach hangs off a loop; its earliest message starts a thread too.
+    let mut reached = vec![false; keys.len()];
+    let mut is_root = vec![false; keys.len()];
+    for &i in &chronological {
+        if parent[i].is_none() {
+            is_root[i] = true;
+            mark_reachable(i, &children, &mut reached);
+        }
+    }
+    for &i in &chronological {
+        if !reached[i] {
+            is_root[i] = true;
+            mark_reachable(i, &children, &mut reached);
+        }
+    }
+
+    let mut placed = vec![false; keys.len()];
+    let mut out = Vec::with_capacity(keys.len());
+    for &root in chronological.iter().filter(|&&i| is_root[i]) {
+        let mut stack = vec![(root, 0)];
+        while let Some((i, depth)) = stack.pop() {
+            if placed[i] {
+                continue;
+            }
+            placed[i] = true;
+            out.push(ThreadEntry {
+                index: i,
+                depth,
+                root,
+                orphan: depth == 0 && keys[i].reply_to.is_some(),
+            });
+            stack.extend(children[i].iter().rev().map(|&child| (child, depth + 1)));
+        }
+    }
+    out
+}
+
+fn mark_reachable(from: usize, children: &[Vec<usize>], reached: &mut [bool]) {
+    let mut stack = vec![from];
+    while let Some(i) = stack.pop() {
+        if !std::mem::replace(&mut reached[i], true) {
+            stack.extend(&children[i]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(log_id: i64, send_at: i64, reply_to: Option<i64>) -> ThreadKey {
+        ThreadKey {
+            log_id,
+            send_at,
+            reply_to,
+        }
+    }
+
+    /// `(log_id, depth, orphan)` in display order.
+    fn shape(keys: &[ThreadKey]) -> Vec<(i64, usize, bool)> {
+        let order = thread_order(keys, |k| *k);
+        assert_eq!(order.len(), keys.len(), "every message is shown once");
+        order
+            .iter()
+            .map(|e| (keys[e.index].log_id, e.depth, e.orphan))
+            .collect()
+    }
+
+    #[test]
+    fn replies_nest_under_their_sources_in_time_order() {
+        let keys = [
+            key(10, 100, None),
+            key(11, 101, None),
+            key(13, 103, Some(10)),
+            key(12, 102, Some(10)),
+            key(14, 104, Some(12)),
+            key(15, 105, Some(11)),
+        ];
+        assert_eq!(
+            shape(&keys),
+            [
+                (10, 0, false),
+                (12, 1, false),
+                (14, 2, false),
+                (13, 1, false),
+                (11, 0, false),
+                (15, 1, false),
+            ]
+        );
+        let order = thread_order(&keys, |k| *k);
+        assert!(order[..4].iter().all(|e| e.root == 0));
+        assert_eq!(order[5].root, 1);
+    }
+
+    #[test]
+    fn replies_to_missing_messages_are_orphans_in_place() {
+        let keys = [
+            key(20, 200, Some(1)),
+            key(21, 201, None),
+            key(22, 202, Some(20)),
+        ];
+        assert_eq!(
+            shape(&keys),
+            [(20, 0, true), (22, 1, false), (21, 0, false)]
+        );
+    }
+
+    #[test]
+    fn loops_are_cut_at_their_earliest_message() {
+        // 30 and 31 answer each other; 32 answers 31; 33 answers itself.
+        let keys = [
+            key(31, 301, Some(30)),
+            key(30, 300, Some(31)),
+            key(32, 302, Some(31)),
+            key(33, 303, Some(33)),
+        ];
+        assert_eq!(
+            shape(&keys),
+            [(30, 0, true), (31, 1, false), (32, 2, false), (33, 0, true)]
+        );
+    }
+
+    #[test]
+    fn deep_chains_do_not_exhaust_the_stack() {
+        let keys: Vec<ThreadKey> = (0..100_000)
+            .map(|i| key(i + 1, i, (i > 0).then_some(i)))
+            .collect();
+        let order = thread_order(&keys, |k| *k);
+        assert_eq!(order.last().unwrap().depth, 99_999);
+        assert!(order.iter().enumerate().all(|(i, e)| e.index == i));
+        assert_eq!(order.last().unwrap().indent().len(), 2 * MAX_INDENT_DEPTH);
+    }
+}
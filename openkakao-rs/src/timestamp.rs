@@ -0,0 +1,189 @@
+//! Epoch detection for timestamps coming from the server and local caches.
+//!
+//! Most payloads send Unix seconds, but some variants send milliseconds, and
+//! Apple caches (`Cache.db` `time_stamp`) store `CFAbsoluteTime`: seconds
+//! since 2001-01-01. Read as Unix seconds these land in 1970 or decades in
+//! the future. [`normalize_timestamp`] tells the three apart by magnitude,
+//! which works because each encoding of a plausible date falls in its own
+//! range:
+//!
+//! | raw value                                 | read as                |
+//! |-------------------------------------------|------------------------|
+//! | `< 283_996_800`                           | invalid                |
+//! | `283_996_800 .. 1_262_304_000`            | CFAbsoluteTime seconds |
+//! | `1_262_304_000 ..= 4_102_444_800`         | Unix seconds           |
+//! | `4_102_444_800 .. 1_262_304_000_000`      | invalid                |
+//! | `1_262_304_000_000 ..= 4_102_444_800_000` | Unix milliseconds      |
+//! | larger                                    | invalid                |
+//!
+//! The CFAbsoluteTime window ends where Unix seconds begin, so Apple times
+//! after 2041 would be misread; nothing in a cache is that new.
+
+use chrono::{DateTime, Utc};
+
+/// Seconds between the Unix epoch and the CFAbsoluteTime epoch (2001-01-01).
+pub const CF_ABSOLUTE_EPOCH_OFFSET: i64 = 978_307_200;
+
+/// 2010-01-01T00:00:00Z. KakaoTalk launched in 2010; anything earlier is a
+/// misread field.
+pub const EARLIEST_UNIX_SECS: i64 = 1_262_304_000;
+
+/// 2100-01-01T00:00:00Z.
+pub const LATEST_UNIX_SECS: i64 = 4_102_444_800;
+
+/// Which epoch and unit a raw value was read in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Epoch {
+    UnixSeconds,
+    UnixMillis,
+    CfAbsolute,
+}
+
+/// Classify `raw` by magnitude, or `None` when no reading gives a date
+/// between [`EARLIEST_UNIX_SECS`] and [`LATEST_UNIX_SECS`].
+pub fn detect_epoch(raw: f64) -> Option<Epoch> {
+    if !raw.is_finite() {
+        return None;
+    }
+    let earliest = EARLIEST_UNIX_SECS as f64;
+    let latest = LATEST_UNIX_SECS as f64;
+    let cf_earliest = (EARLIEST_UNIX_SECS - CF_ABSOLUTE_EPOCH_OFFSET) as f64;
+    if (earliest..=latest).contains(&raw) {
+        Some(Epoch::UnixSeconds)
+    } else if (earliest * 1000.0..=latest * 1000.0).contains(&raw) {
+        Some(Epoch::UnixMillis)
+    } else if (cf_earliest..earliest).contains(&raw) {
+        Some(Epoch::CfAbsolute)
+    } else {
+        None
+    }
+}
+
+/// The instant `raw` stands for, whichever epoch it was written in.
+/// Zero, negative, non-finite and out-of-range values are `None`.
+pub fn normalize_timestamp(raw: f64) -> Option<DateTime<Utc>> {
+    let secs = match detect_epoch(raw)? {
+        Epoch::UnixSeconds => raw,
+        Epoch::UnixMillis => return DateTime::from_timestamp_millis(raw.round() as i64),
+        Epoch::CfAbsolute => raw + CF_ABSOLUTE_EPOCH_OFFSET as f64,
+    };
+    let nanos = (secs.fract() * 1e9).round().clamp(0.0, 999_999_999.0) as u32;
+    DateTime::from_timestamp(secs.trunc() as i64, nanos)
+}
+
+/// [`normalize_timestamp`] as whole Unix seconds, with 0 for "unknown" as
+/// the message structs store it.
+pub fn unix_secs(raw: f64) -> i64 {
+    normalize_timestamp(raw).map_or(0, |dt| dt.timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(raw: f64) -> Option<i64> {
+        normalize_timestamp(raw).map(|dt| dt.timestamp())
+    }
+
+    #[test]
+    fn unix_seconds_pass_through() {
+        assert_eq!(secs(1_700_000_000.0), Some(1_700_000_000));
+        assert_eq!(detect_epoch(1_700_000_000.0), Some(Epoch::UnixSeconds));
+        assert_eq!(
+            normalize_timestamp(1_700_000_000.0).unwrap().to_rfc3339(),
+            "2023-11-14T22:13:20+00:00"
+        );
+    }
+
+    #[test]
+    fn milliseconds_are_scaled() {
+        assert_eq!(secs(1_700_000_000_123.0), Some(1_700_000_000));
+        assert_eq!(detect_epoch(1_700_000_000_123.0), Some(Epoch::UnixMillis));
+        let dt = normalize_timestamp(1_700_000_000_123.0).unwrap();
+        assert_eq!(dt.timestamp_subsec_millis(), 123);
+    }
+
+    #[test]
+    fn cf_absolute_time_is_shifted_to_unix() {
+        // 2023-11-14T22:13:20Z in CFAbsoluteTime.
+        let cf = (1_700_000_000 - CF_ABSOLUTE_EPOCH_OFFSET) as f64;
+        assert_eq!(detect_epoch(cf), Some(Epoch::CfAbsolute));
+        assert_eq!(secs(cf), Some(1_700_000_000));
+        // Cache.db stores fractional seconds.
+        let dt = normalize_timestamp(cf + 0.5).unwrap();
+        assert_eq!(dt.timestamp(), 1_700_000_000);
+        assert_eq!(dt.timestamp_subsec_millis(), 500);
+    }
+
+    #[test]
+    fn boundaries_between_epochs() {
+        let earliest = EARLIEST_UNIX_SECS as f64;
+        let latest = LATEST_UNIX_SECS as f64;
+        let cf_earliest = (EARLIEST_UNIX_SECS - CF_ABSOLUTE_EPOCH_OFFSET) as f64;
+
+        // Unix seconds: both ends inclusive.
+        assert_eq!(detect_epoch(earliest), Some(Epoch::UnixSeconds));
+        assert_eq!(detect_epoch(latest), Some(Epoch::UnixSeconds));
+        assert_eq!(secs(earliest), Some(EARLIEST_UNIX_SECS));
+        assert_eq!(secs(latest), Some(LATEST_UNIX_SECS));
+
+        // Just below Unix seconds is CFAbsoluteTime in 2041.
+        assert_eq!(detect_epoch(earliest - 1.0), Some(Epoch::CfAbsolute));
+        assert_eq!(
+            secs(earliest - 1.0),
+            Some(EARLIEST_UNIX_SECS - 1 + CF_ABSOLUTE_EPOCH_OFFSET)
+        );
+
+        // CFAbsoluteTime starts at 2010 too.
+        assert_eq!(detect_epoch(cf_earliest), Some(Epoch::CfAbsolute));
+        assert_eq!(secs(cf_earliest), Some(EARLIEST_UNIX_SECS));
+        assert_eq!(detect_epoch(cf_earliest - 1.0), None);
+
+        // The gap between Unix seconds and milliseconds.
+        assert_eq!(detect_epoch(latest + 1.0), None);
+        assert_eq!(detect_epoch(earliest * 1000.0 - 1.0), None);
+
+        // Milliseconds: both ends inclusive.
+        assert_eq!(detect_epoch(earliest * 1000.0), Some(Epoch::UnixMillis));
+        assert_eq!(detect_epoch(latest * 1000.0), Some(Epoch::UnixMillis));
+        assert_eq!(secs(earliest * 1000.0), Some(EARLIEST_UNIX_SECS));
+        assert_eq!(secs(latest * 1000.0), Some(LATEST_UNIX_SECS));
+        assert_eq!(detect_epoch(latest * 1000.0 + 1.0), None);
+    }
+
+    #[test]
+    fn invalid_values_are_none() {
+        for raw in [
+            0.0,
+            -1.0,
+            -1_700_000_000.0,
+            1.0,
+            86_400.0,
+            f64::NAN,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            // Microseconds and nanoseconds are not a format anyone sends.
+            1_700_000_000_000_000.0,
+            1_700_000_000_000_000_000.0,
+        ] {
+            assert_eq!(normalize_timestamp(raw), None, "raw {raw}");
+            assert_eq!(unix_secs(raw), 0, "raw {raw}");
+        }
+    }
+
+    #[test]
+    fn the_same_instant_agrees_across_encodings() {
+        for unix in [
+            EARLIEST_UNIX_SECS,
+            1_300_000_000,
+            1_600_000_000,
+            1_790_000_000,
+            2_240_000_000,
+        ] {
+            let expected = Some(unix);
+            assert_eq!(secs(unix as f64), expected);
+            assert_eq!(secs((unix * 1000) as f64), expected);
+            assert_eq!(secs((unix - CF_ABSOLUTE_EPOCH_OFFSET) as f64), expected);
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! Redaction for the `--verbose` request trace.
+//!
+//! Requests, responses and credential extraction are logged as `tracing`
+//! events under the `openkakao_rs` target; the CLI sends them to stderr at
+//! debug level with `-v` and trace level with `-vv`. Whatever goes into an
+//! event passes through here first, so a token never appears in full.
+
+use reqwest::header::HeaderMap;
+use serde_json::Value;
+
+/// How much of a response body a trace-level event shows.
+pub const BODY_EXCERPT_LIMIT: usize = 2048;
+
+/// Characters of a secret kept so two tokens can still be told apart.
+const SECRET_PREFIX_CHARS: usize = 6;
+
+/// Headers whose values are credentials.
+const SECRET_HEADERS: &[&str] = &["authorization", "cookie", "set-cookie"];
+
+/// `secret` as its first few characters and its length, e.g.
+/// `abc123…(142 chars)`. An auth scheme (`Bearer `) is kept as is; a value
+/// too short to spare a prefix shows only its length.
+pub fn redact_secret(secret: &str) -> String {
+    let (scheme, token) = match secret.split_once(' ') {
+        Some((scheme, token)) if !scheme.is_empty() && !token.is_empty() => {
+            (&secret[..scheme.len() + 1], token)
+        }
+        _ => ("", secret),
+    };
+    let len = token.chars().count();
+    if len <= SECRET_PREFIX_CHARS * 2 {
+        return format!("{scheme}…({len} chars)");
+    }
+    let prefix: String = token.chars().take(SECRET_PREFIX_CHARS).collect();
+    format!("{scheme}{prefix}…({len} chars)")
+}
+
+/// `headers` as `name: value` pairs, with credentials redacted.
+pub fn redact_headers(headers: &HeaderMap) -> String {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = String::from_utf8_lossy(value.as_bytes());
+            if SECRET_HEADERS.contains(&name.as_str()) {
+                format!("{name}: {}", redact_secret(&value))
+            } else {
+                format!("{name}: {value}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Whether a JSON key names a credential.
+fn is_secret_key(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    ["token", "password", "passcode", "secret", "cookie"]
+        .iter()
+        .any(|word| key.contains(word))
+}
+
+/// `value` with every credential-named string field redacted, at any depth.
+pub fn redact_json(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, field)| {
+                    let field = match field {
+                        Value::String(secret) if is_secret_key(key) => {
+                            Value::String(redact_secret(secret))
+                        }
+                        field => redact_json(field),
+                    };
+                    (key.clone(), field)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(redact_json).collect()),
+        other => other.clone(),
+    }
+}
+
+/// The first `limit` bytes of `text`, cut at a character boundary, with a
+/// note of how much was left out.
+pub fn excerpt(text: &str, limit: usize) -> String {
+    if text.len() <= limit {
+        return text.to_string();
+    }
+    let mut end = limit;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}…(+{} bytes)", &text[..end], text.len() - end)
+}
+
+/// A response body for a trace event: redacted JSON when it parses,
+/// otherwise the raw text, either way cut to `limit` bytes.
+pub fn body_excerpt(body: &[u8], limit: usize) -> String {
+    match serde_json::from_slice::<Value>(body) {
+        Ok(parsed) => excerpt(&redact_json(&parsed).to_string(), limit),
+        Err(_) => excerpt(&String::from_utf8_lossy(body), limit),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderValue, AUTHORIZATION};
+    use serde_json::json;
+
+    #[test]
+    fn secrets_keep_only_a_prefix_and_their_length() {
+        let token = "a1b2c3d4e5f6g7h8i9j0-device-uuid";
+        let redacted = redact_secret(token);
+        assert_eq!(redacted, "a1b2c3…(32 chars)");
+        assert!(!redacted.contains("device-uuid"));
+        assert_eq!(
+            redact_secret("Bearer 0123456789abcdefXYZ"),
+            "Bearer 012345…(19 chars)"
+        );
+        assert_eq!(redact_secret("short"), "…(5 chars)");
+        assert_eq!(redact_secret("Bearer short"), "Bearer …(5 chars)");
+        assert_eq!(
+            redact_secret("토큰토큰토큰토큰토큰토큰토큰"),
+            "토큰토큰토큰…(14 chars)"
+        );
+    }
+
+    #[test]
+    fn only_credential_headers_are_redacted() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_static("abcdefghijklmnopqrstuvwxyz-uuid"),
+        );
+        headers.insert("A", HeaderValue::from_static("mac/26.1.0/ko"));
+        let line = redact_headers(&headers);
+        assert!(line.contains("authorization: abcdef…(31 chars)"), "{line}");
+        assert!(line.contains("a: mac/26.1.0/ko"), "{line}");
+        assert!(!line.contains("uvwxyz"), "{line}");
+    }
+
+    #[test]
+    fn json_bodies_lose_their_tokens_at_any_depth() {
+        let body = json!({
+            "status": 0,
+            "access_token": "0123456789abcdefghij",
+            "nested": [{"refreshToken": "zyxwvutsrqponmlkjihg", "name": "Dana"}],
+        });
+        let redacted = redact_json(&body);
+        assert_eq!(redacted["access_token"], "012345…(20 chars)");
+        assert_eq!(redacted["nested"][0]["refreshToken"], "zyxwvu…(20 chars)");
+        assert_eq!(redacted["nested"][0]["name"], "Dana");
+        assert_eq!(redacted["status"], 0);
+
+        let excerpt = body_excerpt(body.to_string().as_bytes(), BODY_EXCERPT_LIMIT);
+        assert!(!excerpt.contains("abcdefghij"), "{excerpt}");
+    }
+
+    #[test]
+    fn excerpts_cut_at_a_character_boundary() {
+        assert_eq!(excerpt("hello", 10), "hello");
+        assert_eq!(excerpt("hello world", 5), "hello…(+6 bytes)");
+        // "안" is three bytes; a cut inside it backs off to the boundary.
+        assert_eq!(excerpt("안녕", 4), "안…(+3 bytes)");
+        assert_eq!(body_excerpt(b"<html>oops</html>", 6), "<html>…(+11 bytes)");
+    }
+}
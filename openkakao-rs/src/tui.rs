@@ -0,0 +1,607 @@
+//! State and key handling for `tui`, kept apart from drawing and I/O so it
+//! can be tested without a terminal. `commands::tui` translates terminal
+//! events into [`Key`]s, carries out the [`Effect`]s and draws the result.
+
+use std::collections::HashMap;
+
+use crate::cursor::MessageCursor;
+use crate::hangul::name_matches;
+use crate::model::{ChatMessage, ChatRoom};
+
+/// Narrowest terminal that still gets both panes.
+pub const SPLIT_MIN_WIDTH: u16 = 80;
+/// Below this nothing useful fits; a notice is drawn instead.
+pub const MIN_WIDTH: u16 = 30;
+pub const MIN_HEIGHT: u16 = 6;
+
+/// Keys the app reacts to, independent of the terminal library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Left,
+    Right,
+    Tab,
+    Enter,
+    Esc,
+    Backspace,
+    Char(char),
+    /// Ctrl-C.
+    Interrupt,
+}
+
+/// Work the app asks the event loop to do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Effect {
+    LoadChats,
+    /// The newest page of a chat.
+    LoadMessages {
+        chat_id: i64,
+    },
+    /// The page before `cursor`.
+    LoadOlder {
+        chat_id: i64,
+        cursor: MessageCursor,
+    },
+    MarkRead {
+        chat_id: i64,
+        log_id: i64,
+    },
+    OpenInApp {
+        chat_id: i64,
+    },
+    Export {
+        chat_id: i64,
+    },
+    Quit,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Focus {
+    #[default]
+    Chats,
+    Messages,
+}
+
+/// How the screen is split at a given size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    TooSmall,
+    /// Only the focused pane.
+    Single,
+    Split,
+}
+
+pub fn layout_for(width: u16, height: u16) -> Layout {
+    if width < MIN_WIDTH || height < MIN_HEIGHT {
+        Layout::TooSmall
+    } else if width < SPLIT_MIN_WIDTH {
+        Layout::Single
+    } else {
+        Layout::Split
+    }
+}
+
+/// The chat whose messages are shown.
+#[derive(Debug, Clone, Default)]
+pub struct OpenChat {
+    pub chat_id: i64,
+    /// Oldest first.
+    pub messages: Vec<ChatMessage>,
+    pub names: HashMap<i64, String>,
+    /// Cursor for the next older page; `None` once the start is reached.
+    pub older_cursor: Option<MessageCursor>,
+    /// Messages hidden below the view, counted from the newest.
+    pub scroll: usize,
+    pub loading_older: bool,
+}
+
+impl OpenChat {
+    pub fn newest_log_id(&self) -> Option<i64> {
+        self.messages.iter().map(|m| m.log_id).max()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct App {
+    pub chats: Vec<ChatRoom>,
+    /// Index into [`App::visible_chats`].
+    pub selected: usize,
+    pub focus: Focus,
+    /// Incremental chat filter; it stays applied after Enter.
+    pub search: String,
+    /// Keys go to [`App::search`] rather than navigation.
+    pub searching: bool,
+    pub open: Option<OpenChat>,
+    pub status: Option<String>,
+    /// Mark-read is refused under `--read-only`.
+    pub allow_mark_read: bool,
+}
+
+impl App {
+    pub fn new(allow_mark_read: bool) -> Self {
+        Self {
+            allow_mark_read,
+            ..Self::default()
+        }
+    }
+
+    /// Chats matching the search (title or 초성), in list order.
+    pub fn visible_chats(&self) -> Vec<&ChatRoom> {
+        let query = self.search.trim();
+        self.chats
+            .iter()
+            .filter(|room| query.is_empty() || name_matches(&room.display_title(), query))
+            .collect()
+    }
+
+    pub fn selected_chat(&self) -> Option<&ChatRoom> {
+        self.visible_chats().get(self.selected).copied()
+    }
+
+    pub fn chats_loaded(&mut self, chats: Vec<ChatRoom>) {
+        let keep = self.selected_chat().map(|room| room.chat_id);
+        self.chats = chats;
+        self.selected = keep
+            .and_then(|id| self.visible_chats().iter().position(|r| r.chat_id == id))
+            .unwrap_or(0);
+    }
+
+    /// A page arrived. Newest pages replace what is shown (keeping the
+    /// scroll position when it is the same chat); older pages are prepended.
+    pub fn messages_loaded(
+        &mut self,
+        chat_id: i64,
+        mut page: Vec<ChatMessage>,
+        older_cursor: Option<MessageCursor>,
+        older: bool,
+        names: HashMap<i64, String>,
+    ) {
+        page.sort_by_key(|m| m.log_id);
+        match &mut self.open {
+            Some(open) if open.chat_id == chat_id && older => {
+                open.loading_older = false;
+                let first = open.messages.first().map_or(i64::MAX, |m| m.log_id);
+                page.retain(|m| m.log_id < first);
+                if page.is_empty() {
+                    open.older_cursor = None;
+                } else {
+                    open.older_cursor = older_cursor;
+                }
+                open.messages.splice(0..0, page);
+                open.names.extend(names);
+            }
+            Some(open) if open.chat_id == chat_id => {
+                // A refresh: keep older pages already loaded, add new ones.
+                let newest = open.newest_log_id().unwrap_or(0);
+                let added = page.iter().filter(|m| m.log_id > newest).count();
+                let oldest_new = page.first().map_or(i64::MAX, |m| m.log_id);
+                open.messages.retain(|m| m.log_id < oldest_new);
+                open.messages.extend(page);
+                if open.scroll > 0 {
+                    open.scroll += added;
+                }
+                if open.older_cursor.is_none() && open.messages.len() == added {
+                    open.older_cursor = older_cursor;
+                }
+                open.names.extend(names);
+            }
+            _ if older => {}
+            _ => {
+                self.open = Some(OpenChat {
+                    chat_id,
+                    messages: page,
+                    names,
+                    older_cursor,
+                    ..OpenChat::default()
+                });
+            }
+        }
+    }
+
+    pub fn load_failed(&mut self, message: String) {
+        if let Some(open) = &mut self.open {
+            open.loading_older = false;
+        }
+        self.status = Some(message);
+    }
+
+    /// What a periodic poll should refresh.
+    pub fn poll(&self) -> Vec<Effect> {
+        let mut effects = vec![Effect::LoadChats];
+        if let Some(open) = &self.open {
+            effects.push(Effect::LoadMessages {
+                chat_id: open.chat_id,
+            });
+        }
+        effects
+    }
+
+    pub fn handle_key(&mut self, key: Key) -> Vec<Effect> {
+        if key == Key::Interrupt {
+            return vec![Effect::Quit];
+        }
+        if self.searching {
+            self.search_key(key);
+            return Vec::new();
+        }
+        self.status = None;
+        match self.focus {
+            Focus::Chats => self.chats_key(key),
+            Focus::Messages => self.messages_key(key),
+        }
+    }
+
+    fn search_key(&mut self, key: Key) {
+        match key {
+            Key::Enter => self.searching = false,
+            Key::Esc => {
+                self.searching = false;
+                self.search.clear();
+            }
+            Key::Backspace => {
+                self.search.pop();
+            }
+            Key::Char(c) => self.search.push(c),
+            _ => {}
+        }
+        self.selected = 0;
+    }
+
+    fn chats_key(&mut self, key: Key) -> Vec<Effect> {
+        let count = self.visible_chats().len();
+        let last = count.saturating_sub(1);
+        match key {
+            Key::Char('q') => return vec![Effect::Quit],
+            Key::Up | Key::Char('k') => self.selected = self.selected.saturating_sub(1),
+            Key::Down | Key::Char('j') => self.selected = (self.selected + 1).min(last),
+            Key::PageUp => self.selected = self.selected.saturating_sub(10),
+            Key::PageDown => self.selected = (self.selected + 10).min(last),
+            Key::Home | Key::Char('g') => self.selected = 0,
+            Key::End | Key::Char('G') => self.selected = last,
+            Key::Char('/') => {
+                self.searching = true;
+                self.search.clear();
+                self.selected = 0;
+            }
+            Key::Esc if !self.search.is_empty() => {
+                self.search.clear();
+                self.selected = 0;
+            }
+            Key::Char('r') => return vec![Effect::LoadChats],
+            Key::Tab if self.open.is_some() => self.focus = Focus::Messages,
+            Key::Enter | Key::Right | Key::Char('l') => {
+                if let Some(chat_id) = self.selected_chat().map(|room| room.chat_id) {
+                    self.focus = Focus::Messages;
+                    if self.open.as_ref().map(|open| open.chat_id) != Some(chat_id) {
+                        self.open = None;
+                        return vec![Effect::LoadMessages { chat_id }];
+                    }
+                }
+            }
+            _ => return self.chat_action(key),
+        }
+        Vec::new()
+    }
+
+    fn messages_key(&mut self, key: Key) -> Vec<Effect> {
+        let Some(open) = &mut self.open else {
+            self.focus = Focus::Chats;
+            return Vec::new();
+        };
+        let top = open.messages.len().saturating_sub(1);
+        match key {
+            Key::Char('q') => return vec![Effect::Quit],
+            Key::Esc | Key::Left | Key::Char('h') | Key::Tab => self.focus = Focus::Chats,
+            Key::Up | Key::Char('k') => open.scroll = (open.scroll + 1).min(top),
+            Key::Down | Key::Char('j') => open.scroll = open.scroll.saturating_sub(1),
+            Key::PageUp => open.scroll = (open.scroll + 10).min(top),
+            Key::PageDown => open.scroll = open.scroll.saturating_sub(10),
+            Key::Home | Key::Char('g') => open.scroll = top,
+            Key::End | Key::Char('G') => open.scroll = 0,
+            Key::Char('r') => {
+                return vec![Effect::LoadMessages {
+                    chat_id: open.chat_id,
+                }]
+            }
+            _ => return self.chat_action(key),
+        }
+        // Scrolled to the oldest loaded message: fetch the page before it.
+        match &mut self.open {
+            Some(OpenChat {
+                chat_id,
+                older_cursor: Some(cursor),
+                scroll,
+                loading_older,
+                ..
+            }) if *scroll >= top && !*loading_older => {
+                *loading_older = true;
+                vec![Effect::LoadOlder {
+                    chat_id: *chat_id,
+                    cursor: *cursor,
+                }]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Keys that act on the focused chat from either pane.
+    fn chat_action(&mut self, key: Key) -> Vec<Effect> {
+        let chat_id = match self.focus {
+            Focus::Chats => self.selected_chat().map(|room| room.chat_id),
+            Focus::Messages => self.open.as_ref().map(|open| open.chat_id),
+        };
+        let Some(chat_id) = chat_id else {
+            return Vec::new();
+        };
+        match key {
+            Key::Char('m') if !self.allow_mark_read => {
+                self.status = Some("Mark-read is disabled under --read-only.".into());
+                Vec::new()
+            }
+            Key::Char('m') => {
+                let newest = self
+                    .open
+                    .as_ref()
+                    .filter(|open| open.chat_id == chat_id)
+                    .and_then(OpenChat::newest_log_id)
+                    .or_else(|| {
+                        self.chats
+                            .iter()
+                            .find(|room| room.chat_id == chat_id)
+                            .and_then(|room| room.settings.last_log_id)
+                    });
+                match newest {
+                    Some(log_id) => vec![Effect::MarkRead { chat_id, log_id }],
+                    None => {
+                        self.status = Some("Nothing to mark read yet.".into());
+                        Vec::new()
+                    }
+                }
+            }
+            Key::Char('o') => vec![Effect::OpenInApp { chat_id }],
+            Key::Char('e') => vec![Effect::Export { chat_id }],
+            _ => Vec::new(),
+        }
+    }
+
+    /// After a successful mark-read, clear the badge locally.
+    pub fn marked_read(&mut self, chat_id: i64) {
+        if let Some(room) = self.chats.iter_mut().find(|room| room.chat_id == chat_id) {
+            room.unread_count = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn room(chat_id: i64, title: &str, unread: i64) -> ChatRoom {
+        ChatRoom::from_json(&json!({
+            "chatId": chat_id,
+            "title": title,
+            "unreadCount": unread,
+            "type": "MultiChat",
+        }))
+    }
+
+    fn msgs(ids: &[i64]) -> Vec<ChatMessage> {
+        ids.iter()
+            .map(|&id| ChatMessage::from_json(&json!({"logId": id, "message": format!("m{id}")})))
+            .collect()
+    }
+
+    fn app_with_chats() -> App {
+        let mut app = App::new(true);
+        app.chats_loaded(vec![
+            room(1, "가족", 3),
+            room(2, "Rust 스터디", 0),
+            room(3, "회사 동기", 1),
+        ]);
+        app
+    }
+
+    fn ids(app: &App) -> Vec<i64> {
+        app.open
+            .as_ref()
+            .unwrap()
+            .messages
+            .iter()
+            .map(|m| m.log_id)
+            .collect()
+    }
+
+    #[test]
+    fn layout_degrades_on_small_terminals() {
+        assert_eq!(layout_for(120, 40), Layout::Split);
+        assert_eq!(layout_for(80, 10), Layout::Split);
+        assert_eq!(layout_for(79, 10), Layout::Single);
+        assert_eq!(layout_for(30, 6), Layout::Single);
+        assert_eq!(layout_for(29, 40), Layout::TooSmall);
+        assert_eq!(layout_for(120, 5), Layout::TooSmall);
+    }
+
+    #[test]
+    fn navigation_stays_in_bounds() {
+        let mut app = app_with_chats();
+        app.handle_key(Key::Up);
+        assert_eq!(app.selected, 0);
+        for _ in 0..5 {
+            app.handle_key(Key::Down);
+        }
+        assert_eq!(app.selected, 2);
+        app.handle_key(Key::Home);
+        assert_eq!(app.selected, 0);
+        app.handle_key(Key::End);
+        assert_eq!(app.selected_chat().unwrap().chat_id, 3);
+    }
+
+    #[test]
+    fn chosung_search_filters_incrementally() {
+        let mut app = app_with_chats();
+        app.handle_key(Key::Char('/'));
+        assert!(app.searching);
+        // Keys go to the query, not to navigation or quit.
+        assert!(app.handle_key(Key::Char('ㅎ')).is_empty());
+        assert_eq!(app.visible_chats().len(), 1);
+        assert_eq!(app.selected_chat().unwrap().chat_id, 3);
+        app.handle_key(Key::Backspace);
+        assert_eq!(app.visible_chats().len(), 3);
+        app.handle_key(Key::Char('r'));
+        app.handle_key(Key::Char('u'));
+        app.handle_key(Key::Enter);
+        assert!(!app.searching);
+        assert_eq!(app.selected_chat().unwrap().chat_id, 2);
+        // Esc clears a kept filter.
+        app.handle_key(Key::Esc);
+        assert_eq!(app.visible_chats().len(), 3);
+    }
+
+    #[test]
+    fn opening_a_chat_loads_it_once() {
+        let mut app = app_with_chats();
+        app.handle_key(Key::Down);
+        assert_eq!(
+            app.handle_key(Key::Enter),
+            [Effect::LoadMessages { chat_id: 2 }]
+        );
+        assert_eq!(app.focus, Focus::Messages);
+        app.messages_loaded(
+            2,
+            msgs(&[30, 10, 20]),
+            Some(MessageCursor::new(9)),
+            false,
+            HashMap::new(),
+        );
+        assert_eq!(ids(&app), [10, 20, 30]);
+        app.handle_key(Key::Esc);
+        assert_eq!(app.focus, Focus::Chats);
+        // Re-entering the same chat does not refetch.
+        assert!(app.handle_key(Key::Enter).is_empty());
+    }
+
+    #[test]
+    fn scrolling_to_the_top_loads_older_pages_once() {
+        let mut app = app_with_chats();
+        app.handle_key(Key::Enter);
+        app.messages_loaded(
+            1,
+            msgs(&[10, 11, 12]),
+            Some(MessageCursor::new(9)),
+            false,
+            HashMap::new(),
+        );
+        assert!(app.handle_key(Key::Up).is_empty());
+        assert_eq!(
+            app.handle_key(Key::Up),
+            [Effect::LoadOlder {
+                chat_id: 1,
+                cursor: MessageCursor::new(9)
+            }]
+        );
+        // Already loading: no second request.
+        assert!(app.handle_key(Key::Up).is_empty());
+        app.messages_loaded(1, msgs(&[8, 9, 10]), None, true, HashMap::new());
+        assert_eq!(ids(&app), [8, 9, 10, 11, 12]);
+        // The start of the chat: nothing more to load.
+        app.handle_key(Key::Home);
+        assert!(app.handle_key(Key::Up).is_empty());
+        assert_eq!(app.open.as_ref().unwrap().older_cursor, None);
+    }
+
+    #[test]
+    fn refresh_appends_new_messages_and_keeps_scroll() {
+        let mut app = app_with_chats();
+        app.handle_key(Key::Enter);
+        app.messages_loaded(
+            1,
+            msgs(&[8, 9, 10]),
+            Some(MessageCursor::new(7)),
+            false,
+            HashMap::new(),
+        );
+        app.handle_key(Key::Up);
+        assert_eq!(app.poll().len(), 2);
+        app.messages_loaded(
+            1,
+            msgs(&[9, 10, 11, 12]),
+            Some(MessageCursor::new(8)),
+            false,
+            HashMap::new(),
+        );
+        assert_eq!(ids(&app), [8, 9, 10, 11, 12]);
+        // Two new messages below: the view stays on the same message.
+        assert_eq!(app.open.as_ref().unwrap().scroll, 3);
+        assert_eq!(
+            app.open.as_ref().unwrap().older_cursor,
+            Some(MessageCursor::new(7))
+        );
+    }
+
+    #[test]
+    fn late_pages_for_another_chat_are_ignored() {
+        let mut app = app_with_chats();
+        app.handle_key(Key::Enter);
+        app.messages_loaded(1, msgs(&[10]), None, false, HashMap::new());
+        app.messages_loaded(2, msgs(&[5]), None, true, HashMap::new());
+        assert_eq!(app.open.as_ref().unwrap().chat_id, 1);
+    }
+
+    #[test]
+    fn chat_actions_target_the_focused_chat() {
+        let mut app = app_with_chats();
+        app.handle_key(Key::Down);
+        assert_eq!(
+            app.handle_key(Key::Char('o')),
+            [Effect::OpenInApp { chat_id: 2 }]
+        );
+        assert_eq!(
+            app.handle_key(Key::Char('e')),
+            [Effect::Export { chat_id: 2 }]
+        );
+        // No messages and no known last log id yet.
+        assert!(app.handle_key(Key::Char('m')).is_empty());
+        assert!(app.status.is_some());
+
+        app.handle_key(Key::Enter);
+        app.messages_loaded(2, msgs(&[40, 41]), None, false, HashMap::new());
+        assert_eq!(
+            app.handle_key(Key::Char('m')),
+            [Effect::MarkRead {
+                chat_id: 2,
+                log_id: 41
+            }]
+        );
+        app.marked_read(1);
+        assert_eq!(app.chats[0].unread_count, 0);
+        assert_eq!(app.handle_key(Key::Interrupt), [Effect::Quit]);
+    }
+
+    #[test]
+    fn read_only_refuses_mark_read() {
+        let mut app = App::new(false);
+        app.chats_loaded(vec![room(1, "a", 2)]);
+        app.handle_key(Key::Enter);
+        app.messages_loaded(1, msgs(&[5]), None, false, HashMap::new());
+        assert!(app.handle_key(Key::Char('m')).is_empty());
+        assert!(app.status.as_deref().unwrap().contains("--read-only"));
+    }
+
+    #[test]
+    fn selection_follows_the_chat_across_reloads() {
+        let mut app = app_with_chats();
+        app.handle_key(Key::Down);
+        app.chats_loaded(vec![
+            room(9, "new", 1),
+            room(1, "가족", 0),
+            room(2, "Rust 스터디", 0),
+        ]);
+        assert_eq!(app.selected_chat().unwrap().chat_id, 2);
+    }
+}
@@ -0,0 +1,436 @@
+use std::net::IpAddr;
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, Result};
+use reqwest::Url;
+
+static STRIP_TRACKING: OnceLock<bool> = OnceLock::new();
+
+/// Query parameters dropped by `--strip-tracking`, besides every `utm_*`.
+/// Includes Naver search-ad (`n_*`, `NaPm`) and Daum keyword-ad parameters.
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid",
+    "gclid",
+    "dclid",
+    "msclkid",
+    "yclid",
+    "igshid",
+    "mc_cid",
+    "mc_eid",
+    "_hsenc",
+    "_hsmi",
+    "NaPm",
+    "n_media",
+    "n_query",
+    "n_rank",
+    "n_ad",
+    "n_ad_group",
+    "n_ad_group_type",
+    "n_keyword",
+    "n_keyword_id",
+    "n_campaign_type",
+    "n_contract",
+    "DMKW",
+    "DMSKW",
+    "DMCOL",
+];
+
+/// Set once from `--strip-tracking`.
+pub fn set_strip_tracking(enabled: bool) {
+    let _ = STRIP_TRACKING.set(enabled);
+}
+
+pub fn strip_tracking() -> bool {
+    STRIP_TRACKING.get().copied().unwrap_or(false)
+}
+
+fn is_tracking_param(key: &str) -> bool {
+    key.starts_with("utm_") || TRACKING_PARAMS.contains(&key)
+}
+
+/// Canonical form of a link so the same page compares equal however it was
+/// pasted: http(s) only, scheme-less `www.` links get `https://`, the host is
+/// lowercased and IDN hosts (e.g. Korean domains) become punycode, default
+/// ports, fragments and trailing slashes are dropped, and with
+/// `strip_tracking` so are `utm_*` and known ad/click parameters. The order
+/// and encoding of the remaining query parameters is kept.
+pub fn normalize(url: &str, strip_tracking: bool) -> Result<String> {
+    let trimmed = url.trim();
+    let with_scheme = if !trimmed.contains("://")
+        && trimmed
+            .get(..4)
+            .is_some_and(|p| p.eq_ignore_ascii_case("www."))
+    {
+        format!("https://{trimmed}")
+    } else {
+        trimmed.to_string()
+    };
+    let mut parsed =
+        Url::parse(&with_scheme).map_err(|e| anyhow!("invalid URL '{}': {}", trimmed, e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        anyhow::bail!(
+            "unsupported URL scheme '{}' in '{}'; only http and https links are allowed",
+            parsed.scheme(),
+            trimmed
+        );
+    }
+    if parsed.host_str().is_none_or(str::is_empty) {
+        anyhow::bail!("URL '{}' has no host", trimmed);
+    }
+
+    // `Url` already lowercases the host, applies IDNA and omits default ports.
+    parsed.set_fragment(None);
+    if strip_tracking {
+        let kept: Vec<&str> = parsed
+            .query()
+            .unwrap_or_default()
+            .split('&')
+            .filter(|pair| {
+                let key = pair.split('=').next().unwrap_or_default();
+                !pair.is_empty() && !is_tracking_param(key)
+            })
+            .collect();
+        let query = kept.join("&");
+        parsed.set_query((!query.is_empty()).then_some(query.as_str()));
+    }
+    if parsed.query() == Some("") {
+        parsed.set_query(None);
+    }
+    let path = parsed.path().trim_end_matches('/').to_string();
+    if !path.is_empty() {
+        parsed.set_path(&path);
+    }
+    let mut canonical = parsed.to_string();
+    if parsed.path() == "/" && parsed.query().is_none() {
+        canonical.pop();
+    }
+    Ok(canonical)
+}
+
+/// Whether `url` is worth handing to the preview service: a valid http(s)
+/// link to a public host. Loopback, private and link-local addresses (and
+/// `localhost`) are refused since Kakao's servers cannot reach them.
+pub fn is_fetchable(url: &str) -> bool {
+    let Ok(canonical) = normalize(url, false) else {
+        return false;
+    };
+    let Ok(parsed) = Url::parse(&canonical) else {
+        return false;
+    };
+    let Some(host) = parsed.host_str() else {
+        return false;
+    };
+    match host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+    {
+        Ok(ip) => is_public_ip(ip),
+        Err(_) => {
+            let domain = host.trim_end_matches('.');
+            domain != "localhost" && !domain.ends_with(".localhost") && domain.contains('.')
+        }
+    }
+}
+
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast())
+        }
+        IpAddr::V6(ip) => {
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+/// Links in message text, normalized, in order and without duplicates.
+/// Picks up `http(s)://` and `www.` links, also when Korean text is written
+/// right against them (`https://example.com에서`, `「www.naver.com」`), and
+/// drops trailing punctuation but not a closing bracket the link opened
+/// (`https://en.wikipedia.org/wiki/Rust_(language)`).
+pub fn extract_urls(text: &str, strip_tracking: bool) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+    for token in text.split_whitespace() {
+        let mut rest = token;
+        while let Some(start) = link_start(rest) {
+            let candidate = &rest[start..];
+            let end = link_end(candidate);
+            if let Ok(url) = normalize(trim_link(&candidate[..end]), strip_tracking) {
+                if !urls.contains(&url) {
+                    urls.push(url);
+                }
+            }
+            rest = &candidate[end..];
+        }
+    }
+    urls
+}
+
+fn link_start(text: &str) -> Option<usize> {
+    let lower = text.to_ascii_lowercase();
+    ["https://", "http://", "www."]
+        .iter()
+        .filter_map(|prefix| lower.find(prefix))
+        .min()
+}
+
+/// Where a link starting `text` ends: at a quote or bracket that cannot be
+/// part of it, or where Hangul follows the link's ASCII directly. Hangul
+/// after `/`, `.`, `=` and the like is a Korean path, query or domain
+/// (`https://ko.wikipedia.org/wiki/서울`) and is kept.
+fn link_end(text: &str) -> usize {
+    let mut prev: Option<char> = None;
+    for (i, c) in text.char_indices() {
+        let ends = matches!(
+            c,
+            '<' | '>'
+                | '"'
+                | '「'
+                | '」'
+                | '『'
+                | '』'
+                | '“'
+                | '”'
+                | '‘'
+                | '’'
+                | '【'
+                | '】'
+                | '《'
+                | '》'
+                | '〈'
+                | '〉'
+                | '、'
+                | '。'
+                | '，'
+        ) || (is_hangul(c)
+            && prev.is_some_and(|p| {
+                p.is_ascii() && !matches!(p, '/' | '.' | '=' | '?' | '&' | '#' | '-' | '_' | '+')
+            }));
+        // Never inside the `https://` / `www.` prefix itself.
+        if ends && i >= 4 {
+            return i;
+        }
+        prev = Some(c);
+    }
+    text.len()
+}
+
+fn is_hangul(c: char) -> bool {
+    ('\u{AC00}'..='\u{D7A3}').contains(&c) || crate::hangul::is_compatibility_jamo(c)
+}
+
+/// `link` without trailing sentence punctuation. A closing `)` or `]` is
+/// only punctuation when the link has no matching opening one.
+fn trim_link(mut link: &str) -> &str {
+    while let Some(last) = link.chars().last() {
+        let unbalanced =
+            |open: char, close: char| link.matches(close).count() > link.matches(open).count();
+        let trailing = match last {
+            ')' => unbalanced('(', ')'),
+            ']' => unbalanced('[', ']'),
+            '.' | ',' | ';' | ':' | '!' | '?' | '}' | '\'' | '~' | '…' => true,
+            _ => false,
+        };
+        if !trailing {
+            break;
+        }
+        link = &link[..link.len() - last.len_utf8()];
+    }
+    link
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_case_ports_fragments_and_slashes() {
+        for url in [
+            "https://example.com/a/b",
+            "https://example.com/a/b/",
+            "HTTPS://Example.COM:443/a/b",
+            "https://example.com/a/b#section",
+            "  https://example.com/a/b/#top ",
+        ] {
+            assert_eq!(
+                normalize(url, false).unwrap(),
+                "https://example.com/a/b",
+                "{url}"
+            );
+        }
+        assert_eq!(
+            normalize("http://example.com:80/", false).unwrap(),
+            "http://example.com"
+        );
+        assert_eq!(
+            normalize("www.example.com/x/", false).unwrap(),
+            "https://www.example.com/x"
+        );
+        assert_eq!(
+            normalize("https://example.com:8443/Path/?q=1#f", false).unwrap(),
+            "https://example.com:8443/Path?q=1"
+        );
+    }
+
+    #[test]
+    fn korean_domains_become_punycode() {
+        assert_eq!(
+            normalize("http://한국.kr/", false).unwrap(),
+            "http://xn--3e0b707e.kr"
+        );
+        assert_eq!(
+            normalize("http://한국.kr/소개", false).unwrap(),
+            normalize("http://xn--3e0b707e.kr/%EC%86%8C%EA%B0%9C", false).unwrap()
+        );
+    }
+
+    #[test]
+    fn only_http_and_https_are_accepted() {
+        for url in [
+            "javascript:alert(1)",
+            "ftp://example.com/file",
+            "file:///etc/passwd",
+            "kakaotalk://open",
+            "not a url",
+            "",
+        ] {
+            assert!(normalize(url, false).is_err(), "{url}");
+            assert!(!is_fetchable(url), "{url}");
+        }
+    }
+
+    #[test]
+    fn tracking_parameters_are_stripped_only_on_request() {
+        let blog = "https://m.blog.naver.com/PostView.naver?blogId=foo&logNo=223&utm_source=kakao&utm_medium=share#comment";
+        assert_eq!(
+            normalize(blog, true).unwrap(),
+            "https://m.blog.naver.com/PostView.naver?blogId=foo&logNo=223"
+        );
+        assert_eq!(
+            normalize(blog, false).unwrap(),
+            "https://m.blog.naver.com/PostView.naver?blogId=foo&logNo=223&utm_source=kakao&utm_medium=share"
+        );
+
+        let shop = "https://smartstore.naver.com/shop/products/123?NaPm=ct%3Dlx1%7Cci%3Dabc&n_media=27758&n_query=%EC%8B%A0%EB%B0%9C&n_rank=1";
+        assert_eq!(
+            normalize(shop, true).unwrap(),
+            "https://smartstore.naver.com/shop/products/123"
+        );
+
+        assert_eq!(
+            normalize(
+                "HTTPS://News.KAKAO.com:443/v/2024/?DMKW=abc&tab=1&fbclid=IwAR0x",
+                true
+            )
+            .unwrap(),
+            "https://news.kakao.com/v/2024?tab=1"
+        );
+        // Encoding of the parameters that stay is left alone.
+        assert_eq!(
+            normalize(
+                "https://search.naver.com/search.naver?query=%ED%95%9C+%EA%B8%80&utm_campaign=x",
+                true
+            )
+            .unwrap(),
+            "https://search.naver.com/search.naver?query=%ED%95%9C+%EA%B8%80"
+        );
+    }
+
+    #[test]
+    fn private_and_local_hosts_are_not_fetchable() {
+        assert!(is_fetchable("https://www.naver.com"));
+        assert!(is_fetchable("https://8.8.8.8/dns"));
+        for url in [
+            "http://localhost:8080",
+            "http://127.0.0.1/",
+            "http://192.168.0.10/admin",
+            "http://10.1.2.3",
+            "http://169.254.169.254/latest/meta-data",
+            "http://[::1]/",
+            "http://intranet/",
+        ] {
+            assert!(!is_fetchable(url), "{url}");
+        }
+    }
+
+    #[test]
+    fn extracts_links_from_chat_text() {
+        let text = "공유합니다 https://example.com/a/?utm_source=kakao, 그리고 (www.naver.com) \
+                    또 https://EXAMPLE.com/a#x. 끝";
+        assert_eq!(
+            extract_urls(text, true),
+            ["https://example.com/a", "https://www.naver.com"]
+        );
+        assert_eq!(
+            extract_urls(text, false),
+            [
+                "https://example.com/a?utm_source=kakao",
+                "https://www.naver.com",
+                "https://example.com/a"
+            ]
+        );
+        assert!(extract_urls("no links here", false).is_empty());
+    }
+
+    #[test]
+    fn links_end_where_korean_text_starts() {
+        assert_eq!(
+            extract_urls("여기https://example.com/a에서 봤어요", false),
+            ["https://example.com/a"]
+        );
+        assert_eq!(
+            extract_urls("「www.naver.com」참고, 『https://daum.net』도", false),
+            ["https://www.naver.com", "https://daum.net"]
+        );
+        assert_eq!(
+            extract_urls("https://a.com/x랑https://b.com/y를 비교", false),
+            ["https://a.com/x", "https://b.com/y"]
+        );
+        assert_eq!(
+            extract_urls("https://example.comㅋㅋㅋ", false),
+            ["https://example.com"]
+        );
+        // Korean after a separator belongs to the link.
+        assert_eq!(
+            extract_urls("https://ko.wikipedia.org/wiki/서울 참고", false),
+            [normalize("https://ko.wikipedia.org/wiki/서울", false).unwrap()]
+        );
+        assert_eq!(
+            extract_urls("http://한국.kr/소개", false),
+            [normalize("http://xn--3e0b707e.kr/소개", false).unwrap()]
+        );
+    }
+
+    #[test]
+    fn trailing_punctuation_is_dropped_but_balanced_brackets_are_kept() {
+        assert_eq!(
+            extract_urls(
+                "https://example.com/a?! 진짜~ https://example.com/b~",
+                false
+            ),
+            ["https://example.com/a", "https://example.com/b"]
+        );
+        assert_eq!(
+            extract_urls("(https://en.wikipedia.org/wiki/Rust_(language)).", false),
+            ["https://en.wikipedia.org/wiki/Rust_(language)"]
+        );
+        assert_eq!(
+            extract_urls("[https://example.com/a]…", false),
+            ["https://example.com/a"]
+        );
+        assert_eq!(
+            extract_urls("<https://example.com/q?a=1&b=2>", false),
+            ["https://example.com/q?a=1&b=2"]
+        );
+        assert!(extract_urls("https://, www.", false).is_empty());
+    }
+}
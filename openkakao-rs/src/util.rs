@@ -1,21 +1,48 @@
 use std::collections::HashMap;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::{Mutex, MutexGuard, OnceLock};
 
 use anyhow::Result;
-use chrono::{Datelike, Local, TimeZone};
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
 use owo_colors::OwoColorize;
 
-use crate::model::ChatMember;
+use crate::fuzzy;
+use crate::hangul::name_matches;
+use crate::humanize;
+use crate::names::{NameCacheLimits, NameResolver, NameResolverStats};
+use crate::rest::{EndpointFamily, RestObserver};
+use crate::retry::RetryPolicy;
+use crate::status::KakaoStatus;
+use crate::theme::theme;
+use crate::warnings::{warn, WarningCode};
+use crate::wrap::{char_width, display_width};
 
 pub static NO_COLOR: AtomicBool = AtomicBool::new(false);
+/// Set by `--verbose`: print raw server payloads next to interpreted errors.
+pub static VERBOSE: AtomicBool = AtomicBool::new(false);
+/// Set by `--show-bidi`: render bidi controls as `<U+XXXX>` instead of dropping them.
+pub static SHOW_BIDI: AtomicBool = AtomicBool::new(false);
+/// Set by `--no-cache`: commands neither read nor write the message cache
+/// on their own.
+pub static NO_CACHE: AtomicBool = AtomicBool::new(false);
+/// Exit status of a successful run. Commands that report an outcome through
+/// it (`chats --since-state`) set it; errors still exit 1.
+pub static EXIT_CODE: AtomicI32 = AtomicI32::new(0);
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const SEND_PREFIX: &str = "🤖 [Sent via openkakao]";
+/// Longest text sent as one message. The server's own limit is not
+/// documented, so this is a conservative cap; `send --split` goes around it.
+pub const MAX_MESSAGE_CHARS: usize = 4000;
 
 pub fn color_enabled() -> bool {
     !NO_COLOR.load(Ordering::Relaxed)
 }
 
+pub fn verbose() -> bool {
+    VERBOSE.load(Ordering::Relaxed)
+}
+
 pub fn format_outgoing_message(message: &str, no_prefix: bool) -> String {
     if no_prefix {
         message.to_string()
@@ -24,9 +51,57 @@ pub fn format_outgoing_message(message: &str, no_prefix: bool) -> String {
     }
 }
 
+/// Directional formatting characters that can reorder how a line is drawn.
+fn is_bidi_control(c: char) -> bool {
+    matches!(
+        c,
+        '\u{061C}' | '\u{200E}' | '\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}'
+    )
+}
+
+fn needs_sanitizing(c: char) -> bool {
+    (c.is_control() && c != '\n' && c != '\t') || is_bidi_control(c)
+}
+
+/// Make untrusted text (message bodies, nicknames, chat titles) safe to print
+/// to a terminal. C0 controls and DEL become caret notation (`ESC` → `^[`, so
+/// escape sequences show up as inert text), C1 controls become `\u{..}`, and
+/// bidi controls are dropped, or shown as `<U+XXXX>` with `show_bidi`.
+/// Newlines and tabs pass through. JSON and exports must not use this.
+pub fn sanitize_text(s: &str, show_bidi: bool) -> std::borrow::Cow<'_, str> {
+    if !s.chars().any(needs_sanitizing) {
+        return std::borrow::Cow::Borrowed(s);
+    }
+    let mut out = String::with_capacity(s.len() + 8);
+    for c in s.chars() {
+        match c {
+            '\n' | '\t' => out.push(c),
+            '\u{7f}' => out.push_str("^?"),
+            c if (c as u32) < 0x20 => {
+                out.push('^');
+                out.push(char::from(c as u8 + 0x40));
+            }
+            c if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c if is_bidi_control(c) => {
+                if show_bidi {
+                    out.push_str(&format!("<U+{:04X}>", c as u32));
+                }
+            }
+            c => out.push(c),
+        }
+    }
+    std::borrow::Cow::Owned(out)
+}
+
+/// [`sanitize_text`] with the `--show-bidi` setting, for human output.
+pub fn display_safe(s: &str) -> std::borrow::Cow<'_, str> {
+    sanitize_text(s, SHOW_BIDI.load(Ordering::Relaxed))
+}
+
 pub fn print_section_title(title: &str) {
+    let title = display_safe(title);
     if color_enabled() {
-        println!("{}", title.bold().cyan());
+        println!("{}", title.style(theme().title));
     } else {
         println!("{}", title);
     }
@@ -117,29 +192,20 @@ fn render_file_content(attachment: &Option<serde_json::Value>) -> String {
 
 fn render_multi_photo_content(attachment: &Option<serde_json::Value>) -> String {
     if let Some(att) = attachment {
-        let count = att
-            .get("kl")
-            .and_then(|v| v.as_array())
-            .map(|a| a.len())
-            .unwrap_or(0);
-        if count > 0 {
-            return format!("사진 {}장", count);
+        // Counted as `read` parses them, without malformed entries.
+        if let crate::model::MessageContent::MultiPhoto { photos } =
+            crate::model::MessageContent::parse(27, "", &att.to_string())
+        {
+            return format!("사진 {}장", photos.len());
         }
     }
     "멀티사진을 보냈습니다.".to_string()
 }
 
-fn format_bytes(bytes: u64) -> String {
-    if bytes == 0 {
-        return "0B".to_string();
-    }
-    if bytes < 1024 {
-        return format!("{}B", bytes);
-    }
-    if bytes < 1024 * 1024 {
-        return format!("{:.1}KB", bytes as f64 / 1024.0);
-    }
-    format!("{:.1}MB", bytes as f64 / (1024.0 * 1024.0))
+/// Human-readable size in binary units: `512B`, `1.5KiB`, `10.0MiB`,
+/// `2.0GiB` (see [`humanize::bytes`]).
+pub fn format_bytes(bytes: u64) -> String {
+    humanize::bytes(bytes, humanize::lang())
 }
 
 pub fn type_label(kind: &str) -> &'static str {
@@ -167,28 +233,259 @@ pub fn extract_chat_type(room_info: &bson::Document) -> String {
         .to_string()
 }
 
-pub fn truncate(s: &str, max_chars: usize) -> String {
-    if s.chars().count() <= max_chars {
-        s.to_string()
-    } else {
-        let mut truncated = s.chars().take(max_chars).collect::<String>();
-        truncated.push_str("...");
-        truncated
+/// `s` cut to `max_width` terminal columns, then `...`. Widths are display
+/// columns, so Hangul and emoji count double.
+pub fn truncate(s: &str, max_width: usize) -> String {
+    if display_width(s) <= max_width {
+        return s.to_string();
     }
+    let mut truncated = String::new();
+    let mut width = 0;
+    for c in s.chars() {
+        width += char_width(c);
+        if width > max_width {
+            break;
+        }
+        truncated.push(c);
+    }
+    truncated.truncate(truncated.trim_end().len());
+    truncated.push_str("...");
+    truncated
 }
 
+/// The start of a `--since` value, in any form [`parse_time_bound`] takes.
 pub fn parse_since_date(since: Option<&str>) -> Result<Option<i64>> {
-    let Some(s) = since else { return Ok(None) };
-    let date = chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d")
-        .map_err(|_| anyhow::anyhow!("Invalid --since date '{}'. Expected YYYY-MM-DD.", s))?;
-    let dt = date
-        .and_hms_opt(0, 0, 0)
-        .ok_or_else(|| anyhow::anyhow!("Invalid date"))?;
-    let local_dt = Local
-        .from_local_datetime(&dt)
-        .single()
-        .ok_or_else(|| anyhow::anyhow!("Ambiguous local time for {}", s))?;
-    Ok(Some(local_dt.timestamp()))
+    since
+        .map(|since| {
+            parse_time_bound(since, &Local::now())
+                .map(|(start, _)| start)
+                .map_err(|err| anyhow::anyhow!("--since: {err}"))
+        })
+        .transpose()
+}
+
+/// The span a `--since`/`--until` value names, as `[start, end)` epoch
+/// seconds in the time zone of `now`: the whole day for `YYYY-MM-DD`, the
+/// minute for `YYYY-MM-DDTHH:MM`, the instant that long before `now` for
+/// an age like `2d` or `12h`, and the whole days a day name like `어제`,
+/// `지난주` or `설날` covers (see [`crate::date_literal`]). A local time
+/// skipped by a DST change moves to the first one after it; a repeated one
+/// means its first occurrence.
+pub fn parse_time_bound<Tz: TimeZone>(input: &str, now: &DateTime<Tz>) -> Result<(i64, i64)> {
+    let trimmed = input.trim();
+    let invalid = || {
+        anyhow::anyhow!(
+            "Invalid time '{}'. Expected YYYY-MM-DD, YYYY-MM-DDTHH:MM, an age like 2d or 12h, \
+             or one of: {}.",
+            input,
+            crate::date_literal::supported()
+        )
+    };
+    let tz = now.timezone();
+    let epoch = |local: NaiveDateTime| -> Result<i64> {
+        (0..=2)
+            .find_map(|hours| {
+                tz.from_local_datetime(&(local + chrono::Duration::hours(hours)))
+                    .earliest()
+            })
+            .map(|dt| dt.timestamp())
+            .ok_or_else(invalid)
+    };
+    if let Ok(day) = NaiveDate::parse_from_str(trimmed, "%Y-%m-%d") {
+        let next = day.succ_opt().ok_or_else(invalid)?;
+        return Ok((
+            epoch(day.and_time(NaiveTime::MIN))?,
+            epoch(next.and_time(NaiveTime::MIN))?,
+        ));
+    }
+    for format in ["%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"] {
+        if let Ok(local) = NaiveDateTime::parse_from_str(trimmed, format) {
+            let start = epoch(local)?;
+            return Ok((start, start + 60));
+        }
+    }
+    if trimmed.starts_with(|c: char| c.is_ascii_digit()) && !trimmed.contains('-') {
+        if let Ok(secs) = parse_duration_secs(trimmed) {
+            let at = now.timestamp().saturating_sub(secs);
+            return Ok((at, at));
+        }
+    }
+    match crate::date_literal::parse(trimmed, now.date_naive())? {
+        Some(days) => Ok((
+            epoch(days.first.and_time(NaiveTime::MIN))?,
+            epoch(days.end.and_time(NaiveTime::MIN))?,
+        )),
+        None => Err(invalid()),
+    }
+}
+
+/// `--since`/`--until` as epoch bounds: from the start of the `--since`
+/// span up to the end of the `--until` one, so `--until 2024-05-12`
+/// includes that whole day.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimeWindow {
+    pub since: Option<i64>,
+    /// Exclusive.
+    pub until: Option<i64>,
+}
+
+impl TimeWindow {
+    pub fn parse(since: Option<&str>, until: Option<&str>) -> Result<Self> {
+        Self::parse_at(since, until, &Local::now())
+    }
+
+    pub fn parse_at<Tz: TimeZone>(
+        since: Option<&str>,
+        until: Option<&str>,
+        now: &DateTime<Tz>,
+    ) -> Result<Self> {
+        let bound = |value: Option<&str>, flag: &str| {
+            value
+                .map(|value| {
+                    parse_time_bound(value, now).map_err(|err| anyhow::anyhow!("{flag}: {err}"))
+                })
+                .transpose()
+        };
+        let window = Self {
+            since: bound(since, "--since")?.map(|(start, _)| start),
+            until: bound(until, "--until")?.map(|(_, end)| end),
+        };
+        if let (Some(start), Some(end)) = (window.since, window.until) {
+            if end <= start {
+                anyhow::bail!(
+                    "--until {} is not after --since {}; no message can fall between them.",
+                    until.unwrap_or_default(),
+                    since.unwrap_or_default()
+                );
+            }
+        }
+        Ok(window)
+    }
+
+    pub fn is_bounded(&self) -> bool {
+        self.since.is_some() || self.until.is_some()
+    }
+
+    pub fn contains(&self, send_at: i64) -> bool {
+        self.since.is_none_or(|since| send_at >= since)
+            && self.until.is_none_or(|until| send_at < until)
+    }
+}
+
+/// Parse a date range like `2024-01..2024-02` or `2024-01-05..2024-01-20`
+/// into local `[start, end)` epoch seconds. Month bounds cover the whole
+/// month; a single value without `..` is a range of one month or day.
+pub fn parse_date_range(input: &str) -> Result<(i64, i64)> {
+    let (from, to) = input.split_once("..").unwrap_or((input, input));
+    let start = parse_range_bound(from.trim(), input)?.0;
+    let end = parse_range_bound(to.trim(), input)?.1;
+    if end <= start {
+        anyhow::bail!("Invalid range '{}': end is before start.", input);
+    }
+    Ok((start, end))
+}
+
+/// `[first day, day after last day)` of a `YYYY-MM` or `YYYY-MM-DD` bound.
+fn parse_range_bound(bound: &str, input: &str) -> Result<(i64, i64)> {
+    let invalid = || {
+        anyhow::anyhow!(
+            "Invalid range '{}'. Expected YYYY-MM..YYYY-MM or YYYY-MM-DD..YYYY-MM-DD.",
+            input
+        )
+    };
+    let (first, next) = if let Ok(day) = chrono::NaiveDate::parse_from_str(bound, "%Y-%m-%d") {
+        (day, day.succ_opt().ok_or_else(invalid)?)
+    } else {
+        let first = chrono::NaiveDate::parse_from_str(&format!("{}-01", bound), "%Y-%m-%d")
+            .map_err(|_| invalid())?;
+        let next = if first.month() == 12 {
+            chrono::NaiveDate::from_ymd_opt(first.year() + 1, 1, 1)
+        } else {
+            chrono::NaiveDate::from_ymd_opt(first.year(), first.month() + 1, 1)
+        }
+        .ok_or_else(invalid)?;
+        (first, next)
+    };
+    let to_epoch = |date: chrono::NaiveDate| -> Result<i64> {
+        let dt = date.and_hms_opt(0, 0, 0).ok_or_else(invalid)?;
+        Local
+            .from_local_datetime(&dt)
+            .earliest()
+            .map(|d| d.timestamp())
+            .ok_or_else(invalid)
+    };
+    Ok((to_epoch(first)?, to_epoch(next)?))
+}
+
+/// Percentage change from `before` to `after`, or `None` when `before` is 0.
+pub fn percent_change(before: f64, after: f64) -> Option<f64> {
+    if before == 0.0 {
+        None
+    } else {
+        Some((after - before) / before * 100.0)
+    }
+}
+
+/// Parse a duration like `90d`, `12h`, `2w`, `30m` or `45s` into seconds.
+/// A bare number is read as days.
+pub fn parse_duration_secs(input: &str) -> Result<i64> {
+    let trimmed = input.trim();
+    let (digits, unit) = match trimmed.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+        Some((idx, _)) => trimmed.split_at(idx),
+        None => (trimmed, "d"),
+    };
+    let value: i64 = digits.parse().map_err(|_| {
+        anyhow::anyhow!("Invalid duration '{}'. Expected e.g. 90d, 12h, 2w.", input)
+    })?;
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        "w" => 7 * 86_400,
+        _ => anyhow::bail!("Invalid duration unit in '{}'. Use s, m, h, d or w.", input),
+    };
+    Ok(value.saturating_mul(multiplier))
+}
+
+/// Render the age of `epoch` relative to `now` as e.g. `45s ago` or `3d ago`
+/// (`3일 전` under `--lang ko`); a time after `now` reads as just now.
+pub fn format_relative_age(epoch: i64, now: i64) -> String {
+    if epoch <= 0 {
+        return match humanize::lang() {
+            humanize::Lang::En => "unknown",
+            humanize::Lang::Ko => "알 수 없음",
+        }
+        .to_string();
+    }
+    humanize::ago((now - epoch).max(0), humanize::lang())
+}
+
+/// The `---- 2024-05-12 ----` lines between messages of different local
+/// days.
+#[derive(Debug, Default)]
+pub struct DaySeparator {
+    last: Option<NaiveDate>,
+}
+
+impl DaySeparator {
+    /// The line to print before a message sent at `epoch`, when it is the
+    /// first one or falls on another day than the one before.
+    pub fn next(&mut self, epoch: i64) -> Option<String> {
+        self.next_in(epoch, &Local)
+    }
+
+    fn next_in<Tz: TimeZone>(&mut self, epoch: i64, tz: &Tz) -> Option<String> {
+        if epoch <= 0 {
+            return None;
+        }
+        let day = tz.timestamp_opt(epoch, 0).single()?.date_naive();
+        if self.last == Some(day) {
+            return None;
+        }
+        self.last = Some(day);
+        Some(format!("---- {day} ----"))
+    }
 }
 
 pub fn format_time(epoch: i64) -> String {
@@ -226,73 +523,187 @@ pub fn build_member_name_map_from_bson(members: &[bson::Bson]) -> HashMap<i64, S
     map
 }
 
-pub fn member_name_map(members: &[ChatMember], my_user_id: i64) -> HashMap<i64, String> {
-    let mut out = HashMap::new();
-    for m in members {
-        out.insert(m.user_id, m.display_name());
+/// How many near misses a failed name search suggests.
+const FUZZY_SUGGESTIONS: usize = 5;
+
+/// A `--search` value, plus whether `--fuzzy` may pick a near miss.
+#[derive(Debug, Clone)]
+pub struct NameQuery {
+    pub text: String,
+    pub fuzzy: bool,
+}
+
+impl NameQuery {
+    pub fn from_args(search: Option<String>, fuzzy: bool) -> Option<Self> {
+        search.map(|text| Self { text, fuzzy })
     }
-    out.insert(my_user_id, "Me".to_string());
-    out
 }
 
+/// Keep the items whose names match `query` (substring or 초성). With no
+/// hits, print the closest candidates to stderr; with `--fuzzy`, keep the
+/// best one instead when it clearly beats the rest.
+pub fn retain_name_matches<T, I, N>(items: &mut Vec<T>, query: &NameQuery, id: I, names: N)
+where
+    I: Fn(&T) -> i64,
+    N: Fn(&T) -> Vec<String>,
+{
+    if items
+        .iter()
+        .any(|item| names(item).iter().any(|n| name_matches(n, &query.text)))
+    {
+        items.retain(|item| names(item).iter().any(|n| name_matches(n, &query.text)));
+        return;
+    }
+
+    let candidates: Vec<Vec<String>> = items.iter().map(&names).collect();
+    let ranked = fuzzy::rank(&candidates, &query.text);
+    let label = |idx: usize| {
+        candidates[idx]
+            .iter()
+            .find(|n| !n.is_empty())
+            .cloned()
+            .unwrap_or_default()
+    };
+    if query.fuzzy {
+        if let Some(best) = fuzzy::dominant(&ranked) {
+            eprintln!(
+                "[fuzzy] No exact match for '{}'; using '{}' ({}, {:.0}%)",
+                display_safe(&query.text),
+                display_safe(&label(best)),
+                id(&items[best]),
+                ranked[0].1 * 100.0
+            );
+            let chosen = items.swap_remove(best);
+            *items = vec![chosen];
+            return;
+        }
+    }
+    if !ranked.is_empty() {
+        eprintln!(
+            "[search] No match for '{}'. Closest:",
+            display_safe(&query.text)
+        );
+        for (idx, score) in ranked.iter().take(FUZZY_SUGGESTIONS) {
+            eprintln!(
+                "  {} ({}) {:.0}%",
+                display_safe(&label(*idx)),
+                id(&items[*idx]),
+                score * 100.0
+            );
+        }
+        if !query.fuzzy {
+            eprintln!("  Re-run with --fuzzy to pick the closest match automatically.");
+        }
+    }
+    items.clear();
+}
+
+/// Narrowest a text column is shrunk to when a table is wider than the
+/// terminal; never narrower than its header.
+const MIN_SHRUNK_COLUMN: usize = 8;
+
 pub fn print_table(headers: &[&str], rows: Vec<Vec<String>>) {
-    let mut widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
-    for row in &rows {
-        for (idx, cell) in row.iter().enumerate() {
-            if idx >= widths.len() {
-                widths.push(cell.chars().count());
-            } else {
-                widths[idx] = widths[idx].max(cell.chars().count());
-            }
+    let rows: Vec<Vec<String>> = rows
+        .into_iter()
+        .map(|row| {
+            row.into_iter()
+                .map(|cell| display_safe(&cell).into_owned())
+                .collect()
+        })
+        .collect();
+    let lines = render_table(headers, &rows, crate::wrap::terminal_width());
+    for (idx, line) in lines.iter().enumerate() {
+        match idx {
+            0 if color_enabled() => println!("{}", line.style(theme().header)),
+            1 if color_enabled() => println!("{}", line.style(theme().separator)),
+            _ => println!("{line}"),
         }
     }
+}
 
-    if color_enabled() {
-        let header_line = headers
-            .iter()
-            .enumerate()
-            .map(|(idx, h)| format!("{:width$}", h.bold(), width = widths[idx]))
-            .collect::<Vec<_>>()
-            .join("  ");
-        println!("{header_line}");
-    } else {
-        let header_line = headers
+/// Header, separator and row lines of a table. Columns are measured in
+/// terminal columns; those holding only numbers (or `-` for none) are
+/// right-aligned. With `max_width`, the widest text columns are shrunk
+/// until the table fits, cutting their cells with `...`.
+fn render_table(headers: &[&str], rows: &[Vec<String>], max_width: Option<usize>) -> Vec<String> {
+    let columns = rows
+        .iter()
+        .map(Vec::len)
+        .chain([headers.len()])
+        .max()
+        .unwrap_or_default();
+    let cell = |row: &[String], idx: usize| row.get(idx).map_or("", String::as_str).to_string();
+    let header_row: Vec<String> = (0..columns)
+        .map(|idx| headers.get(idx).copied().unwrap_or_default().to_string())
+        .collect();
+    let mut widths: Vec<usize> = header_row.iter().map(|h| display_width(h)).collect();
+    let mut numeric = vec![false; columns];
+    for (idx, (width, numeric)) in widths.iter_mut().zip(&mut numeric).enumerate() {
+        let cells: Vec<String> = rows.iter().map(|row| cell(row, idx)).collect();
+        *width = cells
             .iter()
-            .enumerate()
-            .map(|(idx, h)| format!("{:width$}", h, width = widths[idx]))
-            .collect::<Vec<_>>()
-            .join("  ");
-        println!("{header_line}");
+            .map(|c| display_width(c))
+            .fold(*width, usize::max);
+        *numeric = cells.iter().any(|c| is_number(c))
+            && cells
+                .iter()
+                .all(|c| is_number(c) || matches!(c.as_str(), "" | "-"));
     }
 
-    let separator = widths
-        .iter()
-        .map(|w| "-".repeat(*w))
-        .collect::<Vec<_>>()
-        .join("  ");
-    if color_enabled() {
-        println!("{}", separator.dimmed());
-    } else {
-        println!("{separator}");
+    if let Some(max_width) = max_width {
+        let floors: Vec<usize> = header_row
+            .iter()
+            .map(|h| display_width(h).max(MIN_SHRUNK_COLUMN))
+            .collect();
+        let mut total = widths.iter().sum::<usize>() + 2 * columns.saturating_sub(1);
+        while total > max_width {
+            let Some(idx) = (0..columns)
+                .filter(|&idx| !numeric[idx] && widths[idx] > floors[idx])
+                .max_by_key(|&idx| (widths[idx], std::cmp::Reverse(idx)))
+            else {
+                break;
+            };
+            widths[idx] -= 1;
+            total -= 1;
+        }
     }
 
-    for row in rows {
-        let line = row
+    let line = |cells: &[String]| {
+        cells
             .iter()
             .enumerate()
-            .map(|(idx, cell)| format!("{:width$}", cell, width = widths[idx]))
+            .map(|(idx, text)| {
+                let text = if display_width(text) > widths[idx] {
+                    truncate(text, widths[idx].saturating_sub(3))
+                } else {
+                    text.clone()
+                };
+                let gap = " ".repeat(widths[idx].saturating_sub(display_width(&text)));
+                if numeric[idx] {
+                    format!("{gap}{text}")
+                } else {
+                    format!("{text}{gap}")
+                }
+            })
             .collect::<Vec<_>>()
-            .join("  ");
-        println!("{line}");
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    let separator: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+    let mut lines = vec![line(&header_row), separator.join("  ")];
+    for row in rows {
+        let cells: Vec<String> = (0..columns).map(|idx| cell(row, idx)).collect();
+        lines.push(line(&cells));
     }
+    lines
 }
 
-pub fn confirm() -> Result<bool> {
-    use std::io::{self, Write};
-    io::stderr().flush()?;
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
-    Ok(input.trim().eq_ignore_ascii_case("y"))
+/// A count or id: digits with an optional sign and thousands separators.
+fn is_number(cell: &str) -> bool {
+    let digits = cell.strip_prefix('-').unwrap_or(cell);
+    digits.starts_with(|c: char| c.is_ascii_digit())
+        && digits.chars().all(|c| c.is_ascii_digit() || c == ',')
 }
 
 pub fn require_permission(enabled: bool, purpose: &str, hint: &str) -> Result<()> {
@@ -307,53 +718,150 @@ pub fn validate_outbound_message(message: &str) -> Result<()> {
     if message.trim().is_empty() {
         anyhow::bail!("refusing to send an empty or whitespace-only message");
     }
+    let chars = message.chars().count();
+    if chars > MAX_MESSAGE_CHARS {
+        anyhow::bail!(
+            "message is {} characters, over the {} a single message allows; shorten it or use `send --split`",
+            chars,
+            MAX_MESSAGE_CHARS
+        );
+    }
     Ok(())
 }
 
-pub fn print_loco_error_hint(status: i64) {
-    match status {
-        -950 => {
-            eprintln!("  Error: Authentication rejected (-950).");
-            eprintln!("  Likely causes:");
-            eprintln!("    1. Token expired: open KakaoTalk, browse chats, then 'openkakao-rs login --save'.");
-            eprintln!("    2. Session conflict: another client may have invalidated this session.");
-            eprintln!("  Will attempt auto-refresh if possible.");
-        }
-        -999 => {
-            eprintln!("  Error: Upgrade required (-999).");
-            eprintln!(
-                "  The app version string is too old. Update KakaoTalk and re-extract credentials."
-            );
-        }
-        -400 => {
-            eprintln!("  Error: Bad request (-400). Missing required parameter.");
-        }
-        -300 => {
-            eprintln!("  Error: Unsupported request or device mismatch (-300).");
-            eprintln!(
-                "  This method/body combination is likely not valid for the macOS LOCO surface."
-            );
+/// Cut `message` into parts of at most `max_chars` characters, preferring
+/// line breaks, then spaces. The separator a cut lands on is dropped, and so
+/// is a part left holding only whitespace by a run of line breaks; a hard
+/// cut never separates an emoji from its modifiers or joiners, or a letter
+/// from its combining marks.
+pub fn split_message(message: &str, max_chars: usize) -> Vec<String> {
+    let max_chars = max_chars.max(1);
+    let mut parts = Vec::new();
+    let mut rest = message;
+    while rest.chars().count() > max_chars {
+        let limit = rest
+            .char_indices()
+            .nth(max_chars)
+            .map_or(rest.len(), |(i, _)| i);
+        let window = &rest[..limit];
+        let (end, skip) = if let Some(i) = window.rfind('\n').filter(|&i| i > 0) {
+            (i, 1)
+        } else if let Some((i, c)) = window
+            .char_indices()
+            .rev()
+            .find(|&(i, c)| i > 0 && c.is_whitespace())
+        {
+            (i, c.len_utf8())
+        } else {
+            (cluster_safe_cut(rest, limit), 0)
+        };
+        if !rest[..end].trim().is_empty() {
+            parts.push(rest[..end].to_string());
         }
-        -203 => {
-            eprintln!("  Error: Missing required parameter (-203).");
-            eprintln!(
-                "  This LOCO method likely exists, but the required body shape is incomplete."
-            );
-        }
-        -301 => {
-            eprintln!("  Error: Account restricted (-301). Your account may be under review.");
-            eprintln!("  WARNING: Do not retry aggressively. Wait and check KakaoTalk app.");
-        }
-        -1 => {
-            eprintln!("  Error: Connection failed or no status in response.");
-            eprintln!("  Run 'openkakao-rs doctor --loco' to check connectivity.");
-        }
-        _ => {
-            eprintln!(
-                "  Unknown LOCO error (status={}). Run 'openkakao-rs doctor' for diagnostics.",
-                status
-            );
+        rest = &rest[end + skip..];
+    }
+    if !rest.trim().is_empty() || parts.is_empty() {
+        parts.push(rest.to_string());
+    }
+    parts
+}
+
+/// Move a byte cut back until it does not fall inside an emoji sequence or
+/// before a combining mark. Falls back to `cut` when the whole window is one
+/// cluster.
+fn cluster_safe_cut(text: &str, cut: usize) -> usize {
+    let joins = |c: char| {
+        matches!(
+            c,
+            '\u{200D}' | '\u{FE0E}' | '\u{FE0F}' | '\u{1F3FB}'..='\u{1F3FF}' | '\u{0300}'..='\u{036F}'
+                | '\u{E0020}'..='\u{E007F}'
+        )
+    };
+    let mut end = cut;
+    while end > 0 {
+        let next = text[end..].chars().next();
+        let prev = text[..end].chars().next_back();
+        if !next.is_some_and(joins) && prev != Some('\u{200D}') {
+            return end;
         }
+        end -= prev.map_or(1, char::len_utf8);
+    }
+    cut
+}
+
+pub fn print_loco_error_hint(status: i64) {
+    let kind = KakaoStatus::from_code(status);
+    if kind.is_known() {
+        eprintln!("  Error: {} ({}).", kind.explanation(), status);
+        eprintln!("  {}", kind.suggestion());
+    } else {
+        eprintln!(
+            "  Unknown LOCO error (status={}). Run 'openkakao-rs doctor' for diagnostics.",
+            status
+        );
+    }
+    if kind == KakaoStatus::AuthRejected {
+        eprintln!("  Will attempt auto-refresh if possible.");
+    }
+}
+
+/// Under `--verbose`, print the raw server payload behind a status error.
+pub fn print_verbose_payload(label: &str, status: i64, payload: &serde_json::Value) {
+    if !verbose() {
+        return;
+    }
+    eprintln!(
+        "[verbose] {} status {} ({:?}): {}",
+        label,
+        status,
+        KakaoStatus::from_code(status),
+        serde_json::to_string_pretty(payload).unwrap_or_default()
+    );
+}
+
+/// Routes what the REST client observes to the CLI: restriction signs into
+/// the health history, scope rejections as coded warnings, retries and
+/// recoveries as stderr notes, raw payloads under `--verbose`.
+pub struct CliRestObserver;
+
+impl RestObserver for CliRestObserver {
+    fn status(&self, source: &str, status: i64) {
+        crate::health::observe_status(source, status);
+    }
+
+    fn error_payload(&self, url: &str, status: i64, payload: &serde_json::Value) {
+        print_verbose_payload(url, status, payload);
+    }
+
+    fn scope_rejected(&self, family: EndpointFamily) {
+        warn(
+            WarningCode::TokenScope,
+            format!(
+                "Token was rejected by {}: {}",
+                family.label(),
+                family.unavailable_hint()
+            ),
+        );
+    }
+
+    fn retry(
+        &self,
+        method: &str,
+        endpoint: &str,
+        error: &anyhow::Error,
+        attempt: u32,
+        retries: u32,
+        wait: std::time::Duration,
+    ) {
+        eprintln!("[rest] {method} {endpoint}: {error}; retry {attempt}/{retries} in {wait:?}");
+    }
+
+    fn token_recovering(&self) {
+        eprintln!("[auth/rest] Saved token rejected; recovering.");
+    }
+
+    fn mirror_failed(&self, base: &str, error: &anyhow::Error) {
+        eprintln!("[endpoint] {base} unreachable ({error:#}); trying next mirror");
     }
 }
 
@@ -384,6 +892,21 @@ pub fn get_bson_i64(doc: &bson::Document, keys: &[&str]) -> i64 {
     0
 }
 
+/// A timestamp field as Unix seconds, whichever epoch the packet used; 0
+/// when missing or implausible.
+pub fn get_bson_timestamp(doc: &bson::Document, keys: &[&str]) -> i64 {
+    for k in keys {
+        let raw = match doc.get(k) {
+            Some(bson::Bson::Int64(v)) => *v as f64,
+            Some(bson::Bson::Int32(v)) => *v as f64,
+            Some(bson::Bson::Double(v)) => *v,
+            _ => continue,
+        };
+        return crate::timestamp::unix_secs(raw);
+    }
+    0
+}
+
 pub fn get_bson_i32(doc: &bson::Document, keys: &[&str]) -> i32 {
     for k in keys {
         if let Ok(v) = doc.get_i32(k) {
@@ -468,19 +991,276 @@ pub fn get_rest_client() -> Result<crate::rest::KakaoRestClient> {
     crate::auth_flow::get_rest_ready_client()
 }
 
+/// Print a command's JSON output in the versioned envelope, projected to
+/// `--fields` when given.
 pub fn output_json<T: serde::Serialize>(data: &T) -> Result<()> {
-    println!("{}", serde_json::to_string_pretty(data)?);
+    let fields = crate::output::json_fields();
+    let command = crate::schema::command();
+    if fields.is_empty() {
+        let envelope = crate::schema::Envelope::new(command, data);
+        println!("{}", serde_json::to_string_pretty(&envelope)?);
+    } else {
+        let projected = crate::output::project(&serde_json::to_value(data)?, fields)?;
+        let envelope = crate::schema::Envelope::new(command, projected);
+        println!("{}", serde_json::to_string_pretty(&envelope)?);
+    }
     Ok(())
 }
 
+/// The local message cache for reads and exports to merge into, or `None`
+/// with `--no-cache` or when it cannot be opened (reported as a warning).
+pub fn message_cache() -> Option<crate::message_db::MessageDb> {
+    if NO_CACHE.load(Ordering::Relaxed) {
+        return None;
+    }
+    match crate::message_db::MessageDb::open() {
+        Ok(db) => Some(db),
+        Err(err) => {
+            warn(
+                WarningCode::CacheUnavailable,
+                format!("Could not open message cache: {err:#}"),
+            );
+            None
+        }
+    }
+}
+
 pub fn get_creds() -> Result<crate::model::KakaoCredentials> {
     crate::auth_flow::resolve_base_credentials()
 }
 
+/// What is wrong with KakaoTalk's `Cache.db` as a credential source, if it
+/// exists and its newest kakao.com request is older than
+/// [`crate::auth::CACHE_STALE_AFTER_SECS`].
+pub fn stale_cache_note(freshness: &crate::auth::CacheFreshness) -> Option<String> {
+    let now = chrono::Utc::now();
+    if !freshness.is_stale(now, crate::auth::CACHE_STALE_AFTER_SECS) {
+        return None;
+    }
+    let age = match freshness.last_updated() {
+        Some(at) => format!(
+            "last updated {}",
+            format_relative_age(at.timestamp(), now.timestamp())
+        ),
+        None => "has no dated kakao.com requests".to_string(),
+    };
+    Some(format!(
+        "KakaoTalk's cache {age}; open KakaoTalk to refresh it before long operations"
+    ))
+}
+
+/// Pre-flight for long jobs: warn when the cache that credential recovery
+/// extracts from is stale. Silent when there is no cache to read.
+pub fn warn_if_cache_stale() {
+    if let Ok(Some(freshness)) = crate::auth::cache_freshness() {
+        if let Some(note) = stale_cache_note(&freshness) {
+            warn(WarningCode::StaleCache, note);
+        }
+    }
+}
+
+/// Launch KakaoTalk, or bring it to the front, so it makes requests that
+/// refresh its cache.
+pub fn nudge_kakaotalk() -> Result<()> {
+    if !cfg!(target_os = "macos") {
+        anyhow::bail!("--nudge opens the KakaoTalk Mac app and works on macOS only");
+    }
+    let status = std::process::Command::new("open")
+        .args(["-a", "KakaoTalk"])
+        .status()?;
+    if !status.success() {
+        anyhow::bail!("`open -a KakaoTalk` failed ({status})");
+    }
+    Ok(())
+}
+
+static RETRY_POLICY: OnceLock<RetryPolicy> = OnceLock::new();
+
+/// Set once from `--retries` at startup.
+pub fn set_retry_policy(policy: RetryPolicy) {
+    let _ = RETRY_POLICY.set(policy);
+}
+
+/// Policy for LOCO connects and REST 429/5xx answers in this run; the
+/// library default unless `--retries` was given.
+pub fn retry_policy() -> RetryPolicy {
+    RETRY_POLICY.get().copied().unwrap_or_default()
+}
+
+static NAME_RESOLVER: OnceLock<Mutex<NameResolver>> = OnceLock::new();
+static NAME_CACHE_LIMITS: OnceLock<NameCacheLimits> = OnceLock::new();
+
+/// Bounds for the name cache created on first use of [`name_resolver`].
+pub fn set_name_cache_limits(limits: NameCacheLimits) {
+    let _ = NAME_CACHE_LIMITS.set(limits);
+}
+
+/// Process-wide name cache shared by every command in this run.
+pub fn name_resolver(my_user_id: i64) -> MutexGuard<'static, NameResolver> {
+    NAME_RESOLVER
+        .get_or_init(|| {
+            let limits = NAME_CACHE_LIMITS.get().copied().unwrap_or_default();
+            Mutex::new(NameResolver::with_limits(my_user_id, limits))
+        })
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+pub fn name_resolver_stats() -> Option<NameResolverStats> {
+    NAME_RESOLVER.get().map(|resolver| {
+        resolver
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .stats()
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn tables_align_hangul_and_emoji_by_display_width() {
+        let rows = vec![
+            vec!["Alice".into(), "hi".into(), "3".into(), "101".into()],
+            vec![
+                "김철수".into(),
+                "안녕하세요 👋".into(),
+                "12".into(),
+                "20".into(),
+            ],
+            vec!["🍕 Pizza".into(), "-".into(), "-".into(), "3".into()],
+        ];
+        assert_eq!(
+            render_table(&["Name", "Status", "Unread", "Chat ID"], &rows, None),
+            [
+                "Name      Status         Unread  Chat ID",
+                "--------  -------------  ------  -------",
+                "Alice     hi                  3      101",
+                "김철수    안녕하세요 👋      12       20",
+                "🍕 Pizza  -                   -        3",
+            ]
+        );
+    }
+
+    #[test]
+    fn tables_wider_than_the_terminal_shrink_text_columns() {
+        let rows = vec![
+            vec![
+                "홍길동".into(),
+                "오늘도 좋은 하루 되세요 😊".into(),
+                "405979308".into(),
+            ],
+            vec!["Bob".into(), "busy".into(), "7".into()],
+        ];
+        let lines = render_table(&["Name", "Status", "User ID"], &rows, Some(30));
+        assert_eq!(
+            lines,
+            [
+                "Name    Status         User ID",
+                "------  -----------  ---------",
+                "홍길동  오늘도...    405979308",
+                "Bob     busy                 7",
+            ]
+        );
+        assert!(lines.iter().all(|line| display_width(line) <= 30));
+        // Wide enough: nothing is cut.
+        assert!(render_table(&["Name", "Status", "User ID"], &rows, Some(80))[2].contains("😊"));
+    }
+
+    #[test]
+    fn truncate_counts_display_columns() {
+        assert_eq!(truncate("abcdef", 3), "abc...");
+        assert_eq!(truncate("abc", 3), "abc");
+        assert_eq!(truncate("김철수입니다", 6), "김철수...");
+        assert_eq!(truncate("김철수입니다", 7), "김철수...");
+        assert_eq!(truncate("hi 👋 there", 4), "hi...");
+    }
+
+    #[test]
+    fn split_message_prefers_line_breaks_then_spaces() {
+        assert_eq!(split_message("short", 10), vec!["short"]);
+        assert_eq!(split_message("", 10), vec![""]);
+        assert_eq!(
+            split_message("first line\nsecond line", 15),
+            vec!["first line", "second line"]
+        );
+        assert_eq!(
+            split_message("one two three four", 9),
+            vec!["one two", "three", "four"]
+        );
+        // No separator: a hard cut.
+        assert_eq!(split_message("abcdefgh", 3), vec!["abc", "def", "gh"]);
+        // Newlines inside a part are kept.
+        assert_eq!(split_message("a\nb\nccc", 4), vec!["a\nb", "ccc"]);
+        // A run of line breaks at a cut leaves no blank part behind.
+        assert_eq!(split_message("aaa\n\n\nbbb", 4), vec!["aaa", "bbb"]);
+        assert_eq!(split_message("aaa\n\n", 4), vec!["aaa"]);
+    }
+
+    #[test]
+    fn split_message_counts_characters_and_keeps_clusters() {
+        let parts = split_message("가나다라마바사", 3);
+        assert_eq!(parts, vec!["가나다", "라마바", "사"]);
+        // 👍🏽 is two chars; the cut must not strand the skin tone.
+        assert_eq!(split_message("ab👍🏽cd", 3), vec!["ab", "👍🏽c", "d"]);
+        // A ZWJ family stays whole when it fits a part.
+        let family = "👨\u{200D}👩\u{200D}👧";
+        let parts = split_message(&format!("x{family}"), 5);
+        assert_eq!(parts, vec!["x".to_string(), family.to_string()]);
+        for part in split_message(&"😀".repeat(10), 4) {
+            assert!(part.chars().count() <= 4);
+        }
+    }
+
+    #[test]
+    fn outbound_message_length_is_capped() {
+        let max = "가".repeat(MAX_MESSAGE_CHARS);
+        assert!(validate_outbound_message(&max).is_ok());
+        let err = validate_outbound_message(&format!("{max}!")).unwrap_err();
+        assert!(err.to_string().contains("--split"), "{err}");
+        assert!(validate_outbound_message("line one\nline two 🎉").is_ok());
+    }
+
+    #[test]
+    fn format_bytes_picks_binary_units() {
+        assert_eq!(format_bytes(0), "0B");
+        assert_eq!(format_bytes(1023), "1023B");
+        assert_eq!(format_bytes(1536), "1.5KiB");
+        assert_eq!(format_bytes(10 * 1024 * 1024), "10.0MiB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024 / 2), "1.5GiB");
+    }
+
+    #[test]
+    fn sanitize_neutralizes_escape_sequences() {
+        let hostile = "hi\x1b[2J\x1b]0;pwned\x07there\r";
+        let clean = sanitize_text(hostile, false);
+        assert_eq!(clean, "hi^[[2J^[]0;pwned^Gthere^M");
+        assert!(!clean.contains('\x1b'));
+        assert_eq!(sanitize_text("a\u{9b}31mb\x7f", false), "a\\u{9b}31mb^?");
+    }
+
+    #[test]
+    fn sanitize_handles_bidi_overrides() {
+        let spoof = "invoice\u{202E}fdp.exe";
+        assert_eq!(sanitize_text(spoof, false), "invoicefdp.exe");
+        assert_eq!(sanitize_text(spoof, true), "invoice<U+202E>fdp.exe");
+        assert_eq!(
+            sanitize_text("\u{2067}x\u{2069}", true),
+            "<U+2067>x<U+2069>"
+        );
+    }
+
+    #[test]
+    fn sanitize_keeps_ordinary_text() {
+        let text = "안녕하세요 👋\n\tשלום café";
+        assert!(matches!(
+            sanitize_text(text, false),
+            std::borrow::Cow::Borrowed(_)
+        ));
+        assert_eq!(sanitize_text(text, true), text);
+    }
+
     #[test]
     fn test_mask_token_short() {
         assert_eq!(mask_token("abc"), "***");
@@ -504,4 +1284,184 @@ mod tests {
     fn test_mask_token_empty() {
         assert_eq!(mask_token(""), "");
     }
+
+    #[test]
+    fn test_parse_date_range_months_cover_whole_months() {
+        let (start, end) = parse_date_range("2024-01..2024-02").unwrap();
+        let (jan_start, _) = parse_date_range("2024-01-01").unwrap();
+        let (_, feb_end) = parse_date_range("2024-02-29").unwrap();
+        assert_eq!(start, jan_start);
+        assert_eq!(end, feb_end);
+
+        let (dec_start, dec_end) = parse_date_range("2023-12").unwrap();
+        assert_eq!(dec_end, jan_start);
+        assert!(dec_start < dec_end);
+    }
+
+    #[test]
+    fn test_parse_date_range_rejects_bad_input() {
+        assert!(parse_date_range("2024-02..2024-01").is_err());
+        assert!(parse_date_range("January").is_err());
+        assert!(parse_date_range("2024-13").is_err());
+    }
+
+    /// 2024-05-13 12:00 in Seoul.
+    fn kst_noon() -> DateTime<chrono::FixedOffset> {
+        chrono::FixedOffset::east_opt(9 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2024, 5, 13, 12, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn time_bounds_are_local_days_minutes_or_ages() {
+        let now = kst_noon();
+        assert_eq!(
+            parse_time_bound("2024-05-12", &now).unwrap(),
+            (1_715_439_600, 1_715_526_000)
+        );
+        assert_eq!(
+            parse_time_bound("2024-05-12T21:30", &now).unwrap(),
+            (1_715_517_000, 1_715_517_060)
+        );
+        assert_eq!(
+            parse_time_bound(" 2024-05-12 21:30 ", &now).unwrap().0,
+            1_715_517_000
+        );
+        // The same date starts nine hours later in UTC.
+        let utc = chrono::Utc.with_ymd_and_hms(2024, 5, 13, 3, 0, 0).unwrap();
+        assert_eq!(
+            parse_time_bound("2024-05-12", &utc).unwrap().0,
+            1_715_472_000
+        );
+
+        let two_days_ago = now.timestamp() - 2 * 86_400;
+        assert_eq!(
+            parse_time_bound("2d", &now).unwrap(),
+            (two_days_ago, two_days_ago)
+        );
+        assert_eq!(
+            parse_time_bound("12h", &now).unwrap().0,
+            now.timestamp() - 12 * 3600
+        );
+        for bad in [
+            "",
+            "tomorrow",
+            "2024-13-01",
+            "2024-05-12T25:00",
+            "-2d",
+            "2y",
+        ] {
+            assert!(parse_time_bound(bad, &now).is_err(), "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn time_windows_include_the_whole_until_day_and_reject_backwards_ranges() {
+        let now = kst_noon();
+        let day = TimeWindow::parse_at(Some("2024-05-12"), Some("2024-05-12"), &now).unwrap();
+        assert_eq!(
+            day,
+            TimeWindow {
+                since: Some(1_715_439_600),
+                until: Some(1_715_526_000),
+            }
+        );
+        assert!(day.contains(1_715_439_600));
+        assert!(day.contains(1_715_525_999));
+        assert!(!day.contains(1_715_526_000));
+        assert!(!day.contains(1_715_439_599));
+
+        let err = TimeWindow::parse_at(Some("2024-05-12"), Some("2024-05-11"), &now).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("--until 2024-05-11 is not after --since 2024-05-12"),
+            "{err}"
+        );
+        // --since 1d starts after --until 2d ends.
+        assert!(TimeWindow::parse_at(Some("1d"), Some("2d"), &now).is_err());
+        let err = TimeWindow::parse_at(None, Some("soon"), &now).unwrap_err();
+        assert!(
+            err.to_string().starts_with("--until: Invalid time 'soon'"),
+            "{err}"
+        );
+        assert!(!TimeWindow::default().is_bounded());
+        assert!(TimeWindow::default().contains(0));
+    }
+
+    #[test]
+    fn day_names_cover_whole_local_days() {
+        let now = kst_noon();
+        let may_12 = (1_715_439_600, 1_715_526_000);
+        assert_eq!(parse_time_bound("어제", &now).unwrap(), may_12);
+        assert_eq!(parse_time_bound(" Yesterday ", &now).unwrap(), may_12);
+        // A day name starts at midnight; the age 3d is 72 hours ago.
+        assert_eq!(
+            parse_time_bound("3일 전", &now).unwrap().0,
+            may_12.0 - 2 * 86_400
+        );
+        assert_eq!(
+            parse_time_bound("3d", &now).unwrap().0,
+            now.timestamp() - 3 * 86_400
+        );
+        let window = TimeWindow::parse_at(Some("지난주"), Some("어제"), &now).unwrap();
+        assert_eq!(window.since, Some(may_12.0 - 6 * 86_400));
+        assert_eq!(window.until, Some(may_12.1));
+        #[cfg(feature = "holidays")]
+        assert_eq!(
+            parse_time_bound("어린이날", &now).unwrap().0,
+            may_12.0 - 7 * 86_400
+        );
+
+        let err = parse_time_bound("내일", &now).unwrap_err().to_string();
+        assert!(err.starts_with("Invalid time '내일'"), "{err}");
+        for name in ["오늘", "N일전", "last week", "last month"] {
+            assert!(err.contains(name), "{err}");
+        }
+        assert!(parse_since_date(Some("그제")).unwrap().is_some());
+        let err = parse_since_date(Some("someday")).unwrap_err().to_string();
+        assert!(err.starts_with("--since: Invalid time 'someday'"), "{err}");
+    }
+
+    #[test]
+    fn day_separators_follow_the_local_date() {
+        let kst = chrono::FixedOffset::east_opt(9 * 3600).unwrap();
+        let mut days = DaySeparator::default();
+        assert_eq!(
+            days.next_in(1_715_517_000, &kst).as_deref(),
+            Some("---- 2024-05-12 ----")
+        );
+        assert_eq!(days.next_in(1_715_525_940, &kst), None);
+        // Just past midnight in Seoul, still the 12th in UTC.
+        assert_eq!(
+            days.next_in(1_715_526_060, &kst).as_deref(),
+            Some("---- 2024-05-13 ----")
+        );
+        assert_eq!(days.next_in(0, &kst), None);
+    }
+
+    #[test]
+    fn test_percent_change() {
+        assert_eq!(percent_change(10.0, 5.0), Some(-50.0));
+        assert_eq!(percent_change(4.0, 5.0), Some(25.0));
+        assert_eq!(percent_change(0.0, 5.0), None);
+    }
+
+    #[test]
+    fn test_parse_duration_secs_units() {
+        assert_eq!(parse_duration_secs("90d").unwrap(), 90 * 86_400);
+        assert_eq!(parse_duration_secs("2w").unwrap(), 14 * 86_400);
+        assert_eq!(parse_duration_secs("12h").unwrap(), 12 * 3600);
+        assert_eq!(parse_duration_secs("30").unwrap(), 30 * 86_400);
+        assert!(parse_duration_secs("d").is_err());
+        assert!(parse_duration_secs("5y").is_err());
+    }
+
+    #[test]
+    fn test_format_relative_age() {
+        let now = 1_700_000_000;
+        assert_eq!(format_relative_age(now - 30, now), "30s ago");
+        assert_eq!(format_relative_age(now - 3 * 86_400, now), "3d ago");
+        assert_eq!(format_relative_age(0, now), "unknown");
+    }
 }
@@ -0,0 +1,189 @@
+//! Tokens the server accepted recently, so choosing among KakaoTalk's cached
+//! credentials does not check every candidate again on each run.
+//!
+//! Kept in `verified-tokens.json` beside the credentials files, keyed by a
+//! hash of the token. The tokens themselves are never written here.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use openkakao_rs::rest::TokenScope;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::scrap_cache::is_fresh;
+use crate::warnings::{warn, WarningCode};
+
+/// How long a token the server accepted is trusted without a new check.
+pub const DEFAULT_TTL_SECS: u64 = 10 * 60;
+
+/// Overrides [`DEFAULT_TTL_SECS`], in seconds; `0` turns the cache off.
+pub const TTL_ENV: &str = "OPENKAKAO_VERIFY_TTL";
+
+/// When a token was last accepted, and whether pilsner took it too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Verified {
+    /// Unix time of the check.
+    pub verified_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pilsner: Option<bool>,
+}
+
+impl Verified {
+    /// The scope the check found: katalk accepted the token.
+    pub fn scope(&self) -> TokenScope {
+        TokenScope {
+            katalk: Some(true),
+            pilsner: self.pilsner,
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct VerifiedTokens {
+    #[serde(default)]
+    tokens: BTreeMap<String, Verified>,
+}
+
+impl VerifiedTokens {
+    pub fn load() -> Self {
+        cache_path()
+            .map(|path| Self::load_from(&path))
+            .unwrap_or_default()
+    }
+
+    /// Missing or unreadable files count as empty.
+    pub fn load_from(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to(&cache_path()?)
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        crate::output::write_atomic(path, |out| {
+            serde_json::to_writer_pretty(&mut *out, self)?;
+            Ok(())
+        })
+    }
+
+    /// The last check of `token`, if it is younger than `ttl_secs`.
+    pub fn fresh(&self, token: &str, now: i64, ttl_secs: u64) -> Option<Verified> {
+        self.tokens
+            .get(&token_key(token))
+            .copied()
+            .filter(|entry| is_fresh(entry.verified_at, now, ttl_secs))
+    }
+
+    /// Record that katalk accepted `token` at `now`, dropping entries older
+    /// than `ttl_secs` so the file stays small.
+    pub fn record(&mut self, token: &str, scope: &TokenScope, now: i64, ttl_secs: u64) {
+        self.tokens
+            .retain(|_, entry| is_fresh(entry.verified_at, now, ttl_secs));
+        self.tokens.insert(
+            token_key(token),
+            Verified {
+                verified_at: now,
+                pilsner: scope.pilsner,
+            },
+        );
+    }
+
+    /// Whether there was an entry to drop.
+    pub fn forget(&mut self, token: &str) -> bool {
+        self.tokens.remove(&token_key(token)).is_some()
+    }
+}
+
+fn token_key(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+pub fn cache_path() -> Result<PathBuf> {
+    Ok(crate::paths::credentials_dir()?.join("verified-tokens.json"))
+}
+
+/// [`DEFAULT_TTL_SECS`] unless `OPENKAKAO_VERIFY_TTL` says otherwise.
+pub fn ttl_secs() -> u64 {
+    match std::env::var(TTL_ENV) {
+        Ok(raw) => parse_ttl(&raw).unwrap_or_else(|| {
+            warn(
+                WarningCode::InvalidEnvironment,
+                format!("Ignoring {TTL_ENV}={raw}: expected seconds; using {DEFAULT_TTL_SECS}."),
+            );
+            DEFAULT_TTL_SECS
+        }),
+        Err(_) => DEFAULT_TTL_SECS,
+    }
+}
+
+fn parse_ttl(raw: &str) -> Option<u64> {
+    raw.trim().parse().ok()
+}
+
+/// Drop `token` from the cache after the server rejected it. Best effort.
+pub fn forget_token(token: &str) {
+    let mut cache = VerifiedTokens::load();
+    if cache.forget(token) {
+        if let Err(err) = cache.save() {
+            warn(
+                WarningCode::CacheUnavailable,
+                format!("Could not update the token verification cache: {err:#}"),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_expire_and_round_trip_without_the_token() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("verified-tokens.json");
+        let scope = TokenScope {
+            katalk: Some(true),
+            pilsner: Some(false),
+        };
+
+        let mut cache = VerifiedTokens::default();
+        cache.record("old-token", &scope, 100, 600);
+        cache.record("new-token", &scope, 1_000, 600);
+        // Recording drops what has expired.
+        assert!(cache.fresh("old-token", 100, 600).is_none());
+        cache.save_to(&path).unwrap();
+        assert!(!fs::read_to_string(&path).unwrap().contains("new-token"));
+
+        let loaded = VerifiedTokens::load_from(&path);
+        assert_eq!(loaded, cache);
+        let entry = loaded.fresh("new-token", 1_599, 600).unwrap();
+        assert_eq!(entry.scope(), scope);
+        assert!(loaded.fresh("new-token", 1_600, 600).is_none());
+        assert!(loaded.fresh("new-token", 1_000, 0).is_none());
+        assert!(loaded.fresh("other-token", 1_000, 600).is_none());
+
+        cache.forget("new-token");
+        assert!(cache.fresh("new-token", 1_000, 600).is_none());
+        assert_eq!(
+            VerifiedTokens::load_from(&dir.path().join("missing.json")),
+            VerifiedTokens::default()
+        );
+    }
+
+    #[test]
+    fn ttl_override_is_seconds() {
+        assert_eq!(parse_ttl(" 30 "), Some(30));
+        assert_eq!(parse_ttl("0"), Some(0));
+        assert_eq!(parse_ttl("10m"), None);
+    }
+}
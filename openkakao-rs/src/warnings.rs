@@ -0,0 +1,179 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+use crate::util::display_safe;
+
+/// Stable identifiers for non-fatal conditions. The string form
+/// (`W001_STALE_TOKEN`, ...) is what scripts match on; never renumber.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum WarningCode {
+    StaleToken,
+    CredentialPermissions,
+    InvalidEnvironment,
+    RestTokenRefresh,
+    OpenChat,
+    PartialData,
+    MemberFetchFailed,
+    RestFallback,
+    CacheUnavailable,
+    HookFailed,
+    DownloadFailed,
+    StateSaveFailed,
+    TokenScope,
+    StaleCache,
+    PartialCredentials,
+    CredentialFormat,
+    AccountMismatch,
+}
+
+impl WarningCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WarningCode::StaleToken => "W001_STALE_TOKEN",
+            WarningCode::CredentialPermissions => "W002_CREDENTIAL_PERMISSIONS",
+            WarningCode::InvalidEnvironment => "W003_INVALID_ENVIRONMENT",
+            WarningCode::RestTokenRefresh => "W004_REST_TOKEN_REFRESH",
+            WarningCode::OpenChat => "W005_OPEN_CHAT",
+            WarningCode::PartialData => "W006_PARTIAL_DATA",
+            WarningCode::MemberFetchFailed => "W007_MEMBER_FETCH_FAILED",
+            WarningCode::RestFallback => "W008_REST_FALLBACK",
+            WarningCode::CacheUnavailable => "W009_CACHE_UNAVAILABLE",
+            WarningCode::HookFailed => "W010_HOOK_FAILED",
+            WarningCode::DownloadFailed => "W011_DOWNLOAD_FAILED",
+            WarningCode::StateSaveFailed => "W012_STATE_SAVE_FAILED",
+            WarningCode::TokenScope => "W013_TOKEN_SCOPE",
+            WarningCode::StaleCache => "W014_STALE_CACHE",
+            WarningCode::PartialCredentials => "W015_PARTIAL_CREDENTIALS",
+            WarningCode::CredentialFormat => "W016_CREDENTIAL_FORMAT",
+            WarningCode::AccountMismatch => "W017_ACCOUNT_MISMATCH",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub code: WarningCode,
+    pub message: String,
+}
+
+impl Warning {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "event": "warning",
+            "code": self.code.as_str(),
+            "message": self.message,
+        })
+    }
+}
+
+static PENDING: Mutex<Vec<Warning>> = Mutex::new(Vec::new());
+static TOTAL: AtomicUsize = AtomicUsize::new(0);
+static STREAMING: AtomicBool = AtomicBool::new(false);
+static NDJSON: AtomicBool = AtomicBool::new(false);
+
+/// For commands that run until interrupted (`watch`) or stream records
+/// (`read --json --all`): emit each warning as it happens instead of at
+/// exit — an NDJSON `{"event":"warning",...}` record on stdout when
+/// `ndjson`, otherwise a stderr line.
+pub fn stream_immediately(ndjson: bool) {
+    NDJSON.store(ndjson, Ordering::Relaxed);
+    STREAMING.store(true, Ordering::Relaxed);
+}
+
+/// Record a warning for the current command.
+pub fn warn(code: WarningCode, message: impl Into<String>) {
+    let warning = Warning {
+        code,
+        message: message.into(),
+    };
+    TOTAL.fetch_add(1, Ordering::Relaxed);
+    if STREAMING.load(Ordering::Relaxed) {
+        if NDJSON.load(Ordering::Relaxed) {
+            println!("{}", warning.to_json());
+        } else {
+            eprintln!(
+                "[warning] {} {}",
+                code.as_str(),
+                display_safe(&warning.message)
+            );
+        }
+        return;
+    }
+    PENDING
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .push(warning);
+}
+
+/// Warnings raised so far, including ones already streamed.
+pub fn count() -> usize {
+    TOTAL.load(Ordering::Relaxed)
+}
+
+/// Print collected warnings to stderr as one block, once, at the end of a run.
+pub fn flush() {
+    let pending = std::mem::take(
+        &mut *PENDING
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()),
+    );
+    if pending.is_empty() {
+        return;
+    }
+    eprintln!("[warnings] {} warning(s):", pending.len());
+    for warning in &pending {
+        eprintln!(
+            "  {}  {}",
+            warning.code.as_str(),
+            display_safe(&warning.message)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_are_unique_and_prefixed() {
+        let codes = [
+            WarningCode::StaleToken,
+            WarningCode::CredentialPermissions,
+            WarningCode::InvalidEnvironment,
+            WarningCode::RestTokenRefresh,
+            WarningCode::OpenChat,
+            WarningCode::PartialData,
+            WarningCode::MemberFetchFailed,
+            WarningCode::RestFallback,
+            WarningCode::CacheUnavailable,
+            WarningCode::HookFailed,
+            WarningCode::DownloadFailed,
+            WarningCode::StateSaveFailed,
+            WarningCode::TokenScope,
+            WarningCode::StaleCache,
+            WarningCode::PartialCredentials,
+            WarningCode::CredentialFormat,
+            WarningCode::AccountMismatch,
+        ];
+        let mut seen = std::collections::HashSet::new();
+        for code in codes {
+            assert!(code.as_str().starts_with('W'));
+            assert!(seen.insert(&code.as_str()[..4]), "{}", code.as_str());
+        }
+        assert_eq!(WarningCode::StaleToken.as_str(), "W001_STALE_TOKEN");
+    }
+
+    #[test]
+    fn ndjson_record_shape() {
+        let warning = Warning {
+            code: WarningCode::OpenChat,
+            message: "reading from an open chat".into(),
+        };
+        let json = warning.to_json();
+        assert_eq!(json["event"], "warning");
+        assert_eq!(json["code"], "W005_OPEN_CHAT");
+        assert_eq!(json["message"], "reading from an open chat");
+    }
+}
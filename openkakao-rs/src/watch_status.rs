@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use crate::util::sanitize_text;
+
+const BELL: &str = "\x07";
+/// Carriage return plus "erase line", so the next write replaces the line.
+const CLEAR_LINE: &str = "\r\x1b[2K";
+/// Longest status line written, in characters; longer lines would wrap and
+/// break the in-place rewrite on narrow panes.
+const MAX_LINE_CHARS: usize = 78;
+
+/// `watch --bell` / `--title` state: unread messages per chat since the
+/// watch started and the chat with the latest activity. Only used on a
+/// terminal; rendering goes to any writer so tests can capture it.
+#[derive(Debug, Default)]
+pub struct WatchStatus {
+    /// Replace scrolling message lines with one line rewritten in place,
+    /// ringing the bell for each new message.
+    bell: bool,
+    /// Put the unread count in the window title (OSC 0).
+    title: bool,
+    unread: BTreeMap<i64, u64>,
+    /// Chat label and local time of the latest incoming message.
+    last: Option<(String, String)>,
+    /// Whether the status line is currently on screen.
+    drawn: bool,
+}
+
+impl WatchStatus {
+    pub fn new(bell: bool, title: bool) -> Self {
+        Self {
+            bell,
+            title,
+            ..Self::default()
+        }
+    }
+
+    /// Whether message lines are replaced by the status line.
+    pub fn replaces_lines(&self) -> bool {
+        self.bell
+    }
+
+    /// A message from someone else arrived in `chat_id`.
+    pub fn incoming(&mut self, chat_id: i64, chat_label: &str, at: &str) {
+        *self.unread.entry(chat_id).or_default() += 1;
+        self.last = Some((one_line(chat_label), at.to_string()));
+    }
+
+    /// `chat_id` was read (a read receipt went out, or you wrote there).
+    pub fn mark_read(&mut self, chat_id: i64) {
+        self.unread.remove(&chat_id);
+    }
+
+    pub fn total_unread(&self) -> u64 {
+        self.unread.values().sum()
+    }
+
+    pub fn summary(&self) -> String {
+        let total = self.total_unread();
+        let counts = match total {
+            0 => "No unread messages".to_string(),
+            1 => "1 unread message".to_string(),
+            n => {
+                let chats = self.unread.len();
+                format!(
+                    "{n} unread in {chats} chat{}",
+                    if chats == 1 { "" } else { "s" }
+                )
+            }
+        };
+        let line = match &self.last {
+            Some((chat, at)) => format!("{counts} · last: {chat} at {at}"),
+            None => format!("{counts} · waiting for messages"),
+        };
+        if line.chars().count() > MAX_LINE_CHARS {
+            let mut cut: String = line.chars().take(MAX_LINE_CHARS - 1).collect();
+            cut.push('…');
+            cut
+        } else {
+            line
+        }
+    }
+
+    /// Redraw the status line and/or title; `ring` adds the bell (only with
+    /// `--bell`).
+    pub fn draw(&mut self, out: &mut dyn Write, ring: bool) -> io::Result<()> {
+        if self.bell {
+            write!(out, "{CLEAR_LINE}{}", self.summary())?;
+            if ring {
+                out.write_all(BELL.as_bytes())?;
+            }
+            self.drawn = true;
+        }
+        if self.title {
+            let title = match self.total_unread() {
+                0 => "openkakao watch".to_string(),
+                n => format!("({n}) openkakao watch"),
+            };
+            write!(out, "\x1b]0;{title}\x1b\\")?;
+        }
+        out.flush()
+    }
+
+    /// Erase the status line so ordinary output starts on a clean line.
+    pub fn clear(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        if self.drawn {
+            out.write_all(CLEAR_LINE.as_bytes())?;
+            self.drawn = false;
+            out.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Leave the terminal as it was: status line erased, title reset.
+    pub fn finish(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        self.clear(out)?;
+        if self.title {
+            out.write_all(b"\x1b]0;\x1b\\")?;
+            out.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Chat names go into a single terminal line: control characters (escape
+/// sequences included) are made visible and line breaks become spaces.
+fn one_line(label: &str) -> String {
+    sanitize_text(label, false)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(status: &mut WatchStatus, ring: bool) -> String {
+        let mut out = Vec::new();
+        status.draw(&mut out, ring).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn bell_rewrites_one_line_in_place() {
+        let mut status = WatchStatus::new(true, false);
+        assert_eq!(
+            render(&mut status, false),
+            "\r\x1b[2KNo unread messages · waiting for messages"
+        );
+
+        status.incoming(1, "Team", "09:00:01");
+        assert_eq!(
+            render(&mut status, true),
+            "\r\x1b[2K1 unread message · last: Team at 09:00:01\x07"
+        );
+        status.incoming(2, "가족", "09:00:05");
+        status.incoming(2, "가족", "09:00:06");
+        assert_eq!(
+            render(&mut status, true),
+            "\r\x1b[2K3 unread in 2 chats · last: 가족 at 09:00:06\x07"
+        );
+
+        status.mark_read(2);
+        assert_eq!(status.total_unread(), 1);
+        assert!(!render(&mut status, false).contains('\x07'));
+    }
+
+    #[test]
+    fn title_uses_osc_and_resets_on_finish() {
+        let mut status = WatchStatus::new(false, true);
+        status.incoming(1, "Team", "09:00:01");
+        // Title alone keeps normal line output: no status line, no bell.
+        assert_eq!(
+            render(&mut status, true),
+            "\x1b]0;(1) openkakao watch\x1b\\"
+        );
+
+        let mut out = Vec::new();
+        status.finish(&mut out).unwrap();
+        assert_eq!(out, b"\x1b]0;\x1b\\");
+    }
+
+    #[test]
+    fn clear_only_erases_a_drawn_line() {
+        let mut status = WatchStatus::new(true, false);
+        let mut out = Vec::new();
+        status.clear(&mut out).unwrap();
+        assert!(out.is_empty());
+
+        render(&mut status, false);
+        status.clear(&mut out).unwrap();
+        status.clear(&mut out).unwrap();
+        assert_eq!(out, b"\r\x1b[2K");
+    }
+
+    #[test]
+    fn chat_labels_cannot_inject_escapes_or_wrap() {
+        let mut status = WatchStatus::new(true, false);
+        status.incoming(1, "evil\x1b]0;pwned\x07\nname", "09:00:01");
+        let line = status.summary();
+        assert!(!line.contains('\x1b') && !line.contains('\x07') && !line.contains('\n'));
+        assert!(line.contains("evil^[]0;pwned^G name"));
+
+        status.incoming(2, &"아주 긴 채팅방 이름 ".repeat(10), "09:00:02");
+        let line = status.summary();
+        assert_eq!(line.chars().count(), MAX_LINE_CHARS);
+        assert!(line.ends_with('…'));
+    }
+}
@@ -0,0 +1,258 @@
+use std::io::{self, IsTerminal};
+
+/// Narrowest body `wrap_body` will wrap to. With a long prefix on a narrow
+/// terminal the body gets at least this many columns and the line overflows
+/// instead of turning into a column of single characters.
+const MIN_BODY_COLUMNS: usize = 20;
+
+/// Columns of the terminal stdout is attached to, or `None` when stdout is
+/// not a terminal (piped output is never wrapped by default).
+pub fn terminal_width() -> Option<usize> {
+    if !io::stdout().is_terminal() {
+        return None;
+    }
+    #[cfg(unix)]
+    {
+        // SAFETY: TIOCGWINSZ only writes into the winsize we pass.
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0
+            && size.ws_col > 0
+        {
+            return Some(size.ws_col as usize);
+        }
+    }
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .filter(|&cols| cols > 0)
+}
+
+/// Terminal columns `c` takes: 2 for Hangul, CJK, fullwidth forms and most
+/// emoji, 0 for combining marks and zero-width characters, 1 otherwise.
+pub fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x0300..=0x036F
+        | 0x1160..=0x11FF
+        | 0x1AB0..=0x1AFF
+        | 0x1DC0..=0x1DFF
+        | 0x200B..=0x200F
+        | 0x20D0..=0x20FF
+        | 0x2060..=0x2064
+        | 0xD7B0..=0xD7FF
+        | 0xFE00..=0xFE0F
+        | 0xFE20..=0xFE2F
+        | 0xFEFF => 0,
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xA960..=0xA97F
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F680..=0x1F6FF
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+pub fn display_width(s: &str) -> usize {
+    s.chars().map(char_width).sum()
+}
+
+/// Whether `word` is a link that must stay on one line to remain clickable.
+fn is_url(word: &str) -> bool {
+    let word = word
+        .trim_start_matches(|c: char| !c.is_alphanumeric())
+        .to_ascii_lowercase();
+    ["http://", "https://", "openkakao://", "www."]
+        .iter()
+        .any(|scheme| word.starts_with(scheme))
+}
+
+/// Soft-wrap a message body printed after a prefix `indent` columns wide so
+/// no line goes past `width` columns. Lines break at spaces; continuation
+/// lines are indented by `indent` so they align under the body. Embedded
+/// newlines are kept and indented the same way. A word wider than a whole
+/// line is split at character boundaries, except URLs, which are left
+/// intact on a line of their own. `width == 0` returns the body unchanged.
+pub fn wrap_body(body: &str, indent: usize, width: usize) -> String {
+    if width == 0 {
+        return body.to_string();
+    }
+    let avail = width.saturating_sub(indent).max(MIN_BODY_COLUMNS);
+    let mut lines = Vec::new();
+    for paragraph in body.split('\n') {
+        wrap_paragraph(paragraph, avail, &mut lines);
+    }
+    let pad = " ".repeat(indent);
+    let mut out = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+            if !line.is_empty() {
+                out.push_str(&pad);
+            }
+        }
+        out.push_str(line);
+    }
+    out
+}
+
+fn wrap_paragraph(paragraph: &str, avail: usize, lines: &mut Vec<String>) {
+    let mut line = String::new();
+    let mut col = 0;
+    // Whitespace before the next word; dropped when the word starts a new
+    // line, kept at the start of the paragraph (indented messages).
+    let mut gap = String::new();
+    let mut started = false;
+
+    for (is_space, token) in tokens(paragraph) {
+        if is_space {
+            gap.push_str(token);
+            continue;
+        }
+        let word_width = display_width(token);
+        let gap_width = display_width(&gap);
+        if col + gap_width + word_width <= avail && (col > 0 || !started) {
+            line.push_str(&gap);
+            line.push_str(token);
+            col += gap_width + word_width;
+            started = true;
+            gap.clear();
+            continue;
+        }
+        if started {
+            lines.push(std::mem::take(&mut line));
+            col = 0;
+        }
+        started = true;
+        gap.clear();
+        if word_width <= avail || is_url(token) {
+            line.push_str(token);
+            col = word_width;
+            continue;
+        }
+        for c in token.chars() {
+            let w = char_width(c);
+            if col + w > avail && col > 0 {
+                lines.push(std::mem::take(&mut line));
+                col = 0;
+            }
+            line.push(c);
+            col += w;
+        }
+    }
+    if col + display_width(&gap) <= avail {
+        line.push_str(&gap);
+    }
+    lines.push(line);
+}
+
+/// Runs of whitespace and non-whitespace, in order.
+fn tokens(s: &str) -> Vec<(bool, &str)> {
+    let mut out = Vec::new();
+    let mut start = 0;
+    let mut current = None;
+    for (i, c) in s.char_indices() {
+        let space = c.is_whitespace();
+        if current.is_some_and(|kind| kind != space) {
+            out.push((!space, &s[start..i]));
+            start = i;
+        }
+        current = Some(space);
+    }
+    if let Some(space) = current {
+        out.push((space, &s[start..]));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn widths(wrapped: &str) -> Vec<usize> {
+        wrapped.lines().map(display_width).collect()
+    }
+
+    #[test]
+    fn short_bodies_are_untouched() {
+        assert_eq!(wrap_body("hello world", 10, 80), "hello world");
+        assert_eq!(wrap_body("", 10, 80), "");
+        assert_eq!(wrap_body("a  b", 0, 0), "a  b");
+    }
+
+    #[test]
+    fn wraps_at_spaces_and_aligns_continuations() {
+        let body = "the quick brown fox jumps over the lazy dog again and again";
+        let wrapped = wrap_body(body, 6, 30);
+        assert_eq!(
+            wrapped,
+            "the quick brown fox\n      jumps over the lazy dog\n      again and again"
+        );
+        assert!(widths(&wrapped).iter().all(|&w| w <= 30));
+    }
+
+    #[test]
+    fn korean_counts_two_columns_and_breaks_between_words() {
+        // Each syllable is two columns: "안녕하세요" is 10 wide.
+        assert_eq!(display_width("안녕하세요"), 10);
+        assert_eq!(display_width("a안b"), 4);
+        let body = "안녕하세요 오늘 회의는 세시에 시작합니다 늦지 마세요";
+        let wrapped = wrap_body(body, 0, 24);
+        for line in wrapped.lines() {
+            assert!(display_width(line) <= 24, "{line:?} too wide");
+            // No word was split in half.
+            for word in line.split(' ') {
+                assert!(body.split(' ').any(|w| w == word), "{word:?} was split");
+            }
+        }
+        assert_eq!(wrapped.lines().count(), 3);
+    }
+
+    #[test]
+    fn long_words_split_on_character_boundaries() {
+        let wrapped = wrap_body(&"가".repeat(25), 0, 20);
+        assert_eq!(widths(&wrapped), [20, 20, 10]);
+        // A wide character never straddles the edge.
+        assert_eq!(widths(&wrap_body("x가가가가가가가가가가", 0, 20)), [19, 2]);
+    }
+
+    #[test]
+    fn urls_stay_intact_on_their_own_line() {
+        let url = "https://example.com/a/very/long/path/that/does/not/fit?q=1";
+        let body = format!("see ({url}) for details");
+        let wrapped = wrap_body(&body, 4, 30);
+        let lines: Vec<&str> = wrapped.lines().collect();
+        assert_eq!(lines[0], "see");
+        assert_eq!(lines[1], format!("    ({url})"));
+        assert_eq!(lines[2], "    for details");
+    }
+
+    #[test]
+    fn embedded_newlines_are_kept_and_indented() {
+        let wrapped = wrap_body("first line\n\n  indented second", 5, 80);
+        assert_eq!(wrapped, "first line\n\n       indented second");
+    }
+
+    #[test]
+    fn narrow_terminals_keep_a_usable_body_width() {
+        let body = "one two three four five six seven";
+        let wrapped = wrap_body(body, 30, 32);
+        let first = wrapped.lines().next().unwrap();
+        assert_eq!(display_width(first), 18);
+    }
+
+    #[test]
+    fn zero_width_marks_do_not_count() {
+        assert_eq!(display_width("e\u{301}"), 1);
+        assert_eq!(display_width("\u{1100}\u{1161}"), 2);
+    }
+}
@@ -184,3 +184,49 @@ fn safety_block_display_includes_reason() {
     let msg = err.to_string();
     assert!(msg.contains("open chat detected"), "display: {msg}");
 }
+
+#[test]
+fn http_auth_rejections_are_typed_and_not_retryable() {
+    let page = b"<!DOCTYPE html>\n<html><head><title>401\n Unauthorized</title></head></html>";
+    let err = OpenKakaoError::from_http(401, page, None);
+    assert!(
+        matches!(&err, OpenKakaoError::Unauthorized { message } if message == "HTML page \"401 Unauthorized\""),
+        "{err}"
+    );
+    assert!(!err.is_retryable());
+    assert_eq!(err.status_code(), Some(-401));
+    assert!(err.to_string().contains("login --save"), "{err}");
+
+    let err = OpenKakaoError::from_http(403, br#"{"message":"blocked"}"#, None);
+    assert!(matches!(&err, OpenKakaoError::Forbidden { message } if message == "blocked"));
+
+    let err = OpenKakaoError::from_http(400, br#"{"reason":"UNAUTHENTICATED"}"#, None);
+    assert!(matches!(err, OpenKakaoError::Unauthorized { .. }));
+}
+
+#[test]
+fn rate_limits_keep_retry_after_and_are_retryable() {
+    let wait = std::time::Duration::from_secs(7);
+    let err = OpenKakaoError::from_http(429, b"", Some(wait));
+    assert!(matches!(err, OpenKakaoError::RateLimited { retry_after: Some(w) } if w == wait));
+    assert!(err.is_retryable());
+    assert_eq!(err.to_string(), "Rate limited by Kakao; retry after 7s");
+}
+
+#[test]
+fn other_http_errors_keep_a_short_body() {
+    let long = "x ".repeat(500);
+    let OpenKakaoError::Http { status, body, .. } =
+        OpenKakaoError::from_http(502, long.as_bytes(), None)
+    else {
+        panic!("expected Http");
+    };
+    assert_eq!(status, 502);
+    assert_eq!(body.chars().count(), 203);
+    assert!(body.ends_with("..."));
+    // A JSON status on a 5xx is left to the retry logic as a plain HTTP error.
+    assert!(matches!(
+        OpenKakaoError::from_http(500, br#"{"status":-500}"#, None),
+        OpenKakaoError::Http { status: 500, .. }
+    ));
+}
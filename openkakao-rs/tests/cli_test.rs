@@ -1,3 +1,5 @@
+#![cfg(feature = "cli")]
+
 use assert_cmd::Command;
 use predicates::prelude::*;
 
@@ -140,6 +142,8 @@ fn doctor_json_outputs_valid_json() {
     let stdout = String::from_utf8_lossy(&output.stdout);
     let parsed: serde_json::Value =
         serde_json::from_str(&stdout).expect("doctor --json output should be valid JSON");
+    assert_eq!(parsed["schema"], "openkakao.doctor.v1");
+    let parsed = &parsed["data"];
     assert!(
         parsed.get("checks").is_some(),
         "doctor --json output should have 'checks' key"
@@ -150,12 +154,38 @@ fn doctor_json_outputs_valid_json() {
     );
 }
 
+#[test]
+fn schema_prints_published_documents() {
+    let output = cmd().args(["schema", "whoami"]).output().unwrap();
+    assert!(output.status.success());
+    let document: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(document["title"], "openkakao.whoami.v1");
+    assert_eq!(
+        document["properties"]["schema"]["const"],
+        "openkakao.whoami.v1"
+    );
+
+    let output = cmd().args(["--json", "schema"]).output().unwrap();
+    let listing: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(listing["schema"], "openkakao.schema.v1");
+    assert!(listing["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|entry| entry["id"] == "openkakao.chats.v2"));
+
+    let output = cmd().args(["schema", "nope"]).output().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Published: chats"));
+}
+
 #[test]
 fn auth_status_json_outputs_valid_json() {
     let output = cmd().args(["--json", "auth-status"]).output().unwrap();
     let stdout = String::from_utf8_lossy(&output.stdout);
     let parsed: serde_json::Value =
         serde_json::from_str(&stdout).expect("auth-status --json output should be valid JSON");
+    let parsed = &parsed["data"];
     assert!(
         parsed.get("consecutive_failures").is_some(),
         "auth-status --json output should have 'consecutive_failures' key"
@@ -172,6 +202,7 @@ fn cache_stats_json_outputs_valid_json() {
     let stdout = String::from_utf8_lossy(&output.stdout);
     let parsed: serde_json::Value =
         serde_json::from_str(&stdout).expect("cache-stats --json output should be valid JSON");
+    let parsed = &parsed["data"];
     assert!(
         parsed.get("total_messages").is_some(),
         "cache-stats --json output should have 'total_messages' key"
@@ -185,3 +216,484 @@ fn cache_stats_json_outputs_valid_json() {
         "cache-stats --json 'chats' should be an array"
     );
 }
+
+#[test]
+fn archive_info_json_reports_the_media_cache() {
+    let output = cmd().args(["--json", "archive", "info"]).output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let parsed: serde_json::Value =
+        serde_json::from_str(&stdout).expect("archive info --json output should be valid JSON");
+    let parsed = &parsed["data"];
+    assert!(parsed["total_messages"].is_i64());
+    assert!(parsed["media_cache"]["bytes"].is_u64());
+    assert!(parsed["media_cache"]["max_bytes"].is_u64());
+}
+
+#[test]
+fn http_overrides_are_checked_before_any_request() {
+    cmd()
+        .args(["--base-url", "https://example.com", "completions", "bash"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("loopback"));
+    cmd()
+        .args(["completions", "bash"])
+        .env("OPENKAKAO_TIMEOUT", "soon")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("OPENKAKAO_TIMEOUT"));
+    cmd()
+        .args(["--proxy", "socks5h://127.0.0.1:1080", "--timeout", "2.5"])
+        .args(["completions", "bash"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn out_writes_primary_output_to_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("completions.bash");
+    cmd()
+        .args(["--out", path.to_str().unwrap(), "completions", "bash"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+    let written = std::fs::read_to_string(&path).unwrap();
+    assert!(written.contains("openkakao-rs"));
+}
+
+#[test]
+fn out_keeps_previous_file_when_command_fails() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("export.txt");
+    std::fs::write(&path, "previous good export").unwrap();
+    cmd()
+        .args([
+            "--out",
+            path.to_str().unwrap(),
+            "export",
+            "1",
+            "--format",
+            "bogus",
+        ])
+        .assert()
+        .failure();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "previous good export"
+    );
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn export_verify_checks_manifests_offline() {
+    let dir = tempfile::tempdir().unwrap();
+    cmd()
+        .args(["export", "verify", dir.path().to_str().unwrap()])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No export manifests"));
+
+    std::fs::write(dir.path().join("chat.txt"), "hello\n").unwrap();
+    std::fs::write(
+        dir.path().join("manifest-7-0001.json"),
+        r#"{"version":1,"crate_version":"0","chat_id":7,"sequence":1,"created_at":0,
+            "parameters":{"format":"txt","max_pages":100,"append":false,"anonymized":false,
+            "no_media":false,"redactions":0,"embed_media":false,"annotate_titles":false,
+            "threads":false},"message_count":1,"covered":{"from":1,"to":1},
+            "files":[{"path":"chat.txt","size":6,
+            "sha256":"5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03"}],
+            "previous":null}"#,
+    )
+    .unwrap();
+    cmd()
+        .args(["export", "verify", dir.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("all intact"));
+
+    std::fs::write(dir.path().join("chat.txt"), "hellO\n").unwrap();
+    cmd()
+        .args(["export", "verify", dir.path().to_str().unwrap()])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("chat.txt was modified"));
+}
+
+#[test]
+fn whoami_offline_reads_saved_credentials_without_network() {
+    let home = tempfile::tempdir().unwrap();
+    cmd()
+        .env("HOME", home.path())
+        .env_remove("OPENKAKAO_TOKEN")
+        .args(["whoami", "--offline"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No saved credentials"));
+
+    let dir = home.path().join(".config").join("openkakao");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("credentials.json"),
+        r#"{"oauth_token":"t","user_id":4242,"device_uuid":"d","app_version":"3.7.0","user_agent":"ua","a_header":"mac/3.7.0/ko"}"#,
+    )
+    .unwrap();
+    let output = cmd()
+        .env("HOME", home.path())
+        .env_remove("OPENKAKAO_TOKEN")
+        .args(["whoami", "--offline", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let parsed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(parsed["schema"], "openkakao.whoami.v1");
+    assert_eq!(parsed["data"]["user_id"], 4242);
+    assert!(parsed["data"]["nickname"].is_null());
+}
+
+#[test]
+fn legacy_credentials_migrate_and_profiles_stay_separate() {
+    let home = tempfile::tempdir().unwrap();
+    let dir = home.path().join(".config").join("openkakao");
+    std::fs::create_dir_all(&dir).unwrap();
+    let creds = |user_id: i64| {
+        format!(
+            r#"{{"oauth_token":"t{user_id}","user_id":{user_id},"device_uuid":"d","app_version":"3.7.0","user_agent":"ua","a_header":"mac/3.7.0/ko"}}"#
+        )
+    };
+    std::fs::write(dir.join("credentials.json"), creds(4242)).unwrap();
+    let whoami = |profile: Option<&str>| {
+        let mut command = cmd();
+        command
+            .env("HOME", home.path())
+            .env_remove("OPENKAKAO_TOKEN");
+        if let Some(profile) = profile {
+            command.args(["--profile", profile]);
+        }
+        command
+            .args(["whoami", "--offline", "--json"])
+            .output()
+            .unwrap()
+    };
+
+    let output = whoami(None);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("default profile"));
+    assert!(!dir.join("credentials.json").exists());
+    assert!(dir.join("credentials").join("default.json").exists());
+    let parsed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(parsed["data"]["user_id"], 4242);
+
+    assert!(!whoami(Some("work")).status.success());
+    std::fs::write(dir.join("credentials").join("work.json"), creds(7)).unwrap();
+    let output = whoami(Some("work"));
+    let parsed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(parsed["data"]["user_id"], 7);
+    let output = whoami(Some("default"));
+    let parsed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(parsed["data"]["user_id"], 4242);
+
+    assert!(!whoami(Some("../x")).status.success());
+}
+
+#[test]
+fn warnings_are_grouped_and_can_fail_the_run() {
+    let home = tempfile::tempdir().unwrap();
+    let run = |extra: &[&str]| {
+        cmd()
+            .env("HOME", home.path())
+            .env("OPENKAKAO_TOKEN", "token-device")
+            .env("OPENKAKAO_USER_ID", "not-a-number")
+            .args(["whoami", "--offline"])
+            .args(extra)
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&[]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("[warnings] 1 warning(s):"), "{stderr}");
+    assert!(stderr.contains("W003_INVALID_ENVIRONMENT"), "{stderr}");
+
+    let output = run(&["--fail-on-warnings"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--fail-on-warnings"));
+}
+
+#[test]
+fn workspace_directory_is_found_from_subdirectories() {
+    let home = tempfile::tempdir().unwrap();
+    let project = tempfile::tempdir().unwrap();
+    let nested = project.path().join("src").join("deep");
+    std::fs::create_dir_all(&nested).unwrap();
+    let config_path = |dir: &std::path::Path, extra: &[&str]| {
+        let output = cmd()
+            .env("HOME", home.path())
+            .current_dir(dir)
+            .args(extra)
+            .args(["config", "path", "--json"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()["data"].take()
+    };
+
+    let parsed = config_path(&nested, &[]);
+    assert_eq!(parsed["scope"]["kind"], "global");
+
+    // --local creates ./.openkakao; later runs below it pick it up.
+    let parsed = config_path(project.path(), &["--local"]);
+    assert_eq!(parsed["scope"]["kind"], "workspace");
+    let workspace = project.path().join(".openkakao");
+    assert!(workspace.join(".gitignore").is_file());
+
+    let parsed = config_path(&nested, &[]);
+    assert_eq!(parsed["scope"]["kind"], "workspace");
+    let state = parsed["paths"]["state"].as_str().unwrap();
+    assert!(
+        std::path::Path::new(state).starts_with(&workspace),
+        "{state}"
+    );
+    let creds = parsed["paths"]["credentials"].as_str().unwrap();
+    assert!(
+        std::path::Path::new(creds).starts_with(home.path()),
+        "{creds}"
+    );
+    assert_eq!(parsed["credentials_scope"], "global");
+
+    let parsed = config_path(&nested, &["--local-credentials"]);
+    let creds = parsed["paths"]["credentials"].as_str().unwrap();
+    assert!(
+        std::path::Path::new(creds).starts_with(&workspace),
+        "{creds}"
+    );
+}
+
+#[test]
+fn prompting_commands_fail_instead_of_waiting_for_stdin() {
+    let home = tempfile::tempdir().unwrap();
+    let config = home.path().join(".config").join("openkakao");
+    std::fs::create_dir_all(&config).unwrap();
+    std::fs::write(
+        config.join("config.toml"),
+        "[safety]\nallow_loco_write = true\n",
+    )
+    .unwrap();
+    // `output()` runs the binary with stdin already closed.
+    let run = |args: &[&str]| {
+        cmd()
+            .env("HOME", home.path())
+            .env("OPENKAKAO_TOKEN", "token-device")
+            .env("OPENKAKAO_USER_ID", "1")
+            .env_remove("OPENKAKAO_NO_INTERACTIVE")
+            .args(args)
+            .timeout(std::time::Duration::from_secs(10))
+            .output()
+            .unwrap()
+    };
+
+    let queued = run(&["send", "42", "queued", "--queue"]);
+    assert!(queued.status.success());
+
+    for args in [
+        &["send", "42", "hi"][..],
+        &["send-me", "hi"],
+        &["dm", "42", "--send", "hi"],
+        &["send-file", "42", "photo.jpg"],
+        &["delete", "42", "7"],
+        &["edit", "42", "7", "fixed"],
+        &["favorite", "42"],
+        &["unfavorite", "42"],
+        &["hide", "42"],
+        &["unhide", "42"],
+        &["unhide", "--all-hidden"],
+        &["outbox", "flush"],
+        &["init"],
+        &["--no-input", "favorite", "42"],
+    ] {
+        let output = run(args);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(6), "{args:?}: {stderr}");
+        assert!(stderr.contains("Pass -y/--yes"), "{args:?}: {stderr}");
+        assert!(!stderr.contains("[y/N]"), "{args:?} prompted: {stderr}");
+    }
+}
+
+#[test]
+fn init_rerun_keeps_current_values_and_writes_nothing() {
+    let home = tempfile::tempdir().unwrap();
+    let dir = home.path().join(".config").join("openkakao");
+    std::fs::create_dir_all(&dir).unwrap();
+    let config = "# mine\n[ui]\noutput = \"json\"\n";
+    std::fs::write(dir.join("config.toml"), config).unwrap();
+
+    let output = cmd()
+        .env("HOME", home.path())
+        .env_remove("OPENKAKAO_TOKEN")
+        .args(["init", "-y"])
+        .timeout(std::time::Duration::from_secs(30))
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert!(stdout.contains("text or json [json]"), "{stdout}");
+    assert!(stdout.contains("unattended sends [10]"), "{stdout}");
+    assert!(stdout.contains("nothing to write"), "{stdout}");
+    assert!(stdout.contains("Try next: openkakao-rs"), "{stdout}");
+    assert_eq!(
+        std::fs::read_to_string(dir.join("config.toml")).unwrap(),
+        config
+    );
+}
+
+#[test]
+fn no_input_refuses_the_credential_prompt() {
+    let home = tempfile::tempdir().unwrap();
+    let output = cmd()
+        .env("HOME", home.path())
+        .env_remove("OPENKAKAO_TOKEN")
+        .env_remove("OPENKAKAO_NO_INTERACTIVE")
+        .args(["--no-input", "whoami"])
+        .timeout(std::time::Duration::from_secs(10))
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("OAuth Token"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--no-input"));
+}
+
+#[test]
+fn panic_writes_a_sanitized_crash_report() {
+    let home = tempfile::tempdir().unwrap();
+    let token = "a1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d6-deadbeef";
+    let text = format!("see you at 6 {token}");
+    let output = cmd()
+        .env("HOME", home.path())
+        .args(["debug-panic", &text])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(101));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("openkakao-rs crashed"), "{stderr}");
+    assert!(stderr.contains("/issues"), "{stderr}");
+    assert!(!stderr.contains(token), "{stderr}");
+
+    let dir = home.path().join(".config/openkakao/crash-reports");
+    let reports: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+    assert_eq!(reports.len(), 1);
+    let path = reports[0].as_ref().unwrap().path();
+    assert!(stderr.contains(&path.display().to_string()), "{stderr}");
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert!(!contents.contains(token), "{contents}");
+    assert!(!contents.contains("see you"), "{contents}");
+    let report: serde_json::Value = serde_json::from_str(&contents).unwrap();
+    assert_eq!(
+        report["command"],
+        serde_json::json!(["debug-panic", "<arg>"])
+    );
+    assert_eq!(report["crate_version"], env!("CARGO_PKG_VERSION"));
+    assert!(report["panic"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("debug-panic requested"));
+    assert!(report["panic"]["location"]
+        .as_str()
+        .unwrap()
+        .contains("main.rs"));
+    assert!(report["backtrace"]
+        .as_array()
+        .is_some_and(|b| !b.is_empty()));
+}
+
+#[test]
+fn profiles_keep_their_state_apart_and_rm_removes_only_its_own() {
+    let home = tempfile::tempdir().unwrap();
+    let root = home.path().join(".config").join("openkakao");
+    std::fs::create_dir_all(&root).unwrap();
+    // State from before profiles had their own trees.
+    std::fs::write(root.join("ignore.json"), r#"{"entries":[]}"#).unwrap();
+    std::fs::create_dir_all(root.join("credentials")).unwrap();
+    for (profile, user_id) in [("work", 7), ("home", 8)] {
+        std::fs::write(
+            root.join("credentials").join(format!("{profile}.json")),
+            format!(
+                r#"{{"oauth_token":"t{user_id}","user_id":{user_id},"device_uuid":"d","app_version":"3.7.0","user_agent":"ua","a_header":"mac/3.7.0/ko"}}"#
+            ),
+        )
+        .unwrap();
+    }
+    let run = |profile: &str, args: &[&str]| {
+        let output = cmd()
+            .env("HOME", home.path())
+            .env_remove("OPENKAKAO_TOKEN")
+            .args(["--profile", profile])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{args:?}: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        output
+    };
+    let footprint = |profile: &str| {
+        let dir = root.join("profiles").join(profile);
+        let mut names: Vec<String> = std::fs::read_dir(&dir)
+            .map(|entries| {
+                entries
+                    .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort();
+        names
+    };
+
+    let output = run("work", &["ignore", "add", "111"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("default profile"));
+    assert!(!root.join("ignore.json").exists());
+    assert_eq!(footprint("default"), ["ignore.json"]);
+
+    run("work", &["archive", "info", "--json"]);
+    run("home", &["ignore", "add", "222"]);
+    assert_eq!(
+        footprint("work"),
+        ["ignore.json", "media-cache", "messages.db"]
+    );
+    assert_eq!(footprint("home"), ["ignore.json"]);
+    let ignored = |profile: &str| {
+        std::fs::read_to_string(root.join("profiles").join(profile).join("ignore.json")).unwrap()
+    };
+    assert!(ignored("work").contains("111") && !ignored("work").contains("222"));
+    assert!(ignored("home").contains("222") && !ignored("home").contains("111"));
+    // Nothing of either profile is written outside its tree.
+    assert!(!root.join("messages.db").exists());
+
+    let output = run("home", &["config", "path", "--json"]);
+    let parsed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(parsed["data"]["profile"], "home");
+    let cache = parsed["data"]["paths"]["message_cache"].as_str().unwrap();
+    assert!(
+        std::path::Path::new(cache).starts_with(root.join("profiles").join("home")),
+        "{cache}"
+    );
+
+    // Without a confirmation nothing is deleted.
+    assert!(!cmd()
+        .env("HOME", home.path())
+        .args(["--no-input", "credentials", "profiles", "rm", "work"])
+        .output()
+        .unwrap()
+        .status
+        .success());
+    assert!(root.join("profiles").join("work").exists());
+
+    run("home", &["credentials", "profiles", "rm", "work", "--yes"]);
+    assert!(!root.join("profiles").join("work").exists());
+    assert!(!root.join("credentials").join("work.json").exists());
+    assert!(root.join("credentials").join("home.json").exists());
+    assert_eq!(footprint("home"), ["ignore.json"]);
+    assert_eq!(footprint("default"), ["ignore.json"]);
+}
@@ -0,0 +1,588 @@
+//! REST client behavior under scripted latency and failures. Run with
+//! `cargo test --features fault-injection`.
+#![cfg(feature = "fault-injection")]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use openkakao_rs::credential_watch::{CredentialWatch, Reload};
+use openkakao_rs::cursor::MessageCursor;
+use openkakao_rs::error::OpenKakaoError;
+use openkakao_rs::fault::{cursor_of, Canned, Fault, FaultyTransport, Scenario};
+use openkakao_rs::model::KakaoCredentials;
+use openkakao_rs::rest::{KakaoRestClient, Reauth, RequestOptions};
+use openkakao_rs::rest_async::AsyncKakaoRestClient;
+use openkakao_rs::retry::RetryPolicy;
+use serde_json::{json, Value};
+
+fn creds(token: &str) -> KakaoCredentials {
+    KakaoCredentials::new(
+        token.into(),
+        42,
+        "device-uuid".into(),
+        "26.1.0".into(),
+        "KT/26.1.0 Mc/26.1.0 ko".into(),
+        "mac/26.1.0/ko".into(),
+    )
+}
+
+/// Three message pages, newest first: log ids 30..21, 20..11, 10..1.
+fn message_server() -> Canned {
+    Canned::new().route("/messages", |request| {
+        let top = cursor_of(request.url).unwrap_or(31) - 1;
+        let logs: Vec<Value> = (top - 9..=top)
+            .rev()
+            .map(|log_id| json!({"logId": log_id, "type": 1, "message": "hi", "sendAt": 1_700_000_000 + log_id}))
+            .collect();
+        let next = if top > 10 { top - 9 } else { 0 };
+        json!({"chatLogs": logs, "nextCursor": next})
+    })
+}
+
+/// Two chat list pages.
+fn chat_server() -> Canned {
+    Canned::new().route("/messaging/chats", |request| match cursor_of(request.url) {
+        None => json!({"chats": [{"chatId": 1}, {"chatId": 2}], "nextCursor": 7, "last": false}),
+        Some(_) => json!({"chats": [{"chatId": 3}], "last": true}),
+    })
+}
+
+/// The default policy with millisecond waits.
+fn fast_retries() -> RetryPolicy {
+    RetryPolicy {
+        backoff_base: Duration::from_millis(1),
+        jitter: 0.0,
+        ..RetryPolicy::default()
+    }
+}
+
+fn client_with(transport: Arc<FaultyTransport<Canned>>) -> KakaoRestClient {
+    KakaoRestClient::new(creds("token"))
+        .unwrap()
+        .with_retry_policy(fast_retries())
+        .with_transport(transport)
+}
+
+/// A client that reports the first failure as is.
+fn client_without_retries(transport: Arc<FaultyTransport<Canned>>) -> KakaoRestClient {
+    client_with(transport).with_retry_policy(fast_retries().with_retries(0))
+}
+
+#[test]
+fn clean_run_pages_through_every_message_once() {
+    let transport = Arc::new(FaultyTransport::new(message_server(), Scenario::new()));
+    let messages = client_with(transport.clone())
+        .get_all_messages(1, 10)
+        .unwrap();
+    let ids: Vec<i64> = messages.iter().map(|m| m.log_id).collect();
+    assert_eq!(ids, (1..=30).collect::<Vec<_>>());
+    assert_eq!(transport.calls().len(), 3);
+}
+
+#[test]
+fn recent_messages_follow_the_cursor_until_count() {
+    let transport = Arc::new(FaultyTransport::new(message_server(), Scenario::new()));
+    let client = client_with(transport.clone());
+    let ids = |messages: &[openkakao_rs::model::ChatMessage]| {
+        messages.iter().map(|m| m.log_id).collect::<Vec<_>>()
+    };
+
+    // Two whole pages: the server's cursor continues past them.
+    let (messages, next) = client.get_recent_messages(1, None, 20, 10).unwrap();
+    assert_eq!(ids(&messages), (11..=30).collect::<Vec<_>>());
+    assert_eq!(next, Some(MessageCursor::new(11)));
+    assert_eq!(transport.calls().len(), 2);
+
+    // A partly used page: the cursor resumes at the oldest message shown.
+    let (messages, next) = client.get_recent_messages(1, next, 5, 10).unwrap();
+    assert_eq!(ids(&messages), (6..=10).collect::<Vec<_>>());
+    assert_eq!(next, Some(MessageCursor::new(6)));
+
+    // Running out of history ends without a cursor.
+    let (messages, next) = client.get_recent_messages(1, None, 100, 10).unwrap();
+    assert_eq!(messages.len(), 30);
+    assert_eq!(next, None);
+
+    // The page cap stops a huge count.
+    let (messages, next) = client.get_recent_messages(1, None, 100, 1).unwrap();
+    assert_eq!(messages.len(), 10);
+    assert_eq!(next, Some(MessageCursor::new(21)));
+}
+
+#[test]
+fn blocked_or_deleted_users_have_no_profile() {
+    let server = Canned::new().route("/profile3/friend.json", |request| {
+        match request.body.unwrap_or_default() {
+            "id=1" => json!({"status": 0, "profile": {
+                "nickName": "Dana",
+                "fullProfileImageUrl": "https://p/full.jpg",
+                "type": 100,
+            }}),
+            "id=2" => json!({"status": 0, "profile": null}),
+            _ => json!({"status": -1002}),
+        }
+    });
+    let transport = Arc::new(FaultyTransport::new(server, Scenario::new()));
+    let client = client_without_retries(transport);
+
+    let profile = client.get_member_profile(1).unwrap();
+    assert_eq!(profile.user_id, 1);
+    assert_eq!(profile.nickname, "Dana");
+    assert_eq!(profile.account_type, "100");
+    for user_id in [2, 3] {
+        let err = client.get_member_profile(user_id).unwrap_err();
+        assert!(
+            matches!(err, OpenKakaoError::ProfileUnavailable { user_id: id } if id == user_id),
+            "{err:?}"
+        );
+        assert!(err.to_string().contains("unavailable"), "{err}");
+    }
+}
+
+#[test]
+fn each_fault_surfaces_as_its_documented_error() {
+    let cases = [
+        (Fault::Status(429), true),
+        (Fault::Status(500), true),
+        (Fault::Drop, true),
+        (Fault::MalformedJson, false),
+    ];
+    for (fault, retryable) in cases {
+        let transport = Arc::new(FaultyTransport::new(
+            message_server(),
+            Scenario::new().then(fault.clone()),
+        ));
+        let err = client_without_retries(transport)
+            .get_messages(1, None)
+            .unwrap_err();
+        assert_eq!(err.is_retryable(), retryable, "{fault:?} gave {err}");
+    }
+
+    let transport = Arc::new(FaultyTransport::new(
+        message_server(),
+        Scenario::new().then(Fault::MalformedJson),
+    ));
+    let err = client_without_retries(transport)
+        .get_messages(1, None)
+        .unwrap_err();
+    assert!(matches!(err, OpenKakaoError::Parse { .. }), "{err}");
+
+    let transport = Arc::new(FaultyTransport::new(
+        message_server(),
+        Scenario::new().then(Fault::Status(429)),
+    ));
+    let err = client_without_retries(transport)
+        .get_messages(1, None)
+        .unwrap_err();
+    assert!(matches!(err, OpenKakaoError::RateLimited { .. }), "{err}");
+}
+
+#[test]
+fn slow_answers_within_the_timeout_succeed_and_longer_ones_time_out() {
+    let options = RequestOptions {
+        timeout: Some(Duration::from_millis(100)),
+    };
+    let transport = Arc::new(FaultyTransport::new(
+        message_server(),
+        Scenario::new()
+            .then(Fault::Latency(Duration::from_millis(20)))
+            .then(Fault::Latency(Duration::from_secs(60))),
+    ));
+    let client = client_with(transport);
+
+    let (messages, _) = client
+        .get_messages_with_options(1, None, options)
+        .expect("slow but within the timeout");
+    assert_eq!(messages.len(), 10);
+
+    let started = Instant::now();
+    let err = client
+        .get_messages_with_options(1, None, options)
+        .unwrap_err();
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert!(err.is_retryable(), "{err}");
+}
+
+#[test]
+fn polling_survives_timeouts_and_drops_on_later_ticks() {
+    // A follow loop polls the newest page and treats a failed tick as
+    // "nothing new yet": it must see every message once the link recovers.
+    // Timeouts and drops fail the tick; the 503 is retried within it.
+    let transport = Arc::new(FaultyTransport::new(
+        message_server(),
+        Scenario::new()
+            .then(Fault::Latency(Duration::from_secs(60)))
+            .then(Fault::Drop)
+            .then(Fault::Status(503)),
+    ));
+    let client = client_with(transport.clone());
+    let options = RequestOptions {
+        timeout: Some(Duration::from_millis(50)),
+    };
+    let mut failed_ticks = 0;
+    let mut newest = None;
+    for _ in 0..5 {
+        match client.get_messages_with_options(1, None, options) {
+            Ok((messages, _)) => {
+                newest = messages.iter().map(|m| m.log_id).max();
+                break;
+            }
+            Err(err) => {
+                assert!(err.is_retryable(), "{err}");
+                failed_ticks += 1;
+            }
+        }
+    }
+    assert_eq!(failed_ticks, 2);
+    assert_eq!(newest, Some(30));
+    assert_eq!(transport.remaining(), 0);
+}
+
+#[test]
+fn pagination_survives_mid_run_retries_without_duplicates() {
+    // A failed page is asked for again from the same cursor; earlier pages
+    // are never fetched twice.
+    let transport = Arc::new(FaultyTransport::new(
+        message_server(),
+        Scenario::new()
+            .pass(1)
+            .burst(Fault::Status(429), 2)
+            .pass(1)
+            .then(Fault::Status(502)),
+    ));
+    let messages = client_with(transport.clone())
+        .get_all_messages(1, 10)
+        .unwrap();
+    let ids: Vec<i64> = messages.iter().map(|m| m.log_id).collect();
+    assert_eq!(ids, (1..=30).collect::<Vec<_>>());
+    let cursors: Vec<Option<i64>> = transport.calls().iter().map(|u| cursor_of(u)).collect();
+    assert_eq!(
+        cursors,
+        [None, Some(21), Some(21), Some(21), Some(11), Some(11)]
+    );
+}
+
+#[test]
+fn chat_list_survives_a_rate_limit_between_pages() {
+    let transport = Arc::new(FaultyTransport::new(
+        chat_server(),
+        Scenario::new().pass(1).burst(Fault::Status(429), 3),
+    ));
+    let chats = client_with(transport.clone()).get_all_chats().unwrap();
+    let ids: Vec<i64> = chats.iter().map(|c| c.chat_id).collect();
+    assert_eq!(ids, [1, 2, 3]);
+    assert_eq!(transport.calls().len(), 5);
+}
+
+#[test]
+fn retries_are_bounded_when_the_server_stays_down() {
+    let transport = Arc::new(FaultyTransport::new(
+        message_server(),
+        Scenario::new().burst(Fault::Status(500), 100),
+    ));
+    let err = client_with(transport.clone())
+        .get_messages(1, None)
+        .unwrap_err();
+    assert!(
+        matches!(err, OpenKakaoError::Http { status: 500, .. }),
+        "{err}"
+    );
+    assert_eq!(
+        transport.calls().len(),
+        RetryPolicy::default().max_attempts as usize
+    );
+}
+
+#[test]
+fn retry_after_is_honored_over_the_backoff() {
+    let transport = Arc::new(FaultyTransport::new(
+        message_server(),
+        Scenario::new().then(Fault::RateLimited(Duration::from_millis(30))),
+    ));
+    let client = client_with(transport.clone()).with_retry_policy(RetryPolicy {
+        backoff_base: Duration::from_secs(30),
+        ..fast_retries()
+    });
+    let started = Instant::now();
+    let (messages, _) = client.get_messages(1, None).unwrap();
+    assert_eq!(messages.len(), 10);
+    let waited = started.elapsed();
+    assert!(waited >= Duration::from_millis(30) && waited < Duration::from_secs(5));
+}
+
+#[test]
+fn waits_past_the_deadline_give_up_at_once() {
+    let transport = Arc::new(FaultyTransport::new(
+        message_server(),
+        Scenario::new().then(Fault::RateLimited(Duration::from_secs(600))),
+    ));
+    let client = client_with(transport.clone()).with_retry_deadline(Duration::from_secs(1));
+    let started = Instant::now();
+    let err = client.get_messages(1, None).unwrap_err();
+    assert!(started.elapsed() < Duration::from_millis(500));
+    assert!(matches!(err, OpenKakaoError::RateLimited { .. }), "{err}");
+    assert_eq!(transport.calls().len(), 1);
+}
+
+#[test]
+fn server_errors_are_not_repeated_for_requests_with_side_effects() {
+    let server = Canned::new().route("add_favorite", |_| json!({"status": 0}));
+    let transport = Arc::new(FaultyTransport::new(
+        server,
+        Scenario::new().then(Fault::Status(500)),
+    ));
+    let client = client_with(transport.clone());
+    assert!(client.add_favorite(7).is_err());
+    assert_eq!(transport.calls().len(), 1);
+
+    // A 429 was never processed, so it is safe to send again.
+    let server = Canned::new().route("add_favorite", |_| json!({"status": 0}));
+    let transport = Arc::new(FaultyTransport::new(
+        server,
+        Scenario::new().then(Fault::Status(429)),
+    ));
+    client_with(transport.clone()).add_favorite(7).unwrap();
+    assert_eq!(transport.calls().len(), 2);
+}
+
+#[test]
+fn chat_list_failure_mid_run_returns_an_error_not_a_short_list() {
+    let transport = Arc::new(FaultyTransport::new(
+        chat_server(),
+        Scenario::new().pass(1).then(Fault::MalformedJson),
+    ));
+    let client = client_with(transport.clone());
+    assert!(client.get_all_chats().is_err());
+
+    // The scenario is used up; the same client completes the next run.
+    let chats = client.get_all_chats().unwrap();
+    let ids: Vec<i64> = chats.iter().map(|c| c.chat_id).collect();
+    assert_eq!(ids, [1, 2, 3]);
+}
+
+struct CountingReauth(Arc<AtomicUsize>);
+
+impl Reauth for CountingReauth {
+    fn recover(self: Box<Self>) -> anyhow::Result<KakaoCredentials> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        Ok(creds("renewed"))
+    }
+}
+
+#[test]
+fn expired_token_recovers_once_then_succeeds() {
+    let server = Canned::new()
+        .route(
+            "profile3/me",
+            |_| json!({"status": 0, "profile": {"nickname": "me", "userId": 42}}),
+        )
+        .route("more_settings", |_| json!({"status": 0, "accountId": 7}));
+    let transport = Arc::new(FaultyTransport::new(
+        server,
+        Scenario::new().then(Fault::TokenExpired),
+    ));
+    let recoveries = Arc::new(AtomicUsize::new(0));
+    let client =
+        client_with(transport.clone()).with_reauth(Box::new(CountingReauth(recoveries.clone())));
+
+    let profile = client.get_my_profile().unwrap();
+    assert_eq!(profile.nickname, "me");
+    assert_eq!(recoveries.load(Ordering::SeqCst), 1);
+    // The rejected request, its retry with the new token, then settings.
+    assert_eq!(transport.calls().len(), 3);
+}
+
+#[test]
+fn token_expiring_again_after_recovery_is_reported() {
+    let server = Canned::new().route("profile3/me", |_| json!({"status": 0}));
+    let transport = Arc::new(FaultyTransport::new(
+        server,
+        Scenario::new().burst(Fault::TokenExpired, 2),
+    ));
+    let recoveries = Arc::new(AtomicUsize::new(0));
+    let client =
+        client_with(transport.clone()).with_reauth(Box::new(CountingReauth(recoveries.clone())));
+
+    let err = client.get_identity().unwrap_err();
+    assert!(err.is_auth_failure(), "{err}");
+    assert_eq!(recoveries.load(Ordering::SeqCst), 1);
+    assert_eq!(transport.calls().len(), 2);
+}
+
+/// A server that accepts only `accepted` tokens and records the token each
+/// profile request carried.
+fn token_checking_server(
+    accepted: &'static [&'static str],
+    seen: Arc<Mutex<Vec<String>>>,
+) -> Canned {
+    let valid =
+        move |request: &openkakao_rs::fault::Request<'_>| accepted.contains(&request.authorization);
+    Canned::new()
+        .route(
+            "more_settings",
+            move |request| json!({"status": if valid(request) { 0 } else { -950 }}),
+        )
+        .route("profile3/me", move |request| {
+            seen.lock().unwrap().push(request.authorization.to_string());
+            json!({"status": 0, "profile": {"nickname": "me", "userId": 42}})
+        })
+}
+
+#[test]
+fn credentials_file_change_mid_run_is_verified_then_swapped_in() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("default.json");
+    let save = |token: &str| {
+        std::fs::write(&path, serde_json::to_string(&creds(token)).unwrap()).unwrap();
+    };
+    let load = || -> anyhow::Result<KakaoCredentials> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(&path)?)?)
+    };
+    save("old");
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let server = token_checking_server(&["old", "refreshed"], seen.clone());
+    let transport = Arc::new(FaultyTransport::new(server, Scenario::new()));
+    let client = KakaoRestClient::new(load().unwrap())
+        .unwrap()
+        .with_transport(transport.clone());
+    let verify = |candidate: &KakaoCredentials| {
+        KakaoRestClient::new(candidate.clone())
+            .unwrap()
+            .with_transport(transport.clone())
+            .check_token()
+    };
+    let mut watch = CredentialWatch::new(load().unwrap(), [path.clone()]);
+    let cycle = |watch: &mut CredentialWatch| {
+        if let Some(Reload::Rotated { current, .. }) = watch.poll(load, verify) {
+            client.replace_credentials(*current);
+        }
+        client.get_my_profile().unwrap();
+    };
+
+    cycle(&mut watch);
+    // Another run refreshes the token.
+    save("refreshed");
+    cycle(&mut watch);
+    cycle(&mut watch);
+    // A token the server refuses is reported and never used.
+    save("refused-by-server");
+    assert!(matches!(
+        watch.poll(load, verify),
+        Some(Reload::Rejected { .. })
+    ));
+    cycle(&mut watch);
+
+    assert_eq!(
+        *seen.lock().unwrap(),
+        ["old", "refreshed", "refreshed", "refreshed"]
+    );
+    assert_eq!(watch.current().oauth_token, "refreshed");
+}
+
+#[test]
+fn requests_in_flight_during_a_swap_keep_one_token() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let server = token_checking_server(&["token", "new"], seen.clone());
+    let transport = Arc::new(FaultyTransport::new(
+        server,
+        Scenario::new().then(Fault::Latency(Duration::from_millis(200))),
+    ));
+    let client = Arc::new(client_with(transport));
+
+    let slow = {
+        let client = client.clone();
+        std::thread::spawn(move || client.get_my_profile().unwrap())
+    };
+    std::thread::sleep(Duration::from_millis(50));
+    assert_eq!(
+        client.replace_credentials(creds("new")).oauth_token,
+        "token"
+    );
+    client.get_my_profile().unwrap();
+    slow.join().unwrap();
+
+    // The fast request already saw the new token; the slow one was sent,
+    // and finished, with the old one.
+    assert_eq!(*seen.lock().unwrap(), ["new", "token"]);
+}
+
+#[test]
+fn board_pages_follow_the_cursor_and_a_rejection_is_not_a_dead_token() {
+    let server = Canned::new()
+        .route(
+            "/comments",
+            |_| json!({"comments": [{"id": 1, "user_id": 5, "text": "ok"}], "last": true}),
+        )
+        .route(
+            "/posts/p-9",
+            |_| json!({"post": {"id": "p-9", "type": "TEXT", "title": "hi"}}),
+        )
+        .route("/chats/7/posts", |request| match cursor_of(request.url) {
+            None => json!({"posts": [{"id": 2}, {"id": 1}], "next_cursor": 1}),
+            Some(_) => json!({"posts": [{"id": 0}], "last": true}),
+        });
+    let transport = Arc::new(FaultyTransport::new(server, Scenario::new()));
+    let client = client_with(transport.clone());
+
+    let first = client.get_board_posts(7, None).unwrap();
+    assert_eq!(first.items.len(), 2);
+    let next = client
+        .get_board_posts(7, first.next_cursor.as_deref())
+        .unwrap();
+    assert_eq!(next.items[0].post_id, "0");
+    assert_eq!(next.next_cursor, None);
+    assert_eq!(client.get_board_post("p-9").unwrap().title, "hi");
+    let comments = client.get_board_comments("p-9", None).unwrap();
+    assert_eq!(comments.items[0].author_id, 5);
+    assert!(transport.calls()[1].contains("talkmoim-api.kakao.com/chats/7/posts?cursor=1"));
+
+    // A token the board turns down still works for katalk: no reauth.
+    let transport = Arc::new(FaultyTransport::new(
+        Canned::new().route("/more_settings.json", |_| json!({"status": 0})),
+        Scenario::new().then(Fault::TokenExpired),
+    ));
+    let recoveries = Arc::new(AtomicUsize::new(0));
+    let client =
+        client_with(transport.clone()).with_reauth(Box::new(CountingReauth(recoveries.clone())));
+    assert!(client.get_board_posts(7, None).is_err());
+    assert_eq!(recoveries.load(Ordering::SeqCst), 0);
+    assert!(transport.calls()[1].contains("more_settings.json"));
+}
+
+#[tokio::test]
+async fn async_client_fetches_member_lists_with_bounded_concurrency() {
+    static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+    static PEAK: AtomicUsize = AtomicUsize::new(0);
+    let members = Canned::new().route("/members", |request| {
+        let now = IN_FLIGHT.fetch_add(1, Ordering::SeqCst) + 1;
+        PEAK.fetch_max(now, Ordering::SeqCst);
+        std::thread::sleep(Duration::from_millis(30));
+        IN_FLIGHT.fetch_sub(1, Ordering::SeqCst);
+        if request.url.contains("/chats/3/") {
+            return json!({"status": -805});
+        }
+        json!({"members": [{"userId": 7, "nickName": "Dana"}]})
+    });
+    let server = members.route("/messaging/chats", |request| match cursor_of(request.url) {
+        None => json!({"chats": [{"chatId": 1}, {"chatId": 2}], "nextCursor": 7, "last": false}),
+        Some(_) => json!({"chats": [{"chatId": 3}, {"chatId": 4}], "last": true}),
+    });
+    let transport = Arc::new(FaultyTransport::new(server, Scenario::new()));
+    // The blocking client cannot be built on a runtime thread.
+    let blocking = {
+        let transport = transport.clone();
+        tokio::task::spawn_blocking(move || client_with(transport))
+    };
+    let client = AsyncKakaoRestClient::from(blocking.await.unwrap());
+
+    let chats = client.get_all_chats_with_members(2).await.unwrap();
+    let ids: Vec<i64> = chats.iter().map(|(chat, _)| chat.chat_id).collect();
+    assert_eq!(ids, [1, 2, 3, 4]);
+    assert_eq!(chats[0].1.as_ref().unwrap()[0].user_id, 7);
+    assert!(chats[2].1.is_err(), "one room failing keeps the others");
+    assert!(chats[3].1.is_ok());
+    assert_eq!(PEAK.load(Ordering::SeqCst), 2);
+    assert_eq!(transport.calls().len(), 6);
+}
@@ -119,3 +119,22 @@ fn packet_builder_default_trait() {
     assert_eq!(pkt.packet_id, 1);
     assert_eq!(pkt.method, "DEFAULT");
 }
+
+#[tokio::test]
+async fn login_without_a_device_uuid_is_refused_before_connecting() {
+    let creds = openkakao_rs::model::KakaoCredentials::new(
+        "token".into(),
+        42,
+        String::new(),
+        "26.1.0".into(),
+        String::new(),
+        "mac/26.1.0/ko".into(),
+    );
+    let mut client = openkakao_rs::loco::client::LocoClient::new(creds);
+    let err = client.full_connect().await.unwrap_err();
+    assert!(
+        err.to_string()
+            .starts_with("LOCO login needs a device UUID"),
+        "{err}"
+    );
+}
@@ -1,3 +1,5 @@
+#![cfg(feature = "archive")]
+
 use openkakao_rs::message_db::{CachedMessage, MessageDb};
 
 fn test_msg(chat_id: i64, log_id: i64, author: &str, msg: &str, send_at: i64) -> CachedMessage {
@@ -81,6 +83,22 @@ fn duplicate_insert_is_upsert() {
     assert_eq!(results.len(), 1);
 }
 
+#[test]
+fn upsert_without_author_name_keeps_known_name() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = MessageDb::open_at(&dir.path().join("test.db")).unwrap();
+
+    db.upsert_messages(&[test_msg(1, 100, "Alice", "hello", 1700000000)])
+        .unwrap();
+    // Re-sync after Alice left the chat: the member lookup no longer knows her.
+    db.upsert_messages(&[test_msg(1, 100, "", "hello", 1700000000)])
+        .unwrap();
+
+    let results = db.get_messages(1, 10).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].author_name, "Alice");
+}
+
 #[test]
 fn cross_chat_search() {
     let dir = tempfile::tempdir().unwrap();
@@ -93,7 +111,7 @@ fn cross_chat_search() {
 
     db.upsert_messages(&msgs).unwrap();
 
-    let results = db.search_all("meeting", 10).unwrap();
+    let results = db.search_all("meeting", 10, false).unwrap();
     assert_eq!(results.len(), 2);
 }
 
@@ -150,7 +168,7 @@ fn fts5_search_via_open_at() {
     assert_eq!(results.len(), 2);
 
     // search_all() also uses FTS5
-    let results = db.search_all("world", 10).unwrap();
+    let results = db.search_all("world", 10, false).unwrap();
     assert_eq!(results.len(), 2);
 }
 
@@ -197,7 +215,7 @@ fn search_all_with_no_matches() {
     let msgs = vec![test_msg(1, 100, "Alice", "hello", 1700000000)];
     db.upsert_messages(&msgs).unwrap();
 
-    let results = db.search_all("nonexistent", 10).unwrap();
+    let results = db.search_all("nonexistent", 10, false).unwrap();
     assert_eq!(results.len(), 0);
 }
 
@@ -322,3 +340,116 @@ fn get_messages_empty_chat() {
     let results = db.get_messages(999, 0).unwrap();
     assert!(results.is_empty());
 }
+
+#[test]
+fn get_messages_between_is_half_open() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = MessageDb::open_at(&dir.path().join("test.db")).unwrap();
+
+    let msgs = vec![
+        test_msg(1, 101, "Alice", "before", 999),
+        test_msg(1, 102, "Alice", "start", 1000),
+        test_msg(1, 103, "Bob", "inside", 1500),
+        test_msg(1, 104, "Bob", "end", 2000),
+        test_msg(2, 200, "Dave", "other chat", 1500),
+    ];
+    db.upsert_messages(&msgs).unwrap();
+
+    let results = db.get_messages_between(1, 1000, 2000).unwrap();
+    let texts: Vec<&str> = results.iter().map(|m| m.message.as_str()).collect();
+    assert_eq!(texts, vec!["start", "inside"]);
+
+    assert_eq!(db.time_bounds(1).unwrap(), Some((999, 2000)));
+    assert_eq!(db.time_bounds(3).unwrap(), None);
+}
+
+#[test]
+fn get_context_surrounds_the_target_message() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = MessageDb::open_at(&dir.path().join("test.db")).unwrap();
+    let msgs: Vec<_> = (1..=9)
+        .map(|i| test_msg(1, i * 10, "Alice", "hi", 1700000000 + i))
+        .chain([test_msg(2, 50, "Bob", "other chat", 1700000000)])
+        .collect();
+    db.upsert_messages(&msgs).unwrap();
+
+    let ids: Vec<i64> = db
+        .get_context(1, 50, 2)
+        .unwrap()
+        .iter()
+        .map(|m| m.log_id)
+        .collect();
+    assert_eq!(ids, vec![30, 40, 50, 60, 70]);
+
+    let ids: Vec<i64> = db
+        .get_context(1, 10, 2)
+        .unwrap()
+        .iter()
+        .map(|m| m.log_id)
+        .collect();
+    assert_eq!(ids, vec![10, 20, 30]);
+}
+
+#[test]
+fn departed_chats_are_tombstoned_until_they_return() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = MessageDb::open_at(&dir.path().join("test.db")).unwrap();
+    db.upsert_messages(&[
+        test_msg(1, 100, "Alice", "meeting in chat 1", 1700000000),
+        test_msg(2, 200, "Bob", "meeting in chat 2", 1700000010),
+        test_msg(3, 300, "Carol", "meeting in chat 3", 1700000020),
+    ])
+    .unwrap();
+
+    // First sync: every archived chat is in the chat list.
+    let changes = db.record_chat_list([1, 2, 3, 4], 1_000).unwrap();
+    assert!(changes.departed.is_empty() && changes.returned.is_empty());
+    assert!(db.departed_chats().unwrap().is_empty());
+
+    // Second sync: chats 2 and 3 are gone (left, or deleted).
+    let changes = db.record_chat_list([1, 4], 2_000).unwrap();
+    assert_eq!(changes.departed, [2, 3]);
+    assert!(changes.returned.is_empty());
+    assert_eq!(db.departed_at(2).unwrap(), Some(2_000));
+    let found: Vec<i64> = db
+        .search_all("meeting", 10, false)
+        .unwrap()
+        .iter()
+        .map(|m| m.chat_id)
+        .collect();
+    assert_eq!(found, [1]);
+    assert_eq!(db.search_all("meeting", 10, true).unwrap().len(), 3);
+    // The archive itself is kept.
+    assert_eq!(db.message_count(2).unwrap(), 1);
+
+    // Third sync: chat 2 was rejoined, chat 3 is still gone and keeps the
+    // time it disappeared.
+    let changes = db.record_chat_list([1, 2, 4], 3_000).unwrap();
+    assert!(changes.departed.is_empty());
+    assert_eq!(changes.returned, [2]);
+    assert_eq!(db.departed_at(2).unwrap(), None);
+    assert_eq!(db.departed_at(3).unwrap(), Some(2_000));
+    assert_eq!(db.search_all("meeting", 10, false).unwrap().len(), 2);
+
+    db.clear_chat(3).unwrap();
+    assert!(db.departed_chats().unwrap().is_empty());
+}
+
+#[test]
+fn log_times_list_one_chat_in_log_id_order() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = MessageDb::open_at(&dir.path().join("test.db")).unwrap();
+
+    db.upsert_messages(&[
+        test_msg(1, 300, "Alice", "later", 1700000300),
+        test_msg(2, 200, "Bob", "elsewhere", 1700000200),
+        test_msg(1, 100, "Alice", "first", 1700000100),
+    ])
+    .unwrap();
+
+    assert_eq!(
+        db.log_times(1).unwrap(),
+        [(100, 1700000100), (300, 1700000300)]
+    );
+    assert!(db.log_times(3).unwrap().is_empty());
+}
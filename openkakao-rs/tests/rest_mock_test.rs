@@ -0,0 +1,313 @@
+//! REST client requests against a local mock server, through the base URL
+//! overrides of `KakaoRestClient::builder`.
+
+use std::time::Duration;
+
+use openkakao_rs::cursor::MessageCursor;
+use openkakao_rs::error::OpenKakaoError;
+use openkakao_rs::model::{KakaoCredentials, OpenLinkRef};
+use openkakao_rs::rest::KakaoRestClient;
+use serde_json::json;
+use wiremock::matchers::{body_string, body_string_contains, header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn creds() -> KakaoCredentials {
+    let mut creds = KakaoCredentials::new(
+        "oauth-token".into(),
+        42,
+        "device-uuid".into(),
+        "26.1.0".into(),
+        "KT/26.1.0 Mc/26.1.0 ko".into(),
+        "mac/26.1.0/ko".into(),
+    );
+    creds.rest_token = Some("pilsner-token".into());
+    creds
+}
+
+/// The blocking client, pointed at `server` for both katalk and pilsner.
+/// It runs on the blocking pool: reqwest's blocking client cannot be used
+/// from a runtime thread.
+async fn with_client<T: Send + 'static>(
+    server: &MockServer,
+    call: impl FnOnce(&KakaoRestClient) -> T + Send + 'static,
+) -> T {
+    let base = server.uri();
+    tokio::task::spawn_blocking(move || {
+        let client = KakaoRestClient::builder(creds())
+            .base_url(format!("{base}/katalk"))
+            .pilsner_url(base)
+            .user_agent("openkakao-test/1.0")
+            .timeout(Duration::from_secs(5))
+            .build()
+            .unwrap();
+        call(&client)
+    })
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn get_chats_follows_the_cursor_with_the_pilsner_token() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/messaging/chats"))
+        .and(query_param("cursor", "7"))
+        .and(header("authorization", "pilsner-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "chats": [{"chatId": 3, "title": "third"}],
+            "last": true,
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/messaging/chats"))
+        .and(header("authorization", "pilsner-token"))
+        .and(header("user-agent", "openkakao-test/1.0"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "chats": [{"chatId": 1}, {"chatId": 2}],
+            "nextCursor": 7,
+            "last": false,
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let chats = with_client(&server, |client| client.get_all_chats())
+        .await
+        .unwrap();
+    let ids: Vec<i64> = chats.iter().map(|chat| chat.chat_id).collect();
+    assert_eq!(ids, [1, 2, 3]);
+}
+
+#[tokio::test]
+async fn get_messages_parses_a_page_and_its_cursor() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/messaging/chats/-5/messages"))
+        .and(query_param("cursor", "100"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "chatLogs": [
+                {"logId": 99, "type": 1, "message": "newer", "authorId": 7, "sendAt": 1_700_000_099},
+                {"logId": 98, "type": 1, "message": "older", "authorId": 8, "sendAt": 1_700_000_098},
+            ],
+            "nextCursor": 98,
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let (messages, next) = with_client(&server, |client| {
+        client.get_messages(-5, Some(MessageCursor::new(100)))
+    })
+    .await
+    .unwrap();
+    assert_eq!(next, Some(MessageCursor::new(98)));
+    let logs: Vec<(i64, &str)> = messages
+        .iter()
+        .map(|m| (m.log_id, m.message.as_str()))
+        .collect();
+    assert_eq!(logs, [(99, "newer"), (98, "older")]);
+}
+
+#[tokio::test]
+async fn katalk_requests_use_the_base_url_and_oauth_token() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/katalk/mac/account/more_settings.json"))
+        .and(header("authorization", "oauth-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"status": 0})))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let valid = with_client(&server, |client| client.verify_token()).await;
+    assert!(valid.unwrap());
+}
+
+#[tokio::test]
+async fn slow_answers_hit_the_client_timeout() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/messaging/chats"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"chats": [], "last": true}))
+                .set_delay(Duration::from_secs(2)),
+        )
+        .mount(&server)
+        .await;
+
+    let base = server.uri();
+    let result = tokio::task::spawn_blocking(move || {
+        KakaoRestClient::builder(creds())
+            .pilsner_url(base)
+            .timeout(Duration::from_millis(200))
+            .build()
+            .unwrap()
+            .get_chats(None)
+    })
+    .await
+    .unwrap();
+    assert!(
+        matches!(
+            result,
+            Err(OpenKakaoError::Network {
+                is_transient: true,
+                ..
+            })
+        ),
+        "{result:?}"
+    );
+}
+
+#[tokio::test]
+async fn a_missing_user_id_is_fetched_once_from_profile3_me() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/katalk/mac/profile3/me.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "status": 0,
+            "profile": {"userId": 77, "nickname": "me"},
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let base = server.uri();
+    let (first, second, kept) = tokio::task::spawn_blocking(move || {
+        let mut partial = creds();
+        partial.user_id = 0;
+        let client = KakaoRestClient::builder(partial)
+            .base_url(format!("{base}/katalk"))
+            .build()
+            .unwrap();
+        let first = client.backfill_user_id().unwrap();
+        let second = client.backfill_user_id().unwrap();
+        (first, second, client.user_id())
+    })
+    .await
+    .unwrap();
+    assert_eq!((first, second, kept), (77, 77, 77));
+}
+
+#[tokio::test]
+async fn open_link_info_is_looked_up_without_joining() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/katalk/mac/openlink/info.json"))
+        .and(body_string(
+            "link_url=https%3A%2F%2Fopen.kakao.com%2Fo%2FgAbC123",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "status": 0,
+            "openLink": {
+                "linkId": 4567,
+                "linkName": "Rust KR",
+                "activeMembersCount": 12,
+                "maxUser": 300,
+                "passcodeRequired": true,
+            },
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/katalk/mac/openlink/join.json"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let link: OpenLinkRef = "https://open.kakao.com/o/gAbC123".parse().unwrap();
+    let info = with_client(&server, move |client| client.get_open_link(&link))
+        .await
+        .unwrap();
+    assert_eq!((info.link_id, info.name.as_str()), (4567, "Rust KR"));
+    assert!(info.passcode_required);
+    assert!(!info.is_full());
+}
+
+#[tokio::test]
+async fn a_refused_join_carries_the_servers_message() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/katalk/mac/openlink/join.json"))
+        .and(body_string_contains("passcode=wrong%20one"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "status": -804,
+            "message": "The passcode is incorrect.",
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/katalk/mac/openlink/join.json"))
+        .and(body_string("link_id=4567&nickname=%EB%AF%BC%EC%88%98"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "status": 0,
+            "chatRoom": {"chatId": 900000000000003_i64},
+        })))
+        .mount(&server)
+        .await;
+
+    let (refused, joined) = with_client(&server, |client| {
+        (
+            client.join_open_link(4567, Some("wrong one"), None),
+            client.join_open_link(4567, None, Some("민수")),
+        )
+    })
+    .await;
+    match refused {
+        Err(OpenKakaoError::RestApi { status, message }) => {
+            assert_eq!(status, -804);
+            assert_eq!(message, "The passcode is incorrect.");
+        }
+        other => panic!("expected the server's refusal, got {other:?}"),
+    }
+    assert_eq!(joined.unwrap(), 900000000000003);
+}
+
+#[tokio::test]
+async fn a_profile_update_is_one_request_and_an_empty_status_clears() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/katalk/mac/profile3/edit.json"))
+        .and(body_string("nickname=%EB%AF%BC%EC%88%98&statusMessage="))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"status": 0})))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/katalk/mac/profile3/edit.json"))
+        .and(body_string(
+            "statusMessage=%EC%9E%90%EB%A6%AC%20%EB%B9%84%EC%9B%80",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "status": -805,
+            "message": "Contains a banned word.",
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let (updated, refused, too_long, empty) = with_client(&server, |client| {
+        (
+            client.update_profile(Some("민수"), Some("")),
+            client.update_profile(None, Some("자리 비움")),
+            client.update_profile(None, Some(&"가".repeat(61))),
+            client.update_profile(None, None),
+        )
+    })
+    .await;
+    updated.unwrap();
+    match refused {
+        Err(OpenKakaoError::RestApi { status, message }) => {
+            assert_eq!(status, -805);
+            assert_eq!(message, "Contains a banned word.");
+        }
+        other => panic!("expected the server's refusal, got {other:?}"),
+    }
+    // Over-long and empty updates never reach the server.
+    assert!(too_long.unwrap_err().to_string().contains("at most 60"));
+    assert!(empty.is_err());
+}
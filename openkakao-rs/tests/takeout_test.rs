@@ -0,0 +1,226 @@
+//! `takeout` against a canned server. Run with
+//! `cargo test --features fault-injection`.
+#![cfg(feature = "fault-injection")]
+
+use std::path::Path;
+use std::sync::Arc;
+
+use openkakao_rs::fault::{cursor_of, Canned, Fault, FaultyTransport, Scenario};
+use openkakao_rs::model::{ChatRoom, KakaoCredentials};
+use openkakao_rs::rest::KakaoRestClient;
+use openkakao_rs::retry::RetryPolicy;
+use openkakao_rs::takeout::{self, Manifest, SectionState, TakeoutOptions, MANIFEST_FILE};
+use serde_json::{json, Value};
+
+fn creds() -> KakaoCredentials {
+    KakaoCredentials::new(
+        "token".into(),
+        42,
+        "device-uuid".into(),
+        "26.1.0".into(),
+        "KT/26.1.0 Mc/26.1.0 ko".into(),
+        "mac/26.1.0/ko".into(),
+    )
+}
+
+/// An account with two friends and three chats. Chat 1 has two pages of
+/// history, chat 2 none; chat 3 is the one the tests leave out. With
+/// `friends` false the friend list answers HTTP 404.
+fn account(friends: bool) -> Canned {
+    let server = Canned::new()
+        .route("/members", |_| {
+            json!({"members": [{"userId": 42, "nickName": "me"}, {"userId": 7, "nickName": "Dana"}]})
+        })
+        .route("/messaging/chats/1/messages", |request| {
+            let logs: Vec<Value> = match cursor_of(request.url) {
+                None => (11..=20).rev().collect::<Vec<i64>>(),
+                Some(_) => (1..=10).rev().collect::<Vec<i64>>(),
+            }
+            .into_iter()
+            .map(|log_id| json!({"logId": log_id, "type": 1, "message": "hi", "sendAt": 1_700_000_000 + log_id}))
+            .collect();
+            let next = if cursor_of(request.url).is_none() { 11 } else { 0 };
+            json!({"chatLogs": logs, "nextCursor": next})
+        })
+        .route("/messages", |_| json!({"chatLogs": [], "nextCursor": 0}))
+        .route("/messaging/chats", |_| {
+            json!({"chats": [
+                {"chatId": 1, "type": "MultiChat", "title": "Family"},
+                {"chatId": 2, "type": "DirectChat", "title": "Dana"},
+                {"chatId": 3, "type": "OpenMultiChat", "title": "Open chat"},
+            ], "last": true})
+        })
+        .route("profile3/me", |_| {
+            json!({"status": 0, "profile": {"nickname": "me", "userId": 42}})
+        })
+        .route("more_settings", |_| json!({"status": 0, "accountId": 7}));
+    if !friends {
+        return server;
+    }
+    server.route("friends/update", |_| {
+        json!({"status": 0, "friends": [{"userId": 7, "nickName": "Dana"}, {"userId": 8, "nickName": "Min"}]})
+    })
+}
+
+fn client(transport: Arc<FaultyTransport<Canned>>) -> KakaoRestClient {
+    KakaoRestClient::new(creds())
+        .unwrap()
+        .with_retry_policy(RetryPolicy::default().with_retries(0))
+        .with_transport(transport)
+}
+
+/// Leaves out chat 3.
+fn skip_open_chats(room: &ChatRoom) -> Option<String> {
+    (room.chat_id == 3).then(|| "excluded by type:open".to_string())
+}
+
+fn run(transport: Arc<FaultyTransport<Canned>>, dir: &Path) -> Manifest {
+    takeout::run(
+        &client(transport),
+        dir,
+        TakeoutOptions::default(),
+        &skip_open_chats,
+        &|_, _| {},
+    )
+    .unwrap()
+}
+
+fn read_json(path: &Path) -> Value {
+    serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap()
+}
+
+#[test]
+fn takeout_writes_every_section_and_a_manifest() {
+    let dir = tempfile::tempdir().unwrap();
+    let transport = Arc::new(FaultyTransport::new(account(true), Scenario::new()));
+    let manifest = run(transport, dir.path());
+
+    assert!(manifest.is_complete());
+    assert!(manifest.finished_at.is_some());
+    assert_eq!(manifest.user_id, 42);
+    assert_eq!(manifest.sections["friends"].count, Some(2));
+    assert_eq!(manifest.sections["chats"].count, Some(3));
+    assert_eq!(
+        manifest.skipped_chats.get(&3).map(String::as_str),
+        Some("excluded by type:open")
+    );
+    assert_eq!(manifest.chats.keys().copied().collect::<Vec<_>>(), [1, 2]);
+
+    let messages = manifest.chats[&1].messages.as_ref().unwrap();
+    assert_eq!(messages.count, Some(20));
+    assert!(messages
+        .note
+        .as_ref()
+        .unwrap()
+        .starts_with("History starts at"));
+    let empty = manifest.chats[&2].messages.as_ref().unwrap();
+    assert_eq!(empty.count, Some(0));
+    assert!(empty.note.as_ref().unwrap().contains("Mac app"));
+
+    let saved = read_json(&dir.path().join("chats/1/messages.json"));
+    let ids: Vec<i64> = saved
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["log_id"].as_i64().unwrap())
+        .collect();
+    assert_eq!(ids, (1..=20).collect::<Vec<_>>());
+    assert_eq!(
+        read_json(&dir.path().join("friends.json"))
+            .as_array()
+            .unwrap()
+            .len(),
+        2
+    );
+    assert_eq!(
+        read_json(&dir.path().join("chats/2/members.json"))
+            .as_array()
+            .unwrap()
+            .len(),
+        2
+    );
+    assert!(dir.path().join("profile.json").exists());
+    assert!(dir.path().join("settings.json").exists());
+    assert!(!dir.path().join("chats/3").exists());
+    assert_eq!(Manifest::load(dir.path()).unwrap().unwrap(), manifest);
+}
+
+#[test]
+fn a_rerun_fetches_only_what_is_not_done() {
+    let dir = tempfile::tempdir().unwrap();
+    let first = run(
+        Arc::new(FaultyTransport::new(account(false), Scenario::new())),
+        dir.path(),
+    );
+    assert_eq!(first.sections["friends"].state, SectionState::Failed);
+    assert!(first.sections["friends"]
+        .note
+        .as_ref()
+        .unwrap()
+        .contains("404"));
+    assert!(first.sections["chats"].is_done());
+    assert!(!first.is_complete());
+    assert!(first.finished_at.is_none());
+
+    let transport = Arc::new(FaultyTransport::new(account(true), Scenario::new()));
+    let second = run(transport.clone(), dir.path());
+    let calls = transport.calls();
+    assert_eq!(calls.len(), 1, "{calls:?}");
+    assert!(calls[0].contains("friends/update"));
+    assert!(second.is_complete());
+    assert_eq!(second.started_at, first.started_at);
+}
+
+#[test]
+fn a_pilsner_refusal_is_recorded_and_the_run_goes_on() {
+    let dir = tempfile::tempdir().unwrap();
+    // Profile (two requests), settings and friends pass; the chat list is refused.
+    let scenario = Scenario::new().pass(4).then(Fault::Status(403));
+    let manifest = run(
+        Arc::new(FaultyTransport::new(account(true), scenario)),
+        dir.path(),
+    );
+    assert_eq!(manifest.sections["chats"].state, SectionState::Unavailable);
+    assert!(manifest.sections["friends"].is_done());
+    assert!(manifest.stopped.is_none());
+    assert!(manifest.chats.is_empty());
+}
+
+#[test]
+fn a_rejected_token_stops_the_run_for_a_later_resume() {
+    let dir = tempfile::tempdir().unwrap();
+    let scenario = Scenario::new().then(Fault::Status(401));
+    let manifest = run(
+        Arc::new(FaultyTransport::new(account(true), scenario)),
+        dir.path(),
+    );
+    assert_eq!(manifest.sections["profile"].state, SectionState::Failed);
+    assert!(manifest.stopped.is_some());
+    assert!(!manifest.sections.contains_key("settings"));
+    assert_eq!(manifest.pending(), 3);
+
+    let resumed = run(
+        Arc::new(FaultyTransport::new(account(true), Scenario::new())),
+        dir.path(),
+    );
+    assert!(resumed.stopped.is_none());
+    assert!(resumed.is_complete());
+}
+
+#[test]
+fn a_directory_with_other_files_is_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("notes.txt"), "mine").unwrap();
+    let transport = Arc::new(FaultyTransport::new(account(true), Scenario::new()));
+    let err = takeout::run(
+        &client(transport.clone()),
+        dir.path(),
+        TakeoutOptions::default(),
+        &skip_open_chats,
+        &|_, _| {},
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("not empty"), "{err}");
+    assert!(transport.calls().is_empty());
+    assert!(!dir.path().join(MANIFEST_FILE).exists());
+}
@@ -0,0 +1,24 @@
+#!/bin/sh
+# Check that the library builds with each feature subset, and the full
+# binary with the default features. Extra arguments go to every cargo call
+# (e.g. --offline).
+set -eu
+
+ROOT="$(CDPATH= cd -- "$(dirname "$0")/.." && pwd)"
+MANIFEST="${ROOT}/openkakao-rs/Cargo.toml"
+
+for FEATURES in "" "store" "extract" "archive" "extract,archive" "schema"; do
+  echo "==> --no-default-features --features '${FEATURES}'"
+  cargo clippy --manifest-path "${MANIFEST}" --all-targets \
+    --no-default-features --features "${FEATURES}" "$@" -- -D warnings
+done
+
+echo "==> cli without keyring and tui"
+cargo clippy --manifest-path "${MANIFEST}" --all-targets \
+  --no-default-features --features cli "$@" -- -D warnings
+
+echo "==> default features (cli, keyring, tui)"
+cargo clippy --manifest-path "${MANIFEST}" --all-targets "$@" -- -D warnings
+
+echo "==> default features + fault-injection"
+cargo clippy --manifest-path "${MANIFEST}" --all-targets --features fault-injection "$@" -- -D warnings